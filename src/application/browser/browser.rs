@@ -5,23 +5,45 @@
 //! # ブラウザアプリケーション
 //!
 //! URLバーと戻るボタンを持つシンプルなWebブラウザ。
+//!
+//! `about:` 以外のURLは `navigate` で読み込み待ちになり、
+//! `load_pending` が `net::http` 経由で（`file://` は VFS から）非同期に取得する。
+//! 文書を表示した後、スタイルシートと画像を `load_next_subresource` で 1 つずつ取得し、
+//! 届くたびにレイアウトし直す。
+//!
+//! マウスとフォーカスの状態は `ElementState` として保持し、変わるたびにスタイルを
+//! 計算し直して :hover / :active / :focus を反映する。
+//!
+//! フォーカスのあるフォーム部品は `io::hid` のキー入力で編集し（`forms`）、
+//! 送信ボタンや Enter でフォームを GET / POST で送信する。
+//!
+//! ページの `<script type="text/rustscript">` は表示時に実行する（`scripting`）。
+//! 部品を編集すると `input` イベントを発火し、スクリプトが書き換えた値は DOM に戻す。
 
 extern crate alloc;
 
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use alloc::vec;
 use alloc::format;
 
-use crate::graphics::image::Image;
+use crate::graphics::image::{decode as decode_image, Image};
 use crate::graphics::Color;
+use crate::io::hid::KeyEvent;
+use crate::net::http::{Request, Url};
+
+use alloc::collections::{BTreeMap, VecDeque};
 
 use super::dom::Node;
 use super::html::HtmlParser;
-use super::css::{Stylesheet, CssParser};
-use super::style::style_tree;
-use super::layout::{layout_tree, Dimensions, Rect};
-use super::render::{build_display_list, paint, DisplayList};
+use super::css::{Stylesheet, CssParser, Origin};
+use super::style::{style_tree_with, ElementState};
+use super::layout::{layout_tree_with, Dimensions, LayoutBox, Rect};
+use super::render::{build_display_list, paint_background_image, paint_image, DisplayCommand, DisplayList};
+use super::loader::{self, LoadedPage, Subresource, SubresourceKind};
+use super::images::ImageCache;
+use super::forms::{self, ControlKind, EditKey, FormAction};
+use super::scripting::PageScript;
 
 // ============================================================================
 // Constants
@@ -76,8 +98,16 @@ pub struct Browser {
     history_pos: usize,
     /// DOMツリー
     dom: Option<Node>,
-    /// スタイルシート
+    /// ページの `<style>` のスタイルシート
     stylesheet: Stylesheet,
+    /// `<link rel="stylesheet">` で読み込んだスタイルシート（読み込み順）
+    external_stylesheets: Vec<Stylesheet>,
+    /// ブラウザ既定のスタイルシート
+    user_agent_stylesheet: Stylesheet,
+    /// :hover / :active / :focus の対象
+    element_state: ElementState,
+    /// 要素のボーダーボックスと DOM 上の位置（描画順）
+    hit_regions: Vec<(Rect, Vec<usize>)>,
     /// 描画リスト
     display_list: DisplayList,
     /// 状態
@@ -96,6 +126,30 @@ pub struct Browser {
     url_focused: bool,
     /// カーソル位置
     cursor_pos: usize,
+    /// 読み込み待ちのURL
+    pending: Option<String>,
+    /// 読み込み待ちのフォーム送信（POST）
+    pending_request: Option<Request>,
+    /// 現在のページのURL（`about:` ページや `load_html` では None）
+    page_url: Option<Url>,
+    /// 取得待ちのサブリソース
+    subresource_queue: VecDeque<(SubresourceKind, Url)>,
+    /// 取得を始めたサブリソースのURL（重複して取得しない）
+    requested: Vec<String>,
+    /// 現在のページのサブリソース
+    subresources: Vec<Subresource>,
+    /// 取得やデコードに失敗したサブリソース (URL, 理由)
+    failed_subresources: Vec<(String, String)>,
+    /// デコード済みの画像
+    images: ImageCache,
+    /// フォーカスのあるテキスト欄のキャレット位置（文字単位）
+    caret: usize,
+    /// フォーカスのあるテキスト欄のコンテンツ領域とフォントサイズ
+    caret_box: Option<(Rect, f32)>,
+    /// 現在のページのスクリプト
+    script: Option<PageScript>,
+    /// 現在のページで失敗したスクリプトのエラー
+    script_errors: Vec<String>,
 }
 
 impl Browser {
@@ -108,6 +162,13 @@ impl Browser {
             history_pos: 0,
             dom: None,
             stylesheet: Stylesheet::default(),
+            external_stylesheets: Vec::new(),
+            user_agent_stylesheet: Stylesheet {
+                origin: Origin::UserAgent,
+                ..CssParser::parse(DEFAULT_USER_AGENT_CSS)
+            },
+            element_state: ElementState::default(),
+            hit_regions: Vec::new(),
             display_list: Vec::new(),
             state: BrowserState::Idle,
             error_message: None,
@@ -117,6 +178,18 @@ impl Browser {
             forward_hover: false,
             url_focused: true,
             cursor_pos: 7,
+            pending: None,
+            pending_request: None,
+            page_url: None,
+            subresource_queue: VecDeque::new(),
+            requested: Vec::new(),
+            subresources: Vec::new(),
+            failed_subresources: Vec::new(),
+            images: ImageCache::default(),
+            caret: 0,
+            caret_box: None,
+            script: None,
+            script_errors: Vec::new(),
         };
 
         // デフォルトページを表示
@@ -147,8 +220,9 @@ impl Browser {
     <p>Basic HTML tags: div, p, h1-h6, a, span, strong, em, ul, ol, li</p>
     <p>Basic CSS: color, background-color, font-size, margin, padding</p>
     <hr>
-    <h2>Example Sites</h2>
-    <p>Try loading http://example.com</p>
+    <h2>Loading Pages</h2>
+    <p>Serve a directory with python -m http.server and open http://10.0.2.2:8000/</p>
+    <p>Local files can be opened with file:///path/to/page.html</p>
 </body>
 </html>
 "#;
@@ -160,17 +234,46 @@ impl Browser {
 
     /// HTMLを読み込む
     pub fn load_html(&mut self, html: &str) {
-        self.state = BrowserState::Loading;
-
         // HTMLをパース
         let dom = HtmlParser::parse(html);
+        self.reset_subresources(None, None);
+        self.load_dom(dom);
+    }
+
+    /// DOMからページを構築する（外部スタイルシートは後から届く）
+    fn load_dom(&mut self, mut dom: Node) {
+        self.state = BrowserState::Loading;
 
         // CSSを抽出してパース
         let css = self.extract_css(&dom);
-        let stylesheet = CssParser::parse(&css);
+        self.stylesheet = CssParser::parse(&css);
+
+        // スクリプトを実行し、書き換えた部品の値を DOM に反映する
+        let mut script = PageScript::new(&dom);
+        self.script_errors = script.run_inline(&dom);
+        script.sync(&mut dom);
+        self.script = Some(script);
+
+        // DOMを保存し、スタイルとレイアウトを計算
+        self.dom = Some(dom);
+        self.element_state = ElementState::default();
+        self.relayout();
+        self.state = BrowserState::Idle;
+        self.scroll_y = 0.0;
+    }
+
+    /// スタイル・レイアウト・描画リストを作り直す
+    fn relayout(&mut self) {
+        let Some(dom) = &self.dom else {
+            return;
+        };
 
-        // スタイルツリーを構築
-        let style_tree = style_tree(&dom, &stylesheet);
+        // スタイルツリーを構築（既定、外部、<style> の順に強くなる）
+        let stylesheets: Vec<&Stylesheet> = core::iter::once(&self.user_agent_stylesheet)
+            .chain(&self.external_stylesheets)
+            .chain(core::iter::once(&self.stylesheet))
+            .collect();
+        let style_tree = style_tree_with(dom, &stylesheets, &self.element_state);
 
         // レイアウトツリーを構築
         let viewport = Dimensions {
@@ -182,17 +285,20 @@ impl Browser {
             ),
             ..Default::default()
         };
-        let layout_tree = layout_tree(&style_tree, viewport);
+        let layout_tree = layout_tree_with(&style_tree, viewport, &self.images);
 
         // 描画リストを生成
         self.display_list = build_display_list(&layout_tree);
+        self.hit_regions = hit_regions(dom, &layout_tree);
+        self.caret_box = self
+            .element_state
+            .focus
+            .as_deref()
+            .and_then(|focus| dom.descendant(focus))
+            .filter(|node| ControlKind::of(node).is_some_and(ControlKind::is_text_field))
+            .and_then(|node| content_box_of(&layout_tree, node));
         self.content_height = self.calculate_content_height();
-
-        // DOMを保存
-        self.dom = Some(dom);
-        self.stylesheet = stylesheet;
-        self.state = BrowserState::Idle;
-        self.scroll_y = 0.0;
+        self.queue_display_list_images();
     }
 
     /// CSSを抽出
@@ -205,9 +311,6 @@ impl Browser {
             css.push('\n');
         }
 
-        // デフォルトスタイル
-        css.push_str(DEFAULT_USER_AGENT_CSS);
-
         css
     }
 
@@ -222,6 +325,10 @@ impl Browser {
                 super::render::DisplayCommand::Text(_, _, _, y, size) => {
                     max_y = max_y.max(y + size * 1.2);
                 }
+                super::render::DisplayCommand::Image(_, rect)
+                | super::render::DisplayCommand::BackgroundImage(_, rect, _, _) => {
+                    max_y = max_y.max(rect.bottom());
+                }
                 _ => {}
             }
         }
@@ -229,11 +336,10 @@ impl Browser {
     }
 
     /// URLに移動
+    ///
+    /// `about:` ページは即座に表示し、それ以外は読み込み待ちにする
+    /// （実際の取得は `load_pending`）
     pub fn navigate(&mut self, url: &str) {
-        self.url = url.into();
-        self.url_input = url.into();
-        self.cursor_pos = url.len();
-
         // 履歴に追加
         if self.history_pos < self.history.len() {
             self.history.truncate(self.history_pos);
@@ -241,62 +347,194 @@ impl Browser {
         self.history.push(url.into());
         self.history_pos = self.history.len();
 
-        // URLに基づいてコンテンツを読み込む
+        self.open(url);
+    }
+
+    /// URLに移動し、サブリソースまで読み込む
+    pub async fn navigate_and_load(&mut self, url: &str) {
+        self.navigate(url);
+        self.load_pending().await;
+        while self.has_pending_subresources() {
+            self.load_next_subresource().await;
+        }
+    }
+
+    /// 履歴を変えずにURLを開く
+    fn open(&mut self, url: &str) {
+        self.url = url.into();
+        self.url_input = url.into();
+        self.cursor_pos = url.len();
+        self.error_message = None;
+        self.pending_request = None;
+
         if url == "about:home" || url.is_empty() {
+            self.pending = None;
             self.load_default_page();
-        } else if url.starts_with("http://example.com") || url.starts_with("https://example.com") {
-            self.load_example_com();
-        } else {
-            self.load_error_page(&format!("Cannot load: {}", url));
+            return;
+        }
+        match loader::resolve_input(url) {
+            Ok(resolved) => {
+                self.pending = Some(resolved.to_string());
+                self.state = BrowserState::Loading;
+            }
+            Err(e) => {
+                self.pending = None;
+                self.load_error_page(&format!("Cannot load {}: {}", url, e));
+            }
         }
     }
 
-    /// example.com を読み込む（シミュレート）
-    fn load_example_com(&mut self) {
-        let html = r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <title>Example Domain</title>
-    <style>
-        body {
-            background-color: #f0f0f2;
-            margin: 0;
-            padding: 0;
-            font-family: sans-serif;
+    /// 読み込み待ちのURLがあるか
+    pub fn has_pending_navigation(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// 読み込み待ちのURLを取得して表示する
+    pub async fn load_pending(&mut self) {
+        let Some(url) = self.pending.take() else {
+            return;
+        };
+        let result = match (self.pending_request.take(), loader::resolve_input(&url)) {
+            (Some(request), _) => loader::load_request(request).await,
+            (None, Ok(resolved)) => loader::load(&resolved).await,
+            (None, Err(e)) => Err(format!("{}", e)),
+        };
+        match result {
+            Ok(page) => self.show_page(page),
+            Err(e) => self.load_error_page(&format!("Cannot load {}: {}", url, e)),
         }
-        div {
-            width: 600px;
-            margin: 50px auto 40px auto;
-            padding: 50px;
-            background-color: #fff;
+    }
+
+    /// 読み込んだページを表示する
+    fn show_page(&mut self, page: LoadedPage) {
+        // リダイレクト後のURLをアドレスバーに反映
+        let final_url = page.url.to_string();
+        if let Some(entry) = self.history.get_mut(self.history_pos.wrapping_sub(1)) {
+            *entry = final_url.clone();
         }
-        h1 {
-            font-size: 32px;
-            margin: 0 0 20px 0;
+        self.cursor_pos = final_url.len();
+        self.url_input = final_url.clone();
+        self.url = final_url;
+
+        self.reset_subresources(Some(page.url.clone()), Some(page.base_url()));
+        for (kind, target) in page.subresource_urls() {
+            self.queue_subresource(kind, target);
         }
-        p {
-            margin: 20px 0;
-            line-height: 1.4;
+        self.load_dom(page.dom);
+        if page.status >= 400 {
+            self.state = BrowserState::Error;
+            self.error_message = Some(format!("HTTP {} {}", page.status, page.reason));
         }
-        a {
-            color: #38488f;
+    }
+
+    /// 現在のページのサブリソース（スタイルシート・スクリプト・画像）
+    pub fn subresources(&self) -> &[Subresource] {
+        &self.subresources
+    }
+
+    /// 取得やデコードに失敗したサブリソース (URL, 理由)
+    pub fn failed_subresources(&self) -> &[(String, String)] {
+        &self.failed_subresources
+    }
+
+    /// 現在のページで失敗したスクリプトのエラー
+    pub fn script_errors(&self) -> &[String] {
+        &self.script_errors
+    }
+
+    /// 取得待ちのサブリソースがあるか
+    pub fn has_pending_subresources(&self) -> bool {
+        !self.subresource_queue.is_empty()
+    }
+
+    /// 取得待ちのサブリソースを 1 つ取得し、ページに反映する
+    pub async fn load_next_subresource(&mut self) {
+        let Some((kind, target)) = self.subresource_queue.pop_front() else {
+            return;
+        };
+        let Some(page_url) = self.page_url.clone() else {
+            return;
+        };
+        match loader::fetch_subresource(kind, &target, &page_url).await {
+            Ok(subresource) => self.apply_subresource(&target, subresource),
+            Err(e) => self.failed_subresources.push((target.to_string(), e)),
         }
-    </style>
-</head>
-<body>
-    <div>
-        <h1>Example Domain</h1>
-        <p>This domain is for use in illustrative examples in documents.</p>
-        <p>You may use this domain in literature without prior coordination or asking for permission.</p>
-        <p>
-            <a href="https://www.iana.org/domains/example">More information...</a>
-        </p>
-    </div>
-</body>
-</html>
-"#;
-        self.load_html(html);
+    }
+
+    /// サブリソースの状態を新しいページ用に初期化
+    fn reset_subresources(&mut self, page_url: Option<Url>, base: Option<Url>) {
+        self.page_url = page_url;
+        self.subresource_queue.clear();
+        self.requested.clear();
+        self.subresources.clear();
+        self.failed_subresources.clear();
+        self.external_stylesheets.clear();
+        self.images = ImageCache::new(base);
+    }
+
+    /// サブリソースを取得待ちに加える（取得済み・取得待ちのURLは無視）
+    fn queue_subresource(&mut self, kind: SubresourceKind, target: Url) {
+        let key = target.to_string();
+        if self.page_url.is_none()
+            || self.requested.contains(&key)
+            || self.requested.len() >= loader::MAX_SUBRESOURCES
+        {
+            return;
+        }
+        self.requested.push(key);
+        self.subresource_queue.push_back((kind, target));
+    }
+
+    /// 描画リストが参照する画像（CSS の `url()` を含む）のうち未取得のものを取得待ちにする
+    fn queue_display_list_images(&mut self) {
+        let mut targets = Vec::new();
+        for cmd in &self.display_list {
+            if let DisplayCommand::Image(src, _) | DisplayCommand::BackgroundImage(src, ..) = cmd
+                && let Some(target) = self.images.resolve(src).and_then(|url| Url::parse(&url).ok())
+            {
+                targets.push(target);
+            }
+        }
+        for target in targets {
+            self.queue_subresource(SubresourceKind::Image, target);
+        }
+    }
+
+    /// 取得したサブリソースをページに反映し、レイアウトし直す
+    ///
+    /// `target` は要求したURL（画像はリダイレクト前のURLで参照される）。
+    fn apply_subresource(&mut self, target: &Url, subresource: Subresource) {
+        match subresource.kind {
+            SubresourceKind::Stylesheet => {
+                // url() はスタイルシート自身のURLからの相対
+                let mut sheet = CssParser::parse(&subresource.text());
+                let base = Url::parse(&subresource.url).unwrap_or_else(|_| target.clone());
+                loader::resolve_stylesheet_urls(&mut sheet, &base);
+                self.external_stylesheets.push(sheet);
+                self.relayout();
+            }
+            SubresourceKind::Image => match decode_image(&subresource.data) {
+                Ok(image) => {
+                    self.images.insert(target.to_string(), image);
+                    self.relayout();
+                }
+                Err(e) => self
+                    .failed_subresources
+                    .push((target.to_string(), format!("Cannot decode image: {:?}", e))),
+            },
+            SubresourceKind::Script => {}
+        }
+        self.subresources.push(subresource);
+    }
+
+    /// 状態
+    pub fn state(&self) -> BrowserState {
+        self.state
+    }
+
+    /// エラーメッセージ
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_deref()
     }
 
     /// エラーページを読み込む
@@ -325,7 +563,6 @@ impl Browser {
     <h1>Page Not Found</h1>
     <p>{}</p>
     <p>The requested page could not be loaded.</p>
-    <p>Note: This browser can only display built-in pages.</p>
 </body>
 </html>
 "#,
@@ -341,15 +578,7 @@ impl Browser {
         if self.history_pos > 1 {
             self.history_pos -= 1;
             let url = self.history[self.history_pos - 1].clone();
-            self.url = url.clone();
-            self.url_input = url.clone();
-            self.cursor_pos = url.len();
-
-            if url == "about:home" {
-                self.load_default_page();
-            } else if url.contains("example.com") {
-                self.load_example_com();
-            }
+            self.open(&url);
         }
     }
 
@@ -358,15 +587,7 @@ impl Browser {
         if self.history_pos < self.history.len() {
             self.history_pos += 1;
             let url = self.history[self.history_pos - 1].clone();
-            self.url = url.clone();
-            self.url_input = url.clone();
-            self.cursor_pos = url.len();
-
-            if url == "about:home" {
-                self.load_default_page();
-            } else if url.contains("example.com") {
-                self.load_example_com();
-            }
+            self.open(&url);
         }
     }
 
@@ -419,9 +640,29 @@ impl Browser {
             return;
         }
 
-        // コンテンツ領域クリックでURLバーのフォーカスを外す
+        // コンテンツ領域クリックでURLバーのフォーカスを外し、クリックした要素にフォーカス
         if y > TOOLBAR_HEIGHT {
             self.url_focused = false;
+            self.focus_at(x, y);
+            self.activate_focused();
+        }
+    }
+
+    /// マウスボタンを押した
+    pub fn on_mouse_down(&mut self, x: u32, y: u32) {
+        if y > TOOLBAR_HEIGHT {
+            let mut state = self.element_state.clone();
+            state.active = self.hit_test(x, y);
+            self.set_element_state(state);
+        }
+    }
+
+    /// マウスボタンを離した
+    pub fn on_mouse_up(&mut self, _x: u32, _y: u32) {
+        if self.element_state.active.is_some() {
+            let mut state = self.element_state.clone();
+            state.active = None;
+            self.set_element_state(state);
         }
     }
 
@@ -429,29 +670,186 @@ impl Browser {
     pub fn on_mouse_move(&mut self, x: u32, y: u32) {
         self.back_hover = self.is_in_back_button(x, y) && self.can_go_back();
         self.forward_hover = self.is_in_forward_button(x, y) && self.can_go_forward();
+
+        let hover = if y > TOOLBAR_HEIGHT { self.hit_test(x, y) } else { None };
+        if hover != self.element_state.hover {
+            let mut state = self.element_state.clone();
+            state.hover = hover;
+            self.set_element_state(state);
+        }
     }
 
-    /// キー入力
-    pub fn on_key_press(&mut self, key: char) {
-        if !self.url_focused {
+    /// クリックした位置のフォーカス可能な要素（リンクやフォーム部品）にフォーカスを移す
+    fn focus_at(&mut self, x: u32, y: u32) {
+        let focus = self.hit_test(x, y).and_then(|mut path| {
+            let dom = self.dom.as_ref()?;
+            // 自身から祖先へ向かって探す
+            loop {
+                if dom.descendant(&path).is_some_and(is_focusable) {
+                    return Some(path);
+                }
+                path.pop()?;
+            }
+        });
+        self.set_focus(focus);
+    }
+
+    /// 文書順で次のフォーカス可能な要素にフォーカスを移す（最後なら先頭へ）
+    fn focus_next(&mut self) {
+        fn collect(node: &Node, path: &mut Vec<usize>, found: &mut Vec<Vec<usize>>) {
+            if is_focusable(node) {
+                found.push(path.clone());
+            }
+            for (i, child) in node.children.iter().enumerate() {
+                path.push(i);
+                collect(child, path, found);
+                path.pop();
+            }
+        }
+
+        let Some(dom) = &self.dom else {
             return;
+        };
+        let mut focusable = Vec::new();
+        collect(dom, &mut Vec::new(), &mut focusable);
+        let next = match &self.element_state.focus {
+            Some(current) => focusable.iter().position(|path| path == current).map_or(0, |i| i + 1),
+            None => 0,
+        };
+        let focus = focusable.get(next).or(focusable.first()).cloned();
+        self.set_focus(focus);
+    }
+
+    /// フォーカスを移す（テキスト欄に移ったらキャレットは値の末尾）
+    fn set_focus(&mut self, focus: Option<Vec<usize>>) {
+        if focus != self.element_state.focus {
+            self.caret = focus
+                .as_deref()
+                .and_then(|path| self.dom.as_ref()?.descendant(path))
+                .map_or(0, |node| forms::value(node).chars().count());
         }
+        let mut state = self.element_state.clone();
+        state.focus = focus;
+        self.set_element_state(state);
+    }
 
-        if key == '\n' || key == '\r' {
-            // Enterでナビゲート
-            let url = self.url_input.clone();
-            self.navigate(&url);
-        } else if key == '\x08' {
-            // Backspace
-            if self.cursor_pos > 0 {
-                self.url_input.remove(self.cursor_pos - 1);
-                self.cursor_pos -= 1;
+    /// フォーカスのあるフォーム部品をクリックしたときの動作（チェックの切り替えや送信）
+    fn activate_focused(&mut self) {
+        let (Some(dom), Some(path)) = (self.dom.as_mut(), self.element_state.focus.clone()) else {
+            return;
+        };
+        let action = forms::activate(dom, &path);
+        self.apply_form_action(&path, action);
+    }
+
+    /// フォーム部品の操作の結果を反映する
+    fn apply_form_action(&mut self, control: &[usize], action: FormAction) {
+        match action {
+            FormAction::Input => {
+                self.dispatch_input(control);
+                self.relayout();
             }
-        } else if key >= ' ' && key <= '~' {
-            // 印字可能文字
-            self.url_input.insert(self.cursor_pos, key);
-            self.cursor_pos += 1;
+            FormAction::Submit => self.submit_form(control),
+            FormAction::None => {}
+        }
+    }
+
+    /// 部品の `input` イベントをスクリプトに送り、ハンドラが書き換えた値を DOM に戻す
+    fn dispatch_input(&mut self, control: &[usize]) {
+        let (Some(script), Some(dom)) = (self.script.as_mut(), self.dom.as_mut()) else {
+            return;
+        };
+        if let Err(e) = script.input(dom, control) {
+            self.script_errors.push(e.to_string());
         }
+        if script.sync(dom) {
+            // フォーカスのある欄の値が短くなったらキャレットを末尾に寄せる
+            let length = self
+                .element_state
+                .focus
+                .as_deref()
+                .and_then(|path| dom.descendant(path))
+                .map_or(0, |node| forms::value(node).chars().count());
+            self.caret = self.caret.min(length);
+        }
+    }
+
+    /// `control`（送信ボタンかテキスト欄）のフォームを送信する
+    ///
+    /// GET はクエリ付きのURLへの移動、POST はボディ付きのリクエストを読み込み待ちにする。
+    fn submit_form(&mut self, control: &[usize]) {
+        let (Some(dom), Some(page_url)) = (&self.dom, &self.page_url) else {
+            return;
+        };
+        let Some(request) = forms::submission(dom, control, page_url) else {
+            return;
+        };
+        self.navigate(&request.url.to_string());
+        if request.method != "GET" && self.pending.is_some() {
+            self.pending_request = Some(request);
+        }
+    }
+
+    /// 要素の状態を更新し、変わっていればスタイルを計算し直す
+    fn set_element_state(&mut self, state: ElementState) {
+        if state != self.element_state {
+            self.element_state = state;
+            self.relayout();
+        }
+    }
+
+    /// ウィンドウ座標にある最も手前の要素（DOM 上の位置）
+    fn hit_test(&self, x: u32, y: u32) -> Option<Vec<usize>> {
+        let page_x = x as f32 - 10.0;
+        let page_y = y as f32 - (TOOLBAR_HEIGHT + 10) as f32 + self.scroll_y;
+        self.hit_regions
+            .iter()
+            .rev()
+            .find(|(rect, _)| {
+                page_x >= rect.x && page_x < rect.right() && page_y >= rect.y && page_y < rect.bottom()
+            })
+            .map(|(_, path)| path.clone())
+    }
+
+    /// キー入力（文字）
+    pub fn on_key_press(&mut self, key: char) {
+        self.on_edit_key(EditKey::from_char(key));
+    }
+
+    /// キー入力（`io::hid` のキーイベント）
+    pub fn on_key_event(&mut self, event: &KeyEvent) {
+        if let Some(key) = EditKey::from_key_event(event) {
+            self.on_edit_key(key);
+        }
+    }
+
+    /// URLバーかフォーカスのあるフォーム部品を編集する（Tab はフォーカスを移す）
+    fn on_edit_key(&mut self, key: EditKey) {
+        if self.url_focused {
+            match key {
+                // Enterでナビゲート
+                EditKey::Enter => {
+                    let url = self.url_input.clone();
+                    self.navigate(&url);
+                }
+                // URLは印字可能なASCII文字のみ
+                EditKey::Char(c) if !(' '..='~').contains(&c) => {}
+                _ => {
+                    forms::edit_text(&mut self.url_input, &mut self.cursor_pos, key, false);
+                }
+            }
+            return;
+        }
+
+        if key == EditKey::Tab {
+            self.focus_next();
+            return;
+        }
+        let (Some(dom), Some(path)) = (self.dom.as_mut(), self.element_state.focus.clone()) else {
+            return;
+        };
+        let action = forms::edit(dom, &path, &mut self.caret, key);
+        self.apply_form_action(&path, action);
     }
 
     /// スクロール
@@ -556,6 +954,32 @@ impl Browser {
         for cmd in &self.display_list {
             self.render_command(image, cmd, viewport, clip_y);
         }
+
+        self.render_caret(image, viewport, clip_y);
+    }
+
+    /// フォーカスのあるテキスト欄のキャレットを描画
+    fn render_caret(&self, image: &mut Image, viewport: Rect, clip_y: u32) {
+        if self.url_focused {
+            return;
+        }
+        let Some((content, font_size)) = self.caret_box else {
+            return;
+        };
+        let Some(node) = self.element_state.focus.as_deref().and_then(|f| self.dom.as_ref()?.descendant(f)) else {
+            return;
+        };
+
+        // キャレットの前の文字数から行と桁を求める（文字の送り幅は draw_text_scaled と同じ）
+        let before: Vec<char> = forms::value(node).chars().take(self.caret).collect();
+        let line = before.iter().filter(|&&c| c == '\n').count();
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count();
+        let scale = (font_size / 6.0).max(1.0) as u32;
+        let x = (content.x + viewport.x) as u32 + column as u32 * (5 * scale + 1);
+        let y = (content.y + viewport.y + line as f32 * font_size * 1.2) as u32;
+        if y >= clip_y && y < BROWSER_HEIGHT {
+            self.fill_rect(image, x, y, 1, 6 * scale, TEXT_COLOR);
+        }
     }
 
     /// 描画コマンドを実行
@@ -601,6 +1025,17 @@ impl Browser {
                     self.fill_rect(image, px, py, *width as u32, 1, gfx_color);
                 }
             }
+            super::render::DisplayCommand::Image(src, rect) => {
+                if let Some(source) = self.images.get(src) {
+                    paint_image(image, source, offset(*rect, viewport), content_clip(clip_y));
+                }
+            }
+            super::render::DisplayCommand::BackgroundImage(src, area, origin, repeat) => {
+                if let Some(source) = self.images.get(src) {
+                    let (area, origin) = (offset(*area, viewport), offset(*origin, viewport));
+                    paint_background_image(image, source, area, origin, *repeat, content_clip(clip_y));
+                }
+            }
             _ => {}
         }
     }
@@ -778,6 +1213,72 @@ impl Browser {
     }
 }
 
+/// 要素のボックスを描画順に集める（後ろほど手前）
+fn hit_regions(dom: &Node, layout: &LayoutBox) -> Vec<(Rect, Vec<usize>)> {
+    // レイアウトボックスは DOM ノードへの参照しか持たないので、アドレスから位置を引く
+    fn index_paths(node: &Node, path: &mut Vec<usize>, paths: &mut BTreeMap<usize, Vec<usize>>) {
+        paths.insert(node as *const Node as usize, path.clone());
+        for (i, child) in node.children.iter().enumerate() {
+            path.push(i);
+            index_paths(child, path, paths);
+            path.pop();
+        }
+    }
+
+    fn collect(layout: &LayoutBox, paths: &BTreeMap<usize, Vec<usize>>, regions: &mut Vec<(Rect, Vec<usize>)>) {
+        if let Some(styled) = layout.styled_node {
+            if styled.node.is_element() {
+                if let Some(path) = paths.get(&(styled.node as *const Node as usize)) {
+                    regions.push((layout.dimensions.border_box(), path.clone()));
+                }
+            }
+        }
+        for child in &layout.children {
+            collect(child, paths, regions);
+        }
+    }
+
+    let mut paths = BTreeMap::new();
+    index_paths(dom, &mut Vec::new(), &mut paths);
+    let mut regions = Vec::new();
+    collect(layout, &paths, &mut regions);
+    regions
+}
+
+/// レイアウト座標の矩形を画面座標にする
+fn offset(rect: Rect, viewport: Rect) -> Rect {
+    Rect::new(rect.x + viewport.x, rect.y + viewport.y, rect.width, rect.height)
+}
+
+/// コンテンツ領域（ツールバーより下）
+fn content_clip(clip_y: u32) -> Rect {
+    Rect::new(0.0, clip_y as f32, BROWSER_WIDTH as f32, (BROWSER_HEIGHT - clip_y) as f32)
+}
+
+/// レイアウトツリーから `node` のボックスを探し、コンテンツ領域とフォントサイズを返す
+fn content_box_of(layout: &LayoutBox, node: &Node) -> Option<(Rect, f32)> {
+    if let Some(styled) = layout.styled_node
+        && core::ptr::eq(styled.node, node)
+    {
+        let font_size = styled.length_px("font-size").max(16.0);
+        return Some((layout.dimensions.content, font_size));
+    }
+    layout.children.iter().find_map(|child| content_box_of(child, node))
+}
+
+/// フォーカスを受け取れる要素か
+fn is_focusable(node: &Node) -> bool {
+    match ControlKind::of(node) {
+        Some(ControlKind::Hidden) => false,
+        Some(_) => !forms::is_disabled(node),
+        None => match node.tag_name() {
+            Some("a") | Some("area") => node.get_attribute("href").is_some(),
+            Some(_) => node.get_attribute("tabindex").is_some(),
+            None => false,
+        },
+    }
+}
+
 impl Default for Browser {
     fn default() -> Self {
         Self::new()
@@ -796,6 +1297,11 @@ h1, h2, h3, h4, h5, h6, p { display: block; }
 ul, ol, li { display: block; }
 a { display: inline; color: #0000EE; }
 span, strong, em, b, i { display: inline; }
+form { display: block; }
+input, select, textarea, button { display: inline-block; border: 1px solid #767676; padding: 2px; background-color: #fff; }
+button, input[type=submit], input[type=button], input[type=reset] { background-color: #efefef; }
+input[type=checkbox], input[type=radio] { padding: 0; margin: 3px; }
+input[type=hidden] { display: none; }
 "#;
 
 // ============================================================================
@@ -824,4 +1330,206 @@ mod tests {
         assert!(!browser.can_go_back());
         assert!(browser.can_go_forward());
     }
+
+    #[test]
+    fn test_navigation_queues_network_loads() {
+        let mut browser = Browser::new();
+
+        browser.navigate("10.0.2.2:8000/index.html");
+        assert_eq!(browser.state(), BrowserState::Loading);
+        assert_eq!(browser.pending.as_deref(), Some("http://10.0.2.2:8000/index.html"));
+
+        // 未対応スキームは読み込まずにエラーページ
+        browser.navigate("gopher://example.com/");
+        assert!(!browser.has_pending_navigation());
+        assert_eq!(browser.state(), BrowserState::Error);
+
+        browser.navigate("about:home");
+        assert_eq!(browser.state(), BrowserState::Idle);
+        assert!(browser.error_message().is_none());
+    }
+
+    #[test]
+    fn test_hover_and_focus_restyle() {
+        let mut browser = Browser::new();
+        browser.load_html(concat!(
+            r#"<a href="/next" style="display: block; height: 20px">next</a>"#,
+            "<style>a:hover { background-color: red } a:focus { background-color: blue }</style>",
+        ));
+        let plain = browser.display_list.len();
+        let over_link = TOOLBAR_HEIGHT + 15;
+
+        // リンクの上に乗ると :hover の背景が描かれる
+        browser.on_mouse_move(20, over_link);
+        assert!(browser.element_state.hover.is_some());
+        assert_eq!(browser.display_list.len(), plain + 1);
+
+        // クリックでフォーカスが移り、離れても :focus の背景は残る
+        browser.on_mouse_click(20, over_link);
+        assert_eq!(browser.element_state.focus, browser.element_state.hover);
+        browser.on_mouse_move(20, BROWSER_HEIGHT - 5);
+        assert!(browser.element_state.hover.is_none());
+        assert_eq!(browser.display_list.len(), plain + 1);
+    }
+
+    /// 24ビットの BMP（全ピクセル同じ色）
+    fn bmp(width: u32, height: u32) -> Vec<u8> {
+        let row = (width * 3).div_ceil(4) * 4;
+        let mut data = b"BM".to_vec();
+        data.extend_from_slice(&(54 + row * height).to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&54u32.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&24u16.to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        data.resize(54 + (row * height) as usize, 0x80);
+        data
+    }
+
+    #[test]
+    fn test_subresources_reflow() {
+        let mut browser = Browser::new();
+        let page_url = Url::parse("http://host/dir/page.html").unwrap();
+        browser.navigate("http://host/dir/page.html");
+        browser.show_page(LoadedPage {
+            url: page_url.clone(),
+            status: 200,
+            reason: "OK".into(),
+            dom: HtmlParser::parse(r#"<link rel=stylesheet href="s.css"><img src="a.bmp"><div id=d></div>"#),
+        });
+        let queued = |browser: &Browser| -> Vec<String> {
+            browser.subresource_queue.iter().map(|(_, url)| url.to_string()).collect()
+        };
+        assert_eq!(queued(&browser), ["http://host/dir/s.css", "http://host/dir/a.bmp"]);
+        assert_eq!(browser.content_height, 0.0);
+
+        // リダイレクト先のスタイルシートの url() はリダイレクト後のURLから解決する
+        let (_, sheet_url) = browser.subresource_queue.pop_front().unwrap();
+        browser.apply_subresource(
+            &sheet_url,
+            Subresource {
+                kind: SubresourceKind::Stylesheet,
+                url: "http://host/css/s.css".into(),
+                content_type: "text/css".into(),
+                data: b"#d { height: 10px; background: url(bg.bmp) no-repeat }".to_vec(),
+            },
+        );
+        assert_eq!(browser.content_height, 10.0);
+        assert_eq!(queued(&browser), ["http://host/dir/a.bmp", "http://host/css/bg.bmp"]);
+
+        // 画像が届くと固有サイズでレイアウトし直す
+        let (_, image_url) = browser.subresource_queue.pop_front().unwrap();
+        let image = |data| Subresource {
+            kind: SubresourceKind::Image,
+            url: image_url.to_string(),
+            content_type: "image/bmp".into(),
+            data,
+        };
+        browser.apply_subresource(&image_url, image(bmp(3, 2)));
+        assert_eq!(browser.images.size("a.bmp"), Some((3.0, 2.0)));
+        assert_eq!(browser.content_height, 12.0);
+
+        browser.apply_subresource(&image_url, image(b"not an image".to_vec()));
+        assert_eq!(browser.failed_subresources().len(), 1);
+        assert_eq!(browser.subresources().len(), 3);
+
+        // 別のページに移るとサブリソースは捨てる
+        browser.load_html("<p>next</p>");
+        assert!(!browser.has_pending_subresources());
+        assert!(browser.images.is_empty());
+    }
+
+    #[test]
+    fn test_form_editing_and_submission() {
+        use crate::io::hid::{KeyCode, KeyState, Modifiers};
+
+        let mut browser = Browser::new();
+        browser.navigate("http://host/form.html");
+        browser.show_page(LoadedPage {
+            url: Url::parse("http://host/form.html").unwrap(),
+            status: 200,
+            reason: "OK".into(),
+            dom: HtmlParser::parse(concat!(
+                r#"<form action="/find"><input name=q><input type=checkbox name=all value=1><button>Go</button></form>"#,
+                r#"<form method=post action="/save"><textarea name=t></textarea><input name=n></form>"#,
+            )),
+        });
+        let texts = |browser: &Browser| -> Vec<String> {
+            browser
+                .display_list
+                .iter()
+                .filter_map(|cmd| match cmd {
+                    DisplayCommand::Text(text, ..) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // テキスト欄をクリックして io::hid のキーイベントと文字で入力する
+        browser.on_mouse_click(20, TOOLBAR_HEIGHT + 15);
+        assert!(!browser.url_focused);
+        assert!(browser.caret_box.is_some());
+        let shift = Modifiers { shift: true, ..Default::default() };
+        let key = |key| KeyEvent { key, state: KeyState::Pressed, modifiers: shift, raw_scancode: 0 };
+        browser.on_key_event(&key(KeyCode::A));
+        browser.on_key_event(&KeyEvent { state: KeyState::Released, ..key(KeyCode::A) });
+        browser.on_key_press('b');
+        assert_eq!(texts(&browser), ["Ab", "Go"]);
+        assert_eq!(browser.caret, 2);
+
+        // Tab でチェックボックスへ移って Space でチェックし、ボタンで GET 送信
+        browser.on_key_press('\t');
+        browser.on_key_press(' ');
+        browser.on_key_press('\t');
+        browser.on_key_press('\n');
+        assert_eq!(browser.pending.as_deref(), Some("http://host/find?q=Ab&all=1"));
+        assert!(browser.pending_request.is_none());
+
+        // <textarea> では Enter は改行、隣のテキスト欄での Enter は POST 送信
+        for key in ['\t', 'x', '\n', 'y', '\t', '\n'] {
+            browser.on_key_press(key);
+        }
+        assert_eq!(browser.pending.as_deref(), Some("http://host/save"));
+        let request = browser.pending_request.as_ref().unwrap();
+        assert_eq!((request.method.as_str(), request.body.as_slice()), ("POST", b"t=x%0D%0Ay&n=".as_slice()));
+        assert_eq!(browser.history.len(), 3);
+    }
+
+    #[test]
+    fn test_form_scripts_see_input_and_set_values() {
+        let mut browser = Browser::new();
+        browser.navigate("http://host/form.html");
+        browser.show_page(LoadedPage {
+            url: Url::parse("http://host/form.html").unwrap(),
+            status: 200,
+            reason: "OK".into(),
+            dom: HtmlParser::parse(concat!(
+                r#"<form action="/find"><input id=q name=q><input id=n name=n><input id=all type=checkbox name=all></form>"#,
+                r#"<script type="text/rustscript">
+                    let q = getElementById("q");
+                    let n = getElementById("n");
+                    let all = getElementById("all");
+                    q.value = "x";
+                    q.on_input(move |event| {
+                        n.value = event.value + "!";
+                        all.checked = true;
+                    });
+                </script>"#,
+            )),
+        });
+        assert!(browser.script_errors().is_empty(), "{:?}", browser.script_errors());
+
+        // 読み込み時にスクリプトが書いた値が部品に出る
+        browser.on_mouse_click(20, TOOLBAR_HEIGHT + 15);
+        assert_eq!(browser.caret, 1);
+
+        // キー入力ごとに oninput が呼ばれ、ハンドラが書いた値とチェックが送信される
+        browser.on_key_press('y');
+        browser.on_key_press('z');
+        browser.on_key_press('\n');
+        assert_eq!(browser.pending.as_deref(), Some("http://host/find?q=xyz&n=xyz%21&all=on"));
+    }
 }
@@ -5,6 +5,10 @@
 //! # CSSパーサー
 //!
 //! CSSルールセット（セレクタとプロパティ）のパーサー。
+//!
+//! セレクタは型・ID・クラス・属性セレクタ、擬似クラス、
+//! 4 種類の結合子（子孫・子・隣接兄弟・後続兄弟）に対応する。
+//! 解釈できないセレクタ（擬似要素など）を含むルールは丸ごと無視する。
 
 extern crate alloc;
 
@@ -22,6 +26,18 @@ use alloc::vec;
 pub struct Stylesheet {
     /// ルールのリスト
     pub rules: Vec<Rule>,
+    /// 出どころ（カスケードの優先順位に使う）
+    pub origin: Origin,
+}
+
+/// スタイルシートの出どころ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Origin {
+    /// ブラウザの既定スタイル
+    UserAgent,
+    /// ページのスタイル（<style>, <link>, style 属性）
+    #[default]
+    Author,
 }
 
 /// CSSルール
@@ -42,6 +58,10 @@ pub enum Selector {
     Descendant(Box<Selector>, Box<Selector>),
     /// 子セレクタ (A > B)
     Child(Box<Selector>, Box<Selector>),
+    /// 隣接兄弟セレクタ (A + B)
+    NextSibling(Box<Selector>, Box<Selector>),
+    /// 後続兄弟セレクタ (A ~ B)
+    SubsequentSibling(Box<Selector>, Box<Selector>),
 }
 
 /// シンプルセレクタ
@@ -55,6 +75,82 @@ pub struct SimpleSelector {
     pub classes: Vec<String>,
     /// ユニバーサル (*)
     pub universal: bool,
+    /// 属性セレクタ ([href], [type="text"])
+    pub attributes: Vec<AttributeSelector>,
+    /// 擬似クラス (:first-child, :hover)
+    pub pseudo_classes: Vec<PseudoClass>,
+}
+
+/// 属性セレクタ
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSelector {
+    /// 属性名（小文字）
+    pub name: String,
+    /// 比較方法
+    pub operator: AttributeOperator,
+    /// 比較する値
+    pub value: String,
+    /// 大文字小文字を区別しない（[a="b" i]）
+    pub case_insensitive: bool,
+}
+
+/// 属性セレクタの比較方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// [a]
+    Exists,
+    /// [a=b]
+    Equals,
+    /// [a~=b]（空白区切りのいずれかの語）
+    Includes,
+    /// [a|=b]（b または b-で始まる）
+    DashMatch,
+    /// [a^=b]
+    Prefix,
+    /// [a$=b]
+    Suffix,
+    /// [a*=b]
+    Substring,
+}
+
+/// 擬似クラス
+///
+/// :first-child などは対応する :nth-child(1) などとして保持する。
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoClass {
+    /// :nth-child(an+b)
+    NthChild(Nth),
+    /// :nth-last-child(an+b)
+    NthLastChild(Nth),
+    /// :nth-of-type(an+b)
+    NthOfType(Nth),
+    /// :nth-last-of-type(an+b)
+    NthLastOfType(Nth),
+    /// :root
+    Root,
+    /// :empty
+    Empty,
+    /// :link, :any-link（href を持つ a, area）
+    Link,
+    /// :visited（履歴を公開しないため常にマッチしない）
+    Visited,
+    /// :not(セレクタリスト)
+    Not(Vec<Selector>),
+    /// :hover（マウスカーソルの下の要素とその祖先）
+    Hover,
+    /// :active（マウスボタンを押している要素とその祖先）
+    Active,
+    /// :focus
+    Focus,
+    /// :focus-within
+    FocusWithin,
+}
+
+/// :nth-child() などの an+b
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nth {
+    pub a: i32,
+    pub b: i32,
 }
 
 /// CSS宣言
@@ -64,6 +160,8 @@ pub struct Declaration {
     pub name: String,
     /// 値
     pub value: Value,
+    /// !important
+    pub important: bool,
 }
 
 /// CSS値
@@ -79,6 +177,8 @@ pub enum Value {
     Percentage(f32),
     /// 数値
     Number(f32),
+    /// URL (url(img.png))。引用符は取り除く
+    Url(String),
 }
 
 /// 長さの単位
@@ -145,11 +245,53 @@ impl Value {
     }
 }
 
+impl AttributeSelector {
+    /// 属性値がマッチするか（属性がなければ None）
+    pub fn matches(&self, actual: Option<&str>) -> bool {
+        let Some(actual) = actual else {
+            return false;
+        };
+        let (actual, expected) = if self.case_insensitive {
+            (actual.to_lowercase(), self.value.to_lowercase())
+        } else {
+            (actual.into(), self.value.clone())
+        };
+        match self.operator {
+            AttributeOperator::Exists => true,
+            AttributeOperator::Equals => actual == expected,
+            AttributeOperator::Includes => actual.split_whitespace().any(|word| word == expected),
+            AttributeOperator::DashMatch => {
+                actual == expected
+                    || (actual.starts_with(expected.as_str()) && actual[expected.len()..].starts_with('-'))
+            }
+            // 空の値に対する ^= $= *= は何にもマッチしない
+            AttributeOperator::Prefix => !expected.is_empty() && actual.starts_with(expected.as_str()),
+            AttributeOperator::Suffix => !expected.is_empty() && actual.ends_with(expected.as_str()),
+            AttributeOperator::Substring => !expected.is_empty() && actual.contains(expected.as_str()),
+        }
+    }
+}
+
+impl Nth {
+    /// :first-child などの 1 番目
+    pub const FIRST: Nth = Nth { a: 0, b: 1 };
+
+    /// 1 から数えた位置 `index` が an+b（n >= 0）で表せるか
+    pub fn matches(&self, index: usize) -> bool {
+        let diff = index as i32 - self.b;
+        if self.a == 0 {
+            diff == 0
+        } else {
+            diff % self.a == 0 && diff / self.a >= 0
+        }
+    }
+}
+
 // ============================================================================
 // Specificity
 // ============================================================================
 
-/// 詳細度 (ID数, クラス数, タグ数)
+/// 詳細度 (ID数, クラス・属性・擬似クラス数, タグ数)
 pub type Specificity = (usize, usize, usize);
 
 impl Selector {
@@ -157,7 +299,10 @@ impl Selector {
     pub fn specificity(&self) -> Specificity {
         match self {
             Selector::Simple(simple) => simple.specificity(),
-            Selector::Descendant(a, b) | Selector::Child(a, b) => {
+            Selector::Descendant(a, b)
+            | Selector::Child(a, b)
+            | Selector::NextSibling(a, b)
+            | Selector::SubsequentSibling(a, b) => {
                 let (a1, a2, a3) = a.specificity();
                 let (b1, b2, b3) = b.specificity();
                 (a1 + b1, a2 + b2, a3 + b3)
@@ -169,9 +314,24 @@ impl Selector {
 impl SimpleSelector {
     pub fn specificity(&self) -> Specificity {
         let id_count = if self.id.is_some() { 1 } else { 0 };
-        let class_count = self.classes.len();
+        let mut class_count = self.classes.len() + self.attributes.len();
         let tag_count = if self.tag_name.is_some() { 1 } else { 0 };
-        (id_count, class_count, tag_count)
+        let mut specificity = (id_count, 0, tag_count);
+
+        for pseudo in &self.pseudo_classes {
+            match pseudo {
+                // :not() は引数のうち最も詳細なセレクタの詳細度
+                PseudoClass::Not(selectors) => {
+                    let (a, b, c) = selectors.iter().map(Selector::specificity).max().unwrap_or_default();
+                    specificity.0 += a;
+                    class_count += b;
+                    specificity.2 += c;
+                }
+                _ => class_count += 1,
+            }
+        }
+        specificity.1 = class_count;
+        specificity
     }
 }
 
@@ -201,6 +361,7 @@ impl CssParser {
         let mut parser = CssParser::new(input);
         Stylesheet {
             rules: parser.parse_rules(),
+            origin: Origin::Author,
         }
     }
 
@@ -301,10 +462,33 @@ impl CssParser {
         }
     }
 
+    /// ブロック（'{' から対応する '}' まで）をスキップ
+    fn skip_block(&mut self) {
+        while let Some(c) = self.advance() {
+            if c == '{' {
+                let mut depth = 1;
+                while depth > 0 {
+                    match self.advance() {
+                        Some('{') => depth += 1,
+                        Some('}') => depth -= 1,
+                        None => break,
+                        _ => {}
+                    }
+                }
+                break;
+            }
+        }
+    }
+
     /// 単一ルールをパース
     fn parse_rule(&mut self) -> Option<Rule> {
-        let selectors = self.parse_selectors();
+        let Some(selectors) = self.parse_selectors() else {
+            // 解釈できないセレクタを含むルールは無視
+            self.skip_block();
+            return None;
+        };
         if selectors.is_empty() {
+            self.skip_block();
             return None;
         }
 
@@ -328,20 +512,20 @@ impl CssParser {
         })
     }
 
-    /// セレクタのリストをパース
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+    /// セレクタのリストをパース（'{' または ')' の手前まで）
+    ///
+    /// ひとつでも解釈できないセレクタがあれば None。
+    fn parse_selectors(&mut self) -> Option<Vec<Selector>> {
         let mut selectors = Vec::new();
 
         loop {
             self.skip_whitespace();
 
-            if self.eof() || self.current_char() == Some('{') {
+            if matches!(self.current_char(), None | Some('{') | Some(')')) {
                 break;
             }
 
-            if let Some(selector) = self.parse_selector() {
-                selectors.push(selector);
-            }
+            selectors.push(self.parse_selector()?);
 
             self.skip_whitespace();
 
@@ -354,7 +538,7 @@ impl CssParser {
 
         // 詳細度でソート（降順）
         selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
-        selectors
+        Some(selectors)
     }
 
     /// 単一セレクタをパース
@@ -362,33 +546,29 @@ impl CssParser {
         let mut result = self.parse_simple_selector()?;
 
         loop {
+            let start = self.pos;
             self.skip_whitespace();
+            let had_space = self.pos != start;
 
-            match self.current_char() {
-                Some('>') => {
+            let combinator = match self.current_char() {
+                None | Some(',') | Some('{') | Some(')') => break,
+                Some(c @ ('>' | '+' | '~')) => {
                     self.advance();
                     self.skip_whitespace();
-                    let right = self.parse_simple_selector()?;
-                    result = Selector::Child(Box::new(result), Box::new(right));
-                }
-                Some(c) if c != ',' && c != '{' && !c.is_whitespace() => {
-                    let right = self.parse_simple_selector()?;
-                    result = Selector::Descendant(Box::new(result), Box::new(right));
-                }
-                Some(c) if c.is_whitespace() => {
-                    // 次の文字を確認
-                    self.skip_whitespace();
-                    if let Some(c) = self.current_char() {
-                        if c != ',' && c != '{' && c != '>' {
-                            let right = self.parse_simple_selector()?;
-                            result = Selector::Descendant(Box::new(result), Box::new(right));
-                            continue;
-                        }
-                    }
-                    break;
+                    c
                 }
-                _ => break,
-            }
+                _ if had_space => ' ',
+                _ => return None,
+            };
+
+            let left = Box::new(result);
+            let right = Box::new(self.parse_simple_selector()?);
+            result = match combinator {
+                '>' => Selector::Child(left, right),
+                '+' => Selector::NextSibling(left, right),
+                '~' => Selector::SubsequentSibling(left, right),
+                _ => Selector::Descendant(left, right),
+            };
         }
 
         Some(result)
@@ -415,23 +595,193 @@ impl CssParser {
                 Some(c) if c.is_alphabetic() || c == '-' || c == '_' => {
                     selector.tag_name = Some(self.parse_identifier());
                 }
+                Some('[') => {
+                    self.advance();
+                    selector.attributes.push(self.parse_attribute_selector()?);
+                }
+                Some(':') => {
+                    self.advance();
+                    // 擬似要素（::before など）は未対応
+                    if self.current_char() == Some(':') {
+                        return None;
+                    }
+                    selector.pseudo_classes.extend(self.parse_pseudo_class()?);
+                }
                 _ => break,
             }
         }
 
-        if selector.tag_name.is_none()
-            && selector.id.is_none()
-            && selector.classes.is_empty()
-            && !selector.universal
-        {
+        if selector == SimpleSelector::default() {
             None
         } else {
             Some(Selector::Simple(selector))
         }
     }
 
-    /// 識別子をパース
+    /// 属性セレクタをパース（'[' の後から ']' まで）
+    fn parse_attribute_selector(&mut self) -> Option<AttributeSelector> {
+        self.skip_whitespace();
+        let name = self.parse_identifier();
+        if name.is_empty() {
+            return None;
+        }
+        self.skip_whitespace();
+
+        let operator = match self.advance()? {
+            ']' => {
+                return Some(AttributeSelector {
+                    name,
+                    operator: AttributeOperator::Exists,
+                    value: String::new(),
+                    case_insensitive: false,
+                });
+            }
+            '=' => AttributeOperator::Equals,
+            c => {
+                let operator = match c {
+                    '~' => AttributeOperator::Includes,
+                    '|' => AttributeOperator::DashMatch,
+                    '^' => AttributeOperator::Prefix,
+                    '$' => AttributeOperator::Suffix,
+                    '*' => AttributeOperator::Substring,
+                    _ => return None,
+                };
+                if self.advance()? != '=' {
+                    return None;
+                }
+                operator
+            }
+        };
+
+        self.skip_whitespace();
+        let value = match self.current_char()? {
+            quote @ ('"' | '\'') => {
+                self.advance();
+                let mut value = String::new();
+                loop {
+                    match self.advance()? {
+                        c if c == quote => break,
+                        c => value.push(c),
+                    }
+                }
+                value
+            }
+            _ => self.parse_raw_identifier(),
+        };
+
+        self.skip_whitespace();
+        let case_insensitive = match self.current_char()? {
+            'i' | 'I' => true,
+            's' | 'S' => false,
+            ']' => {
+                self.advance();
+                return Some(AttributeSelector { name, operator, value, case_insensitive: false });
+            }
+            _ => return None,
+        };
+        self.advance();
+        self.skip_whitespace();
+        if self.advance()? != ']' {
+            return None;
+        }
+
+        Some(AttributeSelector { name, operator, value, case_insensitive })
+    }
+
+    /// 擬似クラスをパース（':' の後から）
+    ///
+    /// :only-child のように 2 つの条件に分かれるものがあるため Vec で返す。
+    fn parse_pseudo_class(&mut self) -> Option<Vec<PseudoClass>> {
+        let name = self.parse_identifier();
+
+        if self.current_char() == Some('(') {
+            self.advance();
+            let pseudo = match name.as_str() {
+                "not" => {
+                    let selectors = self.parse_selectors()?;
+                    if selectors.is_empty() {
+                        return None;
+                    }
+                    PseudoClass::Not(selectors)
+                }
+                "nth-child" => PseudoClass::NthChild(self.parse_nth()?),
+                "nth-last-child" => PseudoClass::NthLastChild(self.parse_nth()?),
+                "nth-of-type" => PseudoClass::NthOfType(self.parse_nth()?),
+                "nth-last-of-type" => PseudoClass::NthLastOfType(self.parse_nth()?),
+                _ => return None,
+            };
+            self.skip_whitespace();
+            if self.advance()? != ')' {
+                return None;
+            }
+            return Some(vec![pseudo]);
+        }
+
+        let pseudo = match name.as_str() {
+            "first-child" => PseudoClass::NthChild(Nth::FIRST),
+            "last-child" => PseudoClass::NthLastChild(Nth::FIRST),
+            "only-child" => {
+                return Some(vec![PseudoClass::NthChild(Nth::FIRST), PseudoClass::NthLastChild(Nth::FIRST)]);
+            }
+            "first-of-type" => PseudoClass::NthOfType(Nth::FIRST),
+            "last-of-type" => PseudoClass::NthLastOfType(Nth::FIRST),
+            "only-of-type" => {
+                return Some(vec![PseudoClass::NthOfType(Nth::FIRST), PseudoClass::NthLastOfType(Nth::FIRST)]);
+            }
+            "root" => PseudoClass::Root,
+            "empty" => PseudoClass::Empty,
+            "link" | "any-link" => PseudoClass::Link,
+            "visited" => PseudoClass::Visited,
+            "hover" => PseudoClass::Hover,
+            "active" => PseudoClass::Active,
+            "focus" => PseudoClass::Focus,
+            "focus-within" => PseudoClass::FocusWithin,
+            _ => return None,
+        };
+        Some(vec![pseudo])
+    }
+
+    /// an+b をパース（odd, even, 3, 2n+1, -n+3 など。')' の手前まで）
+    fn parse_nth(&mut self) -> Option<Nth> {
+        let mut text = String::new();
+        while let Some(c) = self.current_char() {
+            if c == ')' {
+                break;
+            }
+            if !c.is_whitespace() {
+                text.push(c.to_ascii_lowercase());
+            }
+            self.advance();
+        }
+
+        match text.as_str() {
+            "odd" => return Some(Nth { a: 2, b: 1 }),
+            "even" => return Some(Nth { a: 2, b: 0 }),
+            _ => {}
+        }
+
+        let Some((a, b)) = text.split_once('n') else {
+            return Some(Nth { a: 0, b: text.parse().ok()? });
+        };
+        let a = match a {
+            "" | "+" => 1,
+            "-" => -1,
+            a => a.parse().ok()?,
+        };
+        let b = match b {
+            "" => 0,
+            b => b.strip_prefix('+').unwrap_or(b).parse().ok()?,
+        };
+        Some(Nth { a, b })
+    }
+
+    /// 識別子をパース（小文字に正規化）
     fn parse_identifier(&mut self) -> String {
+        self.parse_raw_identifier().to_lowercase()
+    }
+
+    /// 識別子をそのままパース
+    fn parse_raw_identifier(&mut self) -> String {
         let mut name = String::new();
 
         while let Some(c) = self.current_char() {
@@ -443,7 +793,7 @@ impl CssParser {
             }
         }
 
-        name.to_lowercase()
+        name
     }
 
     /// 宣言のリストをパース
@@ -457,9 +807,7 @@ impl CssParser {
                 break;
             }
 
-            if let Some(decl) = self.parse_declaration() {
-                declarations.push(decl);
-            }
+            declarations.extend(self.parse_declaration());
 
             self.skip_whitespace();
 
@@ -471,25 +819,71 @@ impl CssParser {
         declarations
     }
 
-    /// 単一宣言をパース
-    fn parse_declaration(&mut self) -> Option<Declaration> {
+    /// 単一宣言をパース（ショートハンドは個別のプロパティに展開）
+    fn parse_declaration(&mut self) -> Vec<Declaration> {
         let name = self.parse_identifier();
         if name.is_empty() {
-            return None;
+            // 解釈できない文字は宣言の終わりまで読み飛ばす
+            self.skip_value();
+            return Vec::new();
         }
 
         self.skip_whitespace();
 
         if self.current_char() != Some(':') {
-            return None;
+            self.skip_value();
+            return Vec::new();
         }
         self.advance();
 
-        self.skip_whitespace();
+        let (values, important) = self.parse_values();
+
+        let mut declarations = expand_shorthand(&name, values);
+        for declaration in &mut declarations {
+            declaration.important = important;
+        }
+        declarations
+    }
+
+    /// 宣言の値（空白区切りの並び）と !important の有無をパース
+    fn parse_values(&mut self) -> (Vec<Value>, bool) {
+        let mut values = Vec::new();
+        let mut important = false;
+
+        loop {
+            self.skip_whitespace();
+
+            match self.current_char() {
+                None | Some(';') | Some('}') => break,
+                Some('!') => {
+                    self.advance();
+                    self.skip_whitespace();
+                    important |= self.parse_identifier() == "important";
+                }
+                _ => {
+                    let start = self.pos;
+                    let value = self.parse_value();
+                    if self.pos == start {
+                        // カンマや括弧など値として解釈できない文字
+                        self.advance();
+                    } else {
+                        values.push(value);
+                    }
+                }
+            }
+        }
 
-        let value = self.parse_value();
+        (values, important)
+    }
 
-        Some(Declaration { name, value })
+    /// 宣言の終わり（';' または '}'）まで読み飛ばす
+    fn skip_value(&mut self) {
+        while let Some(c) = self.current_char() {
+            if c == ';' || c == '}' {
+                break;
+            }
+            self.advance();
+        }
     }
 
     /// 値をパース
@@ -592,6 +986,10 @@ impl CssParser {
     fn parse_keyword_or_color(&mut self) -> Value {
         let keyword = self.parse_identifier();
 
+        if keyword == "url" && self.current_char() == Some('(') {
+            return self.parse_url();
+        }
+
         // 名前付き色
         let color = match keyword.as_str() {
             "black" => Some(Color::new(0, 0, 0)),
@@ -620,6 +1018,127 @@ impl CssParser {
             Value::Keyword(keyword)
         }
     }
+
+    /// `url(` の後の参照をパース（引用符あり・なしの両方）
+    fn parse_url(&mut self) -> Value {
+        self.advance(); // '('
+        self.skip_whitespace();
+
+        let quote = match self.current_char() {
+            Some(c @ ('"' | '\'')) => {
+                self.advance();
+                Some(c)
+            }
+            _ => None,
+        };
+        let mut url = String::new();
+        while let Some(c) = self.current_char() {
+            if Some(c) == quote || (quote.is_none() && (c == ')' || c.is_whitespace())) {
+                break;
+            }
+            url.push(c);
+            self.advance();
+        }
+        if quote.is_some() {
+            self.advance();
+        }
+
+        self.skip_whitespace();
+        if self.current_char() == Some(')') {
+            self.advance();
+        }
+        Value::Url(url)
+    }
+}
+
+// ============================================================================
+// Shorthand Properties
+// ============================================================================
+
+/// 個別のプロパティに展開するショートハンドか
+pub fn is_shorthand(name: &str) -> bool {
+    matches!(name, "flex" | "flex-flow" | "gap" | "border" | "background")
+}
+
+/// ショートハンドを個別のプロパティに展開
+///
+/// ショートハンド以外は先頭の値だけを使う（複数値のプロパティは未対応）。
+pub fn expand_shorthand(name: &str, values: Vec<Value>) -> Vec<Declaration> {
+    let decl = |name: &str, value: Value| Declaration { name: name.into(), value, important: false };
+    let Some(first) = values.first().cloned() else {
+        return Vec::new();
+    };
+
+    match name {
+        "flex" => {
+            let auto = Value::Keyword("auto".into());
+            let (grow, shrink, basis) = match values.as_slice() {
+                [Value::Keyword(k)] if k == "none" => (0.0, 0.0, auto),
+                [Value::Keyword(k)] if k == "auto" => (1.0, 1.0, auto),
+                [Value::Keyword(k)] if k == "initial" => (0.0, 1.0, auto),
+                // flex: <grow> は flex-basis 0
+                [Value::Number(grow)] => (*grow, 1.0, Value::Length(0.0, Unit::Px)),
+                [basis] => (1.0, 1.0, basis.clone()),
+                [Value::Number(grow), Value::Number(shrink)] => {
+                    (*grow, *shrink, Value::Length(0.0, Unit::Px))
+                }
+                [Value::Number(grow), basis] => (*grow, 1.0, basis.clone()),
+                [Value::Number(grow), Value::Number(shrink), basis, ..] => {
+                    (*grow, *shrink, basis.clone())
+                }
+                _ => return Vec::new(),
+            };
+            vec![
+                decl("flex-grow", Value::Number(grow)),
+                decl("flex-shrink", Value::Number(shrink)),
+                decl("flex-basis", basis),
+            ]
+        }
+        "flex-flow" => values
+            .into_iter()
+            .filter_map(|value| match &value {
+                Value::Keyword(k) if k.starts_with("row") || k.starts_with("column") => {
+                    Some(decl("flex-direction", value))
+                }
+                Value::Keyword(k) if k.contains("wrap") => Some(decl("flex-wrap", value)),
+                _ => None,
+            })
+            .collect(),
+        "border" => values
+            .into_iter()
+            .map(|value| match value {
+                Value::Length(..) | Value::Number(_) => decl("border-width", value),
+                Value::Color(_) => decl("border-color", value),
+                _ => decl("border-style", value),
+            })
+            .collect(),
+        "background" => {
+            // 指定のない項目は初期値に戻す
+            let mut color = Value::Color(Color::TRANSPARENT);
+            let mut image = Value::Keyword("none".into());
+            let mut repeat = Value::Keyword("repeat".into());
+            for value in values {
+                match &value {
+                    Value::Color(_) => color = value,
+                    Value::Url(_) => image = value,
+                    Value::Keyword(k) if k == "none" => image = value,
+                    Value::Keyword(k) if k.contains("repeat") => repeat = value,
+                    // 位置・サイズなどは未対応
+                    _ => {}
+                }
+            }
+            vec![
+                decl("background-color", color),
+                decl("background-image", image),
+                decl("background-repeat", repeat),
+            ]
+        }
+        "gap" => {
+            let column = values.get(1).cloned().unwrap_or_else(|| first.clone());
+            vec![decl("row-gap", first), decl("column-gap", column)]
+        }
+        _ => vec![decl(name, first)],
+    }
 }
 
 // ============================================================================
@@ -657,6 +1176,56 @@ mod tests {
         assert_eq!(*color, Value::Color(Color::new(255, 0, 0)));
     }
 
+    #[test]
+    fn test_parse_shorthand() {
+        let css = "div { flex: 2 1 30%; gap: 4px 8px; margin: 0 auto; font-family: a, b; color: red }";
+        let sheet = CssParser::parse(css);
+
+        let decls = &sheet.rules[0].declarations;
+        let names: Vec<&str> = decls.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["flex-grow", "flex-shrink", "flex-basis", "row-gap", "column-gap", "margin", "font-family", "color"]
+        );
+        assert_eq!(decls[0].value, Value::Number(2.0));
+        assert_eq!(decls[2].value, Value::Percentage(30.0));
+        assert_eq!(decls[4].value, Value::Length(8.0, Unit::Px));
+        assert_eq!(decls[7].value, Value::Color(Color::new(255, 0, 0)));
+
+        let sheet = CssParser::parse("td { border: 1px solid #ccc }");
+        let decls = &sheet.rules[0].declarations;
+        assert_eq!(decls[0].name, "border-width");
+        assert_eq!(decls[2].value, Value::Color(Color::new(204, 204, 204)));
+
+        let sheet = CssParser::parse("a { flex: 1 } b { flex: none }");
+        assert_eq!(sheet.rules[0].declarations[2].value, Value::Length(0.0, Unit::Px));
+        assert_eq!(sheet.rules[1].declarations[0].value, Value::Number(0.0));
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let css = r#"div { background-image: url( "img/a b.png" ) }
+                     p { background: #fff URL(bg.bmp) no-repeat top left }
+                     span { background: red }"#;
+        let sheet = CssParser::parse(css);
+
+        assert_eq!(sheet.rules[0].declarations[0].value, Value::Url("img/a b.png".into()));
+        let values: Vec<(&str, &Value)> = sheet.rules[1]
+            .declarations
+            .iter()
+            .map(|d| (d.name.as_str(), &d.value))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("background-color", &Value::Color(Color::WHITE)),
+                ("background-image", &Value::Url("bg.bmp".into())),
+                ("background-repeat", &Value::Keyword("no-repeat".into())),
+            ]
+        );
+        assert_eq!(sheet.rules[2].declarations[1].value, Value::Keyword("none".into()));
+    }
+
     #[test]
     fn test_specificity() {
         let mut parser = CssParser::new("#id");
@@ -670,5 +1239,43 @@ mod tests {
         let mut parser = CssParser::new("div");
         let selector = parser.parse_selector().unwrap();
         assert_eq!(selector.specificity(), (0, 0, 1));
+
+        let mut parser = CssParser::new("ul > li[data-x]:first-child:not(#a, .b) + p:hover");
+        let selector = parser.parse_selector().unwrap();
+        assert_eq!(selector.specificity(), (1, 3, 3));
+    }
+
+    #[test]
+    fn test_parse_complex_selector() {
+        let sheet = CssParser::parse("a[href^='http' i] ~ li:nth-child(-n+3) { color: red !important }");
+        let rule = &sheet.rules[0];
+        assert!(rule.declarations[0].important);
+
+        let Selector::SubsequentSibling(left, right) = &rule.selectors[0] else {
+            panic!("expected a ~ combinator");
+        };
+        let Selector::Simple(a) = left.as_ref() else { panic!() };
+        assert_eq!(
+            a.attributes,
+            [AttributeSelector {
+                name: "href".into(),
+                operator: AttributeOperator::Prefix,
+                value: "http".into(),
+                case_insensitive: true,
+            }]
+        );
+        let Selector::Simple(li) = right.as_ref() else { panic!() };
+        assert_eq!(li.pseudo_classes, [PseudoClass::NthChild(Nth { a: -1, b: 3 })]);
+
+        let nth = Nth { a: 2, b: 1 };
+        assert!(nth.matches(1) && nth.matches(3) && !nth.matches(2));
+        assert!((1..=3).all(|i| Nth { a: -1, b: 3 }.matches(i)) && !Nth { a: -1, b: 3 }.matches(4));
+    }
+
+    #[test]
+    fn test_invalid_selector_drops_rule() {
+        let sheet = CssParser::parse("p::before { color: red } a:unknown, b { color: red } div { color: blue }");
+        assert_eq!(sheet.rules.len(), 1);
+        assert_eq!(sheet.rules[0].declarations[0].value, Value::Color(Color::new(0, 0, 255)));
     }
 }
@@ -141,11 +141,35 @@ impl Node {
             .unwrap_or_default()
     }
 
+    /// 属性を設定（要素以外では何もしない）
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        if let NodeType::Element(data) = &mut self.node_type {
+            data.set_attribute(name.into(), value.into());
+        }
+    }
+
+    /// 属性を削除
+    pub fn remove_attribute(&mut self, name: &str) {
+        if let NodeType::Element(data) = &mut self.node_type {
+            data.attributes.remove(name);
+        }
+    }
+
     /// 子要素を追加
     pub fn append_child(&mut self, child: Node) {
         self.children.push(child);
     }
 
+    /// 子インデックスの列で子孫を取得（空の列なら自身）
+    pub fn descendant(&self, path: &[usize]) -> Option<&Node> {
+        path.iter().try_fold(self, |node, &i| node.children.get(i))
+    }
+
+    /// 子インデックスの列で子孫を可変で取得
+    pub fn descendant_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+        path.iter().try_fold(self, |node, &i| node.children.get_mut(i))
+    }
+
     /// すべての子孫テキストを結合
     pub fn inner_text(&self) -> String {
         let mut result = String::new();
@@ -0,0 +1,698 @@
+// ============================================================================
+// src/application/browser/forms.rs - Form Controls
+// ============================================================================
+//!
+//! # フォーム部品
+//!
+//! `<input>`、`<textarea>`、`<select>`、`<button>` の値の読み書き、キー入力による編集、
+//! フォームの送信リクエストの組み立て。
+//!
+//! 部品の現在の値は DOM に持たせる（`<input>` は `value` / `checked` 属性、
+//! `<textarea>` は子のテキスト、`<select>` は `<option>` の `selected` 属性）。
+//! スタイル・レイアウト・描画は DOM を見るだけで入力中の値を反映できる。
+//!
+//! 送信は application/x-www-form-urlencoded の GET / POST のみ。`type=reset` は
+//! 初期値を保持していないので何もしないボタンとして扱う。
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::io::hid::{KeyCode, KeyEvent, KeyState};
+use crate::net::http::{Request, Scheme, Url};
+
+use super::dom::Node;
+use super::loader;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// フォーム部品の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlKind {
+    /// 1行のテキスト（`type=text` と未対応の type）
+    Text,
+    /// パスワード（`*` で表示する）
+    Password,
+    /// チェックボックス
+    Checkbox,
+    /// ラジオボタン（同じフォームで同じ name のものから 1 つ）
+    Radio,
+    /// 送信ボタン（`<input type=submit>`、`<button>`）
+    Submit,
+    /// 何もしないボタン（`type=button`、`type=reset`）
+    Button,
+    /// 表示せずに送信だけされる値
+    Hidden,
+    /// 複数行のテキスト
+    TextArea,
+    /// 選択肢
+    Select,
+}
+
+impl ControlKind {
+    /// 要素の部品の種類（フォーム部品でなければ None）
+    pub fn of(node: &Node) -> Option<Self> {
+        let input_type = node.get_attribute("type").map(|t| t.trim().to_ascii_lowercase());
+        let kind = match node.tag_name()? {
+            "input" => match input_type.as_deref() {
+                Some("password") => Self::Password,
+                Some("checkbox") => Self::Checkbox,
+                Some("radio") => Self::Radio,
+                Some("submit") => Self::Submit,
+                Some("button") | Some("reset") => Self::Button,
+                Some("hidden") => Self::Hidden,
+                _ => Self::Text,
+            },
+            "button" => match input_type.as_deref() {
+                Some("button") | Some("reset") => Self::Button,
+                _ => Self::Submit,
+            },
+            "textarea" => Self::TextArea,
+            "select" => Self::Select,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// 文字を入力できる部品か
+    pub fn is_text_field(self) -> bool {
+        matches!(self, Self::Text | Self::Password | Self::TextArea)
+    }
+}
+
+/// 部品や URL バーへのキー操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKey {
+    /// 文字の入力
+    Char(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Enter,
+    Tab,
+}
+
+impl EditKey {
+    /// `io::hid` のキーイベントから（離したときと対応しないキーは None）
+    pub fn from_key_event(event: &KeyEvent) -> Option<Self> {
+        if event.state == KeyState::Released {
+            return None;
+        }
+        let key = match event.key {
+            KeyCode::Backspace => Self::Backspace,
+            KeyCode::Delete => Self::Delete,
+            KeyCode::Left => Self::Left,
+            KeyCode::Right => Self::Right,
+            KeyCode::Up => Self::Up,
+            KeyCode::Down => Self::Down,
+            KeyCode::Home => Self::Home,
+            KeyCode::End => Self::End,
+            KeyCode::Enter | KeyCode::NumPadEnter => Self::Enter,
+            KeyCode::Tab => Self::Tab,
+            _ => return event.to_char().map(Self::from_char),
+        };
+        Some(key)
+    }
+
+    /// 文字から（制御文字は対応するキーにする）
+    pub fn from_char(c: char) -> Self {
+        match c {
+            '\n' | '\r' => Self::Enter,
+            '\x08' => Self::Backspace,
+            '\t' => Self::Tab,
+            '\x7f' => Self::Delete,
+            c => Self::Char(c),
+        }
+    }
+}
+
+/// 部品を操作した結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormAction {
+    /// 値は変わっていない（キャレットが動いただけのこともある）
+    None,
+    /// 値が変わった（input イベントを発火する）
+    Input,
+    /// フォームを送信する
+    Submit,
+}
+
+// ============================================================================
+// Values
+// ============================================================================
+
+/// 部品の現在の値
+pub fn value(node: &Node) -> String {
+    match ControlKind::of(node) {
+        Some(ControlKind::TextArea) => node.inner_text(),
+        Some(ControlKind::Select) => selected_option(node).map(option_value).unwrap_or_default(),
+        Some(ControlKind::Checkbox | ControlKind::Radio) => node.get_attribute("value").unwrap_or("on").into(),
+        _ => node.get_attribute("value").unwrap_or_default().into(),
+    }
+}
+
+/// 部品の値を設定する（`<select>` は値が一致する選択肢を選ぶ）
+pub fn set_value(node: &mut Node, value: &str) {
+    match ControlKind::of(node) {
+        Some(ControlKind::TextArea) => node.children = alloc::vec![Node::text(value.into())],
+        Some(ControlKind::Select) => {
+            if let Some(index) = options(node).iter().position(|option| option_value(option) == value) {
+                select_option(node, index);
+            }
+        }
+        Some(_) => node.set_attribute("value", value),
+        None => {}
+    }
+}
+
+/// チェックされているか
+pub fn is_checked(node: &Node) -> bool {
+    node.get_attribute("checked").is_some()
+}
+
+/// チェックボックス・ラジオボタンのチェックを変える（変わったら true）
+///
+/// ラジオボタンをチェックすると、同じフォームで同じ name のものはチェックが外れる。
+pub fn set_checked(root: &mut Node, path: &[usize], checked: bool) -> bool {
+    let Some(node) = root.descendant(path) else {
+        return false;
+    };
+    let kind = ControlKind::of(node);
+    if !matches!(kind, Some(ControlKind::Checkbox | ControlKind::Radio)) || is_checked(node) == checked {
+        return false;
+    }
+
+    if kind == Some(ControlKind::Radio) && checked
+        && let Some(name) = node.get_attribute("name").filter(|n| !n.is_empty()).map(String::from)
+    {
+        let scope = form_path(root, path).unwrap_or_default();
+        if let Some(scope) = root.descendant_mut(&scope) {
+            visit_mut(scope, &mut |other| {
+                if ControlKind::of(other) == Some(ControlKind::Radio) && other.get_attribute("name") == Some(&name) {
+                    other.remove_attribute("checked");
+                }
+            });
+        }
+    }
+
+    if let Some(node) = root.descendant_mut(path) {
+        if checked {
+            node.set_attribute("checked", "");
+        } else {
+            node.remove_attribute("checked");
+        }
+    }
+    true
+}
+
+/// 無効化されている部品か
+pub fn is_disabled(node: &Node) -> bool {
+    node.get_attribute("disabled").is_some()
+}
+
+/// 部品に表示する文字列（テキスト欄は値、ボタンはラベル、選択肢は選ばれているもの）
+pub fn display_text(node: &Node) -> String {
+    let collapse = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match ControlKind::of(node) {
+        Some(ControlKind::Text | ControlKind::TextArea) => value(node),
+        Some(ControlKind::Password) => "*".repeat(value(node).chars().count()),
+        Some(ControlKind::Submit | ControlKind::Button) if node.tag_name() == Some("button") => {
+            collapse(&node.inner_text())
+        }
+        Some(ControlKind::Submit) => node.get_attribute("value").unwrap_or("Submit").into(),
+        Some(ControlKind::Button) => {
+            let is_reset = node.get_attribute("type").is_some_and(|t| t.trim().eq_ignore_ascii_case("reset"));
+            node.get_attribute("value").unwrap_or(if is_reset { "Reset" } else { "" }).into()
+        }
+        Some(ControlKind::Select) => selected_option(node).map(option_label).unwrap_or_default(),
+        Some(ControlKind::Checkbox | ControlKind::Radio | ControlKind::Hidden) | None => String::new(),
+    }
+}
+
+/// `<select>` の選択肢（`<optgroup>` の中も含む、文書順）
+pub fn options(select: &Node) -> Vec<&Node> {
+    select.find_elements_by_tag("option")
+}
+
+/// 選ばれている選択肢の番号（selected が無ければ先頭、選択肢が無ければ None）
+pub fn selected_index(select: &Node) -> Option<usize> {
+    let options = options(select);
+    if options.is_empty() {
+        return None;
+    }
+    Some(options.iter().rposition(|option| option.get_attribute("selected").is_some()).unwrap_or(0))
+}
+
+/// 選ばれている選択肢
+fn selected_option(select: &Node) -> Option<&Node> {
+    options(select).get(selected_index(select)?).copied()
+}
+
+/// 選択肢に表示する文字列（label 属性が無ければテキスト）
+pub fn option_label(option: &Node) -> String {
+    match option.get_attribute("label") {
+        Some(label) => label.into(),
+        None => option.inner_text().split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// 選択肢の値（value 属性が無ければテキスト）
+fn option_value(option: &Node) -> String {
+    match option.get_attribute("value") {
+        Some(value) => value.into(),
+        None => option.inner_text().split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// `index` 番目の選択肢だけを選ぶ
+fn select_option(select: &mut Node, index: usize) {
+    let mut i = 0;
+    visit_mut(select, &mut |node| {
+        if node.tag_name() == Some("option") {
+            if i == index {
+                node.set_attribute("selected", "");
+            } else {
+                node.remove_attribute("selected");
+            }
+            i += 1;
+        }
+    });
+}
+
+/// 自身と子孫を文書順に訪れる
+fn visit_mut(node: &mut Node, f: &mut dyn FnMut(&mut Node)) {
+    f(node);
+    for child in &mut node.children {
+        visit_mut(child, f);
+    }
+}
+
+// ============================================================================
+// Editing
+// ============================================================================
+
+/// テキストをキー操作で編集する（`caret` は文字単位の位置）
+///
+/// 値が変わったら true。`multiline` でなければ Enter では改行しない。
+pub fn edit_text(text: &mut String, caret: &mut usize, key: EditKey, multiline: bool) -> bool {
+    let chars: Vec<char> = text.chars().collect();
+    let len = chars.len();
+    *caret = (*caret).min(len);
+    let byte_index = |index: usize| text.char_indices().nth(index).map_or(text.len(), |(i, _)| i);
+
+    // キャレットのある行の先頭と末尾
+    let line_start = |at: usize| chars[..at].iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+    let line_end = |at: usize| chars[at..].iter().position(|&c| c == '\n').map_or(len, |i| at + i);
+    let (start, end) = (line_start(*caret), line_end(*caret));
+
+    match key {
+        EditKey::Char(c) if !c.is_control() => {
+            text.insert(byte_index(*caret), c);
+            *caret += 1;
+            true
+        }
+        EditKey::Enter if multiline => {
+            text.insert(byte_index(*caret), '\n');
+            *caret += 1;
+            true
+        }
+        EditKey::Backspace if *caret > 0 => {
+            *caret -= 1;
+            text.remove(byte_index(*caret));
+            true
+        }
+        EditKey::Delete if *caret < len => {
+            text.remove(byte_index(*caret));
+            true
+        }
+        EditKey::Left => {
+            *caret = caret.saturating_sub(1);
+            false
+        }
+        EditKey::Right => {
+            *caret = (*caret + 1).min(len);
+            false
+        }
+        EditKey::Home => {
+            *caret = start;
+            false
+        }
+        EditKey::End => {
+            *caret = end;
+            false
+        }
+        // 上下の行の同じ桁へ（行が短ければその行の末尾）
+        EditKey::Up if start > 0 => {
+            *caret = (line_start(start - 1) + (*caret - start)).min(start - 1);
+            false
+        }
+        EditKey::Down if end < len => {
+            *caret = (end + 1 + (*caret - start)).min(line_end(end + 1));
+            false
+        }
+        _ => false,
+    }
+}
+
+/// フォーカスのある部品をキー操作で編集する
+///
+/// テキスト欄で Enter を押すとフォームを送信する（`<textarea>` では改行）。
+/// ボタン・チェックボックス・ラジオボタンは Space と Enter で `activate` する。
+pub fn edit(root: &mut Node, path: &[usize], caret: &mut usize, key: EditKey) -> FormAction {
+    let Some(node) = root.descendant(path) else {
+        return FormAction::None;
+    };
+    let Some(kind) = ControlKind::of(node) else {
+        return FormAction::None;
+    };
+    if is_disabled(node) {
+        return FormAction::None;
+    }
+
+    match kind {
+        ControlKind::Text | ControlKind::Password if key == EditKey::Enter => FormAction::Submit,
+        ControlKind::Text | ControlKind::Password | ControlKind::TextArea => {
+            let mut text = value(node);
+            let length = text.chars().count();
+            if !edit_text(&mut text, caret, key, kind == ControlKind::TextArea)
+                || node.get_attribute("readonly").is_some()
+            {
+                return FormAction::None;
+            }
+            let max_length = node.get_attribute("maxlength").and_then(|m| m.trim().parse::<usize>().ok());
+            let new_length = text.chars().count();
+            if new_length > length && max_length.is_some_and(|max| new_length > max) {
+                *caret -= 1;
+                return FormAction::None;
+            }
+            if let Some(node) = root.descendant_mut(path) {
+                set_value(node, &text);
+            }
+            FormAction::Input
+        }
+        ControlKind::Select => {
+            let (Some(current), count) = (selected_index(node), options(node).len()) else {
+                return FormAction::None;
+            };
+            let next = match key {
+                EditKey::Up => current.saturating_sub(1),
+                EditKey::Down => (current + 1).min(count - 1),
+                _ => current,
+            };
+            if next == current {
+                return FormAction::None;
+            }
+            if let Some(node) = root.descendant_mut(path) {
+                select_option(node, next);
+            }
+            FormAction::Input
+        }
+        ControlKind::Checkbox | ControlKind::Radio | ControlKind::Submit | ControlKind::Button => {
+            match key {
+                EditKey::Char(' ') | EditKey::Enter => activate(root, path),
+                _ => FormAction::None,
+            }
+        }
+        ControlKind::Hidden => FormAction::None,
+    }
+}
+
+/// 部品をクリックしたときの動作
+///
+/// チェックボックスは切り替え、ラジオボタンはチェック、`<select>` は次の選択肢に進め
+/// （最後なら先頭に戻る）、送信ボタンはフォームを送信する。
+pub fn activate(root: &mut Node, path: &[usize]) -> FormAction {
+    let Some(node) = root.descendant(path).filter(|node| !is_disabled(node)) else {
+        return FormAction::None;
+    };
+    let changed = match ControlKind::of(node) {
+        Some(ControlKind::Checkbox) => {
+            let checked = is_checked(node);
+            set_checked(root, path, !checked)
+        }
+        Some(ControlKind::Radio) => set_checked(root, path, true),
+        Some(ControlKind::Select) => {
+            let count = options(node).len();
+            match selected_index(node) {
+                Some(current) if count > 1 => {
+                    if let Some(node) = root.descendant_mut(path) {
+                        select_option(node, (current + 1) % count);
+                    }
+                    true
+                }
+                _ => false,
+            }
+        }
+        Some(ControlKind::Submit) => return FormAction::Submit,
+        _ => false,
+    };
+    if changed { FormAction::Input } else { FormAction::None }
+}
+
+// ============================================================================
+// Submission
+// ============================================================================
+
+/// 部品が属するフォーム（最も近い祖先の `<form>`）の位置
+pub fn form_path(root: &Node, path: &[usize]) -> Option<Vec<usize>> {
+    (0..path.len())
+        .rev()
+        .map(|len| &path[..len])
+        .find(|ancestor| root.descendant(ancestor).and_then(Node::tag_name) == Some("form"))
+        .map(<[usize]>::to_vec)
+}
+
+/// フォームの送信データ（name と値の組、文書順）
+///
+/// 無効化された部品、name の無い部品、チェックされていないチェックボックスと
+/// ラジオボタン、押された送信ボタン（`submitter`）以外のボタンは含めない。
+pub fn form_data(root: &Node, form: &[usize], submitter: Option<&[usize]>) -> Vec<(String, String)> {
+    fn collect(
+        node: &Node,
+        path: &mut Vec<usize>,
+        submitter: Option<&[usize]>,
+        entries: &mut Vec<(String, String)>,
+    ) {
+        if let Some(kind) = ControlKind::of(node)
+            && let Some(name) = node.get_attribute("name").filter(|n| !n.is_empty())
+            && !is_disabled(node)
+        {
+            let included = match kind {
+                ControlKind::Checkbox | ControlKind::Radio => is_checked(node),
+                ControlKind::Submit => submitter == Some(path.as_slice()),
+                ControlKind::Button => false,
+                ControlKind::Select => selected_index(node).is_some(),
+                _ => true,
+            };
+            if included {
+                let value = match kind {
+                    // 改行は CRLF で送る
+                    ControlKind::TextArea => value(node).replace("\r\n", "\n").replace('\n', "\r\n"),
+                    _ => value(node),
+                };
+                entries.push((name.into(), value));
+            }
+        }
+        for (i, child) in node.children.iter().enumerate() {
+            path.push(i);
+            collect(child, path, submitter, entries);
+            path.pop();
+        }
+    }
+
+    let mut entries = Vec::new();
+    if let Some(form_node) = root.descendant(form) {
+        collect(form_node, &mut form.to_vec(), submitter, &mut entries);
+    }
+    entries
+}
+
+/// フォームを送信するリクエストを作る
+///
+/// `control` は送信ボタンか Enter を押したテキスト欄、`document` はページのURL。
+/// 部品がフォームの中に無いときや、ネットワーク上のページから `file://` へ送ろうとしたときは None。
+pub fn submission(root: &Node, control: &[usize], document: &Url) -> Option<Request> {
+    let form_path = form_path(root, control)?;
+    let form = root.descendant(&form_path)?;
+    let submitter = root
+        .descendant(control)
+        .filter(|node| ControlKind::of(node) == Some(ControlKind::Submit));
+    // 送信ボタンの formaction / formmethod はフォームの指定より優先する
+    let attribute = |name: &str, form_name: &str| {
+        submitter
+            .and_then(|s| s.get_attribute(name))
+            .or_else(|| form.get_attribute(form_name))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let action = match attribute("formaction", "action") {
+        Some(action) => loader::base_url(root, document).join(action).ok()?,
+        None => document.clone(),
+    };
+    if action.scheme == Scheme::File && document.scheme != Scheme::File {
+        return None;
+    }
+
+    let entries = form_data(root, &form_path, submitter.map(|_| control));
+    let query = urlencode(&entries);
+    if attribute("formmethod", "method").is_some_and(|m| m.eq_ignore_ascii_case("post")) {
+        Some(
+            Request::new("POST", action)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(query.into_bytes()),
+        )
+    } else {
+        Some(Request::get(action.join(&format!("?{}", query)).ok()?))
+    }
+}
+
+/// application/x-www-form-urlencoded でエンコードする
+pub fn urlencode(entries: &[(String, String)]) -> String {
+    fn encode(text: &str, out: &mut String) {
+        for byte in text.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => out.push(byte as char),
+                b' ' => out.push('+'),
+                _ => {
+                    let _ = write!(out, "%{:02X}", byte);
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (i, (name, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push('&');
+        }
+        encode(name, &mut out);
+        out.push('=');
+        encode(value, &mut out);
+    }
+    out
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::html::HtmlParser;
+    use alloc::string::ToString;
+
+    /// id で要素の位置を探す
+    fn path_of(root: &Node, id: &str) -> Vec<usize> {
+        fn find(node: &Node, id: &str, path: &mut Vec<usize>) -> bool {
+            if node.id() == Some(id) {
+                return true;
+            }
+            for (i, child) in node.children.iter().enumerate() {
+                path.push(i);
+                if find(child, id, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+        let mut path = Vec::new();
+        assert!(find(root, id, &mut path), "no element #{}", id);
+        path
+    }
+
+    #[test]
+    fn test_edit_text() {
+        let mut text = String::from("ab");
+        let mut caret = 1;
+        assert!(edit_text(&mut text, &mut caret, EditKey::Char('é'), false));
+        assert_eq!((text.as_str(), caret), ("aéb", 2));
+        assert!(!edit_text(&mut text, &mut caret, EditKey::Enter, false));
+        assert!(edit_text(&mut text, &mut caret, EditKey::Backspace, false));
+        assert!(!edit_text(&mut text, &mut caret, EditKey::Home, false));
+        assert!(edit_text(&mut text, &mut caret, EditKey::Delete, false));
+        assert_eq!((text.as_str(), caret), ("b", 0));
+
+        // 複数行: 上下の行の同じ桁へ移る
+        let mut text = String::from("one\nx\nthree");
+        let mut caret = 2;
+        edit_text(&mut text, &mut caret, EditKey::Down, true);
+        assert_eq!(caret, 5);
+        edit_text(&mut text, &mut caret, EditKey::Down, true);
+        assert_eq!(caret, 7);
+        edit_text(&mut text, &mut caret, EditKey::End, true);
+        assert!(edit_text(&mut text, &mut caret, EditKey::Enter, true));
+        assert_eq!(text, "one\nx\nthree\n");
+    }
+
+    #[test]
+    fn test_controls() {
+        let mut dom = HtmlParser::parse(concat!(
+            r#"<form><input id=a type=radio name=r checked><input id=b type=radio name=r>"#,
+            r#"<input id=c type=checkbox><input id=t maxlength=2 value=x>"#,
+            r#"<select id=s><option>One<option value=2 selected>Two<option>Three</select></form>"#,
+            r#"<input id=z type=radio name=r checked>"#,
+        ));
+        let [a, b, c, t, s, z] = ["a", "b", "c", "t", "s", "z"].map(|id| path_of(&dom, id));
+
+        // 同じフォームのラジオボタンだけチェックが外れる
+        assert_eq!(activate(&mut dom, &b), FormAction::Input);
+        assert_eq!(activate(&mut dom, &b), FormAction::None);
+        let checked = |dom: &Node, path: &[usize]| is_checked(dom.descendant(path).unwrap());
+        assert!(!checked(&dom, &a) && checked(&dom, &b) && checked(&dom, &z));
+        assert_eq!(edit(&mut dom, &c, &mut 0, EditKey::Char(' ')), FormAction::Input);
+        assert!(checked(&dom, &c));
+
+        // maxlength を超える入力は受け付けない
+        let mut caret = 1;
+        assert_eq!(edit(&mut dom, &t, &mut caret, EditKey::Char('y')), FormAction::Input);
+        assert_eq!(edit(&mut dom, &t, &mut caret, EditKey::Char('z')), FormAction::None);
+        assert_eq!((value(dom.descendant(&t).unwrap()).as_str(), caret), ("xy", 2));
+        assert_eq!(edit(&mut dom, &t, &mut caret, EditKey::Enter), FormAction::Submit);
+
+        let select = |dom: &Node| (value(dom.descendant(&s).unwrap()), display_text(dom.descendant(&s).unwrap()));
+        assert_eq!(select(&dom), ("2".into(), "Two".into()));
+        assert_eq!(edit(&mut dom, &s, &mut 0, EditKey::Down), FormAction::Input);
+        assert_eq!(select(&dom), ("Three".into(), "Three".into()));
+        assert_eq!(activate(&mut dom, &s), FormAction::Input);
+        assert_eq!(select(&dom), ("One".into(), "One".into()));
+    }
+
+    #[test]
+    fn test_submission() {
+        let dom = HtmlParser::parse(concat!(
+            r#"<form action="search" method=get><input name=q value="a b&c"><input name=off disabled value=1>"#,
+            r#"<input type=checkbox name=c value=yes checked><input type=checkbox name=d>"#,
+            r#"<textarea name=t>1
+2</textarea><button id=go name=go value=1>Go</button><input type=submit name=other></form>"#,
+            r#"<form method=post><input id=p name=p value="é"><button id=send formaction="/post">Send</button></form>"#,
+        ));
+        let page = Url::parse("http://host/dir/page.html?old").unwrap();
+
+        let get = submission(&dom, &path_of(&dom, "go"), &page).unwrap();
+        assert_eq!(get.method, "GET");
+        assert_eq!(get.url.to_string(), "http://host/dir/search?q=a+b%26c&c=yes&t=1%0D%0A2&go=1");
+
+        // テキスト欄で Enter を押したときは送信ボタンの値を含めない
+        let post = submission(&dom, &path_of(&dom, "p"), &page).unwrap();
+        assert_eq!((post.method.as_str(), post.url.to_string()), ("POST", page.to_string()));
+        assert_eq!(post.body, b"p=%C3%A9");
+        assert_eq!(submission(&dom, &path_of(&dom, "send"), &page).unwrap().url.to_string(), "http://host/post");
+
+        // フォームの外の部品は送信しない
+        let dom = HtmlParser::parse("<input id=x name=x>");
+        assert!(submission(&dom, &path_of(&dom, "x"), &page).is_none());
+    }
+}
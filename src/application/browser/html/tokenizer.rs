@@ -0,0 +1,1150 @@
+// ============================================================================
+// src/application/browser/html/tokenizer.rs - HTML Tokenizer
+// ============================================================================
+//!
+//! # HTMLトークナイザー
+//!
+//! WHATWG HTML「Tokenization」節のステートマシン。
+//! 入力の CR / CRLF は LF に正規化してから読む。
+//!
+//! 文字参照は状態として持たず、`&` を読んだ状態から [`Tokenizer::consume_character_reference`]
+//! を呼んでその場で展開する（出力は仕様の状態遷移と同じ）。
+//! RCDATA / RAWTEXT / script data / PLAINTEXT への切り替えはツリー構築側が行う。
+
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::super::dom::AttrMap;
+use super::entities;
+
+/// 置換文字（U+FFFD）
+const REPLACEMENT: char = '\u{FFFD}';
+
+// ============================================================================
+// Tokens
+// ============================================================================
+
+/// トークン
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Doctype(Doctype),
+    StartTag(Tag),
+    EndTag(Tag),
+    Comment(String),
+    Character(char),
+    Eof,
+}
+
+/// DOCTYPE トークン
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Doctype {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+/// 開始タグ・終了タグ
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct Tag {
+    /// タグ名（ASCII 小文字）
+    pub name: String,
+    /// 属性（重複した名前は最初のものだけ残す）
+    pub attributes: AttrMap,
+    pub self_closing: bool,
+}
+
+impl Tag {
+    /// 属性のない開始タグ（ツリー構築が暗黙に挿入する要素用）
+    pub fn named(name: &str) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+}
+
+// ============================================================================
+// Tokenizer State
+// ============================================================================
+
+/// トークナイザーの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum State {
+    Data,
+    Rcdata,
+    Rawtext,
+    ScriptData,
+    Plaintext,
+    TagOpen,
+    EndTagOpen,
+    TagName,
+    RcdataLessThanSign,
+    RcdataEndTagOpen,
+    RcdataEndTagName,
+    RawtextLessThanSign,
+    RawtextEndTagOpen,
+    RawtextEndTagName,
+    ScriptDataLessThanSign,
+    ScriptDataEndTagOpen,
+    ScriptDataEndTagName,
+    ScriptDataEscapeStart,
+    ScriptDataEscapeStartDash,
+    ScriptDataEscaped,
+    ScriptDataEscapedDash,
+    ScriptDataEscapedDashDash,
+    ScriptDataEscapedLessThanSign,
+    ScriptDataEscapedEndTagOpen,
+    ScriptDataEscapedEndTagName,
+    ScriptDataDoubleEscapeStart,
+    ScriptDataDoubleEscaped,
+    ScriptDataDoubleEscapedDash,
+    ScriptDataDoubleEscapedDashDash,
+    ScriptDataDoubleEscapedLessThanSign,
+    ScriptDataDoubleEscapeEnd,
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueDoubleQuoted,
+    AttributeValueSingleQuoted,
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentLessThanSign,
+    CommentLessThanSignBang,
+    CommentLessThanSignBangDash,
+    CommentLessThanSignBangDashDash,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    AfterDoctypePublicKeyword,
+    BeforeDoctypePublicIdentifier,
+    DoctypePublicIdentifierDoubleQuoted,
+    DoctypePublicIdentifierSingleQuoted,
+    AfterDoctypePublicIdentifier,
+    BetweenDoctypePublicAndSystemIdentifiers,
+    AfterDoctypeSystemKeyword,
+    BeforeDoctypeSystemIdentifier,
+    DoctypeSystemIdentifierDoubleQuoted,
+    DoctypeSystemIdentifierSingleQuoted,
+    AfterDoctypeSystemIdentifier,
+    BogusDoctype,
+    CdataSection,
+    CdataSectionBracket,
+    CdataSectionEnd,
+}
+
+// ============================================================================
+// Tokenizer
+// ============================================================================
+
+/// HTMLトークナイザー
+pub(super) struct Tokenizer {
+    /// 入力（改行は LF に正規化済み）
+    input: Vec<char>,
+    /// 次に読む位置
+    pos: usize,
+    /// 現在の状態
+    state: State,
+    /// 出力待ちのトークン
+    tokens: VecDeque<Token>,
+    /// 組み立て中のタグ
+    tag: Tag,
+    /// 組み立て中のタグが終了タグか
+    end_tag: bool,
+    /// 組み立て中の属性（名前, 値）
+    attribute: Option<(String, String)>,
+    /// 組み立て中のコメント
+    comment: String,
+    /// 組み立て中の DOCTYPE
+    doctype: Doctype,
+    /// 一時バッファ
+    buffer: String,
+    /// 最後に出力した開始タグの名前（「適切な終了タグ」の判定用）
+    last_start_tag: Option<String>,
+    /// CDATA セクションを認めるか（調整済みの現在ノードが HTML 名前空間でないとき）
+    pub allow_cdata: bool,
+}
+
+impl Tokenizer {
+    /// 新しいトークナイザーを作成
+    pub fn new(input: &str) -> Self {
+        Self {
+            input: input.replace("\r\n", "\n").replace('\r', "\n").chars().collect(),
+            pos: 0,
+            state: State::Data,
+            tokens: VecDeque::new(),
+            tag: Tag::default(),
+            end_tag: false,
+            attribute: None,
+            comment: String::new(),
+            doctype: Doctype::default(),
+            buffer: String::new(),
+            last_start_tag: None,
+            allow_cdata: false,
+        }
+    }
+
+    /// 状態を切り替える（ツリー構築から RCDATA などへ移すとき）
+    pub fn set_state(&mut self, state: State) {
+        self.state = state;
+    }
+
+    /// 次のトークンを取得（入力の終わりでは Eof を返し続ける）
+    pub fn next_token(&mut self) -> Token {
+        loop {
+            if let Some(token) = self.tokens.pop_front() {
+                return token;
+            }
+            self.step();
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Input
+    // ------------------------------------------------------------------------
+
+    /// 1 文字読む（EOF でも位置は進めるので reconsume で戻せる）
+    fn consume(&mut self) -> Option<char> {
+        let c = self.input.get(self.pos).copied();
+        self.pos += 1;
+        c
+    }
+
+    /// 直前の文字を指定の状態で読み直す
+    fn reconsume(&mut self, state: State) {
+        self.pos -= 1;
+        self.state = state;
+    }
+
+    /// 次の文字を覗く
+    fn peek(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    /// 現在位置から `s` が続くなら読み進める
+    fn consume_if(&mut self, s: &str, ignore_case: bool) -> bool {
+        let len = s.chars().count();
+        let Some(ahead) = self.input.get(self.pos..self.pos + len) else {
+            return false;
+        };
+        let found = ahead.iter().zip(s.chars()).all(|(&a, b)| {
+            if ignore_case { a.eq_ignore_ascii_case(&b) } else { a == b }
+        });
+        if found {
+            self.pos += len;
+        }
+        found
+    }
+
+    // ------------------------------------------------------------------------
+    // Output
+    // ------------------------------------------------------------------------
+
+    fn emit(&mut self, c: char) {
+        self.tokens.push_back(Token::Character(c));
+    }
+
+    fn emit_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.emit(c);
+        }
+    }
+
+    fn emit_eof(&mut self) {
+        self.tokens.push_back(Token::Eof);
+    }
+
+    /// 一時バッファの内容を文字として出力する
+    fn emit_buffer(&mut self) {
+        let buffer = mem::take(&mut self.buffer);
+        self.emit_str(&buffer);
+    }
+
+    fn new_tag(&mut self, end_tag: bool) {
+        self.tag = Tag::default();
+        self.end_tag = end_tag;
+        self.attribute = None;
+    }
+
+    fn new_attribute(&mut self, name: &str) {
+        self.commit_attribute();
+        self.attribute = Some((String::from(name), String::new()));
+    }
+
+    /// 組み立て中の属性をタグに加える（同名の属性が既にあれば捨てる）
+    fn commit_attribute(&mut self) {
+        if let Some((name, value)) = self.attribute.take() {
+            self.tag.attributes.entry(name).or_insert(value);
+        }
+    }
+
+    fn push_attribute_name(&mut self, c: char) {
+        if let Some((name, _)) = &mut self.attribute {
+            name.push(c);
+        }
+    }
+
+    fn push_attribute_value(&mut self, s: &str) {
+        if let Some((_, value)) = &mut self.attribute {
+            value.push_str(s);
+        }
+    }
+
+    fn emit_tag(&mut self) {
+        self.commit_attribute();
+        let tag = mem::take(&mut self.tag);
+        if self.end_tag {
+            self.tokens.push_back(Token::EndTag(tag));
+        } else {
+            self.last_start_tag = Some(tag.name.clone());
+            self.tokens.push_back(Token::StartTag(tag));
+        }
+    }
+
+    fn emit_comment(&mut self) {
+        let comment = mem::take(&mut self.comment);
+        self.tokens.push_back(Token::Comment(comment));
+    }
+
+    fn emit_doctype(&mut self, force_quirks: bool) {
+        let mut doctype = mem::take(&mut self.doctype);
+        doctype.force_quirks |= force_quirks;
+        self.tokens.push_back(Token::Doctype(doctype));
+    }
+
+    /// 組み立て中の終了タグが最後の開始タグに対応するか
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.last_start_tag.as_deref() == Some(self.tag.name.as_str())
+    }
+
+    // ------------------------------------------------------------------------
+    // State Machine
+    // ------------------------------------------------------------------------
+
+    /// 状態機械を 1 文字分進める
+    fn step(&mut self) {
+        let c = self.consume();
+        match self.state {
+            State::Data => match c {
+                Some('&') => {
+                    let text = self.consume_character_reference(false);
+                    self.emit_str(&text);
+                }
+                Some('<') => self.state = State::TagOpen,
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::Rcdata => match c {
+                Some('&') => {
+                    let text = self.consume_character_reference(false);
+                    self.emit_str(&text);
+                }
+                Some('<') => self.state = State::RcdataLessThanSign,
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::Rawtext => match c {
+                Some('<') => self.state = State::RawtextLessThanSign,
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptData => match c {
+                Some('<') => self.state = State::ScriptDataLessThanSign,
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::Plaintext => match c {
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::TagOpen => match c {
+                Some('!') => self.state = State::MarkupDeclarationOpen,
+                Some('/') => self.state = State::EndTagOpen,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.new_tag(false);
+                    self.reconsume(State::TagName);
+                }
+                Some('?') => {
+                    self.comment.clear();
+                    self.reconsume(State::BogusComment);
+                }
+                None => {
+                    self.emit('<');
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.emit('<');
+                    self.reconsume(State::Data);
+                }
+            },
+            State::EndTagOpen => match c {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.new_tag(true);
+                    self.reconsume(State::TagName);
+                }
+                Some('>') => self.state = State::Data,
+                None => {
+                    self.emit_str("</");
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.clear();
+                    self.reconsume(State::BogusComment);
+                }
+            },
+            State::TagName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.tag.name.push(REPLACEMENT),
+                Some(c) => self.tag.name.push(c.to_ascii_lowercase()),
+                None => self.emit_eof(),
+            },
+
+            // RCDATA / RAWTEXT / script data の終了タグ
+            State::RcdataLessThanSign => self.text_less_than_sign(c, State::RcdataEndTagOpen, State::Rcdata),
+            State::RcdataEndTagOpen => self.text_end_tag_open(c, State::RcdataEndTagName, State::Rcdata),
+            State::RcdataEndTagName => self.text_end_tag_name(c, State::Rcdata),
+            State::RawtextLessThanSign => self.text_less_than_sign(c, State::RawtextEndTagOpen, State::Rawtext),
+            State::RawtextEndTagOpen => self.text_end_tag_open(c, State::RawtextEndTagName, State::Rawtext),
+            State::RawtextEndTagName => self.text_end_tag_name(c, State::Rawtext),
+            State::ScriptDataLessThanSign => match c {
+                Some('!') => {
+                    self.state = State::ScriptDataEscapeStart;
+                    self.emit_str("<!");
+                }
+                _ => self.text_less_than_sign(c, State::ScriptDataEndTagOpen, State::ScriptData),
+            },
+            State::ScriptDataEndTagOpen => {
+                self.text_end_tag_open(c, State::ScriptDataEndTagName, State::ScriptData)
+            }
+            State::ScriptDataEndTagName => self.text_end_tag_name(c, State::ScriptData),
+
+            // <!-- ... --> で囲まれた script data
+            State::ScriptDataEscapeStart => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapeStartDash;
+                    self.emit('-');
+                }
+                _ => self.reconsume(State::ScriptData),
+            },
+            State::ScriptDataEscapeStartDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit('-');
+                }
+                _ => self.reconsume(State::ScriptData),
+            },
+            State::ScriptDataEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDash;
+                    self.emit('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptDataEscapedDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataEscapedDashDash;
+                    self.emit('-');
+                }
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit(if c == '\0' { REPLACEMENT } else { c });
+                }
+                None => self.emit_eof(),
+            },
+            State::ScriptDataEscapedDashDash => match c {
+                Some('-') => self.emit('-'),
+                Some('<') => self.state = State::ScriptDataEscapedLessThanSign,
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit('>');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataEscaped;
+                    self.emit(if c == '\0' { REPLACEMENT } else { c });
+                }
+                None => self.emit_eof(),
+            },
+            State::ScriptDataEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.buffer.clear();
+                    self.state = State::ScriptDataEscapedEndTagOpen;
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.buffer.clear();
+                    self.emit('<');
+                    self.reconsume(State::ScriptDataDoubleEscapeStart);
+                }
+                _ => {
+                    self.emit('<');
+                    self.reconsume(State::ScriptDataEscaped);
+                }
+            },
+            State::ScriptDataEscapedEndTagOpen => {
+                self.text_end_tag_open(c, State::ScriptDataEscapedEndTagName, State::ScriptDataEscaped)
+            }
+            State::ScriptDataEscapedEndTagName => self.text_end_tag_name(c, State::ScriptDataEscaped),
+            State::ScriptDataDoubleEscapeStart => {
+                self.double_escape_boundary(c, State::ScriptDataDoubleEscaped, State::ScriptDataEscaped)
+            }
+            State::ScriptDataDoubleEscaped => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDash;
+                    self.emit('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit('<');
+                }
+                Some('\0') => self.emit(REPLACEMENT),
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::ScriptDataDoubleEscapedDash => match c {
+                Some('-') => {
+                    self.state = State::ScriptDataDoubleEscapedDashDash;
+                    self.emit('-');
+                }
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit('<');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit(if c == '\0' { REPLACEMENT } else { c });
+                }
+                None => self.emit_eof(),
+            },
+            State::ScriptDataDoubleEscapedDashDash => match c {
+                Some('-') => self.emit('-'),
+                Some('<') => {
+                    self.state = State::ScriptDataDoubleEscapedLessThanSign;
+                    self.emit('<');
+                }
+                Some('>') => {
+                    self.state = State::ScriptData;
+                    self.emit('>');
+                }
+                Some(c) => {
+                    self.state = State::ScriptDataDoubleEscaped;
+                    self.emit(if c == '\0' { REPLACEMENT } else { c });
+                }
+                None => self.emit_eof(),
+            },
+            State::ScriptDataDoubleEscapedLessThanSign => match c {
+                Some('/') => {
+                    self.buffer.clear();
+                    self.state = State::ScriptDataDoubleEscapeEnd;
+                    self.emit('/');
+                }
+                _ => self.reconsume(State::ScriptDataDoubleEscaped),
+            },
+            State::ScriptDataDoubleEscapeEnd => {
+                self.double_escape_boundary(c, State::ScriptDataEscaped, State::ScriptDataDoubleEscaped)
+            }
+
+            // 属性
+            State::BeforeAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') | Some('>') | None => self.reconsume(State::AfterAttributeName),
+                Some('=') => {
+                    self.new_attribute("=");
+                    self.state = State::AttributeName;
+                }
+                Some(_) => {
+                    self.new_attribute("");
+                    self.reconsume(State::AttributeName);
+                }
+            },
+            State::AttributeName => match c {
+                Some(c) if is_whitespace(c) => self.reconsume(State::AfterAttributeName),
+                Some('/') | Some('>') | None => self.reconsume(State::AfterAttributeName),
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('\0') => self.push_attribute_name(REPLACEMENT),
+                Some(c) => self.push_attribute_name(c.to_ascii_lowercase()),
+            },
+            State::AfterAttributeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('=') => self.state = State::BeforeAttributeValue,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => {
+                    self.new_attribute("");
+                    self.reconsume(State::AttributeName);
+                }
+            },
+            State::BeforeAttributeValue => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('"') => self.state = State::AttributeValueDoubleQuoted,
+                Some('\'') => self.state = State::AttributeValueSingleQuoted,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                _ => self.reconsume(State::AttributeValueUnquoted),
+            },
+            State::AttributeValueDoubleQuoted => self.quoted_attribute_value(c, '"'),
+            State::AttributeValueSingleQuoted => self.quoted_attribute_value(c, '\''),
+            State::AttributeValueUnquoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('&') => {
+                    let text = self.consume_character_reference(true);
+                    self.push_attribute_value(&text);
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.push_attribute_value("\u{FFFD}"),
+                Some(c) => self.push_attribute_value(c.encode_utf8(&mut [0; 4])),
+                None => self.emit_eof(),
+            },
+            State::AfterAttributeValueQuoted => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeAttributeName,
+                Some('/') => self.state = State::SelfClosingStartTag,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => self.reconsume(State::BeforeAttributeName),
+            },
+            State::SelfClosingStartTag => match c {
+                Some('>') => {
+                    self.tag.self_closing = true;
+                    self.state = State::Data;
+                    self.emit_tag();
+                }
+                None => self.emit_eof(),
+                Some(_) => self.reconsume(State::BeforeAttributeName),
+            },
+
+            // コメント
+            State::BogusComment => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('\0') => self.comment.push(REPLACEMENT),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::MarkupDeclarationOpen => {
+                self.pos -= 1;
+                if self.consume_if("--", false) {
+                    self.comment.clear();
+                    self.state = State::CommentStart;
+                } else if self.consume_if("doctype", true) {
+                    self.state = State::Doctype;
+                } else if self.consume_if("[CDATA[", false) {
+                    if self.allow_cdata {
+                        self.state = State::CdataSection;
+                    } else {
+                        self.comment = String::from("[CDATA[");
+                        self.state = State::BogusComment;
+                    }
+                } else {
+                    self.comment.clear();
+                    self.state = State::BogusComment;
+                }
+            }
+            State::CommentStart => match c {
+                Some('-') => self.state = State::CommentStartDash,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentStartDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::Comment => match c {
+                Some('<') => {
+                    self.comment.push('<');
+                    self.state = State::CommentLessThanSign;
+                }
+                Some('-') => self.state = State::CommentEndDash,
+                Some('\0') => self.comment.push(REPLACEMENT),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            State::CommentLessThanSign => match c {
+                Some('!') => {
+                    self.comment.push('!');
+                    self.state = State::CommentLessThanSignBang;
+                }
+                Some('<') => self.comment.push('<'),
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentLessThanSignBang => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDash,
+                _ => self.reconsume(State::Comment),
+            },
+            State::CommentLessThanSignBangDash => match c {
+                Some('-') => self.state = State::CommentLessThanSignBangDashDash,
+                _ => self.reconsume(State::CommentEndDash),
+            },
+            State::CommentLessThanSignBangDashDash => self.reconsume(State::CommentEnd),
+            State::CommentEndDash => match c {
+                Some('-') => self.state = State::CommentEnd,
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::CommentEnd => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                Some('!') => self.state = State::CommentEndBang,
+                Some('-') => self.comment.push('-'),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push_str("--");
+                    self.reconsume(State::Comment);
+                }
+            },
+            State::CommentEndBang => match c {
+                Some('-') => {
+                    self.comment.push_str("--!");
+                    self.state = State::CommentEndDash;
+                }
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_comment();
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.comment.push_str("--!");
+                    self.reconsume(State::Comment);
+                }
+            },
+
+            // DOCTYPE
+            State::Doctype => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeDoctypeName,
+                None => {
+                    self.doctype = Doctype::default();
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+                Some(_) => self.reconsume(State::BeforeDoctypeName),
+            },
+            State::BeforeDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.doctype = Doctype::default();
+                    self.state = State::Data;
+                    self.emit_doctype(true);
+                }
+                None => {
+                    self.doctype = Doctype::default();
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+                Some(c) => {
+                    let c = if c == '\0' { REPLACEMENT } else { c.to_ascii_lowercase() };
+                    self.doctype = Doctype { name: Some(c.into()), ..Default::default() };
+                    self.state = State::DoctypeName;
+                }
+            },
+            State::DoctypeName => match c {
+                Some(c) if is_whitespace(c) => self.state = State::AfterDoctypeName,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype(false);
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+                Some(c) => {
+                    let c = if c == '\0' { REPLACEMENT } else { c.to_ascii_lowercase() };
+                    self.doctype.name.get_or_insert_with(String::new).push(c);
+                }
+            },
+            State::AfterDoctypeName => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype(false);
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+                Some(_) => {
+                    self.pos -= 1;
+                    if self.consume_if("public", true) {
+                        self.state = State::AfterDoctypePublicKeyword;
+                    } else if self.consume_if("system", true) {
+                        self.state = State::AfterDoctypeSystemKeyword;
+                    } else {
+                        self.doctype.force_quirks = true;
+                        self.pos += 1;
+                        self.reconsume(State::BogusDoctype);
+                    }
+                }
+            },
+            State::AfterDoctypePublicKeyword | State::BeforeDoctypePublicIdentifier => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeDoctypePublicIdentifier,
+                Some(quote @ ('"' | '\'')) => {
+                    self.doctype.public_id = Some(String::new());
+                    self.state = if quote == '"' {
+                        State::DoctypePublicIdentifierDoubleQuoted
+                    } else {
+                        State::DoctypePublicIdentifierSingleQuoted
+                    };
+                }
+                _ => self.missing_doctype_identifier(c),
+            },
+            State::DoctypePublicIdentifierDoubleQuoted => {
+                self.doctype_identifier(c, '"', true, State::AfterDoctypePublicIdentifier)
+            }
+            State::DoctypePublicIdentifierSingleQuoted => {
+                self.doctype_identifier(c, '\'', true, State::AfterDoctypePublicIdentifier)
+            }
+            State::AfterDoctypePublicIdentifier | State::BetweenDoctypePublicAndSystemIdentifiers => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BetweenDoctypePublicAndSystemIdentifiers,
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype(false);
+                }
+                Some(quote @ ('"' | '\'')) => self.start_system_identifier(quote),
+                _ => self.missing_doctype_identifier(c),
+            },
+            State::AfterDoctypeSystemKeyword | State::BeforeDoctypeSystemIdentifier => match c {
+                Some(c) if is_whitespace(c) => self.state = State::BeforeDoctypeSystemIdentifier,
+                Some(quote @ ('"' | '\'')) => self.start_system_identifier(quote),
+                _ => self.missing_doctype_identifier(c),
+            },
+            State::DoctypeSystemIdentifierDoubleQuoted => {
+                self.doctype_identifier(c, '"', false, State::AfterDoctypeSystemIdentifier)
+            }
+            State::DoctypeSystemIdentifierSingleQuoted => {
+                self.doctype_identifier(c, '\'', false, State::AfterDoctypeSystemIdentifier)
+            }
+            State::AfterDoctypeSystemIdentifier => match c {
+                Some(c) if is_whitespace(c) => {}
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype(false);
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+                Some(_) => self.reconsume(State::BogusDoctype),
+            },
+            State::BogusDoctype => match c {
+                Some('>') => {
+                    self.state = State::Data;
+                    self.emit_doctype(false);
+                }
+                None => {
+                    self.emit_doctype(false);
+                    self.emit_eof();
+                }
+                Some(_) => {}
+            },
+
+            // CDATA（外部コンテンツ内のみ）
+            State::CdataSection => match c {
+                Some(']') => self.state = State::CdataSectionBracket,
+                Some(c) => self.emit(c),
+                None => self.emit_eof(),
+            },
+            State::CdataSectionBracket => match c {
+                Some(']') => self.state = State::CdataSectionEnd,
+                _ => {
+                    self.emit(']');
+                    self.reconsume(State::CdataSection);
+                }
+            },
+            State::CdataSectionEnd => match c {
+                Some(']') => self.emit(']'),
+                Some('>') => self.state = State::Data,
+                _ => {
+                    self.emit_str("]]");
+                    self.reconsume(State::CdataSection);
+                }
+            },
+        }
+    }
+
+    /// RCDATA / RAWTEXT / script data 中の `<`
+    fn text_less_than_sign(&mut self, c: Option<char>, end_tag_open: State, text: State) {
+        if c == Some('/') {
+            self.buffer.clear();
+            self.state = end_tag_open;
+        } else {
+            self.emit('<');
+            self.reconsume(text);
+        }
+    }
+
+    /// RCDATA / RAWTEXT / script data 中の `</`
+    fn text_end_tag_open(&mut self, c: Option<char>, end_tag_name: State, text: State) {
+        if c.is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.new_tag(true);
+            self.reconsume(end_tag_name);
+        } else {
+            self.emit_str("</");
+            self.reconsume(text);
+        }
+    }
+
+    /// RCDATA / RAWTEXT / script data 中の終了タグ名（適切な終了タグでなければ文字に戻す）
+    fn text_end_tag_name(&mut self, c: Option<char>, text: State) {
+        let appropriate = self.is_appropriate_end_tag();
+        match c {
+            Some(c) if is_whitespace(c) && appropriate => self.state = State::BeforeAttributeName,
+            Some('/') if appropriate => self.state = State::SelfClosingStartTag,
+            Some('>') if appropriate => {
+                self.state = State::Data;
+                self.emit_tag();
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.tag.name.push(c.to_ascii_lowercase());
+                self.buffer.push(c);
+            }
+            _ => {
+                self.emit_str("</");
+                self.emit_buffer();
+                self.reconsume(text);
+            }
+        }
+    }
+
+    /// `<script>` の二重エスケープの開始・終了（一時バッファが "script" なら `matched` へ）
+    fn double_escape_boundary(&mut self, c: Option<char>, matched: State, otherwise: State) {
+        match c {
+            Some(c) if is_whitespace(c) || c == '/' || c == '>' => {
+                self.state = if self.buffer == "script" { matched } else { otherwise };
+                self.emit(c);
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                self.buffer.push(c.to_ascii_lowercase());
+                self.emit(c);
+            }
+            _ => self.reconsume(otherwise),
+        }
+    }
+
+    /// 引用符付きの属性値
+    fn quoted_attribute_value(&mut self, c: Option<char>, quote: char) {
+        match c {
+            Some(c) if c == quote => self.state = State::AfterAttributeValueQuoted,
+            Some('&') => {
+                let text = self.consume_character_reference(true);
+                self.push_attribute_value(&text);
+            }
+            Some('\0') => self.push_attribute_value("\u{FFFD}"),
+            Some(c) => self.push_attribute_value(c.encode_utf8(&mut [0; 4])),
+            None => self.emit_eof(),
+        }
+    }
+
+    fn start_system_identifier(&mut self, quote: char) {
+        self.doctype.system_id = Some(String::new());
+        self.state = if quote == '"' {
+            State::DoctypeSystemIdentifierDoubleQuoted
+        } else {
+            State::DoctypeSystemIdentifierSingleQuoted
+        };
+    }
+
+    /// 公開識別子・システム識別子の中身
+    fn doctype_identifier(&mut self, c: Option<char>, quote: char, public: bool, after: State) {
+        let id = if public { &mut self.doctype.public_id } else { &mut self.doctype.system_id };
+        match c {
+            Some(c) if c == quote => self.state = after,
+            Some('>') => {
+                self.state = State::Data;
+                self.emit_doctype(true);
+            }
+            Some(c) => id.get_or_insert_with(String::new).push(if c == '\0' { REPLACEMENT } else { c }),
+            None => {
+                self.emit_doctype(true);
+                self.emit_eof();
+            }
+        }
+    }
+
+    /// 識別子があるべき位置に引用符以外が来た
+    fn missing_doctype_identifier(&mut self, c: Option<char>) {
+        match c {
+            Some('>') => {
+                self.state = State::Data;
+                self.emit_doctype(true);
+            }
+            None => {
+                self.emit_doctype(true);
+                self.emit_eof();
+            }
+            Some(_) => {
+                self.doctype.force_quirks = true;
+                self.reconsume(State::BogusDoctype);
+            }
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Character References
+    // ------------------------------------------------------------------------
+
+    /// `&` の直後から文字参照を読み、置き換える文字列を返す
+    ///
+    /// 参照として解釈できなければ読んだ文字をそのまま返す（`&` だけなら何も読まない）。
+    fn consume_character_reference(&mut self, in_attribute: bool) -> String {
+        match self.peek() {
+            Some(c) if c.is_ascii_alphanumeric() => self.consume_named_reference(in_attribute),
+            Some('#') => {
+                self.pos += 1;
+                self.consume_numeric_reference()
+            }
+            _ => String::from("&"),
+        }
+    }
+
+    /// 名前付き文字参照（表にある最長の名前に一致させる）
+    fn consume_named_reference(&mut self, in_attribute: bool) -> String {
+        let start = self.pos;
+        let mut end = start;
+        while end - start < entities::LONGEST_NAME
+            && self.input.get(end).is_some_and(|c| c.is_ascii_alphanumeric())
+        {
+            end += 1;
+        }
+        if end - start < entities::LONGEST_NAME && self.input.get(end) == Some(&';') {
+            end += 1;
+        }
+        let candidate: String = self.input[start..end].iter().collect();
+
+        for len in (1..=candidate.len()).rev() {
+            let name = &candidate[..len];
+            let Some(value) = entities::lookup(name) else {
+                continue;
+            };
+            self.pos = start + len;
+            // 属性値中の ';' なしの参照は、後ろに '=' や英数字が続けば歴史的理由で展開しない
+            let next = self.peek();
+            if in_attribute && !name.ends_with(';') && next.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric()) {
+                return alloc::format!("&{}", name);
+            }
+            return String::from(value);
+        }
+        // 一致しなければ '&' だけを返し、続く英数字は通常の文字として読ませる
+        String::from("&")
+    }
+
+    /// 数値文字参照（`&#` の後から）
+    fn consume_numeric_reference(&mut self) -> String {
+        let start = self.pos;
+        let radix = if matches!(self.peek(), Some('x') | Some('X')) {
+            self.pos += 1;
+            16
+        } else {
+            10
+        };
+
+        let digits = self.pos;
+        let mut value: u32 = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(radix)) {
+            value = value.saturating_mul(radix).saturating_add(digit);
+            self.pos += 1;
+        }
+        if self.pos == digits {
+            // 数字がなければ "&#" までを文字として返し、'x' 以降は読み直す
+            self.pos = start;
+            return String::from("&#");
+        }
+        if self.peek() == Some(';') {
+            self.pos += 1;
+        }
+        String::from(numeric_reference_char(value))
+    }
+}
+
+/// 数値文字参照の値を文字にする（不正な値は U+FFFD、C1 制御文字は Windows-1252 として読む）
+fn numeric_reference_char(value: u32) -> char {
+    const C1: [(u32, char); 27] = [
+        (0x80, '\u{20AC}'), (0x82, '\u{201A}'), (0x83, '\u{0192}'), (0x84, '\u{201E}'),
+        (0x85, '\u{2026}'), (0x86, '\u{2020}'), (0x87, '\u{2021}'), (0x88, '\u{02C6}'),
+        (0x89, '\u{2030}'), (0x8A, '\u{0160}'), (0x8B, '\u{2039}'), (0x8C, '\u{0152}'),
+        (0x8E, '\u{017D}'), (0x91, '\u{2018}'), (0x92, '\u{2019}'), (0x93, '\u{201C}'),
+        (0x94, '\u{201D}'), (0x95, '\u{2022}'), (0x96, '\u{2013}'), (0x97, '\u{2014}'),
+        (0x98, '\u{02DC}'), (0x99, '\u{2122}'), (0x9A, '\u{0161}'), (0x9B, '\u{203A}'),
+        (0x9C, '\u{0153}'), (0x9E, '\u{017E}'), (0x9F, '\u{0178}'),
+    ];
+    if value == 0 {
+        return REPLACEMENT;
+    }
+    if let Some(&(_, c)) = C1.iter().find(|(code, _)| *code == value) {
+        return c;
+    }
+    char::from_u32(value).unwrap_or(REPLACEMENT)
+}
+
+/// トークナイザーが空白として扱う文字
+pub(super) fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
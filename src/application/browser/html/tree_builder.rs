@@ -0,0 +1,1967 @@
+// ============================================================================
+// src/application/browser/html/tree_builder.rs - HTML Tree Construction
+// ============================================================================
+//!
+//! # ツリー構築
+//!
+//! WHATWG HTML「Tree construction」節の挿入モード。
+//! トークナイザーからトークンを受け取り、開いている要素のスタックとアクティブな書式要素の
+//! リストを使って DOM を組み立てる。
+//!
+//! 構築中は親ポインタを持つアリーナ（[`Entry`] の配列）でノードを管理し、
+//! 養子縁組（adoption agency）アルゴリズムや里親（foster parenting）による付け替えを行う。
+//! 最後に [`dom::Node`](super::super::dom::Node) のツリーへ変換する。
+//!
+//! 簡略化している点：
+//! - スクリプトは無効として扱う（`<noscript>` の中身は通常の要素として解析する）
+//! - `<template>` の内容は別の DocumentFragment ではなく template 要素の子として持つ
+//! - SVG / MathML の要素名・属性名の大文字小文字の補正は行わない
+//! - DOCTYPE ノードは DOM に残さない（互換モードの判定にだけ使う）
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::super::dom::{AttrMap, ElementData, Node, NodeType};
+use super::tokenizer::{is_whitespace, Doctype, State, Tag, Token, Tokenizer};
+
+/// アリーナ中の Document ノードの位置
+const DOCUMENT: usize = 0;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 挿入モード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    InHeadNoscript,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+/// 要素の名前空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+}
+
+/// 要素のスコープの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+/// 構築中のノード
+struct Entry {
+    node_type: NodeType,
+    namespace: Namespace,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// アクティブな書式要素のリストの項目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Formatting {
+    Marker,
+    Element(usize),
+}
+
+/// 養子縁組アルゴリズムのブックマーク
+enum Bookmark {
+    /// 書式要素の位置をそのまま置き換える
+    Replace,
+    /// 指定の要素の直後に入れる
+    After(usize),
+}
+
+// ============================================================================
+// Tree Builder
+// ============================================================================
+
+/// ツリー構築器
+pub(super) struct TreeBuilder {
+    tokenizer: Tokenizer,
+    /// ノードのアリーナ（0 番は Document）
+    nodes: Vec<Entry>,
+    mode: InsertionMode,
+    /// Text / InTableText から戻る先のモード
+    original_mode: InsertionMode,
+    /// テンプレート挿入モードのスタック
+    template_modes: Vec<InsertionMode>,
+    /// 開いている要素のスタック
+    open: Vec<usize>,
+    /// アクティブな書式要素のリスト
+    formatting: Vec<Formatting>,
+    head: Option<usize>,
+    form: Option<usize>,
+    frameset_ok: bool,
+    foster_parenting: bool,
+    /// 互換モード（quirks mode）か
+    quirks: bool,
+    /// 表の中で保留している文字
+    pending_table_text: Vec<char>,
+    /// 次の改行を無視する（`<pre>`、`<listing>`、`<textarea>` の直後）
+    skip_newline: bool,
+}
+
+impl TreeBuilder {
+    /// 新しいツリー構築器を作成
+    pub fn new(input: &str) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(input),
+            nodes: alloc::vec![Entry {
+                node_type: NodeType::Document,
+                namespace: Namespace::Html,
+                parent: None,
+                children: Vec::new(),
+            }],
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            template_modes: Vec::new(),
+            open: Vec::new(),
+            formatting: Vec::new(),
+            head: None,
+            form: None,
+            frameset_ok: true,
+            foster_parenting: false,
+            quirks: false,
+            pending_table_text: Vec::new(),
+            skip_newline: false,
+        }
+    }
+
+    /// 入力をすべて処理して Document ノードを返す
+    pub fn run(mut self) -> Node {
+        loop {
+            self.tokenizer.allow_cdata = self
+                .open
+                .last()
+                .is_some_and(|&id| self.nodes[id].namespace != Namespace::Html);
+            let token = self.tokenizer.next_token();
+            if mem::take(&mut self.skip_newline) && token == Token::Character('\n') {
+                continue;
+            }
+            let eof = token == Token::Eof;
+            self.process(token);
+            if eof {
+                break;
+            }
+        }
+        self.build(DOCUMENT)
+    }
+
+    /// アリーナのノードを DOM ノードに変換する
+    fn build(&mut self, id: usize) -> Node {
+        let children = mem::take(&mut self.nodes[id].children)
+            .into_iter()
+            .map(|child| self.build(child))
+            .collect();
+        Node {
+            node_type: mem::replace(&mut self.nodes[id].node_type, NodeType::Document),
+            children,
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Arena
+    // ------------------------------------------------------------------------
+
+    fn create(&mut self, node_type: NodeType, namespace: Namespace) -> usize {
+        self.nodes.push(Entry { node_type, namespace, parent: None, children: Vec::new() });
+        self.nodes.len() - 1
+    }
+
+    fn create_element(&mut self, tag: &Tag, namespace: Namespace) -> usize {
+        let data = ElementData { tag_name: tag.name.clone(), attributes: tag.attributes.clone() };
+        self.create(NodeType::Element(data), namespace)
+    }
+
+    /// 同じ名前・属性の要素を新しく作る
+    fn clone_element(&mut self, id: usize) -> usize {
+        let node_type = self.nodes[id].node_type.clone();
+        self.create(node_type, self.nodes[id].namespace)
+    }
+
+    /// ノードを親から外す
+    fn detach(&mut self, id: usize) {
+        if let Some(parent) = self.nodes[id].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != id);
+        }
+    }
+
+    /// `parent` の最後の子として付ける
+    fn append(&mut self, parent: usize, child: usize) {
+        self.insert_at((parent, None), child);
+    }
+
+    /// 挿入位置 (親, この子の直前) にノードを付ける
+    fn insert_at(&mut self, (parent, before): (usize, Option<usize>), child: usize) {
+        self.detach(child);
+        self.nodes[child].parent = Some(parent);
+        let children = &mut self.nodes[parent].children;
+        match before.and_then(|before| children.iter().position(|&c| c == before)) {
+            Some(index) => children.insert(index, child),
+            None => children.push(child),
+        }
+    }
+
+    /// 要素のタグ名（要素でなければ空文字列）
+    fn name(&self, id: usize) -> &str {
+        match &self.nodes[id].node_type {
+            NodeType::Element(data) => &data.tag_name,
+            _ => "",
+        }
+    }
+
+    /// 指定の名前の HTML 要素か
+    fn is_html(&self, id: usize, name: &str) -> bool {
+        self.nodes[id].namespace == Namespace::Html && self.name(id) == name
+    }
+
+    /// いずれかの名前の HTML 要素か
+    fn is_html_one_of(&self, id: usize, names: &[&str]) -> bool {
+        self.nodes[id].namespace == Namespace::Html && names.contains(&self.name(id))
+    }
+
+    /// 特殊（special）カテゴリの要素か
+    fn is_special(&self, id: usize) -> bool {
+        let name = self.name(id);
+        match self.nodes[id].namespace {
+            Namespace::Html => matches!(
+                name,
+                "address" | "applet" | "area" | "article" | "aside" | "base" | "basefont"
+                    | "bgsound" | "blockquote" | "body" | "br" | "button" | "caption" | "center"
+                    | "col" | "colgroup" | "dd" | "details" | "dir" | "div" | "dl" | "dt"
+                    | "embed" | "fieldset" | "figcaption" | "figure" | "footer" | "form"
+                    | "frame" | "frameset" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head"
+                    | "header" | "hgroup" | "hr" | "html" | "iframe" | "img" | "input"
+                    | "keygen" | "li" | "link" | "listing" | "main" | "marquee" | "menu"
+                    | "meta" | "nav" | "noembed" | "noframes" | "noscript" | "object" | "ol"
+                    | "p" | "param" | "plaintext" | "pre" | "script" | "search" | "section"
+                    | "select" | "source" | "style" | "summary" | "table" | "tbody" | "td"
+                    | "template" | "textarea" | "tfoot" | "th" | "thead" | "title" | "tr"
+                    | "track" | "ul" | "wbr" | "xmp"
+            ),
+            Namespace::MathMl => is_mathml_text_integration_point(name) || name == "annotation-xml",
+            Namespace::Svg => is_html_integration_point(name),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Stack of Open Elements
+    // ------------------------------------------------------------------------
+
+    /// 現在のノード
+    fn current(&self) -> usize {
+        self.open.last().copied().unwrap_or(DOCUMENT)
+    }
+
+    fn current_is(&self, names: &[&str]) -> bool {
+        self.is_html_one_of(self.current(), names)
+    }
+
+    fn has_open(&self, name: &str) -> bool {
+        self.open.iter().any(|&id| self.is_html(id, name))
+    }
+
+    /// スコープの境界になる要素か
+    fn is_scope_boundary(&self, id: usize, scope: Scope) -> bool {
+        let name = self.name(id);
+        match (self.nodes[id].namespace, scope) {
+            (Namespace::Html, Scope::Select) => !matches!(name, "optgroup" | "option"),
+            (Namespace::Html, Scope::Table) => matches!(name, "html" | "table" | "template"),
+            (Namespace::Html, _) => {
+                matches!(
+                    name,
+                    "applet" | "caption" | "html" | "table" | "td" | "th" | "marquee" | "object" | "template"
+                ) || (scope == Scope::ListItem && matches!(name, "ol" | "ul"))
+                    || (scope == Scope::Button && name == "button")
+            }
+            (_, Scope::Select) => true,
+            (_, Scope::Table) => false,
+            (_, _) => self.is_special(id),
+        }
+    }
+
+    /// いずれかの名前の HTML 要素がスコープ内にあるか
+    fn in_scope(&self, names: &[&str], scope: Scope) -> bool {
+        for &id in self.open.iter().rev() {
+            if self.is_html_one_of(id, names) {
+                return true;
+            }
+            if self.is_scope_boundary(id, scope) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// 特定の要素がスコープ内にあるか
+    fn element_in_scope(&self, target: usize) -> bool {
+        for &id in self.open.iter().rev() {
+            if id == target {
+                return true;
+            }
+            if self.is_scope_boundary(id, Scope::Default) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// いずれかの名前の HTML 要素を取り出すまでポップする
+    fn pop_until(&mut self, names: &[&str]) {
+        while let Some(id) = self.open.pop() {
+            if self.is_html_one_of(id, names) {
+                break;
+            }
+        }
+    }
+
+    /// 現在のノードがいずれかの名前（または html）になるまでポップする
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while !self.current_is(names) && !self.current_is(&["html"]) {
+            self.open.pop();
+        }
+    }
+
+    /// 暗黙の終了タグを生成する
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        const IMPLIED: &[&str] = &["dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc"];
+        while self.current_is(IMPLIED) && Some(self.name(self.current())) != except {
+            self.open.pop();
+        }
+    }
+
+    /// 表関連の要素も含めて暗黙の終了タグを生成する
+    fn generate_all_implied_end_tags(&mut self) {
+        const IMPLIED: &[&str] = &[
+            "caption", "colgroup", "dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt",
+            "rtc", "tbody", "td", "tfoot", "th", "thead", "tr",
+        ];
+        while self.current_is(IMPLIED) {
+            self.open.pop();
+        }
+    }
+
+    /// ボタンスコープ内の `<p>` を閉じる
+    fn close_p_in_button_scope(&mut self) {
+        if self.in_scope(&["p"], Scope::Button) {
+            self.generate_implied_end_tags(Some("p"));
+            self.pop_until(&["p"]);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Active Formatting Elements
+    // ------------------------------------------------------------------------
+
+    fn formatting_position(&self, id: usize) -> Option<usize> {
+        self.formatting.iter().position(|&entry| entry == Formatting::Element(id))
+    }
+
+    /// 書式要素をリストに加える（Noah's Ark: 同じ要素は最後のマーカー以降 3 つまで）
+    fn push_formatting(&mut self, id: usize) {
+        let mut same = Vec::new();
+        for (index, &entry) in self.formatting.iter().enumerate().rev() {
+            match entry {
+                Formatting::Marker => break,
+                Formatting::Element(other)
+                    if self.nodes[other].namespace == self.nodes[id].namespace
+                        && self.nodes[other].node_type == self.nodes[id].node_type =>
+                {
+                    same.push(index)
+                }
+                Formatting::Element(_) => {}
+            }
+        }
+        if same.len() >= 3 {
+            self.formatting.remove(same[same.len() - 1]);
+        }
+        self.formatting.push(Formatting::Element(id));
+    }
+
+    /// 閉じられた書式要素を開き直す
+    fn reconstruct_formatting(&mut self) {
+        let is_settled = |builder: &Self, entry: Formatting| match entry {
+            Formatting::Marker => true,
+            Formatting::Element(id) => builder.open.contains(&id),
+        };
+        let Some(&last) = self.formatting.last() else {
+            return;
+        };
+        if is_settled(self, last) {
+            return;
+        }
+        let mut start = self.formatting.len() - 1;
+        while start > 0 && !is_settled(self, self.formatting[start - 1]) {
+            start -= 1;
+        }
+        for index in start..self.formatting.len() {
+            if let Formatting::Element(old) = self.formatting[index] {
+                let new = self.clone_element(old);
+                let place = self.appropriate_place(None);
+                self.insert_at(place, new);
+                self.open.push(new);
+                self.formatting[index] = Formatting::Element(new);
+            }
+        }
+    }
+
+    /// 最後のマーカーまでリストを消す
+    fn clear_formatting_to_marker(&mut self) {
+        while let Some(entry) = self.formatting.pop() {
+            if entry == Formatting::Marker {
+                break;
+            }
+        }
+    }
+
+    /// 養子縁組アルゴリズム（誤った入れ子の書式要素の終了タグ）
+    ///
+    /// 「その他の終了タグ」として扱うべきときは false を返す。
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        let current = self.current();
+        if self.is_html(current, subject) && self.formatting_position(current).is_none() {
+            self.open.pop();
+            return true;
+        }
+
+        for _ in 0..8 {
+            // 最後のマーカー以降で subject という名前の書式要素
+            let mut found = None;
+            for (index, &entry) in self.formatting.iter().enumerate().rev() {
+                match entry {
+                    Formatting::Marker => break,
+                    Formatting::Element(id) if self.is_html(id, subject) => {
+                        found = Some((index, id));
+                        break;
+                    }
+                    Formatting::Element(_) => {}
+                }
+            }
+            let Some((formatting_index, element)) = found else {
+                return false;
+            };
+            let Some(element_index) = self.open.iter().position(|&id| id == element) else {
+                self.formatting.remove(formatting_index);
+                return true;
+            };
+            if !self.element_in_scope(element) {
+                return true;
+            }
+
+            // 書式要素より下にある最初の特殊要素（最遠ブロック）
+            let Some(block_index) = (element_index + 1..self.open.len()).find(|&i| self.is_special(self.open[i]))
+            else {
+                self.open.truncate(element_index);
+                self.formatting.remove(formatting_index);
+                return true;
+            };
+            let furthest_block = self.open[block_index];
+            let common_ancestor = self.open[element_index - 1];
+
+            let mut bookmark = Bookmark::Replace;
+            let mut node_index = block_index;
+            let mut last_node = furthest_block;
+            let mut inner = 0;
+            loop {
+                inner += 1;
+                node_index -= 1;
+                let node = self.open[node_index];
+                if node == element {
+                    break;
+                }
+                let mut position = self.formatting_position(node);
+                if inner > 3 {
+                    if let Some(index) = position.take() {
+                        self.formatting.remove(index);
+                    }
+                }
+                let Some(position) = position else {
+                    self.open.remove(node_index);
+                    continue;
+                };
+                let new = self.clone_element(node);
+                self.formatting[position] = Formatting::Element(new);
+                self.open[node_index] = new;
+                if last_node == furthest_block {
+                    bookmark = Bookmark::After(new);
+                }
+                self.append(new, last_node);
+                last_node = new;
+            }
+
+            let place = self.appropriate_place(Some(common_ancestor));
+            self.insert_at(place, last_node);
+
+            // 最遠ブロックの子を書式要素の複製へ移す
+            let new = self.clone_element(element);
+            for child in mem::take(&mut self.nodes[furthest_block].children) {
+                self.nodes[child].parent = Some(new);
+                self.nodes[new].children.push(child);
+            }
+            self.append(furthest_block, new);
+
+            match bookmark {
+                Bookmark::Replace => {
+                    if let Some(index) = self.formatting_position(element) {
+                        self.formatting[index] = Formatting::Element(new);
+                    }
+                }
+                Bookmark::After(after) => {
+                    if let Some(index) = self.formatting_position(element) {
+                        self.formatting.remove(index);
+                    }
+                    let index = self.formatting_position(after).map_or(self.formatting.len(), |i| i + 1);
+                    self.formatting.insert(index, Formatting::Element(new));
+                }
+            }
+            self.open.retain(|&id| id != element);
+            let index = self.open.iter().position(|&id| id == furthest_block).map_or(self.open.len(), |i| i + 1);
+            self.open.insert(index, new);
+        }
+        true
+    }
+
+    // ------------------------------------------------------------------------
+    // Insertion
+    // ------------------------------------------------------------------------
+
+    /// ノードを挿入する適切な位置 (親, この子の直前)
+    fn appropriate_place(&self, override_target: Option<usize>) -> (usize, Option<usize>) {
+        let target = override_target.unwrap_or_else(|| self.current());
+        if !self.foster_parenting || !self.is_html_one_of(target, &["table", "tbody", "tfoot", "thead", "tr"]) {
+            return (target, None);
+        }
+
+        // 里親: 表の直前（表がなければ html 要素）に入れる
+        let last_template = self.open.iter().rposition(|&id| self.is_html(id, "template"));
+        let last_table = self.open.iter().rposition(|&id| self.is_html(id, "table"));
+        match (last_template, last_table) {
+            (Some(template), table) if table.is_none_or(|table| template > table) => (self.open[template], None),
+            (_, None) => (self.open[0], None),
+            (_, Some(index)) => {
+                let table = self.open[index];
+                match self.nodes[table].parent {
+                    Some(parent) => (parent, Some(table)),
+                    None => (self.open[index - 1], None),
+                }
+            }
+        }
+    }
+
+    /// 要素を適切な位置に挿入し、スタックに積む
+    fn insert_element(&mut self, tag: &Tag, namespace: Namespace) -> usize {
+        let id = self.create_element(tag, namespace);
+        let place = self.appropriate_place(None);
+        self.insert_at(place, id);
+        self.open.push(id);
+        id
+    }
+
+    fn insert_html(&mut self, tag: &Tag) -> usize {
+        self.insert_element(tag, Namespace::Html)
+    }
+
+    /// 空の要素（`<br>` など）を挿入する
+    fn insert_void(&mut self, tag: &Tag) {
+        self.insert_html(tag);
+        self.open.pop();
+    }
+
+    /// 文字を挿入する（直前のテキストノードがあればつなげる）
+    fn insert_character(&mut self, c: char) {
+        let (parent, before) = self.appropriate_place(None);
+        if parent == DOCUMENT {
+            return;
+        }
+        let children = &self.nodes[parent].children;
+        let previous = match before {
+            Some(before) => children
+                .iter()
+                .position(|&child| child == before)
+                .and_then(|index| index.checked_sub(1))
+                .map(|index| children[index]),
+            None => children.last().copied(),
+        };
+        if let Some(previous) = previous {
+            if let NodeType::Text(text) = &mut self.nodes[previous].node_type {
+                text.push(c);
+                return;
+            }
+        }
+        let text = self.create(NodeType::Text(c.into()), Namespace::Html);
+        self.insert_at((parent, before), text);
+    }
+
+    fn insert_comment(&mut self, text: String) {
+        let place = self.appropriate_place(None);
+        let comment = self.create(NodeType::Comment(text), Namespace::Html);
+        self.insert_at(place, comment);
+    }
+
+    fn append_comment(&mut self, parent: usize, text: String) {
+        let comment = self.create(NodeType::Comment(text), Namespace::Html);
+        self.append(parent, comment);
+    }
+
+    /// RAWTEXT / RCDATA の要素（`<style>`、`<title>` など）を読み始める
+    fn parse_text_element(&mut self, tag: &Tag, state: State) {
+        self.insert_html(tag);
+        self.tokenizer.set_state(state);
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    /// 属性を既存の要素に足す（既にある属性は上書きしない）
+    fn merge_attributes(&mut self, id: usize, attributes: &AttrMap) {
+        if let NodeType::Element(data) = &mut self.nodes[id].node_type {
+            for (name, value) in attributes {
+                data.attributes.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+
+    /// 開いている要素から挿入モードを決め直す
+    fn reset_insertion_mode(&mut self) {
+        for index in (0..self.open.len()).rev() {
+            let node = self.open[index];
+            let last = index == 0;
+            let mode = match self.name(node) {
+                "select" if !last => {
+                    let in_table = self.open[..index]
+                        .iter()
+                        .rev()
+                        .take_while(|&&id| !self.is_html(id, "template"))
+                        .any(|&id| self.is_html(id, "table"));
+                    if in_table { InsertionMode::InSelectInTable } else { InsertionMode::InSelect }
+                }
+                "select" => InsertionMode::InSelect,
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "template" => self.template_modes.last().copied().unwrap_or(InsertionMode::InTemplate),
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "frameset" => InsertionMode::InFrameset,
+                "html" if self.head.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            if self.nodes[node].namespace == Namespace::Html || last {
+                self.mode = mode;
+                return;
+            }
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    // ------------------------------------------------------------------------
+    // Dispatch
+    // ------------------------------------------------------------------------
+
+    /// トークンを処理する（外部コンテンツ中かどうかで規則を選ぶ）
+    fn process(&mut self, token: Token) {
+        if self.use_foreign_rules(&token) {
+            self.in_foreign_content(token);
+        } else {
+            self.process_in(self.mode, token);
+        }
+    }
+
+    /// 指定の挿入モードの規則でトークンを処理する
+    fn process_in(&mut self, mode: InsertionMode, token: Token) {
+        match mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::InHeadNoscript => self.in_head_noscript(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::InTemplate => self.in_template(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    /// 外部コンテンツ（SVG / MathML）の規則を使うか
+    fn use_foreign_rules(&self, token: &Token) -> bool {
+        let Some(&node) = self.open.last() else {
+            return false;
+        };
+        let namespace = self.nodes[node].namespace;
+        if namespace == Namespace::Html {
+            return false;
+        }
+        let name = self.name(node);
+        let text_point = namespace == Namespace::MathMl && is_mathml_text_integration_point(name);
+        let html_point = namespace == Namespace::Svg && is_html_integration_point(name);
+        match token {
+            Token::StartTag(tag) if text_point => matches!(tag.name.as_str(), "mglyph" | "malignmark"),
+            Token::StartTag(tag) if namespace == Namespace::MathMl && name == "annotation-xml" => tag.name != "svg",
+            Token::StartTag(_) | Token::Character(_) => !(text_point || html_point),
+            Token::Eof => false,
+            _ => true,
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Insertion Modes
+    // ------------------------------------------------------------------------
+
+    fn initial(&mut self, token: Token) {
+        match token {
+            Token::Character(c) if is_whitespace(c) => {}
+            Token::Comment(text) => self.append_comment(DOCUMENT, text),
+            Token::Doctype(doctype) => {
+                self.quirks = is_quirks_doctype(&doctype);
+                self.mode = InsertionMode::BeforeHtml;
+            }
+            token => {
+                self.quirks = true;
+                self.mode = InsertionMode::BeforeHtml;
+                self.process(token);
+            }
+        }
+    }
+
+    fn before_html(&mut self, token: Token) {
+        match &token {
+            Token::Doctype(_) => return,
+            Token::Comment(text) => return self.append_comment(DOCUMENT, text.clone()),
+            Token::Character(c) if is_whitespace(*c) => return,
+            Token::StartTag(tag) if tag.name == "html" => {
+                let html = self.create_element(tag, Namespace::Html);
+                self.append(DOCUMENT, html);
+                self.open.push(html);
+                self.mode = InsertionMode::BeforeHead;
+                return;
+            }
+            Token::EndTag(tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => return,
+            _ => {}
+        }
+        let html = self.create_element(&Tag::named("html"), Namespace::Html);
+        self.append(DOCUMENT, html);
+        self.open.push(html);
+        self.mode = InsertionMode::BeforeHead;
+        self.process(token);
+    }
+
+    fn before_head(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => return,
+            Token::Comment(text) => return self.insert_comment(text.clone()),
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) if tag.name == "html" => return self.in_body(token),
+            Token::StartTag(tag) if tag.name == "head" => {
+                self.head = Some(self.insert_html(tag));
+                self.mode = InsertionMode::InHead;
+                return;
+            }
+            Token::EndTag(tag) if !matches!(tag.name.as_str(), "head" | "body" | "html" | "br") => return,
+            _ => {}
+        }
+        self.head = Some(self.insert_html(&Tag::named("head")));
+        self.mode = InsertionMode::InHead;
+        self.process(token);
+    }
+
+    fn in_head(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => return self.insert_character(*c),
+            Token::Comment(text) => return self.insert_comment(text.clone()),
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => return self.in_body(token),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => return self.insert_void(tag),
+                "title" => return self.parse_text_element(tag, State::Rcdata),
+                "noframes" | "style" => return self.parse_text_element(tag, State::Rawtext),
+                "noscript" => {
+                    self.insert_html(tag);
+                    self.mode = InsertionMode::InHeadNoscript;
+                    return;
+                }
+                "script" => return self.parse_text_element(tag, State::ScriptData),
+                "template" => {
+                    self.insert_html(tag);
+                    self.formatting.push(Formatting::Marker);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InTemplate;
+                    self.template_modes.push(InsertionMode::InTemplate);
+                    return;
+                }
+                "head" => return,
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "head" => {
+                    self.open.pop();
+                    self.mode = InsertionMode::AfterHead;
+                    return;
+                }
+                "body" | "html" | "br" => {}
+                "template" => return self.end_template(),
+                _ => return,
+            },
+            _ => {}
+        }
+        self.open.pop();
+        self.mode = InsertionMode::AfterHead;
+        self.process(token);
+    }
+
+    /// `</template>`（head 内の規則）
+    fn end_template(&mut self) {
+        if !self.has_open("template") {
+            return;
+        }
+        self.generate_all_implied_end_tags();
+        self.pop_until(&["template"]);
+        self.clear_formatting_to_marker();
+        self.template_modes.pop();
+        self.reset_insertion_mode();
+    }
+
+    fn in_head_noscript(&mut self, token: Token) {
+        match &token {
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) if tag.name == "html" => return self.in_body(token),
+            Token::EndTag(tag) if tag.name == "noscript" => {
+                self.open.pop();
+                self.mode = InsertionMode::InHead;
+                return;
+            }
+            Token::Character(c) if is_whitespace(*c) => return self.in_head(token),
+            Token::Comment(_) => return self.in_head(token),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "basefont" | "bgsound" | "link" | "meta" | "noframes" | "style" => return self.in_head(token),
+                "head" | "noscript" => return,
+                _ => {}
+            },
+            Token::EndTag(tag) if tag.name != "br" => return,
+            _ => {}
+        }
+        self.open.pop();
+        self.mode = InsertionMode::InHead;
+        self.process(token);
+    }
+
+    fn after_head(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => return self.insert_character(*c),
+            Token::Comment(text) => return self.insert_comment(text.clone()),
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => return self.in_body(token),
+                "body" => {
+                    self.insert_html(tag);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InBody;
+                    return;
+                }
+                "frameset" => {
+                    self.insert_html(tag);
+                    self.mode = InsertionMode::InFrameset;
+                    return;
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style"
+                | "template" | "title" => {
+                    // head 要素に戻して処理する
+                    let Some(head) = self.head else {
+                        return;
+                    };
+                    self.open.push(head);
+                    self.in_head(token);
+                    self.open.retain(|&id| id != head);
+                    return;
+                }
+                "head" => return,
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "template" => return self.in_head(token),
+                "body" | "html" | "br" => {}
+                _ => return,
+            },
+            _ => {}
+        }
+        self.insert_html(&Tag::named("body"));
+        self.mode = InsertionMode::InBody;
+        self.process(token);
+    }
+
+    fn in_body(&mut self, token: Token) {
+        match token {
+            Token::Character('\0') => {}
+            Token::Character(c) => {
+                self.reconstruct_formatting();
+                self.insert_character(c);
+                if !is_whitespace(c) {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => self.in_body_start_tag(tag),
+            Token::EndTag(tag) => self.in_body_end_tag(tag),
+            Token::Eof => {
+                if !self.template_modes.is_empty() {
+                    self.in_template(Token::Eof);
+                }
+            }
+        }
+    }
+
+    fn in_body_start_tag(&mut self, tag: Tag) {
+        match tag.name.as_str() {
+            "html" => {
+                if !self.has_open("template") {
+                    self.merge_attributes(self.open[0], &tag.attributes);
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style" | "template"
+            | "title" => self.in_head(Token::StartTag(tag)),
+            "body" => {
+                if self.open.len() > 1 && self.is_html(self.open[1], "body") && !self.has_open("template") {
+                    self.frameset_ok = false;
+                    self.merge_attributes(self.open[1], &tag.attributes);
+                }
+            }
+            "frameset" => {
+                if self.open.len() > 1 && self.is_html(self.open[1], "body") && self.frameset_ok {
+                    self.detach(self.open[1]);
+                    self.open.truncate(1);
+                    self.insert_html(&tag);
+                    self.mode = InsertionMode::InFrameset;
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog" | "dir" | "div"
+            | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "main" | "menu"
+            | "nav" | "ol" | "p" | "search" | "section" | "summary" | "ul" => {
+                self.close_p_in_button_scope();
+                self.insert_html(&tag);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_in_button_scope();
+                if self.current_is(&["h1", "h2", "h3", "h4", "h5", "h6"]) {
+                    self.open.pop();
+                }
+                self.insert_html(&tag);
+            }
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_html(&tag);
+                self.skip_newline = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                let in_template = self.has_open("template");
+                if self.form.is_some() && !in_template {
+                    return;
+                }
+                self.close_p_in_button_scope();
+                let form = self.insert_html(&tag);
+                if !in_template {
+                    self.form = Some(form);
+                }
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+                let closes: &[&str] = if tag.name == "li" { &["li"] } else { &["dd", "dt"] };
+                for index in (0..self.open.len()).rev() {
+                    let node = self.open[index];
+                    if self.is_html_one_of(node, closes) {
+                        let name = String::from(self.name(node));
+                        self.generate_implied_end_tags(Some(name.as_str()));
+                        self.pop_until(&[name.as_str()]);
+                        break;
+                    }
+                    if self.is_special(node) && !self.is_html_one_of(node, &["address", "div", "p"]) {
+                        break;
+                    }
+                }
+                self.close_p_in_button_scope();
+                self.insert_html(&tag);
+            }
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_html(&tag);
+                self.tokenizer.set_state(State::Plaintext);
+            }
+            "button" => {
+                if self.in_scope(&["button"], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&["button"]);
+                }
+                self.reconstruct_formatting();
+                self.insert_html(&tag);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                let open_anchor = self
+                    .formatting
+                    .iter()
+                    .rev()
+                    .take_while(|&&entry| entry != Formatting::Marker)
+                    .find_map(|&entry| match entry {
+                        Formatting::Element(id) if self.is_html(id, "a") => Some(id),
+                        _ => None,
+                    });
+                if let Some(anchor) = open_anchor {
+                    self.adoption_agency("a");
+                    if let Some(index) = self.formatting_position(anchor) {
+                        self.formatting.remove(index);
+                    }
+                    self.open.retain(|&id| id != anchor);
+                }
+                self.reconstruct_formatting();
+                let id = self.insert_html(&tag);
+                self.push_formatting(id);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong" | "tt" | "u" => {
+                self.reconstruct_formatting();
+                let id = self.insert_html(&tag);
+                self.push_formatting(id);
+            }
+            "nobr" => {
+                self.reconstruct_formatting();
+                if self.in_scope(&["nobr"], Scope::Default) {
+                    self.adoption_agency("nobr");
+                    self.reconstruct_formatting();
+                }
+                let id = self.insert_html(&tag);
+                self.push_formatting(id);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_formatting();
+                self.insert_html(&tag);
+                self.formatting.push(Formatting::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                if !self.quirks {
+                    self.close_p_in_button_scope();
+                }
+                self.insert_html(&tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" => {
+                self.reconstruct_formatting();
+                self.insert_void(&tag);
+                self.frameset_ok = false;
+            }
+            "input" => {
+                self.reconstruct_formatting();
+                self.insert_void(&tag);
+                if !is_hidden_input(&tag) {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => self.insert_void(&tag),
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_void(&tag);
+                self.frameset_ok = false;
+            }
+            "image" => self.process(Token::StartTag(Tag { name: "img".into(), ..tag })),
+            "textarea" => {
+                self.insert_html(&tag);
+                self.skip_newline = true;
+                self.tokenizer.set_state(State::Rcdata);
+                self.original_mode = self.mode;
+                self.frameset_ok = false;
+                self.mode = InsertionMode::Text;
+            }
+            "xmp" => {
+                self.close_p_in_button_scope();
+                self.reconstruct_formatting();
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_text_element(&tag, State::Rawtext);
+            }
+            "noembed" => self.parse_text_element(&tag, State::Rawtext),
+            "select" => {
+                self.reconstruct_formatting();
+                self.insert_html(&tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.current_is(&["option"]) {
+                    self.open.pop();
+                }
+                self.reconstruct_formatting();
+                self.insert_html(&tag);
+            }
+            "rb" | "rtc" => {
+                if self.in_scope(&["ruby"], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                }
+                self.insert_html(&tag);
+            }
+            "rp" | "rt" => {
+                if self.in_scope(&["ruby"], Scope::Default) {
+                    self.generate_implied_end_tags(Some("rtc"));
+                }
+                self.insert_html(&tag);
+            }
+            "math" | "svg" => {
+                self.reconstruct_formatting();
+                let namespace = if tag.name == "math" { Namespace::MathMl } else { Namespace::Svg };
+                self.insert_element(&tag, namespace);
+                if tag.self_closing {
+                    self.open.pop();
+                }
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th" | "thead"
+            | "tr" => {}
+            _ => {
+                self.reconstruct_formatting();
+                self.insert_html(&tag);
+            }
+        }
+    }
+
+    fn in_body_end_tag(&mut self, tag: Tag) {
+        match tag.name.as_str() {
+            "template" => self.end_template(),
+            "body" | "html" => {
+                if self.in_scope(&["body"], Scope::Default) {
+                    self.mode = InsertionMode::AfterBody;
+                    if tag.name == "html" {
+                        self.process(Token::EndTag(tag));
+                    }
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details" | "dialog" | "dir"
+            | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "listing"
+            | "main" | "menu" | "nav" | "ol" | "pre" | "search" | "section" | "summary" | "ul" => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[tag.name.as_str()]);
+                }
+            }
+            "form" => {
+                if self.has_open("template") {
+                    if self.in_scope(&["form"], Scope::Default) {
+                        self.generate_implied_end_tags(None);
+                        self.pop_until(&["form"]);
+                    }
+                    return;
+                }
+                let Some(form) = self.form.take() else {
+                    return;
+                };
+                if self.element_in_scope(form) {
+                    self.generate_implied_end_tags(None);
+                    self.open.retain(|&id| id != form);
+                }
+            }
+            "p" => {
+                if !self.in_scope(&["p"], Scope::Button) {
+                    self.insert_html(&Tag::named("p"));
+                }
+                self.close_p_in_button_scope();
+            }
+            "li" => {
+                if self.in_scope(&["li"], Scope::ListItem) {
+                    self.generate_implied_end_tags(Some("li"));
+                    self.pop_until(&["li"]);
+                }
+            }
+            "dd" | "dt" => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Default) {
+                    self.generate_implied_end_tags(Some(tag.name.as_str()));
+                    self.pop_until(&[tag.name.as_str()]);
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+                if self.in_scope(HEADINGS, Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(HEADINGS);
+                }
+            }
+            "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s" | "small" | "strike" | "strong"
+            | "tt" | "u" => {
+                if !self.adoption_agency(&tag.name) {
+                    self.any_other_end_tag(&tag.name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[tag.name.as_str()]);
+                    self.clear_formatting_to_marker();
+                }
+            }
+            "br" => self.in_body_start_tag(Tag::named("br")),
+            _ => self.any_other_end_tag(&tag.name),
+        }
+    }
+
+    /// 「その他の終了タグ」（同名の要素まで閉じる。途中に特殊要素があれば無視）
+    fn any_other_end_tag(&mut self, name: &str) {
+        for index in (0..self.open.len()).rev() {
+            let node = self.open[index];
+            if self.is_html(node, name) {
+                self.generate_implied_end_tags(Some(name));
+                self.open.truncate(index);
+                return;
+            }
+            if self.is_special(node) {
+                return;
+            }
+        }
+    }
+
+    fn text(&mut self, token: Token) {
+        match token {
+            Token::Character(c) => self.insert_character(c),
+            Token::Eof => {
+                self.open.pop();
+                self.mode = self.original_mode;
+                self.process(Token::Eof);
+            }
+            Token::EndTag(_) => {
+                self.open.pop();
+                self.mode = self.original_mode;
+            }
+            _ => {}
+        }
+    }
+
+    fn in_table(&mut self, token: Token) {
+        const TABLE_CONTEXT: &[&str] = &["table", "template"];
+        match &token {
+            Token::Character(_) if self.current_is(&["table", "tbody", "template", "tfoot", "thead", "tr"]) => {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::InTableText;
+                return self.process(token);
+            }
+            Token::Comment(text) => return self.insert_comment(text.clone()),
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "caption" => {
+                    self.clear_stack_back_to(TABLE_CONTEXT);
+                    self.formatting.push(Formatting::Marker);
+                    self.insert_html(tag);
+                    self.mode = InsertionMode::InCaption;
+                    return;
+                }
+                "colgroup" => {
+                    self.clear_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html(tag);
+                    self.mode = InsertionMode::InColumnGroup;
+                    return;
+                }
+                "col" => {
+                    self.clear_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html(&Tag::named("colgroup"));
+                    self.mode = InsertionMode::InColumnGroup;
+                    return self.process(token);
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html(tag);
+                    self.mode = InsertionMode::InTableBody;
+                    return;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_stack_back_to(TABLE_CONTEXT);
+                    self.insert_html(&Tag::named("tbody"));
+                    self.mode = InsertionMode::InTableBody;
+                    return self.process(token);
+                }
+                "table" => {
+                    if self.in_scope(&["table"], Scope::Table) {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                        self.process(token);
+                    }
+                    return;
+                }
+                "style" | "script" | "template" => return self.in_head(token),
+                "input" if is_hidden_input(tag) => return self.insert_void(tag),
+                "form" => {
+                    if self.form.is_none() && !self.has_open("template") {
+                        self.form = Some(self.insert_html(tag));
+                        self.open.pop();
+                    }
+                    return;
+                }
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "table" => {
+                    if self.in_scope(&["table"], Scope::Table) {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                    }
+                    return;
+                }
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead"
+                | "tr" => return,
+                "template" => return self.in_head(token),
+                _ => {}
+            },
+            Token::Eof => return self.in_body(token),
+            _ => {}
+        }
+        // 表の中に置けない内容は表の前へ里親に出す
+        self.foster_parenting = true;
+        self.in_body(token);
+        self.foster_parenting = false;
+    }
+
+    fn in_table_text(&mut self, token: Token) {
+        match token {
+            Token::Character('\0') => {}
+            Token::Character(c) => self.pending_table_text.push(c),
+            token => {
+                let pending = mem::take(&mut self.pending_table_text);
+                if pending.iter().any(|&c| !is_whitespace(c)) {
+                    self.foster_parenting = true;
+                    for c in pending {
+                        self.in_body(Token::Character(c));
+                    }
+                    self.foster_parenting = false;
+                } else {
+                    for c in pending {
+                        self.insert_character(c);
+                    }
+                }
+                self.mode = self.original_mode;
+                self.process(token);
+            }
+        }
+    }
+
+    /// `<caption>` を閉じる（スコープになければ false）
+    fn close_caption(&mut self) -> bool {
+        if !self.in_scope(&["caption"], Scope::Table) {
+            return false;
+        }
+        self.generate_implied_end_tags(None);
+        self.pop_until(&["caption"]);
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    fn in_caption(&mut self, token: Token) {
+        match &token {
+            Token::EndTag(tag) if tag.name == "caption" => {
+                self.close_caption();
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_caption() {
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag) if tag.name == "table" => {
+                if self.close_caption() {
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) => {}
+            _ => self.in_body(token),
+        }
+    }
+
+    fn in_column_group(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => return self.insert_character(*c),
+            Token::Comment(text) => return self.insert_comment(text.clone()),
+            Token::Doctype(_) => return,
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => return self.in_body(token),
+                "col" => return self.insert_void(tag),
+                "template" => return self.in_head(token),
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "colgroup" => {
+                    if self.current_is(&["colgroup"]) {
+                        self.open.pop();
+                        self.mode = InsertionMode::InTable;
+                    }
+                    return;
+                }
+                "col" => return,
+                "template" => return self.in_head(token),
+                _ => {}
+            },
+            Token::Eof => return self.in_body(token),
+            _ => {}
+        }
+        if self.current_is(&["colgroup"]) {
+            self.open.pop();
+            self.mode = InsertionMode::InTable;
+            self.process(token);
+        }
+    }
+
+    fn in_table_body(&mut self, token: Token) {
+        const TABLE_BODY_CONTEXT: &[&str] = &["tbody", "tfoot", "thead", "template"];
+        match &token {
+            Token::StartTag(tag) if tag.name == "tr" => {
+                self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_html(tag);
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_html(&Tag::named("tr"));
+                self.mode = InsertionMode::InRow;
+                self.process(token);
+            }
+            Token::EndTag(tag) if matches!(tag.name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Table) {
+                    self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                    self.open.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            Token::StartTag(tag)
+                if matches!(tag.name.as_str(), "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead") =>
+            {
+                self.close_table_body(token)
+            }
+            Token::EndTag(tag) if tag.name == "table" => self.close_table_body(token),
+            Token::EndTag(tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+                ) => {}
+            _ => self.in_table(token),
+        }
+    }
+
+    /// 行グループを閉じてから表の規則で処理し直す
+    fn close_table_body(&mut self, token: Token) {
+        if self.in_scope(&["tbody", "thead", "tfoot"], Scope::Table) {
+            self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template"]);
+            self.open.pop();
+            self.mode = InsertionMode::InTable;
+            self.process(token);
+        }
+    }
+
+    /// `<tr>` を閉じる（スコープになければ false）
+    fn close_row(&mut self) -> bool {
+        if !self.in_scope(&["tr"], Scope::Table) {
+            return false;
+        }
+        self.clear_stack_back_to(&["tr", "template"]);
+        self.open.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
+    }
+
+    fn in_row(&mut self, token: Token) {
+        match &token {
+            Token::StartTag(tag) if matches!(tag.name.as_str(), "th" | "td") => {
+                self.clear_stack_back_to(&["tr", "template"]);
+                self.insert_html(tag);
+                self.mode = InsertionMode::InCell;
+                self.formatting.push(Formatting::Marker);
+            }
+            Token::EndTag(tag) if tag.name == "tr" => {
+                self.close_row();
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                if self.close_row() {
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag) if tag.name == "table" => {
+                if self.close_row() {
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag) if matches!(tag.name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Table) && self.close_row() {
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag)
+                if matches!(tag.name.as_str(), "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th") => {}
+            _ => self.in_table(token),
+        }
+    }
+
+    /// セルを閉じる
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        self.pop_until(&["td", "th"]);
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    fn in_cell(&mut self, token: Token) {
+        match &token {
+            Token::EndTag(tag) if matches!(tag.name.as_str(), "td" | "th") => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Table) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[tag.name.as_str()]);
+                    self.clear_formatting_to_marker();
+                    self.mode = InsertionMode::InRow;
+                }
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    tag.name.as_str(),
+                    "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr"
+                ) =>
+            {
+                if self.in_scope(&["td", "th"], Scope::Table) {
+                    self.close_cell();
+                    self.process(token);
+                }
+            }
+            Token::EndTag(tag) if matches!(tag.name.as_str(), "body" | "caption" | "col" | "colgroup" | "html") => {}
+            Token::EndTag(tag) if matches!(tag.name.as_str(), "table" | "tbody" | "tfoot" | "thead" | "tr") => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Table) {
+                    self.close_cell();
+                    self.process(token);
+                }
+            }
+            _ => self.in_body(token),
+        }
+    }
+
+    /// `<select>` を閉じる（スコープになければ false）
+    fn close_select(&mut self) -> bool {
+        if !self.in_scope(&["select"], Scope::Select) {
+            return false;
+        }
+        self.pop_until(&["select"]);
+        self.reset_insertion_mode();
+        true
+    }
+
+    fn in_select(&mut self, token: Token) {
+        match &token {
+            Token::Character('\0') => {}
+            Token::Character(c) => self.insert_character(*c),
+            Token::Comment(text) => self.insert_comment(text.clone()),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(token),
+                "option" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    self.insert_html(tag);
+                }
+                "optgroup" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                    self.insert_html(tag);
+                }
+                "hr" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                    self.insert_void(tag);
+                }
+                "select" | "input" | "keygen" | "textarea" => {
+                    // <select> の中の <select> は閉じるだけ、入力欄は select を閉じてから処理し直す
+                    let reprocess = tag.name != "select";
+                    if self.close_select() && reprocess {
+                        self.process(token);
+                    }
+                }
+                "script" | "template" => self.in_head(token),
+                _ => {}
+            },
+            Token::EndTag(tag) => match tag.name.as_str() {
+                "optgroup" => {
+                    let len = self.open.len();
+                    if self.current_is(&["option"]) && len > 1 && self.is_html(self.open[len - 2], "optgroup") {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                }
+                "option" if self.current_is(&["option"]) => {
+                    self.open.pop();
+                }
+                "select" => {
+                    self.close_select();
+                }
+                "template" => self.in_head(token),
+                _ => {}
+            },
+            Token::Eof => self.in_body(token),
+        }
+    }
+
+    fn in_select_in_table(&mut self, token: Token) {
+        const TABLE_PARTS: &[&str] = &["caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th"];
+        match &token {
+            Token::StartTag(tag) if TABLE_PARTS.contains(&tag.name.as_str()) => {
+                self.pop_until(&["select"]);
+                self.reset_insertion_mode();
+                self.process(token);
+            }
+            Token::EndTag(tag) if TABLE_PARTS.contains(&tag.name.as_str()) => {
+                if self.in_scope(&[tag.name.as_str()], Scope::Table) {
+                    self.pop_until(&["select"]);
+                    self.reset_insertion_mode();
+                    self.process(token);
+                }
+            }
+            _ => self.in_select(token),
+        }
+    }
+
+    fn in_template(&mut self, token: Token) {
+        let next = match &token {
+            Token::Character(_) | Token::Comment(_) | Token::Doctype(_) => return self.in_body(token),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style"
+                | "template" | "title" => return self.in_head(token),
+                "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => InsertionMode::InTable,
+                "col" => InsertionMode::InColumnGroup,
+                "tr" => InsertionMode::InTableBody,
+                "td" | "th" => InsertionMode::InRow,
+                _ => InsertionMode::InBody,
+            },
+            Token::EndTag(tag) if tag.name == "template" => return self.in_head(token),
+            Token::EndTag(_) => return,
+            Token::Eof => {
+                if self.has_open("template") {
+                    self.pop_until(&["template"]);
+                    self.clear_formatting_to_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                    self.process(token);
+                }
+                return;
+            }
+        };
+        self.template_modes.pop();
+        self.template_modes.push(next);
+        self.mode = next;
+        self.process(token);
+    }
+
+    fn after_body(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => self.in_body(token),
+            Token::Comment(text) => self.append_comment(self.open[0], text.clone()),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) if tag.name == "html" => self.in_body(token),
+            Token::EndTag(tag) if tag.name == "html" => self.mode = InsertionMode::AfterAfterBody,
+            Token::Eof => {}
+            _ => {
+                self.mode = InsertionMode::InBody;
+                self.process(token);
+            }
+        }
+    }
+
+    fn in_frameset(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => self.insert_character(*c),
+            Token::Comment(text) => self.insert_comment(text.clone()),
+            Token::StartTag(tag) => match tag.name.as_str() {
+                "html" => self.in_body(token),
+                "frameset" => {
+                    self.insert_html(tag);
+                }
+                "frame" => self.insert_void(tag),
+                "noframes" => self.in_head(token),
+                _ => {}
+            },
+            Token::EndTag(tag) if tag.name == "frameset" && !self.current_is(&["html"]) => {
+                self.open.pop();
+                if !self.current_is(&["frameset"]) {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn after_frameset(&mut self, token: Token) {
+        match &token {
+            Token::Character(c) if is_whitespace(*c) => self.insert_character(*c),
+            Token::Comment(text) => self.insert_comment(text.clone()),
+            Token::StartTag(tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "noframes" => self.in_head(token),
+            Token::EndTag(tag) if tag.name == "html" => self.mode = InsertionMode::AfterAfterFrameset,
+            _ => {}
+        }
+    }
+
+    fn after_after_body(&mut self, token: Token) {
+        match &token {
+            Token::Comment(text) => self.append_comment(DOCUMENT, text.clone()),
+            Token::Character(c) if is_whitespace(*c) => self.in_body(token),
+            Token::Doctype(_) => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "html" => self.in_body(token),
+            Token::Eof => {}
+            _ => {
+                self.mode = InsertionMode::InBody;
+                self.process(token);
+            }
+        }
+    }
+
+    fn after_after_frameset(&mut self, token: Token) {
+        match &token {
+            Token::Comment(text) => self.append_comment(DOCUMENT, text.clone()),
+            Token::Character(c) if is_whitespace(*c) => self.in_body(token),
+            Token::Doctype(_) => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "html" => self.in_body(token),
+            Token::StartTag(tag) if tag.name == "noframes" => self.in_head(token),
+            _ => {}
+        }
+    }
+
+    /// 外部コンテンツ（SVG / MathML の中）
+    fn in_foreign_content(&mut self, token: Token) {
+        match &token {
+            Token::Character('\0') => self.insert_character('\u{FFFD}'),
+            Token::Character(c) => {
+                self.insert_character(*c);
+                if !is_whitespace(*c) {
+                    self.frameset_ok = false;
+                }
+            }
+            Token::Comment(text) => self.insert_comment(text.clone()),
+            Token::Doctype(_) => {}
+            Token::StartTag(tag) if breaks_out_of_foreign_content(tag) => {
+                // HTML の要素が来たら外部コンテンツを抜ける
+                while let Some(&node) = self.open.last() {
+                    let name = self.name(node);
+                    let done = match self.nodes[node].namespace {
+                        Namespace::Html => true,
+                        Namespace::MathMl => is_mathml_text_integration_point(name),
+                        Namespace::Svg => is_html_integration_point(name),
+                    };
+                    if done {
+                        break;
+                    }
+                    self.open.pop();
+                }
+                self.process_in(self.mode, token);
+            }
+            Token::StartTag(tag) => {
+                let namespace = self.nodes[self.current()].namespace;
+                self.insert_element(tag, namespace);
+                if tag.self_closing {
+                    self.open.pop();
+                }
+            }
+            Token::EndTag(tag) => {
+                let mut index = self.open.len() - 1;
+                loop {
+                    if index == 0 {
+                        return;
+                    }
+                    if self.name(self.open[index]).eq_ignore_ascii_case(&tag.name) {
+                        self.open.truncate(index);
+                        return;
+                    }
+                    index -= 1;
+                    if self.nodes[self.open[index]].namespace == Namespace::Html {
+                        return self.process_in(self.mode, token);
+                    }
+                }
+            }
+            Token::Eof => self.process_in(self.mode, token),
+        }
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// SVG の HTML 統合点（中身を HTML として解析する要素）
+fn is_html_integration_point(name: &str) -> bool {
+    matches!(name, "foreignobject" | "desc" | "title")
+}
+
+/// MathML のテキスト統合点
+fn is_mathml_text_integration_point(name: &str) -> bool {
+    matches!(name, "mi" | "mo" | "mn" | "ms" | "mtext")
+}
+
+/// 外部コンテンツを抜ける開始タグか
+fn breaks_out_of_foreign_content(tag: &Tag) -> bool {
+    match tag.name.as_str() {
+        "b" | "big" | "blockquote" | "body" | "br" | "center" | "code" | "dd" | "div" | "dl" | "dt" | "em"
+        | "embed" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "head" | "hr" | "i" | "img" | "li" | "listing"
+        | "menu" | "meta" | "nobr" | "ol" | "p" | "pre" | "ruby" | "s" | "small" | "span" | "strong"
+        | "strike" | "sub" | "sup" | "table" | "tt" | "u" | "ul" | "var" => true,
+        "font" => ["color", "face", "size"].iter().any(|&name| tag.attributes.contains_key(name)),
+        _ => false,
+    }
+}
+
+/// `<input type="hidden">` か
+fn is_hidden_input(tag: &Tag) -> bool {
+    tag.attributes.get("type").is_some_and(|value| value.eq_ignore_ascii_case("hidden"))
+}
+
+/// DOCTYPE が互換モード（quirks mode）を選ぶか
+fn is_quirks_doctype(doctype: &Doctype) -> bool {
+    const QUIRKY_PUBLIC_PREFIXES: &[&str] = &[
+        "+//silmaril//dtd html pro v0r11 19970101//",
+        "-//as//dtd html 3.0 aswedit + extensions//",
+        "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
+        "-//ietf//dtd html 2.0 level 1//",
+        "-//ietf//dtd html 2.0 level 2//",
+        "-//ietf//dtd html 2.0 strict level 1//",
+        "-//ietf//dtd html 2.0 strict level 2//",
+        "-//ietf//dtd html 2.0 strict//",
+        "-//ietf//dtd html 2.0//",
+        "-//ietf//dtd html 2.1e//",
+        "-//ietf//dtd html 3.0//",
+        "-//ietf//dtd html 3.2 final//",
+        "-//ietf//dtd html 3.2//",
+        "-//ietf//dtd html 3//",
+        "-//ietf//dtd html level 0//",
+        "-//ietf//dtd html level 1//",
+        "-//ietf//dtd html level 2//",
+        "-//ietf//dtd html level 3//",
+        "-//ietf//dtd html strict level 0//",
+        "-//ietf//dtd html strict level 1//",
+        "-//ietf//dtd html strict level 2//",
+        "-//ietf//dtd html strict level 3//",
+        "-//ietf//dtd html strict//",
+        "-//ietf//dtd html//",
+        "-//metrius//dtd metrius presentational//",
+        "-//microsoft//dtd internet explorer 2.0 html strict//",
+        "-//microsoft//dtd internet explorer 2.0 html//",
+        "-//microsoft//dtd internet explorer 2.0 tables//",
+        "-//microsoft//dtd internet explorer 3.0 html strict//",
+        "-//microsoft//dtd internet explorer 3.0 html//",
+        "-//microsoft//dtd internet explorer 3.0 tables//",
+        "-//netscape comm. corp.//dtd html//",
+        "-//netscape comm. corp.//dtd strict html//",
+        "-//o'reilly and associates//dtd html 2.0//",
+        "-//o'reilly and associates//dtd html extended 1.0//",
+        "-//o'reilly and associates//dtd html extended relaxed 1.0//",
+        "-//sq//dtd html 2.0 hotmetal + extensions//",
+        "-//softquad software//dtd hotmetal pro 6.0::19990601::extensions to html 4.0//",
+        "-//softquad//dtd hotmetal pro 4.0::19971010::extensions to html 4.0//",
+        "-//spyglass//dtd html 2.0 extended//",
+        "-//sun microsystems corp.//dtd hotjava html//",
+        "-//sun microsystems corp.//dtd hotjava strict html//",
+        "-//w3c//dtd html 3 1995-03-24//",
+        "-//w3c//dtd html 3.2 draft//",
+        "-//w3c//dtd html 3.2 final//",
+        "-//w3c//dtd html 3.2//",
+        "-//w3c//dtd html 3.2s draft//",
+        "-//w3c//dtd html 4.0 frameset//",
+        "-//w3c//dtd html 4.0 transitional//",
+        "-//w3c//dtd html experimental 19960712//",
+        "-//w3c//dtd html experimental 970421//",
+        "-//w3c//dtd w3 html//",
+        "-//w3o//dtd w3 html 3.0//",
+        "-//webtechs//dtd mozilla html 2.0//",
+        "-//webtechs//dtd mozilla html//",
+    ];
+
+    if doctype.force_quirks || doctype.name.as_deref() != Some("html") {
+        return true;
+    }
+    let public = doctype.public_id.as_deref().unwrap_or("").to_ascii_lowercase();
+    let system = doctype.system_id.as_deref().map(|id| id.to_ascii_lowercase());
+    matches!(
+        public.as_str(),
+        "-//w3o//dtd w3 html strict 3.0//en//" | "-/w3c/dtd html 4.0 transitional/en" | "html"
+    ) || system.as_deref() == Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd")
+        || QUIRKY_PUBLIC_PREFIXES.iter().any(|prefix| public.starts_with(prefix))
+        || (system.is_none()
+            && (public.starts_with("-//w3c//dtd html 4.01 frameset//")
+                || public.starts_with("-//w3c//dtd html 4.01 transitional//")))
+}
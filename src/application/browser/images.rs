@@ -0,0 +1,69 @@
+// ============================================================================
+// src/application/browser/images.rs - Decoded Image Cache
+// ============================================================================
+//!
+//! # 画像キャッシュ
+//!
+//! `<img src>` と `background-image: url()` で参照されるデコード済みの画像。
+//! 画像は絶対URLをキーに保持し、参照は文書の基準URLで解決して引く
+//! （外部スタイルシートの `url()` は読み込み時に絶対URLへ書き換えてある）。
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+
+use crate::graphics::image::Image;
+use crate::net::http::Url;
+
+/// デコード済み画像
+#[derive(Default)]
+pub struct ImageCache {
+    /// 参照を解決する基準URL（None なら参照をそのままキーにする）
+    base: Option<Url>,
+    /// 絶対URL → 画像
+    images: BTreeMap<String, Image>,
+}
+
+impl ImageCache {
+    /// 空のキャッシュを作成
+    pub fn new(base: Option<Url>) -> Self {
+        Self {
+            base,
+            images: BTreeMap::new(),
+        }
+    }
+
+    /// 参照（`src` や `url()` の中身）をキーとなるURLにする
+    pub fn resolve(&self, reference: &str) -> Option<String> {
+        let reference = reference.trim();
+        if reference.is_empty() {
+            return None;
+        }
+        match &self.base {
+            Some(base) => base.join(reference).ok().map(|url| url.to_string()),
+            None => Some(reference.into()),
+        }
+    }
+
+    /// 画像を追加
+    pub fn insert(&mut self, url: String, image: Image) {
+        self.images.insert(url, image);
+    }
+
+    /// 参照の画像を取得（未読み込みなら None）
+    pub fn get(&self, reference: &str) -> Option<&Image> {
+        self.images.get(&self.resolve(reference)?)
+    }
+
+    /// 参照の画像の固有サイズ
+    pub fn size(&self, reference: &str) -> Option<(f32, f32)> {
+        self.get(reference)
+            .map(|image| (image.width() as f32, image.height() as f32))
+    }
+
+    /// 画像が 1 つもないか
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
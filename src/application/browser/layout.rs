@@ -5,15 +5,23 @@
 //! # レイアウトツリー
 //!
 //! スタイルツリーに基づき、各要素の座標とサイズを計算。
+//! フレックスコンテナの子の配置は `flex`、表の配置は `table` サブモジュールが行う。
+//! `<img>` は置換要素として、画像の固有サイズと width / height から大きさを決める。
+//! フォーム部品も置換要素で、固有サイズは size / cols / rows やラベルの長さから決める。
 
 extern crate alloc;
 
+mod flex;
+mod table;
+
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
 use super::style::{StyledNode, Display};
 use super::css::{Value, Unit};
 use super::dom::NodeType;
+use super::forms::{self, ControlKind};
+use super::images::ImageCache;
 
 // ============================================================================
 // Dimensions
@@ -116,6 +124,18 @@ pub enum BoxType {
     Inline,
     /// 匿名ブロック
     AnonymousBlock,
+    /// フレックスコンテナ
+    Flex,
+    /// インラインフレックスコンテナ
+    InlineFlex,
+    /// 表（子はキャプションのブロックと行グループ）
+    Table,
+    /// 行グループ（thead, tbody, tfoot）
+    TableRowGroup,
+    /// 表の行
+    TableRow,
+    /// 表のセル
+    TableCell,
 }
 
 /// レイアウトボックス
@@ -129,6 +149,8 @@ pub struct LayoutBox<'a> {
     pub styled_node: Option<&'a StyledNode<'a>>,
     /// 子ボックス
     pub children: Vec<LayoutBox<'a>>,
+    /// 置換要素（画像とフォーム部品）の固有サイズ（画像の読み込み前は None）
+    pub intrinsic_size: Option<(f32, f32)>,
 }
 
 impl<'a> LayoutBox<'a> {
@@ -139,6 +161,7 @@ impl<'a> LayoutBox<'a> {
             box_type,
             styled_node: None,
             children: Vec::new(),
+            intrinsic_size: None,
         }
     }
 
@@ -149,6 +172,7 @@ impl<'a> LayoutBox<'a> {
             box_type,
             styled_node: Some(styled_node),
             children: Vec::new(),
+            intrinsic_size: None,
         }
     }
 
@@ -156,7 +180,13 @@ impl<'a> LayoutBox<'a> {
     fn get_inline_container(&mut self) -> &mut LayoutBox<'a> {
         match self.box_type {
             BoxType::Inline | BoxType::AnonymousBlock => self,
-            BoxType::Block => {
+            BoxType::Block
+            | BoxType::Flex
+            | BoxType::InlineFlex
+            | BoxType::Table
+            | BoxType::TableRowGroup
+            | BoxType::TableRow
+            | BoxType::TableCell => {
                 // 最後の子が匿名ブロックでなければ作成
                 let needs_new = match self.children.last() {
                     Some(last) => last.box_type != BoxType::AnonymousBlock,
@@ -179,12 +209,24 @@ impl<'a> LayoutBox<'a> {
 pub fn layout_tree<'a>(
     styled_root: &'a StyledNode<'a>,
     containing_block: Dimensions,
+) -> LayoutBox<'a> {
+    layout_tree_with(styled_root, containing_block, &ImageCache::default())
+}
+
+/// 読み込み済みの画像の固有サイズを使ってレイアウトツリーを構築
+pub fn layout_tree_with<'a>(
+    styled_root: &'a StyledNode<'a>,
+    containing_block: Dimensions,
+    images: &ImageCache,
 ) -> LayoutBox<'a> {
     // ルートボックスを作成
     let mut root = build_layout_tree(styled_root);
+    root.assign_intrinsic_sizes(images);
 
-    // レイアウトを計算
-    root.layout(containing_block);
+    // レイアウトを計算（ブロックは包含ブロックの高さの下に積まれるので、高さ 0 から始める）
+    let mut initial = containing_block;
+    initial.content.height = 0.0;
+    root.layout(initial);
 
     root
 }
@@ -195,7 +237,12 @@ fn build_layout_tree<'a>(styled_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
     let box_type = match styled_node.display() {
         Display::Block => BoxType::Block,
         Display::Inline | Display::InlineBlock => BoxType::Inline,
-        Display::None => {
+        Display::Flex => BoxType::Flex,
+        Display::InlineFlex => BoxType::InlineFlex,
+        Display::Table => BoxType::Table,
+        // 表の外にある表の内部要素はブロックとして扱う
+        display if display.is_table_part() => BoxType::Block,
+        _ => {
             // display: none は空のボックス
             return LayoutBox::new(BoxType::Block);
         }
@@ -203,21 +250,218 @@ fn build_layout_tree<'a>(styled_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
 
     let mut root = LayoutBox::from_styled(styled_node, box_type);
 
-    // 子要素を処理
+    match box_type {
+        // 置換要素の中身（<option> や <textarea> のテキスト）は部品が描く
+        _ if root.is_replaced() => {}
+        BoxType::Flex | BoxType::InlineFlex => build_flex_items(&mut root, styled_node),
+        BoxType::Table => build_table_parts(&mut root, styled_node),
+        _ => build_children(&mut root, styled_node),
+    }
+
+    root
+}
+
+impl<'a> LayoutBox<'a> {
+    /// 置換要素（`<img>` とフォーム部品）か
+    fn is_replaced(&self) -> bool {
+        self.styled_node
+            .is_some_and(|s| s.node.tag_name() == Some("img") || ControlKind::of(s.node).is_some())
+    }
+
+    /// 画像とフォーム部品の固有サイズを設定
+    fn assign_intrinsic_sizes(&mut self, images: &ImageCache) {
+        if let Some(style) = self.styled_node.filter(|_| self.is_replaced()) {
+            self.intrinsic_size = match style.node.get_attribute("src") {
+                Some(src) if style.node.tag_name() == Some("img") => images.size(src),
+                _ => control_size(style),
+            };
+        }
+        for child in &mut self.children {
+            child.assign_intrinsic_sizes(images);
+        }
+    }
+}
+
+/// 子要素のボックスを構築（インラインの並びは匿名ブロックにまとめる）
+fn build_children<'a>(root: &mut LayoutBox<'a>, styled_node: &'a StyledNode<'a>) {
     for child in &styled_node.children {
         match child.display() {
             Display::None => continue,
-            Display::Block => {
-                root.children.push(build_layout_tree(child));
-            }
-            Display::Inline | Display::InlineBlock => {
+            Display::Inline | Display::InlineBlock | Display::InlineFlex => {
                 let container = root.get_inline_container();
                 container.children.push(build_layout_tree(child));
             }
+            _ => {
+                root.children.push(build_layout_tree(child));
+            }
         }
     }
+}
 
-    root
+/// 表の構造を構築
+///
+/// 子はキャプション、thead、tbody、tfoot の順に並べ替える（tfoot がソース上で先にあっても最後）。
+/// 行グループの外にある行は匿名の行グループに、行の外にあるセルは匿名の行に入れる。
+fn build_table_parts<'a>(table: &mut LayoutBox<'a>, styled_node: &'a StyledNode<'a>) {
+    let mut captions = Vec::new();
+    let mut headers = Vec::new();
+    let mut bodies: Vec<LayoutBox<'a>> = Vec::new();
+    let mut footers = Vec::new();
+
+    for child in &styled_node.children {
+        if is_ignorable(child) {
+            continue;
+        }
+        match child.display() {
+            Display::None => {}
+            Display::TableCaption => {
+                let mut caption = LayoutBox::from_styled(child, BoxType::Block);
+                build_children(&mut caption, child);
+                captions.push(caption);
+            }
+            Display::TableHeaderGroup => headers.push(build_row_group(child)),
+            Display::TableFooterGroup => footers.push(build_row_group(child)),
+            Display::TableRowGroup => bodies.push(build_row_group(child)),
+            display => {
+                // 行グループの外の行・セル
+                let needs_new = match bodies.last() {
+                    Some(last) => last.styled_node.is_some(),
+                    None => true,
+                };
+                if needs_new {
+                    bodies.push(LayoutBox::new(BoxType::TableRowGroup));
+                }
+                if let Some(group) = bodies.last_mut() {
+                    if display == Display::TableRow {
+                        group.children.push(build_row(child));
+                    } else {
+                        push_orphan_cell(group, child);
+                    }
+                }
+            }
+        }
+    }
+
+    table.children.extend(captions);
+    table.children.extend(headers);
+    table.children.extend(bodies);
+    table.children.extend(footers);
+}
+
+/// 行グループを構築
+fn build_row_group<'a>(styled_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    let mut group = LayoutBox::from_styled(styled_node, BoxType::TableRowGroup);
+    for child in &styled_node.children {
+        if is_ignorable(child) || child.display() == Display::None {
+            continue;
+        }
+        if child.display() == Display::TableRow {
+            group.children.push(build_row(child));
+        } else {
+            push_orphan_cell(&mut group, child);
+        }
+    }
+    group
+}
+
+/// 行を構築（セル以外の子は匿名のセルに入れる）
+fn build_row<'a>(styled_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    let mut row = LayoutBox::from_styled(styled_node, BoxType::TableRow);
+    for child in &styled_node.children {
+        if is_ignorable(child) || child.display() == Display::None {
+            continue;
+        }
+        if child.display() == Display::TableCell {
+            row.children.push(build_cell(child));
+        } else {
+            let needs_new = match row.children.last() {
+                Some(last) => last.styled_node.is_some(),
+                None => true,
+            };
+            if needs_new {
+                row.children.push(LayoutBox::new(BoxType::TableCell));
+            }
+            if let Some(cell) = row.children.last_mut() {
+                cell.children.push(build_layout_tree(child));
+            }
+        }
+    }
+    row
+}
+
+/// セルを構築（中身は通常のブロックと同じ）
+fn build_cell<'a>(styled_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
+    let mut cell = LayoutBox::from_styled(styled_node, BoxType::TableCell);
+    build_children(&mut cell, styled_node);
+    cell
+}
+
+/// 行の外にあるセル（またはその他の内容）を、グループ末尾の匿名の行に追加
+fn push_orphan_cell<'a>(group: &mut LayoutBox<'a>, styled_node: &'a StyledNode<'a>) {
+    let needs_new = match group.children.last() {
+        Some(last) => last.styled_node.is_some(),
+        None => true,
+    };
+    if needs_new {
+        group.children.push(LayoutBox::new(BoxType::TableRow));
+    }
+    if let Some(row) = group.children.last_mut() {
+        if styled_node.display() == Display::TableCell {
+            row.children.push(build_cell(styled_node));
+        } else {
+            let mut cell = LayoutBox::new(BoxType::TableCell);
+            cell.children.push(build_layout_tree(styled_node));
+            row.children.push(cell);
+        }
+    }
+}
+
+/// 表の構造の中で無視するノード（空白だけのテキストとコメント）
+fn is_ignorable(styled_node: &StyledNode) -> bool {
+    match &styled_node.node.node_type {
+        NodeType::Text(text) => text.trim().is_empty(),
+        NodeType::Element(_) => false,
+        _ => true,
+    }
+}
+
+/// フレックスアイテムを構築
+///
+/// 子要素はブロック化し（inline → block, inline-flex → flex）、
+/// 連続するテキストは 1 つの匿名ブロックにまとめる。空白だけのテキストは無視する。
+fn build_flex_items<'a>(container: &mut LayoutBox<'a>, styled_node: &'a StyledNode<'a>) {
+    for child in &styled_node.children {
+        match &child.node.node_type {
+            NodeType::Text(text) => {
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let needs_new = match container.children.last() {
+                    Some(last) => last.box_type != BoxType::AnonymousBlock,
+                    None => true,
+                };
+                if needs_new {
+                    container.children.push(LayoutBox::new(BoxType::AnonymousBlock));
+                }
+                if let Some(anonymous) = container.children.last_mut() {
+                    anonymous.children.push(build_layout_tree(child));
+                }
+            }
+            NodeType::Element(_) => {
+                if child.display() == Display::None {
+                    continue;
+                }
+                let mut item = build_layout_tree(child);
+                item.box_type = match item.box_type {
+                    BoxType::Inline => BoxType::Block,
+                    BoxType::InlineFlex => BoxType::Flex,
+                    other => other,
+                };
+                container.children.push(item);
+            }
+            _ => {}
+        }
+    }
 }
 
 // ============================================================================
@@ -228,12 +472,23 @@ impl<'a> LayoutBox<'a> {
     /// レイアウトを計算
     pub fn layout(&mut self, containing_block: Dimensions) {
         match self.box_type {
-            BoxType::Block | BoxType::AnonymousBlock => {
+            // 行グループ・行・セルは表のレイアウトが配置する
+            BoxType::Block
+            | BoxType::AnonymousBlock
+            | BoxType::TableRowGroup
+            | BoxType::TableRow
+            | BoxType::TableCell => {
                 self.layout_block(containing_block);
             }
             BoxType::Inline => {
                 self.layout_inline(containing_block);
             }
+            BoxType::Flex | BoxType::InlineFlex => {
+                self.layout_flex(containing_block);
+            }
+            BoxType::Table => {
+                self.layout_table(containing_block);
+            }
         }
     }
 
@@ -283,8 +538,11 @@ impl<'a> LayoutBox<'a> {
             + border_left.to_px()
             + border_right.to_px();
 
-        // width: auto の場合、残りの幅を使用
-        if width == auto {
+        // 置換要素は画像の大きさ、width: auto の場合は残りの幅を使用
+        if self.is_replaced() {
+            let (replaced_width, _) = self.replaced_size(Some(containing_block.content.width));
+            width = Value::Length(replaced_width, Unit::Px);
+        } else if width == auto {
             let remaining = containing_block.content.width - total;
             width = Value::Length(remaining.max(0.0), Unit::Px);
         }
@@ -335,9 +593,11 @@ impl<'a> LayoutBox<'a> {
 
     /// 子要素をレイアウト
     fn layout_block_children(&mut self) {
-        let d = self.dimensions;
+        // 再レイアウトに備えて高さを積み直す
+        self.dimensions.content.height = 0.0;
         for child in &mut self.children {
-            child.layout(d);
+            // 前の兄弟の下に配置するため、毎回現在の高さを渡す
+            child.layout(self.dimensions);
             // 高さを更新
             self.dimensions.content.height =
                 self.dimensions.content.height + child.dimensions.margin_box().height;
@@ -346,6 +606,18 @@ impl<'a> LayoutBox<'a> {
 
     /// ブロックの高さを計算
     fn calculate_block_height(&mut self) {
+        // 置換要素は決まった幅から縦横比を保って高さを決める
+        if self.is_replaced() {
+            let width = self.dimensions.content.width;
+            self.dimensions.content.height = match (self.specified_px("height", None), self.intrinsic_size) {
+                (Some(height), _) => height,
+                (None, Some((w, h))) if w > 0.0 && self.has_intrinsic_ratio() => width * h / w,
+                (None, Some((_, h))) => h,
+                _ => 0.0,
+            };
+            return;
+        }
+
         // 明示的な height が指定されていれば使用
         if let Some(style) = self.styled_node {
             if let Some(Value::Length(h, _)) = style.value("height") {
@@ -363,14 +635,28 @@ impl<'a> LayoutBox<'a> {
             }
         };
 
+        // 置換要素は上下の余白も含めて行の中に置く
+        if self.is_replaced() {
+            let (margin, border, padding) = self.specified_edges();
+            let (width, height) = self.replaced_size(Some(containing_block.content.width));
+            let d = &mut self.dimensions;
+            d.margin = margin;
+            d.border = border;
+            d.padding = padding;
+            d.content.width = width;
+            d.content.height = height;
+            d.content.x = containing_block.content.x + margin.left + border.left + padding.left;
+            d.content.y = containing_block.content.y
+                + containing_block.content.height
+                + margin.top
+                + border.top
+                + padding.top;
+            return;
+        }
+
         // テキストノードの場合
         if let NodeType::Text(text) = &style.node.node_type {
-            let font_size = style.length_px("font-size").max(16.0);
-            let line_height = font_size * 1.2;
-
-            // 文字幅を計算（簡易）
-            let char_width = font_size * 0.6;
-            let text_width = text.len() as f32 * char_width;
+            let (text_width, line_height) = text_size(style, text);
 
             self.dimensions.content.width = text_width.min(containing_block.content.width);
             self.dimensions.content.height = line_height;
@@ -403,6 +689,174 @@ impl<'a> LayoutBox<'a> {
     }
 }
 
+// ============================================================================
+// Intrinsic Sizes
+// ============================================================================
+
+impl<'a> LayoutBox<'a> {
+    /// 内容を折り返さずに並べたときの幅（max-content、コンテンツ領域）
+    fn max_content_width(&self) -> f32 {
+        if self.is_replaced() {
+            return self.replaced_size(None).0;
+        }
+        if let Some(style) = self.styled_node {
+            if let NodeType::Text(text) = &style.node.node_type {
+                return text_size(style, text).0;
+            }
+            if let Some(width) = self.specified_px("width", None) {
+                return width;
+            }
+        }
+
+        let outer = |child: &LayoutBox| child.max_content_width() + child.specified_horizontal_edges();
+        let row_gap = self
+            .styled_node
+            .filter(|s| s.flex_direction().is_row())
+            .map(|s| s.length_px("column-gap"));
+
+        match (self.box_type, row_gap) {
+            // インラインの内容、行方向のフレックスアイテム、表のセルは横に並ぶ
+            (BoxType::Inline | BoxType::TableRow, _) => self.children.iter().map(outer).sum(),
+            (BoxType::Flex | BoxType::InlineFlex, Some(gap)) => {
+                let gaps = gap * self.children.len().saturating_sub(1) as f32;
+                self.children.iter().map(outer).sum::<f32>() + gaps
+            }
+            _ => self.children.iter().map(outer).fold(0.0, f32::max),
+        }
+    }
+
+    /// 折り返せるところですべて折り返したときの幅（min-content、コンテンツ領域）
+    fn min_content_width(&self) -> f32 {
+        if self.is_replaced() {
+            return self.replaced_size(None).0;
+        }
+        if let Some(style) = self.styled_node {
+            if let NodeType::Text(text) = &style.node.node_type {
+                // 最も長い単語
+                return text
+                    .split_whitespace()
+                    .map(|word| text_size(style, word).0)
+                    .fold(0.0, f32::max);
+            }
+            if let Some(width) = self.specified_px("width", None) {
+                return width;
+            }
+        }
+
+        let outer = |child: &LayoutBox| child.min_content_width() + child.specified_horizontal_edges();
+        match self.box_type {
+            BoxType::TableRow => self.children.iter().map(outer).sum(),
+            _ => self.children.iter().map(outer).fold(0.0, f32::max),
+        }
+    }
+
+    /// 置換要素のコンテンツ領域の幅と高さ
+    ///
+    /// width / height の片方だけが指定されていれば、もう片方は固有の縦横比から決める
+    /// （フォーム部品は縦横比を保たず、固有サイズのまま）。
+    /// 画像が読み込まれていなければ、指定のない方は 0。
+    fn replaced_size(&self, containing_width: Option<f32>) -> (f32, f32) {
+        let width = self.specified_px("width", containing_width);
+        let height = self.specified_px("height", None);
+        match (width, height, self.intrinsic_size) {
+            (Some(w), Some(h), _) => (w, h),
+            (Some(w), None, Some((_, ih))) if !self.has_intrinsic_ratio() => (w, ih),
+            (None, Some(h), Some((iw, _))) if !self.has_intrinsic_ratio() => (iw, h),
+            (Some(w), None, Some((iw, ih))) if iw > 0.0 => (w, w * ih / iw),
+            (None, Some(h), Some((iw, ih))) if ih > 0.0 => (h * iw / ih, h),
+            (None, None, Some(size)) => size,
+            (w, h, _) => (w.unwrap_or(0.0), h.unwrap_or(0.0)),
+        }
+    }
+
+    /// 固有の縦横比を持つか（画像のみ）
+    fn has_intrinsic_ratio(&self) -> bool {
+        self.styled_node.and_then(|s| s.node.tag_name()) == Some("img")
+    }
+
+    /// スタイルで指定された左右の margin + border + padding
+    fn specified_horizontal_edges(&self) -> f32 {
+        let (margin, border, padding) = self.specified_edges();
+        margin.left + margin.right + border.left + border.right + padding.left + padding.right
+    }
+
+    /// スタイルで指定された margin, border, padding
+    fn specified_edges(&self) -> (EdgeSizes, EdgeSizes, EdgeSizes) {
+        let Some(style) = self.styled_node else {
+            return Default::default();
+        };
+        // テキストノードは親のスタイルを継承しているだけなので余白を持たない
+        if let NodeType::Text(_) = style.node.node_type {
+            return Default::default();
+        }
+
+        let zero = Value::Length(0.0, Unit::Px);
+        let edges = |prefix: &str, suffix: &str| {
+            let side = |name: &str| {
+                let longhand = alloc::format!("{}-{}{}", prefix, name, suffix);
+                let shorthand = alloc::format!("{}{}", prefix, suffix);
+                style.lookup(&longhand, &shorthand, &zero).to_px()
+            };
+            EdgeSizes::new(side("top"), side("right"), side("bottom"), side("left"))
+        };
+        (edges("margin", ""), edges("border", "-width"), edges("padding", ""))
+    }
+
+    /// width / height などの指定をピクセルで取得（auto や未指定は None）
+    ///
+    /// パーセントは `base` があればその割合、なければ None。
+    fn specified_px(&self, name: &str, base: Option<f32>) -> Option<f32> {
+        let value = self.styled_node?.value(name)?;
+        resolve_length(value, base)
+    }
+}
+
+/// 長さの値をピクセルに変換（auto などのキーワードは None）
+fn resolve_length(value: &Value, base: Option<f32>) -> Option<f32> {
+    match value {
+        Value::Length(..) | Value::Number(_) => Some(value.to_px()),
+        Value::Percentage(p) => base.map(|base| base * p / 100.0),
+        _ => None,
+    }
+}
+
+/// フォーム部品の固有サイズ（コンテンツ領域）
+///
+/// テキスト欄は size（既定 20）文字、`<textarea>` は cols × rows（既定 20 × 2）、
+/// ボタンはラベル、`<select>` は最も長い選択肢と開閉の印の幅。
+fn control_size(style: &StyledNode) -> Option<(f32, f32)> {
+    let node = style.node;
+    let (char_width, line_height) = text_size(style, "x");
+    let count = |name: &str, default: usize| {
+        node.get_attribute(name)
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(default) as f32
+    };
+    let size = match ControlKind::of(node)? {
+        ControlKind::Text | ControlKind::Password => (count("size", 20) * char_width, line_height),
+        ControlKind::TextArea => (count("cols", 20) * char_width, count("rows", 2) * line_height),
+        ControlKind::Checkbox | ControlKind::Radio => (13.0, 13.0),
+        ControlKind::Submit | ControlKind::Button => text_size(style, &forms::display_text(node)),
+        ControlKind::Select => {
+            let widest = forms::options(node)
+                .iter()
+                .map(|option| text_size(style, &forms::option_label(option)).0)
+                .fold(0.0, f32::max);
+            (widest + char_width * 2.0, line_height)
+        }
+        ControlKind::Hidden => (0.0, 0.0),
+    };
+    Some(size)
+}
+
+/// テキストの幅と行の高さ（簡易: 文字幅はフォントサイズの 0.6 倍）
+fn text_size(style: &StyledNode, text: &str) -> (f32, f32) {
+    let font_size = style.length_px("font-size").max(16.0);
+    let char_width = font_size * 0.6;
+    (text.len() as f32 * char_width, font_size * 1.2)
+}
+
 // ============================================================================
 // Unit Tests
 // ============================================================================
@@ -410,6 +864,82 @@ impl<'a> LayoutBox<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::css::CssParser;
+    use super::super::html::HtmlParser;
+    use super::super::style::style_tree;
+    use crate::graphics::image::Image;
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    /// レイアウトツリーを "タグ x,y wxh" の行に書き出す（ボーダーボックス）
+    fn dump(layout: &LayoutBox, depth: usize, out: &mut String) {
+        let name = match layout.styled_node.map(|s| &s.node.node_type) {
+            Some(NodeType::Element(elem)) => {
+                let mut name = elem.tag_name.clone();
+                if let Some(id) = elem.attributes.get("id") {
+                    name.push('#');
+                    name.push_str(id);
+                }
+                name
+            }
+            Some(NodeType::Text(_)) => String::from("#text"),
+            Some(_) => String::from("#document"),
+            None => String::from("#anonymous"),
+        };
+        let b = layout.dimensions.border_box();
+        let _ = writeln!(out, "{}{} {},{} {}x{}", "  ".repeat(depth), name, b.x, b.y, b.width, b.height);
+        for child in &layout.children {
+            dump(child, depth + 1, out);
+        }
+    }
+
+    /// 幅 800px のビューポートでレイアウトし、body 以下を書き出す
+    pub(super) fn render(html: &str, css: &str) -> String {
+        render_with_images(html, css, &ImageCache::default())
+    }
+
+    /// 読み込み済みの画像を使って `render` する
+    fn render_with_images(html: &str, css: &str, images: &ImageCache) -> String {
+        let dom = HtmlParser::parse(html);
+        let stylesheet = CssParser::parse(css);
+        let styled = style_tree(&dom, &stylesheet);
+        let mut viewport = Dimensions::default();
+        viewport.content.width = 800.0;
+        let layout = layout_tree_with(&styled, viewport, images);
+
+        let mut out = String::new();
+        if let Some(body) = find_body(&layout) {
+            for child in &body.children {
+                dump(child, 0, &mut out);
+            }
+        }
+        out
+    }
+
+    fn find_body<'a, 'b>(layout: &'b LayoutBox<'a>) -> Option<&'b LayoutBox<'a>> {
+        if layout.styled_node.and_then(|s| s.node.tag_name()) == Some("body") {
+            return Some(layout);
+        }
+        layout.children.iter().find_map(find_body)
+    }
+
+    /// 期待値の共通インデントを取り除く
+    pub(super) fn golden(expected: &str) -> String {
+        let lines: Vec<&str> = expected.lines().filter(|line| !line.trim().is_empty()).collect();
+        let indent = lines
+            .iter()
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        lines
+            .iter()
+            .map(|line| &line[indent..])
+            .fold(String::new(), |mut out, line| {
+                out.push_str(line);
+                out.push('\n');
+                out
+            })
+    }
 
     #[test]
     fn test_rect() {
@@ -430,4 +960,59 @@ mod tests {
         assert_eq!(border_box.width, 122.0); // 100 + 10*2 + 1*2
         assert_eq!(border_box.height, 72.0); // 50 + 10*2 + 1*2
     }
+
+    #[test]
+    fn test_replaced_elements() {
+        let mut images = ImageCache::default();
+        images.insert("a.png".into(), Image::new(40, 20));
+
+        let html = concat!(
+            r#"<img id="a" src="a.png"><img id="b" src="a.png" width="20">"#,
+            r#"<img id="c" src="a.png" style="height: 40px">"#,
+            r#"<img id="d" src="missing.png" width="10" height="5"><img id="e" src="missing.png">"#,
+            r#"<div id="f"><img id="g" src="a.png"></div>"#,
+        );
+        let css = "#a { margin: 1px } #g { display: block; width: 50%; padding: 2px }";
+        assert_eq!(
+            render_with_images(html, css, &images),
+            golden(
+                "
+                #anonymous 0,0 800x77
+                  img#a 1,1 40x20
+                  img#b 0,22 20x10
+                  img#c 0,32 80x40
+                  img#d 0,72 10x5
+                  img#e 0,77 0x0
+                div#f 0,77 800x204
+                  img#g 0,77 404x204
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_form_controls() {
+        // 文字幅 9.6px、行の高さ 19.2px
+        let html = concat!(
+            r#"<form><input id="t" size="10"><input id="c" type="checkbox"><input id="w" style="width: 50px">"#,
+            r#"<textarea id="a" cols="5" rows="2">hello</textarea><select id="s"><option>a<option>longer</select>"#,
+            r#"<button id="b"><b>Go</b></button></form>"#,
+        );
+        let css = "#t { padding: 2px; border: 1px solid black }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                form 0,0 800x134.2
+                  #anonymous 0,0 800x134.2
+                    input#t 0,0 102x25.2
+                    input#c 0,25.2 13x13
+                    input#w 0,38.2 50x19.2
+                    textarea#a 0,57.4 48x38.4
+                    select#s 0,95.8 76.8x19.2
+                    button#b 0,115 19.2x19.2
+                "
+            )
+        );
+    }
 }
@@ -0,0 +1,587 @@
+// ============================================================================
+// src/application/browser/layout/flex.rs - Flexbox Layout
+// ============================================================================
+//!
+//! # Flexbox レイアウト
+//!
+//! `display: flex` / `inline-flex` のコンテナの子（フレックスアイテム）を配置する。
+//!
+//! 1. 各アイテムの flex base size（主軸方向の仮のサイズ）を決める
+//! 2. flex-wrap に従ってアイテムを行に分ける
+//! 3. 行ごとに余り（不足）を flex-grow（flex-shrink）で配分する
+//! 4. justify-content で主軸方向、align-items / align-self で交差軸方向に配置する
+//!
+//! min-width / max-width（最小コンテンツサイズによる縮小の制限を含む）、
+//! order、align-content、auto マージンは未対応。行は交差軸の先頭から詰めて並べる。
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use super::super::style::{AlignItems, FlexWrap, JustifyContent, StyledNode};
+use super::{resolve_length, BoxType, Dimensions, LayoutBox};
+
+// ============================================================================
+// Flex Item
+// ============================================================================
+
+/// 配置計算中のフレックスアイテム
+///
+/// サイズはすべてコンテンツ領域。`*_edges` は margin + border + padding。
+#[derive(Debug, Clone, Copy)]
+struct FlexItem {
+    grow: f32,
+    shrink: f32,
+    /// flex base size
+    base: f32,
+    /// 確定した主軸サイズ
+    main: f32,
+    /// 交差軸サイズ
+    cross: f32,
+    main_edges: f32,
+    cross_edges: f32,
+    align: AlignItems,
+    /// 交差軸のサイズ指定が auto（stretch の対象）
+    cross_auto: bool,
+    /// 配置位置（コンテナのコンテンツ領域からのマージンボックスの位置）
+    main_pos: f32,
+    cross_pos: f32,
+}
+
+impl FlexItem {
+    fn outer_base(&self) -> f32 {
+        self.base + self.main_edges
+    }
+
+    fn outer_main(&self) -> f32 {
+        self.main + self.main_edges
+    }
+
+    fn outer_cross(&self) -> f32 {
+        self.cross + self.cross_edges
+    }
+}
+
+/// アイテムの行（`items` の範囲と交差軸サイズ）
+#[derive(Debug, Clone, Copy)]
+struct FlexLine {
+    start: usize,
+    end: usize,
+    cross: f32,
+}
+
+// ============================================================================
+// Flex Container Layout
+// ============================================================================
+
+impl<'a> LayoutBox<'a> {
+    /// フレックスコンテナのレイアウト
+    pub(super) fn layout_flex(&mut self, containing_block: Dimensions) {
+        self.calculate_block_width(containing_block);
+
+        // inline-flex で width: auto なら内容に合わせる（利用可能な幅が上限）
+        if self.box_type == BoxType::InlineFlex && self.specified_px("width", None).is_none() {
+            self.dimensions.content.width =
+                self.max_content_width().min(self.dimensions.content.width);
+        }
+
+        self.calculate_block_position(containing_block);
+
+        let height = self.specified_px("height", None);
+        self.layout_flex_children(height);
+        if let Some(height) = height {
+            self.dimensions.content.height = height;
+        }
+    }
+
+    /// フレックスアイテムを配置し、コンテナの高さを決める
+    ///
+    /// `definite_height`: コンテナの高さが確定している場合その値（column の主軸サイズ）
+    fn layout_flex_children(&mut self, definite_height: Option<f32>) {
+        let Some(style) = self.styled_node else {
+            self.layout_block_children();
+            return;
+        };
+
+        let direction = style.flex_direction();
+        let wrap = style.flex_wrap();
+        let justify = style.justify_content();
+        let align_items = style.align_items();
+        let is_row = direction.is_row();
+
+        let width = self.dimensions.content.width;
+        let (main_gap, cross_gap) = if is_row {
+            (style.length_px("column-gap"), style.length_px("row-gap"))
+        } else {
+            (style.length_px("row-gap"), style.length_px("column-gap"))
+        };
+        let (main_size, cross_size) = if is_row {
+            (Some(width), definite_height)
+        } else {
+            (definite_height, Some(width))
+        };
+
+        // 1. flex base size
+        let mut items: Vec<FlexItem> = self
+            .children
+            .iter_mut()
+            .map(|child| child.flex_item(is_row, main_size, width, align_items))
+            .collect();
+
+        // 2. 行に分ける
+        let mut lines = collect_lines(&items, wrap, main_size, main_gap);
+
+        // 3. 主軸サイズを確定し、交差軸サイズを求める
+        for line in &mut lines {
+            let line_items = &mut items[line.start..line.end];
+            resolve_flexible_lengths(line_items, main_size, main_gap);
+
+            for (item, child) in line_items.iter_mut().zip(&mut self.children[line.start..line.end]) {
+                if is_row {
+                    // 幅を確定させて内容の高さを測る
+                    child.layout_flex_item(0.0, 0.0, item.main, None);
+                    item.cross = child.dimensions.content.height;
+                }
+            }
+
+            line.cross = match cross_size {
+                Some(cross) if lines_single(wrap, main_size) => cross,
+                _ => line_items.iter().map(FlexItem::outer_cross).fold(0.0, f32::max),
+            };
+
+            for item in line_items.iter_mut() {
+                if item.align == AlignItems::Stretch && item.cross_auto {
+                    item.cross = (line.cross - item.cross_edges).max(0.0);
+                }
+            }
+        }
+
+        // 4. 主軸方向の配置
+        let mut used_main = 0.0f32;
+        for line in &lines {
+            let line_items = &mut items[line.start..line.end];
+            used_main = used_main.max(justify_line(line_items, main_size, main_gap, justify));
+        }
+        let container_main = main_size.unwrap_or(used_main);
+
+        // 5. 交差軸方向の配置
+        let mut line_pos = 0.0f32;
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                line_pos += cross_gap;
+            }
+            for item in &mut items[line.start..line.end] {
+                let free = line.cross - item.outer_cross();
+                item.cross_pos = line_pos
+                    + match item.align {
+                        AlignItems::FlexEnd => free,
+                        AlignItems::Center => free / 2.0,
+                        AlignItems::FlexStart | AlignItems::Baseline | AlignItems::Stretch => 0.0,
+                    };
+            }
+            line_pos += line.cross;
+        }
+        let container_cross = cross_size.unwrap_or(line_pos);
+
+        // 6. 逆方向の指定は位置を反転し、アイテムをレイアウト
+        let origin = self.dimensions.content;
+        for (item, child) in items.iter_mut().zip(&mut self.children) {
+            if direction.is_reverse() {
+                item.main_pos = container_main - item.main_pos - item.outer_main();
+            }
+            if wrap == FlexWrap::WrapReverse {
+                item.cross_pos = container_cross - item.cross_pos - item.outer_cross();
+            }
+            if is_row {
+                child.layout_flex_item(
+                    origin.x + item.main_pos,
+                    origin.y + item.cross_pos,
+                    item.main,
+                    Some(item.cross),
+                );
+            } else {
+                child.layout_flex_item(
+                    origin.x + item.cross_pos,
+                    origin.y + item.main_pos,
+                    item.cross,
+                    Some(item.main),
+                );
+            }
+        }
+
+        self.dimensions.content.height = if is_row { container_cross } else { container_main };
+    }
+
+    /// アイテムの flex base size と余白を求める
+    fn flex_item(
+        &mut self,
+        is_row: bool,
+        main_size: Option<f32>,
+        container_width: f32,
+        align_items: AlignItems,
+    ) -> FlexItem {
+        let (margin, border, padding) = self.specified_edges();
+        self.dimensions.margin = margin;
+        self.dimensions.border = border;
+        self.dimensions.padding = padding;
+        let horizontal = margin.left + margin.right + border.left + border.right
+            + padding.left + padding.right;
+        let vertical = margin.top + margin.bottom + border.top + border.bottom
+            + padding.top + padding.bottom;
+
+        let style = self.styled_node;
+        let align = style
+            .and_then(StyledNode::align_self)
+            .unwrap_or(align_items);
+        let (main_prop, cross_prop) = if is_row { ("width", "height") } else { ("height", "width") };
+        let cross_specified = self.specified_px(cross_prop, (!is_row).then_some(container_width));
+
+        let mut item = FlexItem {
+            grow: style.map(StyledNode::flex_grow).unwrap_or(0.0),
+            shrink: style.map(StyledNode::flex_shrink).unwrap_or(1.0),
+            base: 0.0,
+            main: 0.0,
+            cross: cross_specified.unwrap_or(0.0),
+            main_edges: if is_row { horizontal } else { vertical },
+            cross_edges: if is_row { vertical } else { horizontal },
+            align,
+            cross_auto: cross_specified.is_none(),
+            main_pos: 0.0,
+            cross_pos: 0.0,
+        };
+
+        // column の幅（交差軸サイズ）は高さを測る前に決める
+        if !is_row && item.cross_auto {
+            let available = (container_width - horizontal).max(0.0);
+            item.cross = if align == AlignItems::Stretch {
+                available
+            } else {
+                self.max_content_width().min(available)
+            };
+        }
+
+        // flex-basis → 主軸方向のサイズ指定 → 内容のサイズ
+        let basis = match style.and_then(|s| s.value("flex-basis")) {
+            Some(value) => resolve_length(value, main_size),
+            None => None,
+        };
+        item.base = match basis.or_else(|| self.specified_px(main_prop, main_size)) {
+            Some(size) => size,
+            None if is_row => self.max_content_width(),
+            None => {
+                self.layout_flex_item(0.0, 0.0, item.cross, None);
+                self.dimensions.content.height
+            }
+        };
+        item.main = item.base;
+        item
+    }
+
+    /// アイテムを指定の位置とサイズでレイアウト
+    ///
+    /// `x`, `y` はマージンボックスの左上。`height` が None なら内容（または height 指定）に合わせる。
+    fn layout_flex_item(&mut self, x: f32, y: f32, width: f32, height: Option<f32>) {
+        let d = &mut self.dimensions;
+        d.content.x = x + d.margin.left + d.border.left + d.padding.left;
+        d.content.y = y + d.margin.top + d.border.top + d.padding.top;
+        d.content.width = width;
+        d.content.height = 0.0;
+
+        match self.box_type {
+            BoxType::Flex | BoxType::InlineFlex => {
+                let definite = height.or_else(|| self.specified_px("height", None));
+                self.layout_flex_children(definite);
+            }
+            _ => self.layout_block_children(),
+        }
+
+        match height {
+            Some(height) => self.dimensions.content.height = height,
+            None => self.calculate_block_height(),
+        }
+    }
+}
+
+// ============================================================================
+// Flex Algorithm
+// ============================================================================
+
+/// 単一行のコンテナか（折り返さない、または主軸サイズが不定）
+fn lines_single(wrap: FlexWrap, main_size: Option<f32>) -> bool {
+    wrap == FlexWrap::NoWrap || main_size.is_none()
+}
+
+/// アイテムを行に分ける
+fn collect_lines(items: &[FlexItem], wrap: FlexWrap, main_size: Option<f32>, gap: f32) -> Vec<FlexLine> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut used = 0.0f32;
+
+    for (i, item) in items.iter().enumerate() {
+        if let Some(limit) = main_size.filter(|_| !lines_single(wrap, main_size)) {
+            // 先頭以外で収まらなければ改行
+            if i > start && used + gap + item.outer_base() > limit {
+                lines.push(FlexLine { start, end: i, cross: 0.0 });
+                start = i;
+                used = 0.0;
+            }
+        }
+        used += if i > start { gap } else { 0.0 } + item.outer_base();
+    }
+    lines.push(FlexLine { start, end: items.len(), cross: 0.0 });
+    lines
+}
+
+/// 行内の主軸サイズを確定する（flex-grow / flex-shrink）
+///
+/// 0 未満に縮むアイテムは 0 に固定し、残りのアイテムで配分し直す。
+fn resolve_flexible_lengths(items: &mut [FlexItem], main_size: Option<f32>, gap: f32) {
+    let Some(available) = main_size else {
+        return;
+    };
+    let gaps = gap * items.len().saturating_sub(1) as f32;
+    let free = available - gaps - items.iter().map(FlexItem::outer_base).sum::<f32>();
+    let growing = free > 0.0;
+
+    // 伸縮しないアイテムは base のまま固定
+    let mut frozen: Vec<bool> = items
+        .iter()
+        .map(|item| if growing { item.grow == 0.0 } else { item.shrink == 0.0 || item.base == 0.0 })
+        .collect();
+
+    for _ in 0..items.len() {
+        let fixed: f32 = items
+            .iter()
+            .zip(&frozen)
+            .map(|(item, &frozen)| if frozen { item.outer_main() } else { item.outer_base() })
+            .sum();
+        let remaining = available - gaps - fixed;
+        let active = || items.iter().zip(&frozen).filter(|(_, frozen)| !**frozen).map(|(item, _)| *item);
+
+        if growing {
+            let total_grow: f32 = active().map(|item| item.grow).sum();
+            if total_grow <= 0.0 {
+                return;
+            }
+            // 合計が 1 未満なら余りの一部だけを配る
+            let share = if total_grow < 1.0 { remaining * total_grow } else { remaining };
+            for (item, _) in items.iter_mut().zip(&frozen).filter(|(_, frozen)| !**frozen) {
+                item.main = item.base + share * item.grow / total_grow;
+            }
+            return;
+        }
+
+        let total_scaled: f32 = active().map(|item| item.shrink * item.base).sum();
+        if total_scaled <= 0.0 {
+            return;
+        }
+        let mut clamped = false;
+        for (item, frozen) in items.iter_mut().zip(frozen.iter_mut()).filter(|(_, frozen)| !**frozen) {
+            let target = item.base + remaining * item.shrink * item.base / total_scaled;
+            if target < 0.0 {
+                item.main = 0.0;
+                *frozen = true;
+                clamped = true;
+            } else {
+                item.main = target;
+            }
+        }
+        if !clamped {
+            return;
+        }
+    }
+}
+
+/// 行内のアイテムを justify-content に従って主軸方向に並べる
+///
+/// 戻り値: 行が使う主軸方向の長さ
+fn justify_line(items: &mut [FlexItem], main_size: Option<f32>, gap: f32, justify: JustifyContent) -> f32 {
+    let count = items.len() as f32;
+    let used = items.iter().map(FlexItem::outer_main).sum::<f32>() + gap * (count - 1.0).max(0.0);
+    let free = main_size.map(|size| size - used).unwrap_or(0.0);
+
+    // (先頭の余白, アイテム間に追加する余白)
+    let (offset, between) = match justify {
+        JustifyContent::FlexStart => (0.0, 0.0),
+        JustifyContent::FlexEnd => (free, 0.0),
+        JustifyContent::Center => (free / 2.0, 0.0),
+        JustifyContent::SpaceBetween if free > 0.0 && count > 1.0 => (0.0, free / (count - 1.0)),
+        JustifyContent::SpaceBetween => (0.0, 0.0),
+        JustifyContent::SpaceAround if free > 0.0 => (free / count / 2.0, free / count),
+        JustifyContent::SpaceEvenly if free > 0.0 => (free / (count + 1.0), free / (count + 1.0)),
+        // 余りがなければ中央寄せ
+        JustifyContent::SpaceAround | JustifyContent::SpaceEvenly => (free / 2.0, 0.0),
+    };
+
+    let mut pos = offset;
+    for item in items.iter_mut() {
+        item.main_pos = pos;
+        pos += item.outer_main() + gap + between;
+    }
+    used
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{golden, render};
+
+    #[test]
+    fn test_row_grow_and_gap() {
+        let html = r#"<div id="c"><div id="a"></div><div id="b"></div><div id="d"></div></div>"#;
+        let css = "#c { display: flex; gap: 10px; padding: 5px; height: 50px }
+                   #a { width: 100px } #b { flex: 1 } #d { flex: 2; margin-left: 5px; margin-right: 5px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 800x60
+                  div#a 5,5 100x50
+                  div#b 115,5 220x50
+                  div#d 350,5 440x50
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_shrink_and_basis() {
+        let html = r#"<div id="c"><div id="a"></div><div id="b"></div></div>"#;
+        let css = "#c { display: flex; width: 300px }
+                   #a { flex-basis: 300px; height: 10px } #b { flex: 0 3 200px; height: 20px }";
+        // 不足 200px を flex-shrink × base (300 : 600) で配分
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 300x20
+                  div#a 0,0 233.33334x10
+                  div#b 233.33334,0 66.66667x20
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_justify_and_align() {
+        let html = r#"<div id="c"><div id="a"></div><div id="b"></div><div id="d"></div></div>"#;
+        let css = "#c { display: flex; justify-content: space-between; align-items: center; height: 100px }
+                   #a { width: 100px; height: 20px } #b { width: 100px; height: 40px; align-self: flex-end }
+                   #d { width: 100px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 800x100
+                  div#a 0,40 100x20
+                  div#b 350,60 100x40
+                  div#d 700,50 100x0
+                "
+            )
+        );
+
+        let css = "#c { display: flex; justify-content: space-evenly; height: 30px }
+                   #a, #b, #d { width: 140px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 800x30
+                  div#a 95,0 140x30
+                  div#b 330,0 140x30
+                  div#d 565,0 140x30
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let html = r#"<div id="c"><div id="a"></div><div id="b"></div><div id="d"></div></div>"#;
+        let css = "#c { display: flex; flex-wrap: wrap; width: 500px; row-gap: 10px; column-gap: 20px }
+                   #a { width: 200px; height: 30px } #b { width: 250px; height: 50px }
+                   #d { width: 300px; height: 40px; flex-grow: 1 }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 500x100
+                  div#a 0,0 200x30
+                  div#b 220,0 250x50
+                  div#d 0,60 500x40
+                "
+            )
+        );
+
+        let css = "#c { display: flex; flex-wrap: wrap-reverse; width: 500px; align-items: flex-start }
+                   #a { width: 200px; height: 30px } #b { width: 250px; height: 50px }
+                   #d { width: 300px; height: 40px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 500x90
+                  div#a 0,60 200x30
+                  div#b 200,40 250x50
+                  div#d 0,0 300x40
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_column_and_reverse() {
+        let html = r#"<div id="c"><div id="a"></div><div id="b"></div></div>"#;
+        let css = "#c { display: flex; flex-direction: column; height: 200px; align-items: center }
+                   #a { width: 100px; height: 50px } #b { flex-grow: 1; width: 60px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 800x200
+                  div#a 350,0 100x50
+                  div#b 370,50 60x150
+                "
+            )
+        );
+
+        let css = "#c { display: flex; flex-direction: row-reverse; height: 10px }
+                   #a { width: 100px } #b { width: 60px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#c 0,0 800x10
+                  div#a 700,0 100x10
+                  div#b 640,0 60x10
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_content_sized_items() {
+        // テキストは匿名アイテム、span はブロック化される（16px → 文字幅 9.6px, 行の高さ 19.2px）
+        let html = r#"<div id="c">Hi <span>abc</span></div><p id="p">x</p>"#;
+        let css = "#c { display: inline-flex; padding: 2px } span { padding-left: 4px; padding-right: 4px }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                #anonymous 0,0 800x23.2
+                  div#c 0,0 69.600006x23.2
+                    #anonymous 2,2 28.800001x19.2
+                      #text 2,2 28.800001x19.2
+                    span 30.800003,2 36.800003x19.2
+                      #text 34.800003,2 28.800001x19.2
+                p#p 0,39.2 800x19.2
+                  #anonymous 0,39.2 800x19.2
+                    #text 0,39.2 9.6x19.2
+                "
+            )
+        );
+    }
+}
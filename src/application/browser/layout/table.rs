@@ -0,0 +1,680 @@
+// ============================================================================
+// src/application/browser/layout/table.rs - Table Layout
+// ============================================================================
+//!
+//! # 表のレイアウト
+//!
+//! `display: table` のボックスを自動レイアウト（table-layout: auto）で配置する。
+//!
+//! 1. 行グループ・行・セルを走査し、colspan / rowspan を考慮してセルを格子に割り当てる
+//! 2. セルの最小・最大コンテンツ幅から列ごとの最小幅・最大幅を求める
+//! 3. 表の幅に合わせて列幅を決める（余れば最大幅の比で配分、足りなければ最小幅と最大幅の間を補間）
+//! 4. 行の高さを決め、キャプション、行グループ、行、セルの順に配置する
+//!
+//! `border-collapse: collapse` は隣り合うセルの境界線を 1 本にまとめる簡易実装
+//! （太い方を選ぶ解決はせず、左・上側の線を省く）。キャプションは常に表の上に置く。
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::super::css::Value;
+use super::{resolve_length, BoxType, Dimensions, EdgeSizes, LayoutBox, Rect};
+
+/// 既定のセル間隔（border-spacing）
+const DEFAULT_SPACING: f32 = 2.0;
+
+/// 既定のセルの padding（UA スタイルの td, th { padding: 1px } 相当）
+const DEFAULT_CELL_PADDING: f32 = 1.0;
+
+/// colspan / rowspan の上限
+const MAX_SPAN: usize = 1000;
+
+// ============================================================================
+// Table Grid
+// ============================================================================
+
+/// 格子に割り当てたセル
+#[derive(Debug, Clone, Copy)]
+struct GridCell {
+    /// `LayoutBox::children` 内の行グループ・行・セルの位置
+    group: usize,
+    row_index: usize,
+    cell_index: usize,
+    /// 表全体での行番号と列番号
+    row: usize,
+    col: usize,
+    rowspan: usize,
+    colspan: usize,
+    /// ボーダーボックスの最小・最大幅
+    min: f32,
+    max: f32,
+}
+
+/// 表の行
+#[derive(Debug, Clone, Copy)]
+struct GridRow {
+    group: usize,
+    index: usize,
+    height: f32,
+}
+
+/// セルの縦位置（vertical-align）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+// ============================================================================
+// Table Layout
+// ============================================================================
+
+impl<'a> LayoutBox<'a> {
+    /// 表のレイアウト
+    pub(super) fn layout_table(&mut self, containing_block: Dimensions) {
+        self.calculate_block_width(containing_block);
+        self.calculate_block_position(containing_block);
+
+        let Some(style) = self.styled_node else {
+            self.layout_block_children();
+            return;
+        };
+
+        let collapse = matches!(style.value("border-collapse"), Some(Value::Keyword(k)) if k == "collapse");
+        let spacing = if collapse {
+            0.0
+        } else {
+            style
+                .value("border-spacing")
+                .and_then(|value| resolve_length(value, None))
+                .or_else(|| attribute_px(self, "cellspacing"))
+                .unwrap_or(DEFAULT_SPACING)
+        };
+        let cell_padding = attribute_px(self, "cellpadding");
+        let border_attribute = attribute_px(self, "border").is_some_and(|width| width > 0.0);
+        let table_border = self.dimensions.border;
+
+        // 1. セルを格子に割り当てる
+        let (mut rows, mut cells, columns) = self.build_grid();
+        for cell in &mut cells {
+            let last_row = cell.row + cell.rowspan == rows.len();
+            let last_col = cell.col + cell.colspan == columns;
+            let edges = (cell.row == 0, last_col, last_row, cell.col == 0);
+            let cell_box = self.cell_mut(cell);
+            cell_box.prepare_cell(cell_padding, border_attribute, collapse.then_some((table_border, edges)));
+            let horizontal = cell_box.horizontal_edges();
+            cell.min = cell_box.min_content_width() + horizontal;
+            cell.max = cell_box.max_content_width().max(cell.min - horizontal) + horizontal;
+        }
+
+        // 2. 列の最小・最大幅
+        let (min, max) = column_bounds(&cells, columns, spacing);
+
+        // 3. 列幅（表の幅が auto なら内容に合わせて縮める）
+        let available = self.dimensions.content.width;
+        let specified = self.specified_px("width", Some(containing_block.content.width));
+        let total_spacing = if columns > 0 { spacing * (columns + 1) as f32 } else { 0.0 };
+        let target = specified.unwrap_or(available) - total_spacing;
+        let widths = distribute_widths(&min, &max, target, specified.is_some());
+        self.dimensions.content.width = match (columns, specified) {
+            (0, width) => width.unwrap_or(0.0),
+            _ => widths.iter().sum::<f32>() + total_spacing,
+        };
+
+        // 4. キャプション（表の幅で上から積む）
+        self.dimensions.content.height = 0.0;
+        for i in 0..self.children.len() {
+            if self.children[i].box_type == BoxType::Block {
+                let block = self.dimensions;
+                let caption = &mut self.children[i];
+                caption.layout(block);
+                self.dimensions.content.height += caption.dimensions.margin_box().height;
+            }
+        }
+
+        // 5. 行の高さ（まず列幅でセルの内容の高さを測る）
+        let col_x: Vec<f32> = widths
+            .iter()
+            .scan(spacing, |x, width| {
+                let start = *x;
+                *x += width + spacing;
+                Some(start)
+            })
+            .collect();
+        let span_width = |cell: &GridCell| {
+            widths[cell.col..cell.col + cell.colspan].iter().sum::<f32>() + spacing * (cell.colspan - 1) as f32
+        };
+
+        for row in &mut rows {
+            let row_box = &self.children[row.group].children[row.index];
+            row.height = row_box.specified_px("height", None).unwrap_or(0.0);
+        }
+        let mut heights = Vec::with_capacity(cells.len());
+        for cell in &cells {
+            let width = span_width(cell);
+            let cell_box = self.cell_mut(cell);
+            let content_width = (width - cell_box.horizontal_edges()).max(0.0);
+            cell_box.layout_cell(0.0, 0.0, content_width, None);
+            heights.push(cell_box.dimensions.border_box().height);
+        }
+        // 単一行のセル → 行をまたぐセル（不足分を均等に配る）の順
+        for (cell, &height) in cells.iter().zip(&heights) {
+            if cell.rowspan == 1 {
+                rows[cell.row].height = rows[cell.row].height.max(height);
+            }
+        }
+        for (cell, &height) in cells.iter().zip(&heights) {
+            if cell.rowspan > 1 {
+                let spanned = &mut rows[cell.row..cell.row + cell.rowspan];
+                let current = spanned.iter().map(|row| row.height).sum::<f32>() + spacing * (cell.rowspan - 1) as f32;
+                if height > current {
+                    let extra = (height - current) / cell.rowspan as f32;
+                    for row in spanned {
+                        row.height += extra;
+                    }
+                }
+            }
+        }
+
+        // 6. 行グループと行を配置
+        let x = self.dimensions.content.x;
+        let inner_width = (self.dimensions.content.width - 2.0 * spacing).max(0.0);
+        let mut y = self.dimensions.content.y + self.dimensions.content.height;
+        if !rows.is_empty() {
+            y += spacing;
+        }
+        let mut row_y = Vec::with_capacity(rows.len());
+        for row in &rows {
+            row_y.push(y);
+            let row_box = &mut self.children[row.group].children[row.index];
+            row_box.dimensions = Dimensions::default();
+            row_box.dimensions.content = Rect::new(x + spacing, y, inner_width, row.height);
+            y += row.height + spacing;
+        }
+        for (group, group_box) in self.children.iter_mut().enumerate() {
+            if group_box.box_type != BoxType::TableRowGroup {
+                continue;
+            }
+            let spanned: Vec<(f32, f32)> = rows
+                .iter()
+                .zip(&row_y)
+                .filter(|(row, _)| row.group == group)
+                .map(|(row, &y)| (y, y + row.height))
+                .collect();
+            let (top, bottom) = match (spanned.first(), spanned.last()) {
+                (Some(first), Some(last)) => (first.0, last.1),
+                _ => (y, y),
+            };
+            group_box.dimensions = Dimensions::default();
+            group_box.dimensions.content = Rect::new(x + spacing, top, inner_width, bottom - top);
+        }
+
+        // 7. セルを配置
+        for cell in &cells {
+            let width = span_width(cell);
+            let height = rows[cell.row..cell.row + cell.rowspan].iter().map(|row| row.height).sum::<f32>()
+                + spacing * (cell.rowspan - 1) as f32;
+            let cell_box = self.cell_mut(cell);
+            let d = cell_box.dimensions;
+            let content_width = (width - cell_box.horizontal_edges()).max(0.0);
+            let content_height = (height - d.border.top - d.border.bottom - d.padding.top - d.padding.bottom).max(0.0);
+            cell_box.layout_cell(x + col_x[cell.col], row_y[cell.row], content_width, Some(content_height));
+        }
+
+        // 8. 表の高さ（height 指定は最小の高さとして扱う）
+        let content_bottom = y - self.dimensions.content.y;
+        self.dimensions.content.height = content_bottom.max(self.dimensions.content.height);
+        if let Some(height) = self.specified_px("height", None) {
+            self.dimensions.content.height = self.dimensions.content.height.max(height);
+        }
+    }
+
+    /// 行グループ・行・セルを走査して格子を作る
+    ///
+    /// 戻り値は (行, セル, 列数)。rowspan は行グループの末尾で打ち切る（rowspan="0" は末尾まで）。
+    fn build_grid(&self) -> (Vec<GridRow>, Vec<GridCell>, usize) {
+        let mut rows = Vec::new();
+        let mut cells = Vec::new();
+        let mut columns = 0;
+
+        for (group, group_box) in self.children.iter().enumerate() {
+            if group_box.box_type != BoxType::TableRowGroup {
+                continue;
+            }
+            let group_rows = group_box.children.len();
+            // 上の行から伸びてきたセルが占める残りの行数（列ごと）
+            let mut occupied: Vec<usize> = Vec::new();
+
+            for (row_index, row_box) in group_box.children.iter().enumerate() {
+                let row = rows.len();
+                rows.push(GridRow { group, index: row_index, height: 0.0 });
+
+                let mut col = 0;
+                for (cell_index, cell_box) in row_box.children.iter().enumerate() {
+                    while occupied.get(col).is_some_and(|&n| n > 0) {
+                        col += 1;
+                    }
+                    let colspan = span_attribute(cell_box, "colspan").unwrap_or(1).clamp(1, MAX_SPAN);
+                    let remaining = group_rows - row_index;
+                    let rowspan = match span_attribute(cell_box, "rowspan") {
+                        Some(0) => remaining,
+                        Some(n) => n.min(remaining),
+                        None => 1,
+                    };
+
+                    if occupied.len() < col + colspan {
+                        occupied.resize(col + colspan, 0);
+                    }
+                    for slot in &mut occupied[col..col + colspan] {
+                        *slot = (*slot).max(rowspan);
+                    }
+                    cells.push(GridCell {
+                        group,
+                        row_index,
+                        cell_index,
+                        row,
+                        col,
+                        rowspan,
+                        colspan,
+                        min: 0.0,
+                        max: 0.0,
+                    });
+                    col += colspan;
+                }
+                columns = columns.max(occupied.len());
+
+                for slot in &mut occupied {
+                    *slot = slot.saturating_sub(1);
+                }
+            }
+        }
+
+        (rows, cells, columns)
+    }
+
+    /// 格子のセルに対応するボックス
+    fn cell_mut(&mut self, cell: &GridCell) -> &mut LayoutBox<'a> {
+        &mut self.children[cell.group].children[cell.row_index].children[cell.cell_index]
+    }
+
+    /// セルの border と padding を決める
+    ///
+    /// padding の指定がなければ表の cellpadding 属性（なければ 1px）。
+    /// 表に border 属性があり、セルに枠線の指定がなければ 1px の枠線を付ける。
+    /// `collapse` は (表の枠線, セルが (上, 右, 下, 左) の端にあるか)。
+    fn prepare_cell(
+        &mut self,
+        cell_padding: Option<f32>,
+        border_attribute: bool,
+        collapse: Option<(EdgeSizes, (bool, bool, bool, bool))>,
+    ) {
+        let Some(style) = self.styled_node else {
+            self.dimensions = Dimensions::default();
+            return;
+        };
+
+        let (_, mut border, mut padding) = self.specified_edges();
+        let has_padding = ["padding", "padding-top", "padding-right", "padding-bottom", "padding-left"]
+            .iter()
+            .any(|name| style.value(name).is_some());
+        if !has_padding {
+            padding = EdgeSizes::uniform(cell_padding.unwrap_or(DEFAULT_CELL_PADDING));
+        }
+        if border_attribute && border.top + border.right + border.bottom + border.left == 0.0 {
+            border = EdgeSizes::uniform(1.0);
+        }
+
+        if let Some((table, (top, right, bottom, left))) = collapse {
+            // 隣のセル（または表）の線と重ならないよう、左と上の線は省く
+            if !left || table.left > 0.0 {
+                border.left = 0.0;
+            }
+            if !top || table.top > 0.0 {
+                border.top = 0.0;
+            }
+            if right && table.right > 0.0 {
+                border.right = 0.0;
+            }
+            if bottom && table.bottom > 0.0 {
+                border.bottom = 0.0;
+            }
+        }
+
+        // セルは margin を持たない
+        self.dimensions = Dimensions { border, padding, ..Dimensions::default() };
+    }
+
+    /// 左右の margin + border + padding（確定済みの値）
+    fn horizontal_edges(&self) -> f32 {
+        let d = &self.dimensions;
+        d.margin.left + d.margin.right + d.border.left + d.border.right + d.padding.left + d.padding.right
+    }
+
+    /// セルを指定の位置とサイズでレイアウト
+    ///
+    /// `x`, `y` はボーダーボックスの左上。`height`（コンテンツ領域）を指定した場合は
+    /// vertical-align に従って内容を縦に寄せる。
+    fn layout_cell(&mut self, x: f32, y: f32, width: f32, height: Option<f32>) {
+        let d = &mut self.dimensions;
+        d.content.x = x + d.border.left + d.padding.left;
+        d.content.y = y + d.border.top + d.padding.top;
+        d.content.width = width;
+        self.layout_block_children();
+
+        let natural = self
+            .dimensions
+            .content
+            .height
+            .max(self.specified_px("height", None).unwrap_or(0.0));
+        let Some(height) = height else {
+            self.dimensions.content.height = natural;
+            return;
+        };
+
+        let content_height = self.dimensions.content.height;
+        let offset = match self.vertical_align() {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => (height - content_height) / 2.0,
+            VerticalAlign::Bottom => height - content_height,
+        };
+        if offset > 0.0 {
+            // 内容をずらしてレイアウトし直す
+            let top = self.dimensions.content.y;
+            self.dimensions.content.y = top + offset;
+            self.layout_block_children();
+            self.dimensions.content.y = top;
+        }
+        self.dimensions.content.height = height;
+    }
+
+    /// セルの vertical-align（既定は middle）
+    fn vertical_align(&self) -> VerticalAlign {
+        match self.styled_node.and_then(|s| s.value("vertical-align")) {
+            Some(Value::Keyword(k)) => match k.as_str() {
+                "top" | "baseline" | "text-top" => VerticalAlign::Top,
+                "bottom" | "text-bottom" => VerticalAlign::Bottom,
+                _ => VerticalAlign::Middle,
+            },
+            _ => VerticalAlign::Middle,
+        }
+    }
+}
+
+// ============================================================================
+// Column Widths
+// ============================================================================
+
+/// 列ごとの最小幅と最大幅
+///
+/// 1 列のセルで初期値を決め、複数列にまたがるセルは足りない分を
+/// 列の最大幅の比（すべて 0 なら均等）で配る。
+fn column_bounds(cells: &[GridCell], columns: usize, spacing: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut min = vec![0.0f32; columns];
+    let mut max = vec![0.0f32; columns];
+
+    for cell in cells.iter().filter(|cell| cell.colspan == 1) {
+        min[cell.col] = min[cell.col].max(cell.min);
+        max[cell.col] = max[cell.col].max(cell.max);
+    }
+
+    let mut spanning: Vec<&GridCell> = cells.iter().filter(|cell| cell.colspan > 1).collect();
+    spanning.sort_by_key(|cell| cell.colspan);
+    for cell in spanning {
+        let range = cell.col..cell.col + cell.colspan;
+        let gaps = spacing * (cell.colspan - 1) as f32;
+        let weights: Vec<f32> = max[range.clone()].to_vec();
+        spread(&mut min[range.clone()], &weights, cell.min - gaps);
+        spread(&mut max[range], &weights, cell.max - gaps);
+    }
+
+    for (max, min) in max.iter_mut().zip(&min) {
+        *max = max.max(*min);
+    }
+    (min, max)
+}
+
+/// `widths` の合計が `needed` に満たなければ、不足分を `weights` の比で足す
+fn spread(widths: &mut [f32], weights: &[f32], needed: f32) {
+    let current: f32 = widths.iter().sum();
+    if needed <= current {
+        return;
+    }
+    let extra = needed - current;
+    let total: f32 = weights.iter().sum();
+    for (width, weight) in widths.iter_mut().zip(weights) {
+        *width += if total > 0.0 {
+            extra * weight / total
+        } else {
+            extra / weights.len() as f32
+        };
+    }
+}
+
+/// 列幅を決める
+///
+/// `target` は列に使える幅。`fill` なら余りも配り切る（width 指定のある表）、
+/// そうでなければ最大幅の合計より広くはしない。最小幅の合計を下回ることはない。
+fn distribute_widths(min: &[f32], max: &[f32], target: f32, fill: bool) -> Vec<f32> {
+    let min_total: f32 = min.iter().sum();
+    let max_total: f32 = max.iter().sum();
+
+    if target <= min_total {
+        return min.to_vec();
+    }
+    if target <= max_total {
+        // 最小幅と最大幅の間を補間
+        let ratio = if max_total > min_total {
+            (target - min_total) / (max_total - min_total)
+        } else {
+            0.0
+        };
+        return min.iter().zip(max).map(|(min, max)| min + (max - min) * ratio).collect();
+    }
+    if !fill {
+        return max.to_vec();
+    }
+
+    // 余りを最大幅の比で配る
+    let extra = target - max_total;
+    let columns = max.len() as f32;
+    max.iter()
+        .map(|width| {
+            if max_total > 0.0 {
+                width + extra * width / max_total
+            } else {
+                width + extra / columns
+            }
+        })
+        .collect()
+}
+
+/// 表の属性をピクセルで取得（cellspacing, cellpadding, border）
+fn attribute_px(table: &LayoutBox, name: &str) -> Option<f32> {
+    let value = table.styled_node?.node.get_attribute(name)?;
+    value.trim().trim_end_matches("px").parse::<f32>().ok().filter(|v| *v >= 0.0)
+}
+
+/// セルの colspan / rowspan 属性
+fn span_attribute(cell: &LayoutBox, name: &str) -> Option<usize> {
+    cell.styled_node?.node.get_attribute(name)?.trim().parse::<usize>().ok()
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{golden, render};
+
+    #[test]
+    fn test_auto_column_widths() {
+        // 幅の指定がなければ内容に合わせて縮み、既定の border-spacing 2px と padding 1px が付く
+        let html = r#"<table id="t"><tr><td id="a">ab</td><td id="b">abcd</td></tr><tr><td id="c">a</td><td></td></tr></table>"#;
+        assert_eq!(
+            render(html, ""),
+            golden(
+                "
+                table#t 0,0 67.600006x48.4
+                  tbody 2,2 63.600006x44.4
+                    tr 2,2 63.600006x21.2
+                      td#a 2,2 21.2x21.2
+                        #anonymous 3,3 19.2x19.2
+                          #text 3,3 19.2x19.2
+                      td#b 25.2,2 40.4x21.2
+                        #anonymous 26.2,3 38.4x19.2
+                          #text 26.2,3 38.4x19.2
+                    tr 2,25.2 63.600006x21.2
+                      td#c 2,25.2 21.2x21.2
+                        #anonymous 3,26.2 19.2x19.2
+                          #text 3,26.2 9.6x19.2
+                      td 25.2,25.2 40.4x21.2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_specified_width() {
+        // 余った幅は列の最大幅の比で配る
+        let html = r#"<table id="t" width="500"><tr><td id="a">ab</td><td id="b">abcdef</td></tr></table>"#;
+        assert_eq!(
+            render(html, ""),
+            golden(
+                "
+                table#t 0,0 500x25.2
+                  tbody 2,2 496x21.2
+                    tr 2,2 496x21.2
+                      td#a 2,2 129.61386x21.2
+                        #anonymous 3,3 127.61386x19.2
+                          #text 3,3 19.2x19.2
+                      td#b 133.61386,2 364.38614x21.2
+                        #anonymous 134.61386,3 362.38614x19.2
+                          #text 134.61386,3 57.600002x19.2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_colspan_and_rowspan() {
+        let html = r#"<table id="t" cellspacing="0" cellpadding="4">
+            <tr><td id="a" colspan="2">abcdefghij</td><td id="b" rowspan="2">x</td></tr>
+            <tr><td id="c">a</td><td id="d">b</td></tr>
+        </table>"#;
+        assert_eq!(
+            render(html, ""),
+            golden(
+                "
+                table#t 0,0 121.6x54.4
+                  tbody 0,0 121.6x54.4
+                    tr 0,0 121.6x27.2
+                      td#a 0,0 104x27.2
+                        #anonymous 4,4 96x19.2
+                          #text 4,4 96x19.2
+                      td#b 104,0 17.6x54.4
+                        #anonymous 108,17.6 9.6x19.2
+                          #text 108,17.6 9.6x19.2
+                    tr 0,27.2 121.6x27.2
+                      td#c 0,27.2 52x27.2
+                        #anonymous 4,31.2 44x19.2
+                          #text 4,31.2 9.6x19.2
+                      td#d 52,27.2 52x27.2
+                        #anonymous 56,31.2 44x19.2
+                          #text 56,31.2 9.6x19.2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_row_groups_and_border_collapse() {
+        // キャプション、thead、tbody、tfoot の順に並び、隣り合う枠線は 1 本になる
+        let html = r#"<table id="t" border="1">
+            <tfoot><tr><td id="f">f</td></tr></tfoot>
+            <thead><tr><th id="h">h</th></tr></thead>
+            <tbody><tr><td id="b">b</td></tr></tbody>
+            <caption id="cap">cap</caption>
+        </table>"#;
+        assert_eq!(
+            render(html, "#t { border-collapse: collapse }"),
+            golden(
+                "
+                table#t 0,0 13.6x86.8
+                  caption#cap 1,1 11.6x19.2
+                    #anonymous 1,1 11.6x19.2
+                      #text 1,1 11.6x19.2
+                  thead 1,20.2 11.6x22.2
+                    tr 1,20.2 11.6x22.2
+                      th#h 1,20.2 11.6x22.2
+                        #anonymous 2,21.2 9.6x19.2
+                          #text 2,21.2 9.6x19.2
+                  tbody 1,42.4 11.6x22.200005
+                    tr 1,42.4 11.6x22.2
+                      td#b 1,42.4 11.6x22.2
+                        #anonymous 2,43.4 9.6x19.2
+                          #text 2,43.4 9.6x19.2
+                  tfoot 1,64.600006 11.6x21.199997
+                    tr 1,64.600006 11.6x21.2
+                      td#f 1,64.600006 11.6x21.2
+                        #anonymous 2,65.600006 9.6x19.2
+                          #text 2,65.600006 9.6x19.2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_anonymous_table_parts() {
+        // display: table-cell だけを並べると、行グループと行は匿名ボックスで補われる
+        let html = r#"<div id="t"><div id="a">a</div><div id="b">b</div></div>"#;
+        let css = "#t { display: table } #a, #b { display: table-cell }";
+        assert_eq!(
+            render(html, css),
+            golden(
+                "
+                div#t 0,0 29.2x25.2
+                  #anonymous 2,2 25.2x21.2
+                    #anonymous 2,2 25.2x21.2
+                      div#a 2,2 11.6x21.2
+                        #anonymous 3,3 9.6x19.2
+                          #text 3,3 9.6x19.2
+                      div#b 15.6,2 11.6x21.2
+                        #anonymous 16.6,3 9.6x19.2
+                          #text 16.6,3 9.6x19.2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_vertical_align() {
+        let html = r#"<table id="t"><tr>
+            <td id="a" style="height: 60px">a</td>
+            <td id="b" style="vertical-align: bottom">b</td>
+            <td id="c">c</td>
+        </tr></table>"#;
+        assert_eq!(
+            render(html, "td { vertical-align: top } #c { vertical-align: middle }"),
+            golden(
+                "
+                table#t 0,0 42.800003x66
+                  tbody 2,2 38.800003x62
+                    tr 2,2 38.800003x62
+                      td#a 2,2 11.6x62
+                        #anonymous 3,3 9.6x19.2
+                          #text 3,3 9.6x19.2
+                      td#b 15.6,2 11.6x62
+                        #anonymous 16.6,43.8 9.6x19.2
+                          #text 16.6,43.8 9.6x19.2
+                      td#c 29.2,2 11.6x62
+                        #anonymous 30.2,23.4 9.6x19.2
+                          #text 30.2,23.4 9.6x19.2
+                "
+            )
+        );
+    }
+}
@@ -0,0 +1,298 @@
+// ============================================================================
+// src/application/browser/loader.rs - Page & Subresource Loader
+// ============================================================================
+//!
+//! # ページローダー
+//!
+//! `net::http` でドキュメントを取得する。`file://` URL は VFS から読む。
+//!
+//! リンクされたサブリソース（`<link rel="stylesheet">`、`<script src>`、`<img src>`、
+//! CSS の `url()`）は文書の表示後に `fetch_subresource` で 1 つずつ取得する。
+//! 取得の失敗はページ全体の失敗にはしない。
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::net::http::{self, Response, Url, UrlError};
+
+use super::css::{Stylesheet, Value};
+use super::dom::Node;
+use super::html::HtmlParser;
+
+/// 1ページあたりのサブリソース取得数の上限
+pub const MAX_SUBRESOURCES: usize = 64;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// サブリソースの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubresourceKind {
+    /// `<link rel="stylesheet" href>`
+    Stylesheet,
+    /// `<script src>`
+    Script,
+    /// `<img src>` と CSS の `url()`
+    Image,
+}
+
+/// 取得済みのサブリソース
+#[derive(Debug, Clone)]
+pub struct Subresource {
+    /// 種類
+    pub kind: SubresourceKind,
+    /// 取得元URL
+    pub url: String,
+    /// Content-Type
+    pub content_type: String,
+    /// 内容
+    pub data: Vec<u8>,
+}
+
+impl Subresource {
+    /// 内容を文字列として取得
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data).into_owned()
+    }
+}
+
+/// 読み込んだページ（サブリソースはまだ取得していない）
+#[derive(Debug, Clone)]
+pub struct LoadedPage {
+    /// 最終URL（リダイレクト後）
+    pub url: Url,
+    /// HTTPステータス
+    pub status: u16,
+    /// 理由句
+    pub reason: String,
+    /// DOMツリー
+    pub dom: Node,
+}
+
+impl LoadedPage {
+    /// 文書の基準URL
+    pub fn base_url(&self) -> Url {
+        base_url(&self.dom, &self.url)
+    }
+
+    /// 取得すべきサブリソース（文書順）
+    pub fn subresource_urls(&self) -> Vec<(SubresourceKind, Url)> {
+        subresource_urls(&self.dom, &self.url)
+    }
+}
+
+// ============================================================================
+// URL handling
+// ============================================================================
+
+/// アドレスバーの入力をURLにする
+///
+/// スキームが無ければ `/` 始まりは file://、それ以外は http:// とみなす
+pub fn resolve_input(input: &str) -> Result<Url, UrlError> {
+    let input = input.trim();
+    if input.contains("://") {
+        Url::parse(input)
+    } else if input.starts_with('/') {
+        Url::parse(&format!("file://{}", input))
+    } else {
+        Url::parse(&format!("http://{}", input))
+    }
+}
+
+/// 文書の基準URL（`<base href>` があればそれを使う）
+pub fn base_url(dom: &Node, url: &Url) -> Url {
+    dom.find_elements_by_tag("base")
+        .first()
+        .and_then(|base| base.get_attribute("href"))
+        .and_then(|href| url.join(href).ok())
+        .unwrap_or_else(|| url.clone())
+}
+
+/// スタイルシート中の `url()` を `base`（スタイルシート自身のURL）からの絶対URLにする
+pub fn resolve_stylesheet_urls(sheet: &mut Stylesheet, base: &Url) {
+    for rule in &mut sheet.rules {
+        for declaration in &mut rule.declarations {
+            if let Value::Url(reference) = &mut declaration.value
+                && let Ok(target) = base.join(reference)
+            {
+                *reference = format!("{}", target);
+            }
+        }
+    }
+}
+
+/// 文書から取得すべきサブリソースを文書順に集める（重複は除く）
+pub fn subresource_urls(dom: &Node, url: &Url) -> Vec<(SubresourceKind, Url)> {
+    let base = base_url(dom, url);
+    let mut found: Vec<(SubresourceKind, Url)> = Vec::new();
+    let mut push = |kind, reference: Option<&str>| {
+        let Some(reference) = reference.filter(|r| !r.trim().is_empty()) else {
+            return;
+        };
+        if let Ok(target) = base.join(reference)
+            && !found.iter().any(|(_, u)| *u == target)
+        {
+            found.push((kind, target));
+        }
+    };
+
+    for link in dom.find_elements_by_tag("link") {
+        let is_stylesheet = link.get_attribute("rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("stylesheet"))
+        });
+        if is_stylesheet {
+            push(SubresourceKind::Stylesheet, link.get_attribute("href"));
+        }
+    }
+    for script in dom.find_elements_by_tag("script") {
+        push(SubresourceKind::Script, script.get_attribute("src"));
+    }
+    for image in dom.find_elements_by_tag("img") {
+        push(SubresourceKind::Image, image.get_attribute("src"));
+    }
+
+    found.truncate(MAX_SUBRESOURCES);
+    found
+}
+
+// ============================================================================
+// Loading
+// ============================================================================
+
+/// HTML以外のレスポンスを表示用のHTMLにする
+fn document_html(response: &Response) -> Result<String, String> {
+    let content_type = response.content_type();
+    if content_type.is_empty() || content_type == "text/html" {
+        return Ok(response.text());
+    }
+    if content_type.starts_with("text/") {
+        let escaped = response
+            .text()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        return Ok(format!("<html><body><pre>{}</pre></body></html>", escaped));
+    }
+    Err(format!("Cannot display {} content", content_type))
+}
+
+/// ページを読み込む
+pub async fn load(url: &Url) -> Result<LoadedPage, String> {
+    load_request(http::Request::get(url.clone())).await
+}
+
+/// リクエスト（フォームの POST など）の結果をページとして読み込む
+pub async fn load_request(request: http::Request) -> Result<LoadedPage, String> {
+    let response = http::send(request)
+        .await
+        .map_err(|e| format!("{}", e))?;
+    let dom = HtmlParser::parse(&document_html(&response)?);
+
+    Ok(LoadedPage {
+        url: response.url,
+        status: response.status,
+        reason: response.reason,
+        dom,
+    })
+}
+
+/// `document` のページから参照されたサブリソースを取得する
+pub async fn fetch_subresource(
+    kind: SubresourceKind,
+    target: &Url,
+    document: &Url,
+) -> Result<Subresource, String> {
+    // ネットワーク上のページからローカルファイルは読ませない
+    if target.scheme == http::Scheme::File && document.scheme != http::Scheme::File {
+        return Err(String::from("Blocked file:// subresource"));
+    }
+    match http::send(http::Request::get(target.clone())).await {
+        Ok(response) if response.is_success() => Ok(Subresource {
+            kind,
+            url: format!("{}", response.url),
+            content_type: response.content_type(),
+            data: response.body,
+        }),
+        Ok(response) => Err(format!("HTTP {}", response.status)),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_resolve_input() {
+        assert_eq!(
+            resolve_input("10.0.2.2:8000/index.html").unwrap().to_string(),
+            "http://10.0.2.2:8000/index.html"
+        );
+        assert_eq!(
+            resolve_input("/www/index.html").unwrap().to_string(),
+            "file:///www/index.html"
+        );
+        assert!(resolve_input("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_subresource_urls() {
+        let html = r#"<html><head>
+            <base href="/site/">
+            <link rel="Stylesheet" href="css/main.css">
+            <link rel="icon" href="favicon.ico">
+            <script src="app.rs"></script>
+            <script>inline();</script>
+        </head><body>
+            <img src="logo.png"><img src="logo.png"><img src="">
+        </body></html>"#;
+        let dom = HtmlParser::parse(html);
+        let url = Url::parse("http://host:8000/index.html").unwrap();
+
+        let found: Vec<(SubresourceKind, String)> = subresource_urls(&dom, &url)
+            .into_iter()
+            .map(|(kind, url)| (kind, url.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (SubresourceKind::Stylesheet, "http://host:8000/site/css/main.css".into()),
+                (SubresourceKind::Script, "http://host:8000/site/app.rs".into()),
+                (SubresourceKind::Image, "http://host:8000/site/logo.png".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_stylesheet_urls() {
+        let mut sheet = super::super::css::CssParser::parse(
+            "div { background: url(../img/bg.png) } p { background-image: url('http://cdn/x.png') }",
+        );
+        resolve_stylesheet_urls(&mut sheet, &Url::parse("http://host/css/main.css").unwrap());
+
+        let urls: Vec<&Value> = sheet
+            .rules
+            .iter()
+            .flat_map(|rule| &rule.declarations)
+            .map(|decl| &decl.value)
+            .filter(|value| matches!(value, Value::Url(_)))
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                &Value::Url("http://host/img/bg.png".into()),
+                &Value::Url("http://cdn/x.png".into()),
+            ]
+        );
+    }
+}
@@ -9,6 +9,8 @@
 //! ## アーキテクチャ
 //!
 //! ```text
+//! URL → [loader.rs] → HTML / CSS / Script / Image (net::http, file://)
+//!                              ↓
 //! HTML String → [html.rs] → DOM Tree
 //!                              ↓
 //! CSS String  → [css.rs]  → Stylesheet
@@ -18,6 +20,12 @@
 //!             [layout.rs] → Layout Tree (positions & sizes)
 //!                              ↓
 //!             [render.rs] → Display Commands → Screen
+//!                              ↑
+//!             [images.rs] → Decoded <img> / background-image (graphics::image)
+//!
+//! io::hid keys → [forms.rs] → Form control values (DOM) / GET・POST submission
+//!                 ↕
+//!         [scripting.rs] → <script type="text/rustscript"> (input events, value / checked)
 //! ```
 //!
 //! ## RustScript
@@ -34,6 +42,10 @@ pub mod style;
 pub mod layout;
 pub mod render;
 pub mod browser;
+pub mod loader;
+pub mod images;
+pub mod forms;
+pub mod scripting;
 pub mod script;
 
 // Re-exports
@@ -5,6 +5,10 @@
 //! # レンダリング
 //!
 //! レイアウトツリーを走査し、描画コマンドを生成。
+//!
+//! 画像（`<img>` と background-image）のコマンドは参照だけを持ち、
+//! 描画時に `ImageCache` から読み込み済みの画像を引く。未読み込みの画像は描かない。
+//! フォーム部品は DOM にある現在の値（`forms`）を文字やチェックの印として描く。
 
 extern crate alloc;
 
@@ -14,6 +18,9 @@ use alloc::vec::Vec;
 use super::layout::{LayoutBox, BoxType, Rect, Dimensions};
 use super::css::{Color, Value};
 use super::dom::NodeType;
+use super::forms::{self, ControlKind};
+use super::images::ImageCache;
+use super::style::BackgroundRepeat;
 
 // ============================================================================
 // Display Commands
@@ -30,6 +37,8 @@ pub enum DisplayCommand {
     Text(String, Color, f32, f32, f32), // text, color, x, y, font_size
     /// 画像を描画
     Image(String, Rect), // src, rect
+    /// 背景画像を描画
+    BackgroundImage(String, Rect, Rect, BackgroundRepeat), // src, area (border box), origin (padding box), repeat
     /// 水平線
     HorizontalRule(Color, f32, f32, f32), // color, x, y, width
 }
@@ -50,8 +59,13 @@ pub fn build_display_list(layout_root: &LayoutBox) -> DisplayList {
 
 /// 単一のレイアウトボックスをレンダリング
 fn render_layout_box(list: &mut DisplayList, layout_box: &LayoutBox) {
-    render_background(list, layout_box);
-    render_borders(list, layout_box);
+    if !is_hidden_empty_cell(layout_box) {
+        render_background(list, layout_box);
+        render_background_image(list, layout_box);
+        render_borders(list, layout_box);
+    }
+    render_image(list, layout_box);
+    render_control(list, layout_box);
     render_text(list, layout_box);
 
     for child in &layout_box.children {
@@ -59,6 +73,27 @@ fn render_layout_box(list: &mut DisplayList, layout_box: &LayoutBox) {
     }
 }
 
+/// `empty-cells: hide` の空のセルか（背景と枠線を描かない）
+fn is_hidden_empty_cell(layout_box: &LayoutBox) -> bool {
+    if layout_box.box_type != BoxType::TableCell {
+        return false;
+    }
+    let hide = layout_box
+        .styled_node
+        .and_then(|s| s.value("empty-cells"))
+        .is_some_and(|v| *v == Value::Keyword("hide".into()));
+    hide && !has_visible_content(layout_box)
+}
+
+/// 空白以外のテキストまたは要素を含むか
+fn has_visible_content(layout_box: &LayoutBox) -> bool {
+    layout_box.children.iter().any(|child| match child.styled_node.map(|s| &s.node.node_type) {
+        Some(NodeType::Text(text)) => !text.trim().is_empty(),
+        Some(_) => true,
+        None => has_visible_content(child),
+    })
+}
+
 /// 背景を描画
 fn render_background(list: &mut DisplayList, layout_box: &LayoutBox) {
     let style = match layout_box.styled_node {
@@ -77,6 +112,96 @@ fn render_background(list: &mut DisplayList, layout_box: &LayoutBox) {
     ));
 }
 
+/// 背景画像を描画（パディングボックスの左上から並べ、ボーダーボックスまで塗る）
+fn render_background_image(list: &mut DisplayList, layout_box: &LayoutBox) {
+    let Some(style) = layout_box.styled_node else {
+        return;
+    };
+    let Some(src) = style.background_image() else {
+        return;
+    };
+
+    list.push(DisplayCommand::BackgroundImage(
+        src.into(),
+        layout_box.dimensions.border_box(),
+        layout_box.dimensions.padding_box(),
+        style.background_repeat(),
+    ));
+}
+
+/// 画像（`<img>`）を描画
+fn render_image(list: &mut DisplayList, layout_box: &LayoutBox) {
+    let Some(style) = layout_box.styled_node else {
+        return;
+    };
+    if style.node.tag_name() != Some("img") {
+        return;
+    }
+    let Some(src) = style.node.get_attribute("src") else {
+        return;
+    };
+
+    list.push(DisplayCommand::Image(src.into(), layout_box.dimensions.content));
+}
+
+/// フォーム部品の中身（値・ラベル・チェックの印）を描画
+fn render_control(list: &mut DisplayList, layout_box: &LayoutBox) {
+    let Some(style) = layout_box.styled_node else {
+        return;
+    };
+    let Some(kind) = ControlKind::of(style.node) else {
+        return;
+    };
+
+    let content = layout_box.dimensions.content;
+    let color = style.color("color").unwrap_or(Color::BLACK);
+    let font_size = style.length_px("font-size").max(16.0);
+    let line_height = font_size * 1.2;
+
+    match kind {
+        ControlKind::Checkbox | ControlKind::Radio => {
+            if forms::is_checked(style.node) {
+                let inset = if kind == ControlKind::Radio { 4.0 } else { 3.0 };
+                let mark = Rect::new(
+                    content.x + inset,
+                    content.y + inset,
+                    (content.width - inset * 2.0).max(1.0),
+                    (content.height - inset * 2.0).max(1.0),
+                );
+                list.push(DisplayCommand::SolidColor(color, mark));
+            }
+        }
+        ControlKind::Hidden => {}
+        _ => {
+            let mut text = forms::display_text(style.node);
+            let mut text_color = color;
+            // 値が空ならプレースホルダーを薄く描く
+            if text.is_empty()
+                && kind.is_text_field()
+                && let Some(placeholder) = style.node.get_attribute("placeholder")
+            {
+                text = placeholder.into();
+                text_color = Color::new(117, 117, 117);
+            }
+            // <textarea> は行ごとに、入りきる行だけ
+            for (i, line) in text.split('\n').enumerate() {
+                let y = content.y + i as f32 * line_height;
+                if i > 0 && y + line_height > content.bottom() {
+                    break;
+                }
+                if !line.is_empty() {
+                    list.push(DisplayCommand::Text(line.into(), text_color, content.x, y, font_size));
+                }
+            }
+            // <select> の開閉の印
+            if kind == ControlKind::Select {
+                let x = content.right() - font_size * 0.6 * 1.5;
+                list.push(DisplayCommand::Text("v".into(), color, x, content.y, font_size));
+            }
+        }
+    }
+}
+
 /// 枠線を描画
 fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
     let style = match layout_box.styled_node {
@@ -201,14 +326,14 @@ use crate::graphics::image::Image;
 use crate::graphics::Color as GraphicsColor;
 
 /// 描画リストを画像にレンダリング
-pub fn paint(display_list: &DisplayList, bounds: Rect, image: &mut Image) {
+pub fn paint(display_list: &DisplayList, bounds: Rect, image: &mut Image, images: &ImageCache) {
     for command in display_list {
-        paint_command(command, bounds, image);
+        paint_command(command, bounds, image, images);
     }
 }
 
 /// 単一コマンドを描画
-fn paint_command(command: &DisplayCommand, _bounds: Rect, image: &mut Image) {
+fn paint_command(command: &DisplayCommand, bounds: Rect, image: &mut Image, images: &ImageCache) {
     match command {
         DisplayCommand::SolidColor(color, rect) => {
             paint_solid_color(image, color, rect);
@@ -219,8 +344,15 @@ fn paint_command(command: &DisplayCommand, _bounds: Rect, image: &mut Image) {
         DisplayCommand::Text(text, color, x, y, font_size) => {
             paint_text(image, text, color, *x, *y, *font_size);
         }
-        DisplayCommand::Image(_src, _rect) => {
-            // 画像レンダリングは未実装
+        DisplayCommand::Image(src, rect) => {
+            if let Some(source) = images.get(src) {
+                paint_image(image, source, *rect, bounds);
+            }
+        }
+        DisplayCommand::BackgroundImage(src, area, origin, repeat) => {
+            if let Some(source) = images.get(src) {
+                paint_background_image(image, source, *area, *origin, *repeat, bounds);
+            }
         }
         DisplayCommand::HorizontalRule(color, x, y, width) => {
             paint_horizontal_rule(image, color, *x, *y, *width);
@@ -228,6 +360,78 @@ fn paint_command(command: &DisplayCommand, _bounds: Rect, image: &mut Image) {
     }
 }
 
+/// 画像を矩形の大きさに拡大縮小して描画（最近傍補間、`clip` の外は描かない）
+pub fn paint_image(target: &mut Image, source: &Image, rect: Rect, clip: Rect) {
+    if rect.width <= 0.0 || rect.height <= 0.0 || source.width() == 0 || source.height() == 0 {
+        return;
+    }
+    let visible = intersection(rect, clip);
+    let x0 = visible.x.max(0.0) as u32;
+    let y0 = visible.y.max(0.0) as u32;
+    let x1 = visible.right().min(target.width() as f32).max(0.0) as u32;
+    let y1 = visible.bottom().min(target.height() as f32).max(0.0) as u32;
+
+    let scale_x = source.width() as f32 / rect.width;
+    let scale_y = source.height() as f32 / rect.height;
+    for y in y0..y1 {
+        let sy = ((y as f32 + 0.5 - rect.y) * scale_y) as u32;
+        for x in x0..x1 {
+            let sx = ((x as f32 + 0.5 - rect.x) * scale_x) as u32;
+            let color = source.get_pixel(sx.min(source.width() - 1), sy.min(source.height() - 1));
+            if color.alpha > 0 {
+                target.blend_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// 背景画像を `origin` の左上を基準に並べ、`area` の中に描画（`clip` の外は描かない）
+pub fn paint_background_image(
+    target: &mut Image,
+    source: &Image,
+    area: Rect,
+    origin: Rect,
+    repeat: BackgroundRepeat,
+    clip: Rect,
+) {
+    let (width, height) = (source.width() as f32, source.height() as f32);
+    if width == 0.0 || height == 0.0 {
+        return;
+    }
+    // 繰り返す方向は area の端から端まで、繰り返さない方向は 1 枚だけ
+    let tiles = |start: f32, area_start: f32, area_end: f32, size: f32, repeats: bool| {
+        if repeats {
+            // area の左上を覆う最初のタイル（余分な 1 枚はクリップされる）
+            let before = ((start - area_start) / size) as i32 + 1;
+            (start - before as f32 * size, area_end)
+        } else {
+            (start, start + size)
+        }
+    };
+    let (x_start, x_end) = tiles(origin.x, area.x, area.right(), width, repeat.repeats_x());
+    let (y_start, y_end) = tiles(origin.y, area.y, area.bottom(), height, repeat.repeats_y());
+    let clip = intersection(area, clip);
+
+    let mut y = y_start;
+    while y < y_end {
+        let mut x = x_start;
+        while x < x_end {
+            paint_image(target, source, Rect::new(x, y, width, height), clip);
+            x += width;
+        }
+        y += height;
+    }
+}
+
+/// 2 つの矩形の共通部分（重ならなければ幅か高さが 0）
+fn intersection(a: Rect, b: Rect) -> Rect {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = a.right().min(b.right());
+    let bottom = a.bottom().min(b.bottom());
+    Rect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+}
+
 /// 塗りつぶし矩形を描画
 fn paint_solid_color(image: &mut Image, color: &Color, rect: &Rect) {
     let gfx_color = GraphicsColor {
@@ -493,4 +697,65 @@ mod tests {
             _ => panic!("Expected SolidColor"),
         }
     }
+
+    #[test]
+    fn test_images() {
+        use super::super::css::CssParser;
+        use super::super::html::HtmlParser;
+        use super::super::layout::layout_tree;
+        use super::super::style::style_tree;
+        use alloc::string::String;
+
+        let html = r#"<div style="background: url(dot.png) repeat-x; height: 2px; padding: 1px"></div><img src="dot.png" width="4" height="2"><img src="missing.png" width="1" height="1">"#;
+        let dom = HtmlParser::parse(html);
+        let stylesheet = CssParser::parse("");
+        let styled = style_tree(&dom, &stylesheet);
+        let mut viewport = Dimensions::default();
+        viewport.content.width = 10.0;
+        let list = build_display_list(&layout_tree(&styled, viewport));
+
+        let sources: Vec<&str> = list
+            .iter()
+            .filter_map(|cmd| match cmd {
+                DisplayCommand::Image(src, _) | DisplayCommand::BackgroundImage(src, ..) => Some(src.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sources, ["dot.png", "dot.png", "missing.png"]);
+
+        // 赤と青の 2x1 の画像
+        let mut dot = Image::new(2, 1);
+        dot.set_pixel(0, 0, GraphicsColor::new(255, 0, 0));
+        dot.set_pixel(1, 0, GraphicsColor::new(0, 0, 255));
+        let mut images = ImageCache::default();
+        images.insert("dot.png".into(), dot);
+
+        let mut canvas = Image::new(10, 8);
+        paint(&list, Rect::new(0.0, 0.0, 10.0, 8.0), &mut canvas, &images);
+        let rows: Vec<String> = (0..8)
+            .map(|y| {
+                (0..10)
+                    .map(|x| match canvas.get_pixel(x, y) {
+                        c if c.alpha == 0 => '.',
+                        c if c.red == 255 => 'r',
+                        _ => 'b',
+                    })
+                    .collect()
+            })
+            .collect();
+        // 背景は横方向だけ繰り返し、<img> は 4x2 に拡大される
+        assert_eq!(
+            rows,
+            [
+                "rbrbrbrbrb",
+                "..........",
+                "..........",
+                "..........",
+                "rrbb......",
+                "rrbb......",
+                "..........",
+                "..........",
+            ]
+        );
+    }
 }
@@ -98,9 +98,12 @@ pub enum Stmt {
     /// loop文: `loop { ... }`
     Loop(Box<Stmt>),
 
-    /// 関数定義: `fn name(params) -> ret { body }`
+    /// 関数定義: `fn name<T: Bound>(params) -> ret { body }`
     Function {
         name: String,
+        /// `async fn`（呼び出すとタスクを生成して Promise を返す）
+        is_async: bool,
+        generics: Vec<GenericParam>,
         params: Vec<FunctionParam>,
         return_type: Option<TypeAnnotation>,
         body: Box<Stmt>,
@@ -115,18 +118,27 @@ pub enum Stmt {
     /// continue文: `continue;`
     Continue,
 
-    /// 構造体定義: `struct Name { fields }`
+    /// 構造体定義: `struct Name<T> { fields }`
     Struct {
         name: String,
+        generics: Vec<GenericParam>,
         fields: Vec<StructField>,
     },
 
-    /// impl ブロック: `impl Name { methods }`
+    /// impl ブロック: `impl<T> Name<T> { methods }` or `impl Trait for Name { methods }`
     Impl {
+        generics: Vec<GenericParam>,
+        trait_name: Option<String>,
         type_name: String,
         methods: Vec<Stmt>,
     },
 
+    /// トレイト定義: `trait Name { fn method(&self); ... }`
+    Trait {
+        name: String,
+        methods: Vec<TraitMethod>,
+    },
+
     /// match文: `match expr { arms }`
     Match {
         value: Expr,
@@ -145,6 +157,24 @@ pub struct FunctionParam {
     pub mutable: bool,
 }
 
+/// ジェネリクスの型パラメータ: `T: Bound + Other`
+#[derive(Debug, Clone)]
+pub struct GenericParam {
+    pub name: String,
+    pub bounds: Vec<String>,
+}
+
+/// トレイトのメソッド
+#[derive(Debug, Clone)]
+pub struct TraitMethod {
+    pub name: String,
+    pub is_async: bool,
+    pub params: Vec<FunctionParam>,
+    pub return_type: Option<TypeAnnotation>,
+    /// デフォルト実装（宣言のみなら None）
+    pub default: Option<Box<Stmt>>,
+}
+
 /// 構造体フィールド
 #[derive(Debug, Clone)]
 pub struct StructField {
@@ -309,6 +339,11 @@ pub enum Expr {
     /// await式: `expr.await`
     Await(Box<Expr>),
 
+    /// asyncブロック・asyncクロージャ: `async { ... }`, `async move || ...`
+    ///
+    /// 中身はブロック式かクロージャ式。
+    Async(Box<Expr>),
+
     /// try式: `expr?`
     Try(Box<Expr>),
 }
@@ -442,6 +477,12 @@ pub enum TypeAnnotation {
         inner: Box<TypeAnnotation>,
     },
 
+    /// トレイト境界型: `impl Trait`, `dyn Trait + Other`
+    Bound {
+        dynamic: bool,
+        traits: Vec<TypeAnnotation>,
+    },
+
     /// オプション型: `T?` (sugar for Option<T>)
     Optional(Box<TypeAnnotation>),
 
@@ -0,0 +1,233 @@
+// ============================================================================
+// src/application/browser/script/compiler/expressions.rs - Expression Compilation
+// ============================================================================
+//!
+//! 式のコンパイル。
+
+use alloc::string::String;
+
+use super::super::ast::{BinaryOp, Expr, Literal, UnaryOp};
+use super::super::value::ScriptValue;
+use super::super::vm::Instruction;
+use super::super::ScriptError;
+use super::Compiler;
+
+impl Compiler {
+    pub(crate) fn compile_expression(&mut self, expr: &Expr) -> Result<(), ScriptError> {
+        match expr {
+            Expr::Literal(lit) => {
+                let value = match lit {
+                    Literal::Nil => ScriptValue::Nil,
+                    Literal::Bool(b) => ScriptValue::Bool(*b),
+                    Literal::Integer(i) => ScriptValue::Int(*i),
+                    Literal::Float(f) => ScriptValue::Float(*f),
+                    Literal::String(s) => ScriptValue::String(s.clone()),
+                };
+                self.emit_constant(value);
+            }
+            Expr::Identifier(name) => {
+                self.emit_load(name);
+            }
+            Expr::Unary { op, operand } => {
+                self.compile_expression(operand)?;
+                match op {
+                    UnaryOp::Neg => self.emit(Instruction::Neg),
+                    UnaryOp::Not => self.emit(Instruction::Not),
+                    UnaryOp::BitNot => self.emit(Instruction::BitNot),
+                };
+            }
+            Expr::Binary { left, op, right } => {
+                self.compile_expression(left)?;
+                self.compile_expression(right)?;
+                match op {
+                    BinaryOp::Add => self.emit(Instruction::Add),
+                    BinaryOp::Sub => self.emit(Instruction::Sub),
+                    BinaryOp::Mul => self.emit(Instruction::Mul),
+                    BinaryOp::Div => self.emit(Instruction::Div),
+                    BinaryOp::Mod => self.emit(Instruction::Mod),
+                    BinaryOp::Eq => self.emit(Instruction::Eq),
+                    BinaryOp::NotEq => self.emit(Instruction::Ne),
+                    BinaryOp::Lt => self.emit(Instruction::Lt),
+                    BinaryOp::LtEq => self.emit(Instruction::Le),
+                    BinaryOp::Gt => self.emit(Instruction::Gt),
+                    BinaryOp::GtEq => self.emit(Instruction::Ge),
+                    BinaryOp::And => self.emit(Instruction::And),
+                    BinaryOp::Or => self.emit(Instruction::Or),
+                    BinaryOp::BitAnd => self.emit(Instruction::BitAnd),
+                    BinaryOp::BitOr => self.emit(Instruction::BitOr),
+                    BinaryOp::BitXor => self.emit(Instruction::BitXor),
+                    BinaryOp::Shl => self.emit(Instruction::Shl),
+                    BinaryOp::Shr => self.emit(Instruction::Shr),
+                };
+            }
+            Expr::Call { callee, args } => {
+                if let Expr::Path(segments) = callee.as_ref()
+                    && self.compile_std_call(segments, args)?
+                {
+                    return Ok(());
+                }
+
+                // 引数をプッシュ
+                for arg in args {
+                    self.compile_expression(arg)?;
+                }
+                // 関数をプッシュ
+                self.compile_expression(callee)?;
+                // 呼び出し
+                self.emit(Instruction::Call(args.len()));
+            }
+            Expr::MethodCall { object, method, args } => {
+                // レシーバー、引数の順にプッシュ
+                self.compile_expression(object)?;
+                for arg in args {
+                    self.compile_expression(arg)?;
+                }
+                // メソッド呼び出し（レシーバーの型でディスパッチ）
+                self.emit(Instruction::CallMethod(method.clone(), args.len()));
+            }
+            Expr::FieldAccess { object, field } => {
+                self.compile_expression(object)?;
+                self.emit(Instruction::GetField(field.clone()));
+            }
+            Expr::Index { object, index } => {
+                self.compile_expression(object)?;
+                self.compile_expression(index)?;
+                self.emit(Instruction::GetIndex);
+            }
+            Expr::Array(elements) => {
+                for elem in elements {
+                    self.compile_expression(elem)?;
+                }
+                self.emit(Instruction::MakeArray(elements.len()));
+            }
+            Expr::StructLit { name, fields } => {
+                for (key, value) in fields {
+                    self.emit_constant(ScriptValue::String(key.clone()));
+                    self.compile_expression(value)?;
+                }
+                let type_name = self.resolve_self(name);
+                self.emit(Instruction::MakeStruct(type_name, fields.len()));
+            }
+            Expr::If { condition, then_branch, else_branch } => {
+                self.compile_expression(condition)?;
+                let jump_if_false = self.emit(Instruction::JumpIfFalse(0));
+
+                self.compile_expression(then_branch)?;
+
+                if let Some(else_expr) = else_branch {
+                    let jump_over_else = self.emit(Instruction::Jump(0));
+                    self.patch_jump(jump_if_false);
+                    self.compile_expression(else_expr)?;
+                    self.patch_jump(jump_over_else);
+                } else {
+                    let jump_over_else = self.emit(Instruction::Jump(0));
+                    self.patch_jump(jump_if_false);
+                    self.emit_nil();
+                    self.patch_jump(jump_over_else);
+                }
+            }
+            Expr::Block { statements, value } => {
+                self.enter_scope();
+                self.compile_statements(statements)?;
+                if let Some(val_expr) = value {
+                    self.compile_expression(val_expr)?;
+                } else {
+                    self.emit_nil();
+                }
+                self.exit_scope();
+            }
+            Expr::Closure { params, body } => {
+                self.compile_closure(params, body, false)?;
+            }
+            Expr::Async(inner) => match inner.as_ref() {
+                Expr::Closure { params, body } => self.compile_closure(params, body, true)?,
+                // async ブロックは引数なしの async クロージャをその場で呼び出したもの
+                block => {
+                    self.compile_closure(&[], block, true)?;
+                    self.emit(Instruction::Call(0));
+                }
+            },
+            Expr::Await(inner) => {
+                self.compile_expression(inner)?;
+                self.emit(Instruction::Await);
+            }
+            Expr::Path(segments) => match segments.as_slice() {
+                // `Type::function`（関連関数）
+                [type_name, name] => {
+                    let type_name = Self::type_key(&self.resolve_self(type_name));
+                    self.emit(Instruction::LoadMethod(type_name, name.clone()));
+                }
+                _ => {
+                    let name = segments.last().cloned().unwrap_or_default();
+                    self.emit_load(&name);
+                }
+            },
+            Expr::Ref { expr: inner, .. } | Expr::Deref(inner) | Expr::Cast { expr: inner, .. } => {
+                // 参照は値そのものとして扱う
+                self.compile_expression(inner)?;
+            }
+            Expr::Range { start, end, inclusive } => {
+                if let Some(s) = start {
+                    self.compile_expression(s)?;
+                } else {
+                    self.emit_constant(ScriptValue::Int(0));
+                }
+
+                if let Some(e) = end {
+                    self.compile_expression(e)?;
+                } else {
+                    self.emit_constant(ScriptValue::Int(i64::MAX));
+                }
+
+                if *inclusive {
+                    self.emit(Instruction::MakeRangeInclusive);
+                } else {
+                    self.emit(Instruction::MakeRange);
+                }
+            }
+            Expr::Tuple(elements) => {
+                for elem in elements {
+                    self.compile_expression(elem)?;
+                }
+                self.emit(Instruction::MakeArray(elements.len()));
+            }
+            _ => {
+                // 未対応の式はNilを返す
+                self.emit_nil();
+            }
+        }
+        Ok(())
+    }
+
+    /// 値を包むだけの標準ライブラリ呼び出し（`Box::new(x)` など）
+    ///
+    /// 該当しなければ何も生成せず false を返す。
+    fn compile_std_call(&mut self, segments: &[String], args: &[Expr]) -> Result<bool, ScriptError> {
+        let [type_name, name] = segments else {
+            return Ok(false);
+        };
+        match (type_name.as_str(), name.as_str(), args) {
+            ("Box" | "Rc" | "Arc" | "RefCell" | "Cell", "new", [value]) => {
+                self.compile_expression(value)?;
+            }
+            ("String", "from", [value]) => {
+                self.compile_expression(value)?;
+                self.emit(Instruction::CallMethod(String::from("to_string"), 0));
+            }
+            ("String", "new", []) => self.emit_constant(ScriptValue::String(String::new())),
+            ("Vec", "new", []) => {
+                self.emit(Instruction::MakeArray(0));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// `Self` を impl の型名に置き換える
+    pub(crate) fn resolve_self(&self, name: &str) -> String {
+        match (name, &self.impl_type) {
+            ("Self", Some(type_name)) => type_name.clone(),
+            _ => String::from(name),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+// ============================================================================
+// src/application/browser/script/compiler/items.rs - Functions, Closures and Traits
+// ============================================================================
+//!
+//! 関数・クロージャ・impl・トレイトのコンパイル。
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::ast::{ClosureParam, Expr, FunctionParam, GenericParam, Stmt, TypeAnnotation};
+use super::super::value::{FunctionValue, ScriptValue};
+use super::super::vm::Instruction;
+use super::super::ScriptError;
+use super::{Compiler, FunctionState};
+
+impl Compiler {
+    /// 先に登録しておく項目か（関数・構造体・impl・トレイト）
+    pub(crate) fn is_item(stmt: &Stmt) -> bool {
+        matches!(
+            stmt,
+            Stmt::Function { .. } | Stmt::Struct { .. } | Stmt::Impl { .. } | Stmt::Trait { .. }
+        )
+    }
+
+    /// 文の並びに含まれる項目をコンパイル（トレイトは impl より先に登録する）
+    pub(crate) fn compile_items(&mut self, statements: &[Stmt]) -> Result<(), ScriptError> {
+        let traits = statements.iter().filter(|stmt| matches!(stmt, Stmt::Trait { .. }));
+        let others = statements
+            .iter()
+            .filter(|stmt| Self::is_item(stmt) && !matches!(stmt, Stmt::Trait { .. }));
+        for stmt in traits.chain(others) {
+            self.compile_item(stmt)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn compile_item(&mut self, stmt: &Stmt) -> Result<(), ScriptError> {
+        match stmt {
+            Stmt::Function { name, is_async, generics, params, body, .. } => {
+                let func = self.compile_function(name, *is_async, generics, params, body)?;
+                self.functions.insert(name.clone(), func.body_addr);
+
+                // 関数オブジェクトをグローバルに登録
+                self.emit_constant(ScriptValue::Function(func));
+                self.emit(Instruction::StoreGlobal(name.clone()));
+            }
+            Stmt::Impl { generics, trait_name, type_name, methods } => {
+                let key = Self::type_key(type_name);
+                let saved_type = self.impl_type.replace(type_name.clone());
+                let saved_generics = core::mem::replace(&mut self.impl_generics, generics.clone());
+
+                for method in methods {
+                    if let Stmt::Function { name, is_async, generics, params, body, .. } = method {
+                        let qualified = format!("{}::{}", type_name, name);
+                        let func = self.compile_function(&qualified, *is_async, generics, params, body)?;
+                        self.emit_constant(ScriptValue::Function(func));
+                        self.emit(Instruction::DefineMethod(key.clone(), name.clone()));
+                    }
+                }
+                if let Some(trait_name) = trait_name {
+                    self.emit(Instruction::ImplTrait(trait_name.clone(), key));
+                }
+
+                self.impl_type = saved_type;
+                self.impl_generics = saved_generics;
+            }
+            Stmt::Trait { name, methods } => {
+                let names = methods.iter().map(|m| m.name.clone()).collect();
+                self.emit(Instruction::DefineTrait(name.clone(), names));
+
+                // デフォルト実装の中の `Self` は実装する型ごとに変わるので解決しない
+                let saved_type = self.impl_type.take();
+                for method in methods {
+                    if let Some(body) = &method.default {
+                        let qualified = format!("{}::{}", name, method.name);
+                        let func = self.compile_function(&qualified, method.is_async, &[], &method.params, body)?;
+                        self.emit_constant(ScriptValue::Function(func));
+                        self.emit(Instruction::DefineDefault(name.clone(), method.name.clone()));
+                    }
+                }
+                self.impl_type = saved_type;
+            }
+            // 構造体はフィールドを実行時に持つだけなので命令は不要
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// 関数本体をコンパイルし、関数値を返す
+    ///
+    /// 本体は命令列の途中に置かれるので、前後をジャンプで飛び越す。
+    fn compile_function(
+        &mut self,
+        name: &str,
+        is_async: bool,
+        generics: &[GenericParam],
+        params: &[FunctionParam],
+        body: &Stmt,
+    ) -> Result<FunctionValue, ScriptError> {
+        let skip_jump = self.emit(Instruction::Jump(0));
+        let func_addr = self.here();
+
+        // fn 項目は外側のローカル変数を参照できない
+        self.states.push(FunctionState::new(false));
+
+        // パラメータをローカル変数として定義し、トレイト境界を検査する
+        for param in params {
+            let slot = self.define_local(&param.name);
+            if let Some(ty) = &param.type_ann {
+                for bound in self.trait_bounds(ty, generics) {
+                    self.emit(Instruction::CheckTrait(slot, bound));
+                }
+            }
+        }
+
+        // 本体をコンパイル
+        self.compile_statement(body)?;
+
+        // 暗黙のreturn
+        self.emit_nil();
+        self.emit(Instruction::Return);
+
+        self.states.pop();
+        self.patch_jump(skip_jump);
+
+        let param_names = params.iter().map(|p| p.name.clone()).collect();
+        let func = FunctionValue::new(name, param_names, func_addr);
+        Ok(if is_async { func.into_async() } else { func })
+    }
+
+    /// クロージャをコンパイルし、実行時にキャプチャ変数を束ねる `MakeClosure` を生成する
+    pub(crate) fn compile_closure(
+        &mut self,
+        params: &[ClosureParam],
+        body: &Expr,
+        is_async: bool,
+    ) -> Result<(), ScriptError> {
+        // クロージャ本体をスキップするジャンプ
+        let skip_jump = self.emit(Instruction::Jump(0));
+        let closure_addr = self.here();
+
+        self.states.push(FunctionState::new(true));
+
+        // パラメータをローカル変数として定義
+        for param in params {
+            self.define_local(&param.name);
+        }
+
+        // 本体をコンパイル
+        self.compile_expression(body)?;
+        self.emit(Instruction::Return);
+
+        let state = self.states.pop().expect("closure state was pushed above");
+        self.patch_jump(skip_jump);
+
+        // 本体で参照した外側の変数をキャプチャする
+        let captures = state.upvalues.iter().map(|(_, capture)| *capture).collect();
+        let param_names = params.iter().map(|p| p.name.clone()).collect();
+        let mut template = FunctionValue::closure(param_names, closure_addr, Vec::new());
+        template.is_async = is_async;
+        let const_idx = self.constant(ScriptValue::Function(template));
+        self.emit(Instruction::MakeClosure(const_idx, captures));
+        Ok(())
+    }
+
+    /// パラメータの型が要求するトレイト（`T: Bound`, `impl Bound`, `&dyn Bound`, `Box<dyn Bound>`）
+    fn trait_bounds(&self, ty: &TypeAnnotation, generics: &[GenericParam]) -> Vec<String> {
+        match ty {
+            TypeAnnotation::Simple(name) => generics
+                .iter()
+                .chain(self.impl_generics.iter())
+                .filter(|g| &g.name == name)
+                .flat_map(|g| g.bounds.iter().cloned())
+                .collect(),
+            TypeAnnotation::Reference { inner, .. } => self.trait_bounds(inner, generics),
+            TypeAnnotation::Bound { traits, .. } => traits
+                .iter()
+                .filter_map(|t| match t {
+                    TypeAnnotation::Simple(name) | TypeAnnotation::Generic { name, .. } => Some(name.clone()),
+                    _ => None,
+                })
+                .collect(),
+            TypeAnnotation::Generic { name, params } if matches!(name.as_str(), "Box" | "Rc" | "Arc") => {
+                params.iter().flat_map(|p| self.trait_bounds(p, generics)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// 型名をメソッド表のキーにする（組み込み型は実行時の値の型名に揃える）
+    pub(crate) fn type_key(name: &str) -> String {
+        let key = match name {
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize" => "int",
+            "f32" | "f64" => "float",
+            "String" | "str" | "char" => "string",
+            "bool" => "bool",
+            "Vec" | "VecDeque" => "array",
+            "HashMap" | "BTreeMap" => "object",
+            other => other,
+        };
+        String::from(key)
+    }
+}
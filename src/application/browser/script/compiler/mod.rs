@@ -0,0 +1,283 @@
+// ============================================================================
+// src/application/browser/script/compiler/mod.rs - Bytecode Compiler
+// ============================================================================
+//!
+//! # バイトコードコンパイラ
+//!
+//! ASTをVMの命令列に変換する。
+//!
+//! ## 変数の解決
+//! 名前はローカル変数 → 外側の関数のローカル変数（クロージャのみ）→ グローバルの順に解決する。
+//! クロージャが外側の変数を参照すると、その変数はクロージャのキャプチャ変数（upvalue）になり、
+//! `MakeClosure` の実行時にヒープ上のセルへ移される。以後は外側の関数とクロージャが同じセルを
+//! 読み書きするため、クロージャは宣言した関数から戻った後も変数を保持し続ける。
+//! `fn` 項目は Rust と同様に外側のローカル変数を参照できない。
+//!
+//! ## トレイトとジェネリクス
+//! `impl` のメソッドは型ごとのメソッド表に登録され、メソッド呼び出しは実行時の値の型で
+//! ディスパッチされる。ジェネリクスは型消去し、トレイト境界は関数の入口で検査する。
+//!
+//! ## モジュール構成
+//! - `statements` - 文
+//! - `expressions` - 式
+//! - `items` - 関数・クロージャ・impl・トレイト
+
+extern crate alloc;
+
+mod expressions;
+mod items;
+mod statements;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::ast::{Ast, GenericParam, Stmt};
+use super::value::ScriptValue;
+use super::vm::{Capture, ConstantPool, Instruction};
+use super::ScriptError;
+
+// ============================================================================
+// Compiler
+// ============================================================================
+
+/// ASTをバイトコードにコンパイルするコンパイラ
+pub struct Compiler {
+    /// 生成された命令列
+    pub(crate) instructions: Vec<Instruction>,
+    /// 定数プール
+    pub(crate) constants: ConstantPool,
+    /// 関数テーブル（関数名 -> バイトコード位置）
+    pub(crate) functions: BTreeMap<String, usize>,
+    /// 命令アドレスの起点（VMに読み込み済みの命令数）
+    code_base: usize,
+    /// 定数インデックスの起点（VMに読み込み済みの定数の数）
+    const_base: usize,
+    /// コンパイル中の関数（末尾が最も内側）
+    states: Vec<FunctionState>,
+    /// コンパイル中の impl の型名（`Self` の解決用）
+    impl_type: Option<String>,
+    /// コンパイル中の impl の型パラメータ
+    impl_generics: Vec<GenericParam>,
+}
+
+/// コンパイル中の関数の状態
+struct FunctionState {
+    /// スコープスタック
+    scopes: Vec<Scope>,
+    /// 次に割り当てるローカル変数スロット
+    next_slot: usize,
+    /// キャプチャ変数（名前, 外側の関数での場所）
+    upvalues: Vec<(String, Capture)>,
+    /// クロージャか（外側のローカル変数を参照できるか）
+    is_closure: bool,
+    /// 囲んでいるループ
+    loops: Vec<LoopContext>,
+}
+
+/// ブロックスコープ
+struct Scope {
+    /// 変数名 -> スロット
+    vars: BTreeMap<String, usize>,
+    /// スコープ開始時のスロット（抜けると再利用する）
+    start_slot: usize,
+}
+
+/// ループの情報
+struct LoopContext {
+    /// `continue` のジャンプ先
+    continue_target: usize,
+    /// `break` のジャンプ命令（ループ終了時にパッチする）
+    breaks: Vec<usize>,
+}
+
+/// 名前の解決結果
+enum Variable {
+    Local(usize),
+    Upvalue(usize),
+    Global,
+}
+
+impl FunctionState {
+    fn new(is_closure: bool) -> Self {
+        Self {
+            scopes: vec![Scope { vars: BTreeMap::new(), start_slot: 0 }],
+            next_slot: 0,
+            upvalues: Vec::new(),
+            is_closure,
+            loops: Vec::new(),
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rev().find_map(|scope| scope.vars.get(name).copied())
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::with_base(0, 0)
+    }
+
+    /// 既に命令と定数が読み込まれた VM の後ろに追加するコードを生成する
+    pub fn with_base(code_base: usize, const_base: usize) -> Self {
+        Self {
+            instructions: Vec::new(),
+            constants: ConstantPool::new(),
+            functions: BTreeMap::new(),
+            code_base,
+            const_base,
+            states: vec![FunctionState::new(false)],
+            impl_type: None,
+            impl_generics: Vec::new(),
+        }
+    }
+
+    /// プログラム全体をコンパイル（最後の式文の値がスクリプトの値になる）
+    pub fn compile(&mut self, ast: &Ast) -> Result<(), ScriptError> {
+        self.compile_items(&ast.statements)?;
+
+        let last = ast.statements.len().checked_sub(1);
+        for (i, stmt) in ast.statements.iter().enumerate() {
+            match stmt {
+                _ if Self::is_item(stmt) => {}
+                Stmt::Expression(expr) if Some(i) == last => self.compile_expression(expr)?,
+                _ => self.compile_statement(stmt)?,
+            }
+        }
+
+        self.emit(Instruction::Halt);
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------------
+    // Emit helpers
+    // ------------------------------------------------------------------------
+
+    /// 命令を追加し、その（このコンパイラ内の）インデックスを返す
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let idx = self.instructions.len();
+        self.instructions.push(instruction);
+        idx
+    }
+
+    /// 次に追加される命令のアドレス
+    fn here(&self) -> usize {
+        self.code_base + self.instructions.len()
+    }
+
+    /// 定数を追加し、VM 上のインデックスを返す
+    fn constant(&mut self, value: ScriptValue) -> usize {
+        self.const_base + self.constants.add(value)
+    }
+
+    fn emit_constant(&mut self, value: ScriptValue) {
+        let idx = self.constant(value);
+        self.emit(Instruction::Const(idx));
+    }
+
+    fn emit_nil(&mut self) {
+        self.emit_constant(ScriptValue::Nil);
+    }
+
+    /// ジャンプ命令の飛び先を現在位置にする
+    fn patch_jump(&mut self, idx: usize) {
+        let target = self.here();
+        match &mut self.instructions[idx] {
+            Instruction::Jump(addr) => *addr = target,
+            Instruction::JumpIfFalse(addr) => *addr = target,
+            Instruction::JumpIfTrue(addr) => *addr = target,
+            _ => {}
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Scopes and variables
+    // ------------------------------------------------------------------------
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.states.last_mut().expect("compiler always has a function state")
+    }
+
+    fn enter_scope(&mut self) {
+        let state = self.state();
+        let start_slot = state.next_slot;
+        state.scopes.push(Scope { vars: BTreeMap::new(), start_slot });
+    }
+
+    fn exit_scope(&mut self) {
+        let state = self.state();
+        if let Some(scope) = state.scopes.pop() {
+            state.next_slot = scope.start_slot;
+        }
+    }
+
+    fn define_local(&mut self, name: &str) -> usize {
+        let state = self.state();
+        let slot = state.next_slot;
+        state.next_slot += 1;
+        if let Some(scope) = state.scopes.last_mut() {
+            scope.vars.insert(String::from(name), slot);
+        }
+        slot
+    }
+
+    fn resolve(&mut self, name: &str) -> Variable {
+        let depth = self.states.len() - 1;
+        if let Some(slot) = self.states[depth].resolve_local(name) {
+            return Variable::Local(slot);
+        }
+        match self.resolve_upvalue(depth, name) {
+            Some(index) => Variable::Upvalue(index),
+            None => Variable::Global,
+        }
+    }
+
+    /// 外側の関数の変数をキャプチャ変数として登録する
+    fn resolve_upvalue(&mut self, depth: usize, name: &str) -> Option<usize> {
+        if depth == 0 || !self.states[depth].is_closure {
+            return None;
+        }
+        if let Some(index) = self.states[depth].upvalues.iter().position(|(n, _)| n == name) {
+            return Some(index);
+        }
+
+        let capture = match self.states[depth - 1].resolve_local(name) {
+            Some(slot) => Capture::Local(slot),
+            None => Capture::Upvalue(self.resolve_upvalue(depth - 1, name)?),
+        };
+        let upvalues = &mut self.states[depth].upvalues;
+        upvalues.push((String::from(name), capture));
+        Some(upvalues.len() - 1)
+    }
+
+    fn emit_load(&mut self, name: &str) {
+        let instruction = match self.resolve(name) {
+            Variable::Local(slot) => Instruction::LoadLocal(slot),
+            Variable::Upvalue(index) => Instruction::LoadUpvalue(index),
+            Variable::Global => Instruction::LoadGlobal(String::from(name)),
+        };
+        self.emit(instruction);
+    }
+
+    fn emit_store(&mut self, name: &str) {
+        let instruction = match self.resolve(name) {
+            Variable::Local(slot) => Instruction::StoreLocal(slot),
+            Variable::Upvalue(index) => Instruction::StoreUpvalue(index),
+            Variable::Global => Instruction::StoreGlobal(String::from(name)),
+        };
+        self.emit(instruction);
+    }
+
+    /// 生成結果を取り出す
+    pub fn finish(self) -> (Vec<Instruction>, ConstantPool, BTreeMap<String, usize>) {
+        (self.instructions, self.constants, self.functions)
+    }
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,216 @@
+// ============================================================================
+// src/application/browser/script/compiler/statements.rs - Statement Compilation
+// ============================================================================
+//!
+//! 文のコンパイル。
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use super::super::ast::{Expr, Stmt};
+use super::super::vm::Instruction;
+use super::super::ScriptError;
+use super::{Compiler, LoopContext};
+
+impl Compiler {
+    /// 文の並びをコンパイル（項目は先に登録するので定義前に呼び出せる）
+    pub(crate) fn compile_statements(&mut self, statements: &[Stmt]) -> Result<(), ScriptError> {
+        self.compile_items(statements)?;
+        for stmt in statements.iter().filter(|stmt| !Self::is_item(stmt)) {
+            self.compile_statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn compile_statement(&mut self, stmt: &Stmt) -> Result<(), ScriptError> {
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                // 初期化子をコンパイル（`let x = x + 1` の右辺は外側の x を指す）
+                if let Some(init) = value {
+                    self.compile_expression(init)?;
+                } else {
+                    self.emit_nil();
+                }
+
+                // 新しいスロットに束縛
+                let slot = self.define_local(name);
+                self.emit(Instruction::DefineLocal(slot));
+            }
+            Stmt::Assign { target, value } => {
+                self.compile_expression(value)?;
+                self.compile_store(target)?;
+            }
+            Stmt::CompoundAssign { target, op, value } => {
+                let combined = Expr::Binary {
+                    left: Box::new(target.clone()),
+                    op: *op,
+                    right: Box::new(value.clone()),
+                };
+                self.compile_expression(&combined)?;
+                self.compile_store(target)?;
+            }
+            Stmt::Expression(expr) => {
+                self.compile_expression(expr)?;
+                self.emit(Instruction::Pop);
+            }
+            Stmt::Block(statements) => {
+                self.enter_scope();
+                self.compile_statements(statements)?;
+                self.exit_scope();
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.compile_expression(condition)?;
+
+                let jump_if_false = self.emit(Instruction::JumpIfFalse(0));
+
+                self.compile_statement(then_branch)?;
+
+                if let Some(else_stmt) = else_branch {
+                    let jump_over_else = self.emit(Instruction::Jump(0));
+                    self.patch_jump(jump_if_false);
+                    self.compile_statement(else_stmt)?;
+                    self.patch_jump(jump_over_else);
+                } else {
+                    self.patch_jump(jump_if_false);
+                }
+            }
+            Stmt::While { condition, body } => {
+                let loop_start = self.here();
+
+                self.compile_expression(condition)?;
+                let exit_jump = self.emit(Instruction::JumpIfFalse(0));
+
+                self.compile_loop_body(loop_start, body)?;
+                self.emit(Instruction::Jump(loop_start));
+
+                self.patch_jump(exit_jump);
+                self.patch_breaks();
+            }
+            Stmt::For { variable, iterator, body } => {
+                // イテレータはループ中スタックに置いておく
+                self.compile_expression(iterator)?;
+                self.emit(Instruction::MakeIterator);
+
+                let loop_start = self.here();
+
+                // 次の値を取得: [iter, value, done]
+                self.emit(Instruction::IterNext);
+                let exit_jump = self.emit(Instruction::JumpIfTrue(0));
+
+                // 反復ごとに新しい変数として束縛する（クロージャは反復ごとの値をキャプチャする）
+                self.enter_scope();
+                let slot = self.define_local(variable);
+                self.emit(Instruction::DefineLocal(slot));
+
+                self.compile_loop_body(loop_start, body)?;
+                self.exit_scope();
+                self.emit(Instruction::Jump(loop_start));
+
+                // 終了時は [iter, nil]、break 時は [iter] が残っている
+                self.patch_jump(exit_jump);
+                self.emit(Instruction::Pop);
+                self.patch_breaks();
+                self.emit(Instruction::Pop);
+            }
+            Stmt::Loop(body) => {
+                let loop_start = self.here();
+
+                self.compile_loop_body(loop_start, body)?;
+                self.emit(Instruction::Jump(loop_start));
+
+                self.patch_breaks();
+            }
+            Stmt::Function { .. } | Stmt::Struct { .. } | Stmt::Impl { .. } | Stmt::Trait { .. } => {
+                self.compile_item(stmt)?;
+            }
+            Stmt::Return(value_opt) => {
+                if let Some(v) = value_opt {
+                    self.compile_expression(v)?;
+                } else {
+                    self.emit_nil();
+                }
+                self.emit(Instruction::Return);
+            }
+            Stmt::Break => {
+                let jump = self.emit(Instruction::Jump(0));
+                match self.state().loops.last_mut() {
+                    Some(ctx) => ctx.breaks.push(jump),
+                    None => return Err(ScriptError::syntax("`break` outside of a loop", 0, 0)),
+                }
+            }
+            Stmt::Continue => {
+                let target = match self.state().loops.last() {
+                    Some(ctx) => ctx.continue_target,
+                    None => return Err(ScriptError::syntax("`continue` outside of a loop", 0, 0)),
+                };
+                self.emit(Instruction::Jump(target));
+            }
+            Stmt::Match { .. } | Stmt::Empty => {}
+        }
+        Ok(())
+    }
+
+    /// ループ本体をコンパイル（`break` / `continue` の飛び先を登録する）
+    fn compile_loop_body(&mut self, continue_target: usize, body: &Stmt) -> Result<(), ScriptError> {
+        self.state().loops.push(LoopContext {
+            continue_target,
+            breaks: Vec::new(),
+        });
+        self.compile_statement(body)
+    }
+
+    /// 最も内側のループの `break` を現在位置へパッチする
+    fn patch_breaks(&mut self) {
+        if let Some(ctx) = self.state().loops.pop() {
+            for jump in ctx.breaks {
+                self.patch_jump(jump);
+            }
+        }
+    }
+
+    /// スタックトップの値を代入先に格納する
+    ///
+    /// 値型のフィールドや要素に代入したときは、更新したコンテナを元の場所へ書き戻す。
+    pub(crate) fn compile_store(&mut self, target: &Expr) -> Result<(), ScriptError> {
+        match target {
+            Expr::Identifier(name) => self.emit_store(name),
+            Expr::FieldAccess { object, field } => {
+                // [value] -> [object, value] -> [object']
+                self.compile_expression(object)?;
+                self.emit(Instruction::Swap);
+                self.emit(Instruction::SetField(field.clone()));
+                self.store_container(object)?;
+            }
+            Expr::Index { object, index } => {
+                // [value] -> [value, object, index, value] -> [value, object'] -> [object']
+                self.compile_expression(object)?;
+                self.compile_expression(index)?;
+                self.emit(Instruction::DupN(2));
+                self.emit(Instruction::SetIndex);
+                self.emit(Instruction::Swap);
+                self.emit(Instruction::Pop);
+                self.store_container(object)?;
+            }
+            Expr::Deref(inner) | Expr::Ref { expr: inner, .. } => self.compile_store(inner)?,
+            _ => {
+                self.emit(Instruction::Pop);
+            }
+        }
+        Ok(())
+    }
+
+    /// 更新したコンテナを書き戻す（代入先になれない式なら捨てる）
+    fn store_container(&mut self, object: &Expr) -> Result<(), ScriptError> {
+        match object {
+            Expr::Identifier(_)
+            | Expr::FieldAccess { .. }
+            | Expr::Index { .. }
+            | Expr::Deref(_)
+            | Expr::Ref { .. } => self.compile_store(object),
+            _ => {
+                self.emit(Instruction::Pop);
+                Ok(())
+            }
+        }
+    }
+}
@@ -9,7 +9,7 @@
 extern crate alloc;
 
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
@@ -36,6 +36,8 @@ pub struct DomBinding {
     next_element_id: usize,
     /// イベントハンドラ
     event_handlers: BTreeMap<(usize, String), Vec<usize>>,
+    /// 値かチェックが変わったフォーム部品（ホストが `take_changed_controls` で取り出す）
+    changed_controls: BTreeSet<usize>,
 }
 
 /// 要素情報
@@ -70,6 +72,7 @@ impl DomBinding {
             tag_to_elements: BTreeMap::new(),
             next_element_id: 1,
             event_handlers: BTreeMap::new(),
+            changed_controls: BTreeSet::new(),
         }
     }
 
@@ -332,17 +335,130 @@ impl DomBinding {
                     ScriptValue::Bool(false)
                 }
             }
-            DomOperation::AddEventListener(elem_id, event, handler_addr) => {
+            DomOperation::AddEventListener(elem_id, event, handler) => {
                 let key = (elem_id, event);
                 self.event_handlers
                     .entry(key)
                     .or_insert_with(Vec::new)
-                    .push(handler_addr);
+                    .push(handler);
                 ScriptValue::Bool(true)
             }
+            DomOperation::GetValue(id) => {
+                if let Some(elem) = self.element_cache.get(&id) {
+                    ScriptValue::String(Self::control_value(elem))
+                } else {
+                    ScriptValue::Nil
+                }
+            }
+            DomOperation::SetValue(id, value) => {
+                if let Some(elem) = self.element_cache.get_mut(&id) {
+                    if elem.tag_name == "textarea" {
+                        elem.text_content = value;
+                    } else {
+                        elem.attributes.insert(String::from("value"), value);
+                    }
+                    self.changed_controls.insert(id);
+                    ScriptValue::Bool(true)
+                } else {
+                    ScriptValue::Bool(false)
+                }
+            }
+            DomOperation::GetChecked(id) => {
+                if let Some(elem) = self.element_cache.get(&id) {
+                    ScriptValue::Bool(elem.attributes.contains_key("checked"))
+                } else {
+                    ScriptValue::Nil
+                }
+            }
+            DomOperation::SetChecked(id, checked) => {
+                if !self.element_cache.contains_key(&id) {
+                    return ScriptValue::Bool(false);
+                }
+                if checked {
+                    // 同じフォーム内の同名ラジオボタンは排他
+                    for other in self.radio_group(id) {
+                        if let Some(elem) = self.element_cache.get_mut(&other) {
+                            elem.attributes.remove("checked");
+                            self.changed_controls.insert(other);
+                        }
+                    }
+                }
+                if let Some(elem) = self.element_cache.get_mut(&id) {
+                    if checked {
+                        elem.attributes.insert(String::from("checked"), String::new());
+                    } else {
+                        elem.attributes.remove("checked");
+                    }
+                }
+                self.changed_controls.insert(id);
+                ScriptValue::Bool(true)
+            }
+        }
+    }
+
+    /// 前回から値かチェックが変わったフォーム部品の ID
+    pub fn take_changed_controls(&mut self) -> Vec<usize> {
+        core::mem::take(&mut self.changed_controls).into_iter().collect()
+    }
+
+    /// フォーム部品の現在値
+    fn control_value(elem: &ElementInfo) -> String {
+        if elem.tag_name == "textarea" {
+            return elem.text_content.clone();
+        }
+        match elem.attributes.get("value") {
+            Some(value) => value.clone(),
+            None => match elem.attributes.get("type").map(|t| t.as_str()) {
+                // 値のないチェックボックス・ラジオボタンは "on"
+                Some("checkbox") | Some("radio") => String::from("on"),
+                _ => String::new(),
+            },
         }
     }
 
+    /// 要素と同じグループに属する他のラジオボタン（同じフォーム内の同名のもの）
+    fn radio_group(&self, id: usize) -> Vec<usize> {
+        let Some(elem) = self.element_cache.get(&id) else {
+            return Vec::new();
+        };
+        if elem.attributes.get("type").map(|t| t.as_str()) != Some("radio") {
+            return Vec::new();
+        }
+        let Some(name) = elem.attributes.get("name").filter(|n| !n.is_empty()) else {
+            return Vec::new();
+        };
+        let form = self.form_of(id);
+        self.tag_to_elements
+            .get("input")
+            .map(|ids| {
+                ids.iter()
+                    .copied()
+                    .filter(|&other| other != id)
+                    .filter(|other| {
+                        self.element_cache.get(other).is_some_and(|e| {
+                            e.attributes.get("type").map(|t| t.as_str()) == Some("radio")
+                                && e.attributes.get("name") == Some(name)
+                        })
+                    })
+                    .filter(|&other| self.form_of(other) == form)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 要素を含む最も近い `<form>`
+    fn form_of(&self, id: usize) -> Option<usize> {
+        let mut current = self.element_cache.get(&id)?.parent_id;
+        while let Some(parent) = current {
+            let elem = self.element_cache.get(&parent)?;
+            if elem.tag_name == "form" {
+                return Some(parent);
+            }
+            current = elem.parent_id;
+        }
+        None
+    }
+
     /// ElementRefを作成
     fn create_element_ref(&self, id: usize) -> ScriptValue {
         if let Some(elem) = self.element_cache.get(&id) {
@@ -567,4 +683,40 @@ mod tests {
             panic!("Expected element");
         }
     }
+
+    #[test]
+    fn test_form_controls() {
+        let mut binding = DomBinding::new();
+        let root = DocumentNode::new("form")
+            .with_child(DocumentNode::new("input").with_id("q").with_attribute("value", "a"))
+            .with_child(DocumentNode::new("textarea").with_id("t").with_text("x"))
+            .with_child(DocumentNode::new("input").with_id("r1")
+                .with_attribute("type", "radio").with_attribute("name", "g")
+                .with_attribute("checked", ""))
+            .with_child(DocumentNode::new("input").with_id("r2")
+                .with_attribute("type", "radio").with_attribute("name", "g"));
+        binding.initialize_from_html(&root);
+        let id = |binding: &mut DomBinding, html_id: &str| {
+            match binding.handle_operation(DomOperation::GetElementById(String::from(html_id))) {
+                ScriptValue::Element(e) => e.id,
+                _ => panic!("Expected element"),
+            }
+        };
+        let q = id(&mut binding, "q");
+        let t = id(&mut binding, "t");
+        let r1 = id(&mut binding, "r1");
+        let r2 = id(&mut binding, "r2");
+
+        // 値は value 属性、textarea はテキスト
+        binding.handle_operation(DomOperation::SetValue(q, String::from("abc")));
+        binding.handle_operation(DomOperation::SetValue(t, String::from("y")));
+        assert!(matches!(binding.handle_operation(DomOperation::GetValue(q)), ScriptValue::String(ref v) if v == "abc"));
+        assert!(matches!(binding.handle_operation(DomOperation::GetText(t)), ScriptValue::String(ref v) if v == "y"));
+        assert!(matches!(binding.handle_operation(DomOperation::GetValue(r2)), ScriptValue::String(ref v) if v == "on"));
+
+        // ラジオボタンは排他
+        binding.handle_operation(DomOperation::SetChecked(r2, true));
+        assert!(matches!(binding.handle_operation(DomOperation::GetChecked(r1)), ScriptValue::Bool(false)));
+        assert!(matches!(binding.handle_operation(DomOperation::GetChecked(r2)), ScriptValue::Bool(true)));
+    }
 }
@@ -0,0 +1,289 @@
+// ============================================================================
+// src/application/browser/script/event_loop.rs - Script Event Loop
+// ============================================================================
+//!
+//! # スクリプトのイベントループ
+//!
+//! タイマー（`set_timeout` / `set_interval` / `sleep`）と `fetch` の待ち合わせを管理し、
+//! スクリプトのタスクを命令数の予算ごとに実行する。
+//!
+//! VM のネイティブ関数は DOM 操作と同じくコールバック経由で `HostRequest` を送り、
+//! `EventLoop` がタイマー表と取得待ちの列に積む。時刻はカーネルのタイマーティック
+//! （1 ms）で数える。`run_once` / `run_until_idle` はカーネルのエグゼキュータ上の
+//! 非同期タスクとして動き、予算を使い切るたびに `yield_now` で制御を返すので、
+//! 終わらないループを書いたスクリプトがあってもコンポジタは止まらない。
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::net::http::{self, Url};
+use crate::task::{current_tick, sleep_ms, yield_now};
+
+use super::runtime::ScriptRuntime;
+use super::value::ScriptValue;
+use super::vm::HostRequest;
+use super::ScriptError;
+
+/// 1回の実行で使う命令数の既定の予算
+pub const DEFAULT_INSTRUCTION_BUDGET: usize = 20_000;
+
+/// 待つものが無いときに一度に眠る最大時間（ミリ秒）
+const MAX_IDLE_SLEEP_MS: u64 = 50;
+
+// ============================================================================
+// Timers and fetches
+// ============================================================================
+
+/// 期限が来たときの動作（値は `VirtualMachine::pin` のハンドル）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerAction {
+    /// 関数をタスクとして呼ぶ
+    Call(usize),
+    /// Promise を解決する（`sleep`）
+    Resolve(usize),
+}
+
+/// タイマー情報
+#[derive(Debug, Clone)]
+struct TimerInfo {
+    /// 動作
+    action: TimerAction,
+    /// 実行予定時刻（ティック）
+    execute_at: u64,
+    /// 繰り返し間隔（setIntervalの場合）
+    interval: Option<u64>,
+}
+
+/// 取得待ちの fetch
+#[derive(Debug, Clone)]
+pub struct PendingFetch {
+    /// 解決する Promise のハンドル
+    pub promise: usize,
+    /// 取得する URL
+    pub url: String,
+}
+
+/// fetch のレスポンス（スクリプトには `status`, `ok`, `url`, `content_type`, `body` を持つオブジェクトとして渡す）
+#[derive(Debug, Clone)]
+pub struct FetchResponse {
+    pub status: u16,
+    pub url: String,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl FetchResponse {
+    /// スクリプトに渡すオブジェクト
+    pub fn to_value(&self) -> ScriptValue {
+        let mut map = BTreeMap::new();
+        map.insert(String::from("status"), ScriptValue::Int(i64::from(self.status)));
+        map.insert(String::from("ok"), ScriptValue::Bool((200..300).contains(&self.status)));
+        map.insert(String::from("url"), ScriptValue::String(self.url.clone()));
+        map.insert(String::from("content_type"), ScriptValue::String(self.content_type.clone()));
+        map.insert(String::from("body"), ScriptValue::String(self.body.clone()));
+        ScriptValue::Object(map)
+    }
+}
+
+/// タイマー表と取得待ちの列
+#[derive(Debug, Default)]
+pub struct EventLoop {
+    /// タイマー（ID -> タイマー情報）
+    timers: BTreeMap<usize, TimerInfo>,
+    /// 次のタイマーID
+    next_timer_id: usize,
+    /// 最後に処理した時刻（ティック）
+    now: u64,
+    /// 取得待ちの fetch
+    fetches: VecDeque<PendingFetch>,
+    /// 固定を外すハンドル（止めたタイマーのコールバック）
+    released: Vec<usize>,
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        Self {
+            next_timer_id: 1,
+            ..Self::default()
+        }
+    }
+
+    /// VM からの要求を処理する
+    pub fn handle_request(&mut self, request: HostRequest) -> ScriptValue {
+        match request {
+            HostRequest::StartTimer { callback, delay_ms, repeat } => {
+                let interval = repeat.then_some(delay_ms);
+                ScriptValue::Int(self.add_timer(TimerAction::Call(callback), delay_ms, interval) as i64)
+            }
+            HostRequest::ClearTimer(id) => {
+                self.clear_timer(id);
+                ScriptValue::Nil
+            }
+            HostRequest::Sleep { promise, delay_ms } => {
+                self.add_timer(TimerAction::Resolve(promise), delay_ms, None);
+                ScriptValue::Nil
+            }
+            HostRequest::Fetch { promise, url } => {
+                self.fetches.push_back(PendingFetch { promise, url });
+                ScriptValue::Nil
+            }
+        }
+    }
+
+    /// タイマーを登録し、IDを返す（時刻は最後に処理した時刻から数える）
+    pub fn add_timer(&mut self, action: TimerAction, delay_ms: u64, interval: Option<u64>) -> usize {
+        let id = self.next_timer_id;
+        self.next_timer_id += 1;
+        self.timers.insert(
+            id,
+            TimerInfo {
+                action,
+                execute_at: self.now + delay_ms,
+                interval: interval.map(|ms| ms.max(1)),
+            },
+        );
+        id
+    }
+
+    /// タイマーを止める
+    pub fn clear_timer(&mut self, id: usize) {
+        if let Some(timer) = self.timers.remove(&id) {
+            match timer.action {
+                TimerAction::Call(handle) | TimerAction::Resolve(handle) => self.released.push(handle),
+            }
+        }
+    }
+
+    /// 期限が来たタイマーを予定時刻順に取り出す（最後の実行なら true を添える）
+    ///
+    /// 繰り返しのタイマーは次の予定時刻に設定し直す。遅れた分はまとめて1回にする。
+    pub fn due_timers(&mut self, now: u64) -> Vec<(TimerAction, bool)> {
+        self.now = self.now.max(now);
+        let mut due: Vec<(u64, usize)> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.execute_at <= self.now)
+            .map(|(&id, timer)| (timer.execute_at, id))
+            .collect();
+        due.sort_unstable();
+
+        let mut actions = Vec::with_capacity(due.len());
+        for (_, id) in due {
+            let Some(timer) = self.timers.get_mut(&id) else {
+                continue;
+            };
+            match timer.interval {
+                Some(interval) => {
+                    while timer.execute_at <= self.now {
+                        timer.execute_at += interval;
+                    }
+                    actions.push((timer.action, false));
+                }
+                None => {
+                    actions.push((timer.action, true));
+                    self.timers.remove(&id);
+                }
+            }
+        }
+        actions
+    }
+
+    /// 最も早いタイマーの予定時刻
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.timers.values().map(|timer| timer.execute_at).min()
+    }
+
+    /// 登録中のタイマーの数
+    pub fn timer_count(&self) -> usize {
+        self.timers.len()
+    }
+
+    /// 取得待ちの fetch を取り出す
+    pub fn take_fetches(&mut self) -> Vec<PendingFetch> {
+        self.fetches.drain(..).collect()
+    }
+
+    /// 取得待ちの fetch があるか
+    pub fn has_fetches(&self) -> bool {
+        !self.fetches.is_empty()
+    }
+
+    /// 固定を外すハンドルを取り出す
+    pub fn take_released(&mut self) -> Vec<usize> {
+        core::mem::take(&mut self.released)
+    }
+}
+
+// ============================================================================
+// Driving the loop on the kernel executor
+// ============================================================================
+
+/// イベントループを1周回す
+///
+/// 期限が来たタイマーとキューのイベントをタスクにし、予算の範囲でタスクを実行してから、
+/// 取得待ちの fetch を1つずつ取得する。最後にエグゼキュータへ制御を返す。
+/// まだ仕事が残っていれば true を返す。
+pub async fn run_once(runtime: &mut ScriptRuntime) -> Result<bool, ScriptError> {
+    let mut first_error = runtime.process_timers(current_tick()).err();
+    if let Err(err) = runtime.process_events() {
+        first_error.get_or_insert(err);
+    }
+
+    for pending in runtime.take_fetches() {
+        let result = fetch(&pending.url).await;
+        runtime.complete_fetch(pending.promise, result);
+    }
+    let busy = match runtime.run_tasks() {
+        Ok(busy) => busy,
+        Err(err) => {
+            first_error.get_or_insert(err);
+            true
+        }
+    };
+
+    yield_now().await;
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(busy || runtime.has_pending_fetches()),
+    }
+}
+
+/// タイマーも取得待ちも実行待ちのタスクも無くなるまでイベントループを回す
+///
+/// 次のタイマーまでは `sleep_ms` で眠る。タスクのエラーでは止まらず、集めて返す。
+pub async fn run_until_idle(runtime: &mut ScriptRuntime) -> Vec<ScriptError> {
+    let mut errors = Vec::new();
+    loop {
+        let busy = match run_once(runtime).await {
+            Ok(busy) => busy,
+            Err(err) => {
+                errors.push(err);
+                true
+            }
+        };
+        if busy {
+            continue;
+        }
+        let Some(deadline) = runtime.next_timer_deadline() else {
+            return errors;
+        };
+        let wait = deadline.saturating_sub(current_tick()).clamp(1, MAX_IDLE_SLEEP_MS);
+        sleep_ms(wait).await;
+    }
+}
+
+/// URL を取得する
+async fn fetch(url: &str) -> Result<FetchResponse, String> {
+    let target = Url::parse(url).map_err(|e| format!("{}", e))?;
+    match http::send(http::Request::get(target)).await {
+        Ok(response) => Ok(FetchResponse {
+            status: response.status,
+            url: format!("{}", response.url),
+            content_type: response.content_type(),
+            body: response.text(),
+        }),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
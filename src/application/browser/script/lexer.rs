@@ -46,9 +46,13 @@ pub enum TokenKind {
     Match,
     Struct,
     Impl,
+    Trait,
+    Dyn,
+    Where,
     SelfLower,      // self
     Pub,
     Move,           // move (クロージャ)
+    Async,          // async（`await` は `.await` の位置でだけ意味を持つので識別子のまま）
     Underscore,     // _
 
     // 演算子
@@ -470,6 +474,11 @@ impl<'a> Lexer<'a> {
             "match" => TokenKind::Match,
             "struct" => TokenKind::Struct,
             "impl" => TokenKind::Impl,
+            "trait" => TokenKind::Trait,
+            "dyn" => TokenKind::Dyn,
+            "where" => TokenKind::Where,
+            "move" => TokenKind::Move,
+            "async" => TokenKind::Async,
             "self" => TokenKind::SelfLower,
             "pub" => TokenKind::Pub,
             "true" => TokenKind::True,
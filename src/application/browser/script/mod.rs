@@ -26,6 +26,14 @@
 //!
 //! // スタイル変更
 //! dom.get_element_by_id("title").style.color = "red";
+//!
+//! // タイマーとネットワーク（`.await` はタスクを中断し、他のタスクに譲る）
+//! async fn refresh() {
+//!     let response = fetch("http://example.com/count").await;
+//!     dom.get_element_by_id("counter").set_text(response.body);
+//! }
+//! set_interval(|| refresh(), 1000);
+//! sleep(500).await;
 //! </script>
 //! ```
 
@@ -34,10 +42,12 @@ extern crate alloc;
 pub mod lexer;
 pub mod parser;
 pub mod ast;
+pub mod compiler;
 pub mod vm;
 pub mod value;
 pub mod dom_binding;
 pub mod runtime;
+pub mod event_loop;
 pub mod shell_bridge;
 
 // Re-exports
@@ -52,6 +62,7 @@ pub use shell_bridge::ShellRuntime;
 
 use alloc::string::String;
 use alloc::format;
+use alloc::vec::Vec;
 
 /// スクリプトの実行結果型
 pub type ScriptResult<T> = Result<T, ScriptError>;
@@ -82,6 +93,8 @@ pub enum ErrorKind {
     Runtime,
     /// DOM操作エラー
     Dom,
+    /// メモリ上限超過
+    Memory,
 }
 
 impl ScriptError {
@@ -113,6 +126,10 @@ impl ScriptError {
     pub fn dom(message: &str) -> Self {
         Self::new(ErrorKind::Dom, message, 0, 0)
     }
+
+    pub fn memory(message: &str) -> Self {
+        Self::new(ErrorKind::Memory, message, 0, 0)
+    }
 }
 
 impl core::fmt::Display for ScriptError {
@@ -162,6 +179,11 @@ impl RustScriptEngine {
         self.runtime.dispatch_event(event);
     }
 
+    /// フォーム部品への入力を反映し、`input` イベントを発火
+    pub fn dispatch_input(&mut self, target_id: usize, value: &str) {
+        self.runtime.dispatch_input(target_id, value);
+    }
+
     /// イベントキューを処理
     pub fn process_events(&mut self) -> ScriptResult<()> {
         self.runtime.process_events()
@@ -172,8 +194,25 @@ impl RustScriptEngine {
         self.runtime.process_timers(current_tick)
     }
 
+    /// スクリプトをタスクとして実行待ちにする（実行はイベントループで進める）
+    pub fn spawn(&mut self, source: &str) -> ScriptResult<()> {
+        self.runtime.spawn(source)
+    }
+
+    /// イベントループを1周回す（仕事が残っていれば true）
+    ///
+    /// コンポジタのループから毎フレーム呼ぶ。1周で実行する命令数は予算で制限される。
+    pub async fn run_event_loop_once(&mut self) -> ScriptResult<bool> {
+        event_loop::run_once(&mut self.runtime).await
+    }
+
+    /// 仕事が無くなるまでイベントループを回し、途中のエラーを返す
+    pub async fn run_event_loop(&mut self) -> Vec<ScriptError> {
+        event_loop::run_until_idle(&mut self.runtime).await
+    }
+
     /// DOMバインディングへの参照を取得
-    pub fn dom(&mut self) -> &mut DomBinding {
+    pub fn dom(&mut self) -> core::cell::RefMut<'_, DomBinding> {
         self.runtime.dom()
     }
 }
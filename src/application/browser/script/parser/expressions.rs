@@ -8,6 +8,7 @@
 extern crate alloc;
 
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::vec;
 
@@ -24,7 +25,49 @@ use super::Parser;
 impl Parser {
     /// 式をパース
     pub(crate) fn parse_expression(&mut self) -> Result<Expr, ScriptError> {
-        self.parse_or_expression()
+        self.parse_range_expression()
+    }
+
+    /// 条件式（`if`/`while`/`for`/`match` の直後で、`{` は構造体リテラルでなくブロックを始める）
+    pub(crate) fn parse_condition(&mut self) -> Result<Expr, ScriptError> {
+        let no_struct_literal = core::mem::replace(&mut self.no_struct_literal, true);
+        let result = self.parse_expression();
+        self.no_struct_literal = no_struct_literal;
+        result
+    }
+
+    /// 範囲式: `a..b`, `a..=b`, `a..`
+    pub(crate) fn parse_range_expression(&mut self) -> Result<Expr, ScriptError> {
+        let start = self.parse_or_expression()?;
+
+        if !self.check(TokenKind::DotDot) && !self.check(TokenKind::DotDotEq) {
+            return Ok(start);
+        }
+        let inclusive = self.check(TokenKind::DotDotEq);
+        self.advance();
+
+        let open_ended = self.is_at_end()
+            || [
+                TokenKind::RightParen,
+                TokenKind::RightBracket,
+                TokenKind::RightBrace,
+                TokenKind::LeftBrace,
+                TokenKind::Comma,
+                TokenKind::Semicolon,
+            ]
+            .iter()
+            .any(|&kind| self.check(kind));
+        let end = if open_ended {
+            None
+        } else {
+            Some(Box::new(self.parse_or_expression()?))
+        };
+
+        Ok(Expr::Range {
+            start: Some(Box::new(start)),
+            end,
+            inclusive,
+        })
     }
 
     /// 論理OR式
@@ -251,6 +294,27 @@ impl Parser {
             });
         }
 
+        // 参照: `&expr`, `&mut expr`
+        if self.check(TokenKind::Ampersand) {
+            self.advance();
+            let mutable = self.check(TokenKind::Mut);
+            if mutable {
+                self.advance();
+            }
+            let operand = self.parse_unary_expression()?;
+            return Ok(Expr::Ref {
+                mutable,
+                expr: Box::new(operand),
+            });
+        }
+
+        // デリファレンス: `*expr`
+        if self.check(TokenKind::Star) {
+            self.advance();
+            let operand = self.parse_unary_expression()?;
+            return Ok(Expr::Deref(Box::new(operand)));
+        }
+
         self.parse_postfix_expression()
     }
 
@@ -282,7 +346,9 @@ impl Parser {
                 self.advance();
                 let name = self.expect_identifier()?;
 
-                if self.check(TokenKind::LeftParen) {
+                if name == "await" && !self.check(TokenKind::LeftParen) {
+                    expr = Expr::Await(Box::new(expr));
+                } else if self.check(TokenKind::LeftParen) {
                     // メソッド呼び出し
                     self.advance();
                     let method_args = self.parse_arguments()?;
@@ -302,6 +368,13 @@ impl Parser {
             } else if self.check(TokenKind::DoubleColon) {
                 // 名前空間アクセス (Pathとして扱う)
                 self.advance();
+
+                // ターボフィッシュ `::<T>` の型引数は消去する
+                if self.check(TokenKind::Lt) {
+                    let _ = self.parse_generic_args()?;
+                    continue;
+                }
+
                 let member = self.expect_identifier()?;
                 // 既存のPathに追加するか、新しいPathを作成
                 match expr {
@@ -345,8 +418,7 @@ impl Parser {
 
         if self.check(TokenKind::StringLit) {
             let token = self.advance();
-            let value = self.parse_string_literal(&token.lexeme);
-            return Ok(Expr::Literal(Literal::String(value)));
+            return Ok(Expr::Literal(Literal::String(token.lexeme)));
         }
 
         if self.check(TokenKind::True) {
@@ -364,10 +436,14 @@ impl Parser {
             return Ok(Expr::Literal(Literal::Nil));
         }
 
-        // 識別子
+        // 識別子または構造体リテラル
         if self.check(TokenKind::Identifier) {
             let token = self.advance();
-            return Ok(Expr::Identifier(token.lexeme.clone()));
+            let is_type_name = token.lexeme.starts_with(|c: char| c.is_ascii_uppercase());
+            if is_type_name && !self.no_struct_literal && self.check(TokenKind::LeftBrace) {
+                return self.parse_struct_literal(token.lexeme);
+            }
+            return Ok(Expr::Identifier(token.lexeme));
         }
 
         // セルフ参照
@@ -426,8 +502,13 @@ impl Parser {
             return self.parse_match_expression();
         }
 
-        // クロージャ
-        if self.check(TokenKind::Pipe) || self.check(TokenKind::Move) {
+        // asyncブロック・asyncクロージャ
+        if self.check(TokenKind::Async) {
+            return self.parse_async_expression();
+        }
+
+        // クロージャ（`||` は引数なし）
+        if self.check(TokenKind::Pipe) || self.check(TokenKind::Or) || self.check(TokenKind::Move) {
             return self.parse_closure_expression();
         }
 
@@ -472,16 +553,39 @@ impl Parser {
         Ok(Expr::Array(elements))
     }
 
+    /// 構造体リテラル: `Point { x: 1, y }`
+    pub(crate) fn parse_struct_literal(&mut self, name: String) -> Result<Expr, ScriptError> {
+        self.expect(TokenKind::LeftBrace)?;
+
+        let mut fields = Vec::new();
+        while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+            let field = self.expect_identifier()?;
+            let value = if self.check(TokenKind::Colon) {
+                self.advance();
+                self.parse_expression()?
+            } else {
+                // 省略記法 `Point { x }`
+                Expr::Identifier(field.clone())
+            };
+            fields.push((field, value));
+
+            if !self.check(TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        self.expect(TokenKind::RightBrace)?;
+
+        Ok(Expr::StructLit { name, fields })
+    }
+
     /// ブロック式
     pub(crate) fn parse_block_expression(&mut self) -> Result<Expr, ScriptError> {
-        let block = self.parse_block()?;
-        let statements = match block {
-            Stmt::Block(stmts) => stmts,
-            _ => vec![block],
-        };
+        let (statements, value) = self.parse_block_contents()?;
         Ok(Expr::Block {
             statements,
-            value: None,
+            value: value.map(Box::new),
         })
     }
 
@@ -489,8 +593,8 @@ impl Parser {
     pub(crate) fn parse_if_expression(&mut self) -> Result<Expr, ScriptError> {
         self.expect(TokenKind::If)?;
 
-        let condition = self.parse_expression()?;
-        let then_block = self.parse_block()?;
+        let condition = self.parse_condition()?;
+        let then_expr = self.parse_block_expression()?;
 
         let else_block = if self.check(TokenKind::Else) {
             self.advance();
@@ -498,29 +602,12 @@ impl Parser {
                 let else_if = self.parse_if_expression()?;
                 Some(Box::new(else_if))
             } else {
-                let else_stmt = self.parse_block()?;
-                // Block文をBlock式に変換
-                Some(Box::new(Expr::Block {
-                    statements: match else_stmt {
-                        Stmt::Block(stmts) => stmts,
-                        _ => vec![else_stmt],
-                    },
-                    value: None,
-                }))
+                Some(Box::new(self.parse_block_expression()?))
             }
         } else {
             None
         };
 
-        // then_blockをExprに変換
-        let then_expr = Expr::Block {
-            statements: match then_block {
-                Stmt::Block(stmts) => stmts,
-                _ => vec![then_block],
-            },
-            value: None,
-        };
-
         Ok(Expr::If {
             condition: Box::new(condition),
             then_branch: Box::new(then_expr),
@@ -532,7 +619,7 @@ impl Parser {
     pub(crate) fn parse_match_expression(&mut self) -> Result<Expr, ScriptError> {
         self.expect(TokenKind::Match)?;
 
-        let value = self.parse_expression()?;
+        let value = self.parse_condition()?;
 
         self.expect(TokenKind::LeftBrace)?;
 
@@ -581,6 +668,23 @@ impl Parser {
         })
     }
 
+    /// `async { ... }` / `async move { ... }` / `async |x| ...`
+    pub(crate) fn parse_async_expression(&mut self) -> Result<Expr, ScriptError> {
+        self.expect(TokenKind::Async)?;
+
+        // `async move {` の move はブロックでは意味を持たない（値は参照で共有する）
+        if self.check(TokenKind::Move) && self.check_next(TokenKind::LeftBrace) {
+            self.advance();
+        }
+
+        let body = if self.check(TokenKind::LeftBrace) {
+            self.parse_block_expression()?
+        } else {
+            self.parse_closure_expression()?
+        };
+        Ok(Expr::Async(Box::new(body)))
+    }
+
     /// クロージャ式
     pub(crate) fn parse_closure_expression(&mut self) -> Result<Expr, ScriptError> {
         let _is_move = if self.check(TokenKind::Move) {
@@ -590,31 +694,16 @@ impl Parser {
             false
         };
 
-        self.expect(TokenKind::Pipe)?;
-
-        let mut params = Vec::new();
-        if !self.check(TokenKind::Pipe) {
-            loop {
-                let name = self.expect_identifier()?;
-                let type_annotation = if self.check(TokenKind::Colon) {
-                    self.advance();
-                    Some(self.parse_type_annotation()?)
-                } else {
-                    None
-                };
-                params.push(ClosureParam {
-                    name,
-                    type_ann: type_annotation,
-                });
-
-                if !self.check(TokenKind::Comma) {
-                    break;
-                }
-                self.advance();
-            }
-        }
-
-        self.expect(TokenKind::Pipe)?;
+        let params = if self.check(TokenKind::Or) {
+            // `||`: 引数なし
+            self.advance();
+            Vec::new()
+        } else {
+            self.expect(TokenKind::Pipe)?;
+            let params = self.parse_closure_params()?;
+            self.expect(TokenKind::Pipe)?;
+            params
+        };
 
         // 戻り値の型（クロージャでは無視）
         if self.check(TokenKind::Arrow) {
@@ -624,14 +713,7 @@ impl Parser {
 
         // 本体
         let body = if self.check(TokenKind::LeftBrace) {
-            let block_stmt = self.parse_block()?;
-            Expr::Block {
-                statements: match block_stmt {
-                    Stmt::Block(stmts) => stmts,
-                    _ => vec![block_stmt],
-                },
-                value: None,
-            }
+            self.parse_block_expression()?
         } else {
             self.parse_expression()?
         };
@@ -641,4 +723,33 @@ impl Parser {
             body: Box::new(body),
         })
     }
+
+    /// クロージャの引数リスト（`|` の間）
+    pub(crate) fn parse_closure_params(&mut self) -> Result<Vec<ClosureParam>, ScriptError> {
+        let mut params = Vec::new();
+        if self.check(TokenKind::Pipe) {
+            return Ok(params);
+        }
+
+        loop {
+            let name = self.expect_identifier()?;
+            let type_annotation = if self.check(TokenKind::Colon) {
+                self.advance();
+                Some(self.parse_type_annotation()?)
+            } else {
+                None
+            };
+            params.push(ClosureParam {
+                name,
+                type_ann: type_annotation,
+            });
+
+            if !self.check(TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+
+        Ok(params)
+    }
 }
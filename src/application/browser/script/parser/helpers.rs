@@ -10,6 +10,7 @@ use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
+use alloc::vec;
 
 use crate::application::browser::script::lexer::{Token, TokenKind};
 use crate::application::browser::script::ast::*;
@@ -37,8 +38,7 @@ impl Parser {
 
         if self.check(TokenKind::StringLit) {
             let token = self.advance();
-            let value = self.parse_string_literal(&token.lexeme);
-            return Ok(Pattern::Literal(Expr::Literal(Literal::String(value))));
+            return Ok(Pattern::Literal(Expr::Literal(Literal::String(token.lexeme))));
         }
 
         if self.check(TokenKind::True) {
@@ -86,22 +86,30 @@ impl Parser {
 
             // ジェネリック型
             if self.check(TokenKind::Lt) {
-                self.advance();
-                let mut params = Vec::new();
-                loop {
-                    params.push(self.parse_type_annotation()?);
-                    if !self.check(TokenKind::Comma) {
-                        break;
-                    }
-                    self.advance();
-                }
-                self.expect(TokenKind::Gt)?;
+                let params = self.parse_generic_args()?;
                 return Ok(TypeAnnotation::Generic { name, params });
             }
 
+            // クロージャのトレイト: `Fn(T1, T2) -> R`
+            if matches!(name.as_str(), "Fn" | "FnMut" | "FnOnce") && self.check(TokenKind::LeftParen) {
+                return self.parse_function_type();
+            }
+
             return Ok(TypeAnnotation::Simple(name));
         }
 
+        // トレイト境界型: `impl Trait`, `dyn Trait`
+        if self.check(TokenKind::Impl) || self.check(TokenKind::Dyn) {
+            let dynamic = self.check(TokenKind::Dyn);
+            self.advance();
+            let mut traits = vec![self.parse_type_annotation()?];
+            while self.check(TokenKind::Plus) {
+                self.advance();
+                traits.push(self.parse_type_annotation()?);
+            }
+            return Ok(TypeAnnotation::Bound { dynamic, traits });
+        }
+
         // 配列型
         if self.check(TokenKind::LeftBracket) {
             self.advance();
@@ -149,40 +157,148 @@ impl Parser {
         // 関数型
         if self.check(TokenKind::Fn) {
             self.advance();
-            self.expect(TokenKind::LeftParen)?;
-            let mut params = Vec::new();
-            if !self.check(TokenKind::RightParen) {
-                loop {
-                    params.push(self.parse_type_annotation()?);
-                    if !self.check(TokenKind::Comma) {
-                        break;
-                    }
-                    self.advance();
+            return self.parse_function_type();
+        }
+
+        // Option型のシンタックスシュガー
+        if self.check(TokenKind::Question) {
+            self.advance();
+            let inner = self.parse_type_annotation()?;
+            return Ok(TypeAnnotation::Optional(Box::new(inner)));
+        }
+
+        Err(self.error("Expected type annotation"))
+    }
+
+    /// 関数型の引数と戻り値: `(T1, T2) -> R`
+    pub(crate) fn parse_function_type(&mut self) -> Result<TypeAnnotation, ScriptError> {
+        self.expect(TokenKind::LeftParen)?;
+        let mut params = Vec::new();
+        if !self.check(TokenKind::RightParen) {
+            loop {
+                params.push(self.parse_type_annotation()?);
+                if !self.check(TokenKind::Comma) {
+                    break;
                 }
+                self.advance();
             }
-            self.expect(TokenKind::RightParen)?;
+        }
+        self.expect(TokenKind::RightParen)?;
+
+        let return_type = if self.check(TokenKind::Arrow) {
+            self.advance();
+            self.parse_type_annotation()?
+        } else {
+            TypeAnnotation::Simple(String::from("()"))
+        };
+
+        Ok(TypeAnnotation::Function {
+            params,
+            return_type: Box::new(return_type),
+        })
+    }
+
+    /// 型引数リスト: `<T1, T2>`
+    pub(crate) fn parse_generic_args(&mut self) -> Result<Vec<TypeAnnotation>, ScriptError> {
+        self.expect(TokenKind::Lt)?;
+        let mut params = Vec::new();
+        loop {
+            params.push(self.parse_type_annotation()?);
+            if !self.check(TokenKind::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_generic_close()?;
+        Ok(params)
+    }
+
+    /// 型パラメータ宣言: `<T: Bound + Other, U>`（なければ空）
+    pub(crate) fn parse_generic_params(&mut self) -> Result<Vec<GenericParam>, ScriptError> {
+        let mut generics = Vec::new();
+        if !self.check(TokenKind::Lt) {
+            return Ok(generics);
+        }
+        self.advance();
 
-            let return_type = if self.check(TokenKind::Arrow) {
+        while !self.check(TokenKind::Gt) && !self.check(TokenKind::Shr) && !self.is_at_end() {
+            let name = self.expect_identifier()?;
+            let bounds = if self.check(TokenKind::Colon) {
                 self.advance();
-                self.parse_type_annotation()?
+                self.parse_bounds()?
             } else {
-                TypeAnnotation::Simple(String::from("()"))
+                Vec::new()
             };
+            generics.push(GenericParam { name, bounds });
 
-            return Ok(TypeAnnotation::Function {
-                params,
-                return_type: Box::new(return_type),
-            });
+            if !self.check(TokenKind::Comma) {
+                break;
+            }
+            self.advance();
         }
+        self.expect_generic_close()?;
 
-        // Option型のシンタックスシュガー
-        if self.check(TokenKind::Question) {
+        Ok(generics)
+    }
+
+    /// where節: `where T: Bound, U: Other`（境界を型パラメータに追加する）
+    pub(crate) fn parse_where_clause(&mut self, generics: &mut Vec<GenericParam>) -> Result<(), ScriptError> {
+        if !self.check(TokenKind::Where) {
+            return Ok(());
+        }
+        self.advance();
+
+        while self.check(TokenKind::Identifier) {
+            let name = self.expect_identifier()?;
+            self.expect(TokenKind::Colon)?;
+            let bounds = self.parse_bounds()?;
+            match generics.iter_mut().find(|g| g.name == name) {
+                Some(param) => param.bounds.extend(bounds),
+                None => generics.push(GenericParam { name, bounds }),
+            }
+
+            if !self.check(TokenKind::Comma) {
+                break;
+            }
             self.advance();
-            let inner = self.parse_type_annotation()?;
-            return Ok(TypeAnnotation::Optional(Box::new(inner)));
         }
 
-        Err(self.error("Expected type annotation"))
+        Ok(())
+    }
+
+    /// トレイト境界のリスト: `A + B<T>`（型引数は消去してトレイト名だけを残す）
+    pub(crate) fn parse_bounds(&mut self) -> Result<Vec<String>, ScriptError> {
+        let mut bounds = Vec::new();
+        loop {
+            let bound = self.parse_type_annotation()?;
+            bounds.push(self.type_name(&bound)?);
+            if !self.check(TokenKind::Plus) {
+                break;
+            }
+            self.advance();
+        }
+        Ok(bounds)
+    }
+
+    /// 型注釈の名前（`Wrapper<T>` → `Wrapper`）
+    pub(crate) fn type_name(&self, ty: &TypeAnnotation) -> Result<String, ScriptError> {
+        match ty {
+            TypeAnnotation::Simple(name) | TypeAnnotation::Generic { name, .. } => Ok(name.clone()),
+            TypeAnnotation::Function { .. } => Ok(String::from("Fn")),
+            _ => Err(self.error("Expected type name")),
+        }
+    }
+
+    /// 型引数の閉じ `>` を消費（`>>` は半分だけ消費する）
+    pub(crate) fn expect_generic_close(&mut self) -> Result<(), ScriptError> {
+        if self.check(TokenKind::Shr) {
+            let token = &mut self.tokens[self.current];
+            token.kind = TokenKind::Gt;
+            token.lexeme = String::from(">");
+            return Ok(());
+        }
+        self.expect(TokenKind::Gt)?;
+        Ok(())
     }
 
     /// 関数パラメータをパース
@@ -194,13 +310,18 @@ impl Parser {
         }
 
         loop {
+            let mutable = self.check(TokenKind::Mut);
+            if mutable {
+                self.advance();
+            }
+
             // self参照
             if self.check(TokenKind::SelfLower) {
                 self.advance();
                 params.push(FunctionParam {
                     name: String::from("self"),
                     type_ann: None,
-                    mutable: false,
+                    mutable,
                 });
             } else if self.check(TokenKind::Ampersand) {
                 // &self または &mut self
@@ -212,13 +333,8 @@ impl Parser {
                     false
                 };
                 self.expect(TokenKind::SelfLower)?;
-                let name = if is_mutable {
-                    String::from("&mut self")
-                } else {
-                    String::from("&self")
-                };
                 params.push(FunctionParam {
-                    name,
+                    name: String::from("self"),
                     type_ann: None,
                     mutable: is_mutable,
                 });
@@ -237,7 +353,7 @@ impl Parser {
                 params.push(FunctionParam {
                     name: param_name,
                     type_ann: type_annotation,
-                    mutable: false,
+                    mutable,
                 });
             }
 
@@ -294,46 +410,6 @@ impl Parser {
             .map_err(|_| self.error("Invalid float"))
     }
 
-    /// 文字列リテラルをパース（エスケープ処理）
-    pub(crate) fn parse_string_literal(&self, s: &str) -> String {
-        // 最初と最後のクォートを除去
-        let s = if s.len() >= 2 {
-            &s[1..s.len()-1]
-        } else {
-            s
-        };
-
-        let mut result = String::new();
-        let mut chars = s.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '\\' {
-                if let Some(&next) = chars.peek() {
-                    chars.next();
-                    match next {
-                        'n' => result.push('\n'),
-                        'r' => result.push('\r'),
-                        't' => result.push('\t'),
-                        '\\' => result.push('\\'),
-                        '"' => result.push('"'),
-                        '\'' => result.push('\''),
-                        '0' => result.push('\0'),
-                        _ => {
-                            result.push('\\');
-                            result.push(next);
-                        }
-                    }
-                } else {
-                    result.push('\\');
-                }
-            } else {
-                result.push(c);
-            }
-        }
-
-        result
-    }
-
     // ========================================================================
     // Token Utilities
     // ========================================================================
@@ -346,6 +422,13 @@ impl Parser {
         self.peek().kind == kind
     }
 
+    /// 次のトークンが指定した種類かチェック（1トークン先読み）
+    pub(crate) fn check_next(&self, kind: TokenKind) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.kind == kind)
+    }
+
     /// 次のトークンに進む
     pub(crate) fn advance(&mut self) -> Token {
         if !self.is_at_end() {
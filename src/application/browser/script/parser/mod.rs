@@ -34,12 +34,18 @@ pub struct Parser {
     tokens: Vec<Token>,
     /// 現在位置
     current: usize,
+    /// 構造体リテラルを禁止するか（`if`/`while` の条件式など `{` がブロックを始める位置）
+    no_struct_literal: bool,
 }
 
 impl Parser {
     /// 新しいパーサーを作成
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            no_struct_literal: false,
+        }
     }
 
     /// パース
@@ -76,6 +82,7 @@ impl Parser {
 mod tests {
     use super::*;
     use super::super::lexer::Lexer;
+    use super::super::ast::Stmt;
 
     fn parse(source: &str) -> Result<Ast, ScriptError> {
         let mut lexer = Lexer::new(source);
@@ -101,4 +108,51 @@ mod tests {
         let ast = parse("if x > 0 { 1 } else { -1 }").unwrap();
         assert_eq!(ast.statements.len(), 1);
     }
+
+    #[test]
+    fn test_trait_and_generics() {
+        let ast = parse("
+            pub trait Shape { fn area(&self) -> f64; fn name(&self) -> String { String::from(\"shape\") } }
+            impl<T: Clone> Shape for Wrapper<T> where T: Shape { fn area(&self) -> f64 { 1.0 } }
+            fn total<S>(items: &[Box<dyn Shape>], f: impl Fn(&S) -> Vec<Vec<i32>>) -> f64 where S: Shape { 0.0 }
+        ").unwrap();
+        assert_eq!(ast.statements.len(), 3);
+
+        match &ast.statements[0] {
+            Stmt::Trait { name, methods } => {
+                assert_eq!(name, "Shape");
+                assert!(methods[0].default.is_none());
+                assert!(methods[1].default.is_some());
+            }
+            _ => panic!("Expected trait"),
+        }
+        match &ast.statements[1] {
+            Stmt::Impl { generics, trait_name, type_name, .. } => {
+                assert_eq!(trait_name.as_deref(), Some("Shape"));
+                assert_eq!(type_name, "Wrapper");
+                assert_eq!(generics[0].bounds.len(), 2);
+            }
+            _ => panic!("Expected impl"),
+        }
+        match &ast.statements[2] {
+            Stmt::Function { generics, params, .. } => {
+                assert_eq!(generics[0].bounds[0], "Shape");
+                assert_eq!(params.len(), 2);
+            }
+            _ => panic!("Expected function"),
+        }
+    }
+
+    #[test]
+    fn test_async_and_await() {
+        let ast = parse("
+            async fn load(url: String) -> String { fetch(url).await.body }
+            let task = async move { load(\"a\").await };
+        ").unwrap();
+        assert_eq!(ast.statements.len(), 2);
+        match &ast.statements[0] {
+            Stmt::Function { is_async, .. } => assert!(*is_async),
+            _ => panic!("Expected function"),
+        }
+    }
 }
@@ -16,6 +16,15 @@ use crate::application::browser::script::{ScriptError};
 
 use super::Parser;
 
+/// 関数シグネチャ（関数定義とトレイトのメソッド宣言で共有）
+pub(crate) struct FunctionSignature {
+    pub name: String,
+    pub is_async: bool,
+    pub generics: Vec<GenericParam>,
+    pub params: Vec<FunctionParam>,
+    pub return_type: Option<TypeAnnotation>,
+}
+
 // ============================================================================
 // Statement Parsing Methods
 // ============================================================================
@@ -32,15 +41,23 @@ impl Parser {
             return Ok(Stmt::Empty);
         }
 
+        // 可視性は無視する
+        if self.check(TokenKind::Pub) {
+            self.advance();
+            return self.parse_statement();
+        }
+
         // キーワードによる分岐
         if self.check(TokenKind::Let) {
             return self.parse_let_statement();
         }
-        if self.check(TokenKind::Fn) {
+        if self.check(TokenKind::Fn) || (self.check(TokenKind::Async) && self.check_next(TokenKind::Fn)) {
             return self.parse_function_statement();
         }
         if self.check(TokenKind::If) {
-            return self.parse_if_statement();
+            // 文の位置の if も式として扱う（ブロック末尾なら値になる）
+            let expr = self.parse_if_expression()?;
+            return Ok(Stmt::Expression(expr));
         }
         if self.check(TokenKind::While) {
             return self.parse_while_statement();
@@ -68,12 +85,16 @@ impl Parser {
         if self.check(TokenKind::Impl) {
             return self.parse_impl_statement();
         }
+        if self.check(TokenKind::Trait) {
+            return self.parse_trait_statement();
+        }
         if self.check(TokenKind::Match) {
             let expr = self.parse_match_expression()?;
             return Ok(Stmt::Expression(expr));
         }
         if self.check(TokenKind::LeftBrace) {
-            return self.parse_block_statement();
+            let expr = self.parse_block_expression()?;
+            return Ok(Stmt::Expression(expr));
         }
 
         // 代入または式文
@@ -125,9 +146,31 @@ impl Parser {
 
     /// 関数定義
     pub(crate) fn parse_function_statement(&mut self) -> Result<Stmt, ScriptError> {
+        let signature = self.parse_function_signature()?;
+
+        // 関数本体
+        let body = self.parse_function_body()?;
+
+        Ok(Stmt::Function {
+            name: signature.name,
+            is_async: signature.is_async,
+            generics: signature.generics,
+            params: signature.params,
+            return_type: signature.return_type,
+            body: Box::new(body),
+        })
+    }
+
+    /// 関数シグネチャ: `[async] fn name<T: Bound>(params) -> ret where T: Bound`
+    pub(crate) fn parse_function_signature(&mut self) -> Result<FunctionSignature, ScriptError> {
+        let is_async = self.check(TokenKind::Async);
+        if is_async {
+            self.advance();
+        }
         self.expect(TokenKind::Fn)?;
 
         let name = self.expect_identifier()?;
+        let mut generics = self.parse_generic_params()?;
 
         self.expect(TokenKind::LeftParen)?;
         let params = self.parse_function_params()?;
@@ -141,48 +184,31 @@ impl Parser {
             None
         };
 
-        // 関数本体
-        let body = self.parse_block()?;
+        self.parse_where_clause(&mut generics)?;
 
-        Ok(Stmt::Function {
+        Ok(FunctionSignature {
             name,
+            is_async,
+            generics,
             params,
             return_type,
-            body: Box::new(body),
         })
     }
 
-    /// if文
-    pub(crate) fn parse_if_statement(&mut self) -> Result<Stmt, ScriptError> {
-        self.expect(TokenKind::If)?;
-
-        let condition = self.parse_expression()?;
-        let then_block = self.parse_block()?;
-
-        let else_block = if self.check(TokenKind::Else) {
-            self.advance();
-            if self.check(TokenKind::If) {
-                // else if
-                Some(Box::new(self.parse_if_statement()?))
-            } else {
-                Some(Box::new(self.parse_block_statement()?))
-            }
-        } else {
-            None
-        };
-
-        Ok(Stmt::If {
-            condition,
-            then_branch: Box::new(then_block),
-            else_branch: else_block,
-        })
+    /// 関数本体（末尾の式は戻り値として `return` に変換する）
+    pub(crate) fn parse_function_body(&mut self) -> Result<Stmt, ScriptError> {
+        let (mut statements, tail) = self.parse_block_contents()?;
+        if let Some(tail) = tail {
+            statements.push(Stmt::Return(Some(tail)));
+        }
+        Ok(Stmt::Block(statements))
     }
 
     /// while文
     pub(crate) fn parse_while_statement(&mut self) -> Result<Stmt, ScriptError> {
         self.expect(TokenKind::While)?;
 
-        let condition = self.parse_expression()?;
+        let condition = self.parse_condition()?;
         let body = self.parse_block()?;
 
         Ok(Stmt::While {
@@ -199,7 +225,7 @@ impl Parser {
 
         self.expect(TokenKind::In)?;
 
-        let iterable = self.parse_expression()?;
+        let iterable = self.parse_condition()?;
         let body = self.parse_block()?;
 
         // pattern を String に変換
@@ -244,6 +270,8 @@ impl Parser {
         self.expect(TokenKind::Struct)?;
 
         let name = self.expect_identifier()?;
+        let mut generics = self.parse_generic_params()?;
+        self.parse_where_clause(&mut generics)?;
 
         self.expect(TokenKind::LeftBrace)?;
 
@@ -258,6 +286,11 @@ impl Parser {
                 break;
             }
 
+            let public = self.check(TokenKind::Pub);
+            if public {
+                self.advance();
+            }
+
             let field_name = self.expect_identifier()?;
             self.expect(TokenKind::Colon)?;
             let field_type = self.parse_type_annotation()?;
@@ -265,7 +298,7 @@ impl Parser {
             fields.push(StructField {
                 name: field_name,
                 type_ann: field_type,
-                public: false,
+                public,
             });
 
             // コンマは省略可能
@@ -276,14 +309,31 @@ impl Parser {
 
         self.expect(TokenKind::RightBrace)?;
 
-        Ok(Stmt::Struct { name, fields })
+        Ok(Stmt::Struct {
+            name,
+            generics,
+            fields,
+        })
     }
 
     /// impl定義
     pub(crate) fn parse_impl_statement(&mut self) -> Result<Stmt, ScriptError> {
         self.expect(TokenKind::Impl)?;
 
-        let type_name = self.expect_identifier()?;
+        let mut generics = self.parse_generic_params()?;
+
+        // `impl Trait for Type` または `impl Type`
+        let first = self.parse_type_annotation()?;
+        let (trait_name, target) = if self.check(TokenKind::For) {
+            self.advance();
+            (Some(first), self.parse_type_annotation()?)
+        } else {
+            (None, first)
+        };
+        let trait_name = trait_name.map(|t| self.type_name(&t)).transpose()?;
+        let type_name = self.type_name(&target)?;
+
+        self.parse_where_clause(&mut generics)?;
 
         self.expect(TokenKind::LeftBrace)?;
 
@@ -298,26 +348,88 @@ impl Parser {
                 break;
             }
 
+            if self.check(TokenKind::Pub) {
+                self.advance();
+            }
+
             let method = self.parse_function_statement()?;
             methods.push(method);
         }
 
         self.expect(TokenKind::RightBrace)?;
 
-        Ok(Stmt::Impl { type_name, methods })
+        Ok(Stmt::Impl {
+            generics,
+            trait_name,
+            type_name,
+            methods,
+        })
     }
 
-    /// ブロック文
-    pub(crate) fn parse_block_statement(&mut self) -> Result<Stmt, ScriptError> {
-        let block = self.parse_block()?;
-        Ok(block)
+    /// trait定義
+    pub(crate) fn parse_trait_statement(&mut self) -> Result<Stmt, ScriptError> {
+        self.expect(TokenKind::Trait)?;
+
+        let name = self.expect_identifier()?;
+        // トレイトの型パラメータは消去する
+        let _ = self.parse_generic_params()?;
+
+        self.expect(TokenKind::LeftBrace)?;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
+            while self.check(TokenKind::Newline) || self.check(TokenKind::Semicolon) {
+                self.advance();
+            }
+
+            if self.check(TokenKind::RightBrace) {
+                break;
+            }
+
+            let signature = self.parse_function_signature()?;
+
+            // `;` で終われば宣言のみ、ブロックが続けばデフォルト実装
+            let default = if self.check(TokenKind::LeftBrace) {
+                Some(Box::new(self.parse_function_body()?))
+            } else {
+                self.expect(TokenKind::Semicolon)?;
+                None
+            };
+
+            methods.push(TraitMethod {
+                name: signature.name,
+                is_async: signature.is_async,
+                params: signature.params,
+                return_type: signature.return_type,
+                default,
+            });
+        }
+
+        self.expect(TokenKind::RightBrace)?;
+
+        Ok(Stmt::Trait { name, methods })
     }
 
     /// ブロック
     pub(crate) fn parse_block(&mut self) -> Result<Stmt, ScriptError> {
+        let (mut statements, tail) = self.parse_block_contents()?;
+        if let Some(tail) = tail {
+            statements.push(Stmt::Expression(tail));
+        }
+        Ok(Stmt::Block(statements))
+    }
+
+    /// ブロックの中身: `{ stmts; expr }`
+    ///
+    /// `;` で終わらない末尾の式はブロックの値として分けて返す。
+    pub(crate) fn parse_block_contents(&mut self) -> Result<(Vec<Stmt>, Option<Expr>), ScriptError> {
         self.expect(TokenKind::LeftBrace)?;
 
+        // ブロック内では構造体リテラルを再び許可する
+        let no_struct_literal = core::mem::replace(&mut self.no_struct_literal, false);
+
         let mut statements = Vec::new();
+        let mut tail = None;
         while !self.check(TokenKind::RightBrace) && !self.is_at_end() {
             // 改行やセミコロンをスキップ
             while self.check(TokenKind::Newline) || self.check(TokenKind::Semicolon) {
@@ -328,12 +440,20 @@ impl Parser {
                 break;
             }
 
-            statements.push(self.parse_statement()?);
+            let stmt = self.parse_statement()?;
+            let terminated = self.previous().kind == TokenKind::Semicolon;
+            match stmt {
+                Stmt::Expression(expr) if !terminated && self.check(TokenKind::RightBrace) => {
+                    tail = Some(expr);
+                }
+                stmt => statements.push(stmt),
+            }
         }
 
         self.expect(TokenKind::RightBrace)?;
+        self.no_struct_literal = no_struct_literal;
 
-        Ok(Stmt::Block(statements))
+        Ok((statements, tail))
     }
 
     /// 式文または代入文
@@ -6,22 +6,28 @@
 //!
 //! RustScriptの実行環境を提供する。
 //! DOM操作、イベント処理、タイマー管理を統合。
+//!
+//! イベントハンドラとタイマーのコールバックはそれぞれタスクとして実行待ちにし、
+//! `run_tasks` が命令数の予算の範囲で進める。非同期のループは `event_loop` を参照。
 
 extern crate alloc;
 
-use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
-use alloc::string::String;
-use alloc::vec::Vec;
 use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::{RefCell, RefMut};
 
 use super::lexer::Lexer;
 use super::parser::Parser;
 use super::ast::*;
-use super::value::{ScriptValue, NativeFunction, NativeFunctionId, FunctionValue};
+use super::compiler::Compiler;
+use super::value::{ScriptValue, NativeFunctionId};
 use super::vm::{VirtualMachine, Instruction, ConstantPool, DomOperation};
 use super::dom_binding::{DomBinding, DocumentNode};
+use super::event_loop::{EventLoop, FetchResponse, PendingFetch, TimerAction, DEFAULT_INSTRUCTION_BUDGET};
 use super::{ScriptResult, ScriptError, ErrorKind};
 
 // ============================================================================
@@ -32,16 +38,16 @@ use super::{ScriptResult, ScriptError, ErrorKind};
 pub struct ScriptRuntime {
     /// 仮想マシン
     vm: VirtualMachine,
-    /// DOMバインディング
-    dom: DomBinding,
+    /// DOMバインディング（VMのDOMコールバックと共有）
+    dom: Rc<RefCell<DomBinding>>,
     /// コンパイル済みコード
     compiled_scripts: Vec<CompiledScript>,
     /// グローバル変数
     globals: BTreeMap<String, ScriptValue>,
-    /// タイマー（ID -> タイマー情報）
-    timers: BTreeMap<usize, TimerInfo>,
-    /// 次のタイマーID
-    next_timer_id: usize,
+    /// タイマーと取得待ちの fetch（VMのホストコールバックと共有）
+    event_loop: Rc<RefCell<EventLoop>>,
+    /// `run_tasks` 1回で実行する命令数
+    instruction_budget: usize,
     /// イベントキュー
     event_queue: Vec<Event>,
     /// 登録済みイベントハンドラ
@@ -60,19 +66,6 @@ struct CompiledScript {
     functions: BTreeMap<String, usize>,
 }
 
-/// タイマー情報
-#[derive(Debug, Clone)]
-struct TimerInfo {
-    /// タイマーID
-    id: usize,
-    /// コールバックのバイトコード位置
-    callback_addr: usize,
-    /// 実行予定時刻（ティック）
-    execute_at: u64,
-    /// 繰り返し間隔（setIntervalの場合）
-    interval: Option<u64>,
-}
-
 /// イベント
 #[derive(Debug, Clone)]
 pub struct Event {
@@ -101,13 +94,21 @@ struct RegisteredHandler {
 
 impl ScriptRuntime {
     pub fn new() -> Self {
+        let dom = Rc::new(RefCell::new(DomBinding::new()));
+        let mut vm = VirtualMachine::new();
+        let binding = Rc::clone(&dom);
+        vm.set_dom_callback(move |op| binding.borrow_mut().handle_operation(op));
+        let event_loop = Rc::new(RefCell::new(EventLoop::new()));
+        let host = Rc::clone(&event_loop);
+        vm.set_host_callback(move |request| host.borrow_mut().handle_request(request));
+
         let mut runtime = Self {
-            vm: VirtualMachine::new(),
-            dom: DomBinding::new(),
+            vm,
+            dom,
             compiled_scripts: Vec::new(),
             globals: BTreeMap::new(),
-            timers: BTreeMap::new(),
-            next_timer_id: 1,
+            event_loop,
+            instruction_budget: DEFAULT_INSTRUCTION_BUDGET,
             event_queue: Vec::new(),
             event_handlers: BTreeMap::new(),
             running: false,
@@ -121,15 +122,36 @@ impl ScriptRuntime {
 
     /// DOMを初期化
     pub fn initialize_dom(&mut self, root: &DocumentNode) {
-        self.dom.initialize_from_html(root);
+        let mut dom = self.dom.borrow_mut();
+        dom.initialize_from_html(root);
 
         // documentオブジェクトをグローバルに登録
-        let doc = self.dom.create_document_object();
+        let doc = dom.create_document_object();
         self.globals.insert(String::from("document"), doc);
     }
 
     /// スクリプトを実行
+    ///
+    /// 最後まで実行して結果を返す。タイマーや fetch を await して止まったときは
+    /// 未解決の Promise を返し、続きは `run_tasks`（イベントループ）で実行する。
     pub fn execute(&mut self, source: &str) -> ScriptResult<ScriptValue> {
+        self.load(source)?;
+        let result = self.vm.run();
+        self.release_timers();
+        result
+    }
+
+    /// スクリプトをタスクとして実行待ちにする
+    ///
+    /// `execute` と違ってその場では実行しないので、長いループを含むスクリプトも
+    /// `run_tasks` の予算ごとに少しずつ進む。
+    pub fn spawn(&mut self, source: &str) -> ScriptResult<()> {
+        self.load(source)?;
+        self.vm.start()
+    }
+
+    /// スクリプトをコンパイルして VM に読み込む
+    fn load(&mut self, source: &str) -> ScriptResult<()> {
         // レキサー
         let mut lexer = Lexer::new(source);
         let tokens = lexer.tokenize()?;
@@ -141,27 +163,20 @@ impl ScriptRuntime {
         // コンパイラ
         let (instructions, constants, _functions) = self.compile(&ast)?;
 
-        // 実行
         self.vm.load(instructions, constants);
 
         // グローバル変数を設定
         for (name, value) in &self.globals {
-            self.vm.set_global(name, value.clone());
+            self.vm.set_global(name, value.clone())?;
         }
-
-        // TODO: DOMコールバックを設定（FnMut対応が必要）
-        // 現時点ではDOM操作は直接DomBindingを通じて行う
-
-        let result = self.vm.run()?;
-
-        Ok(result)
+        Ok(())
     }
 
-    /// ASTをバイトコードにコンパイル
+    /// ASTをバイトコードにコンパイル（読み込み済みのコードの後ろに置くアドレスで生成する）
     fn compile(&mut self, ast: &Ast) -> Result<(Vec<Instruction>, ConstantPool, BTreeMap<String, usize>), ScriptError> {
-        let mut compiler = Compiler::new();
+        let mut compiler = Compiler::with_base(self.vm.code_len(), self.vm.constant_count());
         compiler.compile(ast)?;
-        Ok((compiler.instructions, compiler.constants, compiler.functions))
+        Ok(compiler.finish())
     }
 
     /// 組み込み関数を登録
@@ -195,6 +210,14 @@ impl ScriptRuntime {
         self.vm.register_native_function("len", NativeFunctionId::ArrayLength, 1);
         self.vm.register_native_function("push", NativeFunctionId::ArrayPush, 2);
         self.vm.register_native_function("pop", NativeFunctionId::ArrayPop, 1);
+
+        // タイマーとネットワーク（結果は Promise で受け取る）
+        self.vm.register_native_function("set_timeout", NativeFunctionId::SetTimeout, 2);
+        self.vm.register_native_function("set_interval", NativeFunctionId::SetInterval, 2);
+        self.vm.register_native_function("clear_timeout", NativeFunctionId::ClearTimeout, 1);
+        self.vm.register_native_function("clear_interval", NativeFunctionId::ClearInterval, 1);
+        self.vm.register_native_function("sleep", NativeFunctionId::Sleep, 1);
+        self.vm.register_native_function("fetch", NativeFunctionId::Fetch, 1);
     }
 
     /// イベントを発火
@@ -202,23 +225,45 @@ impl ScriptRuntime {
         self.event_queue.push(event);
     }
 
+    /// フォーム部品への入力を反映し、`input` イベントを発火
+    pub fn dispatch_input(&mut self, target_id: usize, value: &str) {
+        self.dom
+            .borrow_mut()
+            .handle_operation(DomOperation::SetValue(target_id, String::from(value)));
+        let mut data = BTreeMap::new();
+        data.insert(String::from("value"), ScriptValue::String(String::from(value)));
+        self.dispatch_event(Event {
+            event_type: String::from("input"),
+            target_id,
+            data,
+            propagation_stopped: false,
+            default_prevented: false,
+        });
+    }
+
     /// イベントキューを処理
+    ///
+    /// ハンドラをタスクにして予算の範囲で実行する。失敗したタスクがあれば最初のエラーを返す。
     pub fn process_events(&mut self) -> ScriptResult<()> {
         while let Some(event) = self.event_queue.pop() {
             self.handle_event(&event)?;
         }
+        self.run_tasks()?;
         Ok(())
     }
 
-    /// 単一イベントを処理
+    /// 単一イベントのハンドラを実行待ちにする
     fn handle_event(&mut self, event: &Event) -> ScriptResult<()> {
         // 要素に登録されたハンドラを取得
-        let handlers = self.dom.dispatch_event(event.target_id, &event.event_type);
+        let handlers = self.dom.borrow().dispatch_event(event.target_id, &event.event_type);
 
-        for handler_addr in handlers {
+        for handle in handlers {
             if event.propagation_stopped {
                 break;
             }
+            let Some(handler) = self.vm.pinned(handle).cloned() else {
+                continue;
+            };
 
             // イベントオブジェクトを作成
             let mut event_obj = BTreeMap::new();
@@ -228,89 +273,112 @@ impl ScriptRuntime {
                 event_obj.insert(key.clone(), value.clone());
             }
 
-            // ハンドラを実行
-            // 実際の実装ではVMを使って実行
-            // TODO: ハンドラ実行の実装
+            // ハンドラを実行待ちにする（クロージャはキャプチャした変数ごと呼び出される）
+            self.vm.spawn(&handler, vec![ScriptValue::Object(event_obj)])?;
         }
 
         Ok(())
     }
 
-    /// タイマーを設定
-    pub fn set_timeout(&mut self, callback_addr: usize, delay_ms: u64, current_tick: u64) -> usize {
-        let id = self.next_timer_id;
-        self.next_timer_id += 1;
-
-        let timer = TimerInfo {
-            id,
-            callback_addr,
-            execute_at: current_tick + delay_ms,
-            interval: None,
-        };
-
-        self.timers.insert(id, timer);
-        id
+    /// タイマーを設定（`delay_ms` 後に関数を1回呼ぶ）
+    pub fn set_timeout(&mut self, callback: ScriptValue, delay_ms: u64) -> ScriptResult<usize> {
+        self.add_timer(callback, delay_ms, None)
     }
 
-    /// インターバルを設定
-    pub fn set_interval(&mut self, callback_addr: usize, interval_ms: u64, current_tick: u64) -> usize {
-        let id = self.next_timer_id;
-        self.next_timer_id += 1;
-
-        let timer = TimerInfo {
-            id,
-            callback_addr,
-            execute_at: current_tick + interval_ms,
-            interval: Some(interval_ms),
-        };
+    /// インターバルを設定（`interval_ms` ごとに関数を呼ぶ）
+    pub fn set_interval(&mut self, callback: ScriptValue, interval_ms: u64) -> ScriptResult<usize> {
+        self.add_timer(callback, interval_ms, Some(interval_ms))
+    }
 
-        self.timers.insert(id, timer);
-        id
+    fn add_timer(&mut self, callback: ScriptValue, delay_ms: u64, interval: Option<u64>) -> ScriptResult<usize> {
+        let callback = self.vm.adopt(callback)?;
+        let handle = self.vm.pin(callback);
+        Ok(self
+            .event_loop
+            .borrow_mut()
+            .add_timer(TimerAction::Call(handle), delay_ms, interval))
     }
 
     /// タイマーをクリア
     pub fn clear_timer(&mut self, id: usize) {
-        self.timers.remove(&id);
+        self.event_loop.borrow_mut().clear_timer(id);
+        self.release_timers();
     }
 
     /// タイマーを処理
+    ///
+    /// 期限が来たコールバックをタスクにし、`sleep` の Promise を解決してから、
+    /// 予算の範囲でタスクを実行する。
     pub fn process_timers(&mut self, current_tick: u64) -> ScriptResult<()> {
-        let mut to_execute = Vec::new();
-        let mut to_reschedule = Vec::new();
-
-        for (&id, timer) in &self.timers {
-            if current_tick >= timer.execute_at {
-                to_execute.push(timer.callback_addr);
-                if let Some(interval) = timer.interval {
-                    to_reschedule.push((id, timer.execute_at + interval));
+        let due = self.event_loop.borrow_mut().due_timers(current_tick);
+        for (action, last) in due {
+            match action {
+                TimerAction::Call(handle) => {
+                    if let Some(callback) = self.vm.pinned(handle).cloned() {
+                        self.vm.spawn(&callback, Vec::new())?;
+                    }
+                    if last {
+                        self.vm.unpin(handle);
+                    }
                 }
+                TimerAction::Resolve(handle) => self.vm.settle_pinned(handle, Ok(ScriptValue::Nil)),
             }
         }
+        self.run_tasks()?;
+        Ok(())
+    }
 
-        // 一回限りのタイマーを削除
-        for addr in &to_execute {
-            let mut ids_to_remove = Vec::new();
-            for (&id, timer) in &self.timers {
-                if timer.callback_addr == *addr && timer.interval.is_none() {
-                    ids_to_remove.push(id);
-                }
-            }
-            for id in ids_to_remove {
-                self.timers.remove(&id);
-            }
+    /// 実行待ちのタスクを命令数の予算の範囲で実行する（実行待ちが残っていれば true）
+    ///
+    /// 失敗したタスクがあれば最初のエラーを返す（残りは次の呼び出しで返す）。
+    pub fn run_tasks(&mut self) -> ScriptResult<bool> {
+        let busy = self.vm.run_tasks(self.instruction_budget);
+        self.release_timers();
+        match self.vm.take_task_error() {
+            Some(err) => Err(err),
+            None => Ok(busy),
         }
+    }
 
-        // インターバルを再スケジュール
-        for (id, new_time) in to_reschedule {
-            if let Some(timer) = self.timers.get_mut(&id) {
-                timer.execute_at = new_time;
-            }
+    /// `run_tasks` 1回で実行する命令数を設定
+    pub fn set_instruction_budget(&mut self, budget: usize) {
+        self.instruction_budget = budget.max(1);
+    }
+
+    /// 止めたタイマーのコールバックの固定を外す
+    fn release_timers(&mut self) {
+        let released = self.event_loop.borrow_mut().take_released();
+        for handle in released {
+            self.vm.unpin(handle);
         }
+    }
 
-        // コールバックを実行
-        // TODO: 実際のコールバック実行
+    /// 最も早いタイマーの予定時刻（ティック）
+    pub fn next_timer_deadline(&self) -> Option<u64> {
+        self.event_loop.borrow().next_deadline()
+    }
 
-        Ok(())
+    /// 取得待ちの fetch を取り出す（取得したら `complete_fetch` で結果を渡す）
+    pub fn take_fetches(&mut self) -> Vec<PendingFetch> {
+        self.event_loop.borrow_mut().take_fetches()
+    }
+
+    /// 取得待ちの fetch があるか
+    pub fn has_pending_fetches(&self) -> bool {
+        self.event_loop.borrow().has_fetches()
+    }
+
+    /// fetch の結果で Promise を解決し、待っていたタスクを実行待ちに戻す
+    pub fn complete_fetch(&mut self, promise: usize, result: Result<FetchResponse, String>) {
+        let result = result
+            .map(|response| response.to_value())
+            .map_err(|e| ScriptError::runtime(&format!("fetch failed: {}", e)));
+        self.vm.settle_pinned(promise, result);
+    }
+
+    /// 実行待ち・待機中のタスクの数
+    pub fn task_count(&self) -> usize {
+        self.vm.task_count()
     }
 
     /// グローバル変数を設定
@@ -323,9 +391,16 @@ impl ScriptRuntime {
         self.globals.get(name)
     }
 
+    /// スクリプトが使えるヒープの上限（バイト）を設定
+    ///
+    /// 上限を超える確保は `ErrorKind::Memory` のエラーになる。
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.vm.set_memory_limit(bytes);
+    }
+
     /// DOMバインディングへの参照を取得
-    pub fn dom(&mut self) -> &mut DomBinding {
-        &mut self.dom
+    pub fn dom(&mut self) -> RefMut<'_, DomBinding> {
+        self.dom.borrow_mut()
     }
 }
 
@@ -336,467 +411,509 @@ impl Default for ScriptRuntime {
 }
 
 // ============================================================================
-// Compiler
+// Unit Tests
 // ============================================================================
 
-/// ASTをバイトコードにコンパイルするコンパイラ
-struct Compiler {
-    /// 生成された命令列
-    instructions: Vec<Instruction>,
-    /// 定数プール
-    constants: ConstantPool,
-    /// 関数テーブル
-    functions: BTreeMap<String, usize>,
-    /// ローカル変数（スコープスタック）
-    locals: Vec<BTreeMap<String, usize>>,
-    /// 次のローカル変数インデックス
-    next_local: usize,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Compiler {
-    fn new() -> Self {
-        Self {
-            instructions: Vec::new(),
-            constants: ConstantPool::new(),
-            functions: BTreeMap::new(),
-            locals: vec![BTreeMap::new()],
-            next_local: 0,
-        }
+    #[test]
+    fn test_runtime_basic() {
+        let mut runtime = ScriptRuntime::new();
+        let result = runtime.execute("let x = 42; x").unwrap();
+        assert_eq!(result.as_int(), Some(42));
     }
 
-    fn compile(&mut self, ast: &Ast) -> Result<(), ScriptError> {
-        for stmt in &ast.statements {
-            self.compile_statement(stmt)?;
-        }
-        self.emit(Instruction::Halt);
-        Ok(())
+    #[test]
+    fn test_runtime_arithmetic() {
+        let mut runtime = ScriptRuntime::new();
+        let result = runtime.execute("1 + 2 * 3").unwrap();
+        assert_eq!(result.as_int(), Some(7));
     }
 
-    fn compile_statement(&mut self, stmt: &Stmt) -> Result<(), ScriptError> {
-        match stmt {
-            Stmt::Let { name, value, .. } => {
-                // 初期化子をコンパイル
-                if let Some(init) = value {
-                    self.compile_expression(init)?;
-                } else {
-                    let const_idx = self.constants.add(ScriptValue::Nil);
-                    self.emit(Instruction::Const(const_idx));
-                }
-
-                // ローカル変数に格納
-                let local_idx = self.define_local(name);
-                self.emit(Instruction::StoreLocal(local_idx));
-            }
-            Stmt::Assign { target, value } => {
-                self.compile_expression(value)?;
-
-                match target {
-                    Expr::Identifier(var_name) => {
-                        if let Some(local_idx) = self.resolve_local(var_name) {
-                            self.emit(Instruction::StoreLocal(local_idx));
-                        } else {
-                            self.emit(Instruction::StoreGlobal(var_name.clone()));
-                        }
-                    }
-                    Expr::FieldAccess { object, field } => {
-                        self.compile_expression(object)?;
-                        self.emit(Instruction::SetField(field.clone()));
-                    }
-                    Expr::Index { object, index } => {
-                        self.compile_expression(object)?;
-                        self.compile_expression(index)?;
-                        self.emit(Instruction::SetIndex);
-                    }
-                    _ => {}
-                }
-            }
-            Stmt::Expression(expr) => {
-                self.compile_expression(expr)?;
-                self.emit(Instruction::Pop);
-            }
-            Stmt::Block(statements) => {
-                self.enter_scope();
-                for s in statements {
-                    self.compile_statement(s)?;
-                }
-                self.exit_scope();
+    #[test]
+    fn test_runtime_function() {
+        let mut runtime = ScriptRuntime::new();
+        let result = runtime.execute("
+            fn add(a: i32, b: i32) -> i32 {
+                a + b
             }
-            Stmt::If { condition, then_branch, else_branch } => {
-                self.compile_expression(condition)?;
-
-                let jump_if_false = self.emit(Instruction::JumpIfFalse(0));
+            add(3, 4)
+        ").unwrap();
+        assert_eq!(result.as_int(), Some(7));
+    }
 
-                self.compile_statement(then_branch)?;
+    fn run(source: &str) -> ScriptValue {
+        let mut runtime = ScriptRuntime::new();
+        runtime.execute(source).unwrap()
+    }
 
-                if let Some(else_stmt) = else_branch {
-                    let jump_over_else = self.emit(Instruction::Jump(0));
-                    self.patch_jump(jump_if_false);
-                    self.compile_statement(else_stmt)?;
-                    self.patch_jump(jump_over_else);
-                } else {
-                    self.patch_jump(jump_if_false);
+    #[test]
+    fn test_closure_counter_state() {
+        // 各カウンタは自分のキャプチャ変数を持ち、作成した関数から戻った後も残る
+        let result = run("
+            fn make_counter(step: i64) -> impl FnMut() -> i64 {
+                let mut count = 0;
+                move || {
+                    count += step;
+                    count
                 }
             }
-            Stmt::While { condition, body } => {
-                self.emit(Instruction::LoopStart);
-                let loop_start = self.instructions.len();
+            let mut a = make_counter(1);
+            let mut b = make_counter(10);
+            a();
+            a();
+            b();
+            b();
+            b();
+            a() * 10 + b() / 20
+        ");
+        assert_eq!(result.as_int(), Some(32));
+    }
 
-                self.compile_expression(condition)?;
-                let exit_jump = self.emit(Instruction::JumpIfFalse(0));
+    #[test]
+    fn test_closures_share_captured_variable() {
+        let result = run("
+            let mut total = 0;
+            let add = |n| { total += n; };
+            let double = || { total = total * 2; };
+            add(5);
+            double();
+            add(2);
+            double();
+            total
+        ");
+        assert_eq!(result.as_int(), Some(24));
+    }
 
-                self.compile_statement(body)?;
-                self.emit(Instruction::Jump(loop_start));
+    #[test]
+    fn test_nested_closure_captures_parameter() {
+        let result = run("
+            let adder = |x| move |y| x + y;
+            let add10 = adder(10);
+            add10(5)
+        ");
+        assert_eq!(result.as_int(), Some(15));
+    }
 
-                self.patch_jump(exit_jump);
-                self.emit(Instruction::LoopEnd);
-            }
-            Stmt::For { variable, iterator, body } => {
-                // イテラブルをスタックにプッシュ
-                self.compile_expression(iterator)?;
-                self.emit(Instruction::MakeIterator);
+    #[test]
+    fn test_captured_variable_outlives_block() {
+        let result = run("
+            let get = {
+                let secret = 40;
+                move || secret + 2
+            };
+            let other = 7;
+            get()
+        ");
+        assert_eq!(result.as_int(), Some(42));
+    }
 
-                self.emit(Instruction::LoopStart);
-                let loop_start = self.instructions.len();
+    #[test]
+    fn test_loop_closures_capture_each_iteration() {
+        let result = run("
+            let mut fs = Vec::new();
+            for i in 1..4 {
+                fs.push(move || i * 2);
+            }
+            let mut sum = 0;
+            for f in fs {
+                sum += f();
+            }
+            sum
+        ");
+        assert_eq!(result.as_int(), Some(12));
+    }
 
-                // 次の値を取得
-                self.emit(Instruction::IterNext);
-                let exit_jump = self.emit(Instruction::JumpIfTrue(0));
+    #[test]
+    fn test_recursion_and_higher_order_functions() {
+        let result = run("
+            fn fib(n: i64) -> i64 {
+                if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+            }
+            fn apply_twice<F: Fn(i64) -> i64>(f: F, x: i64) -> i64 {
+                f(f(x))
+            }
+            let offset = 3;
+            apply_twice(|x| x + offset, fib(10)) - 40
+        ");
+        assert_eq!(result.as_int(), Some(21));
+    }
 
-                // 変数に束縛
-                let local_idx = self.define_local(variable);
-                self.emit(Instruction::StoreLocal(local_idx));
+    #[test]
+    fn test_trait_dynamic_dispatch() {
+        let result = run("
+            trait Shape {
+                fn area(&self) -> i64;
+                fn describe(&self) -> String {
+                    self.name() + \":\" + self.area().to_string()
+                }
+                fn name(&self) -> String;
+            }
+            struct Square { side: i64 }
+            struct Rect { w: i64, h: i64 }
+            impl Shape for Square {
+                fn area(&self) -> i64 { self.side * self.side }
+                fn name(&self) -> String { String::from(\"square\") }
+            }
+            impl Shape for Rect {
+                fn area(&self) -> i64 { self.w * self.h }
+                fn name(&self) -> String { String::from(\"rect\") }
+                fn describe(&self) -> String { String::from(\"a rectangle\") }
+            }
+            fn show(shape: &dyn Shape) -> String { shape.describe() }
+            let shapes: Vec<Box<dyn Shape>> = [Box::new(Square { side: 3 }), Box::new(Rect { w: 2, h: 5 })];
+            let mut parts = Vec::new();
+            for s in shapes {
+                parts.push(show(&s));
+            }
+            parts.join(\", \")
+        ");
+        assert_eq!(result.as_string(), Some("square:9, a rectangle"));
+    }
 
-                // 本体を実行
-                self.compile_statement(body)?;
+    #[test]
+    fn test_trait_for_builtin_type() {
+        let result = run("
+            trait Double { fn double(&self) -> Self; }
+            impl Double for i64 {
+                fn double(&self) -> i64 { *self * 2 }
+            }
+            21.double()
+        ");
+        assert_eq!(result.as_int(), Some(42));
+    }
 
-                self.emit(Instruction::Jump(loop_start));
-                self.patch_jump(exit_jump);
-                self.emit(Instruction::LoopEnd);
+    #[test]
+    fn test_generic_functions_and_structs() {
+        let result = run("
+            fn largest<T: PartialOrd>(items: &[T]) -> T {
+                let mut best = items[0];
+                for item in items {
+                    if item > best { best = item; }
+                }
+                best
             }
-            Stmt::Loop(body) => {
-                self.emit(Instruction::LoopStart);
-                let loop_start = self.instructions.len();
-
-                self.compile_statement(body)?;
-
-                self.emit(Instruction::Jump(loop_start));
-                self.emit(Instruction::LoopEnd);
+            struct Wrapper<T> { value: T }
+            impl<T> Wrapper<T> {
+                fn new(value: T) -> Self { Self { value } }
+                fn get(&self) -> T { self.value }
             }
-            Stmt::Function { name, params, body, .. } => {
-                // 関数本体へのジャンプを記録
-                let func_addr = self.instructions.len() + 1;
-                self.functions.insert(name.clone(), func_addr);
-
-                // 関数本体をスキップするジャンプ
-                let skip_jump = self.emit(Instruction::Jump(0));
-
-                // 新しいスコープを開始
-                self.enter_scope();
+            let w = Wrapper::new(largest(&[\"pear\", \"apple\", \"zucchini\"]));
+            w.get() + largest(&[3, 9, 4]).to_string()
+        ");
+        assert_eq!(result.as_string(), Some("zucchini9"));
+    }
 
-                // パラメータをローカル変数として定義
-                for param in params {
-                    self.define_local(&param.name);
-                }
+    #[test]
+    fn test_trait_bound_is_checked() {
+        let mut runtime = ScriptRuntime::new();
+        let err = runtime.execute("
+            trait Speak { fn speak(&self) -> String; }
+            struct Dog {}
+            struct Rock {}
+            impl Speak for Dog {
+                fn speak(&self) -> String { String::from(\"woof\") }
+            }
+            fn talk<T: Speak>(x: T) -> String { x.speak() }
+            fn talk_dyn(x: &dyn Speak) -> String { x.speak() }
+            talk(Dog {}) + talk_dyn(&Dog {});
+            talk(Rock {})
+        ").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Type);
+        assert!(err.message.contains("Rock: Speak"));
+    }
 
-                // 本体をコンパイル
-                self.compile_statement(body)?;
+    #[test]
+    fn test_missing_trait_method_is_an_error() {
+        let mut runtime = ScriptRuntime::new();
+        let err = runtime.execute("
+            trait Named { fn name(&self) -> String; }
+            struct Anon {}
+            impl Named for Anon {}
+        ").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Type);
+        assert!(err.message.contains("`name`"));
+    }
 
-                // 暗黙のreturn
-                let nil_idx = self.constants.add(ScriptValue::Nil);
-                self.emit(Instruction::Const(nil_idx));
-                self.emit(Instruction::Return);
+    #[test]
+    fn test_event_handler_closes_over_state() {
+        let root = DocumentNode::new("body").with_child(DocumentNode::new("button").with_id("btn"));
 
-                self.exit_scope();
-                self.patch_jump(skip_jump);
+        let mut runtime = ScriptRuntime::new();
+        runtime.initialize_dom(&root);
+        runtime.execute("
+            let mut clicks = 0;
+            let button = getElementById(\"btn\");
+            button.on_click(move |event| {
+                clicks += 1;
+                button.set_text(\"clicked \" + clicks.to_string());
+            });
+        ").unwrap();
 
-                // 関数オブジェクトをグローバルに登録
-                let param_names: Vec<String> = params.iter().map(|p| p.name.clone()).collect();
-                let func = FunctionValue::new(name, param_names, func_addr);
-                let const_idx = self.constants.add(ScriptValue::Function(func));
-                self.emit(Instruction::Const(const_idx));
-                self.emit(Instruction::StoreGlobal(name.clone()));
-            }
-            Stmt::Return(value_opt) => {
-                if let Some(v) = value_opt {
-                    self.compile_expression(v)?;
-                } else {
-                    let nil_idx = self.constants.add(ScriptValue::Nil);
-                    self.emit(Instruction::Const(nil_idx));
-                }
-                self.emit(Instruction::Return);
-            }
-            Stmt::Break => {
-                self.emit(Instruction::Break);
-            }
-            Stmt::Continue => {
-                self.emit(Instruction::Continue);
-            }
-            Stmt::Empty => {}
-            _ => {}
+        let target_id = match runtime.dom().handle_operation(DomOperation::GetElementById(String::from("btn"))) {
+            ScriptValue::Element(elem) => elem.id,
+            _ => panic!("Expected element"),
+        };
+        for _ in 0..2 {
+            runtime.dispatch_event(Event {
+                event_type: String::from("click"),
+                target_id,
+                data: BTreeMap::new(),
+                propagation_stopped: false,
+                default_prevented: false,
+            });
+            runtime.process_events().unwrap();
         }
-        Ok(())
-    }
 
-    fn compile_expression(&mut self, expr: &Expr) -> Result<(), ScriptError> {
-        match expr {
-            Expr::Literal(lit) => {
-                let value = match lit {
-                    Literal::Nil => ScriptValue::Nil,
-                    Literal::Bool(b) => ScriptValue::Bool(*b),
-                    Literal::Integer(i) => ScriptValue::Int(*i),
-                    Literal::Float(f) => ScriptValue::Float(*f),
-                    Literal::String(s) => ScriptValue::String(s.clone()),
-                };
-                let const_idx = self.constants.add(value);
-                self.emit(Instruction::Const(const_idx));
-            }
-            Expr::Identifier(name) => {
-                if let Some(local_idx) = self.resolve_local(name) {
-                    self.emit(Instruction::LoadLocal(local_idx));
-                } else {
-                    self.emit(Instruction::LoadGlobal(name.clone()));
-                }
-            }
-            Expr::Unary { op, operand } => {
-                self.compile_expression(operand)?;
-                match op {
-                    UnaryOp::Neg => self.emit(Instruction::Neg),
-                    UnaryOp::Not => self.emit(Instruction::Not),
-                    UnaryOp::BitNot => self.emit(Instruction::BitNot),
-                };
-            }
-            Expr::Binary { left, op, right } => {
-                self.compile_expression(left)?;
-                self.compile_expression(right)?;
-                match op {
-                    BinaryOp::Add => self.emit(Instruction::Add),
-                    BinaryOp::Sub => self.emit(Instruction::Sub),
-                    BinaryOp::Mul => self.emit(Instruction::Mul),
-                    BinaryOp::Div => self.emit(Instruction::Div),
-                    BinaryOp::Mod => self.emit(Instruction::Mod),
-                    BinaryOp::Eq => self.emit(Instruction::Eq),
-                    BinaryOp::NotEq => self.emit(Instruction::Ne),
-                    BinaryOp::Lt => self.emit(Instruction::Lt),
-                    BinaryOp::LtEq => self.emit(Instruction::Le),
-                    BinaryOp::Gt => self.emit(Instruction::Gt),
-                    BinaryOp::GtEq => self.emit(Instruction::Ge),
-                    BinaryOp::And => self.emit(Instruction::And),
-                    BinaryOp::Or => self.emit(Instruction::Or),
-                    BinaryOp::BitAnd => self.emit(Instruction::BitAnd),
-                    BinaryOp::BitOr => self.emit(Instruction::BitOr),
-                    BinaryOp::BitXor => self.emit(Instruction::BitXor),
-                    BinaryOp::Shl => self.emit(Instruction::Shl),
-                    BinaryOp::Shr => self.emit(Instruction::Shr),
-                };
-            }
-            Expr::Call { callee, args } => {
-                // 引数をプッシュ
-                for arg in args {
-                    self.compile_expression(arg)?;
-                }
-                // 関数をプッシュ
-                self.compile_expression(callee)?;
-                // 呼び出し
-                self.emit(Instruction::Call(args.len()));
-            }
-            Expr::MethodCall { object, method, args } => {
-                // 引数をプッシュ
-                for arg in args {
-                    self.compile_expression(arg)?;
-                }
-                // オブジェクトをプッシュ
-                self.compile_expression(object)?;
-                // メソッド呼び出し
-                self.emit(Instruction::CallMethod(method.clone(), args.len()));
-            }
-            Expr::FieldAccess { object, field } => {
-                self.compile_expression(object)?;
-                self.emit(Instruction::GetField(field.clone()));
-            }
-            Expr::Index { object, index } => {
-                self.compile_expression(object)?;
-                self.compile_expression(index)?;
-                self.emit(Instruction::GetIndex);
-            }
-            Expr::Array(elements) => {
-                for elem in elements {
-                    self.compile_expression(elem)?;
-                }
-                self.emit(Instruction::MakeArray(elements.len()));
-            }
-            Expr::StructLit { name: _, fields } => {
-                for (key, value) in fields {
-                    let key_const = self.constants.add(ScriptValue::String(key.clone()));
-                    self.emit(Instruction::Const(key_const));
-                    self.compile_expression(value)?;
-                }
-                self.emit(Instruction::MakeObject(fields.len()));
-            }
-            Expr::If { condition, then_branch, else_branch } => {
-                self.compile_expression(condition)?;
-                let jump_if_false = self.emit(Instruction::JumpIfFalse(0));
-
-                self.compile_expression(then_branch)?;
-
-                if let Some(else_expr) = else_branch {
-                    let jump_over_else = self.emit(Instruction::Jump(0));
-                    self.patch_jump(jump_if_false);
-                    self.compile_expression(else_expr)?;
-                    self.patch_jump(jump_over_else);
-                } else {
-                    self.patch_jump(jump_if_false);
-                    let nil_idx = self.constants.add(ScriptValue::Nil);
-                    self.emit(Instruction::Const(nil_idx));
-                }
-            }
-            Expr::Block { statements, value } => {
-                self.enter_scope();
-                for s in statements {
-                    self.compile_statement(s)?;
-                }
-                if let Some(val_expr) = value {
-                    self.compile_expression(val_expr)?;
-                } else {
-                    let nil_idx = self.constants.add(ScriptValue::Nil);
-                    self.emit(Instruction::Const(nil_idx));
-                }
-                self.exit_scope();
-            }
-            Expr::Closure { params, body, .. } => {
-                // クロージャ本体をスキップするジャンプ
-                let skip_jump = self.emit(Instruction::Jump(0));
-                let closure_addr = self.instructions.len();
+        let text = runtime.dom().handle_operation(DomOperation::GetText(target_id));
+        assert_eq!(text.as_string(), Some("clicked 2"));
+    }
 
-                self.enter_scope();
+    #[test]
+    fn test_input_event_and_value_properties() {
+        let root = DocumentNode::new("form")
+            .with_child(DocumentNode::new("input").with_id("q"))
+            .with_child(DocumentNode::new("input").with_id("all").with_attribute("type", "checkbox"));
 
-                // パラメータをローカル変数として定義
-                for param in params {
-                    self.define_local(&param.name);
-                }
+        let mut runtime = ScriptRuntime::new();
+        runtime.initialize_dom(&root);
+        runtime.execute("
+            let q = getElementById(\"q\");
+            let all = getElementById(\"all\");
+            q.on_input(move |event| {
+                q.value = event.value + \"!\";
+                all.checked = all.value == \"on\";
+            });
+        ").unwrap();
+        runtime.dom().take_changed_controls();
 
-                // 本体をコンパイル
-                self.compile_expression(body)?;
-                self.emit(Instruction::Return);
+        let q = match runtime.dom().handle_operation(DomOperation::GetElementById(String::from("q"))) {
+            ScriptValue::Element(elem) => elem.id,
+            _ => panic!("Expected element"),
+        };
+        runtime.dispatch_input(q, "a");
+        runtime.process_events().unwrap();
 
-                self.exit_scope();
-                self.patch_jump(skip_jump);
+        assert_eq!(runtime.dom().handle_operation(DomOperation::GetValue(q)).as_string(), Some("a!"));
+        assert!(matches!(runtime.dom().handle_operation(DomOperation::GetChecked(q + 1)), ScriptValue::Bool(true)));
+        assert_eq!(runtime.dom().take_changed_controls(), [q, q + 1]);
+    }
 
-                // クロージャを作成
-                let capture_names: Vec<String> = Vec::new(); // TODO: キャプチャ変数の分析
-                self.emit(Instruction::MakeClosure(closure_addr, capture_names));
-            }
-            Expr::Range { start, end, inclusive } => {
-                if let Some(s) = start {
-                    self.compile_expression(s)?;
-                } else {
-                    let zero = self.constants.add(ScriptValue::Int(0));
-                    self.emit(Instruction::Const(zero));
-                }
+    #[test]
+    fn test_arrays_and_structs_are_shared_by_reference() {
+        let result = run("
+            struct Counter { count: i64 }
+            impl Counter {
+                fn bump(&mut self) { self.count += 1; }
+            }
+            fn fill(items: &mut Vec<i64>) {
+                items.push(1);
+                items.push(2);
+            }
+            let a = Vec::new();
+            let b = a;
+            fill(&mut b);
+            let c = Counter { count: 0 };
+            let d = c;
+            d.bump();
+            c.bump();
+            let copy = a.clone();
+            copy.push(3);
+            a.len() * 100 + c.count * 10 + copy.len()
+        ");
+        assert_eq!(result.as_int(), Some(223));
+    }
 
-                if let Some(e) = end {
-                    self.compile_expression(e)?;
-                } else {
-                    let max = self.constants.add(ScriptValue::Int(i64::MAX));
-                    self.emit(Instruction::Const(max));
-                }
+    #[test]
+    fn test_cyclic_garbage_is_collected_under_limit() {
+        let mut runtime = ScriptRuntime::new();
+        runtime.set_memory_limit(64 * 1024);
+        let result = runtime.execute("
+            struct Node { next: i64, payload: Vec<i64> }
+            let mut total = 0;
+            for i in 0..2000 {
+                let a = Node { next: 0, payload: [i, i, i, i] };
+                let b = Node { next: a, payload: [i] };
+                a.next = b;
+                total += b.next.payload.len();
+            }
+            total
+        ").unwrap();
+        assert_eq!(result.as_int(), Some(8000));
+    }
 
-                if *inclusive {
-                    self.emit(Instruction::MakeRangeInclusive);
-                } else {
-                    self.emit(Instruction::MakeRange);
-                }
-            }
-            Expr::Tuple(elements) => {
-                for elem in elements {
-                    self.compile_expression(elem)?;
-                }
-                self.emit(Instruction::MakeArray(elements.len()));
-            }
-            _ => {
-                // 未対応の式はNilを返す
-                let nil_idx = self.constants.add(ScriptValue::Nil);
-                self.emit(Instruction::Const(nil_idx));
-            }
-        }
-        Ok(())
+    #[test]
+    fn test_memory_limit_is_a_catchable_error() {
+        let mut runtime = ScriptRuntime::new();
+        runtime.set_memory_limit(64 * 1024);
+        let err = runtime.execute("
+            let hoard = Vec::new();
+            loop { hoard.push([1, 2, 3]); }
+        ").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Memory);
+
+        let err = runtime.execute("
+            let mut s = \"x\";
+            loop { s = s + s; }
+        ").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Memory);
+
+        // 上限に達した後も実行を続けられる
+        let result = runtime.execute("let v = [1, 2, 3]; v.len()").unwrap();
+        assert_eq!(result.as_int(), Some(3));
     }
 
-    fn emit(&mut self, instruction: Instruction) -> usize {
-        let idx = self.instructions.len();
-        self.instructions.push(instruction);
-        idx
+    #[test]
+    fn test_runaway_recursion_is_an_error() {
+        let mut runtime = ScriptRuntime::new();
+        let err = runtime.execute("
+            fn down(n: i64) -> i64 { down(n + 1) }
+            down(0)
+        ").unwrap_err();
+        assert!(err.message.contains("stack overflow"));
     }
 
-    fn patch_jump(&mut self, idx: usize) {
-        let target = self.instructions.len();
-        match &mut self.instructions[idx] {
-            Instruction::Jump(addr) => *addr = target,
-            Instruction::JumpIfFalse(addr) => *addr = target,
-            Instruction::JumpIfTrue(addr) => *addr = target,
-            _ => {}
+    fn global_strings(runtime: &ScriptRuntime, name: &str) -> Vec<String> {
+        match runtime.vm.get_global(name) {
+            Some(value) => value
+                .as_array()
+                .map(|items| items.iter().filter_map(|v| v.as_string().map(String::from)).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
         }
     }
 
-    fn enter_scope(&mut self) {
-        self.locals.push(BTreeMap::new());
+    #[test]
+    fn test_async_fn_await() {
+        let result = run("
+            async fn double(x: i64) -> i64 { x * 2 }
+            let add = async |a: i64, b: i64| a + b;
+            let block = async { 5 };
+            double(4).await + add(1, 2).await + block.await
+        ");
+        assert_eq!(result.as_int(), Some(16));
     }
 
-    fn exit_scope(&mut self) {
-        if let Some(scope) = self.locals.pop() {
-            self.next_local = self.next_local.saturating_sub(scope.len());
-        }
+    #[test]
+    fn test_async_error_propagates_through_await() {
+        let mut runtime = ScriptRuntime::new();
+        let err = runtime.execute("
+            async fn boom() { undefined_fn() }
+            boom().await;
+            1
+        ").unwrap_err();
+        assert!(err.message.contains("Not a function"));
+        // await していたタスクで報告済みなので、二重には報告しない
+        assert!(matches!(runtime.run_tasks(), Ok(false)));
     }
 
-    fn define_local(&mut self, name: &str) -> usize {
-        let idx = self.next_local;
-        self.next_local += 1;
-        if let Some(scope) = self.locals.last_mut() {
-            scope.insert(String::from(name), idx);
-        }
-        idx
+    #[test]
+    fn test_sleep_interleaves_tasks() {
+        let mut runtime = ScriptRuntime::new();
+        runtime.execute("
+            steps = [];
+            async fn worker(name: String, ms: i64) {
+                steps.push(name + \"-start\");
+                sleep(ms).await;
+                steps.push(name + \"-done\");
+            }
+            worker(\"slow\", 20);
+            worker(\"fast\", 10);
+            steps.push(\"main\");
+        ").unwrap();
+        assert_eq!(global_strings(&runtime, "steps"), ["main"]);
+
+        runtime.run_tasks().unwrap();
+        assert_eq!(global_strings(&runtime, "steps"), ["main", "slow-start", "fast-start"]);
+
+        runtime.process_timers(10).unwrap();
+        assert_eq!(global_strings(&runtime, "steps").last().map(String::as_str), Some("fast-done"));
+        runtime.process_timers(20).unwrap();
+        assert_eq!(
+            global_strings(&runtime, "steps"),
+            ["main", "slow-start", "fast-start", "fast-done", "slow-done"]
+        );
+        assert_eq!(runtime.task_count(), 0);
     }
 
-    fn resolve_local(&self, name: &str) -> Option<usize> {
-        for scope in self.locals.iter().rev() {
-            if let Some(&idx) = scope.get(name) {
-                return Some(idx);
-            }
-        }
-        None
-    }
-}
+    #[test]
+    fn test_top_level_await_suspends_script() {
+        let mut runtime = ScriptRuntime::new();
+        let result = runtime.execute("done = false; sleep(5).await; done = true;").unwrap();
+        assert!(matches!(result, ScriptValue::Promise(_)));
+        assert_eq!(runtime.vm.get_global("done").and_then(|v| v.as_bool()), Some(false));
+        assert_eq!(runtime.next_timer_deadline(), Some(5));
 
-// ============================================================================
-// Unit Tests
-// ============================================================================
+        runtime.process_timers(5).unwrap();
+        assert_eq!(runtime.vm.get_global("done").and_then(|v| v.as_bool()), Some(true));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_interval_until_cleared() {
+        let mut runtime = ScriptRuntime::new();
+        runtime.execute("
+            ticks = 0;
+            id = set_interval(|| {
+                ticks += 1;
+                if ticks == 3 { clear_interval(id); }
+            }, 10);
+        ").unwrap();
+        for tick in 1..=6 {
+            runtime.process_timers(tick * 10).unwrap();
+        }
+        assert_eq!(runtime.vm.get_global("ticks").and_then(|v| v.as_int()), Some(3));
+        assert_eq!(runtime.event_loop.borrow().timer_count(), 0);
+    }
 
     #[test]
-    fn test_runtime_basic() {
+    fn test_fetch_resolves_with_response() {
         let mut runtime = ScriptRuntime::new();
-        let result = runtime.execute("let x = 42; x").unwrap();
-        assert_eq!(result.as_int(), Some(42));
+        runtime.spawn("
+            let response = fetch(\"http://example.com/data\").await;
+            status = response.status;
+            body = response.body;
+        ").unwrap();
+        runtime.run_tasks().unwrap();
+
+        let fetches = runtime.take_fetches();
+        assert_eq!(fetches.len(), 1);
+        assert_eq!(fetches[0].url, "http://example.com/data");
+        runtime.complete_fetch(fetches[0].promise, Ok(FetchResponse {
+            status: 200,
+            url: String::from("http://example.com/data"),
+            content_type: String::from("text/plain"),
+            body: String::from("hello"),
+        }));
+        runtime.run_tasks().unwrap();
+        assert_eq!(runtime.vm.get_global("status").and_then(|v| v.as_int()), Some(200));
+        assert_eq!(runtime.vm.get_global("body").as_ref().and_then(|v| v.as_string()), Some("hello"));
     }
 
     #[test]
-    fn test_runtime_arithmetic() {
+    fn test_failed_fetch_rejects() {
         let mut runtime = ScriptRuntime::new();
-        let result = runtime.execute("1 + 2 * 3").unwrap();
-        assert_eq!(result.as_int(), Some(7));
+        runtime.spawn("fetch(\"http://unreachable.invalid/\").await;").unwrap();
+        runtime.run_tasks().unwrap();
+        let pending = runtime.take_fetches().remove(0);
+        runtime.complete_fetch(pending.promise, Err(String::from("connection refused")));
+        let err = runtime.run_tasks().unwrap_err();
+        assert!(err.message.contains("fetch failed"));
     }
 
     #[test]
-    fn test_runtime_function() {
+    fn test_instruction_budget_preempts_long_scripts() {
         let mut runtime = ScriptRuntime::new();
-        let result = runtime.execute("
-            fn add(a: i32, b: i32) -> i32 {
-                a + b
-            }
-            add(3, 4)
-        ").unwrap();
-        assert_eq!(result.as_int(), Some(7));
+        runtime.set_instruction_budget(1000);
+        runtime.spawn("count = 0; loop { count += 1; }").unwrap();
+        runtime.spawn("other = 1;").unwrap();
+
+        // 終わらないループがあっても予算を使い切ると制御が戻る
+        assert!(runtime.run_tasks().unwrap());
+        assert!(runtime.run_tasks().unwrap());
+        assert_eq!(runtime.vm.get_global("other").and_then(|v| v.as_int()), Some(1));
+        assert_eq!(runtime.task_count(), 1);
+        assert!(runtime.vm.get_global("count").and_then(|v| v.as_int()).unwrap_or(0) > 0);
     }
 }
@@ -219,6 +219,11 @@ pub mod conversion {
             }
             ScriptValue::Array(_) => "array",
             ScriptValue::Object(_) => "object",
+            ScriptValue::Struct(s) => {
+                data.insert(String::from("name"), s.type_name.clone());
+                "struct"
+            }
+            ScriptValue::Ref(_) => "ref",
             ScriptValue::Element(_) => "element",
             ScriptValue::Function(_) => "function",
             ScriptValue::NativeFunction(_) => "native_function",
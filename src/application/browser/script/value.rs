@@ -269,6 +269,15 @@ pub enum ScriptValue {
     /// オブジェクト（マップ）
     Object(BTreeMap<String, ScriptValue>),
 
+    /// 構造体インスタンス（メソッドは型名で引く）
+    Struct(StructValue),
+
+    /// VMヒープ上のオブジェクトへの参照（配列・オブジェクト・構造体）
+    ///
+    /// VM内で生成したコンテナは全てヒープに置かれ、代入や引数渡しでは参照が共有される。
+    /// ホストへ返すときは `Array` / `Object` / `Struct` に書き出される。
+    Ref(HeapRef),
+
     /// DOM要素への参照
     Element(ElementRef),
 
@@ -327,6 +336,8 @@ impl ScriptValue {
             ScriptValue::Bytes(b) => !b.is_empty(),
             ScriptValue::Array(arr) => !arr.is_empty(),
             ScriptValue::Object(obj) => !obj.is_empty(),
+            ScriptValue::Struct(_) => true,
+            ScriptValue::Ref(_) => true,
             ScriptValue::Element(_) => true,
             ScriptValue::Function(_) => true,
             ScriptValue::NativeFunction(_) => true,
@@ -411,6 +422,13 @@ impl ScriptValue {
                     .collect();
                 format!("{{ {} }}", pairs.join(", "))
             }
+            ScriptValue::Struct(s) => {
+                let pairs: Vec<String> = s.fields.iter()
+                    .map(|(k, v)| format!("{}: {}", k, v.to_string_value()))
+                    .collect();
+                format!("{} {{ {} }}", s.type_name, pairs.join(", "))
+            }
+            ScriptValue::Ref(r) => format!("<Ref #{}>", r.index),
             ScriptValue::Element(e) => format!("<Element #{}>", e.id),
             ScriptValue::Function(f) => format!("<Function {}>", f.name),
             ScriptValue::NativeFunction(f) => format!("<NativeFunction {}>", f.name),
@@ -448,6 +466,8 @@ impl ScriptValue {
             ScriptValue::Bytes(_) => "bytes",
             ScriptValue::Array(_) => "array",
             ScriptValue::Object(_) => "object",
+            ScriptValue::Struct(_) => "struct",
+            ScriptValue::Ref(_) => "ref",
             ScriptValue::Element(_) => "element",
             ScriptValue::Function(_) => "function",
             ScriptValue::NativeFunction(_) => "native_function",
@@ -513,6 +533,28 @@ impl ElementRef {
     }
 }
 
+// ============================================================================
+// Struct Value
+// ============================================================================
+
+/// 構造体インスタンス
+#[derive(Debug, Clone)]
+pub struct StructValue {
+    /// 型名（メソッド表のキー）
+    pub type_name: String,
+    /// フィールド
+    pub fields: BTreeMap<String, ScriptValue>,
+}
+
+impl StructValue {
+    pub fn new(type_name: &str, fields: BTreeMap<String, ScriptValue>) -> Self {
+        Self {
+            type_name: String::from(type_name),
+            fields,
+        }
+    }
+}
+
 // ============================================================================
 // Function Value
 // ============================================================================
@@ -526,8 +568,10 @@ pub struct FunctionValue {
     pub params: Vec<String>,
     /// 関数本体のバイトコード位置
     pub body_addr: usize,
-    /// キャプチャされた変数（クロージャ用）
-    pub captures: BTreeMap<String, ScriptValue>,
+    /// キャプチャされた変数のセル（クロージャ用）
+    pub upvalues: Vec<HeapRef>,
+    /// async 関数か（呼び出すと本体を新しいタスクで実行し、Promise を返す）
+    pub is_async: bool,
 }
 
 impl FunctionValue {
@@ -536,18 +580,38 @@ impl FunctionValue {
             name: String::from(name),
             params,
             body_addr,
-            captures: BTreeMap::new(),
+            upvalues: Vec::new(),
+            is_async: false,
         }
     }
 
-    pub fn closure(params: Vec<String>, body_addr: usize, captures: BTreeMap<String, ScriptValue>) -> Self {
+    pub fn closure(params: Vec<String>, body_addr: usize, upvalues: Vec<HeapRef>) -> Self {
         Self {
             name: String::from("<closure>"),
             params,
             body_addr,
-            captures,
+            upvalues,
+            is_async: false,
         }
     }
+
+    /// async 関数にする
+    pub fn into_async(mut self) -> Self {
+        self.is_async = true;
+        self
+    }
+}
+
+/// VMヒープ上のオブジェクトへの参照
+///
+/// スロット番号と世代の組。回収されたスロットが再利用されると世代が変わるので、
+/// 古い参照は無効として扱える。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HeapRef {
+    /// スロット番号
+    pub index: u32,
+    /// スロットの世代
+    pub generation: u32,
 }
 
 // ============================================================================
@@ -594,6 +658,12 @@ pub enum NativeFunctionId {
     SetInterval,
     ClearTimeout,
     ClearInterval,
+    /// 指定ミリ秒後に解決する Promise を返す
+    Sleep,
+
+    // ネットワーク
+    /// URL を取得し、レスポンスで解決する Promise を返す
+    Fetch,
 
     // 文字列操作
     StringLength,
@@ -720,6 +790,16 @@ impl IteratorValue {
         }
     }
 
+    /// ソースが保持している値（まだ返していないものも含む）
+    pub fn values(&self) -> impl Iterator<Item = &ScriptValue> {
+        let (items, entries): (&[ScriptValue], &[(String, ScriptValue)]) = match &self.source {
+            IteratorSource::Array(items) | IteratorSource::ObjectValues(items) => (items, &[]),
+            IteratorSource::ObjectEntries(entries) => (&[], entries),
+            _ => (&[], &[]),
+        };
+        items.iter().chain(entries.iter().map(|(_, value)| value))
+    }
+
     /// 次の値を取得
     pub fn next(&mut self) -> Option<ScriptValue> {
         match &self.source {
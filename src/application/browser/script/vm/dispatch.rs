@@ -0,0 +1,177 @@
+// ============================================================================
+// src/application/browser/script/vm/dispatch.rs - Calls and Trait Dispatch
+// ============================================================================
+//!
+//! 関数呼び出しとトレイトによる動的ディスパッチ。
+//!
+//! `value.method()` はまず値の型のメソッド表（`impl Type` / `impl Trait for Type`
+//! で登録されたもの）を引き、無ければ組み込みメソッドにフォールバックする。
+//! ジェネリクスは型消去で扱い、トレイト境界は呼び出し時に `CheckTrait` で検査する。
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::value::{FunctionValue, ScriptValue};
+use super::super::{ErrorKind, ScriptError};
+use super::frame::{CallFrame, LocalSlot};
+use super::vm_core::VirtualMachine;
+
+/// 呼び出しの深さの上限（無限再帰でメモリを使い切らないように）
+const MAX_CALL_DEPTH: usize = 512;
+
+/// スクリプトで定義されたトレイト
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TraitInfo {
+    /// 宣言されたメソッド名
+    pub(crate) methods: Vec<String>,
+    /// デフォルト実装
+    pub(crate) defaults: BTreeMap<String, FunctionValue>,
+}
+
+impl VirtualMachine {
+    /// 関数を呼び出す
+    ///
+    /// スクリプト関数ならフレームを積んで本体へジャンプし、結果は `Return` がスタックに積む。
+    /// async 関数は本体を新しいタスクにして、その結果の Promise を積む。
+    /// ネイティブ関数はその場で実行して結果を積む。
+    pub(crate) fn invoke(&mut self, func: ScriptValue, mut args: Vec<ScriptValue>) -> Result<(), ScriptError> {
+        match func {
+            ScriptValue::Function(f) if f.is_async => {
+                let promise = self.spawn_call(ScriptValue::Function(f), args)?;
+                self.stack.push(ScriptValue::Ref(promise));
+                Ok(())
+            }
+            ScriptValue::Function(f) => {
+                if self.call_stack.len() >= MAX_CALL_DEPTH {
+                    return Err(ScriptError::runtime(&format!(
+                        "stack overflow: call depth exceeded {} in `{}`",
+                        MAX_CALL_DEPTH, f.name
+                    )));
+                }
+
+                // 引数の過不足はパラメータ数に合わせる（ハンドラはイベント引数を無視できる）
+                args.resize(f.params.len(), ScriptValue::Nil);
+
+                let frame = CallFrame {
+                    return_addr: self.pc,
+                    base_pointer: self.locals.len(),
+                    stack_depth: self.stack.len(),
+                    function_name: f.name,
+                    upvalues: f.upvalues,
+                };
+                self.call_stack.push(frame);
+
+                // 引数をローカル変数として設定
+                self.locals.extend(args.into_iter().map(LocalSlot::Value));
+
+                // 関数本体へジャンプ
+                self.pc = f.body_addr;
+                Ok(())
+            }
+            ScriptValue::NativeFunction(native) => {
+                let result = self.call_native(native.id, args)?;
+                self.stack.push(result);
+                Ok(())
+            }
+            _ => Err(ScriptError::new(ErrorKind::Runtime, "Not a function", 0, 0)),
+        }
+    }
+
+    /// メソッドを呼び出す（ユーザー定義メソッドは `self` を第1引数に渡す）
+    pub(crate) fn invoke_method(
+        &mut self,
+        receiver: ScriptValue,
+        name: &str,
+        args: Vec<ScriptValue>,
+    ) -> Result<(), ScriptError> {
+        let method = self
+            .methods
+            .get(&self.type_key(&receiver))
+            .and_then(|table| table.get(name))
+            .cloned();
+
+        if let Some(method) = method {
+            let mut full_args = Vec::with_capacity(args.len() + 1);
+            full_args.push(receiver);
+            full_args.extend(args);
+            return self.invoke(ScriptValue::Function(method), full_args);
+        }
+
+        // ヒープ上の値はその場で操作し、それ以外は引数を書き出して組み込みメソッドに渡す
+        let result = match receiver {
+            ScriptValue::Ref(r) => self.heap_method(r, name, args)?,
+            receiver => {
+                let args = args.iter().map(|arg| self.export(arg)).collect();
+                self.call_method(receiver, name, args)?
+            }
+        };
+        let result = self.adopt(result)?;
+        self.stack.push(result);
+        Ok(())
+    }
+
+    /// 関数を呼び出して結果を返す
+    ///
+    /// イベントハンドラなどホスト側から呼ぶためのもので、呼び出しを1つのタスクとして
+    /// 終わるまで実行する。await で止まったときは未解決の Promise を返す。
+    /// 引数はヒープに移し、結果は書き出したコピーを返す。
+    pub fn call_function(&mut self, func: &ScriptValue, args: Vec<ScriptValue>) -> Result<ScriptValue, ScriptError> {
+        let args = args
+            .into_iter()
+            .map(|arg| self.adopt(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let promise = self.spawn_call(func.clone(), args)?;
+        self.run_until_settled(promise)
+    }
+
+    /// `impl Trait for Type` を登録する
+    ///
+    /// 実装されていないメソッドはデフォルト実装で埋め、それも無ければエラーにする。
+    /// スクリプトで定義されていないトレイト（`Display` など）は実装の記録だけ行う。
+    pub(crate) fn impl_trait(&mut self, trait_name: String, type_name: String) -> Result<(), ScriptError> {
+        if let Some(info) = self.traits.get(&trait_name) {
+            let table = self.methods.entry(type_name.clone()).or_default();
+            for method in &info.methods {
+                if table.contains_key(method) {
+                    continue;
+                }
+                match info.defaults.get(method) {
+                    Some(default) => {
+                        table.insert(method.clone(), default.clone());
+                    }
+                    None => {
+                        return Err(ScriptError::type_error(
+                            &format!(
+                                "not all trait items implemented, missing: `{}` in `impl {} for {}`",
+                                method, trait_name, type_name
+                            ),
+                            0,
+                            0,
+                        ));
+                    }
+                }
+            }
+        }
+        self.trait_impls.insert((type_name, trait_name));
+        Ok(())
+    }
+
+    /// 値がトレイト境界を満たすか検査する（スクリプトで定義されたトレイトのみ）
+    pub(crate) fn check_trait(&self, value: &ScriptValue, trait_name: &str) -> Result<(), ScriptError> {
+        if !self.traits.contains_key(trait_name) {
+            return Ok(());
+        }
+        let type_name = self.type_key(value);
+        if self.trait_impls.contains(&(type_name.clone(), String::from(trait_name))) {
+            Ok(())
+        } else {
+            Err(ScriptError::type_error(
+                &format!("the trait bound `{}: {}` is not satisfied", type_name, trait_name),
+                0,
+                0,
+            ))
+        }
+    }
+}
@@ -23,5 +23,12 @@ pub enum DomOperation {
     GetStyle(usize, String),
     AddClass(usize, String),
     RemoveClass(usize, String),
+    /// ハンドラは `VirtualMachine::pin` で固定した関数のハンドル
     AddEventListener(usize, String, usize),
+    /// フォーム部品の値（`<textarea>` はテキスト）
+    GetValue(usize),
+    SetValue(usize, String),
+    /// チェックボックス・ラジオボタンのチェック
+    GetChecked(usize),
+    SetChecked(usize, bool),
 }
@@ -5,13 +5,14 @@
 //! 命令の実行。
 
 use alloc::collections::BTreeMap;
-use alloc::string::String;
 use alloc::vec::Vec;
 
-use super::super::value::{FunctionValue, IteratorValue, RangeValue, ScriptValue};
+use super::super::value::{FunctionValue, HeapRef, IteratorValue, RangeValue, ScriptValue, StructValue};
 use super::super::{ErrorKind, ScriptError};
-use super::frame::{CallFrame, LoopInfo};
-use super::instructions::Instruction;
+use super::frame::LocalSlot;
+use super::heap::HeapObject;
+use super::instructions::{Capture, Instruction};
+use super::dispatch::TraitInfo;
 use super::ops;
 use super::vm_core::VirtualMachine;
 
@@ -54,22 +55,16 @@ impl VirtualMachine {
 
             // 変数操作
             Instruction::LoadLocal(index) => {
-                let base = self.current_base_pointer();
-                let value = self
-                    .locals
-                    .get(base + index)
-                    .cloned()
-                    .unwrap_or(ScriptValue::Nil);
+                let value = self.read_local(index);
                 self.stack.push(value);
             }
             Instruction::StoreLocal(index) => {
-                let base = self.current_base_pointer();
                 let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                let target = base + index;
-                while self.locals.len() <= target {
-                    self.locals.push(ScriptValue::Nil);
-                }
-                self.locals[target] = value;
+                self.write_local(index, value)?;
+            }
+            Instruction::DefineLocal(index) => {
+                let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
+                *self.local_slot(index) = LocalSlot::Value(value);
             }
             Instruction::LoadGlobal(name) => {
                 let value = self.globals.get(&name).cloned().unwrap_or(ScriptValue::Nil);
@@ -79,18 +74,17 @@ impl VirtualMachine {
                 let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 self.globals.insert(name, value);
             }
-            Instruction::LoadCapture(name) => {
-                let value = if let Some(frame) = self.call_stack.last() {
-                    frame.captures.get(&name).cloned().unwrap_or(ScriptValue::Nil)
-                } else {
-                    ScriptValue::Nil
-                };
+            Instruction::LoadUpvalue(index) => {
+                let value = self
+                    .current_upvalue(index)
+                    .map(|cell| self.read_cell(cell))
+                    .unwrap_or(ScriptValue::Nil);
                 self.stack.push(value);
             }
-            Instruction::StoreCapture(name) => {
+            Instruction::StoreUpvalue(index) => {
                 let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                if let Some(frame) = self.call_stack.last_mut() {
-                    frame.captures.insert(name, value);
+                if let Some(cell) = self.current_upvalue(index) {
+                    self.heap.set_cell(cell, value)?;
                 }
             }
 
@@ -103,7 +97,8 @@ impl VirtualMachine {
                     }
                 }
                 arr.reverse();
-                self.stack.push(ScriptValue::Array(arr));
+                let r = self.heap.alloc(HeapObject::Array(arr))?;
+                self.stack.push(ScriptValue::Ref(r));
             }
             Instruction::MakeObject(count) => {
                 let mut obj = BTreeMap::new();
@@ -113,54 +108,53 @@ impl VirtualMachine {
                         obj.insert(key, value);
                     }
                 }
-                self.stack.push(ScriptValue::Object(obj));
+                let r = self.heap.alloc(HeapObject::Object(obj))?;
+                self.stack.push(ScriptValue::Ref(r));
+            }
+            Instruction::MakeStruct(type_name, count) => {
+                let mut fields = BTreeMap::new();
+                for _ in 0..count {
+                    let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
+                    if let Some(ScriptValue::String(key)) = self.stack.pop() {
+                        fields.insert(key, value);
+                    }
+                }
+                let r = self.heap.alloc(HeapObject::Struct(StructValue::new(&type_name, fields)))?;
+                self.stack.push(ScriptValue::Ref(r));
             }
             Instruction::GetField(name) => {
                 let obj = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                let value = match obj {
-                    ScriptValue::Object(map) => map.get(&name).cloned().unwrap_or(ScriptValue::Nil),
-                    ScriptValue::Element(elem) => match name.as_str() {
-                        "id" => ScriptValue::Int(elem.id as i64),
-                        "tag" => ScriptValue::String(elem.tag_name),
-                        "html_id" => elem.html_id.map(ScriptValue::String).unwrap_or(ScriptValue::Nil),
-                        _ => ScriptValue::Nil,
-                    },
-                    _ => ScriptValue::Nil,
-                };
+                let value = self.get_field(&obj, &name);
                 self.stack.push(value);
             }
             Instruction::SetField(name) => {
+                // ヒープ上の値はその場で書き換え、同じ参照を積み直す
                 let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 let obj = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                if let ScriptValue::Object(mut map) = obj {
-                    map.insert(name, value.clone());
-                    self.stack.push(ScriptValue::Object(map));
-                } else {
-                    self.stack.push(ScriptValue::Nil);
+                match obj {
+                    ScriptValue::Ref(r) => {
+                        self.heap.set_field(r, &name, value)?;
+                        self.stack.push(ScriptValue::Ref(r));
+                    }
+                    ScriptValue::Object(mut map) => {
+                        map.insert(name, value);
+                        self.stack.push(ScriptValue::Object(map));
+                    }
+                    ScriptValue::Struct(mut s) => {
+                        s.fields.insert(name, value);
+                        self.stack.push(ScriptValue::Struct(s));
+                    }
+                    ScriptValue::Element(elem) => {
+                        self.set_element_field(&elem, &name, value);
+                        self.stack.push(ScriptValue::Element(elem));
+                    }
+                    _ => self.stack.push(ScriptValue::Nil),
                 }
             }
             Instruction::GetIndex => {
                 let index = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 let container = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                let value = match (&container, &index) {
-                    (ScriptValue::Array(arr), ScriptValue::Int(i)) => {
-                        let idx = *i as usize;
-                        arr.get(idx).cloned().unwrap_or(ScriptValue::Nil)
-                    }
-                    (ScriptValue::String(s), ScriptValue::Int(i)) => {
-                        let chars: Vec<char> = s.chars().collect();
-                        let idx = *i as usize;
-                        if idx < chars.len() {
-                            ScriptValue::String(String::from(chars[idx]))
-                        } else {
-                            ScriptValue::Nil
-                        }
-                    }
-                    (ScriptValue::Object(map), ScriptValue::String(key)) => {
-                        map.get(key).cloned().unwrap_or(ScriptValue::Nil)
-                    }
-                    _ => ScriptValue::Nil,
-                };
+                let value = self.get_index(&container, &index);
                 self.stack.push(value);
             }
             Instruction::SetIndex => {
@@ -168,6 +162,14 @@ impl VirtualMachine {
                 let index = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 let container = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 match (container, &index) {
+                    (ScriptValue::Ref(r), ScriptValue::Int(i)) => {
+                        self.heap.set_index(r, *i as usize, value)?;
+                        self.stack.push(ScriptValue::Ref(r));
+                    }
+                    (ScriptValue::Ref(r), ScriptValue::String(key)) => {
+                        self.heap.set_field(r, key, value)?;
+                        self.stack.push(ScriptValue::Ref(r));
+                    }
                     (ScriptValue::Array(mut arr), ScriptValue::Int(i)) => {
                         let idx = *i as usize;
                         if idx < arr.len() {
@@ -187,9 +189,16 @@ impl VirtualMachine {
 
             // 算術演算
             Instruction::Add => {
-                let b = self.stack.pop().unwrap_or(ScriptValue::Nil);
+                let mut b = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 let a = self.stack.pop().unwrap_or(ScriptValue::Nil);
+                if matches!((&a, &b), (ScriptValue::String(_), ScriptValue::Ref(_))) {
+                    b = ScriptValue::String(self.display(&b));
+                }
                 let result = ops::op_add(a, b)?;
+                // 文字列はヒープの外にあるが、連結で上限を超える大きさには育てない
+                if let ScriptValue::String(s) = &result {
+                    self.heap.reserve(s.len())?;
+                }
                 self.stack.push(result);
             }
             Instruction::Sub => {
@@ -351,82 +360,20 @@ impl VirtualMachine {
                     self.pc = addr;
                 }
             }
-            Instruction::LoopStart => {
-                // ループスタックに現在位置を記録
-                let start = self.pc;
-                // 対応するLoopEndを探す
-                let mut depth = 1;
-                let mut end = self.pc;
-                while depth > 0 && end < self.instructions.len() {
-                    match &self.instructions[end] {
-                        Instruction::LoopStart => depth += 1,
-                        Instruction::LoopEnd => depth -= 1,
-                        _ => {}
-                    }
-                    end += 1;
-                }
-                self.loop_stack.push(LoopInfo { start, end });
-            }
-            Instruction::LoopEnd => {
-                self.loop_stack.pop();
-            }
-            Instruction::Break => {
-                if let Some(loop_info) = self.loop_stack.last() {
-                    self.pc = loop_info.end;
-                }
-            }
-            Instruction::Continue => {
-                if let Some(loop_info) = self.loop_stack.last() {
-                    self.pc = loop_info.start;
-                }
-            }
 
             // 関数呼び出し
             Instruction::Call(argc) => {
                 // 関数を取得
                 let func = self.stack.pop().unwrap_or(ScriptValue::Nil);
-                match func {
-                    ScriptValue::Function(f) => {
-                        // 引数を収集
-                        let mut args = Vec::new();
-                        for _ in 0..argc {
-                            args.push(self.stack.pop().unwrap_or(ScriptValue::Nil));
-                        }
-                        args.reverse();
-
-                        // 呼び出しフレームを作成
-                        let frame = CallFrame {
-                            return_addr: self.pc,
-                            base_pointer: self.locals.len(),
-                            function_name: f.name.clone(),
-                            captures: f.captures.clone(),
-                        };
-                        self.call_stack.push(frame);
-
-                        // 引数をローカル変数として設定
-                        for arg in args {
-                            self.locals.push(arg);
-                        }
-
-                        // 関数本体へジャンプ
-                        self.pc = f.body_addr;
-                    }
-                    ScriptValue::NativeFunction(native) => {
-                        // 引数を収集
-                        let mut args = Vec::new();
-                        for _ in 0..argc {
-                            args.push(self.stack.pop().unwrap_or(ScriptValue::Nil));
-                        }
-                        args.reverse();
 
-                        // ネイティブ関数を実行
-                        let result = self.call_native(native.id, args)?;
-                        self.stack.push(result);
-                    }
-                    _ => {
-                        return Err(ScriptError::new(ErrorKind::Runtime, "Not a function", 0, 0));
-                    }
+                // 引数を収集
+                let mut args = Vec::new();
+                for _ in 0..argc {
+                    args.push(self.stack.pop().unwrap_or(ScriptValue::Nil));
                 }
+                args.reverse();
+
+                self.invoke(func, args)?;
             }
             Instruction::CallMethod(name, argc) => {
                 // 引数を収集
@@ -440,8 +387,7 @@ impl VirtualMachine {
                 let receiver = self.stack.pop().unwrap_or(ScriptValue::Nil);
 
                 // メソッドを実行
-                let result = self.call_method(receiver, &name, args)?;
-                self.stack.push(result);
+                self.invoke_method(receiver, &name, args)?;
             }
             Instruction::CallNative(id, argc) => {
                 let mut args = Vec::new();
@@ -457,8 +403,9 @@ impl VirtualMachine {
                 let return_value = self.stack.pop().unwrap_or(ScriptValue::Nil);
 
                 if let Some(frame) = self.call_stack.pop() {
-                    // ローカル変数をクリーンアップ
+                    // ローカル変数と、ループのイテレータなど積み残した値をクリーンアップ
                     self.locals.truncate(frame.base_pointer);
+                    self.stack.truncate(frame.stack_depth);
                     // 戻りアドレスへジャンプ
                     self.pc = frame.return_addr;
                 } else {
@@ -469,23 +416,81 @@ impl VirtualMachine {
                 self.stack.push(return_value);
             }
 
+            // 非同期
+            Instruction::Await => {
+                let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
+                if let Some(result) = self.await_value(value)? {
+                    self.stack.push(result);
+                }
+            }
+
             // クロージャ
-            Instruction::MakeClosure(body_addr, capture_names) => {
-                let mut captures = BTreeMap::new();
-                for name in capture_names {
-                    // 現在のスコープから変数をキャプチャ
-                    if let Some(value) = self.globals.get(&name) {
-                        captures.insert(name, value.clone());
-                    }
+            Instruction::MakeClosure(const_idx, captures) => {
+                let Some(ScriptValue::Function(template)) = self.constants.get(const_idx).cloned() else {
+                    return Err(ScriptError::new(ErrorKind::Runtime, "Invalid closure constant", 0, 0));
+                };
+
+                // キャプチャする変数をセルにして共有する
+                let mut upvalues = Vec::with_capacity(captures.len());
+                for capture in captures {
+                    let cell = match capture {
+                        Capture::Local(index) => self.capture_local(index)?,
+                        Capture::Upvalue(index) => self
+                            .current_upvalue(index)
+                            .ok_or_else(|| ScriptError::new(ErrorKind::Runtime, "Invalid upvalue index", 0, 0))?,
+                    };
+                    upvalues.push(cell);
                 }
-                let func = FunctionValue::closure(Vec::new(), body_addr, captures);
+
+                let func = FunctionValue { upvalues, ..template };
                 self.stack.push(ScriptValue::Function(func));
             }
 
+            // トレイト
+            Instruction::DefineMethod(type_name, name) => {
+                if let Some(ScriptValue::Function(f)) = self.stack.pop() {
+                    self.methods.entry(type_name).or_default().insert(name, f);
+                }
+            }
+            Instruction::DefineTrait(name, methods) => {
+                self.traits.insert(name, TraitInfo { methods, defaults: BTreeMap::new() });
+            }
+            Instruction::DefineDefault(trait_name, name) => {
+                if let Some(ScriptValue::Function(f)) = self.stack.pop()
+                    && let Some(info) = self.traits.get_mut(&trait_name)
+                {
+                    info.defaults.insert(name, f);
+                }
+            }
+            Instruction::ImplTrait(trait_name, type_name) => {
+                self.impl_trait(trait_name, type_name)?;
+            }
+            Instruction::LoadMethod(type_name, name) => {
+                let value = self
+                    .methods
+                    .get(&type_name)
+                    .and_then(|table| table.get(&name))
+                    .cloned()
+                    .map(ScriptValue::Function)
+                    .unwrap_or(ScriptValue::Nil);
+                self.stack.push(value);
+            }
+            Instruction::CheckTrait(index, trait_name) => {
+                let value = self.read_local(index);
+                self.check_trait(&value, &trait_name)?;
+            }
+
             // イテレータ
             Instruction::MakeIterator => {
                 let value = self.stack.pop().unwrap_or(ScriptValue::Nil);
                 let iter = match value {
+                    // 反復中に配列を書き換えても影響しないよう、要素を写しておく
+                    ScriptValue::Ref(r) => match self.heap.get(r) {
+                        Some(HeapObject::Array(items)) => IteratorValue::from_array(items.clone()),
+                        _ => {
+                            return Err(ScriptError::new(ErrorKind::Runtime, "Value is not iterable", 0, 0));
+                        }
+                    },
                     ScriptValue::Array(arr) => IteratorValue::from_array(arr),
                     ScriptValue::Range(r) => r.to_iterator(),
                     ScriptValue::String(s) => IteratorValue::from_string(s),
@@ -551,4 +556,59 @@ impl VirtualMachine {
 
         Ok(())
     }
+
+    /// 現在のフレームのローカル変数スロット（未使用なら Nil で確保する）
+    fn local_slot(&mut self, index: usize) -> &mut LocalSlot {
+        let target = self.current_base_pointer() + index;
+        while self.locals.len() <= target {
+            self.locals.push(LocalSlot::Value(ScriptValue::Nil));
+        }
+        &mut self.locals[target]
+    }
+
+    /// ローカル変数の値（キャプチャ済みならセルから読む）
+    fn read_local(&self, index: usize) -> ScriptValue {
+        match self.locals.get(self.current_base_pointer() + index) {
+            Some(LocalSlot::Value(value)) => value.clone(),
+            Some(LocalSlot::Captured(cell)) => self.read_cell(*cell),
+            None => ScriptValue::Nil,
+        }
+    }
+
+    /// ローカル変数に書く（キャプチャ済みならセルに書く）
+    fn write_local(&mut self, index: usize, value: ScriptValue) -> Result<(), ScriptError> {
+        match self.local_slot(index) {
+            LocalSlot::Value(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            LocalSlot::Captured(cell) => {
+                let cell = *cell;
+                self.heap.set_cell(cell, value)
+            }
+        }
+    }
+
+    /// ローカル変数をセルに移して返す（既にセルならそれを共有する）
+    fn capture_local(&mut self, index: usize) -> Result<HeapRef, ScriptError> {
+        let value = match self.local_slot(index) {
+            LocalSlot::Captured(cell) => return Ok(*cell),
+            LocalSlot::Value(value) => value.clone(),
+        };
+        let cell = self.heap.alloc(HeapObject::Cell(value))?;
+        *self.local_slot(index) = LocalSlot::Captured(cell);
+        Ok(cell)
+    }
+
+    /// 実行中のクロージャのキャプチャ変数のセル
+    fn current_upvalue(&self, index: usize) -> Option<HeapRef> {
+        self.call_stack.last().and_then(|frame| frame.upvalues.get(index).copied())
+    }
+
+    fn read_cell(&self, cell: HeapRef) -> ScriptValue {
+        match self.heap.get(cell) {
+            Some(HeapObject::Cell(value)) => value.clone(),
+            _ => ScriptValue::Nil,
+        }
+    }
 }
@@ -1,13 +1,13 @@
 // ============================================================================
-// src/application/browser/script/vm/frame.rs - Call Frame and Loop Info
+// src/application/browser/script/vm/frame.rs - Call Frame and Local Slots
 // ============================================================================
 //!
-//! 呼び出しフレームとループ情報。
+//! 呼び出しフレームとローカル変数スロット。
 
-use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
 
-use super::super::value::ScriptValue;
+use super::super::value::{HeapRef, ScriptValue};
 
 // ============================================================================
 // Call Frame
@@ -20,21 +20,26 @@ pub struct CallFrame {
     pub return_addr: usize,
     /// ローカル変数のベースポインタ
     pub base_pointer: usize,
+    /// 呼び出し時のオペランドスタックの深さ（return で巻き戻す）
+    pub stack_depth: usize,
     /// 関数名（デバッグ用）
     pub function_name: String,
-    /// キャプチャされた変数（クロージャ用）
-    pub captures: BTreeMap<String, ScriptValue>,
+    /// キャプチャされた変数のセル（クロージャ用）
+    pub upvalues: Vec<HeapRef>,
 }
 
 // ============================================================================
-// Loop Info
+// Local Slot
 // ============================================================================
 
-/// ループ情報
+/// ローカル変数スロット
+///
+/// クロージャにキャプチャされた変数はヒープ上のセルへ移され、
+/// 以後の読み書きはセル経由になる。
 #[derive(Debug, Clone)]
-pub struct LoopInfo {
-    /// ループの開始位置
-    pub start: usize,
-    /// ループの終了位置（break時のジャンプ先）
-    pub end: usize,
+pub enum LocalSlot {
+    /// 通常の値
+    Value(ScriptValue),
+    /// キャプチャ済みの変数（セルへの参照）
+    Captured(HeapRef),
 }
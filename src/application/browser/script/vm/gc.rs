@@ -0,0 +1,150 @@
+// ============================================================================
+// src/application/browser/script/vm/gc.rs - Garbage Collection
+// ============================================================================
+//!
+//! VM 側の回収処理。
+//!
+//! 回収は命令の合間（セーフポイント）でだけ進めるので、実行中の命令が手元に
+//! 持っている値が回収されることはない。ルートは実行中と中断中の各タスクの
+//! オペランドスタック・ローカル変数・呼び出しフレームのセル、タスクの結果と await 中の
+//! Promise、グローバル変数、固定された値（イベントハンドラやタイマーのコールバックなど）。
+
+use super::super::value::ScriptValue;
+use super::frame::{CallFrame, LocalSlot};
+use super::heap::{GcPhase, Heap};
+use super::vm_core::VirtualMachine;
+
+/// 1回のセーフポイントでたどる灰色オブジェクトの数
+const MARK_BUDGET: usize = 64;
+
+/// 1回のセーフポイントでスイープするスロットの数
+const SWEEP_BUDGET: usize = 256;
+
+impl VirtualMachine {
+    /// 命令の合間に回収を少し進める
+    pub(crate) fn gc_safepoint(&mut self) {
+        if self.heap.phase() == GcPhase::Idle {
+            if !self.heap.should_collect() {
+                return;
+            }
+            self.heap.begin_mark();
+            self.shade_roots();
+        }
+
+        // 上限が近ければ少しずつ進める余裕は無い
+        if self.heap.under_pressure() {
+            self.finish_gc_cycle();
+            return;
+        }
+
+        match self.heap.phase() {
+            GcPhase::Mark => {
+                if self.heap.mark_step(MARK_BUDGET) {
+                    self.finish_mark();
+                }
+            }
+            GcPhase::Sweep => {
+                self.heap.sweep_step(SWEEP_BUDGET);
+            }
+            GcPhase::Idle => {}
+        }
+    }
+
+    /// 全ての到達不能なオブジェクトを回収する
+    pub fn collect_garbage(&mut self) {
+        // 進行中のサイクルより後に捨てられたものも回収するため、もう1サイクル回す
+        self.finish_gc_cycle();
+        self.heap.begin_mark();
+        self.finish_gc_cycle();
+    }
+
+    /// 進行中のサイクルを最後まで進める
+    fn finish_gc_cycle(&mut self) {
+        if self.heap.phase() == GcPhase::Mark {
+            self.finish_mark();
+        }
+        self.heap.sweep_step(usize::MAX);
+    }
+
+    /// ルートを再走査して印付けを終え、スイープに移る
+    fn finish_mark(&mut self) {
+        self.shade_roots();
+        self.heap.mark_step(usize::MAX);
+        self.heap.begin_sweep();
+    }
+
+    fn shade_roots(&mut self) {
+        shade_context(&mut self.heap, &self.stack, &self.locals, &self.call_stack);
+        if let Some(promise) = self.awaiting {
+            self.heap.shade_ref(promise);
+        }
+        for task in self.tasks.values() {
+            let context = &task.context;
+            shade_context(&mut self.heap, &context.stack, &context.locals, &context.call_stack);
+            self.heap.shade_ref(task.promise);
+            if let Some(promise) = task.awaiting {
+                self.heap.shade_ref(promise);
+            }
+        }
+        for value in self.globals.values().chain(self.pinned.values()) {
+            self.heap.shade_value(value);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Pinned values
+    // ------------------------------------------------------------------------
+
+    /// 値を回収されないように固定し、ハンドルを返す
+    ///
+    /// VM の外（DOM のイベントハンドラ表など）が保持する値はルートから見えないので、
+    /// ハンドル越しに参照すること。
+    pub fn pin(&mut self, value: ScriptValue) -> usize {
+        let handle = self.next_pin;
+        self.next_pin += 1;
+        self.pinned.insert(handle, value);
+        handle
+    }
+
+    /// 固定した値
+    pub fn pinned(&self, handle: usize) -> Option<&ScriptValue> {
+        self.pinned.get(&handle)
+    }
+
+    /// 固定を外す
+    pub fn unpin(&mut self, handle: usize) -> Option<ScriptValue> {
+        self.pinned.remove(&handle)
+    }
+
+    // ------------------------------------------------------------------------
+    // Memory limit
+    // ------------------------------------------------------------------------
+
+    /// ヒープのメモリ上限（バイト）を設定する
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.heap.set_limit(bytes);
+    }
+
+    /// ヒープの使用量の見積もり（バイト）
+    pub fn memory_usage(&self) -> usize {
+        self.heap.bytes()
+    }
+}
+
+/// タスクの実行状態に含まれる参照を灰色にする
+fn shade_context(heap: &mut Heap, stack: &[ScriptValue], locals: &[LocalSlot], call_stack: &[CallFrame]) {
+    for value in stack {
+        heap.shade_value(value);
+    }
+    for slot in locals {
+        match slot {
+            LocalSlot::Value(value) => heap.shade_value(value),
+            LocalSlot::Captured(cell) => heap.shade_ref(*cell),
+        }
+    }
+    for frame in call_stack {
+        for cell in &frame.upvalues {
+            heap.shade_ref(*cell);
+        }
+    }
+}
@@ -0,0 +1,657 @@
+// ============================================================================
+// src/application/browser/script/vm/heap.rs - Traced Script Heap
+// ============================================================================
+//!
+//! # スクリプトヒープ
+//!
+//! 配列・オブジェクト・構造体・キャプチャ変数のセル・Promise を置くヒープ。
+//! 値は `HeapRef`（スロット番号と世代）で参照されるので、循環した構造も表現できる。
+//!
+//! ## インクリメンタル・マーク＆スイープ
+//! 回収は Idle → Mark → Sweep の3段階で、VM が命令の合間に少しずつ進める。
+//! - Mark: ルートから灰色リストをたどって印を付ける。マーク中にオブジェクトへ格納された値は
+//!   書き込みバリアで灰色にし、新しく確保したオブジェクトも灰色で生まれる。
+//!   灰色リストが空になったらルートを再走査して残りを一度に印付けする。
+//! - Sweep: スロットを順に走査し、印の無いものを解放する。
+//!   走査位置より後ろに確保したオブジェクトは印付きで生まれるので解放されない。
+//!
+//! ## メモリ上限
+//! 確保量はオブジェクトごとの概算で数え、上限を超える確保は `ErrorKind::Memory` の
+//! エラーになる。
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use super::super::value::{HeapRef, ScriptValue, StructValue};
+use super::super::ScriptError;
+
+/// タスクの識別子（Promise の待ち行列に入れる）
+pub type TaskId = u64;
+
+/// 既定のメモリ上限（バイト）
+pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// 回収を始める確保量の下限
+const MIN_THRESHOLD: usize = 256 * 1024;
+
+/// オブジェクト1個あたりの固定の見積もり
+const OBJECT_OVERHEAD: usize = 48;
+
+// ============================================================================
+// Heap Object
+// ============================================================================
+
+/// ヒープ上のオブジェクト
+#[derive(Debug, Clone)]
+pub enum HeapObject {
+    /// 配列
+    Array(Vec<ScriptValue>),
+    /// オブジェクト（キー -> 値）
+    Object(BTreeMap<String, ScriptValue>),
+    /// 構造体インスタンス
+    Struct(StructValue),
+    /// クロージャにキャプチャされた変数
+    Cell(ScriptValue),
+    /// Promise（async 関数の結果・タイマー・fetch）
+    Promise(PromiseCell),
+}
+
+/// Promise の状態
+#[derive(Debug, Clone)]
+pub enum PromiseCell {
+    /// 未解決（await して待っているタスク）
+    Pending(Vec<TaskId>),
+    /// 成功
+    Fulfilled(ScriptValue),
+    /// 失敗（await したタスクに同じエラーを返す）
+    Rejected(ScriptError),
+}
+
+/// 回収の段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcPhase {
+    /// 回収していない
+    Idle,
+    /// 到達可能なオブジェクトに印を付けている
+    Mark,
+    /// 印の無いオブジェクトを解放している
+    Sweep,
+}
+
+/// ヒープのスロット
+#[derive(Debug)]
+struct Slot {
+    /// 世代（解放するたびに増える）
+    generation: u32,
+    /// 印
+    marked: bool,
+    /// 見積もりサイズ
+    size: usize,
+    /// オブジェクト（空きスロットなら None）
+    object: Option<HeapObject>,
+}
+
+// ============================================================================
+// Heap
+// ============================================================================
+
+/// スクリプトヒープ
+#[derive(Debug)]
+pub struct Heap {
+    /// スロット
+    slots: Vec<Slot>,
+    /// 空きスロット
+    free: Vec<u32>,
+    /// 確保量の見積もり
+    bytes: usize,
+    /// メモリ上限
+    limit: usize,
+    /// 次の回収を始める確保量
+    threshold: usize,
+    /// 回収の段階
+    phase: GcPhase,
+    /// 印を付けたが中身をまだたどっていないオブジェクト
+    gray: Vec<HeapRef>,
+    /// スイープの走査位置
+    sweep_cursor: usize,
+    /// 完了した回収サイクル数
+    cycles: usize,
+}
+
+impl Heap {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            bytes: 0,
+            limit,
+            threshold: Self::next_threshold(0, limit),
+            phase: GcPhase::Idle,
+            gray: Vec::new(),
+            sweep_cursor: 0,
+            cycles: 0,
+        }
+    }
+
+    /// 回収後の生存量から次の回収開始点を決める（上限に近いほど間隔を詰める）
+    fn next_threshold(live: usize, limit: usize) -> usize {
+        let room = limit.saturating_sub(live);
+        (live * 2).max(MIN_THRESHOLD).min(live + room / 2)
+    }
+
+    /// メモリ上限を変更する
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.threshold = Self::next_threshold(self.bytes, limit);
+    }
+
+    /// メモリ上限
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// 確保量の見積もり
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// 生きているオブジェクトの数
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 完了した回収サイクル数
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn phase(&self) -> GcPhase {
+        self.phase
+    }
+
+    /// 回収を始める確保量に達したか
+    pub fn should_collect(&self) -> bool {
+        self.bytes >= self.threshold
+    }
+
+    /// 上限の 7/8 を超えたか（進行中の回収をその場で終わらせる）
+    pub fn under_pressure(&self) -> bool {
+        self.bytes >= self.limit / 8 * 7
+    }
+
+    // ------------------------------------------------------------------------
+    // Allocation and access
+    // ------------------------------------------------------------------------
+
+    /// 上限内に `extra` バイトを確保できるか検査する
+    pub fn reserve(&self, extra: usize) -> Result<(), ScriptError> {
+        if self.bytes.saturating_add(extra) > self.limit {
+            return Err(ScriptError::memory(&format!(
+                "script heap limit of {} bytes exceeded",
+                self.limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// オブジェクトを確保する
+    pub fn alloc(&mut self, object: HeapObject) -> Result<HeapRef, ScriptError> {
+        let size = object_size(&object);
+        self.reserve(size)?;
+
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, marked: false, size: 0, object: None });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let marked = match self.phase {
+            GcPhase::Idle => false,
+            GcPhase::Mark => true,
+            // 走査済みの位置なら次のサイクルまで白、未走査なら今回は残す
+            GcPhase::Sweep => index as usize >= self.sweep_cursor,
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.marked = marked;
+        slot.size = size;
+        slot.object = Some(object);
+        self.bytes += size;
+
+        let r = HeapRef { index, generation: slot.generation };
+        if self.phase == GcPhase::Mark {
+            // 中身はスタックから移されたばかりでルートから見えないので、たどり直す
+            self.gray.push(r);
+        }
+        Ok(r)
+    }
+
+    fn slot(&self, r: HeapRef) -> Option<&Slot> {
+        self.slots
+            .get(r.index as usize)
+            .filter(|slot| slot.generation == r.generation && slot.object.is_some())
+    }
+
+    fn slot_mut(&mut self, r: HeapRef) -> Result<&mut Slot, ScriptError> {
+        self.slots
+            .get_mut(r.index as usize)
+            .filter(|slot| slot.generation == r.generation && slot.object.is_some())
+            .ok_or_else(|| ScriptError::runtime("Dangling heap reference"))
+    }
+
+    /// オブジェクトを参照する（回収済みなら None）
+    pub fn get(&self, r: HeapRef) -> Option<&HeapObject> {
+        self.slot(r).and_then(|slot| slot.object.as_ref())
+    }
+
+    /// オブジェクトを書き換える
+    ///
+    /// `stored` は格納する値（書き込みバリアと上限の検査に使う）、`extra` はそれ以外に
+    /// 増える見積もり。`update` は書き換えで外れた値の見積もりサイズを返す。
+    fn update<F>(&mut self, r: HeapRef, stored: Option<&ScriptValue>, extra: usize, update: F) -> Result<(), ScriptError>
+    where
+        F: FnOnce(&mut HeapObject) -> usize,
+    {
+        let grow = stored.map(value_size).unwrap_or(0) + extra;
+        self.reserve(grow)?;
+        if let Some(value) = stored {
+            self.write_barrier(value);
+        }
+
+        let slot = self.slot_mut(r)?;
+        let object = slot.object.as_mut().expect("slot_mut only returns live slots");
+        let shrink = update(object);
+        slot.size = (slot.size + grow).saturating_sub(shrink);
+        self.bytes = (self.bytes + grow).saturating_sub(shrink);
+        Ok(())
+    }
+
+    /// 配列の末尾に追加する
+    pub fn push(&mut self, r: HeapRef, value: ScriptValue) -> Result<(), ScriptError> {
+        let stored = value.clone();
+        self.update(r, Some(&stored), 0, |object| match object {
+            HeapObject::Array(items) => {
+                items.push(value);
+                0
+            }
+            _ => value_size(&value),
+        })
+    }
+
+    /// 配列の末尾を取り出す
+    pub fn pop(&mut self, r: HeapRef) -> Result<Option<ScriptValue>, ScriptError> {
+        let mut popped = None;
+        self.update(r, None, 0, |object| match object {
+            HeapObject::Array(items) => {
+                popped = items.pop();
+                popped.as_ref().map(value_size).unwrap_or(0)
+            }
+            _ => 0,
+        })?;
+        Ok(popped)
+    }
+
+    /// 配列の要素を書き換える（範囲外なら何もしない）
+    pub fn set_index(&mut self, r: HeapRef, index: usize, value: ScriptValue) -> Result<(), ScriptError> {
+        let stored = value.clone();
+        self.update(r, Some(&stored), 0, |object| match object {
+            HeapObject::Array(items) if index < items.len() => {
+                value_size(&core::mem::replace(&mut items[index], value))
+            }
+            _ => value_size(&value),
+        })
+    }
+
+    /// オブジェクトのキー・構造体のフィールドを書き換える
+    pub fn set_field(&mut self, r: HeapRef, key: &str, value: ScriptValue) -> Result<(), ScriptError> {
+        let new_key = match self.get(r) {
+            Some(HeapObject::Object(map)) => !map.contains_key(key),
+            Some(HeapObject::Struct(s)) => !s.fields.contains_key(key),
+            _ => false,
+        };
+        let stored = value.clone();
+        let extra = if new_key { key.len() } else { 0 };
+        self.update(r, Some(&stored), extra, |object| {
+            let fields = match object {
+                HeapObject::Object(map) => map,
+                HeapObject::Struct(s) => &mut s.fields,
+                _ => return value_size(&value),
+            };
+            fields.insert(String::from(key), value).map(|old| value_size(&old)).unwrap_or(0)
+        })
+    }
+
+    /// セルの値を書き換える
+    pub fn set_cell(&mut self, r: HeapRef, value: ScriptValue) -> Result<(), ScriptError> {
+        let stored = value.clone();
+        self.update(r, Some(&stored), 0, |object| match object {
+            HeapObject::Cell(slot) => value_size(&core::mem::replace(slot, value)),
+            _ => value_size(&value),
+        })
+    }
+
+    /// 未解決の Promise を待つタスクを登録する
+    pub fn add_waiter(&mut self, r: HeapRef, task: TaskId) -> Result<(), ScriptError> {
+        self.update(r, None, 0, |object| {
+            if let HeapObject::Promise(PromiseCell::Pending(waiters)) = object {
+                waiters.push(task);
+            }
+            0
+        })
+    }
+
+    /// Promise を解決し、待っていたタスクを返す（解決済みなら何もしない）
+    pub fn settle_promise(
+        &mut self,
+        r: HeapRef,
+        result: Result<ScriptValue, ScriptError>,
+    ) -> Result<Vec<TaskId>, ScriptError> {
+        let stored = result.as_ref().ok().cloned();
+        let size = stored.as_ref().map(value_size).unwrap_or(0);
+        let mut waiters = Vec::new();
+        self.update(r, stored.as_ref(), 0, |object| match object {
+            HeapObject::Promise(cell @ PromiseCell::Pending(_)) => {
+                let settled = match result {
+                    Ok(value) => PromiseCell::Fulfilled(value),
+                    Err(err) => PromiseCell::Rejected(err),
+                };
+                if let PromiseCell::Pending(pending) = core::mem::replace(cell, settled) {
+                    waiters = pending;
+                }
+                0
+            }
+            // 格納しなかった分の見積もりを戻す
+            _ => size,
+        })?;
+        Ok(waiters)
+    }
+
+    /// 配列を反転する
+    pub fn reverse(&mut self, r: HeapRef) -> Result<(), ScriptError> {
+        self.update(r, None, 0, |object| {
+            if let HeapObject::Array(items) = object {
+                items.reverse();
+            }
+            0
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // Marking
+    // ------------------------------------------------------------------------
+
+    /// 書き込みバリア（マーク中に格納された値は灰色にする）
+    pub fn write_barrier(&mut self, value: &ScriptValue) {
+        if self.phase == GcPhase::Mark {
+            self.shade_value(value);
+        }
+    }
+
+    /// 回収サイクルを始める（続けてルートを灰色にすること）
+    pub fn begin_mark(&mut self) {
+        if self.phase == GcPhase::Idle {
+            self.phase = GcPhase::Mark;
+        }
+    }
+
+    /// 参照先を灰色にする
+    pub fn shade_ref(&mut self, r: HeapRef) {
+        if let Some(slot) = self.slots.get_mut(r.index as usize)
+            && slot.generation == r.generation
+            && slot.object.is_some()
+            && !slot.marked
+        {
+            slot.marked = true;
+            self.gray.push(r);
+        }
+    }
+
+    /// 値に含まれる参照を灰色にする
+    pub fn shade_value(&mut self, value: &ScriptValue) {
+        let mut refs = Vec::new();
+        collect_refs(value, &mut refs);
+        for r in refs {
+            self.shade_ref(r);
+        }
+    }
+
+    /// 灰色のオブジェクトを最大 `budget` 個たどる（灰色が無くなれば true）
+    pub fn mark_step(&mut self, budget: usize) -> bool {
+        let mut refs = Vec::new();
+        for _ in 0..budget {
+            let Some(r) = self.gray.pop() else {
+                break;
+            };
+            if let Some(object) = self.get(r) {
+                match object {
+                    HeapObject::Array(items) => items.iter().for_each(|v| collect_refs(v, &mut refs)),
+                    HeapObject::Object(map) => map.values().for_each(|v| collect_refs(v, &mut refs)),
+                    HeapObject::Struct(s) => s.fields.values().for_each(|v| collect_refs(v, &mut refs)),
+                    HeapObject::Cell(value) | HeapObject::Promise(PromiseCell::Fulfilled(value)) => {
+                        collect_refs(value, &mut refs)
+                    }
+                    HeapObject::Promise(_) => {}
+                }
+            }
+            for child in refs.drain(..) {
+                self.shade_ref(child);
+            }
+        }
+        self.gray.is_empty()
+    }
+
+    /// スイープを始める（ルートの再走査と灰色の処理を終えてから呼ぶ）
+    pub fn begin_sweep(&mut self) {
+        if self.phase == GcPhase::Mark {
+            self.phase = GcPhase::Sweep;
+            self.sweep_cursor = 0;
+        }
+    }
+
+    /// スロットを最大 `budget` 個スイープする（サイクルが終われば true）
+    pub fn sweep_step(&mut self, budget: usize) -> bool {
+        if self.phase != GcPhase::Sweep {
+            return true;
+        }
+        let end = self.sweep_cursor.saturating_add(budget).min(self.slots.len());
+        for index in self.sweep_cursor..end {
+            let slot = &mut self.slots[index];
+            if slot.object.is_none() {
+                continue;
+            }
+            if slot.marked {
+                slot.marked = false;
+            } else {
+                slot.object = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.bytes -= slot.size;
+                slot.size = 0;
+                self.free.push(index as u32);
+            }
+        }
+        self.sweep_cursor = end;
+
+        if self.sweep_cursor < self.slots.len() {
+            return false;
+        }
+        self.phase = GcPhase::Idle;
+        self.threshold = Self::next_threshold(self.bytes, self.limit);
+        self.cycles += 1;
+        true
+    }
+}
+
+// ============================================================================
+// Size estimation and tracing helpers
+// ============================================================================
+
+/// 値の見積もりサイズ
+pub fn value_size(value: &ScriptValue) -> usize {
+    size_of::<ScriptValue>()
+        + match value {
+            ScriptValue::String(s) => s.len(),
+            ScriptValue::Bytes(b) => b.len(),
+            ScriptValue::Array(items) => items.iter().map(value_size).sum(),
+            ScriptValue::Object(map) => map.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+            ScriptValue::Struct(s) => s.fields.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+            ScriptValue::Function(f) => f.upvalues.len() * size_of::<HeapRef>(),
+            _ => 0,
+        }
+}
+
+/// オブジェクトの見積もりサイズ
+fn object_size(object: &HeapObject) -> usize {
+    OBJECT_OVERHEAD
+        + match object {
+            HeapObject::Array(items) => items.iter().map(value_size).sum(),
+            HeapObject::Object(map) => map.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+            HeapObject::Struct(s) => {
+                s.type_name.len() + s.fields.iter().map(|(k, v)| k.len() + value_size(v)).sum::<usize>()
+            }
+            HeapObject::Cell(value) | HeapObject::Promise(PromiseCell::Fulfilled(value)) => value_size(value),
+            HeapObject::Promise(_) => 0,
+        }
+}
+
+/// 値が直接持つヒープ参照を集める
+fn collect_refs(value: &ScriptValue, out: &mut Vec<HeapRef>) {
+    match value {
+        ScriptValue::Ref(r) => out.push(*r),
+        ScriptValue::Function(f) => out.extend(f.upvalues.iter().copied()),
+        ScriptValue::Array(items) => items.iter().for_each(|v| collect_refs(v, out)),
+        ScriptValue::Object(map) => map.values().for_each(|v| collect_refs(v, out)),
+        ScriptValue::Struct(s) => s.fields.values().for_each(|v| collect_refs(v, out)),
+        ScriptValue::Iterator(iter) => iter.values().for_each(|v| collect_refs(v, out)),
+        _ => {}
+    }
+}
+
+// ============================================================================
+// Unit Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::super::ErrorKind;
+    use alloc::vec;
+
+    /// `roots` から到達できるもの以外を回収する
+    fn collect(heap: &mut Heap, roots: &[ScriptValue]) {
+        heap.begin_mark();
+        for root in roots {
+            heap.shade_value(root);
+        }
+        heap.mark_step(usize::MAX);
+        heap.begin_sweep();
+        heap.sweep_step(usize::MAX);
+    }
+
+    #[test]
+    fn test_alloc_and_get() {
+        let mut heap = Heap::new(DEFAULT_MEMORY_LIMIT);
+        let r = heap.alloc(HeapObject::Array(vec![ScriptValue::Int(1)])).unwrap();
+        heap.push(r, ScriptValue::Int(2)).unwrap();
+        match heap.get(r) {
+            Some(HeapObject::Array(items)) => assert_eq!(items.len(), 2),
+            other => panic!("Expected array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unreachable_cycle_is_collected() {
+        let mut heap = Heap::new(DEFAULT_MEMORY_LIMIT);
+        let kept = heap.alloc(HeapObject::Array(Vec::new())).unwrap();
+        let root = heap.alloc(HeapObject::Array(vec![ScriptValue::Ref(kept)])).unwrap();
+
+        let a = heap.alloc(HeapObject::Array(Vec::new())).unwrap();
+        let b = heap.alloc(HeapObject::Array(vec![ScriptValue::Ref(a)])).unwrap();
+        heap.push(a, ScriptValue::Ref(b)).unwrap();
+
+        collect(&mut heap, &[ScriptValue::Ref(root)]);
+
+        assert!(heap.get(root).is_some());
+        assert!(heap.get(kept).is_some());
+        assert!(heap.get(a).is_none());
+        assert!(heap.get(b).is_none());
+        assert_eq!(heap.len(), 2);
+    }
+
+    #[test]
+    fn test_stale_reference_after_reuse() {
+        let mut heap = Heap::new(DEFAULT_MEMORY_LIMIT);
+        let old = heap.alloc(HeapObject::Cell(ScriptValue::Int(1))).unwrap();
+        collect(&mut heap, &[]);
+
+        let new = heap.alloc(HeapObject::Cell(ScriptValue::Int(2))).unwrap();
+        assert_eq!(new.index, old.index);
+        assert!(heap.get(old).is_none());
+        assert!(heap.set_cell(old, ScriptValue::Nil).is_err());
+    }
+
+    #[test]
+    fn test_store_during_mark_keeps_value_alive() {
+        let mut heap = Heap::new(DEFAULT_MEMORY_LIMIT);
+        let root = heap.alloc(HeapObject::Array(Vec::new())).unwrap();
+        let holder = heap.alloc(HeapObject::Array(Vec::new())).unwrap();
+        let moved = heap.alloc(HeapObject::Cell(ScriptValue::Int(7))).unwrap();
+        heap.push(holder, ScriptValue::Ref(moved)).unwrap();
+
+        // root を黒にしてから、holder から root へ参照を移す
+        heap.begin_mark();
+        heap.shade_ref(holder);
+        heap.shade_ref(root);
+        heap.mark_step(1);
+        heap.push(root, ScriptValue::Ref(moved)).unwrap();
+        heap.pop(holder).unwrap();
+        heap.mark_step(usize::MAX);
+        heap.begin_sweep();
+        heap.sweep_step(usize::MAX);
+
+        assert!(heap.get(moved).is_some());
+    }
+
+    #[test]
+    fn test_limit_is_enforced() {
+        let mut heap = Heap::new(4096);
+        let r = heap.alloc(HeapObject::Array(Vec::new())).unwrap();
+        let mut result = Ok(());
+        for i in 0..1000 {
+            result = heap.push(r, ScriptValue::Int(i));
+            if result.is_err() {
+                break;
+            }
+        }
+        let err = result.unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Memory);
+        assert!(heap.bytes() <= heap.limit());
+    }
+
+    #[test]
+    fn test_settled_promise_keeps_value_alive() {
+        let mut heap = Heap::new(DEFAULT_MEMORY_LIMIT);
+        let promise = heap.alloc(HeapObject::Promise(PromiseCell::Pending(Vec::new()))).unwrap();
+        heap.add_waiter(promise, 7).unwrap();
+
+        let value = heap.alloc(HeapObject::Array(vec![ScriptValue::Int(1)])).unwrap();
+        let waiters = heap.settle_promise(promise, Ok(ScriptValue::Ref(value))).unwrap();
+        assert_eq!(waiters, [7]);
+        // 2回目の解決は無視される
+        assert!(heap.settle_promise(promise, Ok(ScriptValue::Nil)).unwrap().is_empty());
+
+        collect(&mut heap, &[ScriptValue::Ref(promise)]);
+        assert!(matches!(heap.get(value), Some(HeapObject::Array(_))));
+        match heap.get(promise) {
+            Some(HeapObject::Promise(PromiseCell::Fulfilled(ScriptValue::Ref(r)))) => assert_eq!(*r, value),
+            other => panic!("Expected fulfilled promise, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+// ============================================================================
+// src/application/browser/script/vm/host.rs - Host Requests
+// ============================================================================
+//!
+//! タイマー・ネットワークなど、VM の外（イベントループ）に頼む操作の定義。
+
+use alloc::string::String;
+
+/// イベントループへの要求
+///
+/// 関数と Promise は `VirtualMachine::pin` で固定したハンドルで渡す。
+#[derive(Debug, Clone)]
+pub enum HostRequest {
+    /// `delay_ms` 後に関数を呼ぶタイマーを登録し、タイマーIDを返す（`repeat` なら繰り返す）
+    StartTimer { callback: usize, delay_ms: u64, repeat: bool },
+    /// タイマーを止める
+    ClearTimer(usize),
+    /// `delay_ms` 後に Promise を `nil` で解決する
+    Sleep { promise: usize, delay_ms: u64 },
+    /// URL を取得し、レスポンスで Promise を解決する
+    Fetch { promise: usize, url: String },
+}
@@ -31,22 +31,26 @@ pub enum Instruction {
     // 変数操作
     /// ローカル変数をロード
     LoadLocal(usize),
-    /// ローカル変数にストア
+    /// ローカル変数にストア（キャプチャ済みならセルに書く）
     StoreLocal(usize),
+    /// ローカル変数を新しく束縛（`let` / ループ変数。以前のセルとは切り離す）
+    DefineLocal(usize),
     /// グローバル変数をロード
     LoadGlobal(String),
     /// グローバル変数にストア
     StoreGlobal(String),
     /// キャプチャ変数をロード（クロージャ用）
-    LoadCapture(String),
+    LoadUpvalue(usize),
     /// キャプチャ変数にストア
-    StoreCapture(String),
+    StoreUpvalue(usize),
 
     // オブジェクト/配列操作
     /// 配列を生成
     MakeArray(usize),
     /// オブジェクトを生成
     MakeObject(usize),
+    /// 構造体インスタンスを生成（型名, フィールド数）
+    MakeStruct(String, usize),
     /// フィールドを取得
     GetField(String),
     /// フィールドを設定
@@ -92,14 +96,6 @@ pub enum Instruction {
     JumpIfFalse(usize),
     /// 条件付きジャンプ（trueの場合）
     JumpIfTrue(usize),
-    /// ループの開始
-    LoopStart,
-    /// ループの終了
-    LoopEnd,
-    /// ループを抜ける
-    Break,
-    /// ループの次のイテレーションへ
-    Continue,
 
     // 関数
     /// 関数呼び出し
@@ -111,9 +107,27 @@ pub enum Instruction {
     /// リターン
     Return,
 
+    // 非同期
+    /// スタックトップの Promise の結果を待つ（未解決ならタスクを中断する）
+    Await,
+
     // クロージャ
-    /// クロージャを生成
-    MakeClosure(usize, Vec<String>),
+    /// クロージャを生成（関数定数, キャプチャする変数）
+    MakeClosure(usize, Vec<Capture>),
+
+    // トレイト
+    /// スタックトップの関数を型のメソッドとして登録（型名, メソッド名）
+    DefineMethod(String, String),
+    /// トレイトを登録（トレイト名, メソッド名）
+    DefineTrait(String, Vec<String>),
+    /// スタックトップの関数をトレイトのデフォルト実装として登録
+    DefineDefault(String, String),
+    /// `impl Trait for Type` を登録（トレイト名, 型名）
+    ImplTrait(String, String),
+    /// 型のメソッドをロード（`Type::method`）
+    LoadMethod(String, String),
+    /// ローカル変数の値がトレイトを実装しているか検査（スロット, トレイト名）
+    CheckTrait(usize, String),
 
     // イテレータ
     /// イテレータを生成
@@ -136,6 +150,15 @@ pub enum Instruction {
     Halt,
 }
 
+/// クロージャがキャプチャする変数の場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    /// 生成する関数のローカル変数
+    Local(usize),
+    /// 生成する関数自身のキャプチャ変数
+    Upvalue(usize),
+}
+
 // ============================================================================
 // Constant Pool
 // ============================================================================
@@ -160,4 +183,17 @@ impl ConstantPool {
     pub fn get(&self, index: usize) -> Option<&ScriptValue> {
         self.constants.get(index)
     }
+
+    pub fn len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty()
+    }
+
+    /// 別のプールの定数を末尾に追加
+    pub fn append(&mut self, other: ConstantPool) {
+        self.constants.extend(other.constants);
+    }
 }
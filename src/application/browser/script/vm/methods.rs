@@ -22,6 +22,13 @@ impl VirtualMachine {
         name: &str,
         args: Vec<ScriptValue>,
     ) -> Result<ScriptValue, ScriptError> {
+        // 全ての型に共通のメソッド
+        match name {
+            "to_string" => return Ok(ScriptValue::String(receiver.to_string_value())),
+            "clone" => return Ok(receiver),
+            _ => {}
+        }
+
         match receiver {
             ScriptValue::String(s) => match name {
                 "len" | "length" => Ok(ScriptValue::Int(s.len() as i64)),
@@ -143,6 +150,24 @@ impl VirtualMachine {
                 _ => Ok(ScriptValue::Nil),
             },
             ScriptValue::Element(elem) => {
+                // イベントハンドラは VM に固定し、DOM にはハンドルだけを渡す
+                let listener = match (name, args.first(), args.get(1)) {
+                    ("on_click", Some(f @ ScriptValue::Function(_)), _) => Some((String::from("click"), f)),
+                    ("on_input", Some(f @ ScriptValue::Function(_)), _) => Some((String::from("input"), f)),
+                    ("on", Some(ScriptValue::String(event)), Some(f @ ScriptValue::Function(_))) => {
+                        Some((event.clone(), f))
+                    }
+                    _ => None,
+                };
+                if let Some((event, handler)) = listener
+                    && self.dom_callback.is_some()
+                {
+                    let handle = self.pin(handler.clone());
+                    if let Some(callback) = &self.dom_callback {
+                        return Ok(callback(DomOperation::AddEventListener(elem.id, event, handle)));
+                    }
+                }
+
                 // DOM要素のメソッドはDOMコールバック経由で処理
                 if let Some(callback) = &self.dom_callback {
                     match name {
@@ -206,16 +231,25 @@ impl VirtualMachine {
                                 )));
                             }
                         }
-                        "on" => {
-                            if let (
-                                Some(ScriptValue::String(event)),
-                                Some(ScriptValue::Function(f)),
-                            ) = (args.get(0), args.get(1))
-                            {
-                                return Ok(callback(DomOperation::AddEventListener(
+                        "value" => {
+                            return Ok(callback(DomOperation::GetValue(elem.id)));
+                        }
+                        "set_value" => {
+                            if let Some(value) = args.get(0) {
+                                return Ok(callback(DomOperation::SetValue(
+                                    elem.id,
+                                    value.to_string_value(),
+                                )));
+                            }
+                        }
+                        "checked" => {
+                            return Ok(callback(DomOperation::GetChecked(elem.id)));
+                        }
+                        "set_checked" => {
+                            if let Some(value) = args.get(0) {
+                                return Ok(callback(DomOperation::SetChecked(
                                     elem.id,
-                                    event.clone(),
-                                    f.body_addr,
+                                    value.is_truthy(),
                                 )));
                             }
                         }
@@ -8,25 +8,39 @@
 //!
 //! ## モジュール構成
 //! - `instructions` - バイトコード命令と定数プール
-//! - `frame` - 呼び出しフレームとループ情報
+//! - `frame` - 呼び出しフレームとローカル変数スロット
 //! - `dom` - DOM操作定義
 //! - `vm_core` - VM本体
 //! - `exec` - 命令実行
+//! - `dispatch` - 関数呼び出しとトレイトによる動的ディスパッチ
+//! - `tasks` - タスクの切り替え・Promise・命令数の予算による実行
+//! - `host` - イベントループへの要求（タイマー・fetch）
+//! - `heap` - ヒープとインクリメンタル・マーク＆スイープ
+//! - `gc` - ルートの走査と回収の進行
+//! - `objects` - ヒープ上の配列・オブジェクト・構造体の操作
 //! - `ops` - 演算ヘルパー
 //! - `native` - ネイティブ関数実行
 //! - `methods` - メソッド呼び出し
 
+mod dispatch;
 mod dom;
 mod exec;
 mod frame;
+mod gc;
+mod heap;
+mod host;
 mod instructions;
 mod methods;
 mod native;
+mod objects;
 mod ops;
+mod tasks;
 mod vm_core;
 
 // 型の再エクスポート
 pub use dom::DomOperation;
-pub use frame::CallFrame;
-pub use instructions::{ConstantPool, Instruction};
+pub use frame::{CallFrame, LocalSlot};
+pub use heap::{GcPhase, Heap, HeapObject, PromiseCell, TaskId, DEFAULT_MEMORY_LIMIT};
+pub use host::HostRequest;
+pub use instructions::{Capture, ConstantPool, Instruction};
 pub use vm_core::VirtualMachine;
@@ -10,15 +10,122 @@ use alloc::vec::Vec;
 use super::super::value::{NativeFunctionId, ScriptValue};
 use super::super::{ErrorKind, ScriptError};
 use super::dom::DomOperation;
+use super::heap::HeapObject;
+use super::host::HostRequest;
 use super::ops;
 use super::vm_core::VirtualMachine;
 
 impl VirtualMachine {
     /// ネイティブ関数の実行
+    ///
+    /// 配列関数はヒープ上の配列をその場で操作し、タイマーと fetch はホストに要求を出す。
+    /// それ以外の関数には引数を書き出して渡し、返ってきたコンテナはヒープに移す。
     pub(crate) fn call_native(
         &mut self,
         id: NativeFunctionId,
         args: Vec<ScriptValue>,
+    ) -> Result<ScriptValue, ScriptError> {
+        // 配列操作
+        let array = match args.first() {
+            Some(ScriptValue::Ref(r)) => Some(*r),
+            _ => None,
+        };
+        match id {
+            NativeFunctionId::ArrayLength => {
+                return Ok(match array.and_then(|r| self.heap.get(r)) {
+                    Some(HeapObject::Array(items)) => ScriptValue::Int(items.len() as i64),
+                    _ => ScriptValue::Int(0),
+                });
+            }
+            NativeFunctionId::ArrayPush => {
+                if let (Some(r), Some(value)) = (array, args.get(1)) {
+                    self.heap.push(r, value.clone())?;
+                }
+                return Ok(ScriptValue::Nil);
+            }
+            NativeFunctionId::ArrayPop => {
+                return Ok(match array {
+                    Some(r) => self.heap.pop(r)?.unwrap_or(ScriptValue::Nil),
+                    None => ScriptValue::Nil,
+                });
+            }
+            NativeFunctionId::SetTimeout
+            | NativeFunctionId::SetInterval
+            | NativeFunctionId::ClearTimeout
+            | NativeFunctionId::ClearInterval
+            | NativeFunctionId::Sleep
+            | NativeFunctionId::Fetch => return self.call_host_native(id, args),
+            _ => {}
+        }
+
+        let args = args.iter().map(|arg| self.export(arg)).collect();
+        let result = self.call_owned_native(id, args)?;
+        self.adopt(result)
+    }
+
+    /// タイマーと fetch（ホストが無ければタイマーは登録されず、Promise は解決しない）
+    fn call_host_native(
+        &mut self,
+        id: NativeFunctionId,
+        args: Vec<ScriptValue>,
+    ) -> Result<ScriptValue, ScriptError> {
+        let delay_ms = |index: usize| match args.get(index) {
+            Some(ScriptValue::Int(ms)) => (*ms).max(0) as u64,
+            Some(ScriptValue::Float(ms)) if *ms > 0.0 => *ms as u64,
+            _ => 0,
+        };
+
+        match id {
+            NativeFunctionId::SetTimeout | NativeFunctionId::SetInterval => {
+                let Some(callback) = args
+                    .first()
+                    .filter(|f| matches!(f, ScriptValue::Function(_) | ScriptValue::NativeFunction(_))) else {
+                    return Err(ScriptError::type_error("timer callback must be a function", 0, 0));
+                };
+                if !self.has_host() {
+                    return Ok(ScriptValue::Nil);
+                }
+                let request = HostRequest::StartTimer {
+                    callback: self.pin(callback.clone()),
+                    delay_ms: delay_ms(1),
+                    repeat: id == NativeFunctionId::SetInterval,
+                };
+                Ok(self.host_request(request))
+            }
+            NativeFunctionId::ClearTimeout | NativeFunctionId::ClearInterval => {
+                if let Some(ScriptValue::Int(timer)) = args.first() {
+                    self.host_request(HostRequest::ClearTimer(*timer as usize));
+                }
+                Ok(ScriptValue::Nil)
+            }
+            NativeFunctionId::Sleep => {
+                let promise = self.new_promise()?;
+                if self.has_host() {
+                    let handle = self.pin(ScriptValue::Ref(promise));
+                    self.host_request(HostRequest::Sleep { promise: handle, delay_ms: delay_ms(0) });
+                }
+                Ok(ScriptValue::Ref(promise))
+            }
+            NativeFunctionId::Fetch => {
+                let Some(ScriptValue::String(url)) = args.first() else {
+                    return Err(ScriptError::type_error("fetch expects a URL string", 0, 0));
+                };
+                let promise = self.new_promise()?;
+                if self.has_host() {
+                    let handle = self.pin(ScriptValue::Ref(promise));
+                    self.host_request(HostRequest::Fetch { promise: handle, url: url.clone() });
+                }
+                Ok(ScriptValue::Ref(promise))
+            }
+            _ => Ok(ScriptValue::Nil),
+        }
+    }
+
+    /// 所有値を受け取るネイティブ関数
+    fn call_owned_native(
+        &mut self,
+        id: NativeFunctionId,
+        args: Vec<ScriptValue>,
     ) -> Result<ScriptValue, ScriptError> {
         match id {
             NativeFunctionId::ConsoleLog => {
@@ -131,33 +238,6 @@ impl VirtualMachine {
                 }
             }
 
-            // 配列操作
-            NativeFunctionId::ArrayLength => {
-                if let Some(ScriptValue::Array(arr)) = args.get(0) {
-                    Ok(ScriptValue::Int(arr.len() as i64))
-                } else {
-                    Ok(ScriptValue::Int(0))
-                }
-            }
-            NativeFunctionId::ArrayPush => {
-                if let (Some(ScriptValue::Array(arr)), Some(value)) = (args.get(0), args.get(1)) {
-                    let mut new_arr = arr.clone();
-                    new_arr.push(value.clone());
-                    Ok(ScriptValue::Array(new_arr))
-                } else {
-                    Ok(ScriptValue::Nil)
-                }
-            }
-            NativeFunctionId::ArrayPop => {
-                if let Some(ScriptValue::Array(arr)) = args.get(0) {
-                    let mut new_arr = arr.clone();
-                    let value = new_arr.pop().unwrap_or(ScriptValue::Nil);
-                    Ok(value)
-                } else {
-                    Ok(ScriptValue::Nil)
-                }
-            }
-
             // 数学関数
             NativeFunctionId::MathAbs => match args.get(0) {
                 Some(ScriptValue::Int(i)) => Ok(ScriptValue::Int(i.abs())),
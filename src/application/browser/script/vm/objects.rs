@@ -0,0 +1,296 @@
+// ============================================================================
+// src/application/browser/script/vm/objects.rs - Heap Values
+// ============================================================================
+//!
+//! ヒープ上の配列・オブジェクト・構造体の操作。
+//!
+//! VM の中ではコンテナと Promise は全て `ScriptValue::Ref` で扱い、代入や引数渡しで参照を共有する。
+//! ホストとの境界では `adopt` で所有値をヒープへ移し、`export` で所有値に書き出す。
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::value::{ElementRef, HeapRef, IteratorValue, PromiseState, PromiseValue, ScriptValue, StructValue};
+use super::super::ScriptError;
+use super::dom::DomOperation;
+use super::heap::{HeapObject, PromiseCell};
+use super::ops;
+use super::vm_core::VirtualMachine;
+
+impl VirtualMachine {
+    /// 所有値のコンテナをヒープに移し、参照にする
+    pub(crate) fn adopt(&mut self, value: ScriptValue) -> Result<ScriptValue, ScriptError> {
+        let object = match value {
+            ScriptValue::Array(items) => {
+                let items = items
+                    .into_iter()
+                    .map(|item| self.adopt(item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                HeapObject::Array(items)
+            }
+            ScriptValue::Object(map) => HeapObject::Object(self.adopt_fields(map)?),
+            ScriptValue::Struct(s) => {
+                let fields = self.adopt_fields(s.fields)?;
+                HeapObject::Struct(StructValue { type_name: s.type_name, fields })
+            }
+            ScriptValue::Promise(p) => HeapObject::Promise(match p.state {
+                PromiseState::Pending => PromiseCell::Pending(Vec::new()),
+                PromiseState::Fulfilled => {
+                    PromiseCell::Fulfilled(self.adopt(p.value.map(|v| *v).unwrap_or(ScriptValue::Nil))?)
+                }
+                PromiseState::Rejected => {
+                    PromiseCell::Rejected(ScriptError::runtime(&p.error.unwrap_or_default()))
+                }
+            }),
+            other => return Ok(other),
+        };
+        Ok(ScriptValue::Ref(self.heap.alloc(object)?))
+    }
+
+    fn adopt_fields(
+        &mut self,
+        fields: BTreeMap<String, ScriptValue>,
+    ) -> Result<BTreeMap<String, ScriptValue>, ScriptError> {
+        fields
+            .into_iter()
+            .map(|(key, value)| Ok((key, self.adopt(value)?)))
+            .collect()
+    }
+
+    /// 参照をたどって所有値に書き出す（循環している箇所は Nil になる）
+    pub fn export(&self, value: &ScriptValue) -> ScriptValue {
+        self.export_inner(value, &mut Vec::new())
+    }
+
+    fn export_inner(&self, value: &ScriptValue, path: &mut Vec<HeapRef>) -> ScriptValue {
+        let r = match value {
+            ScriptValue::Ref(r) => *r,
+            ScriptValue::Array(items) => {
+                return ScriptValue::Array(items.iter().map(|item| self.export_inner(item, path)).collect());
+            }
+            ScriptValue::Object(map) => return ScriptValue::Object(self.export_fields(map, path)),
+            ScriptValue::Struct(s) => {
+                return ScriptValue::Struct(StructValue::new(&s.type_name, self.export_fields(&s.fields, path)));
+            }
+            other => return other.clone(),
+        };
+        if path.contains(&r) {
+            return ScriptValue::Nil;
+        }
+        let Some(object) = self.heap.get(r) else {
+            return ScriptValue::Nil;
+        };
+
+        path.push(r);
+        let exported = match object {
+            HeapObject::Array(items) => {
+                ScriptValue::Array(items.iter().map(|item| self.export_inner(item, path)).collect())
+            }
+            HeapObject::Object(map) => ScriptValue::Object(self.export_fields(map, path)),
+            HeapObject::Struct(s) => ScriptValue::Struct(StructValue::new(&s.type_name, self.export_fields(&s.fields, path))),
+            HeapObject::Cell(value) => self.export_inner(value, path),
+            HeapObject::Promise(cell) => {
+                let promise = PromiseValue::new(u64::from(r.index));
+                ScriptValue::Promise(match cell {
+                    PromiseCell::Pending(_) => promise,
+                    PromiseCell::Fulfilled(value) => promise.resolve(self.export_inner(value, path)),
+                    PromiseCell::Rejected(err) => promise.reject(err.message.clone()),
+                })
+            }
+        };
+        path.pop();
+        exported
+    }
+
+    fn export_fields(
+        &self,
+        fields: &BTreeMap<String, ScriptValue>,
+        path: &mut Vec<HeapRef>,
+    ) -> BTreeMap<String, ScriptValue> {
+        fields
+            .iter()
+            .map(|(key, value)| (key.clone(), self.export_inner(value, path)))
+            .collect()
+    }
+
+    /// 表示用の文字列
+    pub(crate) fn display(&self, value: &ScriptValue) -> String {
+        match value {
+            ScriptValue::Ref(_) => self.export(value).to_string_value(),
+            other => other.to_string_value(),
+        }
+    }
+
+    /// メソッド表のキーとなる型名（構造体は宣言名、それ以外は組み込みの型名）
+    pub(crate) fn type_key(&self, value: &ScriptValue) -> String {
+        match value {
+            ScriptValue::Ref(r) => match self.heap.get(*r) {
+                Some(HeapObject::Array(_)) => String::from("array"),
+                Some(HeapObject::Object(_)) => String::from("object"),
+                Some(HeapObject::Struct(s)) => s.type_name.clone(),
+                Some(HeapObject::Cell(value)) => self.type_key(value),
+                Some(HeapObject::Promise(_)) => String::from("promise"),
+                None => String::from("nil"),
+            },
+            ScriptValue::Struct(s) => s.type_name.clone(),
+            other => String::from(other.type_name()),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Field and index access
+    // ------------------------------------------------------------------------
+
+    /// `object.name`
+    pub(crate) fn get_field(&self, object: &ScriptValue, name: &str) -> ScriptValue {
+        let value = match object {
+            ScriptValue::Ref(r) => match self.heap.get(*r) {
+                Some(HeapObject::Object(map)) => map.get(name),
+                Some(HeapObject::Struct(s)) => s.fields.get(name),
+                // タプルの `.0`
+                Some(HeapObject::Array(items)) => name.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            },
+            ScriptValue::Object(map) => map.get(name),
+            ScriptValue::Struct(s) => s.fields.get(name),
+            ScriptValue::Element(elem) => {
+                return match name {
+                    "id" => ScriptValue::Int(elem.id as i64),
+                    "tag" => ScriptValue::String(elem.tag_name.clone()),
+                    "html_id" => elem.html_id.clone().map(ScriptValue::String).unwrap_or(ScriptValue::Nil),
+                    "value" => self.dom_operation(DomOperation::GetValue(elem.id)),
+                    "checked" => self.dom_operation(DomOperation::GetChecked(elem.id)),
+                    _ => ScriptValue::Nil,
+                };
+            }
+            _ => None,
+        };
+        value.cloned().unwrap_or(ScriptValue::Nil)
+    }
+
+    /// `element.value = ...` / `element.checked = ...`（他のフィールドは読み取り専用）
+    pub(crate) fn set_element_field(&self, elem: &ElementRef, name: &str, value: ScriptValue) {
+        match name {
+            "value" => {
+                self.dom_operation(DomOperation::SetValue(elem.id, value.to_string_value()));
+            }
+            "checked" => {
+                self.dom_operation(DomOperation::SetChecked(elem.id, value.is_truthy()));
+            }
+            _ => {}
+        }
+    }
+
+    /// DOM 操作をホストに渡す（DOM が無ければ nil）
+    fn dom_operation(&self, op: DomOperation) -> ScriptValue {
+        self.dom_callback.as_ref().map_or(ScriptValue::Nil, |callback| callback(op))
+    }
+
+    /// `container[index]`
+    pub(crate) fn get_index(&self, container: &ScriptValue, index: &ScriptValue) -> ScriptValue {
+        let value = match (container, index) {
+            (ScriptValue::Ref(r), _) => match (self.heap.get(*r), index) {
+                (Some(HeapObject::Array(items)), ScriptValue::Int(i)) => items.get(*i as usize),
+                (Some(HeapObject::Object(map)), ScriptValue::String(key)) => map.get(key),
+                _ => None,
+            },
+            (ScriptValue::Array(items), ScriptValue::Int(i)) => items.get(*i as usize),
+            (ScriptValue::Object(map), ScriptValue::String(key)) => map.get(key),
+            (ScriptValue::String(s), ScriptValue::Int(i)) => {
+                return s
+                    .chars()
+                    .nth(*i as usize)
+                    .map(|c| ScriptValue::String(String::from(c)))
+                    .unwrap_or(ScriptValue::Nil);
+            }
+            _ => None,
+        };
+        value.cloned().unwrap_or(ScriptValue::Nil)
+    }
+
+    // ------------------------------------------------------------------------
+    // Built-in methods of heap values
+    // ------------------------------------------------------------------------
+
+    /// ヒープ上の値の組み込みメソッド（配列・オブジェクトはその場で書き換える）
+    pub(crate) fn heap_method(
+        &mut self,
+        receiver: HeapRef,
+        name: &str,
+        args: Vec<ScriptValue>,
+    ) -> Result<ScriptValue, ScriptError> {
+        let value = ScriptValue::Ref(receiver);
+        let is_object = matches!(self.heap.get(receiver), Some(HeapObject::Object(_)));
+
+        // 全ての型に共通のメソッドと、その場で書き換えるメソッド
+        match name {
+            "to_string" => return Ok(ScriptValue::String(self.display(&value))),
+            // Promise は複製しても同じ結果を待つ
+            "clone" if matches!(self.heap.get(receiver), Some(HeapObject::Promise(_))) => return Ok(value),
+            "clone" => {
+                let copy = self.export(&value);
+                return self.adopt(copy);
+            }
+            "push" => {
+                for arg in args {
+                    self.heap.push(receiver, arg)?;
+                }
+                return Ok(ScriptValue::Nil);
+            }
+            "pop" => return Ok(self.heap.pop(receiver)?.unwrap_or(ScriptValue::Nil)),
+            "reverse" => {
+                self.heap.reverse(receiver)?;
+                return Ok(ScriptValue::Nil);
+            }
+            "insert" if is_object => {
+                let mut args = args.into_iter();
+                if let (Some(ScriptValue::String(key)), Some(value)) = (args.next(), args.next()) {
+                    self.heap.set_field(receiver, &key, value)?;
+                }
+                return Ok(ScriptValue::Nil);
+            }
+            _ => {}
+        }
+
+        let result = match self.heap.get(receiver) {
+            Some(HeapObject::Array(items)) => match name {
+                "len" | "length" => ScriptValue::Int(items.len() as i64),
+                "is_empty" => ScriptValue::Bool(items.is_empty()),
+                "first" => items.first().cloned().unwrap_or(ScriptValue::Nil),
+                "last" => items.last().cloned().unwrap_or(ScriptValue::Nil),
+                "get" => match args.first() {
+                    Some(ScriptValue::Int(i)) => items.get(*i as usize).cloned().unwrap_or(ScriptValue::Nil),
+                    _ => ScriptValue::Nil,
+                },
+                "contains" => match args.first() {
+                    Some(target) => ScriptValue::Bool(items.iter().any(|item| ops::op_eq(item, target))),
+                    None => ScriptValue::Bool(false),
+                },
+                "join" => {
+                    let delim = args.first().and_then(|v| v.as_string()).unwrap_or(",");
+                    let parts: Vec<String> = items.iter().map(|item| self.display(item)).collect();
+                    ScriptValue::String(parts.join(delim))
+                }
+                "iter" => ScriptValue::Iterator(IteratorValue::from_array(items.clone())),
+                _ => ScriptValue::Nil,
+            },
+            Some(HeapObject::Object(map)) => match name {
+                "len" => ScriptValue::Int(map.len() as i64),
+                "keys" => ScriptValue::Array(map.keys().map(|k| ScriptValue::String(k.clone())).collect()),
+                "values" => ScriptValue::Array(map.values().cloned().collect()),
+                "get" => match args.first() {
+                    Some(ScriptValue::String(key)) => map.get(key).cloned().unwrap_or(ScriptValue::Nil),
+                    _ => ScriptValue::Nil,
+                },
+                "contains_key" | "has" => match args.first() {
+                    Some(ScriptValue::String(key)) => ScriptValue::Bool(map.contains_key(key)),
+                    _ => ScriptValue::Bool(false),
+                },
+                _ => ScriptValue::Nil,
+            },
+            _ => ScriptValue::Nil,
+        };
+        Ok(result)
+    }
+}
@@ -0,0 +1,389 @@
+// ============================================================================
+// src/application/browser/script/vm/tasks.rs - Tasks and Promises
+// ============================================================================
+//!
+//! # タスクと Promise
+//!
+//! スクリプトはタスク単位で実行する。スクリプト本体・イベントハンドラ・タイマーの
+//! コールバック・async 関数の呼び出しがそれぞれ1つのタスクになり、自分の
+//! オペランドスタック・呼び出しスタック・ローカル変数を持つ。
+//!
+//! 実行するときはタスクの状態を VM の実行中の状態と入れ替える。`.await` が未解決の
+//! Promise に当たるとタスクは状態を保存して中断し、Promise の待ち行列に入る。
+//! 解決されると実行待ちに戻り、結果をスタックに積んで続きから再開する。
+//! 命令数の予算を使い切ったタスクも同じように中断し、実行待ちの最後に回る。
+
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::super::value::{HeapRef, ScriptValue};
+use super::super::ScriptError;
+use super::frame::{CallFrame, LocalSlot};
+use super::heap::{HeapObject, PromiseCell, TaskId};
+use super::host::HostRequest;
+use super::vm_core::VirtualMachine;
+
+/// 同時に存在できるタスクの数（async 関数の無限再帰でメモリを使い切らないように）
+const MAX_TASKS: usize = 4096;
+
+/// タスクの実行状態（実行中は VM のフィールドと入れ替わっている）
+#[derive(Debug, Default)]
+pub(crate) struct ExecContext {
+    pub(crate) pc: usize,
+    pub(crate) stack: Vec<ScriptValue>,
+    pub(crate) call_stack: Vec<CallFrame>,
+    pub(crate) locals: Vec<LocalSlot>,
+    pub(crate) running: bool,
+}
+
+/// タスク
+#[derive(Debug)]
+pub(crate) struct Task {
+    /// 実行状態
+    pub(crate) context: ExecContext,
+    /// 終わったときに結果で解決する Promise
+    pub(crate) promise: HeapRef,
+    /// 関数呼び出しのタスクは最初のフレームから戻ったら終わる（スクリプト本体は `Halt` で終わる）
+    pub(crate) is_call: bool,
+    /// await している Promise（再開時にその結果をスタックに積む）
+    pub(crate) awaiting: Option<HeapRef>,
+}
+
+/// 1回の実行の終わり方
+enum Slice {
+    /// 最後まで実行した（結果）
+    Finished(ScriptValue),
+    /// 未解決の Promise を待つ
+    Awaiting(HeapRef),
+    /// 予算を使い切った
+    Preempted,
+}
+
+impl VirtualMachine {
+    // ------------------------------------------------------------------------
+    // Spawning
+    // ------------------------------------------------------------------------
+
+    /// 読み込んだスクリプト本体を実行待ちにする（実行は `run_tasks` で進める）
+    pub fn start(&mut self) -> Result<(), ScriptError> {
+        self.spawn_script()?;
+        Ok(())
+    }
+
+    /// スクリプト本体のタスクを作り、結果の Promise を返す
+    pub(crate) fn spawn_script(&mut self) -> Result<HeapRef, ScriptError> {
+        let context = ExecContext {
+            pc: self.entry,
+            running: true,
+            ..ExecContext::default()
+        };
+        self.spawn_task(context, false)
+    }
+
+    /// 関数呼び出しを実行待ちにする（引数はヒープに移す）
+    pub fn spawn(&mut self, func: &ScriptValue, args: Vec<ScriptValue>) -> Result<(), ScriptError> {
+        let args = args
+            .into_iter()
+            .map(|arg| self.adopt(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.spawn_call(func.clone(), args)?;
+        Ok(())
+    }
+
+    /// 関数呼び出しのタスクを作り、結果の Promise を返す
+    ///
+    /// ネイティブ関数はその場で実行し、解決済みの Promise を返す。
+    pub(crate) fn spawn_call(&mut self, func: ScriptValue, mut args: Vec<ScriptValue>) -> Result<HeapRef, ScriptError> {
+        let f = match func {
+            ScriptValue::Function(f) => f,
+            ScriptValue::NativeFunction(native) => {
+                let promise = self.new_promise()?;
+                let result = self.call_native(native.id, args);
+                self.settle(promise, result);
+                return Ok(promise);
+            }
+            _ => return Err(ScriptError::runtime("Not a function")),
+        };
+
+        args.resize(f.params.len(), ScriptValue::Nil);
+        let frame = CallFrame {
+            return_addr: f.body_addr,
+            base_pointer: 0,
+            stack_depth: 0,
+            function_name: f.name,
+            upvalues: f.upvalues,
+        };
+        let context = ExecContext {
+            pc: f.body_addr,
+            stack: Vec::new(),
+            call_stack: vec![frame],
+            locals: args.into_iter().map(LocalSlot::Value).collect(),
+            running: true,
+        };
+        self.spawn_task(context, true)
+    }
+
+    fn spawn_task(&mut self, context: ExecContext, is_call: bool) -> Result<HeapRef, ScriptError> {
+        if self.tasks.len() >= MAX_TASKS {
+            return Err(ScriptError::runtime(&format!("too many tasks: limit is {}", MAX_TASKS)));
+        }
+        let promise = self.new_promise()?;
+        let id = self.next_task;
+        self.next_task += 1;
+        self.tasks.insert(
+            id,
+            Task {
+                context,
+                promise,
+                is_call,
+                awaiting: None,
+            },
+        );
+        self.ready.push_back(id);
+        Ok(promise)
+    }
+
+    // ------------------------------------------------------------------------
+    // Scheduling
+    // ------------------------------------------------------------------------
+
+    /// 実行待ちのタスクを合計 `budget` 命令まで実行する（実行待ちが残っていれば true）
+    ///
+    /// 予算を使い切ったタスクは中断して実行待ちの最後に回すので、終わらないループも
+    /// 他のタスクや呼び出し側を止めない。失敗したタスクのエラーは `take_task_error` で取り出す。
+    pub fn run_tasks(&mut self, budget: usize) -> bool {
+        let mut remaining = budget;
+        while remaining > 0 {
+            let Some(id) = self.ready.pop_front() else {
+                break;
+            };
+            match self.run_task(id, remaining) {
+                Ok(used) => remaining = remaining.saturating_sub(used),
+                Err(err) => self.task_errors.push_back(err),
+            }
+        }
+        self.has_ready_tasks()
+    }
+
+    /// 実行待ちのタスクがあるか
+    pub fn has_ready_tasks(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    /// 存在するタスク（実行待ち・待機中）の数
+    pub fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// 失敗したタスクのエラーを古い順に取り出す
+    pub fn take_task_error(&mut self) -> Option<ScriptError> {
+        self.task_errors.pop_front()
+    }
+
+    /// Promise が解決するか、実行できるタスクが無くなるまで実行する
+    ///
+    /// 解決すれば結果を書き出して返す。タイマーなどを待っていて解決しなければ、
+    /// 未解決の Promise を書き出して返す（タスクは `run_tasks` で続きを実行する）。
+    pub(crate) fn run_until_settled(&mut self, promise: HeapRef) -> Result<ScriptValue, ScriptError> {
+        while self.promise_result(promise).is_none() {
+            let Some(id) = self.ready.pop_front() else {
+                break;
+            };
+            let owner = self.tasks.get(&id).is_some_and(|task| task.promise == promise);
+            if let Err(err) = self.run_task(id, usize::MAX) {
+                if owner {
+                    return Err(err);
+                }
+                self.task_errors.push_back(err);
+            }
+        }
+        match self.promise_result(promise) {
+            Some(Ok(value)) => Ok(self.export(&value)),
+            Some(Err(err)) => Err(err),
+            None => Ok(self.export(&ScriptValue::Ref(promise))),
+        }
+    }
+
+    /// タスクを最大 `budget` 命令実行し、使った命令数を返す
+    ///
+    /// タスクは実行中も表に残し、結果の Promise と await 中の Promise をルートにしておく。
+    /// エラーで止まったタスクは捨てて結果の Promise を失敗させ、ゴミを回収する。
+    /// 誰も await していなければエラーを返す。
+    fn run_task(&mut self, id: TaskId, budget: usize) -> Result<usize, ScriptError> {
+        let Some(task) = self.tasks.get_mut(&id) else {
+            return Ok(0);
+        };
+        let mut context = mem::take(&mut task.context);
+        let awaited = task.awaiting.take();
+        let is_call = task.is_call;
+
+        self.swap_context(&mut context);
+        let mut used = 0;
+        let result = self
+            .resume(awaited)
+            .and_then(|()| self.run_slice(is_call, budget, &mut used));
+        self.swap_context(&mut context);
+
+        let outcome = match result {
+            Ok(Slice::Finished(value)) => Ok(Ok(value)),
+            Ok(Slice::Awaiting(awaited)) => match self.heap.add_waiter(awaited, id) {
+                Ok(()) => {
+                    if let Some(task) = self.tasks.get_mut(&id) {
+                        task.context = context;
+                        task.awaiting = Some(awaited);
+                    }
+                    return Ok(used);
+                }
+                Err(err) => Err(err),
+            },
+            Ok(Slice::Preempted) => {
+                if let Some(task) = self.tasks.get_mut(&id) {
+                    task.context = context;
+                }
+                self.ready.push_back(id);
+                return Ok(used);
+            }
+            Err(err) => Err(err),
+        };
+
+        // 終わったタスク
+        let Some(task) = self.tasks.remove(&id) else {
+            return Ok(used);
+        };
+        match outcome {
+            Ok(result) => {
+                self.settle(task.promise, result);
+                Ok(used)
+            }
+            Err(err) => {
+                // await しているタスクがあれば、エラーはそちらで起きる
+                let awaited = self.settle(task.promise, Err(err.clone()));
+                drop(context);
+                self.collect_garbage();
+                if awaited { Ok(used) } else { Err(err) }
+            }
+        }
+    }
+
+    /// 終わるか、await で止まるか、予算を使い切るまで実行する
+    fn run_slice(&mut self, is_call: bool, budget: usize, used: &mut usize) -> Result<Slice, ScriptError> {
+        loop {
+            let finished = if is_call {
+                self.call_stack.is_empty()
+            } else {
+                !self.running || self.pc >= self.instructions.len()
+            };
+            if finished {
+                return Ok(Slice::Finished(self.stack.pop().unwrap_or(ScriptValue::Nil)));
+            }
+            if *used >= budget {
+                return Ok(Slice::Preempted);
+            }
+            if self.pc >= self.instructions.len() {
+                return Err(ScriptError::runtime("Function ran past the end of the program"));
+            }
+
+            *used += 1;
+            self.step()?;
+            if let Some(promise) = self.awaiting.take() {
+                return Ok(Slice::Awaiting(promise));
+            }
+        }
+    }
+
+    /// await していた Promise の結果をスタックに積む
+    fn resume(&mut self, awaited: Option<HeapRef>) -> Result<(), ScriptError> {
+        if let Some(promise) = awaited {
+            let value = self
+                .promise_result(promise)
+                .ok_or_else(|| ScriptError::runtime("Task resumed before its promise settled"))??;
+            self.stack.push(value);
+        }
+        Ok(())
+    }
+
+    /// VM の実行中の状態とタスクの状態を入れ替える
+    fn swap_context(&mut self, context: &mut ExecContext) {
+        mem::swap(&mut self.pc, &mut context.pc);
+        mem::swap(&mut self.stack, &mut context.stack);
+        mem::swap(&mut self.call_stack, &mut context.call_stack);
+        mem::swap(&mut self.locals, &mut context.locals);
+        mem::swap(&mut self.running, &mut context.running);
+    }
+
+    // ------------------------------------------------------------------------
+    // Promises
+    // ------------------------------------------------------------------------
+
+    /// 未解決の Promise を作る
+    pub(crate) fn new_promise(&mut self) -> Result<HeapRef, ScriptError> {
+        self.heap.alloc(HeapObject::Promise(PromiseCell::Pending(Vec::new())))
+    }
+
+    /// Promise を解決し、待っていたタスクを実行待ちに戻す
+    ///
+    /// 結果を格納できなければ（メモリ上限）そのエラーで失敗させる。
+    /// 待っていたタスクがあれば true を返す。
+    pub(crate) fn settle(&mut self, promise: HeapRef, result: Result<ScriptValue, ScriptError>) -> bool {
+        let waiters = match self.heap.settle_promise(promise, result) {
+            Ok(waiters) => waiters,
+            Err(err) => self.heap.settle_promise(promise, Err(err)).unwrap_or_default(),
+        };
+        let awaited = !waiters.is_empty();
+        self.ready.extend(waiters);
+        awaited
+    }
+
+    /// 固定した Promise を解決し、固定を外す（結果はヒープに移す）
+    pub fn settle_pinned(&mut self, handle: usize, result: Result<ScriptValue, ScriptError>) {
+        if let Some(ScriptValue::Ref(promise)) = self.unpin(handle) {
+            let result = result.and_then(|value| self.adopt(value));
+            self.settle(promise, result);
+        }
+    }
+
+    /// 解決済みの Promise の結果（未解決・Promise でなければ None）
+    pub(crate) fn promise_result(&self, promise: HeapRef) -> Option<Result<ScriptValue, ScriptError>> {
+        match self.heap.get(promise) {
+            Some(HeapObject::Promise(PromiseCell::Fulfilled(value))) => Some(Ok(value.clone())),
+            Some(HeapObject::Promise(PromiseCell::Rejected(err))) => Some(Err(err.clone())),
+            _ => None,
+        }
+    }
+
+    /// `.await` する値（未解決の Promise なら None を返し、実行ループがタスクを中断する）
+    ///
+    /// Promise でない値はそのまま結果になる。
+    pub(crate) fn await_value(&mut self, value: ScriptValue) -> Result<Option<ScriptValue>, ScriptError> {
+        let ScriptValue::Ref(r) = value else {
+            return Ok(Some(value));
+        };
+        match self.heap.get(r) {
+            Some(HeapObject::Promise(PromiseCell::Pending(_))) => {
+                self.awaiting = Some(r);
+                Ok(None)
+            }
+            Some(HeapObject::Promise(_)) => self.promise_result(r).transpose(),
+            _ => Ok(Some(value)),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Host requests
+    // ------------------------------------------------------------------------
+
+    /// ホストに要求を出す（ホストが無ければ Nil）
+    pub(crate) fn host_request(&self, request: HostRequest) -> ScriptValue {
+        match &self.host_callback {
+            Some(callback) => callback(request),
+            None => ScriptValue::Nil,
+        }
+    }
+
+    /// ホストが設定されているか
+    pub(crate) fn has_host(&self) -> bool {
+        self.host_callback.is_some()
+    }
+}
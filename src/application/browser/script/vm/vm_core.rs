@@ -7,15 +7,19 @@
 extern crate alloc;
 
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
 use alloc::string::String;
 use alloc::vec::Vec;
 
-use super::super::value::{NativeFunction, NativeFunctionId, ScriptValue};
+use super::super::value::{FunctionValue, HeapRef, NativeFunction, NativeFunctionId, ScriptValue};
 use super::super::ScriptError;
+use super::dispatch::TraitInfo;
 use super::dom::DomOperation;
-use super::frame::{CallFrame, LoopInfo};
+use super::frame::{CallFrame, LocalSlot};
+use super::heap::{Heap, TaskId, DEFAULT_MEMORY_LIMIT};
+use super::host::HostRequest;
 use super::instructions::{ConstantPool, Instruction};
+use super::tasks::Task;
 
 // ============================================================================
 // Virtual Machine
@@ -29,20 +33,44 @@ pub struct VirtualMachine {
     pub(crate) constants: ConstantPool,
     /// プログラムカウンタ
     pub(crate) pc: usize,
+    /// 最後に読み込んだスクリプトの開始位置
+    pub(crate) entry: usize,
     /// スタック
     pub(crate) stack: Vec<ScriptValue>,
     /// グローバル変数
     pub(crate) globals: BTreeMap<String, ScriptValue>,
     /// 呼び出しスタック
     pub(crate) call_stack: Vec<CallFrame>,
-    /// ループスタック
-    pub(crate) loop_stack: Vec<LoopInfo>,
     /// ローカル変数
-    pub(crate) locals: Vec<ScriptValue>,
+    pub(crate) locals: Vec<LocalSlot>,
+    /// 型ごとのメソッド表（型名 -> メソッド名 -> 関数）
+    pub(crate) methods: BTreeMap<String, BTreeMap<String, FunctionValue>>,
+    /// スクリプトで定義されたトレイト
+    pub(crate) traits: BTreeMap<String, TraitInfo>,
+    /// 実装済みのトレイト（型名, トレイト名）
+    pub(crate) trait_impls: BTreeSet<(String, String)>,
+    /// 配列・オブジェクト・構造体・セルを置くヒープ
+    pub(crate) heap: Heap,
+    /// 回収されないように固定した値（ハンドル -> 値）
+    pub(crate) pinned: BTreeMap<usize, ScriptValue>,
+    /// 次の固定ハンドル
+    pub(crate) next_pin: usize,
+    /// 実行中のタスク以外のタスク（実行中のタスクも状態を入れ替えたまま残っている）
+    pub(crate) tasks: BTreeMap<TaskId, Task>,
+    /// 実行待ちのタスク
+    pub(crate) ready: VecDeque<TaskId>,
+    /// 次のタスクID
+    pub(crate) next_task: TaskId,
+    /// 実行中のタスクが待つ Promise（`Await` が設定し、実行ループがタスクを中断する）
+    pub(crate) awaiting: Option<HeapRef>,
+    /// 失敗したタスクのエラー
+    pub(crate) task_errors: VecDeque<ScriptError>,
     /// 実行中フラグ
     pub(crate) running: bool,
     /// DOM要素へのコールバック
     pub(crate) dom_callback: Option<Box<dyn Fn(DomOperation) -> ScriptValue>>,
+    /// タイマー・ネットワークを扱うホストへのコールバック
+    pub(crate) host_callback: Option<Box<dyn Fn(HostRequest) -> ScriptValue>>,
 }
 
 impl VirtualMachine {
@@ -51,27 +79,53 @@ impl VirtualMachine {
             instructions: Vec::new(),
             constants: ConstantPool::new(),
             pc: 0,
+            entry: 0,
             stack: Vec::new(),
             globals: BTreeMap::new(),
             call_stack: Vec::new(),
-            loop_stack: Vec::new(),
             locals: Vec::new(),
+            methods: BTreeMap::new(),
+            traits: BTreeMap::new(),
+            trait_impls: BTreeSet::new(),
+            heap: Heap::new(DEFAULT_MEMORY_LIMIT),
+            pinned: BTreeMap::new(),
+            next_pin: 1,
+            tasks: BTreeMap::new(),
+            ready: VecDeque::new(),
+            next_task: 1,
+            awaiting: None,
+            task_errors: VecDeque::new(),
             running: false,
             dom_callback: None,
+            host_callback: None,
         }
     }
 
-    /// 命令列を設定
+    /// 命令列を読み込む
+    ///
+    /// 以前のスクリプトのコードと定数は残したまま末尾に追加する
+    /// （登録済みのイベントハンドラやクロージャがそのアドレスを指しているため）。
+    /// コンパイラは `code_len()` / `constant_count()` を起点にアドレスを振ること。
     pub fn load(&mut self, instructions: Vec<Instruction>, constants: ConstantPool) {
-        self.instructions = instructions;
-        self.constants = constants;
-        self.pc = 0;
+        self.entry = self.instructions.len();
+        self.instructions.extend(instructions);
+        self.constants.append(constants);
+        self.pc = self.entry;
         self.stack.clear();
         self.call_stack.clear();
-        self.loop_stack.clear();
         self.locals.clear();
     }
 
+    /// 読み込み済みの命令数
+    pub fn code_len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// 読み込み済みの定数の数
+    pub fn constant_count(&self) -> usize {
+        self.constants.len()
+    }
+
     /// DOMコールバックを設定
     pub fn set_dom_callback<F>(&mut self, callback: F)
     where
@@ -80,14 +134,24 @@ impl VirtualMachine {
         self.dom_callback = Some(Box::new(callback));
     }
 
-    /// グローバル変数を設定
-    pub fn set_global(&mut self, name: &str, value: ScriptValue) {
+    /// ホスト（イベントループ）へのコールバックを設定
+    pub fn set_host_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(HostRequest) -> ScriptValue + 'static,
+    {
+        self.host_callback = Some(Box::new(callback));
+    }
+
+    /// グローバル変数を設定（配列などのコンテナはヒープに移す）
+    pub fn set_global(&mut self, name: &str, value: ScriptValue) -> Result<(), ScriptError> {
+        let value = self.adopt(value)?;
         self.globals.insert(String::from(name), value);
+        Ok(())
     }
 
-    /// グローバル変数を取得
-    pub fn get_global(&self, name: &str) -> Option<&ScriptValue> {
-        self.globals.get(name)
+    /// グローバル変数を取得（ヒープ上の値は書き出したコピーを返す）
+    pub fn get_global(&self, name: &str) -> Option<ScriptValue> {
+        self.globals.get(name).map(|value| self.export(value))
     }
 
     /// ネイティブ関数を登録
@@ -97,17 +161,22 @@ impl VirtualMachine {
             .insert(String::from(name), ScriptValue::NativeFunction(native));
     }
 
-    /// 実行
+    /// 読み込んだスクリプトを実行
+    ///
+    /// スクリプト本体をタスクとして実行し、結果を返す。タイマーや fetch を await して
+    /// 止まったときは未解決の Promise を返し、続きは `run_tasks` で実行する。
+    /// エラーで止まったときは実行中の状態を捨ててゴミを回収するので、
+    /// メモリ上限に達した後も続けて別のスクリプトを実行できる。
     pub fn run(&mut self) -> Result<ScriptValue, ScriptError> {
-        self.running = true;
-        self.pc = 0;
-
-        while self.running && self.pc < self.instructions.len() {
-            self.execute_instruction()?;
-        }
+        let promise = self.spawn_script()?;
+        self.run_until_settled(promise)
+    }
 
-        // スタックの最後の値を返す
-        Ok(self.stack.pop().unwrap_or(ScriptValue::Nil))
+    /// 1命令を実行し、回収を少し進める
+    pub(crate) fn step(&mut self) -> Result<(), ScriptError> {
+        self.execute_instruction()?;
+        self.gc_safepoint();
+        Ok(())
     }
 
     /// 現在のベースポインタを取得
@@ -0,0 +1,136 @@
+// ============================================================================
+// src/application/browser/scripting.rs - Page Scripts
+// ============================================================================
+//!
+//! # ページのスクリプト
+//!
+//! `<script type="text/rustscript">` をページごとの `ScriptRuntime` で実行し、
+//! フォーム部品の値とチェックを DOM（`forms`）とスクリプトの DOM バインディングの
+//! 両方向で同期する。
+//!
+//! - キー入力やクリックで部品が変わったら、値をバインディングに書いて `input` イベントを発火する
+//! - スクリプトが `value` / `checked` を書き換えたら、変わった部品を DOM に書き戻す
+//!
+//! バインディングは初期の要素に文書順で 1 から ID を振るので、同じ順で DOM 上の位置を
+//! 記録して ID と対応させる。
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::dom::Node;
+use super::forms::{self, ControlKind};
+use super::script::vm::DomOperation;
+use super::script::{DocumentNode, ScriptResult, ScriptRuntime, ScriptValue};
+
+/// 実行するスクリプトの type
+const SCRIPT_TYPE: &str = "text/rustscript";
+
+/// ページのスクリプト実行環境
+pub struct PageScript {
+    runtime: ScriptRuntime,
+    /// バインディングの要素 ID から DOM 上の位置（`paths[id - 1]`）
+    paths: Vec<Vec<usize>>,
+}
+
+impl PageScript {
+    /// DOM からバインディングを作る
+    pub fn new(dom: &Node) -> Self {
+        let mut paths = Vec::new();
+        let root = document_node(dom, &mut Vec::new(), &mut paths);
+        let mut runtime = ScriptRuntime::new();
+        runtime.initialize_dom(&root);
+        Self { runtime, paths }
+    }
+
+    /// 文書中の `<script type="text/rustscript">` を順に実行する（失敗したもののエラーを返す）
+    ///
+    /// `src` 付きのものはサブリソースとして取得するので、ここでは実行しない。
+    pub fn run_inline(&mut self, dom: &Node) -> Vec<String> {
+        dom.find_elements_by_tag("script")
+            .into_iter()
+            .filter(|script| script.get_attribute("type") == Some(SCRIPT_TYPE))
+            .filter(|script| script.get_attribute("src").is_none())
+            .filter_map(|script| self.runtime.execute(&script.inner_text()).err())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    /// 部品 `control` の変更をバインディングに反映し、`input` イベントのハンドラを実行する
+    pub fn input(&mut self, dom: &Node, control: &[usize]) -> ScriptResult<()> {
+        let (Some(id), Some(node)) = (self.id_of(control), dom.descendant(control)) else {
+            return Ok(());
+        };
+        if matches!(ControlKind::of(node), Some(ControlKind::Checkbox | ControlKind::Radio)) {
+            self.runtime
+                .dom()
+                .handle_operation(DomOperation::SetChecked(id, forms::is_checked(node)));
+        }
+        self.runtime.dispatch_input(id, &forms::value(node));
+        self.runtime.process_events()
+    }
+
+    /// スクリプトが書き換えた部品の値とチェックを DOM に書き戻す（変わったら true）
+    pub fn sync(&mut self, dom: &mut Node) -> bool {
+        let mut changed = false;
+        let ids = self.runtime.dom().take_changed_controls();
+        for id in ids {
+            let Some(path) = self.paths.get(id.wrapping_sub(1)) else {
+                continue;
+            };
+            let mut binding = self.runtime.dom();
+            let value = binding.handle_operation(DomOperation::GetValue(id));
+            let checked = binding.handle_operation(DomOperation::GetChecked(id));
+            drop(binding);
+
+            let Some(node) = dom.descendant_mut(path) else {
+                continue;
+            };
+            if let Some(value) = value.as_string()
+                && forms::value(node) != value
+            {
+                forms::set_value(node, value);
+                changed = true;
+            }
+            if matches!(ControlKind::of(node), Some(ControlKind::Checkbox | ControlKind::Radio))
+                && let ScriptValue::Bool(checked) = checked
+            {
+                changed |= forms::set_checked(dom, path, checked);
+            }
+        }
+        changed
+    }
+
+    /// DOM 上の位置にある要素のバインディングでの ID
+    fn id_of(&self, path: &[usize]) -> Option<usize> {
+        self.paths.iter().position(|p| p == path).map(|i| i + 1)
+    }
+}
+
+/// DOM の要素（と文書ノード）をバインディングの入力に写す
+///
+/// 位置は `paths` に文書順で積む。テキストやコメントは親のテキストとしてだけ残す。
+fn document_node(node: &Node, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) -> DocumentNode {
+    paths.push(path.clone());
+    let mut doc = DocumentNode::new(node.tag_name().unwrap_or("#document"));
+    if let Some(data) = node.element_data() {
+        doc.id = node.id().map(String::from);
+        doc.classes = node.classes().into_iter().map(String::from).collect();
+        doc.attributes = data.attributes.clone();
+        doc.text_content = node.inner_text();
+    }
+    // `<select>` の値は選ばれている `<option>` の値
+    if ControlKind::of(node) == Some(ControlKind::Select) {
+        doc.attributes.insert(String::from("value"), forms::value(node));
+    }
+
+    for (i, child) in node.children.iter().enumerate() {
+        if child.is_element() {
+            path.push(i);
+            doc.children.push(document_node(child, path, paths));
+            path.pop();
+        }
+    }
+    doc
+}
@@ -14,7 +14,10 @@ use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
 
 use super::dom::{Node, NodeType, ElementData};
-use super::css::{Stylesheet, Rule, Selector, SimpleSelector, Declaration, Value, Color};
+use super::css::{
+    Stylesheet, Selector, SimpleSelector, Declaration, Value, Color, Origin, PseudoClass, Specificity,
+    expand_shorthand, is_shorthand,
+};
 
 // ============================================================================
 // Styled Node
@@ -47,10 +50,137 @@ pub enum Display {
     Inline,
     /// インラインブロック
     InlineBlock,
+    /// フレックスコンテナ
+    Flex,
+    /// インラインフレックスコンテナ
+    InlineFlex,
+    /// 表 (table)
+    Table,
+    /// 表のキャプション (caption)
+    TableCaption,
+    /// ヘッダ行グループ (thead)
+    TableHeaderGroup,
+    /// 行グループ (tbody)
+    TableRowGroup,
+    /// フッタ行グループ (tfoot)
+    TableFooterGroup,
+    /// 表の行 (tr)
+    TableRow,
+    /// 表のセル (td, th)
+    TableCell,
     /// なし
     None,
 }
 
+impl Display {
+    /// 表の内部要素（表の外ではブロックとして扱う）か
+    pub fn is_table_part(&self) -> bool {
+        matches!(
+            self,
+            Display::TableCaption
+                | Display::TableHeaderGroup
+                | Display::TableRowGroup
+                | Display::TableFooterGroup
+                | Display::TableRow
+                | Display::TableCell
+        )
+    }
+}
+
+// ============================================================================
+// Flexbox Properties
+// ============================================================================
+
+/// 主軸の方向 (flex-direction)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlexDirection {
+    Row,
+    RowReverse,
+    Column,
+    ColumnReverse,
+}
+
+impl FlexDirection {
+    /// 主軸が水平か
+    pub fn is_row(&self) -> bool {
+        matches!(self, FlexDirection::Row | FlexDirection::RowReverse)
+    }
+
+    /// 主軸の向きが逆か
+    pub fn is_reverse(&self) -> bool {
+        matches!(self, FlexDirection::RowReverse | FlexDirection::ColumnReverse)
+    }
+}
+
+/// 折り返し (flex-wrap)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlexWrap {
+    NoWrap,
+    Wrap,
+    /// 行を交差軸の逆順に並べる
+    WrapReverse,
+}
+
+/// 主軸方向の配置 (justify-content)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JustifyContent {
+    FlexStart,
+    FlexEnd,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// 交差軸方向の配置 (align-items / align-self)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlignItems {
+    FlexStart,
+    FlexEnd,
+    Center,
+    /// ベースラインは未対応（flex-start と同じ）
+    Baseline,
+    Stretch,
+}
+
+impl AlignItems {
+    fn from_keyword(s: &str) -> Option<Self> {
+        match s {
+            "flex-start" | "start" | "self-start" => Some(AlignItems::FlexStart),
+            "flex-end" | "end" | "self-end" => Some(AlignItems::FlexEnd),
+            "center" => Some(AlignItems::Center),
+            "baseline" => Some(AlignItems::Baseline),
+            "stretch" | "normal" => Some(AlignItems::Stretch),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// Background Properties
+// ============================================================================
+
+/// 背景画像の繰り返し (background-repeat)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackgroundRepeat {
+    Repeat,
+    RepeatX,
+    RepeatY,
+    NoRepeat,
+}
+
+impl BackgroundRepeat {
+    /// 水平方向に繰り返すか
+    pub fn repeats_x(&self) -> bool {
+        matches!(self, BackgroundRepeat::Repeat | BackgroundRepeat::RepeatX)
+    }
+
+    /// 垂直方向に繰り返すか
+    pub fn repeats_y(&self) -> bool {
+        matches!(self, BackgroundRepeat::Repeat | BackgroundRepeat::RepeatY)
+    }
+}
+
 impl<'a> StyledNode<'a> {
     /// display プロパティを取得
     pub fn display(&self) -> Display {
@@ -59,6 +189,16 @@ impl<'a> StyledNode<'a> {
                 "block" => Display::Block,
                 "none" => Display::None,
                 "inline-block" => Display::InlineBlock,
+                "flex" => Display::Flex,
+                "inline-flex" => Display::InlineFlex,
+                "table" | "inline-table" => Display::Table,
+                "table-caption" => Display::TableCaption,
+                "table-header-group" => Display::TableHeaderGroup,
+                "table-row-group" => Display::TableRowGroup,
+                "table-footer-group" => Display::TableFooterGroup,
+                "table-row" => Display::TableRow,
+                "table-cell" => Display::TableCell,
+                "table-column" | "table-column-group" => Display::None,
                 _ => Display::Inline,
             },
             _ => self.default_display(),
@@ -71,11 +211,20 @@ impl<'a> StyledNode<'a> {
             NodeType::Text(_) => Display::Inline,
             NodeType::Element(data) => {
                 match data.tag_name.as_str() {
-                    "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+                    "html" | "body"
+                    | "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
                     | "header" | "footer" | "main" | "section" | "article"
-                    | "nav" | "aside" | "ul" | "ol" | "li" | "table"
+                    | "nav" | "aside" | "ul" | "ol" | "li"
                     | "form" | "blockquote" | "pre" | "hr" => Display::Block,
-                    "script" | "style" | "head" | "meta" | "link" | "title" => Display::None,
+                    "table" => Display::Table,
+                    "caption" => Display::TableCaption,
+                    "thead" => Display::TableHeaderGroup,
+                    "tbody" => Display::TableRowGroup,
+                    "tfoot" => Display::TableFooterGroup,
+                    "tr" => Display::TableRow,
+                    "td" | "th" => Display::TableCell,
+                    "colgroup" | "col" => Display::None,
+                    "script" | "style" | "head" | "meta" | "link" | "title" | "template" => Display::None,
                     _ => Display::Inline,
                 }
             }
@@ -105,23 +254,144 @@ impl<'a> StyledNode<'a> {
     pub fn length_px(&self, name: &str) -> f32 {
         self.value(name).map(|v| v.to_px()).unwrap_or(0.0)
     }
+
+    /// キーワード値を取得
+    fn keyword(&self, name: &str) -> Option<&str> {
+        match self.value(name) {
+            Some(Value::Keyword(s)) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// flex-direction（既定: row）
+    pub fn flex_direction(&self) -> FlexDirection {
+        match self.keyword("flex-direction") {
+            Some("row-reverse") => FlexDirection::RowReverse,
+            Some("column") => FlexDirection::Column,
+            Some("column-reverse") => FlexDirection::ColumnReverse,
+            _ => FlexDirection::Row,
+        }
+    }
+
+    /// flex-wrap（既定: nowrap）
+    pub fn flex_wrap(&self) -> FlexWrap {
+        match self.keyword("flex-wrap") {
+            Some("wrap") => FlexWrap::Wrap,
+            Some("wrap-reverse") => FlexWrap::WrapReverse,
+            _ => FlexWrap::NoWrap,
+        }
+    }
+
+    /// justify-content（既定: flex-start）
+    pub fn justify_content(&self) -> JustifyContent {
+        match self.keyword("justify-content") {
+            Some("flex-end" | "end" | "right") => JustifyContent::FlexEnd,
+            Some("center") => JustifyContent::Center,
+            Some("space-between") => JustifyContent::SpaceBetween,
+            Some("space-around") => JustifyContent::SpaceAround,
+            Some("space-evenly") => JustifyContent::SpaceEvenly,
+            _ => JustifyContent::FlexStart,
+        }
+    }
+
+    /// align-items（既定: stretch）
+    pub fn align_items(&self) -> AlignItems {
+        self.keyword("align-items")
+            .and_then(AlignItems::from_keyword)
+            .unwrap_or(AlignItems::Stretch)
+    }
+
+    /// align-self（auto の場合は None で、親の align-items に従う）
+    pub fn align_self(&self) -> Option<AlignItems> {
+        self.keyword("align-self").and_then(AlignItems::from_keyword)
+    }
+
+    /// flex-grow（既定: 0）
+    pub fn flex_grow(&self) -> f32 {
+        match self.value("flex-grow") {
+            Some(Value::Number(n)) => n.max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// flex-shrink（既定: 1）
+    pub fn flex_shrink(&self) -> f32 {
+        match self.value("flex-shrink") {
+            Some(Value::Number(n)) => n.max(0.0),
+            _ => 1.0,
+        }
+    }
+
+    /// background-image の参照（none なら None）
+    pub fn background_image(&self) -> Option<&str> {
+        match self.value("background-image") {
+            Some(Value::Url(url)) if !url.is_empty() => Some(url.as_str()),
+            _ => None,
+        }
+    }
+
+    /// background-repeat（既定: repeat）
+    pub fn background_repeat(&self) -> BackgroundRepeat {
+        match self.keyword("background-repeat") {
+            Some("repeat-x") => BackgroundRepeat::RepeatX,
+            Some("repeat-y") => BackgroundRepeat::RepeatY,
+            Some("no-repeat") => BackgroundRepeat::NoRepeat,
+            _ => BackgroundRepeat::Repeat,
+        }
+    }
 }
 
 // ============================================================================
 // Style Tree Construction
 // ============================================================================
 
+/// 動的擬似クラス（:hover, :active, :focus）の対象
+///
+/// 要素はスタイルツリーのルートからの子インデックスの並びで指す。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElementState {
+    /// マウスカーソルの下の要素
+    pub hover: Option<Vec<usize>>,
+    /// マウスボタンを押している要素
+    pub active: Option<Vec<usize>>,
+    /// フォーカスのある要素
+    pub focus: Option<Vec<usize>>,
+}
+
 /// スタイルツリーを構築
 pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<'a> {
+    style_tree_with(root, &[stylesheet], &ElementState::default())
+}
+
+/// 複数のスタイルシートと要素の状態からスタイルツリーを構築
+///
+/// スタイルシートは出どころ（`Origin`）に関係なく、後に渡したものほど同じ優先順位の中で強い。
+pub fn style_tree_with<'a>(
+    root: &'a Node,
+    stylesheets: &[&Stylesheet],
+    state: &ElementState,
+) -> StyledNode<'a> {
     let default_styles = default_styles();
-    style_node(root, stylesheet, &default_styles)
+    let context = StyleContext { stylesheets, state };
+    style_node(root, &context, &default_styles, &mut Vec::new(), &mut Vec::new())
+}
+
+/// スタイル計算の入力
+struct StyleContext<'s> {
+    stylesheets: &'s [&'s Stylesheet],
+    state: &'s ElementState,
 }
 
 /// 単一ノードのスタイルを計算
+///
+/// `ancestors` はルートから親までのノード、`path` はそれぞれの子インデックス
+/// （`path[i]` は `ancestors[i]` の子としての位置。最後はこのノード自身の位置）。
 fn style_node<'a>(
     node: &'a Node,
-    stylesheet: &'a Stylesheet,
+    context: &StyleContext,
     parent_styles: &PropertyMap,
+    ancestors: &mut Vec<&'a Node>,
+    path: &mut Vec<usize>,
 ) -> StyledNode<'a> {
     // 継承可能なスタイルを親から取得
     let mut specified_values = inherited_properties(parent_styles);
@@ -129,23 +399,14 @@ fn style_node<'a>(
     // CSSルールを適用
     match &node.node_type {
         NodeType::Element(elem) => {
-            // ユーザーエージェントスタイル
+            // ユーザーエージェントスタイル（HTML の属性を含む）
             apply_ua_styles(&mut specified_values, elem);
 
-            // CSSルールをマッチング
-            let matching_rules = matching_rules(elem, stylesheet);
-            for (_, rule) in matching_rules {
-                for decl in &rule.declarations {
-                    specified_values.insert(decl.name.clone(), decl.value.clone());
-                }
-            }
-
-            // style属性（最優先）
-            if let Some(style) = elem.attributes.get("style") {
-                let inline = parse_inline_style(style);
-                for (name, value) in inline {
-                    specified_values.insert(name, value);
-                }
+            // スタイルシートと style 属性の宣言を優先順位の低い順に適用
+            let element = Element::new(node, elem, ancestors, path, context.state);
+            let inline = elem.attributes.get("style").map(|style| parse_inline_style(style));
+            for decl in cascade(element, context.stylesheets, inline.as_deref()) {
+                specified_values.insert(decl.name.clone(), decl.value.clone());
             }
         }
         NodeType::Text(_) => {
@@ -155,11 +416,19 @@ fn style_node<'a>(
     }
 
     // 子ノードを処理
+    ancestors.push(node);
     let children = node
         .children
         .iter()
-        .map(|child| style_node(child, stylesheet, &specified_values))
+        .enumerate()
+        .map(|(i, child)| {
+            path.push(i);
+            let styled = style_node(child, context, &specified_values, ancestors, path);
+            path.pop();
+            styled
+        })
         .collect();
+    ancestors.pop();
 
     StyledNode {
         node,
@@ -189,6 +458,9 @@ fn inherited_properties(parent: &PropertyMap) -> PropertyMap {
         "text-align",
         "visibility",
         "cursor",
+        "border-collapse",
+        "border-spacing",
+        "empty-cells",
     ];
 
     parent
@@ -246,6 +518,24 @@ fn apply_ua_styles(styles: &mut PropertyMap, elem: &ElementData) {
         "pre" | "code" => {
             styles.insert("font-family".into(), Value::Keyword("monospace".into()));
         }
+        "th" => {
+            styles.insert("font-weight".into(), Value::Keyword("bold".into()));
+            apply_size_attribute(styles, elem, "width");
+        }
+        "td" => {
+            apply_size_attribute(styles, elem, "width");
+        }
+        "table" => {
+            // border 属性（セルの枠線はレイアウト時に付ける）
+            if let Some(width) = elem.attributes.get("border").and_then(|b| b.trim().parse::<f32>().ok()) {
+                styles.insert("border-width".into(), Value::Length(width, Unit::Px));
+            }
+            apply_size_attribute(styles, elem, "width");
+        }
+        "img" => {
+            apply_size_attribute(styles, elem, "width");
+            apply_size_attribute(styles, elem, "height");
+        }
         "hr" => {
             styles.insert("margin-top".into(), Value::Length(8.0, Unit::Px));
             styles.insert("margin-bottom".into(), Value::Length(8.0, Unit::Px));
@@ -255,47 +545,210 @@ fn apply_ua_styles(styles: &mut PropertyMap, elem: &ElementData) {
     }
 }
 
-/// マッチするルールを取得
-fn matching_rules<'a>(
-    elem: &ElementData,
-    stylesheet: &'a Stylesheet,
-) -> Vec<(super::css::Specificity, &'a Rule)> {
-    let mut matches = Vec::new();
+/// width / height 属性（"120" や "50%"）を同名のプロパティに変換
+fn apply_size_attribute(styles: &mut PropertyMap, elem: &ElementData, name: &str) {
+    if let Some(size) = elem.attributes.get(name) {
+        let value = parse_simple_value(size.trim());
+        if matches!(value, Value::Number(_) | Value::Length(..) | Value::Percentage(_)) {
+            styles.insert(name.into(), value);
+        }
+    }
+}
+
+// ============================================================================
+// Cascade
+// ============================================================================
+
+/// カスケードの優先順位（後のものほど強い）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CascadeLevel {
+    UserAgent,
+    Author,
+    AuthorImportant,
+    UserAgentImportant,
+}
+
+impl CascadeLevel {
+    fn new(origin: Origin, important: bool) -> Self {
+        match (origin, important) {
+            (Origin::UserAgent, false) => CascadeLevel::UserAgent,
+            (Origin::Author, false) => CascadeLevel::Author,
+            (Origin::Author, true) => CascadeLevel::AuthorImportant,
+            (Origin::UserAgent, true) => CascadeLevel::UserAgentImportant,
+        }
+    }
+}
 
-    for rule in &stylesheet.rules {
-        for selector in &rule.selectors {
-            if matches_selector(elem, selector) {
-                matches.push((selector.specificity(), rule));
-                break; // 最も詳細なセレクタのみ
+/// カスケードの並べ替えキー: (出どころと !important, style 属性か, 詳細度, 出現順)
+type CascadeKey = (CascadeLevel, bool, Specificity, usize);
+
+/// 要素に適用される宣言を優先順位の低い順に並べる
+///
+/// ルールの詳細度はマッチしたセレクタのうち最も詳細なもの。
+fn cascade<'d>(
+    element: Element,
+    stylesheets: &[&'d Stylesheet],
+    inline: Option<&'d [Declaration]>,
+) -> Vec<&'d Declaration> {
+    let mut matched: Vec<(CascadeKey, &'d Declaration)> = Vec::new();
+    let mut order = 0;
+
+    for sheet in stylesheets {
+        for rule in &sheet.rules {
+            // セレクタは詳細度の降順に並んでいる
+            let Some(selector) = rule.selectors.iter().find(|s| matches_selector(element, s)) else {
+                continue;
+            };
+            let specificity = selector.specificity();
+            for decl in &rule.declarations {
+                let level = CascadeLevel::new(sheet.origin, decl.important);
+                matched.push(((level, false, specificity, order), decl));
+                order += 1;
             }
         }
     }
+    for decl in inline.unwrap_or_default() {
+        let level = CascadeLevel::new(Origin::Author, decl.important);
+        matched.push(((level, true, (0, 0, 0), order), decl));
+        order += 1;
+    }
 
-    // 詳細度でソート
-    matches.sort_by(|a, b| a.0.cmp(&b.0));
-    matches
+    matched.sort_by_key(|(key, _)| *key);
+    matched.into_iter().map(|(_, decl)| decl).collect()
+}
+
+// ============================================================================
+// Selector Matching
+// ============================================================================
+
+/// セレクタ照合中の要素
+///
+/// DOM は親へのリンクを持たないため、ルートからの祖先と子インデックスを一緒に持ち歩く。
+#[derive(Clone, Copy)]
+struct Element<'n, 'c> {
+    node: &'n Node,
+    data: &'n ElementData,
+    /// ルートから親までの祖先
+    ancestors: &'c [&'n Node],
+    /// 親までの子インデックス（祖先が n 個なら n - 1 個）
+    parent_path: &'c [usize],
+    /// 親の子としての位置（祖先がなければ 0）
+    index: usize,
+    state: &'c ElementState,
+}
+
+impl<'n, 'c> Element<'n, 'c> {
+    /// `path` はルートからの子インデックス（`ancestors` と同じ長さ）
+    fn new(
+        node: &'n Node,
+        data: &'n ElementData,
+        ancestors: &'c [&'n Node],
+        path: &'c [usize],
+        state: &'c ElementState,
+    ) -> Self {
+        let (parent_path, index) = match path.split_last() {
+            Some((&index, parent_path)) => (parent_path, index),
+            None => (path, 0),
+        };
+        Self { node, data, ancestors, parent_path, index, state }
+    }
+
+    /// 親要素
+    fn parent(&self) -> Option<Self> {
+        let (&parent, ancestors) = self.ancestors.split_last()?;
+        let data = parent.element_data()?;
+        let (parent_path, index) = match self.parent_path.split_last() {
+            Some((&index, parent_path)) => (parent_path, index),
+            None => (self.parent_path, 0),
+        };
+        Some(Self { node: parent, data, ancestors, parent_path, index, state: self.state })
+    }
+
+    /// 兄弟ノード（親がなければ空）
+    fn siblings(&self) -> &'n [Node] {
+        self.ancestors.last().map_or(&[], |parent| parent.children.as_slice())
+    }
+
+    /// 前の兄弟要素（近い順）
+    fn previous_siblings(&self) -> impl Iterator<Item = Element<'n, 'c>> + '_ {
+        let siblings = self.siblings();
+        siblings[..self.index.min(siblings.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(move |(index, node)| {
+                let data = node.element_data()?;
+                Some(Self { node, data, index, ..*self })
+            })
+    }
+
+    /// 兄弟要素の中での位置（1 から）。`of_type` なら同じタグ名だけを数える
+    fn position(&self, of_type: bool, from_end: bool) -> usize {
+        let siblings = self.siblings();
+        if siblings.is_empty() {
+            return 1;
+        }
+        let counted = |node: &Node| match node.element_data() {
+            Some(data) => !of_type || data.tag_name == self.data.tag_name,
+            None => false,
+        };
+        let others = if from_end {
+            siblings[self.index + 1..].iter().filter(|n| counted(n)).count()
+        } else {
+            siblings[..self.index].iter().filter(|n| counted(n)).count()
+        };
+        others + 1
+    }
+
+    /// `target` がこの要素自身か
+    fn is(&self, target: &[usize]) -> bool {
+        if self.ancestors.is_empty() {
+            return target.is_empty();
+        }
+        target.len() == self.parent_path.len() + 1 && self.contains(target)
+    }
+
+    /// `target` がこの要素自身またはその子孫か
+    fn contains(&self, target: &[usize]) -> bool {
+        if self.ancestors.is_empty() {
+            return true;
+        }
+        target.len() > self.parent_path.len()
+            && target.starts_with(self.parent_path)
+            && target[self.parent_path.len()] == self.index
+    }
 }
 
 /// セレクタがマッチするか
-fn matches_selector(elem: &ElementData, selector: &Selector) -> bool {
+fn matches_selector(element: Element, selector: &Selector) -> bool {
     match selector {
-        Selector::Simple(simple) => matches_simple_selector(elem, simple),
-        Selector::Descendant(_, right) => {
-            // 簡略化: 子孫セレクタは右側のみチェック
-            matches_selector(elem, right)
+        Selector::Simple(simple) => matches_simple_selector(element, simple),
+        Selector::Descendant(left, right) => {
+            matches_selector(element, right)
+                && core::iter::successors(element.parent(), Element::parent)
+                    .any(|ancestor| matches_selector(ancestor, left))
+        }
+        Selector::Child(left, right) => {
+            matches_selector(element, right)
+                && element.parent().is_some_and(|parent| matches_selector(parent, left))
+        }
+        Selector::NextSibling(left, right) => {
+            matches_selector(element, right)
+                && element
+                    .previous_siblings()
+                    .next()
+                    .is_some_and(|sibling| matches_selector(sibling, left))
         }
-        Selector::Child(_, right) => {
-            matches_selector(elem, right)
+        Selector::SubsequentSibling(left, right) => {
+            matches_selector(element, right)
+                && element.previous_siblings().any(|sibling| matches_selector(sibling, left))
         }
     }
 }
 
 /// シンプルセレクタがマッチするか
-fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
-    // ユニバーサルセレクタ
-    if selector.universal {
-        return true;
-    }
+fn matches_simple_selector(element: Element, selector: &SimpleSelector) -> bool {
+    let elem = element.data;
 
     // タグ名
     if let Some(ref tag) = selector.tag_name {
@@ -324,20 +777,72 @@ fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> boo
         }
     }
 
-    true
+    // 属性
+    for attribute in &selector.attributes {
+        if !attribute.matches(elem.attributes.get(&attribute.name).map(String::as_str)) {
+            return false;
+        }
+    }
+
+    // 擬似クラス
+    selector
+        .pseudo_classes
+        .iter()
+        .all(|pseudo| matches_pseudo_class(element, pseudo))
+}
+
+/// 擬似クラスがマッチするか
+fn matches_pseudo_class(element: Element, pseudo: &PseudoClass) -> bool {
+    let state = element.state;
+    match pseudo {
+        PseudoClass::NthChild(nth) => nth.matches(element.position(false, false)),
+        PseudoClass::NthLastChild(nth) => nth.matches(element.position(false, true)),
+        PseudoClass::NthOfType(nth) => nth.matches(element.position(true, false)),
+        PseudoClass::NthLastOfType(nth) => nth.matches(element.position(true, true)),
+        PseudoClass::Root => element.parent().is_none(),
+        PseudoClass::Empty => element.node.children.iter().all(|child| match &child.node_type {
+            NodeType::Text(text) => text.is_empty(),
+            NodeType::Comment(_) => true,
+            _ => false,
+        }),
+        PseudoClass::Link => {
+            matches!(element.data.tag_name.as_str(), "a" | "area")
+                && element.data.attributes.contains_key("href")
+        }
+        PseudoClass::Visited => false,
+        PseudoClass::Not(selectors) => !selectors.iter().any(|s| matches_selector(element, s)),
+        PseudoClass::Hover => state.hover.as_deref().is_some_and(|target| element.contains(target)),
+        PseudoClass::Active => state.active.as_deref().is_some_and(|target| element.contains(target)),
+        PseudoClass::Focus => state.focus.as_deref().is_some_and(|target| element.is(target)),
+        PseudoClass::FocusWithin => state.focus.as_deref().is_some_and(|target| element.contains(target)),
+    }
 }
 
 /// インラインスタイルをパース
-fn parse_inline_style(style: &str) -> Vec<(String, Value)> {
+fn parse_inline_style(style: &str) -> Vec<Declaration> {
     let mut result = Vec::new();
 
     for declaration in style.split(';') {
         let parts: Vec<&str> = declaration.splitn(2, ':').collect();
         if parts.len() == 2 {
             let name = parts[0].trim().to_lowercase();
-            let value_str = parts[1].trim();
-            let value = parse_simple_value(value_str);
-            result.push((name, value));
+            let mut value_str = parts[1].trim();
+            let mut important = false;
+            if let Some(pos) = value_str.find('!') {
+                important = value_str[pos + 1..].trim().eq_ignore_ascii_case("important");
+                value_str = value_str[..pos].trim_end();
+            }
+            if is_shorthand(&name) {
+                let values = value_str.split_whitespace().map(parse_simple_value).collect();
+                result.extend(
+                    expand_shorthand(&name, values)
+                        .into_iter()
+                        .map(|decl| Declaration { important, ..decl }),
+                );
+            } else {
+                let value = parse_simple_value(value_str);
+                result.push(Declaration { name, value, important });
+            }
         }
     }
 
@@ -346,6 +851,16 @@ fn parse_inline_style(style: &str) -> Vec<(String, Value)> {
 
 /// 簡易的な値パーサー
 fn parse_simple_value(s: &str) -> Value {
+    // url(...)
+    if let Some(inner) = s
+        .get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("url("))
+        .and_then(|_| s[4..].strip_suffix(')'))
+    {
+        let url = inner.trim().trim_matches(|c| c == '"' || c == '\'');
+        return Value::Url(String::from(url));
+    }
+
     // 16進数色
     if s.starts_with('#') {
         let hex = &s[1..];
@@ -400,6 +915,7 @@ mod tests {
     use super::*;
     use super::super::html::HtmlParser;
     use super::super::css::CssParser;
+    use alloc::vec;
 
     #[test]
     fn test_style_tree() {
@@ -423,7 +939,77 @@ mod tests {
         let stylesheet = CssParser::parse(css);
         let styled = style_tree(&dom, &stylesheet);
 
-        let div = &styled.children[0];
+        // Document > html > body > div
+        let div = &styled.children[0].children[1].children[0];
+        assert_eq!(div.node.tag_name(), Some("div"));
         assert_eq!(div.display(), Display::Block);
     }
+
+    /// id を持つ要素の color を "id=色" の形で集める
+    fn colors(styled: &StyledNode, out: &mut Vec<String>) {
+        if let (Some(id), Some(Value::Color(c))) = (styled.node.id(), styled.value("color")) {
+            out.push(alloc::format!("{}={},{},{}", id, c.r, c.g, c.b));
+        }
+        for child in &styled.children {
+            colors(child, out);
+        }
+    }
+
+    fn styled_colors(html: &str, css: &str, state: &ElementState) -> Vec<String> {
+        let dom = HtmlParser::parse(html);
+        let stylesheet = CssParser::parse(css);
+        let styled = style_tree_with(&dom, &[&stylesheet], state);
+        let mut out = Vec::new();
+        colors(&styled, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_combinators_and_pseudo_classes() {
+        let html = r#"<ul><li id="a">a</li><li id="b" class="x">b</li><li id="c">c</li><li id="d" data-k="en-US">d</li></ul>"#;
+        let css = "li { color: black }
+                   ul li:first-child { color: red }
+                   .x + li { color: blue }
+                   .x ~ li:last-child { color: green }
+                   li:nth-child(2n):not(.x) { color: yellow }
+                   ul > [data-k|=en]:nth-last-child(1) { color: navy }";
+        assert_eq!(
+            styled_colors(html, css, &ElementState::default()),
+            ["a=255,0,0", "b=0,0,0", "c=0,0,255", "d=0,0,128"]
+        );
+    }
+
+    #[test]
+    fn test_cascade_order() {
+        // !important は詳細度より強く、style 属性の !important はさらに強い
+        let html = r#"<p id="a" class="c">a</p><p id="b" class="c" style="color: blue !important">b</p><p id="c" style="color: blue">c</p>"#;
+        let css = "#a, #b, #c { color: red } p { color: green !important } .c { color: yellow }";
+        assert_eq!(
+            styled_colors(html, css, &ElementState::default()),
+            ["a=0,128,0", "b=0,0,255", "c=0,128,0"]
+        );
+
+        // ブラウザ既定のスタイルシートは、後に渡してもページのスタイルより弱い
+        let dom = HtmlParser::parse(r#"<a id="l" href="/">l</a>"#);
+        let author = CssParser::parse("a { color: red }");
+        let user_agent = Stylesheet { origin: Origin::UserAgent, ..CssParser::parse("a { color: blue }") };
+        let styled = style_tree_with(&dom, &[&author, &user_agent], &ElementState::default());
+        let mut out = Vec::new();
+        colors(&styled, &mut out);
+        assert_eq!(out, ["l=255,0,0"]);
+    }
+
+    #[test]
+    fn test_dynamic_pseudo_classes() {
+        let html = r#"<div id="outer"><a id="link" href="/">x</a><span id="other">y</span></div>"#;
+        let css = "a, div, span { color: black } div:hover { color: red } a:focus { color: blue }
+                   :focus-within { color: green } a:active { color: yellow }";
+
+        // パスは Document からの子の番号（html > body > div > a）
+        let hover = ElementState { hover: Some(vec![0, 1, 0]), ..Default::default() };
+        assert_eq!(styled_colors(html, css, &hover), ["outer=255,0,0", "link=0,0,0", "other=0,0,0"]);
+
+        let focus = ElementState { focus: Some(vec![0, 1, 0, 0]), active: Some(vec![0, 1, 0, 0]), ..Default::default() };
+        assert_eq!(styled_colors(html, css, &focus), ["outer=0,128,0", "link=255,255,0", "other=0,0,0"]);
+    }
 }
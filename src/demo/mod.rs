@@ -5,8 +5,8 @@
 
 // Demo modules
 pub mod echo_server;
+pub mod http_server;
 // Note: These modules are disabled until API stabilization
-// pub mod http_server;
 // pub mod performance_demo;
 
 use alloc::string::String;
@@ -25,7 +25,7 @@ pub fn init() {
 /// List available demos
 pub fn list_demos() {
     crate::log!("\nAvailable Demos:\n");
-    crate::log!("  1. http_server  - Simple HTTP server (zero-copy) [WIP]\n");
+    crate::log!("  1. http_server  - HTTP dashboard server (port 8080) [Ready]\n");
     crate::log!("  2. echo_server  - TCP echo server [Ready]\n");
     crate::log!("  3. performance  - Performance demonstration [WIP]\n");
     crate::log!("\n");
@@ -34,10 +34,7 @@ pub fn list_demos() {
 /// Run demo by name
 pub fn run_demo(name: &str) -> DemoResult {
     match name {
-        "http" | "http_server" => {
-            crate::log!("[DEMO] HTTP server demo not yet implemented\n");
-            DemoResult::Error(String::from("Not implemented"))
-        }
+        "http" | "http_server" => http_server::run(),
         "echo" | "echo_server" => {
             // Run simulation-based echo server demo
             echo_server::run()
@@ -578,6 +578,11 @@ pub fn get_stats() -> DomainStats {
     get_domain_stats()
 }
 
+/// 登録済みドメインのID一覧（ID順）
+pub fn domain_ids() -> Vec<DomainId> {
+    REGISTRY.lock().domains.keys().copied().collect()
+}
+
 /// ドメイン一覧を表示
 pub fn print_domain_list() {
     let registry = REGISTRY.lock();
@@ -540,6 +540,7 @@ impl From<crate::io::virtio::net::VirtioNetError> for IoError {
         match e {
             VE::NotInitialized => IoError::DeviceNotFound,
             VE::QueueFull => IoError::NoResources,
+            VE::NoMemory => IoError::NoResources,
             VE::BufferTooSmall => IoError::InvalidParameter,
             VE::DeviceError => IoError::VirtIoError,
             VE::Timeout => IoError::Timeout,
@@ -702,10 +702,30 @@ impl MountTable {
         // Find longest matching mount point
         mounts
             .iter()
-            .filter(|m| path.starts_with(&m.path))
+            .filter(|m| is_under(path, &m.path))
             .max_by_key(|m| m.path.len())
             .map(|m| m.fs.clone())
     }
+
+    /// Find filesystem for a path, with the path relative to its mount point
+    pub fn find_relative(&self, path: &str) -> Option<(Arc<dyn FileSystem>, String)> {
+        let mounts = self.mounts.read();
+
+        mounts
+            .iter()
+            .filter(|m| is_under(path, &m.path))
+            .max_by_key(|m| m.path.len())
+            .map(|m| {
+                let rest = path[m.path.len()..].trim_start_matches('/');
+                (m.fs.clone(), alloc::format!("/{}", rest))
+            })
+    }
+}
+
+/// Whether `path` is `mount` itself or inside it (whole path components only)
+fn is_under(path: &str, mount: &str) -> bool {
+    path.strip_prefix(mount)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || mount.ends_with('/'))
 }
 
 /// Global mount table instance
@@ -716,6 +736,40 @@ pub fn mount_table() -> &'static MountTable {
     &MOUNT_TABLE
 }
 
+/// Read a whole file through the mount table (fallback: shell memfs)
+pub fn read_file(path: &str) -> FsResult<Vec<u8>> {
+    let Some((fs, relative)) = mount_table().find_relative(path) else {
+        return super::memfs::read_file_content(path, "/");
+    };
+    let inode = PathResolver::new(fs.root()?).resolve(&relative)?;
+    let size = inode.getattr()?.size as usize;
+    let mut data = alloc::vec![0u8; size];
+    let read = inode.read(0, &mut data)?;
+    data.truncate(read);
+    Ok(data)
+}
+
+/// Write a whole file through the mount table (fallback: shell memfs)
+///
+/// Creates the file if it is missing and truncates it otherwise.
+pub fn write_file(path: &str, data: &[u8]) -> FsResult<()> {
+    let Some((fs, relative)) = mount_table().find_relative(path) else {
+        return super::memfs::write_file_content(path, "/", data);
+    };
+    let resolver = PathResolver::new(fs.root()?);
+    let inode = match resolver.resolve(&relative) {
+        Ok(inode) => inode,
+        Err(FsError::NotFound) => {
+            let (parent, name) = resolver.resolve_parent(&relative)?;
+            parent.create(&name, FileMode::DEFAULT_FILE, OpenFlags::default())?
+        }
+        Err(e) => return Err(e),
+    };
+    inode.truncate(0)?;
+    inode.write(0, data)?;
+    Ok(())
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -732,6 +786,28 @@ mod tests {
         assert!(!mode.owner_execute());
     }
 
+    #[test]
+    fn test_mount_lookup_matches_whole_components() {
+        let table = MountTable::new();
+        table.mount("/", super::super::memfs::MemoryFs::new()).unwrap();
+        table.mount("/mnt", super::super::memfs::MemoryFs::new()).unwrap();
+        let mnt = table.find("/mnt").unwrap();
+        let is_mnt = |fs: &Arc<dyn FileSystem>| Arc::ptr_eq(fs, &mnt);
+
+        let (fs, relative) = table.find_relative("/mnt/x").unwrap();
+        assert!(is_mnt(&fs));
+        assert_eq!(relative, "/x");
+        let (fs, relative) = table.find_relative("/mnt").unwrap();
+        assert!(is_mnt(&fs));
+        assert_eq!(relative, "/");
+
+        // "/mnt2" is not inside "/mnt"
+        let (fs, relative) = table.find_relative("/mnt2/x").unwrap();
+        assert!(!is_mnt(&fs));
+        assert_eq!(relative, "/mnt2/x");
+        assert!(!is_mnt(&table.find("/mnt2").unwrap()));
+    }
+
     #[test]
     fn test_open_flags() {
         let flags = OpenFlags(OpenFlags::O_RDWR | OpenFlags::O_CREAT);
@@ -63,7 +63,7 @@ pub use fat32::Fat32FileSystem;
 pub use fs_abstraction::{
     AsyncReadFuture, AsyncWriteFuture, DirEntry, FileAttr, FileHandle, FileMode, FileSystem,
     FileType, FsError, FsResult, FsStats, Inode, MountTable, OpenFlags, PathResolver, SeekFrom,
-    mount_table,
+    mount_table, read_file, write_file,
 };
 #[allow(unused_imports)]
 pub use memfs::{
@@ -4,11 +4,11 @@
 //!
 //! # 画像処理
 //!
-//! BMP、PNG（簡易）、アイコン等の画像フォーマット対応。
+//! BMP、PNG、アイコン等の画像フォーマット対応。
 //!
 //! ## 機能
 //! - BMPファイル読み込み
-//! - PNG簡易デコード
+//! - PNGデコード（DEFLATE の展開は `util::inflate` を使う）
 //! - 画像リサイズ・変換
 //! - アルファブレンディング
 
@@ -17,6 +17,8 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
+use crate::util::inflate::zlib_decompress;
+
 use super::{Color, Framebuffer, Rect};
 
 // ============================================================================
@@ -616,6 +618,308 @@ pub fn decode_tga(data: &[u8]) -> ImageResult<Image> {
     Ok(image)
 }
 
+// ============================================================================
+// PNG Decoder
+// ============================================================================
+
+/// PNGシグネチャ
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// デコードする画像の最大ピクセル数
+const PNG_MAX_PIXELS: u64 = 4096 * 4096;
+
+/// Adam7 インターレースの各パス (開始x, 開始y, x間隔, y間隔)
+const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// IHDRチャンク
+#[derive(Clone, Copy)]
+struct PngHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    /// 0=グレー, 2=RGB, 3=パレット, 4=グレー+α, 6=RGBA
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl PngHeader {
+    fn parse(body: &[u8]) -> ImageResult<Self> {
+        if body.len() != 13 {
+            return Err(ImageError::InvalidFormat);
+        }
+        let header = Self {
+            width: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+            height: u32::from_be_bytes([body[4], body[5], body[6], body[7]]),
+            bit_depth: body[8],
+            color_type: body[9],
+            interlaced: body[12] == 1,
+        };
+
+        let valid_depth = match header.color_type {
+            0 => matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16),
+            3 => matches!(header.bit_depth, 1 | 2 | 4 | 8),
+            2 | 4 | 6 => matches!(header.bit_depth, 8 | 16),
+            _ => false,
+        };
+        // 圧縮方式・フィルタ方式は 0 のみ定義されている
+        if !valid_depth || body[10] != 0 || body[11] != 0 || body[12] > 1 {
+            return Err(ImageError::UnsupportedFormat);
+        }
+        if header.width == 0 || header.height == 0 {
+            return Err(ImageError::InvalidData);
+        }
+        if header.width as u64 * header.height as u64 > PNG_MAX_PIXELS {
+            return Err(ImageError::UnsupportedFormat);
+        }
+        Ok(header)
+    }
+
+    /// 1ピクセルのサンプル数
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    /// 幅 `width` の走査線のバイト数（フィルタ種別を除く）
+    fn stride(&self, width: u32) -> usize {
+        (width as usize * self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    /// フィルタが参照する左隣までのバイト数
+    fn filter_unit(&self) -> usize {
+        (self.channels() * self.bit_depth as usize / 8).max(1)
+    }
+
+    /// パスごとの (開始x, 開始y, x間隔, y間隔, 幅, 高さ)
+    fn passes(&self) -> Vec<(u32, u32, u32, u32, u32, u32)> {
+        let passes: &[(u32, u32, u32, u32)] = if self.interlaced {
+            &ADAM7_PASSES
+        } else {
+            &[(0, 0, 1, 1)]
+        };
+        passes
+            .iter()
+            .map(|&(x0, y0, dx, dy)| {
+                let w = self.width.saturating_sub(x0).div_ceil(dx);
+                let h = self.height.saturating_sub(y0).div_ceil(dy);
+                (x0, y0, dx, dy, w, h)
+            })
+            .filter(|&(.., w, h)| w > 0 && h > 0)
+            .collect()
+    }
+
+    /// 走査線の `index` 番目のサンプル（ビット深度のまま）
+    fn sample(&self, line: &[u8], index: usize) -> u16 {
+        match self.bit_depth {
+            16 => u16::from_be_bytes([line[index * 2], line[index * 2 + 1]]),
+            8 => line[index] as u16,
+            depth => {
+                let depth = depth as usize;
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                ((line[bit / 8] >> shift) & ((1 << depth) - 1)) as u16
+            }
+        }
+    }
+
+    /// サンプルを 8 ビットに変換
+    fn scale(&self, value: u16) -> u8 {
+        match self.bit_depth {
+            16 => (value >> 8) as u8,
+            8 => value as u8,
+            depth => (value as u32 * 255 / ((1 << depth) - 1)) as u8,
+        }
+    }
+
+    /// 走査線の `x` 番目のピクセル
+    fn pixel(&self, line: &[u8], x: usize, palette: &[Color], transparent: Option<[u16; 3]>) -> Color {
+        let base = x * self.channels();
+        let sample = |c: usize| self.sample(line, base + c);
+        match self.color_type {
+            0 => {
+                let v = sample(0);
+                let alpha = if transparent == Some([v, v, v]) { 0 } else { 255 };
+                let g = self.scale(v);
+                Color::with_alpha(g, g, g, alpha)
+            }
+            2 => {
+                let rgb = [sample(0), sample(1), sample(2)];
+                let alpha = if transparent == Some(rgb) { 0 } else { 255 };
+                Color::with_alpha(self.scale(rgb[0]), self.scale(rgb[1]), self.scale(rgb[2]), alpha)
+            }
+            3 => palette.get(sample(0) as usize).copied().unwrap_or(Color::TRANSPARENT),
+            4 => {
+                let g = self.scale(sample(0));
+                Color::with_alpha(g, g, g, self.scale(sample(1)))
+            }
+            _ => Color::with_alpha(
+                self.scale(sample(0)),
+                self.scale(sample(1)),
+                self.scale(sample(2)),
+                self.scale(sample(3)),
+            ),
+        }
+    }
+}
+
+/// PNGファイルをデコード
+///
+/// 全カラータイプ・ビット深度と Adam7 インターレースに対応する
+/// （16ビットのサンプルは上位バイトを使う）。チャンクの CRC は検証しない。
+pub fn decode_png(data: &[u8]) -> ImageResult<Image> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(ImageError::InvalidFormat);
+    }
+
+    let mut header: Option<PngHeader> = None;
+    let mut palette: Vec<Color> = Vec::new();
+    let mut transparent: Option<[u16; 3]> = None;
+    let mut compressed = Vec::new();
+
+    // チャンク: 長さ(4) 種別(4) データ CRC(4)
+    let mut pos = PNG_SIGNATURE.len();
+    while pos + 12 <= data.len() {
+        let length = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let body = data
+            .get(pos + 8..pos + 8 + length)
+            .ok_or(ImageError::InvalidData)?;
+        pos += 12 + length;
+
+        match kind {
+            b"IHDR" => header = Some(PngHeader::parse(body)?),
+            b"PLTE" => {
+                palette = body
+                    .chunks_exact(3)
+                    .map(|rgb| Color::new(rgb[0], rgb[1], rgb[2]))
+                    .collect();
+            }
+            b"tRNS" => {
+                let word = |i: usize| body.get(i..i + 2).map(|w| u16::from_be_bytes([w[0], w[1]]));
+                match header.map(|h| h.color_type) {
+                    Some(3) => {
+                        for (color, &alpha) in palette.iter_mut().zip(body) {
+                            color.alpha = alpha;
+                        }
+                    }
+                    Some(0) => transparent = word(0).map(|v| [v, v, v]),
+                    Some(2) => {
+                        if let (Some(r), Some(g), Some(b)) = (word(0), word(2), word(4)) {
+                            transparent = Some([r, g, b]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // 補助チャンクは無視
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or(ImageError::InvalidFormat)?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(ImageError::InvalidData);
+    }
+
+    let passes = header.passes();
+    let raw_size = passes
+        .iter()
+        .map(|&(.., w, h)| h as usize * (1 + header.stride(w)))
+        .sum();
+    let raw = zlib_decompress(&compressed, raw_size).map_err(|_| ImageError::DecompressionError)?;
+
+    let mut image = Image::new(header.width, header.height);
+    let unit = header.filter_unit();
+    let mut offset = 0;
+    for (x0, y0, dx, dy, width, height) in passes {
+        let stride = header.stride(width);
+        let mut previous = vec![0u8; stride];
+        for row in 0..height {
+            let filter = *raw.get(offset).ok_or(ImageError::InvalidData)?;
+            let mut line = raw
+                .get(offset + 1..offset + 1 + stride)
+                .ok_or(ImageError::InvalidData)?
+                .to_vec();
+            offset += 1 + stride;
+            unfilter(filter, &mut line, &previous, unit)?;
+
+            for col in 0..width {
+                let color = header.pixel(&line, col as usize, &palette, transparent);
+                image.set_pixel(x0 + col * dx, y0 + row * dy, color);
+            }
+            previous = line;
+        }
+    }
+
+    Ok(image)
+}
+
+/// 走査線のフィルタを戻す
+fn unfilter(filter: u8, line: &mut [u8], previous: &[u8], unit: usize) -> ImageResult<()> {
+    for i in 0..line.len() {
+        let left = if i >= unit { line[i - unit] } else { 0 };
+        let up = previous[i];
+        let upper_left = if i >= unit { previous[i - unit] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, upper_left),
+            _ => return Err(ImageError::InvalidData),
+        };
+        line[i] = line[i].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+/// Paeth 予測子
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// 先頭のバイト列から形式を判別してデコード（PNG / BMP / ICO）
+///
+/// ICO は含まれるうち最も大きいアイコンを返す。
+pub fn decode(data: &[u8]) -> ImageResult<Image> {
+    if data.starts_with(PNG_SIGNATURE) {
+        decode_png(data)
+    } else if data.starts_with(b"BM") {
+        decode_bmp(data)
+    } else if data.starts_with(&[0, 0, 1, 0]) {
+        decode_ico(data)?
+            .into_iter()
+            .max_by_key(|image| image.width() * image.height())
+            .ok_or(ImageError::InvalidData)
+    } else {
+        Err(ImageError::UnsupportedFormat)
+    }
+}
+
 // ============================================================================
 // ICO/CUR Decoder
 // ============================================================================
@@ -676,9 +980,10 @@ pub fn decode_ico(data: &[u8]) -> ImageResult<Vec<Image>> {
         let image_data = &data[image_offset..image_offset + image_size];
 
         // PNGまたはBMPをチェック
-        if image_data.len() >= 8 && &image_data[0..8] == b"\x89PNG\r\n\x1a\n" {
-            // PNG形式（簡易対応は省略）
-            continue;
+        if image_data.starts_with(PNG_SIGNATURE) {
+            if let Ok(image) = decode_png(image_data) {
+                images.push(image);
+            }
         } else {
             // BMP形式（DIBヘッダから）
             if let Ok(image) = decode_ico_bmp(image_data, entry.width, entry.height) {
@@ -991,3 +1296,96 @@ impl IconGenerator {
         image
     }
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::inflate::adler32;
+
+    /// 非圧縮ブロックの zlib ストリーム
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut out = vec![0x78, 0x01, 0x01];
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(data);
+        out.extend_from_slice(&adler32(data).to_be_bytes());
+        out
+    }
+
+    /// チャンクを並べて PNG を組み立てる（CRC は 0）
+    fn png(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut out = PNG_SIGNATURE.to_vec();
+        for (kind, body) in chunks {
+            out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            out.extend_from_slice(*kind);
+            out.extend_from_slice(body);
+            out.extend_from_slice(&[0; 4]);
+        }
+        out
+    }
+
+    fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> Vec<u8> {
+        let mut body = width.to_be_bytes().to_vec();
+        body.extend_from_slice(&height.to_be_bytes());
+        body.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        body
+    }
+
+    #[test]
+    fn test_decode_png_rgba_filters() {
+        // 1行目は Sub、2行目は Up フィルタ
+        let raw = [
+            1, 10, 20, 30, 255, 5, 5, 5, 0,
+            2, 1, 1, 1, 0, 0, 0, 0, 0,
+        ];
+        let data = png(&[
+            (b"IHDR", ihdr(2, 2, 8, 6, 0)),
+            (b"IDAT", zlib_stored(&raw)),
+            (b"IEND", Vec::new()),
+        ]);
+        let image = decode(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 2));
+        assert_eq!(image.get_pixel(0, 0), Color::with_alpha(10, 20, 30, 255));
+        assert_eq!(image.get_pixel(1, 0), Color::with_alpha(15, 25, 35, 255));
+        assert_eq!(image.get_pixel(0, 1), Color::with_alpha(11, 21, 31, 255));
+        assert_eq!(image.get_pixel(1, 1), Color::with_alpha(15, 25, 35, 255));
+    }
+
+    #[test]
+    fn test_decode_png_palette_and_interlace() {
+        // 1ビットのパレット、インデックス 0 は tRNS で透明
+        let raw = [0, 0b0100_0000];
+        let data = png(&[
+            (b"IHDR", ihdr(2, 1, 1, 3, 0)),
+            (b"PLTE", vec![255, 0, 0, 0, 0, 255]),
+            (b"tRNS", vec![0]),
+            (b"IDAT", zlib_stored(&raw)),
+            (b"IEND", Vec::new()),
+        ]);
+        let image = decode_png(&data).unwrap();
+        assert_eq!(image.get_pixel(0, 0), Color::with_alpha(255, 0, 0, 0));
+        assert_eq!(image.get_pixel(1, 0), Color::new(0, 0, 255));
+
+        // 2x2 のグレー: Adam7 ではパス 1, 6, 7 に 1/1/2 ピクセルずつ入る
+        let raw = [0, 10, 0, 20, 0, 30, 40];
+        let data = png(&[
+            (b"IHDR", ihdr(2, 2, 8, 0, 1)),
+            (b"IDAT", zlib_stored(&raw)),
+            (b"IEND", Vec::new()),
+        ]);
+        let image = decode_png(&data).unwrap();
+        let grays: Vec<u8> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(|&(x, y)| image.get_pixel(x, y).red)
+            .collect();
+        assert_eq!(grays, [10, 20, 30, 40]);
+
+        assert!(decode_png(&data[..40]).is_err());
+        assert!(matches!(decode(b"GIF89a"), Err(ImageError::UnsupportedFormat)));
+    }
+}
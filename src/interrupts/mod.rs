@@ -459,9 +459,14 @@ fn dispatch_pci_interrupt(irq: u8) {
     if hda_irq == irq {
         crate::io::audio::hda::handle_interrupt();
     }
+
+    // e1000 NIC をチェック（INTx 使用時のみ）
+    if crate::io::e1000::get_irq() == irq {
+        crate::io::e1000::handle_interrupt();
+    }
     
     // 将来的には他の PCI デバイスもここに追加
-    // 例: NVMe, ネットワークカードなど
+    // 例: NVMe など
 }
 
 /// 現在のタイマーティック数を取得
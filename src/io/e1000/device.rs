@@ -0,0 +1,571 @@
+// ============================================================================
+// src/io/e1000/device.rs - e1000 Controller
+// ============================================================================
+//!
+//! コントローラの初期化、割り込み設定、送受信処理
+
+#![allow(dead_code)]
+
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+
+use crate::io::pci::{
+    Bar, MsiCapability, MsiConfig, MsixCapability, MsixTableEntry,
+    PciDeviceInfo, disable_intx, get_legacy_accessor,
+};
+use crate::io::virtio::net::offload::{self, TxOffloads};
+use crate::task::interrupt_waker::InterruptSource;
+use crate::time;
+
+use super::regs::*;
+use super::ring::{RxRing, TxChecksum, TxRing, DEFAULT_RING_SIZE};
+use super::types::{E1000Error, E1000Result, E1000Stats, LinkStatus, Model};
+
+/// 受信フレームの配送先（Ethernet フレーム）
+pub type RxHandler = fn(&[u8]);
+
+/// 1回のポーリングで処理する最大受信フレーム数
+pub const RX_BATCH: usize = 64;
+
+/// Ethernet MTU
+const MTU: usize = 1500;
+
+/// 割り込みの配送方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptMode {
+    /// 割り込みなし（タイマーでポーリング）
+    Polling,
+    /// INTx（PIC/IO-APIC の IRQ 番号）
+    Legacy { irq: u8 },
+    /// MSI（単一ベクタ）
+    Msi { vector: u8 },
+    /// MSI-X（82574: 受信・送信・その他で別ベクタ）
+    MsiX { rx: u8, tx: u8, other: u8 },
+}
+
+impl InterruptMode {
+    /// 送受信タスクを起こす割り込みソース
+    pub fn wake_source(&self) -> Option<InterruptSource> {
+        match *self {
+            InterruptMode::Polling => None,
+            InterruptMode::Legacy { irq } => Some(InterruptSource::Irq(irq)),
+            InterruptMode::Msi { vector } | InterruptMode::MsiX { rx: vector, .. } => {
+                Some(InterruptSource::Irq(vector))
+            }
+        }
+    }
+
+    /// このデバイスのベクタか
+    pub fn owns_vector(&self, vector: u8) -> bool {
+        match *self {
+            InterruptMode::Msi { vector: v } => v == vector,
+            InterruptMode::MsiX { rx, tx, other } => [rx, tx, other].contains(&vector),
+            _ => false,
+        }
+    }
+}
+
+/// e1000/e1000e コントローラ
+pub struct E1000Device {
+    /// PCI デバイス情報
+    pci: PciDeviceInfo,
+    /// BAR0 MMIO ベースアドレス
+    mmio_base: u64,
+    /// モデル
+    model: Model,
+    /// MAC アドレス
+    mac: [u8; 6],
+    /// 受信リング
+    rx: Mutex<RxRing>,
+    /// 送信リング
+    tx: Mutex<TxRing>,
+    /// 割り込みの配送方法
+    interrupts: InterruptMode,
+    /// リンクアップ状態
+    link_up: AtomicBool,
+    /// 受信フレームの配送先
+    rx_handler: Mutex<Option<RxHandler>>,
+    /// 統計
+    stats: E1000Stats,
+    /// 初期化済み
+    initialized: AtomicBool,
+}
+
+impl E1000Device {
+    /// コントローラを作成（リングを確保するだけでハードウェアには触れない）
+    pub fn new(pci: PciDeviceInfo, model: Model) -> E1000Result<Self> {
+        let mmio_base = match &pci.bars[0] {
+            Some(Bar::Memory32 { base, .. }) | Some(Bar::Memory64 { base, .. }) => *base,
+            _ => return Err(E1000Error::InvalidBar),
+        };
+        Ok(Self {
+            pci,
+            mmio_base,
+            model,
+            mac: [0; 6],
+            rx: Mutex::new(RxRing::new(DEFAULT_RING_SIZE)?),
+            tx: Mutex::new(TxRing::new(DEFAULT_RING_SIZE)?),
+            interrupts: InterruptMode::Polling,
+            link_up: AtomicBool::new(false),
+            rx_handler: Mutex::new(None),
+            stats: E1000Stats::default(),
+            initialized: AtomicBool::new(false),
+        })
+    }
+
+    // ========================================================================
+    // Register Access
+    // ========================================================================
+
+    /// Read a 32-bit register
+    pub fn read32(&self, offset: u32) -> u32 {
+        // SAFETY: mmio_base は PCI BAR0 から取得。レジスタは4バイト境界
+        unsafe { read_volatile((self.mmio_base + offset as u64) as *const u32) }
+    }
+
+    /// Write a 32-bit register
+    pub fn write32(&self, offset: u32, value: u32) {
+        // SAFETY: 同上
+        unsafe { write_volatile((self.mmio_base + offset as u64) as *mut u32, value) }
+    }
+
+    fn set_bits(&self, offset: u32, bits: u32) {
+        self.write32(offset, self.read32(offset) | bits);
+    }
+
+    fn clear_bits(&self, offset: u32, bits: u32) {
+        self.write32(offset, self.read32(offset) & !bits);
+    }
+
+    // ========================================================================
+    // Initialization
+    // ========================================================================
+
+    /// コントローラを初期化
+    pub fn init(&mut self) -> E1000Result<()> {
+        self.reset()?;
+
+        // リンク設定: 自動ネゴシエーション結果に従う
+        self.clear_bits(REG_CTRL, ctrl::PHY_RST | ctrl::VME);
+        self.set_bits(REG_CTRL, ctrl::SLU | ctrl::ASDE);
+
+        let mac = self.read_mac_address()?;
+        self.mac = mac;
+        self.write_receive_address(&mac);
+        for i in 0..MTA_ENTRIES {
+            self.write32(REG_MTA + i * 4, 0);
+        }
+
+        self.setup_rx();
+        self.setup_tx();
+        self.write32(REG_ITR, ITR_DEFAULT);
+
+        self.interrupts = self.setup_interrupts();
+        let mut mask = int::ENABLED;
+        if matches!(self.interrupts, InterruptMode::MsiX { .. }) {
+            mask |= int::RXQ0 | int::TXQ0 | int::OTHER;
+        }
+        self.read32(REG_ICR);
+        self.write32(REG_IMS, mask);
+
+        let link = self.link_status();
+        self.link_up.store(link.up, Ordering::Release);
+        self.initialized.store(true, Ordering::Release);
+
+        crate::log!(
+            "[e1000] {} MAC {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}, link {}, irq {:?}\n",
+            self.model.name(),
+            self.mac[0], self.mac[1], self.mac[2], self.mac[3], self.mac[4], self.mac[5],
+            link,
+            self.interrupts
+        );
+        Ok(())
+    }
+
+    /// デバイスリセット
+    fn reset(&self) -> E1000Result<()> {
+        self.write32(REG_IMC, 0xFFFF_FFFF);
+        self.set_bits(REG_CTRL, ctrl::RST);
+        // RST はハードウェアが自動でクリアする（完了まで最大 1ms 程度）
+        time::pit().delay_us(1000);
+        let mut cleared = false;
+        for _ in 0..100 {
+            if self.read32(REG_CTRL) & ctrl::RST == 0 {
+                cleared = true;
+                break;
+            }
+            time::pit().delay_us(100);
+        }
+        if !cleared {
+            return Err(E1000Error::ResetTimeout);
+        }
+        // リセット後も割り込みは全マスク
+        self.write32(REG_IMC, 0xFFFF_FFFF);
+        self.read32(REG_ICR);
+        Ok(())
+    }
+
+    /// EEPROM のワードを読み取り（EERD）
+    fn read_eeprom(&self, word: u8) -> E1000Result<u16> {
+        let (shift, done) = if self.model.is_pcie() {
+            (eerd::ADDR_SHIFT_8257X, eerd::DONE_8257X)
+        } else {
+            (eerd::ADDR_SHIFT_8254X, eerd::DONE_8254X)
+        };
+        self.write32(REG_EERD, ((word as u32) << shift) | eerd::START);
+        for _ in 0..1000 {
+            let value = self.read32(REG_EERD);
+            if value & done != 0 {
+                return Ok((value >> eerd::DATA_SHIFT) as u16);
+            }
+            time::pit().delay_us(10);
+        }
+        Err(E1000Error::EepromTimeout)
+    }
+
+    /// MAC アドレスを取得
+    ///
+    /// リセット時に EEPROM から RAL0/RAH0 にロードされていればそれを使い、
+    /// なければ EEPROM のワード 0-2 を直接読む。
+    fn read_mac_address(&self) -> E1000Result<[u8; 6]> {
+        let ral = self.read32(REG_RAL0);
+        let rah = self.read32(REG_RAH0);
+        if rah & rah::AV != 0 && (ral != 0 || rah & 0xFFFF != 0) {
+            let low = ral.to_le_bytes();
+            let high = (rah as u16).to_le_bytes();
+            return Ok([low[0], low[1], low[2], low[3], high[0], high[1]]);
+        }
+        let mut mac = [0u8; 6];
+        for i in 0..3 {
+            let word = self.read_eeprom(i as u8)?.to_le_bytes();
+            mac[i * 2] = word[0];
+            mac[i * 2 + 1] = word[1];
+        }
+        Ok(mac)
+    }
+
+    /// 受信アドレスフィルタ（RAL0/RAH0）を設定
+    fn write_receive_address(&self, mac: &[u8; 6]) {
+        let ral = u32::from_le_bytes([mac[0], mac[1], mac[2], mac[3]]);
+        let rah = u16::from_le_bytes([mac[4], mac[5]]) as u32 | rah::AV;
+        self.write32(REG_RAL0, ral);
+        self.write32(REG_RAH0, rah);
+    }
+
+    /// 受信側を設定
+    fn setup_rx(&self) {
+        let rx = self.rx.lock();
+        let base = rx.phys_addr();
+        self.write32(REG_RDBAL, base as u32);
+        self.write32(REG_RDBAH, (base >> 32) as u32);
+        self.write32(REG_RDLEN, rx.byte_len());
+        self.write32(REG_RDH, 0);
+        self.write32(REG_RDT, rx.initial_tail());
+        self.write32(REG_RDTR, 0);
+        self.write32(REG_RADV, 0);
+
+        // IP/TCP/UDP チェックサム検証をオフロード
+        self.write32(REG_RXCSUM, rxcsum::IPOFL | rxcsum::TUOFL);
+        self.write32(
+            REG_RCTL,
+            rctl::EN | rctl::BAM | rctl::SECRC | rctl::BSIZE_2048 | rctl::RDMTS_HALF,
+        );
+    }
+
+    /// 送信側を設定
+    fn setup_tx(&self) {
+        let tx = self.tx.lock();
+        let base = tx.phys_addr();
+        self.write32(REG_TDBAL, base as u32);
+        self.write32(REG_TDBAH, (base >> 32) as u32);
+        self.write32(REG_TDLEN, tx.byte_len());
+        self.write32(REG_TDH, 0);
+        self.write32(REG_TDT, 0);
+        if self.model.is_pcie() {
+            self.set_bits(REG_TXDCTL, txdctl::GRAN | txdctl::WTHRESH_1);
+        }
+        self.write32(REG_TIPG, TIPG_DEFAULT);
+        self.write32(REG_TCTL, tctl::DEFAULT);
+    }
+
+    /// 割り込みを設定（MSI-X → MSI → INTx → ポーリングの順に試す）
+    fn setup_interrupts(&self) -> InterruptMode {
+        if self.model.has_ivar()
+            && let Some(mode) = self.setup_msix()
+        {
+            return mode;
+        }
+        if let Some(mode) = self.setup_msi() {
+            return mode;
+        }
+        match self.pci.interrupt_line {
+            irq @ 1..=15 => InterruptMode::Legacy { irq },
+            _ => InterruptMode::Polling,
+        }
+    }
+
+    /// 割り込み先 APIC（BSP）
+    fn target_apic() -> u8 {
+        crate::io::acpi::local_apics()
+            .iter()
+            .find(|apic| apic.enabled)
+            .map_or(0, |apic| apic.apic_id)
+    }
+
+    fn bdf(&self) -> u32 {
+        self.pci.bdf.to_u16() as u32
+    }
+
+    /// MSI を設定
+    fn setup_msi(&self) -> Option<InterruptMode> {
+        let accessor = get_legacy_accessor();
+        let msi = MsiCapability::probe(accessor, &self.pci)?;
+        let apic = Self::target_apic();
+        let vector = crate::io::interrupt_manager::allocate_msi(self.bdf(), "e1000", Some(apic))
+            .ok()?
+            .vector;
+        msi.enable(accessor, &MsiConfig::new(apic, vector));
+        disable_intx(accessor, &self.pci);
+        Some(InterruptMode::Msi { vector })
+    }
+
+    /// MSI-X を設定（82574: エントリ 0=受信, 1=送信, 2=その他）
+    fn setup_msix(&self) -> Option<InterruptMode> {
+        let accessor = get_legacy_accessor();
+        let msix = MsixCapability::probe(accessor, &self.pci)?;
+        if msix.table_size() < 3 {
+            return None;
+        }
+        let table_base = self.pci.bars.get(msix.table_bar() as usize)?.as_ref()?.address()?
+            + msix.table_offset() as u64;
+        let table = table_base as *mut MsixTableEntry;
+
+        let apic = Self::target_apic();
+        let vectors = crate::io::interrupt_manager::allocate_msix(self.bdf(), 3, "e1000e", Some(apic)).ok()?;
+        msix.enable(accessor);
+        for (entry, allocation) in vectors.iter().enumerate() {
+            // SAFETY: テーブルは BAR 内にマップされ、エントリ数は table_size 以上
+            unsafe {
+                msix.configure_vector(table, entry as u16, &MsiConfig::new(apic, allocation.vector));
+                msix.unmask_vector(table, entry as u16);
+            }
+        }
+        msix.clear_function_mask(accessor);
+        disable_intx(accessor, &self.pci);
+
+        self.set_bits(REG_CTRL_EXT, ctrl_ext::PBA_SUPPORT);
+        self.write32(
+            REG_IVAR,
+            ((ivar::VALID) << ivar::RXQ0_SHIFT)
+                | ((1 | ivar::VALID) << ivar::TXQ0_SHIFT)
+                | ((2 | ivar::VALID) << ivar::OTHER_SHIFT)
+                | ivar::TX_INT_EVERY_WB,
+        );
+        // 受信・送信の原因ビットはベクタ発火時に自動クリア
+        self.write32(REG_EIAC, int::RXQ0 | int::TXQ0);
+
+        Some(InterruptMode::MsiX {
+            rx: vectors[0].vector,
+            tx: vectors[1].vector,
+            other: vectors[2].vector,
+        })
+    }
+
+    // ========================================================================
+    // Accessors
+    // ========================================================================
+
+    /// MAC アドレス
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    /// モデル
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// PCI デバイス情報
+    pub fn pci_device(&self) -> &PciDeviceInfo {
+        &self.pci
+    }
+
+    /// 割り込みの配送方法
+    pub fn interrupt_mode(&self) -> InterruptMode {
+        self.interrupts
+    }
+
+    /// 統計
+    pub fn stats(&self) -> &E1000Stats {
+        &self.stats
+    }
+
+    /// 現在のリンク状態（STATUS レジスタ）
+    pub fn link_status(&self) -> LinkStatus {
+        let status = self.read32(REG_STATUS);
+        LinkStatus {
+            up: status & status::LU != 0,
+            speed_mbps: match (status & status::SPEED_MASK) >> status::SPEED_SHIFT {
+                0 => 10,
+                1 => 100,
+                _ => 1000,
+            },
+            full_duplex: status & status::FD != 0,
+        }
+    }
+
+    /// リンクアップしているか（最後の割り込み時点）
+    pub fn link_up(&self) -> bool {
+        self.link_up.load(Ordering::Acquire)
+    }
+
+    /// 送信オフロード（チェックサム挿入のみ。TSO は使わない）
+    pub fn tx_offloads(&self) -> TxOffloads {
+        TxOffloads { csum: true, tso4: false }
+    }
+
+    /// 受信フレームの配送先を設定
+    pub fn set_rx_handler(&self, handler: RxHandler) {
+        *self.rx_handler.lock() = Some(handler);
+    }
+
+    // ========================================================================
+    // Data Path
+    // ========================================================================
+
+    /// Ethernet フレームを送信
+    ///
+    /// TCP/UDP チェックサムはハードウェアが挿入する（レガシーディスクリプタの IC）。
+    pub fn transmit(&self, frame: &[u8]) -> E1000Result<()> {
+        if !self.initialized.load(Ordering::Acquire) {
+            return Err(E1000Error::NotInitialized);
+        }
+        let metrics = crate::net::optimization::metrics();
+        let offloads = self.tx_offloads();
+        let mut offloaded = false;
+
+        let mut tx = self.tx.lock();
+        let result = tx.submit(frame, |buf| {
+            let header = offload::prepare_tx(buf, MTU, offloads);
+            if header.flags & offload::hdr_flags::NEEDS_CSUM == 0 {
+                return None;
+            }
+            offloaded = true;
+            Some(TxChecksum {
+                css: header.csum_start as u8,
+                cso: (header.csum_start + header.csum_offset) as u8,
+            })
+        });
+        match result {
+            Ok(tail) => {
+                self.write32(REG_TDT, tail);
+                drop(tx);
+                self.stats.tx_packets.fetch_add(1, Ordering::Relaxed);
+                self.stats.tx_bytes.fetch_add(frame.len() as u64, Ordering::Relaxed);
+                if offloaded {
+                    self.stats.tx_csum_offloaded.fetch_add(1, Ordering::Relaxed);
+                }
+                metrics.tx_packets.fetch_add(1, Ordering::Relaxed);
+                metrics.tx_bytes.fetch_add(frame.len() as u64, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => {
+                self.stats.tx_drops.fetch_add(1, Ordering::Relaxed);
+                metrics.tx_drops.fetch_add(1, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    /// 受信フレームを1バッチ処理して送信完了を回収
+    ///
+    /// リングのロックを外してから配送するので、配送先から送信してよい。
+    /// 戻り値: 処理した受信フレーム数
+    pub fn poll(&self) -> usize {
+        let frames = {
+            let mut rx = self.rx.lock();
+            let mut frames = alloc::vec::Vec::new();
+            while frames.len() < RX_BATCH {
+                let Some(frame) = rx.poll() else { break };
+                frames.push(frame);
+            }
+            if !frames.is_empty() {
+                self.write32(REG_RDT, rx.tail());
+            }
+            frames
+        };
+        self.tx.lock().reclaim();
+
+        if frames.is_empty() {
+            return 0;
+        }
+        let metrics = crate::net::optimization::metrics();
+        metrics.batched_packets.fetch_add(frames.len() as u64, Ordering::Relaxed);
+        if let Some(processor) = crate::net::optimization::batch_processor() {
+            processor.stats().record_batch(frames.len());
+        }
+
+        let handler = *self.rx_handler.lock();
+        let count = frames.len();
+        for frame in frames {
+            if frame.errors & super::ring::rx_errors::FRAME != 0 {
+                self.count_rx_drop(&self.stats.rx_drops);
+                continue;
+            }
+            if frame.errors & super::ring::rx_errors::CHECKSUM != 0 {
+                self.count_rx_drop(&self.stats.rx_csum_errors);
+                continue;
+            }
+            if frame.checksum_verified() {
+                self.stats.rx_csum_offloaded.fetch_add(1, Ordering::Relaxed);
+            }
+            self.stats.rx_packets.fetch_add(1, Ordering::Relaxed);
+            self.stats.rx_bytes.fetch_add(frame.data.len() as u64, Ordering::Relaxed);
+            metrics.rx_packets.fetch_add(1, Ordering::Relaxed);
+            metrics.rx_bytes.fetch_add(frame.data.len() as u64, Ordering::Relaxed);
+            match handler {
+                Some(handler) => handler(&frame.data),
+                None => self.count_rx_drop(&self.stats.rx_drops),
+            }
+        }
+        count
+    }
+
+    fn count_rx_drop(&self, counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        crate::net::optimization::metrics()
+            .rx_drops
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    // ========================================================================
+    // Interrupts
+    // ========================================================================
+
+    /// 割り込み処理（ISR から呼ばれる）
+    ///
+    /// ICR を読むと原因ビットがクリアされる。リンク状態の変化はここで反映し、
+    /// 送受信は起床したタスクが処理する。
+    /// 戻り値: このデバイスが割り込みを上げていたか
+    pub fn handle_interrupt(&self) -> bool {
+        let cause = self.read32(REG_ICR);
+        if cause == 0 {
+            return false;
+        }
+        self.stats.interrupts.fetch_add(1, Ordering::Relaxed);
+        if cause & int::LSC != 0 {
+            self.update_link();
+        }
+        true
+    }
+
+    /// リンク状態を読み直す
+    fn update_link(&self) {
+        let link = self.link_status();
+        if self.link_up.swap(link.up, Ordering::AcqRel) != link.up {
+            self.stats.link_changes.fetch_add(1, Ordering::Relaxed);
+            crate::log!("[e1000] link {}\n", link);
+        }
+    }
+}
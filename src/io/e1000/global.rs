@@ -0,0 +1,150 @@
+// ============================================================================
+// src/io/e1000/global.rs - Global Driver Instance and Public API
+// ============================================================================
+//!
+//! e1000 ドライバのグローバルインスタンスと公開API。
+//!
+//! - PCI デバイス検出と初期化
+//! - 送受信タスク（割り込みで起床、割り込みがなければタイマーでポーリング）
+//! - 割り込みハンドラ
+
+#![allow(dead_code)]
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU8, Ordering};
+use spin::Mutex;
+
+use crate::io::pci::find_by_class;
+use crate::task::interrupt_waker;
+
+use super::device::{E1000Device, InterruptMode, RX_BATCH};
+use super::types::{E1000Error, E1000Result, INTEL_VENDOR_ID, Model};
+
+/// PCI クラス: ネットワークコントローラ / Ethernet
+const NET_CLASS: u8 = 0x02;
+const ETHERNET_SUBCLASS: u8 = 0x00;
+
+/// 割り込みがない場合のポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 10;
+
+// ============================================================================
+// Global e1000 Driver Instance
+// ============================================================================
+
+static E1000_DEVICE: Mutex<Option<Arc<E1000Device>>> = Mutex::new(None);
+
+/// INTx の IRQ 番号（INTx を使っていなければ 0）
+/// ISR からロックなしで参照するため別に保持する
+static E1000_IRQ: AtomicU8 = AtomicU8::new(0);
+
+/// Initialize the e1000 driver
+///
+/// 最初に見つかった対応コントローラを初期化し、送受信タスクを起動する。
+pub fn init() -> E1000Result<()> {
+    let (pci_device, model) = find_by_class(NET_CLASS, ETHERNET_SUBCLASS)
+        .into_iter()
+        .filter(|dev| dev.vendor_id.0 == INTEL_VENDOR_ID)
+        .find_map(|dev| Model::from_device_id(dev.device_id.0).map(|model| (dev, model)))
+        .ok_or(E1000Error::NoDevice)?;
+
+    crate::log!(
+        "[e1000] Found {} ({:04x}:{:04x}) at {:02x}:{:02x}.{}\n",
+        model.name(),
+        pci_device.vendor_id.0,
+        pci_device.device_id.0,
+        pci_device.bdf.bus(),
+        pci_device.bdf.device(),
+        pci_device.bdf.function()
+    );
+
+    pci_device.enable_memory_space();
+    pci_device.enable_bus_master();
+
+    let mut device = E1000Device::new(pci_device, model)?;
+    device.init()?;
+    if let InterruptMode::Legacy { irq } = device.interrupt_mode() {
+        E1000_IRQ.store(irq, Ordering::SeqCst);
+    }
+    let device = Arc::new(device);
+    *E1000_DEVICE.lock() = Some(Arc::clone(&device));
+
+    crate::task::spawn(run_device(device));
+    Ok(())
+}
+
+/// 送受信タスク
+async fn run_device(device: Arc<E1000Device>) {
+    let source = device.interrupt_mode().wake_source();
+    loop {
+        if device.poll() >= RX_BATCH {
+            crate::task::preemption::yield_now().await;
+            continue;
+        }
+        match source {
+            Some(source) => interrupt_waker::wait_for_interrupt(source).await,
+            None => crate::task::sleep_ms(POLL_INTERVAL_MS).await,
+        }
+    }
+}
+
+/// Access the e1000 driver
+pub fn with_device<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&E1000Device) -> R,
+{
+    let device = E1000_DEVICE.lock().clone();
+    device.as_deref().map(f)
+}
+
+/// e1000 が初期化済みか
+pub fn is_present() -> bool {
+    E1000_DEVICE.lock().is_some()
+}
+
+/// Ethernet フレームを送信
+pub fn transmit(frame: &[u8]) -> E1000Result<()> {
+    with_device(|device| device.transmit(frame)).unwrap_or(Err(E1000Error::NotInitialized))
+}
+
+// ============================================================================
+// Interrupt Handling
+// ============================================================================
+
+/// INTx の IRQ 番号（INTx を使っていなければ 0）
+pub fn get_irq() -> u8 {
+    E1000_IRQ.load(Ordering::SeqCst)
+}
+
+/// INTx 割り込みハンドラ（IRQ 共有のため原因がなければ何もしない）
+pub fn handle_interrupt() {
+    let device = E1000_DEVICE.lock().clone();
+    if let Some(device) = device
+        && device.handle_interrupt()
+        && let Some(source) = device.interrupt_mode().wake_source()
+    {
+        interrupt_waker::wake_from_interrupt(source);
+    }
+}
+
+/// MSI/MSI-X 割り込みハンドラ
+///
+/// MSI-X の受信・送信ベクタは EIAC で原因が自動クリアされるので、
+/// ICR を読むのは MSI と「その他」ベクタのときだけ。
+pub fn handle_msi_interrupt(vector: u8) {
+    let device = E1000_DEVICE.lock().clone();
+    let Some(device) = device else { return };
+    let mode = device.interrupt_mode();
+    if !mode.owns_vector(vector) {
+        return;
+    }
+    crate::io::interrupt_manager::record_interrupt(vector);
+    match mode {
+        InterruptMode::MsiX { other, .. } if vector != other => {}
+        _ => {
+            device.handle_interrupt();
+        }
+    }
+    if let Some(source) = mode.wake_source() {
+        interrupt_waker::wake_from_interrupt(source);
+    }
+}
@@ -0,0 +1,42 @@
+// ============================================================================
+// src/io/e1000/mod.rs - Intel e1000/e1000e Ethernet Driver
+// ============================================================================
+//!
+//! # Intel e1000/e1000e ドライバ
+//!
+//! 8254x（QEMU `e1000`）と 8257x/82574L（QEMU `e1000e`）の Ethernet ドライバ。
+//! virtio-net がない環境や実機での標準的な NIC として使う。
+//!
+//! ## 機能
+//! - EEPROM / RAL からの MAC アドレス取得
+//! - レガシーディスクリプタによる送受信リング
+//! - MSI-X（82574L: 受信・送信・その他で別ベクタ）、MSI、INTx、ポーリング
+//! - 受信チェックサム検証と送信チェックサム挿入のオフロード
+//! - リンク状態変化の検出
+//!
+//! ## モジュール構成
+//! - `regs` - レジスタ定義
+//! - `types` - エラー型、対応モデル、統計
+//! - `ring` - 送受信ディスクリプタリング
+//! - `device` - E1000Device の実装
+//! - `global` - グローバルインスタンスと公開API
+
+#![allow(dead_code)]
+
+// サブモジュール
+mod device;
+mod global;
+mod regs;
+mod ring;
+mod types;
+
+// 型の再エクスポート
+pub use types::{E1000Error, E1000Result, E1000Stats, LinkStatus, Model, INTEL_VENDOR_ID};
+
+// デバイスの再エクスポート
+pub use device::{E1000Device, InterruptMode, RxHandler};
+
+// 公開API関数の再エクスポート
+pub use global::{
+    get_irq, handle_interrupt, handle_msi_interrupt, init, is_present, transmit, with_device,
+};
@@ -0,0 +1,240 @@
+// ============================================================================
+// src/io/e1000/regs.rs - e1000/e1000e Register Definitions
+// ============================================================================
+//!
+//! 8254x / 8257x のレジスタオフセットとビット定義
+//! （Intel PCI/PCI-X Family of Gigabit Ethernet Controllers SDM, 82574 Datasheet）
+
+#![allow(dead_code)]
+
+// ============================================================================
+// Register Offsets (BAR0 MMIO)
+// ============================================================================
+
+/// Device Control
+pub const REG_CTRL: u32 = 0x0000;
+/// Device Status
+pub const REG_STATUS: u32 = 0x0008;
+/// EEPROM/Flash Control
+pub const REG_EECD: u32 = 0x0010;
+/// EEPROM Read
+pub const REG_EERD: u32 = 0x0014;
+/// Extended Device Control
+pub const REG_CTRL_EXT: u32 = 0x0018;
+/// MDI Control
+pub const REG_MDIC: u32 = 0x0020;
+/// Interrupt Cause Read
+pub const REG_ICR: u32 = 0x00C0;
+/// Interrupt Throttling
+pub const REG_ITR: u32 = 0x00C4;
+/// Interrupt Cause Set
+pub const REG_ICS: u32 = 0x00C8;
+/// Interrupt Mask Set/Read
+pub const REG_IMS: u32 = 0x00D0;
+/// Interrupt Mask Clear
+pub const REG_IMC: u32 = 0x00D8;
+/// Interrupt Auto Clear (82574)
+pub const REG_EIAC: u32 = 0x00DC;
+/// Interrupt Vector Allocation (82574)
+pub const REG_IVAR: u32 = 0x00E4;
+/// Receive Control
+pub const REG_RCTL: u32 = 0x0100;
+/// Transmit Control
+pub const REG_TCTL: u32 = 0x0400;
+/// Transmit IPG
+pub const REG_TIPG: u32 = 0x0410;
+/// Receive Descriptor Base Low/High
+pub const REG_RDBAL: u32 = 0x2800;
+pub const REG_RDBAH: u32 = 0x2804;
+/// Receive Descriptor Length
+pub const REG_RDLEN: u32 = 0x2808;
+/// Receive Descriptor Head/Tail
+pub const REG_RDH: u32 = 0x2810;
+pub const REG_RDT: u32 = 0x2818;
+/// Receive Delay Timer
+pub const REG_RDTR: u32 = 0x2820;
+/// Receive Interrupt Absolute Delay
+pub const REG_RADV: u32 = 0x282C;
+/// Transmit Descriptor Base Low/High
+pub const REG_TDBAL: u32 = 0x3800;
+pub const REG_TDBAH: u32 = 0x3804;
+/// Transmit Descriptor Length
+pub const REG_TDLEN: u32 = 0x3808;
+/// Transmit Descriptor Head/Tail
+pub const REG_TDH: u32 = 0x3810;
+pub const REG_TDT: u32 = 0x3818;
+/// Transmit Descriptor Control
+pub const REG_TXDCTL: u32 = 0x3828;
+/// CRC Error Count
+pub const REG_CRCERRS: u32 = 0x4000;
+/// Missed Packets Count
+pub const REG_MPC: u32 = 0x4010;
+/// Good Packets Received Count
+pub const REG_GPRC: u32 = 0x4074;
+/// Good Packets Transmitted Count
+pub const REG_GPTC: u32 = 0x4080;
+/// Receive Checksum Control
+pub const REG_RXCSUM: u32 = 0x5000;
+/// Multicast Table Array (128 x 32bit)
+pub const REG_MTA: u32 = 0x5200;
+pub const MTA_ENTRIES: u32 = 128;
+/// Receive Address Low/High (entry 0)
+pub const REG_RAL0: u32 = 0x5400;
+pub const REG_RAH0: u32 = 0x5404;
+
+// ============================================================================
+// Bit Definitions
+// ============================================================================
+
+/// CTRL レジスタ
+pub mod ctrl {
+    /// Full Duplex
+    pub const FD: u32 = 1 << 0;
+    /// Auto-Speed Detection Enable
+    pub const ASDE: u32 = 1 << 5;
+    /// Set Link Up
+    pub const SLU: u32 = 1 << 6;
+    /// Device Reset
+    pub const RST: u32 = 1 << 26;
+    /// VLAN Mode Enable
+    pub const VME: u32 = 1 << 30;
+    /// PHY Reset
+    pub const PHY_RST: u32 = 1 << 31;
+}
+
+/// STATUS レジスタ
+pub mod status {
+    /// Full Duplex
+    pub const FD: u32 = 1 << 0;
+    /// Link Up
+    pub const LU: u32 = 1 << 1;
+    /// Link Speed (00=10, 01=100, 1x=1000)
+    pub const SPEED_SHIFT: u32 = 6;
+    pub const SPEED_MASK: u32 = 0b11 << SPEED_SHIFT;
+}
+
+/// EERD レジスタ（8254x と 8257x でフィールド位置が異なる）
+pub mod eerd {
+    pub const START: u32 = 1 << 0;
+    /// 8254x: DONE ビット / アドレスシフト
+    pub const DONE_8254X: u32 = 1 << 4;
+    pub const ADDR_SHIFT_8254X: u32 = 8;
+    /// 8257x: DONE ビット / アドレスシフト
+    pub const DONE_8257X: u32 = 1 << 1;
+    pub const ADDR_SHIFT_8257X: u32 = 2;
+    pub const DATA_SHIFT: u32 = 16;
+}
+
+/// CTRL_EXT レジスタ
+pub mod ctrl_ext {
+    /// PBA Support（82574 で MSI-X を使う場合に必須）
+    pub const PBA_SUPPORT: u32 = 1 << 31;
+}
+
+/// 割り込み原因（ICR / IMS / IMC 共通）
+pub mod int {
+    /// Transmit Descriptor Written Back
+    pub const TXDW: u32 = 1 << 0;
+    /// Transmit Queue Empty
+    pub const TXQE: u32 = 1 << 1;
+    /// Link Status Change
+    pub const LSC: u32 = 1 << 2;
+    /// Receive Descriptor Minimum Threshold
+    pub const RXDMT0: u32 = 1 << 4;
+    /// Receiver Overrun
+    pub const RXO: u32 = 1 << 6;
+    /// Receiver Timer Interrupt
+    pub const RXT0: u32 = 1 << 7;
+    /// 82574 MSI-X: Receive Queue 0
+    pub const RXQ0: u32 = 1 << 20;
+    /// 82574 MSI-X: Transmit Queue 0
+    pub const TXQ0: u32 = 1 << 22;
+    /// 82574 MSI-X: Other (link など)
+    pub const OTHER: u32 = 1 << 24;
+    /// Interrupt Asserted (82574)
+    pub const INT_ASSERTED: u32 = 1 << 31;
+
+    /// 受信関連
+    pub const RX: u32 = RXT0 | RXO | RXDMT0 | RXQ0;
+    /// 送信関連
+    pub const TX: u32 = TXDW | TXQ0;
+    /// ドライバが有効にする割り込み
+    pub const ENABLED: u32 = RXT0 | RXO | RXDMT0 | TXDW | LSC;
+}
+
+/// IVAR レジスタ（82574 MSI-X ベクタ割り当て）
+pub mod ivar {
+    pub const VALID: u32 = 1 << 3;
+    pub const RXQ0_SHIFT: u32 = 0;
+    pub const TXQ0_SHIFT: u32 = 8;
+    pub const OTHER_SHIFT: u32 = 16;
+    /// 送信ライトバックごとに割り込み
+    pub const TX_INT_EVERY_WB: u32 = 1 << 31;
+}
+
+/// RCTL レジスタ
+pub mod rctl {
+    /// Receiver Enable
+    pub const EN: u32 = 1 << 1;
+    /// Store Bad Packets
+    pub const SBP: u32 = 1 << 2;
+    /// Unicast Promiscuous
+    pub const UPE: u32 = 1 << 3;
+    /// Multicast Promiscuous
+    pub const MPE: u32 = 1 << 4;
+    /// Long Packet Enable
+    pub const LPE: u32 = 1 << 5;
+    /// Receive Descriptor Minimum Threshold = 1/2
+    pub const RDMTS_HALF: u32 = 0 << 8;
+    /// Broadcast Accept Mode
+    pub const BAM: u32 = 1 << 15;
+    /// Buffer Size 2048 (BSEX=0, BSIZE=00)
+    pub const BSIZE_2048: u32 = 0 << 16;
+    /// Strip Ethernet CRC
+    pub const SECRC: u32 = 1 << 26;
+}
+
+/// TCTL レジスタ
+pub mod tctl {
+    /// Transmit Enable
+    pub const EN: u32 = 1 << 1;
+    /// Pad Short Packets
+    pub const PSP: u32 = 1 << 3;
+    /// Collision Threshold
+    pub const CT_SHIFT: u32 = 4;
+    /// Collision Distance
+    pub const COLD_SHIFT: u32 = 12;
+    /// Re-transmit on Late Collision
+    pub const RTLC: u32 = 1 << 24;
+
+    /// 全二重の推奨値（CT=15, COLD=64）
+    pub const DEFAULT: u32 = EN | PSP | (0x0F << CT_SHIFT) | (0x40 << COLD_SHIFT) | RTLC;
+}
+
+/// TIPG 推奨値（IEEE 802.3 銅線: IPGT=10, IPGR1=8, IPGR2=6）
+pub const TIPG_DEFAULT: u32 = 10 | (8 << 10) | (6 << 20);
+
+/// TXDCTL レジスタ
+pub mod txdctl {
+    /// Descriptor Granularity（82574 では 1 必須）
+    pub const GRAN: u32 = 1 << 24;
+    /// Write-back Threshold = 1
+    pub const WTHRESH_1: u32 = 1 << 16;
+}
+
+/// RXCSUM レジスタ
+pub mod rxcsum {
+    /// IP Checksum Off-load Enable
+    pub const IPOFL: u32 = 1 << 8;
+    /// TCP/UDP Checksum Off-load Enable
+    pub const TUOFL: u32 = 1 << 9;
+}
+
+/// RAH レジスタ
+pub mod rah {
+    /// Address Valid
+    pub const AV: u32 = 1 << 31;
+}
+
+/// ITR 値: 割り込み間隔（256ns 単位）。約 20000 割り込み/秒
+pub const ITR_DEFAULT: u32 = 195;
@@ -0,0 +1,475 @@
+// ============================================================================
+// src/io/e1000/ring.rs - e1000 Descriptor Rings
+// ============================================================================
+//!
+//! レガシー形式の送受信ディスクリプタリング
+//!
+//! ディスクリプタとパケットバッファは `io::dma` のコヒーレントバッファに置く
+//! （DMA領域は恒等マップ: 物理アドレス = 仮想アドレス）。
+//! パケットバッファはスロットごとに固定の 2KB で、送信時はフレームをコピーする。
+
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::io::dma::{CoherentDmaBuffer, DmaMemoryAttributes};
+
+use super::types::{E1000Error, E1000Result};
+
+/// パケットバッファサイズ（RCTL.BSIZE = 2048）
+pub const BUFFER_SIZE: usize = 2048;
+
+/// 既定のリング長（RDLEN/TDLEN は 128 バイト = 8 ディスクリプタ単位）
+pub const DEFAULT_RING_SIZE: usize = 256;
+
+// ============================================================================
+// Descriptors
+// ============================================================================
+
+/// 受信ディスクリプタ（レガシー形式）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RxDesc {
+    /// バッファアドレス
+    pub addr: u64,
+    /// 受信長
+    pub length: u16,
+    /// パケットチェックサム
+    pub checksum: u16,
+    /// ステータス
+    pub status: u8,
+    /// エラー
+    pub errors: u8,
+    /// VLAN タグ
+    pub special: u16,
+}
+
+/// 受信ステータスビット
+pub mod rx_status {
+    /// Descriptor Done
+    pub const DD: u8 = 1 << 0;
+    /// End of Packet
+    pub const EOP: u8 = 1 << 1;
+    /// Ignore Checksum Indication
+    pub const IXSM: u8 = 1 << 2;
+    /// 802.1Q タグ付き
+    pub const VP: u8 = 1 << 3;
+    /// TCP/UDP チェックサム計算済み
+    pub const TCPCS: u8 = 1 << 5;
+    /// IP チェックサム計算済み
+    pub const IPCS: u8 = 1 << 6;
+}
+
+/// 受信エラービット
+pub mod rx_errors {
+    /// CRC/Alignment Error
+    pub const CE: u8 = 1 << 0;
+    /// Symbol Error
+    pub const SE: u8 = 1 << 1;
+    /// Sequence Error
+    pub const SEQ: u8 = 1 << 2;
+    /// Carrier Extension Error
+    pub const CXE: u8 = 1 << 4;
+    /// TCP/UDP Checksum Error
+    pub const TCPE: u8 = 1 << 5;
+    /// IP Checksum Error
+    pub const IPE: u8 = 1 << 6;
+    /// RX Data Error
+    pub const RXE: u8 = 1 << 7;
+
+    /// フレームを破棄すべきエラー
+    pub const FRAME: u8 = CE | SE | SEQ | CXE | RXE;
+    /// チェックサムエラー
+    pub const CHECKSUM: u8 = TCPE | IPE;
+}
+
+/// 送信ディスクリプタ（レガシー形式）
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TxDesc {
+    /// バッファアドレス
+    pub addr: u64,
+    /// 送信長
+    pub length: u16,
+    /// チェックサム挿入位置
+    pub cso: u8,
+    /// コマンド
+    pub cmd: u8,
+    /// ステータス
+    pub status: u8,
+    /// チェックサム計算開始位置
+    pub css: u8,
+    /// VLAN タグ
+    pub special: u16,
+}
+
+/// 送信コマンドビット
+pub mod tx_cmd {
+    /// End of Packet
+    pub const EOP: u8 = 1 << 0;
+    /// Insert FCS
+    pub const IFCS: u8 = 1 << 1;
+    /// Insert Checksum
+    pub const IC: u8 = 1 << 2;
+    /// Report Status
+    pub const RS: u8 = 1 << 3;
+    /// VLAN Packet Enable
+    pub const VLE: u8 = 1 << 6;
+    /// Interrupt Delay Enable
+    pub const IDE: u8 = 1 << 7;
+}
+
+/// 送信ステータスビット
+pub mod tx_status {
+    /// Descriptor Done
+    pub const DD: u8 = 1 << 0;
+}
+
+/// 送信チェックサムオフロード指定
+///
+/// `css` からフレーム末尾までの1の補数和を `cso` に書き込む。
+/// チェックサム欄には擬似ヘッダの部分和を入れておく（virtio-net の部分チェックサムと同じ形式）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxChecksum {
+    pub css: u8,
+    pub cso: u8,
+}
+
+// ============================================================================
+// Descriptor Ring
+// ============================================================================
+
+/// ディスクリプタ配列とスロットごとのパケットバッファ
+struct DescRing<T> {
+    descs: CoherentDmaBuffer,
+    buffers: CoherentDmaBuffer,
+    size: usize,
+    _desc: PhantomData<T>,
+}
+
+impl<T: Copy + Default> DescRing<T> {
+    fn new(size: usize) -> E1000Result<Self> {
+        let descs = CoherentDmaBuffer::new(size * core::mem::size_of::<T>(), DmaMemoryAttributes::MMIO)
+            .ok_or(E1000Error::NoMemory)?;
+        let buffers = CoherentDmaBuffer::new(size * BUFFER_SIZE, DmaMemoryAttributes::MMIO)
+            .ok_or(E1000Error::NoMemory)?;
+        Ok(Self { descs, buffers, size, _desc: PhantomData })
+    }
+
+    fn desc_ptr(&self, index: usize) -> *mut T {
+        debug_assert!(index < self.size);
+        (self.descs.phys_addr().as_u64() as *mut T).wrapping_add(index)
+    }
+
+    fn read(&self, index: usize) -> T {
+        // SAFETY: index < size のディスクリプタ配列内。デバイスが書き換えるので volatile
+        unsafe { read_volatile(self.desc_ptr(index)) }
+    }
+
+    fn write(&self, index: usize, desc: T) {
+        // SAFETY: 同上
+        unsafe { write_volatile(self.desc_ptr(index), desc) }
+    }
+
+    fn buffer_addr(&self, index: usize) -> u64 {
+        self.buffers.phys_addr().as_u64() + (index * BUFFER_SIZE) as u64
+    }
+
+    fn buffer(&self, index: usize, len: usize) -> &[u8] {
+        // SAFETY: スロット index のバッファ（BUFFER_SIZE バイト）内
+        unsafe { core::slice::from_raw_parts(self.buffer_addr(index) as *const u8, len.min(BUFFER_SIZE)) }
+    }
+
+    fn buffer_mut(&mut self, index: usize, len: usize) -> &mut [u8] {
+        // SAFETY: 同上。スロットはデバイスに渡していない
+        unsafe { core::slice::from_raw_parts_mut(self.buffer_addr(index) as *mut u8, len.min(BUFFER_SIZE)) }
+    }
+
+    fn phys_addr(&self) -> u64 {
+        self.descs.phys_addr().as_u64()
+    }
+
+    fn byte_len(&self) -> u32 {
+        (self.size * core::mem::size_of::<T>()) as u32
+    }
+}
+
+// ============================================================================
+// Receive Ring
+// ============================================================================
+
+/// 受信したフレーム
+#[derive(Debug)]
+pub struct RxCompletion {
+    /// Ethernet フレーム（FCS なし）
+    pub data: Vec<u8>,
+    /// 最終ディスクリプタのステータス
+    pub status: u8,
+    /// エラー（全ディスクリプタの論理和）
+    pub errors: u8,
+    /// VLAN タグ（`rx_status::VP` のとき有効）
+    pub vlan_tag: u16,
+}
+
+impl RxCompletion {
+    /// ハードウェアがチェックサムを検証して正しかったか
+    pub fn checksum_verified(&self) -> bool {
+        self.status & rx_status::IXSM == 0
+            && self.status & (rx_status::TCPCS | rx_status::IPCS) != 0
+            && self.errors & rx_errors::CHECKSUM == 0
+    }
+}
+
+/// 受信リング
+pub struct RxRing {
+    ring: DescRing<RxDesc>,
+    /// 次に検査するディスクリプタ
+    next: usize,
+    /// 組み立て中のフレーム（EOP 前のディスクリプタ）
+    partial: Vec<u8>,
+    partial_errors: u8,
+}
+
+impl RxRing {
+    /// 全スロットにバッファを設定した受信リングを作成
+    pub fn new(size: usize) -> E1000Result<Self> {
+        let ring = DescRing::new(size)?;
+        for i in 0..size {
+            ring.write(i, RxDesc { addr: ring.buffer_addr(i), ..Default::default() });
+        }
+        Ok(Self { ring, next: 0, partial: Vec::new(), partial_errors: 0 })
+    }
+
+    /// リング長
+    pub fn size(&self) -> usize {
+        self.ring.size
+    }
+
+    /// ディスクリプタ配列の物理アドレス（RDBAL/RDBAH）
+    pub fn phys_addr(&self) -> u64 {
+        self.ring.phys_addr()
+    }
+
+    /// ディスクリプタ配列のバイト長（RDLEN）
+    pub fn byte_len(&self) -> u32 {
+        self.ring.byte_len()
+    }
+
+    /// 初期 RDT: ヘッドと一致しないよう1つ空けて全スロットを渡す
+    pub fn initial_tail(&self) -> u32 {
+        (self.ring.size - 1) as u32
+    }
+
+    /// 返却済みの最後のディスクリプタ（RDT に書く値）
+    pub fn tail(&self) -> u32 {
+        ((self.next + self.ring.size - 1) % self.ring.size) as u32
+    }
+
+    /// 受信済みフレームを1つ取り出す
+    ///
+    /// 使い終わったディスクリプタはその場でデバイスに返す（呼び出し側は `tail` を RDT に書く）。
+    pub fn poll(&mut self) -> Option<RxCompletion> {
+        loop {
+            let desc = self.ring.read(self.next);
+            if desc.status & rx_status::DD == 0 {
+                return None;
+            }
+            let index = self.next;
+            self.partial
+                .extend_from_slice(self.ring.buffer(index, desc.length as usize));
+            self.partial_errors |= desc.errors;
+            self.ring
+                .write(index, RxDesc { addr: self.ring.buffer_addr(index), ..Default::default() });
+            self.next = (index + 1) % self.ring.size;
+
+            if desc.status & rx_status::EOP != 0 {
+                let errors = core::mem::take(&mut self.partial_errors);
+                return Some(RxCompletion {
+                    data: core::mem::take(&mut self.partial),
+                    status: desc.status,
+                    errors,
+                    vlan_tag: desc.special,
+                });
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Transmit Ring
+// ============================================================================
+
+/// 送信リング
+pub struct TxRing {
+    ring: DescRing<TxDesc>,
+    /// 次に書き込むディスクリプタ（TDT）
+    tail: usize,
+    /// 次に回収するディスクリプタ
+    clean: usize,
+}
+
+impl TxRing {
+    /// 送信リングを作成
+    pub fn new(size: usize) -> E1000Result<Self> {
+        Ok(Self { ring: DescRing::new(size)?, tail: 0, clean: 0 })
+    }
+
+    /// リング長
+    pub fn size(&self) -> usize {
+        self.ring.size
+    }
+
+    /// ディスクリプタ配列の物理アドレス（TDBAL/TDBAH）
+    pub fn phys_addr(&self) -> u64 {
+        self.ring.phys_addr()
+    }
+
+    /// ディスクリプタ配列のバイト長（TDLEN）
+    pub fn byte_len(&self) -> u32 {
+        self.ring.byte_len()
+    }
+
+    /// 送信中のディスクリプタ数
+    pub fn in_flight(&self) -> usize {
+        (self.tail + self.ring.size - self.clean) % self.ring.size
+    }
+
+    /// 空きディスクリプタ数（ヘッド = テールは空を意味するので1つ空ける）
+    pub fn free(&self) -> usize {
+        self.ring.size - 1 - self.in_flight()
+    }
+
+    /// 送信完了したディスクリプタを回収
+    pub fn reclaim(&mut self) -> usize {
+        let mut count = 0;
+        while self.clean != self.tail && self.ring.read(self.clean).status & tx_status::DD != 0 {
+            self.clean = (self.clean + 1) % self.ring.size;
+            count += 1;
+        }
+        count
+    }
+
+    /// フレームをコピーして投入
+    ///
+    /// `prepare` はバッファ上のフレームを書き換えてチェックサムオフロード指定を返す。
+    /// 戻り値: 新しい TDT
+    pub fn submit(
+        &mut self,
+        frame: &[u8],
+        prepare: impl FnOnce(&mut [u8]) -> Option<TxChecksum>,
+    ) -> E1000Result<u32> {
+        if frame.len() > BUFFER_SIZE {
+            return Err(E1000Error::FrameTooLarge);
+        }
+        if self.free() == 0 && (self.reclaim() == 0 || self.free() == 0) {
+            return Err(E1000Error::RingFull);
+        }
+        let index = self.tail;
+        let buffer = self.ring.buffer_mut(index, frame.len());
+        buffer.copy_from_slice(frame);
+        let checksum = prepare(buffer);
+
+        let mut desc = TxDesc {
+            addr: self.ring.buffer_addr(index),
+            length: frame.len() as u16,
+            cmd: tx_cmd::EOP | tx_cmd::IFCS | tx_cmd::RS,
+            ..Default::default()
+        };
+        if let Some(csum) = checksum {
+            desc.cmd |= tx_cmd::IC;
+            desc.css = csum.css;
+            desc.cso = csum.cso;
+        }
+        self.ring.write(index, desc);
+        self.tail = (index + 1) % self.ring.size;
+        Ok(self.tail as u32)
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// デバイスの受信書き込みを模擬
+    fn device_receive(ring: &RxRing, index: usize, data: &[u8], status: u8, errors: u8) {
+        let desc = ring.ring.read(index);
+        // SAFETY: テスト用にバッファへ直接書き込む
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), desc.addr as *mut u8, data.len());
+        }
+        ring.ring.write(
+            index,
+            RxDesc { length: data.len() as u16, status: status | rx_status::DD, errors, ..desc },
+        );
+    }
+
+    #[test]
+    fn test_descriptor_layout() {
+        assert_eq!(core::mem::size_of::<RxDesc>(), 16);
+        assert_eq!(core::mem::size_of::<TxDesc>(), 16);
+    }
+
+    #[test]
+    fn test_rx_ring() {
+        let mut ring = RxRing::new(8).unwrap();
+        assert_eq!(ring.initial_tail(), 7);
+        assert!(ring.poll().is_none());
+
+        device_receive(&ring, 0, &[1, 2, 3], rx_status::EOP | rx_status::TCPCS, 0);
+        let frame = ring.poll().unwrap();
+        assert_eq!(frame.data, [1, 2, 3]);
+        assert!(frame.checksum_verified());
+        assert_eq!(ring.tail(), 0);
+        assert!(ring.poll().is_none());
+
+        // 2ディスクリプタにまたがるフレーム、チェックサムエラー付き
+        device_receive(&ring, 1, &[4, 5], 0, 0);
+        device_receive(&ring, 2, &[6], rx_status::EOP | rx_status::TCPCS, rx_errors::TCPE);
+        let frame = ring.poll().unwrap();
+        assert_eq!(frame.data, [4, 5, 6]);
+        assert!(!frame.checksum_verified());
+        assert_eq!(ring.tail(), 2);
+
+        // 返却したディスクリプタはバッファアドレスだけ残してクリア
+        let desc = ring.ring.read(1);
+        assert_eq!(desc.status, 0);
+        assert_eq!(desc.addr, ring.ring.buffer_addr(1));
+    }
+
+    #[test]
+    fn test_tx_ring() {
+        let mut ring = TxRing::new(4).unwrap();
+        assert_eq!(ring.free(), 3);
+
+        let tail = ring
+            .submit(&[0xAA; 60], |buf| {
+                buf[50] = 0x12;
+                Some(TxChecksum { css: 34, cso: 50 })
+            })
+            .unwrap();
+        assert_eq!(tail, 1);
+        let desc = ring.ring.read(0);
+        assert_eq!(desc.length, 60);
+        assert_eq!(desc.cmd, tx_cmd::EOP | tx_cmd::IFCS | tx_cmd::RS | tx_cmd::IC);
+        assert_eq!((desc.css, desc.cso), (34, 50));
+        assert_eq!(ring.ring.buffer(0, 60)[50], 0x12);
+
+        ring.submit(&[0; 60], |_| None).unwrap();
+        ring.submit(&[0; 60], |_| None).unwrap();
+        assert_eq!(ring.submit(&[0; 60], |_| None), Err(E1000Error::RingFull));
+        assert_eq!(ring.submit(&[0; BUFFER_SIZE + 1], |_| None), Err(E1000Error::FrameTooLarge));
+
+        // デバイスが先頭を送信完了
+        let mut done = ring.ring.read(0);
+        done.status = tx_status::DD;
+        ring.ring.write(0, done);
+        assert_eq!(ring.submit(&[0; 60], |_| None), Ok(0));
+        assert_eq!(ring.in_flight(), 3);
+    }
+}
@@ -0,0 +1,176 @@
+// ============================================================================
+// src/io/e1000/types.rs - e1000 Types
+// ============================================================================
+//!
+//! エラー型、対応モデル、リンク状態、統計
+
+#![allow(dead_code)]
+
+use core::fmt;
+use core::sync::atomic::AtomicU64;
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// e1000 ドライバエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum E1000Error {
+    /// 対応デバイスが見つからない
+    NoDevice,
+    /// BAR0 がメモリ空間でない
+    InvalidBar,
+    /// DMA メモリを確保できない
+    NoMemory,
+    /// リセットが完了しない
+    ResetTimeout,
+    /// EEPROM 読み取りが完了しない
+    EepromTimeout,
+    /// 送信リングが満杯
+    RingFull,
+    /// フレームがバッファより大きい
+    FrameTooLarge,
+    /// デバイスが初期化されていない
+    NotInitialized,
+}
+
+impl fmt::Display for E1000Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            E1000Error::NoDevice => write!(f, "No e1000 device found"),
+            E1000Error::InvalidBar => write!(f, "Invalid BAR0"),
+            E1000Error::NoMemory => write!(f, "Out of DMA memory"),
+            E1000Error::ResetTimeout => write!(f, "Device reset timed out"),
+            E1000Error::EepromTimeout => write!(f, "EEPROM read timed out"),
+            E1000Error::RingFull => write!(f, "Transmit ring is full"),
+            E1000Error::FrameTooLarge => write!(f, "Frame too large"),
+            E1000Error::NotInitialized => write!(f, "Device not initialized"),
+        }
+    }
+}
+
+/// e1000 ドライバの結果型
+pub type E1000Result<T> = Result<T, E1000Error>;
+
+// ============================================================================
+// Supported Models
+// ============================================================================
+
+/// Intel PCI ベンダーID
+pub const INTEL_VENDOR_ID: u16 = 0x8086;
+
+/// 対応コントローラ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// 82540EM（QEMU `e1000`）
+    I82540Em,
+    /// 82545EM（QEMU `e1000-82545em`）
+    I82545Em,
+    /// 82545GM
+    I82545Gm,
+    /// 82541PI
+    I82541Pi,
+    /// 82571EB
+    I82571Eb,
+    /// 82572EI
+    I82572Ei,
+    /// 82574L（QEMU `e1000e`）
+    I82574L,
+    /// 82583V
+    I82583V,
+}
+
+impl Model {
+    /// PCI デバイスIDからモデルを判定
+    pub fn from_device_id(device_id: u16) -> Option<Self> {
+        Some(match device_id {
+            0x100E => Model::I82540Em,
+            0x100F => Model::I82545Em,
+            0x1026 => Model::I82545Gm,
+            0x107C => Model::I82541Pi,
+            0x105E => Model::I82571Eb,
+            0x107D => Model::I82572Ei,
+            0x10D3 | 0x10F6 => Model::I82574L,
+            0x150C => Model::I82583V,
+            _ => return None,
+        })
+    }
+
+    /// モデル名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::I82540Em => "82540EM",
+            Model::I82545Em => "82545EM",
+            Model::I82545Gm => "82545GM",
+            Model::I82541Pi => "82541PI",
+            Model::I82571Eb => "82571EB",
+            Model::I82572Ei => "82572EI",
+            Model::I82574L => "82574L",
+            Model::I82583V => "82583V",
+        }
+    }
+
+    /// PCIe 世代（e1000e）か
+    ///
+    /// EERD のフィールド位置が 8254x と異なる。
+    pub fn is_pcie(&self) -> bool {
+        matches!(
+            self,
+            Model::I82571Eb | Model::I82572Ei | Model::I82574L | Model::I82583V
+        )
+    }
+
+    /// キューごとの MSI-X ベクタ（IVAR）をサポートするか
+    pub fn has_ivar(&self) -> bool {
+        matches!(self, Model::I82574L)
+    }
+}
+
+// ============================================================================
+// Link Status
+// ============================================================================
+
+/// リンク状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LinkStatus {
+    /// リンクアップ
+    pub up: bool,
+    /// 速度 (Mbps)
+    pub speed_mbps: u16,
+    /// 全二重
+    pub full_duplex: bool,
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.up {
+            let duplex = if self.full_duplex { "full" } else { "half" };
+            write!(f, "up {} Mbps {} duplex", self.speed_mbps, duplex)
+        } else {
+            write!(f, "down")
+        }
+    }
+}
+
+// ============================================================================
+// Statistics
+// ============================================================================
+
+/// ドライバ統計
+#[derive(Debug, Default)]
+pub struct E1000Stats {
+    pub rx_packets: AtomicU64,
+    pub rx_bytes: AtomicU64,
+    pub rx_drops: AtomicU64,
+    /// ハードウェアが検出したチェックサムエラー
+    pub rx_csum_errors: AtomicU64,
+    /// ハードウェアでチェックサム検証済みの受信フレーム
+    pub rx_csum_offloaded: AtomicU64,
+    pub tx_packets: AtomicU64,
+    pub tx_bytes: AtomicU64,
+    pub tx_drops: AtomicU64,
+    /// チェックサム挿入をオフロードした送信フレーム
+    pub tx_csum_offloaded: AtomicU64,
+    pub interrupts: AtomicU64,
+    pub link_changes: AtomicU64,
+}
@@ -8,9 +8,11 @@ pub mod ahci_atapi;   // AHCI ATAPI (CD/DVD) Support
 pub mod apic;
 pub mod audio;
 pub mod dma;
+pub mod e1000;       // Intel e1000/e1000e Ethernet driver (directory)
 pub mod hid;         // HID subsystem (directory) - keyboard.rs, mouse.rs, ps2.rs
 pub mod ide;
 pub mod io_scheduler; // Polling/Executor連携 I/Oスケジューラ
+pub mod interrupt_manager; // MSI/MSI-X ベクタ割り当て
 pub mod iommu;
 pub mod log;
 pub mod nvme;        // NVMe module (directory) - includes driver.rs
@@ -104,8 +106,9 @@ pub use virtio::{
 #[allow(unused_imports)]
 pub use virtio::{
     VirtioNetDevice, VirtioNetHeader, VirtioNetStats, VirtioNetConfig,
-    NetVirtQueue, VringDesc as NetVringDesc,
-    net_features, handle_virtio_net_interrupt, init_virtio_net,
+    QueuePair as NetQueuePair, QueuePairStats, VringDesc as NetVringDesc,
+    net_features, handle_virtio_net_interrupt, handle_virtio_net_queue_interrupt,
+    init_virtio_net, init_virtio_net_pci,
     with_virtio_net,
 };
 
@@ -249,6 +249,18 @@ impl SerialPort {
         }
     }
 
+    /// Send raw bytes (blocking)
+    pub fn send_bytes(&self, data: &[u8]) {
+        for &byte in data {
+            self.send(byte);
+        }
+    }
+
+    /// Check if the port has been initialized
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Acquire)
+    }
+
     /// Receive a byte (non-blocking)
     pub fn try_receive(&self) -> Result<u8, SerialError> {
         if self.can_receive() {
@@ -772,6 +784,18 @@ pub fn serial1() -> &'static AsyncSerialPort {
     &SERIAL1
 }
 
+/// COM2: binary data channel (packet capture etc.)
+/// コンソール出力（COM1）と混在させないため別ポートを使用する
+static SERIAL2: SerialPort = SerialPort::new(ComPort::Com2);
+
+/// Get COM2, initializing it (8N1, polling mode) on first use
+pub fn serial2() -> Result<&'static SerialPort, SerialError> {
+    if !SERIAL2.is_initialized() {
+        SERIAL2.init(BaudRate::Baud115200, DataBits::Bits8, StopBits::Stop1, Parity::None)?;
+    }
+    Ok(&SERIAL2)
+}
+
 pub fn handle_interrupt() {
     SERIAL1.handle_interrupt();
 }
@@ -43,10 +43,14 @@ pub use net::{
     VirtioNetHeader,
     VirtioNetStats,
     VirtioNetConfig,
-    NetVirtQueue,
+    QueuePair,
+    QueuePairStats,
+    MsixBinding,
     VringDesc,
     init_virtio_net,
+    init_virtio_net_pci,
     handle_virtio_net_interrupt,
+    handle_virtio_net_queue_interrupt,
     with_virtio_net,
     features as net_features,
 };
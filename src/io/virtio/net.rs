@@ -5,14 +5,15 @@
 // ============================================================================
 #![allow(dead_code)]
 
+pub mod offload;
+pub mod queue;
+pub mod rss;
+
 use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::future::Future;
-use core::pin::Pin;
-use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, Ordering};
-use core::task::{Context, Poll, Waker};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use spin::Mutex;
 use x86_64::PhysAddr;
 
@@ -21,8 +22,13 @@ use super::defs::{status, VirtioDeviceType};
 use super::transport::VirtioTransport;
 use crate::io::dma::{TypedDmaSlice, CpuOwned, DeviceOwned, CoherentDmaBuffer, DmaMemoryAttributes};
 use crate::io::io_scheduler::{DeviceId, IoRequestId, IoResult, PollHandler, hybrid_coordinator};
-// Import PacketRef for zero-copy
-use crate::net::mempool::PacketRef;
+use crate::task::interrupt_waker::{self, InterruptSource};
+use crate::task::per_core_executor::{self, Priority};
+
+pub use super::defs::VringDesc;
+pub use offload::TxOffloads;
+pub use queue::{CtrlQueue, QueueStats, Ring, RxFrame, RxQueue, TxQueue};
+pub use rss::RssConfig;
 
 // ============================================================================
 // VirtIO Net Transport Helper Functions
@@ -60,14 +66,82 @@ pub mod features {
     pub const VIRTIO_NET_F_GUEST_TSO4: u64 = 1 << 7;
     /// ゲストTSO6
     pub const VIRTIO_NET_F_GUEST_TSO6: u64 = 1 << 8;
+    /// ホストTSO4（送信側の大きなTCPフレームをデバイスが分割）
+    pub const VIRTIO_NET_F_HOST_TSO4: u64 = 1 << 11;
+    /// ホストTSO6
+    pub const VIRTIO_NET_F_HOST_TSO6: u64 = 1 << 12;
+    /// マージ可能受信バッファ
+    pub const VIRTIO_NET_F_MRG_RXBUF: u64 = 1 << 15;
+    /// リンク状態フィールド
+    pub const VIRTIO_NET_F_STATUS: u64 = 1 << 16;
     /// マルチキューサポート
     pub const VIRTIO_NET_F_MQ: u64 = 1 << 22;
     /// CTRL_VQサポート
     pub const VIRTIO_NET_F_CTRL_VQ: u64 = 1 << 17;
+    /// VirtIO 1.0 準拠デバイス
+    pub const VIRTIO_F_VERSION_1: u64 = 1 << 32;
     /// 割り込み抑制
     pub const VIRTIO_NET_F_NOTIF_COAL: u64 = 1 << 52;
+    /// 受信ヘッダへのハッシュ値報告
+    pub const VIRTIO_NET_F_HASH_REPORT: u64 = 1 << 57;
+    /// RSS（受信キューの振り分け）
+    pub const VIRTIO_NET_F_RSS: u64 = 1 << 60;
+
+    /// ドライバが要求するフィーチャー
+    ///
+    /// GUEST_TSO4/6 (LRO) は要求しない: スタックは MTU を超える受信フレームを扱えない。
+    pub const DRIVER_FEATURES: u64 = VIRTIO_NET_F_MAC
+        | VIRTIO_NET_F_MTU
+        | VIRTIO_NET_F_STATUS
+        | VIRTIO_NET_F_CSUM
+        | VIRTIO_NET_F_GUEST_CSUM
+        | VIRTIO_NET_F_HOST_TSO4
+        | VIRTIO_NET_F_MRG_RXBUF
+        | VIRTIO_NET_F_CTRL_VQ
+        | VIRTIO_NET_F_MQ
+        | VIRTIO_NET_F_RSS
+        | VIRTIO_F_VERSION_1;
+
+    /// デバイスが提示したフィーチャーから受け入れるものを決める
+    ///
+    /// 依存関係を満たさないフィーチャーは落とす
+    /// （TSO は CSUM、MQ/RSS は CTRL_VQ が前提）。
+    pub fn negotiate(offered: u64) -> u64 {
+        let mut accepted = offered & DRIVER_FEATURES;
+        if accepted & VIRTIO_NET_F_CSUM == 0 {
+            accepted &= !VIRTIO_NET_F_HOST_TSO4;
+        }
+        if accepted & VIRTIO_NET_F_CTRL_VQ == 0 {
+            accepted &= !(VIRTIO_NET_F_MQ | VIRTIO_NET_F_RSS);
+        }
+        accepted
+    }
 }
 
+/// デバイス設定空間のオフセット
+mod config_offset {
+    pub const MAC: usize = 0;
+    pub const STATUS: usize = 6;
+    pub const MAX_VIRTQUEUE_PAIRS: usize = 8;
+    pub const MTU: usize = 10;
+    pub const RSS_MAX_KEY_SIZE: usize = 17;
+    pub const RSS_MAX_INDIRECTION_TABLE_LENGTH: usize = 18;
+    pub const SUPPORTED_HASH_TYPES: usize = 20;
+}
+
+/// 制御コマンド
+mod ctrl {
+    pub const CLASS_MQ: u8 = 4;
+    pub const MQ_VQ_PAIRS_SET: u8 = 0;
+    pub const MQ_RSS_CONFIG: u8 = 1;
+}
+
+/// 最大キューペア数（割り込みソース VirtioNet(0..16) に対応）
+pub const MAX_QUEUE_PAIRS: u16 = 16;
+
+/// 1キューあたりの最大ディスクリプタ数
+const MAX_QUEUE_SIZE: u16 = 256;
+
 // ============================================================================
 // VirtIO Net Header
 // ============================================================================
@@ -94,326 +168,147 @@ pub struct VirtioNetHeader {
 
 impl VirtioNetHeader {
     pub const SIZE: usize = core::mem::size_of::<Self>();
+    /// num_buffers を持たないレガシーヘッダの長さ
+    pub const LEGACY_SIZE: usize = 10;
 
     /// 単純な送信用ヘッダを作成
     pub fn new_tx() -> Self {
         Self::default()
     }
-}
 
-// ============================================================================
-// VirtQueue for Network
-// ============================================================================
-
-/// VirtQueue ディスクリプタ
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
-pub struct VringDesc {
-    /// バッファの物理アドレス
-    pub addr: u64,
-    /// バッファの長さ
-    pub len: u32,
-    /// フラグ
-    pub flags: u16,
-    /// 次のディスクリプタのインデックス
-    pub next: u16,
-}
-
-impl VringDesc {
-    /// 書き込み可能フラグ
-    pub const VRING_DESC_F_WRITE: u16 = 2;
-    /// 次のディスクリプタが続くフラグ
-    pub const VRING_DESC_F_NEXT: u16 = 1;
-}
-
-/// VirtQueue Available Ring
-#[repr(C)]
-pub struct VringAvail {
-    pub flags: u16,
-    pub idx: u16,
-    pub ring: [u16; 256],
-}
-
-/// VirtQueue Used Ring Element
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
-pub struct VringUsedElem {
-    pub id: u32,
-    pub len: u32,
-}
+    /// バイト列から読み取り（10バイトなら num_buffers は 0）
+    pub fn from_bytes(buf: &[u8]) -> Self {
+        let u16_at = |offset: usize| {
+            buf.get(offset..offset + 2)
+                .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        Self {
+            flags: buf.first().copied().unwrap_or(0),
+            gso_type: buf.get(1).copied().unwrap_or(0),
+            hdr_len: u16_at(2),
+            gso_size: u16_at(4),
+            csum_start: u16_at(6),
+            csum_offset: u16_at(8),
+            num_buffers: u16_at(10),
+        }
+    }
 
-/// VirtQueue Used Ring
-#[repr(C)]
-pub struct VringUsed {
-    pub flags: u16,
-    pub idx: u16,
-    pub ring: [VringUsedElem; 256],
+    /// バイト列に書き込み（`buf` の長さ分だけ: 10 または 12 バイト）
+    pub fn write_to(&self, buf: &mut [u8]) {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[0] = self.flags;
+        bytes[1] = self.gso_type;
+        bytes[2..4].copy_from_slice(&self.hdr_len.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.gso_size.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.csum_start.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.csum_offset.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.num_buffers.to_le_bytes());
+        let len = buf.len().min(Self::SIZE);
+        buf[..len].copy_from_slice(&bytes[..len]);
+    }
 }
 
 // ============================================================================
-// Send-safe pointer wrapper
+// Queue Pairs
 // ============================================================================
 
-/// 生ポインタをSend可能にするラッパー
+/// キューペアの MSI-X 割り当て
 ///
-/// # Safety
-/// このラッパーを使う側が、ポインタの有効性とスレッド安全性を保証する必要がある
-struct SendPtr<T>(*mut T);
-
-unsafe impl<T> Send for SendPtr<T> {}
-unsafe impl<T> Sync for SendPtr<T> {}
-
-impl<T> SendPtr<T> {
-    fn new(ptr: *mut T) -> Self {
-        Self(ptr)
-    }
-
-    fn as_ptr(&self) -> *mut T {
-        self.0
-    }
-}
-
-impl<T> Clone for SendPtr<T> {
-    fn clone(&self) -> Self {
-        Self(self.0)
-    }
+/// MSI-X テーブル自体（アドレス/データ）は PCI 層が `msix_bindings` から設定する。
+#[derive(Debug, Clone, Copy)]
+pub struct MsixBinding {
+    /// MSI-X テーブルエントリ（0 は設定変更通知用に空けておく）
+    pub entry: u16,
+    /// CPU 割り込みベクタ
+    pub vector: u8,
+    /// 割り込み先 APIC ID
+    pub apic_id: u8,
 }
 
-impl<T> Copy for SendPtr<T> {}
-
-/// ネットワーク VirtQueue
-pub struct NetVirtQueue {
-    /// キューインデックス (0=RX, 1=TX)
+/// 受信・送信キューのペア
+///
+/// ペア k は受信キュー 2k と送信キュー 2k+1 からなり、
+/// コア `core` のエグゼキュータ上のタスクが処理する。
+pub struct QueuePair {
+    /// ペア番号
     pub index: u16,
-    /// キューサイズ
-    pub size: u16,
-    /// ディスクリプタテーブル
-    desc_table: SendPtr<VringDesc>,
-    /// Available Ring
-    avail_ring: SendPtr<VringAvail>,
-    /// Used Ring
-    used_ring: SendPtr<VringUsed>,
-    /// 次の空きディスクリプタ
-    next_free_desc: AtomicU16,
-    /// 最後に処理した Used インデックス
-    last_used_idx: AtomicU16,
-    /// 割り込み待機中のWaker
-    pending_wakers: Mutex<Vec<Waker>>,
-    /// ペンディングバッファの追跡 (desc_id -> callback)
-    pending_buffers: Mutex<Vec<Option<PendingBuffer>>>,
+    /// 処理を担当するコア
+    pub core: u32,
+    /// MSI-X 割り当て
+    pub msix: Option<MsixBinding>,
+    rx: Mutex<RxQueue>,
+    tx: Mutex<TxQueue>,
 }
 
-// NetVirtQueueをSend/Syncにする
-unsafe impl Send for NetVirtQueue {}
-unsafe impl Sync for NetVirtQueue {}
-
-/// ペンディングバッファ情報
-struct PendingBuffer {
-    /// 元のバッファアドレス
-    buffer_addr: usize,
-    /// バッファサイズ
-    buffer_size: usize,
-    /// 完了時のWaker
-    waker: Option<Waker>,
-}
-
-impl NetVirtQueue {
-    /// 新しいVirtQueueを作成
-    ///
-    /// # Safety
-    /// desc_table, avail_ring, used_ring は有効なDMA可能メモリを指している必要がある
-    pub unsafe fn new(
-        index: u16,
-        size: u16,
-        desc_table: *mut VringDesc,
-        avail_ring: *mut VringAvail,
-        used_ring: *mut VringUsed,
-    ) -> Self {
-        // ペンディングバッファ配列を初期化
-        let mut pending = Vec::with_capacity(size as usize);
-        pending.resize_with(size as usize, || None);
-
-        Self {
-            index,
-            size,
-            desc_table: SendPtr::new(desc_table),
-            avail_ring: SendPtr::new(avail_ring),
-            used_ring: SendPtr::new(used_ring),
-            next_free_desc: AtomicU16::new(0),
-            last_used_idx: AtomicU16::new(0),
-            pending_wakers: Mutex::new(Vec::new()),
-            pending_buffers: Mutex::new(pending),
-        }
-    }
-
-    /// ディスクリプタを割り当て
-    fn alloc_desc(&self) -> Option<u16> {
-        let idx = self.next_free_desc.fetch_add(1, Ordering::AcqRel);
-        if idx < self.size {
-            Some(idx)
-        } else {
-            self.next_free_desc.fetch_sub(1, Ordering::AcqRel);
-            None
-        }
-    }
-
-    /// 送信バッファを追加
-    pub fn add_tx_buffer(
-        &self,
-        _header: &VirtioNetHeader,
-        data: &[u8],
-    ) -> Result<u16, VirtioNetError> {
-        let desc_idx = self.alloc_desc().ok_or(VirtioNetError::QueueFull)?;
-
-        unsafe {
-            // ディスクリプタを設定
-            let desc = &mut *self.desc_table.as_ptr().add(desc_idx as usize);
-            desc.addr = data.as_ptr() as u64;
-            desc.len = (VirtioNetHeader::SIZE + data.len()) as u32;
-            desc.flags = 0;
-            desc.next = 0;
-
-            // Available Ringに追加
-            let avail = &mut *self.avail_ring.as_ptr();
-            let avail_idx = avail.idx;
-            avail.ring[(avail_idx % self.size) as usize] = desc_idx;
-
-            // メモリバリア
-            core::sync::atomic::fence(Ordering::Release);
-
-            avail.idx = avail_idx.wrapping_add(1);
-        }
-
-        Ok(desc_idx)
+impl QueuePair {
+    /// 受信キューのインデックス
+    pub fn rx_index(&self) -> u16 {
+        self.index * 2
     }
 
-    /// ゼロコピー送信バッファを追加（設計書 6.2準拠）
-    /// 物理アドレスを直接使用し、メモリコピーを回避
-    pub fn add_tx_buffer_zero_copy(
-        &self,
-        phys_addr: u64,
-        data_len: usize,
-    ) -> Result<u16, VirtioNetError> {
-        let desc_idx = self.alloc_desc().ok_or(VirtioNetError::QueueFull)?;
-
-        unsafe {
-            // ディスクリプタを設定（物理アドレスを直接使用）
-            let desc = &mut *self.desc_table.as_ptr().add(desc_idx as usize);
-            desc.addr = phys_addr;
-            desc.len = (VirtioNetHeader::SIZE + data_len) as u32;
-            desc.flags = 0;
-            desc.next = 0;
-
-            // Available Ringに追加
-            let avail = &mut *self.avail_ring.as_ptr();
-            let avail_idx = avail.idx;
-            avail.ring[(avail_idx % self.size) as usize] = desc_idx;
-
-            // メモリバリア
-            core::sync::atomic::fence(Ordering::Release);
-
-            avail.idx = avail_idx.wrapping_add(1);
-        }
-
-        Ok(desc_idx)
+    /// 送信キューのインデックス
+    pub fn tx_index(&self) -> u16 {
+        self.index * 2 + 1
     }
 
-    /// 受信バッファを追加
-    pub fn add_rx_buffer(&self, buffer: &mut [u8]) -> Result<u16, VirtioNetError> {
-        let desc_idx = self.alloc_desc().ok_or(VirtioNetError::QueueFull)?;
-
-        unsafe {
-            // ディスクリプタを設定（書き込み可能）
-            let desc = &mut *self.desc_table.as_ptr().add(desc_idx as usize);
-            desc.addr = buffer.as_ptr() as u64;
-            desc.len = buffer.len() as u32;
-            desc.flags = VringDesc::VRING_DESC_F_WRITE;
-            desc.next = 0;
-
-            // Available Ringに追加
-            let avail = &mut *self.avail_ring.as_ptr();
-            let avail_idx = avail.idx;
-            avail.ring[(avail_idx % self.size) as usize] = desc_idx;
-
-            core::sync::atomic::fence(Ordering::Release);
-
-            avail.idx = avail_idx.wrapping_add(1);
-        }
-
-        Ok(desc_idx)
+    /// 割り込みソース
+    pub fn interrupt_source(&self) -> InterruptSource {
+        InterruptSource::VirtioNet(self.index as u8)
     }
 
-    /// ゼロコピー受信バッファを追加（設計書 6.2準拠）
-    /// Mempool物理アドレスを直接使用
-    pub fn add_rx_buffer_zero_copy(
-        &self,
-        phys_addr: u64,
-        buffer_len: usize,
-    ) -> Result<u16, VirtioNetError> {
-        let desc_idx = self.alloc_desc().ok_or(VirtioNetError::QueueFull)?;
-
-        unsafe {
-            // ディスクリプタを設定（書き込み可能、物理アドレス直接使用）
-            let desc = &mut *self.desc_table.as_ptr().add(desc_idx as usize);
-            desc.addr = phys_addr;
-            desc.len = buffer_len as u32;
-            desc.flags = VringDesc::VRING_DESC_F_WRITE;
-            desc.next = 0;
-
-            // Available Ringに追加
-            let avail = &mut *self.avail_ring.as_ptr();
-            let avail_idx = avail.idx;
-            avail.ring[(avail_idx % self.size) as usize] = desc_idx;
-
-            core::sync::atomic::fence(Ordering::Release);
-
-            avail.idx = avail_idx.wrapping_add(1);
+    /// 統計スナップショット
+    pub fn stats(&self) -> QueuePairStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let rx = self.rx.lock();
+        let tx = self.tx.lock();
+        QueuePairStats {
+            index: self.index,
+            core: self.core,
+            vector: self.msix.map(|m| m.vector),
+            rx_packets: load(&rx.stats.packets),
+            rx_bytes: load(&rx.stats.bytes),
+            rx_drops: load(&rx.stats.drops),
+            rx_csum_offloaded: load(&rx.stats.csum_offloaded),
+            rx_merged: load(&rx.stats.large_frames),
+            rx_batches: load(&rx.stats.batch.batches_processed),
+            rx_avg_batch_x100: load(&rx.stats.batch.avg_batch_size_x100),
+            rx_max_batch: rx.stats.batch.max_batch_size.load(Ordering::Relaxed),
+            tx_packets: load(&tx.stats.packets),
+            tx_bytes: load(&tx.stats.bytes),
+            tx_drops: load(&tx.stats.drops),
+            tx_csum_offloaded: load(&tx.stats.csum_offloaded),
+            tx_tso_frames: load(&tx.stats.large_frames),
+            tx_gso_segments: load(&tx.stats.gso_segments),
+            tx_queue_full: load(&tx.stats.queue_full),
         }
-
-        Ok(desc_idx)
-    }
-
-    /// 完了したバッファを処理
-    pub fn process_used(&self) -> Vec<(u16, u32)> {
-        let mut completed = Vec::new();
-
-        unsafe {
-            let used = &*self.used_ring.as_ptr();
-            let mut last_idx = self.last_used_idx.load(Ordering::Acquire);
-
-            while last_idx != used.idx {
-                let elem = &used.ring[(last_idx % self.size) as usize];
-                completed.push((elem.id as u16, elem.len));
-                last_idx = last_idx.wrapping_add(1);
-            }
-
-            self.last_used_idx.store(last_idx, Ordering::Release);
-        }
-
-        // 完了したバッファのWakerを起動
-        if !completed.is_empty() {
-            let wakers: Vec<Waker> = self.pending_wakers.lock().drain(..).collect();
-            for waker in wakers {
-                waker.wake();
-            }
-        }
-
-        completed
-    }
-
-    /// Wakerを登録
-    pub fn register_waker(&self, waker: Waker) {
-        self.pending_wakers.lock().push(waker);
     }
+}
 
-    /// ペンディングバッファがあるかチェック
-    pub fn has_pending(&self) -> bool {
-        unsafe {
-            let used = &*self.used_ring.as_ptr();
-            let last_idx = self.last_used_idx.load(Ordering::Acquire);
-            last_idx != used.idx
-        }
-    }
+/// キューペア統計
+#[derive(Debug, Clone, Default)]
+pub struct QueuePairStats {
+    pub index: u16,
+    pub core: u32,
+    pub vector: Option<u8>,
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_drops: u64,
+    pub rx_csum_offloaded: u64,
+    /// 複数バッファにまたがった受信フレーム数
+    pub rx_merged: u64,
+    pub rx_batches: u64,
+    /// 平均バッチサイズ (x100)
+    pub rx_avg_batch_x100: u64,
+    pub rx_max_batch: usize,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_drops: u64,
+    pub tx_csum_offloaded: u64,
+    /// TSO で送ったスーパーフレーム数
+    pub tx_tso_frames: u64,
+    /// ソフトウェア GSO で生成したセグメント数
+    pub tx_gso_segments: u64,
+    pub tx_queue_full: u64,
 }
 
 // ============================================================================
@@ -441,26 +336,35 @@ impl Default for VirtioNetConfig {
     }
 }
 
+/// 受信フレームの配送先（Ethernet フレーム）
+pub type RxHandler = fn(&[u8]);
+
 /// VirtIO ネットワークデバイス
 pub struct VirtioNetDevice {
     /// トランスポート層（MMIO/PCI共通インターフェース）
-    transport: Box<dyn VirtioTransport>,
+    transport: Mutex<Box<dyn VirtioTransport>>,
     /// 設定
     config: VirtioNetConfig,
-    /// 受信キュー
-    rx_queue: Option<NetVirtQueue>,
-    /// 送信キュー
-    tx_queue: Option<NetVirtQueue>,
+    /// ネゴシエーション済みフィーチャー
+    features: u64,
+    /// virtio-net ヘッダ長（10 または 12）
+    hdr_len: usize,
+    /// PCI BDF（MSI-X ベクタ割り当て用。MMIO では None）
+    bdf: Option<u32>,
+    /// キューペア
+    pairs: Vec<QueuePair>,
+    /// 有効なキューペア数（MQ 設定に失敗したら 1）
+    active_pairs: usize,
+    /// 制御キュー
+    ctrl: Option<Mutex<CtrlQueue>>,
+    /// 送信キュー選択用の RSS 設定（デバイス RSS 有効時は同じ設定を渡す）
+    steering: RssConfig,
+    /// デバイス側 RSS が有効か
+    device_rss: bool,
+    /// 受信フレームの配送先
+    rx_handler: Mutex<Option<RxHandler>>,
     /// 初期化済みフラグ
     initialized: AtomicBool,
-    /// 統計: 送信パケット数
-    tx_packets: AtomicU32,
-    /// 統計: 受信パケット数
-    rx_packets: AtomicU32,
-    /// 統計: 送信バイト数
-    tx_bytes: AtomicU32,
-    /// 統計: 受信バイト数
-    rx_bytes: AtomicU32,
 }
 
 impl VirtioNetDevice {
@@ -471,423 +375,490 @@ impl VirtioNetDevice {
     ///   トランスポートはmagic/version検証を通過している必要がある
     pub fn new(transport: Box<dyn VirtioTransport>) -> Self {
         Self {
-            transport,
-            config: VirtioNetConfig {
-                mac: [0x52, 0x54, 0x00, 0x12, 0x34, 0x56],
-                max_queues: 1,
-                mtu: 1500,
-            },
-            rx_queue: None,
-            tx_queue: None,
+            transport: Mutex::new(transport),
+            config: VirtioNetConfig::default(),
+            features: 0,
+            hdr_len: VirtioNetHeader::LEGACY_SIZE,
+            bdf: None,
+            pairs: Vec::new(),
+            active_pairs: 0,
+            ctrl: None,
+            steering: RssConfig::new(1, 1, &rss::DEFAULT_KEY, rss::SUPPORTED_HASH_TYPES),
+            device_rss: false,
+            rx_handler: Mutex::new(None),
             initialized: AtomicBool::new(false),
-            tx_packets: AtomicU32::new(0),
-            rx_packets: AtomicU32::new(0),
-            tx_bytes: AtomicU32::new(0),
-            rx_bytes: AtomicU32::new(0),
         }
     }
 
+    /// PCI デバイスの BDF を設定（MSI-X ベクタ割り当てに使う）
+    pub fn with_pci_bdf(mut self, bdf: u32) -> Self {
+        self.bdf = Some(bdf);
+        self
+    }
+
     /// デバイスを初期化
     pub fn init(&mut self) -> Result<(), VirtioNetError> {
+        let transport = self.transport.get_mut();
+
         // 1. デバイスタイプ確認（トランスポートはすでにmagic/version検証済み）
-        if self.transport.device_type() != VirtioDeviceType::Network {
+        if transport.device_type() != VirtioDeviceType::Network {
             return Err(VirtioNetError::DeviceError);
         }
         
         // 2. デバイスリセット
-        self.transport.reset();
+        transport.reset();
         
         // 3. ACKNOWLEDGE ステータスビットを設定
-        self.transport.set_status(status::VIRTIO_STATUS_ACKNOWLEDGE);
+        transport.set_status(status::VIRTIO_STATUS_ACKNOWLEDGE);
         
         // 4. DRIVER ステータスビットを設定
-        self.transport.set_status(
+        transport.set_status(
             status::VIRTIO_STATUS_ACKNOWLEDGE | status::VIRTIO_STATUS_DRIVER
         );
         
         // 5. Feature negotiation
-        let device_features_low = self.transport.get_device_features_low();
-        let device_features_high = self.transport.get_device_features_high();
-        
-        // 必要なフィーチャーのみを受け入れる
-        let accepted_features_low = device_features_low & 
-            (features::VIRTIO_NET_F_MAC as u32 | features::VIRTIO_NET_F_CSUM as u32);
-        let accepted_features_high = device_features_high;
-        
-        self.transport.set_driver_features_low(accepted_features_low);
-        self.transport.set_driver_features_high(accepted_features_high);
+        let accepted = features::negotiate(transport.get_device_features());
+        transport.set_driver_features(accepted);
         
         // 6. FEATURES_OK を設定
-        self.transport.set_status(
+        transport.set_status(
             status::VIRTIO_STATUS_ACKNOWLEDGE | 
             status::VIRTIO_STATUS_DRIVER | 
             status::VIRTIO_STATUS_FEATURES_OK
         );
         
         // FEATURES_OK が設定されたか確認
-        if (self.transport.get_status() & status::VIRTIO_STATUS_FEATURES_OK) == 0 {
-            self.transport.set_status(status::VIRTIO_STATUS_FAILED);
+        if (transport.get_status() & status::VIRTIO_STATUS_FEATURES_OK) == 0 {
+            transport.set_status(status::VIRTIO_STATUS_FAILED);
             return Err(VirtioNetError::DeviceError);
         }
+        self.features = accepted;
+        self.hdr_len = if self.has_feature(features::VIRTIO_NET_F_MRG_RXBUF | features::VIRTIO_F_VERSION_1) {
+            VirtioNetHeader::SIZE
+        } else {
+            VirtioNetHeader::LEGACY_SIZE
+        };
         
-        // 7. MACアドレスを読み取り
-        if (accepted_features_low & features::VIRTIO_NET_F_MAC as u32) != 0 {
-            self.config.mac = read_mac_address(self.transport.as_ref());
+        // 7. 設定空間を読み取り（MAC / MTU / 最大キューペア数）
+        let transport = self.transport.get_mut();
+        if accepted & features::VIRTIO_NET_F_MAC != 0 {
+            self.config.mac = read_mac_address(transport.as_ref());
+        }
+        if accepted & features::VIRTIO_NET_F_MTU != 0 {
+            self.config.mtu = transport.read_config_u16(config_offset::MTU).max(68);
         }
+        let max_pairs = if accepted & features::VIRTIO_NET_F_MQ != 0 {
+            transport.read_config_u16(config_offset::MAX_VIRTQUEUE_PAIRS).max(1)
+        } else {
+            1
+        };
+        self.config.max_queues = max_pairs;
         
         // 8. キューの設定
-        self.setup_queues()?;
+        let cores = per_core_executor::executor_manager().core_count().max(1);
+        let pairs = max_pairs.min(MAX_QUEUE_PAIRS).min(cores as u16);
+        self.setup_queues(max_pairs, pairs)?;
         
         // 9. DRIVER_OK を設定
-        self.transport.set_status(
+        self.transport.get_mut().set_status(
             status::VIRTIO_STATUS_ACKNOWLEDGE | 
             status::VIRTIO_STATUS_DRIVER | 
             status::VIRTIO_STATUS_FEATURES_OK |
             status::VIRTIO_STATUS_DRIVER_OK
         );
+        for pair in &self.pairs {
+            self.kick(pair.rx_index());
+        }
+
+        // 10. マルチキュー / RSS（制御キューは DRIVER_OK 後に使える）
+        self.configure_multiqueue();
 
         self.initialized.store(true, Ordering::Release);
         Ok(())
     }
-    
-    /// VirtQueueを設定
-    fn setup_queues(&mut self) -> Result<(), VirtioNetError> {
-        // RX queue (queue 0)
-        self.setup_single_queue(0)?;
-        
-        // TX queue (queue 1)
-        self.setup_single_queue(1)?;
-        
+
+    /// キューペアと制御キューを設定
+    ///
+    /// 制御キューのインデックスはデバイスの最大ペア数で決まる（MQ なしなら 2）。
+    fn setup_queues(&mut self, max_pairs: u16, pairs: u16) -> Result<(), VirtioNetError> {
+        let mergeable = self.has_feature(features::VIRTIO_NET_F_MRG_RXBUF);
+        let rx_buffer_size = if mergeable {
+            queue::MRG_RX_BUFFER_SIZE
+        } else {
+            // マージできない場合は1バッファに最大フレームが収まる必要がある
+            (self.hdr_len + 14 + self.config.mtu as usize).max(queue::MRG_RX_BUFFER_SIZE)
+        };
+        let apics: Vec<u8> = crate::io::acpi::local_apics()
+            .iter()
+            .filter(|apic| apic.enabled)
+            .map(|apic| apic.apic_id)
+            .collect();
+
+        for index in 0..pairs {
+            let core = index as u32;
+            let apic_id = apics.get(index as usize).copied().unwrap_or(index as u8);
+            let msix = self.allocate_msix(index, apic_id);
+
+            let rx_ring = self.setup_ring(index * 2, msix.map(|m| m.entry))?;
+            let tx_ring = self.setup_ring(index * 2 + 1, msix.map(|m| m.entry))?;
+            let mut rx = RxQueue::new(rx_ring, self.hdr_len, rx_buffer_size, mergeable);
+            if rx.refill() == 0 {
+                return Err(VirtioNetError::NoMemory);
+            }
+            self.pairs.push(QueuePair {
+                index,
+                core,
+                msix,
+                rx: Mutex::new(rx),
+                tx: Mutex::new(TxQueue::new(tx_ring, self.hdr_len)),
+            });
+        }
+        self.active_pairs = self.pairs.len();
+
+        if self.has_feature(features::VIRTIO_NET_F_CTRL_VQ) {
+            let ctrl_index = if self.has_feature(features::VIRTIO_NET_F_MQ) { max_pairs * 2 } else { 2 };
+            let ring = self.setup_ring(ctrl_index, None)?;
+            self.ctrl = Some(Mutex::new(CtrlQueue::new(ring)));
+        }
         Ok(())
     }
-    
+
+    /// キューペア用の MSI-X ベクタを割り当て
+    fn allocate_msix(&self, index: u16, apic_id: u8) -> Option<MsixBinding> {
+        let bdf = self.bdf?;
+        if !self.transport.lock().supports_msix() {
+            return None;
+        }
+        let allocation = crate::io::interrupt_manager::allocate_msix(bdf, 1, "virtio-net", Some(apic_id))
+            .ok()?
+            .into_iter()
+            .next()?;
+        Some(MsixBinding {
+            entry: index + 1,
+            vector: allocation.vector,
+            apic_id,
+        })
+    }
+
     /// 単一のキューを設定
-    fn setup_single_queue(&mut self, queue_index: u16) -> Result<(), VirtioNetError> {
+    fn setup_ring(&mut self, queue_index: u16, msix_entry: Option<u16>) -> Result<Ring, VirtioNetError> {
+        let transport = self.transport.get_mut();
+
         // キューを選択
-        self.transport.select_queue(queue_index);
+        transport.select_queue(queue_index);
         
         // 最大キューサイズを取得
-        let max_size = self.transport.get_queue_max_size();
+        let max_size = transport.get_queue_max_size();
         if max_size == 0 {
             return Err(VirtioNetError::DeviceError);
         }
         
-        // キューサイズを設定（最大256エントリに制限）
-        let queue_size = max_size.min(256);
-        self.transport.set_queue_size(queue_size);
-        
-        // メモリをアロケート（実際の実装ではDMA対応メモリが必要）
-        // ここでは簡略化のためスキップ
-        // 実際のキュー設定はVirtQueueの初期化と連携が必要
+        // キューサイズを設定（最大256エントリ、2のべき乗に切り下げ）
+        let limited = max_size.min(MAX_QUEUE_SIZE);
+        let queue_size = 1u16 << (15 - limited.leading_zeros());
+        transport.set_queue_size(queue_size);
+
+        let ring = Ring::new(queue_index, queue_size)?;
+        let (desc, avail, used) = ring.addresses();
+        transport.set_queue_desc_addr(desc);
+        transport.set_queue_avail_addr(avail);
+        transport.set_queue_used_addr(used);
+
+        if let Some(entry) = msix_entry {
+            transport
+                .configure_msix(queue_index, entry)
+                .map_err(|_| VirtioNetError::DeviceError)?;
+            transport.select_queue(queue_index);
+        }
         
         // キューを有効化
-        self.transport.enable_queue();
+        transport.enable_queue();
         
-        Ok(())
-    }
-    
-    /// デバイスに通知（キュー更新）
-    pub fn notify(&mut self, queue_index: u16) {
-        self.transport.notify_queue(queue_index);
+        Ok(ring)
     }
 
-    /// パケットを送信（非同期）
-    pub fn send_async(&self, data: &[u8]) -> SendFuture<'_> {
-        SendFuture {
-            device: self,
-            data: data.as_ptr(),
-            len: data.len(),
-            submitted: false,
+    /// マルチキューを有効化
+    ///
+    /// RSS が使えればインダイレクションテーブルとキーを渡し、
+    /// なければ VQ_PAIRS_SET でペア数だけ設定する（デバイスは送信キューに合わせて受信を振り分ける）。
+    /// 失敗したらペア 0 だけを使う。
+    fn configure_multiqueue(&mut self) {
+        let pairs = self.pairs.len() as u16;
+        let transport = self.transport.get_mut();
+        let (table_len, key_len, hash_types) = if self.features & features::VIRTIO_NET_F_RSS != 0 {
+            (
+                transport.read_config_u16(config_offset::RSS_MAX_INDIRECTION_TABLE_LENGTH) as usize,
+                transport.read_config_u8(config_offset::RSS_MAX_KEY_SIZE) as usize,
+                transport.read_config_u32(config_offset::SUPPORTED_HASH_TYPES) & rss::SUPPORTED_HASH_TYPES,
+            )
+        } else {
+            (rss::DEFAULT_TABLE_LEN, rss::DEFAULT_KEY.len(), rss::SUPPORTED_HASH_TYPES)
+        };
+        let table_len = table_len.clamp(1, rss::DEFAULT_TABLE_LEN);
+        let key = &rss::DEFAULT_KEY[..key_len.min(rss::DEFAULT_KEY.len())];
+        self.steering = RssConfig::new(pairs, table_len, key, hash_types);
+
+        if pairs <= 1 && self.features & features::VIRTIO_NET_F_RSS == 0 {
+            return;
         }
-    }
-
-    /// ゼロコピーパケット送信（設計書 6.2準拠）
-    /// 
-    /// PacketRefを直接使用し、コピーなしでDMAバッファに渡す。
-    /// 送信完了まで所有権を保持し、完了後に自動解放される。
-    pub fn send_zero_copy(&self, packet: PacketRef) -> ZeroCopySendFuture<'_> {
-        ZeroCopySendFuture {
-            device: self,
-            packet: Some(packet),
-            submitted: false,
-            desc_idx: 0,
+        let result = if self.features & features::VIRTIO_NET_F_RSS != 0 {
+            self.control(ctrl::CLASS_MQ, ctrl::MQ_RSS_CONFIG, &self.steering.to_bytes())
+                .inspect(|_| self.device_rss = true)
+        } else if self.features & features::VIRTIO_NET_F_MQ != 0 {
+            self.control(ctrl::CLASS_MQ, ctrl::MQ_VQ_PAIRS_SET, &pairs.to_le_bytes())
+        } else {
+            Err(VirtioNetError::DeviceError)
+        };
+        if let Err(e) = result {
+            crate::log!("[virtio-net] multiqueue setup failed ({}), using 1 queue pair\n", e);
+            self.active_pairs = 1;
+            self.steering = RssConfig::new(1, 1, key, hash_types);
         }
     }
 
-    /// パケットを受信（非同期）
-    pub fn recv_async<'a>(&'a self, buffer: &'a mut [u8]) -> RecvFuture<'a> {
-        RecvFuture {
-            device: self,
-            buffer,
-            submitted: false,
-        }
+    /// 制御コマンドを送信
+    fn control(&self, class: u8, cmd: u8, data: &[u8]) -> Result<(), VirtioNetError> {
+        let ctrl = self.ctrl.as_ref().ok_or(VirtioNetError::DeviceError)?;
+        let mut ctrl = ctrl.lock();
+        let index = ctrl.ring().index();
+        ctrl.command(class, cmd, data, || self.kick(index))
     }
 
-    /// ゼロコピーパケット受信（設計書 6.2準拠）
-    /// 
-    /// Mempoolから割り当てられたバッファに直接受信し、
-    /// PacketRefとして返却する。
-    pub fn recv_zero_copy(&self, pool: &'static crate::net::mempool::Mempool) -> ZeroCopyRecvFuture<'_> {
-        ZeroCopyRecvFuture {
-            device: self,
-            pool,
-            packet: None,
-            submitted: false,
-        }
+    /// フィーチャーがネゴシエーション済みか
+    pub fn has_feature(&self, feature: u64) -> bool {
+        self.features & feature != 0
     }
 
-    /// MACアドレスを取得
-    pub fn mac_address(&self) -> [u8; 6] {
-        self.config.mac
+    /// ネゴシエーション済みフィーチャー
+    pub fn features(&self) -> u64 {
+        self.features
     }
 
-    /// 割り込みハンドラ
-    pub fn handle_interrupt(&self) {
-        // RXキューを処理
-        if let Some(ref rx_queue) = self.rx_queue {
-            let completed = rx_queue.process_used();
-            self.rx_packets
-                .fetch_add(completed.len() as u32, Ordering::Relaxed);
+    /// 送信オフロード
+    pub fn tx_offloads(&self) -> TxOffloads {
+        TxOffloads {
+            csum: self.has_feature(features::VIRTIO_NET_F_CSUM),
+            tso4: self.has_feature(features::VIRTIO_NET_F_HOST_TSO4),
         }
+    }
 
-        // TXキューを処理
-        if let Some(ref tx_queue) = self.tx_queue {
-            let completed = tx_queue.process_used();
-            self.tx_packets
-                .fetch_add(completed.len() as u32, Ordering::Relaxed);
-        }
+    /// キューの更新をデバイスに通知（トランスポート経由）
+    pub fn kick(&self, queue_index: u16) {
+        self.transport.lock().notify_queue(queue_index);
+    }
 
-        // HybridIoCoordinator 経由でパケット処理を通知（io_scheduler 統一後）
-        // Note: 旧 polling::net_io_controller() は削除済み
-        // io_scheduler の complete_request はリクエストID単位のため、
-        // ここではwaker通知のみで十分
+    /// デバイスに通知（キュー更新）
+    pub fn notify(&mut self, queue_index: u16) {
+        self.kick(queue_index);
+    }
 
-        // Interrupt-Wakerブリッジに通知（設計書 4.2）
-        // RX/TXで待機中のFutureを起床
-        crate::task::interrupt_waker::wake_from_interrupt(
-            crate::task::interrupt_waker::InterruptSource::VirtioNet(0),
-        );
+    /// 有効なキューペア
+    pub fn queue_pairs(&self) -> &[QueuePair] {
+        &self.pairs[..self.active_pairs]
     }
 
-    /// 統計を取得
-    pub fn stats(&self) -> VirtioNetStats {
-        VirtioNetStats {
-            tx_packets: self.tx_packets.load(Ordering::Relaxed),
-            rx_packets: self.rx_packets.load(Ordering::Relaxed),
-            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
-            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
-        }
+    /// MSI-X 割り当て一覧（PCI 層が MSI-X テーブルを設定するのに使う）
+    pub fn msix_bindings(&self) -> Vec<MsixBinding> {
+        self.pairs.iter().filter_map(|pair| pair.msix).collect()
     }
-}
 
-// ============================================================================
-// Async Futures
-// ============================================================================
+    /// デバイス側 RSS が有効か
+    pub fn rss_enabled(&self) -> bool {
+        self.device_rss
+    }
 
-/// 送信用Future
-pub struct SendFuture<'a> {
-    device: &'a VirtioNetDevice,
-    data: *const u8,
-    len: usize,
-    submitted: bool,
-}
+    /// 受信フレームの配送先を設定
+    pub fn set_rx_handler(&self, handler: RxHandler) {
+        *self.rx_handler.lock() = Some(handler);
+    }
 
-impl<'a> Future for SendFuture<'a> {
-    type Output = Result<usize, VirtioNetError>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if !self.submitted {
-            // 送信をキューに追加
-            if let Some(ref tx_queue) = self.device.tx_queue {
-                let header = VirtioNetHeader::new_tx();
-                let data = unsafe { core::slice::from_raw_parts(self.data, self.len) };
-
-                match tx_queue.add_tx_buffer(&header, data) {
-                    Ok(_) => {
-                        self.submitted = true;
-                        tx_queue.register_waker(cx.waker().clone());
-
-                        // デバイスに通知
-                        // TODO: virtio_notify()
-                    }
-                    Err(e) => return Poll::Ready(Err(e)),
-                }
-            } else {
-                return Poll::Ready(Err(VirtioNetError::NotInitialized));
-            }
+    /// Ethernet フレームを送信
+    ///
+    /// フローハッシュでキューペアを選ぶので、同じフローは受信と同じペアで送られる。
+    /// MTU を超える TCP フレームはデバイス TSO、使えなければソフトウェア GSO で分割する。
+    pub fn transmit(&self, frame: &[u8]) -> Result<(), VirtioNetError> {
+        if !self.initialized.load(Ordering::Acquire) {
+            return Err(VirtioNetError::NotInitialized);
         }
-
-        // 完了を確認
-        if let Some(ref tx_queue) = self.device.tx_queue {
-            if tx_queue.has_pending() {
-                Poll::Ready(Ok(self.len))
-            } else {
-                tx_queue.register_waker(cx.waker().clone());
-                Poll::Pending
-            }
+        let pair = &self.pairs[self.steering.steer(frame) as usize % self.active_pairs];
+        let mtu = self.config.mtu as usize;
+        let offloads = self.tx_offloads();
+        let metrics = crate::net::optimization::metrics();
+
+        let mut tx = pair.tx.lock();
+        let result = if offload::needs_software_gso(frame, mtu, offloads) {
+            let segments = offload::segment_tcp4(frame, mtu).ok_or(VirtioNetError::BufferTooSmall)?;
+            let count = segments.len() as u64;
+            segments
+                .iter()
+                .try_for_each(|seg| Self::submit(&mut tx, seg, mtu, offloads))
+                .inspect(|_| {
+                    tx.stats.gso_segments.fetch_add(count, Ordering::Relaxed);
+                    metrics.tso_segments.fetch_add(count, Ordering::Relaxed);
+                })
         } else {
-            Poll::Ready(Err(VirtioNetError::NotInitialized))
+            Self::submit(&mut tx, frame, mtu, offloads)
+        };
+        if result.is_err() {
+            tx.stats.drops.fetch_add(1, Ordering::Relaxed);
+            metrics.tx_drops.fetch_add(1, Ordering::Relaxed);
+        }
+        let index = pair.tx_index();
+        drop(tx);
+        self.kick(index);
+
+        if result.is_ok() {
+            metrics.tx_packets.fetch_add(1, Ordering::Relaxed);
+            metrics.tx_bytes.fetch_add(frame.len() as u64, Ordering::Relaxed);
         }
+        result
     }
-}
 
-/// 受信用Future
-pub struct RecvFuture<'a> {
-    device: &'a VirtioNetDevice,
-    buffer: &'a mut [u8],
-    submitted: bool,
-}
+    /// 1フレームを送信キューに投入
+    fn submit(tx: &mut TxQueue, frame: &[u8], mtu: usize, offloads: TxOffloads) -> Result<(), VirtioNetError> {
+        let header = tx.submit(frame, |data| offload::prepare_tx(data, mtu, offloads))?;
+        if header.flags & offload::hdr_flags::NEEDS_CSUM != 0 {
+            tx.stats.csum_offloaded.fetch_add(1, Ordering::Relaxed);
+        }
+        if header.gso_type != offload::gso::NONE {
+            tx.stats.large_frames.fetch_add(1, Ordering::Relaxed);
+            let segments = frame.len().div_ceil(ETH_HLEN + header.gso_size.max(1) as usize) as u64;
+            crate::net::optimization::metrics()
+                .tso_segments
+                .fetch_add(segments, Ordering::Relaxed);
+        }
+        Ok(())
+    }
 
-impl<'a> Future for RecvFuture<'a> {
-    type Output = Result<usize, VirtioNetError>;
-
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if !self.submitted {
-            // 受信バッファをキューに追加
-            if let Some(ref rx_queue) = self.device.rx_queue {
-                match rx_queue.add_rx_buffer(self.buffer) {
-                    Ok(_) => {
-                        self.submitted = true;
-                        rx_queue.register_waker(cx.waker().clone());
-                    }
-                    Err(e) => return Poll::Ready(Err(e)),
-                }
-            } else {
-                return Poll::Ready(Err(VirtioNetError::NotInitialized));
-            }
+    /// キューペアを1バッチ分処理
+    ///
+    /// 受信フレームを取り出してバッファを補充し、送信完了を回収する。
+    /// キューのロックを外してから配送するので、配送先から送信してよい。
+    /// 戻り値: 処理した受信フレーム数
+    pub fn poll_pair(&self, index: usize) -> usize {
+        let Some(pair) = self.pairs.get(index) else {
+            return 0;
+        };
+        let (mut frames, refilled) = {
+            let mut rx = pair.rx.lock();
+            let frames = rx.poll_batch(queue::RX_BATCH);
+            (frames, rx.refill() > 0)
+        };
+        if refilled {
+            self.kick(pair.rx_index());
+        }
+        pair.tx.lock().reclaim();
+
+        if frames.is_empty() {
+            return 0;
+        }
+        let metrics = crate::net::optimization::metrics();
+        metrics.batched_packets.fetch_add(frames.len() as u64, Ordering::Relaxed);
+        if let Some(processor) = crate::net::optimization::batch_processor() {
+            processor.stats().record_batch(frames.len());
         }
 
-        // 完了を確認
-        if let Some(ref rx_queue) = self.device.rx_queue {
-            let completed = rx_queue.process_used();
-            if let Some((_, len)) = completed.first() {
-                Poll::Ready(Ok(*len as usize))
-            } else {
-                rx_queue.register_waker(cx.waker().clone());
-                Poll::Pending
+        let handler = *self.rx_handler.lock();
+        let count = frames.len();
+        let (mut drops, mut csum_offloaded) = (0u64, 0u64);
+        for frame in &mut frames {
+            let header = frame.header;
+            if !offload::complete_rx_checksum(&mut frame.data, &header) {
+                drops += 1;
+                metrics.rx_drops.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            if header.flags & (offload::hdr_flags::NEEDS_CSUM | offload::hdr_flags::DATA_VALID) != 0 {
+                csum_offloaded += 1;
+            }
+            metrics.rx_packets.fetch_add(1, Ordering::Relaxed);
+            metrics.rx_bytes.fetch_add(frame.data.len() as u64, Ordering::Relaxed);
+            match handler {
+                Some(handler) => handler(&frame.data),
+                None => drops += 1,
             }
-        } else {
-            Poll::Ready(Err(VirtioNetError::NotInitialized))
         }
+        let rx = pair.rx.lock();
+        rx.stats.drops.fetch_add(drops, Ordering::Relaxed);
+        rx.stats.csum_offloaded.fetch_add(csum_offloaded, Ordering::Relaxed);
+        count
     }
-}
 
-// ============================================================================
-// ゼロコピー送受信 Futures（設計書 6.2）
-// ============================================================================
+    /// キューペアごとのタスクを起動
+    ///
+    /// ペア k のタスクはコア k のエグゼキュータに固定され、
+    /// そのペアの割り込み（MSI-X ベクタ）で起床する。
+    pub fn start(self: &Arc<Self>) {
+        for pair in self.queue_pairs() {
+            let device = Arc::clone(self);
+            let index = pair.index as usize;
+            per_core_executor::spawn_on(pair.core, run_queue_pair(device, index), Priority::High);
+        }
+    }
 
-/// ゼロコピー送信用Future
-/// 
-/// PacketRefの所有権を取得し、DMA転送が完了するまで保持する。
-/// 完了後、PacketRefは自動的にMempoolに返却される。
-pub struct ZeroCopySendFuture<'a> {
-    device: &'a VirtioNetDevice,
-    packet: Option<PacketRef>,
-    submitted: bool,
-    desc_idx: u16,
-}
+    /// MACアドレスを取得
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.config.mac
+    }
 
-impl<'a> Future for ZeroCopySendFuture<'a> {
-    type Output = Result<usize, VirtioNetError>;
+    /// MTU を取得
+    pub fn mtu(&self) -> u16 {
+        self.config.mtu
+    }
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = &mut *self;
-        
-        if !this.submitted {
-            // 送信をキューに追加
-            if let Some(ref tx_queue) = this.device.tx_queue {
-                if let Some(ref packet) = this.packet {
-                    let data = packet.data();
-                    let phys_addr = packet.phys_addr();
-                    
-                    // ゼロコピー: 物理アドレスを直接VirtQueueに渡す
-                    match tx_queue.add_tx_buffer_zero_copy(phys_addr.as_u64(), data.len()) {
-                        Ok(desc_idx) => {
-                            this.submitted = true;
-                            this.desc_idx = desc_idx;
-                            tx_queue.register_waker(cx.waker().clone());
-                        }
-                        Err(e) => return Poll::Ready(Err(e)),
-                    }
-                } else {
-                    return Poll::Ready(Err(VirtioNetError::BufferTooSmall));
-                }
-            } else {
-                return Poll::Ready(Err(VirtioNetError::NotInitialized));
-            }
+    /// リンクが上がっているか（STATUS 非対応なら常に true）
+    pub fn link_up(&self) -> bool {
+        if !self.has_feature(features::VIRTIO_NET_F_STATUS) {
+            return true;
         }
+        self.transport.lock().read_config_u16(config_offset::STATUS) & 1 != 0
+    }
 
-        // 完了を確認
-        if let Some(ref tx_queue) = this.device.tx_queue {
-            if tx_queue.has_pending() {
-                // 送信完了: PacketRefをドロップしてMempoolに返却
-                let packet = this.packet.take();
-                let len = packet.map(|p| p.data().len()).unwrap_or(0);
-                Poll::Ready(Ok(len))
-            } else {
-                tx_queue.register_waker(cx.waker().clone());
-                Poll::Pending
-            }
-        } else {
-            Poll::Ready(Err(VirtioNetError::NotInitialized))
+    /// 割り込みハンドラ（INTx / MMIO: 全キュー共通）
+    pub fn handle_interrupt(&self) {
+        // ISR ステータスの読み取りで割り込みを解除する
+        // （送信中のコアがトランスポートを握っていたら次回に回す）
+        if let Some(mut transport) = self.transport.try_lock() {
+            let isr = transport.get_interrupt_status();
+            transport.ack_interrupt(isr);
+        }
+
+        // Interrupt-Wakerブリッジに通知（設計書 4.2）
+        for pair in self.queue_pairs() {
+            interrupt_waker::wake_from_interrupt(pair.interrupt_source());
         }
     }
-}
 
-/// ゼロコピー受信用Future
-/// 
-/// Mempoolから直接バッファを割り当て、DMAバッファとして使用。
-/// 受信完了後、PacketRefとしてデータを返却する。
-pub struct ZeroCopyRecvFuture<'a> {
-    device: &'a VirtioNetDevice,
-    pool: &'static crate::net::mempool::Mempool,
-    packet: Option<PacketRef>,
-    submitted: bool,
-}
+    /// 統計を取得
+    pub fn stats(&self) -> VirtioNetStats {
+        self.queue_stats().iter().fold(VirtioNetStats::default(), |mut total, q| {
+            total.tx_packets += q.tx_packets;
+            total.rx_packets += q.rx_packets;
+            total.tx_bytes += q.tx_bytes;
+            total.rx_bytes += q.rx_bytes;
+            total
+        })
+    }
 
-impl<'a> Future for ZeroCopyRecvFuture<'a> {
-    type Output = Result<PacketRef, VirtioNetError>;
+    /// キューペアごとの統計
+    pub fn queue_stats(&self) -> Vec<QueuePairStats> {
+        self.queue_pairs().iter().map(QueuePair::stats).collect()
+    }
+}
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = &mut *self;
-        
-        if !this.submitted {
-            // Mempoolからバッファを割り当て
-            let packet = this.pool.alloc().ok_or(VirtioNetError::BufferTooSmall)?;
-            let phys_addr = packet.phys_addr();
-            
-            // 受信バッファをキューに追加
-            if let Some(ref rx_queue) = this.device.rx_queue {
-                match rx_queue.add_rx_buffer_zero_copy(phys_addr.as_u64(), packet.data().len()) {
-                    Ok(_) => {
-                        this.packet = Some(packet);
-                        this.submitted = true;
-                        rx_queue.register_waker(cx.waker().clone());
-                    }
-                    Err(e) => return Poll::Ready(Err(e)),
-                }
-            } else {
-                return Poll::Ready(Err(VirtioNetError::NotInitialized));
-            }
-        }
+const ETH_HLEN: usize = 14;
 
-        // 完了を確認
-        if let Some(ref rx_queue) = this.device.rx_queue {
-            let completed = rx_queue.process_used();
-            if let Some((_, len)) = completed.first() {
-                // 受信完了: データ長を設定してPacketRefを返却
-                if let Some(packet) = this.packet.take() {
-                    packet.set_len(*len as usize);
-                    return Poll::Ready(Ok(packet));
-                }
-                return Poll::Ready(Err(VirtioNetError::BufferTooSmall));
-            } else {
-                rx_queue.register_waker(cx.waker().clone());
-                Poll::Pending
-            }
-        } else {
-            Poll::Ready(Err(VirtioNetError::NotInitialized))
+/// キューペア処理タスク
+///
+/// バッチが満杯なら他のタスクに譲ってから続け、空になったら割り込みを待つ。
+async fn run_queue_pair(device: Arc<VirtioNetDevice>, index: usize) {
+    let source = InterruptSource::VirtioNet(index as u8);
+    loop {
+        if device.poll_pair(index) >= queue::RX_BATCH {
+            crate::task::preemption::yield_now().await;
+            continue;
         }
+        interrupt_waker::wait_for_interrupt(source).await;
     }
 }
 
@@ -904,6 +875,8 @@ pub enum VirtioNetError {
     QueueFull,
     /// バッファが不足
     BufferTooSmall,
+    /// DMAメモリを確保できない
+    NoMemory,
     /// デバイスエラー
     DeviceError,
     /// タイムアウト
@@ -916,6 +889,7 @@ impl core::fmt::Display for VirtioNetError {
             VirtioNetError::NotInitialized => write!(f, "Device not initialized"),
             VirtioNetError::QueueFull => write!(f, "Queue is full"),
             VirtioNetError::BufferTooSmall => write!(f, "Buffer too small"),
+            VirtioNetError::NoMemory => write!(f, "Out of DMA memory"),
             VirtioNetError::DeviceError => write!(f, "Device error"),
             VirtioNetError::Timeout => write!(f, "Operation timed out"),
         }
@@ -926,13 +900,13 @@ impl core::fmt::Display for VirtioNetError {
 // Statistics
 // ============================================================================
 
-/// VirtIO ネットワーク統計
-#[derive(Debug, Clone)]
+/// VirtIO ネットワーク統計（全キューペアの合計）
+#[derive(Debug, Clone, Default)]
 pub struct VirtioNetStats {
-    pub tx_packets: u32,
-    pub rx_packets: u32,
-    pub tx_bytes: u32,
-    pub rx_bytes: u32,
+    pub tx_packets: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
 }
 
 // ============================================================================
@@ -941,7 +915,22 @@ pub struct VirtioNetStats {
 
 use super::transport::VirtioMmioTransport;
 
-static VIRTIO_NET_DEVICE: Mutex<Option<VirtioNetDevice>> = Mutex::new(None);
+static VIRTIO_NET_DEVICE: Mutex<Option<Arc<VirtioNetDevice>>> = Mutex::new(None);
+
+/// 初期化済みデバイスを登録してキューペアのタスクを起動
+fn install(mut device: VirtioNetDevice) -> Result<(), VirtioNetError> {
+    device.init()?;
+    let device = Arc::new(device);
+    device.start();
+    crate::log!(
+        "[virtio-net] {} queue pair(s), features={:#x}, rss={}\n",
+        device.queue_pairs().len(),
+        device.features(),
+        device.rss_enabled()
+    );
+    *VIRTIO_NET_DEVICE.lock() = Some(device);
+    Ok(())
+}
 
 /// VirtIO ネットワークデバイス（MMIO）を初期化
 /// 
@@ -954,27 +943,41 @@ pub fn init_virtio_net(base_addr: usize) -> Result<(), VirtioNetError> {
             .map_err(|_| VirtioNetError::DeviceError)?
     };
     
-    let mut device = VirtioNetDevice::new(Box::new(transport));
-    device.init()?;
-    *VIRTIO_NET_DEVICE.lock() = Some(device);
-    Ok(())
+    install(VirtioNetDevice::new(Box::new(transport)))
+}
+
+/// VirtIO ネットワークデバイス（PCI）を初期化
+///
+/// MSI-X が使えればキューペアごとにベクタを割り当てる。
+pub fn init_virtio_net_pci(bdf: u32, transport: Box<dyn VirtioTransport>) -> Result<(), VirtioNetError> {
+    install(VirtioNetDevice::new(transport).with_pci_bdf(bdf))
 }
 
 /// VirtIO ネットワークデバイスにアクセス
+///
+/// グローバルロックはコールバック前に外す（コールバック内から送信してよい）。
 pub fn with_virtio_net<F, R>(f: F) -> Option<R>
 where
     F: FnOnce(&VirtioNetDevice) -> R,
 {
-    VIRTIO_NET_DEVICE.lock().as_ref().map(f)
+    let device = VIRTIO_NET_DEVICE.lock().clone()?;
+    Some(f(&device))
 }
 
-/// 割り込みハンドラ
+/// 割り込みハンドラ（INTx / MMIO）
 pub fn handle_virtio_net_interrupt() {
-    if let Some(ref device) = *VIRTIO_NET_DEVICE.lock() {
+    let device = VIRTIO_NET_DEVICE.lock().clone();
+    if let Some(device) = device {
         device.handle_interrupt();
     }
 }
 
+/// キューペア単位の割り込みハンドラ（MSI-X）
+pub fn handle_virtio_net_queue_interrupt(pair: u8, vector: u8) {
+    crate::io::interrupt_manager::record_interrupt(vector);
+    interrupt_waker::wake_from_interrupt(InterruptSource::VirtioNet(pair));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -985,6 +988,44 @@ mod tests {
         assert_eq!(header.flags, 0);
         assert_eq!(VirtioNetHeader::SIZE, 12);
     }
+
+    #[test]
+    fn test_header_bytes_roundtrip() {
+        let header = VirtioNetHeader {
+            flags: 1,
+            gso_type: 1,
+            hdr_len: 54,
+            gso_size: 1460,
+            csum_start: 34,
+            csum_offset: 16,
+            num_buffers: 3,
+        };
+        let mut bytes = [0u8; 12];
+        header.write_to(&mut bytes);
+        assert_eq!(&bytes[4..6], &1460u16.to_le_bytes());
+        let parsed = VirtioNetHeader::from_bytes(&bytes);
+        assert_eq!(parsed.gso_size, 1460);
+        assert_eq!(parsed.num_buffers, 3);
+
+        // レガシーヘッダには num_buffers がない
+        let mut legacy = [0u8; VirtioNetHeader::LEGACY_SIZE];
+        header.write_to(&mut legacy);
+        assert_eq!(VirtioNetHeader::from_bytes(&legacy).num_buffers, 0);
+    }
+
+    #[test]
+    fn test_feature_negotiation() {
+        use features::*;
+        let offered = VIRTIO_NET_F_HOST_TSO4 | VIRTIO_NET_F_MQ | VIRTIO_NET_F_GUEST_TSO4 | VIRTIO_NET_F_MAC;
+        // CSUM と CTRL_VQ がないので TSO と MQ は落ちる。LRO は要求しない
+        assert_eq!(negotiate(offered), VIRTIO_NET_F_MAC);
+
+        let offered = offered | VIRTIO_NET_F_CSUM | VIRTIO_NET_F_CTRL_VQ | VIRTIO_NET_F_RSS;
+        let accepted = negotiate(offered);
+        assert_ne!(accepted & VIRTIO_NET_F_HOST_TSO4, 0);
+        assert_ne!(accepted & VIRTIO_NET_F_RSS, 0);
+        assert_eq!(accepted & VIRTIO_NET_F_GUEST_TSO4, 0);
+    }
 }
 
 // ============================================================================
@@ -994,7 +1035,7 @@ mod tests {
 /// VirtIO ネットワーク PollHandler 実装
 pub struct VirtioNetPollHandler {
     /// デバイスへの参照
-    device_lock: &'static Mutex<Option<VirtioNetDevice>>,
+    device_lock: &'static Mutex<Option<Arc<VirtioNetDevice>>>,
     /// 保留中リクエスト (IoRequestId -> buffer_index)
     pending_rx: Mutex<BTreeMap<IoRequestId, u16>>,
     pending_tx: Mutex<BTreeMap<IoRequestId, u16>>,
@@ -1034,8 +1075,8 @@ impl PollHandler for VirtioNetPollHandler {
         let mut results = Vec::new();
         
         if let Some(ref device) = *self.device_lock.lock() {
-            // RX 完了をチェック - rx_queue が存在するか確認
-            if let Some(ref rx_queue) = device.rx_queue {
+            // RX 完了をチェック - キューペア 0 が存在するか確認
+            if let Some(rx_queue) = device.queue_pairs().first() {
                 let mut pending = self.pending_rx.lock();
                 let mut completed = Vec::new();
                 
@@ -1055,7 +1096,7 @@ impl PollHandler for VirtioNetPollHandler {
             }
             
             // TX 完了をチェック
-            if let Some(ref tx_queue) = device.tx_queue {
+            if let Some(tx_queue) = device.queue_pairs().first() {
                 let mut pending = self.pending_tx.lock();
                 let mut completed = Vec::new();
                 
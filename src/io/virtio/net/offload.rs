@@ -0,0 +1,432 @@
+// ============================================================================
+// src/io/virtio/net/offload.rs - Checksum / Segmentation Offload
+// ============================================================================
+//!
+//! virtio-net のチェックサム・セグメンテーションオフロード
+//!
+//! 送信フレームから `VirtioNetHeader` を組み立てる（NEEDS_CSUM / TSO）。
+//! デバイスが TSO を持たない場合は MTU を超える TCP フレームを
+//! ソフトウェア GSO で MSS ごとに分割する。
+//! 受信側ではデバイスが残した部分チェックサムを完成させる。
+
+use alloc::vec::Vec;
+
+use super::VirtioNetHeader;
+
+/// ヘッダフラグ（`VirtioNetHeader::flags`）
+pub mod hdr_flags {
+    /// csum_start からのチェックサム計算が必要
+    pub const NEEDS_CSUM: u8 = 1;
+    /// デバイスがチェックサムを検証済み
+    pub const DATA_VALID: u8 = 2;
+}
+
+/// GSO タイプ（`VirtioNetHeader::gso_type`）
+pub mod gso {
+    pub const NONE: u8 = 0;
+    pub const TCPV4: u8 = 1;
+    pub const UDP: u8 = 3;
+    pub const TCPV6: u8 = 4;
+    /// ECN (CWR) ビット付き
+    pub const ECN: u8 = 0x80;
+}
+
+const ETH_HLEN: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+/// L4 ヘッダ先頭からチェックサム欄までのオフセット
+const TCP_CSUM_OFFSET: usize = 16;
+const UDP_CSUM_OFFSET: usize = 6;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_PSH: u8 = 0x08;
+const TCP_CWR: u8 = 0x80;
+
+/// 送信で使えるオフロード（ネゴシエーション結果）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxOffloads {
+    /// チェックサムオフロード (VIRTIO_NET_F_CSUM)
+    pub csum: bool,
+    /// IPv4 TSO (VIRTIO_NET_F_HOST_TSO4)
+    pub tso4: bool,
+}
+
+/// Ethernet フレーム内の IPv4/L4 の位置
+#[derive(Debug, Clone, Copy)]
+struct L4Info {
+    /// IPv4 ヘッダ長
+    ihl: usize,
+    /// プロトコル番号
+    protocol: u8,
+    /// L4 ヘッダの開始オフセット（フレーム先頭から）
+    l4_start: usize,
+    /// IPv4 パケットの終端（パディングを除く）
+    end: usize,
+}
+
+impl L4Info {
+    /// 非フラグメントの IPv4 フレームを解析
+    fn parse(frame: &[u8]) -> Option<Self> {
+        if frame.len() < ETH_HLEN + 20 {
+            return None;
+        }
+        if u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_IPV4 {
+            return None;
+        }
+        let ip = &frame[ETH_HLEN..];
+        if ip[0] >> 4 != 4 {
+            return None;
+        }
+        let ihl = ((ip[0] & 0x0f) as usize) * 4;
+        let total_len = u16::from_be_bytes([ip[2], ip[3]]) as usize;
+        if ihl < 20 || total_len < ihl || total_len > ip.len() {
+            return None;
+        }
+        // MF フラグまたはフラグメントオフセット付きはオフロード対象外
+        if u16::from_be_bytes([ip[6], ip[7]]) & 0x3fff != 0 {
+            return None;
+        }
+        Some(L4Info {
+            ihl,
+            protocol: ip[9],
+            l4_start: ETH_HLEN + ihl,
+            end: ETH_HLEN + total_len,
+        })
+    }
+
+    fn l4_len(&self) -> usize {
+        self.end - self.l4_start
+    }
+
+    fn csum_offset(&self) -> Option<usize> {
+        match self.protocol {
+            IPPROTO_TCP => Some(TCP_CSUM_OFFSET),
+            IPPROTO_UDP => Some(UDP_CSUM_OFFSET),
+            _ => None,
+        }
+    }
+
+    /// TCP ヘッダ長（データオフセット）
+    fn tcp_header_len(&self, frame: &[u8]) -> Option<usize> {
+        let doff = ((*frame.get(self.l4_start + 12)? >> 4) as usize) * 4;
+        (doff >= 20 && self.l4_start + doff <= self.end).then_some(doff)
+    }
+}
+
+/// 16ビット1の補数和（未折り畳み）
+fn sum16(data: &[u8], mut sum: u32) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+/// 32ビット和を16ビットに折り畳む
+fn fold(mut sum: u32) -> u16 {
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// 疑似ヘッダの和（送信元・宛先・プロトコル・L4長）
+fn pseudo_header_sum(frame: &[u8], info: &L4Info) -> u32 {
+    let addrs = &frame[ETH_HLEN + 12..ETH_HLEN + 20];
+    sum16(addrs, info.protocol as u32 + info.l4_len() as u32)
+}
+
+fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+/// IPv4 ヘッダチェックサムを再計算
+fn fix_ip_checksum(frame: &mut [u8], ihl: usize) {
+    write_u16(frame, ETH_HLEN + 10, 0);
+    let csum = !fold(sum16(&frame[ETH_HLEN..ETH_HLEN + ihl], 0));
+    write_u16(frame, ETH_HLEN + 10, csum);
+}
+
+/// L4 チェックサムをソフトウェアで完全に計算
+fn fix_l4_checksum(frame: &mut [u8], info: &L4Info, csum_offset: usize) {
+    let field = info.l4_start + csum_offset;
+    write_u16(frame, field, 0);
+    let sum = sum16(&frame[info.l4_start..info.end], pseudo_header_sum(frame, info));
+    write_u16(frame, field, !fold(sum));
+}
+
+/// ソフトウェア GSO が必要か
+///
+/// MTU を超える TCP フレームで、デバイス側 TSO が使えない場合に true。
+pub fn needs_software_gso(frame: &[u8], mtu: usize, offloads: TxOffloads) -> bool {
+    frame.len() > ETH_HLEN + mtu && !(offloads.tso4 && offloads.csum)
+}
+
+/// 送信フレーム用のヘッダを準備
+///
+/// チェックサムオフロードが有効なら L4 チェックサム欄を疑似ヘッダ和に書き換え、
+/// NEEDS_CSUM を立てる（残りはデバイスが計算する）。
+/// MTU を超える TCP フレームには TSO の GSO 情報を付ける。
+/// オフロードできないフレームはスタックが計算したチェックサムのまま送る。
+pub fn prepare_tx(frame: &mut [u8], mtu: usize, offloads: TxOffloads) -> VirtioNetHeader {
+    let mut header = VirtioNetHeader::new_tx();
+    if !offloads.csum {
+        return header;
+    }
+    let Some(info) = L4Info::parse(frame) else {
+        return header;
+    };
+    // パディング付きフレームはデバイスがパディングまで和に含めてしまう
+    if info.end != frame.len() {
+        return header;
+    }
+    let Some(csum_offset) = info.csum_offset() else {
+        return header;
+    };
+    let field = info.l4_start + csum_offset;
+    if field + 2 > info.end {
+        return header;
+    }
+    // UDP チェックサム 0 は「チェックサムなし」
+    if info.protocol == IPPROTO_UDP && frame[field] == 0 && frame[field + 1] == 0 {
+        return header;
+    }
+
+    let partial = fold(pseudo_header_sum(frame, &info));
+    write_u16(frame, field, partial);
+    header.flags = hdr_flags::NEEDS_CSUM;
+    header.csum_start = info.l4_start as u16;
+    header.csum_offset = csum_offset as u16;
+
+    if frame.len() > ETH_HLEN + mtu
+        && info.protocol == IPPROTO_TCP
+        && offloads.tso4
+        && let Some(thl) = info.tcp_header_len(frame)
+    {
+        header.gso_type = gso::TCPV4;
+        if frame[info.l4_start + 13] & TCP_CWR != 0 {
+            header.gso_type |= gso::ECN;
+        }
+        header.hdr_len = (info.l4_start + thl) as u16;
+        header.gso_size = mtu.saturating_sub(info.ihl + thl) as u16;
+    }
+    header
+}
+
+/// TCP/IPv4 フレームを MSS ごとに分割（ソフトウェア GSO）
+///
+/// 各セグメントの IP 長・ID・チェックサムと TCP シーケンス番号・チェックサムを直す。
+/// FIN/PSH は最後のセグメント、CWR は最初のセグメントにだけ残す。
+/// TCP/IPv4 でないフレームは None。
+pub fn segment_tcp4(frame: &[u8], mtu: usize) -> Option<Vec<Vec<u8>>> {
+    let info = L4Info::parse(frame)?;
+    if info.protocol != IPPROTO_TCP {
+        return None;
+    }
+    let thl = info.tcp_header_len(frame)?;
+    let hdr_end = info.l4_start + thl;
+    let mss = mtu.checked_sub(info.ihl + thl).filter(|&mss| mss > 0)?;
+    let payload = &frame[hdr_end..info.end];
+    if payload.len() <= mss {
+        return Some(alloc::vec![frame[..info.end].to_vec()]);
+    }
+
+    let ip_id = u16::from_be_bytes([frame[ETH_HLEN + 4], frame[ETH_HLEN + 5]]);
+    let seq = u32::from_be_bytes([
+        frame[info.l4_start + 4],
+        frame[info.l4_start + 5],
+        frame[info.l4_start + 6],
+        frame[info.l4_start + 7],
+    ]);
+    let flags = frame[info.l4_start + 13];
+    let count = payload.len().div_ceil(mss);
+
+    let segments = payload
+        .chunks(mss)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut seg = Vec::with_capacity(hdr_end + chunk.len());
+            seg.extend_from_slice(&frame[..hdr_end]);
+            seg.extend_from_slice(chunk);
+
+            let total_len = (info.ihl + thl + chunk.len()) as u16;
+            write_u16(&mut seg, ETH_HLEN + 2, total_len);
+            write_u16(&mut seg, ETH_HLEN + 4, ip_id.wrapping_add(i as u16));
+            fix_ip_checksum(&mut seg, info.ihl);
+
+            let seg_seq = seq.wrapping_add((i * mss) as u32);
+            seg[info.l4_start + 4..info.l4_start + 8].copy_from_slice(&seg_seq.to_be_bytes());
+            let mut seg_flags = flags;
+            if i + 1 < count {
+                seg_flags &= !(TCP_FIN | TCP_PSH);
+            }
+            if i > 0 {
+                seg_flags &= !TCP_CWR;
+            }
+            seg[info.l4_start + 13] = seg_flags;
+
+            let seg_info = L4Info { end: seg.len(), ..info };
+            fix_l4_checksum(&mut seg, &seg_info, TCP_CSUM_OFFSET);
+            seg
+        })
+        .collect();
+    Some(segments)
+}
+
+/// 受信フレームの部分チェックサムを完成させる
+///
+/// NEEDS_CSUM 付きのフレームは csum_start 以降の和がまだ書き込まれていない
+/// （チェックサム欄には疑似ヘッダ和が入っている）。
+/// 戻り値: フレームが有効なら true（オフセット不正なら false）
+pub fn complete_rx_checksum(frame: &mut [u8], header: &VirtioNetHeader) -> bool {
+    if header.flags & hdr_flags::NEEDS_CSUM == 0 {
+        return true;
+    }
+    let start = header.csum_start as usize;
+    let field = start + header.csum_offset as usize;
+    if start > frame.len() || field + 2 > frame.len() {
+        return false;
+    }
+    let csum = !fold(sum16(&frame[start..], 0));
+    write_u16(frame, field, csum);
+    true
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTU: usize = 1500;
+
+    /// チェックサム計算済みの TCP/IPv4 フレームを作る
+    fn tcp_frame(payload_len: usize, flags: u8) -> Vec<u8> {
+        let total = 20 + 20 + payload_len;
+        let mut frame = alloc::vec![0u8; ETH_HLEN + total];
+        frame[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let ip = ETH_HLEN;
+        frame[ip] = 0x45;
+        write_u16(&mut frame, ip + 2, total as u16);
+        write_u16(&mut frame, ip + 4, 0x1234);
+        frame[ip + 6] = 0x40; // DF
+        frame[ip + 8] = 64;
+        frame[ip + 9] = IPPROTO_TCP;
+        frame[ip + 12..ip + 16].copy_from_slice(&[10, 0, 2, 15]);
+        frame[ip + 16..ip + 20].copy_from_slice(&[10, 0, 2, 2]);
+        let tcp = ip + 20;
+        write_u16(&mut frame, tcp, 40000);
+        write_u16(&mut frame, tcp + 2, 80);
+        frame[tcp + 4..tcp + 8].copy_from_slice(&1000u32.to_be_bytes());
+        frame[tcp + 12] = 5 << 4;
+        frame[tcp + 13] = flags;
+        write_u16(&mut frame, tcp + 14, 65535);
+        for (i, b) in frame[tcp + 20..].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let info = L4Info::parse(&frame).unwrap();
+        fix_ip_checksum(&mut frame, 20);
+        fix_l4_checksum(&mut frame, &info, TCP_CSUM_OFFSET);
+        frame
+    }
+
+    fn checksums_valid(frame: &[u8]) -> bool {
+        let info = L4Info::parse(frame).unwrap();
+        let ip_ok = fold(sum16(&frame[ETH_HLEN..ETH_HLEN + info.ihl], 0)) == 0xffff;
+        let l4 = sum16(&frame[info.l4_start..info.end], pseudo_header_sum(frame, &info));
+        ip_ok && fold(l4) == 0xffff
+    }
+
+    #[test]
+    fn test_csum_offload_roundtrip() {
+        let mut frame = tcp_frame(100, 0x18);
+        let expected = frame.clone();
+        let offloads = TxOffloads { csum: true, tso4: false };
+
+        let header = prepare_tx(&mut frame, MTU, offloads);
+        assert_eq!(header.flags, hdr_flags::NEEDS_CSUM);
+        assert_eq!(header.csum_start, 34);
+        assert_eq!(header.csum_offset, 16);
+        assert_eq!(header.gso_type, gso::NONE);
+        assert_ne!(frame, expected);
+
+        // デバイス側の処理を再現
+        assert!(complete_rx_checksum(&mut frame, &header));
+        assert_eq!(frame, expected);
+    }
+
+    #[test]
+    fn test_no_offload_keeps_frame() {
+        let mut frame = tcp_frame(100, 0x18);
+        let expected = frame.clone();
+        let header = prepare_tx(&mut frame, MTU, TxOffloads::default());
+        assert_eq!(header.flags, 0);
+        assert_eq!(frame, expected);
+    }
+
+    #[test]
+    fn test_tso_header() {
+        let mut frame = tcp_frame(4000, 0x18);
+        let offloads = TxOffloads { csum: true, tso4: true };
+        assert!(!needs_software_gso(&frame, MTU, offloads));
+
+        let header = prepare_tx(&mut frame, MTU, offloads);
+        assert_eq!(header.gso_type, gso::TCPV4);
+        assert_eq!(header.gso_size, 1460);
+        assert_eq!(header.hdr_len, 54);
+        assert_eq!(header.flags, hdr_flags::NEEDS_CSUM);
+    }
+
+    #[test]
+    fn test_software_gso() {
+        let frame = tcp_frame(4000, 0x19); // ACK|PSH|FIN
+        assert!(needs_software_gso(&frame, MTU, TxOffloads { csum: true, tso4: false }));
+
+        let segments = segment_tcp4(&frame, MTU).unwrap();
+        assert_eq!(segments.len(), 3);
+        let mut seq = 1000u32;
+        for (i, seg) in segments.iter().enumerate() {
+            assert!(seg.len() <= ETH_HLEN + MTU);
+            assert!(checksums_valid(seg));
+            let tcp = ETH_HLEN + 20;
+            assert_eq!(u32::from_be_bytes(seg[tcp + 4..tcp + 8].try_into().unwrap()), seq);
+            let last = i + 1 == segments.len();
+            assert_eq!(seg[tcp + 13] & TCP_FIN != 0, last);
+            assert_eq!(seg[tcp + 13] & TCP_PSH != 0, last);
+            assert_eq!(
+                u16::from_be_bytes([seg[ETH_HLEN + 4], seg[ETH_HLEN + 5]]),
+                0x1234 + i as u16
+            );
+            seq += (seg.len() - tcp - 20) as u32;
+        }
+        assert_eq!(seq, 1000 + 4000);
+    }
+
+    #[test]
+    fn test_udp_zero_checksum_untouched() {
+        let mut frame = tcp_frame(20, 0);
+        frame[ETH_HLEN + 9] = IPPROTO_UDP;
+        frame[ETH_HLEN + 20 + 6] = 0;
+        frame[ETH_HLEN + 20 + 7] = 0;
+        let header = prepare_tx(&mut frame, MTU, TxOffloads { csum: true, tso4: true });
+        assert_eq!(header.flags, 0);
+    }
+
+    #[test]
+    fn test_rx_checksum_bad_offset() {
+        let mut frame = tcp_frame(10, 0x10);
+        let header = VirtioNetHeader {
+            flags: hdr_flags::NEEDS_CSUM,
+            csum_start: 60,
+            csum_offset: 16,
+            ..VirtioNetHeader::default()
+        };
+        assert!(!complete_rx_checksum(&mut frame, &header));
+    }
+}
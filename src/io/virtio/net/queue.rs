@@ -0,0 +1,553 @@
+// ============================================================================
+// src/io/virtio/net/queue.rs - VirtIO Net Queues
+// ============================================================================
+//!
+//! virtio-net の受信・送信・制御キュー
+//!
+//! - 受信: 固定長バッファを常に投入しておき、マージ可能バッファ
+//!   (VIRTIO_NET_F_MRG_RXBUF) では `num_buffers` 個をつなげて1フレームにする
+//! - 送信: ヘッダとフレームを1つのDMAバッファに置き、完了時に回収する
+//! - 制御: コマンドを投入して完了をポーリングする
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::super::core::VirtQueue;
+use super::super::defs::{VringAvailHeader, VringDesc, VringUsedHeader};
+use super::{VirtQueueDmaBuffers, VirtioNetError, VirtioNetHeader};
+use crate::io::dma::{CpuOwned, DeviceOwned, TypedDmaSlice};
+use crate::net::optimization::BatchStats;
+
+/// マージ可能バッファ使用時の受信バッファサイズ
+pub const MRG_RX_BUFFER_SIZE: usize = 2048;
+
+/// 1回のポーリングで取り出す最大フレーム数
+pub const RX_BATCH: usize = 64;
+
+/// 制御コマンド完了を待つ最大スピン回数
+const CTRL_TIMEOUT_SPINS: usize = 1_000_000;
+
+/// 制御コマンド成功
+const VIRTIO_NET_OK: u8 = 0;
+
+// ============================================================================
+// Ring
+// ============================================================================
+
+/// DMAメモリ付きの VirtQueue
+///
+/// 通知はトランスポート経由で行うため、VirtQueue の通知アドレスは使わない。
+pub struct Ring {
+    vq: VirtQueue,
+    mem: VirtQueueDmaBuffers,
+}
+
+impl Ring {
+    /// キューメモリを確保して VirtQueue を初期化
+    pub fn new(index: u16, size: u16) -> Result<Self, VirtioNetError> {
+        let mem = VirtQueueDmaBuffers::new(size).ok_or(VirtioNetError::NoMemory)?;
+        // DMA領域は恒等マップ（物理アドレス = 仮想アドレス）
+        let vq = unsafe {
+            VirtQueue::new(
+                index,
+                size,
+                mem.desc_table_addr() as *mut VringDesc,
+                mem.avail_ring_addr() as *mut VringAvailHeader,
+                mem.used_ring_addr() as *mut VringUsedHeader,
+                core::ptr::null_mut(),
+                0,
+            )
+        }
+        .map_err(|_| VirtioNetError::DeviceError)?;
+        Ok(Ring { vq, mem })
+    }
+
+    /// キューインデックス
+    pub fn index(&self) -> u16 {
+        self.vq.queue_index()
+    }
+
+    /// キューサイズ
+    pub fn size(&self) -> u16 {
+        self.vq.queue_size()
+    }
+
+    /// (ディスクリプタテーブル, Available リング, Used リング) の物理アドレス
+    pub fn addresses(&self) -> (u64, u64, u64) {
+        (
+            self.mem.desc_table_addr(),
+            self.mem.avail_ring_addr(),
+            self.mem.used_ring_addr(),
+        )
+    }
+
+    /// デバイスが処理していないバッファ数
+    pub fn pending(&self) -> u16 {
+        self.vq.pending_count()
+    }
+}
+
+// ============================================================================
+// Statistics
+// ============================================================================
+
+/// キュー統計
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    /// パケット数
+    pub packets: AtomicU64,
+    /// バイト数
+    pub bytes: AtomicU64,
+    /// 破棄したパケット数
+    pub drops: AtomicU64,
+    /// チェックサムをオフロードしたパケット数
+    pub csum_offloaded: AtomicU64,
+    /// TSO で送ったスーパーフレーム数（受信側は複数バッファにまたがったフレーム数）
+    pub large_frames: AtomicU64,
+    /// ソフトウェア GSO で生成したセグメント数
+    pub gso_segments: AtomicU64,
+    /// キューが満杯で送れなかった回数
+    pub queue_full: AtomicU64,
+    /// 受信バッチ統計
+    pub batch: BatchStats,
+}
+
+impl QueueStats {
+    fn add(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+// ============================================================================
+// Receive Queue
+// ============================================================================
+
+/// 受信ヘッダを解析
+///
+/// 戻り値: (ヘッダ, このフレームが使うバッファ数)
+pub fn parse_rx_header(buf: &[u8], hdr_len: usize, mergeable: bool) -> Option<(VirtioNetHeader, u16)> {
+    if buf.len() < hdr_len {
+        return None;
+    }
+    let header = VirtioNetHeader::from_bytes(&buf[..hdr_len]);
+    let buffers = if mergeable { header.num_buffers.max(1) } else { 1 };
+    Some((header, buffers))
+}
+
+/// 受信したフレーム
+pub struct RxFrame {
+    /// デバイスが付けたヘッダ
+    pub header: VirtioNetHeader,
+    /// Ethernet フレーム
+    pub data: Vec<u8>,
+}
+
+/// 受信キュー
+pub struct RxQueue {
+    ring: Ring,
+    /// ディスクリプタ番号ごとの投入済みバッファ
+    slots: Vec<Option<TypedDmaSlice<DeviceOwned>>>,
+    buffer_size: usize,
+    hdr_len: usize,
+    mergeable: bool,
+    /// 統計
+    pub stats: QueueStats,
+}
+
+impl RxQueue {
+    /// 受信キューを作成（バッファは `refill` で投入）
+    pub fn new(
+        ring: Ring,
+        hdr_len: usize,
+        buffer_size: usize,
+        mergeable: bool,
+    ) -> Self {
+        let mut slots = Vec::with_capacity(ring.size() as usize);
+        slots.resize_with(ring.size() as usize, || None);
+        RxQueue {
+            ring,
+            slots,
+            buffer_size,
+            hdr_len,
+            mergeable,
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// キューのリング
+    pub fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// 空いているディスクリプタにバッファを投入
+    ///
+    /// 戻り値: 投入したバッファ数（> 0 ならデバイスへの通知が必要）
+    pub fn refill(&mut self) -> usize {
+        let mut added = 0;
+        while self.ring.pending() < self.ring.size() {
+            let Some(buf) = TypedDmaSlice::new(self.buffer_size) else {
+                break;
+            };
+            if !self.post(buf) {
+                break;
+            }
+            added += 1;
+        }
+        added
+    }
+
+    /// バッファを1つ投入
+    fn post(&mut self, buf: TypedDmaSlice<CpuOwned>) -> bool {
+        let addr = buf.phys_addr().as_u64();
+        match unsafe { self.ring.vq.add_buffer_single(addr, buf.len() as u32, true) } {
+            Ok(id) => {
+                self.slots[id as usize] = Some(buf.start_dma());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 完了したバッファを1つ取り出す
+    fn take_used(&mut self) -> Option<(TypedDmaSlice<CpuOwned>, usize)> {
+        let (id, len) = self.ring.vq.poll_used()?;
+        self.ring.vq.free_desc(id);
+        let buf = self.slots.get_mut(id as usize)?.take()?.complete_dma();
+        let len = (len as usize).min(buf.len());
+        Some((buf, len))
+    }
+
+    /// 受信フレームを1つ取り出す
+    ///
+    /// 使い終わったバッファはそのまま再投入する。
+    pub fn poll(&mut self) -> Option<RxFrame> {
+        loop {
+            let (first, len) = self.take_used()?;
+            let Some((header, buffers)) =
+                parse_rx_header(&first.as_slice()[..len], self.hdr_len, self.mergeable)
+            else {
+                self.post(first);
+                QueueStats::add(&self.stats.drops, 1);
+                continue;
+            };
+
+            let mut data = Vec::with_capacity(len - self.hdr_len);
+            data.extend_from_slice(&first.as_slice()[self.hdr_len..len]);
+            self.post(first);
+
+            // デバイスは全バッファを書いてから Used インデックスを進める
+            let mut complete = true;
+            for _ in 1..buffers {
+                match self.take_used() {
+                    Some((buf, len)) => {
+                        data.extend_from_slice(&buf.as_slice()[..len]);
+                        self.post(buf);
+                    }
+                    None => {
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+            if !complete {
+                QueueStats::add(&self.stats.drops, 1);
+                continue;
+            }
+            if buffers > 1 {
+                QueueStats::add(&self.stats.large_frames, 1);
+            }
+            QueueStats::add(&self.stats.packets, 1);
+            QueueStats::add(&self.stats.bytes, data.len() as u64);
+            return Some(RxFrame { header, data });
+        }
+    }
+
+    /// 最大 `max` フレームをまとめて取り出す
+    pub fn poll_batch(&mut self, max: usize) -> Vec<RxFrame> {
+        let mut frames = Vec::new();
+        while frames.len() < max {
+            match self.poll() {
+                Some(frame) => frames.push(frame),
+                None => break,
+            }
+        }
+        if !frames.is_empty() {
+            self.stats.batch.record_batch(frames.len());
+        }
+        frames
+    }
+}
+
+// ============================================================================
+// Transmit Queue
+// ============================================================================
+
+/// 送信キュー
+pub struct TxQueue {
+    ring: Ring,
+    /// 先頭ディスクリプタ番号ごとの送信中バッファ
+    in_flight: Vec<Option<TypedDmaSlice<DeviceOwned>>>,
+    /// 送信中のフレーム数
+    outstanding: usize,
+    hdr_len: usize,
+    /// 統計
+    pub stats: QueueStats,
+}
+
+impl TxQueue {
+    /// 送信キューを作成
+    pub fn new(ring: Ring, hdr_len: usize) -> Self {
+        let mut in_flight = Vec::with_capacity(ring.size() as usize);
+        in_flight.resize_with(ring.size() as usize, || None);
+        TxQueue {
+            ring,
+            in_flight,
+            outstanding: 0,
+            hdr_len,
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// キューのリング
+    pub fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// 送信完了したバッファを回収
+    pub fn reclaim(&mut self) -> usize {
+        let mut count = 0;
+        while let Some((id, _)) = self.ring.vq.poll_used() {
+            self.ring.vq.free_desc_chain(id);
+            if let Some(buf) = self.in_flight.get_mut(id as usize).and_then(Option::take) {
+                drop(buf.complete_dma());
+            }
+            self.outstanding = self.outstanding.saturating_sub(1);
+            count += 1;
+        }
+        count
+    }
+
+    /// フレームを投入（通知は呼び出し側）
+    ///
+    /// フレームを DMA バッファにコピーしてから `prepare` でヘッダを作る
+    /// （チェックサム欄の書き換えはコピー先で行う）。
+    /// ヘッダとフレームは別ディスクリプタに置くので ANY_LAYOUT は不要。
+    pub fn submit(
+        &mut self,
+        frame: &[u8],
+        prepare: impl FnOnce(&mut [u8]) -> VirtioNetHeader,
+    ) -> Result<VirtioNetHeader, VirtioNetError> {
+        // 1フレーム = 2ディスクリプタ
+        if (self.outstanding + 1) * 2 > self.ring.size() as usize {
+            self.reclaim();
+            if (self.outstanding + 1) * 2 > self.ring.size() as usize {
+                QueueStats::add(&self.stats.queue_full, 1);
+                return Err(VirtioNetError::QueueFull);
+            }
+        }
+
+        let hdr_len = self.hdr_len;
+        let mut buf = TypedDmaSlice::new(hdr_len + frame.len()).ok_or(VirtioNetError::NoMemory)?;
+        let (hdr, data) = buf.as_mut_slice().split_at_mut(hdr_len);
+        data.copy_from_slice(frame);
+        let header = prepare(data);
+        header.write_to(hdr);
+
+        let addr = buf.phys_addr().as_u64();
+        let chain = [
+            (addr, hdr_len as u32, false),
+            (addr + hdr_len as u64, frame.len() as u32, false),
+        ];
+        let head = unsafe { self.ring.vq.add_buffer_chain(&chain) }
+            .map_err(|_| VirtioNetError::QueueFull)?;
+        self.in_flight[head as usize] = Some(buf.start_dma());
+        self.outstanding += 1;
+
+        QueueStats::add(&self.stats.packets, 1);
+        QueueStats::add(&self.stats.bytes, frame.len() as u64);
+        Ok(header)
+    }
+}
+
+// ============================================================================
+// Control Queue
+// ============================================================================
+
+/// 制御キュー
+pub struct CtrlQueue {
+    ring: Ring,
+}
+
+impl CtrlQueue {
+    /// 制御キューを作成
+    pub fn new(ring: Ring) -> Self {
+        CtrlQueue { ring }
+    }
+
+    /// キューのリング
+    pub fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// コマンドを実行
+    ///
+    /// `kick` でデバイスに通知し、完了までポーリングする。
+    /// タイムアウトした場合はデバイスがまだ書き込む可能性があるのでバッファを解放しない。
+    pub fn command(
+        &mut self,
+        class: u8,
+        cmd: u8,
+        data: &[u8],
+        kick: impl FnOnce(),
+    ) -> Result<(), VirtioNetError> {
+        let out_len = 2 + data.len();
+        let mut buf = TypedDmaSlice::new(out_len + 1).ok_or(VirtioNetError::NoMemory)?;
+        {
+            let bytes = buf.as_mut_slice();
+            bytes[0] = class;
+            bytes[1] = cmd;
+            bytes[2..out_len].copy_from_slice(data);
+            bytes[out_len] = 0xff;
+        }
+
+        let addr = buf.phys_addr().as_u64();
+        let chain = [
+            (addr, out_len as u32, false),
+            (addr + out_len as u64, 1, true),
+        ];
+        let head = unsafe { self.ring.vq.add_buffer_chain(&chain) }
+            .map_err(|_| VirtioNetError::QueueFull)?;
+        let buf = buf.start_dma();
+        kick();
+
+        for _ in 0..CTRL_TIMEOUT_SPINS {
+            if let Some((id, _)) = self.ring.vq.poll_used() {
+                self.ring.vq.free_desc_chain(id);
+                let buf = buf.complete_dma();
+                debug_assert_eq!(id, head);
+                return if buf.as_slice()[out_len] == VIRTIO_NET_OK {
+                    Ok(())
+                } else {
+                    Err(VirtioNetError::DeviceError)
+                };
+            }
+            core::hint::spin_loop();
+        }
+        core::mem::forget(buf);
+        Err(VirtioNetError::Timeout)
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// デバイス側: Available リングの n 番目に投入されたディスクリプタ
+    fn avail_entry(ring: &Ring, n: u16) -> u16 {
+        let (_, avail, _) = ring.addresses();
+        unsafe { *(avail as *const u16).add(2 + (n % ring.size()) as usize) }
+    }
+
+    /// デバイス側: ディスクリプタにデータを書き込む
+    fn device_write(ring: &Ring, id: u16, data: &[u8]) {
+        let (desc, _, _) = ring.addresses();
+        let desc = unsafe { &*(desc as *const VringDesc).add(id as usize) };
+        assert!(data.len() <= desc.len as usize);
+        unsafe {
+            core::ptr::copy_nonoverlapping(data.as_ptr(), desc.addr as *mut u8, data.len());
+        }
+    }
+
+    /// デバイス側: Used リングに完了を書き込む
+    fn device_complete(ring: &Ring, completions: &[(u16, u32)]) {
+        let (_, _, used) = ring.addresses();
+        unsafe {
+            let header = used as *mut u16;
+            let mut idx = *header.add(1);
+            let elems = (used + 4) as *mut u32;
+            for &(id, len) in completions {
+                let slot = (idx % ring.size()) as usize;
+                *elems.add(slot * 2) = id as u32;
+                *elems.add(slot * 2 + 1) = len;
+                idx = idx.wrapping_add(1);
+            }
+            *header.add(1) = idx;
+        }
+    }
+
+    fn header_bytes(num_buffers: u16) -> [u8; 12] {
+        let mut bytes = [0u8; 12];
+        VirtioNetHeader { num_buffers, ..VirtioNetHeader::default() }.write_to(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_rx_header() {
+        let bytes = header_bytes(3);
+        assert_eq!(parse_rx_header(&bytes, 12, true).unwrap().1, 3);
+        assert_eq!(parse_rx_header(&bytes, 12, false).unwrap().1, 1);
+        assert_eq!(parse_rx_header(&bytes[..10], 10, false).unwrap().1, 1);
+        assert!(parse_rx_header(&bytes[..8], 10, false).is_none());
+    }
+
+    #[test]
+    fn test_rx_mergeable_buffers() {
+        let mut rx = RxQueue::new(Ring::new(0, 8).unwrap(), 12, 64, true);
+        assert_eq!(rx.refill(), 8);
+        assert_eq!(rx.refill(), 0);
+
+        // 12バイトヘッダ + 100バイトのフレームを2バッファに分割
+        let frame: Vec<u8> = (0..100u8).collect();
+        let first = avail_entry(rx.ring(), 0);
+        let second = avail_entry(rx.ring(), 1);
+        let mut chunk = header_bytes(2).to_vec();
+        chunk.extend_from_slice(&frame[..52]);
+        device_write(rx.ring(), first, &chunk);
+        device_write(rx.ring(), second, &frame[52..]);
+        device_complete(rx.ring(), &[(first, 64), (second, 48)]);
+
+        let received = rx.poll_batch(RX_BATCH);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].data, frame);
+        assert_eq!(received[0].header.num_buffers, 2);
+        assert_eq!(rx.stats.large_frames.load(Ordering::Relaxed), 1);
+        assert_eq!(rx.stats.batch.batches_processed.load(Ordering::Relaxed), 1);
+        // 使い終わったバッファは再投入済み
+        assert_eq!(rx.ring().pending(), 8);
+    }
+
+    #[test]
+    fn test_rx_truncated_chain_dropped() {
+        let mut rx = RxQueue::new(Ring::new(0, 4).unwrap(), 12, 64, true);
+        rx.refill();
+        let first = avail_entry(rx.ring(), 0);
+        device_write(rx.ring(), first, &header_bytes(2));
+        device_complete(rx.ring(), &[(first, 12)]);
+        assert!(rx.poll().is_none());
+        assert_eq!(rx.stats.drops.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_tx_submit_and_reclaim() {
+        let mut tx = TxQueue::new(Ring::new(1, 4).unwrap(), 12);
+        let frame = [0xaau8; 60];
+        tx.submit(&frame, |_| VirtioNetHeader::new_tx()).unwrap();
+        tx.submit(&frame, |_| VirtioNetHeader::new_tx()).unwrap();
+        // 4ディスクリプタで2フレームが上限
+        assert_eq!(
+            tx.submit(&frame, |_| VirtioNetHeader::new_tx()).unwrap_err(),
+            VirtioNetError::QueueFull
+        );
+
+        let head = avail_entry(tx.ring(), 0);
+        device_complete(tx.ring(), &[(head, 0)]);
+        assert_eq!(tx.reclaim(), 1);
+        assert!(tx.submit(&frame, |_| VirtioNetHeader::new_tx()).is_ok());
+        assert_eq!(tx.stats.packets.load(Ordering::Relaxed), 3);
+        assert_eq!(tx.stats.queue_full.load(Ordering::Relaxed), 1);
+    }
+}
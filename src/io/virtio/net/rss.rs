@@ -0,0 +1,227 @@
+// ============================================================================
+// src/io/virtio/net/rss.rs - Receive Side Scaling
+// ============================================================================
+//!
+//! RSS（Receive Side Scaling）によるキューペアの振り分け
+//!
+//! Toeplitz ハッシュとインダイレクションテーブルでフローをキューペアに割り当てる。
+//! 同じ設定を `VIRTIO_NET_CTRL_MQ_RSS_CONFIG` でデバイスにも渡すので、
+//! 受信を振り分けるデバイスと送信キューを選ぶドライバで結果が一致する。
+
+use alloc::vec::Vec;
+
+/// Microsoft RSS 仕様の既定キー
+pub const DEFAULT_KEY: [u8; 40] = [
+    0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f,
+    0xb0, 0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30,
+    0xf2, 0x0c, 0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+];
+
+/// 既定のインダイレクションテーブル長
+pub const DEFAULT_TABLE_LEN: usize = 128;
+
+/// ハッシュ対象（`supported_hash_types` / `hash_types`）
+pub mod hash_types {
+    pub const IPV4: u32 = 1 << 0;
+    pub const TCPV4: u32 = 1 << 1;
+    pub const UDPV4: u32 = 1 << 2;
+    pub const IPV6: u32 = 1 << 3;
+    pub const TCPV6: u32 = 1 << 4;
+    pub const UDPV6: u32 = 1 << 5;
+}
+
+/// このドライバがハッシュできる種別
+pub const SUPPORTED_HASH_TYPES: u32 = hash_types::IPV4 | hash_types::TCPV4 | hash_types::UDPV4;
+
+/// Toeplitz ハッシュ
+///
+/// 入力の各ビットが 1 なら、キーの対応位置から始まる 32 ビット窓を XOR する。
+pub fn toeplitz(key: &[u8], input: &[u8]) -> u32 {
+    let key_bit = |n: usize| key.get(n / 8).map_or(0, |b| ((b >> (7 - n % 8)) & 1) as u32);
+    let mut window = (0..32).fold(0u32, |w, n| (w << 1) | key_bit(n));
+    let mut hash = 0u32;
+    for (i, byte) in input.iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                hash ^= window;
+            }
+            window = (window << 1) | key_bit(i * 8 + bit + 32);
+        }
+    }
+    hash
+}
+
+/// Ethernet フレームの RSS ハッシュ入力を取り出す
+///
+/// IPv4 の TCP/UDP は (src, dst, sport, dport)、それ以外の IPv4 は (src, dst)。
+/// フラグメントはポートが読めないので IPv4 ハッシュに落とす。
+fn hash_input(frame: &[u8], types: u32) -> Option<([u8; 12], usize)> {
+    const ETH_HLEN: usize = 14;
+    if frame.len() < ETH_HLEN + 20 || frame[12..14] != [0x08, 0x00] {
+        return None;
+    }
+    let ip = &frame[ETH_HLEN..];
+    let ihl = ((ip[0] & 0x0f) as usize) * 4;
+    if ip[0] >> 4 != 4 || ihl < 20 || ip.len() < ihl {
+        return None;
+    }
+    let mut input = [0u8; 12];
+    input[..8].copy_from_slice(&ip[12..20]);
+
+    let fragment = u16::from_be_bytes([ip[6], ip[7]]) & 0x3fff != 0;
+    let l4_type = match ip[9] {
+        6 => hash_types::TCPV4,
+        17 => hash_types::UDPV4,
+        _ => 0,
+    };
+    if l4_type != 0 && types & l4_type != 0 && !fragment && ip.len() >= ihl + 4 {
+        input[8..12].copy_from_slice(&ip[ihl..ihl + 4]);
+        return Some((input, 12));
+    }
+    (types & hash_types::IPV4 != 0).then_some((input, 8))
+}
+
+/// フレームのフローハッシュ（ハッシュ対象外なら None）
+pub fn flow_hash(key: &[u8], types: u32, frame: &[u8]) -> Option<u32> {
+    let (input, len) = hash_input(frame, types)?;
+    Some(toeplitz(key, &input[..len]))
+}
+
+/// RSS 設定
+#[derive(Debug, Clone)]
+pub struct RssConfig {
+    /// ハッシュ対象
+    pub hash_types: u32,
+    /// ハッシュキー
+    pub key: Vec<u8>,
+    /// インダイレクションテーブル（キューペア番号、長さは2のべき乗）
+    pub table: Vec<u16>,
+    /// 分類できないパケットの受信キューペア
+    pub unclassified_queue: u16,
+    /// 使用する送信キュー数
+    pub max_tx_vq: u16,
+}
+
+impl RssConfig {
+    /// キューペアにラウンドロビンで割り当てたテーブルを作成
+    ///
+    /// `table_len` は2のべき乗に切り下げる。
+    pub fn new(pairs: u16, table_len: usize, key: &[u8], hash_types: u32) -> Self {
+        let pairs = pairs.max(1);
+        let table_len = match table_len {
+            0 => 1,
+            n => 1 << (usize::BITS - 1 - n.leading_zeros()),
+        };
+        RssConfig {
+            hash_types,
+            key: key.to_vec(),
+            table: (0..table_len).map(|i| (i % pairs as usize) as u16).collect(),
+            unclassified_queue: 0,
+            max_tx_vq: pairs,
+        }
+    }
+
+    /// ハッシュ値に対応するキューペア
+    pub fn queue_for_hash(&self, hash: u32) -> u16 {
+        self.table[hash as usize & (self.table.len() - 1)]
+    }
+
+    /// フレームを振り分けるキューペア
+    pub fn steer(&self, frame: &[u8]) -> u16 {
+        flow_hash(&self.key, self.hash_types, frame)
+            .map_or(self.unclassified_queue, |hash| self.queue_for_hash(hash))
+    }
+
+    /// `virtio_net_rss_config` 構造体にシリアライズ（リトルエンディアン）
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + self.table.len() * 2 + 3 + self.key.len());
+        buf.extend_from_slice(&self.hash_types.to_le_bytes());
+        buf.extend_from_slice(&((self.table.len() - 1) as u16).to_le_bytes());
+        buf.extend_from_slice(&self.unclassified_queue.to_le_bytes());
+        for entry in &self.table {
+            buf.extend_from_slice(&entry.to_le_bytes());
+        }
+        buf.extend_from_slice(&self.max_tx_vq.to_le_bytes());
+        buf.push(self.key.len() as u8);
+        buf.extend_from_slice(&self.key);
+        buf
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::endpoint::TcpSegmentBuilder;
+    use crate::net::ethernet::{EtherType, EthernetFrameMut, MacAddress};
+    use crate::net::ipv4::{IpProtocol, Ipv4Address};
+    use crate::net::stack::NetworkStack;
+
+    /// 送信元/宛先アドレスとポートを持つ TCP/IPv4 フレーム
+    fn frame(src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16) -> Vec<u8> {
+        let mut buffer = alloc::vec![0u8; 128];
+        let mut frame = EthernetFrameMut::new(&mut buffer).unwrap();
+        frame
+            .set_destination(MacAddress::BROADCAST)
+            .set_source(MacAddress::ZERO)
+            .set_ether_type(EtherType::Ipv4);
+        let (src_ip, dst_ip) = (Ipv4Address::new(src), Ipv4Address::new(dst));
+        let len = NetworkStack::build_ipv4(frame.payload_mut(), src_ip, dst_ip, IpProtocol::Tcp, 64, |buf| {
+            let mut segment = TcpSegmentBuilder::new(sport, dport).build();
+            TcpSegmentBuilder::calculate_checksum(&mut segment, src, dst);
+            buf.get_mut(..segment.len())?.copy_from_slice(&segment);
+            Some(segment.len())
+        })
+        .unwrap();
+        frame.set_payload_len(len);
+        frame.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_toeplitz_vectors() {
+        // Microsoft RSS 検証用ベクタ
+        let input = [66, 9, 149, 187, 161, 142, 100, 80];
+        assert_eq!(toeplitz(&DEFAULT_KEY, &input), 0x323e8fc2);
+        let mut input = [0u8; 12];
+        input[..8].copy_from_slice(&[66, 9, 149, 187, 161, 142, 100, 80]);
+        input[8..10].copy_from_slice(&2794u16.to_be_bytes());
+        input[10..12].copy_from_slice(&1766u16.to_be_bytes());
+        assert_eq!(toeplitz(&DEFAULT_KEY, &input), 0x51ccc178);
+    }
+
+    #[test]
+    fn test_flow_hash() {
+        let f = frame([199, 92, 111, 2], [65, 69, 140, 83], 14230, 4739);
+        assert_eq!(flow_hash(&DEFAULT_KEY, SUPPORTED_HASH_TYPES, &f), Some(0xc626b0ea));
+        assert_eq!(flow_hash(&DEFAULT_KEY, hash_types::IPV4, &f), Some(0xd718262a));
+        assert_eq!(flow_hash(&DEFAULT_KEY, 0, &f), None);
+    }
+
+    #[test]
+    fn test_indirection_table() {
+        let rss = RssConfig::new(3, 100, &DEFAULT_KEY, SUPPORTED_HASH_TYPES);
+        assert_eq!(rss.table.len(), 64);
+        assert_eq!(&rss.table[..4], &[0, 1, 2, 0]);
+        assert_eq!(rss.queue_for_hash(0x41), rss.table[1]);
+
+        let f = frame([199, 92, 111, 2], [65, 69, 140, 83], 14230, 4739);
+        assert_eq!(rss.steer(&f), rss.queue_for_hash(0xc626b0ea));
+        assert_eq!(rss.steer(&[0u8; 20]), 0);
+    }
+
+    #[test]
+    fn test_rss_config_layout() {
+        let rss = RssConfig::new(2, 4, &DEFAULT_KEY[..8], SUPPORTED_HASH_TYPES);
+        let bytes = rss.to_bytes();
+        assert_eq!(bytes.len(), 4 + 2 + 2 + 4 * 2 + 2 + 1 + 8);
+        assert_eq!(&bytes[..4], &SUPPORTED_HASH_TYPES.to_le_bytes());
+        assert_eq!(&bytes[4..6], &3u16.to_le_bytes());
+        assert_eq!(&bytes[8..16], &[0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(&bytes[16..18], &2u16.to_le_bytes());
+        assert_eq!(bytes[18], 8);
+        assert_eq!(&bytes[19..], &DEFAULT_KEY[..8]);
+    }
+}
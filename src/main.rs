@@ -34,6 +34,7 @@ mod sync;
 mod task;
 mod time;
 mod unwind;
+mod util;
 mod vga;
 
 // Phase 4: High-Performance & Advanced Features
@@ -225,6 +226,11 @@ extern "C" fn kmain() -> ! {
     serial_print("[BOOT] Interrupt system initialized\r\n");
     info!(target: "init", "Interrupt system initialized");
 
+    // 0.5 時間管理の初期化（TSCキャリブレーション、RTCから時刻を取得）
+    serial_print("[BOOT] Initializing time subsystem...\r\n");
+    time::init();
+    info!(target: "init", "Time subsystem initialized (boot time {})", time::system_clock().boot_time());
+
     // 1. メモリ管理の初期化
     serial_print("[BOOT] Initializing memory management...\r\n");
     info!(target: "init", "Initializing memory management");
@@ -331,7 +337,14 @@ extern "C" fn kmain() -> ! {
     info!(target: "init", "Network shell API initialized");
     graphics::update_boot_progress_with_message(50, "Network stack ready");
 
-    // 3.6.1. ネットワークドライバブリッジの初期化
+    // 3.6.1. Intel e1000/e1000e NIC の初期化（存在しない環境もあるため継続）
+    info!(target: "init", "Initializing e1000 NIC");
+    match io::e1000::init() {
+        Ok(()) => info!(target: "init", "e1000 NIC initialized"),
+        Err(e) => warn!(target: "init", "e1000 init skipped: {}", e),
+    }
+
+    // 3.6.2. ネットワークドライバブリッジの初期化
     info!(target: "init", "Initializing network driver bridge");
     if let Err(e) = net::init_driver_bridge() {
         warn!(target: "init", "Network driver bridge failed: {}", e);
@@ -339,6 +352,10 @@ extern "C" fn kmain() -> ! {
         info!(target: "init", "Network driver bridge initialized");
     };
 
+    // 3.6.3. ソケット層（エンドポイントAPI）の初期化
+    net::init_socket_manager();
+    net::init_network_event_handler();
+
     // 3.7. ファイルシステム（memfs）の初期化
     info!(target: "init", "Initializing memory filesystem");
     fs::init_shell_fs();
@@ -546,6 +563,11 @@ fn spawn_kernel_tasks(executor: &mut task::Executor) {
     //     info!(target: "task7", "Integration test task completed");
     // }));
 
+    // ネットワークイベント処理（ソケット要求・ループバック受信）
+    executor.spawn(Task::new(net::network_event_task()));
+    // TCPタイマー（ペーシング・再送タイムアウト）
+    executor.spawn(Task::new(net::tcp_timer_task()));
+
     // タスク8: 非同期シリアルシェル（IRQ4駆動）
     // シリアルシェルはバックグラウンドで維持（シリアル接続用）
     executor.spawn(Task::new(async {
@@ -0,0 +1,730 @@
+// ============================================================================
+// src/monitor/api.rs - Metrics and Control API
+// JSON REST endpoints (and a Prometheus scrape target) on net::http_server
+// ============================================================================
+//
+// GET  /api/v1                      endpoint list
+// GET  /api/v1/snapshot             monitor snapshot + thermal + watchdog
+// GET  /api/v1/tasks                executor statistics and process table
+// GET  /api/v1/domains              all domains
+// GET  /api/v1/domains/:id          one domain
+// POST /api/v1/domains/:id/kill     {"mode": "terminate" | "stop"}
+// GET  /api/v1/net/connections      TCP connections with congestion state
+// GET  /api/v1/net/arp              ARP cache
+// GET  /api/v1/net/dns              unicast DNS and mDNS caches
+// GET  /api/v1/log/level            current log level
+// POST /api/v1/log/level            {"level": "debug"}
+// GET  /api/v1/profiler             profiler report
+// POST /api/v1/profiler/start       {"sample_rate_hz": 1000, "mode": "cpu" | "all"}
+// POST /api/v1/profiler/stop
+// GET  /metrics                     Prometheus text format
+//
+// POST endpoints require `Authorization: Bearer <token>` and are disabled
+// (403) while no token is configured.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use log::LevelFilter;
+use spin::Mutex;
+
+use crate::domain_system::{self, Domain, DomainId};
+use crate::net::http_server::{
+    self, Json, Request, Response, Router, ServerConfig, ServerHandle, Status,
+};
+
+/// Default port (the usual exporter port)
+pub const DEFAULT_PORT: u16 = 9100;
+
+/// Highest accepted profiler sample rate
+pub const MAX_SAMPLE_RATE_HZ: u64 = 100_000;
+
+/// API server configuration
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    /// TCP port
+    pub port: u16,
+    /// Bearer token for control (POST) endpoints; `None` disables them
+    pub token: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            port: DEFAULT_PORT,
+            token: None,
+        }
+    }
+}
+
+/// Running server (port, handle)
+static SERVER: Mutex<Option<(u16, ServerHandle)>> = Mutex::new(None);
+
+/// Token checked by control endpoints
+static TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Start the API server
+pub fn start(config: ApiConfig) -> Result<(), String> {
+    let mut server = SERVER.lock();
+    if let Some((port, handle)) = server.as_ref()
+        && !handle.is_stopped()
+    {
+        return Err(format!("API server already running on port {}", port));
+    }
+
+    *TOKEN.lock() = config.token;
+    let http_config = ServerConfig {
+        port: config.port,
+        ..ServerConfig::default()
+    };
+    let handle = http_server::spawn(router(), http_config)?;
+    *server = Some((config.port, handle));
+    crate::log!("[MONITOR] API listening on port {}\n", config.port);
+    Ok(())
+}
+
+/// Stop the API server (false if it was not running)
+pub fn stop() -> bool {
+    match SERVER.lock().take() {
+        Some((_, handle)) => {
+            handle.stop();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Port of the running API server
+pub fn running_port() -> Option<u16> {
+    SERVER
+        .lock()
+        .as_ref()
+        .filter(|(_, handle)| !handle.is_stopped())
+        .map(|(port, _)| *port)
+}
+
+/// Whether control (POST) endpoints accept requests (a token is configured)
+pub fn control_enabled() -> bool {
+    TOKEN.lock().as_deref().is_some_and(|t| !t.is_empty())
+}
+
+/// Build the API router
+pub fn router() -> Router {
+    Router::new()
+        .get("/api/v1", route_index)
+        .get("/api/v1/snapshot", route_snapshot)
+        .get("/api/v1/tasks", route_tasks)
+        .get("/api/v1/domains", route_domains)
+        .get("/api/v1/domains/:id", route_domain)
+        .post("/api/v1/domains/:id/kill", route_kill_domain)
+        .get("/api/v1/net/connections", route_connections)
+        .get("/api/v1/net/arp", route_arp)
+        .get("/api/v1/net/dns", route_dns)
+        .get("/api/v1/log/level", route_log_level)
+        .post("/api/v1/log/level", route_set_log_level)
+        .get("/api/v1/profiler", route_profiler)
+        .post("/api/v1/profiler/start", route_profiler_start)
+        .post("/api/v1/profiler/stop", route_profiler_stop)
+        .get("/metrics", route_metrics)
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// JSON response with a status
+fn json(status: Status, value: Json) -> Response {
+    Response::bytes(status, "application/json", value.to_string().into_bytes())
+}
+
+/// `{"error": message}`
+fn json_error(status: Status, message: &str) -> Response {
+    json(status, Json::object().with("error", message))
+}
+
+/// Compare without an early exit on the first differing byte
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Check the bearer token and parse the body of a control request
+///
+/// Without a configured token every control request is refused.
+/// An empty body is treated as `{}`.
+fn control_request(req: &Request, token: Option<&str>) -> Result<Json, Response> {
+    let Some(expected) = token.filter(|t| !t.is_empty()) else {
+        return Err(json_error(
+            Status::FORBIDDEN,
+            "control endpoints are disabled: no API token configured",
+        ));
+    };
+    let given = req
+        .header("Authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim);
+    if !given.is_some_and(|given| token_matches(expected, given)) {
+        return Err(json_error(Status::UNAUTHORIZED, "missing or invalid token")
+            .header("WWW-Authenticate", "Bearer"));
+    }
+
+    if req.body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Json::object());
+    }
+    let body = Json::parse(&req.text())
+        .map_err(|e| json_error(Status::BAD_REQUEST, &format!("invalid JSON: {}", e)))?;
+    if !matches!(body, Json::Object(_)) {
+        return Err(json_error(
+            Status::BAD_REQUEST,
+            "body must be a JSON object",
+        ));
+    }
+    Ok(body)
+}
+
+/// `control_request` with the configured token
+fn authorize(req: &Request) -> Result<Json, Response> {
+    let token = TOKEN.lock().clone();
+    control_request(req, token.as_deref())
+}
+
+/// Lowercase `Debug` name of a state enum
+fn state_name(state: impl core::fmt::Debug) -> String {
+    format!("{:?}", state).to_lowercase()
+}
+
+/// Parse a log level name ("off", "error" ... "trace", any case)
+fn parse_level(name: &str) -> Option<LevelFilter> {
+    name.trim().parse().ok()
+}
+
+fn level_name(level: LevelFilter) -> String {
+    level.to_string().to_lowercase()
+}
+
+// ============================================================================
+// System
+// ============================================================================
+
+async fn route_index(_req: Request) -> Response {
+    let endpoints: Vec<&str> = alloc::vec![
+        "GET /api/v1/snapshot",
+        "GET /api/v1/tasks",
+        "GET /api/v1/domains",
+        "GET /api/v1/domains/:id",
+        "POST /api/v1/domains/:id/kill",
+        "GET /api/v1/net/connections",
+        "GET /api/v1/net/arp",
+        "GET /api/v1/net/dns",
+        "GET /api/v1/log/level",
+        "POST /api/v1/log/level",
+        "GET /api/v1/profiler",
+        "POST /api/v1/profiler/start",
+        "POST /api/v1/profiler/stop",
+        "GET /metrics",
+    ];
+    json(
+        Status::OK,
+        Json::object()
+            .with("version", 1u32)
+            .with("endpoints", endpoints),
+    )
+}
+
+async fn route_snapshot(_req: Request) -> Response {
+    let snap = super::snapshot();
+
+    let memory = Json::object()
+        .with("heap_used", snap.memory.heap_used)
+        .with("heap_free", snap.memory.heap_free)
+        .with("heap_total", snap.memory.heap_total)
+        .with("usage_percent", snap.memory.usage_percent);
+    let domains = Json::object()
+        .with("total", snap.domains.total)
+        .with("running", snap.domains.running)
+        .with("stopped", snap.domains.stopped);
+    let tasks = Json::object()
+        .with("voluntary_yields", snap.tasks.voluntary_yields)
+        .with("forced_preemptions", snap.tasks.forced_preemptions);
+    let network = Json::object()
+        .with("rx_packets", snap.network.rx_packets)
+        .with("tx_packets", snap.network.tx_packets)
+        .with("rx_bytes", snap.network.rx_bytes)
+        .with("tx_bytes", snap.network.tx_bytes)
+        .with("rx_errors", snap.network.rx_errors);
+
+    let tm = crate::thermal::thermal_manager();
+    let (polling_count, trip_events) = tm.stats();
+    let throttle = tm.throttle_controller();
+    let thermal = Json::object()
+        .with(
+            "cpu_celsius",
+            crate::thermal::cpu_temperature().map(|t| t.millicelsius() as f64 / 1000.0),
+        )
+        .with("polling_count", polling_count)
+        .with("trip_events", trip_events)
+        .with("throttle_policy", state_name(throttle.current_policy()))
+        .with("throttle_count", throttle.throttle_count());
+
+    let wm = crate::watchdog::watchdog_manager();
+    let (heartbeats, timeouts, checks) = wm.software().stats();
+    let watchdog = Json::object()
+        .with("heartbeats", heartbeats)
+        .with("timeouts", timeouts)
+        .with("checks", checks)
+        .with(
+            "deadlocks_detected",
+            wm.deadlock_detector().deadlocks_detected(),
+        );
+
+    json(
+        Status::OK,
+        Json::object()
+            .with("timestamp", snap.timestamp)
+            .with("uptime_ms", crate::time::current_tick())
+            .with("cpu_usage", snap.cpu_usage)
+            .with("memory", memory)
+            .with("domains", domains)
+            .with("tasks", tasks)
+            .with("network", network)
+            .with("thermal", thermal)
+            .with("watchdog", watchdog),
+    )
+}
+
+async fn route_tasks(_req: Request) -> Response {
+    let executor = crate::task::get_executor_stats();
+    let preempt = crate::task::preemption_controller().stats();
+    let manager = crate::task::process_manager();
+
+    let processes: Vec<Json> = manager
+        .list()
+        .into_iter()
+        .filter_map(|pid| manager.get(pid))
+        .map(|info| {
+            let info = info.read();
+            let children: Vec<u64> = info.children().iter().map(|c| c.as_u64()).collect();
+            Json::object()
+                .with("pid", info.pid.as_u64())
+                .with("ppid", info.ppid.as_u64())
+                .with("name", info.name.as_str())
+                .with("state", state_name(info.state))
+                .with("cwd", info.cwd.as_str())
+                .with("threads", info.threads().len())
+                .with("children", children)
+        })
+        .collect();
+
+    json(
+        Status::OK,
+        Json::object()
+            .with(
+                "executor",
+                Json::object()
+                    .with("tasks_spawned", executor.tasks_spawned)
+                    .with("tasks_completed", executor.tasks_completed)
+                    .with("wakeups", executor.wakeups)
+                    .with("poll_cycles", executor.poll_cycles)
+                    .with("idle_cycles", executor.idle_cycles)
+                    .with("steals", executor.steals),
+            )
+            .with(
+                "preemption",
+                Json::object()
+                    .with("enabled", preempt.enabled)
+                    .with("time_slice", preempt.current_time_slice)
+                    .with("voluntary_yields", preempt.voluntary_yields)
+                    .with("forced_preemptions", preempt.forced_preemptions),
+            )
+            .with("processes", processes),
+    )
+}
+
+// ============================================================================
+// Domains
+// ============================================================================
+
+fn domain_json(domain: &Domain) -> Json {
+    let dependencies: Vec<u64> = domain.dependencies.iter().map(|d| d.as_u64()).collect();
+    Json::object()
+        .with("id", domain.id.as_u64())
+        .with("name", domain.name.as_str())
+        .with("state", state_name(domain.state))
+        .with("tasks", domain.tasks.len())
+        .with("rrefs", domain.rref_count)
+        .with("memory_bytes", domain.allocated_memory)
+        .with("runtime_ticks", domain.runtime_ticks)
+        .with("context_switches", domain.context_switches)
+        .with("created_at", domain.created_at)
+        .with("dependencies", dependencies)
+        .with("panic_message", domain.panic_message.clone())
+        .with("last_error", domain.last_error.clone())
+}
+
+/// Domain id from the `:id` path parameter
+fn domain_param(req: &Request) -> Result<DomainId, Response> {
+    req.param("id")
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(DomainId::new)
+        .ok_or_else(|| json_error(Status::BAD_REQUEST, "domain id must be a number"))
+}
+
+async fn route_domains(_req: Request) -> Response {
+    let domains: Vec<Json> = domain_system::domain_ids()
+        .into_iter()
+        .filter_map(|id| domain_system::with_domain(id, domain_json))
+        .collect();
+    json(Status::OK, Json::Array(domains))
+}
+
+async fn route_domain(req: Request) -> Response {
+    let id = match domain_param(&req) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    match domain_system::with_domain(id, domain_json) {
+        Some(domain) => json(Status::OK, domain),
+        None => json_error(Status::NOT_FOUND, "domain not found"),
+    }
+}
+
+async fn route_kill_domain(req: Request) -> Response {
+    let body = match authorize(&req) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let id = match domain_param(&req) {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let result = match body
+        .get("mode")
+        .and_then(Json::as_str)
+        .unwrap_or("terminate")
+    {
+        "terminate" => domain_system::terminate_domain(id),
+        "stop" => domain_system::stop_domain(id),
+        _ => {
+            return json_error(
+                Status::BAD_REQUEST,
+                "mode must be \"terminate\" or \"stop\"",
+            );
+        }
+    };
+    match result {
+        Ok(()) => match domain_system::with_domain(id, domain_json) {
+            Some(domain) => json(Status::OK, domain),
+            None => json(Status::OK, Json::object().with("id", id.as_u64())),
+        },
+        Err(e) if id == DomainId::KERNEL => json_error(Status::FORBIDDEN, e),
+        Err(e) => json_error(Status::NOT_FOUND, e),
+    }
+}
+
+// ============================================================================
+// Network
+// ============================================================================
+
+async fn route_connections(_req: Request) -> Response {
+    let connections: Vec<Json> = crate::net::get_tcp_connections()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|conn| {
+            let s = &conn.stats;
+            Json::object()
+                .with("local", conn.local_addr)
+                .with("remote", conn.remote_addr)
+                .with("state", conn.state)
+                .with("congestion_algorithm", s.congestion_algorithm)
+                .with("cwnd", s.cwnd)
+                .with("ssthresh", s.ssthresh)
+                .with("bytes_in_flight", s.bytes_in_flight)
+                .with("pacing_rate", s.pacing_rate)
+                .with("rtt_us", s.rtt_us)
+                .with("srtt_us", s.srtt_us)
+                .with("min_rtt_us", s.min_rtt_us)
+                .with("bytes_sent", s.bytes_sent)
+                .with("bytes_received", s.bytes_received)
+                .with("retransmissions", s.retransmissions)
+                .with("paws_rejected", s.paws_rejected)
+        })
+        .collect();
+    json(Status::OK, Json::Array(connections))
+}
+
+async fn route_arp(_req: Request) -> Response {
+    let entries: Vec<Json> = crate::net::get_arp_cache()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| {
+            let [a, b, c, d] = entry.ip;
+            let m = entry.mac;
+            Json::object()
+                .with("ip", format!("{}.{}.{}.{}", a, b, c, d))
+                .with(
+                    "mac",
+                    format!(
+                        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                        m[0], m[1], m[2], m[3], m[4], m[5]
+                    ),
+                )
+                .with("complete", entry.complete)
+        })
+        .collect();
+    json(Status::OK, Json::Array(entries))
+}
+
+async fn route_dns(_req: Request) -> Response {
+    use crate::net::dns::DnsRecordData;
+
+    let now = crate::time::current_tick();
+    let unicast: Vec<Json> = crate::net::dns::cache_entries(now)
+        .into_iter()
+        .map(|(name, ttl, records)| {
+            let records: Vec<Json> = records
+                .iter()
+                .map(|record| {
+                    let data = match &record.data {
+                        DnsRecordData::A(ip) => ip.to_string(),
+                        DnsRecordData::Name(name) => name.clone(),
+                        DnsRecordData::MX(preference, name) => format!("{} {}", preference, name),
+                        DnsRecordData::TXT(text) => text.clone(),
+                        DnsRecordData::Raw(raw) => format!("{} bytes", raw.len()),
+                    };
+                    Json::object()
+                        .with("type", format!("{:?}", record.rtype))
+                        .with("data", data)
+                })
+                .collect();
+            Json::object()
+                .with("name", name)
+                .with("ttl", ttl)
+                .with("records", records)
+        })
+        .collect();
+
+    let mdns: Vec<Json> = crate::net::mdns::with_responder(|responder| {
+        responder
+            .cache()
+            .iter()
+            .filter(|cached| cached.expires_ms > now)
+            .map(|cached| {
+                Json::object()
+                    .with("name", cached.record.name.as_str())
+                    .with("type", cached.record.rtype())
+                    .with("ttl", (cached.expires_ms - now) / 1000)
+                    .with("data", cached.record.data.to_string())
+            })
+            .collect()
+    });
+
+    json(
+        Status::OK,
+        Json::object().with("dns", unicast).with("mdns", mdns),
+    )
+}
+
+// ============================================================================
+// Log level
+// ============================================================================
+
+async fn route_log_level(_req: Request) -> Response {
+    let level = level_name(crate::io::log::current_log_level());
+    json(Status::OK, Json::object().with("level", level))
+}
+
+async fn route_set_log_level(req: Request) -> Response {
+    let body = match authorize(&req) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let Some(level) = body
+        .get("level")
+        .and_then(Json::as_str)
+        .and_then(parse_level)
+    else {
+        return json_error(
+            Status::BAD_REQUEST,
+            "level must be one of off, error, warn, info, debug, trace",
+        );
+    };
+    crate::io::log::set_log_level(level);
+    json(Status::OK, Json::object().with("level", level_name(level)))
+}
+
+// ============================================================================
+// Profiler
+// ============================================================================
+
+fn profiler_status() -> Json {
+    let cpu = crate::profiler::profiler().cpu.stats();
+    Json::object()
+        .with("running", crate::profiler::profiler().cpu.is_enabled())
+        .with("sample_rate_hz", cpu.sample_rate_hz)
+        .with("total_samples", cpu.total_samples)
+        .with("dropped_samples", cpu.dropped_samples)
+        .with("unique_locations", cpu.unique_locations)
+}
+
+async fn route_profiler(_req: Request) -> Response {
+    let report = crate::profiler::report();
+    let memory = &report.memory_stats;
+    let hot_spots: Vec<Json> = report
+        .hot_spots
+        .iter()
+        .map(|(address, hits)| {
+            Json::object()
+                .with("address", format!("{:#x}", address))
+                .with("hits", *hits)
+        })
+        .collect();
+    let latency = Json::Object(
+        report
+            .latency_stats
+            .iter()
+            .map(|(name, h)| {
+                let stats = Json::object()
+                    .with("count", h.count)
+                    .with("min", h.min)
+                    .with("max", h.max)
+                    .with("mean", h.mean)
+                    .with("p50", h.p50)
+                    .with("p95", h.p95)
+                    .with("p99", h.p99);
+                (name.clone(), stats)
+            })
+            .collect(),
+    );
+
+    json(
+        Status::OK,
+        Json::object()
+            .with("cpu", profiler_status())
+            .with(
+                "memory",
+                Json::object()
+                    .with("total_allocated", memory.total_allocated)
+                    .with("total_freed", memory.total_freed)
+                    .with("current_allocated", memory.current_allocated)
+                    .with("peak_allocated", memory.peak_allocated)
+                    .with("alloc_count", memory.alloc_count)
+                    .with("free_count", memory.free_count),
+            )
+            .with("latency_ns", latency)
+            .with("hot_spots", hot_spots),
+    )
+}
+
+async fn route_profiler_start(req: Request) -> Response {
+    let body = match authorize(&req) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let rate = match body.get("sample_rate_hz") {
+        None => 1000,
+        Some(value) => match value.as_u64() {
+            Some(rate) if (1..=MAX_SAMPLE_RATE_HZ).contains(&rate) => rate,
+            _ => {
+                return json_error(
+                    Status::BAD_REQUEST,
+                    &format!("sample_rate_hz must be 1..={}", MAX_SAMPLE_RATE_HZ),
+                );
+            }
+        },
+    };
+    match body.get("mode").and_then(Json::as_str).unwrap_or("cpu") {
+        "cpu" => crate::profiler::start_cpu_profiling(rate),
+        "all" => crate::profiler::start_all(rate),
+        _ => return json_error(Status::BAD_REQUEST, "mode must be \"cpu\" or \"all\""),
+    }
+    json(Status::OK, profiler_status())
+}
+
+async fn route_profiler_stop(req: Request) -> Response {
+    if let Err(response) = authorize(&req) {
+        return response;
+    }
+    crate::profiler::stop_all();
+    json(Status::OK, profiler_status())
+}
+
+// ============================================================================
+// Prometheus
+// ============================================================================
+
+async fn route_metrics(_req: Request) -> Response {
+    Response::bytes(
+        Status::OK,
+        super::prometheus::CONTENT_TYPE,
+        super::prometheus::render().into_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::http_server::Method;
+
+    fn post(body: &str, authorization: Option<&str>) -> Request {
+        let mut headers = alloc::vec![(String::from("Host"), String::from("rig"))];
+        if let Some(value) = authorization {
+            headers.push((String::from("Authorization"), String::from(value)));
+        }
+        Request {
+            method: Method::Post,
+            path: String::from("/api/v1/log/level"),
+            query: Vec::new(),
+            http11: true,
+            headers,
+            body: body.as_bytes().to_vec(),
+            params: Vec::new(),
+            remote: None,
+        }
+    }
+
+    #[test]
+    fn test_control_request() {
+        const AUTH: Option<&str> = Some("Bearer s3cret");
+        const TOKEN: Option<&str> = Some("s3cret");
+
+        let body = control_request(&post(r#"{"level":"debug"}"#, AUTH), TOKEN).unwrap();
+        assert_eq!(body.get("level").and_then(Json::as_str), Some("debug"));
+        assert_eq!(
+            control_request(&post("", AUTH), TOKEN).unwrap(),
+            Json::object()
+        );
+
+        let err = control_request(&post("[1]", AUTH), TOKEN).unwrap_err();
+        assert_eq!(err.status, Status::BAD_REQUEST);
+        let err = control_request(&post("{", AUTH), TOKEN).unwrap_err();
+        assert_eq!(err.status, Status::BAD_REQUEST);
+
+        // Control requests are refused while no token is configured
+        let err = control_request(&post("", None), None).unwrap_err();
+        assert_eq!(err.status, Status::FORBIDDEN);
+        let err = control_request(&post("", AUTH), Some("")).unwrap_err();
+        assert_eq!(err.status, Status::FORBIDDEN);
+
+        let err = control_request(&post("", None), TOKEN).unwrap_err();
+        assert_eq!(err.status, Status::UNAUTHORIZED);
+        let err = control_request(&post("", Some("Bearer nope")), Some("s3cret")).unwrap_err();
+        assert_eq!(err.status, Status::UNAUTHORIZED);
+        assert!(control_request(&post("", AUTH), TOKEN).is_ok());
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level(" WARN "), Some(LevelFilter::Warn));
+        assert_eq!(parse_level("off"), Some(LevelFilter::Off));
+        assert_eq!(parse_level("loud"), None);
+        assert_eq!(level_name(LevelFilter::Trace), "trace");
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abc", "abd"));
+        assert!(!token_matches("abc", "ab"));
+    }
+}
@@ -10,6 +10,8 @@
 // Note: Individual modules disabled until API stabilization
 // pub mod display;
 // pub mod collectors;
+pub mod api;
+pub mod prometheus;
 
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -193,7 +195,17 @@ fn estimate_cpu_usage() -> u8 {
 /// Collect network statistics
 fn collect_network_stats() -> NetworkStats {
     // Collect from network stack if available
-    NetworkStats::default()
+    match crate::net::get_network_stats() {
+        Some(stats) => NetworkStats {
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            rx_errors: stats.rx_errors,
+            tx_errors: 0,
+        },
+        None => NetworkStats::default(),
+    }
 }
 
 /// Print snapshot to console
@@ -0,0 +1,465 @@
+// ============================================================================
+// src/monitor/prometheus.rs - Prometheus Text Exposition
+// Renders kernel metrics in the Prometheus text format (version 0.0.4)
+// ============================================================================
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Write};
+use core::sync::atomic::Ordering;
+
+/// Prefix of every metric name
+pub const METRIC_PREFIX: &str = "exorust_";
+
+/// Content-Type of the exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metric family type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    /// Monotonically increasing value
+    Counter,
+    /// Value that can go up and down
+    Gauge,
+}
+
+impl MetricType {
+    fn as_str(self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+        }
+    }
+}
+
+/// Builds an exposition document family by family
+pub struct MetricsWriter {
+    out: String,
+    /// Full name of the family samples are written to
+    family: String,
+}
+
+impl MetricsWriter {
+    pub fn new() -> Self {
+        MetricsWriter {
+            out: String::new(),
+            family: String::new(),
+        }
+    }
+
+    /// Start a family: writes `# HELP` and `# TYPE` once
+    pub fn family(&mut self, name: &str, kind: MetricType, help: &str) -> &mut Self {
+        self.family = format!("{}{}", METRIC_PREFIX, name);
+        let _ = writeln!(self.out, "# HELP {} {}", self.family, escape_help(help));
+        let _ = writeln!(self.out, "# TYPE {} {}", self.family, kind.as_str());
+        self
+    }
+
+    /// Add a sample to the current family
+    pub fn sample(&mut self, labels: &[(&str, &str)], value: impl Display) -> &mut Self {
+        self.out.push_str(&self.family);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (name, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{}=\"{}\"", name, escape_label(value));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {}", value);
+        self
+    }
+
+    /// Single unlabelled counter
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.family(name, MetricType::Counter, help)
+            .sample(&[], value)
+    }
+
+    /// Single unlabelled gauge
+    pub fn gauge(&mut self, name: &str, help: &str, value: impl Display) -> &mut Self {
+        self.family(name, MetricType::Gauge, help)
+            .sample(&[], value)
+    }
+
+    /// Finished document
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+impl Default for MetricsWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Escape a label value (`\`, `"` and newlines)
+fn escape_label(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape a HELP string (`\` and newlines)
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Lowercase `Debug` name of a state enum, used as a label value
+fn state_label(state: impl core::fmt::Debug) -> String {
+    format!("{:?}", state).to_lowercase()
+}
+
+/// Render all kernel metrics
+pub fn render() -> String {
+    let mut w = MetricsWriter::new();
+    let snap = super::snapshot();
+
+    w.counter(
+        "timer_ticks_total",
+        "Timer ticks since boot",
+        snap.timestamp,
+    );
+    w.gauge("cpu_usage_percent", "Estimated CPU usage", snap.cpu_usage);
+
+    // Memory
+    w.gauge(
+        "heap_used_bytes",
+        "Kernel heap in use",
+        snap.memory.heap_used,
+    );
+    w.gauge("heap_free_bytes", "Kernel heap free", snap.memory.heap_free);
+    w.gauge(
+        "heap_size_bytes",
+        "Kernel heap size",
+        snap.memory.heap_total,
+    );
+
+    render_domains(&mut w);
+    render_tasks(&mut w);
+    render_network(&mut w, &snap.network);
+    render_http(&mut w);
+    render_health(&mut w);
+
+    w.finish()
+}
+
+/// Domains by state, plus per-domain memory and runtime
+fn render_domains(w: &mut MetricsWriter) {
+    use crate::domain_system::{domain_ids, with_domain};
+
+    let domains: Vec<(String, String, String, u64, u64)> = domain_ids()
+        .into_iter()
+        .filter_map(|id| {
+            with_domain(id, |d| {
+                (
+                    id.as_u64().to_string(),
+                    d.name.clone(),
+                    state_label(d.state),
+                    d.allocated_memory,
+                    d.runtime_ticks,
+                )
+            })
+        })
+        .collect();
+
+    let mut by_state: BTreeMap<&str, u64> = BTreeMap::new();
+    for state in [
+        "initializing",
+        "running",
+        "suspended",
+        "stopped",
+        "terminated",
+    ] {
+        by_state.insert(state, 0);
+    }
+    for (_, _, state, _, _) in &domains {
+        *by_state.entry(state.as_str()).or_insert(0) += 1;
+    }
+    w.family("domains", MetricType::Gauge, "Domains by lifecycle state");
+    for (state, count) in &by_state {
+        w.sample(&[("state", state)], count);
+    }
+
+    w.family(
+        "domain_memory_bytes",
+        MetricType::Gauge,
+        "Memory allocated by a domain",
+    );
+    for (id, name, _, memory, _) in &domains {
+        w.sample(&[("id", id), ("name", name)], memory);
+    }
+    w.family(
+        "domain_runtime_ticks_total",
+        MetricType::Counter,
+        "Ticks a domain has run",
+    );
+    for (id, name, _, _, runtime) in &domains {
+        w.sample(&[("id", id), ("name", name)], runtime);
+    }
+}
+
+/// Executor, preemption and process table
+fn render_tasks(w: &mut MetricsWriter) {
+    let executor = crate::task::get_executor_stats();
+    w.counter(
+        "tasks_spawned_total",
+        "Async tasks spawned",
+        executor.tasks_spawned,
+    );
+    w.counter(
+        "tasks_completed_total",
+        "Async tasks completed",
+        executor.tasks_completed,
+    );
+    w.counter("executor_wakeups_total", "Task wakeups", executor.wakeups);
+    w.counter(
+        "executor_poll_cycles_total",
+        "Executor poll cycles",
+        executor.poll_cycles,
+    );
+    w.counter(
+        "executor_steals_total",
+        "Tasks taken by work stealing",
+        executor.steals,
+    );
+
+    let preempt = crate::task::preemption_controller().stats();
+    w.counter(
+        "voluntary_yields_total",
+        "Voluntary task yields",
+        preempt.voluntary_yields,
+    );
+    w.counter(
+        "forced_preemptions_total",
+        "Timer-driven preemptions",
+        preempt.forced_preemptions,
+    );
+
+    let manager = crate::task::process_manager();
+    let mut by_state: BTreeMap<String, u64> = BTreeMap::new();
+    for pid in manager.list() {
+        if let Some(info) = manager.get(pid) {
+            *by_state.entry(state_label(info.read().state)).or_insert(0) += 1;
+        }
+    }
+    w.family("processes", MetricType::Gauge, "Processes by state");
+    for (state, count) in &by_state {
+        w.sample(&[("state", state)], count);
+    }
+}
+
+/// Interface counters, TCP connections and ARP cache
+fn render_network(w: &mut MetricsWriter, net: &super::NetworkStats) {
+    w.counter("net_rx_packets_total", "Packets received", net.rx_packets);
+    w.counter(
+        "net_tx_packets_total",
+        "Packets transmitted",
+        net.tx_packets,
+    );
+    w.counter("net_rx_bytes_total", "Bytes received", net.rx_bytes);
+    w.counter("net_tx_bytes_total", "Bytes transmitted", net.tx_bytes);
+    w.counter("net_rx_errors_total", "Receive errors", net.rx_errors);
+
+    let mut by_state: BTreeMap<String, u64> = BTreeMap::new();
+    for conn in crate::net::get_tcp_connections().unwrap_or_default() {
+        *by_state.entry(conn.state.to_lowercase()).or_insert(0) += 1;
+    }
+    w.family(
+        "tcp_connections",
+        MetricType::Gauge,
+        "TCP connections by state",
+    );
+    for (state, count) in &by_state {
+        w.sample(&[("state", state)], count);
+    }
+
+    let arp = crate::net::get_arp_cache().unwrap_or_default();
+    w.gauge("arp_entries", "ARP cache entries", arp.len());
+
+    render_nic_queues(w);
+}
+
+/// virtio-net queue pairs (offloads, RSS distribution, RX batching)
+fn render_nic_queues(w: &mut MetricsWriter) {
+    let queues = crate::io::virtio::with_virtio_net(|dev| dev.queue_stats()).unwrap_or_default();
+    if queues.is_empty() {
+        return;
+    }
+    type Field = fn(&crate::io::virtio::QueuePairStats) -> u64;
+    let families: [(&str, &str, Field); 9] = [
+        ("nic_queue_rx_packets_total", "Packets received per queue pair", |q| q.rx_packets),
+        ("nic_queue_tx_packets_total", "Packets transmitted per queue pair", |q| q.tx_packets),
+        ("nic_queue_rx_drops_total", "Receive drops per queue pair", |q| q.rx_drops),
+        ("nic_queue_tx_drops_total", "Transmit drops per queue pair", |q| q.tx_drops),
+        ("nic_queue_rx_csum_offloaded_total", "Received frames with checksum offload", |q| q.rx_csum_offloaded),
+        ("nic_queue_tx_csum_offloaded_total", "Transmitted frames with checksum offload", |q| q.tx_csum_offloaded),
+        ("nic_queue_tx_tso_frames_total", "TCP frames segmented by the device", |q| q.tx_tso_frames),
+        ("nic_queue_tx_gso_segments_total", "TCP segments produced by software GSO", |q| q.tx_gso_segments),
+        ("nic_queue_rx_batches_total", "Receive batches processed", |q| q.rx_batches),
+    ];
+    let labels: Vec<String> = queues.iter().map(|q| q.index.to_string()).collect();
+    for (name, help, field) in families {
+        w.family(name, MetricType::Counter, help);
+        for (q, label) in queues.iter().zip(&labels) {
+            w.sample(&[("queue", label)], field(q));
+        }
+    }
+}
+
+/// HTTP server and client
+fn render_http(w: &mut MetricsWriter) {
+    let server = crate::net::http_server::stats();
+    let load = |counter: &core::sync::atomic::AtomicU64| counter.load(Ordering::Relaxed);
+    w.counter(
+        "http_server_requests_total",
+        "HTTP requests served",
+        load(&server.requests),
+    );
+    w.counter(
+        "http_server_bad_requests_total",
+        "Malformed or timed out HTTP requests",
+        load(&server.bad_requests),
+    );
+    w.counter(
+        "http_server_connections_total",
+        "HTTP connections accepted",
+        load(&server.connections_accepted),
+    );
+    w.counter(
+        "http_server_connections_rejected_total",
+        "HTTP connections refused over the limit",
+        load(&server.connections_rejected),
+    );
+    w.gauge(
+        "http_server_connections_active",
+        "HTTP connections being served",
+        load(&server.connections_active),
+    );
+    w.counter(
+        "http_server_received_bytes_total",
+        "Bytes received by the HTTP server",
+        load(&server.bytes_received),
+    );
+    w.counter(
+        "http_server_sent_bytes_total",
+        "Bytes sent by the HTTP server",
+        load(&server.bytes_sent),
+    );
+
+    let client = crate::net::http::stats();
+    w.counter(
+        "http_client_requests_total",
+        "HTTP client requests",
+        load(&client.requests),
+    );
+    w.counter(
+        "http_client_connections_opened_total",
+        "HTTP client connections opened",
+        load(&client.connections_opened),
+    );
+    w.counter(
+        "http_client_connections_reused_total",
+        "HTTP client connections reused from the pool",
+        load(&client.connections_reused),
+    );
+}
+
+/// Thermal, watchdog and profiler
+fn render_health(w: &mut MetricsWriter) {
+    let thermal = crate::thermal::thermal_manager();
+    w.family(
+        "thermal_sensor_celsius",
+        MetricType::Gauge,
+        "Sensor temperature",
+    );
+    for sensor in thermal.sensors().iter() {
+        if sensor.current.is_valid() {
+            let celsius = sensor.current.millicelsius() as f64 / 1000.0;
+            w.sample(&[("sensor", &sensor.name)], celsius);
+        }
+    }
+    let (_, trip_events) = thermal.stats();
+    w.counter(
+        "thermal_trip_events_total",
+        "Thermal trip points crossed",
+        trip_events,
+    );
+    w.counter(
+        "thermal_throttle_total",
+        "Times the CPU was throttled",
+        thermal.throttle_controller().throttle_count(),
+    );
+
+    let watchdog = crate::watchdog::watchdog_manager();
+    let (heartbeats, timeouts, _) = watchdog.software().stats();
+    w.counter(
+        "watchdog_heartbeats_total",
+        "Watchdog heartbeats",
+        heartbeats,
+    );
+    w.counter("watchdog_timeouts_total", "Watchdog timeouts", timeouts);
+    w.counter(
+        "watchdog_deadlocks_total",
+        "Deadlocks detected",
+        watchdog.deadlock_detector().deadlocks_detected(),
+    );
+
+    let profiler = crate::profiler::profiler();
+    w.gauge(
+        "profiler_running",
+        "1 while CPU profiling is on",
+        profiler.cpu.is_enabled() as u8,
+    );
+    w.counter(
+        "profiler_samples_total",
+        "CPU profiler samples",
+        profiler.cpu.stats().total_samples,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer_format() {
+        let mut w = MetricsWriter::new();
+        w.counter("requests_total", "Requests\nserved", 7);
+        w.family("domains", MetricType::Gauge, "Domains by state")
+            .sample(&[("state", "running")], 2)
+            .sample(&[("state", "stopped"), ("name", "net")], 0);
+        assert_eq!(
+            w.finish(),
+            "# HELP exorust_requests_total Requests\\nserved\n\
+             # TYPE exorust_requests_total counter\n\
+             exorust_requests_total 7\n\
+             # HELP exorust_domains Domains by state\n\
+             # TYPE exorust_domains gauge\n\
+             exorust_domains{state=\"running\"} 2\n\
+             exorust_domains{state=\"stopped\",name=\"net\"} 0\n"
+        );
+    }
+
+    #[test]
+    fn test_label_escaping() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("x\ny"), "x\\ny");
+        assert_eq!(
+            state_label(crate::domain_system::DomainState::Running),
+            "running"
+        );
+    }
+}
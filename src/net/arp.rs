@@ -494,6 +494,8 @@ pub enum ArpResult {
     SendReply {
         target_mac: MacAddress,
         target_ip: Ipv4Address,
+        /// Our address that was asked for
+        local_ip: Ipv4Address,
     },
     /// Cache was updated
     CacheUpdated,
@@ -526,6 +528,18 @@ impl ArpProcessor {
 
     /// Process an incoming ARP packet
     pub fn process(&self, data: &[u8], current_time: u64) -> ArpResult {
+        self.process_with(data, current_time, |ip| *ip == self.local_ip)
+    }
+
+    /// Process an incoming ARP packet, answering for addresses accepted by `is_local`
+    ///
+    /// 複数インターフェース環境では受信インターフェースのアドレスで判定する。
+    pub fn process_with(
+        &self,
+        data: &[u8],
+        current_time: u64,
+        is_local: impl Fn(&Ipv4Address) -> bool,
+    ) -> ArpResult {
         if data.len() < ArpPacket::SIZE {
             return ArpResult::Invalid;
         }
@@ -549,10 +563,11 @@ impl ArpProcessor {
         match packet.operation() {
             ArpOperation::Request => {
                 // Is this request for us?
-                if target_ip == self.local_ip {
+                if is_local(&target_ip) {
                     ArpResult::SendReply {
                         target_mac: sender_mac,
                         target_ip: sender_ip,
+                        local_ip: target_ip,
                     }
                 } else {
                     ArpResult::Ignored
@@ -568,6 +583,17 @@ impl ArpProcessor {
 
     /// Build an ARP request packet
     pub fn build_request(&self, buffer: &mut [u8], target_ip: Ipv4Address) -> Option<usize> {
+        self.build_request_from(buffer, self.local_mac, self.local_ip, target_ip)
+    }
+
+    /// Build an ARP request packet with explicit sender addresses
+    pub fn build_request_from(
+        &self,
+        buffer: &mut [u8],
+        sender_mac: MacAddress,
+        sender_ip: Ipv4Address,
+        target_ip: Ipv4Address,
+    ) -> Option<usize> {
         if buffer.len() < ArpPacket::SIZE {
             return None;
         }
@@ -575,7 +601,7 @@ impl ArpProcessor {
         // SAFETY: Buffer is large enough
         let packet = unsafe { &mut *(buffer.as_mut_ptr() as *mut ArpPacket) };
 
-        packet.init_request(self.local_mac, self.local_ip, target_ip);
+        packet.init_request(sender_mac, sender_ip, target_ip);
         Some(ArpPacket::SIZE)
     }
 
@@ -585,6 +611,18 @@ impl ArpProcessor {
         buffer: &mut [u8],
         target_mac: MacAddress,
         target_ip: Ipv4Address,
+    ) -> Option<usize> {
+        self.build_reply_from(buffer, self.local_mac, self.local_ip, target_mac, target_ip)
+    }
+
+    /// Build an ARP reply packet with explicit sender addresses
+    pub fn build_reply_from(
+        &self,
+        buffer: &mut [u8],
+        sender_mac: MacAddress,
+        sender_ip: Ipv4Address,
+        target_mac: MacAddress,
+        target_ip: Ipv4Address,
     ) -> Option<usize> {
         if buffer.len() < ArpPacket::SIZE {
             return None;
@@ -593,7 +631,7 @@ impl ArpProcessor {
         // SAFETY: Buffer is large enough
         let packet = unsafe { &mut *(buffer.as_mut_ptr() as *mut ArpPacket) };
 
-        packet.init_reply(self.local_mac, self.local_ip, target_mac, target_ip);
+        packet.init_reply(sender_mac, sender_ip, target_mac, target_ip);
         Some(ArpPacket::SIZE)
     }
 
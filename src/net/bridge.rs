@@ -0,0 +1,419 @@
+//! # Ethernet ブリッジ
+//!
+//! 複数の NIC や VLAN インターフェースを 1 つの L2 セグメントにまとめる学習ブリッジ
+//! （`br0` など）。Linux の bridge と同じく、ブリッジ自身もインターフェースで、
+//! ホストのアドレスはポートではなくブリッジに割り当てる。
+//!
+//! - 受信フレームの送信元 MAC を受信ポートと結び付けて MAC テーブルに学習する
+//! - 宛先が学習済みならそのポートだけへ、未学習・ブロードキャスト・マルチキャストなら
+//!   受信ポート以外の全ポートへフラッディングする
+//! - 一定時間（既定 300 秒）フレームを見ないエントリはエージングで消す
+//!
+//! 本体（`Bridge`）は転送先を決めるだけで、送受信は `NetworkStack` が行う。
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::ethernet::{EthernetFrame, MacAddress};
+use super::interface::{InterfaceError, InterfaceId};
+
+/// MAC テーブルのエントリを保持する既定の時間
+pub const DEFAULT_AGEING_MS: u64 = 300_000;
+
+/// MAC テーブルの最大エントリ数
+pub const MAX_FDB_ENTRIES: usize = 4096;
+
+/// 1 つのブリッジに追加できるポート数
+pub const MAX_PORTS: usize = 32;
+
+/// 期限切れエントリを掃除する間隔
+const SWEEP_INTERVAL_MS: u64 = 1000;
+
+/// Bridge errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeError {
+    /// Interface error (not found, ...)
+    Interface(InterfaceError),
+    /// Not a bridge interface
+    NotBridge,
+    /// Port cannot be added (loopback, tunnel, the bridge itself)
+    InvalidPort,
+    /// Interface is already a port of a bridge
+    PortInUse,
+    /// Interface is not a port of this bridge
+    NotAPort,
+    /// Port limit reached
+    TooManyPorts,
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeError::Interface(e) => write!(f, "{}", e),
+            BridgeError::NotBridge => write!(f, "Not a bridge interface"),
+            BridgeError::InvalidPort => write!(f, "Interface cannot be a bridge port"),
+            BridgeError::PortInUse => write!(f, "Interface is already a bridge port"),
+            BridgeError::NotAPort => write!(f, "Interface is not a port of this bridge"),
+            BridgeError::TooManyPorts => write!(f, "Too many bridge ports"),
+        }
+    }
+}
+
+impl From<InterfaceError> for BridgeError {
+    fn from(e: InterfaceError) -> Self {
+        BridgeError::Interface(e)
+    }
+}
+
+/// MAC table entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdbEntry {
+    /// Port the address was learned on
+    pub port: InterfaceId,
+    /// Last time a frame from the address was seen
+    pub last_seen_ms: u64,
+    /// Static entries never age out and are not relearned
+    pub is_static: bool,
+}
+
+/// Bridge counters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BridgeStats {
+    /// Frames sent to a single learned port
+    pub forwarded: u64,
+    /// Frames flooded to all other ports
+    pub flooded: u64,
+    /// Frames delivered to the host
+    pub local: u64,
+    /// Frames dropped (destination on the ingress port, not a port)
+    pub dropped: u64,
+    /// Addresses learned
+    pub learned: u64,
+    /// Addresses that moved to another port
+    pub moved: u64,
+    /// Entries removed by ageing
+    pub aged: u64,
+}
+
+/// Where a received frame goes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Decision {
+    /// Ports to transmit the frame on
+    pub ports: Vec<InterfaceId>,
+    /// Deliver the frame to the host through the bridge interface
+    pub local: bool,
+}
+
+/// Learning Ethernet bridge
+pub struct Bridge {
+    /// Bridge interface MAC (frames for the host)
+    mac: MacAddress,
+    /// Member ports (in order added)
+    ports: Vec<InterfaceId>,
+    /// MAC table
+    fdb: BTreeMap<MacAddress, FdbEntry>,
+    /// Ageing time
+    ageing_ms: u64,
+    /// Last ageing sweep
+    last_sweep_ms: u64,
+    /// Counters
+    stats: BridgeStats,
+}
+
+impl Bridge {
+    /// Create a bridge without ports
+    pub fn new(mac: MacAddress) -> Self {
+        Bridge {
+            mac,
+            ports: Vec::new(),
+            fdb: BTreeMap::new(),
+            ageing_ms: DEFAULT_AGEING_MS,
+            last_sweep_ms: 0,
+            stats: BridgeStats::default(),
+        }
+    }
+
+    /// Bridge interface MAC
+    pub fn mac(&self) -> MacAddress {
+        self.mac
+    }
+
+    /// Change the bridge's own MAC address
+    pub fn set_mac(&mut self, mac: MacAddress) {
+        self.mac = mac;
+    }
+
+    /// Member ports
+    pub fn ports(&self) -> &[InterfaceId] {
+        &self.ports
+    }
+
+    /// Check if an interface is a member port
+    pub fn has_port(&self, port: InterfaceId) -> bool {
+        self.ports.contains(&port)
+    }
+
+    /// Add a port
+    pub fn add_port(&mut self, port: InterfaceId) -> Result<(), BridgeError> {
+        if self.has_port(port) {
+            return Err(BridgeError::PortInUse);
+        }
+        if self.ports.len() >= MAX_PORTS {
+            return Err(BridgeError::TooManyPorts);
+        }
+        self.ports.push(port);
+        Ok(())
+    }
+
+    /// Remove a port and the addresses learned on it
+    pub fn remove_port(&mut self, port: InterfaceId) -> Result<(), BridgeError> {
+        if !self.has_port(port) {
+            return Err(BridgeError::NotAPort);
+        }
+        self.ports.retain(|p| *p != port);
+        self.fdb.retain(|_, entry| entry.port != port);
+        Ok(())
+    }
+
+    /// Ageing time
+    pub fn ageing_ms(&self) -> u64 {
+        self.ageing_ms
+    }
+
+    /// Set the ageing time (0 = never age out)
+    pub fn set_ageing_ms(&mut self, ageing_ms: u64) {
+        self.ageing_ms = ageing_ms;
+    }
+
+    /// Counters
+    pub fn stats(&self) -> &BridgeStats {
+        &self.stats
+    }
+
+    /// MAC table entries
+    pub fn fdb(&self) -> impl Iterator<Item = (&MacAddress, &FdbEntry)> {
+        self.fdb.iter()
+    }
+
+    /// Add a static MAC table entry
+    pub fn add_static(&mut self, mac: MacAddress, port: InterfaceId) -> Result<(), BridgeError> {
+        if !self.has_port(port) {
+            return Err(BridgeError::NotAPort);
+        }
+        self.fdb.insert(mac, FdbEntry { port, last_seen_ms: 0, is_static: true });
+        Ok(())
+    }
+
+    /// Remove all learned (non-static) entries
+    pub fn flush(&mut self) {
+        self.fdb.retain(|_, entry| entry.is_static);
+    }
+
+    /// Check if an entry has aged out
+    fn expired(&self, entry: &FdbEntry, now: u64) -> bool {
+        !entry.is_static && self.ageing_ms != 0 && now.saturating_sub(entry.last_seen_ms) >= self.ageing_ms
+    }
+
+    /// Remove aged-out entries
+    ///
+    /// 戻り値: 削除したエントリ数
+    pub fn expire(&mut self, now: u64) -> usize {
+        let before = self.fdb.len();
+        let ageing_ms = self.ageing_ms;
+        if ageing_ms != 0 {
+            self.fdb.retain(|_, entry| {
+                entry.is_static || now.saturating_sub(entry.last_seen_ms) < ageing_ms
+            });
+        }
+        self.last_sweep_ms = now;
+        let removed = before - self.fdb.len();
+        self.stats.aged += removed as u64;
+        removed
+    }
+
+    /// Port a destination was learned on (None if unknown or aged out)
+    pub fn lookup(&self, mac: &MacAddress, now: u64) -> Option<InterfaceId> {
+        self.fdb
+            .get(mac)
+            .filter(|entry| !self.expired(entry, now))
+            .map(|entry| entry.port)
+    }
+
+    /// Learn the source address of a frame received on a port
+    fn learn(&mut self, src: MacAddress, port: InterfaceId, now: u64) {
+        // マルチキャスト送信元は不正なフレーム。自分の MAC も学習しない
+        if src.is_multicast() || src == MacAddress::ZERO || src == self.mac {
+            return;
+        }
+        if let Some(entry) = self.fdb.get_mut(&src) {
+            if entry.is_static {
+                return;
+            }
+            if entry.port != port {
+                entry.port = port;
+                self.stats.moved += 1;
+            }
+            entry.last_seen_ms = now;
+            return;
+        }
+        if self.fdb.len() >= MAX_FDB_ENTRIES && self.expire(now) == 0 {
+            return;
+        }
+        self.fdb.insert(src, FdbEntry { port, last_seen_ms: now, is_static: false });
+        self.stats.learned += 1;
+    }
+
+    /// Ports other than `except`
+    fn flood_ports(&self, except: Option<InterfaceId>) -> Vec<InterfaceId> {
+        self.ports.iter().copied().filter(|p| Some(*p) != except).collect()
+    }
+
+    /// Decide where a frame received on a port goes
+    pub fn input(&mut self, ingress: InterfaceId, frame: &[u8], now: u64) -> Decision {
+        let Some(frame) = EthernetFrame::parse(frame) else {
+            self.stats.dropped += 1;
+            return Decision::default();
+        };
+        if !self.has_port(ingress) {
+            self.stats.dropped += 1;
+            return Decision::default();
+        }
+        if now.saturating_sub(self.last_sweep_ms) >= SWEEP_INTERVAL_MS {
+            self.expire(now);
+        }
+        self.learn(frame.source(), ingress, now);
+
+        let dst = frame.destination();
+        if dst == self.mac {
+            self.stats.local += 1;
+            return Decision { ports: Vec::new(), local: true };
+        }
+        if dst.is_multicast() {
+            self.stats.flooded += 1;
+            self.stats.local += 1;
+            return Decision { ports: self.flood_ports(Some(ingress)), local: true };
+        }
+        match self.lookup(&dst, now) {
+            // 宛先が受信ポート側にいるなら転送しない
+            Some(port) if port == ingress => {
+                self.stats.dropped += 1;
+                Decision::default()
+            }
+            Some(port) => {
+                self.stats.forwarded += 1;
+                Decision { ports: alloc::vec![port], local: false }
+            }
+            None => {
+                self.stats.flooded += 1;
+                Decision { ports: self.flood_ports(Some(ingress)), local: false }
+            }
+        }
+    }
+
+    /// Ports to transmit a frame sent by the host on
+    pub fn output(&mut self, frame: &[u8], now: u64) -> Vec<InterfaceId> {
+        let Some(frame) = EthernetFrame::parse(frame) else {
+            return Vec::new();
+        };
+        let dst = frame.destination();
+        match (!dst.is_multicast()).then(|| self.lookup(&dst, now)).flatten() {
+            Some(port) => {
+                self.stats.forwarded += 1;
+                alloc::vec![port]
+            }
+            None => {
+                self.stats.flooded += 1;
+                self.flood_ports(None)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BR_MAC: MacAddress = MacAddress::new([0x02, 0, 0, 0, 0, 0xbb]);
+    const HOST_A: MacAddress = MacAddress::new([0x52, 0x54, 0, 0, 0, 0x0a]);
+    const HOST_B: MacAddress = MacAddress::new([0x52, 0x54, 0, 0, 0, 0x0b]);
+
+    fn frame(dst: MacAddress, src: MacAddress) -> [u8; 60] {
+        let mut frame = [0u8; 60];
+        frame[..6].copy_from_slice(dst.as_bytes());
+        frame[6..12].copy_from_slice(src.as_bytes());
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        frame
+    }
+
+    fn bridge() -> Bridge {
+        let mut br = Bridge::new(BR_MAC);
+        for port in [1, 2, 3] {
+            br.add_port(port).unwrap();
+        }
+        br
+    }
+
+    #[test]
+    fn test_learning_and_flooding() {
+        let mut br = bridge();
+        assert_eq!(br.add_port(2), Err(BridgeError::PortInUse));
+
+        // 未学習の宛先は受信ポート以外へフラッディング
+        let d = br.input(1, &frame(HOST_B, HOST_A), 0);
+        assert_eq!(d, Decision { ports: alloc::vec![2, 3], local: false });
+        assert_eq!(br.lookup(&HOST_A, 0), Some(1));
+
+        // 応答で B を学習し、以降は A へ直接転送
+        let d = br.input(2, &frame(HOST_A, HOST_B), 10);
+        assert_eq!(d, Decision { ports: alloc::vec![1], local: false });
+        let d = br.input(1, &frame(HOST_B, HOST_A), 20);
+        assert_eq!(d.ports, alloc::vec![2]);
+
+        // 同じポート側の宛先は転送しない
+        assert_eq!(br.input(2, &frame(HOST_B, HOST_A), 30), Decision::default());
+        assert_eq!(br.lookup(&HOST_A, 30), Some(2));
+        assert_eq!(br.stats().moved, 1);
+
+        // ブロードキャストは全ポートとホストへ、ブリッジ宛てはホストだけ
+        let d = br.input(3, &frame(MacAddress::BROADCAST, HOST_B), 40);
+        assert_eq!(d, Decision { ports: alloc::vec![1, 2], local: true });
+        let d = br.input(3, &frame(BR_MAC, HOST_B), 50);
+        assert_eq!(d, Decision { ports: Vec::new(), local: true });
+
+        // ポート以外からのフレームは捨てる
+        assert_eq!(br.input(9, &frame(HOST_A, HOST_B), 60), Decision::default());
+
+        // ホストからの送信
+        assert_eq!(br.output(&frame(HOST_B, BR_MAC), 70), alloc::vec![3]);
+        assert_eq!(br.output(&frame(MacAddress::BROADCAST, BR_MAC), 70), alloc::vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ageing() {
+        let mut br = bridge();
+        br.set_ageing_ms(1000);
+        br.input(1, &frame(HOST_B, HOST_A), 0);
+        br.add_static(HOST_B, 3).unwrap();
+        assert_eq!(br.lookup(&HOST_A, 999), Some(1));
+
+        // 期限切れのエントリは参照されず、掃除で消える
+        assert_eq!(br.lookup(&HOST_A, 1000), None);
+        assert_eq!(br.expire(1000), 1);
+        assert_eq!(br.stats().aged, 1);
+
+        // 静的エントリはエージングも再学習もしない
+        br.input(2, &frame(HOST_A, HOST_B), 5000);
+        assert_eq!(br.lookup(&HOST_B, 100_000), Some(3));
+
+        // ポートを外すとそのポートで学習したエントリも消える
+        br.input(1, &frame(HOST_B, HOST_A), 6000);
+        br.remove_port(1).unwrap();
+        assert_eq!(br.lookup(&HOST_A, 6000), None);
+        assert_eq!(br.remove_port(1), Err(BridgeError::NotAPort));
+    }
+}
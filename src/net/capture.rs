@@ -0,0 +1,556 @@
+//! # Packet Capture - パケットキャプチャ
+//!
+//! driver_bridge の RX/TX をタップし、フィルタに一致したフレームを
+//! リングバッファへ保存する。保存したパケットは pcapng 形式で
+//! マウント済みの任意のファイルシステム、またはシリアルポート（COM2）へ
+//! 書き出せるため、ホスト側の Wireshark でそのまま開ける。
+//!
+//! 受信タップは割り込みコンテキストから呼ばれうるため、キャプチャ状態の
+//! ロックが取れない場合はパケットを破棄して `dropped` に数える（try_lock）。
+
+pub mod filter;
+pub mod pcapng;
+
+pub use filter::{CaptureFilter, FilterError, FilterExpr, PacketInfo};
+pub use pcapng::PcapngWriter;
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use spin::Mutex;
+
+use crate::fs::FsError;
+use crate::io::serial::SerialError;
+
+/// Default ring size (packets)
+pub const DEFAULT_RING_PACKETS: usize = 1024;
+
+/// Default ring size (bytes of captured data)
+pub const DEFAULT_RING_BYTES: usize = 4 * 1024 * 1024;
+
+/// Default snapshot length
+pub const DEFAULT_SNAPLEN: usize = 65535;
+
+/// Interface name recorded in the pcapng IDB
+pub const CAPTURE_INTERFACE: &str = "eth0";
+
+/// Application name recorded in the pcapng SHB
+const CAPTURE_APPLICATION: &str = "Rany_OS packet capture";
+
+/// Packet direction relative to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the NIC
+    Rx,
+    /// Transmitted to the NIC
+    Tx,
+}
+
+impl Direction {
+    /// Short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Rx => "rx",
+            Direction::Tx => "tx",
+        }
+    }
+
+    /// pcapng epb_flags value
+    fn epb_flags(&self) -> u32 {
+        match self {
+            Direction::Rx => pcapng::EPB_FLAG_INBOUND,
+            Direction::Tx => pcapng::EPB_FLAG_OUTBOUND,
+        }
+    }
+}
+
+/// A captured frame
+#[derive(Debug, Clone)]
+pub struct CapturedPacket {
+    /// Capture time (µs since the Unix epoch)
+    pub timestamp_us: u64,
+    /// Direction
+    pub direction: Direction,
+    /// Length on the wire
+    pub original_len: usize,
+    /// Frame data (truncated to snaplen)
+    pub data: Vec<u8>,
+}
+
+impl CapturedPacket {
+    /// Dissect the captured frame
+    pub fn info(&self) -> PacketInfo {
+        PacketInfo::parse(&self.data)
+    }
+}
+
+/// Capture configuration
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    /// Filter (None = capture everything)
+    pub filter: Option<CaptureFilter>,
+    /// Maximum bytes stored per packet
+    pub snaplen: usize,
+    /// Ring capacity in packets
+    pub max_packets: usize,
+    /// Ring capacity in bytes
+    pub max_bytes: usize,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            filter: None,
+            snaplen: DEFAULT_SNAPLEN,
+            max_packets: DEFAULT_RING_PACKETS,
+            max_bytes: DEFAULT_RING_BYTES,
+        }
+    }
+}
+
+/// Capture statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Frames seen by the tap
+    pub seen: u64,
+    /// Frames stored in the ring
+    pub captured: u64,
+    /// Frames rejected by the filter
+    pub filtered: u64,
+    /// Old frames evicted from the full ring
+    pub overwritten: u64,
+    /// Frames lost because the capture state was busy
+    pub dropped: u64,
+    /// Frames currently in the ring
+    pub packets: usize,
+    /// Bytes currently in the ring
+    pub bytes: usize,
+}
+
+/// Capture errors
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Filter expression could not be parsed
+    InvalidFilter(FilterError),
+    /// No capture session has been started
+    NotStarted,
+    /// Writing the file failed
+    Filesystem(FsError),
+    /// Serial port unavailable
+    Serial(SerialError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::InvalidFilter(e) => write!(f, "{}", e),
+            CaptureError::NotStarted => write!(f, "No capture session"),
+            CaptureError::Filesystem(e) => write!(f, "Failed to write capture file: {:?}", e),
+            CaptureError::Serial(e) => write!(f, "Serial port unavailable: {:?}", e),
+        }
+    }
+}
+
+impl From<FilterError> for CaptureError {
+    fn from(e: FilterError) -> Self {
+        CaptureError::InvalidFilter(e)
+    }
+}
+
+// ============================================================================
+// Ring buffer
+// ============================================================================
+
+/// Bounded packet ring (oldest packets are overwritten)
+#[derive(Debug)]
+pub struct CaptureRing {
+    packets: VecDeque<CapturedPacket>,
+    max_packets: usize,
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl CaptureRing {
+    /// Create a ring bounded by packet count and total bytes
+    pub fn new(max_packets: usize, max_bytes: usize) -> Self {
+        CaptureRing {
+            packets: VecDeque::new(),
+            max_packets: max_packets.max(1),
+            max_bytes,
+            bytes: 0,
+        }
+    }
+
+    /// Store a packet, returning the number of evicted packets
+    pub fn push(&mut self, packet: CapturedPacket) -> usize {
+        let mut evicted = 0;
+        while !self.packets.is_empty()
+            && (self.packets.len() >= self.max_packets
+                || self.bytes + packet.data.len() > self.max_bytes)
+        {
+            if let Some(old) = self.packets.pop_front() {
+                self.bytes -= old.data.len();
+                evicted += 1;
+            }
+        }
+        self.bytes += packet.data.len();
+        self.packets.push_back(packet);
+        evicted
+    }
+
+    /// Number of stored packets
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Check if the ring is empty
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Bytes of stored packet data
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Iterate packets, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &CapturedPacket> {
+        self.packets.iter()
+    }
+
+    /// Discard all packets
+    pub fn clear(&mut self) {
+        self.packets.clear();
+        self.bytes = 0;
+    }
+}
+
+// ============================================================================
+// Capture session
+// ============================================================================
+
+/// A capture session: filter, ring and counters
+#[derive(Debug)]
+pub struct Capture {
+    config: CaptureConfig,
+    ring: CaptureRing,
+    stats: CaptureStats,
+    running: bool,
+}
+
+impl Capture {
+    /// Create a running session
+    pub fn new(config: CaptureConfig) -> Self {
+        let ring = CaptureRing::new(config.max_packets, config.max_bytes);
+        Capture {
+            config,
+            ring,
+            stats: CaptureStats::default(),
+            running: true,
+        }
+    }
+
+    /// Offer a frame to the session
+    ///
+    /// 戻り値: リングに保存したら true
+    pub fn process(&mut self, direction: Direction, frame: &[u8], timestamp_us: u64) -> bool {
+        if !self.running {
+            return false;
+        }
+        self.stats.seen += 1;
+        if let Some(ref filter) = self.config.filter
+            && !filter.matches(frame)
+        {
+            self.stats.filtered += 1;
+            return false;
+        }
+
+        let len = frame.len().min(self.config.snaplen);
+        let evicted = self.ring.push(CapturedPacket {
+            timestamp_us,
+            direction,
+            original_len: frame.len(),
+            data: frame[..len].to_vec(),
+        });
+        self.stats.captured += 1;
+        self.stats.overwritten += evicted as u64;
+        true
+    }
+
+    /// Whether the session is still capturing
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Stop capturing (stored packets are kept)
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    /// Session configuration
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Statistics
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            packets: self.ring.len(),
+            bytes: self.ring.bytes(),
+            ..self.stats
+        }
+    }
+
+    /// Stored packets
+    pub fn ring(&self) -> &CaptureRing {
+        &self.ring
+    }
+
+    /// Encode the stored packets as a pcapng file
+    pub fn to_pcapng(&self) -> Vec<u8> {
+        let mut writer = PcapngWriter::new(CAPTURE_APPLICATION);
+        let interface = writer.add_interface(
+            pcapng::LINKTYPE_ETHERNET,
+            self.config.snaplen as u32,
+            CAPTURE_INTERFACE,
+        );
+        for packet in self.ring.iter() {
+            writer.add_packet(
+                interface,
+                packet.timestamp_us,
+                &packet.data,
+                packet.original_len,
+                packet.direction.epb_flags(),
+            );
+        }
+        writer.finish()
+    }
+}
+
+// ============================================================================
+// Global capture state
+// ============================================================================
+
+/// Fast-path flag checked by the taps
+static CAPTURE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Current (or last) capture session
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Frames lost to lock contention
+static CAPTURE_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// Start a new capture session (replaces any previous one)
+pub fn start(config: CaptureConfig) {
+    *CAPTURE.lock() = Some(Capture::new(config));
+    CAPTURE_DROPPED.store(0, Ordering::Relaxed);
+    CAPTURE_ACTIVE.store(true, Ordering::Release);
+}
+
+/// Stop capturing; the ring stays available for export
+pub fn stop() -> Option<CaptureStats> {
+    CAPTURE_ACTIVE.store(false, Ordering::Release);
+    CAPTURE.lock().as_mut().map(|capture| {
+        capture.stop();
+        capture.stats()
+    })
+}
+
+/// Whether a capture is running
+pub fn is_active() -> bool {
+    CAPTURE_ACTIVE.load(Ordering::Acquire)
+}
+
+/// Tap a frame at the driver bridge
+#[inline]
+pub fn tap(direction: Direction, frame: &[u8]) {
+    if !CAPTURE_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let timestamp_us = timestamp_us();
+    match CAPTURE.try_lock() {
+        Some(mut guard) => {
+            if let Some(capture) = guard.as_mut() {
+                capture.process(direction, frame, timestamp_us);
+            }
+        }
+        None => {
+            CAPTURE_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Statistics of the current session
+pub fn stats() -> Option<CaptureStats> {
+    CAPTURE.lock().as_ref().map(|capture| CaptureStats {
+        dropped: CAPTURE_DROPPED.load(Ordering::Relaxed),
+        ..capture.stats()
+    })
+}
+
+/// Active filter expression
+pub fn filter_expression() -> Option<String> {
+    CAPTURE.lock().as_ref().map(|capture| {
+        capture
+            .config()
+            .filter
+            .as_ref()
+            .map(|f| String::from(f.expression()))
+            .unwrap_or_default()
+    })
+}
+
+/// Copy of the most recent `limit` packets (oldest first)
+pub fn recent_packets(limit: usize) -> Vec<CapturedPacket> {
+    let guard = CAPTURE.lock();
+    let Some(capture) = guard.as_ref() else {
+        return Vec::new();
+    };
+    let skip = capture.ring().len().saturating_sub(limit);
+    capture.ring().iter().skip(skip).cloned().collect()
+}
+
+/// Discard stored packets of the current session
+pub fn clear() {
+    if let Some(capture) = CAPTURE.lock().as_mut() {
+        capture.ring.clear();
+    }
+}
+
+/// Encode the current session as pcapng
+pub fn export_pcapng() -> Result<Vec<u8>, CaptureError> {
+    CAPTURE
+        .lock()
+        .as_ref()
+        .map(Capture::to_pcapng)
+        .ok_or(CaptureError::NotStarted)
+}
+
+/// Write the capture as a pcapng file
+///
+/// マウントテーブルに登録されたファイルシステムを優先し、
+/// 該当しなければシェルのルートファイルシステム（memfs）へ書き込む。
+/// 戻り値: 書き込んだバイト数
+pub fn save(path: &str) -> Result<usize, CaptureError> {
+    let data = export_pcapng()?;
+    crate::fs::write_file(path, &data).map_err(CaptureError::Filesystem)?;
+    Ok(data.len())
+}
+
+/// Stream the capture as pcapng over COM2
+///
+/// ホスト側では QEMU の `-serial stdio -serial file:capture.pcapng` 等で受け取る。
+/// 戻り値: 送信したバイト数
+pub fn stream_serial() -> Result<usize, CaptureError> {
+    let data = export_pcapng()?;
+    let port = crate::io::serial::serial2().map_err(CaptureError::Serial)?;
+    port.send_bytes(&data);
+    Ok(data.len())
+}
+
+/// Current time in µs since the Unix epoch (lock-free, safe on the RX path)
+fn timestamp_us() -> u64 {
+    crate::time::now_nanos() / 1_000
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ethernet::{EtherType, EthernetFrameMut, MacAddress};
+    use crate::net::ipv4::{IpProtocol, Ipv4Address};
+    use crate::net::stack::NetworkStack;
+    use crate::net::udp::UdpProcessor;
+
+    /// Ethernet + IPv4/UDP の `len` バイトのフレーム
+    fn udp_frame(dport: u16, len: usize) -> Vec<u8> {
+        let src = Ipv4Address::from_octets(10, 0, 2, 15);
+        let dst = Ipv4Address::from_octets(10, 0, 2, 2);
+        let payload = alloc::vec![0u8; len.saturating_sub(42)];
+        let mut buffer = alloc::vec![0u8; len.max(42)];
+        let mut frame = EthernetFrameMut::new(&mut buffer).unwrap();
+        frame
+            .set_destination(MacAddress::BROADCAST)
+            .set_source(MacAddress::ZERO)
+            .set_ether_type(EtherType::Ipv4);
+        let ip_len = NetworkStack::build_ipv4(frame.payload_mut(), src, dst, IpProtocol::Udp, 64, |buf| {
+            UdpProcessor::build_packet(buf, src, 40000, dst, dport, &payload)
+        })
+        .unwrap();
+        frame.set_payload_len(ip_len);
+        frame.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_ring_eviction_by_count_and_bytes() {
+        let packet = |len: usize| CapturedPacket {
+            timestamp_us: 0,
+            direction: Direction::Rx,
+            original_len: len,
+            data: alloc::vec![0; len],
+        };
+        let mut ring = CaptureRing::new(3, 250);
+        assert_eq!(ring.push(packet(100)), 0);
+        assert_eq!(ring.push(packet(100)), 0);
+        // 250バイトを超えるため最古を1つ追い出す
+        assert_eq!(ring.push(packet(100)), 1);
+        assert_eq!((ring.len(), ring.bytes()), (2, 200));
+
+        let mut ring = CaptureRing::new(2, 10_000);
+        for _ in 0..5 {
+            ring.push(packet(10));
+        }
+        assert_eq!(ring.len(), 2);
+    }
+
+    #[test]
+    fn test_capture_filter_and_snaplen() {
+        let mut capture = Capture::new(CaptureConfig {
+            filter: Some(CaptureFilter::parse("udp port 53").unwrap()),
+            snaplen: 64,
+            ..CaptureConfig::default()
+        });
+        assert!(capture.process(Direction::Tx, &udp_frame(53, 200), 1));
+        assert!(!capture.process(Direction::Rx, &udp_frame(80, 200), 2));
+
+        let stats = capture.stats();
+        assert_eq!((stats.seen, stats.captured, stats.filtered), (2, 1, 1));
+        let packet = capture.ring().iter().next().unwrap();
+        assert_eq!((packet.data.len(), packet.original_len), (64, 200));
+
+        capture.stop();
+        assert!(!capture.process(Direction::Tx, &udp_frame(53, 60), 3));
+        assert_eq!(capture.stats().seen, 2);
+    }
+
+    #[test]
+    fn test_capture_to_pcapng() {
+        let mut capture = Capture::new(CaptureConfig::default());
+        capture.process(Direction::Rx, &udp_frame(53, 60), 1_000_000);
+        capture.process(Direction::Tx, &udp_frame(53, 61), 2_000_000);
+
+        let file = capture.to_pcapng();
+        assert_eq!(
+            u32::from_le_bytes([file[0], file[1], file[2], file[3]]),
+            pcapng::BLOCK_SECTION_HEADER
+        );
+        // SHB + IDB + EPB x2
+        let mut offset = 0;
+        let mut blocks = 0;
+        while offset < file.len() {
+            offset += u32::from_le_bytes([
+                file[offset + 4],
+                file[offset + 5],
+                file[offset + 6],
+                file[offset + 7],
+            ]) as usize;
+            blocks += 1;
+        }
+        assert_eq!(blocks, 4);
+    }
+}
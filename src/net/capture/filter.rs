@@ -0,0 +1,500 @@
+//! # Capture Filter - BPF風キャプチャフィルタ
+//!
+//! tcpdump 互換のサブセット式をパースし、Ethernetフレームに対して評価する。
+//!
+//! ```text
+//! expr      := and_expr (("or" | "||") and_expr)*
+//! and_expr  := unary ([("and" | "&&")] unary)*
+//! unary     := ("not" | "!") unary | "(" expr ")" | primitive
+//! primitive := proto | [dir] "host" ADDR | [dir] "net" ADDR/LEN | [dir] "port" N | dir ADDR
+//! proto     := "arp" | "ip" | "icmp" | "tcp" | "udp"
+//! dir       := "src" | "dst"
+//! ```
+//!
+//! 例: `tcp port 80 and host 10.0.2.2`, `udp and not port 53`, `arp or icmp`
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::net::ipv4::Ipv4Address;
+
+/// EtherType: IPv4
+const ETHERTYPE_IPV4: u16 = 0x0800;
+/// EtherType: ARP
+const ETHERTYPE_ARP: u16 = 0x0806;
+/// EtherType: 802.1Q VLAN tag
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+/// IP protocol numbers
+const PROTO_ICMP: u8 = 1;
+const PROTO_TCP: u8 = 6;
+const PROTO_UDP: u8 = 17;
+
+// ============================================================================
+// Packet dissection
+// ============================================================================
+
+/// Header fields extracted from an Ethernet frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PacketInfo {
+    /// EtherType (after any VLAN tag)
+    pub ethertype: u16,
+    /// IP protocol number (IPv4 only)
+    pub protocol: Option<u8>,
+    /// Source address (IPv4 source / ARP sender)
+    pub src: Option<Ipv4Address>,
+    /// Destination address (IPv4 destination / ARP target)
+    pub dst: Option<Ipv4Address>,
+    /// TCP/UDP source port (first fragment only)
+    pub src_port: Option<u16>,
+    /// TCP/UDP destination port (first fragment only)
+    pub dst_port: Option<u16>,
+}
+
+impl PacketInfo {
+    /// Dissect an Ethernet frame
+    ///
+    /// 短すぎる・未知のフレームは解析できたフィールドのみを返す
+    pub fn parse(frame: &[u8]) -> Self {
+        let mut info = PacketInfo::default();
+        if frame.len() < 14 {
+            return info;
+        }
+
+        let mut offset = 12;
+        let mut ethertype = u16::from_be_bytes([frame[offset], frame[offset + 1]]);
+        offset += 2;
+        if ethertype == ETHERTYPE_VLAN && frame.len() >= offset + 4 {
+            ethertype = u16::from_be_bytes([frame[offset + 2], frame[offset + 3]]);
+            offset += 4;
+        }
+        info.ethertype = ethertype;
+        let payload = &frame[offset..];
+
+        match ethertype {
+            ETHERTYPE_IPV4 => info.parse_ipv4(payload),
+            ETHERTYPE_ARP if payload.len() >= 28 => {
+                info.src = Some(Ipv4Address::new([payload[14], payload[15], payload[16], payload[17]]));
+                info.dst = Some(Ipv4Address::new([payload[24], payload[25], payload[26], payload[27]]));
+            }
+            _ => {}
+        }
+        info
+    }
+
+    fn parse_ipv4(&mut self, packet: &[u8]) {
+        if packet.len() < 20 || packet[0] >> 4 != 4 {
+            return;
+        }
+        let header_len = ((packet[0] & 0x0F) as usize) * 4;
+        self.protocol = Some(packet[9]);
+        self.src = Some(Ipv4Address::new([packet[12], packet[13], packet[14], packet[15]]));
+        self.dst = Some(Ipv4Address::new([packet[16], packet[17], packet[18], packet[19]]));
+
+        // ポート番号は先頭フラグメントのみに存在する
+        let fragment_offset = u16::from_be_bytes([packet[6], packet[7]]) & 0x1FFF;
+        if fragment_offset != 0 || !matches!(packet[9], PROTO_TCP | PROTO_UDP) {
+            return;
+        }
+        if let Some(ports) = packet.get(header_len..header_len + 4) {
+            self.src_port = Some(u16::from_be_bytes([ports[0], ports[1]]));
+            self.dst_port = Some(u16::from_be_bytes([ports[2], ports[3]]));
+        }
+    }
+
+    /// One-line summary (tcpdump style)
+    pub fn summary(&self) -> String {
+        let proto = match (self.ethertype, self.protocol) {
+            (ETHERTYPE_ARP, _) => "ARP",
+            (ETHERTYPE_IPV4, Some(PROTO_TCP)) => "TCP",
+            (ETHERTYPE_IPV4, Some(PROTO_UDP)) => "UDP",
+            (ETHERTYPE_IPV4, Some(PROTO_ICMP)) => "ICMP",
+            (ETHERTYPE_IPV4, _) => "IP",
+            _ => return format!("ethertype 0x{:04x}", self.ethertype),
+        };
+        let endpoint = |addr: Option<Ipv4Address>, port: Option<u16>| match (addr, port) {
+            (Some(addr), Some(port)) => format!("{}:{}", addr, port),
+            (Some(addr), None) => format!("{}", addr),
+            _ => String::from("?"),
+        };
+        format!(
+            "{} {} > {}",
+            proto,
+            endpoint(self.src, self.src_port),
+            endpoint(self.dst, self.dst_port)
+        )
+    }
+}
+
+// ============================================================================
+// Filter expression
+// ============================================================================
+
+/// Protocol qualifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterProto {
+    Arp,
+    Ip,
+    Icmp,
+    Tcp,
+    Udp,
+}
+
+/// Direction qualifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterDir {
+    /// src or dst
+    Any,
+    Src,
+    Dst,
+}
+
+/// Filter expression tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    /// Matches every packet
+    All,
+    Proto(FilterProto),
+    Host(FilterDir, Ipv4Address),
+    Net(FilterDir, Ipv4Address, u8),
+    Port(FilterDir, u16),
+    Not(Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Evaluate against dissected packet fields
+    pub fn matches(&self, info: &PacketInfo) -> bool {
+        match self {
+            FilterExpr::All => true,
+            FilterExpr::Proto(proto) => match proto {
+                FilterProto::Arp => info.ethertype == ETHERTYPE_ARP,
+                FilterProto::Ip => info.ethertype == ETHERTYPE_IPV4,
+                FilterProto::Icmp => info.protocol == Some(PROTO_ICMP),
+                FilterProto::Tcp => info.protocol == Some(PROTO_TCP),
+                FilterProto::Udp => info.protocol == Some(PROTO_UDP),
+            },
+            FilterExpr::Host(dir, addr) => Self::match_dir(*dir, info.src, info.dst, |a| a == *addr),
+            FilterExpr::Net(dir, net, prefix_len) => {
+                let mask = crate::net::interface::prefix_to_mask(*prefix_len);
+                Self::match_dir(*dir, info.src, info.dst, |a| a.same_subnet(net, mask))
+            }
+            FilterExpr::Port(dir, port) => {
+                Self::match_dir(*dir, info.src_port, info.dst_port, |p| p == *port)
+            }
+            FilterExpr::Not(inner) => !inner.matches(info),
+            FilterExpr::And(a, b) => a.matches(info) && b.matches(info),
+            FilterExpr::Or(a, b) => a.matches(info) || b.matches(info),
+        }
+    }
+
+    fn match_dir<T: Copy>(dir: FilterDir, src: Option<T>, dst: Option<T>, pred: impl Fn(T) -> bool) -> bool {
+        let src = src.is_some_and(&pred);
+        let dst = dst.is_some_and(&pred);
+        match dir {
+            FilterDir::Any => src || dst,
+            FilterDir::Src => src,
+            FilterDir::Dst => dst,
+        }
+    }
+}
+
+/// Filter parse errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    /// Expression ended where more input was expected
+    UnexpectedEnd,
+    /// Token not valid at this position
+    UnexpectedToken(String),
+    /// Malformed IPv4 address or network
+    InvalidAddress(String),
+    /// Malformed port number
+    InvalidPort(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::UnexpectedEnd => write!(f, "Unexpected end of filter expression"),
+            FilterError::UnexpectedToken(t) => write!(f, "Unexpected token in filter: '{}'", t),
+            FilterError::InvalidAddress(t) => write!(f, "Invalid address in filter: '{}'", t),
+            FilterError::InvalidPort(t) => write!(f, "Invalid port in filter: '{}'", t),
+        }
+    }
+}
+
+/// Compiled capture filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureFilter {
+    /// Source expression
+    source: String,
+    /// Parsed expression
+    expr: FilterExpr,
+}
+
+impl CaptureFilter {
+    /// Parse a filter expression (empty = match all)
+    pub fn parse(source: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(source);
+        let expr = if tokens.is_empty() {
+            FilterExpr::All
+        } else {
+            let mut parser = Parser { tokens: &tokens, pos: 0 };
+            let expr = parser.expr()?;
+            if let Some(token) = parser.peek() {
+                return Err(FilterError::UnexpectedToken(String::from(token)));
+            }
+            expr
+        };
+        Ok(CaptureFilter {
+            source: String::from(source.trim()),
+            expr,
+        })
+    }
+
+    /// Filter that matches every packet
+    pub fn all() -> Self {
+        CaptureFilter {
+            source: String::new(),
+            expr: FilterExpr::All,
+        }
+    }
+
+    /// Source expression
+    pub fn expression(&self) -> &str {
+        &self.source
+    }
+
+    /// Parsed expression tree
+    pub fn expr(&self) -> &FilterExpr {
+        &self.expr
+    }
+
+    /// Check an Ethernet frame against the filter
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        match self.expr {
+            FilterExpr::All => true,
+            _ => self.expr.matches(&PacketInfo::parse(frame)),
+        }
+    }
+}
+
+/// Split an expression into tokens (parentheses and `!` are separate tokens)
+fn tokenize(source: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in source.char_indices() {
+        let special = matches!(c, '(' | ')') || (c == '!' && !source[i..].starts_with("!="));
+        if c.is_whitespace() || special {
+            if let Some(s) = start.take() {
+                tokens.push(&source[s..i]);
+            }
+            if special {
+                tokens.push(&source[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&source[s..]);
+    }
+    tokens
+}
+
+/// Recursive-descent parser over tokens
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, FilterError> {
+        let token = self.peek().ok_or(FilterError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expr(&mut self) -> Result<FilterExpr, FilterError> {
+        let mut lhs = self.and_expr()?;
+        while matches!(self.peek(), Some("or" | "||")) {
+            self.pos += 1;
+            let rhs = self.and_expr()?;
+            lhs = FilterExpr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn and_expr(&mut self) -> Result<FilterExpr, FilterError> {
+        let mut lhs = self.unary()?;
+        loop {
+            match self.peek() {
+                Some("and" | "&&") => self.pos += 1,
+                // 暗黙のand（例: "tcp port 80"）
+                Some(token) if token != "or" && token != "||" && token != ")" => {}
+                _ => break,
+            }
+            let rhs = self.unary()?;
+            lhs = FilterExpr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<FilterExpr, FilterError> {
+        match self.next()? {
+            "not" | "!" => Ok(FilterExpr::Not(Box::new(self.unary()?))),
+            "(" => {
+                let inner = self.expr()?;
+                match self.next()? {
+                    ")" => Ok(inner),
+                    token => Err(FilterError::UnexpectedToken(String::from(token))),
+                }
+            }
+            token => self.primitive(token),
+        }
+    }
+
+    fn primitive(&mut self, token: &'a str) -> Result<FilterExpr, FilterError> {
+        let proto = match token {
+            "arp" => Some(FilterProto::Arp),
+            "ip" => Some(FilterProto::Ip),
+            "icmp" => Some(FilterProto::Icmp),
+            "tcp" => Some(FilterProto::Tcp),
+            "udp" => Some(FilterProto::Udp),
+            _ => None,
+        };
+        if let Some(proto) = proto {
+            return Ok(FilterExpr::Proto(proto));
+        }
+
+        let (dir, keyword) = match token {
+            "src" => (FilterDir::Src, self.next()?),
+            "dst" => (FilterDir::Dst, self.next()?),
+            _ => (FilterDir::Any, token),
+        };
+        match keyword {
+            "host" => Ok(FilterExpr::Host(dir, parse_address(self.next()?)?)),
+            "net" => {
+                let value = self.next()?;
+                let (addr, prefix_len) = match value.split_once('/') {
+                    Some((addr, len)) => (
+                        addr,
+                        len.parse::<u8>()
+                            .ok()
+                            .filter(|len| *len <= 32)
+                            .ok_or_else(|| FilterError::InvalidAddress(String::from(value)))?,
+                    ),
+                    None => (value, 32),
+                };
+                Ok(FilterExpr::Net(dir, parse_address(addr)?, prefix_len))
+            }
+            "port" => {
+                let value = self.next()?;
+                let port = value
+                    .parse::<u16>()
+                    .map_err(|_| FilterError::InvalidPort(String::from(value)))?;
+                Ok(FilterExpr::Port(dir, port))
+            }
+            // "src 10.0.2.2" は "src host 10.0.2.2" と同じ
+            other if dir != FilterDir::Any => Ok(FilterExpr::Host(dir, parse_address(other)?)),
+            other => Err(FilterError::UnexpectedToken(String::from(other))),
+        }
+    }
+}
+
+/// Parse a dotted-quad IPv4 address
+fn parse_address(s: &str) -> Result<Ipv4Address, FilterError> {
+    let mut octets = [0u8; 4];
+    let mut parts = s.split('.');
+    for octet in octets.iter_mut() {
+        *octet = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(|| FilterError::InvalidAddress(String::from(s)))?;
+    }
+    if parts.next().is_some() {
+        return Err(FilterError::InvalidAddress(String::from(s)));
+    }
+    Ok(Ipv4Address::new(octets))
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet + IPv4 + TCP/UDPヘッダ（ポートまで）のフレームを作る
+    fn ipv4_frame(protocol: u8, src: [u8; 4], dst: [u8; 4], sport: u16, dport: u16) -> Vec<u8> {
+        let mut frame = alloc::vec![0u8; 14 + 20 + 8];
+        frame[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame[14] = 0x45;
+        frame[23] = protocol;
+        frame[26..30].copy_from_slice(&src);
+        frame[30..34].copy_from_slice(&dst);
+        frame[34..36].copy_from_slice(&sport.to_be_bytes());
+        frame[36..38].copy_from_slice(&dport.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_dissect_tcp_frame() {
+        let frame = ipv4_frame(PROTO_TCP, [10, 0, 2, 15], [10, 0, 2, 2], 49152, 80);
+        let info = PacketInfo::parse(&frame);
+        assert_eq!(info.protocol, Some(PROTO_TCP));
+        assert_eq!(info.dst_port, Some(80));
+        assert_eq!(info.summary(), "TCP 10.0.2.15:49152 > 10.0.2.2:80");
+    }
+
+    #[test]
+    fn test_filter_proto_port_host() {
+        let http = ipv4_frame(PROTO_TCP, [10, 0, 2, 15], [10, 0, 2, 2], 49152, 80);
+        let dns = ipv4_frame(PROTO_UDP, [10, 0, 2, 15], [10, 0, 2, 3], 5353, 53);
+
+        let filter = CaptureFilter::parse("tcp port 80 and host 10.0.2.2").unwrap();
+        assert!(filter.matches(&http));
+        assert!(!filter.matches(&dns));
+
+        let filter = CaptureFilter::parse("udp and not port 53").unwrap();
+        assert!(!filter.matches(&dns));
+
+        let filter = CaptureFilter::parse("dst net 10.0.2.0/24 && (port 53 || port 80)").unwrap();
+        assert!(filter.matches(&http));
+        assert!(filter.matches(&dns));
+
+        let filter = CaptureFilter::parse("src 10.0.2.2").unwrap();
+        assert!(!filter.matches(&http));
+        assert!(CaptureFilter::parse("").unwrap().matches(&dns));
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert_eq!(CaptureFilter::parse("port"), Err(FilterError::UnexpectedEnd));
+        assert_eq!(
+            CaptureFilter::parse("port http"),
+            Err(FilterError::InvalidPort(String::from("http")))
+        );
+        assert_eq!(
+            CaptureFilter::parse("host 10.0.2"),
+            Err(FilterError::InvalidAddress(String::from("10.0.2")))
+        );
+        assert_eq!(
+            CaptureFilter::parse("(tcp"),
+            Err(FilterError::UnexpectedEnd)
+        );
+        assert_eq!(
+            CaptureFilter::parse("tcp )"),
+            Err(FilterError::UnexpectedToken(String::from(")")))
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! # pcapng Writer - pcapngファイル生成
+//!
+//! PCAP Next Generation 形式（リトルエンディアン）でブロックを組み立てる。
+//! - Section Header Block (SHB)
+//! - Interface Description Block (IDB)
+//! - Enhanced Packet Block (EPB) + epb_flags（送受信方向）
+//!
+//! タイムスタンプはマイクロ秒単位（if_tsresol 省略時の既定値）。
+
+use alloc::vec::Vec;
+
+/// Block type: Section Header
+pub const BLOCK_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+/// Block type: Interface Description
+pub const BLOCK_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+/// Block type: Enhanced Packet
+pub const BLOCK_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+/// Byte-order magic
+pub const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// LINKTYPE_ETHERNET
+pub const LINKTYPE_ETHERNET: u16 = 1;
+/// LINKTYPE_RAW (IPv4/IPv6 without link-layer header)
+pub const LINKTYPE_RAW: u16 = 101;
+
+/// epb_flags: inbound
+pub const EPB_FLAG_INBOUND: u32 = 0b01;
+/// epb_flags: outbound
+pub const EPB_FLAG_OUTBOUND: u32 = 0b10;
+
+/// Option codes
+const OPT_END: u16 = 0;
+const SHB_USERAPPL: u16 = 4;
+const IF_NAME: u16 = 2;
+const EPB_FLAGS: u16 = 2;
+
+/// Incremental pcapng writer
+#[derive(Debug, Default)]
+pub struct PcapngWriter {
+    /// Encoded blocks
+    buf: Vec<u8>,
+    /// Interfaces described so far
+    interfaces: u32,
+}
+
+impl PcapngWriter {
+    /// Create a writer and emit the Section Header Block
+    pub fn new(application: &str) -> Self {
+        let mut writer = PcapngWriter::default();
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes()); // major
+        body.extend_from_slice(&0u16.to_le_bytes()); // minor
+        body.extend_from_slice(&(-1i64).to_le_bytes()); // section length: 未指定
+        push_option(&mut body, SHB_USERAPPL, application.as_bytes());
+        push_option(&mut body, OPT_END, &[]);
+        writer.block(BLOCK_SECTION_HEADER, &body);
+        writer
+    }
+
+    /// Emit an Interface Description Block, returning its interface ID
+    pub fn add_interface(&mut self, link_type: u16, snaplen: u32, name: &str) -> u32 {
+        let mut body = Vec::new();
+        body.extend_from_slice(&link_type.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes()); // reserved
+        body.extend_from_slice(&snaplen.to_le_bytes());
+        push_option(&mut body, IF_NAME, name.as_bytes());
+        push_option(&mut body, OPT_END, &[]);
+        self.block(BLOCK_INTERFACE_DESCRIPTION, &body);
+
+        let id = self.interfaces;
+        self.interfaces += 1;
+        id
+    }
+
+    /// Emit an Enhanced Packet Block
+    pub fn add_packet(
+        &mut self,
+        interface_id: u32,
+        timestamp_us: u64,
+        data: &[u8],
+        original_len: usize,
+        flags: u32,
+    ) {
+        let mut body = Vec::with_capacity(20 + data.len() + 16);
+        body.extend_from_slice(&interface_id.to_le_bytes());
+        body.extend_from_slice(&((timestamp_us >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp_us as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(original_len as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        pad32(&mut body);
+        if flags != 0 {
+            push_option(&mut body, EPB_FLAGS, &flags.to_le_bytes());
+            push_option(&mut body, OPT_END, &[]);
+        }
+        self.block(BLOCK_ENHANCED_PACKET, &body);
+    }
+
+    /// Encoded bytes so far
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Finish and take the encoded file
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Append a block: type, total length, body, total length
+    fn block(&mut self, block_type: u32, body: &[u8]) {
+        let total_len = (12 + body.len()) as u32;
+        self.buf.extend_from_slice(&block_type.to_le_bytes());
+        self.buf.extend_from_slice(&total_len.to_le_bytes());
+        self.buf.extend_from_slice(body);
+        self.buf.extend_from_slice(&total_len.to_le_bytes());
+    }
+}
+
+/// Append an option (code, length, value padded to 32 bits)
+fn push_option(buf: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buf.extend_from_slice(&code.to_le_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buf.extend_from_slice(value);
+    pad32(buf);
+}
+
+/// Pad to a 32-bit boundary
+fn pad32(buf: &mut Vec<u8>) {
+    while buf.len() % 4 != 0 {
+        buf.push(0);
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(buf: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+    }
+
+    /// ブロック列を (type, 開始位置) に分解し、先頭と末尾の長さが一致することを確認
+    fn blocks(buf: &[u8]) -> Vec<(u32, usize)> {
+        let mut result = Vec::new();
+        let mut offset = 0;
+        while offset < buf.len() {
+            let len = read_u32(buf, offset + 4) as usize;
+            assert_eq!(len % 4, 0);
+            assert_eq!(read_u32(buf, offset + len - 4) as usize, len);
+            result.push((read_u32(buf, offset), offset));
+            offset += len;
+        }
+        assert_eq!(offset, buf.len());
+        result
+    }
+
+    #[test]
+    fn test_pcapng_layout() {
+        let mut writer = PcapngWriter::new("Rany_OS");
+        let id = writer.add_interface(LINKTYPE_ETHERNET, 65535, "eth0");
+        assert_eq!(id, 0);
+        writer.add_packet(id, 0x1_0000_0002, &[0xAA; 61], 1514, EPB_FLAG_INBOUND);
+        let buf = writer.finish();
+
+        let blocks = blocks(&buf);
+        let types: Vec<u32> = blocks.iter().map(|(t, _)| *t).collect();
+        assert_eq!(
+            types,
+            [BLOCK_SECTION_HEADER, BLOCK_INTERFACE_DESCRIPTION, BLOCK_ENHANCED_PACKET]
+        );
+        assert_eq!(read_u32(&buf, 8), BYTE_ORDER_MAGIC);
+
+        let idb = blocks[1].1;
+        assert_eq!(u16::from_le_bytes([buf[idb + 8], buf[idb + 9]]), LINKTYPE_ETHERNET);
+
+        let epb = blocks[2].1;
+        assert_eq!(read_u32(&buf, epb + 12), 1); // timestamp high
+        assert_eq!(read_u32(&buf, epb + 16), 2); // timestamp low
+        assert_eq!(read_u32(&buf, epb + 20), 61); // captured length
+        assert_eq!(read_u32(&buf, epb + 24), 1514); // original length
+        // パケットデータは4バイト境界までパディングされ、その後にepb_flags
+        let opt = epb + 28 + 64;
+        assert_eq!(u16::from_le_bytes([buf[opt], buf[opt + 1]]), EPB_FLAGS);
+        assert_eq!(read_u32(&buf, opt + 4), EPB_FLAG_INBOUND);
+    }
+}
@@ -2,11 +2,14 @@
 //!
 //! DHCPを使用してIPアドレス、サブネットマスク、ゲートウェイ、
 //! DNSサーバーなどのネットワーク設定を自動取得する。
+//! ルーターとしてアドレスを配るサーバーは `server`。
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+pub mod server;
+
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use spin::Mutex;
@@ -91,6 +94,8 @@ pub enum DhcpOption {
     Hostname = 12,
     /// ドメイン名
     DomainName = 15,
+    /// NTPサーバー
+    NtpServers = 42,
     /// 要求されたIPアドレス
     RequestedIp = 50,
     /// リース時間
@@ -197,6 +202,8 @@ pub struct DhcpLease {
     pub hostname: Option<Vec<u8>>,
     /// ドメイン名
     pub domain_name: Option<Vec<u8>>,
+    /// NTPサーバー
+    pub ntp_servers: Vec<Ipv4Address>,
 }
 
 impl DhcpLease {
@@ -316,12 +323,13 @@ impl DhcpClient {
 
         // パラメータ要求リスト
         buffer[offset] = DhcpOption::ParameterRequestList as u8;
-        buffer[offset + 1] = 4;
+        buffer[offset + 1] = 5;
         buffer[offset + 2] = DhcpOption::SubnetMask as u8;
         buffer[offset + 3] = DhcpOption::Router as u8;
         buffer[offset + 4] = DhcpOption::DnsServer as u8;
         buffer[offset + 5] = DhcpOption::DomainName as u8;
-        offset += 6;
+        buffer[offset + 6] = DhcpOption::NtpServers as u8;
+        offset += 7;
 
         // クライアント識別子
         buffer[offset] = DhcpOption::ClientIdentifier as u8;
@@ -395,12 +403,13 @@ impl DhcpClient {
 
         // パラメータ要求リスト
         buffer[offset] = DhcpOption::ParameterRequestList as u8;
-        buffer[offset + 1] = 4;
+        buffer[offset + 1] = 5;
         buffer[offset + 2] = DhcpOption::SubnetMask as u8;
         buffer[offset + 3] = DhcpOption::Router as u8;
         buffer[offset + 4] = DhcpOption::DnsServer as u8;
         buffer[offset + 5] = DhcpOption::DomainName as u8;
-        offset += 6;
+        buffer[offset + 6] = DhcpOption::NtpServers as u8;
+        offset += 7;
 
         // クライアント識別子
         buffer[offset] = DhcpOption::ClientIdentifier as u8;
@@ -454,6 +463,7 @@ impl DhcpClient {
         let mut subnet_mask = None;
         let mut router = None;
         let mut dns_servers = Vec::new();
+        let mut ntp_servers = Vec::new();
         let mut lease_time = 86400u32; // デフォルト1日
         let mut server_id = None;
         let mut hostname = None;
@@ -512,6 +522,16 @@ impl DhcpClient {
                         }
                     }
                 }
+                42 => {
+                    // NTP Servers
+                    for chunk in opt_data.chunks(4) {
+                        if chunk.len() == 4 {
+                            let mut bytes = [0u8; 4];
+                            bytes.copy_from_slice(chunk);
+                            ntp_servers.push(Ipv4Address::new(bytes));
+                        }
+                    }
+                }
                 51 => {
                     // Lease Time
                     if opt_data.len() >= 4 {
@@ -556,6 +576,7 @@ impl DhcpClient {
                     obtained_at: current_tick,
                     hostname,
                     domain_name,
+                    ntp_servers,
                 };
 
                 *self.offered_lease.lock() = Some(lease.clone());
@@ -573,6 +594,7 @@ impl DhcpClient {
                     obtained_at: current_tick,
                     hostname,
                     domain_name,
+                    ntp_servers,
                 };
 
                 *self.lease.lock() = Some(lease.clone());
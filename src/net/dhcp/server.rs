@@ -0,0 +1,997 @@
+//! DHCPv4 サーバー
+//!
+//! 隔離したネットワークでカーネル自身がルーターとなり、他のホストへアドレスを配る。
+//!
+//! - アドレスプール（複数の範囲）と MAC アドレスによる固定割り当て
+//! - DISCOVER / REQUEST / DECLINE / RELEASE / INFORM を処理（RFC 2131）
+//! - ルーター・DNS・ドメイン名・NTP サーバーのオプションを配布
+//! - 確定したリースをファイルに保存し、再起動後も同じアドレスを返す
+//!
+//! リースの期限は Unix 時刻（秒）で持つ。`DhcpServer` はパケットを受け取って
+//! 応答を返すだけで、送受信は `start` が起動するタスクが行う。
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use spin::Mutex;
+
+use super::{
+    DHCP_CLIENT_PORT, DHCP_MAGIC_COOKIE, DHCP_SERVER_PORT, DhcpHeader, DhcpMessageType,
+    DhcpOperation, DhcpOption,
+};
+use crate::net::ethernet::MacAddress;
+use crate::net::ipv4::Ipv4Address;
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// 既定のリース時間 (秒)
+pub const DEFAULT_LEASE_TIME: u32 = 3600;
+
+/// OFFER したアドレスを予約しておく時間 (秒)
+pub const OFFER_HOLD_SECS: u64 = 60;
+
+/// 既定のリースファイル
+pub const DEFAULT_LEASE_FILE: &str = "/var/lib/dhcpd.leases";
+
+/// BOOTP の最小メッセージ長（古いクライアント向けにパディングする）
+const MIN_MESSAGE_SIZE: usize = 300;
+
+/// 受信のポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// オプション: 更新時間 T1
+const OPTION_RENEWAL_TIME: u8 = 58;
+/// オプション: 再バインド時間 T2
+const OPTION_REBINDING_TIME: u8 = 59;
+/// オプション: サーバーからのメッセージ
+const OPTION_MESSAGE: u8 = 56;
+
+// ============================================================================
+// 設定
+// ============================================================================
+
+/// アドレスプール（両端を含む）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressPool {
+    /// 先頭
+    pub start: Ipv4Address,
+    /// 末尾
+    pub end: Ipv4Address,
+}
+
+impl AddressPool {
+    /// プールを作成
+    pub const fn new(start: Ipv4Address, end: Ipv4Address) -> Self {
+        Self { start, end }
+    }
+
+    /// アドレスを含むか
+    pub fn contains(&self, address: Ipv4Address) -> bool {
+        (self.start.to_u32()..=self.end.to_u32()).contains(&address.to_u32())
+    }
+
+    /// 含まれるアドレスを順に列挙
+    pub fn addresses(&self) -> impl Iterator<Item = Ipv4Address> {
+        (self.start.to_u32()..=self.end.to_u32()).map(Ipv4Address::from_u32)
+    }
+}
+
+/// 固定割り当て
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticLease {
+    /// クライアントの MAC アドレス
+    pub mac: MacAddress,
+    /// 割り当てるアドレス
+    pub address: Ipv4Address,
+}
+
+/// サーバー設定
+#[derive(Debug, Clone)]
+pub struct DhcpServerConfig {
+    /// サーバー自身のアドレス（サーバー識別子）
+    pub server_address: Ipv4Address,
+    /// サブネットマスク
+    pub subnet_mask: Ipv4Address,
+    /// アドレスプール
+    pub pools: Vec<AddressPool>,
+    /// 固定割り当て
+    pub static_leases: Vec<StaticLease>,
+    /// デフォルトゲートウェイ（オプション3）
+    pub router: Option<Ipv4Address>,
+    /// DNS サーバー（オプション6）
+    pub dns_servers: Vec<Ipv4Address>,
+    /// ドメイン名（オプション15）
+    pub domain_name: Option<String>,
+    /// NTP サーバー（オプション42）
+    pub ntp_servers: Vec<Ipv4Address>,
+    /// リース時間 (秒)
+    pub lease_time: u32,
+    /// リースの保存先（None なら保存しない）
+    pub lease_file: Option<String>,
+}
+
+impl DhcpServerConfig {
+    /// サーバー自身をルーター・DNS・NTP として配る設定を作成
+    pub fn new(server_address: Ipv4Address, subnet_mask: Ipv4Address) -> Self {
+        Self {
+            server_address,
+            subnet_mask,
+            pools: Vec::new(),
+            static_leases: Vec::new(),
+            router: Some(server_address),
+            dns_servers: alloc::vec![server_address],
+            domain_name: None,
+            ntp_servers: Vec::new(),
+            lease_time: DEFAULT_LEASE_TIME,
+            lease_file: Some(String::from(DEFAULT_LEASE_FILE)),
+        }
+    }
+
+    /// プールを追加
+    pub fn pool(mut self, start: Ipv4Address, end: Ipv4Address) -> Self {
+        self.pools.push(AddressPool::new(start, end));
+        self
+    }
+
+    /// 固定割り当てを追加
+    pub fn static_lease(mut self, mac: MacAddress, address: Ipv4Address) -> Self {
+        self.static_leases.push(StaticLease { mac, address });
+        self
+    }
+
+    /// 設定の整合性を確認
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pools.is_empty() && self.static_leases.is_empty() {
+            return Err(String::from("no address pool or static lease configured"));
+        }
+        let on_subnet =
+            |address: Ipv4Address| address.same_subnet(&self.server_address, self.subnet_mask);
+        for pool in &self.pools {
+            if pool.start.to_u32() > pool.end.to_u32() {
+                return Err(format!("pool {}-{} is reversed", pool.start, pool.end));
+            }
+            if !on_subnet(pool.start) || !on_subnet(pool.end) {
+                return Err(format!(
+                    "pool {}-{} is outside the server subnet",
+                    pool.start, pool.end
+                ));
+            }
+        }
+        for lease in &self.static_leases {
+            if !on_subnet(lease.address) {
+                return Err(format!(
+                    "static lease {} is outside the server subnet",
+                    lease.address
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// リース
+// ============================================================================
+
+/// リースの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaseState {
+    /// OFFER 済み（REQUEST 待ち）
+    Offered,
+    /// 確定
+    Bound,
+    /// クライアントが使用中と報告した（DECLINE）
+    Declined,
+}
+
+/// サーバー側のリース
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerLease {
+    /// クライアントの MAC アドレス
+    pub mac: MacAddress,
+    /// 割り当てたアドレス
+    pub address: Ipv4Address,
+    /// 期限 (Unix 時刻, 秒)
+    pub expires: u64,
+    /// 状態
+    pub state: LeaseState,
+    /// クライアントのホスト名（オプション12）
+    pub hostname: Option<String>,
+}
+
+// ============================================================================
+// メッセージ
+// ============================================================================
+
+/// クライアントからのメッセージ
+#[derive(Debug, Clone)]
+pub struct ClientMessage {
+    /// メッセージタイプ
+    pub message_type: DhcpMessageType,
+    /// トランザクションID
+    pub xid: [u8; 4],
+    /// フラグ
+    pub flags: [u8; 2],
+    /// クライアントの現在のアドレス
+    pub ciaddr: Ipv4Address,
+    /// リレーエージェントのアドレス
+    pub giaddr: Ipv4Address,
+    /// クライアントの MAC アドレス
+    pub mac: MacAddress,
+    /// 要求されたアドレス（オプション50）
+    pub requested: Option<Ipv4Address>,
+    /// 選んだサーバー（オプション54）
+    pub server_id: Option<Ipv4Address>,
+    /// ホスト名（オプション12）
+    pub hostname: Option<String>,
+}
+
+impl ClientMessage {
+    /// BOOTREQUEST を解析
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < DhcpHeader::SIZE + 4 {
+            return None;
+        }
+        let header = unsafe { &*(data.as_ptr() as *const DhcpHeader) };
+        if header.op != DhcpOperation::Request as u8 || header.htype != 1 || header.hlen != 6 {
+            return None;
+        }
+        if data[DhcpHeader::SIZE..DhcpHeader::SIZE + 4] != DHCP_MAGIC_COOKIE {
+            return None;
+        }
+        let chaddr = header.chaddr;
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&chaddr[..6]);
+
+        let mut message_type = None;
+        let mut requested = None;
+        let mut server_id = None;
+        let mut hostname = None;
+        let address = |value: &[u8]| {
+            (value.len() == 4).then(|| Ipv4Address::new([value[0], value[1], value[2], value[3]]))
+        };
+
+        let mut offset = DhcpHeader::SIZE + 4;
+        while offset < data.len() {
+            let code = data[offset];
+            if code == DhcpOption::End as u8 {
+                break;
+            }
+            if code == DhcpOption::Pad as u8 {
+                offset += 1;
+                continue;
+            }
+            let len = *data.get(offset + 1)? as usize;
+            let value = data.get(offset + 2..offset + 2 + len)?;
+            match code {
+                53 => message_type = value.first().and_then(|t| DhcpMessageType::from_u8(*t)),
+                50 => requested = address(value),
+                54 => server_id = address(value),
+                12 => hostname = Some(String::from_utf8_lossy(value).into_owned()),
+                _ => {}
+            }
+            offset += 2 + len;
+        }
+
+        Some(Self {
+            message_type: message_type?,
+            xid: header.xid,
+            flags: header.flags,
+            ciaddr: header.ciaddr(),
+            giaddr: Ipv4Address::new(header.giaddr),
+            mac: MacAddress::new(mac),
+            requested,
+            server_id,
+            hostname,
+        })
+    }
+}
+
+/// 送信する応答
+#[derive(Debug, Clone)]
+pub struct Reply {
+    /// 宛先アドレス
+    pub destination: Ipv4Address,
+    /// 宛先ポート
+    pub port: u16,
+    /// メッセージ
+    pub data: Vec<u8>,
+}
+
+// ============================================================================
+// サーバー
+// ============================================================================
+
+/// DHCP サーバーの状態
+pub struct DhcpServer {
+    config: DhcpServerConfig,
+    leases: Vec<ServerLease>,
+}
+
+impl DhcpServer {
+    /// サーバーを作成
+    pub fn new(config: DhcpServerConfig) -> Self {
+        Self {
+            config,
+            leases: Vec::new(),
+        }
+    }
+
+    /// 設定
+    pub fn config(&self) -> &DhcpServerConfig {
+        &self.config
+    }
+
+    /// リース一覧
+    pub fn leases(&self) -> &[ServerLease] {
+        &self.leases
+    }
+
+    /// 期限切れのリースを捨てる
+    pub fn expire(&mut self, now: u64) {
+        self.leases.retain(|lease| lease.expires > now);
+    }
+
+    /// MAC アドレスの固定割り当て
+    fn static_address(&self, mac: MacAddress) -> Option<Ipv4Address> {
+        self.config
+            .static_leases
+            .iter()
+            .find(|lease| lease.mac == mac)
+            .map(|lease| lease.address)
+    }
+
+    /// 有効なリース（DECLINE 以外）
+    fn lease_of(&self, mac: MacAddress) -> Option<&ServerLease> {
+        self.leases
+            .iter()
+            .find(|lease| lease.mac == mac && lease.state != LeaseState::Declined)
+    }
+
+    /// `mac` に割り当ててよいアドレスか
+    fn is_assignable(&self, address: Ipv4Address, mac: MacAddress) -> bool {
+        if let Some(reserved) = self.static_address(mac) {
+            return address == reserved;
+        }
+        address != self.config.server_address
+            && self.config.pools.iter().any(|pool| pool.contains(address))
+            && !self
+                .config
+                .static_leases
+                .iter()
+                .any(|lease| lease.address == address)
+            && !self.leases.iter().any(|lease| {
+                lease.address == address
+                    && (lease.mac != mac || lease.state == LeaseState::Declined)
+            })
+    }
+
+    /// DISCOVER に対して提案するアドレス
+    fn select_address(&self, message: &ClientMessage) -> Option<Ipv4Address> {
+        if let Some(address) = self.static_address(message.mac) {
+            return Some(address);
+        }
+        if let Some(lease) = self.lease_of(message.mac) {
+            return Some(lease.address);
+        }
+        if let Some(requested) = message.requested
+            && self.is_assignable(requested, message.mac)
+        {
+            return Some(requested);
+        }
+        self.config
+            .pools
+            .iter()
+            .flat_map(|pool| pool.addresses())
+            .find(|address| self.is_assignable(*address, message.mac))
+    }
+
+    /// リースを記録（同じ MAC・同じアドレスの古い記録は置き換える）
+    fn record(&mut self, lease: ServerLease) {
+        self.leases
+            .retain(|l| l.mac != lease.mac && l.address != lease.address);
+        self.leases.push(lease);
+    }
+
+    /// メッセージを処理し、必要なら応答を返す
+    ///
+    /// 2つ目の値はリースが確定・解放されたか（保存が必要か）。
+    pub fn handle(&mut self, data: &[u8], now: u64) -> (Option<Reply>, bool) {
+        self.expire(now);
+        let Some(message) = ClientMessage::parse(data) else {
+            return (None, false);
+        };
+        match message.message_type {
+            DhcpMessageType::Discover => (self.discover(&message, now), false),
+            DhcpMessageType::Request => self.request(&message, now),
+            DhcpMessageType::Decline => {
+                let Some(address) = message.requested else {
+                    return (None, false);
+                };
+                if self
+                    .lease_of(message.mac)
+                    .is_some_and(|l| l.address == address)
+                {
+                    self.record(ServerLease {
+                        mac: message.mac,
+                        address,
+                        expires: now + self.config.lease_time as u64,
+                        state: LeaseState::Declined,
+                        hostname: None,
+                    });
+                    return (None, true);
+                }
+                (None, false)
+            }
+            DhcpMessageType::Release => {
+                let before = self.leases.len();
+                self.leases
+                    .retain(|lease| !(lease.mac == message.mac && lease.address == message.ciaddr));
+                (None, self.leases.len() != before)
+            }
+            DhcpMessageType::Inform => (
+                Some(self.reply(&message, DhcpMessageType::Ack, Ipv4Address::ANY)),
+                false,
+            ),
+            _ => (None, false),
+        }
+    }
+
+    fn discover(&mut self, message: &ClientMessage, now: u64) -> Option<Reply> {
+        let address = self.select_address(message)?;
+        // 確定済みのリースは期限を縮めない
+        if self
+            .lease_of(message.mac)
+            .is_none_or(|lease| lease.address != address || lease.state != LeaseState::Bound)
+        {
+            self.record(ServerLease {
+                mac: message.mac,
+                address,
+                expires: now + OFFER_HOLD_SECS,
+                state: LeaseState::Offered,
+                hostname: message.hostname.clone(),
+            });
+        }
+        Some(self.reply(message, DhcpMessageType::Offer, address))
+    }
+
+    fn request(&mut self, message: &ClientMessage, now: u64) -> (Option<Reply>, bool) {
+        let Some(address) = message
+            .requested
+            .or(Some(message.ciaddr).filter(|a| !a.is_any()))
+        else {
+            return (None, false);
+        };
+
+        match message.server_id {
+            // SELECTING: 他のサーバーを選んだなら提案を取り消す
+            Some(server) if server != self.config.server_address => {
+                self.leases.retain(|lease| {
+                    !(lease.mac == message.mac && lease.state == LeaseState::Offered)
+                });
+                return (None, false);
+            }
+            Some(_) => {
+                if self
+                    .lease_of(message.mac)
+                    .is_none_or(|lease| lease.address != address)
+                {
+                    return (Some(self.nak(message, "offer expired")), false);
+                }
+            }
+            // INIT-REBOOT / RENEWING / REBINDING
+            None => {
+                if !address.same_subnet(&self.config.server_address, self.config.subnet_mask) {
+                    return (Some(self.nak(message, "wrong network")), false);
+                }
+                if !self.is_assignable(address, message.mac) {
+                    return (Some(self.nak(message, "address not available")), false);
+                }
+            }
+        }
+
+        let hostname = message
+            .hostname
+            .clone()
+            .or_else(|| self.lease_of(message.mac).and_then(|l| l.hostname.clone()));
+        self.record(ServerLease {
+            mac: message.mac,
+            address,
+            expires: now + self.config.lease_time as u64,
+            state: LeaseState::Bound,
+            hostname,
+        });
+        (
+            Some(self.reply(message, DhcpMessageType::Ack, address)),
+            true,
+        )
+    }
+
+    /// 応答の宛先（RFC 2131 4.1）
+    fn destination(message: &ClientMessage, message_type: DhcpMessageType) -> (Ipv4Address, u16) {
+        if !message.giaddr.is_any() {
+            (message.giaddr, DHCP_SERVER_PORT)
+        } else if message_type != DhcpMessageType::Nak && !message.ciaddr.is_any() {
+            (message.ciaddr, DHCP_CLIENT_PORT)
+        } else {
+            // ARP を引けないクライアントにはブロードキャストで返す
+            (Ipv4Address::BROADCAST, DHCP_CLIENT_PORT)
+        }
+    }
+
+    fn nak(&self, message: &ClientMessage, reason: &str) -> Reply {
+        let mut reply = self.build(message, DhcpMessageType::Nak, Ipv4Address::ANY);
+        push_option(&mut reply.data, OPTION_MESSAGE, reason.as_bytes());
+        finish(&mut reply.data);
+        reply
+    }
+
+    fn reply(
+        &self,
+        message: &ClientMessage,
+        message_type: DhcpMessageType,
+        address: Ipv4Address,
+    ) -> Reply {
+        let config = &self.config;
+        let mut reply = self.build(message, message_type, address);
+        let data = &mut reply.data;
+
+        if message.message_type != DhcpMessageType::Inform {
+            let lease_time = config.lease_time;
+            push_option(data, DhcpOption::LeaseTime as u8, &lease_time.to_be_bytes());
+            push_option(data, OPTION_RENEWAL_TIME, &(lease_time / 2).to_be_bytes());
+            push_option(
+                data,
+                OPTION_REBINDING_TIME,
+                &(lease_time / 8 * 7).to_be_bytes(),
+            );
+        }
+        push_option(
+            data,
+            DhcpOption::SubnetMask as u8,
+            config.subnet_mask.as_bytes(),
+        );
+        if let Some(router) = config.router {
+            push_option(data, DhcpOption::Router as u8, router.as_bytes());
+        }
+        push_addresses(data, DhcpOption::DnsServer as u8, &config.dns_servers);
+        if let Some(domain) = &config.domain_name {
+            push_option(data, DhcpOption::DomainName as u8, domain.as_bytes());
+        }
+        push_addresses(data, DhcpOption::NtpServers as u8, &config.ntp_servers);
+        finish(data);
+        reply
+    }
+
+    /// ヘッダとメッセージタイプ・サーバー識別子まで書く
+    fn build(
+        &self,
+        message: &ClientMessage,
+        message_type: DhcpMessageType,
+        yiaddr: Ipv4Address,
+    ) -> Reply {
+        let mut data = alloc::vec![0u8; DhcpHeader::SIZE];
+        data[0] = DhcpOperation::Reply as u8;
+        data[1] = 1;
+        data[2] = 6;
+        data[4..8].copy_from_slice(&message.xid);
+        data[10..12].copy_from_slice(&message.flags);
+        if message_type != DhcpMessageType::Nak {
+            data[12..16].copy_from_slice(message.ciaddr.as_bytes());
+        }
+        data[16..20].copy_from_slice(yiaddr.as_bytes());
+        if message_type != DhcpMessageType::Nak {
+            data[20..24].copy_from_slice(self.config.server_address.as_bytes());
+        }
+        data[24..28].copy_from_slice(message.giaddr.as_bytes());
+        data[28..34].copy_from_slice(message.mac.as_bytes());
+        data.extend_from_slice(&DHCP_MAGIC_COOKIE);
+        push_option(
+            &mut data,
+            DhcpOption::MessageType as u8,
+            &[message_type as u8],
+        );
+        push_option(
+            &mut data,
+            DhcpOption::ServerIdentifier as u8,
+            self.config.server_address.as_bytes(),
+        );
+
+        let (destination, port) = Self::destination(message, message_type);
+        Reply {
+            destination,
+            port,
+            data,
+        }
+    }
+
+    /// 確定したリースをファイル形式で書き出す
+    ///
+    /// 1行に1リース: `<MAC> <アドレス> <期限 (Unix 秒)> [ホスト名]`
+    pub fn save_leases(&self) -> String {
+        let mut text = String::from("# mac address expires hostname\n");
+        for lease in self.leases.iter().filter(|l| l.state == LeaseState::Bound) {
+            text.push_str(&format!(
+                "{} {} {}",
+                lease.mac, lease.address, lease.expires
+            ));
+            if let Some(hostname) = &lease.hostname {
+                text.push(' ');
+                text.push_str(&hostname.replace(char::is_whitespace, "_"));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// ファイル形式のリースを読み込む（期限切れ・割り当てられないものは捨てる）
+    pub fn load_leases(&mut self, text: &str, now: u64) -> usize {
+        let mut loaded = 0;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (Some(mac), Some(address), Some(expires)) = (
+                fields.next().and_then(MacAddress::parse),
+                fields.next().and_then(parse_address),
+                fields.next().and_then(|e| e.parse::<u64>().ok()),
+            ) else {
+                continue;
+            };
+            if expires <= now || !self.is_assignable(address, mac) {
+                continue;
+            }
+            self.record(ServerLease {
+                mac,
+                address,
+                expires,
+                state: LeaseState::Bound,
+                hostname: fields.next().map(ToString::to_string),
+            });
+            loaded += 1;
+        }
+        loaded
+    }
+}
+
+/// オプションを追加
+fn push_option(data: &mut Vec<u8>, code: u8, value: &[u8]) {
+    let value = &value[..value.len().min(255)];
+    data.push(code);
+    data.push(value.len() as u8);
+    data.extend_from_slice(value);
+}
+
+/// アドレスの並びのオプションを追加（空なら省く）
+fn push_addresses(data: &mut Vec<u8>, code: u8, addresses: &[Ipv4Address]) {
+    if addresses.is_empty() {
+        return;
+    }
+    let bytes: Vec<u8> = addresses
+        .iter()
+        .take(63)
+        .flat_map(|address| *address.as_bytes())
+        .collect();
+    push_option(data, code, &bytes);
+}
+
+/// 終端を書き、最小長までパディング
+fn finish(data: &mut Vec<u8>) {
+    data.push(DhcpOption::End as u8);
+    if data.len() < MIN_MESSAGE_SIZE {
+        data.resize(MIN_MESSAGE_SIZE, 0);
+    }
+}
+
+/// "a.b.c.d" を解析
+fn parse_address(s: &str) -> Option<Ipv4Address> {
+    let mut octets = [0u8; 4];
+    let mut parts = s.split('.');
+    for octet in octets.iter_mut() {
+        *octet = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Ipv4Address::new(octets))
+}
+
+// ============================================================================
+// 実行
+// ============================================================================
+
+/// 実行中のサーバー
+static SERVER: Mutex<Option<DhcpServer>> = Mutex::new(None);
+
+/// 受信ループの世代（`stop` で進める）
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// サーバーを開始
+///
+/// 保存済みのリースを読み込み、UDP 67番で待ち受ける。
+pub fn start(config: DhcpServerConfig) -> Result<(), String> {
+    config.validate()?;
+    let mut server = SERVER.lock();
+    if server.is_some() {
+        return Err(String::from("DHCP server already running"));
+    }
+    let socket = crate::net::stack::bind_udp(DHCP_SERVER_PORT)
+        .ok_or_else(|| format!("UDP port {} is in use", DHCP_SERVER_PORT))?;
+
+    let mut state = DhcpServer::new(config);
+    if let Some(path) = state.config.lease_file.clone()
+        && let Ok(data) = crate::fs::read_file(&path)
+    {
+        let loaded = state.load_leases(&String::from_utf8_lossy(&data), crate::time::now());
+        crate::log!("[DHCPD] Loaded {} leases from {}\n", loaded, path);
+    }
+    crate::log!(
+        "[DHCPD] Serving {} on port {}\n",
+        state.config.server_address,
+        DHCP_SERVER_PORT
+    );
+    *server = Some(state);
+
+    let generation = GENERATION.load(Ordering::Acquire);
+    crate::task::spawn(serve(socket, generation));
+    Ok(())
+}
+
+/// サーバーを止める（動いていなければ false）
+pub fn stop() -> bool {
+    if SERVER.lock().take().is_none() {
+        return false;
+    }
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+    crate::net::stack::unbind_udp(DHCP_SERVER_PORT);
+    true
+}
+
+/// 動作中のサーバーに固定割り当てを追加
+pub fn add_static_lease(mac: MacAddress, address: Ipv4Address) -> Result<(), String> {
+    let mut server = SERVER.lock();
+    let Some(server) = server.as_mut() else {
+        return Err(String::from("DHCP server not running"));
+    };
+    let config = &mut server.config;
+    if !address.same_subnet(&config.server_address, config.subnet_mask) {
+        return Err(format!("{} is outside the server subnet", address));
+    }
+    config
+        .static_leases
+        .retain(|lease| lease.mac != mac && lease.address != address);
+    config.static_leases.push(StaticLease { mac, address });
+    // 他のクライアントに渡していたリースは次の更新で NAK になる
+    server
+        .leases
+        .retain(|lease| lease.mac == mac || lease.address != address);
+    Ok(())
+}
+
+/// 動いているか
+pub fn is_running() -> bool {
+    SERVER.lock().is_some()
+}
+
+/// 現在のリース一覧
+pub fn leases() -> Vec<ServerLease> {
+    let now = crate::time::now();
+    SERVER.lock().as_mut().map_or_else(Vec::new, |server| {
+        server.expire(now);
+        server.leases().to_vec()
+    })
+}
+
+/// 受信ループ
+async fn serve(socket: crate::net::udp::UdpSocket, generation: u32) {
+    while GENERATION.load(Ordering::Acquire) == generation {
+        while socket.rx_queue_len() > 0 {
+            let Some(datagram) = socket.recv().await else {
+                return;
+            };
+            let (reply, changed, lease_file) = {
+                let mut server = SERVER.lock();
+                let Some(server) = server.as_mut() else {
+                    return;
+                };
+                let (reply, changed) = server.handle(&datagram.data, crate::time::now());
+                let lease_file = server
+                    .config
+                    .lease_file
+                    .clone()
+                    .filter(|_| changed)
+                    .map(|path| (path, server.save_leases()));
+                (reply, changed, lease_file)
+            };
+            if let Some(reply) = reply {
+                crate::net::stack::send_udp(
+                    DHCP_SERVER_PORT,
+                    reply.destination,
+                    reply.port,
+                    &reply.data,
+                );
+            }
+            if let Some((path, text)) = lease_file
+                && let Err(e) = crate::fs::write_file(&path, text.as_bytes())
+            {
+                crate::log!("[DHCPD] Failed to save leases to {}: {:?}\n", path, e);
+            }
+        }
+        crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: Ipv4Address = Ipv4Address::new([10, 0, 0, 1]);
+    const MASK: Ipv4Address = Ipv4Address::new([255, 255, 255, 0]);
+    const NOW: u64 = 1_700_000_000;
+
+    fn config() -> DhcpServerConfig {
+        let mut config = DhcpServerConfig::new(SERVER, MASK)
+            .pool(
+                Ipv4Address::new([10, 0, 0, 100]),
+                Ipv4Address::new([10, 0, 0, 101]),
+            )
+            .static_lease(
+                MacAddress::from_octets(0x52, 0x54, 0, 0, 0, 0x99),
+                Ipv4Address::new([10, 0, 0, 50]),
+            );
+        config.ntp_servers = alloc::vec![SERVER];
+        config.domain_name = Some(String::from("lab"));
+        config
+    }
+
+    fn message(
+        message_type: DhcpMessageType,
+        mac: u8,
+        requested: Option<Ipv4Address>,
+        server_id: Option<Ipv4Address>,
+    ) -> Vec<u8> {
+        let mut data = alloc::vec![0u8; DhcpHeader::SIZE];
+        data[0] = DhcpOperation::Request as u8;
+        data[1] = 1;
+        data[2] = 6;
+        data[4..8].copy_from_slice(&[1, 2, 3, 4]);
+        data[28..34].copy_from_slice(&[0x52, 0x54, 0, 0, 0, mac]);
+        data.extend_from_slice(&DHCP_MAGIC_COOKIE);
+        push_option(&mut data, 53, &[message_type as u8]);
+        if let Some(address) = requested {
+            push_option(&mut data, 50, address.as_bytes());
+        }
+        if let Some(server) = server_id {
+            push_option(&mut data, 54, server.as_bytes());
+        }
+        push_option(&mut data, 12, b"guest");
+        data.push(DhcpOption::End as u8);
+        data
+    }
+
+    /// 応答のメッセージタイプ・yiaddr・オプション
+    fn decode(reply: &Reply) -> (u8, Ipv4Address, Vec<(u8, Vec<u8>)>) {
+        let data = &reply.data;
+        let yiaddr = Ipv4Address::new([data[16], data[17], data[18], data[19]]);
+        let mut options = Vec::new();
+        let mut offset = DhcpHeader::SIZE + 4;
+        while data[offset] != DhcpOption::End as u8 {
+            let len = data[offset + 1] as usize;
+            options.push((data[offset], data[offset + 2..offset + 2 + len].to_vec()));
+            offset += 2 + len;
+        }
+        (options[0].1[0], yiaddr, options)
+    }
+
+    #[test]
+    fn test_discover_request_release() {
+        let mut server = DhcpServer::new(config());
+        let pool_first = Ipv4Address::new([10, 0, 0, 100]);
+
+        let (offer, _) = server.handle(&message(DhcpMessageType::Discover, 1, None, None), NOW);
+        let offer = offer.unwrap();
+        assert_eq!(offer.destination, Ipv4Address::BROADCAST);
+        assert_eq!(offer.port, DHCP_CLIENT_PORT);
+        assert!(offer.data.len() >= MIN_MESSAGE_SIZE);
+        let (kind, yiaddr, options) = decode(&offer);
+        assert_eq!(kind, DhcpMessageType::Offer as u8);
+        assert_eq!(yiaddr, pool_first);
+        let option = |code: u8| {
+            options
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(option(54), Some(SERVER.as_bytes().to_vec()));
+        assert_eq!(option(3), Some(SERVER.as_bytes().to_vec()));
+        assert_eq!(option(42), Some(SERVER.as_bytes().to_vec()));
+        assert_eq!(option(15), Some(b"lab".to_vec()));
+        assert_eq!(option(51), Some(3600u32.to_be_bytes().to_vec()));
+
+        // 別のクライアントには次のアドレスを提案する
+        let (other, _) = server.handle(&message(DhcpMessageType::Discover, 2, None, None), NOW);
+        assert_eq!(decode(&other.unwrap()).1, Ipv4Address::new([10, 0, 0, 101]));
+
+        let (ack, changed) = server.handle(
+            &message(DhcpMessageType::Request, 1, Some(pool_first), Some(SERVER)),
+            NOW,
+        );
+        assert!(changed);
+        assert_eq!(decode(&ack.unwrap()).0, DhcpMessageType::Ack as u8);
+        let lease = server
+            .leases()
+            .iter()
+            .find(|l| l.address == pool_first)
+            .unwrap();
+        assert_eq!(lease.state, LeaseState::Bound);
+        assert_eq!(lease.hostname.as_deref(), Some("guest"));
+
+        // プールが尽きたら提案しない
+        let (none, _) = server.handle(&message(DhcpMessageType::Discover, 3, None, None), NOW);
+        assert!(none.is_none());
+        // OFFER の予約は期限が切れれば再利用できる
+        let (later, _) = server.handle(
+            &message(DhcpMessageType::Discover, 3, None, None),
+            NOW + OFFER_HOLD_SECS + 1,
+        );
+        assert_eq!(decode(&later.unwrap()).1, Ipv4Address::new([10, 0, 0, 101]));
+
+        // 使用中のアドレスを要求すると NAK
+        let (nak, _) = server.handle(
+            &message(DhcpMessageType::Request, 4, Some(pool_first), None),
+            NOW,
+        );
+        assert_eq!(decode(&nak.unwrap()).0, DhcpMessageType::Nak as u8);
+
+        // 固定割り当て
+        let (fixed, _) = server.handle(&message(DhcpMessageType::Discover, 0x99, None, None), NOW);
+        assert_eq!(decode(&fixed.unwrap()).1, Ipv4Address::new([10, 0, 0, 50]));
+
+        let mut release = message(DhcpMessageType::Release, 1, None, Some(SERVER));
+        release[12..16].copy_from_slice(pool_first.as_bytes());
+        let (_, changed) = server.handle(&release, NOW);
+        assert!(changed);
+        assert!(server.leases().iter().all(|l| l.address != pool_first));
+    }
+
+    #[test]
+    fn test_lease_persistence_and_validation() {
+        let mut server = DhcpServer::new(config());
+        let requested = Ipv4Address::new([10, 0, 0, 101]);
+        server.handle(
+            &message(DhcpMessageType::Discover, 7, Some(requested), None),
+            NOW,
+        );
+        server.handle(
+            &message(DhcpMessageType::Request, 7, Some(requested), Some(SERVER)),
+            NOW,
+        );
+        let saved = server.save_leases();
+        assert!(saved.contains("52:54:00:00:00:07 10.0.0.101 1700003600 guest"));
+
+        let mut restored = DhcpServer::new(config());
+        assert_eq!(restored.load_leases(&saved, NOW + 10), 1);
+        // 再起動後も同じクライアントには同じアドレス
+        let (offer, _) =
+            restored.handle(&message(DhcpMessageType::Discover, 7, None, None), NOW + 10);
+        assert_eq!(decode(&offer.unwrap()).1, requested);
+        // 期限切れのリースは読み込まない
+        assert_eq!(DhcpServer::new(config()).load_leases(&saved, NOW + 3600), 0);
+
+        let mut bad = config();
+        bad.pools.push(AddressPool::new(
+            Ipv4Address::new([192, 168, 1, 10]),
+            Ipv4Address::new([192, 168, 1, 20]),
+        ));
+        assert!(bad.validate().is_err());
+        assert!(config().validate().is_ok());
+    }
+}
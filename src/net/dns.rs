@@ -2,11 +2,14 @@
 //!
 //! ドメイン名からIPアドレスへの解決を行うDNSリゾルバ。
 //! 簡易的なキャッシュ機能付き。
+//! ルーターとして動かすときのキャッシュ付きフォワーダは `forwarder`。
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+pub mod forwarder;
+
 use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -14,6 +17,7 @@ use core::sync::atomic::{AtomicU16, AtomicU64, Ordering};
 use spin::Mutex;
 
 use super::ipv4::Ipv4Address;
+use super::udp::UdpSocket;
 
 /// DNSポート
 pub const DNS_PORT: u16 = 53;
@@ -38,6 +42,8 @@ pub enum DnsQueryType {
     TXT = 16,
     /// IPv6アドレス
     AAAA = 28,
+    /// サービスロケーション (DNS-SD)
+    SRV = 33,
     /// 全タイプ
     ALL = 255,
 }
@@ -54,6 +60,7 @@ impl DnsQueryType {
             15 => Some(Self::MX),
             16 => Some(Self::TXT),
             28 => Some(Self::AAAA),
+            33 => Some(Self::SRV),
             255 => Some(Self::ALL),
             _ => None,
         }
@@ -201,6 +208,12 @@ impl DnsCacheEntry {
         let elapsed_secs = (current_tick.saturating_sub(self.cached_at)) / tick_rate;
         elapsed_secs > self.min_ttl as u64
     }
+
+    /// 残りTTL（秒）
+    pub fn remaining_ttl(&self, current_tick: u64, tick_rate: u64) -> u32 {
+        let elapsed_secs = (current_tick.saturating_sub(self.cached_at)) / tick_rate;
+        (self.min_ttl as u64).saturating_sub(elapsed_secs) as u32
+    }
 }
 
 /// DNSキャッシュ
@@ -259,6 +272,20 @@ impl DnsCache {
             .retain(|_, entry| !entry.is_expired(current_tick, self.tick_rate));
     }
 
+    /// 有効なエントリを (名前, 残りTTL秒, エントリ) で列挙
+    pub fn entries(&self, current_tick: u64) -> impl Iterator<Item = (&str, u32, &DnsCacheEntry)> {
+        self.entries
+            .iter()
+            .filter(move |(_, entry)| !entry.is_expired(current_tick, self.tick_rate))
+            .map(move |(name, entry)| {
+                (
+                    name.as_str(),
+                    entry.remaining_ttl(current_tick, self.tick_rate),
+                    entry,
+                )
+            })
+    }
+
     /// エントリを削除
     pub fn remove(&mut self, name: &str) {
         self.entries.remove(name);
@@ -348,6 +375,27 @@ impl DnsClient {
         None
     }
 
+    /// 解決結果をキャッシュに登録
+    pub fn cache_records(&self, name: &str, records: Vec<DnsRecord>, current_tick: u64) {
+        self.cache.lock().insert(String::from(name), records, current_tick);
+    }
+
+    /// キャッシュからAレコードを全て取得
+    pub fn cached_addresses(&self, name: &str, current_tick: u64) -> Vec<Ipv4Address> {
+        let cache = self.cache.lock();
+        let Some(entry) = cache.lookup(name, current_tick) else {
+            return Vec::new();
+        };
+        entry
+            .records
+            .iter()
+            .filter_map(|record| match record.data {
+                DnsRecordData::A(ip) => Some(ip),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// DNSクエリパケットを構築
     pub fn build_query(
         &self,
@@ -427,6 +475,30 @@ impl DnsClient {
             return Err(rcode);
         }
 
+        let records = self.parse_records(data)?;
+
+        self.stats
+            .responses_received
+            .fetch_add(1, Ordering::Relaxed);
+
+        // キャッシュに追加
+        if !records.is_empty() {
+            if let Some(first) = records.first() {
+                let mut cache = self.cache.lock();
+                cache.insert(first.name.clone(), records.clone(), current_tick);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// 応答の回答セクションを解析（応答コードの確認とキャッシュは行わない）
+    pub fn parse_records(&self, data: &[u8]) -> Result<Vec<DnsRecord>, DnsResponseCode> {
+        if data.len() < DnsHeader::SIZE {
+            return Err(DnsResponseCode::FormatError);
+        }
+        let header = unsafe { &*(data.as_ptr() as *const DnsHeader) };
+
         let qcount = header.question_count() as usize;
         let acount = header.answer_count() as usize;
 
@@ -522,18 +594,6 @@ impl DnsClient {
             });
         }
 
-        self.stats
-            .responses_received
-            .fetch_add(1, Ordering::Relaxed);
-
-        // キャッシュに追加
-        if !records.is_empty() {
-            if let Some(first) = records.first() {
-                let mut cache = self.cache.lock();
-                cache.insert(first.name.clone(), records.clone(), current_tick);
-            }
-        }
-
         Ok(records)
     }
 
@@ -643,11 +703,15 @@ pub fn init(tick_rate: u64) {
     *DNS_CLIENT.lock() = Some(client);
 }
 
+/// グローバルクライアントにアクセス（未初期化ならミリ秒ティックで作成）
+fn with_client<R>(f: impl FnOnce(&DnsClient) -> R) -> R {
+    let mut client = DNS_CLIENT.lock();
+    f(client.get_or_insert_with(|| DnsClient::new(1000)))
+}
+
 /// DNSサーバーを設定
 pub fn set_servers(servers: Vec<Ipv4Address>) {
-    if let Some(client) = DNS_CLIENT.lock().as_ref() {
-        client.set_servers(servers);
-    }
+    with_client(|client| client.set_servers(servers));
 }
 
 /// キャッシュからIPアドレスを解決
@@ -657,3 +721,133 @@ pub fn resolve_cached(name: &str, current_tick: u64) -> Option<Ipv4Address> {
         .as_ref()
         .and_then(|c| c.resolve_cached(name, current_tick))
 }
+
+/// キャッシュ内容を (名前, 残りTTL秒, レコード) で取得
+pub fn cache_entries(current_tick: u64) -> Vec<(String, u32, Vec<DnsRecord>)> {
+    DNS_CLIENT.lock().as_ref().map_or_else(Vec::new, |client| {
+        client
+            .cache
+            .lock()
+            .entries(current_tick)
+            .map(|(name, ttl, entry)| (String::from(name), ttl, entry.records.clone()))
+            .collect()
+    })
+}
+
+// ============================================================================
+// 問い合わせ
+// ============================================================================
+
+/// 問い合わせのタイムアウト (ms)
+pub const QUERY_TIMEOUT_MS: u64 = 3000;
+
+/// 再送間隔 (ms)
+const RETRANSMIT_MS: u64 = 1000;
+
+/// 応答待ちのポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// 問い合わせ元ポートの範囲
+const QUERY_PORT_BASE: u16 = 49700;
+const QUERY_PORT_COUNT: u16 = 64;
+
+/// 次に試す問い合わせ元ポートのオフセット
+static NEXT_QUERY_PORT: AtomicU16 = AtomicU16::new(0);
+
+/// 問い合わせ先サーバー
+///
+/// 設定済みのサーバー（DHCPなど）を優先し、なければインターフェース設定の
+/// DNS、最後にデフォルトゲートウェイを使う
+fn query_server() -> Option<Ipv4Address> {
+    if let Some(server) = with_client(|client| client.primary_server()) {
+        return Some(server);
+    }
+    let ipv4 = super::stack::stack().lock().as_ref()?.config().ipv4;
+    ipv4.dns
+        .or(Some(ipv4.gateway))
+        .filter(|server| !server.is_any())
+}
+
+/// 空いている問い合わせ元ポートをバインド
+fn bind_query_socket() -> Option<(u16, UdpSocket)> {
+    for _ in 0..QUERY_PORT_COUNT {
+        let offset = NEXT_QUERY_PORT.fetch_add(1, Ordering::Relaxed) % QUERY_PORT_COUNT;
+        let port = QUERY_PORT_BASE + offset;
+        if let Some(socket) = super::stack::bind_udp(port) {
+            return Some((port, socket));
+        }
+    }
+    None
+}
+
+/// 名前をAレコードへ解決する（async）
+///
+/// キャッシュを確認し、なければ設定済みサーバーへ再帰問い合わせを送る。
+/// 応答はTTLに従ってキャッシュされる。
+pub async fn query(name: &str, timeout_ms: u64) -> Result<Vec<Ipv4Address>, String> {
+    let name = name.trim_end_matches('.');
+    let cached = with_client(|client| client.cached_addresses(name, crate::time::current_tick()));
+    if !cached.is_empty() {
+        return Ok(cached);
+    }
+
+    let server = query_server().ok_or_else(|| String::from("DNS server not configured"))?;
+    let mut buffer = [0u8; 512];
+    let len = with_client(|client| client.build_query(&mut buffer, name, DnsQueryType::A))
+        .map_err(String::from)?;
+    let id = u16::from_be_bytes([buffer[0], buffer[1]]);
+
+    let (port, socket) =
+        bind_query_socket().ok_or_else(|| String::from("No free port for DNS query"))?;
+    let response = exchange(&socket, port, server, &buffer[..len], id, timeout_ms).await;
+    super::stack::unbind_udp(port);
+    let response = response.ok_or_else(|| alloc::format!("DNS query for {} timed out", name))?;
+
+    let now = crate::time::current_tick();
+    let records = with_client(|client| client.parse_response(&response, now))
+        .map_err(|code| alloc::format!("DNS error for {}: {:?}", name, code))?;
+    let addresses: Vec<Ipv4Address> = records
+        .iter()
+        .filter_map(|record| match record.data {
+            DnsRecordData::A(ip) => Some(ip),
+            _ => None,
+        })
+        .collect();
+    if addresses.is_empty() {
+        return Err(alloc::format!("No address record for {}", name));
+    }
+    with_client(|client| client.cache_records(name, records, now));
+    Ok(addresses)
+}
+
+/// クエリを送り、IDの一致する応答を待つ
+async fn exchange(
+    socket: &UdpSocket,
+    port: u16,
+    server: Ipv4Address,
+    query: &[u8],
+    id: u16,
+    timeout_ms: u64,
+) -> Option<Vec<u8>> {
+    let start = crate::time::current_tick();
+    let mut last_sent = None;
+    while crate::time::current_tick().saturating_sub(start) < timeout_ms {
+        let now = crate::time::current_tick();
+        if last_sent.is_none_or(|sent: u64| now.saturating_sub(sent) >= RETRANSMIT_MS) {
+            super::stack::send_udp(port, server, DNS_PORT, query);
+            last_sent = Some(now);
+        }
+        while socket.rx_queue_len() > 0 {
+            let datagram = socket.recv().await?;
+            if datagram.src.ip == server
+                && datagram.src.port == DNS_PORT
+                && datagram.data.len() >= DnsHeader::SIZE
+                && u16::from_be_bytes([datagram.data[0], datagram.data[1]]) == id
+            {
+                return Some(datagram.data);
+            }
+        }
+        crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+    }
+    None
+}
@@ -0,0 +1,496 @@
+//! キャッシュ付き DNS フォワーダ
+//!
+//! UDP 53番で受けた再帰問い合わせを上流サーバーへ転送し、応答を `DnsCache` に
+//! (名前, タイプ) 単位で保存する。キャッシュにあれば上流に問い合わせず、
+//! 残り TTL を入れた応答を組み立てて返す。
+//!
+//! 同じキャッシュで正しく再構成できるレコード（A / AAAA / CNAME / NS / PTR / MX）
+//! だけを保存し、それ以外を含む応答はそのまま中継する。否定応答はキャッシュしない。
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use spin::Mutex;
+
+use super::{
+    DNS_PORT, DnsCache, DnsHeader, DnsQueryType, DnsRecord, DnsRecordData, DnsResponseCode,
+};
+use crate::net::ipv4::Ipv4Address;
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// 上流への問い合わせのタイムアウト (ms)
+pub const UPSTREAM_TIMEOUT_MS: u64 = 3000;
+
+/// 同時に上流へ転送する問い合わせの上限
+pub const MAX_IN_FLIGHT: usize = 32;
+
+/// 受信のポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 5;
+
+/// UDP で返せる応答の最大長
+const MAX_UDP_RESPONSE: usize = 512;
+
+/// フラグ: 応答
+const FLAG_QR: u16 = 0x8000;
+/// フラグ: 再帰希望
+const FLAG_RD: u16 = 0x0100;
+/// フラグ: 再帰可能
+const FLAG_RA: u16 = 0x0080;
+/// フラグ: オペコード
+const FLAG_OPCODE: u16 = 0x7800;
+
+// ============================================================================
+// 問い合わせの解析と応答の生成
+// ============================================================================
+
+/// 問い合わせの質問
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// トランザクションID
+    pub id: u16,
+    /// フラグ
+    pub flags: u16,
+    /// 名前（小文字、末尾のドットなし）
+    pub name: String,
+    /// タイプ
+    pub qtype: u16,
+    /// クラス
+    pub qclass: u16,
+    /// 質問セクションの終端オフセット
+    pub end: usize,
+}
+
+impl Question {
+    /// キャッシュのキー
+    pub fn cache_key(&self) -> String {
+        format!("{}/{}", self.name, self.qtype)
+    }
+}
+
+/// 標準問い合わせ（質問1つ）を解析
+pub fn parse_query(data: &[u8]) -> Result<Question, DnsResponseCode> {
+    if data.len() < DnsHeader::SIZE {
+        return Err(DnsResponseCode::FormatError);
+    }
+    let id = u16::from_be_bytes([data[0], data[1]]);
+    let flags = u16::from_be_bytes([data[2], data[3]]);
+    let qdcount = u16::from_be_bytes([data[4], data[5]]);
+    if flags & FLAG_QR != 0 || qdcount != 1 {
+        return Err(DnsResponseCode::FormatError);
+    }
+    if flags & FLAG_OPCODE != 0 {
+        return Err(DnsResponseCode::NotImplemented);
+    }
+
+    let mut name = String::new();
+    let mut offset = DnsHeader::SIZE;
+    loop {
+        let len = *data.get(offset).ok_or(DnsResponseCode::FormatError)? as usize;
+        offset += 1;
+        if len == 0 {
+            break;
+        }
+        // 問い合わせに圧縮ポインタは現れない
+        if len & 0xC0 != 0 || offset + len > data.len() {
+            return Err(DnsResponseCode::FormatError);
+        }
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&String::from_utf8_lossy(&data[offset..offset + len]).to_ascii_lowercase());
+        offset += len;
+    }
+    if offset + 4 > data.len() {
+        return Err(DnsResponseCode::FormatError);
+    }
+    Ok(Question {
+        id,
+        flags,
+        name,
+        qtype: u16::from_be_bytes([data[offset], data[offset + 1]]),
+        qclass: u16::from_be_bytes([data[offset + 2], data[offset + 3]]),
+        end: offset + 4,
+    })
+}
+
+/// キャッシュから正しく再構成できる応答か
+pub fn is_cacheable(records: &[DnsRecord]) -> bool {
+    !records.is_empty()
+        && records.iter().all(|record| match (record.rtype, &record.data) {
+            (DnsQueryType::A, DnsRecordData::A(_)) => true,
+            (DnsQueryType::CNAME | DnsQueryType::NS | DnsQueryType::PTR, DnsRecordData::Name(_)) => {
+                true
+            }
+            (DnsQueryType::MX, DnsRecordData::MX(..)) => true,
+            (DnsQueryType::AAAA, DnsRecordData::Raw(raw)) => raw.len() == 16,
+            _ => false,
+        })
+}
+
+/// ドメイン名を圧縮なしでエンコード
+fn encode_name(buffer: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label);
+    }
+    buffer.push(0);
+}
+
+/// 問い合わせへの応答ヘッダと質問セクション
+fn response_prefix(query: &[u8], question: &Question, rcode: DnsResponseCode, answers: u16) -> Vec<u8> {
+    let flags = FLAG_QR | FLAG_RA | (question.flags & FLAG_RD) | rcode as u16;
+    let mut buffer = Vec::with_capacity(MAX_UDP_RESPONSE);
+    buffer.extend_from_slice(&question.id.to_be_bytes());
+    buffer.extend_from_slice(&flags.to_be_bytes());
+    buffer.extend_from_slice(&1u16.to_be_bytes());
+    buffer.extend_from_slice(&answers.to_be_bytes());
+    buffer.extend_from_slice(&[0, 0, 0, 0]);
+    buffer.extend_from_slice(&query[DnsHeader::SIZE..question.end]);
+    buffer
+}
+
+/// 回答のない応答（エラー通知）
+pub fn error_response(query: &[u8], question: &Question, rcode: DnsResponseCode) -> Vec<u8> {
+    response_prefix(query, question, rcode, 0)
+}
+
+/// キャッシュしたレコードから応答を組み立てる（TTL は `ttl` に揃える）
+pub fn cached_response(query: &[u8], question: &Question, records: &[DnsRecord], ttl: u32) -> Vec<u8> {
+    let mut buffer = response_prefix(query, question, DnsResponseCode::NoError, records.len() as u16);
+    for record in records {
+        encode_name(&mut buffer, &record.name);
+        buffer.extend_from_slice(&(record.rtype as u16).to_be_bytes());
+        buffer.extend_from_slice(&1u16.to_be_bytes());
+        buffer.extend_from_slice(&ttl.to_be_bytes());
+
+        let length_at = buffer.len();
+        buffer.extend_from_slice(&[0, 0]);
+        match &record.data {
+            DnsRecordData::A(address) => buffer.extend_from_slice(address.as_bytes()),
+            DnsRecordData::Name(name) => encode_name(&mut buffer, name),
+            DnsRecordData::MX(preference, exchange) => {
+                buffer.extend_from_slice(&preference.to_be_bytes());
+                encode_name(&mut buffer, exchange);
+            }
+            DnsRecordData::TXT(text) => {
+                let text = &text.as_bytes()[..text.len().min(255)];
+                buffer.push(text.len() as u8);
+                buffer.extend_from_slice(text);
+            }
+            DnsRecordData::Raw(raw) => buffer.extend_from_slice(raw),
+        }
+        let length = (buffer.len() - length_at - 2) as u16;
+        buffer[length_at..length_at + 2].copy_from_slice(&length.to_be_bytes());
+    }
+    buffer
+}
+
+// ============================================================================
+// フォワーダ
+// ============================================================================
+
+/// フォワーダの設定
+#[derive(Debug, Clone)]
+pub struct ForwarderConfig {
+    /// 待ち受けポート
+    pub port: u16,
+    /// 上流サーバー（空ならリゾルバの設定を使う）
+    pub upstreams: Vec<Ipv4Address>,
+}
+
+impl Default for ForwarderConfig {
+    fn default() -> Self {
+        Self {
+            port: DNS_PORT,
+            upstreams: Vec::new(),
+        }
+    }
+}
+
+/// フォワーダの統計
+pub struct ForwarderStats {
+    /// 受けた問い合わせ
+    pub queries: AtomicU64,
+    /// キャッシュから答えた数
+    pub cache_hits: AtomicU64,
+    /// 上流へ転送した数
+    pub forwarded: AtomicU64,
+    /// 上流が応答しなかった・転送できなかった数
+    pub failures: AtomicU64,
+}
+
+static STATS: ForwarderStats = ForwarderStats {
+    queries: AtomicU64::new(0),
+    cache_hits: AtomicU64::new(0),
+    forwarded: AtomicU64::new(0),
+    failures: AtomicU64::new(0),
+};
+
+/// フォワーダの統計を取得
+pub fn stats() -> &'static ForwarderStats {
+    &STATS
+}
+
+/// 転送用キャッシュ（リゾルバのキャッシュとは別に (名前, タイプ) で持つ）
+static CACHE: Mutex<DnsCache> = Mutex::new(DnsCache::new(1000));
+
+/// 上流サーバー
+static UPSTREAMS: Mutex<Vec<Ipv4Address>> = Mutex::new(Vec::new());
+
+/// 待ち受け中のポート（0 なら停止中）
+static PORT: AtomicU16 = AtomicU16::new(0);
+
+/// 受信ループの世代（`stop` で進める）
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// 上流へ転送中の問い合わせ数
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// 上流へ送る問い合わせのID
+static NEXT_ID: AtomicU16 = AtomicU16::new(0x5A5A);
+
+/// フォワーダを開始
+pub fn start(config: ForwarderConfig) -> Result<(), String> {
+    if PORT.load(Ordering::Acquire) != 0 {
+        return Err(String::from("DNS forwarder already running"));
+    }
+    let socket = crate::net::stack::bind_udp(config.port)
+        .ok_or_else(|| format!("UDP port {} is in use", config.port))?;
+    *UPSTREAMS.lock() = config.upstreams;
+    PORT.store(config.port, Ordering::Release);
+    let generation = GENERATION.load(Ordering::Acquire);
+    crate::task::spawn(serve(socket, config.port, generation));
+    crate::log!("[DNS] Forwarder listening on port {}\n", config.port);
+    Ok(())
+}
+
+/// フォワーダを止める（動いていなければ false）
+pub fn stop() -> bool {
+    let port = PORT.swap(0, Ordering::AcqRel);
+    if port == 0 {
+        return false;
+    }
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+    crate::net::stack::unbind_udp(port);
+    true
+}
+
+/// 待ち受け中のポート
+pub fn running_port() -> Option<u16> {
+    Some(PORT.load(Ordering::Acquire)).filter(|port| *port != 0)
+}
+
+/// キャッシュの内容を (キー, 残りTTL秒, レコード数) で列挙
+pub fn cache_entries() -> Vec<(String, u32, usize)> {
+    let now = crate::time::current_tick();
+    CACHE
+        .lock()
+        .entries(now)
+        .map(|(key, ttl, entry)| (String::from(key), ttl, entry.records.len()))
+        .collect()
+}
+
+/// キャッシュを空にする
+pub fn flush_cache() {
+    CACHE.lock().clear();
+}
+
+/// 上流サーバー
+fn upstream() -> Option<Ipv4Address> {
+    UPSTREAMS.lock().first().copied().or_else(super::query_server)
+}
+
+/// 受信ループ
+async fn serve(socket: crate::net::udp::UdpSocket, port: u16, generation: u32) {
+    while GENERATION.load(Ordering::Acquire) == generation {
+        while socket.rx_queue_len() > 0 {
+            let Some(datagram) = socket.recv().await else {
+                return;
+            };
+            handle(port, datagram.src.ip, datagram.src.port, datagram.data);
+        }
+        crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+    }
+}
+
+/// 1つの問い合わせを処理する
+fn handle(port: u16, client: Ipv4Address, client_port: u16, query: Vec<u8>) {
+    STATS.queries.fetch_add(1, Ordering::Relaxed);
+    let question = match parse_query(&query) {
+        Ok(question) => question,
+        Err(rcode) => {
+            // 応答やヘッダに満たないものは黙って捨てる
+            if query.len() >= DnsHeader::SIZE && query[2] & 0x80 == 0 {
+                let flags = u16::from_be_bytes([query[2], query[3]]);
+                let flags = FLAG_QR | FLAG_RA | (flags & (FLAG_OPCODE | FLAG_RD)) | rcode as u16;
+                let mut reply = query[..DnsHeader::SIZE].to_vec();
+                reply[2..4].copy_from_slice(&flags.to_be_bytes());
+                reply[4..12].fill(0);
+                crate::net::stack::send_udp(port, client, client_port, &reply);
+            }
+            return;
+        }
+    };
+
+    let now = crate::time::current_tick();
+    let cached = CACHE.lock().lookup(&question.cache_key(), now).map(|entry| {
+        let ttl = entry.remaining_ttl(now, 1000);
+        cached_response(&query, &question, &entry.records, ttl)
+    });
+    if let Some(reply) = cached {
+        STATS.cache_hits.fetch_add(1, Ordering::Relaxed);
+        crate::net::stack::send_udp(port, client, client_port, &reply);
+        return;
+    }
+
+    if IN_FLIGHT.fetch_add(1, Ordering::AcqRel) >= MAX_IN_FLIGHT {
+        IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
+        STATS.failures.fetch_add(1, Ordering::Relaxed);
+        let reply = error_response(&query, &question, DnsResponseCode::ServerFailure);
+        crate::net::stack::send_udp(port, client, client_port, &reply);
+        return;
+    }
+    crate::task::spawn(async move {
+        forward(port, client, client_port, query, question).await;
+        IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
+    });
+}
+
+/// 上流へ転送して応答を中継する（async）
+async fn forward(port: u16, client: Ipv4Address, client_port: u16, mut query: Vec<u8>, question: Question) {
+    let reply = match upstream() {
+        Some(server) => {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed) ^ (crate::time::current_tick() as u16);
+            query[0..2].copy_from_slice(&id.to_be_bytes());
+            match super::bind_query_socket() {
+                Some((local, socket)) => {
+                    STATS.forwarded.fetch_add(1, Ordering::Relaxed);
+                    let response =
+                        super::exchange(&socket, local, server, &query, id, UPSTREAM_TIMEOUT_MS).await;
+                    crate::net::stack::unbind_udp(local);
+                    response
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+
+    let Some(mut reply) = reply else {
+        STATS.failures.fetch_add(1, Ordering::Relaxed);
+        query[0..2].copy_from_slice(&question.id.to_be_bytes());
+        let reply = error_response(&query, &question, DnsResponseCode::ServerFailure);
+        crate::net::stack::send_udp(port, client, client_port, &reply);
+        return;
+    };
+
+    // 切り詰められていない肯定応答ならキャッシュする
+    let truncated = reply[2] & 0x02 != 0;
+    if !truncated
+        && DnsResponseCode::from_u8(reply[3]) == DnsResponseCode::NoError
+        && let Ok(records) = super::with_client(|client| client.parse_records(&reply))
+        && is_cacheable(&records)
+    {
+        CACHE
+            .lock()
+            .insert(question.cache_key(), records, crate::time::current_tick());
+    }
+
+    reply[0..2].copy_from_slice(&question.id.to_be_bytes());
+    crate::net::stack::send_udp(port, client, client_port, &reply);
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DnsClient, DnsQueryClass};
+    use super::*;
+
+    fn record(name: &str, rtype: DnsQueryType, data: DnsRecordData) -> DnsRecord {
+        DnsRecord {
+            name: String::from(name),
+            rtype,
+            rclass: DnsQueryClass::IN,
+            ttl: 300,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_parse_query_and_cached_response() {
+        let client = DnsClient::new(1000);
+        let mut query = [0u8; 512];
+        let len = client
+            .build_query(&mut query, "WWW.Example.com", DnsQueryType::A)
+            .unwrap();
+        let query = &query[..len];
+
+        let question = parse_query(query).unwrap();
+        assert_eq!(question.name, "www.example.com");
+        assert_eq!(question.qtype, DnsQueryType::A as u16);
+        assert_eq!(question.end, len);
+        assert_eq!(question.cache_key(), "www.example.com/1");
+
+        let records = alloc::vec![
+            record(
+                "www.example.com",
+                DnsQueryType::CNAME,
+                DnsRecordData::Name(String::from("web.example.com")),
+            ),
+            record(
+                "web.example.com",
+                DnsQueryType::A,
+                DnsRecordData::A(Ipv4Address::new([192, 0, 2, 7])),
+            ),
+        ];
+        assert!(is_cacheable(&records));
+
+        let reply = cached_response(query, &question, &records, 42);
+        assert_eq!(&reply[0..2], &query[0..2]);
+        assert_eq!(reply[2] & 0x81, 0x81); // QR + RD
+        let parsed = client.parse_records(&reply).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].ttl, 42);
+        assert!(matches!(&parsed[0].data, DnsRecordData::Name(n) if n == "web.example.com"));
+        assert!(matches!(parsed[1].data, DnsRecordData::A(ip) if ip == Ipv4Address::new([192, 0, 2, 7])));
+
+        let error = error_response(query, &question, DnsResponseCode::ServerFailure);
+        assert_eq!(error[3] & 0x0F, DnsResponseCode::ServerFailure as u8);
+        assert_eq!(&error[6..8], &[0, 0]);
+    }
+
+    #[test]
+    fn test_rejects_and_cacheability() {
+        // 応答・質問なし・非標準オペコード
+        let mut header = [0u8; DnsHeader::SIZE];
+        header[5] = 1;
+        header[2] = 0x80;
+        assert_eq!(parse_query(&header), Err(DnsResponseCode::FormatError));
+        header[2] = 0x10;
+        assert_eq!(parse_query(&header), Err(DnsResponseCode::NotImplemented));
+        header[2] = 0;
+        header[5] = 0;
+        assert_eq!(parse_query(&header), Err(DnsResponseCode::FormatError));
+
+        assert!(!is_cacheable(&[]));
+        assert!(is_cacheable(&[record(
+            "v6.example.com",
+            DnsQueryType::AAAA,
+            DnsRecordData::Raw(alloc::vec![0; 16]),
+        )]));
+        // 名前を含む可能性のある未解析のレコード (SRV) は中継のみ
+        assert!(!is_cacheable(&[record(
+            "_http._tcp.example.com",
+            DnsQueryType::SRV,
+            DnsRecordData::Raw(alloc::vec![0; 8]),
+        )]));
+    }
+}
@@ -1,17 +1,21 @@
 // ============================================================================
-// src/net/driver_bridge.rs - VirtIO-Net <-> NetworkStack Bridge
+// src/net/driver_bridge.rs - NIC Driver <-> NetworkStack Bridge
 // ============================================================================
 //!
-//! VirtIO-NetドライバとNetworkStackを接続するブリッジモジュール。
+//! NICドライバ（VirtIO-Net / Intel e1000）とNetworkStackを接続するブリッジモジュール。
 //! 送信コールバック設定と受信パケット処理を統合します。
+//! VirtIO-Net があればそれを使い、なければ e1000 を使います。
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
-use crate::io::virtio::{with_virtio_net, VirtioNetDevice, VirtioNetHeader};
+use crate::io::e1000;
+use crate::io::virtio::{net_features, with_virtio_net, VirtioNetDevice, VirtioNetHeader};
+use super::capture;
 use super::stack::{self, NetworkStack, NetworkConfig};
 use super::ethernet::MacAddress;
+use super::interface::Offloads;
 use super::ipv4::{Ipv4Address, Ipv4Config};
 use alloc::vec::Vec;
 use spin::Mutex;
@@ -42,6 +46,8 @@ static RX_BUFFER: Mutex<[u8; 2048]> = Mutex::new([0u8; 2048]);
 /// Transmit callback for NetworkStack
 /// This is called when NetworkStack needs to send a packet
 fn virtio_transmit(data: &[u8]) -> bool {
+    capture::tap(capture::Direction::Tx, data);
+
     // VirtIO-Netデバイスが利用可能か確認
     let result = with_virtio_net(|device| {
         // 簡単な同期送信を試みる
@@ -68,27 +74,11 @@ fn virtio_transmit(data: &[u8]) -> bool {
 }
 
 /// Low-level packet transmission via VirtIO-Net
+///
+/// チェックサム/TSO はドライバがオフロード（非対応ならソフトウェアで分割）する。
 fn transmit_packet(device: &VirtioNetDevice, data: &[u8]) -> Result<(), &'static str> {
-    // VirtIO-Netヘッダを先頭に追加
-    let mut tx_buffer = alloc::vec![0u8; VirtioNetHeader::SIZE + data.len()];
-    
-    // ヘッダ（デフォルト値でOK）
-    let header = VirtioNetHeader::new_tx();
-    let header_bytes: &[u8] = unsafe {
-        core::slice::from_raw_parts(
-            &header as *const _ as *const u8,
-            VirtioNetHeader::SIZE
-        )
-    };
-    tx_buffer[..VirtioNetHeader::SIZE].copy_from_slice(header_bytes);
-    tx_buffer[VirtioNetHeader::SIZE..].copy_from_slice(data);
-    
-    // 同期送信（実際はasyncが好ましい）
-    // 現在の実装ではTXキューに直接追加
-    // TODO: 非同期送信の完全実装
-    
-    let _ = device; // 現在は未使用（キュー操作が必要）
-    
+    device.transmit(data).map_err(|_| "virtio-net transmit failed")?;
+
     // デバッグ用：パケット送信ログ
     #[cfg(debug_assertions)]
     if data.len() >= 14 {
@@ -102,6 +92,23 @@ fn transmit_packet(device: &VirtioNetDevice, data: &[u8]) -> Result<(), &'static
     Ok(())
 }
 
+/// Transmit callback for the Intel e1000 NIC
+/// チェックサム挿入はドライバがオフロードする（TSO は使わない）
+fn e1000_transmit(data: &[u8]) -> bool {
+    capture::tap(capture::Direction::Tx, data);
+
+    match e1000::transmit(data) {
+        Ok(()) => {
+            TX_PACKETS.fetch_add(1, Ordering::Relaxed);
+            true
+        }
+        Err(e) => {
+            crate::serial_println!("[NET BRIDGE] e1000 transmit error: {}", e);
+            false
+        }
+    }
+}
+
 // ============================================================================
 // Receive Bridge
 // ============================================================================
@@ -117,6 +124,7 @@ pub fn process_received_packet(data: &[u8]) {
     let ethernet_data = &data[VirtioNetHeader::SIZE..];
     
     RX_PACKETS.fetch_add(1, Ordering::Relaxed);
+    capture::tap(capture::Direction::Rx, ethernet_data);
     
     // NetworkStackに渡す
     stack::receive(ethernet_data);
@@ -132,22 +140,37 @@ pub fn process_received_packet(data: &[u8]) {
     }
 }
 
+/// Receive callback for NIC drivers
+/// ドライバがヘッダを外し、チェックサムを検証済みの Ethernet フレームを渡す
+fn driver_receive(frame: &[u8]) {
+    RX_PACKETS.fetch_add(1, Ordering::Relaxed);
+    capture::tap(capture::Direction::Rx, frame);
+    stack::receive(frame);
+}
+
 // ============================================================================
 // Initialization
 // ============================================================================
 
 /// Initialize the network bridge
-/// Connects VirtIO-Net driver to NetworkStack
+/// Connects VirtIO-Net (or e1000) driver to NetworkStack
 pub fn init_bridge() -> Result<(), &'static str> {
     if BRIDGE_INITIALIZED.swap(true, Ordering::SeqCst) {
         return Ok(()); // Already initialized
     }
     
-    crate::serial_println!("[NET BRIDGE] Initializing VirtIO-Net <-> NetworkStack bridge...");
+    crate::serial_println!("[NET BRIDGE] Initializing NIC <-> NetworkStack bridge...");
     
-    // Get MAC address from VirtIO-Net if available
-    let mac = with_virtio_net(|device| {
-        let mac_bytes = device.mac_address();
+    // VirtIO-Net を優先し、なければ e1000 を使う
+    let use_e1000 = with_virtio_net(|_| ()).is_none() && e1000::is_present();
+
+    // Get MAC address from the NIC if available
+    let mac_bytes = if use_e1000 {
+        e1000::with_device(|device| device.mac_address())
+    } else {
+        with_virtio_net(|device| device.mac_address())
+    };
+    let mac = mac_bytes.map(|mac_bytes| {
         MacAddress::from_octets(
             mac_bytes[0], mac_bytes[1], mac_bytes[2],
             mac_bytes[3], mac_bytes[4], mac_bytes[5]
@@ -174,7 +197,35 @@ pub fn init_bridge() -> Result<(), &'static str> {
     
     // Set transmit callback
     if let Some(ref stack) = *stack::stack().lock() {
-        stack.set_transmit_fn(virtio_transmit);
+        let offloads = if use_e1000 {
+            stack.set_transmit_fn(e1000_transmit);
+            // e1000 はチェックサムのみオフロード（TSO はスタックで分割させる）
+            e1000::with_device(|device| {
+                device.set_rx_handler(driver_receive);
+                Offloads {
+                    tx_checksum: true,
+                    rx_checksum: true,
+                    tso: false,
+                }
+            })
+        } else {
+            stack.set_transmit_fn(virtio_transmit);
+            // TSO 非対応デバイスでもドライバがソフトウェア GSO で分割する
+            with_virtio_net(|device| {
+                device.set_rx_handler(driver_receive);
+                let tx = device.tx_offloads();
+                Offloads {
+                    tx_checksum: tx.csum,
+                    rx_checksum: device.has_feature(net_features::VIRTIO_NET_F_GUEST_CSUM),
+                    tso: true,
+                }
+            })
+        };
+        if let Some(offloads) = offloads {
+            stack.set_offloads(offloads);
+        }
+        // <hostname>.local とサービスを mDNS でアナウンス
+        stack.announce_mdns();
     }
     
     crate::serial_println!("[NET BRIDGE] Bridge initialized");
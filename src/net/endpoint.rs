@@ -9,20 +9,26 @@
 //! - Event-driven: NetworkEvent for protocol stack coordination
 
 // Sub-module declarations
+pub mod bbr;
 pub mod congestion;
+pub mod cubic;
 pub mod event;
 pub mod flow_control;
 pub mod futures;
 pub mod handler;
 pub mod inner;
 pub mod manager;
+pub mod rack;
 pub mod retransmit;
+pub mod sack;
 pub mod segment;
 pub mod socket;
 pub mod tcb;
 pub mod tcp_rx;
+pub mod tcp_tx;
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
+pub mod timestamp;
 pub mod types;
 pub mod window_scale;
 
@@ -34,7 +40,7 @@ pub use types::{
 
 // Re-exports: event
 pub use event::{
-    EventWaitFuture, NetworkEvent, NetworkEventQueue, event_queue,
+    EventWaitFuture, NetworkEvent, NetworkEventQueue, event_queue, send_event_ignore,
 };
 
 // Re-exports: inner
@@ -46,11 +52,16 @@ pub use tcb::{
 
 // Re-exports: retransmit
 pub use retransmit::{
-    RetransmitQueue, RtoCalculator, UnackedSegment, check_retransmit_timeouts,
-    get_or_create_retransmit_queue, retransmit_queue_ack, retransmit_queue_push,
-    retransmit_queue_remove,
+    AckInfo, AckOutcome, RetransmitQueue, RtoCalculator, UnackedSegment,
+    check_retransmit_timeouts, get_or_create_retransmit_queue, retransmit_queue_ack,
+    retransmit_queue_push, retransmit_queue_remove,
 };
 
+// Re-exports: sack / rack / timestamp
+pub use rack::{RackState, TlpState};
+pub use sack::{ReassemblyQueue, SackBlock, SackScoreboard};
+pub use timestamp::TimestampOption;
+
 // Re-exports: segment
 pub use segment::{TcpSegmentBuilder, send_tcp_segment};
 
@@ -70,9 +81,12 @@ pub use futures::{AcceptFuture, RecvFromFuture, RecvFuture, SendFuture};
 pub use handler::{EventHandleResult, NetworkEventHandler, init_network_event_handler};
 
 // Re-exports: tcp_rx
-pub use tcp_rx::{network_event_task, process_tcp_segment};
+pub use tcp_rx::{network_event_task, process_tcp_segment, tcp_timer_task};
 
 // Re-exports: congestion
+pub use congestion::{
+    CongestionAlgorithm, CongestionController, default_algorithm, set_default_algorithm,
+};
 
 // Re-exports: window_scale
 
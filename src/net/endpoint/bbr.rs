@@ -0,0 +1,596 @@
+//! # TCP BBR - 輻輳制御
+//!
+//! BBR v1 (draft-cardwell-iccrg-bbr-congestion-control-00) 準拠実装
+//! - ボトルネック帯域 (BtlBw) の窓付き最大値フィルタ（10ラウンド）
+//! - 往復伝搬遅延 (RTprop) の窓付き最小値フィルタ（10秒）
+//! - Startup / Drain / ProbeBW / ProbeRTT 状態機械
+//! - ペーシングレートと cwnd = gain × BDP の算出
+//!
+//! 時間はtick単位（1 tick ≒ 1ms）、帯域はバイト/秒で扱う。
+//! ゲインは BBR_UNIT (=1000) を1.0とする固定小数点。
+
+use core::cmp::{max, min};
+
+/// ゲインの固定小数点単位 (1.0)
+pub const BBR_UNIT: u64 = 1000;
+
+/// Startupゲイン 2/ln(2) ≒ 2.885
+pub const BBR_HIGH_GAIN: u64 = 2885;
+
+/// Drainゲイン 1/high_gain
+pub const BBR_DRAIN_GAIN: u64 = BBR_UNIT * BBR_UNIT / BBR_HIGH_GAIN;
+
+/// 定常状態のcwndゲイン
+pub const BBR_CWND_GAIN: u64 = 2000;
+
+/// ProbeBWのペーシングゲインサイクル
+pub const BBR_PACING_GAIN_CYCLE: [u64; 8] = [1250, 750, 1000, 1000, 1000, 1000, 1000, 1000];
+
+/// BtlBwフィルタの窓（ラウンド数）
+pub const BBR_BW_FILTER_ROUNDS: u64 = 10;
+
+/// RTpropフィルタの窓（tick）
+pub const BBR_MIN_RTT_WINDOW: u64 = 10_000;
+
+/// ProbeRTTの最小滞在時間（tick）
+pub const BBR_PROBE_RTT_DURATION: u64 = 200;
+
+/// パイプ充填判定: 帯域が25%以上伸びなければ飽和とみなす
+pub const BBR_FULL_BW_THRESH: u64 = 1250;
+
+/// パイプ充填判定に必要な連続ラウンド数
+pub const BBR_FULL_BW_COUNT: u8 = 3;
+
+/// 最小cwnd（セグメント数）
+pub const BBR_MIN_CWND_SEGMENTS: u32 = 4;
+
+/// 1秒あたりのtick数
+const TICKS_PER_SEC: u64 = 1000;
+
+/// 窓付き最大値フィルタ（Kathleen Nicholsのアルゴリズム）
+///
+/// 上位3つの (時刻, 値) を保持し、O(1) で窓内最大値を追跡する。
+#[derive(Debug, Clone, Copy)]
+pub struct WindowedMaxFilter {
+    samples: [(u64, u64); 3],
+}
+
+impl WindowedMaxFilter {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            samples: [(0, 0); 3],
+        }
+    }
+
+    /// 現在の最大値
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.samples[0].1
+    }
+
+    /// フィルタをリセット
+    pub fn reset(&mut self, time: u64, value: u64) {
+        self.samples = [(time, value); 3];
+    }
+
+    /// 新しい測定値を追加
+    pub fn update(&mut self, window: u64, time: u64, value: u64) -> u64 {
+        // 新しい最大値、または窓全体が期限切れならリセット
+        if value >= self.samples[0].1 || time.saturating_sub(self.samples[2].0) > window {
+            self.reset(time, value);
+            return self.get();
+        }
+
+        if value >= self.samples[1].1 {
+            self.samples[1] = (time, value);
+            self.samples[2] = (time, value);
+        } else if value >= self.samples[2].1 {
+            self.samples[2] = (time, value);
+        }
+
+        self.subwindow_update(window, time, value)
+    }
+
+    /// 古いサンプルの繰り上げ
+    fn subwindow_update(&mut self, window: u64, time: u64, value: u64) -> u64 {
+        let dt = time.saturating_sub(self.samples[0].0);
+
+        if dt > window {
+            // 最良サンプルが期限切れ: 2番目・3番目を繰り上げ
+            self.samples[0] = self.samples[1];
+            self.samples[1] = self.samples[2];
+            self.samples[2] = (time, value);
+            if time.saturating_sub(self.samples[0].0) > window {
+                self.samples[0] = self.samples[1];
+                self.samples[1] = self.samples[2];
+                self.samples[2] = (time, value);
+            }
+        } else if self.samples[1].0 == self.samples[0].0 && dt > window / 4 {
+            // 窓の1/4経過: 2番目の候補を取り直す
+            self.samples[1] = (time, value);
+            self.samples[2] = (time, value);
+        } else if self.samples[2].0 == self.samples[1].0 && dt > window / 2 {
+            // 窓の1/2経過: 3番目の候補を取り直す
+            self.samples[2] = (time, value);
+        }
+
+        self.get()
+    }
+}
+
+impl Default for WindowedMaxFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// BBR状態機械のモード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BbrMode {
+    /// 指数的に帯域を探索
+    Startup,
+    /// Startupで溜めたキューを排出
+    Drain,
+    /// 定常状態: ゲインサイクルで帯域を探索
+    ProbeBw,
+    /// RTpropを再測定するためにinflightを絞る
+    ProbeRtt,
+}
+
+/// ACK毎にBBRへ渡す情報
+#[derive(Debug, Clone, Copy)]
+pub struct BbrAck {
+    /// 現在時刻（tick）
+    pub now: u64,
+    /// これまでに配送済みの累積バイト数
+    pub delivered: u64,
+    /// 今回ACKされたバイト数
+    pub bytes_acked: u32,
+    /// ACK処理後の送信中バイト数
+    pub bytes_in_flight: u32,
+    /// 新しいラウンドの開始か
+    pub round_start: bool,
+    /// RTTサンプル（tick）
+    pub rtt: Option<u64>,
+    /// 平滑化RTT（tick, 0 = 未測定）
+    pub srtt: u64,
+    /// MSS
+    pub mss: u32,
+    /// 現在の cwnd
+    pub cwnd: u32,
+    /// 損失回復中か
+    pub in_recovery: bool,
+}
+
+/// BBR状態
+#[derive(Debug, Clone)]
+pub struct BbrState {
+    /// 現在のモード
+    mode: BbrMode,
+    /// ボトルネック帯域フィルタ（バイト/秒、時間軸はラウンド数）
+    btl_bw: WindowedMaxFilter,
+    /// RTprop（tick、None = 未測定）
+    min_rtt: Option<u64>,
+    /// RTpropの測定時刻
+    min_rtt_stamp: u64,
+    /// ペーシングゲイン
+    pacing_gain: u64,
+    /// cwndゲイン
+    cwnd_gain: u64,
+    /// ProbeBWサイクル位置
+    cycle_index: usize,
+    /// 現サイクルフェーズ開始時刻
+    cycle_stamp: u64,
+    /// パイプ充填判定用の直近帯域
+    full_bw: u64,
+    /// 帯域が伸びなかった連続ラウンド数
+    full_bw_count: u8,
+    /// パイプ充填済みか
+    filled_pipe: bool,
+    /// ProbeRTT終了予定時刻
+    probe_rtt_done_stamp: Option<u64>,
+    /// ProbeRTT中に1ラウンド経過したか
+    probe_rtt_round_done: bool,
+    /// ProbeRTT / 損失回復前のcwnd
+    prior_cwnd: u32,
+    /// 経過ラウンド数
+    round_count: u64,
+    /// 配送レートサンプル区間の開始時刻
+    sample_start_tick: u64,
+    /// 配送レートサンプル区間開始時点の配送済みバイト数
+    sample_start_delivered: u64,
+    /// 現在のペーシングレート（バイト/秒）
+    pacing_rate: u64,
+}
+
+impl BbrState {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            mode: BbrMode::Startup,
+            btl_bw: WindowedMaxFilter::new(),
+            min_rtt: None,
+            min_rtt_stamp: 0,
+            pacing_gain: BBR_HIGH_GAIN,
+            cwnd_gain: BBR_HIGH_GAIN,
+            cycle_index: 0,
+            cycle_stamp: 0,
+            full_bw: 0,
+            full_bw_count: 0,
+            filled_pipe: false,
+            probe_rtt_done_stamp: None,
+            probe_rtt_round_done: false,
+            prior_cwnd: 0,
+            round_count: 0,
+            sample_start_tick: 0,
+            sample_start_delivered: 0,
+            pacing_rate: 0,
+        }
+    }
+
+    /// 現在のモード
+    #[inline]
+    pub fn mode(&self) -> BbrMode {
+        self.mode
+    }
+
+    /// ボトルネック帯域推定値（バイト/秒）
+    #[inline]
+    pub fn btl_bw(&self) -> u64 {
+        self.btl_bw.get()
+    }
+
+    /// RTprop推定値（tick）
+    #[inline]
+    pub fn min_rtt(&self) -> Option<u64> {
+        self.min_rtt
+    }
+
+    /// ペーシングレート（バイト/秒）
+    #[inline]
+    pub fn pacing_rate(&self) -> u64 {
+        self.pacing_rate
+    }
+
+    /// パイプ充填済みか
+    #[inline]
+    pub fn filled_pipe(&self) -> bool {
+        self.filled_pipe
+    }
+
+    /// 現在のペーシングゲイン
+    #[inline]
+    pub fn pacing_gain(&self) -> u64 {
+        self.pacing_gain
+    }
+
+    /// BDP × gain（バイト）
+    fn inflight(&self, gain: u64, mss: u32, initial_cwnd: u32) -> u32 {
+        let Some(min_rtt) = self.min_rtt else {
+            return initial_cwnd;
+        };
+        let bw = self.btl_bw();
+        if bw == 0 {
+            return initial_cwnd;
+        }
+        let bdp = bw * min_rtt / TICKS_PER_SEC;
+        let target = bdp * gain / BBR_UNIT;
+        // 送信側/受信側の量子化（TSO・遅延ACK）分の余裕
+        let target = target + 3 * mss as u64;
+        min(target, u32::MAX as u64) as u32
+    }
+
+    /// 配送レートを測定してBtlBwフィルタを更新
+    fn update_bandwidth(&mut self, ack: &BbrAck) {
+        if ack.round_start {
+            self.round_count += 1;
+        }
+
+        let interval = max(self.min_rtt.unwrap_or(ack.srtt), 1);
+        let elapsed = ack.now.saturating_sub(self.sample_start_tick);
+        if elapsed >= interval {
+            let delivered = ack.delivered.saturating_sub(self.sample_start_delivered);
+            let rate = delivered * TICKS_PER_SEC / elapsed;
+            self.btl_bw
+                .update(BBR_BW_FILTER_ROUNDS, self.round_count, rate);
+            self.sample_start_tick = ack.now;
+            self.sample_start_delivered = ack.delivered;
+        }
+    }
+
+    /// Startupでパイプが満杯になったか判定
+    fn check_full_pipe(&mut self, ack: &BbrAck) {
+        if self.filled_pipe || !ack.round_start || ack.in_recovery {
+            return;
+        }
+
+        let bw = self.btl_bw();
+        if bw >= self.full_bw * BBR_FULL_BW_THRESH / BBR_UNIT {
+            self.full_bw = bw;
+            self.full_bw_count = 0;
+            return;
+        }
+
+        self.full_bw_count += 1;
+        if self.full_bw_count >= BBR_FULL_BW_COUNT {
+            self.filled_pipe = true;
+        }
+    }
+
+    /// Startup → Drain → ProbeBW の遷移
+    fn check_drain(&mut self, ack: &BbrAck, initial_cwnd: u32) {
+        if self.mode == BbrMode::Startup && self.filled_pipe {
+            self.mode = BbrMode::Drain;
+            self.pacing_gain = BBR_DRAIN_GAIN;
+            self.cwnd_gain = BBR_HIGH_GAIN;
+        }
+
+        if self.mode == BbrMode::Drain
+            && ack.bytes_in_flight <= self.inflight(BBR_UNIT, ack.mss, initial_cwnd)
+        {
+            self.enter_probe_bw(ack.now);
+        }
+    }
+
+    /// ProbeBWへ遷移
+    fn enter_probe_bw(&mut self, now: u64) {
+        self.mode = BbrMode::ProbeBw;
+        self.cwnd_gain = BBR_CWND_GAIN;
+        // 最初の位相は減速(0.75)を避ける
+        self.cycle_index = (now as usize) % (BBR_PACING_GAIN_CYCLE.len() - 1);
+        if self.cycle_index >= 1 {
+            self.cycle_index += 1;
+        }
+        self.cycle_stamp = now;
+        self.pacing_gain = BBR_PACING_GAIN_CYCLE[self.cycle_index];
+    }
+
+    /// ProbeBWのゲインサイクルを進める
+    fn update_cycle_phase(&mut self, ack: &BbrAck, initial_cwnd: u32) {
+        if self.mode != BbrMode::ProbeBw {
+            return;
+        }
+
+        let min_rtt = self.min_rtt.unwrap_or(ack.srtt);
+        let full_length = ack.now.saturating_sub(self.cycle_stamp) > min_rtt;
+
+        let advance = if self.pacing_gain > BBR_UNIT {
+            // 加速位相: 1 RTTprop経過し、かつ目標inflightに到達（または損失）
+            full_length
+                && (ack.in_recovery
+                    || ack.bytes_in_flight
+                        >= self.inflight(self.pacing_gain, ack.mss, initial_cwnd))
+        } else if self.pacing_gain < BBR_UNIT {
+            // 減速位相: 1 RTTprop経過、またはキューを排出し切った
+            full_length || ack.bytes_in_flight <= self.inflight(BBR_UNIT, ack.mss, initial_cwnd)
+        } else {
+            full_length
+        };
+
+        if advance {
+            self.cycle_index = (self.cycle_index + 1) % BBR_PACING_GAIN_CYCLE.len();
+            self.cycle_stamp = ack.now;
+            self.pacing_gain = BBR_PACING_GAIN_CYCLE[self.cycle_index];
+        }
+    }
+
+    /// RTprop更新とProbeRTTの管理
+    fn update_min_rtt(&mut self, ack: &BbrAck, cwnd: &mut u32) {
+        let expired = ack.now.saturating_sub(self.min_rtt_stamp) > BBR_MIN_RTT_WINDOW;
+
+        if let Some(rtt) = ack.rtt
+            && (self.min_rtt.is_none_or(|m| rtt <= m) || expired)
+        {
+            self.min_rtt = Some(rtt);
+            self.min_rtt_stamp = ack.now;
+        }
+
+        if expired && self.mode != BbrMode::ProbeRtt && self.min_rtt.is_some() {
+            self.mode = BbrMode::ProbeRtt;
+            self.pacing_gain = BBR_UNIT;
+            self.cwnd_gain = BBR_UNIT;
+            self.prior_cwnd = max(self.prior_cwnd, *cwnd);
+            self.probe_rtt_done_stamp = None;
+        }
+
+        if self.mode != BbrMode::ProbeRtt {
+            return;
+        }
+
+        let min_cwnd = BBR_MIN_CWND_SEGMENTS * ack.mss;
+        match self.probe_rtt_done_stamp {
+            None if ack.bytes_in_flight <= min_cwnd => {
+                self.probe_rtt_done_stamp = Some(ack.now + BBR_PROBE_RTT_DURATION);
+                self.probe_rtt_round_done = false;
+            }
+            None => {}
+            Some(done) => {
+                if ack.round_start {
+                    self.probe_rtt_round_done = true;
+                }
+                if self.probe_rtt_round_done && ack.now >= done {
+                    self.min_rtt_stamp = ack.now;
+                    *cwnd = max(*cwnd, self.prior_cwnd);
+                    self.prior_cwnd = 0;
+                    if self.filled_pipe {
+                        self.enter_probe_bw(ack.now);
+                    } else {
+                        self.mode = BbrMode::Startup;
+                        self.pacing_gain = BBR_HIGH_GAIN;
+                        self.cwnd_gain = BBR_HIGH_GAIN;
+                    }
+                }
+            }
+        }
+    }
+
+    /// ペーシングレート更新
+    fn update_pacing_rate(&mut self, ack: &BbrAck) {
+        let bw = self.btl_bw();
+        let rate = if bw > 0 {
+            bw * self.pacing_gain / BBR_UNIT
+        } else if let Some(rate) = ((ack.cwnd as u64) * TICKS_PER_SEC).checked_div(ack.srtt) {
+            // 帯域サンプル取得前: cwnd / srtt から初期レートを推定
+            rate * self.pacing_gain / BBR_UNIT
+        } else {
+            return;
+        };
+
+        // Startup中はレートを下げない
+        if self.filled_pipe || rate > self.pacing_rate {
+            self.pacing_rate = rate;
+        }
+    }
+
+    /// ACK処理
+    ///
+    /// 戻り値: 新しい cwnd（バイト）
+    pub fn on_ack(&mut self, ack: BbrAck, initial_cwnd: u32) -> u32 {
+        self.update_bandwidth(&ack);
+        self.check_full_pipe(&ack);
+        self.check_drain(&ack, initial_cwnd);
+        self.update_cycle_phase(&ack, initial_cwnd);
+
+        let mut cwnd = ack.cwnd;
+        self.update_min_rtt(&ack, &mut cwnd);
+        self.update_pacing_rate(&ack);
+
+        // cwnd = cwnd_gain × BDP
+        let target = self.inflight(self.cwnd_gain, ack.mss, initial_cwnd);
+        if ack.in_recovery {
+            // パケット保存則: ACKされた分だけ送信を許可
+            cwnd = max(cwnd, ack.bytes_in_flight.saturating_add(ack.bytes_acked));
+        } else if self.filled_pipe {
+            cwnd = min(cwnd.saturating_add(ack.bytes_acked), target);
+        } else if cwnd < target || ack.delivered < initial_cwnd as u64 {
+            cwnd = cwnd.saturating_add(ack.bytes_acked);
+        }
+
+        let min_cwnd = BBR_MIN_CWND_SEGMENTS * ack.mss;
+        cwnd = max(cwnd, min_cwnd);
+        if self.mode == BbrMode::ProbeRtt {
+            cwnd = min(cwnd, min_cwnd);
+        }
+        cwnd
+    }
+
+    /// 損失回復開始（パケット保存則）
+    ///
+    /// 戻り値: 新しい cwnd（バイト）
+    pub fn on_enter_recovery(&mut self, cwnd: u32, bytes_in_flight: u32, mss: u32) -> u32 {
+        self.prior_cwnd = max(self.prior_cwnd, cwnd);
+        max(bytes_in_flight.saturating_add(mss), BBR_MIN_CWND_SEGMENTS * mss)
+    }
+
+    /// 損失回復終了
+    ///
+    /// 戻り値: 新しい cwnd（バイト）
+    pub fn on_exit_recovery(&mut self, cwnd: u32) -> u32 {
+        let restored = max(cwnd, self.prior_cwnd);
+        self.prior_cwnd = 0;
+        restored
+    }
+
+    /// タイムアウト時の処理
+    ///
+    /// 戻り値: 新しい cwnd（バイト）
+    pub fn on_timeout(&mut self, cwnd: u32, mss: u32) -> u32 {
+        self.prior_cwnd = max(self.prior_cwnd, cwnd);
+        self.full_bw = 0;
+        self.full_bw_count = 0;
+        mss
+    }
+
+    /// 状態リセット
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for BbrState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =====================================================
+// テスト
+// =====================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ack(now: u64, delivered: u64, in_flight: u32, round_start: bool, cwnd: u32) -> BbrAck {
+        BbrAck {
+            now,
+            delivered,
+            bytes_acked: 1000,
+            bytes_in_flight: in_flight,
+            round_start,
+            rtt: Some(20),
+            srtt: 20,
+            mss: 1000,
+            cwnd,
+            in_recovery: false,
+        }
+    }
+
+    #[test]
+    fn test_windowed_max_filter() {
+        let mut f = WindowedMaxFilter::new();
+        assert_eq!(f.update(10, 0, 100), 100);
+        assert_eq!(f.update(10, 1, 50), 100);
+        assert_eq!(f.update(10, 2, 200), 200);
+        // 窓を外れると古い最大値は忘れられる
+        assert_eq!(f.update(10, 20, 80), 80);
+    }
+
+    #[test]
+    fn test_startup_fills_pipe_and_drains() {
+        let mut bbr = BbrState::new();
+        let mut cwnd = 10_000;
+        let mut delivered = 0;
+        let mut now = 0;
+
+        // 帯域が一定（1ms毎に1000バイト = 1MB/s）→ 3ラウンドでパイプ充填
+        for round in 0..20 {
+            for i in 0..20 {
+                now += 1;
+                delivered += 1000;
+                cwnd = bbr.on_ack(ack(now, delivered, 20_000, i == 0 && round > 0, cwnd), 10_000);
+            }
+        }
+
+        assert!(bbr.filled_pipe());
+        assert_ne!(bbr.mode(), BbrMode::Startup);
+        assert!(bbr.btl_bw() >= 900_000 && bbr.btl_bw() <= 1_100_000);
+        assert_eq!(bbr.min_rtt(), Some(20));
+        // BDP = 1MB/s × 20ms = 20KB → cwnd ≒ 2 × BDP
+        assert!(cwnd <= 2 * 20_000 + 3 * 1000);
+    }
+
+    #[test]
+    fn test_probe_rtt_after_window_expiry() {
+        let mut bbr = BbrState::new();
+        let mut cwnd = 50_000;
+        let _ = bbr.on_ack(ack(1, 1000, 10_000, false, cwnd), 10_000);
+
+        // RTprop窓切れ（RTTは増加）
+        let mut late = ack(BBR_MIN_RTT_WINDOW + 10, 2000, 10_000, false, cwnd);
+        late.rtt = Some(40);
+        cwnd = bbr.on_ack(late, 10_000);
+
+        assert_eq!(bbr.mode(), BbrMode::ProbeRtt);
+        assert_eq!(cwnd, BBR_MIN_CWND_SEGMENTS * 1000);
+    }
+
+    #[test]
+    fn test_recovery_packet_conservation() {
+        let mut bbr = BbrState::new();
+        let cwnd = bbr.on_enter_recovery(40_000, 30_000, 1000);
+        assert_eq!(cwnd, 31_000);
+        assert_eq!(bbr.on_exit_recovery(cwnd), 40_000);
+    }
+}
@@ -4,8 +4,16 @@
 //! - Slow Start
 //! - Congestion Avoidance  
 //! - Fast Retransmit / Fast Recovery (NewReno)
+//!
+//! アルゴリズム固有の処理は別モジュールに委譲する
+//! - RFC 8312 CUBIC (+HyStart): [`super::cubic`]
+//! - BBR v1: [`super::bbr`]
 
 use core::cmp::{max, min};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use super::bbr::{BbrAck, BbrMode, BbrState};
+use super::cubic::CubicState;
 
 /// Maximum Segment Size (デフォルト)
 pub const DEFAULT_MSS: u32 = 1460;
@@ -16,14 +24,17 @@ pub const INITIAL_WINDOW: u32 = 10;
 /// 最小輻輳ウィンドウ
 pub const MIN_CWND: u32 = 2;
 
+/// 保持するRTTサンプル履歴の数
+pub const RTT_HISTORY_LEN: usize = 8;
+
 /// 輻輳制御アルゴリズムの種類
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CongestionAlgorithm {
     /// RFC 5681 NewReno
     NewReno,
-    /// RFC 8312 CUBIC (将来実装)
+    /// RFC 8312 CUBIC
     Cubic,
-    /// RFC 9002 BBR (将来実装)
+    /// BBR v1
     Bbr,
 }
 
@@ -33,6 +44,66 @@ impl Default for CongestionAlgorithm {
     }
 }
 
+impl CongestionAlgorithm {
+    /// 全アルゴリズム
+    pub const ALL: [CongestionAlgorithm; 3] = [
+        CongestionAlgorithm::NewReno,
+        CongestionAlgorithm::Cubic,
+        CongestionAlgorithm::Bbr,
+    ];
+
+    /// アルゴリズム名
+    pub const fn name(self) -> &'static str {
+        match self {
+            CongestionAlgorithm::NewReno => "newreno",
+            CongestionAlgorithm::Cubic => "cubic",
+            CongestionAlgorithm::Bbr => "bbr",
+        }
+    }
+
+    /// 名前からアルゴリズムを取得（大文字小文字を区別しない）
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("reno") {
+            return Some(CongestionAlgorithm::NewReno);
+        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|alg| alg.name().eq_ignore_ascii_case(name))
+    }
+
+    const fn to_u8(self) -> u8 {
+        match self {
+            CongestionAlgorithm::NewReno => 0,
+            CongestionAlgorithm::Cubic => 1,
+            CongestionAlgorithm::Bbr => 2,
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => CongestionAlgorithm::Cubic,
+            2 => CongestionAlgorithm::Bbr,
+            _ => CongestionAlgorithm::NewReno,
+        }
+    }
+}
+
+/// システムデフォルトの輻輳制御アルゴリズム
+static DEFAULT_ALGORITHM: AtomicU8 = AtomicU8::new(0);
+
+/// システムデフォルトのアルゴリズムを取得
+pub fn default_algorithm() -> CongestionAlgorithm {
+    CongestionAlgorithm::from_u8(DEFAULT_ALGORITHM.load(Ordering::Relaxed))
+}
+
+/// システムデフォルトのアルゴリズムを設定
+///
+/// 以降に作成される接続に適用される（既存接続は変更しない）
+pub fn set_default_algorithm(algorithm: CongestionAlgorithm) {
+    DEFAULT_ALGORITHM.store(algorithm.to_u8(), Ordering::Relaxed);
+}
+
 /// 輻輳制御状態
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CongestionState {
@@ -50,11 +121,155 @@ impl Default for CongestionState {
     }
 }
 
-/// 輻輳制御コントローラ (NewReno)
+/// ACK受信時の入力
+#[derive(Debug, Clone, Copy)]
+pub struct AckSample {
+    /// 今回ACKされたバイト数（新規ACK）
+    pub bytes_acked: u32,
+    /// 重複ACKかどうか
+    pub is_dup_ack: bool,
+    /// 未確認の最古シーケンス番号
+    pub snd_una: u32,
+    /// 現在時刻（tick）
+    pub now: u64,
+    /// RTTサンプル（tick）
+    pub rtt: Option<u64>,
+}
+
+/// RTT推定値（tick単位）
+#[derive(Debug, Clone, Copy)]
+pub struct RttEstimate {
+    /// 最新サンプル
+    pub latest: u64,
+    /// 平滑化RTT (0 = 未測定)
+    pub srtt: u64,
+    /// 最小RTT (u64::MAX = 未測定)
+    pub min: u64,
+    /// サンプル総数
+    pub samples: u64,
+    /// 直近のサンプル（リングバッファ）
+    history: [u64; RTT_HISTORY_LEN],
+}
+
+impl RttEstimate {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            latest: 0,
+            srtt: 0,
+            min: u64::MAX,
+            samples: 0,
+            history: [0; RTT_HISTORY_LEN],
+        }
+    }
+
+    /// サンプル追加
+    pub fn add_sample(&mut self, rtt: u64) {
+        self.history[(self.samples as usize) % RTT_HISTORY_LEN] = rtt;
+        self.samples += 1;
+        self.latest = rtt;
+        self.min = min(self.min, rtt);
+        self.srtt = if self.srtt == 0 {
+            rtt
+        } else {
+            (7 * self.srtt + rtt) / 8
+        };
+    }
+
+    /// 最小RTT（未測定ならNone）
+    pub fn min_rtt(&self) -> Option<u64> {
+        (self.min != u64::MAX).then_some(self.min)
+    }
+
+    /// 直近のサンプルを古い順に返す
+    pub fn recent(&self) -> impl Iterator<Item = u64> + '_ {
+        let count = min(self.samples as usize, RTT_HISTORY_LEN);
+        let start = (self.samples as usize).wrapping_sub(count);
+        (start..start + count).map(move |i| self.history[i % RTT_HISTORY_LEN])
+    }
+}
+
+impl Default for RttEstimate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// ペーサー（トークンバケット）
+///
+/// ペーシングレートに従って送信可能バイト数を払い出す。
+/// tick粒度が粗いため、1 tick分 + 2 MSS をバースト上限とする。
+#[derive(Debug, Clone, Copy)]
+pub struct Pacer {
+    /// 送信可能なクレジット（バイト）
+    credit: u64,
+    /// 最終補充時刻
+    last_refill: u64,
+}
+
+impl Pacer {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            credit: 0,
+            last_refill: 0,
+        }
+    }
+
+    /// クレジットを補充
+    fn refill(&mut self, now: u64, rate: u64, mss: u32) {
+        let elapsed = now.saturating_sub(self.last_refill);
+        self.last_refill = now;
+        let burst = rate / 1000 + 2 * mss as u64;
+        self.credit = min(self.credit.saturating_add(rate * elapsed / 1000), burst);
+    }
+
+    /// 送信可能か（rate == 0 はペーシング無効）
+    pub fn can_send(&mut self, now: u64, rate: u64, mss: u32, bytes: u32) -> bool {
+        if rate == 0 {
+            return true;
+        }
+        self.refill(now, rate, mss);
+        self.credit >= bytes as u64 || self.credit >= mss as u64
+    }
+
+    /// 送信を記録
+    pub fn on_send(&mut self, bytes: u32) {
+        self.credit = self.credit.saturating_sub(bytes as u64);
+    }
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// アルゴリズム固有の状態
+#[derive(Debug, Clone)]
+enum AlgorithmState {
+    NewReno,
+    Cubic(CubicState),
+    Bbr(BbrState),
+}
+
+impl AlgorithmState {
+    fn for_algorithm(algorithm: CongestionAlgorithm) -> Self {
+        match algorithm {
+            CongestionAlgorithm::NewReno => AlgorithmState::NewReno,
+            CongestionAlgorithm::Cubic => AlgorithmState::Cubic(CubicState::new()),
+            CongestionAlgorithm::Bbr => AlgorithmState::Bbr(BbrState::new()),
+        }
+    }
+}
+
+/// 輻輳制御コントローラ
 #[derive(Debug, Clone)]
 pub struct CongestionController {
     /// アルゴリズム
     algorithm: CongestionAlgorithm,
+    /// アルゴリズム固有の状態
+    algo_state: AlgorithmState,
     /// 現在の状態
     state: CongestionState,
     /// 輻輳ウィンドウ (cwnd) - バイト単位
@@ -71,37 +286,67 @@ pub struct CongestionController {
     bytes_acked: u32,
     /// 送信中 (in-flight) のバイト数
     bytes_in_flight: u32,
+    /// 配送済みの累積バイト数
+    delivered: u64,
+    /// 次のラウンド開始となる配送済みバイト数
+    next_round_delivered: u64,
+    /// 最後に観測した時刻（tick）
+    now: u64,
+    /// RTT推定
+    rtt: RttEstimate,
+    /// ペーサー
+    pacer: Pacer,
 }
 
 impl CongestionController {
-    /// 新規作成
+    /// 新規作成（システムデフォルトのアルゴリズム）
     pub fn new() -> Self {
-        let mss = DEFAULT_MSS;
-        Self {
-            algorithm: CongestionAlgorithm::NewReno,
-            state: CongestionState::SlowStart,
-            cwnd: INITIAL_WINDOW * mss,
-            ssthresh: u32::MAX, // 初期値は無限大（最初のロスまで）
-            mss,
-            dup_ack_count: 0,
-            recover: 0,
-            bytes_acked: 0,
-            bytes_in_flight: 0,
-        }
+        Self::with_algorithm(default_algorithm(), DEFAULT_MSS)
     }
 
     /// MSSを指定して作成
     pub fn with_mss(mss: u32) -> Self {
+        Self::with_algorithm(default_algorithm(), mss)
+    }
+
+    /// アルゴリズムとMSSを指定して作成
+    pub fn with_algorithm(algorithm: CongestionAlgorithm, mss: u32) -> Self {
         Self {
-            algorithm: CongestionAlgorithm::NewReno,
+            algorithm,
+            algo_state: AlgorithmState::for_algorithm(algorithm),
             state: CongestionState::SlowStart,
             cwnd: INITIAL_WINDOW * mss,
-            ssthresh: u32::MAX,
+            ssthresh: u32::MAX, // 初期値は無限大（最初のロスまで）
             mss,
             dup_ack_count: 0,
             recover: 0,
             bytes_acked: 0,
             bytes_in_flight: 0,
+            delivered: 0,
+            next_round_delivered: 0,
+            now: 0,
+            rtt: RttEstimate::new(),
+            pacer: Pacer::new(),
+        }
+    }
+
+    /// アルゴリズム取得
+    #[inline]
+    pub fn algorithm(&self) -> CongestionAlgorithm {
+        self.algorithm
+    }
+
+    /// アルゴリズムを切り替え
+    ///
+    /// cwnd/ssthresh は引き継ぎ、アルゴリズム固有の状態は初期化する
+    pub fn set_algorithm(&mut self, algorithm: CongestionAlgorithm) {
+        if self.algorithm == algorithm {
+            return;
+        }
+        self.algorithm = algorithm;
+        self.algo_state = AlgorithmState::for_algorithm(algorithm);
+        if algorithm == CongestionAlgorithm::Bbr && self.state != CongestionState::FastRecovery {
+            self.state = CongestionState::SlowStart;
         }
     }
 
@@ -129,6 +374,52 @@ impl CongestionController {
         self.mss
     }
 
+    /// 送信中バイト数取得
+    #[inline]
+    pub fn bytes_in_flight(&self) -> u32 {
+        self.bytes_in_flight
+    }
+
+    /// RTT推定値取得
+    #[inline]
+    pub fn rtt(&self) -> &RttEstimate {
+        &self.rtt
+    }
+
+    /// BBR状態取得（BBR選択時のみ）
+    pub fn bbr(&self) -> Option<&BbrState> {
+        match &self.algo_state {
+            AlgorithmState::Bbr(bbr) => Some(bbr),
+            _ => None,
+        }
+    }
+
+    /// CUBIC状態取得（CUBIC選択時のみ）
+    pub fn cubic(&self) -> Option<&CubicState> {
+        match &self.algo_state {
+            AlgorithmState::Cubic(cubic) => Some(cubic),
+            _ => None,
+        }
+    }
+
+    /// ペーシングレート（バイト/秒、0 = 未算出）
+    ///
+    /// BBRはモデルから算出し、ロスベースのアルゴリズムは
+    /// cwnd/srtt にスロースタート中2.0、それ以外1.2のゲインを掛ける
+    pub fn pacing_rate(&self) -> u64 {
+        if let AlgorithmState::Bbr(bbr) = &self.algo_state {
+            return bbr.pacing_rate();
+        }
+        if self.rtt.srtt == 0 {
+            return 0;
+        }
+        let base = (self.cwnd as u64) * 1000 / self.rtt.srtt;
+        match self.state {
+            CongestionState::SlowStart => base * 2,
+            _ => base * 12 / 10,
+        }
+    }
+
     /// 送信可能なバイト数を計算
     /// effective_window = min(cwnd, rwnd) - bytes_in_flight
     pub fn available_window(&self, rwnd: u32) -> u32 {
@@ -141,9 +432,19 @@ impl CongestionController {
         self.available_window(rwnd) >= bytes
     }
 
+    /// ペーシングを考慮して送信可能かどうか
+    pub fn can_send_paced(&mut self, rwnd: u32, bytes: u32, now: u64) -> bool {
+        if !self.can_send(rwnd, bytes) {
+            return false;
+        }
+        let rate = self.pacing_rate();
+        self.pacer.can_send(now, rate, self.mss, bytes)
+    }
+
     /// データ送信を記録
     pub fn on_send(&mut self, bytes: u32) {
         self.bytes_in_flight = self.bytes_in_flight.saturating_add(bytes);
+        self.pacer.on_send(bytes);
     }
 
     /// ACK受信時の処理 (RFC 5681 Section 3.1)
@@ -152,23 +453,61 @@ impl CongestionController {
     /// - is_dup_ack: 重複ACKかどうか
     /// - snd_una: 未確認の最古シーケンス番号
     pub fn on_ack(&mut self, bytes_acked: u32, is_dup_ack: bool, snd_una: u32) {
+        self.on_ack_sample(AckSample {
+            bytes_acked,
+            is_dup_ack,
+            snd_una,
+            now: self.now,
+            rtt: None,
+        });
+    }
+
+    /// ACK受信時の処理（時刻・RTTサンプル付き）
+    pub fn on_ack_sample(&mut self, sample: AckSample) {
+        let bytes_acked = sample.bytes_acked;
+        self.now = max(self.now, sample.now);
+        if let Some(rtt) = sample.rtt {
+            self.rtt.add_sample(rtt);
+        }
+
         // in-flight更新
         self.bytes_in_flight = self.bytes_in_flight.saturating_sub(bytes_acked);
 
-        if is_dup_ack {
-            self.on_dup_ack(snd_una);
+        // 配送量とラウンドの追跡
+        self.delivered = self.delivered.saturating_add(bytes_acked as u64);
+        let round_start = bytes_acked > 0 && self.delivered >= self.next_round_delivered;
+        if round_start {
+            self.next_round_delivered = self.delivered + self.bytes_in_flight as u64;
+        }
+
+        if sample.is_dup_ack {
+            self.on_dup_ack(sample.snd_una);
             return;
         }
 
         // 新規ACK - 重複カウンタリセット
         self.dup_ack_count = 0;
 
+        if matches!(self.algo_state, AlgorithmState::Bbr(_)) {
+            self.on_ack_bbr(bytes_acked, sample, round_start);
+            return;
+        }
+
         match self.state {
             CongestionState::SlowStart => {
                 // Slow Start: cwnd += min(N, SMSS) for each ACK
                 // 簡略化: cwnd += bytes_acked (1 MSS per ACK in practice)
                 self.cwnd = self.cwnd.saturating_add(min(bytes_acked, self.mss));
 
+                // HyStart: 遅延増加・ACKトレインでスロースタートを早期終了
+                let (now, rtt, min_rtt, cwnd, mss) =
+                    (self.now, sample.rtt, self.rtt.min_rtt(), self.cwnd, self.mss);
+                if let AlgorithmState::Cubic(cubic) = &mut self.algo_state
+                    && cubic.hystart.on_ack(now, round_start, rtt, min_rtt, cwnd, mss)
+                {
+                    self.ssthresh = self.cwnd;
+                }
+
                 // ssthreshに達したらCongestion Avoidanceへ
                 if self.cwnd >= self.ssthresh {
                     self.state = CongestionState::CongestionAvoidance;
@@ -176,6 +515,13 @@ impl CongestionController {
                 }
             }
             CongestionState::CongestionAvoidance => {
+                if let AlgorithmState::Cubic(cubic) = &mut self.algo_state {
+                    let rtt = self.rtt.min_rtt().unwrap_or(0);
+                    self.cwnd =
+                        cubic.congestion_avoidance(self.cwnd, bytes_acked, self.mss, self.now, rtt);
+                    return;
+                }
+
                 // Congestion Avoidance: cwnd += SMSS * SMSS / cwnd for each ACK
                 // RFC 5681の推奨: cwnd += SMSS per RTT (approximately)
                 self.bytes_acked = self.bytes_acked.saturating_add(bytes_acked);
@@ -188,7 +534,7 @@ impl CongestionController {
             }
             CongestionState::FastRecovery => {
                 // Fast Recovery: 新規ACKで回復完了
-                if snd_una > self.recover {
+                if sample.snd_una > self.recover {
                     // 回復完了 - Congestion Avoidanceへ
                     self.cwnd = self.ssthresh;
                     self.state = CongestionState::CongestionAvoidance;
@@ -202,6 +548,44 @@ impl CongestionController {
         }
     }
 
+    /// BBRのACK処理
+    fn on_ack_bbr(&mut self, bytes_acked: u32, sample: AckSample, round_start: bool) {
+        let initial_cwnd = INITIAL_WINDOW * self.mss;
+        let mut in_recovery = self.state == CongestionState::FastRecovery;
+        let AlgorithmState::Bbr(bbr) = &mut self.algo_state else {
+            return;
+        };
+
+        if in_recovery && sample.snd_una > self.recover {
+            self.cwnd = bbr.on_exit_recovery(self.cwnd);
+            in_recovery = false;
+        }
+
+        self.cwnd = bbr.on_ack(
+            BbrAck {
+                now: self.now,
+                delivered: self.delivered,
+                bytes_acked,
+                bytes_in_flight: self.bytes_in_flight,
+                round_start,
+                rtt: sample.rtt,
+                srtt: self.rtt.srtt,
+                mss: self.mss,
+                cwnd: self.cwnd,
+                in_recovery,
+            },
+            initial_cwnd,
+        );
+
+        self.state = if in_recovery {
+            CongestionState::FastRecovery
+        } else if bbr.mode() == BbrMode::Startup {
+            CongestionState::SlowStart
+        } else {
+            CongestionState::CongestionAvoidance
+        };
+    }
+
     /// 重複ACK処理 (Fast Retransmit / Fast Recovery)
     fn on_dup_ack(&mut self, snd_una: u32) {
         self.dup_ack_count = self.dup_ack_count.saturating_add(1);
@@ -222,12 +606,25 @@ impl CongestionController {
 
     /// Fast Recovery開始
     fn enter_fast_recovery(&mut self, snd_una: u32) {
-        // ssthresh = max(FlightSize / 2, 2*SMSS)
-        let flight_size = self.bytes_in_flight;
-        self.ssthresh = max(flight_size / 2, MIN_CWND * self.mss);
-
-        // cwnd = ssthresh + 3*SMSS (既受信の3重複ACK分)
-        self.cwnd = self.ssthresh + 3 * self.mss;
+        match &mut self.algo_state {
+            AlgorithmState::NewReno => {
+                // ssthresh = max(FlightSize / 2, 2*SMSS)
+                let flight_size = self.bytes_in_flight;
+                self.ssthresh = max(flight_size / 2, MIN_CWND * self.mss);
+
+                // cwnd = ssthresh + 3*SMSS (既受信の3重複ACK分)
+                self.cwnd = self.ssthresh + 3 * self.mss;
+            }
+            AlgorithmState::Cubic(cubic) => {
+                // ssthresh = cwnd * β_cubic
+                self.ssthresh = cubic.on_congestion_event(self.cwnd, self.mss);
+                self.cwnd = self.ssthresh + 3 * self.mss;
+            }
+            AlgorithmState::Bbr(bbr) => {
+                // BBRはssthreshを使わず、パケット保存則でcwndを抑える
+                self.cwnd = bbr.on_enter_recovery(self.cwnd, self.bytes_in_flight, self.mss);
+            }
+        }
 
         // 回復ポイント設定
         self.recover = snd_una;
@@ -235,14 +632,55 @@ impl CongestionController {
         self.state = CongestionState::FastRecovery;
     }
 
+    /// SACK/RACKによるロス検出時の処理 (RFC 6675 / RFC 8985)
+    ///
+    /// 既に高速回復中なら何もしない（1ウィンドウにつき1回の削減）
+    /// - high_data: 回復ポイント（ロス検出時のsnd_nxt）
+    pub fn on_loss_detected(&mut self, snd_una: u32, high_data: u32) {
+        if self.state == CongestionState::FastRecovery {
+            return;
+        }
+        self.enter_fast_recovery(snd_una);
+        // 送信済み最大シーケンス番号 (RFC 6582 の recover)
+        self.recover = high_data.wrapping_sub(1);
+        self.dup_ack_count = 0;
+    }
+
+    /// SACK情報の反映（on_ackより前に呼ぶ）
+    ///
+    /// - newly_sacked: 新たにSACKされたバイト数（パイプから除外）
+    /// - sacked_acked: 累積ACKで確認されたうち既にSACK済みだったバイト数
+    ///   （on_ackで二重に差し引かれないよう先に戻す）
+    pub fn on_sack(&mut self, newly_sacked: u32, sacked_acked: u32) {
+        self.bytes_in_flight = self
+            .bytes_in_flight
+            .saturating_add(sacked_acked)
+            .saturating_sub(newly_sacked);
+        self.delivered = self
+            .delivered
+            .saturating_sub(sacked_acked as u64)
+            .saturating_add(newly_sacked as u64);
+    }
+
     /// タイムアウト時の処理 (RFC 5681 Section 3.1)
     pub fn on_timeout(&mut self) {
-        // ssthresh = max(FlightSize / 2, 2*SMSS)
-        let flight_size = self.bytes_in_flight;
-        self.ssthresh = max(flight_size / 2, MIN_CWND * self.mss);
-
-        // cwnd = 1 MSS (または loss window)
-        self.cwnd = self.mss;
+        match &mut self.algo_state {
+            AlgorithmState::NewReno => {
+                // ssthresh = max(FlightSize / 2, 2*SMSS)
+                let flight_size = self.bytes_in_flight;
+                self.ssthresh = max(flight_size / 2, MIN_CWND * self.mss);
+
+                // cwnd = 1 MSS (または loss window)
+                self.cwnd = self.mss;
+            }
+            AlgorithmState::Cubic(cubic) => {
+                self.ssthresh = cubic.on_timeout(self.cwnd, self.mss);
+                self.cwnd = self.mss;
+            }
+            AlgorithmState::Bbr(bbr) => {
+                self.cwnd = bbr.on_timeout(self.cwnd, self.mss);
+            }
+        }
 
         // Slow Startに戻る
         self.state = CongestionState::SlowStart;
@@ -264,6 +702,11 @@ impl CongestionController {
         self.recover = 0;
         self.bytes_acked = 0;
         self.bytes_in_flight = 0;
+        self.delivered = 0;
+        self.next_round_delivered = 0;
+        self.rtt = RttEstimate::new();
+        self.pacer = Pacer::new();
+        self.algo_state = AlgorithmState::for_algorithm(self.algorithm);
     }
 
     /// デバッグ情報
@@ -276,6 +719,9 @@ impl CongestionController {
             mss: self.mss,
             bytes_in_flight: self.bytes_in_flight,
             dup_ack_count: self.dup_ack_count,
+            pacing_rate: self.pacing_rate(),
+            srtt: self.rtt.srtt,
+            min_rtt: self.rtt.min_rtt(),
         }
     }
 }
@@ -296,6 +742,9 @@ pub struct CongestionDebugInfo {
     pub mss: u32,
     pub bytes_in_flight: u32,
     pub dup_ack_count: u8,
+    pub pacing_rate: u64,
+    pub srtt: u64,
+    pub min_rtt: Option<u64>,
 }
 
 // =====================================================
@@ -377,4 +826,107 @@ mod tests {
         // rwnd制限
         assert_eq!(cc.available_window(5000), 2000);
     }
+
+    #[test]
+    fn test_algorithm_names() {
+        assert_eq!(CongestionAlgorithm::from_name("CUBIC"), Some(CongestionAlgorithm::Cubic));
+        assert_eq!(CongestionAlgorithm::from_name("bbr"), Some(CongestionAlgorithm::Bbr));
+        assert_eq!(CongestionAlgorithm::from_name("reno"), Some(CongestionAlgorithm::NewReno));
+        assert_eq!(CongestionAlgorithm::from_name("vegas"), None);
+    }
+
+    #[test]
+    fn test_cubic_reduction_is_beta() {
+        let mut cc = CongestionController::with_algorithm(CongestionAlgorithm::Cubic, 1000);
+        cc.cwnd = 100_000;
+        cc.bytes_in_flight = 100_000;
+
+        cc.on_ack(0, true, 1000);
+        cc.on_ack(0, true, 1000);
+        cc.on_ack(0, true, 1000);
+
+        assert_eq!(cc.state(), CongestionState::FastRecovery);
+        // NewRenoの 1/2 ではなく β=0.7
+        assert_eq!(cc.ssthresh(), 70_000);
+    }
+
+    #[test]
+    fn test_rtt_samples_and_pacing() {
+        let mut cc = CongestionController::with_algorithm(CongestionAlgorithm::NewReno, 1000);
+        for (i, rtt) in [30u64, 20, 25].iter().enumerate() {
+            cc.on_ack_sample(AckSample {
+                bytes_acked: 1000,
+                is_dup_ack: false,
+                snd_una: 0,
+                now: i as u64 * 10,
+                rtt: Some(*rtt),
+            });
+        }
+
+        assert_eq!(cc.rtt().latest, 25);
+        assert_eq!(cc.rtt().min_rtt(), Some(20));
+        assert_eq!(cc.rtt().samples, 3);
+        let recent: alloc::vec::Vec<u64> = cc.rtt().recent().collect();
+        assert_eq!(recent, alloc::vec![30, 20, 25]);
+
+        // スロースタート中: 2 × cwnd / srtt
+        let expected = 2 * (cc.cwnd() as u64) * 1000 / cc.rtt().srtt;
+        assert_eq!(cc.pacing_rate(), expected);
+    }
+
+    #[test]
+    fn test_bbr_paces_sends() {
+        let mut cc = CongestionController::with_algorithm(CongestionAlgorithm::Bbr, 1000);
+        let mut now = 0;
+        for _ in 0..50 {
+            now += 1;
+            cc.on_send(1000);
+            cc.on_ack_sample(AckSample {
+                bytes_acked: 1000,
+                is_dup_ack: false,
+                snd_una: 0,
+                now,
+                rtt: Some(10),
+            });
+        }
+        assert!(cc.pacing_rate() > 0);
+        assert!(cc.bbr().is_some());
+
+        // クレジットを使い切ると同一tick内の送信は抑制される
+        let mut sent = 0;
+        while cc.can_send_paced(u32::MAX, 1000, now) && sent < 1000 {
+            cc.on_send(1000);
+            sent += 1;
+        }
+        assert!(sent < 1000);
+    }
+
+    #[test]
+    fn test_switch_algorithm_keeps_window() {
+        let mut cc = CongestionController::with_algorithm(CongestionAlgorithm::NewReno, 1000);
+        cc.cwnd = 42_000;
+        cc.set_algorithm(CongestionAlgorithm::Cubic);
+        assert_eq!(cc.algorithm(), CongestionAlgorithm::Cubic);
+        assert_eq!(cc.cwnd(), 42_000);
+        assert!(cc.cubic().is_some());
+    }
+
+    #[test]
+    fn test_loss_detected_once_per_window() {
+        let mut cc = CongestionController::with_algorithm(CongestionAlgorithm::NewReno, 1000);
+        cc.cwnd = 20_000;
+        cc.bytes_in_flight = 20_000;
+
+        cc.on_loss_detected(1000, 21_000);
+        assert_eq!(cc.state(), CongestionState::FastRecovery);
+        let ssthresh = cc.ssthresh();
+
+        // 同一回復中の追加ロスでは再削減しない
+        cc.on_loss_detected(2000, 21_000);
+        assert_eq!(cc.ssthresh(), ssthresh);
+
+        // SACKされたバイトはパイプから除外
+        cc.on_sack(3000, 0);
+        assert_eq!(cc.bytes_in_flight, 17_000);
+    }
 }
@@ -0,0 +1,452 @@
+//! # TCP CUBIC - 輻輳制御
+//!
+//! RFC 8312 (CUBIC for Fast Long-Distance Networks) 準拠実装
+//! - 3次関数によるウィンドウ成長 W_cubic(t) = C(t-K)^3 + W_max
+//! - TCPフレンドリー領域 (W_est)
+//! - Fast Convergence
+//! - HyStart (遅延増加 / ACKトレイン検出によるスロースタート早期終了)
+//!
+//! 時間はtick単位（1 tick ≒ 1ms）、ウィンドウはバイト単位で扱う。
+//! 浮動小数点は使用せず、係数は有理数で表現する。
+
+use core::cmp::{max, min};
+
+/// 乗算減少係数 β = 0.7 (分子)
+pub const CUBIC_BETA_NUM: u64 = 7;
+/// 乗算減少係数 β = 0.7 (分母)
+pub const CUBIC_BETA_DEN: u64 = 10;
+
+/// スケーリング定数 C = 0.4 (分子)
+pub const CUBIC_C_NUM: u64 = 4;
+/// スケーリング定数 C = 0.4 (分母)
+pub const CUBIC_C_DEN: u64 = 10;
+
+/// TCPフレンドリー領域の加算増加係数 α = 3(1-β)/(1+β) = 9/17
+const CUBIC_ALPHA_NUM: u64 = 9;
+const CUBIC_ALPHA_DEN: u64 = 17;
+
+/// 1秒あたりのtick数（ms精度）
+const TICKS_PER_SEC: u64 = 1000;
+
+/// W_cubic の計算に使う |t - K| の上限（tick, 約12日）
+///
+/// 4 * 2^90 * u32::MAX < 2^127 なので C * d^3 * MSS が i128 に収まる。
+/// MSS = 1 でもこの手前で W_cubic は u32::MAX に飽和する。
+const CUBIC_MAX_DELTA_TICKS: i128 = 1 << 30;
+
+/// HyStartを適用する最小cwnd（セグメント数）
+pub const HYSTART_LOW_WINDOW: u32 = 16;
+/// 1ラウンドで収集するRTTサンプル数
+pub const HYSTART_MIN_SAMPLES: u8 = 8;
+/// 遅延増加判定閾値の下限（tick）
+pub const HYSTART_DELAY_MIN: u64 = 4;
+/// 遅延増加判定閾値の上限（tick）
+pub const HYSTART_DELAY_MAX: u64 = 16;
+/// ACKトレインとみなすACK間隔（tick）
+pub const HYSTART_ACK_DELTA: u64 = 2;
+
+/// 整数立方根（floor）
+pub fn cube_root(n: u128) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+
+    // 二分探索: 2^43 の3乗は u128 に収まる
+    let mut lo: u64 = 0;
+    let mut hi: u64 = 1 << 43;
+    while lo < hi {
+        let mid = lo + (hi - lo).div_ceil(2);
+        let cube = (mid as u128) * (mid as u128) * (mid as u128);
+        if cube <= n {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    lo
+}
+
+/// HyStart状態
+#[derive(Debug, Clone)]
+pub struct HyStart {
+    /// 有効フラグ
+    pub enabled: bool,
+    /// 現在のラウンド開始時刻
+    round_start: u64,
+    /// 直近のACK受信時刻（ACKトレイン検出用）
+    last_ack: u64,
+    /// 現ラウンドの最小RTT
+    curr_rtt_min: u64,
+    /// 前ラウンドの最小RTT
+    last_rtt_min: u64,
+    /// 現ラウンドで収集したサンプル数
+    sample_count: u8,
+    /// スロースタート終了点を検出済みか
+    found: bool,
+}
+
+impl HyStart {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            enabled: true,
+            round_start: 0,
+            last_ack: 0,
+            curr_rtt_min: u64::MAX,
+            last_rtt_min: u64::MAX,
+            sample_count: 0,
+            found: false,
+        }
+    }
+
+    /// リセット（タイムアウト後など）
+    pub fn reset(&mut self) {
+        let enabled = self.enabled;
+        *self = Self::new();
+        self.enabled = enabled;
+    }
+
+    /// ラウンド開始処理
+    fn start_round(&mut self, now: u64) {
+        if self.curr_rtt_min != u64::MAX {
+            self.last_rtt_min = self.curr_rtt_min;
+        }
+        self.curr_rtt_min = u64::MAX;
+        self.sample_count = 0;
+        self.round_start = now;
+        self.last_ack = now;
+    }
+
+    /// スロースタート中のACK処理
+    ///
+    /// 戻り値: スロースタートを終了すべきなら true
+    pub fn on_ack(
+        &mut self,
+        now: u64,
+        round_start: bool,
+        rtt: Option<u64>,
+        min_rtt: Option<u64>,
+        cwnd: u32,
+        mss: u32,
+    ) -> bool {
+        if !self.enabled || self.found {
+            return self.found;
+        }
+
+        if round_start {
+            self.start_round(now);
+        }
+
+        // 小さなウィンドウでは判定しない（誤検出防止）
+        if cwnd < HYSTART_LOW_WINDOW * mss {
+            return false;
+        }
+
+        // ACKトレイン: 密に並んだACKの列がmin_rtt/2を超えたらパイプが満杯
+        if now.saturating_sub(self.last_ack) <= HYSTART_ACK_DELTA {
+            self.last_ack = now;
+            if let Some(min_rtt) = min_rtt
+                && min_rtt > 0
+                && now.saturating_sub(self.round_start) >= min_rtt / 2
+            {
+                self.found = true;
+                return true;
+            }
+        }
+
+        // 遅延増加: ラウンド最小RTTが前ラウンドより閾値以上増えた
+        if let Some(rtt) = rtt {
+            if self.sample_count < HYSTART_MIN_SAMPLES {
+                self.curr_rtt_min = min(self.curr_rtt_min, rtt);
+                self.sample_count += 1;
+            }
+
+            if self.sample_count >= HYSTART_MIN_SAMPLES && self.last_rtt_min != u64::MAX {
+                let threshold =
+                    (self.last_rtt_min / 8).clamp(HYSTART_DELAY_MIN, HYSTART_DELAY_MAX);
+                if self.curr_rtt_min >= self.last_rtt_min + threshold {
+                    self.found = true;
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// 終了点を検出済みか
+    #[inline]
+    pub fn found(&self) -> bool {
+        self.found
+    }
+}
+
+impl Default for HyStart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// CUBIC状態
+#[derive(Debug, Clone)]
+pub struct CubicState {
+    /// 直近の輻輳イベント直前のウィンドウ W_max（バイト）
+    w_max: u32,
+    /// Fast Convergence用の前回 W_max（バイト）
+    w_last_max: u32,
+    /// 現在のエポック開始時刻（None = 未開始）
+    epoch_start: Option<u64>,
+    /// W_cubic が origin_point に達するまでの時間 K（tick）
+    k: u64,
+    /// 3次関数の原点（バイト）
+    origin_point: u32,
+    /// TCPフレンドリー推定ウィンドウ W_est（バイト）
+    w_est: u32,
+    /// Fast Convergence 有効
+    pub fast_convergence: bool,
+    /// HyStart
+    pub hystart: HyStart,
+}
+
+impl CubicState {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            w_max: 0,
+            w_last_max: 0,
+            epoch_start: None,
+            k: 0,
+            origin_point: 0,
+            w_est: 0,
+            fast_convergence: true,
+            hystart: HyStart::new(),
+        }
+    }
+
+    /// W_max 取得
+    #[inline]
+    pub fn w_max(&self) -> u32 {
+        self.w_max
+    }
+
+    /// K 取得（tick）
+    #[inline]
+    pub fn k(&self) -> u64 {
+        self.k
+    }
+
+    /// 輻輳イベント（3重複ACK / RACK検出）時の処理
+    ///
+    /// 戻り値: 新しい ssthresh（バイト）
+    pub fn on_congestion_event(&mut self, cwnd: u32, mss: u32) -> u32 {
+        self.epoch_start = None;
+
+        // Fast Convergence (RFC 8312 Section 4.6)
+        if self.fast_convergence && cwnd < self.w_last_max {
+            self.w_last_max = cwnd;
+            let reduced = (cwnd as u64) * (CUBIC_BETA_DEN + CUBIC_BETA_NUM) / (2 * CUBIC_BETA_DEN);
+            self.w_max = reduced as u32;
+        } else {
+            self.w_last_max = cwnd;
+            self.w_max = cwnd;
+        }
+
+        let ssthresh = (cwnd as u64) * CUBIC_BETA_NUM / CUBIC_BETA_DEN;
+        max(ssthresh as u32, 2 * mss)
+    }
+
+    /// タイムアウト時の処理
+    ///
+    /// 戻り値: 新しい ssthresh（バイト）
+    pub fn on_timeout(&mut self, cwnd: u32, mss: u32) -> u32 {
+        let ssthresh = self.on_congestion_event(cwnd, mss);
+        self.hystart.reset();
+        ssthresh
+    }
+
+    /// W_cubic(t) をバイト単位で計算
+    fn w_cubic(&self, t: u64, mss: u32) -> u32 {
+        // delta = C * (t - K)^3 [segments], t/K はms → 秒^3 = ms^3 / 10^9
+        let d = t as i128 - self.k as i128;
+        let d = d.clamp(-CUBIC_MAX_DELTA_TICKS, CUBIC_MAX_DELTA_TICKS);
+        let delta = (CUBIC_C_NUM as i128) * d * d * d * (mss as i128)
+            / ((CUBIC_C_DEN as i128) * (TICKS_PER_SEC as i128).pow(3));
+        let w = self.origin_point as i128 + delta;
+        w.clamp(mss as i128, u32::MAX as i128) as u32
+    }
+
+    /// 輻輳回避中のACK処理
+    ///
+    /// 戻り値: 新しい cwnd（バイト）
+    pub fn congestion_avoidance(
+        &mut self,
+        cwnd: u32,
+        bytes_acked: u32,
+        mss: u32,
+        now: u64,
+        rtt: u64,
+    ) -> u32 {
+        if cwnd == 0 || bytes_acked == 0 {
+            return cwnd;
+        }
+
+        if self.epoch_start.is_none() {
+            self.epoch_start = Some(now);
+            if cwnd < self.w_max {
+                // K = cbrt((W_max - cwnd) / C) [秒] → tick
+                let diff_segs_scaled = (self.w_max - cwnd) as u128
+                    * (CUBIC_C_DEN as u128)
+                    * (TICKS_PER_SEC as u128).pow(3)
+                    / ((CUBIC_C_NUM as u128) * (mss as u128));
+                self.k = cube_root(diff_segs_scaled);
+                self.origin_point = self.w_max;
+            } else {
+                self.k = 0;
+                self.origin_point = cwnd;
+            }
+            self.w_est = cwnd;
+        }
+
+        let epoch = self.epoch_start.unwrap_or(now);
+        let t = now.saturating_sub(epoch).saturating_add(rtt);
+        let mut target = self.w_cubic(t, mss);
+
+        // TCPフレンドリー領域: W_est += α * MSS * acked / cwnd
+        let est_inc =
+            (CUBIC_ALPHA_NUM * mss as u64 * bytes_acked as u64) / (CUBIC_ALPHA_DEN * cwnd as u64);
+        self.w_est = self.w_est.saturating_add(est_inc as u32);
+        target = max(target, self.w_est);
+
+        // 1 RTTあたり最大1.5倍に制限
+        let target = min(target as u64, cwnd as u64 * 3 / 2) as u32;
+
+        if target > cwnd {
+            // cwnd += (target - cwnd) / cwnd per ACK（バイトカウント版）
+            let inc = ((target - cwnd) as u64 * bytes_acked as u64) / cwnd as u64;
+            cwnd.saturating_add(max(inc as u32, 1))
+        } else {
+            // プラトー付近: 100 RTTで1 MSS程度のごく僅かな増加
+            let inc = (mss as u64 * bytes_acked as u64) / (100 * cwnd as u64);
+            cwnd.saturating_add(inc as u32)
+        }
+    }
+
+    /// 状態リセット
+    pub fn reset(&mut self) {
+        let fast_convergence = self.fast_convergence;
+        let hystart_enabled = self.hystart.enabled;
+        *self = Self::new();
+        self.fast_convergence = fast_convergence;
+        self.hystart.enabled = hystart_enabled;
+    }
+}
+
+impl Default for CubicState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =====================================================
+// テスト
+// =====================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_root() {
+        assert_eq!(cube_root(0), 0);
+        assert_eq!(cube_root(1), 1);
+        assert_eq!(cube_root(26), 2);
+        assert_eq!(cube_root(27), 3);
+        assert_eq!(cube_root(1_000_000_000), 1000);
+    }
+
+    #[test]
+    fn test_multiplicative_decrease() {
+        let mut cubic = CubicState::new();
+        let ssthresh = cubic.on_congestion_event(100_000, 1000);
+        assert_eq!(ssthresh, 70_000);
+        assert_eq!(cubic.w_max(), 100_000);
+
+        // Fast Convergence: 前回より小さいW_maxではさらに下げる
+        let _ = cubic.on_congestion_event(80_000, 1000);
+        assert_eq!(cubic.w_max(), 68_000);
+    }
+
+    #[test]
+    fn test_k_matches_rfc() {
+        // W_max = 100 seg, β = 0.7 → K = cbrt(100 * 0.3 / 0.4) ≒ 4.217秒
+        let mut cubic = CubicState::new();
+        let ssthresh = cubic.on_congestion_event(100_000, 1000);
+        let _ = cubic.congestion_avoidance(ssthresh, 1000, 1000, 0, 10);
+        assert!(cubic.k() >= 4200 && cubic.k() <= 4230);
+    }
+
+    #[test]
+    fn test_concave_then_convex_growth() {
+        let mut cubic = CubicState::new();
+        let mss = 1000;
+        let mut cwnd = cubic.on_congestion_event(100_000, mss);
+        let mut now = 0;
+
+        // 凹領域: W_max に向けて成長し、超えない
+        for _ in 0..200 {
+            cwnd = cubic.congestion_avoidance(cwnd, mss, mss, now, 50);
+            now += 10;
+        }
+        assert!(cwnd > 70_000);
+        assert!(cwnd <= 100_500);
+
+        // K を十分超えた後は凸領域で W_max を超える
+        now = 9000;
+        for _ in 0..200 {
+            cwnd = cubic.congestion_avoidance(cwnd, mss, mss, now, 50);
+            now += 10;
+        }
+        assert!(cwnd > 100_000);
+    }
+
+    #[test]
+    fn test_w_cubic_saturates_after_long_epoch() {
+        let mut cubic = CubicState::new();
+        let ssthresh = cubic.on_congestion_event(100_000, 1000);
+        cubic.congestion_avoidance(ssthresh, 1000, 1000, 0, 100);
+        assert_eq!(cubic.w_cubic(cubic.k(), 1000), 100_000);
+        // 損失なしで長時間経っても溢れずに飽和する
+        assert_eq!(cubic.w_cubic(u64::MAX, u32::MAX), u32::MAX);
+        assert_eq!(cubic.w_cubic(u64::MAX, 1), u32::MAX);
+    }
+
+    #[test]
+    fn test_hystart_delay_increase() {
+        let mut hs = HyStart::new();
+        let mss = 1000;
+        let cwnd = 32 * mss;
+
+        // 1ラウンド目: RTT 100
+        for i in 0..8 {
+            assert!(!hs.on_ack(i * 10, i == 0, Some(100), None, cwnd, mss));
+        }
+        // 2ラウンド目: RTT 120 (閾値 clamp(100/8, 4, 16) = 12 以上の増加)
+        let mut exited = false;
+        for i in 0..8 {
+            exited |= hs.on_ack(1000 + i * 10, i == 0, Some(120), None, cwnd, mss);
+        }
+        assert!(exited);
+        assert!(hs.found());
+    }
+
+    #[test]
+    fn test_hystart_ignores_small_window() {
+        let mut hs = HyStart::new();
+        for i in 0..8 {
+            hs.on_ack(i * 10, i == 0, Some(100), None, 4000, 1000);
+        }
+        for i in 0..8 {
+            assert!(!hs.on_ack(1000 + i * 10, i == 0, Some(500), None, 4000, 1000));
+        }
+    }
+}
@@ -40,6 +40,8 @@ pub enum NetworkEvent {
         data: Vec<u8>,
         remote: SocketAddr,
     },
+    /// ループバックの受信キューにパケットあり
+    LoopbackRx,
 }
 
 /// イベントキュー（ロックフリーリングバッファ）
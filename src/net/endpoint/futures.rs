@@ -7,8 +7,10 @@ use core::future::Future;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
+use super::event::{NetworkEvent, send_event_ignore};
 use super::socket::{OwnedSocket, Socket};
-use super::types::{SocketAddr, SocketError, SocketResult, SocketState};
+use super::tcp_tx;
+use super::types::{SocketAddr, SocketError, SocketResult, SocketState, SocketType};
 
 /// 非同期受信Future
 pub struct RecvFuture {
@@ -34,20 +36,14 @@ impl Future for RecvFuture {
 
         let mut inner = this.socket.inner().lock();
 
-        // 状態チェック
-        if !inner.state.can_receive() {
-            return Poll::Ready(Err(SocketError::NotConnected));
-        }
-
-        // データがあれば即座に返す（O(1)）
+        // データがあれば即座に返す（O(1)、相手のFIN後に残ったデータも含む）
         if !inner.recv_buffer.is_empty() {
-            let len = this.buffer.len().min(inner.recv_buffer.len());
-            for i in 0..len {
-                if let Some(byte) = inner.recv_buffer.pop_front() {
-                    this.buffer[i] = byte;
-                }
-            }
+            let len = inner.recv_from_buffer(&mut this.buffer);
             this.buffer.truncate(len);
+            drop(inner);
+            if this.socket.socket_type() == SocketType::Tcp {
+                tcp_tx::on_consumed(this.socket.fd(), len);
+            }
             return Poll::Ready(Ok(core::mem::take(&mut this.buffer)));
         }
 
@@ -56,6 +52,11 @@ impl Future for RecvFuture {
             return Poll::Ready(Ok(Vec::new()));
         }
 
+        // 状態チェック
+        if !inner.state.can_receive() {
+            return Poll::Ready(Err(SocketError::NotConnected));
+        }
+
         // Wakerを登録してPending
         inner.recv_waker = Some(cx.waker().clone());
         Poll::Pending
@@ -102,6 +103,7 @@ impl Future for SendFuture {
         let available = inner
             .send_buffer_limit
             .saturating_sub(inner.send_buffer.len());
+        let mut written = false;
         if available > 0 {
             let remaining = &this.data[this.offset..];
             let to_send = remaining.len().min(available);
@@ -109,16 +111,29 @@ impl Future for SendFuture {
                 .send_buffer
                 .extend(remaining[..to_send].iter().copied());
             this.offset += to_send;
+            written = to_send > 0;
+        }
 
-            // 全データ送信済みなら完了
-            if this.offset >= this.data.len() {
-                return Poll::Ready(Ok(this.offset));
-            }
+        // 全データ書き込み済みでなければWakerを登録（送信で空きができたら起こされる）
+        let done = this.offset >= this.data.len();
+        if !done {
+            inner.send_waker = Some(cx.waker().clone());
+        }
+        drop(inner);
+
+        // 送信データがあることをネットワークスタックに通知
+        if written {
+            send_event_ignore(NetworkEvent::DataReady {
+                fd: this.socket.fd(),
+                socket_type: this.socket.socket_type(),
+            });
         }
 
-        // Wakerを登録してPending
-        inner.send_waker = Some(cx.waker().clone());
-        Poll::Pending
+        if done {
+            Poll::Ready(Ok(this.offset))
+        } else {
+            Poll::Pending
+        }
     }
 }
 
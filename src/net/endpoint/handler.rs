@@ -4,11 +4,19 @@
 
 use alloc::vec::Vec;
 
+use crate::net::ipv4::Ipv4Address;
+
 use super::event::NetworkEvent;
+use super::inner::SocketInner;
 use super::manager::SOCKET_MANAGER;
-use super::segment::TcpSegmentBuilder;
+use super::retransmit::{get_or_create_retransmit_queue, retransmit_queue_push};
+use super::segment::{TcpSegmentBuilder, send_tcp_segment};
 use super::tcb::{TcpConnectionState, TcpControlBlockEntry, tcb_table};
-use super::types::{SocketAddr, SocketError, SocketFd, SocketResult, SocketType};
+use super::tcp_tx;
+use super::types::{SocketAddr, SocketError, SocketFd, SocketType};
+
+/// TIME-WAIT を避けてエフェメラルポートを選び直す回数
+const TIME_WAIT_PORT_ATTEMPTS: usize = 16;
 
 /// イベント処理の結果
 #[derive(Debug)]
@@ -46,40 +54,31 @@ impl NetworkEventHandler {
             NetworkEvent::Listen { fd, local, backlog } => self.handle_listen(fd, local, backlog),
             NetworkEvent::Close { fd } => self.handle_close(fd),
             NetworkEvent::SendTo { fd, data, remote } => self.handle_send_to(fd, remote, data),
+            NetworkEvent::LoopbackRx => {
+                crate::net::stack::poll_loopback();
+                EventHandleResult::Success
+            }
         }
     }
 
     /// DataReadyイベント処理
-    /// 送信バッファにデータがあるのでTCPで送信
-    fn handle_data_ready(&self, fd: SocketFd, _socket_type: SocketType) -> EventHandleResult {
-        let manager = SOCKET_MANAGER.read();
-        let Some(ref mgr) = *manager else {
-            return EventHandleResult::SocketNotFound(fd);
-        };
+    /// 送信バッファのデータをTCPセグメントにして送信
+    fn handle_data_ready(&self, fd: SocketFd, socket_type: SocketType) -> EventHandleResult {
+        if socket_type != SocketType::Tcp {
+            return EventHandleResult::ProtocolError(SocketError::InvalidArgument);
+        }
 
-        let Some(socket) = mgr.get(fd) else {
+        let Some(socket) = tcp_tx::socket_for(fd) else {
             return EventHandleResult::SocketNotFound(fd);
         };
 
-        // 送信バッファからデータを取得
-        let data = {
-            let mut inner = socket.inner().lock();
-            if inner.send_buffer.is_empty() {
-                return EventHandleResult::Success;
-            }
-            inner.send_buffer.drain(..).collect::<Vec<u8>>()
-        };
-
-        // TCPストリーム経由で送信（実際のプロトコルスタック呼び出し）
-        let inner = socket.inner().lock();
-        if let Some(ref _tcp_stream) = inner.tcp_stream {
-            // TODO: TCP送信の実装
-            // tcp_stream.send(&data)?;
-            let _ = data; // 現時点では未使用警告を抑制
-            EventHandleResult::Success
-        } else {
-            EventHandleResult::ProtocolError(SocketError::NotConnected)
+        // 接続確立前のデータはSYN-ACK受信後に送る
+        if tcb_table().find_by_fd(fd).is_none() {
+            return EventHandleResult::ProtocolError(SocketError::NotConnected);
         }
+
+        tcp_tx::transmit(&socket);
+        EventHandleResult::Success
     }
 
     /// Connectイベント処理
@@ -100,25 +99,49 @@ impl NetworkEventHandler {
         };
 
         // ローカルポートが未割り当ての場合はエフェメラルポートを割り当て
+        // ローカルIPが未指定の場合は経路に応じた送信元アドレスを選択
+        let local_ip = if local.ip == [0, 0, 0, 0] {
+            crate::net::stack::select_source(Ipv4Address::new(remote.ip))
+                .map(|addr| *addr.as_bytes())
+                .unwrap_or(local.ip)
+        } else {
+            local.ip
+        };
+        // ローカルポートが未割り当ての場合はエフェメラルポートを割り当て
+        // （TIME-WAIT 中の4タプルになるポートは避ける）
         let local_port = if local.port == 0 {
-            mgr.allocate_ephemeral_port(SocketType::Tcp)
+            (0..TIME_WAIT_PORT_ATTEMPTS)
+                .filter_map(|_| mgr.allocate_ephemeral_port(SocketType::Tcp))
+                .find(|&port| {
+                    tcb_table()
+                        .time_wait(SocketAddr::new(local_ip, port), remote)
+                        .is_none()
+                })
                 .unwrap_or(49152)
         } else {
             local.port
         };
-        let local_addr = SocketAddr::new(local.ip, local_port);
+        let local_addr = SocketAddr::new(local_ip, local_port);
 
         // ソケットのローカルアドレスを更新
-        {
+        let congestion_algorithm = {
             let mut inner = socket.inner().lock();
             inner.local_addr = Some(local_addr);
-        }
+            inner.congestion_algorithm
+        };
 
         // TCB（TCP Control Block）を作成
         let isn = tcb_table().generate_isn();
         let mut tcb = TcpControlBlockEntry::new(fd, local_addr, remote);
         tcb.initialize_seq(isn);
+        if let Some(algorithm) = congestion_algorithm {
+            tcb.set_congestion_algorithm(algorithm);
+        }
         tcb.state = TcpConnectionState::SynSent;
+        // SYNは1バイト消費
+        tcb.snd_nxt = isn.wrapping_add(1);
+        // MSS・ウィンドウスケール・SACK-Permitted・タイムスタンプを提示
+        let syn_options = tcb.syn_options(tcb_table().get_current_tick(), false);
         tcb_table().insert(tcb);
 
         // SYNパケット構築
@@ -126,16 +149,18 @@ impl NetworkEventHandler {
             .seq(isn)
             .syn()
             .window(65535)
+            .options(&syn_options)
             .build();
 
         // チェックサム計算
         TcpSegmentBuilder::calculate_checksum(&mut syn_segment, local_addr.ip, remote.ip);
 
+        // SYNも再送対象（SYN-ACKが来なければRTOで再送）
+        get_or_create_retransmit_queue(local_addr, remote);
+        retransmit_queue_push(local_addr, remote, isn, syn_segment.clone());
+
         // パケット送信（IPスタック経由）
-        if let Err(e) = self.send_tcp_segment(local_addr, remote, syn_segment) {
-            crate::serial_println!("TCP: Failed to send SYN packet: {:?}", e);
-            return EventHandleResult::ProtocolError(SocketError::Internal);
-        }
+        send_tcp_segment(local_addr, remote, syn_segment);
 
         crate::serial_println!(
             "TCP: SYN sent {}:{} -> {}:{} (seq={})",
@@ -152,31 +177,6 @@ impl NetworkEventHandler {
         EventHandleResult::Success
     }
 
-    /// TCPセグメント送信（IPスタック経由）
-    fn send_tcp_segment(
-        &self,
-        src: SocketAddr,
-        dst: SocketAddr,
-        segment: Vec<u8>,
-    ) -> SocketResult<()> {
-        // IPv4パケットを構築して送信
-        // 1. IPv4ヘッダ構築
-        // 2. ネットワークスタック経由で送信
-
-        // TODO: 実際のIP層との統合
-        // 現時点ではスタブ実装
-        let _ = (src, dst, segment);
-
-        // グローバルネットワークスタックを使用
-        // crate::net::stack::global_stack()?.send_ipv4(
-        //     dst.ip,
-        //     crate::net::ipv4::IpProtocol::TCP,
-        //     &segment
-        // )?;
-
-        Ok(())
-    }
-
     /// Listenイベント処理
     /// サーバーソケットを設定
     fn handle_listen(&self, fd: SocketFd, local: SocketAddr, backlog: u32) -> EventHandleResult {
@@ -185,35 +185,24 @@ impl NetworkEventHandler {
             return EventHandleResult::SocketNotFound(fd);
         };
 
-        let Some(_socket) = mgr.get(fd) else {
+        let Some(socket) = mgr.get(fd) else {
             return EventHandleResult::SocketNotFound(fd);
         };
 
-        // TODO: TCP Listenの実装
-        // 1. TCPリスナー登録
-        // 2. 接続要求キューの設定
-        let _ = (local, backlog);
+        // SYNの宛先ポートからリスナーを引けるようにする
+        if let Err(e) = mgr.bind_port(SocketType::Tcp, local.port, fd) {
+            return EventHandleResult::ProtocolError(e);
+        }
+        let mut inner = socket.inner().lock();
+        inner.accept_backlog = (backlog as usize).clamp(1, SocketInner::DEFAULT_BACKLOG);
 
         EventHandleResult::Success
     }
 
     /// Closeイベント処理
-    /// 接続を終了
+    /// 送信バッファを送り切ってからFINを送る（ハンドシェイク中なら破棄）
     fn handle_close(&self, fd: SocketFd) -> EventHandleResult {
-        let manager = SOCKET_MANAGER.read();
-        let Some(ref mgr) = *manager else {
-            return EventHandleResult::SocketNotFound(fd);
-        };
-
-        let Some(_socket) = mgr.get(fd) else {
-            return EventHandleResult::SocketNotFound(fd);
-        };
-
-        // TODO: TCP終了処理
-        // 1. FINパケット送信
-        // 2. FIN-ACK待機
-        // 3. リソース解放
-
+        tcp_tx::close(fd);
         EventHandleResult::Success
     }
 
@@ -250,7 +239,6 @@ impl Default for NetworkEventHandler {
 /// ネットワークイベント処理の初期化
 pub fn init_network_event_handler() {
     // イベントキューは既に初期化済み（NETWORK_EVENT_QUEUE）
-    // タスクスケジューラにnetwork_event_taskを登録する
-    // TODO: タスクスケジューラとの統合
+    // network_event_task はカーネル起動時にエグゼキュータへ登録される
     crate::serial_println!("Network: Event handler initialized");
 }
@@ -8,6 +8,7 @@ use alloc::vec::Vec;
 use crate::net::tcp::{TcpListener as TcpListenerImpl, TcpStream};
 use crate::net::udp::UdpSocket as RawUdpSocket;
 
+use super::congestion::CongestionAlgorithm;
 use super::types::{AcceptedConnection, SocketAddr, SocketError, SocketResult, SocketState};
 
 /// ソケットの可変状態（Mutex保護対象）
@@ -40,6 +41,8 @@ pub struct SocketInner {
     pub accept_backlog: usize,
     /// エラー状態
     pub last_error: Option<SocketError>,
+    /// 輻輳制御アルゴリズム（None = システムデフォルト）
+    pub congestion_algorithm: Option<CongestionAlgorithm>,
     /// 受信待ちWaker（非同期通知用）
     pub recv_waker: Option<core::task::Waker>,
     /// 送信待ちWaker（非同期通知用）
@@ -75,6 +78,7 @@ impl SocketInner {
             accept_queue: VecDeque::with_capacity(Self::DEFAULT_BACKLOG),
             accept_backlog: Self::DEFAULT_BACKLOG,
             last_error: None,
+            congestion_algorithm: None,
             recv_waker: None,
             send_waker: None,
             connect_waker: None,
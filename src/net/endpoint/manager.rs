@@ -7,7 +7,7 @@ use core::sync::atomic::{AtomicU32, Ordering};
 use spin::RwLock;
 
 use super::socket::Socket;
-use super::types::{SocketError, SocketFd, SocketResult, SocketType};
+use super::types::{NEXT_FD, SocketError, SocketFd, SocketResult, SocketType};
 
 /// エフェメラルポート範囲
 const EPHEMERAL_PORT_START: u16 = 49152;
@@ -71,15 +71,16 @@ impl SocketManager {
 
         if let Some(ref s) = socket {
             // ポートの解放
+            // acceptした接続はリスナーとポートを共有するので、自分の登録だけ外す
             if let Some(addr) = s.local_addr() {
-                match s.socket_type() {
-                    SocketType::Tcp => {
-                        self.tcp_ports.write().remove(&addr.port);
-                    }
-                    SocketType::Udp => {
-                        self.udp_ports.write().remove(&addr.port);
-                    }
-                    _ => {}
+                let ports = match s.socket_type() {
+                    SocketType::Tcp => &self.tcp_ports,
+                    SocketType::Udp => &self.udp_ports,
+                    _ => return socket,
+                };
+                let mut ports = ports.write();
+                if ports.get(&addr.port) == Some(&fd) {
+                    ports.remove(&addr.port);
                 }
             }
         }
@@ -136,9 +137,10 @@ impl SocketManager {
     }
 
     /// 次のソケットFD生成（内部用）
+    ///
+    /// Socket::newと同じカウンタを使い、FDの重複を避ける
     pub fn generate_fd(&self) -> SocketFd {
-        static FD_COUNTER: AtomicU32 = AtomicU32::new(1);
-        SocketFd::from_raw(FD_COUNTER.fetch_add(1, Ordering::Relaxed))
+        SocketFd::from_raw(NEXT_FD.fetch_add(1, Ordering::Relaxed))
     }
 }
 
@@ -0,0 +1,333 @@
+//! # RACK-TLP - 時間ベースのロス検出
+//!
+//! RFC 8985 準拠実装
+//! - RACK: 後から送ったセグメントが配送済みなら、先に送った未確認セグメントは
+//!   RTT + 再順序ウィンドウ経過後にロスとみなす
+//! - TLP: 末尾ロスをRTOを待たずに検出するためのプローブ送信
+//! - D-SACKによる再順序ウィンドウの適応
+
+use super::sack::{seq_le, seq_lt};
+
+/// 再順序ウィンドウ拡大後の持続回数（回復回数）
+pub const RACK_REO_WND_PERSIST: u8 = 16;
+
+/// 再順序ウィンドウを0にする判定用の重複閾値（セグメント数）
+pub const RACK_DUPTHRESH: usize = 3;
+
+/// 遅延ACK最悪値 (WCDelAckT, tick)
+pub const TLP_WC_DEL_ACK: u64 = 200;
+
+/// RTT未測定時のPTO (tick)
+pub const TLP_INITIAL_PTO: u64 = 1000;
+
+/// ロス判定結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RackVerdict {
+    /// まだ判定対象ではない（RACKセグメントより後に送信）
+    NotYet,
+    /// ロス
+    Lost,
+    /// 指定tick後に再判定
+    Wait(u64),
+}
+
+/// RACK状態
+#[derive(Debug, Clone)]
+pub struct RackState {
+    /// 配送済みセグメントのうち最も新しい送信時刻
+    xmit_ts: u64,
+    /// そのセグメントの終端シーケンス番号
+    end_seq: u32,
+    /// 配送済みセグメントの最大終端 (FACK)
+    fack: u32,
+    /// 最新のRTT（RACK.rtt）
+    rtt: u64,
+    /// 最小RTT
+    min_rtt: u64,
+    /// サンプル取得済みか
+    active: bool,
+    /// 再順序を観測したか
+    reordering_seen: bool,
+    /// 再順序ウィンドウ倍率
+    reo_wnd_mult: u32,
+    /// 倍率の残り持続回数
+    reo_wnd_persist: u8,
+    /// D-SACKラウンド終端
+    dsack_round: Option<u32>,
+}
+
+impl RackState {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            xmit_ts: 0,
+            end_seq: 0,
+            fack: 0,
+            rtt: 0,
+            min_rtt: u64::MAX,
+            active: false,
+            reordering_seen: false,
+            reo_wnd_mult: 1,
+            reo_wnd_persist: 0,
+            dsack_round: None,
+        }
+    }
+
+    /// RACK.rtt
+    #[inline]
+    pub fn rtt(&self) -> u64 {
+        self.rtt
+    }
+
+    /// 最小RTT（未測定ならNone）
+    #[inline]
+    pub fn min_rtt(&self) -> Option<u64> {
+        (self.min_rtt != u64::MAX).then_some(self.min_rtt)
+    }
+
+    /// 再順序を観測したか
+    #[inline]
+    pub fn reordering_seen(&self) -> bool {
+        self.reordering_seen
+    }
+
+    /// 再順序ウィンドウ倍率
+    #[inline]
+    pub fn reo_wnd_mult(&self) -> u32 {
+        self.reo_wnd_mult
+    }
+
+    /// セグメントが配送された（累積ACKまたはSACK）
+    ///
+    /// RFC 8985 Section 6.2 Step 2/3
+    pub fn on_delivered(&mut self, xmit_ts: u64, end_seq: u32, is_retransmit: bool, now: u64) {
+        let rtt = now.saturating_sub(xmit_ts);
+
+        // 再送セグメントで最小RTTより短いACKは元送信へのACKの可能性が高い
+        if is_retransmit && self.min_rtt().is_some_and(|min| rtt < min) {
+            return;
+        }
+        self.min_rtt = self.min_rtt.min(rtt);
+
+        // 再順序検出: FACKより前のセグメントが新たに配送された
+        if self.active && seq_lt(end_seq, self.fack) {
+            self.reordering_seen = true;
+        } else {
+            self.fack = end_seq;
+        }
+
+        if !self.active
+            || xmit_ts > self.xmit_ts
+            || (xmit_ts == self.xmit_ts && seq_lt(self.end_seq, end_seq))
+        {
+            self.xmit_ts = xmit_ts;
+            self.end_seq = end_seq;
+            self.rtt = rtt;
+        }
+        self.active = true;
+    }
+
+    /// D-SACK受信時の再順序ウィンドウ適応（RFC 8985 Section 6.2 Step 4）
+    pub fn on_dsack(&mut self, snd_una: u32, snd_nxt: u32) {
+        if self.dsack_round.is_none_or(|round| seq_le(round, snd_una)) {
+            self.dsack_round = Some(snd_nxt);
+            self.reo_wnd_mult = self.reo_wnd_mult.saturating_add(1);
+            self.reo_wnd_persist = RACK_REO_WND_PERSIST;
+        }
+    }
+
+    /// 損失回復の完了
+    pub fn on_recovery_exit(&mut self) {
+        if self.reo_wnd_persist > 0 {
+            self.reo_wnd_persist -= 1;
+            if self.reo_wnd_persist == 0 {
+                self.reo_wnd_mult = 1;
+            }
+        }
+    }
+
+    /// 再順序ウィンドウ（RFC 8985 Section 6.2 Step 4）
+    pub fn reo_wnd(&self, in_recovery: bool, sacked_segments: usize, srtt: u64) -> u64 {
+        if !self.reordering_seen && (in_recovery || sacked_segments >= RACK_DUPTHRESH) {
+            return 0;
+        }
+        let Some(min_rtt) = self.min_rtt() else {
+            return 0;
+        };
+        let wnd = (self.reo_wnd_mult as u64) * min_rtt / 4;
+        if srtt > 0 { wnd.min(srtt) } else { wnd }
+    }
+
+    /// 未確認セグメントのロス判定（RFC 8985 Section 6.2 Step 5）
+    pub fn verdict(&self, xmit_ts: u64, end_seq: u32, now: u64, reo_wnd: u64) -> RackVerdict {
+        if !self.active {
+            return RackVerdict::NotYet;
+        }
+        let sent_before = xmit_ts < self.xmit_ts
+            || (xmit_ts == self.xmit_ts && seq_lt(end_seq, self.end_seq));
+        if !sent_before {
+            return RackVerdict::NotYet;
+        }
+        let deadline = xmit_ts + self.rtt + reo_wnd;
+        if deadline <= now {
+            RackVerdict::Lost
+        } else {
+            RackVerdict::Wait(deadline - now)
+        }
+    }
+}
+
+impl Default for RackState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tail Loss Probe 状態
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlpState {
+    /// PTO満了時刻
+    deadline: Option<u64>,
+    /// 送信済みプローブの終端（未確認の間は次のプローブを送らない）
+    probe_end: Option<u32>,
+    /// 送信したプローブ数
+    probes_sent: u32,
+}
+
+impl TlpState {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            deadline: None,
+            probe_end: None,
+            probes_sent: 0,
+        }
+    }
+
+    /// PTO計算（RFC 8985 Section 7.2）
+    pub fn pto(srtt: Option<u64>, segments_in_flight: usize, rto: u64) -> u64 {
+        let pto = match srtt {
+            Some(srtt) if srtt > 0 => {
+                let mut pto = 2 * srtt;
+                if segments_in_flight == 1 {
+                    pto += TLP_WC_DEL_ACK;
+                }
+                pto
+            }
+            _ => TLP_INITIAL_PTO,
+        };
+        pto.min(rto)
+    }
+
+    /// PTOタイマー起動
+    pub fn arm(&mut self, now: u64, pto: u64) {
+        if self.probe_end.is_none() {
+            self.deadline = Some(now + pto);
+        }
+    }
+
+    /// PTOタイマー停止
+    pub fn disarm(&mut self) {
+        self.deadline = None;
+    }
+
+    /// プローブを送るべきか
+    pub fn should_probe(&self, now: u64) -> bool {
+        self.probe_end.is_none() && self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// プローブ送信を記録
+    pub fn on_probe_sent(&mut self, end_seq: u32) {
+        self.probe_end = Some(end_seq);
+        self.deadline = None;
+        self.probes_sent = self.probes_sent.saturating_add(1);
+    }
+
+    /// ACK受信（プローブが確認されたら次のプローブを許可）
+    pub fn on_ack(&mut self, ack: u32) {
+        if self.probe_end.is_some_and(|end| seq_le(end, ack)) {
+            self.probe_end = None;
+        }
+    }
+
+    /// 送信したプローブ数
+    pub fn probes_sent(&self) -> u32 {
+        self.probes_sent
+    }
+
+    /// 未確認のプローブがあるか
+    pub fn probe_outstanding(&self) -> bool {
+        self.probe_end.is_some()
+    }
+}
+
+// =====================================================
+// テスト
+// =====================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rack_marks_earlier_segment_lost() {
+        let mut rack = RackState::new();
+        // seg2 (送信時刻10) が時刻40に配送
+        rack.on_delivered(10, 2000, false, 40);
+        assert_eq!(rack.rtt(), 30);
+
+        // seg1 (送信時刻0) は 0 + 30 + reo_wnd 経過でロス
+        let reo_wnd = rack.reo_wnd(false, 0, 30);
+        assert_eq!(reo_wnd, 7); // min_rtt/4
+        assert_eq!(rack.verdict(0, 1000, 30, reo_wnd), RackVerdict::Wait(7));
+        assert_eq!(rack.verdict(0, 1000, 37, reo_wnd), RackVerdict::Lost);
+        // 後から送ったセグメントは対象外
+        assert_eq!(rack.verdict(20, 3000, 100, reo_wnd), RackVerdict::NotYet);
+    }
+
+    #[test]
+    fn test_reo_wnd_zero_without_reordering() {
+        let mut rack = RackState::new();
+        rack.on_delivered(0, 1000, false, 40);
+        assert_eq!(rack.reo_wnd(false, RACK_DUPTHRESH, 40), 0);
+        assert_eq!(rack.reo_wnd(true, 0, 40), 0);
+
+        // 再順序を観測するとウィンドウが開く
+        rack.on_delivered(0, 500, false, 41);
+        assert!(rack.reordering_seen());
+        assert!(rack.reo_wnd(true, RACK_DUPTHRESH, 40) > 0);
+    }
+
+    #[test]
+    fn test_dsack_grows_reo_wnd() {
+        let mut rack = RackState::new();
+        rack.on_delivered(0, 1000, false, 40);
+        rack.on_dsack(1000, 5000);
+        assert_eq!(rack.reo_wnd_mult(), 2);
+        // 同一ラウンド内の追加D-SACKは無視
+        rack.on_dsack(2000, 6000);
+        assert_eq!(rack.reo_wnd_mult(), 2);
+
+        for _ in 0..RACK_REO_WND_PERSIST {
+            rack.on_recovery_exit();
+        }
+        assert_eq!(rack.reo_wnd_mult(), 1);
+    }
+
+    #[test]
+    fn test_tlp_pto() {
+        assert_eq!(TlpState::pto(Some(50), 3, 1000), 100);
+        assert_eq!(TlpState::pto(Some(50), 1, 1000), 300);
+        assert_eq!(TlpState::pto(None, 1, 400), 400);
+
+        let mut tlp = TlpState::new();
+        tlp.arm(0, 100);
+        assert!(!tlp.should_probe(50));
+        assert!(tlp.should_probe(100));
+        tlp.on_probe_sent(5000);
+        tlp.arm(100, 100);
+        assert!(!tlp.should_probe(300));
+        tlp.on_ack(5000);
+        assert!(!tlp.probe_outstanding());
+    }
+}
@@ -1,14 +1,18 @@
 //! # 再送タイマー・キュー
 //!
 //! RtoCalculator, RetransmitQueue, UnackedSegment
+//! SACKスコアボードとRACK-TLPによるロス検出もここで行う
 
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::vec::Vec;
 use core::sync::atomic::Ordering;
 use spin::RwLock;
 
+use super::congestion::CongestionState;
+use super::rack::{RackState, RackVerdict, TlpState};
+use super::sack::{SackBlock, SackScoreboard, seq_le};
 use super::segment::send_tcp_segment;
-use super::tcb::tcb_table;
+use super::tcb::{tcb_table, tcp_flags};
 use super::types::SocketAddr;
 
 /// 未確認セグメント（再送用）
@@ -24,6 +28,77 @@ pub struct UnackedSegment {
     pub retransmit_count: u8,
     /// RTOサンプル用フラグ（再送済みはRTTサンプルに使わない）
     pub is_retransmit: bool,
+    /// 終端シーケンス番号（SYN/FINの1バイトを含む）
+    pub end_seq: u32,
+    /// SACK済みか
+    pub sacked: bool,
+    /// ロスと判定され再送待ちか
+    pub lost: bool,
+}
+
+impl UnackedSegment {
+    /// シーケンス空間上の長さ
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.end_seq.wrapping_sub(self.seq)
+    }
+
+    /// 長さ0か
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.end_seq == self.seq
+    }
+}
+
+/// セグメントがシーケンス空間で占める長さを求める
+///
+/// TCPヘッダとして解釈できればペイロード長 + SYN/FIN、そうでなければバイト列長
+pub fn segment_seq_len(data: &[u8]) -> u32 {
+    if data.len() >= 20 {
+        let header_len = ((data[12] >> 4) as usize) * 4;
+        if header_len >= 20 && header_len <= data.len() {
+            let flags = data[13];
+            let mut len = (data.len() - header_len) as u32;
+            if flags & tcp_flags::SYN != 0 {
+                len += 1;
+            }
+            if flags & tcp_flags::FIN != 0 {
+                len += 1;
+            }
+            return len;
+        }
+    }
+    data.len() as u32
+}
+
+/// ACK受信時にRetransmitQueueへ渡す情報
+#[derive(Debug, Clone, Default)]
+pub struct AckInfo {
+    /// 累積ACK番号
+    pub ack_num: u32,
+    /// SACKブロック（D-SACKを含む、受信順）
+    pub sack_blocks: Vec<SackBlock>,
+    /// タイムスタンプ（TSecr）から得たRTTサンプル
+    pub ts_rtt: Option<u64>,
+    /// 次に送信するシーケンス番号
+    pub snd_nxt: u32,
+    /// 損失回復中か
+    pub in_recovery: bool,
+}
+
+/// ACK処理結果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AckOutcome {
+    /// RTTサンプル（tick）
+    pub rtt: Option<u64>,
+    /// 新たにSACKされたバイト数
+    pub newly_sacked: u32,
+    /// 累積ACKで確認されたうち、既にSACK済みだったバイト数
+    pub sacked_acked: u32,
+    /// D-SACKを受信したか
+    pub dsack: bool,
+    /// 新たにロスと判定されたバイト数
+    pub lost_bytes: u32,
 }
 
 /// RTO（Retransmission Timeout）計算器
@@ -93,6 +168,11 @@ impl RtoCalculator {
         self.rto
     }
 
+    /// 平滑化RTT取得
+    pub fn srtt(&self) -> Option<u64> {
+        self.srtt
+    }
+
     /// リセット
     pub fn reset(&mut self) {
         self.srtt = None;
@@ -110,6 +190,14 @@ pub struct RetransmitQueue {
     rto_calc: RtoCalculator,
     /// 最大再送回数
     max_retries: u8,
+    /// SACKスコアボード
+    scoreboard: SackScoreboard,
+    /// RACK状態
+    rack: RackState,
+    /// RACK再順序タイマー満了時刻
+    rack_deadline: Option<u64>,
+    /// Tail Loss Probe状態
+    tlp: TlpState,
 }
 
 impl RetransmitQueue {
@@ -119,39 +207,217 @@ impl RetransmitQueue {
             unacked: VecDeque::new(),
             rto_calc: RtoCalculator::new(),
             max_retries: 5,
+            scoreboard: SackScoreboard::new(),
+            rack: RackState::new(),
+            rack_deadline: None,
+            tlp: TlpState::new(),
         }
     }
 
     /// セグメントを追加
     pub fn push(&mut self, seq: u32, data: Vec<u8>, current_tick: u64) {
+        let end_seq = seq.wrapping_add(segment_seq_len(&data));
         self.unacked.push_back(UnackedSegment {
             seq,
             data,
             send_tick: current_tick,
             retransmit_count: 0,
             is_retransmit: false,
+            end_seq,
+            sacked: false,
+            lost: false,
         });
+        self.arm_tlp(current_tick);
     }
 
     /// ACK受信時の処理（累積ACK）
     /// 確認されたセグメントを削除し、RTTサンプルを収集
-    pub fn ack_received(&mut self, ack_num: u32, current_tick: u64) {
-        // 累積ACKより前のセグメントを全て削除
+    ///
+    /// 戻り値: 今回得られた最新のRTTサンプル（tick）
+    pub fn ack_received(&mut self, ack_num: u32, current_tick: u64) -> Option<u64> {
+        self.ack_cumulative(ack_num, current_tick).0
+    }
+
+    /// 累積ACK処理
+    ///
+    /// 戻り値: (RTTサンプル, 既にSACK済みだったバイト数)
+    fn ack_cumulative(&mut self, ack_num: u32, current_tick: u64) -> (Option<u64>, u32) {
+        let mut rtt_sample = None;
+        let mut sacked_acked = 0u32;
+        // 累積ACKで全体が確認されたセグメントを全て削除
         while let Some(seg) = self.unacked.front() {
-            // seqがack_numより前なら確認済み
-            if Self::seq_before(seg.seq, ack_num) {
-                let seg = self.unacked.pop_front().unwrap();
-                // 再送でないセグメントのみRTTサンプルとして使用（Karnのアルゴリズム）
-                if !seg.is_retransmit {
-                    let rtt = current_tick.saturating_sub(seg.send_tick);
-                    if rtt > 0 {
-                        self.rto_calc.update(rtt);
-                    }
-                }
-            } else {
+            if !Self::seq_leq(seg.end_seq, ack_num) {
                 break;
             }
+            let seg = self.unacked.pop_front().unwrap();
+            if seg.sacked {
+                sacked_acked = sacked_acked.saturating_add(seg.len());
+                continue;
+            }
+            // 再送でないセグメントのみRTTサンプルとして使用（Karnのアルゴリズム）
+            if !seg.is_retransmit {
+                let rtt = current_tick.saturating_sub(seg.send_tick);
+                if rtt > 0 {
+                    self.rto_calc.update(rtt);
+                    rtt_sample = Some(rtt);
+                }
+            }
+            self.rack
+                .on_delivered(seg.send_tick, seg.end_seq, seg.is_retransmit, current_tick);
+        }
+        self.scoreboard.advance(ack_num);
+        self.tlp.on_ack(ack_num);
+        if self.unacked.is_empty() {
+            self.tlp.disarm();
+        } else {
+            self.arm_tlp(current_tick);
+        }
+        (rtt_sample, sacked_acked)
+    }
+
+    /// ACK受信時の処理（SACK・タイムスタンプ・RACK込み）
+    pub fn on_ack(&mut self, ack: &AckInfo, current_tick: u64) -> AckOutcome {
+        let mut outcome = AckOutcome::default();
+        let before = self.unacked.len();
+
+        // 1. 累積ACK
+        let (rtt, sacked_acked) = self.ack_cumulative(ack.ack_num, current_tick);
+        outcome.rtt = rtt;
+        outcome.sacked_acked = sacked_acked;
+
+        // タイムスタンプによるRTTは再送セグメントでも有効（RFC 7323 RTTM）
+        if let Some(ts_rtt) = ack.ts_rtt
+            && self.unacked.len() != before
+        {
+            if outcome.rtt.is_none() && ts_rtt > 0 {
+                self.rto_calc.update(ts_rtt);
+            }
+            outcome.rtt = Some(ts_rtt);
+        }
+
+        // 2. SACKスコアボード更新
+        let update = self.scoreboard.update(ack.ack_num, &ack.sack_blocks);
+        outcome.newly_sacked = update.newly_sacked;
+        if update.dsack.is_some() {
+            outcome.dsack = true;
+            self.rack.on_dsack(ack.ack_num, ack.snd_nxt);
+        }
+        if update.newly_sacked > 0 {
+            for seg in self.unacked.iter_mut() {
+                if !seg.sacked && self.scoreboard.is_sacked(seg.seq, seg.end_seq) {
+                    seg.sacked = true;
+                    seg.lost = false;
+                    self.rack
+                        .on_delivered(seg.send_tick, seg.end_seq, seg.is_retransmit, current_tick);
+                }
+            }
+        }
+
+        // 3. RACKロス検出
+        outcome.lost_bytes = self.detect_losses(current_tick, ack.in_recovery);
+
+        outcome
+    }
+
+    /// RACKによるロス判定（RFC 8985 Section 6.2 Step 5）
+    ///
+    /// 戻り値: 新たにロスと判定されたバイト数
+    pub fn detect_losses(&mut self, current_tick: u64, in_recovery: bool) -> u32 {
+        let sacked_segments = self.unacked.iter().filter(|s| s.sacked).count();
+        let srtt = self.rto_calc.srtt().unwrap_or(0);
+        let reo_wnd = self.rack.reo_wnd(in_recovery, sacked_segments, srtt);
+
+        let mut lost_bytes = 0u32;
+        let mut wait: Option<u64> = None;
+        for seg in self.unacked.iter_mut().filter(|s| !s.sacked && !s.lost) {
+            match self.rack.verdict(seg.send_tick, seg.end_seq, current_tick, reo_wnd) {
+                RackVerdict::Lost => {
+                    seg.lost = true;
+                    lost_bytes = lost_bytes.saturating_add(seg.len());
+                }
+                RackVerdict::Wait(remaining) => {
+                    wait = Some(wait.map_or(remaining, |w| w.max(remaining)));
+                }
+                RackVerdict::NotYet => {}
+            }
+        }
+        self.rack_deadline = wait.map(|w| current_tick + w);
+        lost_bytes
+    }
+
+    /// RACK再順序タイマーの確認
+    ///
+    /// 戻り値: 新たにロスと判定されたバイト数
+    pub fn check_rack_timer(&mut self, current_tick: u64, in_recovery: bool) -> u32 {
+        match self.rack_deadline {
+            Some(deadline) if current_tick >= deadline => {
+                self.detect_losses(current_tick, in_recovery)
+            }
+            _ => 0,
+        }
+    }
+
+    /// ロス判定済みセグメントを再送用に取り出す
+    ///
+    /// 戻り値: (シーケンス番号, セグメントデータ) のリスト
+    pub fn take_lost(&mut self, current_tick: u64) -> Vec<(u32, Vec<u8>)> {
+        let mut segments = Vec::new();
+        for seg in self.unacked.iter_mut().filter(|s| s.lost && !s.sacked) {
+            seg.lost = false;
+            seg.retransmit_count = seg.retransmit_count.saturating_add(1);
+            seg.send_tick = current_tick;
+            seg.is_retransmit = true;
+            segments.push((seg.seq, seg.data.clone()));
+        }
+        segments
+    }
+
+    /// PTO満了時のTail Loss Probe（RFC 8985 Section 7.3）
+    ///
+    /// 新規データがないため、最後の未SACKセグメントを再送する
+    pub fn tlp_probe(&mut self, current_tick: u64) -> Option<Vec<u8>> {
+        if !self.tlp.should_probe(current_tick) {
+            return None;
         }
+        let seg = self.unacked.iter_mut().rev().find(|s| !s.sacked)?;
+        seg.send_tick = current_tick;
+        seg.is_retransmit = true;
+        seg.retransmit_count = seg.retransmit_count.saturating_add(1);
+        let end_seq = seg.end_seq;
+        let data = seg.data.clone();
+        self.tlp.on_probe_sent(end_seq);
+        Some(data)
+    }
+
+    /// PTOタイマー起動
+    fn arm_tlp(&mut self, current_tick: u64) {
+        let pto = TlpState::pto(self.rto_calc.srtt(), self.unacked.len(), self.rto_calc.get_rto());
+        self.tlp.arm(current_tick, pto);
+    }
+
+    /// 損失回復の完了を通知（再順序ウィンドウの持続回数を消費）
+    pub fn on_recovery_exit(&mut self) {
+        self.rack.on_recovery_exit();
+    }
+
+    /// SACKスコアボード
+    pub fn scoreboard(&self) -> &SackScoreboard {
+        &self.scoreboard
+    }
+
+    /// RACK状態
+    pub fn rack(&self) -> &RackState {
+        &self.rack
+    }
+
+    /// TLP状態
+    pub fn tlp(&self) -> &TlpState {
+        &self.tlp
+    }
+
+    /// 未確認セグメント
+    pub fn segments(&self) -> impl Iterator<Item = &UnackedSegment> {
+        self.unacked.iter()
     }
 
     /// タイムアウトチェック
@@ -176,6 +442,7 @@ impl RetransmitQueue {
             seg.send_tick = current_tick;
             seg.is_retransmit = true;
             self.rto_calc.backoff();
+            self.tlp.disarm();
 
             return Some(seg.data.clone());
         }
@@ -238,12 +505,23 @@ pub fn retransmit_queue_push(local: SocketAddr, remote: SocketAddr, seq: u32, da
 }
 
 /// ACK受信時の再送キュー更新
-pub fn retransmit_queue_ack(local: SocketAddr, remote: SocketAddr, ack_num: u32) {
+///
+/// 戻り値: RTTサンプル（tick）。輻輳制御へ渡す
+pub fn retransmit_queue_ack(local: SocketAddr, remote: SocketAddr, ack_num: u32) -> Option<u64> {
     let current_tick = tcb_table().current_tick.load(Ordering::Relaxed);
     let mut queues = RETRANSMIT_QUEUES.write();
-    if let Some(queue) = queues.get_mut(&(local, remote)) {
-        queue.ack_received(ack_num, current_tick);
-    }
+    queues
+        .get_mut(&(local, remote))
+        .and_then(|queue| queue.ack_received(ack_num, current_tick))
+}
+
+/// 再送キューに対して処理を実行
+pub fn with_retransmit_queue<R>(
+    local: SocketAddr,
+    remote: SocketAddr,
+    f: impl FnOnce(&mut RetransmitQueue) -> R,
+) -> Option<R> {
+    RETRANSMIT_QUEUES.write().get_mut(&(local, remote)).map(f)
 }
 
 /// 再送キュー削除
@@ -260,6 +538,9 @@ pub fn check_retransmit_timeouts() {
     for ((local, remote), queue) in queues.iter_mut() {
         if queue.check_timeout(current_tick).is_some() {
             if let Some(segment_data) = queue.retransmit(current_tick) {
+                // 輻輳制御へRTOを通知
+                tcb_table().update(*local, *remote, |tcb| tcb.on_timeout());
+
                 // 再送実行
                 send_tcp_segment(*local, *remote, segment_data);
             } else {
@@ -274,6 +555,26 @@ pub fn check_retransmit_timeouts() {
                 // TCBも削除
                 tcb_table().remove(*local, *remote);
             }
+            continue;
+        }
+
+        // RACK再順序タイマー
+        let in_recovery = tcb_table()
+            .get(*local, *remote)
+            .is_some_and(|tcb| tcb.congestion.state() == CongestionState::FastRecovery);
+        if queue.check_rack_timer(current_tick, in_recovery) > 0 {
+            tcb_table().update(*local, *remote, |tcb| {
+                let (snd_una, snd_nxt) = (tcb.snd_una, tcb.snd_nxt);
+                tcb.congestion.on_loss_detected(snd_una, snd_nxt);
+            });
+            for (_, segment_data) in queue.take_lost(current_tick) {
+                send_tcp_segment(*local, *remote, segment_data);
+            }
+        }
+
+        // Tail Loss Probe
+        if let Some(segment_data) = queue.tlp_probe(current_tick) {
+            send_tcp_segment(*local, *remote, segment_data);
         }
     }
 
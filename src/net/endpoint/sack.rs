@@ -0,0 +1,429 @@
+//! # TCP SACK - 選択的確認応答
+//!
+//! RFC 2018 (SACK) / RFC 2883 (D-SACK) / RFC 6675 準拠実装
+//! - 送信側スコアボード（SACK済み範囲の管理）
+//! - D-SACK検出
+//! - 受信側の順序外データ再構成とSACKブロック生成
+
+use alloc::vec::Vec;
+use core::cmp::min;
+
+/// 1セグメントに載せられる最大SACKブロック数
+pub const MAX_SACK_BLOCKS: usize = 4;
+
+/// タイムスタンプ併用時の最大SACKブロック数（オプション領域40バイト制限）
+pub const MAX_SACK_BLOCKS_WITH_TS: usize = 3;
+
+/// 再構成キューに保持する最大セグメント数
+pub const MAX_OUT_OF_ORDER_SEGMENTS: usize = 64;
+
+// =====================================================
+// シーケンス番号比較（wrapping考慮）
+// =====================================================
+
+/// a < b
+#[inline]
+pub fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+/// a <= b
+#[inline]
+pub fn seq_le(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) <= 0
+}
+
+/// max(a, b)
+#[inline]
+pub fn seq_max(a: u32, b: u32) -> u32 {
+    if seq_lt(a, b) { b } else { a }
+}
+
+/// min(a, b)
+#[inline]
+pub fn seq_min(a: u32, b: u32) -> u32 {
+    if seq_lt(a, b) { a } else { b }
+}
+
+/// SACKブロック [start, end)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SackBlock {
+    /// 左端（含む）
+    pub start: u32,
+    /// 右端（含まない）
+    pub end: u32,
+}
+
+impl SackBlock {
+    /// 新規作成
+    pub const fn new(start: u32, end: u32) -> Self {
+        Self { start, end }
+    }
+
+    /// ブロック長（バイト）
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.end.wrapping_sub(self.start)
+    }
+
+    /// 空かどうか
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !seq_lt(self.start, self.end)
+    }
+
+    /// 範囲 [start, end) を完全に含むか
+    #[inline]
+    pub fn covers(&self, start: u32, end: u32) -> bool {
+        seq_le(self.start, start) && seq_le(end, self.end)
+    }
+
+    /// 重なるか隣接しているか（マージ可能か）
+    #[inline]
+    pub fn touches(&self, other: &SackBlock) -> bool {
+        seq_le(self.start, other.end) && seq_le(other.start, self.end)
+    }
+
+    /// 重なっているバイト数
+    pub fn overlap(&self, other: &SackBlock) -> u32 {
+        let start = seq_max(self.start, other.start);
+        let end = seq_min(self.end, other.end);
+        if seq_lt(start, end) {
+            end.wrapping_sub(start)
+        } else {
+            0
+        }
+    }
+}
+
+// =====================================================
+// 送信側: SACKスコアボード
+// =====================================================
+
+/// SACK処理結果
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SackUpdate {
+    /// 新たにSACKされたバイト数
+    pub newly_sacked: u32,
+    /// D-SACKブロック（重複受信の報告）
+    pub dsack: Option<SackBlock>,
+}
+
+/// 送信側スコアボード（RFC 6675）
+///
+/// SACK済み範囲をシーケンス順・互いに素な状態で保持する
+#[derive(Debug, Clone, Default)]
+pub struct SackScoreboard {
+    /// SACK済み範囲（昇順、重なりなし）
+    blocks: Vec<SackBlock>,
+    /// 受信したD-SACK数
+    dsack_count: u32,
+}
+
+impl SackScoreboard {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            dsack_count: 0,
+        }
+    }
+
+    /// D-SACKかどうか判定（RFC 2883 Section 4）
+    ///
+    /// 先頭ブロックが累積ACK以下、または2番目のブロックに包含される場合
+    pub fn is_dsack(ack: u32, blocks: &[SackBlock]) -> bool {
+        match blocks {
+            [] => false,
+            [first] => seq_le(first.end, ack),
+            [first, second, ..] => {
+                seq_le(first.end, ack) || second.covers(first.start, first.end)
+            }
+        }
+    }
+
+    /// ACKのSACKブロックでスコアボードを更新
+    pub fn update(&mut self, snd_una: u32, blocks: &[SackBlock]) -> SackUpdate {
+        let mut result = SackUpdate::default();
+        self.advance(snd_una);
+
+        let mut rest = blocks;
+        if Self::is_dsack(snd_una, blocks) {
+            result.dsack = Some(blocks[0]);
+            self.dsack_count = self.dsack_count.saturating_add(1);
+            rest = &blocks[1..];
+        }
+
+        for block in rest {
+            if block.is_empty() || seq_le(block.end, snd_una) {
+                continue;
+            }
+            // 累積ACK以前の部分は切り捨て
+            let clipped = SackBlock::new(seq_max(block.start, snd_una), block.end);
+            result.newly_sacked = result.newly_sacked.saturating_add(self.insert(clipped));
+        }
+
+        result
+    }
+
+    /// 範囲を追加して新たにカバーされたバイト数を返す
+    fn insert(&mut self, block: SackBlock) -> u32 {
+        let already: u32 = self.blocks.iter().map(|b| b.overlap(&block)).sum();
+        let newly = block.len().saturating_sub(already);
+
+        // 重なる・隣接する範囲をマージ
+        let mut merged = block;
+        self.blocks.retain(|b| {
+            if b.touches(&merged) {
+                merged.start = seq_min(merged.start, b.start);
+                merged.end = seq_max(merged.end, b.end);
+                false
+            } else {
+                true
+            }
+        });
+        let pos = self
+            .blocks
+            .iter()
+            .position(|b| seq_lt(merged.start, b.start))
+            .unwrap_or(self.blocks.len());
+        self.blocks.insert(pos, merged);
+
+        newly
+    }
+
+    /// 累積ACKの進行に合わせて古い範囲を破棄
+    pub fn advance(&mut self, snd_una: u32) {
+        self.blocks.retain(|b| seq_lt(snd_una, b.end));
+        for block in &mut self.blocks {
+            block.start = seq_max(block.start, snd_una);
+        }
+    }
+
+    /// 範囲 [start, end) がSACK済みか
+    pub fn is_sacked(&self, start: u32, end: u32) -> bool {
+        self.blocks.iter().any(|b| b.covers(start, end))
+    }
+
+    /// SACK済みバイト数の合計
+    pub fn sacked_bytes(&self) -> u32 {
+        self.blocks.iter().map(|b| b.len()).sum()
+    }
+
+    /// SACKされた最大シーケンス番号
+    pub fn highest_sacked(&self) -> Option<u32> {
+        self.blocks.last().map(|b| b.end)
+    }
+
+    /// SACK済み範囲
+    pub fn blocks(&self) -> &[SackBlock] {
+        &self.blocks
+    }
+
+    /// 受信したD-SACK数
+    pub fn dsack_count(&self) -> u32 {
+        self.dsack_count
+    }
+
+    /// クリア（RTO後など）
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+// =====================================================
+// 受信側: 順序外データの再構成
+// =====================================================
+
+/// 受信側の再構成キュー
+///
+/// 順序外に届いたセグメントを保持し、広告用のSACKブロックを生成する。
+/// SACKブロックは直近に更新された範囲が先頭になる（RFC 2018 Section 4）
+#[derive(Debug, Clone, Default)]
+pub struct ReassemblyQueue {
+    /// 保持中のセグメント (seq, data)
+    segments: Vec<(u32, Vec<u8>)>,
+    /// 受信済み範囲（直近の更新順）
+    ranges: Vec<SackBlock>,
+    /// 次のACKで報告するD-SACKブロック
+    pending_dsack: Option<SackBlock>,
+}
+
+impl ReassemblyQueue {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            ranges: Vec::new(),
+            pending_dsack: None,
+        }
+    }
+
+    /// 保持中のセグメント数
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// 空かどうか
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// 保持中のバイト数
+    pub fn buffered_bytes(&self) -> usize {
+        self.segments.iter().map(|(_, data)| data.len()).sum()
+    }
+
+    /// セグメントを受信
+    ///
+    /// 戻り値: rcv_nxt から連続して配送可能になったデータ
+    pub fn on_segment(&mut self, rcv_nxt: &mut u32, seq: u32, data: &[u8]) -> Vec<u8> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let block = SackBlock::new(seq, seq.wrapping_add(data.len() as u32));
+
+        // 既に受信済み（重複） → D-SACK
+        if seq_le(block.end, *rcv_nxt) || self.ranges.iter().any(|r| r.covers(block.start, block.end)) {
+            self.pending_dsack = Some(block);
+            return Vec::new();
+        }
+
+        if seq_lt(*rcv_nxt, seq) {
+            // 順序外: 保持してSACK範囲を更新
+            if self.segments.len() < MAX_OUT_OF_ORDER_SEGMENTS {
+                self.segments.push((seq, data.to_vec()));
+                self.add_range(block);
+            }
+            return Vec::new();
+        }
+
+        // 順序通り（先頭が重複している場合は切り詰め）
+        let skip = rcv_nxt.wrapping_sub(seq) as usize;
+        let mut delivered = data[skip..].to_vec();
+        *rcv_nxt = block.end;
+
+        // 保持中のセグメントで連続する部分を配送
+        loop {
+            let next = self
+                .segments
+                .iter()
+                .position(|(s, d)| {
+                    seq_le(*s, *rcv_nxt) && seq_lt(*rcv_nxt, s.wrapping_add(d.len() as u32))
+                });
+            let Some(index) = next else {
+                break;
+            };
+            let (s, d) = self.segments.swap_remove(index);
+            let skip = rcv_nxt.wrapping_sub(s) as usize;
+            delivered.extend_from_slice(&d[skip..]);
+            *rcv_nxt = s.wrapping_add(d.len() as u32);
+        }
+
+        // 配送済み範囲を破棄
+        let acked = *rcv_nxt;
+        self.segments
+            .retain(|(s, d)| seq_lt(acked, s.wrapping_add(d.len() as u32)));
+        self.ranges.retain(|r| seq_lt(acked, r.end));
+        for range in &mut self.ranges {
+            range.start = seq_max(range.start, acked);
+        }
+
+        delivered
+    }
+
+    /// 受信済み範囲を追加（マージした範囲を先頭へ）
+    fn add_range(&mut self, block: SackBlock) {
+        let mut merged = block;
+        self.ranges.retain(|r| {
+            if r.touches(&merged) {
+                merged.start = seq_min(merged.start, r.start);
+                merged.end = seq_max(merged.end, r.end);
+                false
+            } else {
+                true
+            }
+        });
+        self.ranges.insert(0, merged);
+    }
+
+    /// 次のACKに載せるSACKブロックを生成
+    ///
+    /// D-SACKがあれば先頭に置き、報告後はクリアする
+    pub fn sack_blocks(&mut self, max_blocks: usize) -> Vec<SackBlock> {
+        let mut blocks = Vec::with_capacity(max_blocks);
+        if let Some(dsack) = self.pending_dsack.take() {
+            blocks.push(dsack);
+        }
+        let remaining = max_blocks.saturating_sub(blocks.len());
+        blocks.extend(self.ranges.iter().take(min(remaining, self.ranges.len())));
+        blocks
+    }
+
+    /// 全破棄
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.ranges.clear();
+        self.pending_dsack = None;
+    }
+}
+
+// =====================================================
+// テスト
+// =====================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_compare_wrapping() {
+        assert!(seq_lt(0xFFFF_FFF0, 0x10));
+        assert!(seq_le(5, 5));
+        assert_eq!(seq_max(0xFFFF_FFF0, 0x10), 0x10);
+    }
+
+    #[test]
+    fn test_scoreboard_merge() {
+        let mut sb = SackScoreboard::new();
+        let update = sb.update(1000, &[SackBlock::new(3000, 4000)]);
+        assert_eq!(update.newly_sacked, 1000);
+
+        // 隣接ブロックはマージされる
+        let update = sb.update(1000, &[SackBlock::new(2000, 3000), SackBlock::new(3000, 4000)]);
+        assert_eq!(update.newly_sacked, 1000);
+        assert_eq!(sb.blocks(), &[SackBlock::new(2000, 4000)]);
+        assert!(sb.is_sacked(2500, 3500));
+        assert!(!sb.is_sacked(1000, 2000));
+
+        // 累積ACKで切り詰め
+        sb.advance(3000);
+        assert_eq!(sb.sacked_bytes(), 1000);
+    }
+
+    #[test]
+    fn test_dsack_detection() {
+        // 累積ACK以下のブロック
+        assert!(SackScoreboard::is_dsack(5000, &[SackBlock::new(1000, 2000)]));
+        // 2番目のブロックに包含
+        assert!(SackScoreboard::is_dsack(
+            1000,
+            &[SackBlock::new(3000, 3500), SackBlock::new(3000, 4000)]
+        ));
+        assert!(!SackScoreboard::is_dsack(1000, &[SackBlock::new(3000, 4000)]));
+    }
+
+    #[test]
+    fn test_reassembly_in_order_after_gap() {
+        let mut rq = ReassemblyQueue::new();
+        let mut rcv_nxt = 100;
+
+        assert!(rq.on_segment(&mut rcv_nxt, 110, &[2; 10]).is_empty());
+        assert_eq!(rq.sack_blocks(4), alloc::vec![SackBlock::new(110, 120)]);
+
+        let delivered = rq.on_segment(&mut rcv_nxt, 100, &[1; 10]);
+        assert_eq!(delivered.len(), 20);
+        assert_eq!(rcv_nxt, 120);
+        assert!(rq.sack_blocks(4).is_empty());
+    }
+}
@@ -15,6 +15,7 @@ pub struct TcpSegmentBuilder {
     ack_num: u32,
     flags: u8,
     window: u16,
+    options: Vec<u8>,
     data: Vec<u8>,
 }
 
@@ -28,6 +29,7 @@ impl TcpSegmentBuilder {
             ack_num: 0,
             flags: 0,
             window: 65535,
+            options: Vec::new(),
             data: Vec::new(),
         }
     }
@@ -80,6 +82,16 @@ impl TcpSegmentBuilder {
         self
     }
 
+    /// TCPオプション設定（4バイト境界にパディング、最大40バイト）
+    pub fn options(mut self, options: &[u8]) -> Self {
+        let len = options.len().min(40);
+        self.options = options[..len].to_vec();
+        while self.options.len() % 4 != 0 {
+            self.options.push(super::window_scale::tcp_option_kind::NOP);
+        }
+        self
+    }
+
     /// データ設定
     pub fn data(mut self, data: Vec<u8>) -> Self {
         self.data = data;
@@ -88,7 +100,7 @@ impl TcpSegmentBuilder {
 
     /// TCPセグメントをバイト列に構築
     pub fn build(self) -> Vec<u8> {
-        let data_offset = 5u8; // 20バイト（オプションなし）
+        let data_offset = 5u8 + (self.options.len() / 4) as u8; // 20バイト + オプション
         let header_len = (data_offset as usize) * 4;
         let total_len = header_len + self.data.len();
 
@@ -112,6 +124,9 @@ impl TcpSegmentBuilder {
         // Urgent pointer (2 bytes)
         segment[18..20].copy_from_slice(&0u16.to_be_bytes());
 
+        // Options
+        segment[20..header_len].copy_from_slice(&self.options);
+
         // Data
         if !self.data.is_empty() {
             segment[header_len..].copy_from_slice(&self.data);
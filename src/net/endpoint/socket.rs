@@ -8,12 +8,16 @@ use core::sync::atomic::Ordering;
 use spin::Mutex;
 
 use crate::net::tcp::{
-    Ipv4Addr, SocketAddr as TcpSocketAddr, TcpListener as TcpListenerImpl, TcpStream,
+    Ipv4Addr, SocketAddr as TcpSocketAddr, TcpListener as TcpListenerImpl, TcpStats, TcpStream,
 };
+use crate::security::policy::{self, PolicyAction, PolicyOperation};
 
+use super::congestion::{CongestionAlgorithm, default_algorithm};
 use super::event::{NetworkEvent, send_event, send_event_ignore};
 use super::inner::SocketInner;
 use super::manager::SOCKET_MANAGER;
+use super::tcb::tcb_table;
+use super::tcp_tx;
 use super::types::{
     NEXT_FD, SocketAddr, SocketError, SocketFd, SocketResult, SocketState, SocketType,
 };
@@ -86,6 +90,8 @@ impl Socket {
 
     /// バインド
     pub fn bind(&self, addr: SocketAddr) -> SocketResult<()> {
+        check_port_policy(self.socket_type, addr.port, PolicyOperation::Receive)?;
+
         let mut inner = self.inner.lock();
 
         if !inner.state.can_bind() {
@@ -99,6 +105,8 @@ impl Socket {
 
     /// 接続（TCP用）
     pub fn connect(&self, addr: SocketAddr) -> SocketResult<()> {
+        check_port_policy(self.socket_type, addr.port, PolicyOperation::Send)?;
+
         let local_addr;
         {
             let mut inner = self.inner.lock();
@@ -179,19 +187,27 @@ impl Socket {
 
         // Acceptキューから接続を取得
         if let Some(conn) = inner.accept_queue.pop_front() {
-            // 新しいソケットを作成
-            let new_socket = Socket::new_with_fd(SocketType::Tcp, conn.fd);
-            {
-                let mut new_inner = new_socket.inner.lock();
-                new_inner.local_addr = Some(conn.local_addr);
-                new_inner.remote_addr = Some(conn.remote_addr);
-                let _ = new_inner.transition_to(SocketState::Connected);
-            }
-
-            // ソケットマネージャに登録
-            if let Some(ref mgr) = *SOCKET_MANAGER.read() {
-                mgr.register(new_socket.clone());
-            }
+            // ハンドシェイク完了時に登録済みのソケット（受信済みデータを保持）を使う
+            let registered = SOCKET_MANAGER.read().as_ref().and_then(|mgr| mgr.get(conn.fd));
+            let new_socket = match registered {
+                Some(socket) => socket,
+                None => {
+                    let new_socket = Socket::new_with_fd(SocketType::Tcp, conn.fd);
+                    {
+                        let mut new_inner = new_socket.inner.lock();
+                        new_inner.local_addr = Some(conn.local_addr);
+                        new_inner.remote_addr = Some(conn.remote_addr);
+                        let _ = new_inner.transition_to(SocketState::Bound);
+                        let _ = new_inner.transition_to(SocketState::Connected);
+                    }
+
+                    // ソケットマネージャに登録
+                    if let Some(ref mgr) = *SOCKET_MANAGER.read() {
+                        mgr.register(new_socket.clone());
+                    }
+                    new_socket
+                }
+            };
 
             crate::serial_println!(
                 "TCP: Accepted connection from {:?}:{}",
@@ -264,19 +280,27 @@ impl Socket {
     }
 
     /// データ受信
+    ///
+    /// 相手がFINを送った後もバッファに残ったデータは読める
     pub fn recv(&self, buf: &mut [u8]) -> SocketResult<usize> {
-        let mut inner = self.inner.lock();
-
-        if !inner.state.can_receive() {
-            return Err(SocketError::NotConnected);
-        }
+        let len = {
+            let mut inner = self.inner.lock();
+            let len = inner.recv_from_buffer(buf);
+            if len == 0 {
+                return if inner.state.can_receive() {
+                    Err(SocketError::Timeout)
+                } else {
+                    Err(SocketError::NotConnected)
+                };
+            }
+            len
+        };
 
-        let len = inner.recv_from_buffer(buf);
-        if len > 0 {
-            Ok(len)
-        } else {
-            Err(SocketError::Timeout)
+        // 受信ウィンドウを開く
+        if self.socket_type == SocketType::Tcp {
+            tcp_tx::on_consumed(self.fd, len);
         }
+        Ok(len)
     }
 
     /// UDP送信
@@ -284,6 +308,7 @@ impl Socket {
         if self.socket_type != SocketType::Udp {
             return Err(SocketError::InvalidArgument);
         }
+        check_port_policy(self.socket_type, addr.port, PolicyOperation::Send)?;
 
         {
             let inner = self.inner.lock();
@@ -353,7 +378,11 @@ impl Socket {
     }
 
     /// クローズ
+    ///
+    /// 接続済みTCPソケットは送信バッファを送り切ってからFINを送る
     pub fn close(&self) -> SocketResult<()> {
+        let graceful = self.socket_type == SocketType::Tcp
+            && tcb_table().find_by_fd(self.fd).is_some();
         {
             let mut inner = self.inner.lock();
 
@@ -364,9 +393,11 @@ impl Socket {
             inner.tcp_listener = None;
             inner.udp_socket = None;
 
-            // バッファクリア
+            // バッファクリア（送信済みでないデータはFIN前に送る）
             inner.recv_buffer.clear();
-            inner.send_buffer.clear();
+            if !graceful {
+                inner.send_buffer.clear();
+            }
 
             // 待機中のタスクを起こす
             if let Some(waker) = inner.recv_waker.take() {
@@ -382,6 +413,10 @@ impl Socket {
             inner.transition_to(SocketState::Closed)?;
         }
 
+        if graceful {
+            tcp_tx::linger(self);
+        }
+
         // ネットワークスタックにクローズを通知（エラーは無視 - クローズは必ず進める）
         send_event_ignore(NetworkEvent::Close { fd: self.fd });
 
@@ -405,6 +440,32 @@ impl Socket {
     pub fn has_data(&self) -> bool {
         self.inner.lock().recv_buffer.len() > 0
     }
+
+    /// 輻輳制御アルゴリズムを設定
+    ///
+    /// 接続済みならTCBにも即座に反映する。Listeningソケットに設定すると
+    /// acceptされる接続に継承される
+    pub fn set_congestion_algorithm(&self, algorithm: CongestionAlgorithm) -> SocketResult<()> {
+        if self.socket_type != SocketType::Tcp {
+            return Err(SocketError::InvalidArgument);
+        }
+        self.inner.lock().congestion_algorithm = Some(algorithm);
+        tcb_table().update_by_fd(self.fd, |tcb| tcb.set_congestion_algorithm(algorithm));
+        Ok(())
+    }
+
+    /// 輻輳制御アルゴリズム取得（未設定ならシステムデフォルト）
+    pub fn congestion_algorithm(&self) -> CongestionAlgorithm {
+        self.inner
+            .lock()
+            .congestion_algorithm
+            .unwrap_or_else(default_algorithm)
+    }
+
+    /// TCP統計（輻輳制御・RTT）を取得
+    pub fn tcp_stats(&self) -> Option<TcpStats> {
+        tcb_table().find_by_fd(self.fd).map(|tcb| tcb.tcp_stats())
+    }
 }
 
 impl Clone for Socket {
@@ -417,6 +478,42 @@ impl Clone for Socket {
     }
 }
 
+// =====================================================
+// ネットワークポリシー（security::policy）
+// =====================================================
+
+/// 現在のドメインがこのポートを使えるか確認
+///
+/// bind（待ち受け）は Receive、connect / send_to は Send として判定する。
+fn check_port_policy(
+    socket_type: SocketType,
+    port: u16,
+    operation: PolicyOperation,
+) -> SocketResult<()> {
+    let protocol = match socket_type {
+        SocketType::Tcp => "tcp",
+        SocketType::Udp => "udp",
+        SocketType::Raw => "raw",
+    };
+    let domain = crate::domain_system::current_domain().as_u64();
+    let decision = policy::check_network_policy(domain, "", protocol, port, operation);
+
+    if decision.action == PolicyAction::DenyAudit {
+        crate::security::audit_log().log(crate::security::AuditEvent {
+            timestamp: crate::time::current_tick(),
+            event_type: crate::security::AuditEventType::SecurityViolation,
+            domain_id: domain,
+            details: Some(alloc::format!("{:?} {}:{} denied by network policy", operation, protocol, port)),
+        });
+    }
+
+    if decision.action.is_allow() {
+        Ok(())
+    } else {
+        Err(SocketError::PermissionDenied)
+    }
+}
+
 // =====================================================
 // OwnedSocket - RAII リソース管理
 // =====================================================
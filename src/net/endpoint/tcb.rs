@@ -1,20 +1,33 @@
 //! # TCP Control Block - 接続状態管理
 //!
-//! TcpConnectionState, TcpControlBlockEntry, TcbTable, tcp_flags
+//! TcpConnectionState, TcpControlBlockEntry, TimeWaitEntry, TcbTable, tcp_flags
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
 use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use spin::RwLock;
 
-use super::congestion::CongestionController;
+use super::congestion::{AckSample, CongestionAlgorithm, CongestionController};
 use super::flow_control::FlowController;
 use super::retransmit::check_retransmit_timeouts;
+use super::sack::{MAX_SACK_BLOCKS, MAX_SACK_BLOCKS_WITH_TS, ReassemblyQueue};
+use super::tcp_tx;
+use super::timestamp::TimestampOption;
 use super::types::{SocketAddr, SocketFd};
-use super::window_scale::WindowScaleOption;
+use super::window_scale::{TcpOptionBuilder, TcpOptionParser, WindowScaleOption};
+use crate::net::tcp::TcpStats;
+
+/// 1 tick あたりのマイクロ秒
+const TICK_US: u64 = 1000;
+
+/// TIME-WAIT の保持時間（2MSL, MSL = 30秒）
+pub const TIME_WAIT_TICKS: u64 = 60_000;
+/// 同時に保持する TIME-WAIT 接続の上限（超えた分は保持せず破棄）
+pub const MAX_TIME_WAIT: usize = 1024;
 
 /// TCPフラグ
 pub mod tcp_flags {
@@ -65,6 +78,8 @@ pub struct TcpControlBlockEntry {
     pub rcv_wnd: u16,
     /// 再送回数
     pub retransmit_count: u8,
+    /// PAWSで破棄したセグメント数
+    pub paws_rejected: u64,
     /// 最終送信時刻（tick）
     pub last_send_tick: u64,
     /// 輻輳制御コントローラ
@@ -75,6 +90,27 @@ pub struct TcpControlBlockEntry {
     pub flow_control: FlowController,
     /// Maximum Segment Size (peer's)
     pub mss: u32,
+    /// SACKが合意されたか（双方がSYNでSACK-Permittedを送った）
+    pub sack_permitted: bool,
+    /// タイムスタンプオプション
+    pub timestamps: TimestampOption,
+    /// 順序外データの再構成キュー（SACKブロック生成元）
+    pub reassembly: ReassemblyQueue,
+    /// アプリケーションがクローズ済み（送信バッファを送り切ったらFINを送る）
+    pub close_requested: bool,
+    /// ペーシングで送信を保留中（次のtickで再開する）
+    pub paced: bool,
+}
+
+/// 送信できる量（`TcpControlBlockEntry::send_quota`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendQuota {
+    /// このバイト数を今すぐ送れる
+    Ready(u32),
+    /// cwnd / rwnd が埋まっている（ACK待ち）
+    Blocked,
+    /// ペーシングのクレジット待ち（tickで再開）
+    Paced,
 }
 
 impl TcpControlBlockEntry {
@@ -91,14 +127,84 @@ impl TcpControlBlockEntry {
             snd_wnd: 65535,
             rcv_wnd: 65535,
             retransmit_count: 0,
+            paws_rejected: 0,
             last_send_tick: 0,
             congestion: CongestionController::new(),
             window_scale: WindowScaleOption::default_enabled(),
             flow_control: FlowController::new(),
             mss: 1460, // Default MSS
+            sack_permitted: false,
+            timestamps: TimestampOption::new(),
+            reassembly: ReassemblyQueue::new(),
+            close_requested: false,
+            paced: false,
         }
     }
 
+    /// 送信するSYN/SYN-ACKのオプションを生成
+    ///
+    /// SYN-ACKでは相手が提示したオプションのみを返す
+    pub fn syn_options(&self, now: u64, is_syn_ack: bool) -> Vec<u8> {
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_mss(1460);
+        if self.window_scale.enabled {
+            builder.add_window_scale(self.window_scale.rcv_scale);
+        }
+        if !is_syn_ack || self.sack_permitted {
+            builder.add_sack_permitted();
+        }
+        if !is_syn_ack || self.timestamps.enabled {
+            builder.add_timestamps(TimestampOption::tsval(now), self.timestamps.tsecr());
+        }
+        builder.finalize().to_vec()
+    }
+
+    /// 受信したSYN/SYN-ACKのオプションで機能を合意
+    pub fn negotiate_options(&mut self, options: &[u8], now: u64) {
+        let mut parser = TcpOptionParser::new(options);
+        if let Some(mss) = parser.find_mss() {
+            self.mss = mss as u32;
+        }
+        match parser.find_window_scale() {
+            Some(scale) => self.window_scale.set_snd_scale(scale),
+            // 相手が対応していなければ双方とも無効 (RFC 7323 Section 2.2)
+            None => self.window_scale = WindowScaleOption::new(),
+        }
+        self.sack_permitted = parser.find_sack_permitted();
+        match parser.find_timestamps() {
+            Some((tsval, _)) => self.timestamps.enable(tsval, now),
+            None => self.timestamps = TimestampOption::new(),
+        }
+    }
+
+    /// 送信するACKのオプション（タイムスタンプ・SACKブロック）を生成
+    pub fn ack_options(&mut self, now: u64) -> Vec<u8> {
+        let mut builder = TcpOptionBuilder::new();
+        let max_blocks = if self.timestamps.enabled {
+            builder.add_timestamps(TimestampOption::tsval(now), self.timestamps.tsecr());
+            MAX_SACK_BLOCKS_WITH_TS
+        } else {
+            MAX_SACK_BLOCKS
+        };
+        if self.sack_permitted {
+            let blocks = self.reassembly.sack_blocks(max_blocks);
+            builder.add_sack(&blocks);
+        }
+        self.timestamps.on_ack_sent(self.rcv_nxt);
+        builder.finalize().to_vec()
+    }
+
+    /// データセグメント受信（順序外データの再構成込み）
+    ///
+    /// 戻り値: アプリケーションへ配送可能になったデータ
+    pub fn on_data_segment(&mut self, seq: u32, data: &[u8]) -> Vec<u8> {
+        let delivered = self.reassembly.on_segment(&mut self.rcv_nxt, seq, data);
+        if !delivered.is_empty() {
+            self.on_data_received(delivered.len() as u32);
+        }
+        delivered
+    }
+
     /// 初期シーケンス番号を設定
     pub fn initialize_seq(&mut self, isn: u32) {
         self.snd_nxt = isn;
@@ -124,17 +230,55 @@ impl TcpControlBlockEntry {
 
     /// ACK受信時の処理
     pub fn on_ack_received(&mut self, ack_num: u32, is_dup: bool) {
-        let bytes_acked = if ack_num > self.snd_una && !is_dup {
+        self.on_ack_received_at(ack_num, is_dup, tcb_table().get_current_tick(), None);
+    }
+
+    /// ACK受信時の処理（時刻・RTTサンプル付き）
+    pub fn on_ack_received_at(&mut self, ack_num: u32, is_dup: bool, now: u64, rtt: Option<u64>) {
+        // シーケンス番号のラップアラウンドを考慮して比較
+        let advances = (ack_num.wrapping_sub(self.snd_una) as i32) > 0;
+        let bytes_acked = if advances && !is_dup {
             ack_num.wrapping_sub(self.snd_una)
         } else {
             0
         };
 
-        self.congestion.on_ack(bytes_acked, is_dup, self.snd_una);
-
-        if !is_dup && ack_num > self.snd_una {
+        if !is_dup && advances {
             self.snd_una = ack_num;
         }
+
+        self.congestion.on_ack_sample(AckSample {
+            bytes_acked,
+            is_dup_ack: is_dup,
+            snd_una: self.snd_una,
+            now,
+            rtt,
+        });
+    }
+
+    /// 輻輳制御アルゴリズムを変更
+    pub fn set_congestion_algorithm(&mut self, algorithm: CongestionAlgorithm) {
+        self.congestion.set_algorithm(algorithm);
+    }
+
+    /// 輻輳制御・RTTの統計を取得
+    pub fn tcp_stats(&self) -> TcpStats {
+        let rtt = self.congestion.rtt();
+        TcpStats {
+            retransmissions: self.retransmit_count as u64,
+            paws_rejected: self.paws_rejected,
+            rtt_us: rtt.latest * TICK_US,
+            congestion_algorithm: self.congestion.algorithm().name(),
+            cwnd: self.congestion.cwnd(),
+            ssthresh: self.congestion.ssthresh(),
+            bytes_in_flight: self.congestion.bytes_in_flight(),
+            pacing_rate: self.congestion.pacing_rate(),
+            srtt_us: rtt.srtt * TICK_US,
+            min_rtt_us: rtt.min_rtt().map_or(0, |min| min * TICK_US),
+            rtt_samples: rtt.samples,
+            recent_rtt_us: rtt.recent().map(|sample| sample * TICK_US).collect(),
+            ..TcpStats::default()
+        }
     }
 
     /// データ受信時の処理
@@ -171,12 +315,66 @@ impl TcpControlBlockEntry {
     pub fn can_send(&self, bytes: u32) -> bool {
         self.effective_send_window() >= bytes && self.flow_control.can_send()
     }
+
+    /// 次のセグメントで送れるバイト数（MSS・cwnd・rwnd・ペーシングで制限）
+    pub fn send_quota(&mut self, pending: u32, now: u64) -> SendQuota {
+        let len = pending.min(self.mss).min(self.effective_send_window());
+        if len == 0 || !self.flow_control.can_send() {
+            return SendQuota::Blocked;
+        }
+        let scaled_rwnd = self.window_scale.scale_snd_window(self.snd_wnd);
+        if !self.congestion.can_send_paced(scaled_rwnd, len, now) {
+            return SendQuota::Paced;
+        }
+        SendQuota::Ready(len)
+    }
+}
+
+/// TIME-WAIT の接続
+///
+/// ソケット・再送キューからは切り離し、再送された FIN に ACK を返すのに要る分だけ残す
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWaitEntry {
+    /// 送信シーケンス番号（送った FIN の次）
+    pub snd_nxt: u32,
+    /// 受信シーケンス番号（相手の FIN の次）
+    pub rcv_nxt: u32,
+    /// 広告する受信ウィンドウ
+    pub rcv_wnd: u16,
+    /// 相手の TS.Recent（タイムスタンプ未合意なら None）
+    pub ts_recent: Option<u32>,
+    /// 破棄する tick
+    pub expires: u64,
+}
+
+impl TimeWaitEntry {
+    /// 接続の最終状態から作る
+    fn from_tcb(tcb: &TcpControlBlockEntry, now: u64) -> Self {
+        Self {
+            snd_nxt: tcb.snd_nxt,
+            rcv_nxt: tcb.rcv_nxt,
+            rcv_wnd: tcb.advertised_recv_window(),
+            ts_recent: tcb.timestamps.enabled.then(|| tcb.timestamps.tsecr()),
+            expires: now + TIME_WAIT_TICKS,
+        }
+    }
+
+    /// ACK に付けるオプション
+    pub fn ack_options(&self, now: u64) -> Vec<u8> {
+        let mut builder = TcpOptionBuilder::new();
+        if let Some(ts_recent) = self.ts_recent {
+            builder.add_timestamps(TimestampOption::tsval(now), ts_recent);
+        }
+        builder.finalize().to_vec()
+    }
 }
 
 /// TCBテーブル（接続管理）
 pub struct TcbTable {
     /// アクティブな接続
     entries: RwLock<BTreeMap<(SocketAddr, SocketAddr), TcpControlBlockEntry>>,
+    /// TIME-WAIT の接続（2MSL 経つまで同じ4タプルを再利用させない）
+    time_wait: RwLock<BTreeMap<(SocketAddr, SocketAddr), TimeWaitEntry>>,
     /// シーケンス番号カウンタ
     seq_counter: AtomicU32,
     /// 現在のtick（再送タイマー用）
@@ -188,6 +386,7 @@ impl TcbTable {
     pub const fn new() -> Self {
         Self {
             entries: RwLock::new(BTreeMap::new()),
+            time_wait: RwLock::new(BTreeMap::new()),
             seq_counter: AtomicU32::new(0),
             current_tick: AtomicU64::new(0),
         }
@@ -204,9 +403,22 @@ impl TcbTable {
     pub fn tick(&self) {
         let tick = self.current_tick.fetch_add(1, Ordering::Relaxed);
 
-        // 100tickごとに再送チェック（パフォーマンス最適化）
+        // ペーシングで保留した送信を再開
+        let paced: Vec<SocketFd> = self
+            .entries
+            .read()
+            .values()
+            .filter(|entry| entry.paced)
+            .map(|entry| entry.fd)
+            .collect();
+        for fd in paced {
+            tcp_tx::resume(fd);
+        }
+
+        // 100tickごとに再送チェックと TIME-WAIT の期限切れ破棄（パフォーマンス最適化）
         if tick % 100 == 0 {
             check_retransmit_timeouts();
+            self.time_wait.write().retain(|_, tw| tw.expires > tick);
         }
     }
 
@@ -249,6 +461,67 @@ impl TcbTable {
     pub fn find_by_fd(&self, fd: SocketFd) -> Option<TcpControlBlockEntry> {
         self.entries.read().values().find(|e| e.fd == fd).cloned()
     }
+
+    /// FDで接続更新
+    pub fn update_by_fd<F>(&self, fd: SocketFd, f: F) -> bool
+    where
+        F: FnOnce(&mut TcpControlBlockEntry),
+    {
+        let mut entries = self.entries.write();
+        if let Some(entry) = entries.values_mut().find(|e| e.fd == fd) {
+            f(entry);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 全接続のスナップショット
+    pub fn snapshot(&self) -> Vec<TcpControlBlockEntry> {
+        self.entries.read().values().cloned().collect()
+    }
+
+    /// 接続を TIME-WAIT に移す（TCB は削除する）
+    ///
+    /// 戻り値: TIME-WAIT として保持したか（上限に達していれば保持しない）
+    pub fn enter_time_wait(&self, local: SocketAddr, remote: SocketAddr) -> bool {
+        let Some(tcb) = self.remove(local, remote) else {
+            return false;
+        };
+        let now = self.get_current_tick();
+        let mut time_wait = self.time_wait.write();
+        if time_wait.len() >= MAX_TIME_WAIT {
+            time_wait.retain(|_, tw| tw.expires > now);
+            if time_wait.len() >= MAX_TIME_WAIT {
+                return false;
+            }
+        }
+        time_wait.insert((local, remote), TimeWaitEntry::from_tcb(&tcb, now));
+        true
+    }
+
+    /// TIME-WAIT の接続を取得（期限切れは None）
+    pub fn time_wait(&self, local: SocketAddr, remote: SocketAddr) -> Option<TimeWaitEntry> {
+        let now = self.get_current_tick();
+        self.time_wait
+            .read()
+            .get(&(local, remote))
+            .filter(|tw| tw.expires > now)
+            .copied()
+    }
+
+    /// TIME-WAIT の 2MSL タイマーを再開
+    pub fn restart_time_wait(&self, local: SocketAddr, remote: SocketAddr) {
+        let expires = self.get_current_tick() + TIME_WAIT_TICKS;
+        if let Some(tw) = self.time_wait.write().get_mut(&(local, remote)) {
+            tw.expires = expires;
+        }
+    }
+
+    /// TIME-WAIT の接続を破棄
+    pub fn remove_time_wait(&self, local: SocketAddr, remote: SocketAddr) -> Option<TimeWaitEntry> {
+        self.time_wait.write().remove(&(local, remote))
+    }
 }
 
 /// グローバルTCBテーブル
@@ -308,4 +581,28 @@ mod tests {
         let syn_ack = tcp_flags::SYN | tcp_flags::ACK;
         assert_eq!(syn_ack, 0x12);
     }
+
+    #[test]
+    fn test_ack_updates_congestion_and_rtt() {
+        let fd = SocketFd::from_raw(2);
+        let local = SocketAddr::new([10, 0, 2, 15], 40000);
+        let remote = SocketAddr::new([10, 0, 2, 2], 80);
+
+        let mut tcb = TcpControlBlockEntry::new(fd, local, remote);
+        tcb.congestion = CongestionController::with_algorithm(CongestionAlgorithm::Cubic, 1000);
+        // ラップアラウンド直前のISN
+        tcb.initialize_seq(u32::MAX - 499);
+        tcb.on_send(1000);
+        let cwnd = tcb.congestion.cwnd();
+
+        tcb.on_ack_received_at(500, false, 10, Some(12));
+        assert_eq!(tcb.snd_una, 500);
+        assert!(tcb.congestion.cwnd() > cwnd);
+
+        let stats = tcb.tcp_stats();
+        assert_eq!(stats.congestion_algorithm, "cubic");
+        assert_eq!(stats.rtt_us, 12 * TICK_US);
+        assert_eq!(stats.rtt_samples, 1);
+        assert_eq!(stats.bytes_in_flight, 0);
+    }
 }
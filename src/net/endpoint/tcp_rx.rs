@@ -1,23 +1,37 @@
 //! # TCP受信処理 - 3ウェイハンドシェイク・データ受信
 //!
-//! process_tcp_segment, network_event_task
+//! process_tcp_segment, network_event_task, tcp_timer_task
+//!
+//! 自分から閉じた接続は TIME-WAIT（2MSL）として残し、再送された FIN に ACK を返す
 
 #![allow(dead_code)]
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+use alloc::vec::Vec;
+
+use super::congestion::CongestionState;
 use super::event::event_queue;
 use super::handler::{EventHandleResult, NetworkEventHandler};
 use super::manager::SOCKET_MANAGER;
 use super::retransmit::{
-    get_or_create_retransmit_queue, retransmit_queue_ack, retransmit_queue_remove,
+    AckInfo, AckOutcome, RetransmitQueue, get_or_create_retransmit_queue, retransmit_queue_ack,
+    retransmit_queue_push, retransmit_queue_remove, with_retransmit_queue,
 };
-use super::segment::TcpSegmentBuilder;
+use super::sack::seq_lt;
+use super::segment::{TcpSegmentBuilder, send_tcp_segment};
 use super::socket::Socket;
-use super::tcb::{TcpConnectionState, TcpControlBlockEntry, tcb_table, tcp_flags};
+use super::tcb::{TcpConnectionState, TcpControlBlockEntry, TimeWaitEntry, tcb_table, tcp_flags};
+use super::tcp_tx;
 use super::types::{
     AcceptedConnection, SocketAddr, SocketError, SocketFd, SocketState, SocketType,
 };
+use super::window_scale::TcpOptionParser;
+
+/// ヘッダからTCPオプション部分を切り出す
+fn segment_options(segment: &[u8], data_offset: usize) -> &[u8] {
+    segment.get(20..data_offset).unwrap_or(&[])
+}
 
 /// TCPセグメント受信処理
 /// プロトコルスタック（ipv4.rs）から呼ばれる
@@ -34,40 +48,89 @@ pub fn process_tcp_segment(src_ip: [u8; 4], dst_ip: [u8; 4], segment: &[u8]) {
     let data_off_flags = u16::from_be_bytes([segment[12], segment[13]]);
     let data_offset = ((data_off_flags >> 12) & 0x0F) as usize * 4;
     let flags = (data_off_flags & 0x003F) as u8;
-    let _window = u16::from_be_bytes([segment[14], segment[15]]);
+    let window = u16::from_be_bytes([segment[14], segment[15]]);
 
     let remote = SocketAddr::new(src_ip, src_port);
     let local = SocketAddr::new(dst_ip, dst_port);
 
     // TCBを検索
     if let Some(tcb) = tcb_table().get(local, remote) {
-        process_tcp_with_tcb(tcb, flags, seq_num, ack_num, segment, data_offset);
+        process_tcp_with_tcb(tcb, flags, seq_num, ack_num, window, segment, data_offset);
+    } else if let Some(tw) = tcb_table().time_wait(local, remote) {
+        process_time_wait(local, remote, tw, flags, seq_num, segment, data_offset);
     } else {
         // 新規接続要求の可能性（LISTENソケット検索）
         process_tcp_new_connection(local, remote, flags, seq_num, segment, data_offset);
     }
 }
 
+/// TIME-WAIT の接続に対するセグメント処理
+///
+/// - RST は無視する（RFC 1337: TIME-WAIT を縮めない）
+/// - 以前の接続より後ろのシーケンス番号の SYN は新規接続として受け付ける（RFC 1122 4.2.2.13）
+/// - 再送された FIN には ACK を返し、2MSL を数え直す
+fn process_time_wait(
+    local: SocketAddr,
+    remote: SocketAddr,
+    tw: TimeWaitEntry,
+    flags: u8,
+    seq_num: u32,
+    segment: &[u8],
+    data_offset: usize,
+) {
+    if flags & tcp_flags::RST != 0 {
+        return;
+    }
+    if flags & tcp_flags::SYN != 0 {
+        if seq_lt(tw.rcv_nxt, seq_num) {
+            tcb_table().remove_time_wait(local, remote);
+            process_tcp_new_connection(local, remote, flags, seq_num, segment, data_offset);
+        }
+        return;
+    }
+    if flags & tcp_flags::FIN != 0 {
+        tcb_table().restart_time_wait(local, remote);
+        tcp_tx::send_time_wait_ack(local, remote, &tw);
+    }
+}
+
 /// 既存TCBに対するTCPセグメント処理
 fn process_tcp_with_tcb(
     tcb: TcpControlBlockEntry,
     flags: u8,
     seq_num: u32,
     ack_num: u32,
+    window: u16,
     segment: &[u8],
     data_offset: usize,
 ) {
     let is_syn = (flags & tcp_flags::SYN) != 0;
     let is_ack = (flags & tcp_flags::ACK) != 0;
-    let is_fin = (flags & tcp_flags::FIN) != 0;
     let is_rst = (flags & tcp_flags::RST) != 0;
+    let options = segment_options(segment, data_offset);
+
+    // PAWS: 古いタイムスタンプのセグメントを破棄 (RFC 7323 Section 5)
+    if tcb.timestamps.enabled
+        && let Some((tsval, _)) = TcpOptionParser::new(options).find_timestamps()
+    {
+        let now = tcb_table().get_current_tick();
+        if !tcb.timestamps.paws_check(tsval, now, is_rst) {
+            // 破棄したセグメントにも ACK を返す（RST は PAWS で破棄しない）
+            tcb_table().update(tcb.local, tcb.remote, |entry| entry.paws_rejected += 1);
+            tcp_tx::send_ack(tcb.local, tcb.remote);
+            return;
+        }
+        tcb_table().update(tcb.local, tcb.remote, |entry| {
+            entry.timestamps.on_segment(seq_num, tsval, now);
+        });
+    }
 
     match tcb.state {
         TcpConnectionState::SynSent => {
             // SYN-ACK待ち
             if is_syn && is_ack {
                 // SYN-ACK受信 → ACK送信して接続確立
-                handle_syn_ack_received(tcb, seq_num, ack_num);
+                handle_syn_ack_received(tcb, seq_num, ack_num, window, options);
             } else if is_rst {
                 // RST受信 → 接続失敗
                 handle_rst_received(tcb);
@@ -75,51 +138,83 @@ fn process_tcp_with_tcb(
         }
         TcpConnectionState::SynReceived => {
             // ACK待ち（サーバー側）
-            if is_ack {
-                handle_ack_for_syn(tcb, ack_num);
-            }
-        }
-        TcpConnectionState::Established => {
-            // データ受信または終了処理
-            if is_fin {
-                handle_fin_received(tcb, seq_num);
-            } else if is_rst {
+            if is_rst {
                 handle_rst_received(tcb);
-            } else {
-                // データ受信
-                let data_start = data_offset;
-                if data_start < segment.len() {
-                    let data = &segment[data_start..];
-                    handle_data_received(tcb, seq_num, data);
-                } else if is_ack {
-                    // ACKのみ（データなし）
-                    handle_ack_received(tcb, ack_num);
+            } else if is_ack && handle_ack_for_syn(tcb.clone(), ack_num, window) {
+                // ハンドシェイクACKにデータ・FINが載っていれば続けて処理
+                if let Some(tcb) = tcb_table().get(tcb.local, tcb.remote) {
+                    let flags = flags & !tcp_flags::ACK;
+                    process_synchronized(tcb, flags, seq_num, ack_num, window, segment, data_offset);
                 }
             }
         }
-        TcpConnectionState::FinWait1 => {
-            if is_fin && is_ack {
-                handle_fin_ack_received(tcb, seq_num, ack_num);
-            } else if is_ack {
-                handle_ack_for_fin(tcb, ack_num);
-            }
-        }
-        TcpConnectionState::FinWait2 => {
-            if is_fin {
-                handle_fin_received(tcb, seq_num);
-            }
-        }
-        TcpConnectionState::CloseWait | TcpConnectionState::LastAck => {
-            if is_ack {
-                handle_final_ack(tcb, ack_num);
-            }
+        TcpConnectionState::Closed | TcpConnectionState::Listen => {}
+        _ => process_synchronized(tcb, flags, seq_num, ack_num, window, segment, data_offset),
+    }
+}
+
+/// 接続確立後のセグメント処理
+///
+/// ACK → データ → FIN の順に処理する（FINはデータの直後のシーケンス番号）
+fn process_synchronized(
+    tcb: TcpControlBlockEntry,
+    flags: u8,
+    seq_num: u32,
+    ack_num: u32,
+    window: u16,
+    segment: &[u8],
+    data_offset: usize,
+) {
+    let is_ack = (flags & tcp_flags::ACK) != 0;
+    let is_fin = (flags & tcp_flags::FIN) != 0;
+    let is_rst = (flags & tcp_flags::RST) != 0;
+    let options = segment_options(segment, data_offset);
+
+    if is_rst {
+        handle_rst_received(tcb);
+        return;
+    }
+
+    // ACK処理（データへのピギーバックを含む）
+    if is_ack {
+        handle_ack_received(tcb.clone(), ack_num, window, options);
+        let fin_sent = matches!(
+            tcb.state,
+            TcpConnectionState::FinWait1 | TcpConnectionState::Closing | TcpConnectionState::LastAck
+        );
+        if fin_sent {
+            handle_ack_for_fin(tcb.clone(), ack_num);
         }
-        _ => {}
+    }
+
+    // データ受信（相手がFINを送る前の状態のみ）
+    let data = segment.get(data_offset..).unwrap_or(&[]);
+    let can_receive = matches!(
+        tcb.state,
+        TcpConnectionState::Established | TcpConnectionState::FinWait1 | TcpConnectionState::FinWait2
+    );
+    if !data.is_empty() && can_receive {
+        handle_data_received(tcb.clone(), seq_num, data);
+    }
+
+    if is_fin {
+        handle_fin_received(tcb.clone(), seq_num.wrapping_add(data.len() as u32));
+    }
+
+    // ウィンドウが開いた分の送信を再開
+    if is_ack {
+        tcp_tx::resume(tcb.fd);
     }
 }
 
 /// SYN-ACK受信処理（クライアント側3ウェイハンドシェイク）
-fn handle_syn_ack_received(tcb: TcpControlBlockEntry, seq_num: u32, ack_num: u32) {
+fn handle_syn_ack_received(
+    tcb: TcpControlBlockEntry,
+    seq_num: u32,
+    ack_num: u32,
+    window: u16,
+    options: &[u8],
+) {
     // ACK番号を検証
     if ack_num != tcb.snd_nxt {
         crate::serial_println!(
@@ -130,28 +225,27 @@ fn handle_syn_ack_received(tcb: TcpControlBlockEntry, seq_num: u32, ack_num: u32
         return;
     }
 
-    // TCB更新
+    // TCB更新（SYN-ACKのオプションでSACK・タイムスタンプ等を合意）
+    let now = tcb_table().get_current_tick();
     let updated = tcb_table().update(tcb.local, tcb.remote, |entry| {
         entry.rcv_nxt = seq_num.wrapping_add(1); // SYNは1バイト消費
         entry.snd_una = ack_num;
         entry.state = TcpConnectionState::Established;
+        entry.negotiate_options(options, now);
+        // SYN-ACKのウィンドウはスケールされない (RFC 7323 Section 2.2)
+        entry.update_peer_window(window >> entry.window_scale.snd_scale);
     });
 
     if !updated {
         return;
     }
 
-    // ACKパケット送信
-    let mut ack_segment = TcpSegmentBuilder::new(tcb.local.port, tcb.remote.port)
-        .seq(ack_num)
-        .ack(seq_num.wrapping_add(1))
-        .ack_flag()
-        .window(65535)
-        .build();
+    // SYNを再送キューから外す
+    retransmit_queue_ack(tcb.local, tcb.remote, ack_num);
 
-    TcpSegmentBuilder::calculate_checksum(&mut ack_segment, tcb.local.ip, tcb.remote.ip);
+    // ACKパケット送信
+    tcp_tx::send_ack(tcb.local, tcb.remote);
 
-    // TODO: パケット送信
     crate::serial_println!(
         "TCP: Connection established {}:{} <-> {}:{}",
         tcb.local.ip[0],
@@ -170,8 +264,8 @@ fn process_tcp_new_connection(
     remote: SocketAddr,
     flags: u8,
     seq_num: u32,
-    _segment: &[u8],
-    _data_offset: usize,
+    segment: &[u8],
+    data_offset: usize,
 ) {
     let is_syn = (flags & tcp_flags::SYN) != 0;
 
@@ -196,14 +290,27 @@ fn process_tcp_new_connection(
     if inner.state != SocketState::Listening {
         return;
     }
+    let congestion_algorithm = inner.congestion_algorithm;
     drop(inner);
 
     // TCB作成
     let isn = tcb_table().generate_isn();
     let mut tcb = TcpControlBlockEntry::new(socket.fd(), local, remote);
     tcb.initialize_seq(isn);
+    // リッスンソケットの輻輳制御アルゴリズムを継承
+    if let Some(algorithm) = congestion_algorithm {
+        tcb.set_congestion_algorithm(algorithm);
+    }
     tcb.rcv_nxt = seq_num.wrapping_add(1);
+    // SYNは1バイト消費
+    tcb.snd_nxt = isn.wrapping_add(1);
     tcb.state = TcpConnectionState::SynReceived;
+
+    // SYNのオプションで合意し、SYN-ACKで応答する
+    let now = tcb_table().get_current_tick();
+    tcb.negotiate_options(segment_options(segment, data_offset), now);
+    let syn_ack_options = tcb.syn_options(now, true);
+    tcb.timestamps.on_ack_sent(tcb.rcv_nxt);
     tcb_table().insert(tcb);
 
     // SYN-ACK送信
@@ -213,11 +320,16 @@ fn process_tcp_new_connection(
         .syn()
         .ack_flag()
         .window(65535)
+        .options(&syn_ack_options)
         .build();
 
     TcpSegmentBuilder::calculate_checksum(&mut syn_ack, local.ip, remote.ip);
 
-    // TODO: パケット送信
+    // SYN-ACKも再送対象（ハンドシェイクACKが来なければRTOで再送）
+    get_or_create_retransmit_queue(local, remote);
+    retransmit_queue_push(local, remote, isn, syn_ack.clone());
+    send_tcp_segment(local, remote, syn_ack);
+
     crate::serial_println!(
         "TCP: SYN-ACK sent {}:{} -> {}:{}",
         local.ip[0],
@@ -248,30 +360,111 @@ fn handle_rst_received(tcb: TcpControlBlockEntry) {
 }
 
 /// ACK受信処理（データ確認応答）
-fn handle_ack_received(tcb: TcpControlBlockEntry, ack_num: u32) {
-    tcb_table().update(tcb.local, tcb.remote, |entry| {
-        if ack_num > entry.snd_una {
-            entry.snd_una = ack_num;
-            entry.retransmit_count = 0; // 再送カウンタリセット
-        }
+fn handle_ack_received(tcb: TcpControlBlockEntry, ack_num: u32, window: u16, options: &[u8]) {
+    let now = tcb_table().get_current_tick();
+
+    // 再送キュー・TCBを更新し、ロスと判定されたセグメントを取り出す
+    let lost = with_retransmit_queue(tcb.local, tcb.remote, |queue| {
+        tcb_table().update(tcb.local, tcb.remote, |entry| {
+            entry.update_peer_window(window);
+            process_ack(entry, queue, ack_num, options, now);
+        });
+        queue.take_lost(now)
     });
 
-    // 再送キューからACK済みセグメントを削除（RTT測定も実行）
-    retransmit_queue_ack(tcb.local, tcb.remote, ack_num);
+    match lost {
+        // ロス判定されたセグメントを再送（ロック解放後）
+        Some(segments) => {
+            for (_, segment_data) in segments {
+                send_tcp_segment(tcb.local, tcb.remote, segment_data);
+            }
+        }
+        // 再送キューなし: 累積ACKのみ反映
+        None => {
+            tcb_table().update(tcb.local, tcb.remote, |entry| {
+                entry.update_peer_window(window);
+                let is_dup = ack_num == entry.snd_una && entry.snd_una != entry.snd_nxt;
+                if seq_lt(entry.snd_una, ack_num) {
+                    entry.retransmit_count = 0; // 再送カウンタリセット
+                }
+                entry.on_ack_received_at(ack_num, is_dup, now, None);
+            });
+        }
+    }
+}
+
+/// ACKセグメントをTCB・再送キューに反映
+///
+/// SACKブロック・タイムスタンプを解釈し、RACKのロス検出結果を輻輳制御へ渡す
+pub(super) fn process_ack(
+    entry: &mut TcpControlBlockEntry,
+    queue: &mut RetransmitQueue,
+    ack_num: u32,
+    options: &[u8],
+    now: u64,
+) -> AckOutcome {
+    let mut parser = TcpOptionParser::new(options);
+    let ts_rtt = parser
+        .find_timestamps()
+        .and_then(|(_, tsecr)| entry.timestamps.rtt_sample(now, tsecr));
+    let sack_blocks = if entry.sack_permitted {
+        parser.find_sack_blocks()
+    } else {
+        Vec::new()
+    };
+    let was_in_recovery = entry.congestion.state() == CongestionState::FastRecovery;
+
+    let outcome = queue.on_ack(
+        &AckInfo {
+            ack_num,
+            sack_blocks,
+            ts_rtt,
+            snd_nxt: entry.snd_nxt,
+            in_recovery: was_in_recovery,
+        },
+        now,
+    );
+
+    // 未確認データがあるのにsnd_unaが進まない → 重複ACK
+    let is_dup = ack_num == entry.snd_una && entry.snd_una != entry.snd_nxt;
+    if seq_lt(entry.snd_una, ack_num) {
+        entry.retransmit_count = 0; // 再送カウンタリセット
+    }
+
+    // SACK分のパイプ補正を先に行い、累積ACKで差し引く
+    entry
+        .congestion
+        .on_sack(outcome.newly_sacked, outcome.sacked_acked);
+    // 輻輳制御へ通知（snd_unaもここで更新される）
+    entry.on_ack_received_at(ack_num, is_dup, now, outcome.rtt);
+    if outcome.lost_bytes > 0 {
+        let (snd_una, snd_nxt) = (entry.snd_una, entry.snd_nxt);
+        entry.congestion.on_loss_detected(snd_una, snd_nxt);
+    }
+    if was_in_recovery && entry.congestion.state() != CongestionState::FastRecovery {
+        queue.on_recovery_exit();
+    }
+
+    outcome
 }
 
 /// SYN確認応答処理（サーバー側）
 /// ハンドシェイク完了時にAcceptキューに追加
-fn handle_ack_for_syn(tcb: TcpControlBlockEntry, ack_num: u32) {
+///
+/// 戻り値: 接続が確立したか
+fn handle_ack_for_syn(tcb: TcpControlBlockEntry, ack_num: u32, window: u16) -> bool {
     if ack_num != tcb.snd_nxt {
-        return;
+        return false;
     }
 
     // TCBを更新してEstablished状態に
     tcb_table().update(tcb.local, tcb.remote, |entry| {
         entry.snd_una = ack_num;
         entry.state = TcpConnectionState::Established;
+        entry.update_peer_window(window);
     });
+    // SYN-ACKを再送キューから外す
+    retransmit_queue_ack(tcb.local, tcb.remote, ack_num);
 
     crate::serial_println!(
         "TCP: Server connection established {}:{} <- {}:{}",
@@ -286,17 +479,27 @@ fn handle_ack_for_syn(tcb: TcpControlBlockEntry, ack_num: u32) {
         Some(s) => s,
         None => {
             crate::serial_println!("TCP: Failed to create accepted socket");
-            return;
+            return false;
         }
     };
 
     // Listeningソケットを探してAcceptキューに追加
+    let fd = new_socket.fd;
     if !push_to_accept_queue(tcb.local.port, new_socket) {
         crate::serial_println!("TCP: No listening socket found for port {}", tcb.local.port);
+        if let Some(ref mgr) = *SOCKET_MANAGER.read() {
+            mgr.unregister(fd);
+        }
+        retransmit_queue_remove(tcb.local, tcb.remote);
+        tcb_table().remove(tcb.local, tcb.remote);
+        return false;
     }
+    true
 }
 
 /// Accept用の新規ソケットを作成
+///
+/// accept()より先に届いたデータを受け取れるよう、ソケットはこの時点で登録する
 fn create_accepted_socket(tcb: &TcpControlBlockEntry) -> Option<AcceptedConnection> {
     let manager = SOCKET_MANAGER.read();
     let mgr = manager.as_ref()?;
@@ -309,12 +512,21 @@ fn create_accepted_socket(tcb: &TcpControlBlockEntry) -> Option<AcceptedConnecti
         entry.fd = new_fd;
     });
 
-    // 再送キューを作成
-    get_or_create_retransmit_queue(tcb.local, tcb.remote);
-
     // 更新されたTCBを取得
     let updated_tcb = tcb_table().get(tcb.local, tcb.remote)?;
 
+    let socket = Socket::new_with_fd(SocketType::Tcp, new_fd);
+    {
+        let mut inner = socket.inner().lock();
+        inner.local_addr = Some(tcb.local);
+        inner.remote_addr = Some(tcb.remote);
+        inner.congestion_algorithm = Some(updated_tcb.congestion.algorithm());
+        // リスナーのアドレスにバインド済みの接続として扱う
+        let _ = inner.transition_to(SocketState::Bound);
+        let _ = inner.transition_to(SocketState::Connected);
+    }
+    mgr.register(socket);
+
     Some(AcceptedConnection::new(
         new_fd,
         tcb.local,
@@ -367,92 +579,92 @@ fn push_to_accept_queue(local_port: u16, conn: AcceptedConnection) -> bool {
 
 /// データ受信処理
 fn handle_data_received(tcb: TcpControlBlockEntry, seq_num: u32, data: &[u8]) {
-    // シーケンス番号チェック
-    if seq_num != tcb.rcv_nxt {
-        // Out-of-order → 将来の再送処理で対応
-        return;
-    }
-
-    // TCB更新
+    // TCB更新（順序外データは再構成キューに保持し、SACKで報告する）
+    let mut delivered = Vec::new();
     tcb_table().update(tcb.local, tcb.remote, |entry| {
-        entry.rcv_nxt = entry.rcv_nxt.wrapping_add(data.len() as u32);
+        delivered = entry.on_data_segment(seq_num, data);
     });
 
     // ソケットの受信バッファにデータ追加
-    if let Some(socket) = get_socket_by_fd(tcb.fd) {
-        socket.push_data(data);
+    if !delivered.is_empty()
+        && let Some(socket) = get_socket_by_fd(tcb.fd)
+    {
+        socket.push_data(&delivered);
     }
 
-    // ACK送信
-    let new_rcv_nxt = tcb.rcv_nxt.wrapping_add(data.len() as u32);
-    let mut ack = TcpSegmentBuilder::new(tcb.local.port, tcb.remote.port)
-        .seq(tcb.snd_nxt)
-        .ack(new_rcv_nxt)
-        .ack_flag()
-        .window(65535)
-        .build();
-
-    TcpSegmentBuilder::calculate_checksum(&mut ack, tcb.local.ip, tcb.remote.ip);
-    // TODO: パケット送信
+    // ACK送信（順序外・重複なら重複ACK + SACK/D-SACK）
+    tcp_tx::send_ack(tcb.local, tcb.remote);
 }
 
 /// FIN受信処理
-fn handle_fin_received(tcb: TcpControlBlockEntry, seq_num: u32) {
+///
+/// - fin_seq: FINのシーケンス番号（同じセグメントのデータの直後）
+fn handle_fin_received(tcb: TcpControlBlockEntry, fin_seq: u32) {
+    let mut state = None;
     tcb_table().update(tcb.local, tcb.remote, |entry| {
-        entry.rcv_nxt = seq_num.wrapping_add(1); // FINは1バイト消費
+        // 順序外のFINは受け付けない（欠けたデータの再送を待つ）
+        if entry.rcv_nxt != fin_seq {
+            return;
+        }
+        entry.rcv_nxt = fin_seq.wrapping_add(1); // FINは1バイト消費
         entry.state = match entry.state {
             TcpConnectionState::Established => TcpConnectionState::CloseWait,
             TcpConnectionState::FinWait1 => TcpConnectionState::Closing,
             TcpConnectionState::FinWait2 => TcpConnectionState::TimeWait,
             s => s,
         };
+        state = Some(entry.state);
     });
 
     // ACK送信
-    let mut ack = TcpSegmentBuilder::new(tcb.local.port, tcb.remote.port)
-        .seq(tcb.snd_nxt)
-        .ack(seq_num.wrapping_add(1))
-        .ack_flag()
-        .window(65535)
-        .build();
-
-    TcpSegmentBuilder::calculate_checksum(&mut ack, tcb.local.ip, tcb.remote.ip);
-    // TODO: パケット送信
-}
+    tcp_tx::send_ack(tcb.local, tcb.remote);
 
-/// FIN-ACK受信処理
-fn handle_fin_ack_received(tcb: TcpControlBlockEntry, seq_num: u32, ack_num: u32) {
-    handle_ack_received(tcb.clone(), ack_num);
-    handle_fin_received(tcb, seq_num);
+    let Some(state) = state else {
+        return;
+    };
+    // 受信側にEOFを通知
+    notify_socket_eof(tcb.fd);
+    if state == TcpConnectionState::TimeWait {
+        enter_time_wait(&tcb);
+    }
 }
 
-/// FIN確認応答処理
+/// 送信したFINへの確認応答処理
 fn handle_ack_for_fin(tcb: TcpControlBlockEntry, ack_num: u32) {
     if ack_num != tcb.snd_nxt {
         return;
     }
 
+    let mut state = tcb.state;
     tcb_table().update(tcb.local, tcb.remote, |entry| {
-        entry.snd_una = ack_num;
-        entry.state = TcpConnectionState::FinWait2;
+        entry.state = match entry.state {
+            TcpConnectionState::FinWait1 => TcpConnectionState::FinWait2,
+            TcpConnectionState::Closing => TcpConnectionState::TimeWait,
+            TcpConnectionState::LastAck => TcpConnectionState::Closed,
+            s => s,
+        };
+        state = entry.state;
     });
-}
 
-/// 最終ACK処理
-fn handle_final_ack(tcb: TcpControlBlockEntry, ack_num: u32) {
-    if ack_num != tcb.snd_nxt {
-        return;
+    match state {
+        TcpConnectionState::TimeWait => enter_time_wait(&tcb),
+        TcpConnectionState::Closed => release_connection(&tcb),
+        _ => {}
     }
+}
 
-    tcb_table().update(tcb.local, tcb.remote, |entry| {
-        entry.state = TcpConnectionState::Closed;
-    });
-
-    // リソースクリーンアップ
+/// 接続のリソースを解放（TCB・再送キューを破棄する）
+fn release_connection(tcb: &TcpControlBlockEntry) {
     retransmit_queue_remove(tcb.local, tcb.remote);
     tcb_table().remove(tcb.local, tcb.remote);
 }
 
+/// 接続を TIME-WAIT に移す（再送キューは破棄し、2MSL の間4タプルを保持する）
+fn enter_time_wait(tcb: &TcpControlBlockEntry) {
+    retransmit_queue_remove(tcb.local, tcb.remote);
+    tcb_table().enter_time_wait(tcb.local, tcb.remote);
+}
+
 /// ソケットに接続完了を通知
 fn notify_socket_connected(fd: SocketFd) {
     let manager = SOCKET_MANAGER.read();
@@ -469,6 +681,22 @@ fn notify_socket_connected(fd: SocketFd) {
     }
 }
 
+/// ソケットに相手のFIN（EOF）を通知
+fn notify_socket_eof(fd: SocketFd) {
+    let Some(socket) = get_socket_by_fd(fd) else {
+        return;
+    };
+
+    let waker = {
+        let mut inner = socket.inner().lock();
+        let _ = inner.transition_to(SocketState::Closing);
+        inner.recv_waker.take()
+    };
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
 /// FDでソケット取得
 fn get_socket_by_fd(fd: SocketFd) -> Option<Socket> {
     let manager = SOCKET_MANAGER.read();
@@ -511,3 +739,12 @@ pub async fn network_event_task() {
         }
     }
 }
+
+/// TCPタイマータスク
+/// 1 tickごとにTCBテーブルを進める（ペーシング再開・再送タイムアウト）
+pub async fn tcp_timer_task() {
+    loop {
+        crate::task::sleep_ms(1).await;
+        tcb_table().tick();
+    }
+}
@@ -0,0 +1,258 @@
+//! # TCP送信処理 - データセグメント化・ACK・FIN送信
+//!
+//! transmit, send_ack, send_time_wait_ack, resume, on_consumed, close
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use super::manager::SOCKET_MANAGER;
+use super::retransmit::{retransmit_queue_push, retransmit_queue_remove};
+use super::segment::{TcpSegmentBuilder, send_tcp_segment};
+use super::socket::Socket;
+use super::tcb::{
+    SendQuota, TcpConnectionState, TcpControlBlockEntry, TimeWaitEntry, tcb_table, tcp_flags,
+};
+use super::types::{SocketAddr, SocketFd};
+
+/// クローズ済みでFIN送信前のソケット（送信バッファを送り切るまで保持）
+static LINGERING: Mutex<BTreeMap<SocketFd, Socket>> = Mutex::new(BTreeMap::new());
+
+/// 送出するセグメント（ソケット・TCBのロック解放後に送る）
+struct Outgoing {
+    seq: u32,
+    segment: Vec<u8>,
+}
+
+/// TCBから確定させたセグメントヘッダ
+struct SegmentHeader {
+    seq: u32,
+    len: u32,
+    flags: u8,
+    ack: u32,
+    window: u16,
+    options: Vec<u8>,
+}
+
+impl SegmentHeader {
+    /// snd_nxtから`seq_len`だけシーケンス空間を消費するヘッダを作る
+    fn advance(tcb: &mut TcpControlBlockEntry, len: u32, seq_len: u32, flags: u8, now: u64) -> Self {
+        let seq = tcb.snd_nxt;
+        tcb.snd_nxt = seq.wrapping_add(seq_len);
+        tcb.last_send_tick = now;
+        Self {
+            seq,
+            len,
+            flags,
+            ack: tcb.rcv_nxt,
+            window: tcb.advertised_recv_window(),
+            options: tcb.ack_options(now),
+        }
+    }
+}
+
+/// FDでソケット取得（クローズ後の送信待ちソケットを含む）
+pub(super) fn socket_for(fd: SocketFd) -> Option<Socket> {
+    let registered = SOCKET_MANAGER.read().as_ref().and_then(|mgr| mgr.get(fd));
+    registered.or_else(|| LINGERING.lock().get(&fd).cloned())
+}
+
+/// クローズしたTCPソケットをFIN送信まで保持
+pub(super) fn linger(socket: &Socket) {
+    LINGERING.lock().insert(socket.fd(), socket.clone());
+}
+
+/// 送信バッファをセグメント化して送信
+///
+/// MSS・cwnd・rwnd・ペーシングの範囲で送れるだけ送り、送ったセグメントは再送キューに積む。
+/// クローズ要求済みで送信バッファが空になればFINを送る。
+///
+/// 戻り値: 送信したデータバイト数
+pub fn transmit(socket: &Socket) -> usize {
+    let fd = socket.fd();
+    let now = tcb_table().get_current_tick();
+    let mut outgoing = Vec::new();
+    let mut endpoints: Option<(SocketAddr, SocketAddr)> = None;
+    let mut sent = 0usize;
+    let mut fin_sent = false;
+
+    {
+        let mut inner = socket.inner().lock();
+        loop {
+            let pending = inner.send_buffer.len().min(u32::MAX as usize) as u32;
+            let mut header = None;
+            tcb_table().update_by_fd(fd, |tcb| {
+                endpoints = Some((tcb.local, tcb.remote));
+                tcb.paced = false;
+                if !matches!(
+                    tcb.state,
+                    TcpConnectionState::Established | TcpConnectionState::CloseWait
+                ) {
+                    return;
+                }
+                if pending == 0 {
+                    if tcb.close_requested {
+                        // FINは1バイト消費
+                        let flags = tcp_flags::FIN | tcp_flags::ACK;
+                        header = Some(SegmentHeader::advance(tcb, 0, 1, flags, now));
+                        tcb.state = match tcb.state {
+                            TcpConnectionState::CloseWait => TcpConnectionState::LastAck,
+                            _ => TcpConnectionState::FinWait1,
+                        };
+                    }
+                    return;
+                }
+                match tcb.send_quota(pending, now) {
+                    SendQuota::Ready(len) => {
+                        let flags = tcp_flags::ACK | tcp_flags::PSH;
+                        header = Some(SegmentHeader::advance(tcb, len, len, flags, now));
+                        tcb.on_send(len);
+                    }
+                    SendQuota::Paced => tcb.paced = true,
+                    SendQuota::Blocked => {}
+                }
+            });
+
+            let (Some(header), Some((local, remote))) = (header, endpoints) else {
+                break;
+            };
+            let data: Vec<u8> = inner.send_buffer.drain(..header.len as usize).collect();
+            let mut segment = TcpSegmentBuilder::new(local.port, remote.port)
+                .seq(header.seq)
+                .ack(header.ack)
+                .flags(header.flags)
+                .window(header.window)
+                .options(&header.options)
+                .data(data)
+                .build();
+            TcpSegmentBuilder::calculate_checksum(&mut segment, local.ip, remote.ip);
+            outgoing.push(Outgoing {
+                seq: header.seq,
+                segment,
+            });
+
+            sent += header.len as usize;
+            if header.flags & tcp_flags::FIN != 0 {
+                fin_sent = true;
+                break;
+            }
+        }
+
+        // 送信バッファに空きができたので送信待ちを起こす
+        if sent > 0
+            && let Some(waker) = inner.send_waker.take()
+        {
+            waker.wake();
+        }
+    }
+
+    if let Some((local, remote)) = endpoints {
+        for out in outgoing {
+            retransmit_queue_push(local, remote, out.seq, out.segment.clone());
+            send_tcp_segment(local, remote, out.segment);
+        }
+    }
+    if fin_sent {
+        LINGERING.lock().remove(&fd);
+    }
+    sent
+}
+
+/// 現在のTCBの状態でACKを送信
+///
+/// 受信データ・FINへの応答やウィンドウ更新に使う
+pub fn send_ack(local: SocketAddr, remote: SocketAddr) {
+    let now = tcb_table().get_current_tick();
+    let mut header = None;
+    tcb_table().update(local, remote, |tcb| {
+        tcb.flow_control.clear_window_update();
+        header = Some(SegmentHeader::advance(tcb, 0, 0, tcp_flags::ACK, now));
+    });
+    let Some(header) = header else {
+        return;
+    };
+
+    let mut segment = TcpSegmentBuilder::new(local.port, remote.port)
+        .seq(header.seq)
+        .ack(header.ack)
+        .flags(header.flags)
+        .window(header.window)
+        .options(&header.options)
+        .build();
+    TcpSegmentBuilder::calculate_checksum(&mut segment, local.ip, remote.ip);
+    send_tcp_segment(local, remote, segment);
+}
+
+/// TIME-WAIT の接続からACKを送信（再送されたFINへの応答）
+pub fn send_time_wait_ack(local: SocketAddr, remote: SocketAddr, tw: &TimeWaitEntry) {
+    let now = tcb_table().get_current_tick();
+    let mut segment = TcpSegmentBuilder::new(local.port, remote.port)
+        .seq(tw.snd_nxt)
+        .ack(tw.rcv_nxt)
+        .flags(tcp_flags::ACK)
+        .window(tw.rcv_wnd)
+        .options(&tw.ack_options(now))
+        .build();
+    TcpSegmentBuilder::calculate_checksum(&mut segment, local.ip, remote.ip);
+    send_tcp_segment(local, remote, segment);
+}
+
+/// 送信再開（ACK受信・ペーシングのtickでcwnd・rwnd・クレジットが開いた分を送る）
+pub fn resume(fd: SocketFd) {
+    if let Some(socket) = socket_for(fd) {
+        transmit(&socket);
+    }
+}
+
+/// アプリケーションが受信データを消費した
+///
+/// 受信ウィンドウが大きく開いたらウィンドウ更新を送る
+pub fn on_consumed(fd: SocketFd, bytes: usize) {
+    let mut update = None;
+    tcb_table().update_by_fd(fd, |tcb| {
+        tcb.on_data_consumed(bytes.min(u32::MAX as usize) as u32);
+        if tcb.flow_control.needs_window_update() {
+            update = Some((tcb.local, tcb.remote));
+        }
+    });
+    if let Some((local, remote)) = update {
+        send_ack(local, remote);
+    }
+}
+
+/// アプリケーションのクローズ要求
+///
+/// 接続済みなら送信バッファを送り切ってからFINを送る。
+/// ハンドシェイク中なら接続を破棄する。
+pub fn close(fd: SocketFd) {
+    let Some(tcb) = tcb_table().find_by_fd(fd) else {
+        LINGERING.lock().remove(&fd);
+        return;
+    };
+
+    match tcb.state {
+        TcpConnectionState::Established | TcpConnectionState::CloseWait => {
+            tcb_table().update(tcb.local, tcb.remote, |entry| entry.close_requested = true);
+            match socket_for(fd) {
+                Some(socket) => {
+                    transmit(&socket);
+                }
+                None => abort(tcb.local, tcb.remote),
+            }
+        }
+        TcpConnectionState::SynSent | TcpConnectionState::SynReceived => {
+            abort(tcb.local, tcb.remote);
+            LINGERING.lock().remove(&fd);
+        }
+        // FIN送信済み
+        _ => {
+            LINGERING.lock().remove(&fd);
+        }
+    }
+}
+
+/// 接続を破棄（TCB・再送キューを解放）
+fn abort(local: SocketAddr, remote: SocketAddr) {
+    retransmit_queue_remove(local, remote);
+    tcb_table().remove(local, remote);
+}
@@ -1,6 +1,7 @@
-//! # テスト - Accept関連テスト
+//! # テスト - Accept関連・セグメント単位のテスト
 //!
 //! Accept機能の単体テスト
+//! SACK・タイムスタンプ・RACK-TLPのセグメント単位テスト
 
 #[cfg(test)]
 mod tests {
@@ -138,4 +139,542 @@ mod tests {
         let inner = socket.inner().lock();
         assert_eq!(inner.accept_queue.len(), 2);
     }
+
+    // =====================================================
+    // SACK / タイムスタンプ / RACK-TLP（セグメント単位）
+    // =====================================================
+
+    use super::super::congestion::{CongestionAlgorithm, CongestionController, CongestionState};
+    use super::super::retransmit::RetransmitQueue;
+    use super::super::sack::SackBlock;
+    use super::super::segment::TcpSegmentBuilder;
+    use super::super::tcp_rx::process_ack;
+    use super::super::window_scale::{TcpOptionBuilder, TcpOptionParser};
+
+    const MSS: u32 = 1000;
+
+    fn test_tcb() -> TcpControlBlockEntry {
+        let local = SocketAddr::new([10, 0, 2, 15], 40000);
+        let remote = SocketAddr::new([10, 0, 2, 2], 80);
+        let mut tcb = TcpControlBlockEntry::new(SocketFd::from_raw(500), local, remote);
+        tcb.congestion = CongestionController::with_algorithm(CongestionAlgorithm::NewReno, MSS);
+        tcb.mss = MSS;
+        tcb
+    }
+
+    /// データセグメント（ヘッダ込み）
+    fn data_segment(seq: u32, len: usize) -> Vec<u8> {
+        TcpSegmentBuilder::new(40000, 80)
+            .seq(seq)
+            .ack_flag()
+            .data(alloc::vec![0xAB; len])
+            .build()
+    }
+
+    /// ACKセグメント
+    fn ack_segment(ack: u32, options: &[u8]) -> Vec<u8> {
+        TcpSegmentBuilder::new(80, 40000)
+            .ack(ack)
+            .ack_flag()
+            .options(options)
+            .build()
+    }
+
+    /// ヘッダから (ACK番号, オプション) を取り出す
+    fn ack_and_options(segment: &[u8]) -> (u32, &[u8]) {
+        let ack = u32::from_be_bytes([segment[8], segment[9], segment[10], segment[11]]);
+        let data_offset = ((segment[12] >> 4) as usize) * 4;
+        (ack, &segment[20..data_offset])
+    }
+
+    /// 送信側: 5セグメントを tick 0..5 に送信
+    fn send_five(tcb: &mut TcpControlBlockEntry, queue: &mut RetransmitQueue) {
+        tcb.initialize_seq(1000);
+        for i in 0..5u32 {
+            let seq = 1000 + i * MSS;
+            queue.push(seq, data_segment(seq, MSS as usize), i as u64);
+            tcb.on_send(MSS);
+            tcb.snd_nxt = seq + MSS;
+        }
+    }
+
+    #[test]
+    fn test_syn_negotiates_sack_and_timestamps() {
+        let mut client = test_tcb();
+        let mut server = test_tcb();
+
+        let syn = TcpSegmentBuilder::new(40000, 80)
+            .seq(1000)
+            .syn()
+            .options(&client.syn_options(100, false))
+            .build();
+        let (_, options) = ack_and_options(&syn);
+        server.negotiate_options(options, 100);
+        assert!(server.sack_permitted);
+        assert!(server.timestamps.enabled);
+        assert_eq!(server.timestamps.ts_recent, 100);
+        assert_eq!(server.window_scale.snd_scale, 7);
+
+        let syn_ack = TcpSegmentBuilder::new(80, 40000)
+            .syn()
+            .ack_flag()
+            .options(&server.syn_options(105, true))
+            .build();
+        let (_, options) = ack_and_options(&syn_ack);
+        client.negotiate_options(options, 110);
+        assert!(client.sack_permitted);
+        assert_eq!(client.timestamps.ts_recent, 105);
+
+        // SACK/タイムスタンプ非対応の相手にはSYN-ACKで提示しない
+        let mut plain = test_tcb();
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_mss(1460);
+        plain.negotiate_options(builder.finalize(), 0);
+        assert!(!plain.sack_permitted);
+        assert!(!plain.timestamps.enabled);
+        assert!(!plain.window_scale.enabled);
+        let offered = plain.syn_options(0, true);
+        let mut parser = TcpOptionParser::new(&offered);
+        assert!(!parser.find_sack_permitted());
+        assert_eq!(parser.find_timestamps(), None);
+    }
+
+    #[test]
+    fn test_sack_rack_recovery() {
+        let mut tcb = test_tcb();
+        tcb.sack_permitted = true;
+        let mut queue = RetransmitQueue::new();
+        send_five(&mut tcb, &mut queue);
+
+        // 先頭2セグメントが失われ、残り3つがSACKされる
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_sack(&[SackBlock::new(3000, 6000)]);
+        let ack = ack_segment(1000, builder.finalize());
+        let (ack_num, options) = ack_and_options(&ack);
+
+        let outcome = process_ack(&mut tcb, &mut queue, ack_num, options, 50);
+        assert_eq!(outcome.newly_sacked, 3000);
+        assert_eq!(queue.scoreboard().sacked_bytes(), 3000);
+        // RACK: 後から送ったセグメントが配送済み → 先頭2つはロス
+        assert_eq!(outcome.lost_bytes, 2000);
+        assert_eq!(tcb.congestion.state(), CongestionState::FastRecovery);
+        assert_eq!(tcb.congestion.bytes_in_flight(), 2000);
+
+        // ロス判定されたセグメントのみ再送（go-back-Nしない）
+        let lost: Vec<u32> = queue.take_lost(50).into_iter().map(|(seq, _)| seq).collect();
+        assert_eq!(lost, alloc::vec![1000, 2000]);
+
+        // 再送が届き全体が確認される
+        let ack = ack_segment(6000, &[]);
+        let (ack_num, options) = ack_and_options(&ack);
+        let outcome = process_ack(&mut tcb, &mut queue, ack_num, options, 100);
+        assert_eq!(outcome.sacked_acked, 3000);
+        assert!(queue.is_empty());
+        assert_eq!(tcb.snd_una, 6000);
+        assert_eq!(tcb.congestion.bytes_in_flight(), 0);
+        assert_eq!(tcb.congestion.state(), CongestionState::CongestionAvoidance);
+    }
+
+    #[test]
+    fn test_sack_ignored_without_negotiation() {
+        let mut tcb = test_tcb();
+        let mut queue = RetransmitQueue::new();
+        send_five(&mut tcb, &mut queue);
+
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_sack(&[SackBlock::new(3000, 6000)]);
+        let ack = ack_segment(1000, builder.finalize());
+        let (ack_num, options) = ack_and_options(&ack);
+
+        let outcome = process_ack(&mut tcb, &mut queue, ack_num, options, 50);
+        assert_eq!(outcome.newly_sacked, 0);
+        assert_eq!(outcome.lost_bytes, 0);
+    }
+
+    #[test]
+    fn test_dsack_widens_reorder_window() {
+        let mut tcb = test_tcb();
+        tcb.sack_permitted = true;
+        let mut queue = RetransmitQueue::new();
+        send_five(&mut tcb, &mut queue);
+
+        let ack = ack_segment(6000, &[]);
+        let (ack_num, options) = ack_and_options(&ack);
+        process_ack(&mut tcb, &mut queue, ack_num, options, 40);
+        assert_eq!(queue.rack().reo_wnd_mult(), 1);
+
+        // 不要な再送を相手がD-SACKで報告（累積ACK以下のブロック）
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_sack(&[SackBlock::new(1000, 2000)]);
+        let ack = ack_segment(6000, builder.finalize());
+        let (ack_num, options) = ack_and_options(&ack);
+        let outcome = process_ack(&mut tcb, &mut queue, ack_num, options, 45);
+
+        assert!(outcome.dsack);
+        assert_eq!(outcome.newly_sacked, 0);
+        assert_eq!(queue.scoreboard().dsack_count(), 1);
+        assert_eq!(queue.rack().reo_wnd_mult(), 2);
+    }
+
+    #[test]
+    fn test_receiver_sack_and_dsack_blocks() {
+        let mut tcb = test_tcb();
+        tcb.sack_permitted = true;
+        tcb.rcv_nxt = 5000;
+
+        // 順序外セグメント → SACKで報告
+        assert!(tcb.on_data_segment(6000, &[1; 1000]).is_empty());
+        let options = tcb.ack_options(10);
+        let ack = ack_segment(tcb.rcv_nxt, &options);
+        let (ack_num, options) = ack_and_options(&ack);
+        assert_eq!(ack_num, 5000);
+        assert_eq!(
+            TcpOptionParser::new(options).find_sack_blocks(),
+            alloc::vec![SackBlock::new(6000, 7000)]
+        );
+
+        // 同じセグメントの重複受信 → 先頭にD-SACKブロック
+        assert!(tcb.on_data_segment(6000, &[1; 1000]).is_empty());
+        let options = tcb.ack_options(11);
+        let blocks = TcpOptionParser::new(&options).find_sack_blocks();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0], SackBlock::new(6000, 7000));
+        assert!(super::super::sack::SackScoreboard::is_dsack(5000, &blocks));
+
+        // 穴が埋まると連続データを配送し、SACKブロックは消える
+        let delivered = tcb.on_data_segment(5000, &[2; 1000]);
+        assert_eq!(delivered.len(), 2000);
+        assert_eq!(tcb.rcv_nxt, 7000);
+        let options = tcb.ack_options(12);
+        assert!(TcpOptionParser::new(&options).find_sack_blocks().is_empty());
+    }
+
+    #[test]
+    fn test_timestamps_rtt_and_paws() {
+        let mut tcb = test_tcb();
+        tcb.timestamps.enable(500, 0);
+        tcb.initialize_seq(1000);
+        let mut queue = RetransmitQueue::new();
+        queue.push(1000, data_segment(1000, MSS as usize), 100);
+        tcb.on_send(MSS);
+        tcb.snd_nxt = 2000;
+
+        // 再送済みセグメント: Karnのアルゴリズムでは測定不可
+        queue.retransmit(300);
+
+        // TSecr=300（再送時のTSval）のエコーでRTTを測定できる
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_timestamps(600, 300);
+        let ack = ack_segment(2000, builder.finalize());
+        let (ack_num, options) = ack_and_options(&ack);
+        let outcome = process_ack(&mut tcb, &mut queue, ack_num, options, 340);
+        assert_eq!(outcome.rtt, Some(40));
+        assert_eq!(tcb.congestion.rtt().latest, 40);
+
+        // PAWS: TS.Recentより古いTSvalは拒否
+        assert!(!tcb.timestamps.paws_check(400, 340, false));
+        assert!(tcb.timestamps.paws_check(600, 340, false));
+    }
+
+    #[test]
+    fn test_transmit_paced_and_counted_in_flight() {
+        use super::super::congestion::AckSample;
+        use super::super::retransmit::with_retransmit_queue;
+        use super::super::tcb::tcb_table;
+        use super::super::tcp_tx;
+        use super::loopback;
+
+        let _guard = loopback::setup();
+        let (client, server, _listener) = loopback::connect(18081);
+        let fd = client.fd();
+
+        // srtt=10ms のペーシングレート（スロースタート中は cwnd/srtt の2倍）
+        loopback::step();
+        let now = tcb_table().get_current_tick();
+        tcb_table().update_by_fd(fd, |tcb| {
+            let snd_una = tcb.snd_una;
+            tcb.congestion.on_ack_sample(AckSample {
+                bytes_acked: 0,
+                is_dup_ack: false,
+                snd_una,
+                now,
+                rtt: Some(10),
+            });
+        });
+        let tcb = tcb_table().find_by_fd(fd).unwrap();
+        let (mss, cwnd, start) = (tcb.mss, tcb.congestion.cwnd(), tcb.snd_nxt);
+        assert!(tcb.congestion.pacing_rate() > 0);
+
+        let data: Vec<u8> = (0..cwnd).map(|i| i as u8).collect();
+        {
+            let mut inner = client.socket().unwrap().inner().lock();
+            inner.send_to_buffer(&data).unwrap();
+        }
+
+        // cwnd内でもクレジット分（1tick + 2MSS）しか送らない
+        let sent = tcp_tx::transmit(client.socket().unwrap()) as u32;
+        let tcb = tcb_table().find_by_fd(fd).unwrap();
+        assert!(sent >= mss && sent < cwnd);
+        assert!(tcb.paced);
+        assert_eq!(tcb.snd_nxt.wrapping_sub(start), sent);
+        assert_eq!(tcb.congestion.bytes_in_flight(), sent);
+        let queued = with_retransmit_queue(tcb.local, tcb.remote, |q| {
+            q.segments().map(|seg| seg.len()).sum::<u32>()
+        });
+        assert_eq!(queued, Some(sent));
+
+        // tickごとに残りが送られる
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 4096];
+        for _ in 0..100 {
+            loopback::step();
+            while let Ok(n) = server.recv(&mut buffer) {
+                received.extend_from_slice(&buffer[..n]);
+            }
+        }
+        assert_eq!(received, data);
+        assert!(!tcb_table().find_by_fd(fd).unwrap().paced);
+    }
+
+    #[test]
+    fn test_lost_segment_recovered_from_retransmit_queue() {
+        use super::super::retransmit::with_retransmit_queue;
+        use super::super::tcb::tcb_table;
+        use super::super::tcp_tx;
+        use super::loopback;
+
+        let _guard = loopback::setup();
+        let (client, server, _listener) = loopback::connect(18082);
+        let tcb = tcb_table().find_by_fd(client.fd()).unwrap();
+        assert!(tcb.sack_permitted);
+
+        let data: Vec<u8> = (0..4 * tcb.mss).map(|i| (i * 7) as u8).collect();
+        {
+            let mut inner = client.socket().unwrap().inner().lock();
+            inner.send_to_buffer(&data).unwrap();
+        }
+        assert_eq!(tcp_tx::transmit(client.socket().unwrap()), data.len());
+
+        // 送った全セグメントが再送キューに積まれている
+        let seqs: Vec<u32> = with_retransmit_queue(tcb.local, tcb.remote, |q| {
+            q.segments().map(|seg| seg.seq).collect()
+        })
+        .unwrap();
+        let expected: Vec<u32> = (0..4).map(|i| tcb.snd_nxt.wrapping_add(i * tcb.mss)).collect();
+        assert_eq!(seqs, expected);
+
+        // 先頭セグメントをループバック上で落とす
+        let dropped = crate::net::stack::stack().lock().as_ref().unwrap().dequeue_loopback();
+        assert!(dropped.is_some());
+
+        // 後続のSACKでロスを検出し、RTOを待たずに再送キューから再送する
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 4096];
+        for _ in 0..50 {
+            loopback::step();
+            while let Ok(n) = server.recv(&mut buffer) {
+                received.extend_from_slice(&buffer[..n]);
+            }
+        }
+        assert_eq!(received, data);
+        let retransmitted = with_retransmit_queue(tcb.local, tcb.remote, |q| q.segments().count());
+        assert_eq!(retransmitted, Some(0));
+        // ロスを輻輳制御へ通知済み（スロースタートを抜けている）
+        let state = tcb_table().find_by_fd(client.fd()).unwrap().congestion.state();
+        assert_ne!(state, CongestionState::SlowStart);
+    }
+
+    #[test]
+    fn test_paws_rejected_segment_is_acked() {
+        use super::super::segment::TcpSegmentBuilder;
+        use super::super::tcb::{tcb_table, tcp_flags};
+        use super::super::tcp_rx::process_tcp_segment;
+        use super::loopback;
+
+        let _guard = loopback::setup();
+        let (client, _server, _listener) = loopback::connect(18085);
+        loopback::pump();
+        let tcb = tcb_table().find_by_fd(client.fd()).unwrap();
+        assert!(tcb.timestamps.enabled);
+
+        // TS.Recent より古い TSval を持つ重複セグメント
+        let mut options = [1, 1, 8, 10, 0, 0, 0, 0, 0, 0, 0, 0];
+        options[4..8].copy_from_slice(&tcb.timestamps.tsecr().wrapping_sub(1000).to_be_bytes());
+        let mut segment = TcpSegmentBuilder::new(tcb.remote.port, tcb.local.port)
+            .seq(tcb.rcv_nxt)
+            .ack(tcb.snd_nxt)
+            .flags(tcp_flags::ACK)
+            .window(65535)
+            .options(&options)
+            .data(alloc::vec![0xAA; 100])
+            .build();
+        TcpSegmentBuilder::calculate_checksum(&mut segment, tcb.remote.ip, tcb.local.ip);
+        process_tcp_segment(tcb.remote.ip, tcb.local.ip, &segment);
+
+        // データは受け取らず、ACK を返して破棄を数える
+        let tcb_after = tcb_table().find_by_fd(client.fd()).unwrap();
+        assert_eq!(tcb_after.rcv_nxt, tcb.rcv_nxt);
+        assert_eq!(tcb_after.tcp_stats().paws_rejected, 1);
+        let ack = crate::net::stack::stack().lock().as_ref().unwrap().dequeue_loopback();
+        let ack = ack.expect("PAWS rejection should be acknowledged");
+        let tcp = &ack[20..];
+        assert_eq!(tcp[13] & tcp_flags::ACK, tcp_flags::ACK);
+        assert_eq!(u32::from_be_bytes([tcp[8], tcp[9], tcp[10], tcp[11]]), tcb.rcv_nxt);
+    }
+
+    #[test]
+    fn test_time_wait_acks_retransmitted_fin() {
+        use super::super::segment::TcpSegmentBuilder;
+        use super::super::tcb::{TIME_WAIT_TICKS, tcb_table, tcp_flags};
+        use super::super::tcp_rx::process_tcp_segment;
+        use super::loopback;
+
+        let _guard = loopback::setup();
+        let (client, server, _listener) = loopback::connect(18086);
+        let tcb = tcb_table().find_by_fd(client.fd()).unwrap();
+        let (local, remote) = (tcb.local, tcb.remote);
+
+        // クライアントから閉じ、続いてサーバーも閉じる
+        drop(client);
+        for _ in 0..10 {
+            loopback::step();
+        }
+        drop(server);
+        for _ in 0..10 {
+            loopback::step();
+        }
+        assert!(tcb_table().get(local, remote).is_none());
+        let tw = tcb_table().time_wait(local, remote).expect("active closer keeps TIME-WAIT");
+
+        // 最後の ACK が失われてサーバーが FIN を再送した
+        let mut fin = TcpSegmentBuilder::new(remote.port, local.port)
+            .seq(tw.rcv_nxt.wrapping_sub(1))
+            .ack(tw.snd_nxt)
+            .flags(tcp_flags::FIN | tcp_flags::ACK)
+            .window(65535)
+            .build();
+        TcpSegmentBuilder::calculate_checksum(&mut fin, remote.ip, local.ip);
+        process_tcp_segment(remote.ip, local.ip, &fin);
+
+        let ack = crate::net::stack::stack().lock().as_ref().unwrap().dequeue_loopback();
+        let ack = ack.expect("retransmitted FIN should be acknowledged");
+        let tcp = &ack[20..];
+        assert_eq!(tcp[13], tcp_flags::ACK);
+        assert_eq!(u32::from_be_bytes([tcp[4], tcp[5], tcp[6], tcp[7]]), tw.snd_nxt);
+        assert_eq!(u32::from_be_bytes([tcp[8], tcp[9], tcp[10], tcp[11]]), tw.rcv_nxt);
+
+        // 2MSL 経てば破棄される
+        tcb_table().current_tick.fetch_add(TIME_WAIT_TICKS + 1, core::sync::atomic::Ordering::Relaxed);
+        assert!(tcb_table().time_wait(local, remote).is_none());
+    }
+
+    #[test]
+    fn test_tail_loss_probe() {
+        let mut queue = RetransmitQueue::new();
+        queue.push(1000, data_segment(1000, MSS as usize), 0);
+        queue.push(2000, data_segment(2000, MSS as usize), 0);
+
+        // 先頭のみACK（srtt=50）、末尾は失われる
+        queue.ack_received(2000, 50);
+
+        // PTO = min(2*srtt + WCDelAckT, RTO) = min(300, 200)
+        assert!(queue.tlp_probe(100).is_none());
+        let probe = queue.tlp_probe(250).expect("probe should fire");
+        assert_eq!(probe, data_segment(2000, MSS as usize));
+        assert_eq!(queue.tlp().probes_sent(), 1);
+
+        // 未確認のプローブがある間は追加送信しない
+        assert!(queue.tlp_probe(1000).is_none());
+    }
+}
+
+/// ループバック上でTCPを動かすテスト用の補助
+///
+/// ネットワークタスクとタイマー割り込みの代わりにイベント処理とtickを手で回す
+#[cfg(test)]
+pub(crate) mod loopback {
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use spin::{Mutex, MutexGuard};
+
+    use super::super::event::event_queue;
+    use super::super::handler::NetworkEventHandler;
+    use super::super::manager::{SOCKET_MANAGER, init_socket_manager};
+    use super::super::socket::{OwnedSocket, create_tcp_server, tcp_connect};
+    use super::super::tcb::tcb_table;
+    use super::super::types::{SocketAddr, SocketState};
+    use crate::net::stack;
+
+    /// グローバルなスタック・TCBテーブルを使うテストを直列化する
+    static NETWORK_TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    /// 最大ステップ数（1ステップ = 1tick）
+    const MAX_STEPS: usize = 20_000;
+
+    /// スタックとソケットマネージャを初期化してロックを取る
+    pub(crate) fn setup() -> MutexGuard<'static, ()> {
+        let guard = NETWORK_TEST_LOCK.lock();
+        if stack::stack().lock().is_none() {
+            stack::init_default();
+        }
+        if SOCKET_MANAGER.read().is_none() {
+            init_socket_manager();
+        }
+        guard
+    }
+
+    /// 溜まったネットワークイベントとループバックパケットを処理し切る
+    pub(crate) fn pump() {
+        let handler = NetworkEventHandler::new();
+        loop {
+            let mut progressed = false;
+            while let Some(event) = event_queue().recv() {
+                handler.handle_event(event);
+                progressed = true;
+            }
+            progressed |= stack::poll_loopback() > 0;
+            if !progressed {
+                break;
+            }
+        }
+    }
+
+    /// 1tick進める
+    pub(crate) fn step() {
+        pump();
+        crate::task::timer::handle_timer_interrupt();
+        tcb_table().tick();
+    }
+
+    /// 127.0.0.1の`port`で接続を確立する
+    ///
+    /// 戻り値: (クライアント, 受け付けた接続, リスナー)
+    pub(crate) fn connect(port: u16) -> (OwnedSocket, OwnedSocket, OwnedSocket) {
+        let addr = SocketAddr::new([127, 0, 0, 1], port);
+        let listener = create_tcp_server(addr, 4).unwrap();
+        let client = tcp_connect(addr).unwrap();
+        for _ in 0..MAX_STEPS {
+            pump();
+            if let Ok((conn, _)) = listener.accept() {
+                assert_eq!(client.socket().unwrap().state(), SocketState::Connected);
+                return (client, conn, listener);
+            }
+            step();
+        }
+        panic!("connection to port {} was not established", port);
+    }
+
+    /// Futureが完了するまでネットワークとタイマーを回す
+    pub(crate) fn run<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..MAX_STEPS {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            step();
+        }
+        panic!("future did not complete within {} steps", MAX_STEPS);
+    }
 }
@@ -0,0 +1,138 @@
+//! # TCP Timestamps - タイムスタンプオプション
+//!
+//! RFC 7323 準拠実装
+//! - RTTM: TSecrによるRTT測定（再送セグメントでも測定可能）
+//! - PAWS: 古いタイムスタンプを持つセグメントの破棄
+
+/// PAWSでts_recentを無効とみなすアイドル時間 (24日, tick)
+pub const PAWS_IDLE_LIMIT: u64 = 24 * 24 * 60 * 60 * 1000;
+
+/// タイムスタンプ比較（wrapping考慮）: a < b
+#[inline]
+fn ts_before(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+/// タイムスタンプオプション状態
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimestampOption {
+    /// タイムスタンプが有効か（SYNで双方が送った場合のみ）
+    pub enabled: bool,
+    /// 相手から受け取った最新のTSval (TS.Recent)
+    pub ts_recent: u32,
+    /// ts_recentを更新した時刻（tick）
+    pub ts_recent_tick: u64,
+    /// 最後に送ったACK番号 (Last.ACK.sent)
+    pub last_ack_sent: u32,
+}
+
+impl TimestampOption {
+    /// 新規作成（無効状態）
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            ts_recent: 0,
+            ts_recent_tick: 0,
+            last_ack_sent: 0,
+        }
+    }
+
+    /// SYN/SYN-ACKで相手のTSvalを受け取り有効化
+    pub fn enable(&mut self, peer_tsval: u32, now: u64) {
+        self.enabled = true;
+        self.ts_recent = peer_tsval;
+        self.ts_recent_tick = now;
+    }
+
+    /// 送信するTSval（1 tick = 1ms のクロック）
+    #[inline]
+    pub fn tsval(now: u64) -> u32 {
+        now as u32
+    }
+
+    /// 送信するTSecr
+    #[inline]
+    pub fn tsecr(&self) -> u32 {
+        self.ts_recent
+    }
+
+    /// PAWSチェック（RFC 7323 Section 5.3）
+    ///
+    /// 戻り値: セグメントを受け入れるなら true
+    pub fn paws_check(&self, tsval: u32, now: u64, is_rst: bool) -> bool {
+        if !self.enabled || is_rst {
+            return true;
+        }
+        // 長時間アイドルならts_recentは信用しない
+        if now.saturating_sub(self.ts_recent_tick) > PAWS_IDLE_LIMIT {
+            return true;
+        }
+        !ts_before(tsval, self.ts_recent)
+    }
+
+    /// 受け入れたセグメントでts_recentを更新（RFC 7323 Section 4.3）
+    pub fn on_segment(&mut self, seq: u32, tsval: u32, now: u64) {
+        if !self.enabled {
+            return;
+        }
+        let seq_le_last_ack = (seq.wrapping_sub(self.last_ack_sent) as i32) <= 0;
+        if seq_le_last_ack && !ts_before(tsval, self.ts_recent) {
+            self.ts_recent = tsval;
+            self.ts_recent_tick = now;
+        }
+    }
+
+    /// ACK送信を記録
+    pub fn on_ack_sent(&mut self, rcv_nxt: u32) {
+        self.last_ack_sent = rcv_nxt;
+    }
+
+    /// TSecrからRTTサンプルを算出（tick）
+    pub fn rtt_sample(&self, now: u64, tsecr: u32) -> Option<u64> {
+        if !self.enabled || tsecr == 0 {
+            return None;
+        }
+        let rtt = Self::tsval(now).wrapping_sub(tsecr);
+        // 未来のTSecr・異常値は無視
+        (rtt < (1 << 31)).then_some(rtt as u64)
+    }
+}
+
+// =====================================================
+// テスト
+// =====================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paws_rejects_old_timestamp() {
+        let mut ts = TimestampOption::new();
+        ts.enable(1000, 0);
+
+        assert!(ts.paws_check(1000, 10, false));
+        assert!(ts.paws_check(1500, 10, false));
+        assert!(!ts.paws_check(999, 10, false));
+        // RSTはPAWS対象外
+        assert!(ts.paws_check(999, 10, true));
+        // 長時間アイドル後は受け入れる
+        assert!(ts.paws_check(999, PAWS_IDLE_LIMIT + 1, false));
+    }
+
+    #[test]
+    fn test_ts_recent_update_and_rtt() {
+        let mut ts = TimestampOption::new();
+        ts.enable(100, 0);
+        ts.on_ack_sent(5000);
+
+        // Last.ACK.sent 以前のセグメントのみ更新
+        ts.on_segment(6000, 200, 1);
+        assert_eq!(ts.tsecr(), 100);
+        ts.on_segment(5000, 200, 1);
+        assert_eq!(ts.tsecr(), 200);
+
+        assert_eq!(ts.rtt_sample(150, 120), Some(30));
+        assert_eq!(ts.rtt_sample(150, 0), None);
+    }
+}
@@ -132,6 +132,8 @@ pub enum SocketError {
     ResourceExhausted,
     /// ポートがすでに使用中
     PortInUse,
+    /// セキュリティポリシーにより拒否
+    PermissionDenied,
     /// 内部エラー
     Internal,
 }
@@ -152,6 +154,7 @@ impl core::fmt::Display for SocketError {
             Self::InvalidStateTransition => write!(f, "Invalid state transition"),
             Self::ResourceExhausted => write!(f, "Resource exhausted"),
             Self::PortInUse => write!(f, "Port already in use"),
+            Self::PermissionDenied => write!(f, "Permission denied by network policy"),
             Self::Internal => write!(f, "Internal error"),
         }
     }
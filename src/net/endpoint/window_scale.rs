@@ -8,6 +8,10 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+use alloc::vec::Vec;
+
+use super::sack::{MAX_SACK_BLOCKS, SackBlock};
+
 /// 最大ウィンドウスケール値 (2^14 = 16384 まで)
 pub const MAX_WINDOW_SCALE: u8 = 14;
 
@@ -196,6 +200,71 @@ impl<'a> TcpOptionParser<'a> {
         }
         None
     }
+
+    /// 指定種別のオプション本体（Kind/Lengthを除く）を探す
+    fn find_option(&mut self, wanted: u8) -> Option<&'a [u8]> {
+        self.pos = 0;
+        while self.pos < self.data.len() {
+            let kind = self.data[self.pos];
+
+            match kind {
+                tcp_option_kind::END_OF_OPTIONS => break,
+                tcp_option_kind::NOP => {
+                    self.pos += 1;
+                }
+                _ => {
+                    if self.pos + 1 >= self.data.len() {
+                        break;
+                    }
+                    let len = self.data[self.pos + 1] as usize;
+                    if len < 2 || self.pos + len > self.data.len() {
+                        break;
+                    }
+                    if kind == wanted {
+                        return Some(&self.data[self.pos + 2..self.pos + len]);
+                    }
+                    self.pos += len;
+                }
+            }
+        }
+        None
+    }
+
+    /// SACK Permitted オプションがあるか
+    pub fn find_sack_permitted(&mut self) -> bool {
+        self.find_option(tcp_option_kind::SACK_PERMITTED)
+            .is_some_and(|body| body.is_empty())
+    }
+
+    /// Timestamps オプションを探す (TSval, TSecr)
+    pub fn find_timestamps(&mut self) -> Option<(u32, u32)> {
+        let body = self.find_option(tcp_option_kind::TIMESTAMP)?;
+        if body.len() != 8 {
+            return None;
+        }
+        let tsval = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+        let tsecr = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+        Some((tsval, tsecr))
+    }
+
+    /// SACK オプションのブロックを取得
+    pub fn find_sack_blocks(&mut self) -> Vec<SackBlock> {
+        let Some(body) = self.find_option(tcp_option_kind::SACK) else {
+            return Vec::new();
+        };
+        if body.len() % 8 != 0 {
+            return Vec::new();
+        }
+        body.chunks_exact(8)
+            .take(MAX_SACK_BLOCKS)
+            .map(|c| {
+                SackBlock::new(
+                    u32::from_be_bytes([c[0], c[1], c[2], c[3]]),
+                    u32::from_be_bytes([c[4], c[5], c[6], c[7]]),
+                )
+            })
+            .collect()
+    }
 }
 
 /// TCPオプションビルダー
@@ -246,6 +315,42 @@ impl TcpOptionBuilder {
         self
     }
 
+    /// Timestamps オプション追加
+    pub fn add_timestamps(&mut self, tsval: u32, tsecr: u32) -> &mut Self {
+        // NOP + NOP + TSopt(10) for 4-byte alignment
+        if self.len + 12 <= 40 {
+            self.buffer[self.len] = tcp_option_kind::NOP;
+            self.buffer[self.len + 1] = tcp_option_kind::NOP;
+            self.buffer[self.len + 2] = tcp_option_kind::TIMESTAMP;
+            self.buffer[self.len + 3] = 10; // length
+            self.buffer[self.len + 4..self.len + 8].copy_from_slice(&tsval.to_be_bytes());
+            self.buffer[self.len + 8..self.len + 12].copy_from_slice(&tsecr.to_be_bytes());
+            self.len += 12;
+        }
+        self
+    }
+
+    /// SACK オプション追加（収まる分だけ）
+    pub fn add_sack(&mut self, blocks: &[SackBlock]) -> &mut Self {
+        // NOP + NOP + Kind(1) + Length(1) + 8バイト × n
+        let room = 40usize.saturating_sub(self.len + 4) / 8;
+        let count = blocks.len().min(room).min(MAX_SACK_BLOCKS);
+        if count == 0 {
+            return self;
+        }
+        self.buffer[self.len] = tcp_option_kind::NOP;
+        self.buffer[self.len + 1] = tcp_option_kind::NOP;
+        self.buffer[self.len + 2] = tcp_option_kind::SACK;
+        self.buffer[self.len + 3] = (2 + 8 * count) as u8;
+        self.len += 4;
+        for block in &blocks[..count] {
+            self.buffer[self.len..self.len + 4].copy_from_slice(&block.start.to_be_bytes());
+            self.buffer[self.len + 4..self.len + 8].copy_from_slice(&block.end.to_be_bytes());
+            self.len += 8;
+        }
+        self
+    }
+
     /// End of Options + パディング
     pub fn finalize(&mut self) -> &[u8] {
         // 4バイト境界にパディング
@@ -336,4 +441,30 @@ mod tests {
         assert_eq!(parser.find_mss(), Some(1460));
         assert_eq!(parser.find_window_scale(), Some(7));
     }
+
+    #[test]
+    fn test_timestamps_and_sack_roundtrip() {
+        let blocks = [SackBlock::new(3000, 4000), SackBlock::new(5000, 6000)];
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_timestamps(12345, 678).add_sack(&blocks);
+
+        let options = builder.finalize();
+        assert_eq!(options.len() % 4, 0);
+
+        let mut parser = TcpOptionParser::new(options);
+        assert_eq!(parser.find_timestamps(), Some((12345, 678)));
+        assert_eq!(parser.find_sack_blocks(), blocks.to_vec());
+        assert!(!parser.find_sack_permitted());
+    }
+
+    #[test]
+    fn test_sack_truncated_to_option_space() {
+        let blocks = [SackBlock::new(0, 1); 4];
+        let mut builder = TcpOptionBuilder::new();
+        builder.add_timestamps(1, 2).add_sack(&blocks);
+
+        // タイムスタンプ併用時は3ブロックまで
+        let mut parser = TcpOptionParser::new(builder.finalize());
+        assert_eq!(parser.find_sack_blocks().len(), 3);
+    }
 }
@@ -7,6 +7,7 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+use alloc::vec::Vec;
 use core::fmt;
 
 /// Ethernet frame type (EtherType)
@@ -50,7 +51,7 @@ impl From<EtherType> for u16 {
 }
 
 /// MAC address (6 bytes)
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MacAddress([u8; 6]);
 
 impl MacAddress {
@@ -90,6 +91,13 @@ impl MacAddress {
         (self.0[0] & 0x01) != 0
     }
 
+    /// Map an IPv4 multicast group to its MAC address (RFC 1112)
+    ///
+    /// 01:00:5e に続けてグループアドレスの下位23ビットを置く
+    pub const fn ipv4_multicast(group: [u8; 4]) -> Self {
+        MacAddress([0x01, 0x00, 0x5e, group[1] & 0x7f, group[2], group[3]])
+    }
+
     /// Check if this is a unicast address
     pub const fn is_unicast(&self) -> bool {
         !self.is_multicast()
@@ -99,6 +107,20 @@ impl MacAddress {
     pub const fn is_local(&self) -> bool {
         (self.0[0] & 0x02) != 0
     }
+
+    /// Parse "xx:xx:xx:xx:xx:xx" (or '-' separated)
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut bytes = [0u8; 6];
+        let mut parts = s.split([':', '-']);
+        for byte in bytes.iter_mut() {
+            let part = parts.next().filter(|p| p.len() == 2)?;
+            *byte = u8::from_str_radix(part, 16).ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(MacAddress(bytes))
+    }
 }
 
 impl fmt::Debug for MacAddress {
@@ -213,6 +235,19 @@ impl<'a> EthernetFrame<'a> {
         &self.data[EthernetHeader::SIZE..]
     }
 
+    /// Get the outer 802.1Q tag (None if untagged)
+    pub fn vlan_tag(&self) -> Option<VlanTag> {
+        if self.ether_type() != EtherType::Vlan || self.data.len() < EthernetHeader::SIZE + 2 {
+            return None;
+        }
+        Some(VlanTag::from_bytes([
+            self.data[12],
+            self.data[13],
+            self.data[14],
+            self.data[15],
+        ]))
+    }
+
     /// Get the entire raw frame data
     pub fn as_bytes(&self) -> &'a [u8] {
         self.data
@@ -311,6 +346,8 @@ impl<'a> EthernetFrameMut<'a> {
 pub struct EthernetProcessor {
     /// Local MAC address
     local_mac: MacAddress,
+    /// Accepted multicast MAC addresses
+    multicast_filter: Vec<MacAddress>,
     /// Statistics
     stats: EthernetStats,
 }
@@ -351,6 +388,8 @@ impl EthernetProcessor {
     pub fn new(local_mac: MacAddress) -> Self {
         EthernetProcessor {
             local_mac,
+            // 全ホストグループ (224.0.0.1) は常に受信する
+            multicast_filter: alloc::vec![MacAddress::ipv4_multicast([224, 0, 0, 1])],
             stats: EthernetStats::default(),
         }
     }
@@ -365,6 +404,23 @@ impl EthernetProcessor {
         self.local_mac = mac;
     }
 
+    /// Accept frames sent to a multicast MAC address
+    pub fn add_multicast(&mut self, mac: MacAddress) {
+        if !self.multicast_filter.contains(&mac) {
+            self.multicast_filter.push(mac);
+        }
+    }
+
+    /// Stop accepting frames sent to a multicast MAC address
+    pub fn remove_multicast(&mut self, mac: MacAddress) {
+        self.multicast_filter.retain(|m| *m != mac);
+    }
+
+    /// Accepted multicast MAC addresses
+    pub fn multicast_filter(&self) -> &[MacAddress] {
+        &self.multicast_filter
+    }
+
     /// Get statistics
     pub fn stats(&self) -> &EthernetStats {
         &self.stats
@@ -407,7 +463,7 @@ impl EthernetProcessor {
 
     /// Check if a MAC address is for us
     fn is_for_us(&self, mac: &MacAddress) -> bool {
-        *mac == self.local_mac || mac.is_broadcast()
+        *mac == self.local_mac || mac.is_broadcast() || self.multicast_filter.contains(mac)
     }
 
     /// Build a reply frame (swaps src/dst)
@@ -432,8 +488,14 @@ impl EthernetProcessor {
     }
 }
 
+/// 802.1Q Tag Protocol Identifier
+pub const VLAN_TPID: u16 = 0x8100;
+
+/// Highest usable VLAN ID (0 = priority tag only, 4095 = reserved)
+pub const VLAN_ID_MAX: u16 = 4094;
+
 /// VLAN tag (802.1Q)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C, packed)]
 pub struct VlanTag {
     /// Tag Protocol Identifier (0x8100)
@@ -460,6 +522,61 @@ impl VlanTag {
     pub fn dei(&self) -> bool {
         (self.tci[0] & 0x10) != 0
     }
+
+    /// Create a tag for a VLAN ID and priority
+    pub const fn new(vlan_id: u16, pcp: u8) -> Self {
+        let tci = ((pcp as u16 & 0x07) << 13) | (vlan_id & 0x0FFF);
+        VlanTag {
+            tpid: VLAN_TPID.to_be_bytes(),
+            tci: tci.to_be_bytes(),
+        }
+    }
+
+    /// Parse a tag from its wire format (TPID + TCI)
+    pub const fn from_bytes(bytes: [u8; 4]) -> Self {
+        VlanTag {
+            tpid: [bytes[0], bytes[1]],
+            tci: [bytes[2], bytes[3]],
+        }
+    }
+
+    /// Wire format (TPID + TCI)
+    pub const fn to_bytes(&self) -> [u8; 4] {
+        [self.tpid[0], self.tpid[1], self.tci[0], self.tci[1]]
+    }
+
+    /// Get Tag Control Information
+    pub fn tci(&self) -> u16 {
+        u16::from_be_bytes(self.tci)
+    }
+}
+
+/// Insert an 802.1Q tag after the source MAC address
+///
+/// 元の EtherType はタグの後ろに残る。タグ付きフレームにはさらにタグを重ねる（Q-in-Q）。
+pub fn insert_vlan_tag(frame: &[u8], tag: VlanTag) -> Option<Vec<u8>> {
+    if frame.len() < EthernetHeader::SIZE {
+        return None;
+    }
+    let mut tagged = Vec::with_capacity(frame.len() + VlanTag::SIZE);
+    tagged.extend_from_slice(&frame[..12]);
+    tagged.extend_from_slice(&tag.to_bytes());
+    tagged.extend_from_slice(&frame[12..]);
+    Some(tagged)
+}
+
+/// Remove the outer 802.1Q tag
+///
+/// 戻り値: タグと、タグを取り除いたフレーム（タグなしなら None）
+pub fn strip_vlan_tag(frame: &[u8]) -> Option<(VlanTag, Vec<u8>)> {
+    let tag = EthernetFrame::parse(frame)?.vlan_tag()?;
+    if frame.len() < EthernetHeader::SIZE + VlanTag::SIZE {
+        return None;
+    }
+    let mut untagged = Vec::with_capacity(frame.len() - VlanTag::SIZE);
+    untagged.extend_from_slice(&frame[..12]);
+    untagged.extend_from_slice(&frame[12 + VlanTag::SIZE..]);
+    Some((tag, untagged))
 }
 
 #[cfg(test)]
@@ -474,6 +591,61 @@ mod tests {
 
         assert!(MacAddress::BROADCAST.is_broadcast());
         assert!(MacAddress::BROADCAST.is_multicast());
+
+        assert_eq!(MacAddress::parse("00:11:22:33:44:55"), Some(mac));
+        assert_eq!(MacAddress::parse("00-11-22-33-44-55"), Some(mac));
+        assert_eq!(MacAddress::parse("00:11:22:33:44"), None);
+        assert_eq!(MacAddress::parse("00:11:22:33:44:55:66"), None);
+        assert_eq!(MacAddress::parse("0:11:22:33:44:55"), None);
+    }
+
+    #[test]
+    fn test_multicast_filter() {
+        let mdns = MacAddress::ipv4_multicast([224, 0, 0, 251]);
+        assert_eq!(mdns, MacAddress::from_octets(0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb));
+        // 上位ビットは落とされる (239.128.0.1 → 01:00:5e:00:00:01)
+        assert_eq!(
+            MacAddress::ipv4_multicast([239, 128, 0, 1]),
+            MacAddress::ipv4_multicast([224, 0, 0, 1])
+        );
+
+        let mut eth = EthernetProcessor::new(MacAddress::from_octets(0x52, 0x54, 0, 0x12, 0x34, 0x56));
+        let mut frame = [0u8; 60];
+        frame[..6].copy_from_slice(mdns.as_bytes());
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        assert!(matches!(eth.process(&frame), ProcessResult::Dropped));
+
+        eth.add_multicast(mdns);
+        assert!(matches!(eth.process(&frame), ProcessResult::Ipv4(_)));
+        eth.remove_multicast(mdns);
+        assert!(matches!(eth.process(&frame), ProcessResult::Dropped));
+    }
+
+    #[test]
+    fn test_vlan_tagging() {
+        let mut frame = [0u8; 60];
+        frame[..6].copy_from_slice(MacAddress::BROADCAST.as_bytes());
+        frame[6..12].copy_from_slice(&[0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+        frame[12..14].copy_from_slice(&0x0806u16.to_be_bytes());
+        assert_eq!(EthernetFrame::parse(&frame).unwrap().vlan_tag(), None);
+        assert!(strip_vlan_tag(&frame).is_none());
+
+        let tag = VlanTag::new(100, 5);
+        assert_eq!(tag.vlan_id(), 100);
+        assert_eq!(tag.pcp(), 5);
+        assert!(!tag.dei());
+
+        let tagged = insert_vlan_tag(&frame, tag).unwrap();
+        assert_eq!(tagged.len(), 64);
+        let parsed = EthernetFrame::parse(&tagged).unwrap();
+        assert_eq!(parsed.ether_type(), EtherType::Vlan);
+        assert_eq!(parsed.source(), MacAddress::from_octets(0x52, 0x54, 0, 0x12, 0x34, 0x56));
+        assert_eq!(parsed.vlan_tag(), Some(tag));
+        assert_eq!(&tagged[16..18], &0x0806u16.to_be_bytes());
+
+        let (stripped_tag, untagged) = strip_vlan_tag(&tagged).unwrap();
+        assert_eq!(stripped_tag.vlan_id(), 100);
+        assert_eq!(untagged.as_slice(), &frame[..]);
     }
 
     #[test]
@@ -0,0 +1,550 @@
+//! # Firewall - ステートフルパケットフィルタ
+//!
+//! IPv4 の input / output / forward フックで評価されるルールベースの
+//! パケットフィルタ。各チェーンのルールは先頭から順に評価され、最初に
+//! 一致したルールの動作（accept / drop）が適用される。一致するルールが
+//! なければチェーンのポリシー（既定は accept）に従う。
+//!
+//! - コネクション追跡（TCP/UDP/ICMP）による `state` マッチ
+//! - 先頭以外の IP フラグメントはポートを持たず、`fragment` ルールか追跡で扱う
+//! - ルールごとのパケット数・バイト数カウンタ
+//! - トークンバケットによる `limit` マッチ
+//!
+//! フィルタ本体は `NetworkStack` が保持し、スタックのロック内から呼ばれる。
+//! ロック順序は interfaces → firewall。
+
+pub mod conntrack;
+pub mod rule;
+
+pub use conntrack::{ConnEntry, ConnKey, ConnState, ConnTrack, TcpTrackState};
+pub use rule::{
+    Action, Chain, Cidr, FirewallRule, PortRange, RateLimit, RuleMatch, RuleParseError, StateMask,
+    protocol_name,
+};
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::ipv4::{Ipv4Address, Ipv4Packet};
+use rule::{PROTO_ICMP, PROTO_TCP, PROTO_UDP};
+
+/// Maximum rules across all chains
+pub const MAX_RULES: usize = 256;
+
+/// Header fields the firewall matches on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PacketMeta<'a> {
+    /// IP protocol number
+    pub protocol: u8,
+    /// Source address
+    pub src: Ipv4Address,
+    /// Destination address
+    pub dst: Ipv4Address,
+    /// Source port (TCP/UDP, None for non-first fragments)
+    pub src_port: Option<u16>,
+    /// Destination port (TCP/UDP, None for non-first fragments)
+    pub dst_port: Option<u16>,
+    /// TCP flags (low 8 bits)
+    pub tcp_flags: u8,
+    /// ICMP type
+    pub icmp_type: Option<u8>,
+    /// ICMP echo identifier
+    pub icmp_id: Option<u16>,
+    /// Flow quoted by an ICMP error
+    pub icmp_inner: Option<ConnKey>,
+    /// IP identification
+    pub ip_id: u16,
+    /// Non-first fragment (no transport header)
+    pub fragment: bool,
+    /// More fragments follow
+    pub more_fragments: bool,
+    /// IP packet length
+    pub len: usize,
+    /// Interface name (ingress for input, egress for output)
+    pub interface: &'a str,
+}
+
+impl<'a> PacketMeta<'a> {
+    /// Extract match fields from an IPv4 packet
+    pub fn parse(packet: &[u8], interface: &'a str) -> Option<Self> {
+        let ip = Ipv4Packet::parse(packet)?;
+        let protocol = u8::from(ip.protocol());
+        let l4 = ip.payload();
+        let header = ip.header();
+        let mut meta = PacketMeta {
+            protocol,
+            src: ip.source(),
+            dst: ip.destination(),
+            ip_id: header.identification(),
+            fragment: header.fragment_offset() != 0,
+            more_fragments: header.more_fragments(),
+            len: ip.as_bytes().len(),
+            interface,
+            ..PacketMeta::default()
+        };
+
+        // 先頭以外のフラグメントの先頭バイトはトランスポートヘッダではない
+        if meta.fragment {
+            return Some(meta);
+        }
+        match protocol {
+            PROTO_TCP | PROTO_UDP if l4.len() >= 4 => {
+                meta.src_port = Some(u16::from_be_bytes([l4[0], l4[1]]));
+                meta.dst_port = Some(u16::from_be_bytes([l4[2], l4[3]]));
+                if protocol == PROTO_TCP && l4.len() >= 14 {
+                    meta.tcp_flags = l4[13];
+                }
+            }
+            PROTO_ICMP if l4.len() >= 8 => {
+                let icmp_type = l4[0];
+                meta.icmp_type = Some(icmp_type);
+                match icmp_type {
+                    0 | 8 => meta.icmp_id = Some(u16::from_be_bytes([l4[4], l4[5]])),
+                    3 | 11 => meta.icmp_inner = quoted_flow(&l4[8..]),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        Some(meta)
+    }
+}
+
+impl fmt::Display for PacketMeta<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match protocol_name(self.protocol) {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "proto {}", self.protocol)?,
+        }
+        match (self.src_port, self.dst_port) {
+            (Some(sport), Some(dport)) => {
+                write!(f, " {}:{} > {}:{}", self.src, sport, self.dst, dport)?
+            }
+            _ => write!(f, " {} > {}", self.src, self.dst)?,
+        }
+        if let Some(icmp_type) = self.icmp_type {
+            write!(f, " type {}", icmp_type)?;
+        }
+        if self.fragment {
+            write!(f, " frag id {}", self.ip_id)?;
+        }
+        write!(f, " len {} if {}", self.len, self.interface)
+    }
+}
+
+/// Flow key of the packet quoted in an ICMP error (IP header + 8 bytes)
+fn quoted_flow(data: &[u8]) -> Option<ConnKey> {
+    let ihl = (*data.first()? & 0x0F) as usize * 4;
+    if ihl < 20 || data.len() < ihl + 8 {
+        return None;
+    }
+    let protocol = data[9];
+    let src = Ipv4Address::new([data[12], data[13], data[14], data[15]]);
+    let dst = Ipv4Address::new([data[16], data[17], data[18], data[19]]);
+    let l4 = &data[ihl..];
+    match protocol {
+        PROTO_TCP | PROTO_UDP => Some(ConnKey::new(
+            protocol,
+            src,
+            u16::from_be_bytes([l4[0], l4[1]]),
+            dst,
+            u16::from_be_bytes([l4[2], l4[3]]),
+        )),
+        PROTO_ICMP => {
+            let id = u16::from_be_bytes([l4[4], l4[5]]);
+            Some(ConnKey::new(PROTO_ICMP, src, id, dst, id))
+        }
+        _ => None,
+    }
+}
+
+/// Firewall verdict for one packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verdict {
+    /// Action taken
+    pub action: Action,
+    /// Conntrack state of the packet
+    pub state: ConnState,
+    /// Rule that decided (None = chain policy)
+    pub rule: Option<u32>,
+    /// Matching rule asked for logging
+    pub log: bool,
+}
+
+/// Per-chain policy and counters
+#[derive(Debug, Clone, Copy)]
+pub struct ChainInfo {
+    /// Chain
+    pub chain: Chain,
+    /// Default action
+    pub policy: Action,
+    /// Packets that fell through to the policy
+    pub policy_packets: u64,
+    /// Bytes that fell through to the policy
+    pub policy_bytes: u64,
+    /// Packets accepted
+    pub accepted: u64,
+    /// Packets dropped
+    pub dropped: u64,
+}
+
+impl ChainInfo {
+    const fn new(chain: Chain) -> Self {
+        ChainInfo {
+            chain,
+            policy: Action::Accept,
+            policy_packets: 0,
+            policy_bytes: 0,
+            accepted: 0,
+            dropped: 0,
+        }
+    }
+}
+
+/// Firewall management errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FirewallError {
+    /// No rule with the given ID
+    RuleNotFound(u32),
+    /// Rule table is full
+    TooManyRules,
+    /// Invalid rule specification
+    Parse(RuleParseError),
+}
+
+impl fmt::Display for FirewallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirewallError::RuleNotFound(id) => write!(f, "No firewall rule with id {}", id),
+            FirewallError::TooManyRules => write!(f, "Too many firewall rules (max {})", MAX_RULES),
+            FirewallError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<RuleParseError> for FirewallError {
+    fn from(e: RuleParseError) -> Self {
+        FirewallError::Parse(e)
+    }
+}
+
+/// Stateful packet filter
+#[derive(Debug)]
+pub struct Firewall {
+    /// Rules of all chains in evaluation order
+    rules: Vec<FirewallRule>,
+    /// Chain policies and counters (indexed by `Chain as usize`)
+    chains: [ChainInfo; 3],
+    /// Connection tracking table
+    conntrack: ConnTrack,
+    /// Next rule ID
+    next_id: u32,
+}
+
+impl Firewall {
+    /// Create a firewall that accepts everything
+    pub const fn new() -> Self {
+        Firewall {
+            rules: Vec::new(),
+            chains: [
+                ChainInfo::new(Chain::Input),
+                ChainInfo::new(Chain::Output),
+                ChainInfo::new(Chain::Forward),
+            ],
+            conntrack: ConnTrack::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Evaluate a packet on a chain and update connection tracking
+    pub fn filter(&mut self, chain: Chain, meta: &PacketMeta<'_>, now_ms: u64) -> Verdict {
+        let class = self.conntrack.classify(meta, now_ms);
+
+        let decided = self
+            .rules
+            .iter_mut()
+            .filter(|r| r.chain == chain)
+            .find_map(|r| r.evaluate(meta, class.state, now_ms).then_some((r.action, r.id, r.log)));
+
+        let info = &mut self.chains[chain as usize];
+        let verdict = match decided {
+            Some((action, id, log)) => Verdict {
+                action,
+                state: class.state,
+                rule: Some(id),
+                log,
+            },
+            None => {
+                info.policy_packets += 1;
+                info.policy_bytes += meta.len as u64;
+                Verdict {
+                    action: info.policy,
+                    state: class.state,
+                    rule: None,
+                    log: false,
+                }
+            }
+        };
+
+        match verdict.action {
+            Action::Accept => {
+                info.accepted += 1;
+                self.conntrack.commit(meta, &class, now_ms);
+            }
+            Action::Drop => info.dropped += 1,
+        }
+        verdict
+    }
+
+    /// Append a rule to its chain, returning the assigned ID
+    pub fn add_rule(&mut self, mut rule: FirewallRule) -> Result<u32, FirewallError> {
+        if self.rules.len() >= MAX_RULES {
+            return Err(FirewallError::TooManyRules);
+        }
+        rule.id = self.next_id;
+        self.next_id += 1;
+        let id = rule.id;
+        self.rules.push(rule);
+        Ok(id)
+    }
+
+    /// Insert a rule at a position within its chain (0 = first)
+    pub fn insert_rule(&mut self, position: usize, mut rule: FirewallRule) -> Result<u32, FirewallError> {
+        if self.rules.len() >= MAX_RULES {
+            return Err(FirewallError::TooManyRules);
+        }
+        // チェーン内の position 番目のルールの直前（なければ末尾）に挿入
+        let index = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.chain == rule.chain)
+            .nth(position)
+            .map_or(self.rules.len(), |(i, _)| i);
+        rule.id = self.next_id;
+        self.next_id += 1;
+        let id = rule.id;
+        self.rules.insert(index, rule);
+        Ok(id)
+    }
+
+    /// Delete a rule by ID
+    pub fn delete_rule(&mut self, id: u32) -> Result<FirewallRule, FirewallError> {
+        let index = self
+            .rules
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or(FirewallError::RuleNotFound(id))?;
+        Ok(self.rules.remove(index))
+    }
+
+    /// Remove all rules of a chain (None = all chains), returning how many were removed
+    pub fn flush(&mut self, chain: Option<Chain>) -> usize {
+        let before = self.rules.len();
+        self.rules.retain(|r| chain.is_some_and(|c| r.chain != c));
+        before - self.rules.len()
+    }
+
+    /// Rules in evaluation order
+    pub fn rules(&self) -> &[FirewallRule] {
+        &self.rules
+    }
+
+    /// Set a chain's default action
+    pub fn set_policy(&mut self, chain: Chain, action: Action) {
+        self.chains[chain as usize].policy = action;
+    }
+
+    /// Get a chain's default action
+    pub fn policy(&self, chain: Chain) -> Action {
+        self.chains[chain as usize].policy
+    }
+
+    /// Policies and counters of all chains
+    pub fn chains(&self) -> [ChainInfo; 3] {
+        self.chains
+    }
+
+    /// Reset rule and chain counters
+    pub fn reset_counters(&mut self) {
+        for rule in &mut self.rules {
+            rule.reset_counters();
+        }
+        for info in &mut self.chains {
+            *info = ChainInfo {
+                policy: info.policy,
+                ..ChainInfo::new(info.chain)
+            };
+        }
+    }
+
+    /// Connection tracking table
+    pub fn conntrack(&self) -> &ConnTrack {
+        &self.conntrack
+    }
+
+    /// Clear the connection tracking table
+    pub fn flush_conntrack(&mut self) {
+        self.conntrack.flush();
+    }
+
+    /// Expire idle connections
+    pub fn expire(&mut self, now_ms: u64) -> usize {
+        self.conntrack.expire(now_ms)
+    }
+}
+
+impl Default for Firewall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::endpoint::TcpSegmentBuilder;
+    use crate::net::ipv4::{IpProtocol, Ipv4PacketMut};
+    use crate::net::stack::NetworkStack;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    const HOST: Ipv4Address = Ipv4Address::from_octets(10, 0, 2, 15);
+    const PEER: Ipv4Address = Ipv4Address::from_octets(10, 0, 2, 2);
+
+    /// IPv4 + TCP パケット（チェックサム付き）
+    fn tcp_packet(src: Ipv4Address, sport: u16, dst: Ipv4Address, dport: u16, flags: u8) -> Vec<u8> {
+        let mut buffer = vec![0u8; 64];
+        let len = NetworkStack::build_ipv4(&mut buffer, src, dst, IpProtocol::Tcp, 64, |buf| {
+            let mut segment = TcpSegmentBuilder::new(sport, dport).flags(flags).build();
+            TcpSegmentBuilder::calculate_checksum(&mut segment, *src.as_bytes(), *dst.as_bytes());
+            buf.get_mut(..segment.len())?.copy_from_slice(&segment);
+            Some(segment.len())
+        })
+        .unwrap();
+        buffer.truncate(len);
+        buffer
+    }
+
+    /// IP ID とフラグメントオフセット（8バイト単位）を設定する
+    fn fragment(mut packet: Vec<u8>, id: u16, offset: u16, more: bool) -> Vec<u8> {
+        let mut ip = Ipv4PacketMut::new(&mut packet).unwrap();
+        let header = ip.header_mut();
+        header.set_identification(id);
+        let flags = if more { 0x2000 } else { 0 };
+        header.flags_fragment = (flags | offset).to_be_bytes();
+        header.update_checksum();
+        packet
+    }
+
+    fn filter(fw: &mut Firewall, chain: Chain, packet: &[u8], now: u64) -> Verdict {
+        let meta = PacketMeta::parse(packet, "eth0").unwrap();
+        fw.filter(chain, &meta, now)
+    }
+
+    #[test]
+    fn test_packet_meta_parse() {
+        let packet = tcp_packet(PEER, 40000, HOST, 22, 0x02);
+        let meta = PacketMeta::parse(&packet, "eth0").unwrap();
+        assert_eq!(meta.protocol, PROTO_TCP);
+        assert_eq!((meta.src_port, meta.dst_port), (Some(40000), Some(22)));
+        assert_eq!(meta.tcp_flags, 0x02);
+        assert_eq!(meta.len, 40);
+        assert_eq!(meta.to_string(), "tcp 10.0.2.2:40000 > 10.0.2.15:22 len 40 if eth0");
+    }
+
+    #[test]
+    fn test_stateful_input_policy() {
+        let mut fw = Firewall::new();
+        fw.add_rule(FirewallRule::parse("input state established,related accept").unwrap()).unwrap();
+        let ssh = fw.add_rule(FirewallRule::parse("input tcp dport 22 accept").unwrap()).unwrap();
+        fw.set_policy(Chain::Input, Action::Drop);
+
+        // 外向き接続の応答は ESTABLISHED として通る
+        let syn = tcp_packet(HOST, 50000, PEER, 80, 0x02);
+        assert_eq!(filter(&mut fw, Chain::Output, &syn, 0).action, Action::Accept);
+        let syn_ack = tcp_packet(PEER, 80, HOST, 50000, 0x12);
+        let verdict = filter(&mut fw, Chain::Input, &syn_ack, 1);
+        assert_eq!(verdict.action, Action::Accept);
+        assert_eq!(verdict.state, ConnState::Established);
+
+        // 22番は許可、それ以外の新規接続はポリシーで破棄
+        let verdict = filter(&mut fw, Chain::Input, &tcp_packet(PEER, 40000, HOST, 22, 0x02), 2);
+        assert_eq!((verdict.action, verdict.rule), (Action::Accept, Some(ssh)));
+        let verdict = filter(&mut fw, Chain::Input, &tcp_packet(PEER, 40001, HOST, 23, 0x02), 3);
+        assert_eq!((verdict.action, verdict.rule), (Action::Drop, None));
+
+        let rule = fw.rules().iter().find(|r| r.id == ssh).unwrap();
+        assert_eq!((rule.packets, rule.bytes), (1, 40));
+        let input = fw.chains()[Chain::Input as usize];
+        assert_eq!((input.accepted, input.dropped, input.policy_packets), (2, 1, 1));
+        assert_eq!(fw.conntrack().len(), 2);
+    }
+
+    #[test]
+    fn test_non_first_fragments() {
+        let mut fw = Firewall::new();
+        let ssh = fw.add_rule(FirewallRule::parse("input tcp dport 22 accept").unwrap()).unwrap();
+        let frag = fw
+            .add_rule(FirewallRule::parse("input fragment state new,established accept").unwrap())
+            .unwrap();
+        fw.set_policy(Chain::Input, Action::Drop);
+
+        // 先頭フラグメントはポートで一致する
+        let first = fragment(tcp_packet(PEER, 40000, HOST, 22, 0x02), 7, 0, true);
+        let verdict = filter(&mut fw, Chain::Input, &first, 0);
+        assert_eq!((verdict.action, verdict.rule), (Action::Accept, Some(ssh)));
+
+        // 続きのフラグメントの先頭バイトはポートとして読まない
+        let rest = fragment(tcp_packet(PEER, 40000, HOST, 22, 0x02), 7, 5, false);
+        let meta = PacketMeta::parse(&rest, "eth0").unwrap();
+        assert!(meta.fragment);
+        assert_eq!((meta.src_port, meta.dst_port), (None, None));
+        let verdict = filter(&mut fw, Chain::Input, &rest, 1);
+        assert_eq!((verdict.action, verdict.rule), (Action::Accept, Some(frag)));
+        assert_eq!(verdict.state, ConnState::New);
+
+        // 先頭を見ていないデータグラムのフラグメントは INVALID で破棄
+        let stray = fragment(tcp_packet(PEER, 40001, HOST, 22, 0x02), 8, 5, false);
+        let verdict = filter(&mut fw, Chain::Input, &stray, 2);
+        assert_eq!((verdict.action, verdict.rule), (Action::Drop, None));
+        assert_eq!(verdict.state, ConnState::Invalid);
+        assert_eq!(fw.conntrack().entries()[0].packets, 2);
+    }
+
+    #[test]
+    fn test_rate_limited_rule() {
+        let mut fw = Firewall::new();
+        fw.add_rule(FirewallRule::parse("input tcp dport 22 state new limit 1/s burst 2 accept").unwrap())
+            .unwrap();
+        fw.add_rule(FirewallRule::parse("input tcp dport 22 drop").unwrap()).unwrap();
+
+        let actions: Vec<Action> = (0..3u16)
+            .map(|i| filter(&mut fw, Chain::Input, &tcp_packet(PEER, 40000 + i, HOST, 22, 0x02), 0).action)
+            .collect();
+        assert_eq!(actions, [Action::Accept, Action::Accept, Action::Drop]);
+        // 1秒後には1件だけ再び許可される
+        assert_eq!(filter(&mut fw, Chain::Input, &tcp_packet(PEER, 41000, HOST, 22, 0x02), 1000).action, Action::Accept);
+    }
+
+    #[test]
+    fn test_rule_management() {
+        let mut fw = Firewall::new();
+        let a = fw.add_rule(FirewallRule::new(Chain::Input, Action::Accept)).unwrap();
+        let b = fw.add_rule(FirewallRule::new(Chain::Output, Action::Drop)).unwrap();
+        let c = fw.insert_rule(0, FirewallRule::new(Chain::Input, Action::Drop)).unwrap();
+        let ids: Vec<u32> = fw.rules().iter().map(|r| r.id).collect();
+        assert_eq!(ids, [c, a, b]);
+
+        assert_eq!(fw.delete_rule(a).unwrap().id, a);
+        assert_eq!(fw.delete_rule(a).unwrap_err(), FirewallError::RuleNotFound(a));
+        assert_eq!(fw.flush(Some(Chain::Input)), 1);
+        assert_eq!(fw.rules().len(), 1);
+        assert_eq!(fw.flush(None), 1);
+        assert!(fw.rules().is_empty());
+    }
+}
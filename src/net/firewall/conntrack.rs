@@ -0,0 +1,661 @@
+//! # Connection Tracking - コネクション追跡
+//!
+//! TCP/UDP/ICMP echo のフローを (プロトコル, 送信元, 送信元ポート,
+//! 宛先, 宛先ポート) で追跡し、パケットを NEW / ESTABLISHED /
+//! RELATED / INVALID に分類する。ICMP echo では識別子をポートとして扱う。
+//!
+//! エントリは最初のパケットが受理された時点で作成され（`commit`）、
+//! 応答方向のパケットは反転したキーで同じエントリに一致する。
+//! ICMP エラー（到達不能・時間超過）は埋め込まれた元ヘッダから
+//! 既存フローを引き、RELATED とする。
+//!
+//! 先頭以外の IP フラグメントはポートを持たないので、受理された先頭フラグメントの
+//! (プロトコル, 送信元, 宛先, IP ID) からフローを覚えておき、同じデータグラムの
+//! 残りをそのフローの状態に分類する。先頭を見ていないフラグメントは INVALID。
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::PacketMeta;
+use super::rule::{PROTO_ICMP, PROTO_TCP, PROTO_UDP};
+use crate::net::ipv4::Ipv4Address;
+
+/// Maximum tracked connections
+pub const MAX_CONNECTIONS: usize = 4096;
+
+/// TCP flags
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+/// ICMP types
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_DEST_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+
+/// Timeouts (milliseconds)
+const TCP_SYN_TIMEOUT_MS: u64 = 120_000;
+const TCP_ESTABLISHED_TIMEOUT_MS: u64 = 3_600_000;
+const TCP_CLOSING_TIMEOUT_MS: u64 = 60_000;
+const TCP_CLOSED_TIMEOUT_MS: u64 = 10_000;
+const UDP_TIMEOUT_MS: u64 = 30_000;
+const UDP_STREAM_TIMEOUT_MS: u64 = 180_000;
+const ICMP_TIMEOUT_MS: u64 = 30_000;
+const FRAGMENT_TIMEOUT_MS: u64 = 30_000;
+
+/// Maximum fragmented datagrams remembered
+pub const MAX_FRAGMENTS: usize = 256;
+
+/// Fragmented datagram: (protocol, source, destination, IP identification)
+type FragmentKey = (u8, [u8; 4], [u8; 4], u16);
+
+/// Flow of a fragmented datagram, learned from its first fragment
+#[derive(Debug, Clone, Copy)]
+struct FragmentFlow {
+    /// Entry key and whether the datagram is in the reply direction
+    flow: (ConnKey, bool),
+    /// Last fragment time
+    last_seen_ms: u64,
+}
+
+impl FragmentFlow {
+    fn expired(&self, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.last_seen_ms) > FRAGMENT_TIMEOUT_MS
+    }
+}
+
+/// Datagram a fragment belongs to
+fn fragment_key(meta: &PacketMeta<'_>) -> FragmentKey {
+    (meta.protocol, *meta.src.as_bytes(), *meta.dst.as_bytes(), meta.ip_id)
+}
+
+/// Connection tracking state of a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnState {
+    /// First packets of a flow (no reply seen yet)
+    New,
+    /// Part of a flow that has seen traffic in both directions
+    Established,
+    /// ICMP error about a tracked flow
+    Related,
+    /// Not part of any valid flow
+    Invalid,
+}
+
+impl ConnState {
+    /// All states
+    pub const ALL: [ConnState; 4] = [
+        ConnState::New,
+        ConnState::Established,
+        ConnState::Related,
+        ConnState::Invalid,
+    ];
+
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnState::New => "new",
+            ConnState::Established => "established",
+            ConnState::Related => "related",
+            ConnState::Invalid => "invalid",
+        }
+    }
+
+    /// Parse a state name
+    pub fn from_name(name: &str) -> Option<ConnState> {
+        match name {
+            "new" | "NEW" => Some(ConnState::New),
+            "established" | "ESTABLISHED" => Some(ConnState::Established),
+            "related" | "RELATED" => Some(ConnState::Related),
+            "invalid" | "INVALID" => Some(ConnState::Invalid),
+            _ => None,
+        }
+    }
+}
+
+/// Simplified TCP connection state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpTrackState {
+    /// SYN seen from the originator
+    SynSent,
+    /// SYN+ACK seen from the responder
+    SynReceived,
+    /// Handshake complete
+    Established,
+    /// FIN seen from one side
+    FinWait,
+    /// FIN seen from both sides
+    TimeWait,
+    /// RST seen
+    Closed,
+}
+
+impl TcpTrackState {
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            TcpTrackState::SynSent => "SYN_SENT",
+            TcpTrackState::SynReceived => "SYN_RECV",
+            TcpTrackState::Established => "ESTABLISHED",
+            TcpTrackState::FinWait => "FIN_WAIT",
+            TcpTrackState::TimeWait => "TIME_WAIT",
+            TcpTrackState::Closed => "CLOSE",
+        }
+    }
+
+    /// Idle timeout for the state
+    fn timeout_ms(&self) -> u64 {
+        match self {
+            TcpTrackState::SynSent | TcpTrackState::SynReceived => TCP_SYN_TIMEOUT_MS,
+            TcpTrackState::Established => TCP_ESTABLISHED_TIMEOUT_MS,
+            TcpTrackState::FinWait | TcpTrackState::TimeWait => TCP_CLOSING_TIMEOUT_MS,
+            TcpTrackState::Closed => TCP_CLOSED_TIMEOUT_MS,
+        }
+    }
+}
+
+/// Flow key in one direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ConnKey {
+    /// IP protocol
+    pub protocol: u8,
+    /// Source address
+    pub src: [u8; 4],
+    /// Source port (ICMP: identifier)
+    pub src_port: u16,
+    /// Destination address
+    pub dst: [u8; 4],
+    /// Destination port (ICMP: identifier)
+    pub dst_port: u16,
+}
+
+impl ConnKey {
+    /// Build a key from addresses and ports
+    pub fn new(protocol: u8, src: Ipv4Address, src_port: u16, dst: Ipv4Address, dst_port: u16) -> Self {
+        ConnKey {
+            protocol,
+            src: *src.as_bytes(),
+            src_port,
+            dst: *dst.as_bytes(),
+            dst_port,
+        }
+    }
+
+    /// Key of a packet (None for untracked protocols and ICMP errors)
+    pub fn from_meta(meta: &PacketMeta<'_>) -> Option<ConnKey> {
+        match meta.protocol {
+            PROTO_TCP | PROTO_UDP => Some(ConnKey::new(
+                meta.protocol,
+                meta.src,
+                meta.src_port?,
+                meta.dst,
+                meta.dst_port?,
+            )),
+            PROTO_ICMP => {
+                let id = meta.icmp_id?;
+                Some(ConnKey::new(PROTO_ICMP, meta.src, id, meta.dst, id))
+            }
+            _ => None,
+        }
+    }
+
+    /// Key of the reply direction
+    pub fn reversed(&self) -> ConnKey {
+        ConnKey {
+            protocol: self.protocol,
+            src: self.dst,
+            src_port: self.dst_port,
+            dst: self.src,
+            dst_port: self.src_port,
+        }
+    }
+}
+
+impl fmt::Display for ConnKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (src, dst) = (Ipv4Address::new(self.src), Ipv4Address::new(self.dst));
+        if self.protocol == PROTO_ICMP {
+            write!(f, "{} -> {} id={}", src, dst, self.src_port)
+        } else {
+            write!(f, "{}:{} -> {}:{}", src, self.src_port, dst, self.dst_port)
+        }
+    }
+}
+
+/// Tracked connection
+#[derive(Debug, Clone, Copy)]
+pub struct ConnEntry {
+    /// Key in the originating direction
+    pub original: ConnKey,
+    /// TCP state (TCP only)
+    pub tcp_state: Option<TcpTrackState>,
+    /// Traffic seen in the reply direction
+    pub seen_reply: bool,
+    /// FIN seen from originator / responder
+    fin_original: bool,
+    fin_reply: bool,
+    /// Creation time
+    pub created_ms: u64,
+    /// Last packet time
+    pub last_seen_ms: u64,
+    /// Packets in both directions
+    pub packets: u64,
+    /// Bytes in both directions
+    pub bytes: u64,
+}
+
+impl ConnEntry {
+    /// Idle timeout for the entry
+    pub fn timeout_ms(&self) -> u64 {
+        match (self.original.protocol, self.tcp_state) {
+            (PROTO_TCP, Some(state)) => state.timeout_ms(),
+            (PROTO_UDP, _) if self.seen_reply => UDP_STREAM_TIMEOUT_MS,
+            (PROTO_UDP, _) => UDP_TIMEOUT_MS,
+            _ => ICMP_TIMEOUT_MS,
+        }
+    }
+
+    /// Check if the entry has timed out
+    pub fn expired(&self, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.last_seen_ms) > self.timeout_ms()
+    }
+
+    /// Update the TCP state machine with a packet's flags
+    fn update_tcp(&mut self, flags: u8, reply: bool) {
+        let Some(state) = self.tcp_state else {
+            return;
+        };
+        let next = if flags & TCP_RST != 0 {
+            TcpTrackState::Closed
+        } else if flags & TCP_FIN != 0 {
+            if reply {
+                self.fin_reply = true;
+            } else {
+                self.fin_original = true;
+            }
+            if self.fin_original && self.fin_reply {
+                TcpTrackState::TimeWait
+            } else {
+                TcpTrackState::FinWait
+            }
+        } else {
+            match state {
+                TcpTrackState::SynSent if reply && flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK => {
+                    TcpTrackState::SynReceived
+                }
+                TcpTrackState::SynReceived if !reply && flags & TCP_ACK != 0 => {
+                    TcpTrackState::Established
+                }
+                other => other,
+            }
+        };
+        self.tcp_state = Some(next);
+    }
+}
+
+/// Result of classifying a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Classification {
+    /// Conntrack state
+    pub state: ConnState,
+    /// Matched entry and whether the packet is in the reply direction
+    pub entry: Option<(ConnKey, bool)>,
+}
+
+/// Connection tracking table
+#[derive(Debug, Default)]
+pub struct ConnTrack {
+    /// Entries keyed by the originating direction
+    entries: BTreeMap<ConnKey, ConnEntry>,
+    /// Fragmented datagrams whose first fragment was accepted
+    fragments: BTreeMap<FragmentKey, FragmentFlow>,
+    /// Entries refused because the table was full
+    pub dropped: u64,
+}
+
+impl ConnTrack {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        ConnTrack {
+            entries: BTreeMap::new(),
+            fragments: BTreeMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Classify a packet without modifying the table
+    pub fn classify(&self, meta: &PacketMeta<'_>, now_ms: u64) -> Classification {
+        let invalid = Classification {
+            state: ConnState::Invalid,
+            entry: None,
+        };
+
+        // 先頭以外のフラグメントは先頭フラグメントのフローに従う
+        if meta.fragment {
+            let Some((original, reply)) = self.fragment_flow(meta, now_ms) else {
+                let tracked = matches!(meta.protocol, PROTO_TCP | PROTO_UDP | PROTO_ICMP);
+                return Classification {
+                    state: if tracked { ConnState::Invalid } else { ConnState::New },
+                    entry: None,
+                };
+            };
+            let state = if reply || self.entries[&original].seen_reply {
+                ConnState::Established
+            } else {
+                ConnState::New
+            };
+            return Classification {
+                state,
+                entry: Some((original, reply)),
+            };
+        }
+
+        // ICMP エラーは埋め込まれた元パケットのフローに関連付ける
+        if meta.protocol == PROTO_ICMP
+            && matches!(meta.icmp_type, Some(ICMP_DEST_UNREACHABLE) | Some(ICMP_TIME_EXCEEDED))
+        {
+            let related = meta
+                .icmp_inner
+                .and_then(|inner| self.lookup(&inner, now_ms))
+                .is_some();
+            return Classification {
+                state: if related { ConnState::Related } else { ConnState::Invalid },
+                entry: None,
+            };
+        }
+
+        let Some(key) = ConnKey::from_meta(meta) else {
+            // 追跡対象外のプロトコルは常に NEW として扱う
+            return Classification {
+                state: if meta.protocol == PROTO_ICMP { ConnState::Invalid } else { ConnState::New },
+                entry: None,
+            };
+        };
+
+        if let Some((original, reply)) = self.lookup(&key, now_ms) {
+            let entry = &self.entries[&original];
+            if entry.tcp_state == Some(TcpTrackState::Closed) && meta.tcp_flags & TCP_SYN != 0 {
+                // 閉じた接続の再利用は新規接続として扱う
+                return Classification {
+                    state: ConnState::New,
+                    entry: None,
+                };
+            }
+            let state = if reply || entry.seen_reply {
+                ConnState::Established
+            } else {
+                ConnState::New
+            };
+            return Classification {
+                state,
+                entry: Some((original, reply)),
+            };
+        }
+
+        // 新規フローの開始として妥当か
+        let starts_flow = match meta.protocol {
+            PROTO_TCP => meta.tcp_flags & (TCP_SYN | TCP_ACK | TCP_RST) == TCP_SYN,
+            PROTO_ICMP => meta.icmp_type == Some(ICMP_ECHO_REQUEST),
+            _ => true,
+        };
+        if starts_flow {
+            Classification {
+                state: ConnState::New,
+                entry: None,
+            }
+        } else {
+            invalid
+        }
+    }
+
+    /// Record an accepted packet (creates or updates its entry)
+    pub fn commit(&mut self, meta: &PacketMeta<'_>, class: &Classification, now_ms: u64) {
+        let flow = match class.entry {
+            Some((original, reply)) => {
+                let Some(entry) = self.entries.get_mut(&original) else {
+                    return;
+                };
+                entry.seen_reply |= reply;
+                entry.last_seen_ms = now_ms;
+                entry.packets += 1;
+                entry.bytes += meta.len as u64;
+                if meta.protocol == PROTO_TCP && !meta.fragment {
+                    entry.update_tcp(meta.tcp_flags, reply);
+                }
+                (original, reply)
+            }
+            None if class.state == ConnState::New => {
+                let Some(key) = ConnKey::from_meta(meta) else {
+                    return;
+                };
+                if self.entries.len() >= MAX_CONNECTIONS {
+                    self.expire(now_ms);
+                    if self.entries.len() >= MAX_CONNECTIONS {
+                        self.dropped += 1;
+                        return;
+                    }
+                }
+                self.entries.insert(
+                    key,
+                    ConnEntry {
+                        original: key,
+                        tcp_state: (meta.protocol == PROTO_TCP).then_some(TcpTrackState::SynSent),
+                        seen_reply: false,
+                        fin_original: false,
+                        fin_reply: false,
+                        created_ms: now_ms,
+                        last_seen_ms: now_ms,
+                        packets: 1,
+                        bytes: meta.len as u64,
+                    },
+                );
+                (key, false)
+            }
+            None => return,
+        };
+        if meta.fragment || meta.more_fragments {
+            self.track_fragment(meta, flow, now_ms);
+        }
+    }
+
+    /// Remember (or refresh) the flow of a fragmented datagram
+    fn track_fragment(&mut self, meta: &PacketMeta<'_>, flow: (ConnKey, bool), now_ms: u64) {
+        let key = fragment_key(meta);
+        if !self.fragments.contains_key(&key) && self.fragments.len() >= MAX_FRAGMENTS {
+            self.fragments.retain(|_, f| !f.expired(now_ms));
+            if self.fragments.len() >= MAX_FRAGMENTS {
+                return;
+            }
+        }
+        self.fragments.insert(
+            key,
+            FragmentFlow {
+                flow,
+                last_seen_ms: now_ms,
+            },
+        );
+    }
+
+    /// Flow of a non-first fragment whose first fragment was accepted
+    fn fragment_flow(&self, meta: &PacketMeta<'_>, now_ms: u64) -> Option<(ConnKey, bool)> {
+        let (original, reply) = self
+            .fragments
+            .get(&fragment_key(meta))
+            .filter(|f| !f.expired(now_ms))?
+            .flow;
+        let live = self.entries.get(&original).is_some_and(|e| !e.expired(now_ms));
+        live.then_some((original, reply))
+    }
+
+    /// Find a live entry for a key in either direction
+    ///
+    /// 戻り値: (元方向のキー, 応答方向なら true)
+    fn lookup(&self, key: &ConnKey, now_ms: u64) -> Option<(ConnKey, bool)> {
+        let live = |k: &ConnKey| self.entries.get(k).filter(|e| !e.expired(now_ms)).is_some();
+        if live(key) {
+            return Some((*key, false));
+        }
+        let reversed = key.reversed();
+        live(&reversed).then_some((reversed, true))
+    }
+
+    /// Remove timed-out entries, returning how many were removed
+    pub fn expire(&mut self, now_ms: u64) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, e| !e.expired(now_ms));
+        self.fragments.retain(|_, f| !f.expired(now_ms));
+        before - self.entries.len()
+    }
+
+    /// Snapshot of all entries
+    pub fn entries(&self) -> Vec<ConnEntry> {
+        self.entries.values().copied().collect()
+    }
+
+    /// Number of tracked connections
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the table is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn flush(&mut self) {
+        self.entries.clear();
+        self.fragments.clear();
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: Ipv4Address = Ipv4Address::from_octets(10, 0, 0, 2);
+    const SERVER: Ipv4Address = Ipv4Address::from_octets(10, 0, 0, 1);
+
+    fn tcp(src: Ipv4Address, sport: u16, dst: Ipv4Address, dport: u16, flags: u8) -> PacketMeta<'static> {
+        PacketMeta {
+            protocol: PROTO_TCP,
+            src,
+            dst,
+            src_port: Some(sport),
+            dst_port: Some(dport),
+            tcp_flags: flags,
+            len: 40,
+            ..PacketMeta::default()
+        }
+    }
+
+    /// 分類して受理として記録
+    fn pass(ct: &mut ConnTrack, meta: &PacketMeta<'_>, now: u64) -> ConnState {
+        let class = ct.classify(meta, now);
+        ct.commit(meta, &class, now);
+        class.state
+    }
+
+    #[test]
+    fn test_tcp_handshake_states() {
+        let mut ct = ConnTrack::new();
+        let syn = tcp(CLIENT, 40000, SERVER, 80, TCP_SYN);
+        let syn_ack = tcp(SERVER, 80, CLIENT, 40000, TCP_SYN | TCP_ACK);
+        let ack = tcp(CLIENT, 40000, SERVER, 80, TCP_ACK);
+
+        assert_eq!(pass(&mut ct, &syn, 0), ConnState::New);
+        assert_eq!(pass(&mut ct, &syn_ack, 1), ConnState::Established);
+        assert_eq!(pass(&mut ct, &ack, 2), ConnState::Established);
+        let entry = ct.entries()[0];
+        assert_eq!(entry.tcp_state, Some(TcpTrackState::Established));
+        assert_eq!(entry.packets, 3);
+
+        // ハンドシェイクなしの ACK は INVALID
+        let stray = tcp(CLIENT, 40001, SERVER, 80, TCP_ACK);
+        assert_eq!(ct.classify(&stray, 3).state, ConnState::Invalid);
+
+        // FIN の往復で TIME_WAIT
+        pass(&mut ct, &tcp(CLIENT, 40000, SERVER, 80, TCP_FIN | TCP_ACK), 4);
+        pass(&mut ct, &tcp(SERVER, 80, CLIENT, 40000, TCP_FIN | TCP_ACK), 5);
+        assert_eq!(ct.entries()[0].tcp_state, Some(TcpTrackState::TimeWait));
+    }
+
+    #[test]
+    fn test_udp_and_expiry() {
+        let mut ct = ConnTrack::new();
+        let query = PacketMeta {
+            protocol: PROTO_UDP,
+            src: CLIENT,
+            dst: SERVER,
+            src_port: Some(5353),
+            dst_port: Some(53),
+            len: 60,
+            ..PacketMeta::default()
+        };
+        let mut answer = query;
+        answer.src = SERVER;
+        answer.dst = CLIENT;
+        answer.src_port = Some(53);
+        answer.dst_port = Some(5353);
+
+        assert_eq!(pass(&mut ct, &query, 0), ConnState::New);
+        assert_eq!(pass(&mut ct, &query, 10), ConnState::New);
+        assert_eq!(pass(&mut ct, &answer, 20), ConnState::Established);
+
+        assert_eq!(ct.expire(20 + UDP_STREAM_TIMEOUT_MS), 0);
+        assert_eq!(ct.expire(21 + UDP_STREAM_TIMEOUT_MS), 1);
+        assert!(ct.is_empty());
+    }
+
+    #[test]
+    fn test_icmp_echo_and_related_error() {
+        let mut ct = ConnTrack::new();
+        let request = PacketMeta {
+            protocol: PROTO_ICMP,
+            src: CLIENT,
+            dst: SERVER,
+            icmp_type: Some(ICMP_ECHO_REQUEST),
+            icmp_id: Some(7),
+            len: 84,
+            ..PacketMeta::default()
+        };
+        let mut reply = request;
+        reply.src = SERVER;
+        reply.dst = CLIENT;
+        reply.icmp_type = Some(ICMP_ECHO_REPLY);
+
+        // 要求なしの応答は INVALID
+        assert_eq!(ct.classify(&reply, 0).state, ConnState::Invalid);
+        assert_eq!(pass(&mut ct, &request, 0), ConnState::New);
+        assert_eq!(pass(&mut ct, &reply, 1), ConnState::Established);
+
+        // 追跡中の UDP フローに対する到達不能は RELATED
+        let udp = PacketMeta {
+            protocol: PROTO_UDP,
+            src: CLIENT,
+            dst: SERVER,
+            src_port: Some(1000),
+            dst_port: Some(2000),
+            ..PacketMeta::default()
+        };
+        pass(&mut ct, &udp, 2);
+        let mut unreachable = PacketMeta {
+            protocol: PROTO_ICMP,
+            src: SERVER,
+            dst: CLIENT,
+            icmp_type: Some(ICMP_DEST_UNREACHABLE),
+            icmp_inner: ConnKey::from_meta(&udp),
+            ..PacketMeta::default()
+        };
+        assert_eq!(ct.classify(&unreachable, 3).state, ConnState::Related);
+        unreachable.icmp_inner = Some(ConnKey::new(PROTO_UDP, CLIENT, 1, SERVER, 2));
+        assert_eq!(ct.classify(&unreachable, 3).state, ConnState::Invalid);
+    }
+}
@@ -0,0 +1,721 @@
+//! # Firewall Rules - ルールとマッチ条件
+//!
+//! ルールはシェルから文字列で与えられる（iptables 風の簡易構文）:
+//!
+//! ```text
+//! <chain> [tcp|udp|icmp|proto N] [src CIDR] [dst CIDR] [sport P[-Q]] [dport P[-Q]]
+//!         [fragment] [iface NAME] [state new,established,related,invalid]
+//!         [limit N/s|N/m [burst B]] <accept|drop> [log]
+//! ```
+//!
+//! 例: `input tcp dport 22 state new limit 5/m burst 3 accept`
+//!
+//! 先頭以外の IP フラグメントにはトランスポートヘッダがないので、ポート条件には
+//! 一致しない。`fragment` はそうしたフラグメントだけに一致する。
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use super::conntrack::ConnState;
+use super::PacketMeta;
+use crate::net::interface::prefix_to_mask;
+use crate::net::ipv4::Ipv4Address;
+
+/// IP protocol numbers understood by the firewall
+pub const PROTO_ICMP: u8 = 1;
+pub const PROTO_TCP: u8 = 6;
+pub const PROTO_UDP: u8 = 17;
+
+/// Default burst for rate limits
+pub const DEFAULT_BURST: u32 = 5;
+
+/// Protocol number to name
+pub fn protocol_name(protocol: u8) -> Option<&'static str> {
+    match protocol {
+        PROTO_ICMP => Some("icmp"),
+        PROTO_TCP => Some("tcp"),
+        PROTO_UDP => Some("udp"),
+        _ => None,
+    }
+}
+
+/// Netfilter-style hook point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Chain {
+    /// Packets addressed to this host
+    Input,
+    /// Packets originating from this host
+    Output,
+    /// Packets routed through this host
+    Forward,
+}
+
+impl Chain {
+    /// All chains
+    pub const ALL: [Chain; 3] = [Chain::Input, Chain::Output, Chain::Forward];
+
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chain::Input => "input",
+            Chain::Output => "output",
+            Chain::Forward => "forward",
+        }
+    }
+
+    /// Parse a chain name
+    pub fn from_name(name: &str) -> Option<Chain> {
+        match name {
+            "input" | "INPUT" => Some(Chain::Input),
+            "output" | "OUTPUT" => Some(Chain::Output),
+            "forward" | "FORWARD" => Some(Chain::Forward),
+            _ => None,
+        }
+    }
+}
+
+/// Rule verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Let the packet through
+    Accept,
+    /// Silently discard the packet
+    Drop,
+}
+
+impl Action {
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Accept => "accept",
+            Action::Drop => "drop",
+        }
+    }
+
+    /// Parse an action name
+    pub fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "accept" | "ACCEPT" | "allow" => Some(Action::Accept),
+            "drop" | "DROP" | "deny" => Some(Action::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// Network prefix (address/prefix_len)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    /// Network address (masked)
+    pub network: Ipv4Address,
+    /// Prefix length (0-32)
+    pub prefix_len: u8,
+}
+
+impl Cidr {
+    /// Create a prefix, masking host bits
+    pub fn new(address: Ipv4Address, prefix_len: u8) -> Self {
+        let prefix_len = prefix_len.min(32);
+        Cidr {
+            network: address.apply_mask(prefix_to_mask(prefix_len)),
+            prefix_len,
+        }
+    }
+
+    /// Check if an address is inside the prefix
+    pub fn contains(&self, addr: &Ipv4Address) -> bool {
+        addr.apply_mask(prefix_to_mask(self.prefix_len)) == self.network
+    }
+
+    /// Parse `a.b.c.d[/n]`
+    pub fn parse(s: &str) -> Option<Cidr> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, prefix.parse::<u8>().ok().filter(|p| *p <= 32)?),
+            None => (s, 32),
+        };
+        let mut octets = [0u8; 4];
+        let mut parts = addr.split('.');
+        for octet in octets.iter_mut() {
+            *octet = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Cidr::new(Ipv4Address::new(octets), prefix_len))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix_len == 32 {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix_len)
+        }
+    }
+}
+
+/// Inclusive port range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    /// First port
+    pub start: u16,
+    /// Last port
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Single port
+    pub const fn single(port: u16) -> Self {
+        PortRange { start: port, end: port }
+    }
+
+    /// Check if a port is in the range
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+
+    /// Parse `P` or `P-Q`
+    pub fn parse(s: &str) -> Option<PortRange> {
+        match s.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+                (start <= end).then_some(PortRange { start, end })
+            }
+            None => s.parse().ok().map(PortRange::single),
+        }
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// Set of connection tracking states
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StateMask(u8);
+
+impl StateMask {
+    /// Empty set
+    pub const NONE: StateMask = StateMask(0);
+
+    /// Mask bit for a state
+    const fn bit(state: ConnState) -> u8 {
+        match state {
+            ConnState::New => 0b0001,
+            ConnState::Established => 0b0010,
+            ConnState::Related => 0b0100,
+            ConnState::Invalid => 0b1000,
+        }
+    }
+
+    /// Add a state to the set
+    pub const fn with(self, state: ConnState) -> Self {
+        StateMask(self.0 | Self::bit(state))
+    }
+
+    /// Check if the set contains a state
+    pub const fn contains(&self, state: ConnState) -> bool {
+        self.0 & Self::bit(state) != 0
+    }
+
+    /// Parse a comma-separated list (`new,established`)
+    pub fn parse(s: &str) -> Option<StateMask> {
+        s.split(',')
+            .try_fold(StateMask::NONE, |mask, name| Some(mask.with(ConnState::from_name(name)?)))
+            .filter(|mask| *mask != StateMask::NONE)
+    }
+}
+
+impl fmt::Display for StateMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for state in ConnState::ALL {
+            if self.contains(state) {
+                if !first {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}", state.name())?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Token-bucket rate limit
+///
+/// 一致したパケットがトークンを1つ消費する。トークンが尽きたら
+/// ルールは一致しなかったものとして次のルールへ進む（iptables の limit と同じ）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Tokens added per period
+    pub rate: u32,
+    /// Refill period in milliseconds (1000 = per second)
+    pub period_ms: u64,
+    /// Bucket size
+    pub burst: u32,
+    /// Current tokens (x1000 for sub-token refill)
+    tokens_milli: u64,
+    /// Last refill time
+    last_ms: Option<u64>,
+}
+
+impl RateLimit {
+    /// Create a full bucket
+    pub fn new(rate: u32, period_ms: u64, burst: u32) -> Self {
+        let burst = burst.max(1);
+        RateLimit {
+            rate,
+            period_ms: period_ms.max(1),
+            burst,
+            tokens_milli: burst as u64 * 1000,
+            last_ms: None,
+        }
+    }
+
+    /// Refill and try to take a token
+    pub fn allow(&mut self, now_ms: u64) -> bool {
+        let capacity = self.burst as u64 * 1000;
+        if let Some(last) = self.last_ms {
+            let elapsed = now_ms.saturating_sub(last);
+            let refill = elapsed * self.rate as u64 * 1000 / self.period_ms;
+            self.tokens_milli = (self.tokens_milli + refill).min(capacity);
+        }
+        self.last_ms = Some(now_ms);
+
+        if self.tokens_milli >= 1000 {
+            self.tokens_milli -= 1000;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse `N/s`, `N/m` (or `/sec`, `/min`, `/h`)
+    fn parse(s: &str, burst: u32) -> Option<RateLimit> {
+        let (rate, unit) = s.split_once('/')?;
+        let period_ms = match unit {
+            "s" | "sec" | "second" => 1_000,
+            "m" | "min" | "minute" => 60_000,
+            "h" | "hour" => 3_600_000,
+            _ => return None,
+        };
+        let rate = rate.parse::<u32>().ok().filter(|r| *r > 0)?;
+        Some(RateLimit::new(rate, period_ms, burst))
+    }
+
+    /// Unit suffix for display
+    fn unit(&self) -> &'static str {
+        match self.period_ms {
+            1_000 => "s",
+            60_000 => "m",
+            3_600_000 => "h",
+            _ => "?",
+        }
+    }
+}
+
+/// Match conditions (all present conditions must hold)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleMatch {
+    /// IP protocol number
+    pub protocol: Option<u8>,
+    /// Source prefix
+    pub src: Option<Cidr>,
+    /// Destination prefix
+    pub dst: Option<Cidr>,
+    /// Source port range (TCP/UDP)
+    pub src_port: Option<PortRange>,
+    /// Destination port range (TCP/UDP)
+    pub dst_port: Option<PortRange>,
+    /// Non-first IPv4 fragments only
+    pub fragment: bool,
+    /// Interface name (input: ingress, output: egress)
+    pub interface: Option<String>,
+    /// Connection tracking states
+    pub state: Option<StateMask>,
+}
+
+impl RuleMatch {
+    /// Check the stateless and conntrack conditions
+    pub fn matches(&self, meta: &PacketMeta<'_>, state: ConnState) -> bool {
+        if self.protocol.is_some_and(|p| p != meta.protocol) {
+            return false;
+        }
+        if self.src.is_some_and(|c| !c.contains(&meta.src)) {
+            return false;
+        }
+        if self.dst.is_some_and(|c| !c.contains(&meta.dst)) {
+            return false;
+        }
+        if let Some(range) = self.src_port
+            && !meta.src_port.is_some_and(|p| range.contains(p))
+        {
+            return false;
+        }
+        if let Some(range) = self.dst_port
+            && !meta.dst_port.is_some_and(|p| range.contains(p))
+        {
+            return false;
+        }
+        if self.fragment && !meta.fragment {
+            return false;
+        }
+        if let Some(ref name) = self.interface
+            && name != meta.interface
+        {
+            return false;
+        }
+        self.state.is_none_or(|mask| mask.contains(state))
+    }
+}
+
+/// Rule parse errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    /// First word is not a chain name
+    UnknownChain(String),
+    /// No accept/drop given
+    MissingAction,
+    /// Keyword without its value
+    MissingValue(&'static str),
+    /// Value could not be parsed
+    InvalidValue {
+        keyword: &'static str,
+        value: String,
+    },
+    /// Port match without tcp/udp
+    PortWithoutProtocol,
+    /// Port match combined with fragment
+    PortWithFragment,
+    /// Unrecognised word
+    UnexpectedToken(String),
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleParseError::UnknownChain(chain) => {
+                write!(f, "Unknown chain '{}' (expected input, output or forward)", chain)
+            }
+            RuleParseError::MissingAction => write!(f, "Missing action (accept or drop)"),
+            RuleParseError::MissingValue(keyword) => write!(f, "'{}' requires a value", keyword),
+            RuleParseError::InvalidValue { keyword, value } => {
+                write!(f, "Invalid {} '{}'", keyword, value)
+            }
+            RuleParseError::PortWithoutProtocol => {
+                write!(f, "Port matches require tcp or udp")
+            }
+            RuleParseError::PortWithFragment => {
+                write!(f, "Port matches cannot apply to fragments")
+            }
+            RuleParseError::UnexpectedToken(token) => write!(f, "Unexpected '{}'", token),
+        }
+    }
+}
+
+/// Firewall rule with counters
+#[derive(Debug, Clone)]
+pub struct FirewallRule {
+    /// Rule ID (assigned by the firewall)
+    pub id: u32,
+    /// Chain the rule belongs to
+    pub chain: Chain,
+    /// Match conditions
+    pub matches: RuleMatch,
+    /// Rate limit (None = unlimited)
+    pub limit: Option<RateLimit>,
+    /// Verdict
+    pub action: Action,
+    /// Log matching packets
+    pub log: bool,
+    /// Packets matched
+    pub packets: u64,
+    /// Bytes matched
+    pub bytes: u64,
+}
+
+impl FirewallRule {
+    /// Create an unconditional rule
+    pub fn new(chain: Chain, action: Action) -> Self {
+        FirewallRule {
+            id: 0,
+            chain,
+            matches: RuleMatch::default(),
+            limit: None,
+            action,
+            log: false,
+            packets: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Set match conditions
+    pub fn with_match(mut self, matches: RuleMatch) -> Self {
+        self.matches = matches;
+        self
+    }
+
+    /// Set a rate limit
+    pub fn with_limit(mut self, limit: RateLimit) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Enable logging
+    pub fn with_log(mut self) -> Self {
+        self.log = true;
+        self
+    }
+
+    /// Evaluate the rule, updating counters and the rate limiter on a match
+    pub fn evaluate(&mut self, meta: &PacketMeta<'_>, state: ConnState, now_ms: u64) -> bool {
+        if !self.matches.matches(meta, state) {
+            return false;
+        }
+        if let Some(ref mut limit) = self.limit
+            && !limit.allow(now_ms)
+        {
+            return false;
+        }
+        self.packets += 1;
+        self.bytes += meta.len as u64;
+        true
+    }
+
+    /// Reset counters
+    pub fn reset_counters(&mut self) {
+        self.packets = 0;
+        self.bytes = 0;
+    }
+
+    /// Parse a rule specification
+    pub fn parse(spec: &str) -> Result<FirewallRule, RuleParseError> {
+        let mut tokens = spec.split_whitespace();
+        let chain_name = tokens.next().ok_or(RuleParseError::MissingAction)?;
+        let chain = Chain::from_name(chain_name)
+            .ok_or_else(|| RuleParseError::UnknownChain(chain_name.to_string()))?;
+
+        let mut matches = RuleMatch::default();
+        let mut limit: Option<(RateLimit, &str)> = None;
+        let mut action = None;
+        let mut log = false;
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "tcp" => matches.protocol = Some(PROTO_TCP),
+                "udp" => matches.protocol = Some(PROTO_UDP),
+                "icmp" => matches.protocol = Some(PROTO_ICMP),
+                "proto" => {
+                    let value = value(&mut tokens, "proto")?;
+                    matches.protocol = Some(match value {
+                        "tcp" => PROTO_TCP,
+                        "udp" => PROTO_UDP,
+                        "icmp" => PROTO_ICMP,
+                        other => other.parse().map_err(|_| invalid("proto", other))?,
+                    });
+                }
+                "src" | "dst" => {
+                    let keyword = if token == "src" { "src" } else { "dst" };
+                    let value = value(&mut tokens, keyword)?;
+                    let cidr = Cidr::parse(value).ok_or_else(|| invalid(keyword, value))?;
+                    if keyword == "src" {
+                        matches.src = Some(cidr);
+                    } else {
+                        matches.dst = Some(cidr);
+                    }
+                }
+                "sport" | "dport" => {
+                    let keyword = if token == "sport" { "sport" } else { "dport" };
+                    let value = value(&mut tokens, keyword)?;
+                    let range = PortRange::parse(value).ok_or_else(|| invalid(keyword, value))?;
+                    if keyword == "sport" {
+                        matches.src_port = Some(range);
+                    } else {
+                        matches.dst_port = Some(range);
+                    }
+                }
+                "fragment" => matches.fragment = true,
+                "iface" => matches.interface = Some(value(&mut tokens, "iface")?.to_string()),
+                "state" => {
+                    let value = value(&mut tokens, "state")?;
+                    matches.state = Some(StateMask::parse(value).ok_or_else(|| invalid("state", value))?);
+                }
+                "limit" => {
+                    let value = value(&mut tokens, "limit")?;
+                    let parsed =
+                        RateLimit::parse(value, DEFAULT_BURST).ok_or_else(|| invalid("limit", value))?;
+                    limit = Some((parsed, value));
+                }
+                "burst" => {
+                    let value = value(&mut tokens, "burst")?;
+                    let burst = value.parse::<u32>().ok().filter(|b| *b > 0);
+                    match (limit.as_mut(), burst) {
+                        (Some((l, _)), Some(burst)) => *l = RateLimit::new(l.rate, l.period_ms, burst),
+                        _ => return Err(invalid("burst", value)),
+                    }
+                }
+                "log" => log = true,
+                other => match Action::from_name(other) {
+                    Some(a) => action = Some(a),
+                    None => return Err(RuleParseError::UnexpectedToken(other.to_string())),
+                },
+            }
+        }
+
+        if (matches.src_port.is_some() || matches.dst_port.is_some())
+            && !matches!(matches.protocol, Some(PROTO_TCP) | Some(PROTO_UDP))
+        {
+            return Err(RuleParseError::PortWithoutProtocol);
+        }
+        if (matches.src_port.is_some() || matches.dst_port.is_some()) && matches.fragment {
+            return Err(RuleParseError::PortWithFragment);
+        }
+
+        let mut rule = FirewallRule::new(chain, action.ok_or(RuleParseError::MissingAction)?)
+            .with_match(matches);
+        rule.limit = limit.map(|(l, _)| l);
+        rule.log = log;
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for FirewallRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = &self.matches;
+        write!(f, "{}", self.chain.name())?;
+        if let Some(p) = m.protocol {
+            match protocol_name(p) {
+                Some(name) => write!(f, " {}", name)?,
+                None => write!(f, " proto {}", p)?,
+            }
+        }
+        if let Some(src) = m.src {
+            write!(f, " src {}", src)?;
+        }
+        if let Some(dst) = m.dst {
+            write!(f, " dst {}", dst)?;
+        }
+        if let Some(range) = m.src_port {
+            write!(f, " sport {}", range)?;
+        }
+        if let Some(range) = m.dst_port {
+            write!(f, " dport {}", range)?;
+        }
+        if m.fragment {
+            write!(f, " fragment")?;
+        }
+        if let Some(ref iface) = m.interface {
+            write!(f, " iface {}", iface)?;
+        }
+        if let Some(state) = m.state {
+            write!(f, " state {}", state)?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " limit {}/{} burst {}", limit.rate, limit.unit(), limit.burst)?;
+        }
+        write!(f, " {}", self.action.name())?;
+        if self.log {
+            write!(f, " log")?;
+        }
+        Ok(())
+    }
+}
+
+/// Take the value following a keyword
+fn value<'a>(
+    tokens: &mut impl Iterator<Item = &'a str>,
+    keyword: &'static str,
+) -> Result<&'a str, RuleParseError> {
+    tokens.next().ok_or(RuleParseError::MissingValue(keyword))
+}
+
+/// Build an InvalidValue error
+fn invalid(keyword: &'static str, value: &str) -> RuleParseError {
+    RuleParseError::InvalidValue {
+        keyword,
+        value: value.to_string(),
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_roundtrip() {
+        let spec = "input tcp src 10.0.0.0/8 dport 8000-8100 iface eth0 state new,established limit 5/s burst 10 accept log";
+        let rule = FirewallRule::parse(spec).unwrap();
+        assert_eq!(rule.chain, Chain::Input);
+        assert_eq!(rule.action, Action::Accept);
+        assert_eq!(rule.matches.protocol, Some(PROTO_TCP));
+        assert_eq!(rule.matches.dst_port, Some(PortRange { start: 8000, end: 8100 }));
+        assert_eq!(rule.limit.map(|l| l.burst), Some(10));
+        assert!(rule.log);
+        assert_eq!(rule.to_string(), spec);
+
+        let spec = "forward udp dst 10.0.0.5 fragment state established accept";
+        let rule = FirewallRule::parse(spec).unwrap();
+        assert!(rule.matches.fragment);
+        assert_eq!(rule.to_string(), spec);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            FirewallRule::parse("prerouting drop").unwrap_err(),
+            RuleParseError::UnknownChain("prerouting".to_string())
+        );
+        assert_eq!(FirewallRule::parse("input tcp").unwrap_err(), RuleParseError::MissingAction);
+        assert_eq!(
+            FirewallRule::parse("input dport 22 drop").unwrap_err(),
+            RuleParseError::PortWithoutProtocol
+        );
+        assert_eq!(
+            FirewallRule::parse("input udp dport 53 fragment drop").unwrap_err(),
+            RuleParseError::PortWithFragment
+        );
+        assert!(matches!(
+            FirewallRule::parse("input src 10.0.0.300 drop").unwrap_err(),
+            RuleParseError::InvalidValue { keyword: "src", .. }
+        ));
+        assert!(matches!(
+            FirewallRule::parse("input burst 3 drop").unwrap_err(),
+            RuleParseError::InvalidValue { keyword: "burst", .. }
+        ));
+    }
+
+    #[test]
+    fn test_rate_limit_token_bucket() {
+        let mut limit = RateLimit::new(2, 1_000, 2);
+        assert!(limit.allow(0));
+        assert!(limit.allow(0));
+        assert!(!limit.allow(100));
+        // 500ms で1トークン補充
+        assert!(limit.allow(600));
+        assert!(!limit.allow(600));
+        // バケットは burst を超えない
+        assert!(limit.allow(10_000));
+        assert!(limit.allow(10_000));
+        assert!(!limit.allow(10_000));
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let cidr = Cidr::parse("192.168.1.77/24").unwrap();
+        assert_eq!(cidr.to_string(), "192.168.1.0/24");
+        assert!(cidr.contains(&Ipv4Address::new([192, 168, 1, 200])));
+        assert!(!cidr.contains(&Ipv4Address::new([192, 168, 2, 1])));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(&Ipv4Address::BROADCAST));
+    }
+}
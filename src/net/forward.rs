@@ -0,0 +1,159 @@
+//! # IPv4 転送
+//!
+//! インターフェース間でパケットをルーティングする際の判定とヘッダ操作。
+//! 経路選択・フィルタ・NAT・送信は `NetworkStack` が行い、ここでは
+//! 転送してよいか、ICMP エラーを返してよいか、TTL の減算を扱う。
+//!
+//! 転送は既定で無効（`NetworkStack::set_forwarding` で有効化）。
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::ipv4::{Ipv4Address, checksum_adjust};
+
+/// ICMP エラーメッセージの型 (Destination Unreachable, Source Quench,
+/// Redirect, Time Exceeded, Parameter Problem)
+const ICMP_ERROR_TYPES: [u8; 5] = [3, 4, 5, 11, 12];
+
+/// Forwarding counters
+#[derive(Debug, Default)]
+pub struct ForwardStats {
+    /// Packets forwarded
+    pub forwarded: AtomicU64,
+    /// Dropped because the TTL expired (ICMP time exceeded sent)
+    pub ttl_exceeded: AtomicU64,
+    /// Dropped for lack of a route
+    pub no_route: AtomicU64,
+    /// Dropped because they exceed the egress MTU with DF set
+    pub too_big: AtomicU64,
+    /// Dropped by the forward chain
+    pub filtered: AtomicU64,
+    /// Dropped by NAT (fragments, table full)
+    pub nat_dropped: AtomicU64,
+    /// Source or destination may not be forwarded
+    pub not_forwardable: AtomicU64,
+    /// Dropped while resolving the next hop
+    pub arp_pending: AtomicU64,
+}
+
+impl ForwardStats {
+    /// Increment a counter
+    pub fn record(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Check if a packet between these addresses may be routed
+///
+/// 未指定・ブロードキャスト・マルチキャスト・ループバック・リンクローカルは
+/// ルーターを越えない。
+pub fn is_forwardable(src: Ipv4Address, dst: Ipv4Address) -> bool {
+    let routable = |addr: &Ipv4Address| {
+        !addr.is_any() && !addr.is_broadcast() && !addr.is_loopback() && !addr.is_link_local()
+    };
+    routable(&src) && routable(&dst) && !src.is_multicast() && !dst.is_multicast()
+}
+
+/// Check if an ICMP error may be sent about a packet (RFC 1812 4.3.2.7)
+///
+/// 後続フラグメント、ICMP エラー、ブロードキャスト/マルチキャスト宛てには返さない。
+pub fn may_send_icmp_error(packet: &[u8]) -> bool {
+    if packet.len() < 20 {
+        return false;
+    }
+    let header_len = (packet[0] & 0x0F) as usize * 4;
+    let src = Ipv4Address::new([packet[12], packet[13], packet[14], packet[15]]);
+    let dst = Ipv4Address::new([packet[16], packet[17], packet[18], packet[19]]);
+    let first_fragment = packet[6] & 0x1F == 0 && packet[7] == 0;
+    let icmp_error = packet[9] == 1
+        && packet
+            .get(header_len)
+            .is_some_and(|icmp_type| ICMP_ERROR_TYPES.contains(icmp_type));
+
+    first_fragment
+        && !icmp_error
+        && !src.is_any()
+        && !src.is_broadcast()
+        && !src.is_multicast()
+        && !dst.is_broadcast()
+        && !dst.is_multicast()
+}
+
+/// Decrement the TTL and update the header checksum
+///
+/// 戻り値: 転送してよければ true（TTL が 1 以下なら変更せず false）
+pub fn decrement_ttl(packet: &mut [u8]) -> bool {
+    if packet.len() < 20 || packet[8] <= 1 {
+        return false;
+    }
+    let old = [packet[8], packet[9]];
+    packet[8] -= 1;
+    let checksum = u16::from_be_bytes([packet[10], packet[11]]);
+    let checksum = checksum_adjust(checksum, &old, &[packet[8], packet[9]]);
+    packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+    true
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ipv4::{IpProtocol, Ipv4Packet};
+    use crate::net::stack::NetworkStack;
+
+    /// 8バイトのペイロードを持つ IPv4 パケット
+    fn packet(src: Ipv4Address, dst: Ipv4Address, protocol: IpProtocol, ttl: u8) -> [u8; 28] {
+        let mut buffer = [0u8; 28];
+        NetworkStack::build_ipv4(&mut buffer, src, dst, protocol, ttl, |_| Some(8)).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_decrement_ttl() {
+        let lan = Ipv4Address::from_octets(192, 168, 50, 10);
+        let wan = Ipv4Address::from_octets(93, 184, 216, 34);
+        let mut data = packet(lan, wan, IpProtocol::Udp, 64);
+        assert!(decrement_ttl(&mut data));
+        let ip = Ipv4Packet::parse(&data).unwrap();
+        assert_eq!(ip.ttl(), 63);
+        assert!(ip.verify_checksum());
+
+        let mut expiring = packet(lan, wan, IpProtocol::Udp, 1);
+        assert!(!decrement_ttl(&mut expiring));
+        assert_eq!(expiring[8], 1);
+    }
+
+    #[test]
+    fn test_forwardable_and_icmp_errors() {
+        let lan = Ipv4Address::from_octets(192, 168, 50, 10);
+        let wan = Ipv4Address::from_octets(93, 184, 216, 34);
+        assert!(is_forwardable(lan, wan));
+        assert!(!is_forwardable(lan, Ipv4Address::BROADCAST));
+        assert!(!is_forwardable(
+            lan,
+            Ipv4Address::from_octets(224, 0, 0, 251)
+        ));
+        assert!(!is_forwardable(
+            Ipv4Address::from_octets(169, 254, 1, 1),
+            wan
+        ));
+        assert!(!is_forwardable(Ipv4Address::from_octets(127, 0, 0, 1), wan));
+
+        assert!(may_send_icmp_error(&packet(lan, wan, IpProtocol::Udp, 1)));
+
+        // ICMP エラーに対してはエラーを返さない
+        let mut error = packet(lan, wan, IpProtocol::Icmp, 1);
+        error[20] = 11;
+        assert!(!may_send_icmp_error(&error));
+        error[20] = 8;
+        assert!(may_send_icmp_error(&error));
+
+        // 後続フラグメント
+        let mut fragment = packet(lan, wan, IpProtocol::Udp, 1);
+        fragment[6] = 0x00;
+        fragment[7] = 0x10;
+        assert!(!may_send_icmp_error(&fragment));
+    }
+}
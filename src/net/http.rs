@@ -0,0 +1,612 @@
+//! # HTTP/1.1 クライアント
+//!
+//! カーネルTCPスタック（`endpoint` ソケット）上の非同期HTTP/1.1クライアント。
+//! ブラウザのページ・サブリソース取得に使う。
+//!
+//! - 名前解決は `net::dns_resolve_async`（DNS / mDNS / 組み込み名）
+//! - `Transfer-Encoding: chunked` / `Content-Length` / 接続終了によるボディ区切り
+//! - リダイレクト（最大 `MAX_REDIRECTS` 回）
+//! - ホストごとの keep-alive 接続プール
+//! - `Content-Encoding: gzip` / `deflate` の展開
+//! - `file://` URL は VFS（シェルのファイルシステム）から読む
+//!
+//! https:// には対応しない（`UrlError::UnsupportedScheme`）。
+
+pub mod response;
+pub mod url;
+
+pub use response::{BodyFraming, ChunkedDecoder, Response, ResponseParser};
+pub use url::{Scheme, Url, UrlError};
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+
+use super::endpoint::{OwnedSocket, SocketAddr, SocketError, SocketState, create_tcp_socket};
+use crate::util::inflate::{self, InflateError};
+
+/// リダイレクトを辿る最大回数
+pub const MAX_REDIRECTS: usize = 5;
+/// ボディの最大サイズ（展開後も同じ上限）
+pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+/// 接続確立のタイムアウト (ms)
+pub const CONNECT_TIMEOUT_MS: u64 = 5000;
+/// 応答受信のタイムアウト（無通信時間, ms）
+pub const RESPONSE_TIMEOUT_MS: u64 = 15_000;
+/// プール内のアイドル接続の寿命 (ms)
+pub const POOL_IDLE_TIMEOUT_MS: u64 = 30_000;
+/// ホストごとに保持するアイドル接続数
+pub const MAX_IDLE_PER_HOST: usize = 4;
+/// User-Agent
+pub const USER_AGENT: &str = "RanyBrowser/0.1 (Rany OS)";
+
+/// ソケットのポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 5;
+/// 1回の受信サイズ
+const RECV_CHUNK: usize = 4096;
+
+// ============================================================================
+// エラー
+// ============================================================================
+
+/// HTTPクライアントのエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    /// URLが不正・未対応
+    Url(UrlError),
+    /// 名前解決に失敗
+    Resolve(String),
+    /// ソケットエラー
+    Socket(SocketError),
+    /// タイムアウト
+    Timeout,
+    /// 応答の途中で接続が閉じられた
+    ConnectionClosed,
+    /// 応答を解析できない
+    InvalidResponse(&'static str),
+    /// ボディが上限を超えた
+    BodyTooLarge,
+    /// リダイレクトが多すぎる
+    TooManyRedirects,
+    /// Content-Encoding の展開に失敗
+    Decode(InflateError),
+    /// file:// の読み込みに失敗
+    File(String),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Url(e) => write!(f, "{}", e),
+            HttpError::Resolve(e) => write!(f, "Name resolution failed: {}", e),
+            HttpError::Socket(e) => write!(f, "Connection failed: {}", e),
+            HttpError::Timeout => write!(f, "Request timed out"),
+            HttpError::ConnectionClosed => write!(f, "Connection closed before response completed"),
+            HttpError::InvalidResponse(e) => write!(f, "Invalid HTTP response: {}", e),
+            HttpError::BodyTooLarge => write!(f, "Response body exceeds {} bytes", MAX_BODY_SIZE),
+            HttpError::TooManyRedirects => write!(f, "Too many redirects (max {})", MAX_REDIRECTS),
+            HttpError::Decode(e) => write!(f, "Content decoding failed: {}", e),
+            HttpError::File(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<UrlError> for HttpError {
+    fn from(e: UrlError) -> Self {
+        HttpError::Url(e)
+    }
+}
+
+impl From<SocketError> for HttpError {
+    fn from(e: SocketError) -> Self {
+        HttpError::Socket(e)
+    }
+}
+
+// ============================================================================
+// リクエスト
+// ============================================================================
+
+/// HTTPリクエスト
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// メソッド
+    pub method: String,
+    /// URL
+    pub url: Url,
+    /// 追加ヘッダ
+    pub headers: Vec<(String, String)>,
+    /// ボディ
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// リクエストを作成
+    pub fn new(method: &str, url: Url) -> Self {
+        Self {
+            method: method.to_ascii_uppercase(),
+            url,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// GETリクエスト
+    pub fn get(url: Url) -> Self {
+        Self::new("GET", url)
+    }
+
+    /// ヘッダを追加
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// ボディを設定
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// 再送しても安全なメソッドか
+    fn is_idempotent(&self) -> bool {
+        matches!(self.method.as_str(), "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE")
+    }
+
+    /// リクエストを送信形式にエンコード
+    pub fn encode(&self) -> Vec<u8> {
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}\r\nAccept: */*\r\n\
+             Accept-Encoding: gzip, deflate\r\nConnection: keep-alive\r\n",
+            self.method,
+            self.url.path,
+            self.url.authority(),
+            USER_AGENT
+        );
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !self.body.is_empty() || matches!(self.method.as_str(), "POST" | "PUT") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+// ============================================================================
+// 接続プール
+// ============================================================================
+
+/// アイドル接続
+struct PooledConnection {
+    /// "host[:port]"
+    authority: String,
+    socket: OwnedSocket,
+    /// プールに戻した時刻 (ms)
+    idle_since: u64,
+}
+
+/// keep-alive 接続プール
+static POOL: Mutex<Vec<PooledConnection>> = Mutex::new(Vec::new());
+
+/// クライアント統計
+pub struct HttpClientStats {
+    /// 送信したリクエスト数
+    pub requests: AtomicU64,
+    /// 新規接続数
+    pub connections_opened: AtomicU64,
+    /// プールから再利用した接続数
+    pub connections_reused: AtomicU64,
+    /// 受信したボディのバイト数（展開前）
+    pub bytes_received: AtomicU64,
+}
+
+static STATS: HttpClientStats = HttpClientStats {
+    requests: AtomicU64::new(0),
+    connections_opened: AtomicU64::new(0),
+    connections_reused: AtomicU64::new(0),
+    bytes_received: AtomicU64::new(0),
+};
+
+/// クライアント統計を取得
+pub fn stats() -> &'static HttpClientStats {
+    &STATS
+}
+
+/// プール内のアイドル接続数
+pub fn pooled_connections() -> usize {
+    POOL.lock().len()
+}
+
+/// アイドル接続を全て閉じる
+pub fn clear_pool() {
+    // OwnedSocket の Drop がクローズする
+    POOL.lock().clear();
+}
+
+/// 接続がまだ使える状態か
+fn is_usable(socket: &OwnedSocket) -> bool {
+    socket
+        .socket()
+        .is_some_and(|s| s.state() == SocketState::Connected)
+}
+
+/// プールから接続を取り出す（期限切れ・切断済みは破棄）
+fn pool_take(authority: &str) -> Option<OwnedSocket> {
+    let now = crate::time::current_tick();
+    let mut pool = POOL.lock();
+    pool.retain(|c| {
+        now.saturating_sub(c.idle_since) < POOL_IDLE_TIMEOUT_MS && is_usable(&c.socket)
+    });
+    let index = pool.iter().rposition(|c| c.authority == authority)?;
+    Some(pool.swap_remove(index).socket)
+}
+
+/// 接続をプールに戻す
+fn pool_put(authority: String, socket: OwnedSocket) {
+    if !is_usable(&socket) {
+        return;
+    }
+    let mut pool = POOL.lock();
+    let idle = pool.iter().filter(|c| c.authority == authority).count();
+    if idle >= MAX_IDLE_PER_HOST {
+        // 最も古い接続を閉じる
+        if let Some(index) = pool.iter().position(|c| c.authority == authority) {
+            pool.remove(index);
+        }
+    }
+    pool.push(PooledConnection {
+        authority,
+        socket,
+        idle_since: crate::time::current_tick(),
+    });
+}
+
+// ============================================================================
+// 送受信
+// ============================================================================
+
+/// URLをGETする
+pub async fn get(url: &str) -> Result<Response, HttpError> {
+    send(Request::get(Url::parse(url)?)).await
+}
+
+/// リクエストを送信し、リダイレクトを辿って最終レスポンスを返す
+///
+/// 3xx 以外のエラーステータスもそのまま `Ok` で返す
+pub async fn send(mut request: Request) -> Result<Response, HttpError> {
+    if request.url.scheme == Scheme::File {
+        return read_file(&request.url);
+    }
+
+    for _ in 0..=MAX_REDIRECTS {
+        let response = send_once(&request).await?;
+        if !response.is_redirect() {
+            return Ok(response);
+        }
+
+        let location = response.header("location").unwrap_or_default();
+        let next = request.url.join(location)?;
+        // ネットワーク上のページからローカルファイルへは飛ばさない
+        if next.scheme != Scheme::Http {
+            return Err(HttpError::Url(UrlError::UnsupportedScheme("file".into())));
+        }
+        if matches!(response.status, 301..=303) && request.method != "HEAD" {
+            request.method = String::from("GET");
+            request.body.clear();
+            request
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+        }
+        request.url = next;
+    }
+    Err(HttpError::TooManyRedirects)
+}
+
+/// 1往復分のリクエスト送信
+///
+/// プールの接続はサーバー側で既に閉じられていることがあるため、
+/// 冪等なリクエストは失敗時に新しい接続で1回だけ再送する
+async fn send_once(request: &Request) -> Result<Response, HttpError> {
+    let authority = request.url.authority();
+    STATS.requests.fetch_add(1, Ordering::Relaxed);
+
+    if let Some(socket) = pool_take(&authority) {
+        STATS.connections_reused.fetch_add(1, Ordering::Relaxed);
+        match round_trip(&socket, request).await {
+            Ok(parser) => return finish(authority, socket, parser, &request.url),
+            Err(HttpError::ConnectionClosed | HttpError::Socket(_)) if request.is_idempotent() => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let socket = connect(&request.url).await?;
+    let parser = round_trip(&socket, request).await?;
+    finish(authority, socket, parser, &request.url)
+}
+
+/// TCP接続を確立する
+async fn connect(url: &Url) -> Result<OwnedSocket, HttpError> {
+    let addresses = super::dns_resolve_async(&url.host)
+        .await
+        .map_err(HttpError::Resolve)?;
+    let ip = *addresses
+        .first()
+        .ok_or_else(|| HttpError::Resolve(format!("No address for {}", url.host)))?;
+
+    let socket = create_tcp_socket();
+    socket.connect(SocketAddr::new(ip, url.port))?;
+    STATS.connections_opened.fetch_add(1, Ordering::Relaxed);
+
+    let start = crate::time::current_tick();
+    loop {
+        match socket.socket().map(|s| s.state()) {
+            Some(SocketState::Connected) => return Ok(socket),
+            Some(SocketState::Connecting) => {}
+            _ => return Err(HttpError::Socket(SocketError::ConnectionRefused)),
+        }
+        if crate::time::current_tick().saturating_sub(start) >= CONNECT_TIMEOUT_MS {
+            return Err(HttpError::Timeout);
+        }
+        crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+    }
+}
+
+/// リクエストを書き込み、レスポンスが完結するまで読む
+async fn round_trip(socket: &OwnedSocket, request: &Request) -> Result<ResponseParser, HttpError> {
+    write_all(socket, &request.encode()).await?;
+
+    let mut parser = ResponseParser::new(request.method == "HEAD", MAX_BODY_SIZE);
+    let mut buffer = alloc::vec![0u8; RECV_CHUNK];
+    let mut last_progress = crate::time::current_tick();
+    loop {
+        match socket.recv(&mut buffer) {
+            Ok(n) => {
+                STATS.bytes_received.fetch_add(n as u64, Ordering::Relaxed);
+                last_progress = crate::time::current_tick();
+                if parser.feed(&buffer[..n])? {
+                    return Ok(parser);
+                }
+            }
+            // データ待ち
+            Err(SocketError::Timeout) => {
+                if crate::time::current_tick().saturating_sub(last_progress) >= RESPONSE_TIMEOUT_MS
+                {
+                    return Err(HttpError::Timeout);
+                }
+                crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+            }
+            // 相手が接続を閉じた
+            Err(SocketError::NotConnected) => {
+                parser.finish()?;
+                return Ok(parser);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// データを全て送信バッファへ書き込む
+///
+/// バッファが空くのを待つ間は他タスクに譲る（HTTPサーバーと共用）
+pub(crate) async fn write_all(socket: &OwnedSocket, data: &[u8]) -> Result<(), HttpError> {
+    let mut sent = 0;
+    let mut last_progress = crate::time::current_tick();
+    while sent < data.len() {
+        let written = match socket.send(&data[sent..]) {
+            Ok(n) => n,
+            Err(SocketError::BufferFull) => 0,
+            Err(e) => return Err(e.into()),
+        };
+        sent += written;
+        if written > 0 {
+            last_progress = crate::time::current_tick();
+        } else if crate::time::current_tick().saturating_sub(last_progress) >= RESPONSE_TIMEOUT_MS {
+            return Err(HttpError::Timeout);
+        } else {
+            crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+        }
+    }
+    Ok(())
+}
+
+/// 接続をプールへ戻し、ボディを展開してレスポンスを返す
+fn finish(
+    authority: String,
+    socket: OwnedSocket,
+    parser: ResponseParser,
+    url: &Url,
+) -> Result<Response, HttpError> {
+    if parser.keep_alive() {
+        pool_put(authority, socket);
+    }
+    let mut response = parser
+        .into_response(url.clone())
+        .ok_or(HttpError::ConnectionClosed)?;
+    decode_body(&mut response)?;
+    Ok(response)
+}
+
+/// Content-Encoding を展開する
+fn decode_body(response: &mut Response) -> Result<(), HttpError> {
+    let encoding = response
+        .header("content-encoding")
+        .map(|e| e.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    let decoded = match encoding.as_str() {
+        "gzip" | "x-gzip" => inflate::gunzip(&response.body, MAX_BODY_SIZE),
+        "deflate" => inflate::zlib_decompress(&response.body, MAX_BODY_SIZE),
+        _ => return Ok(()),
+    };
+    response.body = decoded.map_err(HttpError::Decode)?;
+    response
+        .headers
+        .retain(|(name, _)| !name.eq_ignore_ascii_case("content-encoding"));
+    Ok(())
+}
+
+// ============================================================================
+// file://
+// ============================================================================
+
+/// 拡張子からメディアタイプを推定
+pub fn guess_content_type(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("rs" | "rss") => "text/rustscript",
+        Some("js") => "text/javascript",
+        Some("txt" | "md") => "text/plain",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("bmp") => "image/bmp",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// file:// URL を VFS から読み、HTTPレスポンスの形で返す
+///
+/// ディレクトリは index.html を探す
+fn read_file(url: &Url) -> Result<Response, HttpError> {
+    let path = url.path_only();
+    let (path, body) = match crate::fs::read_file_content(path, "/") {
+        Ok(body) => (String::from(path), body),
+        Err(crate::fs::FsError::IsDirectory) => {
+            let index = format!("{}/index.html", path.trim_end_matches('/'));
+            let body = crate::fs::read_file_content(&index, "/")
+                .map_err(|e| HttpError::File(format!("{}: {:?}", index, e)))?;
+            (index, body)
+        }
+        Err(e) => return Err(HttpError::File(format!("{}: {:?}", path, e))),
+    };
+    if body.len() > MAX_BODY_SIZE {
+        return Err(HttpError::BodyTooLarge);
+    }
+
+    Ok(Response {
+        url: url.clone(),
+        status: 200,
+        reason: String::from("OK"),
+        headers: alloc::vec![
+            (String::from("Content-Type"), String::from(guess_content_type(&path))),
+            (String::from("Content-Length"), body.len().to_string()),
+        ],
+        body,
+    })
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_encoding() {
+        let url = Url::parse("http://192.168.1.5:8000/list?page=2").unwrap();
+        let request = Request::new("post", url).header("X-Test", "1").body(b"a=b".to_vec());
+        let text = String::from_utf8(request.encode()).unwrap();
+
+        assert!(text.starts_with("POST /list?page=2 HTTP/1.1\r\nHost: 192.168.1.5:8000\r\n"));
+        assert!(text.contains("Accept-Encoding: gzip, deflate\r\n"));
+        assert!(text.contains("X-Test: 1\r\nContent-Length: 3\r\n\r\na=b"));
+        assert!(!request.is_idempotent());
+        assert_eq!(guess_content_type("/www/Site.CSS"), "text/css");
+    }
+
+    #[test]
+    fn test_gzip_content_decoding() {
+        let gzip = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x4c, 0x84, 0x80,
+            0x24, 0x30, 0x48, 0x06, 0x82, 0x94, 0x94, 0x94, 0xd4, 0x44, 0x12, 0x44, 0x4b, 0x32,
+            0x52, 0x15, 0x8a, 0x12, 0xf3, 0x2a, 0x15, 0xb2, 0x53, 0x8b, 0xf2, 0x52, 0x73, 0x14,
+            0x12, 0x87, 0x80, 0x89, 0x00, 0x40, 0xd0, 0xf9, 0xe3, 0xf6, 0x00, 0x00, 0x00,
+        ];
+        let mut response = Response {
+            url: Url::parse("http://host/").unwrap(),
+            status: 200,
+            reason: String::from("OK"),
+            headers: alloc::vec![(String::from("Content-Encoding"), String::from("gzip"))],
+            body: gzip.to_vec(),
+        };
+        decode_body(&mut response).unwrap();
+        assert_eq!(response.body.len(), 246);
+        assert!(response.header("content-encoding").is_none());
+
+        response.headers.push((String::from("Content-Encoding"), String::from("gzip")));
+        assert_eq!(
+            decode_body(&mut response),
+            Err(HttpError::Decode(InflateError::InvalidHeader))
+        );
+    }
+
+    #[test]
+    fn test_get_over_loopback() {
+        use crate::net::endpoint::tests::loopback;
+        use crate::net::endpoint::{create_tcp_server, tcb_table};
+        use core::future::Future;
+
+        let _guard = loopback::setup();
+        let server = create_tcp_server(SocketAddr::new([127, 0, 0, 1], 18080), 4).unwrap();
+        loopback::pump();
+
+        // 複数セグメントに分かれる大きさのボディ
+        let body: Vec<u8> = (0..20_000u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let mut reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        reply.extend_from_slice(&body);
+
+        let mut request = Vec::new();
+        let mut conn = None;
+        let mut written = 0;
+        let mut buffer = [0u8; 512];
+        let mut client = core::pin::pin!(get("http://127.0.0.1:18080/hello"));
+        let response = loopback::run(core::future::poll_fn(|cx| {
+            // サーバー側: 受け付け、リクエストを読み、応答を書いてから閉じる
+            if conn.is_none()
+                && let Ok((socket, _)) = server.accept()
+            {
+                conn = Some(socket);
+            }
+            if let Some(socket) = conn.as_ref() {
+                while let Ok(n) = socket.recv(&mut buffer) {
+                    request.extend_from_slice(&buffer[..n]);
+                }
+                if request.ends_with(b"\r\n\r\n") && written < reply.len() {
+                    written += socket.send(&reply[written..]).unwrap_or(0);
+                    if written == reply.len() {
+                        conn = None;
+                    }
+                }
+            }
+            client.as_mut().poll(cx)
+        }))
+        .unwrap();
+
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("GET /hello HTTP/1.1\r\nHost: 127.0.0.1:18080\r\n"));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, body);
+
+        // 双方のクローズでFINが交換され、接続が解放される
+        for _ in 0..100 {
+            loopback::step();
+        }
+        assert!(!tcb_table().snapshot().iter().any(|tcb| tcb.local.port == 18080));
+    }
+}
@@ -0,0 +1,475 @@
+//! # Response - HTTP/1.1 レスポンスのインクリメンタル解析
+//!
+//! TCP から届いた断片を順に `ResponseParser::feed` へ渡すと、
+//! ステータス行とヘッダを解析し、ボディを `Content-Length` /
+//! `Transfer-Encoding: chunked` / 接続終了のいずれかで区切って取り出す。
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::HttpError;
+use super::url::Url;
+
+/// ヘッダ部の最大サイズ
+pub const MAX_HEADER_SIZE: usize = 32 * 1024;
+
+/// HTTPレスポンス
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// 取得元URL（リダイレクト後）
+    pub url: Url,
+    /// ステータスコード
+    pub status: u16,
+    /// 理由句
+    pub reason: String,
+    /// ヘッダ（受信順）
+    pub headers: Vec<(String, String)>,
+    /// ボディ（Content-Encoding 展開後）
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// ヘッダ値を取得（大文字小文字を区別しない）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// 2xx か
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// リダイレクト先を持つ 3xx か
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status, 301 | 302 | 303 | 307 | 308) && self.header("location").is_some()
+    }
+
+    /// メディアタイプ（パラメータを除き小文字化）
+    pub fn content_type(&self) -> String {
+        self.header("content-type")
+            .and_then(|value| value.split(';').next())
+            .map(|media| media.trim().to_ascii_lowercase())
+            .unwrap_or_default()
+    }
+
+    /// ボディを文字列として取得（不正なUTF-8は置換）
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// ヘッダ列から値を探す
+pub(super) fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+// ============================================================================
+// Chunked transfer encoding
+// ============================================================================
+
+/// チャンクデコーダの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    /// チャンクサイズ行
+    Size,
+    /// チャンクデータ（残りバイト数）
+    Data(usize),
+    /// データ直後の CRLF
+    DataEnd,
+    /// 最終チャンク後のトレーラー
+    Trailer,
+    /// 完了
+    Done,
+}
+
+/// `Transfer-Encoding: chunked` のデコーダ
+#[derive(Debug)]
+pub struct ChunkedDecoder {
+    state: ChunkState,
+    /// 読みかけの行
+    line: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    /// 新規作成
+    pub const fn new() -> Self {
+        Self {
+            state: ChunkState::Size,
+            line: Vec::new(),
+        }
+    }
+
+    /// 最終チャンクとトレーラーまで読み終えたか
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    /// 入力を処理してデータを `out` に追加する
+    ///
+    /// 戻り値: 消費したバイト数（完了後の余りは消費しない）
+    pub fn feed(&mut self, data: &[u8], out: &mut Vec<u8>) -> Result<usize, HttpError> {
+        let mut pos = 0;
+        while pos < data.len() && self.state != ChunkState::Done {
+            if let ChunkState::Data(remaining) = self.state {
+                let take = remaining.min(data.len() - pos);
+                out.extend_from_slice(&data[pos..pos + take]);
+                pos += take;
+                self.state = if take == remaining {
+                    ChunkState::DataEnd
+                } else {
+                    ChunkState::Data(remaining - take)
+                };
+                continue;
+            }
+
+            // 行単位の状態: 改行まで溜める
+            let byte = data[pos];
+            pos += 1;
+            if byte != b'\n' {
+                if self.line.len() >= 1024 {
+                    return Err(HttpError::InvalidResponse("chunk line too long"));
+                }
+                self.line.push(byte);
+                continue;
+            }
+            let line = core::mem::take(&mut self.line);
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+
+            self.state = match self.state {
+                ChunkState::Size => {
+                    // chunk-ext (";name=value") は無視する
+                    let size = core::str::from_utf8(line)
+                        .ok()
+                        .and_then(|l| l.split(';').next())
+                        .and_then(|l| usize::from_str_radix(l.trim(), 16).ok())
+                        .ok_or(HttpError::InvalidResponse("invalid chunk size"))?;
+                    if size == 0 {
+                        ChunkState::Trailer
+                    } else {
+                        ChunkState::Data(size)
+                    }
+                }
+                ChunkState::DataEnd if line.is_empty() => ChunkState::Size,
+                ChunkState::DataEnd => {
+                    return Err(HttpError::InvalidResponse("missing CRLF after chunk"));
+                }
+                ChunkState::Trailer if line.is_empty() => ChunkState::Done,
+                state => state,
+            };
+        }
+        Ok(pos)
+    }
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Response parser
+// ============================================================================
+
+/// ボディの区切り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFraming {
+    /// ボディ無し（HEAD、1xx/204/304）
+    Empty,
+    /// Content-Length
+    Length(usize),
+    /// chunked
+    Chunked,
+    /// 接続終了まで
+    UntilClose,
+}
+
+/// 解析済みのステータス行とヘッダ
+#[derive(Debug, Clone)]
+struct Head {
+    /// HTTP/1.1 か
+    http11: bool,
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    framing: BodyFraming,
+}
+
+/// HTTPレスポンスのインクリメンタルパーサ
+#[derive(Debug)]
+pub struct ResponseParser {
+    /// HEAD リクエストへの応答か
+    head_request: bool,
+    /// ボディの上限
+    body_limit: usize,
+    /// ヘッダ受信中のバッファ
+    buffer: Vec<u8>,
+    head: Option<Head>,
+    body: Vec<u8>,
+    chunked: ChunkedDecoder,
+    complete: bool,
+}
+
+impl ResponseParser {
+    /// 新規作成
+    pub fn new(head_request: bool, body_limit: usize) -> Self {
+        Self {
+            head_request,
+            body_limit,
+            buffer: Vec::new(),
+            head: None,
+            body: Vec::new(),
+            chunked: ChunkedDecoder::new(),
+            complete: false,
+        }
+    }
+
+    /// 何かしら受信済みか
+    pub fn has_data(&self) -> bool {
+        self.head.is_some() || !self.buffer.is_empty()
+    }
+
+    /// レスポンスが完結したか
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// 受信データを渡す
+    ///
+    /// 戻り値: レスポンスが完結したら true
+    pub fn feed(&mut self, data: &[u8]) -> Result<bool, HttpError> {
+        if self.complete {
+            return Ok(true);
+        }
+        let mut data = data;
+
+        while self.head.is_none() {
+            self.buffer.extend_from_slice(data);
+            let Some(end) = find_head_end(&self.buffer) else {
+                if self.buffer.len() > MAX_HEADER_SIZE {
+                    return Err(HttpError::InvalidResponse("header section too large"));
+                }
+                return Ok(false);
+            };
+            let head = self.parse_head(&self.buffer[..end])?;
+            let rest = self.buffer.split_off(end);
+            self.buffer.clear();
+            // 1xx 中間応答は読み飛ばして次のレスポンスを待つ
+            if (100..200).contains(&head.status) {
+                self.buffer = rest;
+                data = &[];
+                continue;
+            }
+            self.head = Some(head);
+            return self.feed_body(&rest);
+        }
+        self.feed_body(data)
+    }
+
+    /// 接続終了を通知する
+    ///
+    /// 接続終了で区切るボディはここで完結する
+    pub fn finish(&mut self) -> Result<(), HttpError> {
+        if self.complete {
+            return Ok(());
+        }
+        match self.head.as_ref().map(|h| h.framing) {
+            Some(BodyFraming::UntilClose) => {
+                self.complete = true;
+                Ok(())
+            }
+            _ => Err(HttpError::ConnectionClosed),
+        }
+    }
+
+    /// 接続を再利用できるか（完結後に判定）
+    pub fn keep_alive(&self) -> bool {
+        let Some(head) = self.head.as_ref() else {
+            return false;
+        };
+        if !self.complete || head.framing == BodyFraming::UntilClose {
+            return false;
+        }
+        let connection = find_header(&head.headers, "connection").map(|v| v.to_ascii_lowercase());
+        match connection.as_deref() {
+            Some(value) if value.contains("close") => false,
+            Some(value) if value.contains("keep-alive") => true,
+            _ => head.http11,
+        }
+    }
+
+    /// 完結したレスポンスを取り出す（Content-Encoding は未展開）
+    pub fn into_response(self, url: Url) -> Option<Response> {
+        if !self.complete {
+            return None;
+        }
+        let head = self.head?;
+        Some(Response {
+            url,
+            status: head.status,
+            reason: head.reason,
+            headers: head.headers,
+            body: self.body,
+        })
+    }
+
+    fn feed_body(&mut self, data: &[u8]) -> Result<bool, HttpError> {
+        let framing = self.head.as_ref().map_or(BodyFraming::Empty, |h| h.framing);
+        match framing {
+            BodyFraming::Empty => self.complete = true,
+            BodyFraming::Length(length) => {
+                let take = (length - self.body.len()).min(data.len());
+                self.body.extend_from_slice(&data[..take]);
+                self.complete = self.body.len() == length;
+            }
+            BodyFraming::Chunked => {
+                self.chunked.feed(data, &mut self.body)?;
+                self.complete = self.chunked.is_done();
+            }
+            BodyFraming::UntilClose => self.body.extend_from_slice(data),
+        }
+        if self.body.len() > self.body_limit {
+            return Err(HttpError::BodyTooLarge);
+        }
+        Ok(self.complete)
+    }
+
+    fn parse_head(&self, raw: &[u8]) -> Result<Head, HttpError> {
+        let text = core::str::from_utf8(raw)
+            .map_err(|_| HttpError::InvalidResponse("header is not valid UTF-8"))?;
+        let mut lines = text.lines();
+
+        // ステータス行: "HTTP/1.1 200 OK"
+        let status_line = lines.next().unwrap_or_default();
+        let mut parts = status_line.splitn(3, ' ');
+        let version = parts.next().unwrap_or_default();
+        if !version.starts_with("HTTP/1.") {
+            return Err(HttpError::InvalidResponse("not an HTTP/1.x response"));
+        }
+        let status = parts
+            .next()
+            .and_then(|code| code.parse::<u16>().ok())
+            .filter(|code| (100..600).contains(code))
+            .ok_or(HttpError::InvalidResponse("invalid status code"))?;
+        let reason = parts.next().unwrap_or_default().trim().to_string();
+
+        let mut headers = Vec::new();
+        for line in lines {
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or(HttpError::InvalidResponse("malformed header line"))?;
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+
+        let chunked = find_header(&headers, "transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+        let framing = if self.head_request || matches!(status, 100..=199 | 204 | 304) {
+            BodyFraming::Empty
+        } else if chunked {
+            BodyFraming::Chunked
+        } else if let Some(length) = find_header(&headers, "content-length") {
+            let length = length
+                .parse::<usize>()
+                .map_err(|_| HttpError::InvalidResponse("invalid Content-Length"))?;
+            if length > self.body_limit {
+                return Err(HttpError::BodyTooLarge);
+            }
+            BodyFraming::Length(length)
+        } else {
+            BodyFraming::UntilClose
+        };
+
+        Ok(Head {
+            http11: version == "HTTP/1.1",
+            status,
+            reason,
+            headers,
+            framing,
+        })
+    }
+}
+
+/// ヘッダ部の終端（空行の直後）の位置
+fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    let crlf = buffer.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4);
+    let lf = buffer.windows(2).position(|w| w == b"\n\n").map(|i| i + 2);
+    match (crlf, lf) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("http://10.0.2.2:8000/").unwrap()
+    }
+
+    #[test]
+    fn test_content_length_split_across_reads() {
+        let mut parser = ResponseParser::new(false, 1024);
+        assert!(!parser.feed(b"HTTP/1.1 200 OK\r\nContent-Ty").unwrap());
+        assert!(!parser.feed(b"pe: text/html; charset=utf-8\r\nContent-Length: 5\r\n\r\nhe").unwrap());
+        assert!(parser.feed(b"llo").unwrap());
+        assert!(parser.keep_alive());
+
+        let response = parser.into_response(url()).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type(), "text/html");
+        assert_eq!(response.body, b"hello");
+    }
+
+    #[test]
+    fn test_chunked_with_interim_response() {
+        let mut parser = ResponseParser::new(false, 1024);
+        let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\
+                    Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+                    4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        // 1バイトずつ渡しても同じ結果になる
+        for (i, byte) in raw.iter().enumerate() {
+            let done = parser.feed(core::slice::from_ref(byte)).unwrap();
+            assert_eq!(done, i == raw.len() - 1);
+        }
+        assert!(!parser.keep_alive());
+        assert_eq!(parser.into_response(url()).unwrap().body, b"Wikipedia");
+
+        let mut decoder = ChunkedDecoder::new();
+        let mut out = Vec::new();
+        assert!(decoder.feed(b"zz\r\n", &mut out).is_err());
+    }
+
+    #[test]
+    fn test_until_close_and_limits() {
+        let mut parser = ResponseParser::new(false, 1024);
+        parser.feed(b"HTTP/1.0 200 OK\r\n\r\npartial").unwrap();
+        assert!(!parser.is_complete());
+        parser.finish().unwrap();
+        assert!(!parser.keep_alive());
+        assert_eq!(parser.into_response(url()).unwrap().body, b"partial");
+
+        let mut parser = ResponseParser::new(false, 4);
+        assert_eq!(
+            parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n").unwrap_err(),
+            HttpError::BodyTooLarge
+        );
+
+        let mut parser = ResponseParser::new(true, 1024);
+        assert!(parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n").unwrap());
+
+        let mut parser = ResponseParser::new(false, 1024);
+        parser.feed(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc").unwrap();
+        assert_eq!(parser.finish(), Err(HttpError::ConnectionClosed));
+    }
+}
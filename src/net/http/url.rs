@@ -0,0 +1,247 @@
+//! # URL - http:// / file:// URL の解析と相対参照の解決
+//!
+//! ブラウザとHTTPクライアントが扱う範囲のサブセット（RFC 3986）。
+//! ユーザー情報・IPv6リテラルは扱わない。フラグメントは取り除く。
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// HTTPのデフォルトポート
+pub const HTTP_DEFAULT_PORT: u16 = 80;
+
+/// URL解析エラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    /// スキームが無い
+    MissingScheme,
+    /// 未対応のスキーム
+    UnsupportedScheme(String),
+    /// ホスト名が無い・不正
+    InvalidHost,
+    /// ポート番号が不正
+    InvalidPort,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::MissingScheme => write!(f, "URL has no scheme"),
+            UrlError::UnsupportedScheme(s) => write!(f, "Unsupported URL scheme: {}", s),
+            UrlError::InvalidHost => write!(f, "Invalid host in URL"),
+            UrlError::InvalidPort => write!(f, "Invalid port in URL"),
+        }
+    }
+}
+
+/// URLスキーム
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// http://
+    Http,
+    /// file:// (VFS)
+    File,
+}
+
+/// 解析済みURL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    /// スキーム
+    pub scheme: Scheme,
+    /// ホスト名（小文字化済み、file:// では空）
+    pub host: String,
+    /// ポート
+    pub port: u16,
+    /// パス（'/' 始まり、クエリを含む）
+    pub path: String,
+}
+
+impl Url {
+    /// 絶対URLを解析する
+    pub fn parse(input: &str) -> Result<Self, UrlError> {
+        let input = input.trim();
+        let input = input.split('#').next().unwrap_or_default();
+        let (scheme, rest) = input.split_once("://").ok_or(UrlError::MissingScheme)?;
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "http" => {
+                let split = rest.find(['/', '?']).unwrap_or(rest.len());
+                let (authority, path) = rest.split_at(split);
+                let (host, port) = match authority.rsplit_once(':') {
+                    Some((host, port)) => {
+                        (host, port.parse::<u16>().map_err(|_| UrlError::InvalidPort)?)
+                    }
+                    None => (authority, HTTP_DEFAULT_PORT),
+                };
+                if port == 0 {
+                    return Err(UrlError::InvalidPort);
+                }
+                if host.is_empty()
+                    || !host
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_')
+                {
+                    return Err(UrlError::InvalidHost);
+                }
+                let path = if path.starts_with('?') {
+                    format!("/{}", path)
+                } else {
+                    path.to_string()
+                };
+                Ok(Self {
+                    scheme: Scheme::Http,
+                    host: host.to_ascii_lowercase(),
+                    port,
+                    path: normalize_path(if path.is_empty() { "/" } else { &path }),
+                })
+            }
+            "file" => {
+                // file:///path と file://localhost/path を受け付ける
+                let path = rest.strip_prefix("localhost").unwrap_or(rest);
+                if !path.starts_with('/') {
+                    return Err(UrlError::InvalidHost);
+                }
+                Ok(Self {
+                    scheme: Scheme::File,
+                    host: String::new(),
+                    port: 0,
+                    path: normalize_path(path),
+                })
+            }
+            other => Err(UrlError::UnsupportedScheme(other.to_string())),
+        }
+    }
+
+    /// このURLを基準に参照（href / src）を解決する
+    pub fn join(&self, reference: &str) -> Result<Self, UrlError> {
+        let reference = reference.trim();
+        let reference = reference.split('#').next().unwrap_or_default();
+
+        if reference.contains("://") {
+            return Self::parse(reference);
+        }
+        if let Some(rest) = reference.strip_prefix("//") {
+            let scheme = match self.scheme {
+                Scheme::Http => "http",
+                Scheme::File => "file",
+            };
+            return Self::parse(&format!("{}://{}", scheme, rest));
+        }
+
+        let path = if reference.is_empty() {
+            self.path.clone()
+        } else if reference.starts_with('/') {
+            reference.to_string()
+        } else if reference.starts_with('?') {
+            format!("{}{}", self.path_only(), reference)
+        } else {
+            let base = self.path_only();
+            let dir = &base[..base.rfind('/').map_or(0, |i| i + 1)];
+            format!("{}{}", dir, reference)
+        };
+
+        Ok(Self {
+            path: normalize_path(&path),
+            ..self.clone()
+        })
+    }
+
+    /// クエリを除いたパス
+    pub fn path_only(&self) -> &str {
+        self.path.split('?').next().unwrap_or("/")
+    }
+
+    /// Host ヘッダ / 接続プールのキー（デフォルトポートは省略）
+    pub fn authority(&self) -> String {
+        if self.port == HTTP_DEFAULT_PORT {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.scheme {
+            Scheme::Http => write!(f, "http://{}{}", self.authority(), self.path),
+            Scheme::File => write!(f, "file://{}", self.path),
+        }
+    }
+}
+
+/// `.` / `..` セグメントを取り除く（クエリには触れない）
+fn normalize_path(path: &str) -> String {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            other => segments.push(other),
+        }
+    }
+    // 末尾の "." / ".." はディレクトリを指す
+    if path.ends_with("/.") || path.ends_with("/..") {
+        segments.push("");
+    }
+
+    let mut normalized = String::from("/");
+    normalized.push_str(&segments.join("/"));
+    if let Some(query) = query {
+        normalized.push('?');
+        normalized.push_str(query);
+    }
+    normalized
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let url = Url::parse("http://Example.COM:8000/a/./b/../c?x=1#top").unwrap();
+        assert_eq!(url.scheme, Scheme::Http);
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 8000);
+        assert_eq!(url.path, "/a/c?x=1");
+        assert_eq!(url.to_string(), "http://example.com:8000/a/c?x=1");
+
+        assert_eq!(Url::parse("http://10.0.2.2").unwrap().path, "/");
+        assert_eq!(Url::parse("file:///www/index.html").unwrap().path, "/www/index.html");
+        assert_eq!(
+            Url::parse("https://example.com"),
+            Err(UrlError::UnsupportedScheme("https".into()))
+        );
+        assert_eq!(Url::parse("http://host:0/"), Err(UrlError::InvalidPort));
+        assert_eq!(Url::parse("example.com"), Err(UrlError::MissingScheme));
+    }
+
+    #[test]
+    fn test_join() {
+        let base = Url::parse("http://host:8000/docs/guide/index.html?lang=ja").unwrap();
+        let join = |r: &str| base.join(r).unwrap().to_string();
+
+        assert_eq!(join("style.css"), "http://host:8000/docs/guide/style.css");
+        assert_eq!(join("../img/logo.png"), "http://host:8000/docs/img/logo.png");
+        assert_eq!(join("/app.rs"), "http://host:8000/app.rs");
+        assert_eq!(join("?lang=en"), "http://host:8000/docs/guide/index.html?lang=en");
+        assert_eq!(join("//other/x"), "http://other/x");
+        assert_eq!(join("http://a.local/"), "http://a.local/");
+
+        let file = Url::parse("file:///www/index.html").unwrap();
+        assert_eq!(file.join("css/site.css").unwrap().to_string(), "file:///www/css/site.css");
+    }
+}
@@ -0,0 +1,597 @@
+//! # HTTP/1.1 サーバー
+//!
+//! カーネルTCPスタック（`endpoint` ソケット）上の非同期HTTPサーバーフレームワーク。
+//! カーネル内からダッシュボードやAPIを配信するために使う。
+//!
+//! - `Router` によるパスパターン（`/proc/:pid`、`/static/*path`）と非同期ハンドラ
+//! - クエリ文字列の解析（`Request::query_param`）
+//! - `Content-Length` / chunked / ストリーミングレスポンス
+//! - マウント済みFSからの静的ファイル配信（Range / ETag 対応）
+//! - 持続的接続（keep-alive、パイプライン）とリクエストサイズの制限
+//! - API 用の JSON 値（`Json`）
+//!
+//! ```ignore
+//! let router = Router::new()
+//!     .get("/proc/:pid", |req: Request| async move {
+//!         Response::text(Status::OK, req.param("pid").unwrap_or_default())
+//!     })
+//!     .static_files("/static", "/www");
+//! crate::task::spawn(async move {
+//!     let _ = HttpServer::new(router).serve().await;
+//! });
+//! ```
+
+pub mod files;
+pub mod json;
+pub mod request;
+pub mod response;
+pub mod router;
+
+pub use files::StaticFiles;
+pub use json::{Json, JsonError};
+pub use request::{Limits, Method, ParseError, Request, RequestParser, percent_decode};
+pub use response::{
+    Body, BodySender, BodyStream, ChannelStream, Response, Status, body_channel, iter_stream,
+};
+pub use router::{Handler, HandlerFuture, PathPattern, Router};
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use super::endpoint::{OwnedSocket, SocketAddr, SocketError, create_tcp_server};
+use super::http::{HttpError, write_all};
+
+/// デフォルトの待ち受けポート
+pub const DEFAULT_PORT: u16 = 80;
+/// Server ヘッダ
+pub const SERVER_NAME: &str = "Rany/0.1";
+
+/// accept / recv のポーリング間隔 (ms)
+const POLL_INTERVAL_MS: u64 = 5;
+/// 1回の受信サイズ
+const RECV_CHUNK: usize = 4096;
+/// これ以下のボディはヘッダと一緒に書き込む
+const COALESCE_LIMIT: usize = 8 * 1024;
+
+// ============================================================================
+// 設定・統計
+// ============================================================================
+
+/// サーバー設定
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// 待ち受けポート
+    pub port: u16,
+    /// listen バックログ
+    pub backlog: u32,
+    /// リクエストサイズの上限
+    pub limits: Limits,
+    /// keep-alive 接続のアイドルタイムアウト (ms)
+    pub keep_alive_timeout_ms: u64,
+    /// リクエストを受信し終えるまでのタイムアウト（無通信時間, ms）
+    pub request_timeout_ms: u64,
+    /// 1接続で処理するリクエスト数の上限
+    pub max_requests_per_connection: usize,
+    /// 同時接続数の上限（超えた接続には 503 を返して閉じる）
+    pub max_connections: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            backlog: 64,
+            limits: Limits::default(),
+            keep_alive_timeout_ms: 5_000,
+            request_timeout_ms: 10_000,
+            max_requests_per_connection: 100,
+            max_connections: 64,
+        }
+    }
+}
+
+/// サーバー統計（全サーバー合計）
+pub struct ServerStats {
+    /// 受け付けた接続数
+    pub connections_accepted: AtomicU64,
+    /// 処理中の接続数
+    pub connections_active: AtomicU64,
+    /// 上限超過で断った接続数
+    pub connections_rejected: AtomicU64,
+    /// 処理したリクエスト数
+    pub requests: AtomicU64,
+    /// 不正なリクエスト・タイムアウトの数
+    pub bad_requests: AtomicU64,
+    /// 受信バイト数
+    pub bytes_received: AtomicU64,
+    /// 送信バイト数
+    pub bytes_sent: AtomicU64,
+}
+
+static STATS: ServerStats = ServerStats {
+    connections_accepted: AtomicU64::new(0),
+    connections_active: AtomicU64::new(0),
+    connections_rejected: AtomicU64::new(0),
+    requests: AtomicU64::new(0),
+    bad_requests: AtomicU64::new(0),
+    bytes_received: AtomicU64::new(0),
+    bytes_sent: AtomicU64::new(0),
+};
+
+/// サーバー統計を取得
+pub fn stats() -> &'static ServerStats {
+    &STATS
+}
+
+// ============================================================================
+// サーバー
+// ============================================================================
+
+/// 実行中のサーバーを止めるためのハンドル
+#[derive(Debug, Clone)]
+pub struct ServerHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl ServerHandle {
+    /// 新しい接続の受け付けをやめ、処理中の接続は現在のリクエストで閉じる
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// 停止要求済みか
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+}
+
+/// HTTPサーバー
+pub struct HttpServer {
+    config: ServerConfig,
+    router: Arc<Router>,
+    stop: Arc<AtomicBool>,
+}
+
+impl HttpServer {
+    /// デフォルト設定で作成
+    pub fn new(router: Router) -> Self {
+        Self::with_config(router, ServerConfig::default())
+    }
+
+    /// 設定を指定して作成
+    pub fn with_config(router: Router, config: ServerConfig) -> Self {
+        Self {
+            config,
+            router: Arc::new(router),
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// 停止用ハンドル
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            stop: self.stop.clone(),
+        }
+    }
+
+    /// 待ち受けを開始し、停止されるまで接続を受け付ける
+    ///
+    /// 接続ごとにタスクを生成する。
+    pub async fn serve(self) -> Result<(), SocketError> {
+        let listener = create_tcp_server(
+            SocketAddr::new([0, 0, 0, 0], self.config.port),
+            self.config.backlog,
+        )?;
+        crate::log!("[HTTPD] Listening on port {}\n", self.config.port);
+
+        while !self.stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((socket, remote)) => {
+                    STATS.connections_accepted.fetch_add(1, Ordering::Relaxed);
+                    let active = STATS.connections_active.load(Ordering::Relaxed) as usize;
+                    if active >= self.config.max_connections {
+                        STATS.connections_rejected.fetch_add(1, Ordering::Relaxed);
+                        crate::task::spawn(reject(socket));
+                        continue;
+                    }
+                    STATS.connections_active.fetch_add(1, Ordering::Relaxed);
+                    crate::task::spawn(serve_connection(
+                        self.router.clone(),
+                        socket,
+                        remote,
+                        self.config.clone(),
+                        self.stop.clone(),
+                    ));
+                }
+                Err(SocketError::Timeout) => crate::task::sleep_ms(POLL_INTERVAL_MS).await,
+                Err(e) => return Err(e),
+            }
+        }
+        crate::log!("[HTTPD] Port {} stopped\n", self.config.port);
+        Ok(())
+    }
+}
+
+/// 上限超過の接続に 503 を返して閉じる
+async fn reject(socket: OwnedSocket) {
+    let response = Response::error(Status::SERVICE_UNAVAILABLE).header("Retry-After", "1");
+    let _ = write_response(&socket, response, true, false, false).await;
+}
+
+/// 1接続のリクエストを順に処理する
+async fn serve_connection(
+    router: Arc<Router>,
+    socket: OwnedSocket,
+    remote: SocketAddr,
+    config: ServerConfig,
+    stop: Arc<AtomicBool>,
+) {
+    let mut parser = RequestParser::new(config.limits);
+    let mut buffer = alloc::vec![0u8; RECV_CHUNK];
+    let mut served = 0;
+    let mut last_activity = crate::time::current_tick();
+
+    loop {
+        match parser.next_request() {
+            Ok(Some(mut request)) => {
+                request.remote = Some(remote);
+                served += 1;
+                STATS.requests.fetch_add(1, Ordering::Relaxed);
+
+                let keep_alive = request.keep_alive()
+                    && served < config.max_requests_per_connection
+                    && !stop.load(Ordering::SeqCst);
+                let http11 = request.http11;
+                let head = request.method == Method::Head;
+                let response = router.handle(request).await;
+                match write_response(&socket, response, http11, head, keep_alive).await {
+                    Ok(true) => {
+                        last_activity = crate::time::current_tick();
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                STATS.bad_requests.fetch_add(1, Ordering::Relaxed);
+                let response = Response::error(Status(e.status()));
+                let _ = write_response(&socket, response, true, false, false).await;
+                break;
+            }
+        }
+
+        if parser.take_continue()
+            && write_all(&socket, b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .is_err()
+        {
+            break;
+        }
+
+        match socket.recv(&mut buffer) {
+            Ok(n) => {
+                STATS.bytes_received.fetch_add(n as u64, Ordering::Relaxed);
+                parser.feed(&buffer[..n]);
+                last_activity = crate::time::current_tick();
+            }
+            Err(SocketError::Timeout) => {
+                let idle = crate::time::current_tick().saturating_sub(last_activity);
+                if parser.is_idle() {
+                    if idle >= config.keep_alive_timeout_ms || stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                } else if idle >= config.request_timeout_ms {
+                    STATS.bad_requests.fetch_add(1, Ordering::Relaxed);
+                    let response = Response::error(Status::REQUEST_TIMEOUT);
+                    let _ = write_response(&socket, response, true, false, false).await;
+                    break;
+                }
+                crate::task::sleep_ms(POLL_INTERVAL_MS).await;
+            }
+            // 相手が閉じた・エラー
+            Err(_) => break,
+        }
+    }
+
+    STATS.connections_active.fetch_sub(1, Ordering::Relaxed);
+    // OwnedSocket の Drop がクローズする
+}
+
+// ============================================================================
+// レスポンスの書き込み
+// ============================================================================
+
+/// ボディの送り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// ボディ無し（HEAD では Content-Length だけ付ける）
+    None(Option<u64>),
+    /// Content-Length
+    Length(u64),
+    /// Transfer-Encoding: chunked
+    Chunked,
+    /// 接続終了まで（HTTP/1.0 で長さ不明）
+    Close,
+}
+
+/// サーバーが管理するヘッダ（ハンドラの指定は無視する）
+fn is_hop_header(name: &str) -> bool {
+    [
+        "Content-Length",
+        "Transfer-Encoding",
+        "Connection",
+        "Keep-Alive",
+    ]
+    .iter()
+    .any(|h| h.eq_ignore_ascii_case(name))
+}
+
+/// ステータス行とヘッダを組み立てる
+fn encode_head(response: &Response, framing: Framing, keep_alive: bool) -> Vec<u8> {
+    let status = response.status;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nServer: {}\r\n",
+        status.0,
+        status.reason(),
+        SERVER_NAME
+    );
+    for (name, value) in response.headers.iter().filter(|(n, _)| !is_hop_header(n)) {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    match framing {
+        Framing::None(Some(length)) | Framing::Length(length) => {
+            head.push_str(&format!("Content-Length: {}\r\n", length));
+        }
+        Framing::Chunked => head.push_str("Transfer-Encoding: chunked\r\n"),
+        Framing::None(None) | Framing::Close => {}
+    }
+    head.push_str(if keep_alive {
+        "Connection: keep-alive\r\n\r\n"
+    } else {
+        "Connection: close\r\n\r\n"
+    });
+    head.into_bytes()
+}
+
+/// chunked 形式の1チャンク
+fn encode_chunk(data: &[u8]) -> Vec<u8> {
+    let mut chunk = format!("{:x}\r\n", data.len()).into_bytes();
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(b"\r\n");
+    chunk
+}
+
+/// 書き込んで統計に加える
+async fn send(socket: &OwnedSocket, data: &[u8]) -> Result<(), HttpError> {
+    write_all(socket, data).await?;
+    STATS
+        .bytes_sent
+        .fetch_add(data.len() as u64, Ordering::Relaxed);
+    Ok(())
+}
+
+/// レスポンスを書き込む
+///
+/// 戻り値: 接続を維持できるか
+async fn write_response(
+    socket: &OwnedSocket,
+    response: Response,
+    http11: bool,
+    head_request: bool,
+    keep_alive: bool,
+) -> Result<bool, HttpError> {
+    let length = response.body.len();
+    let framing = if head_request || !response.status.allows_body() {
+        Framing::None(length.filter(|_| response.status.allows_body()))
+    } else {
+        match length {
+            Some(length) => Framing::Length(length),
+            None if http11 => Framing::Chunked,
+            None => Framing::Close,
+        }
+    };
+    let keep_alive = keep_alive && framing != Framing::Close;
+    let mut head = encode_head(&response, framing, keep_alive);
+
+    let stream = match (framing, response.body) {
+        (Framing::None(_), _) | (_, Body::Empty) => {
+            send(socket, &head).await?;
+            return Ok(keep_alive);
+        }
+        (_, Body::Bytes(bytes)) => {
+            if bytes.len() <= COALESCE_LIMIT {
+                head.extend_from_slice(&bytes);
+                send(socket, &head).await?;
+            } else {
+                send(socket, &head).await?;
+                send(socket, &bytes).await?;
+            }
+            return Ok(keep_alive);
+        }
+        (_, Body::Stream { stream, .. }) => stream,
+    };
+    send(socket, &head).await?;
+    write_stream(socket, stream, framing).await?;
+    Ok(keep_alive)
+}
+
+/// ストリームボディを書き込む
+async fn write_stream(
+    socket: &OwnedSocket,
+    mut stream: alloc::boxed::Box<dyn BodyStream>,
+    framing: Framing,
+) -> Result<(), HttpError> {
+    let mut remaining = match framing {
+        Framing::Length(length) => Some(length),
+        _ => None,
+    };
+    while let Some(chunk) = core::future::poll_fn(|cx| stream.poll_chunk(cx)).await {
+        if chunk.is_empty() {
+            continue;
+        }
+        match (framing, remaining.as_mut()) {
+            (Framing::Chunked, _) => send(socket, &encode_chunk(&chunk)).await?,
+            (_, Some(remaining)) => {
+                let take = (*remaining).min(chunk.len() as u64) as usize;
+                send(socket, &chunk[..take]).await?;
+                *remaining -= take as u64;
+                if *remaining == 0 {
+                    break;
+                }
+            }
+            _ => send(socket, &chunk).await?,
+        }
+    }
+
+    match (framing, remaining) {
+        (Framing::Chunked, _) => send(socket, b"0\r\n\r\n").await,
+        // 宣言より短い: 接続を閉じて相手に伝える
+        (_, Some(remaining)) if remaining > 0 => Err(HttpError::ConnectionClosed),
+        _ => Ok(()),
+    }
+}
+
+/// 実行中のサーバーをポートごとに覚えておく（`net::http_server::stop` 用）
+static SERVERS: spin::Mutex<Vec<(u16, ServerHandle)>> = spin::Mutex::new(Vec::new());
+
+/// サーバーをタスクとして起動する
+///
+/// 同じポートで既に動いていればエラー。
+pub fn spawn(router: Router, config: ServerConfig) -> Result<ServerHandle, String> {
+    let port = config.port;
+    let mut servers = SERVERS.lock();
+    servers.retain(|(_, handle)| !handle.is_stopped());
+    if servers.iter().any(|(p, _)| *p == port) {
+        return Err(format!("HTTP server already running on port {}", port));
+    }
+    let server = HttpServer::with_config(router, config);
+    let handle = server.handle();
+    servers.push((port, handle.clone()));
+    drop(servers);
+
+    let stop = handle.clone();
+    crate::task::spawn(async move {
+        if let Err(e) = server.serve().await {
+            crate::log!("[HTTPD] Port {}: {:?}\n", port, e);
+        }
+        stop.stop();
+    });
+    Ok(handle)
+}
+
+/// ポートのサーバーを止める（動いていなければ false）
+pub fn stop(port: u16) -> bool {
+    let mut servers = SERVERS.lock();
+    let Some(index) = servers.iter().position(|(p, _)| *p == port) else {
+        return false;
+    };
+    servers.swap_remove(index).1.stop();
+    true
+}
+
+/// 実行中のサーバーのポート一覧
+pub fn running_ports() -> Vec<u16> {
+    SERVERS
+        .lock()
+        .iter()
+        .filter(|(_, handle)| !handle.is_stopped())
+        .map(|(port, _)| *port)
+        .collect()
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_head() {
+        let response = Response::text(Status::OK, "hi")
+            .header("Content-Length", "999")
+            .header("X-Test", "1");
+        let head = encode_head(&response, Framing::Length(2), true);
+        assert_eq!(
+            core::str::from_utf8(&head).unwrap(),
+            "HTTP/1.1 200 OK\r\nServer: Rany/0.1\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             X-Test: 1\r\nContent-Length: 2\r\nConnection: keep-alive\r\n\r\n"
+        );
+
+        let head = encode_head(
+            &Response::new(Status::NO_CONTENT),
+            Framing::None(None),
+            false,
+        );
+        assert_eq!(
+            core::str::from_utf8(&head).unwrap(),
+            "HTTP/1.1 204 No Content\r\nServer: Rany/0.1\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_chunk() {
+        assert_eq!(
+            encode_chunk(&[b'a'; 26]),
+            b"1a\r\naaaaaaaaaaaaaaaaaaaaaaaaaa\r\n"
+        );
+    }
+
+    #[test]
+    fn test_serve_connection_over_loopback() {
+        use crate::net::endpoint::tests::loopback;
+
+        let _guard = loopback::setup();
+        let (client, conn, _listener) = loopback::connect(18084);
+        let remote = client.socket().unwrap().local_addr().unwrap();
+        let router = Router::new().get("/proc/:pid", |req: Request| async move {
+            Response::text(Status::OK, req.param("pid").unwrap_or_default())
+        });
+
+        // パイプラインで2つ送り、2つ目で接続を閉じてもらう
+        let requests = b"GET /proc/7 HTTP/1.1\r\nHost: lo\r\n\r\n\
+                         GET /proc/42 HTTP/1.1\r\nHost: lo\r\nConnection: close\r\n\r\n";
+        assert_eq!(client.send(requests), Ok(requests.len()));
+        loopback::run(serve_connection(
+            Arc::new(router),
+            conn,
+            remote,
+            ServerConfig::default(),
+            Arc::new(AtomicBool::new(false)),
+        ));
+
+        // クライアントが受け取ったバイト列とEOF
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let mut eof = false;
+        for _ in 0..100 {
+            loopback::step();
+            loop {
+                match client.recv(&mut buffer) {
+                    Ok(n) => received.extend_from_slice(&buffer[..n]),
+                    Err(SocketError::NotConnected) => {
+                        eof = true;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+            if eof {
+                break;
+            }
+        }
+        assert!(eof);
+        assert_eq!(
+            core::str::from_utf8(&received).unwrap(),
+            "HTTP/1.1 200 OK\r\nServer: Rany/0.1\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: 1\r\nConnection: keep-alive\r\n\r\n7\
+             HTTP/1.1 200 OK\r\nServer: Rany/0.1\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: 2\r\nConnection: close\r\n\r\n42"
+        );
+    }
+}
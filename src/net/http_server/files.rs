@@ -0,0 +1,259 @@
+//! # 静的ファイル配信
+//!
+//! マウントテーブル上の任意のファイルシステム（無ければシェルのメモリFS）から
+//! ファイルを配信する。
+//!
+//! - ディレクトリは `index.html` を返す（末尾 '/' が無ければリダイレクト）
+//! - `ETag` と `If-None-Match` による 304
+//! - 単一の `Range: bytes=` 指定による 206 / 416（`If-Range` 対応）
+//! - 内容はストリームで少しずつ読み出す
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::task::{Context, Poll};
+
+use crate::fs::{FileAttr, FileType, FsError, FsResult, Inode, PathResolver};
+use crate::net::http::guess_content_type;
+
+use super::request::{Method, Request, percent_encode};
+use super::response::{BodyStream, Response, Status};
+
+/// 1回に読み出すバイト数
+const READ_CHUNK: usize = 16 * 1024;
+/// ディレクトリで探すファイル
+const INDEX_FILE: &str = "index.html";
+
+/// 静的ファイルのハンドラ
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    /// 配信するディレクトリ（絶対パス）
+    root: String,
+}
+
+impl StaticFiles {
+    /// `root` 以下を配信する
+    pub fn new(root: &str) -> Self {
+        Self {
+            root: String::from(root.trim_end_matches('/')),
+        }
+    }
+
+    /// `relative`（ルートからの相対パス）を配信する
+    pub fn serve(&self, request: &Request, relative: &str) -> Response {
+        if relative.split('/').any(|s| s == ".." || s.contains('\0')) {
+            return Response::error(Status::FORBIDDEN);
+        }
+        let path = format!("{}/{}", self.root, relative);
+
+        let (inode, attr, path) = match open(&path) {
+            Ok((inode, attr)) if attr.file_type == FileType::Directory => {
+                if !request.path.ends_with('/') {
+                    let location = format!("{}/", percent_encode(&request.path));
+                    return Response::redirect(Status::MOVED_PERMANENTLY, &location);
+                }
+                let index = format!("{}/{}", path.trim_end_matches('/'), INDEX_FILE);
+                match inode.lookup(INDEX_FILE).and_then(|i| Ok((i.getattr()?, i))) {
+                    Ok((attr, inode)) if attr.file_type == FileType::Regular => {
+                        (inode, attr, index)
+                    }
+                    Ok(_) | Err(FsError::NotFound) => return Response::error(Status::NOT_FOUND),
+                    Err(_) => return Response::error(Status::INTERNAL_SERVER_ERROR),
+                }
+            }
+            Ok((inode, attr)) if attr.file_type == FileType::Regular => (inode, attr, path),
+            Ok(_) => return Response::error(Status::FORBIDDEN),
+            Err(FsError::NotFound | FsError::NotDirectory) => {
+                return Response::error(Status::NOT_FOUND);
+            }
+            Err(_) => return Response::error(Status::INTERNAL_SERVER_ERROR),
+        };
+
+        let etag = entity_tag(&attr);
+        let content_type = guess_content_type(&path);
+        if request
+            .header("If-None-Match")
+            .is_some_and(|tags| etag_matches(tags, &etag))
+        {
+            return Response::new(Status::NOT_MODIFIED).header("ETag", &etag);
+        }
+
+        // If-Range が一致しなければ全体を返す
+        let range = request
+            .header("Range")
+            .filter(|_| request.method == Method::Get || request.method == Method::Head)
+            .filter(|_| request.header("If-Range").is_none_or(|tag| tag == etag));
+        let (status, start, end) = match range.map(|r| parse_range(r, attr.size)) {
+            None | Some(RangeSpec::Ignore) => (Status::OK, 0, attr.size),
+            Some(RangeSpec::Range(start, end)) => (Status::PARTIAL_CONTENT, start, end),
+            Some(RangeSpec::Unsatisfiable) => {
+                return Response::error(Status::RANGE_NOT_SATISFIABLE)
+                    .header("Content-Range", &format!("bytes */{}", attr.size));
+            }
+        };
+
+        let stream = FileStream {
+            inode,
+            offset: start,
+            end,
+        };
+        let mut response = Response::stream(status, content_type, stream, Some(end - start))
+            .header("ETag", &etag)
+            .header("Accept-Ranges", "bytes");
+        if status == Status::PARTIAL_CONTENT {
+            response = response.header(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", start, end - 1, attr.size),
+            );
+        }
+        response
+    }
+}
+
+/// パスを開く（マウントテーブル → シェルFSの順）
+fn open(path: &str) -> FsResult<(Arc<dyn Inode>, FileAttr)> {
+    let inode = match crate::fs::mount_table().find_relative(path) {
+        Some((fs, relative)) => PathResolver::new(fs.root()?).resolve(&relative)?,
+        None => crate::fs::resolve_path(path, "/")?,
+    };
+    let attr = inode.getattr()?;
+    Ok((inode, attr))
+}
+
+/// サイズ・更新時刻・inode 番号から作る強いETag
+fn entity_tag(attr: &FileAttr) -> String {
+    format!("\"{:x}-{:x}-{:x}\"", attr.size, attr.mtime, attr.ino)
+}
+
+/// `If-None-Match` の値が ETag に一致するか（弱い比較）
+fn etag_matches(tags: &str, etag: &str) -> bool {
+    tags.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// `Range` ヘッダの解釈結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeSpec {
+    /// 無視して全体を返す（複数範囲・未知の単位・構文エラー）
+    Ignore,
+    /// [start, end) のバイト範囲
+    Range(u64, u64),
+    /// 範囲がファイル外
+    Unsatisfiable,
+}
+
+/// `bytes=a-b` / `bytes=a-` / `bytes=-n` を解析
+fn parse_range(header: &str, size: u64) -> RangeSpec {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return RangeSpec::Ignore;
+    };
+    if spec.contains(',') {
+        return RangeSpec::Ignore;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return RangeSpec::Ignore;
+    };
+    let parse = |s: &str| s.trim().parse::<u64>().ok();
+
+    match (first.trim().is_empty(), last.trim().is_empty()) {
+        // 末尾 n バイト
+        (true, false) => match parse(last) {
+            Some(0) => RangeSpec::Unsatisfiable,
+            Some(n) if size > 0 => RangeSpec::Range(size.saturating_sub(n), size),
+            Some(_) => RangeSpec::Unsatisfiable,
+            None => RangeSpec::Ignore,
+        },
+        (false, _) => {
+            let Some(start) = parse(first) else {
+                return RangeSpec::Ignore;
+            };
+            let end = if last.trim().is_empty() {
+                size
+            } else {
+                match parse(last) {
+                    Some(last) if last >= start => (last + 1).min(size),
+                    _ => return RangeSpec::Ignore,
+                }
+            };
+            if start >= size {
+                RangeSpec::Unsatisfiable
+            } else {
+                RangeSpec::Range(start, end)
+            }
+        }
+        (true, true) => RangeSpec::Ignore,
+    }
+}
+
+/// ファイルの一部を順に読み出すストリーム
+struct FileStream {
+    inode: Arc<dyn Inode>,
+    offset: u64,
+    end: u64,
+}
+
+impl BodyStream for FileStream {
+    fn poll_chunk(&mut self, _cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        if self.offset >= self.end {
+            return Poll::Ready(None);
+        }
+        let len = (self.end - self.offset).min(READ_CHUNK as u64) as usize;
+        let mut chunk = alloc::vec![0u8; len];
+        match self.inode.read(self.offset, &mut chunk) {
+            Ok(n) if n > 0 => {
+                chunk.truncate(n);
+                self.offset += n as u64;
+                Poll::Ready(Some(chunk))
+            }
+            // 読めなければ途中で終える（サーバーが接続を閉じる）
+            _ => {
+                self.offset = self.end;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), RangeSpec::Range(0, 100));
+        assert_eq!(parse_range("bytes=900-", 1000), RangeSpec::Range(900, 1000));
+        assert_eq!(
+            parse_range("bytes=900-5000", 1000),
+            RangeSpec::Range(900, 1000)
+        );
+        assert_eq!(parse_range("bytes=-100", 1000), RangeSpec::Range(900, 1000));
+        assert_eq!(parse_range("bytes=-5000", 1000), RangeSpec::Range(0, 1000));
+        assert_eq!(parse_range("bytes=1000-", 1000), RangeSpec::Unsatisfiable);
+        assert_eq!(parse_range("bytes=-0", 1000), RangeSpec::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), RangeSpec::Ignore);
+        assert_eq!(parse_range("items=0-1", 1000), RangeSpec::Ignore);
+        assert_eq!(parse_range("bytes=5-1", 1000), RangeSpec::Ignore);
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let attr = FileAttr {
+            ino: 3,
+            size: 255,
+            mtime: 16,
+            ..FileAttr::default()
+        };
+        let etag = entity_tag(&attr);
+        assert_eq!(etag, "\"ff-10-3\"");
+        assert!(etag_matches("\"x\", \"ff-10-3\"", &etag));
+        assert!(etag_matches("W/\"ff-10-3\"", &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"ff-10-4\"", &etag));
+    }
+}
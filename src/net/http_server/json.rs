@@ -0,0 +1,523 @@
+//! # JSON
+//!
+//! API ハンドラ用の最小限の JSON 値。
+//!
+//! - `Display` でコンパクトな JSON 文字列に直列化する（NaN / 無限大は `null`）
+//! - `Json::parse` でリクエストボディを解析する（ネストの深さに上限あり）
+//! - オブジェクトはキーの挿入順を保つ
+//!
+//! ```ignore
+//! let body = Json::object()
+//!     .with("pid", 42u64)
+//!     .with("name", "init")
+//!     .with("threads", Json::Array(alloc::vec![1u64.into()]));
+//! Response::json(body.to_string())
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// 解析時のネストの上限
+pub const MAX_DEPTH: usize = 64;
+
+/// JSON 値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// 負の整数
+    Int(i64),
+    /// 非負の整数
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// キーと値の組（挿入順）
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// 空のオブジェクト
+    pub fn object() -> Self {
+        Json::Object(Vec::new())
+    }
+
+    /// オブジェクトにメンバーを追加する（オブジェクト以外では何もしない）
+    pub fn with(mut self, key: &str, value: impl Into<Json>) -> Self {
+        if let Json::Object(members) = &mut self {
+            members.push((key.to_string(), value.into()));
+        }
+        self
+    }
+
+    /// オブジェクトのメンバーを取得
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// 文字列として取得
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// 非負整数として取得
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Json::UInt(n) => Some(n),
+            Json::Int(n) => u64::try_from(n).ok(),
+            _ => None,
+        }
+    }
+
+    /// 整数として取得
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Int(n) => Some(n),
+            Json::UInt(n) => i64::try_from(n).ok(),
+            _ => None,
+        }
+    }
+
+    /// 真偽値として取得
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// null か
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    /// JSON テキストを解析する
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+// ============================================================================
+// 変換
+// ============================================================================
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Json {
+            fn from(value: $t) -> Self {
+                Json::UInt(value as u64)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_from_signed {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Json {
+            fn from(value: $t) -> Self {
+                if value < 0 {
+                    Json::Int(value as i64)
+                } else {
+                    Json::UInt(value as u64)
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, usize);
+impl_from_signed!(i8, i16, i32, i64, isize);
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Float(value)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+// ============================================================================
+// 直列化
+// ============================================================================
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(n) => write!(f, "{}", n),
+            Json::UInt(n) => write!(f, "{}", n),
+            // Debug 表記は整数値でも小数点を残す (15.0)
+            Json::Float(x) if x.is_finite() => write!(f, "{:?}", x),
+            Json::Float(_) => f.write_str("null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// 文字列をエスケープして書き出す
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+// ============================================================================
+// 解析
+// ============================================================================
+
+/// 解析エラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    /// 理由
+    pub message: &'static str,
+    /// バイト位置
+    pub offset: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+/// 再帰下降パーサ
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError {
+            message,
+            offset: self.pos,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// `literal` が続けば読み飛ばす
+    fn eat(&mut self, literal: &str) -> bool {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ if self.eat("null") => Ok(Json::Null),
+            _ if self.eat("true") => Ok(Json::Bool(true)),
+            _ if self.eat("false") => Ok(Json::Bool(false)),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected object key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(":") {
+                return Err(self.error("expected ':'"));
+            }
+            let value = self.value(depth + 1)?;
+            members.push((key, value));
+            self.skip_whitespace();
+            if self.eat(",") {
+                continue;
+            }
+            if self.eat("}") {
+                return Ok(Json::Object(members));
+            }
+            return Err(self.error("expected ',' or '}'"));
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.eat(",") {
+                continue;
+            }
+            if self.eat("]") {
+                return Ok(Json::Array(values));
+            }
+            return Err(self.error("expected ',' or ']'"));
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            // エスケープと終端以外はまとめてコピーする
+            let start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // 入力は &str なので ASCII 境界で切れば UTF-8 として正しい
+            out.push_str(core::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default());
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = self.peek().ok_or_else(|| self.error("bad escape"))?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => out.push(self.unicode_escape()?),
+                        _ => return Err(self.error("bad escape")),
+                    }
+                }
+                Some(_) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// `\uXXXX`（サロゲートペア対応）
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid code point"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|d| core::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("bad \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        let mut is_float = false;
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => is_float = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let text = core::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        let number = if is_float {
+            text.parse::<f64>().ok().map(Json::Float)
+        } else if text.starts_with('-') {
+            text.parse::<i64>().ok().map(Json::Int)
+        } else {
+            text.parse::<u64>().ok().map(Json::UInt)
+        };
+        // 整数の桁あふれは浮動小数点で受け取る
+        number
+            .or_else(|| text.parse::<f64>().ok().map(Json::Float))
+            .ok_or(JsonError {
+                message: "invalid number",
+                offset: start,
+            })
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let value = Json::object()
+            .with("name", "a\"b\\c\n")
+            .with("pid", 42u64)
+            .with("delta", -3i32)
+            .with("ratio", 0.5)
+            .with("nan", f64::NAN)
+            .with("tags", alloc::vec!["x", "y"])
+            .with("parent", None::<u64>)
+            .with("ok", true);
+        assert_eq!(
+            value.to_string(),
+            r#"{"name":"a\"b\\c\n","pid":42,"delta":-3,"ratio":0.5,"nan":null,"tags":["x","y"],"parent":null,"ok":true}"#
+        );
+        assert_eq!(Json::String("\u{1}".into()).to_string(), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_parse() {
+        let value =
+            Json::parse(r#" {"level": "debug", "rate": 1000, "neg": -1, "f": 1.5e1, "a": [true, null], "s": "\u00e9\ud83d\ude00\/"} "#)
+                .unwrap();
+        assert_eq!(value.get("level").and_then(Json::as_str), Some("debug"));
+        assert_eq!(value.get("rate").and_then(Json::as_u64), Some(1000));
+        assert_eq!(value.get("neg").and_then(Json::as_i64), Some(-1));
+        assert_eq!(value.get("neg").and_then(Json::as_u64), None);
+        assert_eq!(value.get("f"), Some(&Json::Float(15.0)));
+        assert_eq!(
+            value.get("a"),
+            Some(&Json::Array(alloc::vec![Json::Bool(true), Json::Null]))
+        );
+        assert_eq!(value.get("s").and_then(Json::as_str), Some("é😀/"));
+
+        // 往復
+        let text = value.to_string();
+        assert_eq!(Json::parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Json::parse("").is_err());
+        assert!(Json::parse("{").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1,]").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse("\"\\ud800\"").is_err());
+        assert!(Json::parse("\"tab\there\"").is_err());
+        assert!(Json::parse("nul").is_err());
+
+        let deep = "[".repeat(MAX_DEPTH + 2);
+        assert_eq!(Json::parse(&deep).unwrap_err().message, "nesting too deep");
+    }
+}
@@ -0,0 +1,567 @@
+//! # HTTPリクエストの解析
+//!
+//! 受信したバイト列を `RequestParser::feed` で少しずつ与え、
+//! 完成したリクエストを `next_request` で取り出すインクリメンタルパーサー。
+//! パイプライン化された後続リクエストはバッファに残り、次の呼び出しで返る。
+//!
+//! ボディは `Content-Length` と `Transfer-Encoding: chunked` に対応し、
+//! サイズは `Limits` で制限する。
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::net::endpoint::SocketAddr;
+use crate::net::http::ChunkedDecoder;
+
+/// リクエストサイズの上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// リクエスト行 + ヘッダの最大バイト数
+    pub max_header_size: usize,
+    /// ヘッダの最大個数
+    pub max_headers: usize,
+    /// ボディの最大バイト数（チャンク展開後）
+    pub max_body_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_header_size: 16 * 1024,
+            max_headers: 64,
+            max_body_size: 1024 * 1024,
+        }
+    }
+}
+
+// ============================================================================
+// メソッド / リクエスト
+// ============================================================================
+
+/// HTTPメソッド
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Patch,
+    Options,
+}
+
+impl Method {
+    /// トークンから変換（大文字小文字を区別する）
+    pub fn parse(token: &str) -> Option<Self> {
+        Some(match token {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "PATCH" => Method::Patch,
+            "OPTIONS" => Method::Options,
+            _ => return None,
+        })
+    }
+
+    /// メソッド名
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Patch => "PATCH",
+            Method::Options => "OPTIONS",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 受信したHTTPリクエスト
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// メソッド
+    pub method: Method,
+    /// パス（パーセントデコード済み、クエリを含まない）
+    pub path: String,
+    /// クエリパラメータ（デコード済み、出現順）
+    pub query: Vec<(String, String)>,
+    /// HTTP/1.1 か（false なら HTTP/1.0）
+    pub http11: bool,
+    /// ヘッダ
+    pub headers: Vec<(String, String)>,
+    /// ボディ
+    pub body: Vec<u8>,
+    /// パスパターンで取り出したパラメータ（`Router` が設定する）
+    pub params: Vec<(String, String)>,
+    /// 接続元
+    pub remote: Option<SocketAddr>,
+}
+
+impl Request {
+    /// ヘッダ値を取得（名前は大文字小文字を区別しない）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// パスパラメータを取得（`/proc/:pid` の `pid` など）
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// クエリパラメータを取得（最初の出現）
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 応答後も接続を維持するか
+    pub fn keep_alive(&self) -> bool {
+        match self.header("Connection") {
+            Some(value) if has_token(value, "close") => false,
+            Some(value) if has_token(value, "keep-alive") => true,
+            _ => self.http11,
+        }
+    }
+
+    /// ボディを文字列として取得
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// カンマ区切りのヘッダ値にトークンが含まれるか
+fn has_token(value: &str, token: &str) -> bool {
+    value
+        .split(',')
+        .any(|t| t.trim().eq_ignore_ascii_case(token))
+}
+
+// ============================================================================
+// エラー
+// ============================================================================
+
+/// リクエスト解析エラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// 構文が不正
+    BadRequest(&'static str),
+    /// ヘッダが大きすぎる・多すぎる
+    HeaderTooLarge,
+    /// ボディが上限を超えた
+    BodyTooLarge,
+    /// 未対応のメソッド
+    UnsupportedMethod(String),
+    /// 未対応のHTTPバージョン
+    UnsupportedVersion,
+}
+
+impl ParseError {
+    /// 応答に使うステータスコード
+    pub fn status(&self) -> u16 {
+        match self {
+            ParseError::BadRequest(_) => 400,
+            ParseError::BodyTooLarge => 413,
+            ParseError::HeaderTooLarge => 431,
+            ParseError::UnsupportedMethod(_) => 501,
+            ParseError::UnsupportedVersion => 505,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::BadRequest(e) => write!(f, "Bad request: {}", e),
+            ParseError::HeaderTooLarge => write!(f, "Request header too large"),
+            ParseError::BodyTooLarge => write!(f, "Request body too large"),
+            ParseError::UnsupportedMethod(m) => write!(f, "Unsupported method: {}", m),
+            ParseError::UnsupportedVersion => write!(f, "Unsupported HTTP version"),
+        }
+    }
+}
+
+// ============================================================================
+// パーサー
+// ============================================================================
+
+/// ボディの受信状態
+#[derive(Debug)]
+enum BodyState {
+    /// 残りバイト数
+    Length(usize),
+    /// チャンク形式
+    Chunked(ChunkedDecoder),
+}
+
+/// 解析状態
+#[derive(Debug)]
+enum ParseState {
+    /// リクエスト行とヘッダを待っている
+    Head,
+    /// ボディを受信中
+    Body(Request, BodyState),
+}
+
+/// インクリメンタルなリクエストパーサー
+#[derive(Debug)]
+pub struct RequestParser {
+    limits: Limits,
+    /// 未処理の受信データ
+    buffer: Vec<u8>,
+    state: ParseState,
+    /// `Expect: 100-continue` に応答すべきか
+    expect_continue: bool,
+}
+
+impl RequestParser {
+    /// 新規作成
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            buffer: Vec::new(),
+            state: ParseState::Head,
+            expect_continue: false,
+        }
+    }
+
+    /// 受信データを追加
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// リクエストの途中でない（keep-alive の待機中）か
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, ParseState::Head)
+            && self.buffer.iter().all(|b| matches!(b, b'\r' | b'\n'))
+    }
+
+    /// `100 Continue` を送るべきなら true を返す（一度だけ）
+    pub fn take_continue(&mut self) -> bool {
+        core::mem::take(&mut self.expect_continue)
+    }
+
+    /// 完成したリクエストを取り出す
+    ///
+    /// データが足りなければ `Ok(None)`。エラー後は接続を閉じること。
+    pub fn next_request(&mut self) -> Result<Option<Request>, ParseError> {
+        if matches!(self.state, ParseState::Head) && !self.parse_head()? {
+            return Ok(None);
+        }
+
+        let ParseState::Body(request, body) = &mut self.state else {
+            return Ok(None);
+        };
+        let done = match body {
+            BodyState::Length(remaining) => {
+                let take = (*remaining).min(self.buffer.len());
+                request.body.extend_from_slice(&self.buffer[..take]);
+                self.buffer.drain(..take);
+                *remaining -= take;
+                *remaining == 0
+            }
+            BodyState::Chunked(decoder) => {
+                let consumed = decoder
+                    .feed(&self.buffer, &mut request.body)
+                    .map_err(|_| ParseError::BadRequest("invalid chunked body"))?;
+                self.buffer.drain(..consumed);
+                if request.body.len() > self.limits.max_body_size {
+                    return Err(ParseError::BodyTooLarge);
+                }
+                decoder.is_done()
+            }
+        };
+        if !done {
+            return Ok(None);
+        }
+
+        self.expect_continue = false;
+        match core::mem::replace(&mut self.state, ParseState::Head) {
+            ParseState::Body(request, _) => Ok(Some(request)),
+            ParseState::Head => Ok(None),
+        }
+    }
+
+    /// リクエスト行とヘッダを解析してボディ待ちに移る
+    ///
+    /// 戻り値: ヘッダが揃っていたか
+    fn parse_head(&mut self) -> Result<bool, ParseError> {
+        // リクエスト間の空行は無視する (RFC 9112 2.2)
+        let leading = self
+            .buffer
+            .iter()
+            .take_while(|b| matches!(b, b'\r' | b'\n'))
+            .count();
+        self.buffer.drain(..leading);
+
+        let Some(end) = self.buffer.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.buffer.len() > self.limits.max_header_size {
+                return Err(ParseError::HeaderTooLarge);
+            }
+            return Ok(false);
+        };
+        if end + 4 > self.limits.max_header_size {
+            return Err(ParseError::HeaderTooLarge);
+        }
+
+        let head = core::str::from_utf8(&self.buffer[..end])
+            .map_err(|_| ParseError::BadRequest("header is not UTF-8"))?;
+        let (request, body) = parse_head(head, &self.limits)?;
+        self.expect_continue = request.http11
+            && request
+                .header("Expect")
+                .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"))
+            && !matches!(body, BodyState::Length(0));
+        self.buffer.drain(..end + 4);
+        self.state = ParseState::Body(request, body);
+        Ok(true)
+    }
+}
+
+/// ヘッダ部分（末尾の空行を除く）を解析
+fn parse_head(head: &str, limits: &Limits) -> Result<(Request, BodyState), ParseError> {
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ParseError::BadRequest("malformed request line"));
+    };
+
+    let method =
+        Method::parse(method).ok_or_else(|| ParseError::UnsupportedMethod(method.to_string()))?;
+    let http11 = match version {
+        "HTTP/1.1" => true,
+        "HTTP/1.0" => false,
+        v if v.starts_with("HTTP/") => return Err(ParseError::UnsupportedVersion),
+        _ => return Err(ParseError::BadRequest("malformed HTTP version")),
+    };
+
+    let (path, query) = match target {
+        "*" if method == Method::Options => (String::from("*"), Vec::new()),
+        t if t.starts_with('/') => {
+            let (path, query) = t.split_once('?').unwrap_or((t, ""));
+            (
+                percent_decode(path, false)
+                    .ok_or(ParseError::BadRequest("invalid path encoding"))?,
+                parse_query(query).ok_or(ParseError::BadRequest("invalid query encoding"))?,
+            )
+        }
+        _ => return Err(ParseError::BadRequest("unsupported request target")),
+    };
+
+    let mut headers = Vec::new();
+    for line in lines {
+        if headers.len() >= limits.max_headers {
+            return Err(ParseError::HeaderTooLarge);
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or(ParseError::BadRequest("malformed header line"))?;
+        if name.is_empty() || name.ends_with([' ', '\t']) {
+            return Err(ParseError::BadRequest("malformed header name"));
+        }
+        headers.push((name.to_string(), value.trim().to_string()));
+    }
+
+    let request = Request {
+        method,
+        path,
+        query,
+        http11,
+        headers,
+        body: Vec::new(),
+        params: Vec::new(),
+        remote: None,
+    };
+    if http11 && request.header("Host").is_none() {
+        return Err(ParseError::BadRequest("missing Host header"));
+    }
+
+    let body = if let Some(encoding) = request.header("Transfer-Encoding") {
+        // chunked は最後の転送コーディングでなければならない
+        let last = encoding.rsplit(',').next().unwrap_or_default().trim();
+        if !last.eq_ignore_ascii_case("chunked") {
+            return Err(ParseError::BadRequest("unsupported transfer encoding"));
+        }
+        BodyState::Chunked(ChunkedDecoder::new())
+    } else if let Some(length) = request.header("Content-Length") {
+        let length = length
+            .parse::<usize>()
+            .map_err(|_| ParseError::BadRequest("invalid Content-Length"))?;
+        if length > limits.max_body_size {
+            return Err(ParseError::BodyTooLarge);
+        }
+        BodyState::Length(length)
+    } else {
+        BodyState::Length(0)
+    };
+    Ok((request, body))
+}
+
+/// `a=1&b=x+y` 形式のクエリを解析
+fn parse_query(query: &str) -> Option<Vec<(String, String)>> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((percent_decode(name, true)?, percent_decode(value, true)?))
+        })
+        .collect()
+}
+
+/// パーセントエンコーディングを復号（`plus_as_space` ならクエリ用に '+' を空白にする）
+///
+/// 不正なエスケープや UTF-8 でない結果は `None`
+pub fn percent_decode(input: &str, plus_as_space: bool) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = core::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// パーセントエンコード（パス・クエリに安全な文字以外）
+pub fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~' | b'/') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipelined_requests() {
+        let mut parser = RequestParser::new(Limits::default());
+        parser.feed(b"GET /proc/1%202?verbose&name=a+b HTTP/1.1\r\nHost: x\r\n");
+        assert!(matches!(parser.next_request(), Ok(None)));
+        assert!(!parser.is_idle());
+
+        parser.feed(
+            b"\r\nPOST /echo HTTP/1.0\r\nContent-Length: 5\r\nConnection: keep-alive\r\n\r\nhel",
+        );
+        let first = parser.next_request().unwrap().unwrap();
+        assert_eq!(first.method, Method::Get);
+        assert_eq!(first.path, "/proc/1 2");
+        assert_eq!(first.query_param("verbose"), Some(""));
+        assert_eq!(first.query_param("name"), Some("a b"));
+        assert!(first.keep_alive());
+
+        assert!(matches!(parser.next_request(), Ok(None)));
+        parser.feed(b"lo");
+        let second = parser.next_request().unwrap().unwrap();
+        assert_eq!(second.method, Method::Post);
+        assert!(!second.http11);
+        assert!(second.keep_alive());
+        assert_eq!(second.text(), "hello");
+        assert!(parser.is_idle());
+    }
+
+    #[test]
+    fn test_chunked_body_and_continue() {
+        let mut parser = RequestParser::new(Limits::default());
+        parser.feed(
+            b"PUT /f HTTP/1.1\r\nHost: x\r\nExpect: 100-continue\r\nTransfer-Encoding: chunked\r\n\r\n",
+        );
+        assert!(matches!(parser.next_request(), Ok(None)));
+        assert!(parser.take_continue());
+        assert!(!parser.take_continue());
+
+        parser.feed(b"3\r\nabc\r\n2;ext=1\r\nde\r\n0\r\n\r\n");
+        let request = parser.next_request().unwrap().unwrap();
+        assert_eq!(request.body, b"abcde");
+    }
+
+    #[test]
+    fn test_limits_and_errors() {
+        let limits = Limits {
+            max_header_size: 64,
+            max_headers: 2,
+            max_body_size: 4,
+        };
+        let parse = |data: &[u8]| {
+            let mut parser = RequestParser::new(limits);
+            parser.feed(data);
+            parser.next_request()
+        };
+
+        assert_eq!(
+            parse(b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\n")
+                .unwrap_err()
+                .status(),
+            413
+        );
+        assert_eq!(
+            parse(b"GET / HTTP/1.1\r\nHost: x\r\nA: 1\r\nB: 2\r\n\r\n")
+                .unwrap_err()
+                .status(),
+            431
+        );
+        assert_eq!(parse(&[b'a'; 80]).unwrap_err().status(), 431);
+        assert_eq!(
+            parse(b"BREW / HTTP/1.1\r\nHost: x\r\n\r\n")
+                .unwrap_err()
+                .status(),
+            501
+        );
+        assert_eq!(
+            parse(b"GET / HTTP/2.0\r\nHost: x\r\n\r\n")
+                .unwrap_err()
+                .status(),
+            505
+        );
+        assert_eq!(parse(b"GET / HTTP/1.1\r\n\r\n").unwrap_err().status(), 400);
+        assert_eq!(
+            parse(b"GET /%zz HTTP/1.0\r\n\r\n").unwrap_err().status(),
+            400
+        );
+    }
+}
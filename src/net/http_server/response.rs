@@ -0,0 +1,407 @@
+//! # HTTPレスポンス
+//!
+//! ハンドラが返すレスポンス。ボディは一括 (`Body::Bytes`) か
+//! ストリーム (`Body::Stream`) で、長さ不明のストリームは HTTP/1.1 では
+//! chunked で送られる。
+//!
+//! ストリームは `BodyStream` を実装するか、`body_channel` で作った
+//! `BodySender` から別タスクで書き込む。
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::task::{Context, Poll, Waker};
+use spin::Mutex;
+
+/// `BodySender::send` が待機し始める未送信バイト数
+pub const BODY_CHANNEL_CAPACITY: usize = 64 * 1024;
+
+// ============================================================================
+// ステータス
+// ============================================================================
+
+/// HTTPステータスコード
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Status(pub u16);
+
+impl Status {
+    pub const CONTINUE: Status = Status(100);
+    pub const OK: Status = Status(200);
+    pub const CREATED: Status = Status(201);
+    pub const ACCEPTED: Status = Status(202);
+    pub const NO_CONTENT: Status = Status(204);
+    pub const PARTIAL_CONTENT: Status = Status(206);
+    pub const MOVED_PERMANENTLY: Status = Status(301);
+    pub const FOUND: Status = Status(302);
+    pub const NOT_MODIFIED: Status = Status(304);
+    pub const BAD_REQUEST: Status = Status(400);
+    pub const UNAUTHORIZED: Status = Status(401);
+    pub const FORBIDDEN: Status = Status(403);
+    pub const NOT_FOUND: Status = Status(404);
+    pub const METHOD_NOT_ALLOWED: Status = Status(405);
+    pub const REQUEST_TIMEOUT: Status = Status(408);
+    pub const PAYLOAD_TOO_LARGE: Status = Status(413);
+    pub const RANGE_NOT_SATISFIABLE: Status = Status(416);
+    pub const HEADER_FIELDS_TOO_LARGE: Status = Status(431);
+    pub const INTERNAL_SERVER_ERROR: Status = Status(500);
+    pub const NOT_IMPLEMENTED: Status = Status(501);
+    pub const SERVICE_UNAVAILABLE: Status = Status(503);
+    pub const VERSION_NOT_SUPPORTED: Status = Status(505);
+
+    /// 理由句
+    pub fn reason(self) -> &'static str {
+        match self.0 {
+            100 => "Continue",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            204 => "No Content",
+            206 => "Partial Content",
+            301 => "Moved Permanently",
+            302 => "Found",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            413 => "Content Too Large",
+            416 => "Range Not Satisfiable",
+            431 => "Request Header Fields Too Large",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            503 => "Service Unavailable",
+            505 => "HTTP Version Not Supported",
+            _ => "Unknown",
+        }
+    }
+
+    /// ボディを持てるステータスか（1xx / 204 / 304 は持たない）
+    pub fn allows_body(self) -> bool {
+        !(self.0 < 200 || self.0 == 204 || self.0 == 304)
+    }
+}
+
+// ============================================================================
+// ボディ
+// ============================================================================
+
+/// ストリーミングボディ
+pub trait BodyStream: Send {
+    /// 次のチャンクを返す（`None` で終端）
+    ///
+    /// まだ無ければ `cx` の Waker を登録して `Pending` を返す。
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>>;
+}
+
+/// レスポンスボディ
+pub enum Body {
+    /// 空
+    Empty,
+    /// 一括
+    Bytes(Vec<u8>),
+    /// ストリーム（`length` が分かっていれば Content-Length で送る）
+    Stream {
+        stream: Box<dyn BodyStream>,
+        length: Option<u64>,
+    },
+}
+
+impl Body {
+    /// 長さ（ストリームで不明なら `None`）
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Body::Empty => Some(0),
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream { length, .. } => *length,
+        }
+    }
+
+    /// 空ボディか
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+impl core::fmt::Debug for Body {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Body::Empty => write!(f, "Empty"),
+            Body::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Body::Stream { length, .. } => write!(f, "Stream({:?})", length),
+        }
+    }
+}
+
+/// イテレータをストリームにする
+pub struct IterStream<I>(I);
+
+impl<I: Iterator<Item = Vec<u8>> + Send> BodyStream for IterStream<I> {
+    fn poll_chunk(&mut self, _cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        Poll::Ready(self.0.next())
+    }
+}
+
+/// イテレータからストリームを作る
+pub fn iter_stream<I: Iterator<Item = Vec<u8>> + Send>(iter: I) -> IterStream<I> {
+    IterStream(iter)
+}
+
+/// チャネルの共有状態
+struct ChannelState {
+    chunks: VecDeque<Vec<u8>>,
+    queued: usize,
+    /// 送信側が閉じた
+    closed: bool,
+    /// 受信側（接続）が破棄された
+    receiver_gone: bool,
+    waker: Option<Waker>,
+}
+
+/// `body_channel` の送信側
+///
+/// 破棄されるとストリームが終端する。
+pub struct BodySender {
+    state: Arc<Mutex<ChannelState>>,
+}
+
+/// `body_channel` の受信側（レスポンスのボディになる）
+pub struct ChannelStream {
+    state: Arc<Mutex<ChannelState>>,
+}
+
+/// 別タスクから書き込めるストリーミングボディを作る
+pub fn body_channel() -> (BodySender, ChannelStream) {
+    let state = Arc::new(Mutex::new(ChannelState {
+        chunks: VecDeque::new(),
+        queued: 0,
+        closed: false,
+        receiver_gone: false,
+        waker: None,
+    }));
+    (
+        BodySender {
+            state: state.clone(),
+        },
+        ChannelStream { state },
+    )
+}
+
+impl BodySender {
+    /// チャンクを送る
+    ///
+    /// 未送信データが `BODY_CHANNEL_CAPACITY` を超えている間は待つ。
+    /// 接続が閉じられていれば false。
+    pub async fn send(&self, chunk: Vec<u8>) -> bool {
+        loop {
+            {
+                let mut state = self.state.lock();
+                if state.receiver_gone {
+                    return false;
+                }
+                if state.queued < BODY_CHANNEL_CAPACITY {
+                    state.queued += chunk.len();
+                    state.chunks.push_back(chunk);
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return true;
+                }
+            }
+            crate::task::yield_now().await;
+        }
+    }
+
+    /// 受信側がまだ読んでいるか
+    pub fn is_open(&self) -> bool {
+        !self.state.lock().receiver_gone
+    }
+}
+
+impl Drop for BodySender {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl BodyStream for ChannelStream {
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+        let mut state = self.state.lock();
+        if let Some(chunk) = state.chunks.pop_front() {
+            state.queued -= chunk.len();
+            return Poll::Ready(Some(chunk));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for ChannelStream {
+    fn drop(&mut self) {
+        self.state.lock().receiver_gone = true;
+    }
+}
+
+// ============================================================================
+// レスポンス
+// ============================================================================
+
+/// HTTPレスポンス
+#[derive(Debug)]
+pub struct Response {
+    /// ステータス
+    pub status: Status,
+    /// ヘッダ（Content-Length / Transfer-Encoding / Connection はサーバーが付ける）
+    pub headers: Vec<(String, String)>,
+    /// ボディ
+    pub body: Body,
+}
+
+impl Response {
+    /// ボディ無しのレスポンス
+    pub fn new(status: Status) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Body::Empty,
+        }
+    }
+
+    /// 内容と Content-Type を指定したレスポンス
+    pub fn bytes(status: Status, content_type: &str, body: Vec<u8>) -> Self {
+        Self::new(status)
+            .header("Content-Type", content_type)
+            .with_body(Body::Bytes(body))
+    }
+
+    /// text/plain
+    pub fn text(status: Status, text: &str) -> Self {
+        Self::bytes(
+            status,
+            "text/plain; charset=utf-8",
+            text.as_bytes().to_vec(),
+        )
+    }
+
+    /// 200 text/html
+    pub fn html(html: &str) -> Self {
+        Self::bytes(
+            Status::OK,
+            "text/html; charset=utf-8",
+            html.as_bytes().to_vec(),
+        )
+    }
+
+    /// 200 application/json
+    pub fn json(json: String) -> Self {
+        Self::bytes(Status::OK, "application/json", json.into_bytes())
+    }
+
+    /// ストリーミングレスポンス（`length` が不明なら chunked）
+    pub fn stream(
+        status: Status,
+        content_type: &str,
+        stream: impl BodyStream + 'static,
+        length: Option<u64>,
+    ) -> Self {
+        Self::new(status)
+            .header("Content-Type", content_type)
+            .with_body(Body::Stream {
+                stream: Box::new(stream),
+                length,
+            })
+    }
+
+    /// エラーページ（"404 Not Found" のような本文）
+    pub fn error(status: Status) -> Self {
+        Self::text(status, &format!("{} {}\n", status.0, status.reason()))
+    }
+
+    /// リダイレクト
+    pub fn redirect(status: Status, location: &str) -> Self {
+        Self::new(status).header("Location", location)
+    }
+
+    /// ヘッダを追加
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// ボディを設定
+    pub fn with_body(mut self, body: Body) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// ヘッダ値を取得
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(Status::NOT_FOUND.reason(), "Not Found");
+        assert!(Status::OK.allows_body());
+        assert!(!Status::NOT_MODIFIED.allows_body());
+        assert!(!Status::NO_CONTENT.allows_body());
+
+        let response = Response::error(Status::NOT_FOUND);
+        assert_eq!(
+            response.header_value("content-type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(response.body.len(), Some(14));
+    }
+
+    #[test]
+    fn test_body_channel() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let (sender, mut stream) = body_channel();
+        assert_eq!(stream.poll_chunk(&mut cx), Poll::Pending);
+
+        assert_eq!(
+            pin!(sender.send(b"ab".to_vec())).poll(&mut cx),
+            Poll::Ready(true)
+        );
+        assert_eq!(
+            stream.poll_chunk(&mut cx),
+            Poll::Ready(Some(b"ab".to_vec()))
+        );
+        drop(sender);
+        assert_eq!(stream.poll_chunk(&mut cx), Poll::Ready(None));
+
+        // 接続が閉じたら送信は失敗する
+        let (sender, stream) = body_channel();
+        drop(stream);
+        assert_eq!(
+            pin!(sender.send(b"x".to_vec())).poll(&mut cx),
+            Poll::Ready(false)
+        );
+    }
+}
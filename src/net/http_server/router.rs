@@ -0,0 +1,321 @@
+//! # ルーター
+//!
+//! メソッドとパスパターンで非同期ハンドラを選ぶ。
+//!
+//! パスパターンのセグメント:
+//! - `proc` … 完全一致
+//! - `:pid` … 任意の1セグメント（`Request::param("pid")`）
+//! - `*path` … 残り全て（末尾のみ、空でもよい）
+//!
+//! HEAD は GET のルートで処理し（ボディはサーバーが落とす）、
+//! パスは一致するがメソッドが違えば 405、OPTIONS には Allow を返す。
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+
+use super::files::StaticFiles;
+use super::request::{Method, Request};
+use super::response::{Response, Status};
+
+/// ハンドラが返す Future
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Response> + Send + 'static>>;
+
+/// リクエストハンドラ
+///
+/// `async fn handler(req: Request) -> Response` や
+/// `|req| async move { ... }` がそのまま使える。
+pub trait Handler: Send + Sync {
+    fn call(&self, request: Request) -> HandlerFuture;
+}
+
+impl<F, Fut> Handler for F
+where
+    F: Fn(Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn call(&self, request: Request) -> HandlerFuture {
+        Box::pin(self(request))
+    }
+}
+
+// ============================================================================
+// パスパターン
+// ============================================================================
+
+/// パターンのセグメント
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Rest(String),
+}
+
+/// `/proc/:pid` 形式のパスパターン
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+impl PathPattern {
+    /// パターンを解析
+    pub fn parse(pattern: &str) -> Self {
+        let segments = split_path(pattern)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Rest(name.to_string())
+                } else {
+                    Segment::Literal(segment.to_string())
+                }
+            })
+            .collect();
+        Self { segments }
+    }
+
+    /// パスに一致すればパラメータを返す
+    pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let mut parts = split_path(path);
+        let mut params = Vec::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => {
+                    if parts.next()? != literal {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = parts.next().filter(|p| !p.is_empty())?;
+                    params.push((name.clone(), value.to_string()));
+                }
+                Segment::Rest(name) => {
+                    let rest: Vec<&str> = parts.by_ref().collect();
+                    params.push((name.clone(), rest.join("/")));
+                }
+            }
+        }
+        parts.next().is_none().then_some(params)
+    }
+}
+
+/// 先頭の '/' を除いてセグメントに分ける（"/" は空セグメント1つ）
+fn split_path(path: &str) -> core::str::Split<'_, char> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+// ============================================================================
+// ルーター
+// ============================================================================
+
+/// ルート
+struct Route {
+    method: Method,
+    pattern: PathPattern,
+    handler: Arc<dyn Handler>,
+}
+
+/// ルーター
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// 空のルーター
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// ルートを追加（先に登録したものが優先）
+    pub fn route(mut self, method: Method, pattern: &str, handler: impl Handler + 'static) -> Self {
+        self.routes.push(Route {
+            method,
+            pattern: PathPattern::parse(pattern),
+            handler: Arc::new(handler),
+        });
+        self
+    }
+
+    /// GET ルート
+    pub fn get(self, pattern: &str, handler: impl Handler + 'static) -> Self {
+        self.route(Method::Get, pattern, handler)
+    }
+
+    /// POST ルート
+    pub fn post(self, pattern: &str, handler: impl Handler + 'static) -> Self {
+        self.route(Method::Post, pattern, handler)
+    }
+
+    /// PUT ルート
+    pub fn put(self, pattern: &str, handler: impl Handler + 'static) -> Self {
+        self.route(Method::Put, pattern, handler)
+    }
+
+    /// DELETE ルート
+    pub fn delete(self, pattern: &str, handler: impl Handler + 'static) -> Self {
+        self.route(Method::Delete, pattern, handler)
+    }
+
+    /// `prefix` 以下をマウント済みFSの `root` から配信する
+    pub fn static_files(self, prefix: &str, root: &str) -> Self {
+        let files = Arc::new(StaticFiles::new(root));
+        let pattern = alloc::format!("{}/*path", prefix.trim_end_matches('/'));
+        self.get(&pattern, move |request: Request| {
+            let files = files.clone();
+            async move {
+                let path = request.param("path").unwrap_or_default().to_string();
+                files.serve(&request, &path)
+            }
+        })
+    }
+
+    /// 登録済みルート数
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// ルートが無いか
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// リクエストを処理する
+    pub async fn handle(&self, mut request: Request) -> Response {
+        let mut allowed: Vec<Method> = Vec::new();
+        let mut matched = None;
+        for route in &self.routes {
+            let Some(params) = route.pattern.matches(&request.path) else {
+                continue;
+            };
+            let method_ok = route.method == request.method
+                || (request.method == Method::Head && route.method == Method::Get);
+            if method_ok {
+                matched = Some((route.handler.clone(), params));
+                break;
+            }
+            if !allowed.contains(&route.method) {
+                allowed.push(route.method);
+            }
+        }
+
+        if let Some((handler, params)) = matched {
+            request.params = params;
+            return handler.call(request).await;
+        }
+        if allowed.is_empty() {
+            return Response::error(Status::NOT_FOUND);
+        }
+
+        if allowed.contains(&Method::Get) {
+            allowed.push(Method::Head);
+        }
+        allowed.push(Method::Options);
+        let allow: Vec<&str> = allowed.iter().map(|m| m.as_str()).collect();
+        let allow = allow.join(", ");
+        if request.method == Method::Options {
+            Response::new(Status::NO_CONTENT).header("Allow", &allow)
+        } else {
+            Response::error(Status::METHOD_NOT_ALLOWED).header("Allow", &allow)
+        }
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::http_server::response::Body;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    /// すぐ完了する Future を1回だけポーリングする
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        let mut cx = Context::from_waker(Waker::noop());
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    fn request(method: Method, path: &str) -> Request {
+        Request {
+            method,
+            path: path.to_string(),
+            query: Vec::new(),
+            http11: true,
+            headers: Vec::new(),
+            body: Vec::new(),
+            params: Vec::new(),
+            remote: None,
+        }
+    }
+
+    fn body_text(response: &Response) -> &str {
+        match &response.body {
+            Body::Bytes(bytes) => core::str::from_utf8(bytes).unwrap(),
+            _ => "",
+        }
+    }
+
+    #[test]
+    fn test_path_pattern() {
+        let pattern = PathPattern::parse("/proc/:pid/status");
+        assert_eq!(
+            pattern.matches("/proc/42/status"),
+            Some(alloc::vec![("pid".into(), "42".into())])
+        );
+        assert_eq!(pattern.matches("/proc//status"), None);
+        assert_eq!(pattern.matches("/proc/42"), None);
+        assert_eq!(pattern.matches("/proc/42/status/x"), None);
+
+        let rest = PathPattern::parse("/files/*path");
+        assert_eq!(
+            rest.matches("/files/a/b.txt"),
+            Some(alloc::vec![("path".into(), "a/b.txt".into())])
+        );
+        assert_eq!(
+            rest.matches("/files"),
+            Some(alloc::vec![("path".into(), "".into())])
+        );
+        assert_eq!(PathPattern::parse("/").matches("/"), Some(Vec::new()));
+        assert_eq!(PathPattern::parse("/").matches("/x"), None);
+    }
+
+    #[test]
+    fn test_router_dispatch() {
+        let router = Router::new()
+            .get("/proc/:pid", |req: Request| async move {
+                Response::text(Status::OK, req.param("pid").unwrap_or_default())
+            })
+            .post("/proc/:pid", |_req: Request| async move {
+                Response::new(Status::ACCEPTED)
+            });
+
+        let response = ready(router.handle(request(Method::Get, "/proc/7")));
+        assert_eq!(response.status, Status::OK);
+        assert_eq!(body_text(&response), "7");
+
+        let response = ready(router.handle(request(Method::Head, "/proc/7")));
+        assert_eq!(response.status, Status::OK);
+
+        let response = ready(router.handle(request(Method::Delete, "/proc/7")));
+        assert_eq!(response.status, Status::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            response.header_value("Allow"),
+            Some("GET, POST, HEAD, OPTIONS")
+        );
+
+        let response = ready(router.handle(request(Method::Options, "/proc/7")));
+        assert_eq!(response.status, Status::NO_CONTENT);
+
+        let response = ready(router.handle(request(Method::Get, "/nope")));
+        assert_eq!(response.status, Status::NOT_FOUND);
+    }
+}
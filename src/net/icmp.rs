@@ -219,7 +219,8 @@ impl<'a> IcmpPacket<'a> {
 
     /// Verify checksum
     pub fn verify_checksum(&self) -> bool {
-        data_checksum(self.data, 0) == 0xFFFF
+        // data_checksumは補数を返すため、正しいパケットでは0になる
+        data_checksum(self.data, 0) == 0
     }
 
     /// Try to parse as echo request/reply
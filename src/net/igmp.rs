@@ -0,0 +1,184 @@
+//! IGMP (Internet Group Management Protocol) for ExoRust
+//!
+//! IGMPv2 (RFC 2236) のホスト側実装。マルチキャストグループへの
+//! 参加・脱退を通知し、ルーター（またはスイッチのIGMPスヌーピング）からの
+//! Membership Query に応答する。
+//!
+//! グループ所属はインターフェースが保持する（`NetworkInterface::join_group`）。
+//! 本モジュールはメッセージの組み立てと解析のみを行う。
+
+#![allow(dead_code)]
+
+use super::ipv4::{Ipv4Address, data_checksum};
+
+/// IGMP message size (IGMPv2)
+pub const IGMP_MESSAGE_SIZE: usize = 8;
+
+/// IP TTL for IGMP messages (link-local)
+pub const IGMP_TTL: u8 = 1;
+
+/// IGMP message type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgmpType {
+    /// Membership Query (general or group-specific)
+    MembershipQuery,
+    /// Version 1 Membership Report
+    V1Report,
+    /// Version 2 Membership Report
+    V2Report,
+    /// Leave Group
+    LeaveGroup,
+    /// Unknown type (IGMPv3 report etc.)
+    Unknown(u8),
+}
+
+impl From<u8> for IgmpType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x11 => IgmpType::MembershipQuery,
+            0x12 => IgmpType::V1Report,
+            0x16 => IgmpType::V2Report,
+            0x17 => IgmpType::LeaveGroup,
+            other => IgmpType::Unknown(other),
+        }
+    }
+}
+
+impl From<IgmpType> for u8 {
+    fn from(value: IgmpType) -> Self {
+        match value {
+            IgmpType::MembershipQuery => 0x11,
+            IgmpType::V1Report => 0x12,
+            IgmpType::V2Report => 0x16,
+            IgmpType::LeaveGroup => 0x17,
+            IgmpType::Unknown(v) => v,
+        }
+    }
+}
+
+/// Parsed IGMPv2 message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IgmpMessage {
+    /// Message type
+    pub kind: IgmpType,
+    /// Max response time (1/10 seconds, queries only)
+    pub max_resp_time: u8,
+    /// Group address (0.0.0.0 for a general query)
+    pub group: Ipv4Address,
+}
+
+impl IgmpMessage {
+    /// Membership report for a group
+    pub const fn report(group: Ipv4Address) -> Self {
+        IgmpMessage {
+            kind: IgmpType::V2Report,
+            max_resp_time: 0,
+            group,
+        }
+    }
+
+    /// Leave message for a group
+    pub const fn leave(group: Ipv4Address) -> Self {
+        IgmpMessage {
+            kind: IgmpType::LeaveGroup,
+            max_resp_time: 0,
+            group,
+        }
+    }
+
+    /// Parse an IGMP message (IPv4 payload)
+    ///
+    /// IGMPv3 クエリ（12バイト以上）も先頭8バイトを v2 として解釈する
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < IGMP_MESSAGE_SIZE || data_checksum(data, 0) != 0 {
+            return None;
+        }
+        Some(IgmpMessage {
+            kind: IgmpType::from(data[0]),
+            max_resp_time: data[1],
+            group: Ipv4Address::new([data[4], data[5], data[6], data[7]]),
+        })
+    }
+
+    /// Write the message into a buffer
+    ///
+    /// 戻り値: 書き込んだバイト数
+    pub fn write(&self, buffer: &mut [u8]) -> Option<usize> {
+        let message = buffer.get_mut(..IGMP_MESSAGE_SIZE)?;
+        message[0] = self.kind.into();
+        message[1] = self.max_resp_time;
+        message[2..4].copy_from_slice(&[0, 0]);
+        message[4..8].copy_from_slice(self.group.as_bytes());
+        let checksum = data_checksum(message, 0);
+        message[2..4].copy_from_slice(&checksum.to_be_bytes());
+        Some(IGMP_MESSAGE_SIZE)
+    }
+
+    /// Destination address for a host-originated message
+    ///
+    /// Report はグループ自身へ、Leave は全ルーターグループへ送る
+    pub fn destination(&self) -> Ipv4Address {
+        match self.kind {
+            IgmpType::LeaveGroup => Ipv4Address::ALL_ROUTERS,
+            _ => self.group,
+        }
+    }
+
+    /// Groups a query asks about, given the groups we have joined
+    ///
+    /// 一般クエリは全グループ、グループ指定クエリは該当グループのみ
+    pub fn queried_groups(&self, joined: &[Ipv4Address]) -> alloc::vec::Vec<Ipv4Address> {
+        if self.kind != IgmpType::MembershipQuery {
+            return alloc::vec::Vec::new();
+        }
+        joined
+            .iter()
+            .copied()
+            .filter(|g| self.group.is_any() || *g == self.group)
+            .collect()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_roundtrip() {
+        let group = Ipv4Address::new([224, 0, 0, 251]);
+        let mut buffer = [0u8; 16];
+        let len = IgmpMessage::report(group).write(&mut buffer).unwrap();
+        assert_eq!(len, IGMP_MESSAGE_SIZE);
+        assert_eq!(buffer[0], 0x16);
+
+        let parsed = IgmpMessage::parse(&buffer[..len]).unwrap();
+        assert_eq!(parsed, IgmpMessage::report(group));
+        assert_eq!(IgmpMessage::leave(group).destination(), Ipv4Address::ALL_ROUTERS);
+
+        // チェックサム不一致は破棄
+        buffer[5] ^= 0xff;
+        assert!(IgmpMessage::parse(&buffer[..len]).is_none());
+    }
+
+    #[test]
+    fn test_query_selects_groups() {
+        let mdns = Ipv4Address::new([224, 0, 0, 251]);
+        let other = Ipv4Address::new([239, 1, 2, 3]);
+        let joined = [mdns, other];
+
+        let general = IgmpMessage {
+            kind: IgmpType::MembershipQuery,
+            max_resp_time: 100,
+            group: Ipv4Address::ANY,
+        };
+        assert_eq!(general.queried_groups(&joined), alloc::vec![mdns, other]);
+
+        let specific = IgmpMessage { group: other, ..general };
+        assert_eq!(specific.queried_groups(&joined), alloc::vec![other]);
+        assert!(IgmpMessage::report(mdns).queried_groups(&joined).is_empty());
+    }
+}
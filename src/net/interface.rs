@@ -0,0 +1,638 @@
+//! Network Interfaces for ExoRust
+//!
+//! 複数のネットワークインターフェース（ループバック含む）と、
+//! インターフェースごとの複数IPv4アドレスを管理する。
+
+#![allow(dead_code)]
+
+use super::ethernet::MacAddress;
+use super::ipv4::Ipv4Address;
+use super::stack::TransmitFn;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+extern crate alloc;
+
+/// Interface identifier
+pub type InterfaceId = u32;
+
+/// Loopback interface ID (always present)
+pub const LOOPBACK_ID: InterfaceId = 0;
+
+/// Loopback interface name
+pub const LOOPBACK_NAME: &str = "lo";
+
+/// Loopback MTU
+pub const LOOPBACK_MTU: usize = 65535;
+
+/// Loopback address with prefix (127.0.0.1/8)
+pub const LOOPBACK_ADDRESS: InterfaceAddress = InterfaceAddress {
+    address: Ipv4Address::LOOPBACK,
+    prefix_len: 8,
+};
+
+/// Convert prefix length to netmask
+pub const fn prefix_to_mask(prefix_len: u8) -> Ipv4Address {
+    if prefix_len == 0 {
+        Ipv4Address::ANY
+    } else if prefix_len >= 32 {
+        Ipv4Address::BROADCAST
+    } else {
+        Ipv4Address::from_u32(!((1u32 << (32 - prefix_len)) - 1))
+    }
+}
+
+/// Convert netmask to prefix length (None if not contiguous)
+pub fn mask_to_prefix(mask: Ipv4Address) -> Option<u8> {
+    let bits = mask.to_u32();
+    let prefix_len = bits.leading_ones();
+    (bits.checked_shl(prefix_len).unwrap_or(0) == 0).then_some(prefix_len as u8)
+}
+
+/// Interface type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceKind {
+    /// Software loopback (no link layer)
+    Loopback,
+    /// Ethernet NIC
+    Ethernet,
+    /// WireGuard tunnel (IPv4 packets encrypted over UDP)
+    WireGuard,
+    /// 802.1Q VLAN on top of an Ethernet interface
+    Vlan,
+    /// Learning Ethernet bridge
+    Bridge,
+}
+
+impl InterfaceKind {
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            InterfaceKind::Loopback => "loopback",
+            InterfaceKind::Ethernet => "ethernet",
+            InterfaceKind::WireGuard => "wireguard",
+            InterfaceKind::Vlan => "vlan",
+            InterfaceKind::Bridge => "bridge",
+        }
+    }
+
+    /// Check if the interface carries Ethernet frames (ARP, MAC addresses)
+    pub fn has_link_layer(&self) -> bool {
+        matches!(self, InterfaceKind::Ethernet | InterfaceKind::Vlan | InterfaceKind::Bridge)
+    }
+}
+
+/// 802.1Q binding of a VLAN interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VlanLink {
+    /// Interface carrying the tagged frames
+    pub parent: InterfaceId,
+    /// VLAN ID (1-4094)
+    pub vlan_id: u16,
+    /// Priority Code Point for transmitted frames
+    pub priority: u8,
+}
+
+/// IPv4 address assigned to an interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    /// Address
+    pub address: Ipv4Address,
+    /// Prefix length (0-32)
+    pub prefix_len: u8,
+}
+
+impl InterfaceAddress {
+    /// Create a new interface address
+    pub const fn new(address: Ipv4Address, prefix_len: u8) -> Self {
+        InterfaceAddress {
+            address,
+            prefix_len,
+        }
+    }
+
+    /// Netmask
+    pub const fn netmask(&self) -> Ipv4Address {
+        prefix_to_mask(self.prefix_len)
+    }
+
+    /// Network address
+    pub const fn network(&self) -> Ipv4Address {
+        self.address.apply_mask(self.netmask())
+    }
+
+    /// Directed broadcast address of the subnet
+    pub const fn broadcast(&self) -> Ipv4Address {
+        Ipv4Address::from_u32(self.network().to_u32() | !self.netmask().to_u32())
+    }
+
+    /// Check if an address is on this subnet
+    pub const fn contains(&self, addr: &Ipv4Address) -> bool {
+        self.address.same_subnet(addr, self.netmask())
+    }
+}
+
+impl fmt::Display for InterfaceAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// Per-interface statistics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InterfaceStats {
+    /// Packets received
+    pub rx_packets: u64,
+    /// Packets transmitted
+    pub tx_packets: u64,
+    /// Bytes received
+    pub rx_bytes: u64,
+    /// Bytes transmitted
+    pub tx_bytes: u64,
+    /// Transmit errors
+    pub tx_errors: u64,
+    /// Packets dropped
+    pub dropped: u64,
+}
+
+/// Hardware offloads provided by the driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Offloads {
+    /// Driver fills in L4 checksums on transmit
+    pub tx_checksum: bool,
+    /// Driver verifies L4 checksums on receive
+    pub rx_checksum: bool,
+    /// Driver accepts TCP frames larger than the MTU and segments them
+    pub tso: bool,
+}
+
+/// Interface management errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceError {
+    /// Interface does not exist
+    NotFound,
+    /// Interface name already in use
+    AlreadyExists,
+    /// Address already assigned
+    AddressExists,
+    /// Address not assigned to the interface
+    AddressNotFound,
+    /// Prefix length out of range
+    InvalidPrefix,
+    /// Operation not permitted on this interface (loopback, primary NIC)
+    NotPermitted,
+    /// Not a multicast group address
+    InvalidGroup,
+    /// VLAN ID or priority out of range
+    InvalidVlan,
+}
+
+impl fmt::Display for InterfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterfaceError::NotFound => write!(f, "No such interface"),
+            InterfaceError::AlreadyExists => write!(f, "Interface already exists"),
+            InterfaceError::AddressExists => write!(f, "Address already assigned"),
+            InterfaceError::AddressNotFound => write!(f, "Address not assigned"),
+            InterfaceError::InvalidPrefix => write!(f, "Invalid prefix length"),
+            InterfaceError::NotPermitted => write!(f, "Operation not permitted on this interface"),
+            InterfaceError::InvalidGroup => write!(f, "Not a multicast group address"),
+            InterfaceError::InvalidVlan => write!(f, "Invalid VLAN ID"),
+        }
+    }
+}
+
+/// Network interface
+pub struct NetworkInterface {
+    /// Interface ID
+    pub id: InterfaceId,
+    /// Name (lo, eth0, ...)
+    pub name: String,
+    /// Interface type
+    pub kind: InterfaceKind,
+    /// Hardware address (zero for loopback)
+    pub mac: MacAddress,
+    /// MTU (IP packet size)
+    pub mtu: usize,
+    /// Administrative state
+    pub up: bool,
+    /// Statistics
+    pub stats: InterfaceStats,
+    /// Driver offloads
+    pub offloads: Offloads,
+    /// Parent interface and tag (VLAN only)
+    pub vlan: Option<VlanLink>,
+    /// Bridge this interface is a port of
+    pub master: Option<InterfaceId>,
+    /// Assigned addresses (first = primary)
+    addresses: Vec<InterfaceAddress>,
+    /// Joined multicast groups (group, reference count)
+    groups: Vec<(Ipv4Address, u32)>,
+    /// Driver transmit callback (Ethernet only)
+    transmit_fn: Option<TransmitFn>,
+}
+
+impl NetworkInterface {
+    /// Create a new interface (down, no addresses)
+    pub fn new(id: InterfaceId, name: &str, kind: InterfaceKind, mac: MacAddress, mtu: usize) -> Self {
+        NetworkInterface {
+            id,
+            name: String::from(name),
+            kind,
+            mac,
+            mtu,
+            up: false,
+            stats: InterfaceStats::default(),
+            offloads: Offloads::default(),
+            vlan: None,
+            master: None,
+            addresses: Vec::new(),
+            groups: Vec::new(),
+            transmit_fn: None,
+        }
+    }
+
+    /// Check if this is the loopback interface
+    #[inline]
+    pub fn is_loopback(&self) -> bool {
+        self.kind == InterfaceKind::Loopback
+    }
+
+    /// Assigned addresses
+    pub fn addresses(&self) -> &[InterfaceAddress] {
+        &self.addresses
+    }
+
+    /// Primary address
+    pub fn primary_address(&self) -> Option<InterfaceAddress> {
+        self.addresses.first().copied()
+    }
+
+    /// Check if an address is assigned to this interface
+    pub fn has_address(&self, addr: &Ipv4Address) -> bool {
+        self.addresses.iter().any(|a| a.address == *addr)
+    }
+
+    /// Check if an address is local to this interface
+    ///
+    /// ループバックはサブネット全体 (127.0.0.0/8) をローカルとして扱う
+    pub fn owns(&self, addr: &Ipv4Address) -> bool {
+        if self.is_loopback() {
+            self.addresses.iter().any(|a| a.contains(addr))
+        } else {
+            self.has_address(addr)
+        }
+    }
+
+    /// Check if an address is a directed broadcast of one of our subnets
+    pub fn is_subnet_broadcast(&self, addr: &Ipv4Address) -> bool {
+        self.addresses
+            .iter()
+            .any(|a| a.prefix_len < 31 && a.broadcast() == *addr)
+    }
+
+    /// Assign an address
+    pub fn add_address(&mut self, addr: InterfaceAddress) -> Result<(), InterfaceError> {
+        if addr.prefix_len > 32 {
+            return Err(InterfaceError::InvalidPrefix);
+        }
+        if self.has_address(&addr.address) {
+            return Err(InterfaceError::AddressExists);
+        }
+        self.addresses.push(addr);
+        Ok(())
+    }
+
+    /// Remove an address
+    pub fn remove_address(&mut self, addr: &Ipv4Address) -> Result<InterfaceAddress, InterfaceError> {
+        let index = self
+            .addresses
+            .iter()
+            .position(|a| a.address == *addr)
+            .ok_or(InterfaceError::AddressNotFound)?;
+        Ok(self.addresses.remove(index))
+    }
+
+    /// Replace the primary address (DHCP / static config)
+    ///
+    /// 戻り値: 置き換えられた以前のプライマリアドレス
+    pub fn set_primary_address(&mut self, addr: Option<InterfaceAddress>) -> Option<InterfaceAddress> {
+        let old = if self.addresses.is_empty() {
+            None
+        } else {
+            Some(self.addresses.remove(0))
+        };
+        if let Some(addr) = addr {
+            self.addresses.retain(|a| a.address != addr.address);
+            self.addresses.insert(0, addr);
+        }
+        old
+    }
+
+    /// Join a multicast group
+    ///
+    /// 参照カウント方式。戻り値: 新規に参加した場合 true（IGMP Report が必要）
+    pub fn join_group(&mut self, group: Ipv4Address) -> Result<bool, InterfaceError> {
+        if !group.is_multicast() || group == Ipv4Address::ALL_HOSTS {
+            return Err(InterfaceError::InvalidGroup);
+        }
+        if self.is_loopback() {
+            return Err(InterfaceError::NotPermitted);
+        }
+        match self.groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, refs)) => {
+                *refs += 1;
+                Ok(false)
+            }
+            None => {
+                self.groups.push((group, 1));
+                Ok(true)
+            }
+        }
+    }
+
+    /// Leave a multicast group
+    ///
+    /// 戻り値: 最後の参照が外れた場合 true（IGMP Leave が必要）
+    pub fn leave_group(&mut self, group: Ipv4Address) -> bool {
+        let Some(index) = self.groups.iter().position(|(g, _)| *g == group) else {
+            return false;
+        };
+        self.groups[index].1 -= 1;
+        if self.groups[index].1 == 0 {
+            self.groups.remove(index);
+            return true;
+        }
+        false
+    }
+
+    /// Joined multicast groups (excluding the implicit all-hosts group)
+    pub fn groups(&self) -> impl Iterator<Item = Ipv4Address> + '_ {
+        self.groups.iter().map(|(g, _)| *g)
+    }
+
+    /// Check if the interface receives a multicast group
+    ///
+    /// Ethernet インターフェースは全ホストグループ (224.0.0.1) に常に所属する
+    pub fn is_member(&self, group: &Ipv4Address) -> bool {
+        (*group == Ipv4Address::ALL_HOSTS && !self.is_loopback())
+            || self.groups.iter().any(|(g, _)| g == group)
+    }
+
+    /// Set the driver transmit callback
+    pub fn set_transmit_fn(&mut self, f: TransmitFn) {
+        self.transmit_fn = Some(f);
+    }
+
+    /// Transmit a link-layer frame through the driver
+    pub fn transmit(&mut self, frame: &[u8]) -> bool {
+        if !self.up {
+            self.stats.dropped += 1;
+            return false;
+        }
+        match self.transmit_fn {
+            Some(f) if f(frame) => {
+                self.record_tx(frame.len());
+                true
+            }
+            _ => {
+                self.stats.tx_errors += 1;
+                false
+            }
+        }
+    }
+
+    /// Record transmitted packet
+    pub fn record_tx(&mut self, len: usize) {
+        self.stats.tx_packets += 1;
+        self.stats.tx_bytes += len as u64;
+    }
+
+    /// Record received packet
+    pub fn record_rx(&mut self, len: usize) {
+        self.stats.rx_packets += 1;
+        self.stats.rx_bytes += len as u64;
+    }
+}
+
+/// Interface table
+pub struct InterfaceTable {
+    /// Interfaces (ordered by ID)
+    interfaces: Vec<NetworkInterface>,
+    /// Next interface ID
+    next_id: InterfaceId,
+}
+
+impl InterfaceTable {
+    /// Create a table containing only the loopback interface (up, 127.0.0.1/8)
+    pub fn new() -> Self {
+        let mut lo = NetworkInterface::new(
+            LOOPBACK_ID,
+            LOOPBACK_NAME,
+            InterfaceKind::Loopback,
+            MacAddress::ZERO,
+            LOOPBACK_MTU,
+        );
+        lo.addresses.push(LOOPBACK_ADDRESS);
+        lo.up = true;
+
+        InterfaceTable {
+            interfaces: alloc::vec![lo],
+            next_id: LOOPBACK_ID + 1,
+        }
+    }
+
+    /// Add an interface (initially down)
+    pub fn add(
+        &mut self,
+        name: &str,
+        kind: InterfaceKind,
+        mac: MacAddress,
+        mtu: usize,
+    ) -> Result<InterfaceId, InterfaceError> {
+        if self.by_name(name).is_some() {
+            return Err(InterfaceError::AlreadyExists);
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.interfaces
+            .push(NetworkInterface::new(id, name, kind, mac, mtu));
+        Ok(id)
+    }
+
+    /// Remove an interface
+    pub fn remove(&mut self, id: InterfaceId) -> Result<NetworkInterface, InterfaceError> {
+        if id == LOOPBACK_ID {
+            return Err(InterfaceError::NotPermitted);
+        }
+        let index = self
+            .interfaces
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or(InterfaceError::NotFound)?;
+        Ok(self.interfaces.remove(index))
+    }
+
+    /// Get interface by ID
+    pub fn get(&self, id: InterfaceId) -> Option<&NetworkInterface> {
+        self.interfaces.iter().find(|i| i.id == id)
+    }
+
+    /// Get mutable interface by ID
+    pub fn get_mut(&mut self, id: InterfaceId) -> Option<&mut NetworkInterface> {
+        self.interfaces.iter_mut().find(|i| i.id == id)
+    }
+
+    /// Get interface by name
+    pub fn by_name(&self, name: &str) -> Option<&NetworkInterface> {
+        self.interfaces.iter().find(|i| i.name == name)
+    }
+
+    /// Get mutable interface by name
+    pub fn by_name_mut(&mut self, name: &str) -> Option<&mut NetworkInterface> {
+        self.interfaces.iter_mut().find(|i| i.name == name)
+    }
+
+    /// Iterate over all interfaces
+    pub fn iter(&self) -> impl Iterator<Item = &NetworkInterface> {
+        self.interfaces.iter()
+    }
+
+    /// First Ethernet interface (the primary NIC)
+    pub fn primary_ethernet(&self) -> Option<&NetworkInterface> {
+        self.interfaces
+            .iter()
+            .find(|i| i.kind == InterfaceKind::Ethernet)
+    }
+
+    /// VLAN interface for a tag received on a parent interface
+    pub fn vlan_child(&self, parent: InterfaceId, vlan_id: u16) -> Option<&NetworkInterface> {
+        self.interfaces
+            .iter()
+            .find(|i| i.vlan.is_some_and(|v| v.parent == parent && v.vlan_id == vlan_id))
+    }
+
+    /// Interface that owns a local address (up interfaces only)
+    pub fn owner_of(&self, addr: &Ipv4Address) -> Option<InterfaceId> {
+        // 明示的に割り当てられたアドレスを優先し、次にループバックのサブネット
+        self.interfaces
+            .iter()
+            .filter(|i| i.up)
+            .find(|i| i.has_address(addr))
+            .or_else(|| self.interfaces.iter().filter(|i| i.up).find(|i| i.owns(addr)))
+            .map(|i| i.id)
+    }
+
+    /// Check if an address is local
+    pub fn is_local(&self, addr: &Ipv4Address) -> bool {
+        self.owner_of(addr).is_some()
+    }
+
+    /// Check if a destination should be accepted by the host
+    /// (local address, limited broadcast, subnet broadcast or joined group)
+    pub fn accepts(&self, addr: &Ipv4Address) -> bool {
+        addr.is_broadcast()
+            || self.is_local(addr)
+            || self.interfaces.iter().any(|i| {
+                i.up && (i.is_subnet_broadcast(addr) || (addr.is_multicast() && i.is_member(addr)))
+            })
+    }
+}
+
+impl Default for InterfaceTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_mask_conversion() {
+        assert_eq!(prefix_to_mask(24), Ipv4Address::new([255, 255, 255, 0]));
+        assert_eq!(prefix_to_mask(0), Ipv4Address::ANY);
+        assert_eq!(prefix_to_mask(32), Ipv4Address::BROADCAST);
+        assert_eq!(mask_to_prefix(Ipv4Address::new([255, 255, 240, 0])), Some(20));
+        assert_eq!(mask_to_prefix(Ipv4Address::ANY), Some(0));
+        assert_eq!(mask_to_prefix(Ipv4Address::new([255, 0, 255, 0])), None);
+    }
+
+    #[test]
+    fn test_loopback_always_present() {
+        let table = InterfaceTable::new();
+        let lo = table.by_name(LOOPBACK_NAME).unwrap();
+        assert!(lo.up);
+        assert!(lo.is_loopback());
+        assert_eq!(table.owner_of(&Ipv4Address::LOOPBACK), Some(LOOPBACK_ID));
+        // 127.0.0.0/8 全体がローカル
+        assert!(table.is_local(&Ipv4Address::new([127, 1, 2, 3])));
+    }
+
+    #[test]
+    fn test_multiple_addresses() {
+        let mut table = InterfaceTable::new();
+        let id = table
+            .add("eth0", InterfaceKind::Ethernet, MacAddress::ZERO, 1500)
+            .unwrap();
+        let eth0 = table.get_mut(id).unwrap();
+        eth0.up = true;
+        eth0.add_address(InterfaceAddress::new(Ipv4Address::new([10, 0, 2, 15]), 24))
+            .unwrap();
+        eth0.add_address(InterfaceAddress::new(Ipv4Address::new([192, 168, 7, 1]), 24))
+            .unwrap();
+        assert_eq!(
+            eth0.add_address(InterfaceAddress::new(Ipv4Address::new([10, 0, 2, 15]), 24)),
+            Err(InterfaceError::AddressExists)
+        );
+
+        assert_eq!(table.owner_of(&Ipv4Address::new([192, 168, 7, 1])), Some(id));
+        assert!(!table.is_local(&Ipv4Address::new([10, 0, 2, 16])));
+        assert!(table.accepts(&Ipv4Address::new([192, 168, 7, 255])));
+
+        // ダウンしたインターフェースのアドレスはローカルではない
+        table.get_mut(id).unwrap().up = false;
+        assert!(!table.is_local(&Ipv4Address::new([10, 0, 2, 15])));
+
+        assert_eq!(table.remove(LOOPBACK_ID).err(), Some(InterfaceError::NotPermitted));
+    }
+
+    #[test]
+    fn test_multicast_membership() {
+        let mut table = InterfaceTable::new();
+        let id = table
+            .add("eth0", InterfaceKind::Ethernet, MacAddress::ZERO, 1500)
+            .unwrap();
+        table.get_mut(id).unwrap().up = true;
+        let mdns = Ipv4Address::new([224, 0, 0, 251]);
+
+        // 全ホストグループは参加操作なしで受信
+        assert!(table.accepts(&Ipv4Address::ALL_HOSTS));
+        assert!(!table.accepts(&mdns));
+
+        let eth0 = table.get_mut(id).unwrap();
+        assert_eq!(eth0.join_group(mdns), Ok(true));
+        assert_eq!(eth0.join_group(mdns), Ok(false));
+        assert_eq!(
+            eth0.join_group(Ipv4Address::new([10, 0, 0, 1])),
+            Err(InterfaceError::InvalidGroup)
+        );
+        assert!(table.accepts(&mdns));
+
+        // 参照カウントが 0 になった時点で脱退
+        let eth0 = table.get_mut(id).unwrap();
+        assert!(!eth0.leave_group(mdns));
+        assert!(eth0.leave_group(mdns));
+        assert!(!table.accepts(&mdns));
+
+        let lo = table.get_mut(LOOPBACK_ID).unwrap();
+        assert_eq!(lo.join_group(mdns), Err(InterfaceError::NotPermitted));
+    }
+}
@@ -23,6 +23,12 @@ impl Ipv4Address {
     /// Loopback address (127.0.0.1)
     pub const LOOPBACK: Ipv4Address = Ipv4Address([127, 0, 0, 1]);
 
+    /// All-hosts multicast group (224.0.0.1)
+    pub const ALL_HOSTS: Ipv4Address = Ipv4Address([224, 0, 0, 1]);
+
+    /// All-routers multicast group (224.0.0.2)
+    pub const ALL_ROUTERS: Ipv4Address = Ipv4Address([224, 0, 0, 2]);
+
     /// Create from bytes
     pub const fn new(bytes: [u8; 4]) -> Self {
         Ipv4Address(bytes)
@@ -128,6 +134,8 @@ impl fmt::Display for Ipv4Address {
 pub enum IpProtocol {
     /// Internet Control Message Protocol
     Icmp = 1,
+    /// Internet Group Management Protocol
+    Igmp = 2,
     /// Transmission Control Protocol
     Tcp = 6,
     /// User Datagram Protocol
@@ -142,6 +150,7 @@ impl From<u8> for IpProtocol {
     fn from(value: u8) -> Self {
         match value {
             1 => IpProtocol::Icmp,
+            2 => IpProtocol::Igmp,
             6 => IpProtocol::Tcp,
             17 => IpProtocol::Udp,
             47 => IpProtocol::Gre,
@@ -154,6 +163,7 @@ impl From<IpProtocol> for u8 {
     fn from(value: IpProtocol) -> Self {
         match value {
             IpProtocol::Icmp => 1,
+            IpProtocol::Igmp => 2,
             IpProtocol::Tcp => 6,
             IpProtocol::Udp => 17,
             IpProtocol::Gre => 47,
@@ -641,6 +651,8 @@ pub struct Ipv4Stats {
 pub enum Ipv4ProcessResult<'a> {
     /// ICMP packet
     Icmp(&'a [u8], Ipv4Address),
+    /// IGMP packet (payload, source, destination)
+    Igmp(&'a [u8], Ipv4Address, Ipv4Address),
     /// TCP packet
     Tcp(&'a [u8], Ipv4Address, Ipv4Address),
     /// UDP packet
@@ -678,6 +690,19 @@ impl Ipv4Processor {
 
     /// Process an incoming IPv4 packet
     pub fn process<'a>(&mut self, data: &'a [u8]) -> Ipv4ProcessResult<'a> {
+        let config = self.config;
+        self.process_with(data, |dst| Self::is_for(&config, dst))
+    }
+
+    /// Process an incoming IPv4 packet with a custom local-address filter
+    ///
+    /// 複数インターフェース・複数アドレス構成では、スタックが
+    /// インターフェーステーブルに基づく判定を渡す。
+    pub fn process_with<'a>(
+        &mut self,
+        data: &'a [u8],
+        is_local: impl Fn(&Ipv4Address) -> bool,
+    ) -> Ipv4ProcessResult<'a> {
         let packet = match Ipv4Packet::parse(data) {
             Some(p) => p,
             None => {
@@ -694,7 +719,7 @@ impl Ipv4Processor {
 
         // Check destination
         let dst = packet.destination();
-        if !self.is_for_us(&dst) {
+        if !is_local(&dst) {
             self.stats.rx_dropped += 1;
             return Ipv4ProcessResult::Dropped;
         }
@@ -706,17 +731,16 @@ impl Ipv4Processor {
 
         match packet.protocol() {
             IpProtocol::Icmp => Ipv4ProcessResult::Icmp(payload, src),
+            IpProtocol::Igmp => Ipv4ProcessResult::Igmp(payload, src, dst),
             IpProtocol::Tcp => Ipv4ProcessResult::Tcp(payload, src, dst),
             IpProtocol::Udp => Ipv4ProcessResult::Udp(payload, src, dst),
             _ => Ipv4ProcessResult::Dropped,
         }
     }
 
-    /// Check if a packet is for us
-    fn is_for_us(&self, addr: &Ipv4Address) -> bool {
-        *addr == self.config.address
-            || addr.is_broadcast()
-            || *addr == self.config.broadcast_address()
+    /// Check if a packet is for us (single-address configuration)
+    fn is_for(config: &Ipv4Config, addr: &Ipv4Address) -> bool {
+        *addr == config.address || addr.is_broadcast() || *addr == config.broadcast_address()
     }
 
     /// Get next packet ID
@@ -794,6 +818,30 @@ pub fn data_checksum(data: &[u8], initial: u32) -> u16 {
     !(sum as u16)
 }
 
+/// Incrementally update a checksum after `old` bytes were replaced by `new` (RFC 1624)
+///
+/// `old` と `new` は同じ長さで、チェックサム対象の16ビット境界から始まること。
+/// NAT や TTL の書き換えでパケット全体を再計算せずに済ませる。
+pub fn checksum_adjust(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+    fn words(data: &[u8]) -> impl Iterator<Item = u32> + '_ {
+        data.chunks(2)
+            .map(|w| u16::from_be_bytes([w[0], w.get(1).copied().unwrap_or(0)]) as u32)
+    }
+
+    // HC' = ~(~HC + ~m + m')
+    let mut sum = (!checksum) as u32;
+    for word in words(old) {
+        sum += !word & 0xFFFF;
+    }
+    for word in words(new) {
+        sum += word;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -816,4 +864,21 @@ mod tests {
 
         assert!(addr1.same_subnet(&addr2, mask));
     }
+
+    #[test]
+    fn test_checksum_adjust() {
+        let mut data = [0x45u8, 0x00, 0x00, 0x54, 0x12, 0x34, 0x40, 0x00, 0x40, 0x01, 0, 0, 10, 0, 0, 5, 8, 8, 8, 8];
+        let checksum = data_checksum(&data, 0);
+
+        // 送信元アドレスの書き換え
+        let new_src = [192, 168, 1, 7];
+        let adjusted = checksum_adjust(checksum, &data[12..16], &new_src);
+        data[12..16].copy_from_slice(&new_src);
+        assert_eq!(adjusted, data_checksum(&data, 0));
+
+        // TTL とプロトコルの16ビット語
+        let adjusted = checksum_adjust(adjusted, &[0x40, 0x01], &[0x3F, 0x01]);
+        data[8] = 0x3F;
+        assert_eq!(adjusted, data_checksum(&data, 0));
+    }
 }
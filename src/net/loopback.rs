@@ -0,0 +1,75 @@
+//! Loopback Device for ExoRust
+//!
+//! 127.0.0.0/8 および自ホストのアドレス宛てのIPv4パケットを
+//! NICを介さずに受信側へ折り返すソフトウェアデバイス。
+//!
+//! 送信パスはネットワークスタックのロックを保持したまま呼ばれるため、
+//! パケットはキューに積むだけとし、受信処理はロック解放後に
+//! `stack::poll_loopback()` で行う（再入によるデッドロック回避）。
+
+#![allow(dead_code)]
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+extern crate alloc;
+
+/// Maximum queued packets
+pub const LOOPBACK_QUEUE_LEN: usize = 256;
+
+/// Loopback device (IPv4 packets, no link-layer header)
+#[derive(Debug)]
+pub struct LoopbackDevice {
+    /// Packets waiting for local delivery
+    queue: VecDeque<Vec<u8>>,
+    /// Packets dropped because the queue was full
+    dropped: u64,
+}
+
+impl LoopbackDevice {
+    /// Create a new loopback device
+    pub const fn new() -> Self {
+        LoopbackDevice {
+            queue: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Queue an IPv4 packet for delivery
+    ///
+    /// 戻り値: キューに積めたら true
+    pub fn enqueue(&mut self, packet: Vec<u8>) -> bool {
+        if self.queue.len() >= LOOPBACK_QUEUE_LEN {
+            self.dropped += 1;
+            return false;
+        }
+        self.queue.push_back(packet);
+        true
+    }
+
+    /// Take the next queued packet
+    pub fn dequeue(&mut self) -> Option<Vec<u8>> {
+        self.queue.pop_front()
+    }
+
+    /// Number of queued packets
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Packets dropped due to queue overflow
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Default for LoopbackDevice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
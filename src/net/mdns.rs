@@ -0,0 +1,795 @@
+//! # mDNS / DNS-SD - マルチキャストDNS
+//!
+//! DNSサーバーのないリンク上で `<hostname>.local` の名前解決と
+//! サービス探索を行う（RFC 6762 / RFC 6763）。
+//!
+//! - レスポンダ: 自ホストのAレコードと登録済みサービスの PTR/SRV/TXT に応答し、
+//!   起動時・アドレス変更時にアナウンスする
+//! - リゾルバ: 受信した応答をキャッシュし、`.local` 名の解決とサービス一覧に使う
+//!
+//! パケットの送受信はネットワークスタックが行い（UDP 5353番、224.0.0.251）、
+//! 本モジュールはメッセージの生成と解釈を担う。
+//! ロック順序: NETWORK_STACK → MDNS（MDNS を保持したままスタックをロックしない）
+
+#![allow(dead_code)]
+
+pub mod message;
+
+pub use message::{Message, MessageError, Question, Record, RecordData};
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use super::ipv4::Ipv4Address;
+use super::stack;
+use message::{TYPE_A, TYPE_PTR, TYPE_SRV, TYPE_TXT};
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// mDNS ポート
+pub const MDNS_PORT: u16 = 5353;
+
+/// mDNS マルチキャストグループ
+pub const MDNS_GROUP: Ipv4Address = Ipv4Address::new([224, 0, 0, 251]);
+
+/// mDNS パケットの IP TTL（受信側はリンク外からの応答を拒否できる）
+pub const MDNS_IP_TTL: u8 = 255;
+
+/// ホスト名に紐づくレコード (A, SRV) の TTL（秒）
+pub const HOST_RECORD_TTL: u32 = 120;
+
+/// その他のレコード (PTR, TXT) の TTL（秒）
+pub const SERVICE_RECORD_TTL: u32 = 4500;
+
+/// 既定のホスト名（/etc/hostname の初期値と同じ）
+pub const DEFAULT_HOSTNAME: &str = "ranyos";
+
+/// サービス種別の一覧を返すメタクエリ名 (RFC 6763 §9)
+pub const SERVICES_META: &str = "_services._dns-sd._udp.local";
+
+/// 名前解決の待ち時間（ミリ秒）
+pub const RESOLVE_TIMEOUT_MS: u64 = 1000;
+
+/// 問い合わせの再送間隔（ミリ秒）
+const QUERY_INTERVAL_MS: u64 = 250;
+
+/// レガシーユニキャスト応答の最大TTL（秒, RFC 6762 §6.7）
+const LEGACY_UNICAST_TTL: u32 = 10;
+
+/// キャッシュの最大レコード数
+const MAX_CACHE_RECORDS: usize = 256;
+
+// ============================================================================
+// サービス
+// ============================================================================
+
+/// 公開するサービス
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Service {
+    /// サービス種別 (`_http._tcp`)
+    pub service_type: String,
+    /// ポート番号
+    pub port: u16,
+    /// TXT レコードの内容 (`key=value`)
+    pub txt: Vec<String>,
+}
+
+impl Service {
+    /// サービスを作成
+    pub fn new(service_type: &str, port: u16) -> Self {
+        Service {
+            service_type: String::from(service_type),
+            port,
+            txt: Vec::new(),
+        }
+    }
+
+    /// TXT エントリを追加
+    pub fn with_txt(mut self, entry: &str) -> Self {
+        self.txt.push(String::from(entry));
+        self
+    }
+
+    /// 種別名 (`_http._tcp.local`)
+    pub fn type_name(&self) -> String {
+        format!("{}.local", self.service_type)
+    }
+
+    /// インスタンス名 (`<hostname>._http._tcp.local`)
+    pub fn instance_name(&self, hostname: &str) -> String {
+        format!("{}.{}.local", hostname, self.service_type)
+    }
+}
+
+/// サービス種別の書式チェック (`_name._tcp` / `_name._udp`)
+pub fn valid_service_type(service_type: &str) -> bool {
+    let mut labels = service_type.split('.');
+    match (labels.next(), labels.next(), labels.next()) {
+        (Some(name), Some(proto), None) => {
+            name.len() > 1
+                && name.len() <= 16
+                && name.starts_with('_')
+                && name[1..].bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                && (proto == "_tcp" || proto == "_udp")
+        }
+        _ => false,
+    }
+}
+
+/// 探索で見つかったサービスインスタンス
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInstance {
+    /// インスタンス名
+    pub name: String,
+    /// ホスト名 (SRV ターゲット)
+    pub host: Option<String>,
+    /// ポート番号
+    pub port: Option<u16>,
+    /// ホストのアドレス
+    pub address: Option<Ipv4Address>,
+    /// TXT レコード
+    pub txt: Vec<String>,
+}
+
+/// キャッシュされたレコード
+#[derive(Debug, Clone)]
+pub struct CachedRecord {
+    /// レコード
+    pub record: Record,
+    /// 失効時刻（ミリ秒）
+    pub expires_ms: u64,
+}
+
+/// mDNS 統計
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MdnsStats {
+    /// 受信した問い合わせ
+    pub queries_received: u64,
+    /// 送信した応答
+    pub responses_sent: u64,
+    /// 受信した応答
+    pub responses_received: u64,
+    /// 送信した問い合わせ
+    pub queries_sent: u64,
+    /// 解析できなかったパケット
+    pub errors: u64,
+}
+
+// ============================================================================
+// レスポンダ / キャッシュ
+// ============================================================================
+
+/// mDNS レスポンダとリゾルバキャッシュ
+pub struct MdnsResponder {
+    /// ホスト名（`.local` を含まない）
+    hostname: String,
+    /// 公開サービス
+    services: Vec<Service>,
+    /// 受信したレコード
+    cache: Vec<CachedRecord>,
+    /// 統計
+    stats: MdnsStats,
+}
+
+impl MdnsResponder {
+    /// レスポンダを作成（サービスなし）
+    pub fn new(hostname: &str) -> Self {
+        MdnsResponder {
+            hostname: String::from(hostname),
+            services: Vec::new(),
+            cache: Vec::new(),
+            stats: MdnsStats::default(),
+        }
+    }
+
+    /// カーネル標準のサービス（HTTP, エコー）を公開するレスポンダ
+    pub fn with_default_services() -> Self {
+        let mut responder = Self::new(DEFAULT_HOSTNAME);
+        responder.add_service(Service::new("_http._tcp", 80).with_txt("path=/"));
+        responder.add_service(Service::new("_echo._tcp", 8080));
+        responder
+    }
+
+    /// ホスト名
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// 完全修飾ホスト名 (`<hostname>.local`)
+    pub fn host_name(&self) -> String {
+        format!("{}.local", self.hostname)
+    }
+
+    /// ホスト名を変更
+    pub fn set_hostname(&mut self, hostname: &str) {
+        self.hostname = String::from(hostname);
+    }
+
+    /// 公開サービス
+    pub fn services(&self) -> &[Service] {
+        &self.services
+    }
+
+    /// サービスを公開（同じ種別は置き換え）
+    pub fn add_service(&mut self, service: Service) {
+        self.services.retain(|s| s.service_type != service.service_type);
+        self.services.push(service);
+    }
+
+    /// サービスの公開をやめる
+    pub fn remove_service(&mut self, service_type: &str) -> Option<Service> {
+        let index = self
+            .services
+            .iter()
+            .position(|s| s.service_type == service_type)?;
+        Some(self.services.remove(index))
+    }
+
+    /// 統計
+    pub fn stats(&self) -> MdnsStats {
+        self.stats
+    }
+
+    /// サービス1つ分のレコード (PTR, SRV, TXT)
+    fn service_records(&self, service: &Service, ttl_scale: u32) -> [Record; 3] {
+        let instance = service.instance_name(&self.hostname);
+        [
+            Record::new(
+                &service.type_name(),
+                SERVICE_RECORD_TTL * ttl_scale,
+                false,
+                RecordData::Ptr(instance.clone()),
+            ),
+            Record::new(
+                &instance,
+                HOST_RECORD_TTL * ttl_scale,
+                true,
+                RecordData::Srv {
+                    priority: 0,
+                    weight: 0,
+                    port: service.port,
+                    target: self.host_name(),
+                },
+            ),
+            Record::new(
+                &instance,
+                SERVICE_RECORD_TTL * ttl_scale,
+                true,
+                RecordData::Txt(service.txt.clone()),
+            ),
+        ]
+    }
+
+    /// 自ホストが権威を持つ全レコード
+    ///
+    /// アドレス未設定 (0.0.0.0) のときは A レコードを含めない
+    fn authoritative_records(&self, addr: Ipv4Address) -> Vec<Record> {
+        let mut records = Vec::new();
+        if !addr.is_any() {
+            records.push(Record::new(
+                &self.host_name(),
+                HOST_RECORD_TTL,
+                true,
+                RecordData::A(addr),
+            ));
+        }
+        for service in &self.services {
+            records.push(Record::new(
+                SERVICES_META,
+                SERVICE_RECORD_TTL,
+                false,
+                RecordData::Ptr(service.type_name()),
+            ));
+            records.extend(self.service_records(service, 1));
+        }
+        records
+    }
+
+    /// 問い合わせへの応答を作る
+    ///
+    /// 既知回答（問い合わせの回答セクションにあり、TTL が半分以上残っているもの）は
+    /// 省略する (RFC 6762 §7.1)。応答すべきレコードがなければ None。
+    pub fn answer(&mut self, query: &Message, addr: Ipv4Address) -> Option<Message> {
+        if query.is_response() {
+            return None;
+        }
+        self.stats.queries_received += 1;
+
+        let records = self.authoritative_records(addr);
+        let known = |record: &Record| {
+            query
+                .answers
+                .iter()
+                .any(|k| k.same_data(record) && k.ttl >= record.ttl / 2)
+        };
+        let mut response = Message::response(0);
+        for record in &records {
+            if query.questions.iter().any(|q| q.matches(record)) && !known(record) {
+                response.answers.push(record.clone());
+            }
+        }
+        if response.answers.is_empty() {
+            return None;
+        }
+
+        // 追加セクション: PTR → SRV/TXT、SRV → A (RFC 6763 §12)
+        let mut wanted: Vec<(String, u16)> = Vec::new();
+        for answer in &response.answers {
+            match &answer.data {
+                RecordData::Ptr(instance) if answer.name != SERVICES_META => {
+                    wanted.push((instance.clone(), TYPE_SRV));
+                    wanted.push((instance.clone(), TYPE_TXT));
+                    wanted.push((self.host_name(), TYPE_A));
+                }
+                RecordData::Srv { target, .. } => wanted.push((target.clone(), TYPE_A)),
+                _ => {}
+            }
+        }
+        for record in &records {
+            let needed = wanted
+                .iter()
+                .any(|(name, rtype)| *rtype == record.rtype() && name.eq_ignore_ascii_case(&record.name));
+            let present = response
+                .answers
+                .iter()
+                .chain(&response.additionals)
+                .any(|r| r.same_data(record));
+            if needed && !present {
+                response.additionals.push(record.clone());
+            }
+        }
+
+        self.stats.responses_sent += 1;
+        Some(response)
+    }
+
+    /// 起動時・アドレス変更時のアナウンス（非要求応答）
+    pub fn announcement(&mut self, addr: Ipv4Address) -> Message {
+        let mut message = Message::response(0);
+        message.answers = self.authoritative_records(addr);
+        self.stats.responses_sent += 1;
+        message
+    }
+
+    /// サービス削除の通知 (TTL 0, RFC 6762 §10.1)
+    pub fn goodbye(&self, service: &Service) -> Message {
+        let mut message = Message::response(0);
+        message.answers.extend(self.service_records(service, 0));
+        message
+    }
+
+    /// 受信した応答をキャッシュに取り込む
+    pub fn cache_response(&mut self, response: &Message, now_ms: u64) {
+        if !response.is_response() {
+            return;
+        }
+        self.stats.responses_received += 1;
+        self.expire(now_ms);
+
+        for record in response.answers.iter().chain(&response.additionals) {
+            // cache-flush: 同名・同タイプの古いレコードを置き換える
+            if record.cache_flush {
+                self.cache.retain(|c| {
+                    c.record.rtype() != record.rtype()
+                        || !c.record.name.eq_ignore_ascii_case(&record.name)
+                });
+            } else {
+                self.cache.retain(|c| !c.record.same_data(record));
+            }
+            // TTL 0 は削除通知
+            if record.ttl == 0 {
+                continue;
+            }
+            if self.cache.len() >= MAX_CACHE_RECORDS {
+                // 最も早く失効するレコードを追い出す
+                if let Some(index) = self
+                    .cache
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, c)| c.expires_ms)
+                    .map(|(i, _)| i)
+                {
+                    self.cache.remove(index);
+                }
+            }
+            self.cache.push(CachedRecord {
+                record: record.clone(),
+                expires_ms: now_ms + record.ttl as u64 * 1000,
+            });
+        }
+    }
+
+    /// 失効したレコードを削除
+    pub fn expire(&mut self, now_ms: u64) {
+        self.cache.retain(|c| c.expires_ms > now_ms);
+    }
+
+    /// キャッシュ内容
+    pub fn cache(&self) -> &[CachedRecord] {
+        &self.cache
+    }
+
+    /// キャッシュを消去
+    pub fn flush_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// 有効なキャッシュレコードを名前とタイプで検索
+    fn cached<'a>(&'a self, name: &'a str, rtype: u16, now_ms: u64) -> impl Iterator<Item = &'a Record> + 'a {
+        self.cache
+            .iter()
+            .filter(move |c| {
+                c.expires_ms > now_ms
+                    && c.record.rtype() == rtype
+                    && c.record.name.eq_ignore_ascii_case(name)
+            })
+            .map(|c| &c.record)
+    }
+
+    /// `.local` 名のアドレスを引く（自ホスト名は `local` を返す）
+    pub fn lookup_host(&self, name: &str, local: Ipv4Address, now_ms: u64) -> Vec<Ipv4Address> {
+        let name = name.trim_end_matches('.');
+        if name.eq_ignore_ascii_case(&self.host_name()) && !local.is_any() {
+            return alloc::vec![local];
+        }
+        self.cached(name, TYPE_A, now_ms)
+            .filter_map(|r| match r.data {
+                RecordData::A(addr) => Some(addr),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// キャッシュからサービスインスタンスを列挙
+    pub fn browse(&self, service_type: &str, now_ms: u64) -> Vec<ServiceInstance> {
+        let type_name = format!("{}.local", service_type);
+        let mut instances: Vec<ServiceInstance> = Vec::new();
+        for record in self.cached(&type_name, TYPE_PTR, now_ms) {
+            let RecordData::Ptr(name) = &record.data else {
+                continue;
+            };
+            if instances.iter().any(|i| i.name.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            let mut instance = ServiceInstance {
+                name: name.clone(),
+                host: None,
+                port: None,
+                address: None,
+                txt: Vec::new(),
+            };
+            if let Some(RecordData::Srv { port, target, .. }) =
+                self.cached(name, TYPE_SRV, now_ms).map(|r| &r.data).next()
+            {
+                instance.port = Some(*port);
+                instance.address = self.lookup_host(target, Ipv4Address::ANY, now_ms).first().copied();
+                instance.host = Some(target.clone());
+            }
+            if let Some(RecordData::Txt(txt)) = self.cached(name, TYPE_TXT, now_ms).map(|r| &r.data).next() {
+                instance.txt = txt.clone();
+            }
+            instances.push(instance);
+        }
+        instances
+    }
+
+    /// 問い合わせメッセージを作成
+    pub fn query(&mut self, name: &str, qtype: u16) -> Message {
+        let mut message = Message::query();
+        message.questions.push(Question::new(name, qtype));
+        self.stats.queries_sent += 1;
+        message
+    }
+}
+
+// ============================================================================
+// グローバル状態
+// ============================================================================
+
+/// グローバル mDNS レスポンダ
+static MDNS: Mutex<Option<MdnsResponder>> = Mutex::new(None);
+
+/// レスポンダにアクセス（未初期化なら標準サービスで作成）
+pub fn with_responder<R>(f: impl FnOnce(&mut MdnsResponder) -> R) -> R {
+    let mut mdns = MDNS.lock();
+    f(mdns.get_or_insert_with(MdnsResponder::with_default_services))
+}
+
+/// 送信する mDNS パケット
+#[derive(Debug, Clone)]
+pub struct MdnsReply {
+    /// 宛先アドレス
+    pub dst: Ipv4Address,
+    /// 宛先ポート
+    pub port: u16,
+    /// DNS メッセージ
+    pub data: Vec<u8>,
+}
+
+impl MdnsReply {
+    /// マルチキャストグループ宛て
+    fn multicast(message: &Message) -> Self {
+        MdnsReply {
+            dst: MDNS_GROUP,
+            port: MDNS_PORT,
+            data: message.encode(),
+        }
+    }
+}
+
+/// 受信した mDNS パケットを処理（スタックの UDP 受信から呼ばれる）
+///
+/// 応答は問い合わせに応答し、問い合わせはキャッシュに取り込む。
+/// 戻り値: 送信すべき応答
+pub fn handle_packet(
+    payload: &[u8],
+    src: Ipv4Address,
+    src_port: u16,
+    local: Ipv4Address,
+    now_ms: u64,
+) -> Option<MdnsReply> {
+    let Ok(message) = Message::parse(payload) else {
+        with_responder(|r| r.stats.errors += 1);
+        return None;
+    };
+    if message.is_response() {
+        // ポート 5353 以外からの応答はリンク上の mDNS 応答ではない (RFC 6762 §11)
+        if src_port == MDNS_PORT {
+            with_responder(|r| r.cache_response(&message, now_ms));
+        }
+        return None;
+    }
+
+    let mut response = with_responder(|r| r.answer(&message, local))?;
+    if src_port != MDNS_PORT {
+        // レガシーユニキャスト: ID と質問を返し、TTL を短くして送信元へ直接返す
+        response.id = message.id;
+        response.questions = message.questions.clone();
+        for record in response.answers.iter_mut().chain(response.additionals.iter_mut()) {
+            record.cache_flush = false;
+            record.ttl = record.ttl.min(LEGACY_UNICAST_TTL);
+        }
+        return Some(MdnsReply {
+            dst: src,
+            port: src_port,
+            data: response.encode(),
+        });
+    }
+    if message.questions.iter().all(|q| q.unicast_response) {
+        return Some(MdnsReply {
+            dst: src,
+            port: MDNS_PORT,
+            data: response.encode(),
+        });
+    }
+    Some(MdnsReply::multicast(&response))
+}
+
+/// アナウンスパケットを作成
+pub fn announcement(local: Ipv4Address) -> MdnsReply {
+    with_responder(|r| MdnsReply::multicast(&r.announcement(local)))
+}
+
+/// スタック経由で mDNS パケットを送信
+fn send(reply: &MdnsReply) -> Result<(), String> {
+    let guard = stack::stack().lock();
+    let stack = guard.as_ref().ok_or("Network stack not initialized")?;
+    if stack.send_udp_with_ttl(MDNS_PORT, reply.dst, reply.port, &reply.data, MDNS_IP_TTL) {
+        Ok(())
+    } else {
+        Err(String::from("Failed to send mDNS packet"))
+    }
+}
+
+/// 自ホストのプライマリアドレス
+fn local_address() -> Ipv4Address {
+    stack::stack()
+        .lock()
+        .as_ref()
+        .map_or(Ipv4Address::ANY, |s| s.ipv4_address())
+}
+
+/// ホスト名とサービスをアナウンス
+pub fn announce() -> Result<(), String> {
+    send(&announcement(local_address()))
+}
+
+/// ホスト名を変更してアナウンス
+pub fn set_hostname(hostname: &str) -> Result<(), String> {
+    let valid = !hostname.is_empty()
+        && hostname.len() <= 63
+        && hostname
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        && !hostname.starts_with('-');
+    if !valid {
+        return Err(format!("Invalid hostname: {}", hostname));
+    }
+    with_responder(|r| r.set_hostname(hostname));
+    announce()
+}
+
+/// サービスを公開してアナウンス
+pub fn register_service(service: Service) -> Result<(), String> {
+    if !valid_service_type(&service.service_type) {
+        return Err(format!(
+            "Invalid service type: {} (expected _name._tcp or _name._udp)",
+            service.service_type
+        ));
+    }
+    with_responder(|r| r.add_service(service));
+    announce()
+}
+
+/// サービスの公開をやめ、削除を通知
+pub fn unregister_service(service_type: &str) -> Result<(), String> {
+    let goodbye = with_responder(|r| {
+        r.remove_service(service_type)
+            .map(|service| MdnsReply::multicast(&r.goodbye(&service)))
+    })
+    .ok_or_else(|| format!("Service not registered: {}", service_type))?;
+    send(&goodbye)
+}
+
+/// 問い合わせを送信
+pub fn send_query(name: &str, qtype: u16) -> Result<(), String> {
+    let query = with_responder(|r| MdnsReply::multicast(&r.query(name, qtype)));
+    send(&query)
+}
+
+/// `.local` 名かどうか
+pub fn is_local_name(name: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    name.len() > ".local".len()
+        && name[name.len() - ".local".len()..].eq_ignore_ascii_case(".local")
+}
+
+/// キャッシュ（または自ホスト名）から解決
+pub fn lookup(name: &str) -> Vec<Ipv4Address> {
+    let local = local_address();
+    with_responder(|r| r.lookup_host(name, local, crate::time::current_tick()))
+}
+
+/// `.local` 名を解決（async版 - 待機中は他タスクに譲る）
+pub async fn resolve_async(name: &str) -> Result<Vec<Ipv4Address>, String> {
+    for _ in 0..RESOLVE_TIMEOUT_MS / QUERY_INTERVAL_MS {
+        let found = lookup(name);
+        if !found.is_empty() {
+            return Ok(found);
+        }
+        send_query(name, TYPE_A)?;
+        crate::task::sleep_ms(QUERY_INTERVAL_MS).await;
+    }
+    let found = lookup(name);
+    if found.is_empty() {
+        Err(format!("No mDNS response for {}", name))
+    } else {
+        Ok(found)
+    }
+}
+
+/// サービスを探索（async版）
+///
+/// PTR 問い合わせを送り、応答を待ってからキャッシュの内容を返す
+pub async fn browse(service_type: &str) -> Result<Vec<ServiceInstance>, String> {
+    if !valid_service_type(service_type) {
+        return Err(format!(
+            "Invalid service type: {} (expected _name._tcp or _name._udp)",
+            service_type
+        ));
+    }
+    send_query(&format!("{}.local", service_type), TYPE_PTR)?;
+    crate::task::sleep_ms(RESOLVE_TIMEOUT_MS).await;
+    Ok(with_responder(|r| r.browse(service_type, crate::time::current_tick())))
+}
+
+/// 期限切れキャッシュの掃除（スタックの定期処理から呼ばれる）
+pub fn expire(now_ms: u64) {
+    if let Some(responder) = MDNS.lock().as_mut() {
+        responder.expire(now_ms);
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDR: Ipv4Address = Ipv4Address::new([10, 0, 2, 15]);
+
+    fn query(name: &str, qtype: u16) -> Message {
+        let mut message = Message::query();
+        message.questions.push(Question::new(name, qtype));
+        message
+    }
+
+    #[test]
+    fn test_answers_host_and_services() {
+        let mut responder = MdnsResponder::with_default_services();
+
+        let response = responder.answer(&query("RANYOS.local", TYPE_A), ADDR).unwrap();
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(response.answers[0].data, RecordData::A(ADDR));
+
+        // サービス種別の列挙
+        let response = responder.answer(&query(SERVICES_META, TYPE_PTR), ADDR).unwrap();
+        let types: Vec<_> = response.answers.iter().map(|r| r.data.clone()).collect();
+        assert!(types.contains(&RecordData::Ptr(String::from("_http._tcp.local"))));
+        assert!(types.contains(&RecordData::Ptr(String::from("_echo._tcp.local"))));
+
+        // PTR には SRV/TXT/A が追加セクションで付く
+        let response = responder.answer(&query("_http._tcp.local", TYPE_PTR), ADDR).unwrap();
+        assert_eq!(
+            response.answers[0].data,
+            RecordData::Ptr(String::from("ranyos._http._tcp.local"))
+        );
+        let extra: Vec<u16> = response.additionals.iter().map(|r| r.rtype()).collect();
+        assert!(extra.contains(&TYPE_SRV) && extra.contains(&TYPE_TXT) && extra.contains(&TYPE_A));
+
+        // 他ホスト宛て・アドレス未設定時の A は応答しない
+        assert!(responder.answer(&query("other.local", TYPE_A), ADDR).is_none());
+        assert!(responder.answer(&query("ranyos.local", TYPE_A), Ipv4Address::ANY).is_none());
+    }
+
+    #[test]
+    fn test_known_answer_suppression() {
+        let mut responder = MdnsResponder::with_default_services();
+        let mut message = query("ranyos.local", TYPE_A);
+        message
+            .answers
+            .push(Record::new("ranyos.local", HOST_RECORD_TTL, true, RecordData::A(ADDR)));
+        assert!(responder.answer(&message, ADDR).is_none());
+
+        // TTL が半分未満なら再送する
+        message.answers[0].ttl = HOST_RECORD_TTL / 4;
+        assert!(responder.answer(&message, ADDR).is_some());
+    }
+
+    #[test]
+    fn test_cache_resolve_and_browse() {
+        // 別ホストのアナウンスを受信
+        let mut peer = MdnsResponder::new("peer");
+        peer.add_service(Service::new("_http._tcp", 8000).with_txt("path=/api"));
+        let peer_addr = Ipv4Address::new([10, 0, 2, 20]);
+        let announcement = Message::parse(&peer.announcement(peer_addr).encode()).unwrap();
+
+        let mut responder = MdnsResponder::with_default_services();
+        responder.cache_response(&announcement, 1000);
+        assert_eq!(responder.lookup_host("peer.local", ADDR, 2000), alloc::vec![peer_addr]);
+        assert_eq!(responder.lookup_host("ranyos.local", ADDR, 2000), alloc::vec![ADDR]);
+
+        let found = responder.browse("_http._tcp", 2000);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "peer._http._tcp.local");
+        assert_eq!(found[0].port, Some(8000));
+        assert_eq!(found[0].address, Some(peer_addr));
+        assert_eq!(found[0].txt, alloc::vec![String::from("path=/api")]);
+
+        // TTL 経過で失効
+        let expired = 1000 + HOST_RECORD_TTL as u64 * 1000 + 1;
+        assert!(responder.lookup_host("peer.local", ADDR, expired).is_empty());
+
+        // goodbye (TTL 0) で削除
+        let service = peer.services()[0].clone();
+        responder.cache_response(&peer.goodbye(&service), 3000);
+        assert!(responder.browse("_http._tcp", 3000).is_empty());
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(is_local_name("ranyos.local"));
+        assert!(is_local_name("Printer.LOCAL."));
+        assert!(!is_local_name(".local"));
+        assert!(!is_local_name("example.com"));
+        assert!(valid_service_type("_http._tcp"));
+        assert!(!valid_service_type("http._tcp"));
+        assert!(!valid_service_type("_http._sctp"));
+    }
+}
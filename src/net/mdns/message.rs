@@ -0,0 +1,521 @@
+//! mDNS メッセージ形式
+//!
+//! DNSメッセージ（RFC 1035）のうち mDNS / DNS-SD が使う部分の
+//! 組み立てと解析。名前圧縮に対応し、mDNS 固有の
+//! cache-flush ビット（rrclass 最上位）と unicast-response ビット
+//! （qclass 最上位）を扱う。
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::net::dns::{DnsHeader, DnsQueryClass, DnsQueryType};
+use crate::net::ipv4::Ipv4Address;
+
+/// Aレコード
+pub const TYPE_A: u16 = DnsQueryType::A as u16;
+/// PTRレコード
+pub const TYPE_PTR: u16 = DnsQueryType::PTR as u16;
+/// TXTレコード
+pub const TYPE_TXT: u16 = DnsQueryType::TXT as u16;
+/// SRVレコード
+pub const TYPE_SRV: u16 = DnsQueryType::SRV as u16;
+/// 全タイプ (ANY)
+pub const TYPE_ANY: u16 = DnsQueryType::ALL as u16;
+
+/// レコードタイプ名
+pub fn type_name(rtype: u16) -> &'static str {
+    match rtype {
+        TYPE_A => "A",
+        TYPE_PTR => "PTR",
+        TYPE_TXT => "TXT",
+        TYPE_SRV => "SRV",
+        TYPE_ANY => "ANY",
+        _ => "?",
+    }
+}
+
+/// rrclass / qclass の最上位ビット
+const CLASS_FLAG: u16 = 0x8000;
+/// 応答フラグ (QR=1, AA=1)
+const FLAGS_RESPONSE: u16 = 0x8400;
+/// 圧縮ポインタの最大追跡回数
+const MAX_POINTER_JUMPS: usize = 16;
+
+/// メッセージ解析エラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageError {
+    /// データが途中で切れている
+    Truncated,
+    /// 名前が不正（ラベル長・ポインタループ）
+    InvalidName,
+}
+
+/// 質問セクションのエントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// 問い合わせ名
+    pub name: String,
+    /// 問い合わせタイプ
+    pub qtype: u16,
+    /// ユニキャスト応答の要求 (QU ビット)
+    pub unicast_response: bool,
+}
+
+impl Question {
+    /// 質問を作成 (QM: マルチキャスト応答)
+    pub fn new(name: &str, qtype: u16) -> Self {
+        Question {
+            name: String::from(name),
+            qtype,
+            unicast_response: false,
+        }
+    }
+
+    /// レコードがこの質問の回答になるか
+    pub fn matches(&self, record: &Record) -> bool {
+        (self.qtype == TYPE_ANY || self.qtype == record.rtype())
+            && self.name.eq_ignore_ascii_case(&record.name)
+    }
+}
+
+/// レコードデータ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    /// IPv4アドレス
+    A(Ipv4Address),
+    /// ポインタ（サービス種別 → インスタンス名）
+    Ptr(String),
+    /// サービスの所在
+    Srv {
+        /// 優先度
+        priority: u16,
+        /// 重み
+        weight: u16,
+        /// ポート番号
+        port: u16,
+        /// ホスト名
+        target: String,
+    },
+    /// キー=値 の文字列列
+    Txt(Vec<String>),
+    /// 未対応タイプ (タイプ, 生データ)
+    Other(u16, Vec<u8>),
+}
+
+impl fmt::Display for RecordData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordData::A(addr) => write!(f, "{}", addr),
+            RecordData::Ptr(name) => write!(f, "{}", name),
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(f, "{} {} {} {}", priority, weight, port, target),
+            RecordData::Txt(entries) => write!(f, "{}", entries.join(" ")),
+            RecordData::Other(rtype, raw) => write!(f, "type {} ({} bytes)", rtype, raw.len()),
+        }
+    }
+}
+
+/// リソースレコード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// 所有者名
+    pub name: String,
+    /// 同名・同タイプのキャッシュを置き換える (cache-flush ビット)
+    pub cache_flush: bool,
+    /// TTL (秒)。0 は削除通知 (goodbye)
+    pub ttl: u32,
+    /// レコードデータ
+    pub data: RecordData,
+}
+
+impl Record {
+    /// レコードを作成
+    pub fn new(name: &str, ttl: u32, cache_flush: bool, data: RecordData) -> Self {
+        Record {
+            name: String::from(name),
+            cache_flush,
+            ttl,
+            data,
+        }
+    }
+
+    /// レコードタイプ
+    pub fn rtype(&self) -> u16 {
+        match &self.data {
+            RecordData::A(_) => TYPE_A,
+            RecordData::Ptr(_) => TYPE_PTR,
+            RecordData::Srv { .. } => TYPE_SRV,
+            RecordData::Txt(_) => TYPE_TXT,
+            RecordData::Other(rtype, _) => *rtype,
+        }
+    }
+
+    /// 名前・タイプ・データが同じレコードか（TTL は比較しない）
+    pub fn same_data(&self, other: &Record) -> bool {
+        self.name.eq_ignore_ascii_case(&other.name) && self.data == other.data
+    }
+}
+
+/// DNSメッセージ
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    /// トランザクションID（マルチキャストでは 0）
+    pub id: u16,
+    /// フラグ
+    pub flags: u16,
+    /// 質問セクション
+    pub questions: Vec<Question>,
+    /// 回答セクション
+    pub answers: Vec<Record>,
+    /// 権威セクション（プローブ時の提案レコード）
+    pub authorities: Vec<Record>,
+    /// 追加セクション
+    pub additionals: Vec<Record>,
+}
+
+impl Message {
+    /// 問い合わせメッセージを作成
+    pub fn query() -> Self {
+        Message::default()
+    }
+
+    /// 応答メッセージを作成
+    pub fn response(id: u16) -> Self {
+        Message {
+            id,
+            flags: FLAGS_RESPONSE,
+            ..Message::default()
+        }
+    }
+
+    /// 応答メッセージか (QR ビット)
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// メッセージを解析
+    pub fn parse(data: &[u8]) -> Result<Self, MessageError> {
+        if data.len() < DnsHeader::SIZE {
+            return Err(MessageError::Truncated);
+        }
+        let word = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        let counts = [word(4), word(6), word(8), word(10)];
+
+        let mut message = Message {
+            id: word(0),
+            flags: word(2),
+            ..Message::default()
+        };
+        let mut offset = DnsHeader::SIZE;
+
+        for _ in 0..counts[0] {
+            let (name, next) = read_name(data, offset)?;
+            let fixed = data.get(next..next + 4).ok_or(MessageError::Truncated)?;
+            let qclass = u16::from_be_bytes([fixed[2], fixed[3]]);
+            message.questions.push(Question {
+                name,
+                qtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+                unicast_response: qclass & CLASS_FLAG != 0,
+            });
+            offset = next + 4;
+        }
+
+        let sections = [
+            (counts[1], &mut message.answers),
+            (counts[2], &mut message.authorities),
+            (counts[3], &mut message.additionals),
+        ];
+        for (count, records) in sections {
+            for _ in 0..count {
+                let (record, next) = read_record(data, offset)?;
+                records.push(record);
+                offset = next;
+            }
+        }
+
+        Ok(message)
+    }
+
+    /// ワイヤ形式にエンコード（名前圧縮あり）
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = NameWriter::default();
+        let out = &mut writer.buf;
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.flags.to_be_bytes());
+        for count in [
+            self.questions.len(),
+            self.answers.len(),
+            self.authorities.len(),
+            self.additionals.len(),
+        ] {
+            out.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+
+        for question in &self.questions {
+            writer.name(&question.name);
+            let class = DnsQueryClass::IN as u16
+                | if question.unicast_response { CLASS_FLAG } else { 0 };
+            writer.buf.extend_from_slice(&question.qtype.to_be_bytes());
+            writer.buf.extend_from_slice(&class.to_be_bytes());
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            writer.record(record);
+        }
+        writer.buf
+    }
+}
+
+/// 名前圧縮付きのエンコーダ
+#[derive(Default)]
+struct NameWriter {
+    /// 出力バッファ
+    buf: Vec<u8>,
+    /// 書き込み済みの名前の接尾辞（小文字） → オフセット
+    suffixes: BTreeMap<String, u16>,
+}
+
+impl NameWriter {
+    /// 名前を書き込む（既出の接尾辞は圧縮ポインタで参照）
+    fn name(&mut self, name: &str) {
+        let labels: Vec<&str> = name.split('.').filter(|l| !l.is_empty()).collect();
+        for i in 0..labels.len() {
+            let suffix = labels[i..].join(".").to_ascii_lowercase();
+            if let Some(&offset) = self.suffixes.get(&suffix) {
+                self.buf.extend_from_slice(&(0xC000 | offset).to_be_bytes());
+                return;
+            }
+            if self.buf.len() < 0x3FFF {
+                self.suffixes.insert(suffix, self.buf.len() as u16);
+            }
+            let label = &labels[i].as_bytes()[..labels[i].len().min(63)];
+            self.buf.push(label.len() as u8);
+            self.buf.extend_from_slice(label);
+        }
+        self.buf.push(0);
+    }
+
+    /// リソースレコードを書き込む
+    fn record(&mut self, record: &Record) {
+        self.name(&record.name);
+        let class = DnsQueryClass::IN as u16 | if record.cache_flush { CLASS_FLAG } else { 0 };
+        self.buf.extend_from_slice(&record.rtype().to_be_bytes());
+        self.buf.extend_from_slice(&class.to_be_bytes());
+        self.buf.extend_from_slice(&record.ttl.to_be_bytes());
+
+        // RDLENGTH は RDATA を書いた後に埋める
+        let length_at = self.buf.len();
+        self.buf.extend_from_slice(&[0, 0]);
+        match &record.data {
+            RecordData::A(addr) => self.buf.extend_from_slice(addr.as_bytes()),
+            RecordData::Ptr(name) => self.name(name),
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                for value in [priority, weight, port] {
+                    self.buf.extend_from_slice(&value.to_be_bytes());
+                }
+                self.name(target);
+            }
+            RecordData::Txt(entries) => {
+                // 空の TXT は長さ0の文字列1つで表す (RFC 6763 §6.1)
+                if entries.is_empty() {
+                    self.buf.push(0);
+                }
+                for entry in entries {
+                    let bytes = &entry.as_bytes()[..entry.len().min(255)];
+                    self.buf.push(bytes.len() as u8);
+                    self.buf.extend_from_slice(bytes);
+                }
+            }
+            RecordData::Other(_, raw) => self.buf.extend_from_slice(raw),
+        }
+        let rdlength = (self.buf.len() - length_at - 2) as u16;
+        self.buf[length_at..length_at + 2].copy_from_slice(&rdlength.to_be_bytes());
+    }
+}
+
+/// 名前を読む（圧縮対応）
+///
+/// 戻り値: (名前, 名前の直後のオフセット)
+fn read_name(data: &[u8], mut offset: usize) -> Result<(String, usize), MessageError> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *data.get(offset).ok_or(MessageError::Truncated)? as usize;
+        if len == 0 {
+            return Ok((name, end.unwrap_or(offset + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let low = *data.get(offset + 1).ok_or(MessageError::Truncated)? as usize;
+            jumps += 1;
+            if jumps > MAX_POINTER_JUMPS {
+                return Err(MessageError::InvalidName);
+            }
+            end.get_or_insert(offset + 2);
+            offset = ((len & 0x3F) << 8) | low;
+            continue;
+        }
+        if len > 63 {
+            return Err(MessageError::InvalidName);
+        }
+        let label = data
+            .get(offset + 1..offset + 1 + len)
+            .ok_or(MessageError::Truncated)?;
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&String::from_utf8_lossy(label));
+        offset += 1 + len;
+    }
+}
+
+/// リソースレコードを読む
+fn read_record(data: &[u8], offset: usize) -> Result<(Record, usize), MessageError> {
+    let (name, next) = read_name(data, offset)?;
+    let fixed = data.get(next..next + 10).ok_or(MessageError::Truncated)?;
+    let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let class = u16::from_be_bytes([fixed[2], fixed[3]]);
+    let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+    let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    let start = next + 10;
+    let rdata = data
+        .get(start..start + rdlength)
+        .ok_or(MessageError::Truncated)?;
+
+    let record_data = match rtype {
+        TYPE_A if rdlength == 4 => {
+            RecordData::A(Ipv4Address::new([rdata[0], rdata[1], rdata[2], rdata[3]]))
+        }
+        TYPE_PTR => RecordData::Ptr(read_name(data, start)?.0),
+        TYPE_SRV if rdlength >= 7 => RecordData::Srv {
+            priority: u16::from_be_bytes([rdata[0], rdata[1]]),
+            weight: u16::from_be_bytes([rdata[2], rdata[3]]),
+            port: u16::from_be_bytes([rdata[4], rdata[5]]),
+            target: read_name(data, start + 6)?.0,
+        },
+        TYPE_TXT => {
+            let mut entries = Vec::new();
+            let mut pos = 0;
+            while pos < rdata.len() {
+                let len = rdata[pos] as usize;
+                let entry = rdata
+                    .get(pos + 1..pos + 1 + len)
+                    .ok_or(MessageError::Truncated)?;
+                if !entry.is_empty() {
+                    entries.push(String::from_utf8_lossy(entry).into_owned());
+                }
+                pos += 1 + len;
+            }
+            RecordData::Txt(entries)
+        }
+        _ => RecordData::Other(rtype, rdata.to_vec()),
+    };
+
+    let record = Record {
+        name,
+        cache_flush: class & CLASS_FLAG != 0,
+        ttl,
+        data: record_data,
+    };
+    Ok((record, start + rdlength))
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_with_compression() {
+        let mut message = Message::response(0);
+        message.answers.push(Record::new(
+            "_http._tcp.local",
+            4500,
+            false,
+            RecordData::Ptr(String::from("ranyos._http._tcp.local")),
+        ));
+        message.additionals.push(Record::new(
+            "ranyos._http._tcp.local",
+            120,
+            true,
+            RecordData::Srv {
+                priority: 0,
+                weight: 0,
+                port: 80,
+                target: String::from("ranyos.local"),
+            },
+        ));
+        message.additionals.push(Record::new(
+            "ranyos._http._tcp.local",
+            4500,
+            true,
+            RecordData::Txt(alloc::vec![String::from("path=/")]),
+        ));
+        message.additionals.push(Record::new(
+            "ranyos.local",
+            120,
+            true,
+            RecordData::A(Ipv4Address::new([10, 0, 2, 15])),
+        ));
+
+        let wire = message.encode();
+        let parsed = Message::parse(&wire).unwrap();
+        assert_eq!(parsed, message);
+        assert!(parsed.is_response());
+
+        // 圧縮により "_http._tcp.local" 等の繰り返しは2バイトのポインタになる
+        let uncompressed: usize = ["_http._tcp.local", "ranyos._http._tcp.local"]
+            .iter()
+            .map(|n| n.len() + 2)
+            .sum::<usize>()
+            * 3;
+        assert!(wire.len() < DnsHeader::SIZE + uncompressed);
+    }
+
+    #[test]
+    fn test_question_flags_and_matching() {
+        let mut message = Message::query();
+        let mut question = Question::new("RanyOS.local", TYPE_A);
+        question.unicast_response = true;
+        message.questions.push(question);
+
+        let parsed = Message::parse(&message.encode()).unwrap();
+        assert!(!parsed.is_response());
+        assert!(parsed.questions[0].unicast_response);
+
+        let record = Record::new("ranyos.local", 120, true, RecordData::A(Ipv4Address::LOOPBACK));
+        assert!(parsed.questions[0].matches(&record));
+        assert!(Question::new("ranyos.local", TYPE_ANY).matches(&record));
+        assert!(!Question::new("ranyos.local", TYPE_SRV).matches(&record));
+    }
+
+    #[test]
+    fn test_reject_malformed() {
+        assert_eq!(Message::parse(&[0; 4]), Err(MessageError::Truncated));
+
+        // 自分自身を指す圧縮ポインタ（ループ）
+        let mut data = alloc::vec![0u8; DnsHeader::SIZE];
+        data[5] = 1; // QDCOUNT = 1
+        data.extend_from_slice(&[0xC0, DnsHeader::SIZE as u8, 0, 1, 0, 1]);
+        assert_eq!(Message::parse(&data), Err(MessageError::InvalidName));
+    }
+}
@@ -21,12 +21,33 @@ pub mod optimization;
 pub mod arp;
 pub mod ethernet;
 pub mod icmp;
+pub mod igmp;
 pub mod ipv4;
 pub mod udp;
 
 // Network services
 pub mod dhcp;
 pub mod dns;
+pub mod mdns;
+pub mod ntp;
+
+// Interfaces and routing
+pub mod interface;
+pub mod loopback;
+pub mod route;
+
+// Stateful packet filter
+pub mod firewall;
+
+// IPv4 forwarding and NAT
+pub mod forward;
+pub mod nat;
+
+// WireGuard VPN
+pub mod wireguard;
+
+// Software Ethernet bridge
+pub mod bridge;
 
 // Integrated network stack
 pub mod stack;
@@ -34,12 +55,19 @@ pub mod stack;
 // VirtIO-Net driver bridge
 pub mod driver_bridge;
 
+// Packet capture (pcapng)
+pub mod capture;
+
 // Endpoint API (旧称: socket → ゼロコピー所有権モデルを反映)
 pub mod endpoint;
 
 // TLS support
 pub mod tls;
 
+// HTTP/1.1 client
+pub mod http;
+pub mod http_server;
+
 // Re-export mempool
 #[allow(unused_imports)]
 pub use mempool::{
@@ -61,6 +89,7 @@ pub use tcp::{
     TcpListener,
     TcpProcessor,
     TcpState,
+    TcpStats,
     // ストリーム・リスナー
     TcpStream,
 };
@@ -69,7 +98,7 @@ pub use tcp::{
 #[allow(unused_imports)]
 pub use ethernet::{
     EtherType, EthernetFrame, EthernetFrameMut, EthernetHeader, EthernetProcessor, EthernetStats,
-    MacAddress,
+    MacAddress, VLAN_ID_MAX, VLAN_TPID, VlanTag, insert_vlan_tag, strip_vlan_tag,
 };
 
 // Re-export IPv4
@@ -116,11 +145,60 @@ pub use dns::{
     resolve_cached as dns_resolve_cached, set_servers as set_dns_servers,
 };
 
+// Re-export HTTP client / server
+#[allow(unused_imports)]
+pub use http::{HttpError, Request as HttpRequest, Response as HttpResponse, Url};
+#[allow(unused_imports)]
+pub use http_server::{HttpServer, Router as HttpRouter, ServerConfig as HttpServerConfig};
+
+// Re-export mDNS / IGMP
+#[allow(unused_imports)]
+pub use igmp::{IgmpMessage, IgmpType};
+#[allow(unused_imports)]
+pub use mdns::{
+    MDNS_GROUP, MDNS_PORT, MdnsResponder, MdnsStats, Service as MdnsService,
+    ServiceInstance as MdnsServiceInstance,
+};
+
+// Re-export Interfaces and Routing
+#[allow(unused_imports)]
+pub use interface::{
+    InterfaceAddress, InterfaceError, InterfaceId, InterfaceKind, InterfaceStats, InterfaceTable,
+    LOOPBACK_ADDRESS, LOOPBACK_ID, LOOPBACK_NAME, NetworkInterface, Offloads, VlanLink,
+    mask_to_prefix, prefix_to_mask,
+};
+#[allow(unused_imports)]
+pub use loopback::LoopbackDevice;
+#[allow(unused_imports)]
+pub use route::{Route, RouteError, RouteLookup, RouteOrigin, RoutingTable};
+
+// Re-export Firewall
+#[allow(unused_imports)]
+pub use firewall::{
+    Action as FirewallAction, Chain as FirewallChain, ConnState, ConnTrack, Firewall,
+    FirewallError, FirewallRule, PacketMeta, RuleParseError, Verdict as FirewallVerdict,
+};
+
+// Re-export forwarding and NAT
+#[allow(unused_imports)]
+pub use forward::ForwardStats;
+#[allow(unused_imports)]
+pub use nat::{Nat, NatEntry, NatError, PortForward};
+
+// Re-export WireGuard
+#[allow(unused_imports)]
+pub use wireguard::{PeerConfig as WgPeerConfig, WgError, WgStats, WireGuard};
+
+// Re-export Ethernet bridge（BridgeStats は driver_bridge と衝突するため別名）
+#[allow(unused_imports)]
+pub use bridge::{Bridge, BridgeError, BridgeStats as EthBridgeStats, FdbEntry};
+
 // Re-export Network Stack
 #[allow(unused_imports)]
 pub use stack::{
-    MAX_PACKET_SIZE, MTU, NetworkConfig, NetworkStack, NetworkStats, bind_udp, init as init_stack,
-    init_default as init_stack_default, receive, send_tcp, send_udp, stack as global_stack,
+    MAX_PACKET_SIZE, MTU, NetworkConfig, NetworkStack, NetworkStats, PendingTcpSegment, bind_udp,
+    init as init_stack, init_default as init_stack_default, poll_loopback, poll_wireguard, receive,
+    receive_on, select_source, send_tcp, send_udp, stack as global_stack, unbind_udp,
 };
 
 // Re-export VirtIO-Net driver bridge
@@ -131,12 +209,20 @@ pub use driver_bridge::{
     send_real_icmp_echo, get_real_arp_cache,
 };
 
+// Re-export Packet Capture
+#[allow(unused_imports)]
+pub use capture::{
+    CaptureConfig, CaptureError, CaptureFilter, CaptureStats, CapturedPacket,
+    Direction as CaptureDirection, FilterError, PacketInfo, PcapngWriter,
+};
+
 // VirtIO Netドライバはio/virtio/net.rsにある
 // 再エクスポート
 #[allow(unused_imports)]
 pub use crate::io::virtio::{
-    NetVirtQueue, VirtioNetDevice, VirtioNetHeader, VirtioNetStats, VringDesc as NetVringDesc,
-    net_features, handle_virtio_net_interrupt, init_virtio_net,
+    QueuePair, QueuePairStats, VirtioNetDevice, VirtioNetHeader, VirtioNetStats,
+    VringDesc as NetVringDesc, net_features, handle_virtio_net_interrupt,
+    handle_virtio_net_queue_interrupt, init_virtio_net,
 };
 
 // Re-export Phase 4 High-Performance Networking
@@ -240,6 +326,7 @@ pub use endpoint::{
     tcb_table,
     tcp_connect,
     tcp_flags,
+    tcp_timer_task,
     udp_bind,
 };
 
@@ -280,6 +367,8 @@ pub struct TcpConnectionInfo {
     pub local_addr: String,
     pub remote_addr: String,
     pub state: String,
+    /// 輻輳制御・RTT統計
+    pub stats: TcpStats,
 }
 
 /// UDP socket info for netstat
@@ -321,6 +410,242 @@ pub struct ArpCacheEntry {
     pub complete: bool,
 }
 
+/// Network interface info (net.ifaces)
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub kind: &'static str,
+    pub mac: [u8; 6],
+    pub mtu: usize,
+    pub up: bool,
+    /// CIDR表記のアドレス一覧（先頭がプライマリ）
+    pub addresses: Vec<String>,
+    pub stats: InterfaceStats,
+    /// 所属するブリッジ
+    pub master: Option<String>,
+    /// VLAN インターフェースの VLAN ID
+    pub vlan_id: Option<u16>,
+}
+
+/// Routing table entry (net.routes)
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    /// 宛先（CIDR表記、デフォルトルートは "default"）
+    pub destination: String,
+    pub gateway: Option<[u8; 4]>,
+    pub interface: String,
+    pub metric: u32,
+    pub source: Option<[u8; 4]>,
+    pub origin: &'static str,
+}
+
+/// Route lookup result (net.route_get)
+#[derive(Debug, Clone)]
+pub struct RouteLookupInfo {
+    pub interface: String,
+    pub next_hop: [u8; 4],
+    pub source: [u8; 4],
+    pub mtu: usize,
+}
+
+/// Packet capture status (net.capture_stats)
+#[derive(Debug, Clone)]
+pub struct CaptureStatusInfo {
+    pub active: bool,
+    /// フィルタ式（空文字列 = 全パケット）
+    pub filter: String,
+    pub stats: CaptureStats,
+}
+
+/// Captured packet summary (net.capture_show)
+#[derive(Debug, Clone)]
+pub struct CapturedPacketInfo {
+    pub timestamp_us: u64,
+    pub direction: &'static str,
+    pub length: usize,
+    pub summary: String,
+}
+
+/// Firewall rule (net.fw_list)
+#[derive(Debug, Clone)]
+pub struct FirewallRuleInfo {
+    pub id: u32,
+    pub chain: &'static str,
+    /// ルール仕様（fw_add に渡せる形式）
+    pub rule: String,
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// Firewall chain policy and counters (net.fw_chains)
+#[derive(Debug, Clone)]
+pub struct FirewallChainInfo {
+    pub chain: &'static str,
+    pub policy: &'static str,
+    pub accepted: u64,
+    pub dropped: u64,
+    /// ポリシーで判定されたパケット数
+    pub policy_packets: u64,
+}
+
+/// Tracked connection (net.conntrack)
+#[derive(Debug, Clone)]
+pub struct ConnTrackInfo {
+    pub protocol: &'static str,
+    /// 元方向のフロー（"src:port -> dst:port"）
+    pub flow: String,
+    pub tcp_state: Option<&'static str>,
+    /// 応答方向のパケットを観測済み
+    pub replied: bool,
+    pub packets: u64,
+    pub bytes: u64,
+    /// タイムアウトまでの残り時間
+    pub expires_ms: u64,
+}
+
+/// Forwarding status and counters (net.forward)
+#[derive(Debug, Clone)]
+pub struct ForwardingInfo {
+    pub enabled: bool,
+    /// マスカレードするインターフェース
+    pub masquerade: Vec<String>,
+    pub forwarded: u64,
+    pub ttl_exceeded: u64,
+    pub no_route: u64,
+    pub too_big: u64,
+    pub filtered: u64,
+    pub nat_dropped: u64,
+    /// 変換中のコネクション数
+    pub nat_entries: usize,
+}
+
+/// Port-forwarding rule (net.portfwd)
+#[derive(Debug, Clone)]
+pub struct PortForwardInfo {
+    pub id: u32,
+    /// "tcp 8080 -> 192.168.50.10:80" 形式
+    pub rule: String,
+}
+
+/// Translated connection (net.nat)
+#[derive(Debug, Clone)]
+pub struct NatEntryInfo {
+    pub protocol: &'static str,
+    /// 元方向のフロー（"src:port -> dst:port"）
+    pub original: String,
+    /// 変換後のフロー
+    pub translated: String,
+    /// 応答方向のパケットを観測済み
+    pub replied: bool,
+    pub packets: u64,
+    pub bytes: u64,
+    /// タイムアウトまでの残り時間
+    pub expires_ms: u64,
+}
+
+/// WireGuard peer (net.wg)
+#[derive(Debug, Clone)]
+pub struct WgPeerInfo {
+    /// Base64 の公開鍵
+    pub public_key: String,
+    /// "ip:port" 形式
+    pub endpoint: Option<String>,
+    /// CIDR表記
+    pub allowed_ips: Vec<String>,
+    /// 最後のハンドシェイクからの経過時間
+    pub latest_handshake_ms: Option<u64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// 永続キープアライブの間隔（秒、0 で無効）
+    pub persistent_keepalive: u16,
+    /// 有効なセッションがある
+    pub connected: bool,
+}
+
+/// WireGuard interface (net.wg)
+#[derive(Debug, Clone)]
+pub struct WireGuardInfo {
+    pub name: String,
+    /// Base64 の公開鍵
+    pub public_key: String,
+    pub listen_port: u16,
+    pub peers: Vec<WgPeerInfo>,
+    pub stats: WgStats,
+}
+
+/// Bridge MAC table entry (net.br)
+#[derive(Debug, Clone)]
+pub struct FdbInfo {
+    pub mac: String,
+    pub port: String,
+    /// 最後に見えてからの経過時間
+    pub age_ms: u64,
+    pub is_static: bool,
+}
+
+/// Ethernet bridge (net.br)
+#[derive(Debug, Clone)]
+pub struct BridgeInfo {
+    pub name: String,
+    pub mac: String,
+    pub up: bool,
+    pub ports: Vec<String>,
+    /// エージング時間（0 = 無効）
+    pub ageing_ms: u64,
+    pub fdb: Vec<FdbInfo>,
+    pub stats: EthBridgeStats,
+}
+
+/// Per-domain port policy rule (net.port_rules)
+#[derive(Debug, Clone)]
+pub struct PortPolicyInfo {
+    pub id: u64,
+    /// 対象ドメイン（None = 全ドメイン）
+    pub domain: Option<u64>,
+    /// "tcp:8080" 形式
+    pub ports: String,
+    /// "in" (bind/listen), "out" (connect/send), "any"
+    pub direction: &'static str,
+    pub action: &'static str,
+}
+
+/// Published mDNS service (net.mdns)
+#[derive(Debug, Clone)]
+pub struct MdnsServiceInfo {
+    /// インスタンス名（"ranyos._http._tcp.local"）
+    pub name: String,
+    pub service_type: String,
+    pub port: u16,
+    pub txt: Vec<String>,
+}
+
+/// Cached mDNS record (net.mdns)
+#[derive(Debug, Clone)]
+pub struct MdnsCacheInfo {
+    pub name: String,
+    pub rtype: &'static str,
+    pub data: String,
+    /// 失効までの残り秒数
+    pub ttl: u64,
+}
+
+/// mDNS responder state (net.mdns)
+#[derive(Debug, Clone)]
+pub struct MdnsInfo {
+    /// "<hostname>.local"
+    pub hostname: String,
+    pub services: Vec<MdnsServiceInfo>,
+    pub cache: Vec<MdnsCacheInfo>,
+    pub stats: MdnsStats,
+}
+
+/// Joined multicast group (net.mcast)
+#[derive(Debug, Clone)]
+pub struct MulticastGroupInfo {
+    pub interface: String,
+    pub group: [u8; 4],
+}
+
 // Global network state for shell access
 static NETWORK_CONFIG: Mutex<Option<NetworkConfigSnapshot>> = Mutex::new(None);
 static LAST_DHCP_OFFER: Mutex<Option<DhcpOfferInfo>> = Mutex::new(None);
@@ -388,8 +713,45 @@ pub fn send_icmp_echo(target: [u8; 4], seq: u16) -> Result<f32, String> {
 
 /// Get TCP connections for netstat
 pub fn get_tcp_connections() -> Option<Vec<TcpConnectionInfo>> {
+    let fmt_addr = |addr: endpoint::SocketAddr| {
+        alloc::format!(
+            "{}.{}.{}.{}:{}",
+            addr.ip[0], addr.ip[1], addr.ip[2], addr.ip[3], addr.port
+        )
+    };
+
+    let connections: Vec<TcpConnectionInfo> = endpoint::tcb_table()
+        .snapshot()
+        .into_iter()
+        .map(|tcb| TcpConnectionInfo {
+            local_addr: fmt_addr(tcb.local),
+            remote_addr: fmt_addr(tcb.remote),
+            state: alloc::format!("{:?}", tcb.state),
+            stats: tcb.tcp_stats(),
+        })
+        .collect();
+
     // Return None to show demo output in shell
-    None
+    if connections.is_empty() { None } else { Some(connections) }
+}
+
+/// Get the system default TCP congestion control algorithm
+pub fn get_congestion_algorithm() -> &'static str {
+    endpoint::default_algorithm().name()
+}
+
+/// Set the system default TCP congestion control algorithm
+pub fn set_congestion_algorithm(name: &str) -> Result<(), String> {
+    match endpoint::CongestionAlgorithm::from_name(name) {
+        Some(algorithm) => {
+            endpoint::set_default_algorithm(algorithm);
+            Ok(())
+        }
+        None => Err(alloc::format!(
+            "Unknown congestion control algorithm: {} (available: newreno, cubic, bbr)",
+            name
+        )),
+    }
 }
 
 /// Get UDP sockets for netstat
@@ -397,14 +759,129 @@ pub fn get_udp_sockets() -> Option<Vec<UdpSocketInfo>> {
     None
 }
 
-/// DNS resolution
-pub fn dns_resolve(hostname: &str) -> Result<Vec<[u8; 4]>, String> {
-    // Built-in resolutions
+/// Built-in resolutions
+fn builtin_resolve(hostname: &str) -> Option<Vec<[u8; 4]>> {
+    if let Some(addr) = parse_ipv4_literal(hostname) {
+        return Some(alloc::vec![addr]);
+    }
     match hostname {
-        "localhost" => Ok(alloc::vec![[127, 0, 0, 1]]),
-        "gateway" | "router" => Ok(alloc::vec![[10, 0, 2, 2]]),
-        _ => Err(String::from("DNS server not configured")),
+        "localhost" => Some(alloc::vec![[127, 0, 0, 1]]),
+        "gateway" | "router" => Some(alloc::vec![[10, 0, 2, 2]]),
+        _ => None,
+    }
+}
+
+/// Parse a dotted-quad IPv4 literal
+fn parse_ipv4_literal(text: &str) -> Option<[u8; 4]> {
+    let mut octets = [0u8; 4];
+    let mut parts = text.split('.');
+    for octet in octets.iter_mut() {
+        *octet = parts.next()?.parse().ok()?;
+    }
+    parts.next().is_none().then_some(octets)
+}
+
+/// DNS resolution
+///
+/// `.local` 名は mDNS、それ以外は組み込み名か DNS サーバーへの問い合わせで解決する。
+/// mDNS の応答待ちや DNS サーバーへの問い合わせ中は他タスクに譲る
+pub async fn dns_resolve_async(hostname: &str) -> Result<Vec<[u8; 4]>, String> {
+    if mdns::is_local_name(hostname) {
+        return mdns::resolve_async(hostname)
+            .await
+            .map(|addrs| addrs.iter().map(|a| *a.as_bytes()).collect());
     }
+    if let Some(addrs) = builtin_resolve(hostname) {
+        return Ok(addrs);
+    }
+    dns::query(hostname, dns::QUERY_TIMEOUT_MS)
+        .await
+        .map(|addrs| addrs.iter().map(|a| *a.as_bytes()).collect())
+}
+
+/// Get the mDNS responder state and record cache
+pub fn get_mdns_info() -> MdnsInfo {
+    let now = crate::time::current_tick();
+    mdns::with_responder(|responder| MdnsInfo {
+        hostname: responder.host_name(),
+        services: responder
+            .services()
+            .iter()
+            .map(|service| MdnsServiceInfo {
+                name: service.instance_name(responder.hostname()),
+                service_type: service.service_type.clone(),
+                port: service.port,
+                txt: service.txt.clone(),
+            })
+            .collect(),
+        cache: responder
+            .cache()
+            .iter()
+            .filter(|cached| cached.expires_ms > now)
+            .map(|cached| MdnsCacheInfo {
+                name: cached.record.name.clone(),
+                rtype: mdns::message::type_name(cached.record.rtype()),
+                data: alloc::format!("{}", cached.record.data),
+                ttl: (cached.expires_ms - now) / 1000,
+            })
+            .collect(),
+        stats: responder.stats(),
+    })
+}
+
+/// Change the mDNS hostname (announces the new name)
+pub fn set_mdns_hostname(hostname: &str) -> Result<(), String> {
+    mdns::set_hostname(hostname)
+}
+
+/// Publish a service over DNS-SD ("_http._tcp", port)
+pub fn register_mdns_service(service_type: &str, port: u16, txt: &[&str]) -> Result<(), String> {
+    let service = txt
+        .iter()
+        .fold(MdnsService::new(service_type, port), |service, entry| service.with_txt(entry));
+    mdns::register_service(service)
+}
+
+/// Withdraw a published service
+pub fn unregister_mdns_service(service_type: &str) -> Result<(), String> {
+    mdns::unregister_service(service_type)
+}
+
+/// Browse for DNS-SD service instances on the link
+pub async fn mdns_browse(service_type: &str) -> Result<Vec<MdnsServiceInstance>, String> {
+    mdns::browse(service_type).await
+}
+
+/// Get joined multicast groups
+pub fn get_multicast_groups() -> Option<Vec<MulticastGroupInfo>> {
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_interfaces(|interfaces| {
+        interfaces
+            .iter()
+            .flat_map(|iface| {
+                iface.groups().map(|group| MulticastGroupInfo {
+                    interface: iface.name.clone(),
+                    group: *group.as_bytes(),
+                })
+            })
+            .collect()
+    }))
+}
+
+/// Join a multicast group on an interface (sends an IGMP report)
+pub fn join_multicast_group(interface: &str, group: [u8; 4]) -> Result<(), String> {
+    with_stack(|s| {
+        s.join_multicast(interface, Ipv4Address::new(group))
+            .map_err(|e| alloc::format!("{}: {}", interface, e))
+    })
+}
+
+/// Leave a multicast group on an interface (sends an IGMP leave)
+pub fn leave_multicast_group(interface: &str, group: [u8; 4]) -> Result<(), String> {
+    with_stack(|s| {
+        s.leave_multicast(interface, Ipv4Address::new(group))
+            .map_err(|e| alloc::format!("{}: {}", interface, e))
+    })
 }
 
 /// DHCP discover
@@ -493,6 +970,763 @@ pub fn get_arp_cache() -> Option<Vec<ArpCacheEntry>> {
     None
 }
 
+/// Get network interfaces
+pub fn get_interfaces() -> Option<Vec<InterfaceInfo>> {
+    let guard = stack::stack().lock();
+    let stack_guard = guard.as_ref()?;
+    Some(stack_guard.with_interfaces(|interfaces| {
+        interfaces
+            .iter()
+            .map(|iface| InterfaceInfo {
+                name: iface.name.clone(),
+                kind: iface.kind.name(),
+                mac: *iface.mac.as_bytes(),
+                mtu: iface.mtu,
+                up: iface.up,
+                addresses: iface
+                    .addresses()
+                    .iter()
+                    .map(|addr| alloc::format!("{}", addr))
+                    .collect(),
+                stats: iface.stats,
+                master: iface
+                    .master
+                    .and_then(|id| interfaces.get(id))
+                    .map(|master| master.name.clone()),
+                vlan_id: iface.vlan.map(|vlan| vlan.vlan_id),
+            })
+            .collect()
+    }))
+}
+
+/// Assign an address to an interface
+pub fn add_interface_address(name: &str, ip: [u8; 4], prefix_len: u8) -> Result<(), String> {
+    if prefix_len > 32 {
+        return Err(alloc::format!("{}", InterfaceError::InvalidPrefix));
+    }
+    with_stack(|s| {
+        s.add_address(name, InterfaceAddress::new(Ipv4Address::new(ip), prefix_len))
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Remove an address from an interface
+pub fn remove_interface_address(name: &str, ip: [u8; 4]) -> Result<(), String> {
+    with_stack(|s| {
+        s.remove_address(name, Ipv4Address::new(ip))
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Bring an interface up or down
+pub fn set_interface_up(name: &str, up: bool) -> Result<(), String> {
+    with_stack(|s| {
+        s.set_interface_up(name, up)
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Get the routing table
+pub fn get_routes() -> Option<Vec<RouteInfo>> {
+    let guard = stack::stack().lock();
+    let stack_guard = guard.as_ref()?;
+    let routes = stack_guard.routes();
+    Some(stack_guard.with_interfaces(|interfaces| {
+        routes
+            .iter()
+            .map(|route| RouteInfo {
+                destination: if route.is_default() {
+                    String::from("default")
+                } else {
+                    alloc::format!("{}/{}", route.destination, route.prefix_len)
+                },
+                gateway: route.gateway.map(|gw| *gw.as_bytes()),
+                interface: interfaces
+                    .get(route.interface)
+                    .map(|iface| iface.name.clone())
+                    .unwrap_or_else(|| String::from("?")),
+                metric: route.metric,
+                source: route.source.map(|src| *src.as_bytes()),
+                origin: route.origin.name(),
+            })
+            .collect()
+    }))
+}
+
+/// Add a static route
+///
+/// `interface` が None の場合はゲートウェイへの直結経路から出力インターフェースを決定する
+pub fn add_route(
+    destination: [u8; 4],
+    prefix_len: u8,
+    gateway: Option<[u8; 4]>,
+    interface: Option<&str>,
+    metric: Option<u32>,
+) -> Result<(), String> {
+    if prefix_len > 32 {
+        return Err(alloc::format!("{}", RouteError::InvalidPrefix));
+    }
+    let gateway = gateway.map(Ipv4Address::new);
+    with_stack(|s| {
+        let id = match (interface, gateway) {
+            (Some(name), _) => s
+                .with_interfaces(|interfaces| interfaces.by_name(name).map(|iface| iface.id))
+                .ok_or_else(|| alloc::format!("{}: {}", name, InterfaceError::NotFound))?,
+            (None, Some(gw)) => {
+                let lookup = s.route(gw).map_err(|e| alloc::format!("{}", e))?;
+                if lookup.next_hop != gw {
+                    return Err(alloc::format!("Gateway {} is not directly reachable", gw));
+                }
+                lookup.interface
+            }
+            (None, None) => return Err(String::from("Either a gateway or an interface is required")),
+        };
+        let metric = metric.unwrap_or(route::DEFAULT_ROUTE_METRIC);
+        s.add_route(Route::new(Ipv4Address::new(destination), prefix_len, gateway, id, metric))
+            .map_err(|e| alloc::format!("{}", e))
+    })
+}
+
+/// Remove routes to a destination
+pub fn remove_route(destination: [u8; 4], prefix_len: u8, interface: Option<&str>) -> Result<usize, String> {
+    with_stack(|s| {
+        let id = match interface {
+            Some(name) => Some(
+                s.with_interfaces(|interfaces| interfaces.by_name(name).map(|iface| iface.id))
+                    .ok_or_else(|| alloc::format!("{}: {}", name, InterfaceError::NotFound))?,
+            ),
+            None => None,
+        };
+        s.remove_route(Ipv4Address::new(destination), prefix_len, id)
+            .map_err(|e| alloc::format!("{}", e))
+    })
+}
+
+/// Resolve the route to a destination
+pub fn route_lookup(destination: [u8; 4]) -> Result<RouteLookupInfo, String> {
+    with_stack(|s| {
+        let lookup = s
+            .route(Ipv4Address::new(destination))
+            .map_err(|e| alloc::format!("{}", e))?;
+        let interface = s.with_interfaces(|interfaces| {
+            interfaces
+                .get(lookup.interface)
+                .map(|iface| iface.name.clone())
+                .unwrap_or_else(|| String::from("?"))
+        });
+        Ok(RouteLookupInfo {
+            interface,
+            next_hop: *lookup.next_hop.as_bytes(),
+            source: *lookup.source.as_bytes(),
+            mtu: lookup.mtu,
+        })
+    })
+}
+
+/// Start a packet capture at the driver bridge
+pub fn start_capture(filter: Option<&str>, max_packets: Option<usize>) -> Result<(), String> {
+    let filter = match filter {
+        Some(expr) => Some(CaptureFilter::parse(expr).map_err(|e| alloc::format!("{}", e))?),
+        None => None,
+    };
+    capture::start(CaptureConfig {
+        filter,
+        max_packets: max_packets.unwrap_or(capture::DEFAULT_RING_PACKETS),
+        ..CaptureConfig::default()
+    });
+    Ok(())
+}
+
+/// Stop the packet capture (captured packets are kept)
+pub fn stop_capture() -> Option<CaptureStats> {
+    capture::stop()
+}
+
+/// Get packet capture status
+pub fn get_capture_status() -> Option<CaptureStatusInfo> {
+    let stats = capture::stats()?;
+    Some(CaptureStatusInfo {
+        active: capture::is_active(),
+        filter: capture::filter_expression().unwrap_or_default(),
+        stats,
+    })
+}
+
+/// Get the most recent captured packets
+pub fn get_captured_packets(limit: usize) -> Vec<CapturedPacketInfo> {
+    capture::recent_packets(limit)
+        .iter()
+        .map(|packet| CapturedPacketInfo {
+            timestamp_us: packet.timestamp_us,
+            direction: packet.direction.name(),
+            length: packet.original_len,
+            summary: packet.info().summary(),
+        })
+        .collect()
+}
+
+/// Save the capture as a pcapng file
+pub fn save_capture(path: &str) -> Result<usize, String> {
+    capture::save(path).map_err(|e| alloc::format!("{}", e))
+}
+
+/// Stream the capture as pcapng over the serial port (COM2)
+pub fn stream_capture_serial() -> Result<usize, String> {
+    capture::stream_serial().map_err(|e| alloc::format!("{}", e))
+}
+
+/// Add a firewall rule (appended, or inserted at a position within its chain)
+pub fn add_firewall_rule(spec: &str, position: Option<usize>) -> Result<u32, String> {
+    let rule = FirewallRule::parse(spec).map_err(|e| alloc::format!("{}", e))?;
+    with_stack(|s| {
+        s.with_firewall(|fw| match position {
+            Some(position) => fw.insert_rule(position, rule),
+            None => fw.add_rule(rule),
+        })
+        .map_err(|e| alloc::format!("{}", e))
+    })
+}
+
+/// Delete a firewall rule by ID
+pub fn delete_firewall_rule(id: u32) -> Result<(), String> {
+    with_stack(|s| {
+        s.with_firewall(|fw| fw.delete_rule(id).map(|_| ()))
+            .map_err(|e| alloc::format!("{}", e))
+    })
+}
+
+/// Remove firewall rules of a chain (None = all chains)
+pub fn flush_firewall(chain: Option<&str>) -> Result<usize, String> {
+    let chain = chain.map(parse_chain).transpose()?;
+    with_stack(|s| Ok(s.with_firewall(|fw| fw.flush(chain))))
+}
+
+/// Set a firewall chain's default policy ("accept" or "drop")
+pub fn set_firewall_policy(chain: &str, action: &str) -> Result<(), String> {
+    let chain = parse_chain(chain)?;
+    let action = FirewallAction::from_name(action)
+        .ok_or_else(|| alloc::format!("Unknown action '{}' (expected accept or drop)", action))?;
+    with_stack(|s| {
+        s.with_firewall(|fw| fw.set_policy(chain, action));
+        Ok(())
+    })
+}
+
+/// Get firewall rules in evaluation order
+pub fn get_firewall_rules() -> Option<Vec<FirewallRuleInfo>> {
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_firewall(|fw| {
+        fw.rules()
+            .iter()
+            .map(|rule| FirewallRuleInfo {
+                id: rule.id,
+                chain: rule.chain.name(),
+                rule: alloc::format!("{}", rule),
+                packets: rule.packets,
+                bytes: rule.bytes,
+            })
+            .collect()
+    }))
+}
+
+/// Get firewall chain policies and counters
+pub fn get_firewall_chains() -> Option<Vec<FirewallChainInfo>> {
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_firewall(|fw| {
+        fw.chains()
+            .iter()
+            .map(|info| FirewallChainInfo {
+                chain: info.chain.name(),
+                policy: info.policy.name(),
+                accepted: info.accepted,
+                dropped: info.dropped,
+                policy_packets: info.policy_packets,
+            })
+            .collect()
+    }))
+}
+
+/// Get the connection tracking table
+pub fn get_conntrack() -> Option<Vec<ConnTrackInfo>> {
+    let now = crate::time::current_tick();
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_firewall(|fw| {
+        fw.conntrack()
+            .entries()
+            .iter()
+            .map(|entry| ConnTrackInfo {
+                protocol: firewall::protocol_name(entry.original.protocol).unwrap_or("?"),
+                flow: alloc::format!("{}", entry.original),
+                tcp_state: entry.tcp_state.map(|state| state.name()),
+                replied: entry.seen_reply,
+                packets: entry.packets,
+                bytes: entry.bytes,
+                expires_ms: (entry.last_seen_ms + entry.timeout_ms()).saturating_sub(now),
+            })
+            .collect()
+    }))
+}
+
+/// Clear the connection tracking table
+pub fn flush_conntrack() -> Result<(), String> {
+    with_stack(|s| {
+        s.with_firewall(|fw| fw.flush_conntrack());
+        Ok(())
+    })
+}
+
+/// Enable or disable IPv4 forwarding between interfaces
+pub fn set_forwarding(enabled: bool) -> Result<(), String> {
+    with_stack(|s| {
+        s.set_forwarding(enabled);
+        Ok(())
+    })
+}
+
+/// Get forwarding status and counters
+pub fn get_forwarding() -> Option<ForwardingInfo> {
+    let guard = stack::stack().lock();
+    let s = guard.as_ref()?;
+    let stats = s.forward_stats();
+    let (masquerade, nat_entries) = s.with_nat(|nat| (nat.masquerade().to_vec(), nat.len()));
+    Some(ForwardingInfo {
+        enabled: s.forwarding_enabled(),
+        masquerade,
+        forwarded: stats.forwarded.load(Ordering::Relaxed),
+        ttl_exceeded: stats.ttl_exceeded.load(Ordering::Relaxed),
+        no_route: stats.no_route.load(Ordering::Relaxed),
+        too_big: stats.too_big.load(Ordering::Relaxed),
+        filtered: stats.filtered.load(Ordering::Relaxed),
+        nat_dropped: stats.nat_dropped.load(Ordering::Relaxed),
+        nat_entries,
+    })
+}
+
+/// Masquerade connections forwarded out of an interface
+pub fn add_masquerade(interface: &str) -> Result<(), String> {
+    with_stack(|s| {
+        if s.with_interfaces(|interfaces| interfaces.by_name(interface).is_none()) {
+            return Err(alloc::format!("Interface '{}' not found", interface));
+        }
+        if !s.with_nat(|nat| nat.add_masquerade(interface)) {
+            return Err(alloc::format!("Masquerade already enabled on {}", interface));
+        }
+        Ok(())
+    })
+}
+
+/// Stop masquerading on an interface
+pub fn remove_masquerade(interface: &str) -> Result<(), String> {
+    with_stack(|s| {
+        if !s.with_nat(|nat| nat.remove_masquerade(interface)) {
+            return Err(alloc::format!("Masquerade not enabled on {}", interface));
+        }
+        Ok(())
+    })
+}
+
+/// Forward a local port to an internal host ("tcp" or "udp")
+pub fn add_port_forward(
+    protocol: &str,
+    port: u16,
+    to: [u8; 4],
+    to_port: u16,
+    interface: Option<&str>,
+) -> Result<u32, String> {
+    let protocol = match protocol {
+        "tcp" => firewall::rule::PROTO_TCP,
+        "udp" => firewall::rule::PROTO_UDP,
+        other => return Err(alloc::format!("Unknown protocol '{}' (expected tcp or udp)", other)),
+    };
+    let mut rule = PortForward::new(protocol, port, Ipv4Address::new(to), to_port);
+    if let Some(name) = interface {
+        rule = rule.on_interface(name);
+    }
+    with_stack(|s| {
+        s.with_nat(|nat| nat.add_port_forward(rule))
+            .map_err(|e| alloc::format!("{}", e))
+    })
+}
+
+/// Remove a port-forwarding rule by ID
+pub fn remove_port_forward(id: u32) -> Result<(), String> {
+    with_stack(|s| {
+        s.with_nat(|nat| nat.remove_port_forward(id))
+            .map(|_| ())
+            .ok_or_else(|| alloc::format!("Port forward {} not found", id))
+    })
+}
+
+/// Get port-forwarding rules
+pub fn get_port_forwards() -> Option<Vec<PortForwardInfo>> {
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_nat(|nat| {
+        nat.port_forwards()
+            .iter()
+            .map(|rule| PortForwardInfo {
+                id: rule.id,
+                rule: alloc::format!("{}", rule),
+            })
+            .collect()
+    }))
+}
+
+/// Get the NAT translation table
+pub fn get_nat_entries() -> Option<Vec<NatEntryInfo>> {
+    let now = crate::time::current_tick();
+    let guard = stack::stack().lock();
+    Some(guard.as_ref()?.with_nat(|nat| {
+        nat.entries()
+            .iter()
+            .map(|entry| NatEntryInfo {
+                protocol: firewall::protocol_name(entry.original.protocol).unwrap_or("?"),
+                original: alloc::format!("{}", entry.original),
+                translated: alloc::format!("{}", entry.translated),
+                replied: entry.seen_reply,
+                packets: entry.packets,
+                bytes: entry.bytes,
+                expires_ms: (entry.last_seen_ms + entry.timeout_ms()).saturating_sub(now),
+            })
+            .collect()
+    }))
+}
+
+/// Create a WireGuard interface
+///
+/// `private_key` が None の場合は新しい鍵を生成する。戻り値は公開鍵（Base64）
+pub fn add_wireguard(name: &str, listen_port: u16, private_key: Option<&str>) -> Result<String, String> {
+    let private_key = match private_key {
+        Some(text) => wireguard::crypto::decode_key(text)
+            .ok_or_else(|| alloc::format!("{}", WgError::InvalidKey))?,
+        None => wireguard::generate_private_key().map_err(|e| alloc::format!("{}", e))?,
+    };
+    with_stack(|s| {
+        s.add_wireguard(name, private_key, listen_port)
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })?;
+    wireguard::start_timers();
+    Ok(wireguard::crypto::encode_key(&wireguard::public_key(&private_key)))
+}
+
+/// Remove a WireGuard interface
+pub fn remove_wireguard(name: &str) -> Result<(), String> {
+    with_stack(|s| {
+        s.with_wireguard(name, |_| ())
+            .map_err(|e| alloc::format!("{}: {}", name, e))?;
+        s.remove_interface(name)
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Add or replace a WireGuard peer
+///
+/// `allowed_ips` はカンマ区切りの CIDR（"10.0.0.2/32,192.168.20.0/24"）
+pub fn set_wireguard_peer(
+    name: &str,
+    public_key: &str,
+    allowed_ips: &str,
+    endpoint: Option<([u8; 4], u16)>,
+    persistent_keepalive: u16,
+    preshared_key: Option<&str>,
+) -> Result<(), String> {
+    let key = wireguard::crypto::decode_key(public_key)
+        .ok_or_else(|| alloc::format!("{}", WgError::InvalidKey))?;
+    let mut config = WgPeerConfig::new(key).persistent_keepalive(persistent_keepalive);
+    for cidr in allowed_ips.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+        let (addr, prefix_len) = match cidr.split_once('/') {
+            Some((addr, len)) => (addr, len.parse::<u8>().ok().filter(|len| *len <= 32)),
+            None => (cidr, Some(32)),
+        };
+        match (parse_ipv4_literal(addr), prefix_len) {
+            (Some(ip), Some(prefix_len)) => {
+                config = config.allowed_ip(InterfaceAddress::new(Ipv4Address::new(ip), prefix_len));
+            }
+            _ => return Err(alloc::format!("Invalid allowed IP '{}'", cidr)),
+        }
+    }
+    if let Some((ip, port)) = endpoint {
+        config = config.endpoint(UdpAddr::new(Ipv4Address::new(ip), port));
+    }
+    if let Some(psk) = preshared_key {
+        let psk = wireguard::crypto::decode_key(psk)
+            .ok_or_else(|| alloc::format!("{}", WgError::InvalidKey))?;
+        config = config.preshared_key(psk);
+    }
+    with_stack(|s| {
+        s.with_wireguard(name, |wg| wg.set_peer(config))
+            .and_then(|result| result)
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Remove a WireGuard peer
+pub fn remove_wireguard_peer(name: &str, public_key: &str) -> Result<(), String> {
+    let key = wireguard::crypto::decode_key(public_key)
+        .ok_or_else(|| alloc::format!("{}", WgError::InvalidKey))?;
+    with_stack(|s| {
+        s.with_wireguard(name, |wg| wg.remove_peer(&key))
+            .and_then(|result| result)
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Get WireGuard interfaces and their peers
+pub fn get_wireguard() -> Option<Vec<WireGuardInfo>> {
+    let now = crate::time::current_tick();
+    let guard = stack::stack().lock();
+    let s = guard.as_ref()?;
+    let infos = s
+        .wireguard_interfaces()
+        .into_iter()
+        .filter_map(|name| {
+            s.with_wireguard(&name, |wg| WireGuardInfo {
+                public_key: wireguard::crypto::encode_key(wg.public_key()),
+                listen_port: wg.listen_port(),
+                peers: wg
+                    .peers()
+                    .map(|peer| WgPeerInfo {
+                        public_key: wireguard::crypto::encode_key(&peer.public_key),
+                        endpoint: peer
+                            .endpoint
+                            .map(|e| alloc::format!("{}:{}", e.ip, e.port)),
+                        allowed_ips: wg
+                            .allowed_ips_of(&peer.public_key)
+                            .iter()
+                            .map(|prefix| alloc::format!("{}", prefix))
+                            .collect(),
+                        latest_handshake_ms: peer.last_handshake_ms.map(|t| now.saturating_sub(t)),
+                        rx_bytes: peer.rx_bytes,
+                        tx_bytes: peer.tx_bytes,
+                        persistent_keepalive: peer.persistent_keepalive,
+                        connected: peer.has_session(now),
+                    })
+                    .collect(),
+                stats: *wg.stats(),
+                name: name.clone(),
+            })
+            .ok()
+        })
+        .collect();
+    Some(infos)
+}
+
+/// Create a VLAN interface on an Ethernet interface
+///
+/// 戻り値は作成したインターフェース名（省略時は "<親>.<VLAN ID>"）
+pub fn add_vlan(parent: &str, vlan_id: u16, priority: u8, name: Option<&str>) -> Result<String, String> {
+    with_stack(|s| {
+        let id = s
+            .add_vlan(parent, vlan_id, priority, name)
+            .map_err(|e| alloc::format!("{}: {}", parent, e))?;
+        Ok(s.with_interfaces(|interfaces| {
+            interfaces.get(id).map(|i| i.name.clone()).unwrap_or_default()
+        }))
+    })
+}
+
+/// Remove a VLAN or bridge interface
+pub fn remove_link(name: &str) -> Result<(), String> {
+    with_stack(|s| {
+        let kind = s.with_interfaces(|interfaces| interfaces.by_name(name).map(|i| i.kind));
+        match kind {
+            Some(InterfaceKind::Vlan | InterfaceKind::Bridge) => s
+                .remove_interface(name)
+                .map_err(|e| alloc::format!("{}: {}", name, e)),
+            Some(_) => Err(alloc::format!("{}: not a VLAN or bridge interface", name)),
+            None => Err(alloc::format!("{}: {}", name, InterfaceError::NotFound)),
+        }
+    })
+}
+
+/// Create an Ethernet bridge
+pub fn add_bridge(name: &str) -> Result<(), String> {
+    with_stack(|s| {
+        s.add_bridge(name)
+            .map(|_| ())
+            .map_err(|e| alloc::format!("{}: {}", name, e))
+    })
+}
+
+/// Add a port to a bridge
+pub fn add_bridge_port(bridge: &str, port: &str) -> Result<(), String> {
+    with_stack(|s| {
+        s.add_bridge_port(bridge, port)
+            .map_err(|e| alloc::format!("{}: {}", port, e))
+    })
+}
+
+/// Remove a port from a bridge
+pub fn remove_bridge_port(bridge: &str, port: &str) -> Result<(), String> {
+    with_stack(|s| {
+        s.remove_bridge_port(bridge, port)
+            .map_err(|e| alloc::format!("{}: {}", port, e))
+    })
+}
+
+/// Set a bridge's MAC ageing time (0 = never age out)
+pub fn set_bridge_ageing(bridge: &str, ageing_ms: u64) -> Result<(), String> {
+    with_stack(|s| {
+        s.with_bridge(bridge, |br| br.set_ageing_ms(ageing_ms))
+            .map_err(|e| alloc::format!("{}: {}", bridge, e))
+    })
+}
+
+/// Get bridges with their ports and MAC tables
+pub fn get_bridges() -> Option<Vec<BridgeInfo>> {
+    let now = crate::time::current_tick();
+    let guard = stack::stack().lock();
+    let s = guard.as_ref()?;
+    let port_name = |id: InterfaceId| {
+        s.with_interfaces(|interfaces| interfaces.get(id).map(|i| i.name.clone()))
+            .unwrap_or_else(|| alloc::format!("#{}", id))
+    };
+    let infos = s
+        .bridge_interfaces()
+        .into_iter()
+        .filter_map(|name| {
+            let up = s.with_interfaces(|interfaces| interfaces.by_name(&name).is_some_and(|i| i.up));
+            // ポート名の解決はインターフェーステーブルのロックを取るので、先にブリッジの内容を写す
+            let (mac, ports, ageing_ms, fdb, stats) = s
+                .with_bridge(&name, |br| {
+                    br.expire(now);
+                    (
+                        br.mac(),
+                        br.ports().to_vec(),
+                        br.ageing_ms(),
+                        br.fdb().map(|(mac, entry)| (*mac, *entry)).collect::<Vec<_>>(),
+                        *br.stats(),
+                    )
+                })
+                .ok()?;
+            Some(BridgeInfo {
+                mac: alloc::format!("{}", mac),
+                up,
+                ports: ports.into_iter().map(port_name).collect(),
+                ageing_ms,
+                fdb: fdb
+                    .into_iter()
+                    .map(|(mac, entry)| FdbInfo {
+                        mac: alloc::format!("{}", mac),
+                        port: port_name(entry.port),
+                        age_ms: now.saturating_sub(entry.last_seen_ms),
+                        is_static: entry.is_static,
+                    })
+                    .collect(),
+                stats,
+                name,
+            })
+        })
+        .collect();
+    Some(infos)
+}
+
+/// Parse a firewall chain name
+fn parse_chain(name: &str) -> Result<FirewallChain, String> {
+    FirewallChain::from_name(name)
+        .ok_or_else(|| alloc::format!("Unknown chain '{}' (expected input, output or forward)", name))
+}
+
+/// Restrict or allow a domain's use of network ports (security::policy)
+///
+/// `ports` は "tcp:8080", "udp:5000-6000", "*:*" 形式。
+/// `direction` は "in"（bind/listen）, "out"（connect/send）, "any"。
+/// 許可ルールは拒否ルールより優先されるため、「8080 のみ許可」は
+/// allow tcp:8080 と deny *:* の組で表現できる。
+pub fn add_port_policy(
+    domain: Option<u64>,
+    ports: &str,
+    direction: Option<&str>,
+    allow: bool,
+) -> Result<u64, String> {
+    use crate::security::policy::{
+        self, PolicyAction, PolicyObject, PolicyOperation, PolicyRule, PolicySubject,
+    };
+
+    if !valid_port_pattern(ports) {
+        return Err(alloc::format!(
+            "Invalid port pattern '{}' (expected tcp:80, udp:5000-6000, *:*)",
+            ports
+        ));
+    }
+    let operation = match direction.unwrap_or("any") {
+        "in" => PolicyOperation::Receive,
+        "out" => PolicyOperation::Send,
+        "any" => PolicyOperation::Any,
+        other => return Err(alloc::format!("Unknown direction '{}' (expected in, out or any)", other)),
+    };
+    let subject = domain.map_or(PolicySubject::Any, PolicySubject::Domain);
+    let (action, priority) = if allow {
+        (PolicyAction::Allow, 200)
+    } else {
+        (PolicyAction::DenyAudit, 100)
+    };
+    let rule = PolicyRule::new(subject, PolicyObject::Network(String::from(ports)), operation, action)
+        .with_priority(priority)
+        .with_description("Network port policy");
+    let id = rule.id;
+    policy::add_rule(rule);
+    Ok(id)
+}
+
+/// Get per-domain port policy rules
+pub fn get_port_policies() -> Vec<PortPolicyInfo> {
+    use crate::security::policy::{self, PolicyObject, PolicyOperation, PolicySubject};
+
+    policy::network_rules()
+        .into_iter()
+        .map(|rule| PortPolicyInfo {
+            id: rule.id,
+            domain: match rule.subject {
+                PolicySubject::Domain(id) => Some(id),
+                _ => None,
+            },
+            ports: match rule.object {
+                PolicyObject::Network(ref pattern) => pattern.clone(),
+                _ => String::new(),
+            },
+            direction: match rule.operation {
+                PolicyOperation::Receive => "in",
+                PolicyOperation::Send => "out",
+                _ => "any",
+            },
+            action: if rule.action.is_allow() { "allow" } else { "deny" },
+        })
+        .collect()
+}
+
+/// Remove a port policy rule
+pub fn remove_port_policy(id: u64) -> Result<(), String> {
+    let is_network_rule = get_port_policies().iter().any(|rule| rule.id == id);
+    if is_network_rule && crate::security::policy::remove_rule(id) {
+        Ok(())
+    } else {
+        Err(alloc::format!("No port policy rule with id {}", id))
+    }
+}
+
+/// Validate a "proto:ports" pattern
+fn valid_port_pattern(pattern: &str) -> bool {
+    let (proto, ports) = pattern.split_once(':').unwrap_or((pattern, "*"));
+    let proto_ok = matches!(proto, "tcp" | "udp" | "raw" | "*");
+    let ports_ok = ports == "*"
+        || match ports.split_once('-') {
+            Some((start, end)) => matches!(
+                (start.parse::<u16>(), end.parse::<u16>()),
+                (Ok(start), Ok(end)) if start <= end
+            ),
+            None => ports.parse::<u16>().is_ok(),
+        };
+    proto_ok && ports_ok
+}
+
+/// Run a closure against the initialized network stack
+fn with_stack<R>(f: impl FnOnce(&NetworkStack) -> Result<R, String>) -> Result<R, String> {
+    match stack::stack().lock().as_ref() {
+        Some(stack_guard) => f(stack_guard),
+        None => Err(String::from("Network stack not initialized")),
+    }
+}
+
 /// Initialize network for shell commands
 pub fn init_network_shell() {
     // Initialize default network config (QEMU user mode networking)
@@ -0,0 +1,813 @@
+//! # NAT - マスカレードとポート転送
+//!
+//! 転送するパケットのアドレスとポートを書き換える。
+//!
+//! - マスカレード: 指定したインターフェースから出る新しいコネクションの送信元を
+//!   そのインターフェースのアドレスにし、ポート（ICMP エコーは識別子）を割り当てる
+//! - ポート転送: 自ホストの指定ポートに届いたコネクションの宛先を内部ホストへ変える
+//!
+//! 変換はコネクション単位で記録し、応答方向のパケットと、変換したパケットを引用する
+//! ICMP エラーは逆に変換する。エントリはプロトコルと状態ごとのタイムアウトで消える。
+//! フラグメントは再組み立てしないため、変換が必要なフラグメントは破棄する。
+//!
+//! 本体は `NetworkStack` が保持し、転送経路から呼ばれる。
+//! 経路選択の前に `prerouting`（逆変換・ポート転送）、送信の直前に
+//! `postrouting`（マスカレード）を適用する。
+
+pub mod rewrite;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::firewall::rule::{PROTO_ICMP, PROTO_TCP, PROTO_UDP};
+use super::firewall::{ConnKey, PacketMeta, protocol_name};
+use super::ipv4::Ipv4Address;
+
+/// Maximum translated connections
+pub const MAX_NAT_ENTRIES: usize = 4096;
+
+/// Maximum port-forwarding rules
+pub const MAX_PORT_FORWARDS: usize = 64;
+
+/// Ports (and ICMP identifiers) allocated by masquerade
+///
+/// ローカルの一時ポート (49152-65535) と重ならない範囲を使う。
+pub const NAT_PORT_MIN: u16 = 32768;
+/// Last port allocated by masquerade
+pub const NAT_PORT_MAX: u16 = 49151;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_RST: u8 = 0x04;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+// タイムアウト（ミリ秒）
+const TCP_UNREPLIED_TIMEOUT_MS: u64 = 120_000;
+const TCP_ESTABLISHED_TIMEOUT_MS: u64 = 3_600_000;
+const TCP_CLOSING_TIMEOUT_MS: u64 = 60_000;
+const UDP_TIMEOUT_MS: u64 = 30_000;
+const UDP_STREAM_TIMEOUT_MS: u64 = 180_000;
+const ICMP_TIMEOUT_MS: u64 = 30_000;
+
+/// NAT errors (the packet is dropped)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NatError {
+    /// Fragment of a translated connection
+    Fragmented,
+    /// Translation table is full
+    TableFull,
+    /// No free port on the masquerade address
+    PortsExhausted,
+    /// Too many port-forwarding rules
+    TooManyForwards,
+}
+
+impl fmt::Display for NatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NatError::Fragmented => write!(f, "Cannot translate fragmented packet"),
+            NatError::TableFull => write!(f, "NAT table full (max {})", MAX_NAT_ENTRIES),
+            NatError::PortsExhausted => write!(f, "No free masquerade port"),
+            NatError::TooManyForwards => {
+                write!(f, "Too many port forwards (max {})", MAX_PORT_FORWARDS)
+            }
+        }
+    }
+}
+
+/// Destination port-forwarding rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortForward {
+    /// Rule ID (assigned on insertion)
+    pub id: u32,
+    /// IP protocol (TCP or UDP)
+    pub protocol: u8,
+    /// Ingress interface (None = any)
+    pub interface: Option<String>,
+    /// Local port to forward
+    pub port: u16,
+    /// Internal host
+    pub to: Ipv4Address,
+    /// Port on the internal host
+    pub to_port: u16,
+}
+
+impl PortForward {
+    /// Create a rule (ID is assigned by `Nat::add_port_forward`)
+    pub fn new(protocol: u8, port: u16, to: Ipv4Address, to_port: u16) -> Self {
+        PortForward {
+            id: 0,
+            protocol,
+            interface: None,
+            port,
+            to,
+            to_port,
+        }
+    }
+
+    /// Restrict the rule to an ingress interface
+    pub fn on_interface(mut self, name: &str) -> Self {
+        self.interface = Some(String::from(name));
+        self
+    }
+
+    fn matches(&self, key: &ConnKey, ingress: &str) -> bool {
+        self.protocol == key.protocol
+            && self.port == key.dst_port
+            && self.interface.as_deref().is_none_or(|name| name == ingress)
+    }
+}
+
+impl fmt::Display for PortForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}:{}",
+            protocol_name(self.protocol).unwrap_or("?"),
+            self.port,
+            self.to,
+            self.to_port
+        )?;
+        if let Some(ref name) = self.interface {
+            write!(f, " iface {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Translated connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NatEntry {
+    /// Flow as sent by the initiator
+    pub original: ConnKey,
+    /// Flow after translation (as seen by the responder)
+    pub translated: ConnKey,
+    /// Traffic seen in the reply direction
+    pub seen_reply: bool,
+    /// FIN or RST seen (TCP)
+    pub closing: bool,
+    /// Creation time
+    pub created_ms: u64,
+    /// Last packet time
+    pub last_seen_ms: u64,
+    /// Packets in both directions
+    pub packets: u64,
+    /// Bytes in both directions
+    pub bytes: u64,
+}
+
+impl NatEntry {
+    /// Source is rewritten (masquerade)
+    pub fn snat(&self) -> bool {
+        self.original.src != self.translated.src
+            || self.original.src_port != self.translated.src_port
+    }
+
+    /// Destination is rewritten (port forward)
+    pub fn dnat(&self) -> bool {
+        self.original.dst != self.translated.dst
+            || self.original.dst_port != self.translated.dst_port
+    }
+
+    /// Idle timeout for the entry
+    pub fn timeout_ms(&self) -> u64 {
+        match self.original.protocol {
+            PROTO_TCP if self.closing => TCP_CLOSING_TIMEOUT_MS,
+            PROTO_TCP if self.seen_reply => TCP_ESTABLISHED_TIMEOUT_MS,
+            PROTO_TCP => TCP_UNREPLIED_TIMEOUT_MS,
+            PROTO_UDP if self.seen_reply => UDP_STREAM_TIMEOUT_MS,
+            PROTO_UDP => UDP_TIMEOUT_MS,
+            _ => ICMP_TIMEOUT_MS,
+        }
+    }
+
+    /// Check if the entry has timed out
+    pub fn expired(&self, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.last_seen_ms) > self.timeout_ms()
+    }
+
+    fn touch(&mut self, meta: &PacketMeta<'_>, reply: bool, now_ms: u64) {
+        self.last_seen_ms = now_ms;
+        self.packets += 1;
+        self.bytes += meta.len as u64;
+        self.seen_reply |= reply;
+        if meta.protocol == PROTO_TCP && meta.tcp_flags & (TCP_FIN | TCP_RST) != 0 {
+            self.closing = true;
+        }
+    }
+}
+
+/// NAT state of a packet between `prerouting` and `postrouting`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NatFlow {
+    /// Entry key (originating direction)
+    pub original: ConnKey,
+    /// Packet travels in the reply direction
+    pub reply: bool,
+}
+
+/// NAT counters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NatStats {
+    /// Packets translated
+    pub translated: u64,
+    /// Entries created
+    pub created: u64,
+    /// Entries expired
+    pub expired: u64,
+    /// Packets dropped (fragments, table full, no port)
+    pub dropped: u64,
+}
+
+/// Masquerade and port-forwarding table
+#[derive(Debug, Default)]
+pub struct Nat {
+    /// Interfaces whose outgoing connections are masqueraded
+    masquerade: Vec<String>,
+    /// Port-forwarding rules
+    forwards: Vec<PortForward>,
+    /// Entries keyed by the originating direction
+    entries: BTreeMap<ConnKey, NatEntry>,
+    /// Reply-direction key → originating key
+    replies: BTreeMap<ConnKey, ConnKey>,
+    /// Next port to try when allocating
+    next_port: u16,
+    /// Next rule ID
+    next_id: u32,
+    /// Counters
+    pub stats: NatStats,
+}
+
+impl Nat {
+    /// Create an empty table (no translation)
+    pub const fn new() -> Self {
+        Nat {
+            masquerade: Vec::new(),
+            forwards: Vec::new(),
+            entries: BTreeMap::new(),
+            replies: BTreeMap::new(),
+            next_port: NAT_PORT_MIN,
+            next_id: 1,
+            stats: NatStats {
+                translated: 0,
+                created: 0,
+                expired: 0,
+                dropped: 0,
+            },
+        }
+    }
+
+    /// Any rule or entry exists
+    pub fn is_active(&self) -> bool {
+        !self.masquerade.is_empty() || !self.forwards.is_empty() || !self.entries.is_empty()
+    }
+
+    /// Masquerade connections leaving an interface (false if already enabled)
+    pub fn add_masquerade(&mut self, interface: &str) -> bool {
+        if self.masquerade.iter().any(|name| name == interface) {
+            return false;
+        }
+        self.masquerade.push(String::from(interface));
+        true
+    }
+
+    /// Stop masquerading on an interface (existing entries stay until they expire)
+    pub fn remove_masquerade(&mut self, interface: &str) -> bool {
+        let before = self.masquerade.len();
+        self.masquerade.retain(|name| name != interface);
+        self.masquerade.len() != before
+    }
+
+    /// Interfaces with masquerade enabled
+    pub fn masquerade(&self) -> &[String] {
+        &self.masquerade
+    }
+
+    /// Add a port-forwarding rule, returning the assigned ID
+    pub fn add_port_forward(&mut self, mut rule: PortForward) -> Result<u32, NatError> {
+        if self.forwards.len() >= MAX_PORT_FORWARDS {
+            return Err(NatError::TooManyForwards);
+        }
+        rule.id = self.next_id;
+        self.next_id += 1;
+        let id = rule.id;
+        self.forwards.push(rule);
+        Ok(id)
+    }
+
+    /// Remove a port-forwarding rule
+    pub fn remove_port_forward(&mut self, id: u32) -> Option<PortForward> {
+        let index = self.forwards.iter().position(|rule| rule.id == id)?;
+        Some(self.forwards.remove(index))
+    }
+
+    /// Port-forwarding rules
+    pub fn port_forwards(&self) -> &[PortForward] {
+        &self.forwards
+    }
+
+    /// Translated connections
+    pub fn entries(&self) -> Vec<NatEntry> {
+        self.entries.values().copied().collect()
+    }
+
+    /// Number of translated connections
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if no connection is translated
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all translated connections
+    pub fn flush(&mut self) {
+        self.entries.clear();
+        self.replies.clear();
+    }
+
+    /// Remove timed-out entries, returning how many were removed
+    pub fn expire(&mut self, now_ms: u64) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !entry.expired(now_ms));
+        let entries = &self.entries;
+        self.replies
+            .retain(|_, original| entries.contains_key(original));
+        let removed = before - self.entries.len();
+        self.stats.expired += removed as u64;
+        removed
+    }
+
+    /// Translate a received packet before the routing decision
+    ///
+    /// 既存エントリの応答は逆変換し、ポート転送ルールに一致する新しいコネクションは
+    /// 宛先を書き換える。`is_local` は自ホストのアドレスか（ポート転送の対象）。
+    pub fn prerouting(
+        &mut self,
+        packet: &mut [u8],
+        ingress: &str,
+        is_local: impl Fn(&Ipv4Address) -> bool,
+        now_ms: u64,
+    ) -> Result<Option<NatFlow>, NatError> {
+        let Some(meta) = PacketMeta::parse(packet, ingress) else {
+            return Ok(None);
+        };
+        if let Some(inner) = meta.icmp_inner {
+            self.translate_icmp_error(packet, &meta, inner);
+            return Ok(None);
+        }
+        let Some(key) = ConnKey::from_meta(&meta) else {
+            return Ok(None);
+        };
+
+        // 応答方向: 元のフローの逆向きに戻す
+        if let Some(original) = self.replies.get(&key).copied()
+            && let Some(entry) = self.entries.get_mut(&original)
+        {
+            if rewrite::is_fragment(packet) {
+                self.stats.dropped += 1;
+                return Err(NatError::Fragmented);
+            }
+            if entry.dnat() {
+                rewrite::set_source(packet, Ipv4Address::new(original.dst), original.dst_port);
+            }
+            if entry.snat() {
+                rewrite::set_destination(packet, Ipv4Address::new(original.src), original.src_port);
+            }
+            entry.touch(&meta, true, now_ms);
+            self.stats.translated += 1;
+            return Ok(Some(NatFlow {
+                original,
+                reply: true,
+            }));
+        }
+
+        // 元の方向: 宛先の変換のみここで行う（送信元は postrouting）
+        if let Some(entry) = self.entries.get_mut(&key) {
+            if entry.dnat() {
+                if rewrite::is_fragment(packet) {
+                    self.stats.dropped += 1;
+                    return Err(NatError::Fragmented);
+                }
+                let translated = entry.translated;
+                rewrite::set_destination(
+                    packet,
+                    Ipv4Address::new(translated.dst),
+                    translated.dst_port,
+                );
+                self.stats.translated += 1;
+            }
+            entry.touch(&meta, false, now_ms);
+            return Ok(Some(NatFlow {
+                original: key,
+                reply: false,
+            }));
+        }
+
+        // 新しいコネクション: ポート転送
+        let Some((to, to_port)) = self
+            .forwards
+            .iter()
+            .find(|rule| rule.matches(&key, ingress) && is_local(&meta.dst))
+            .map(|rule| (rule.to, rule.to_port))
+        else {
+            return Ok(None);
+        };
+        if rewrite::is_fragment(packet) {
+            self.stats.dropped += 1;
+            return Err(NatError::Fragmented);
+        }
+        let translated = ConnKey {
+            dst: *to.as_bytes(),
+            dst_port: to_port,
+            ..key
+        };
+        self.insert(key, translated, &meta, now_ms)?;
+        rewrite::set_destination(packet, to, to_port);
+        self.stats.translated += 1;
+        Ok(Some(NatFlow {
+            original: key,
+            reply: false,
+        }))
+    }
+
+    /// Translate a packet about to leave `egress`
+    ///
+    /// マスカレードが有効なインターフェースから出る新しいコネクションに送信元を
+    /// 割り当て、既存エントリの送信元を書き換える。`egress_address` は送信元に使う
+    /// インターフェースのアドレス。
+    pub fn postrouting(
+        &mut self,
+        packet: &mut [u8],
+        flow: Option<NatFlow>,
+        egress: &str,
+        egress_address: Option<Ipv4Address>,
+        now_ms: u64,
+    ) -> Result<(), NatError> {
+        let masquerade = self.masquerade.iter().any(|name| name == egress);
+        match flow {
+            Some(NatFlow { reply: true, .. }) => Ok(()),
+            Some(NatFlow {
+                original,
+                reply: false,
+            }) => {
+                let Some(entry) = self.entries.get(&original).copied() else {
+                    return Ok(());
+                };
+                if entry.snat() {
+                    if rewrite::is_fragment(packet) {
+                        self.stats.dropped += 1;
+                        return Err(NatError::Fragmented);
+                    }
+                    let translated = entry.translated;
+                    rewrite::set_source(
+                        packet,
+                        Ipv4Address::new(translated.src),
+                        translated.src_port,
+                    );
+                    self.stats.translated += 1;
+                    return Ok(());
+                }
+                // ポート転送したばかりのコネクションが別のマスカレード側へ出る場合
+                let Some(address) = egress_address.filter(|_| masquerade && entry.packets == 1)
+                else {
+                    return Ok(());
+                };
+                self.masquerade_entry(packet, original, entry.translated, address)
+            }
+            None => {
+                let Some(address) = egress_address.filter(|_| masquerade) else {
+                    return Ok(());
+                };
+                let Some(meta) = PacketMeta::parse(packet, egress) else {
+                    return Ok(());
+                };
+                let Some(key) = ConnKey::from_meta(&meta) else {
+                    return Ok(());
+                };
+                // 自ホスト発、または ICMP エコー要求以外の ICMP は対象外
+                if meta.src == address
+                    || (key.protocol == PROTO_ICMP && meta.icmp_type != Some(ICMP_ECHO_REQUEST))
+                {
+                    return Ok(());
+                }
+                if rewrite::is_fragment(packet) {
+                    self.stats.dropped += 1;
+                    return Err(NatError::Fragmented);
+                }
+                self.insert(key, key, &meta, now_ms)?;
+                self.masquerade_entry(packet, key, key, address)
+            }
+        }
+    }
+
+    /// Assign a masquerade source to an entry and rewrite the packet
+    fn masquerade_entry(
+        &mut self,
+        packet: &mut [u8],
+        original: ConnKey,
+        translated: ConnKey,
+        address: Ipv4Address,
+    ) -> Result<(), NatError> {
+        let Some(port) = self.allocate_port(&translated, address) else {
+            self.entries.remove(&original);
+            self.replies.remove(&translated.reversed());
+            self.stats.dropped += 1;
+            return Err(NatError::PortsExhausted);
+        };
+        let mut masqueraded = ConnKey {
+            src: *address.as_bytes(),
+            src_port: port,
+            ..translated
+        };
+        if masqueraded.protocol == PROTO_ICMP {
+            masqueraded.dst_port = port;
+        }
+
+        self.replies.remove(&translated.reversed());
+        self.replies.insert(masqueraded.reversed(), original);
+        if let Some(entry) = self.entries.get_mut(&original) {
+            entry.translated = masqueraded;
+        }
+        rewrite::set_source(packet, address, port);
+        self.stats.translated += 1;
+        Ok(())
+    }
+
+    /// Find a port whose reply flow is unused on `address`
+    fn allocate_port(&mut self, flow: &ConnKey, address: Ipv4Address) -> Option<u16> {
+        let range = (NAT_PORT_MAX - NAT_PORT_MIN) as u32 + 1;
+        for _ in 0..range {
+            let port = self.next_port;
+            self.next_port = if port >= NAT_PORT_MAX {
+                NAT_PORT_MIN
+            } else {
+                port + 1
+            };
+
+            let reply = if flow.protocol == PROTO_ICMP {
+                ConnKey::new(PROTO_ICMP, Ipv4Address::new(flow.dst), port, address, port)
+            } else {
+                ConnKey::new(
+                    flow.protocol,
+                    Ipv4Address::new(flow.dst),
+                    flow.dst_port,
+                    address,
+                    port,
+                )
+            };
+            if !self.replies.contains_key(&reply) {
+                return Some(port);
+            }
+        }
+        None
+    }
+
+    /// Record a new entry
+    fn insert(
+        &mut self,
+        original: ConnKey,
+        translated: ConnKey,
+        meta: &PacketMeta<'_>,
+        now_ms: u64,
+    ) -> Result<(), NatError> {
+        if self.entries.len() >= MAX_NAT_ENTRIES && self.expire(now_ms) == 0 {
+            self.stats.dropped += 1;
+            return Err(NatError::TableFull);
+        }
+        let mut entry = NatEntry {
+            original,
+            translated,
+            seen_reply: false,
+            closing: false,
+            created_ms: now_ms,
+            last_seen_ms: now_ms,
+            packets: 0,
+            bytes: 0,
+        };
+        entry.touch(meta, false, now_ms);
+        self.entries.insert(original, entry);
+        self.replies.insert(translated.reversed(), original);
+        self.stats.created += 1;
+        Ok(())
+    }
+
+    /// Translate an ICMP error quoting a translated packet
+    ///
+    /// 引用されたパケットは変換後のフロー（`translated`）なので、引用部分と
+    /// 外側のアドレスを元のフローに戻し、ICMP チェックサムを計算し直す。
+    fn translate_icmp_error(&mut self, packet: &mut [u8], meta: &PacketMeta<'_>, inner: ConnKey) {
+        let Some(original) = self.replies.get(&inner.reversed()).copied() else {
+            return;
+        };
+        let Some(entry) = self.entries.get(&original).copied() else {
+            return;
+        };
+        let header_len = (packet[0] & 0x0F) as usize * 4;
+        let Some(quoted) = packet.get_mut(header_len + 8..) else {
+            return;
+        };
+        if entry.snat() {
+            rewrite::set_source(quoted, Ipv4Address::new(original.src), original.src_port);
+        }
+        if entry.dnat() {
+            rewrite::set_destination(quoted, Ipv4Address::new(original.dst), original.dst_port);
+        }
+
+        if entry.snat() {
+            rewrite::set_destination(packet, Ipv4Address::new(original.src), 0);
+        }
+        if entry.dnat() && *meta.src.as_bytes() == entry.translated.dst {
+            rewrite::set_source(packet, Ipv4Address::new(original.dst), 0);
+        }
+        rewrite::update_icmp_checksum(packet);
+        self.stats.translated += 1;
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ipv4::{
+        IpProtocol, Ipv4Packet, Ipv4PacketMut, data_checksum, pseudo_header_checksum,
+    };
+
+    const INSIDE: Ipv4Address = Ipv4Address::from_octets(192, 168, 50, 10);
+    const ROUTER_WAN: Ipv4Address = Ipv4Address::from_octets(10, 0, 2, 15);
+    const REMOTE: Ipv4Address = Ipv4Address::from_octets(93, 184, 216, 34);
+
+    /// TCP/UDP パケット（チェックサム付き）
+    fn packet(
+        protocol: IpProtocol,
+        src: Ipv4Address,
+        sport: u16,
+        dst: Ipv4Address,
+        dport: u16,
+    ) -> Vec<u8> {
+        let mut buffer = alloc::vec![0u8; 60];
+        let mut ip = Ipv4PacketMut::new(&mut buffer).unwrap();
+        ip.init_header()
+            .set_source(src)
+            .set_destination(dst)
+            .set_protocol(protocol)
+            .set_ttl(64);
+        let l4_len = if protocol == IpProtocol::Tcp { 20 } else { 12 };
+        let l4 = &mut ip.payload_mut()[..l4_len];
+        l4[0..2].copy_from_slice(&sport.to_be_bytes());
+        l4[2..4].copy_from_slice(&dport.to_be_bytes());
+        if protocol == IpProtocol::Tcp {
+            l4[12] = 0x50;
+            l4[13] = 0x02; // SYN
+        } else {
+            l4[4..6].copy_from_slice(&(l4_len as u16).to_be_bytes());
+            l4[8..12].copy_from_slice(b"ping");
+        }
+        let offset = if protocol == IpProtocol::Tcp { 16 } else { 6 };
+        let sum = pseudo_header_checksum(src, dst, protocol, l4_len as u16);
+        let checksum = data_checksum(l4, sum);
+        l4[offset..offset + 2].copy_from_slice(&checksum.to_be_bytes());
+        ip.finalize(l4_len);
+        let len = ip.total_len();
+        buffer.truncate(len);
+        buffer
+    }
+
+    /// IPv4 と L4 のチェックサムが正しいか
+    fn checksums_valid(data: &[u8]) -> bool {
+        let ip = Ipv4Packet::parse(data).unwrap();
+        let l4 = ip.payload();
+        let sum = pseudo_header_checksum(
+            ip.source(),
+            ip.destination(),
+            ip.protocol(),
+            l4.len() as u16,
+        );
+        ip.verify_checksum() && data_checksum(l4, sum) == 0
+    }
+
+    fn endpoints(data: &[u8]) -> (Ipv4Address, u16, Ipv4Address, u16) {
+        let ip = Ipv4Packet::parse(data).unwrap();
+        let l4 = ip.payload();
+        (
+            ip.source(),
+            u16::from_be_bytes([l4[0], l4[1]]),
+            ip.destination(),
+            u16::from_be_bytes([l4[2], l4[3]]),
+        )
+    }
+
+    #[test]
+    fn test_masquerade_round_trip() {
+        let mut nat = Nat::new();
+        assert!(nat.add_masquerade("eth0"));
+        let local = |_: &Ipv4Address| false;
+
+        // 内部ホスト → 外部: 送信元をルーターのアドレスとポートに
+        let mut out = packet(IpProtocol::Tcp, INSIDE, 40000, REMOTE, 80);
+        let flow = nat.prerouting(&mut out, "eth1", local, 0).unwrap();
+        assert_eq!(flow, None);
+        nat.postrouting(&mut out, flow, "eth0", Some(ROUTER_WAN), 0)
+            .unwrap();
+        let (src, sport, dst, dport) = endpoints(&out);
+        assert_eq!((src, dst, dport), (ROUTER_WAN, REMOTE, 80));
+        assert!((NAT_PORT_MIN..=NAT_PORT_MAX).contains(&sport));
+        assert!(checksums_valid(&out));
+
+        // 応答は内部ホストへ戻す
+        let mut reply = packet(IpProtocol::Tcp, REMOTE, 80, ROUTER_WAN, sport);
+        let flow = nat
+            .prerouting(&mut reply, "eth0", local, 10)
+            .unwrap()
+            .unwrap();
+        assert!(flow.reply);
+        assert_eq!(endpoints(&reply), (REMOTE, 80, INSIDE, 40000));
+        assert!(checksums_valid(&reply));
+
+        // 同じフローの2パケット目は同じポートを使い、別フローは別ポート
+        let mut again = packet(IpProtocol::Tcp, INSIDE, 40000, REMOTE, 80);
+        let flow = nat.prerouting(&mut again, "eth1", local, 20).unwrap();
+        nat.postrouting(&mut again, flow, "eth0", Some(ROUTER_WAN), 20)
+            .unwrap();
+        assert_eq!(endpoints(&again).1, sport);
+        let mut udp = packet(IpProtocol::Udp, INSIDE, 5353, REMOTE, 53);
+        nat.postrouting(&mut udp, None, "eth0", Some(ROUTER_WAN), 20)
+            .unwrap();
+        assert!(checksums_valid(&udp));
+        assert_ne!(endpoints(&udp).1, 5353);
+        assert_eq!(nat.len(), 2);
+
+        // マスカレードしないインターフェースからは変換しない
+        let mut lan = packet(IpProtocol::Udp, INSIDE, 5000, REMOTE, 53);
+        nat.postrouting(&mut lan, None, "eth2", Some(ROUTER_WAN), 20)
+            .unwrap();
+        assert_eq!(endpoints(&lan).0, INSIDE);
+
+        // 応答のない UDP は 30 秒、応答のあった TCP は長く残る
+        assert_eq!(nat.expire(20 + UDP_TIMEOUT_MS + 1), 1);
+        assert_eq!(nat.len(), 1);
+    }
+
+    #[test]
+    fn test_port_forward_and_icmp_error() {
+        let mut nat = Nat::new();
+        nat.add_port_forward(PortForward::new(PROTO_TCP, 8080, INSIDE, 80).on_interface("eth0"))
+            .unwrap();
+        let local = |addr: &Ipv4Address| *addr == ROUTER_WAN;
+
+        // 外部 → ルーター:8080 を内部ホスト:80 へ
+        let mut request = packet(IpProtocol::Tcp, REMOTE, 51000, ROUTER_WAN, 8080);
+        let flow = nat.prerouting(&mut request, "eth0", local, 0).unwrap();
+        assert!(flow.is_some_and(|f| !f.reply));
+        assert_eq!(endpoints(&request), (REMOTE, 51000, INSIDE, 80));
+        assert!(checksums_valid(&request));
+        nat.postrouting(&mut request, flow, "eth1", None, 0)
+            .unwrap();
+        assert_eq!(endpoints(&request).0, REMOTE);
+
+        // 内部ホストの応答は送信元をルーター:8080 に戻す
+        let mut reply = packet(IpProtocol::Tcp, INSIDE, 80, REMOTE, 51000);
+        nat.prerouting(&mut reply, "eth1", local, 1).unwrap();
+        assert_eq!(endpoints(&reply), (ROUTER_WAN, 8080, REMOTE, 51000));
+        assert!(checksums_valid(&reply));
+
+        // 他のインターフェースから来たものは転送しない
+        let mut other = packet(IpProtocol::Tcp, REMOTE, 51001, ROUTER_WAN, 8080);
+        assert_eq!(nat.prerouting(&mut other, "eth2", local, 2).unwrap(), None);
+
+        // マスカレードしたフローへの ICMP エラー（経路上のルーターから）
+        nat.add_masquerade("eth0");
+        let mut out = packet(IpProtocol::Udp, INSIDE, 7000, REMOTE, 33434);
+        nat.postrouting(&mut out, None, "eth0", Some(ROUTER_WAN), 3)
+            .unwrap();
+        let hop = Ipv4Address::from_octets(10, 0, 2, 2);
+        let mut error = alloc::vec![0u8; 20 + 8 + out.len()];
+        let mut ip = Ipv4PacketMut::new(&mut error).unwrap();
+        ip.init_header()
+            .set_source(hop)
+            .set_destination(ROUTER_WAN)
+            .set_protocol(IpProtocol::Icmp)
+            .set_ttl(64);
+        let icmp = ip.payload_mut();
+        icmp[0] = 11;
+        icmp[8..8 + out.len()].copy_from_slice(&out);
+        let checksum = data_checksum(&icmp[..8 + out.len()], 0);
+        icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+        ip.finalize(8 + out.len());
+
+        assert_eq!(nat.prerouting(&mut error, "eth0", local, 4).unwrap(), None);
+        let ip = Ipv4Packet::parse(&error).unwrap();
+        assert_eq!((ip.source(), ip.destination()), (hop, INSIDE));
+        assert!(ip.verify_checksum());
+        assert_eq!(data_checksum(ip.payload(), 0), 0);
+        assert_eq!(endpoints(&ip.payload()[8..]), (INSIDE, 7000, REMOTE, 33434));
+
+        assert!(nat.remove_port_forward(1).is_some());
+        assert!(nat.port_forwards().is_empty());
+    }
+}
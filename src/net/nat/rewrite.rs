@@ -0,0 +1,123 @@
+//! パケットの書き換え
+//!
+//! IPv4 パケットの送信元・宛先（アドレスとポート）を書き換え、IPv4 ヘッダと
+//! TCP / UDP / ICMP のチェックサムを差分更新する。ICMP エコーでは識別子を
+//! ポートとして扱う。
+//!
+//! ICMP エラーが引用するパケットは先頭 8 バイトしか L4 を持たないため、
+//! 範囲外のチェックサムは更新しない。
+
+use crate::net::firewall::rule::{PROTO_ICMP, PROTO_TCP, PROTO_UDP};
+use crate::net::ipv4::{Ipv4Address, checksum_adjust};
+
+/// IPv4 ヘッダの送信元アドレス位置
+const SOURCE_OFFSET: usize = 12;
+/// IPv4 ヘッダの宛先アドレス位置
+const DESTINATION_OFFSET: usize = 16;
+/// IPv4 ヘッダチェックサム位置
+const CHECKSUM_OFFSET: usize = 10;
+
+/// ICMP エコー要求 / 応答
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+/// フラグメントされたパケットか（MF または offset != 0）
+pub fn is_fragment(packet: &[u8]) -> bool {
+    packet.len() >= 8 && (packet[6] & 0x3F != 0 || packet[7] != 0)
+}
+
+/// 送信元アドレスとポートを書き換える
+pub fn set_source(packet: &mut [u8], address: Ipv4Address, port: u16) -> bool {
+    rewrite(packet, SOURCE_OFFSET, 0, address, port)
+}
+
+/// 宛先アドレスとポートを書き換える
+pub fn set_destination(packet: &mut [u8], address: Ipv4Address, port: u16) -> bool {
+    rewrite(packet, DESTINATION_OFFSET, 2, address, port)
+}
+
+/// ICMP メッセージのチェックサムを計算し直す（引用パケットを書き換えた後）
+pub fn update_icmp_checksum(packet: &mut [u8]) {
+    let Some((header_len, end)) = bounds(packet) else {
+        return;
+    };
+    let icmp = &mut packet[header_len..end];
+    if icmp.len() < 4 {
+        return;
+    }
+    icmp[2..4].fill(0);
+    let checksum = crate::net::ipv4::data_checksum(icmp, 0);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// ヘッダ長と、パケットとして有効な末尾（引用パケットでは切り詰められている）
+fn bounds(packet: &[u8]) -> Option<(usize, usize)> {
+    let header_len = (*packet.first()? & 0x0F) as usize * 4;
+    if header_len < 20 || packet.len() < header_len {
+        return None;
+    }
+    let total = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    Some((header_len, total.clamp(header_len, packet.len())))
+}
+
+/// アドレス (`address_offset`) とポート (L4 の `port_offset`) を書き換える
+fn rewrite(
+    packet: &mut [u8],
+    address_offset: usize,
+    port_offset: usize,
+    address: Ipv4Address,
+    port: u16,
+) -> bool {
+    let Some((header_len, end)) = bounds(packet) else {
+        return false;
+    };
+    let protocol = packet[9];
+
+    let mut old_address = [0u8; 4];
+    old_address.copy_from_slice(&packet[address_offset..address_offset + 4]);
+    let new_address = *address.as_bytes();
+    packet[address_offset..address_offset + 4].copy_from_slice(&new_address);
+    adjust(packet, CHECKSUM_OFFSET, &old_address, &new_address);
+
+    // 後続フラグメントは L4 ヘッダを持たない
+    if packet[6] & 0x1F != 0 || packet[7] != 0 {
+        return true;
+    }
+    let l4 = &mut packet[header_len..end];
+    let new_port = port.to_be_bytes();
+    match protocol {
+        PROTO_TCP | PROTO_UDP if l4.len() >= 4 => {
+            let checksum_offset = if protocol == PROTO_TCP { 16 } else { 6 };
+            let mut old_port = [0u8; 2];
+            old_port.copy_from_slice(&l4[port_offset..port_offset + 2]);
+            l4[port_offset..port_offset + 2].copy_from_slice(&new_port);
+
+            // UDP のチェックサム 0 は「なし」
+            let has_checksum = l4.len() >= checksum_offset + 2
+                && (protocol == PROTO_TCP || l4[checksum_offset..checksum_offset + 2] != [0, 0]);
+            if has_checksum {
+                // 疑似ヘッダのアドレスとポート
+                adjust(l4, checksum_offset, &old_address, &new_address);
+                adjust(l4, checksum_offset, &old_port, &new_port);
+                if protocol == PROTO_UDP && l4[checksum_offset..checksum_offset + 2] == [0, 0] {
+                    l4[checksum_offset..checksum_offset + 2].copy_from_slice(&[0xFF, 0xFF]);
+                }
+            }
+        }
+        PROTO_ICMP if l4.len() >= 8 && matches!(l4[0], ICMP_ECHO_REQUEST | ICMP_ECHO_REPLY) => {
+            let mut old_id = [0u8; 2];
+            old_id.copy_from_slice(&l4[4..6]);
+            l4[4..6].copy_from_slice(&new_port);
+            adjust(l4, 2, &old_id, &new_port);
+        }
+        _ => {}
+    }
+    true
+}
+
+/// `data[offset..offset + 2]` のチェックサムを差分更新
+fn adjust(data: &mut [u8], offset: usize, old: &[u8], new: &[u8]) {
+    let checksum = u16::from_be_bytes([data[offset], data[offset + 1]]);
+    let checksum = checksum_adjust(checksum, old, new);
+    data[offset..offset + 2].copy_from_slice(&checksum.to_be_bytes());
+}
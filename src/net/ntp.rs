@@ -0,0 +1,446 @@
+//! # NTP クライアント
+//!
+//! NTP (RFC 5905 のクライアントモード) でシステム時計を同期する。
+//!
+//! - サーバー: `set_servers` で設定したもの、なければ DHCP オプション42
+//! - 選別: 直近8サンプルのうち往復遅延が最小のもの（`filter::ClockFilter`）
+//! - 補正: 128ms を超えるずれはステップ、それ以下はスルー（最大500ppm）。
+//!   補正間のずれから TSC の周波数誤差を推定して打ち消す（`filter::Discipline`）
+//! - ポーリング: 16秒から始め、安定していれば 1024秒まで伸ばす。
+//!   Kiss-o'-Death の `RATE` で間隔を伸ばし、`DENY` / `RSTR` でそのサーバーをやめる
+//!
+//! ローカルの chrony や ntpd に対して試験できる。
+//! 例えば chrony なら `allow` を設定し `local stratum 8` で自前の時計を配信させ、
+//! シェルから `sys.ntp("10.0.2.2")` で同期を始めて `sys.time()` で状態を見る。
+//! QEMU のユーザーネットワークではホストへ UDP 123 を転送する必要がある。
+
+#![allow(dead_code)]
+
+pub mod filter;
+pub mod packet;
+
+pub use filter::{Adjustment, ClockFilter, Discipline, Sample};
+pub use packet::{NtpTimestamp, Packet, PacketError};
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use spin::Mutex;
+
+use super::ipv4::Ipv4Address;
+use super::udp::UdpSocket;
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// NTP ポート
+pub const NTP_PORT: u16 = 123;
+
+/// 問い合わせのタイムアウト (ms)
+pub const QUERY_TIMEOUT_MS: u64 = 2000;
+
+/// ポーリング間隔の最小値 (log2 秒, 16秒)
+pub const MIN_POLL: i8 = 4;
+
+/// ポーリング間隔の最大値 (log2 秒, 1024秒)
+pub const MAX_POLL: i8 = 10;
+
+/// 起動直後にフィルタを満たすまでの問い合わせ間隔 (ms)
+const BURST_INTERVAL_MS: u64 = 2000;
+
+/// 起動直後に連続して問い合わせるサンプル数
+const BURST_SAMPLES: usize = 4;
+
+/// これ以下のずれならポーリング間隔を伸ばす (ns)
+const POLL_INCREASE_THRESHOLD_NS: i64 = 2_000_000;
+
+/// 応答待ちのポーリング間隔 (ms, 受信時刻の誤差になるので短くする)
+const RECV_POLL_MS: u64 = 1;
+
+/// 問い合わせ元ポートの範囲
+const QUERY_PORT_BASE: u16 = 49800;
+const QUERY_PORT_COUNT: u16 = 16;
+
+/// 次に試す問い合わせ元ポートのオフセット
+static NEXT_QUERY_PORT: AtomicU16 = AtomicU16::new(0);
+
+// ============================================================================
+// 状態
+// ============================================================================
+
+/// 同期状態
+#[derive(Debug, Clone, Default)]
+pub struct NtpStatus {
+    /// 同期ループが動いているか
+    pub running: bool,
+    /// 少なくとも1回補正したか
+    pub synchronized: bool,
+    /// 最後に応答したサーバー
+    pub server: Option<Ipv4Address>,
+    /// サーバーの階層
+    pub stratum: u8,
+    /// サーバーの参照ID
+    pub reference: String,
+    /// 最後に採用したオフセット (ns)
+    pub offset: i64,
+    /// 最後に採用したサンプルの往復遅延 (ns)
+    pub delay: i64,
+    /// ジッタ (ns)
+    pub jitter: i64,
+    /// 周波数補正 (ppb)
+    pub frequency_ppb: i64,
+    /// ステップした回数
+    pub steps: u32,
+    /// フィルタ内のサンプル数
+    pub samples: usize,
+    /// ポーリング間隔 (log2 秒)
+    pub poll: i8,
+    /// 最後に同期した稼働時間 (ms)
+    pub last_sync: Option<u64>,
+    /// 最後のエラー
+    pub last_error: Option<String>,
+}
+
+/// クライアントの内部状態
+struct NtpState {
+    servers: Vec<Ipv4Address>,
+    /// Kiss-o'-Death で拒否されたサーバー
+    denied: Vec<Ipv4Address>,
+    filter: ClockFilter,
+    discipline: Discipline,
+    status: NtpStatus,
+}
+
+static STATE: Mutex<NtpState> = Mutex::new(NtpState {
+    servers: Vec::new(),
+    denied: Vec::new(),
+    filter: ClockFilter::new(),
+    discipline: Discipline::new(),
+    status: NtpStatus {
+        running: false,
+        synchronized: false,
+        server: None,
+        stratum: 0,
+        reference: String::new(),
+        offset: 0,
+        delay: 0,
+        jitter: 0,
+        frequency_ppb: 0,
+        steps: 0,
+        samples: 0,
+        poll: MIN_POLL,
+        last_sync: None,
+        last_error: None,
+    },
+});
+
+/// 同期ループが動いているか
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 同期ループの世代（`stop` で進め、古いループを終わらせる）
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// 同期先サーバーを設定（空なら DHCP で得たものを使う）
+pub fn set_servers(servers: Vec<Ipv4Address>) {
+    let mut state = STATE.lock();
+    state.servers = servers;
+    state.denied.clear();
+}
+
+/// 同期先サーバー（設定済みのもの、なければ DHCP オプション42）
+pub fn servers() -> Vec<Ipv4Address> {
+    let configured = STATE.lock().servers.clone();
+    if !configured.is_empty() {
+        return configured;
+    }
+    super::dhcp::client()
+        .and_then(|client| client.lock().as_ref().and_then(|c| c.lease()))
+        .map(|lease| lease.ntp_servers)
+        .unwrap_or_default()
+}
+
+/// 現在の同期状態
+pub fn status() -> NtpStatus {
+    let state = STATE.lock();
+    let mut status = state.status.clone();
+    status.running = RUNNING.load(Ordering::Acquire);
+    status.frequency_ppb = state.discipline.frequency_ppb();
+    status.steps = state.discipline.steps();
+    status.samples = state.filter.len();
+    status.jitter = state.filter.jitter();
+    status
+}
+
+// ============================================================================
+// 問い合わせ
+// ============================================================================
+
+/// 1回の問い合わせ結果
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    /// サーバーの応答
+    pub packet: Packet,
+    /// 測定結果
+    pub sample: Sample,
+}
+
+/// 問い合わせの失敗
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// 送信元ポートを確保できない
+    NoPort,
+    /// 応答がない
+    Timeout,
+    /// 応答が不正
+    Invalid(PacketError),
+}
+
+impl core::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            QueryError::NoPort => write!(f, "no free port for NTP query"),
+            QueryError::Timeout => write!(f, "NTP query timed out"),
+            QueryError::Invalid(PacketError::KissOfDeath(code)) => write!(
+                f,
+                "kiss-o'-death {}",
+                core::str::from_utf8(code).unwrap_or("????")
+            ),
+            QueryError::Invalid(error) => write!(f, "invalid NTP response: {:?}", error),
+        }
+    }
+}
+
+/// 空いている問い合わせ元ポートをバインド
+fn bind_query_socket() -> Option<(u16, UdpSocket)> {
+    for _ in 0..QUERY_PORT_COUNT {
+        let offset = NEXT_QUERY_PORT.fetch_add(1, Ordering::Relaxed) % QUERY_PORT_COUNT;
+        let port = QUERY_PORT_BASE + offset;
+        if let Some(socket) = super::stack::bind_udp(port) {
+            return Some((port, socket));
+        }
+    }
+    None
+}
+
+/// サーバーに1回問い合わせてオフセットと往復遅延を測る（async）
+///
+/// 時計は変更しない。
+pub async fn query(server: Ipv4Address, timeout_ms: u64) -> Result<QueryResult, QueryError> {
+    let (port, socket) = bind_query_socket().ok_or(QueryError::NoPort)?;
+    let result = exchange(&socket, port, server, timeout_ms).await;
+    super::stack::unbind_udp(port);
+    result
+}
+
+/// 要求を送り、オリジン時刻の一致する応答を待つ
+///
+/// 再送すると往復遅延が不正確になるので、1回だけ送る。
+async fn exchange(
+    socket: &UdpSocket,
+    port: u16,
+    server: Ipv4Address,
+    timeout_ms: u64,
+) -> Result<QueryResult, QueryError> {
+    let clock = crate::time::system_clock();
+    let poll = STATE.lock().status.poll;
+
+    let t1 = clock.now_nanos();
+    let sent = NtpTimestamp::from_unix_nanos(t1);
+    super::stack::send_udp(
+        port,
+        server,
+        NTP_PORT,
+        &Packet::client_request(sent, poll).encode(),
+    );
+
+    let start = crate::time::current_tick();
+    let mut last_error = QueryError::Timeout;
+    while crate::time::current_tick().saturating_sub(start) < timeout_ms {
+        while socket.rx_queue_len() > 0 {
+            let Some(datagram) = socket.recv().await else {
+                return Err(last_error);
+            };
+            let t4 = clock.now_nanos();
+            if datagram.src.ip != server || datagram.src.port != NTP_PORT {
+                continue;
+            }
+            let packet = match Packet::decode(&datagram.data) {
+                Ok(packet) => packet,
+                Err(error) => {
+                    last_error = QueryError::Invalid(error);
+                    continue;
+                }
+            };
+            match packet.validate_response(sent) {
+                Ok(()) => {}
+                // 古い要求への応答は捨てて待ち続ける
+                Err(PacketError::BogusOrigin) => continue,
+                Err(error) => return Err(QueryError::Invalid(error)),
+            }
+            let (offset, delay) = packet::offset_and_delay(
+                t1,
+                packet.receive.to_unix_nanos(),
+                packet.transmit.to_unix_nanos(),
+                t4,
+            );
+            let sample = Sample {
+                uptime: clock.uptime_nanos(),
+                offset,
+                delay,
+            };
+            return Ok(QueryResult { packet, sample });
+        }
+        crate::task::sleep_ms(RECV_POLL_MS).await;
+    }
+    Err(last_error)
+}
+
+// ============================================================================
+// 同期
+// ============================================================================
+
+/// 設定済みサーバーに順に問い合わせ、時計を1回補正する（async）
+///
+/// 採用したサンプルが前回の補正より古い場合、時計は変更しない。
+pub async fn sync_once() -> Result<NtpStatus, String> {
+    let servers = servers();
+    if servers.is_empty() {
+        return Err(String::from("NTP server not configured"));
+    }
+
+    let mut last_error = String::new();
+    for server in servers {
+        if STATE.lock().denied.contains(&server) {
+            continue;
+        }
+        match query(server, QUERY_TIMEOUT_MS).await {
+            Ok(result) => {
+                apply(server, &result);
+                return Ok(status());
+            }
+            Err(error) => {
+                let mut state = STATE.lock();
+                match &error {
+                    QueryError::Invalid(packet_error) if packet_error.is_fatal() => {
+                        state.denied.push(server);
+                    }
+                    QueryError::Invalid(PacketError::KissOfDeath(_)) => {
+                        state.status.poll = (state.status.poll + 1).min(MAX_POLL);
+                    }
+                    _ => {}
+                }
+                last_error = format!("{}: {}", server, error);
+                state.status.last_error = Some(last_error.clone());
+            }
+        }
+    }
+    if last_error.is_empty() {
+        last_error = String::from("all NTP servers refused service");
+    }
+    Err(last_error)
+}
+
+/// サンプルをフィルタに入れ、時計を補正する
+fn apply(server: Ipv4Address, result: &QueryResult) {
+    let clock = crate::time::system_clock();
+    let mut state = STATE.lock();
+    let state = &mut *state;
+
+    // サーバーが変わったら以前のサンプルは比較できない
+    if state.status.server != Some(server) {
+        state.filter.clear();
+    }
+
+    // フィルタには保留中のスルーを除いたオフセットを入れる
+    let pending = clock.slew_remaining();
+    state.filter.push(Sample {
+        offset: result.sample.offset - pending,
+        ..result.sample
+    });
+
+    state.status.server = Some(server);
+    state.status.stratum = result.packet.stratum;
+    state.status.reference = result.packet.reference_name();
+    state.status.last_error = None;
+
+    let Some(best) = state.filter.best() else {
+        return;
+    };
+    let Some(adjustment) = state.discipline.update(best, pending) else {
+        return;
+    };
+    match adjustment {
+        Adjustment::Step(amount) => {
+            clock.step_by(amount);
+            crate::log!("[NTP] Stepped clock by {} ms\n", amount / 1_000_000);
+        }
+        Adjustment::Slew(amount) => clock.slew_by(amount),
+    }
+    clock.set_frequency_ppb(state.discipline.frequency_ppb());
+    state.filter.adjust(best.offset);
+
+    state.status.synchronized = true;
+    state.status.offset = best.offset + pending;
+    state.status.delay = best.delay;
+    state.status.last_sync = Some(crate::time::current_tick());
+
+    // 安定していれば間隔を伸ばし、大きくずれたら最短に戻す
+    let offset = state.status.offset.abs();
+    state.status.poll =
+        if matches!(adjustment, Adjustment::Step(_)) || offset > 4 * POLL_INCREASE_THRESHOLD_NS {
+            MIN_POLL
+        } else if offset < POLL_INCREASE_THRESHOLD_NS.max(state.filter.jitter() * 2) {
+            (state.status.poll + 1).min(MAX_POLL)
+        } else {
+            state.status.poll
+        };
+}
+
+/// 同期ループを開始（既に動いていれば何もしない）
+pub fn start() -> bool {
+    if RUNNING.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    let generation = GENERATION.load(Ordering::Acquire);
+    crate::task::spawn(run(generation));
+    true
+}
+
+/// 同期ループを止める（時計の補正は保つ）
+pub fn stop() -> bool {
+    if !RUNNING.swap(false, Ordering::AcqRel) {
+        return false;
+    }
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+    true
+}
+
+/// 同期ループ
+async fn run(generation: u32) {
+    let active = || GENERATION.load(Ordering::Acquire) == generation;
+    while active() {
+        if let Err(error) = sync_once().await {
+            STATE.lock().status.last_error = Some(error);
+        }
+
+        let (samples, poll) = {
+            let state = STATE.lock();
+            (state.filter.len(), state.status.poll)
+        };
+        let interval_ms = if samples < BURST_SAMPLES {
+            BURST_INTERVAL_MS
+        } else {
+            1000u64 << poll
+        };
+
+        // 停止要求に素早く応じるため1秒ずつ待つ
+        let mut waited = 0;
+        while waited < interval_ms && active() {
+            let step = (interval_ms - waited).min(1000);
+            crate::task::sleep_ms(step).await;
+            waited += step;
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! NTP サンプルの選別と時計の規律
+//!
+//! - `ClockFilter`: 直近8個のサンプルから往復遅延が最小のものを選ぶ
+//!   （遅延が小さいほど経路の非対称による誤差が小さい）
+//! - `Discipline`: 選んだオフセットから、ステップかスルーか、
+//!   および TSC 周波数の補正量 (FLL) を決める
+//!
+//! フィルタ内のオフセットは「保留中のスルーを吸収し終えた時計」に対する値で持ち、
+//! 補正を適用するたびに適用分を差し引く。これにより同じずれを二重に補正しない。
+
+use alloc::vec::Vec;
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// フィルタに保持するサンプル数
+pub const FILTER_SIZE: usize = 8;
+
+/// これを超えるずれはステップで補正する (ns)
+pub const STEP_THRESHOLD_NS: i64 = 128_000_000;
+
+/// 周波数推定に使う最短間隔 (ns)
+pub const MIN_FLL_INTERVAL_NS: u64 = 8_000_000_000;
+
+/// 周波数推定の減衰（推定値の 1/N だけ補正する）
+pub const FLL_GAIN_DIVISOR: i64 = 4;
+
+/// 周波数補正の上限 (ppb)
+pub const MAX_FREQUENCY_PPB: i64 = 500_000;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+// ============================================================================
+// フィルタ
+// ============================================================================
+
+/// 1回の測定結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// 測定時の稼働時間 (ns)
+    pub uptime: u64,
+    /// オフセット (ns, 正ならローカル時計が遅れている)
+    pub offset: i64,
+    /// 往復遅延 (ns)
+    pub delay: i64,
+}
+
+/// クロックフィルタ
+#[derive(Debug, Clone, Default)]
+pub struct ClockFilter {
+    samples: Vec<Sample>,
+}
+
+impl ClockFilter {
+    /// 空のフィルタを作成
+    pub const fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+        }
+    }
+
+    /// サンプルを追加（古いものから捨てる）
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() == FILTER_SIZE {
+            self.samples.remove(0);
+        }
+        self.samples.push(sample);
+    }
+
+    /// 往復遅延が最小のサンプル（同じなら新しい方）
+    pub fn best(&self) -> Option<Sample> {
+        self.samples.iter().copied().reduce(|best, sample| {
+            if sample.delay <= best.delay {
+                sample
+            } else {
+                best
+            }
+        })
+    }
+
+    /// 最良サンプルに対するオフセットの RMS (ns)
+    pub fn jitter(&self) -> i64 {
+        let Some(best) = self.best() else {
+            return 0;
+        };
+        if self.samples.len() < 2 {
+            return 0;
+        }
+        let sum: i128 = self
+            .samples
+            .iter()
+            .map(|sample| {
+                let diff = (sample.offset - best.offset) as i128;
+                diff * diff
+            })
+            .sum();
+        ((sum / (self.samples.len() as i128 - 1)) as u128).isqrt() as i64
+    }
+
+    /// 時計を `applied` ns 補正したので、保持しているオフセットから差し引く
+    pub fn adjust(&mut self, applied: i64) {
+        for sample in &mut self.samples {
+            sample.offset -= applied;
+        }
+    }
+
+    /// サンプル数
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 空か
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// すべて捨てる
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+// ============================================================================
+// 規律
+// ============================================================================
+
+/// 時計への補正
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjustment {
+    /// 時刻を飛ばす (ns)
+    Step(i64),
+    /// 徐々に吸収する (ns)
+    Slew(i64),
+}
+
+/// 時計の規律（位相と周波数）
+#[derive(Debug, Clone, Default)]
+pub struct Discipline {
+    /// 周波数補正 (ppb)
+    frequency_ppb: i64,
+    /// 最後に補正した稼働時間
+    last_update: Option<u64>,
+    /// ステップした回数
+    steps: u32,
+}
+
+impl Discipline {
+    /// 初期状態を作成
+    pub const fn new() -> Self {
+        Self {
+            frequency_ppb: 0,
+            last_update: None,
+            steps: 0,
+        }
+    }
+
+    /// 周波数補正 (ppb)
+    pub fn frequency_ppb(&self) -> i64 {
+        self.frequency_ppb
+    }
+
+    /// ステップした回数
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// 最後に補正した稼働時間
+    pub fn last_update(&self) -> Option<u64> {
+        self.last_update
+    }
+
+    /// サンプルから補正を決める
+    ///
+    /// `pending` は時計がまだ吸収していないスルー量。`sample.offset` は
+    /// フィルタの規約どおりそれを除いた値とする。使用済みのサンプル
+    /// （前回の補正より古い）は無視して `None` を返す。
+    pub fn update(&mut self, sample: Sample, pending: i64) -> Option<Adjustment> {
+        if self.last_update.is_some_and(|last| sample.uptime <= last) {
+            return None;
+        }
+
+        // 前回の補正以降に生じたずれから周波数誤差を推定する
+        if let Some(last) = self.last_update {
+            let interval = sample.uptime - last;
+            if interval >= MIN_FLL_INTERVAL_NS && sample.offset.abs() <= STEP_THRESHOLD_NS {
+                let estimate = sample.offset as i128 * NANOS_PER_SEC / interval as i128;
+                self.frequency_ppb = (self.frequency_ppb + estimate as i64 / FLL_GAIN_DIVISOR)
+                    .clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+            }
+        }
+        self.last_update = Some(sample.uptime);
+
+        let total = sample.offset + pending;
+        if total.abs() > STEP_THRESHOLD_NS {
+            self.steps += 1;
+            Some(Adjustment::Step(total))
+        } else {
+            Some(Adjustment::Slew(total))
+        }
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Timescale;
+
+    const SEC: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_filter() {
+        let mut filter = ClockFilter::new();
+        assert_eq!(filter.best(), None);
+        for (i, delay) in [30, 10, 20, 10, 40].iter().enumerate() {
+            filter.push(Sample {
+                uptime: i as u64,
+                offset: i as i64 * 2,
+                delay: *delay,
+            });
+        }
+        // 遅延最小で新しい方
+        assert_eq!(filter.best().map(|s| s.uptime), Some(3));
+        // オフセット 0,2,4,6,8 と 6 の差の二乗和 = 36+16+4+0+4 = 60, /4 → 15
+        assert_eq!(filter.jitter(), 3);
+
+        filter.adjust(6);
+        assert_eq!(filter.best().map(|s| s.offset), Some(0));
+
+        for i in 0..FILTER_SIZE as u64 {
+            filter.push(Sample {
+                uptime: 10 + i,
+                offset: 0,
+                delay: 50,
+            });
+        }
+        assert_eq!(filter.len(), FILTER_SIZE);
+        assert_eq!(filter.best().map(|s| s.uptime), Some(17));
+    }
+
+    #[test]
+    fn test_discipline_tracks_drift() {
+        // ローカルの TSC が 100ppm 速い
+        let true_time = |uptime: u64| 1_000_000 * SEC + uptime - uptime / 10_000;
+
+        let mut clock = Timescale::new();
+        // 起動時は 1秒ずれている
+        clock.set(0, true_time(0) - SEC);
+
+        let mut filter = ClockFilter::new();
+        let mut discipline = Discipline::new();
+        let mut uptime = 0;
+        for _ in 0..48 {
+            uptime += 64 * SEC;
+            let pending = clock.slew_remaining(uptime);
+            let offset = true_time(uptime) as i64 - clock.wall_nanos(uptime) as i64;
+            filter.push(Sample {
+                uptime,
+                offset: offset - pending,
+                delay: 1_000_000,
+            });
+            let best = filter.best().unwrap();
+            match discipline.update(best, pending).unwrap() {
+                Adjustment::Step(amount) => clock.step(uptime, amount),
+                Adjustment::Slew(amount) => clock.slew(uptime, amount),
+            }
+            filter.adjust(best.offset);
+            clock.set_frequency(uptime, discipline.frequency_ppb());
+        }
+
+        assert_eq!(discipline.steps(), 1);
+        assert!((discipline.frequency_ppb() + 100_000).abs() < 2_000);
+        let residual =
+            true_time(uptime + 64 * SEC) as i64 - clock.wall_nanos(uptime + 64 * SEC) as i64;
+        assert!(residual.abs() < 1_000_000, "residual {}", residual);
+
+        // 同じサンプルは二度使わない
+        let best = filter.best().unwrap();
+        assert_eq!(discipline.update(best, 0), None);
+    }
+}
@@ -0,0 +1,292 @@
+//! NTP パケット (RFC 5905)
+//!
+//! 48バイトのヘッダのみを扱う（拡張フィールドと認証は使わない）。
+//! 時刻はすべて Unix 時刻のナノ秒で受け渡し、NTP の 32.32 固定小数点との
+//! 変換はこのモジュールに閉じる。
+
+use super::super::ipv4::Ipv4Address;
+
+// ============================================================================
+// 定数
+// ============================================================================
+
+/// パケット長
+pub const PACKET_SIZE: usize = 48;
+
+/// NTP バージョン
+pub const VERSION: u8 = 4;
+
+/// モード: クライアント
+pub const MODE_CLIENT: u8 = 3;
+
+/// モード: サーバー
+pub const MODE_SERVER: u8 = 4;
+
+/// うるう秒指示子: 非同期
+pub const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+/// NTP 紀元 (1900年) から Unix 紀元 (1970年) までの秒数
+pub const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// ============================================================================
+// タイムスタンプ
+// ============================================================================
+
+/// NTP タイムスタンプ（上位32ビットが秒、下位32ビットが秒の小数部）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    /// Unix 時刻 (ns) から変換
+    pub fn from_unix_nanos(nanos: u64) -> Self {
+        let secs = nanos / NANOS_PER_SEC + NTP_UNIX_OFFSET;
+        let fraction = ((nanos % NANOS_PER_SEC) << 32) / NANOS_PER_SEC;
+        Self((secs << 32) | fraction)
+    }
+
+    /// Unix 時刻 (ns) へ変換（Unix 紀元より前は 0）
+    pub fn to_unix_nanos(self) -> u64 {
+        let secs = (self.0 >> 32).saturating_sub(NTP_UNIX_OFFSET);
+        let fraction = ((self.0 & 0xFFFF_FFFF) * NANOS_PER_SEC) >> 32;
+        secs * NANOS_PER_SEC + fraction
+    }
+
+    /// 未設定か
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+}
+
+// ============================================================================
+// パケット
+// ============================================================================
+
+/// 応答の検証エラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketError {
+    /// パケットが短い
+    TooShort,
+    /// サーバー応答ではない
+    NotServer,
+    /// サーバーが同期していない
+    Unsynchronized,
+    /// Kiss-o'-Death（`RATE`、`DENY`、`RSTR` など）
+    KissOfDeath([u8; 4]),
+    /// 自分の送信時刻と応答のオリジン時刻が一致しない
+    BogusOrigin,
+    /// 受信・送信時刻が設定されていない
+    MissingTimestamp,
+}
+
+impl PacketError {
+    /// このサーバーへの問い合わせをやめるべきか（`DENY` / `RSTR`）
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, PacketError::KissOfDeath(code) if code == b"DENY" || code == b"RSTR")
+    }
+}
+
+/// NTP パケット
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Packet {
+    /// うるう秒指示子
+    pub leap: u8,
+    /// バージョン
+    pub version: u8,
+    /// モード
+    pub mode: u8,
+    /// 階層 (0 は Kiss-o'-Death)
+    pub stratum: u8,
+    /// ポーリング間隔 (log2 秒)
+    pub poll: i8,
+    /// 精度 (log2 秒)
+    pub precision: i8,
+    /// ルート遅延 (16.16 秒)
+    pub root_delay: u32,
+    /// ルート分散 (16.16 秒)
+    pub root_dispersion: u32,
+    /// 参照ID（stratum 1 は参照源の名前、それ以上は上位サーバーのアドレス）
+    pub reference_id: [u8; 4],
+    /// 参照時刻
+    pub reference: NtpTimestamp,
+    /// オリジン時刻（クライアントの送信時刻の写し）
+    pub origin: NtpTimestamp,
+    /// サーバー受信時刻
+    pub receive: NtpTimestamp,
+    /// サーバー送信時刻
+    pub transmit: NtpTimestamp,
+}
+
+impl Packet {
+    /// クライアント要求を作成
+    ///
+    /// 送信時刻はサーバーがオリジン時刻として返すので、応答の照合に使う。
+    pub fn client_request(transmit: NtpTimestamp, poll: i8) -> Self {
+        Self {
+            version: VERSION,
+            mode: MODE_CLIENT,
+            poll,
+            transmit,
+            ..Self::default()
+        }
+    }
+
+    /// バイト列へエンコード
+    pub fn encode(&self) -> [u8; PACKET_SIZE] {
+        let mut buffer = [0u8; PACKET_SIZE];
+        buffer[0] = (self.leap << 6) | ((self.version & 0x07) << 3) | (self.mode & 0x07);
+        buffer[1] = self.stratum;
+        buffer[2] = self.poll as u8;
+        buffer[3] = self.precision as u8;
+        buffer[4..8].copy_from_slice(&self.root_delay.to_be_bytes());
+        buffer[8..12].copy_from_slice(&self.root_dispersion.to_be_bytes());
+        buffer[12..16].copy_from_slice(&self.reference_id);
+        buffer[16..24].copy_from_slice(&self.reference.0.to_be_bytes());
+        buffer[24..32].copy_from_slice(&self.origin.0.to_be_bytes());
+        buffer[32..40].copy_from_slice(&self.receive.0.to_be_bytes());
+        buffer[40..48].copy_from_slice(&self.transmit.0.to_be_bytes());
+        buffer
+    }
+
+    /// バイト列からデコード（拡張フィールドは無視）
+    pub fn decode(data: &[u8]) -> Result<Self, PacketError> {
+        if data.len() < PACKET_SIZE {
+            return Err(PacketError::TooShort);
+        }
+        let u32_at =
+            |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let ts_at = |i: usize| NtpTimestamp(((u32_at(i) as u64) << 32) | u32_at(i + 4) as u64);
+        Ok(Self {
+            leap: data[0] >> 6,
+            version: (data[0] >> 3) & 0x07,
+            mode: data[0] & 0x07,
+            stratum: data[1],
+            poll: data[2] as i8,
+            precision: data[3] as i8,
+            root_delay: u32_at(4),
+            root_dispersion: u32_at(8),
+            reference_id: [data[12], data[13], data[14], data[15]],
+            reference: ts_at(16),
+            origin: ts_at(24),
+            receive: ts_at(32),
+            transmit: ts_at(40),
+        })
+    }
+
+    /// `sent` を送信時刻とした要求への応答として検証
+    pub fn validate_response(&self, sent: NtpTimestamp) -> Result<(), PacketError> {
+        if self.mode != MODE_SERVER {
+            return Err(PacketError::NotServer);
+        }
+        if self.stratum == 0 {
+            return Err(PacketError::KissOfDeath(self.reference_id));
+        }
+        if self.origin != sent {
+            return Err(PacketError::BogusOrigin);
+        }
+        if self.leap == LEAP_UNSYNCHRONIZED || self.stratum > 15 {
+            return Err(PacketError::Unsynchronized);
+        }
+        if self.receive.is_zero() || self.transmit.is_zero() {
+            return Err(PacketError::MissingTimestamp);
+        }
+        Ok(())
+    }
+
+    /// 参照ID を表示用に整形
+    pub fn reference_name(&self) -> alloc::string::String {
+        if self.stratum <= 1 {
+            self.reference_id
+                .iter()
+                .take_while(|&&b| b != 0)
+                .map(|&b| if b.is_ascii_graphic() { b as char } else { '?' })
+                .collect()
+        } else {
+            alloc::format!("{}", Ipv4Address::new(self.reference_id))
+        }
+    }
+}
+
+/// 4つの時刻からオフセットと往復遅延を求める (ns)
+///
+/// - `t1`: クライアント送信、`t2`: サーバー受信
+/// - `t3`: サーバー送信、`t4`: クライアント受信
+///
+/// オフセットが正ならローカル時計が遅れている。
+pub fn offset_and_delay(t1: u64, t2: u64, t3: u64, t4: u64) -> (i64, i64) {
+    let (t1, t2, t3, t4) = (t1 as i128, t2 as i128, t3 as i128, t4 as i128);
+    let offset = ((t2 - t1) + (t3 - t4)) / 2;
+    let delay = ((t4 - t1) - (t3 - t2)).max(0);
+    (offset as i64, delay as i64)
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_and_encoding() {
+        // 2024-01-01T00:00:00.5Z
+        let nanos = 1_704_067_200 * NANOS_PER_SEC + 500_000_000;
+        let ts = NtpTimestamp::from_unix_nanos(nanos);
+        assert_eq!(ts.0 >> 32, 1_704_067_200 + NTP_UNIX_OFFSET);
+        assert_eq!(ts.0 & 0xFFFF_FFFF, 0x8000_0000);
+        assert_eq!(ts.to_unix_nanos(), nanos);
+
+        let request = Packet::client_request(ts, 6);
+        let bytes = request.encode();
+        assert_eq!(bytes[0], 0x23); // LI=0, VN=4, Mode=3
+        assert_eq!(bytes[2], 6);
+        assert_eq!(Packet::decode(&bytes), Ok(request));
+        assert_eq!(Packet::decode(&bytes[..47]), Err(PacketError::TooShort));
+    }
+
+    #[test]
+    fn test_validate_and_measure() {
+        let sent = NtpTimestamp::from_unix_nanos(1_000 * NANOS_PER_SEC);
+        let mut response = Packet {
+            version: VERSION,
+            mode: MODE_SERVER,
+            stratum: 2,
+            origin: sent,
+            receive: NtpTimestamp::from_unix_nanos(1_001 * NANOS_PER_SEC),
+            transmit: NtpTimestamp::from_unix_nanos(1_001 * NANOS_PER_SEC + 1_000_000),
+            reference_id: [192, 0, 2, 1],
+            ..Packet::default()
+        };
+        assert_eq!(response.validate_response(sent), Ok(()));
+        assert_eq!(response.reference_name(), "192.0.2.1");
+        assert_eq!(
+            response.validate_response(NtpTimestamp(sent.0 + 1)),
+            Err(PacketError::BogusOrigin)
+        );
+
+        response.leap = LEAP_UNSYNCHRONIZED;
+        assert_eq!(
+            response.validate_response(sent),
+            Err(PacketError::Unsynchronized)
+        );
+
+        response.stratum = 0;
+        response.reference_id = *b"DENY";
+        let error = response.validate_response(sent).unwrap_err();
+        assert_eq!(error, PacketError::KissOfDeath(*b"DENY"));
+        assert!(error.is_fatal());
+        assert!(!PacketError::KissOfDeath(*b"RATE").is_fatal());
+
+        // ローカルが 1秒遅れ、片道 10ms、サーバー処理 1ms
+        let t1 = 1_000 * NANOS_PER_SEC;
+        let t2 = t1 + NANOS_PER_SEC + 10_000_000;
+        let t3 = t2 + 1_000_000;
+        let t4 = t1 + 21_000_000;
+        assert_eq!(
+            offset_and_delay(t1, t2, t3, t4),
+            (1_000_000_000, 20_000_000)
+        );
+    }
+}
@@ -142,7 +142,7 @@ impl BatchStats {
         }
     }
 
-    fn record_batch(&self, size: usize) {
+    pub fn record_batch(&self, size: usize) {
         let total = self.batches_processed.fetch_add(1, Ordering::Relaxed);
         let packets = self
             .packets_processed
@@ -0,0 +1,476 @@
+//! IPv4 Routing Table for ExoRust
+//!
+//! 最長プレフィックス一致 (LPM) による経路選択、メトリック、
+//! デフォルトゲートウェイ、送信元アドレス選択を提供する。
+
+#![allow(dead_code)]
+
+use super::interface::{
+    InterfaceAddress, InterfaceId, InterfaceKind, InterfaceTable, LOOPBACK_ID, prefix_to_mask,
+};
+use super::ipv4::Ipv4Address;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+extern crate alloc;
+
+/// Default metric for connected (on-link) routes
+pub const CONNECTED_METRIC: u32 = 0;
+
+/// Default metric for the default route
+pub const DEFAULT_ROUTE_METRIC: u32 = 100;
+
+/// How a route was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteOrigin {
+    /// Installed automatically for an interface address
+    Connected,
+    /// Installed by configuration (shell, DHCP)
+    Static,
+}
+
+impl RouteOrigin {
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouteOrigin::Connected => "connected",
+            RouteOrigin::Static => "static",
+        }
+    }
+}
+
+/// Routing table entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// Destination network (masked)
+    pub destination: Ipv4Address,
+    /// Prefix length
+    pub prefix_len: u8,
+    /// Next-hop gateway (None = on-link)
+    pub gateway: Option<Ipv4Address>,
+    /// Outgoing interface
+    pub interface: InterfaceId,
+    /// Metric (lower is preferred)
+    pub metric: u32,
+    /// Preferred source address
+    pub source: Option<Ipv4Address>,
+    /// Origin
+    pub origin: RouteOrigin,
+}
+
+impl Route {
+    /// Create an on-link route
+    pub fn connected(addr: InterfaceAddress, interface: InterfaceId) -> Self {
+        Route {
+            destination: addr.network(),
+            prefix_len: addr.prefix_len,
+            gateway: None,
+            interface,
+            metric: CONNECTED_METRIC,
+            source: Some(addr.address),
+            origin: RouteOrigin::Connected,
+        }
+    }
+
+    /// Create a static route
+    pub fn new(
+        destination: Ipv4Address,
+        prefix_len: u8,
+        gateway: Option<Ipv4Address>,
+        interface: InterfaceId,
+        metric: u32,
+    ) -> Self {
+        Route {
+            destination: destination.apply_mask(prefix_to_mask(prefix_len)),
+            prefix_len,
+            gateway,
+            interface,
+            metric,
+            source: None,
+            origin: RouteOrigin::Static,
+        }
+    }
+
+    /// Create a default route via a gateway
+    pub fn default_via(gateway: Ipv4Address, interface: InterfaceId, metric: u32) -> Self {
+        Self::new(Ipv4Address::ANY, 0, Some(gateway), interface, metric)
+    }
+
+    /// Check if this is a default route
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.prefix_len == 0
+    }
+
+    /// Check if a destination matches this route
+    #[inline]
+    pub fn matches(&self, dst: &Ipv4Address) -> bool {
+        self.destination
+            .same_subnet(dst, prefix_to_mask(self.prefix_len))
+    }
+}
+
+/// Routing errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteError {
+    /// Identical route already present
+    AlreadyExists,
+    /// No matching route to remove
+    NotFound,
+    /// Prefix length out of range
+    InvalidPrefix,
+    /// No route to destination
+    Unreachable,
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::AlreadyExists => write!(f, "Route already exists"),
+            RouteError::NotFound => write!(f, "No such route"),
+            RouteError::InvalidPrefix => write!(f, "Invalid prefix length"),
+            RouteError::Unreachable => write!(f, "Network unreachable"),
+        }
+    }
+}
+
+/// Result of resolving a destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteLookup {
+    /// Outgoing interface
+    pub interface: InterfaceId,
+    /// Outgoing interface type
+    pub kind: InterfaceKind,
+    /// Outgoing interface MTU
+    pub mtu: usize,
+    /// Next hop (gateway or destination)
+    pub next_hop: Ipv4Address,
+    /// Selected source address
+    pub source: Ipv4Address,
+}
+
+/// IPv4 routing table
+#[derive(Debug, Default)]
+pub struct RoutingTable {
+    /// Routes
+    routes: Vec<Route>,
+}
+
+impl RoutingTable {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        RoutingTable { routes: Vec::new() }
+    }
+
+    /// Add a route
+    pub fn add(&mut self, route: Route) -> Result<(), RouteError> {
+        if route.prefix_len > 32 {
+            return Err(RouteError::InvalidPrefix);
+        }
+        let duplicate = self.routes.iter().any(|r| {
+            r.destination == route.destination
+                && r.prefix_len == route.prefix_len
+                && r.interface == route.interface
+                && r.metric == route.metric
+        });
+        if duplicate {
+            return Err(RouteError::AlreadyExists);
+        }
+        self.routes.push(route);
+        Ok(())
+    }
+
+    /// Remove routes to a destination (optionally restricted to an interface)
+    pub fn remove(
+        &mut self,
+        destination: Ipv4Address,
+        prefix_len: u8,
+        interface: Option<InterfaceId>,
+    ) -> Result<usize, RouteError> {
+        let destination = destination.apply_mask(prefix_to_mask(prefix_len));
+        let before = self.routes.len();
+        self.routes.retain(|r| {
+            !(r.destination == destination
+                && r.prefix_len == prefix_len
+                && interface.is_none_or(|id| r.interface == id))
+        });
+        match before - self.routes.len() {
+            0 => Err(RouteError::NotFound),
+            removed => Ok(removed),
+        }
+    }
+
+    /// Remove the connected route installed for an interface address
+    pub fn remove_connected(&mut self, addr: InterfaceAddress, interface: InterfaceId) {
+        self.routes.retain(|r| {
+            !(r.origin == RouteOrigin::Connected
+                && r.interface == interface
+                && r.source == Some(addr.address))
+        });
+    }
+
+    /// Remove all routes through an interface
+    pub fn remove_interface(&mut self, interface: InterfaceId) {
+        self.routes.retain(|r| r.interface != interface);
+    }
+
+    /// Replace the static default route(s) with a single gateway
+    pub fn set_default_gateway(&mut self, gateway: Ipv4Address, interface: InterfaceId, metric: u32) {
+        self.routes
+            .retain(|r| !(r.is_default() && r.origin == RouteOrigin::Static));
+        self.routes
+            .push(Route::default_via(gateway, interface, metric));
+    }
+
+    /// Default gateway with the lowest metric
+    pub fn default_gateway(&self) -> Option<Ipv4Address> {
+        self.routes
+            .iter()
+            .filter(|r| r.is_default())
+            .min_by_key(|r| r.metric)
+            .and_then(|r| r.gateway)
+    }
+
+    /// All routes, most specific first
+    pub fn routes(&self) -> Vec<Route> {
+        let mut routes = self.routes.clone();
+        routes.sort_by(|a, b| {
+            b.prefix_len
+                .cmp(&a.prefix_len)
+                .then(a.metric.cmp(&b.metric))
+        });
+        routes
+    }
+
+    /// Longest-prefix match (ties broken by metric)
+    pub fn lookup(&self, dst: &Ipv4Address) -> Option<&Route> {
+        self.lookup_where(dst, |_| true)
+    }
+
+    /// Longest-prefix match restricted by a predicate (e.g. interface is up)
+    pub fn lookup_where(
+        &self,
+        dst: &Ipv4Address,
+        usable: impl Fn(&Route) -> bool,
+    ) -> Option<&Route> {
+        self.routes
+            .iter()
+            .filter(|r| r.matches(dst) && usable(r))
+            .min_by(|a, b| {
+                b.prefix_len
+                    .cmp(&a.prefix_len)
+                    .then(a.metric.cmp(&b.metric))
+            })
+    }
+
+    /// Resolve a destination to an outgoing interface, next hop and source
+    ///
+    /// - ローカルアドレス宛てはループバックへ
+    /// - 制限ブロードキャストはプライマリNICへ（DHCP等、経路未設定でも送信可能）
+    /// - それ以外はLPMで選択した経路
+    pub fn resolve(&self, interfaces: &InterfaceTable, dst: Ipv4Address) -> Result<RouteLookup, RouteError> {
+        // 1. 自ホスト宛て
+        if interfaces.is_local(&dst) {
+            let lo = interfaces.get(LOOPBACK_ID).ok_or(RouteError::Unreachable)?;
+            return Ok(RouteLookup {
+                interface: lo.id,
+                kind: lo.kind,
+                mtu: lo.mtu,
+                next_hop: dst,
+                source: dst,
+            });
+        }
+
+        // 2. 制限ブロードキャスト・マルチキャスト（最初の稼働中NICから送出）
+        //    ブリッジのポートはブリッジ経由で送るので除く
+        if dst.is_broadcast() || dst.is_multicast() {
+            let nic = interfaces
+                .iter()
+                .find(|i| i.up && i.kind.has_link_layer() && i.master.is_none())
+                .ok_or(RouteError::Unreachable)?;
+            return Ok(RouteLookup {
+                interface: nic.id,
+                kind: nic.kind,
+                mtu: nic.mtu,
+                next_hop: dst,
+                source: nic
+                    .primary_address()
+                    .map_or(Ipv4Address::ANY, |a| a.address),
+            });
+        }
+
+        // 3. 最長プレフィックス一致
+        let route = self
+            .lookup_where(&dst, |r| {
+                interfaces.get(r.interface).is_some_and(|i| i.up)
+            })
+            .ok_or(RouteError::Unreachable)?;
+        let interface = interfaces
+            .get(route.interface)
+            .ok_or(RouteError::Unreachable)?;
+        let next_hop = route.gateway.unwrap_or(dst);
+
+        Ok(RouteLookup {
+            interface: interface.id,
+            kind: interface.kind,
+            mtu: interface.mtu,
+            next_hop,
+            source: select_source(interfaces, route, dst, next_hop).unwrap_or(Ipv4Address::ANY),
+        })
+    }
+}
+
+/// Source address selection
+///
+/// 優先順位:
+/// 1. 経路に指定された送信元（出力インターフェースに割り当て済みの場合）
+/// 2. 宛先と同一サブネットの出力インターフェースのアドレス
+/// 3. ネクストホップと同一サブネットの出力インターフェースのアドレス
+/// 4. 出力インターフェースのプライマリアドレス
+/// 5. 他のアップ状態の非ループバックインターフェースのプライマリアドレス
+pub fn select_source(
+    interfaces: &InterfaceTable,
+    route: &Route,
+    dst: Ipv4Address,
+    next_hop: Ipv4Address,
+) -> Option<Ipv4Address> {
+    let interface = interfaces.get(route.interface)?;
+
+    if let Some(preferred) = route.source
+        && interface.has_address(&preferred)
+    {
+        return Some(preferred);
+    }
+
+    let addresses = interface.addresses();
+    addresses
+        .iter()
+        .find(|a| a.contains(&dst))
+        .or_else(|| addresses.iter().find(|a| a.contains(&next_hop)))
+        .or_else(|| addresses.first())
+        .map(|a| a.address)
+        .or_else(|| {
+            interfaces
+                .iter()
+                .filter(|i| i.up && !i.is_loopback())
+                .find_map(|i| i.primary_address())
+                .map(|a| a.address)
+        })
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ethernet::MacAddress;
+
+    fn ip(a: u8, b: u8, c: u8, d: u8) -> Ipv4Address {
+        Ipv4Address::from_octets(a, b, c, d)
+    }
+
+    /// lo + eth0 (10.0.2.15/24, 192.168.7.1/24) + eth1 (172.16.0.5/16)
+    fn setup() -> (InterfaceTable, RoutingTable, InterfaceId, InterfaceId) {
+        let mut interfaces = InterfaceTable::new();
+        let mut routes = RoutingTable::new();
+        routes
+            .add(Route::connected(super::super::interface::LOOPBACK_ADDRESS, LOOPBACK_ID))
+            .unwrap();
+
+        let eth0 = interfaces
+            .add("eth0", InterfaceKind::Ethernet, MacAddress::ZERO, 1500)
+            .unwrap();
+        let eth1 = interfaces
+            .add("eth1", InterfaceKind::Ethernet, MacAddress::ZERO, 1500)
+            .unwrap();
+        for (id, addr, prefix) in [
+            (eth0, ip(10, 0, 2, 15), 24),
+            (eth0, ip(192, 168, 7, 1), 24),
+            (eth1, ip(172, 16, 0, 5), 16),
+        ] {
+            let iface_addr = InterfaceAddress::new(addr, prefix);
+            let iface = interfaces.get_mut(id).unwrap();
+            iface.up = true;
+            iface.add_address(iface_addr).unwrap();
+            routes.add(Route::connected(iface_addr, id)).unwrap();
+        }
+        routes.set_default_gateway(ip(10, 0, 2, 2), eth0, DEFAULT_ROUTE_METRIC);
+        (interfaces, routes, eth0, eth1)
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let (interfaces, mut routes, eth0, eth1) = setup();
+        // 172.16.9.0/24 via 172.16.0.1 は 172.16.0.0/16 より優先
+        routes
+            .add(Route::new(ip(172, 16, 9, 0), 24, Some(ip(172, 16, 0, 1)), eth1, 10))
+            .unwrap();
+
+        let r = routes.resolve(&interfaces, ip(172, 16, 9, 7)).unwrap();
+        assert_eq!(r.interface, eth1);
+        assert_eq!(r.next_hop, ip(172, 16, 0, 1));
+
+        let r = routes.resolve(&interfaces, ip(172, 16, 8, 7)).unwrap();
+        assert_eq!(r.next_hop, ip(172, 16, 8, 7));
+
+        // デフォルト経路
+        let r = routes.resolve(&interfaces, ip(8, 8, 8, 8)).unwrap();
+        assert_eq!(r.interface, eth0);
+        assert_eq!(r.next_hop, ip(10, 0, 2, 2));
+        assert_eq!(routes.default_gateway(), Some(ip(10, 0, 2, 2)));
+    }
+
+    #[test]
+    fn test_metric_breaks_ties() {
+        let (interfaces, mut routes, eth0, eth1) = setup();
+        routes
+            .add(Route::default_via(ip(172, 16, 0, 1), eth1, 50))
+            .unwrap();
+        let r = routes.resolve(&interfaces, ip(1, 1, 1, 1)).unwrap();
+        assert_eq!(r.interface, eth1);
+        assert_eq!(r.source, ip(172, 16, 0, 5));
+
+        // メトリックの低い経路のインターフェースがダウンすると次点へ
+        let mut interfaces = interfaces;
+        interfaces.get_mut(eth1).unwrap().up = false;
+        let r = routes.resolve(&interfaces, ip(1, 1, 1, 1)).unwrap();
+        assert_eq!(r.interface, eth0);
+    }
+
+    #[test]
+    fn test_source_selection() {
+        let (interfaces, routes, eth0, _) = setup();
+        // 宛先と同一サブネットのセカンダリアドレスを選ぶ
+        let r = routes.resolve(&interfaces, ip(192, 168, 7, 20)).unwrap();
+        assert_eq!(r.interface, eth0);
+        assert_eq!(r.source, ip(192, 168, 7, 1));
+        // ゲートウェイ経由はネクストホップのサブネットのアドレス
+        let r = routes.resolve(&interfaces, ip(93, 184, 216, 34)).unwrap();
+        assert_eq!(r.source, ip(10, 0, 2, 15));
+    }
+
+    #[test]
+    fn test_local_destination_uses_loopback() {
+        let (interfaces, routes, _, _) = setup();
+        for dst in [ip(127, 0, 0, 1), ip(10, 0, 2, 15), ip(192, 168, 7, 1)] {
+            let r = routes.resolve(&interfaces, dst).unwrap();
+            assert_eq!(r.interface, LOOPBACK_ID);
+            assert_eq!(r.kind, InterfaceKind::Loopback);
+            assert_eq!(r.source, dst);
+        }
+    }
+
+    #[test]
+    fn test_remove_route() {
+        let (interfaces, mut routes, _, _) = setup();
+        assert_eq!(routes.remove(ip(0, 0, 0, 0), 0, None), Ok(1));
+        assert_eq!(
+            routes.resolve(&interfaces, ip(8, 8, 8, 8)),
+            Err(RouteError::Unreachable)
+        );
+        assert_eq!(routes.remove(ip(0, 0, 0, 0), 0, None), Err(RouteError::NotFound));
+    }
+}
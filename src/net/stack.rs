@@ -4,20 +4,38 @@
 //! a unified zero-copy network stack as specified in Section 6.2.
 
 use super::arp::{ArpProcessor, ArpResult};
+use super::bridge::{Bridge, BridgeError};
 use super::ethernet::{
-    EtherType, EthernetFrameMut, EthernetProcessor, MacAddress, ProcessResult,
+    EtherType, EthernetFrame, EthernetFrameMut, EthernetHeader, EthernetProcessor, MacAddress,
+    ProcessResult, VLAN_ID_MAX, VlanTag, insert_vlan_tag, strip_vlan_tag,
+};
+use super::firewall::{Action, Chain, Firewall, PacketMeta};
+use super::forward::{self, ForwardStats};
+use super::icmp::{
+    DestUnreachCode, IcmpEchoBuilder, IcmpEchoHeader, IcmpProcessor, IcmpResult, TimeExceededCode,
+};
+use super::igmp::{IGMP_MESSAGE_SIZE, IGMP_TTL, IgmpMessage};
+use super::interface::{
+    InterfaceAddress, InterfaceError, InterfaceId, InterfaceKind, InterfaceTable, LOOPBACK_ADDRESS,
+    LOOPBACK_ID, Offloads, VlanLink, mask_to_prefix,
 };
-use super::icmp::{IcmpEchoBuilder, IcmpProcessor, IcmpResult};
 use super::ipv4::{
-    IpProtocol, Ipv4Address, Ipv4Config, Ipv4PacketMut, Ipv4ProcessResult,
-    Ipv4Processor,
+    IpProtocol, Ipv4Address, Ipv4Config, Ipv4Header, Ipv4Packet, Ipv4PacketMut,
+    Ipv4ProcessResult, Ipv4Processor, data_checksum,
 };
+use super::loopback::LoopbackDevice;
+use super::mdns::{MDNS_GROUP, MDNS_IP_TTL, MDNS_PORT};
 use super::mempool::PacketPool;
+use super::nat::{Nat, NatFlow};
+use super::route::{DEFAULT_ROUTE_METRIC, Route, RouteError, RouteLookup, RoutingTable};
 use super::tcp::TcpProcessor;
-use super::udp::{UdpProcessor, UdpResult, UdpSocket};
+use super::udp::{UdpAddr, UdpHeader, UdpPacket, UdpProcessor, UdpResult, UdpSocket};
+use super::wireguard::crypto::Key as WgKey;
+use super::wireguard::{Datagram, WIREGUARD_MTU, WgError, WireGuard};
 
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::vec::Vec;
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use spin::Mutex;
 
 extern crate alloc;
@@ -28,6 +46,15 @@ pub const MAX_PACKET_SIZE: usize = 1518;
 /// Ethernet MTU
 pub const MTU: usize = 1500;
 
+/// Name of the primary NIC interface
+pub const PRIMARY_INTERFACE: &str = "eth0";
+
+/// Maximum TCP segments awaiting delivery to the endpoint layer
+const PENDING_TCP_LEN: usize = 256;
+
+/// Default IP TTL for locally generated packets
+const DEFAULT_TTL: u8 = 64;
+
 /// Network interface configuration
 ///
 /// Note: 全フィールドが Copy 型のため、Copy を実装。
@@ -103,7 +130,21 @@ impl NetworkStats {
 /// Transmit callback function type
 pub type TransmitFn = fn(&[u8]) -> bool;
 
+/// TCP segment received by the stack, delivered to the endpoint layer
+/// after the stack lock is released
+#[derive(Debug, Clone)]
+pub struct PendingTcpSegment {
+    /// Source address
+    pub src: Ipv4Address,
+    /// Destination address
+    pub dst: Ipv4Address,
+    /// TCP segment (header + data)
+    pub segment: Vec<u8>,
+}
+
 /// Integrated network stack
+///
+/// ロック順序: config → (ethernet | ipv4 | arp) → interfaces → routes → firewall → nat → wireguard → bridges → loopback
 pub struct NetworkStack {
     /// Configuration
     config: Mutex<NetworkConfig>,
@@ -123,8 +164,28 @@ pub struct NetworkStack {
     tx_pool: PacketPool,
     /// Statistics
     stats: NetworkStats,
-    /// Transmit callback
-    transmit_fn: Mutex<Option<TransmitFn>>,
+    /// Network interfaces (lo + NICs)
+    interfaces: Mutex<InterfaceTable>,
+    /// IPv4 routing table
+    routes: Mutex<RoutingTable>,
+    /// Primary NIC interface (driven by `receive` / `set_transmit_fn`)
+    primary: InterfaceId,
+    /// Stateful packet filter
+    firewall: Mutex<Firewall>,
+    /// Route transit packets between interfaces
+    forwarding: AtomicBool,
+    /// Forwarding counters
+    forward_stats: ForwardStats,
+    /// Masquerade and port forwarding
+    nat: Mutex<Nat>,
+    /// WireGuard tunnels by interface
+    wireguard: Mutex<BTreeMap<InterfaceId, WireGuard>>,
+    /// Ethernet bridges by interface
+    bridges: Mutex<BTreeMap<InterfaceId, Bridge>>,
+    /// Loopback device
+    loopback: Mutex<LoopbackDevice>,
+    /// TCP segments awaiting delivery to the endpoint layer
+    pending_tcp: Mutex<VecDeque<PendingTcpSegment>>,
     /// Current timestamp (ticks)
     current_time: AtomicU64,
 }
@@ -139,10 +200,27 @@ impl NetworkStack {
         let mac = config.mac;
         let ip = config.ipv4.address;
 
+        // lo (127.0.0.1/8) と プライマリNIC (eth0)
+        let mut interfaces = InterfaceTable::new();
+        let mut routes = RoutingTable::new();
+        let _ = routes.add(Route::connected(LOOPBACK_ADDRESS, LOOPBACK_ID));
+        let primary = interfaces
+            .add(PRIMARY_INTERFACE, InterfaceKind::Ethernet, mac, MTU)
+            .unwrap_or(LOOPBACK_ID + 1);
+        if let Some(eth0) = interfaces.get_mut(primary) {
+            eth0.up = true;
+            // mDNS レスポンダ用（Report はアナウンス時に送る）
+            let _ = eth0.join_group(MDNS_GROUP);
+        }
+        Self::apply_ipv4_config(&mut interfaces, &mut routes, primary, &config.ipv4);
+
         // Note: ipv4.clone() は Ipv4Config が小さい構造体のため
         // アセンブリでは memcpy やレジスタコピーに展開される
+        let mut ethernet = EthernetProcessor::new(mac);
+        ethernet.add_multicast(MacAddress::ipv4_multicast(*MDNS_GROUP.as_bytes()));
+
         NetworkStack {
-            ethernet: Mutex::new(EthernetProcessor::new(mac)),
+            ethernet: Mutex::new(ethernet),
             ipv4: Mutex::new(Ipv4Processor::new(config.ipv4.clone())),
             arp: Mutex::new(ArpProcessor::new(mac, ip)),
             icmp: Mutex::new(IcmpProcessor::new(ip)),
@@ -151,19 +229,75 @@ impl NetworkStack {
             tx_pool: PacketPool::new(64, MAX_PACKET_SIZE),
             config: Mutex::new(config),
             stats: NetworkStats::default(),
-            transmit_fn: Mutex::new(None),
+            interfaces: Mutex::new(interfaces),
+            routes: Mutex::new(routes),
+            primary,
+            firewall: Mutex::new(Firewall::new()),
+            forwarding: AtomicBool::new(false),
+            forward_stats: ForwardStats::default(),
+            nat: Mutex::new(Nat::new()),
+            wireguard: Mutex::new(BTreeMap::new()),
+            bridges: Mutex::new(BTreeMap::new()),
+            loopback: Mutex::new(LoopbackDevice::new()),
+            pending_tcp: Mutex::new(VecDeque::new()),
             current_time: AtomicU64::new(0),
         }
     }
 
+    /// Apply a single-NIC IPv4 configuration to an interface
+    ///
+    /// プライマリアドレス・接続経路・デフォルトゲートウェイを置き換える。
+    /// セカンダリアドレスと静的経路は維持する。
+    fn apply_ipv4_config(
+        interfaces: &mut InterfaceTable,
+        routes: &mut RoutingTable,
+        id: InterfaceId,
+        ipv4: &Ipv4Config,
+    ) {
+        let addr = (!ipv4.address.is_any()).then(|| {
+            InterfaceAddress::new(ipv4.address, mask_to_prefix(ipv4.subnet_mask).unwrap_or(24))
+        });
+        let Some(iface) = interfaces.get_mut(id) else {
+            return;
+        };
+        if let Some(old) = iface.set_primary_address(addr) {
+            routes.remove_connected(old, id);
+        }
+        if let Some(addr) = addr {
+            let _ = routes.add(Route::connected(addr, id));
+        }
+        if !ipv4.gateway.is_any() {
+            routes.set_default_gateway(ipv4.gateway, id, DEFAULT_ROUTE_METRIC);
+        }
+    }
+
     /// Create with default configuration
     pub fn new_default() -> Self {
         Self::new(NetworkConfig::default())
     }
 
-    /// Set transmit callback
+    /// Set transmit callback of the primary NIC
     pub fn set_transmit_fn(&self, f: TransmitFn) {
-        *self.transmit_fn.lock() = Some(f);
+        self.set_interface_transmit_fn(self.primary, f);
+    }
+
+    /// Set transmit callback of an Ethernet interface
+    pub fn set_interface_transmit_fn(&self, id: InterfaceId, f: TransmitFn) {
+        if let Some(iface) = self.interfaces.lock().get_mut(id) {
+            iface.set_transmit_fn(f);
+        }
+    }
+
+    /// Set driver offloads of an Ethernet interface
+    pub fn set_interface_offloads(&self, id: InterfaceId, offloads: Offloads) {
+        if let Some(iface) = self.interfaces.lock().get_mut(id) {
+            iface.offloads = offloads;
+        }
+    }
+
+    /// Set driver offloads of the primary NIC
+    pub fn set_offloads(&self, offloads: Offloads) {
+        self.set_interface_offloads(self.primary, offloads);
     }
 
     /// Update current time (call periodically)
@@ -211,7 +345,19 @@ impl NetworkStack {
         self.ipv4.lock().set_config(config.ipv4.clone());
         self.arp.lock().set_local(config.mac, config.ipv4.address);
 
+        // プライマリNICのアドレス・経路を同期
+        {
+            let mut interfaces = self.interfaces.lock();
+            if let Some(eth0) = interfaces.get_mut(self.primary) {
+                eth0.mac = config.mac;
+            }
+            let mut routes = self.routes.lock();
+            Self::apply_ipv4_config(&mut interfaces, &mut routes, self.primary, &config.ipv4);
+        }
+
         *cfg = config;
+        drop(cfg);
+        self.announce_mdns();
     }
 
     /// Get statistics
@@ -223,6 +369,14 @@ impl NetworkStack {
     pub fn receive(&self, data: &[u8]) {
         let current_time = self.current_time();
 
+        if self.link_input(self.primary, data) {
+            return;
+        }
+
+        if let Some(eth0) = self.interfaces.lock().get_mut(self.primary) {
+            eth0.record_rx(data.len());
+        }
+
         // Process Ethernet frame
         let result = {
             let mut eth = self.ethernet.lock();
@@ -231,10 +385,10 @@ impl NetworkStack {
 
         match result {
             ProcessResult::Ipv4(payload) => {
-                self.process_ipv4(payload, current_time);
+                self.process_ipv4(payload, self.primary, current_time);
             }
             ProcessResult::Arp(payload) => {
-                self.process_arp(payload, current_time);
+                self.process_arp(payload, self.primary, current_time);
             }
             ProcessResult::Ipv6(_payload) => {
                 // IPv6 not yet implemented
@@ -251,23 +405,200 @@ impl NetworkStack {
         self.stats.record_rx(data.len());
     }
 
-    /// Process IPv4 packet
-    fn process_ipv4(&self, data: &[u8], current_time: u64) {
-        let result = {
+    /// Process a frame received on a NIC
+    ///
+    /// プライマリ NIC は `receive` と同じ。それ以外のインターフェースは
+    /// 自身の MAC・ブロードキャスト・参加グループのマルチキャスト宛てを受け取る。
+    pub fn receive_on(&self, id: InterfaceId, data: &[u8]) {
+        if id == self.primary {
+            self.receive(data);
+            return;
+        }
+        let current_time = self.current_time();
+
+        let Some(frame) = EthernetFrame::parse(data) else {
+            self.stats.record_rx_error();
+            return;
+        };
+        if self.link_input(id, data) {
+            return;
+        }
+        let accepted = {
+            let mut interfaces = self.interfaces.lock();
+            match interfaces.get_mut(id) {
+                Some(iface) if iface.up && iface.kind.has_link_layer() => {
+                    iface.record_rx(data.len());
+                    let dst = frame.destination();
+                    dst == iface.mac
+                        || dst.is_broadcast()
+                        || iface
+                            .groups()
+                            .any(|group| MacAddress::ipv4_multicast(*group.as_bytes()) == dst)
+                }
+                _ => false,
+            }
+        };
+        if !accepted {
+            self.stats.record_dropped();
+            return;
+        }
+
+        match frame.ether_type() {
+            EtherType::Ipv4 => self.process_ipv4(frame.payload(), id, current_time),
+            EtherType::Arp => self.process_arp(frame.payload(), id, current_time),
+            _ => self.stats.record_dropped(),
+        }
+        self.stats.record_rx(data.len());
+    }
+
+    /// Hand a frame to a VLAN interface or a bridge
+    ///
+    /// タグが VLAN インターフェースに対応すればタグを外してそのインターフェースで受信し、
+    /// ブリッジのポートならブリッジに渡す（対応する VLAN がないタグ付きフレームもそのまま転送する）。
+    /// 戻り値: フレームを消費した場合 true（通常の受信処理は行わない）
+    fn link_input(&self, id: InterfaceId, data: &[u8]) -> bool {
+        let Some(frame) = EthernetFrame::parse(data) else {
+            return false;
+        };
+        let (child, master) = {
+            let mut interfaces = self.interfaces.lock();
+            let child = frame
+                .vlan_tag()
+                .and_then(|tag| interfaces.vlan_child(id, tag.vlan_id()))
+                .map(|i| i.id);
+            let Some(iface) = interfaces.get_mut(id) else {
+                return false;
+            };
+            if child.is_none() && iface.master.is_none() {
+                return false;
+            }
+            if !iface.up {
+                iface.stats.dropped += 1;
+                self.stats.record_dropped();
+                return true;
+            }
+            iface.record_rx(data.len());
+            (child, iface.master)
+        };
+
+        if let Some(child) = child {
+            match strip_vlan_tag(data) {
+                Some((_, untagged)) => self.receive_on(child, &untagged),
+                None => self.stats.record_rx_error(),
+            }
+        } else if let Some(bridge) = master {
+            self.bridge_input(bridge, id, data);
+        }
+        true
+    }
+
+    /// Switch a frame received on a bridge port
+    fn bridge_input(&self, bridge: InterfaceId, port: InterfaceId, data: &[u8]) {
+        if !self.interfaces.lock().get(bridge).is_some_and(|i| i.up) {
+            self.stats.record_dropped();
+            return;
+        }
+        let now = crate::time::current_tick();
+        let Some(decision) = self
+            .bridges
+            .lock()
+            .get_mut(&bridge)
+            .map(|br| br.input(port, data, now))
+        else {
+            return;
+        };
+        if decision.ports.is_empty() && !decision.local {
+            self.stats.record_dropped();
+            return;
+        }
+        for egress in decision.ports {
+            self.transmit_on(egress, data);
+        }
+        if decision.local {
+            self.receive_on(bridge, data);
+        }
+    }
+
+    /// Process a packet looped back through the loopback device
+    pub fn receive_loopback(&self, packet: &[u8]) {
+        if let Some(lo) = self.interfaces.lock().get_mut(LOOPBACK_ID) {
+            lo.record_rx(packet.len());
+        }
+        self.process_ipv4(packet, LOOPBACK_ID, self.current_time());
+        self.stats.record_rx(packet.len());
+    }
+
+    /// Process IPv4 packet received on an interface
+    fn process_ipv4(&self, data: &[u8], iface: InterfaceId, current_time: u64) {
+        let received = data;
+        let routing = self.forwarding_enabled() && iface != LOOPBACK_ID;
+
+        // 転送時は経路選択の前に NAT（応答の逆変換・ポート転送）を適用
+        let mut translated = None;
+        let mut flow = None;
+        if routing {
+            let interfaces = self.interfaces.lock();
+            let mut nat = self.nat.lock();
+            if nat.is_active() {
+                let name = interfaces.get(iface).map_or("", |i| i.name.as_str());
+                let mut packet = data.to_vec();
+                let now = crate::time::current_tick();
+                match nat.prerouting(&mut packet, name, |addr| interfaces.is_local(addr), now) {
+                    Ok(nat_flow) => {
+                        flow = nat_flow;
+                        translated = Some(packet);
+                    }
+                    Err(_) => {
+                        ForwardStats::record(&self.forward_stats.nat_dropped);
+                        self.stats.record_dropped();
+                        return;
+                    }
+                }
+            }
+        }
+        let data = translated.as_deref().unwrap_or(data);
+
+        let (result, transit) = {
             let mut ipv4 = self.ipv4.lock();
-            ipv4.process(data)
+            let interfaces = self.interfaces.lock();
+            let result = ipv4.process_with(data, |dst| interfaces.accepts(dst));
+
+            // 自ホスト宛てでない正しいパケットは転送対象
+            let transit = routing
+                && matches!(result, Ipv4ProcessResult::Dropped)
+                && Ipv4Packet::parse(data).is_some_and(|ip| !interfaces.accepts(&ip.destination()));
+
+            // 自ホスト宛てと判定されたパケットに input チェーンを適用
+            let local = matches!(
+                result,
+                Ipv4ProcessResult::Icmp(..)
+                    | Ipv4ProcessResult::Igmp(..)
+                    | Ipv4ProcessResult::Tcp(..)
+                    | Ipv4ProcessResult::Udp(..)
+            );
+            let name = interfaces.get(iface).map_or("", |i| i.name.as_str());
+            if local && !self.filter_packet(Chain::Input, data, name) {
+                return;
+            }
+            (result, transit)
         };
 
         match result {
             Ipv4ProcessResult::Icmp(payload, src_ip) => {
                 self.process_icmp(payload, src_ip, current_time);
             }
+            Ipv4ProcessResult::Igmp(payload, _, _) => {
+                self.process_igmp(payload, iface);
+            }
             Ipv4ProcessResult::Udp(payload, src_ip, dst_ip) => {
                 self.process_udp(payload, src_ip, dst_ip);
             }
             Ipv4ProcessResult::Tcp(payload, src_ip, dst_ip) => {
                 self.process_tcp(payload, src_ip, dst_ip);
             }
+            Ipv4ProcessResult::Dropped if transit => {
+                self.forward(data, received, iface, flow);
+            }
             Ipv4ProcessResult::Dropped => {
                 self.stats.record_dropped();
             }
@@ -277,19 +608,23 @@ impl NetworkStack {
         }
     }
 
-    /// Process ARP packet
-    fn process_arp(&self, data: &[u8], current_time: u64) {
+    /// Process ARP packet received on an interface
+    fn process_arp(&self, data: &[u8], iface: InterfaceId, current_time: u64) {
         let result = {
             let arp = self.arp.lock();
-            arp.process(data, current_time)
+            let interfaces = self.interfaces.lock();
+            arp.process_with(data, current_time, |ip| {
+                interfaces.get(iface).is_some_and(|i| i.has_address(ip))
+            })
         };
 
         match result {
             ArpResult::SendReply {
                 target_mac,
                 target_ip,
+                local_ip,
             } => {
-                self.send_arp_reply(target_mac, target_ip);
+                self.send_arp_reply(iface, target_mac, target_ip, local_ip);
             }
             ArpResult::CacheUpdated => {
                 // Cache was updated, check if we have pending sends
@@ -338,8 +673,43 @@ impl NetworkStack {
         }
     }
 
+    /// Process IGMP packet
+    ///
+    /// Membership Query に対し、問い合わせられた参加中グループの Report を返す。
+    /// 他ホストの Report による送信抑制は行わない。
+    fn process_igmp(&self, data: &[u8], iface: InterfaceId) {
+        let Some(message) = IgmpMessage::parse(data) else {
+            self.stats.record_rx_error();
+            return;
+        };
+        let joined: Vec<Ipv4Address> = self
+            .interfaces
+            .lock()
+            .get(iface)
+            .map(|i| i.groups().collect())
+            .unwrap_or_default();
+        for group in message.queried_groups(&joined) {
+            self.send_igmp(iface, IgmpMessage::report(group));
+        }
+    }
+
     /// Process UDP packet
     fn process_udp(&self, data: &[u8], src_ip: Ipv4Address, dst_ip: Ipv4Address) {
+        // mDNS はカーネルのレスポンダが処理する
+        if let Some(packet) = UdpPacket::parse(data)
+            && packet.dst_port() == MDNS_PORT
+        {
+            self.process_mdns(&packet, src_ip, dst_ip);
+            return;
+        }
+
+        // WireGuard の待ち受けポート宛てはトンネルが処理する
+        if let Some(packet) = UdpPacket::parse(data)
+            && self.process_wireguard(&packet, src_ip, dst_ip)
+        {
+            return;
+        }
+
         let result = self.udp.process(data, src_ip, dst_ip);
 
         match result {
@@ -356,15 +726,57 @@ impl NetworkStack {
 
     /// Process TCP packet
     fn process_tcp(&self, data: &[u8], src_ip: Ipv4Address, dst_ip: Ipv4Address) {
-        let mut tcp = self.tcp.lock();
-        tcp.process(data, src_ip, dst_ip);
+        self.tcp.lock().process(data, src_ip, dst_ip);
+
+        // エンドポイント層は応答送信時にスタックのロックを取るため、
+        // 配送はロック解放後に行う（take_pending_tcp）
+        let mut pending = self.pending_tcp.lock();
+        if pending.len() >= PENDING_TCP_LEN {
+            self.stats.record_dropped();
+            return;
+        }
+        pending.push_back(PendingTcpSegment {
+            src: src_ip,
+            dst: dst_ip,
+            segment: data.to_vec(),
+        });
+    }
+
+    /// Process an mDNS packet (UDP port 5353)
+    fn process_mdns(&self, packet: &UdpPacket<'_>, src_ip: Ipv4Address, dst_ip: Ipv4Address) {
+        if !packet.verify_checksum(src_ip, dst_ip) {
+            self.stats.record_rx_error();
+            return;
+        }
+        let reply = super::mdns::handle_packet(
+            packet.payload(),
+            src_ip,
+            packet.src_port(),
+            self.ipv4_address(),
+            crate::time::current_tick(),
+        );
+        if let Some(reply) = reply {
+            self.send_udp_with_ttl(MDNS_PORT, reply.dst, reply.port, &reply.data, MDNS_IP_TTL);
+        }
+    }
+
+    /// Take TCP segments awaiting delivery to the endpoint layer
+    pub fn take_pending_tcp(&self) -> Vec<PendingTcpSegment> {
+        self.pending_tcp.lock().drain(..).collect()
     }
 
-    /// Send an ARP reply
-    fn send_arp_reply(&self, target_mac: MacAddress, target_ip: Ipv4Address) {
+    /// Send an ARP reply for one of our addresses on an interface
+    fn send_arp_reply(
+        &self,
+        iface: InterfaceId,
+        target_mac: MacAddress,
+        target_ip: Ipv4Address,
+        local_ip: Ipv4Address,
+    ) {
         let mut buffer = [0u8; 64];
-        // clone() ではなく Copy 型の個別取得
-        let mac = self.mac_address();
+        let Some(mac) = self.interfaces.lock().get(iface).map(|i| i.mac) else {
+            return;
+        };
 
         // Build Ethernet frame
         if let Some(mut frame) = EthernetFrameMut::new(&mut buffer) {
@@ -374,21 +786,38 @@ impl NetworkStack {
                 .set_ether_type(EtherType::Arp);
 
             let payload = frame.payload_mut();
-            if let Some(len) = self.arp.lock().build_reply(payload, target_mac, target_ip) {
+            let len = self.arp.lock().build_reply_from(payload, mac, local_ip, target_mac, target_ip);
+            if let Some(len) = len {
                 frame.set_payload_len(len);
                 frame.pad_to_minimum();
 
-                self.transmit(frame.as_bytes());
+                self.transmit_on(iface, frame.as_bytes());
             }
         }
     }
 
-    /// Send an ARP request
+    /// Send an ARP request on the primary NIC
     pub fn send_arp_request(&self, target_ip: Ipv4Address) {
+        self.send_arp_request_on(self.primary, target_ip);
+    }
+
+    /// Send an ARP request on an interface
+    ///
+    /// 送信元には対象と同じサブネットのアドレス（なければプライマリアドレス）を使う。
+    pub fn send_arp_request_on(&self, iface: InterfaceId, target_ip: Ipv4Address) {
         let mut buffer = [0u8; 64];
-        // clone() ではなく Copy 型の個別取得
-        let mac = self.mac_address();
         let current_time = self.current_time();
+        let Some((mac, sender_ip)) = self.interfaces.lock().get(iface).map(|i| {
+            let address = i
+                .addresses()
+                .iter()
+                .find(|a| a.contains(&target_ip))
+                .copied()
+                .or_else(|| i.primary_address());
+            (i.mac, address.map_or(Ipv4Address::ANY, |a| a.address))
+        }) else {
+            return;
+        };
 
         // Check if we already have a pending request
         {
@@ -406,14 +835,15 @@ impl NetworkStack {
                 .set_ether_type(EtherType::Arp);
 
             let payload = frame.payload_mut();
-            if let Some(len) = self.arp.lock().build_request(payload, target_ip) {
+            let len = self.arp.lock().build_request_from(payload, mac, sender_ip, target_ip);
+            if let Some(len) = len {
                 frame.set_payload_len(len);
                 frame.pad_to_minimum();
 
                 // Mark request as sent
                 self.arp.lock().request_sent(target_ip, current_time);
 
-                self.transmit(frame.as_bytes());
+                self.transmit_on(iface, frame.as_bytes());
             }
         }
     }
@@ -427,236 +857,1028 @@ impl NetworkStack {
         echo_data: &[u8],
         current_time: u64,
     ) {
-        let config = self.config.lock().clone();
-
-        // Resolve MAC address
-        let dst_mac = if config.ipv4.is_local(&dst_ip) {
-            // Destination is on local subnet, use ARP
-            match self.arp.lock().resolve(dst_ip, current_time) {
-                Some(mac) => mac,
-                None => {
-                    // Need to send ARP request first
-                    self.send_arp_request(dst_ip);
-                    return;
-                }
-            }
-        } else {
-            // Destination is remote, use gateway
-            match self.arp.lock().resolve(config.ipv4.gateway, current_time) {
-                Some(mac) => mac,
-                None => {
-                    self.send_arp_request(config.ipv4.gateway);
-                    return;
-                }
-            }
-        };
-
-        let mut buffer = [0u8; MAX_PACKET_SIZE];
-
-        // Build Ethernet frame
-        if let Some(mut frame) = EthernetFrameMut::new(&mut buffer) {
-            frame
-                .set_destination(dst_mac)
-                .set_source(config.mac)
-                .set_ether_type(EtherType::Ipv4);
-
-            let eth_payload = frame.payload_mut();
-
-            // Build IP packet
-            if let Some(mut ip_packet) = Ipv4PacketMut::new(eth_payload) {
-                ip_packet
-                    .init_header()
-                    .set_source(config.ipv4.address)
-                    .set_destination(dst_ip)
-                    .set_protocol(IpProtocol::Icmp)
-                    .set_ttl(64);
-
-                let ip_payload = ip_packet.payload_mut();
-
-                // Build ICMP packet
-                if let Some(mut icmp) = IcmpEchoBuilder::new(ip_payload) {
-                    icmp.build_reply(identifier, sequence);
-                    icmp.write_data(echo_data);
-                    let icmp_len = icmp.finalize();
-
-                    ip_packet.finalize(icmp_len);
-
-                    let ip_len = ip_packet.total_len();
-                    frame.set_payload_len(ip_len);
-
-                    self.transmit(frame.as_bytes());
-                }
-            }
-        }
+        let _ = current_time;
+        let payload_len = IcmpEchoHeader::SIZE + echo_data.len();
+        self.route_output(None, dst_ip, IpProtocol::Icmp, payload_len, |buf, _| {
+            let mut icmp = IcmpEchoBuilder::new(buf)?;
+            icmp.build_reply(identifier, sequence);
+            icmp.write_data(echo_data);
+            Some(icmp.finalize())
+        });
     }
 
     /// Send a UDP packet
     pub fn send_udp(&self, src_port: u16, dst_ip: Ipv4Address, dst_port: u16, data: &[u8]) -> bool {
-        let config = self.config.lock().clone();
-        let current_time = self.current_time();
+        self.send_udp_with_ttl(src_port, dst_ip, dst_port, data, DEFAULT_TTL)
+    }
 
-        // Resolve MAC address
-        let dst_mac = self.resolve_mac(dst_ip, &config, current_time);
-        let dst_mac = match dst_mac {
-            Some(mac) => mac,
-            None => return false,
+    /// Send a UDP packet with an explicit IP TTL (mDNS uses 255)
+    pub fn send_udp_with_ttl(
+        &self,
+        src_port: u16,
+        dst_ip: Ipv4Address,
+        dst_port: u16,
+        data: &[u8],
+        ttl: u8,
+    ) -> bool {
+        let payload_len = super::udp::UdpHeader::SIZE + data.len();
+        let Ok(lookup) = self.route(dst_ip) else {
+            self.stats.record_tx_error();
+            return false;
         };
+        self.output(lookup, None, dst_ip, IpProtocol::Udp, ttl, payload_len, |buf, src_ip| {
+            super::udp::UdpProcessor::build_packet(buf, src_ip, src_port, dst_ip, dst_port, data)
+        })
+    }
 
-        let mut buffer = [0u8; MAX_PACKET_SIZE];
-
-        // Build Ethernet frame
-        if let Some(mut frame) = EthernetFrameMut::new(&mut buffer) {
-            frame
-                .set_destination(dst_mac)
-                .set_source(config.mac)
-                .set_ether_type(EtherType::Ipv4);
-
-            let eth_payload = frame.payload_mut();
-
-            // Build IP packet
-            if let Some(mut ip_packet) = Ipv4PacketMut::new(eth_payload) {
-                ip_packet
-                    .init_header()
-                    .set_source(config.ipv4.address)
-                    .set_destination(dst_ip)
-                    .set_protocol(IpProtocol::Udp)
-                    .set_ttl(64);
-
-                let ip_payload = ip_packet.payload_mut();
-
-                // Build UDP packet
-                if let Some(udp_len) = super::udp::UdpProcessor::build_packet(
-                    ip_payload,
-                    config.ipv4.address,
-                    src_port,
-                    dst_ip,
-                    dst_port,
-                    data,
-                ) {
-                    ip_packet.finalize(udp_len);
-
-                    let ip_len = ip_packet.total_len();
-                    frame.set_payload_len(ip_len);
-
-                    return self.transmit(frame.as_bytes());
-                }
+    /// Send an IGMP message on an interface
+    fn send_igmp(&self, iface: InterfaceId, message: IgmpMessage) -> bool {
+        let dst_ip = message.destination();
+        let lookup = {
+            let interfaces = self.interfaces.lock();
+            let Some(i) = interfaces.get(iface).filter(|i| i.up && !i.is_loopback()) else {
+                return false;
+            };
+            RouteLookup {
+                interface: i.id,
+                kind: i.kind,
+                mtu: i.mtu,
+                next_hop: dst_ip,
+                source: i.primary_address().map_or(Ipv4Address::ANY, |a| a.address),
             }
-        }
-
-        false
+        };
+        self.output(lookup, None, dst_ip, IpProtocol::Igmp, IGMP_TTL, IGMP_MESSAGE_SIZE, |buf, _| {
+            message.write(buf)
+        })
     }
 
-    /// Resolve IP to MAC address
-    fn resolve_mac(
-        &self,
-        dst_ip: Ipv4Address,
-        config: &NetworkConfig,
-        current_time: u64,
-    ) -> Option<MacAddress> {
-        // Broadcast address
-        if dst_ip.is_broadcast() {
-            return Some(MacAddress::BROADCAST);
-        }
-
-        // Determine next hop
-        let next_hop = if config.ipv4.is_local(&dst_ip) {
-            dst_ip
-        } else {
-            config.ipv4.gateway
+    /// Join a multicast group on an interface
+    ///
+    /// 初回参加時に NIC のマルチキャストフィルタを更新し、IGMP Report を送る
+    pub fn join_multicast(&self, name: &str, group: Ipv4Address) -> Result<(), InterfaceError> {
+        let (id, first) = {
+            let mut ethernet = self.ethernet.lock();
+            let mut interfaces = self.interfaces.lock();
+            let iface = interfaces.by_name_mut(name).ok_or(InterfaceError::NotFound)?;
+            let first = iface.join_group(group)?;
+            if first && iface.id == self.primary {
+                ethernet.add_multicast(MacAddress::ipv4_multicast(*group.as_bytes()));
+            }
+            (iface.id, first)
         };
+        if first {
+            self.send_igmp(id, IgmpMessage::report(group));
+        }
+        Ok(())
+    }
 
-        // Look up in ARP cache
-        let arp = self.arp.lock();
-        match arp.resolve(next_hop, current_time) {
-            Some(mac) => Some(mac),
-            None => {
-                drop(arp);
-                // Need ARP resolution
-                self.send_arp_request(next_hop);
-                None
+    /// Leave a multicast group on an interface
+    pub fn leave_multicast(&self, name: &str, group: Ipv4Address) -> Result<(), InterfaceError> {
+        let (id, last) = {
+            let mut ethernet = self.ethernet.lock();
+            let mut interfaces = self.interfaces.lock();
+            let iface = interfaces.by_name_mut(name).ok_or(InterfaceError::NotFound)?;
+            if !iface.groups().any(|g| g == group) {
+                return Err(InterfaceError::InvalidGroup);
             }
+            let last = iface.leave_group(group);
+            if last && iface.id == self.primary {
+                ethernet.remove_multicast(MacAddress::ipv4_multicast(*group.as_bytes()));
+            }
+            (iface.id, last)
+        };
+        if last {
+            self.send_igmp(id, IgmpMessage::leave(group));
         }
+        Ok(())
+    }
+
+    /// Announce mDNS host and service records on the primary NIC
+    pub fn announce_mdns(&self) -> bool {
+        self.send_igmp(self.primary, IgmpMessage::report(MDNS_GROUP));
+        let reply = super::mdns::announcement(self.ipv4_address());
+        self.send_udp_with_ttl(MDNS_PORT, reply.dst, reply.port, &reply.data, MDNS_IP_TTL)
     }
 
     /// Send a raw TCP segment
     /// tcp_segment should already have the TCP header and data, with checksum calculated
     pub fn send_tcp(&self, src_ip: Ipv4Address, dst_ip: Ipv4Address, tcp_segment: &[u8]) -> bool {
-        let config = self.config.lock().clone();
-        let current_time = self.current_time();
-
-        // Resolve MAC address
-        let dst_mac = self.resolve_mac(dst_ip, &config, current_time);
-        let dst_mac = match dst_mac {
-            Some(mac) => mac,
-            None => return false, // ARP resolution pending
-        };
+        self.route_output(Some(src_ip), dst_ip, IpProtocol::Tcp, tcp_segment.len(), |buf, _| {
+            let segment = buf.get_mut(..tcp_segment.len())?;
+            segment.copy_from_slice(tcp_segment);
+            Some(tcp_segment.len())
+        })
+    }
 
-        let mut buffer = [0u8; MAX_PACKET_SIZE];
+    /// Resolve a destination (outgoing interface, next hop, source address)
+    pub fn route(&self, dst_ip: Ipv4Address) -> Result<RouteLookup, RouteError> {
+        let interfaces = self.interfaces.lock();
+        self.routes.lock().resolve(&interfaces, dst_ip)
+    }
 
-        // Build Ethernet frame
-        if let Some(mut frame) = EthernetFrameMut::new(&mut buffer) {
-            frame
-                .set_destination(dst_mac)
-                .set_source(config.mac)
-                .set_ether_type(EtherType::Ipv4);
+    /// Select the source address for a destination
+    pub fn select_source(&self, dst_ip: Ipv4Address) -> Option<Ipv4Address> {
+        self.route(dst_ip)
+            .ok()
+            .map(|r| r.source)
+            .filter(|src| !src.is_any())
+    }
 
-            let eth_payload = frame.payload_mut();
+    /// Route and transmit an IPv4 packet
+    ///
+    /// `build` は送信元アドレスを受け取り、IPペイロードを書き込んで長さを返す。
+    /// `src_ip` が None（または 0.0.0.0）なら送信元アドレス選択を行う。
+    fn route_output(
+        &self,
+        src_ip: Option<Ipv4Address>,
+        dst_ip: Ipv4Address,
+        protocol: IpProtocol,
+        payload_len: usize,
+        build: impl FnOnce(&mut [u8], Ipv4Address) -> Option<usize>,
+    ) -> bool {
+        let lookup = match self.route(dst_ip) {
+            Ok(lookup) => lookup,
+            Err(_) => {
+                self.stats.record_tx_error();
+                return false;
+            }
+        };
+        self.output(lookup, src_ip, dst_ip, protocol, DEFAULT_TTL, payload_len, build)
+    }
 
-            // Build IP packet
-            if let Some(mut ip_packet) = Ipv4PacketMut::new(eth_payload) {
-                ip_packet
-                    .init_header()
-                    .set_source(src_ip)
-                    .set_destination(dst_ip)
-                    .set_protocol(IpProtocol::Tcp)
-                    .set_ttl(64);
+    /// Transmit an IPv4 packet through a resolved route
+    #[allow(clippy::too_many_arguments)]
+    fn output(
+        &self,
+        lookup: RouteLookup,
+        src_ip: Option<Ipv4Address>,
+        dst_ip: Ipv4Address,
+        protocol: IpProtocol,
+        ttl: u8,
+        payload_len: usize,
+        build: impl FnOnce(&mut [u8], Ipv4Address) -> Option<usize>,
+    ) -> bool {
+        let src_ip = src_ip
+            .filter(|src| !src.is_any())
+            .unwrap_or(lookup.source);
+        let ip_len = Ipv4Header::MIN_SIZE + payload_len;
+        // TSO 対応 NIC には MTU を超える TCP セグメントをそのまま渡す（ドライバが分割）
+        let tso = protocol == IpProtocol::Tcp
+            && lookup.kind == InterfaceKind::Ethernet
+            && self.interfaces.lock().get(lookup.interface).is_some_and(|i| i.offloads.tso);
+        if ip_len > lookup.mtu && !(tso && ip_len <= u16::MAX as usize) {
+            self.stats.record_tx_error();
+            return false;
+        }
 
-                let ip_payload = ip_packet.payload_mut();
+        match lookup.kind {
+            InterfaceKind::Loopback | InterfaceKind::WireGuard => {
+                let mut packet = alloc::vec![0u8; ip_len];
+                let Some(len) = Self::build_ipv4(&mut packet, src_ip, dst_ip, protocol, ttl, |buf| {
+                    build(buf, src_ip)
+                }) else {
+                    return false;
+                };
+                packet.truncate(len);
+                if !self.output_allowed(lookup.interface, &packet) {
+                    return false;
+                }
+                if lookup.kind == InterfaceKind::WireGuard {
+                    self.wireguard_output(lookup.interface, &packet)
+                } else {
+                    self.loopback_output(lookup.interface, packet)
+                }
+            }
+            InterfaceKind::Ethernet | InterfaceKind::Vlan | InterfaceKind::Bridge => {
+                let current_time = self.current_time();
+
+                // Resolve MAC address
+                let Some(dst_mac) =
+                    self.resolve_next_hop(lookup.interface, dst_ip, lookup.next_hop, current_time)
+                else {
+                    return false; // ARP resolution pending
+                };
+                let Some(src_mac) = self.interfaces.lock().get(lookup.interface).map(|i| i.mac)
+                else {
+                    return false;
+                };
 
-                // Copy TCP segment
-                if ip_payload.len() >= tcp_segment.len() {
-                    ip_payload[..tcp_segment.len()].copy_from_slice(tcp_segment);
-                    ip_packet.finalize(tcp_segment.len());
+                let mut stack_buffer = [0u8; MAX_PACKET_SIZE];
+                let mut large_buffer;
+                let buffer: &mut [u8] = if EthernetHeader::SIZE + ip_len > MAX_PACKET_SIZE {
+                    large_buffer = alloc::vec![0u8; EthernetHeader::SIZE + ip_len];
+                    &mut large_buffer
+                } else {
+                    &mut stack_buffer
+                };
 
-                    let ip_len = ip_packet.total_len();
-                    frame.set_payload_len(ip_len);
+                // Build Ethernet frame
+                let Some(mut frame) = EthernetFrameMut::new(buffer) else {
+                    return false;
+                };
+                frame
+                    .set_destination(dst_mac)
+                    .set_source(src_mac)
+                    .set_ether_type(EtherType::Ipv4);
+
+                let Some(len) = Self::build_ipv4(frame.payload_mut(), src_ip, dst_ip, protocol, ttl, |buf| {
+                    build(buf, src_ip)
+                }) else {
+                    return false;
+                };
+                frame.set_payload_len(len);
 
-                    return self.transmit(frame.as_bytes());
+                if !self.output_allowed(lookup.interface, &frame.as_bytes()[EthernetHeader::SIZE..]) {
+                    return false;
                 }
+                self.transmit_on(lookup.interface, frame.as_bytes())
             }
         }
+    }
 
-        false
+    /// Run a locally generated IPv4 packet through the output chain
+    fn output_allowed(&self, id: InterfaceId, packet: &[u8]) -> bool {
+        let interfaces = self.interfaces.lock();
+        let name = interfaces.get(id).map_or("", |i| i.name.as_str());
+        self.filter_packet(Chain::Output, packet, name)
     }
 
-    /// Bind a UDP socket
-    pub fn bind_udp(&self, port: u16) -> Option<UdpSocket> {
-        self.udp.bind(port)
+    /// Evaluate an IPv4 packet on a firewall chain
+    ///
+    /// 戻り値: 通過させるなら true（破棄したパケットは統計に計上）
+    pub fn filter_packet(&self, chain: Chain, packet: &[u8], interface: &str) -> bool {
+        let Some(meta) = PacketMeta::parse(packet, interface) else {
+            return true;
+        };
+        let verdict = self.firewall.lock().filter(chain, &meta, crate::time::current_tick());
+        if verdict.log {
+            crate::serial_println!(
+                "[FIREWALL] {} {} ({}) {}",
+                chain.name(),
+                verdict.action.name(),
+                verdict.state.name(),
+                meta
+            );
+        }
+        if verdict.action == Action::Drop {
+            self.stats.record_dropped();
+            return false;
+        }
+        true
     }
 
-    /// Transmit a raw Ethernet frame
-    pub fn transmit(&self, data: &[u8]) -> bool {
-        let tx_fn = self.transmit_fn.lock();
+    /// Access the firewall
+    pub fn with_firewall<R>(&self, f: impl FnOnce(&mut Firewall) -> R) -> R {
+        f(&mut self.firewall.lock())
+    }
 
-        if let Some(f) = *tx_fn {
-            if f(data) {
-                self.stats.record_tx(data.len());
-                return true;
-            } else {
-                self.stats.record_tx_error();
-                return false;
-            }
-        }
+    /// Enable or disable forwarding between interfaces
+    pub fn set_forwarding(&self, enabled: bool) {
+        self.forwarding.store(enabled, Ordering::Relaxed);
+    }
 
-        false
+    /// Check if forwarding is enabled
+    pub fn forwarding_enabled(&self) -> bool {
+        self.forwarding.load(Ordering::Relaxed)
     }
 
-    /// Get ARP cache entries (for debugging)
-    pub fn arp_cache(&self) -> Vec<(Ipv4Address, MacAddress)> {
-        self.arp
-            .lock()
-            .cache()
-            .all_entries()
+    /// Forwarding counters
+    pub fn forward_stats(&self) -> &ForwardStats {
+        &self.forward_stats
+    }
+
+    /// Access the NAT table
+    pub fn with_nat<R>(&self, f: impl FnOnce(&mut Nat) -> R) -> R {
+        f(&mut self.nat.lock())
+    }
+
+    /// Create a WireGuard interface (initially down, no addresses)
+    pub fn add_wireguard(
+        &self,
+        name: &str,
+        private_key: WgKey,
+        listen_port: u16,
+    ) -> Result<InterfaceId, WgError> {
+        if self.udp.sockets().is_bound(listen_port)
+            || self.wireguard_by_port(listen_port).is_some()
+        {
+            return Err(WgError::PortInUse);
+        }
+        let id = self.interfaces.lock().add(
+            name,
+            InterfaceKind::WireGuard,
+            MacAddress::ZERO,
+            WIREGUARD_MTU,
+        )?;
+        self.wireguard
+            .lock()
+            .insert(id, WireGuard::new(private_key, listen_port));
+        Ok(id)
+    }
+
+    /// Access a WireGuard interface by name
+    pub fn with_wireguard<R>(
+        &self,
+        name: &str,
+        f: impl FnOnce(&mut WireGuard) -> R,
+    ) -> Result<R, WgError> {
+        let id = self.interface_id(name)?;
+        let mut devices = self.wireguard.lock();
+        let device = devices.get_mut(&id).ok_or(WgError::NotWireGuard)?;
+        Ok(f(device))
+    }
+
+    /// Names of WireGuard interfaces
+    pub fn wireguard_interfaces(&self) -> Vec<alloc::string::String> {
+        let interfaces = self.interfaces.lock();
+        interfaces
+            .iter()
+            .filter(|i| i.kind == InterfaceKind::WireGuard)
+            .map(|i| i.name.clone())
+            .collect()
+    }
+
+    fn interface_id(&self, name: &str) -> Result<InterfaceId, InterfaceError> {
+        self.interfaces
+            .lock()
+            .by_name(name)
+            .map(|i| i.id)
+            .ok_or(InterfaceError::NotFound)
+    }
+
+    /// WireGuard interface listening on a UDP port
+    fn wireguard_by_port(&self, port: u16) -> Option<InterfaceId> {
+        self.wireguard
+            .lock()
+            .iter()
+            .find(|(_, wg)| wg.listen_port() == port)
+            .map(|(&id, _)| id)
+    }
+
+    /// Encrypt a packet routed to a WireGuard interface and send it to the peer
+    fn wireguard_output(&self, id: InterfaceId, packet: &[u8]) -> bool {
+        let now = crate::time::current_tick();
+        let (port, result) = match self.wireguard.lock().get_mut(&id) {
+            Some(wg) => (wg.listen_port(), wg.encapsulate(packet, now)),
+            None => return false,
+        };
+        let sent = result.is_ok();
+        if let Some(iface) = self.interfaces.lock().get_mut(id) {
+            if sent {
+                iface.record_tx(packet.len());
+            } else {
+                iface.stats.tx_errors += 1;
+            }
+        }
+        match result {
+            Ok(datagrams) => self.send_encapsulated(port, datagrams),
+            Err(_) => self.stats.record_tx_error(),
+        }
+        sent
+    }
+
+    /// Send WireGuard messages from a tunnel's listen port
+    fn send_encapsulated(&self, port: u16, datagrams: Vec<Datagram>) {
+        for datagram in datagrams {
+            let dst_ip = datagram.endpoint.ip;
+            let lookup = match self.route(dst_ip) {
+                // トンネル自身を通る経路では送らない（ルーティングループ）
+                Ok(lookup) if lookup.kind != InterfaceKind::WireGuard => lookup,
+                _ => {
+                    self.stats.record_tx_error();
+                    continue;
+                }
+            };
+            let payload_len = UdpHeader::SIZE + datagram.data.len();
+            self.output(lookup, None, dst_ip, IpProtocol::Udp, DEFAULT_TTL, payload_len, |buf, src_ip| {
+                UdpProcessor::build_packet(
+                    buf,
+                    src_ip,
+                    port,
+                    dst_ip,
+                    datagram.endpoint.port,
+                    &datagram.data,
+                )
+            });
+        }
+    }
+
+    /// Process a datagram addressed to a WireGuard listen port
+    ///
+    /// 戻り値: WireGuard の待ち受けポート宛てでなければ false（通常の UDP として扱う）
+    fn process_wireguard(&self, packet: &UdpPacket<'_>, src_ip: Ipv4Address, dst_ip: Ipv4Address) -> bool {
+        let Some(id) = self.wireguard_by_port(packet.dst_port()) else {
+            return false;
+        };
+        if !self.interfaces.lock().get(id).is_some_and(|i| i.up) {
+            self.stats.record_dropped();
+            return true;
+        }
+        if !packet.verify_checksum(src_ip, dst_ip) {
+            self.stats.record_rx_error();
+            return true;
+        }
+
+        let src = UdpAddr::new(src_ip, packet.src_port());
+        let now = crate::time::current_tick();
+        let (port, result) = match self.wireguard.lock().get_mut(&id) {
+            Some(wg) => (wg.listen_port(), wg.decapsulate(src, packet.payload(), now)),
+            None => return true,
+        };
+        let decapsulated = match result {
+            Ok(decapsulated) => decapsulated,
+            Err(_) => {
+                self.stats.record_dropped();
+                return true;
+            }
+        };
+        self.send_encapsulated(port, decapsulated.datagrams);
+
+        // 復号したパケットはトンネルのインターフェースで受信する
+        if let Some(inner) = decapsulated.packet {
+            if let Some(iface) = self.interfaces.lock().get_mut(id) {
+                iface.record_rx(inner.len());
+            }
+            self.process_ipv4(&inner, id, self.current_time());
+        }
+        true
+    }
+
+    /// Run WireGuard timers (handshake retries, keepalives, rekeying)
+    ///
+    /// 戻り値: WireGuard インターフェースがあれば true
+    pub fn wireguard_timers(&self) -> bool {
+        let now = crate::time::current_tick();
+        let batches: Vec<(u16, Vec<Datagram>)> = self
+            .wireguard
+            .lock()
+            .values_mut()
+            .map(|wg| (wg.listen_port(), wg.tick(now)))
+            .collect();
+        let active = !batches.is_empty();
+        for (port, datagrams) in batches {
+            self.send_encapsulated(port, datagrams);
+        }
+        active
+    }
+
+    /// Forward a transit packet received on `ingress`
+    ///
+    /// `packet` はプリルーティング NAT 適用後のパケット、`received` は受信した
+    /// ままのパケット（ICMP エラーで引用する）。
+    fn forward(&self, packet: &[u8], received: &[u8], ingress: InterfaceId, flow: Option<NatFlow>) {
+        let counters = &self.forward_stats;
+        let Some(ip) = Ipv4Packet::parse(packet) else {
+            return;
+        };
+        // Ethernet のパディングを除く
+        let packet = ip.as_bytes();
+        let (src, dst) = (ip.source(), ip.destination());
+        if !forward::is_forwardable(src, dst) {
+            ForwardStats::record(&counters.not_forwardable);
+            self.stats.record_dropped();
+            return;
+        }
+
+        if ip.ttl() <= 1 {
+            ForwardStats::record(&counters.ttl_exceeded);
+            self.send_icmp_error(ingress, received, |buf| {
+                IcmpProcessor::build_time_exceeded(buf, TimeExceededCode::TtlExceeded, received)
+            });
+            return;
+        }
+
+        let lookup = match self.route(dst) {
+            Ok(lookup) if lookup.kind != InterfaceKind::Loopback => lookup,
+            Ok(_) => {
+                ForwardStats::record(&counters.not_forwardable);
+                self.stats.record_dropped();
+                return;
+            }
+            Err(_) => {
+                ForwardStats::record(&counters.no_route);
+                self.send_icmp_error(ingress, received, |buf| {
+                    IcmpProcessor::build_dest_unreachable(buf, DestUnreachCode::NetworkUnreachable, received)
+                });
+                return;
+            }
+        };
+
+        let (ingress_name, egress_name, egress_address, src_mac) = {
+            let interfaces = self.interfaces.lock();
+            let Some(egress) = interfaces.get(lookup.interface) else {
+                return;
+            };
+            // 出力側サブネットのブロードキャストは転送しない
+            if egress.is_subnet_broadcast(&dst) {
+                ForwardStats::record(&counters.not_forwardable);
+                self.stats.record_dropped();
+                return;
+            }
+            (
+                interfaces.get(ingress).map_or_else(alloc::string::String::new, |i| i.name.clone()),
+                egress.name.clone(),
+                egress.primary_address().map(|a| a.address),
+                egress.mac,
+            )
+        };
+
+        // フラグメント化は行わない
+        if packet.len() > lookup.mtu || packet.len() > MTU {
+            ForwardStats::record(&counters.too_big);
+            if ip.header().dont_fragment() {
+                let mtu = lookup.mtu.min(MTU) as u16;
+                self.send_icmp_error(ingress, received, |buf| {
+                    let len = IcmpProcessor::build_dest_unreachable(
+                        buf,
+                        DestUnreachCode::FragmentationNeeded,
+                        received,
+                    )?;
+                    // Next-hop MTU (RFC 1191)
+                    buf[6..8].copy_from_slice(&mtu.to_be_bytes());
+                    buf[2..4].fill(0);
+                    let checksum = data_checksum(&buf[..len], 0);
+                    buf[2..4].copy_from_slice(&checksum.to_be_bytes());
+                    Some(len)
+                });
+            } else {
+                self.stats.record_dropped();
+            }
+            return;
+        }
+
+        if !self.filter_packet(Chain::Forward, packet, &ingress_name) {
+            ForwardStats::record(&counters.filtered);
+            return;
+        }
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let Some(mut frame) = EthernetFrameMut::new(&mut buffer) else {
+            return;
+        };
+        let out = &mut frame.payload_mut()[..packet.len()];
+        out.copy_from_slice(packet);
+        forward::decrement_ttl(out);
+
+        // 送信直前に NAT（マスカレード）を適用
+        {
+            let mut nat = self.nat.lock();
+            if nat.is_active() {
+                let now = crate::time::current_tick();
+                if nat.postrouting(out, flow, &egress_name, egress_address, now).is_err() {
+                    ForwardStats::record(&counters.nat_dropped);
+                    self.stats.record_dropped();
+                    return;
+                }
+            }
+        }
+
+        // トンネルへの転送は暗号化して送る
+        if lookup.kind == InterfaceKind::WireGuard {
+            if self.wireguard_output(lookup.interface, out) {
+                ForwardStats::record(&counters.forwarded);
+            }
+            return;
+        }
+
+        let Some(dst_mac) =
+            self.resolve_next_hop(lookup.interface, dst, lookup.next_hop, self.current_time())
+        else {
+            // ARP 解決待ち（パケットは保持しない）
+            ForwardStats::record(&counters.arp_pending);
+            return;
+        };
+        frame
+            .set_destination(dst_mac)
+            .set_source(src_mac)
+            .set_ether_type(EtherType::Ipv4)
+            .set_payload_len(packet.len());
+        if self.transmit_on(lookup.interface, frame.as_bytes()) {
+            ForwardStats::record(&counters.forwarded);
+        }
+    }
+
+    /// Send an ICMP error about a transit packet back to its source
+    ///
+    /// 送信元アドレスは受信インターフェースのアドレス。
+    fn send_icmp_error(
+        &self,
+        ingress: InterfaceId,
+        original: &[u8],
+        build: impl FnOnce(&mut [u8]) -> Option<usize>,
+    ) {
+        if !forward::may_send_icmp_error(original) {
+            self.stats.record_dropped();
+            return;
+        }
+        let Some(ip) = Ipv4Packet::parse(original) else {
+            return;
+        };
+        let src = self
+            .interfaces
+            .lock()
+            .get(ingress)
+            .and_then(|i| i.primary_address())
+            .map(|a| a.address);
+        // ICMP ヘッダ + 未使用 4 バイト + 元の IP ヘッダ + 8 バイト
+        let len = 8 + Ipv4Header::MIN_SIZE + 8;
+        self.route_output(src, ip.source(), IpProtocol::Icmp, len, |buf, _| build(buf));
+    }
+
+    /// Build an IPv4 header around a payload written by `build`
+    pub(crate) fn build_ipv4(
+        buffer: &mut [u8],
+        src_ip: Ipv4Address,
+        dst_ip: Ipv4Address,
+        protocol: IpProtocol,
+        ttl: u8,
+        build: impl FnOnce(&mut [u8]) -> Option<usize>,
+    ) -> Option<usize> {
+        let mut ip_packet = Ipv4PacketMut::new(buffer)?;
+        ip_packet
+            .init_header()
+            .set_source(src_ip)
+            .set_destination(dst_ip)
+            .set_protocol(protocol)
+            .set_ttl(ttl);
+
+        let payload_len = build(ip_packet.payload_mut())?;
+        ip_packet.finalize(payload_len);
+        Some(ip_packet.total_len())
+    }
+
+    /// Queue a packet on the loopback device
+    fn loopback_output(&self, id: InterfaceId, packet: Vec<u8>) -> bool {
+        let len = packet.len();
+        let (queued, was_empty) = {
+            let mut lo = self.loopback.lock();
+            let was_empty = lo.is_empty();
+            (lo.enqueue(packet), was_empty)
+        };
+        if !queued {
+            self.stats.record_tx_error();
+            return false;
+        }
+
+        if let Some(lo) = self.interfaces.lock().get_mut(id) {
+            lo.record_tx(len);
+        }
+        self.stats.record_tx(len);
+
+        // ネットワークタスクに受信処理を依頼
+        if was_empty {
+            super::endpoint::send_event_ignore(super::endpoint::NetworkEvent::LoopbackRx);
+        }
+        true
+    }
+
+    /// Take the next packet from the loopback device
+    pub fn dequeue_loopback(&self) -> Option<Vec<u8>> {
+        self.loopback.lock().dequeue()
+    }
+
+    /// Resolve the next hop on an interface to a MAC address
+    fn resolve_next_hop(
+        &self,
+        iface: InterfaceId,
+        dst_ip: Ipv4Address,
+        next_hop: Ipv4Address,
+        current_time: u64,
+    ) -> Option<MacAddress> {
+        // Broadcast address
+        if dst_ip.is_broadcast() {
+            return Some(MacAddress::BROADCAST);
+        }
+
+        // Multicast group (ARP 不要)
+        if dst_ip.is_multicast() {
+            return Some(MacAddress::ipv4_multicast(*dst_ip.as_bytes()));
+        }
+
+        // Look up in ARP cache
+        let arp = self.arp.lock();
+        match arp.resolve(next_hop, current_time) {
+            Some(mac) => Some(mac),
+            None => {
+                drop(arp);
+                // Need ARP resolution
+                self.send_arp_request_on(iface, next_hop);
+                None
+            }
+        }
+    }
+
+    /// Bind a UDP socket
+    pub fn bind_udp(&self, port: u16) -> Option<UdpSocket> {
+        self.udp.bind(port)
+    }
+
+    /// Release a UDP port bound with `bind_udp`
+    pub fn unbind_udp(&self, port: u16) {
+        self.udp.sockets().unbind(port);
+    }
+
+    /// Transmit a raw Ethernet frame on the primary NIC
+    pub fn transmit(&self, data: &[u8]) -> bool {
+        self.transmit_on(self.primary, data)
+    }
+
+    /// Transmit a raw Ethernet frame on an interface
+    ///
+    /// VLAN インターフェースはタグを付けて親から、ブリッジは MAC テーブルで選んだポートから送る。
+    pub fn transmit_on(&self, id: InterfaceId, data: &[u8]) -> bool {
+        let link = self.interfaces.lock().get(id).map(|i| (i.kind, i.vlan));
+        match link {
+            Some((InterfaceKind::Vlan, Some(vlan))) => return self.vlan_output(id, vlan, data),
+            Some((InterfaceKind::Bridge, _)) => return self.bridge_output(id, data),
+            _ => {}
+        }
+
+        let sent = match self.interfaces.lock().get_mut(id) {
+            Some(iface) => iface.transmit(data),
+            None => false,
+        };
+
+        if sent {
+            self.stats.record_tx(data.len());
+        } else {
+            self.stats.record_tx_error();
+        }
+        sent
+    }
+
+    /// Record a frame sent through a virtual interface
+    ///
+    /// 戻り値: インターフェースが稼働中なら true
+    fn virtual_output(&self, id: InterfaceId, len: usize) -> bool {
+        let mut interfaces = self.interfaces.lock();
+        match interfaces.get_mut(id) {
+            Some(iface) if iface.up => {
+                iface.record_tx(len);
+                true
+            }
+            Some(iface) => {
+                iface.stats.dropped += 1;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Tag a frame and send it on the VLAN's parent interface
+    fn vlan_output(&self, id: InterfaceId, vlan: VlanLink, data: &[u8]) -> bool {
+        if !self.virtual_output(id, data.len()) {
+            self.stats.record_tx_error();
+            return false;
+        }
+        match insert_vlan_tag(data, VlanTag::new(vlan.vlan_id, vlan.priority)) {
+            Some(tagged) => self.transmit_on(vlan.parent, &tagged),
+            None => {
+                self.stats.record_tx_error();
+                false
+            }
+        }
+    }
+
+    /// Send a frame from the host through a bridge
+    fn bridge_output(&self, id: InterfaceId, data: &[u8]) -> bool {
+        if !self.virtual_output(id, data.len()) {
+            self.stats.record_tx_error();
+            return false;
+        }
+        let now = crate::time::current_tick();
+        let ports = self
+            .bridges
+            .lock()
+            .get_mut(&id)
+            .map(|br| br.output(data, now))
+            .unwrap_or_default();
+        // フラッディングは 1 ポートでも送れれば成功
+        ports
+            .into_iter()
+            .fold(false, |sent, port| self.transmit_on(port, data) || sent)
+    }
+
+    /// Create a VLAN interface on top of an Ethernet interface (initially down)
+    ///
+    /// 名前を省略すると "<親>.<VLAN ID>"（eth0.100 など）。MAC と MTU は親と同じ。
+    pub fn add_vlan(
+        &self,
+        parent: &str,
+        vlan_id: u16,
+        priority: u8,
+        name: Option<&str>,
+    ) -> Result<InterfaceId, InterfaceError> {
+        if vlan_id == 0 || vlan_id > VLAN_ID_MAX || priority > 7 {
+            return Err(InterfaceError::InvalidVlan);
+        }
+        let mut interfaces = self.interfaces.lock();
+        let (parent_id, mac, mtu) = {
+            let p = interfaces.by_name(parent).ok_or(InterfaceError::NotFound)?;
+            if !p.kind.has_link_layer() {
+                return Err(InterfaceError::NotPermitted);
+            }
+            (p.id, p.mac, p.mtu)
+        };
+        if interfaces.vlan_child(parent_id, vlan_id).is_some() {
+            return Err(InterfaceError::AlreadyExists);
+        }
+        let default_name;
+        let name = match name {
+            Some(name) => name,
+            None => {
+                default_name = alloc::format!("{}.{}", parent, vlan_id);
+                &default_name
+            }
+        };
+        let id = interfaces.add(name, InterfaceKind::Vlan, mac, mtu)?;
+        if let Some(iface) = interfaces.get_mut(id) {
+            iface.vlan = Some(VlanLink { parent: parent_id, vlan_id, priority });
+        }
+        Ok(id)
+    }
+
+    /// Create a bridge interface without ports (initially down)
+    ///
+    /// MAC アドレスは最初に追加したポートのものを使う。
+    pub fn add_bridge(&self, name: &str) -> Result<InterfaceId, InterfaceError> {
+        let id = self
+            .interfaces
+            .lock()
+            .add(name, InterfaceKind::Bridge, MacAddress::ZERO, MTU)?;
+        self.bridges.lock().insert(id, Bridge::new(MacAddress::ZERO));
+        Ok(id)
+    }
+
+    /// Add an interface to a bridge
+    ///
+    /// ポートで受信したフレームはすべてブリッジが処理する（ポート自身のアドレスは使われない）。
+    pub fn add_bridge_port(&self, bridge: &str, port: &str) -> Result<(), BridgeError> {
+        let mut interfaces = self.interfaces.lock();
+        let bridge_id = interfaces.by_name(bridge).ok_or(InterfaceError::NotFound)?.id;
+        let (port_id, port_mac) = {
+            let p = interfaces.by_name(port).ok_or(InterfaceError::NotFound)?;
+            if !p.kind.has_link_layer() || p.kind == InterfaceKind::Bridge {
+                return Err(BridgeError::InvalidPort);
+            }
+            if p.master.is_some() {
+                return Err(BridgeError::PortInUse);
+            }
+            (p.id, p.mac)
+        };
+        let mut bridges = self.bridges.lock();
+        let br = bridges.get_mut(&bridge_id).ok_or(BridgeError::NotBridge)?;
+        br.add_port(port_id)?;
+        if br.mac() == MacAddress::ZERO {
+            br.set_mac(port_mac);
+            if let Some(iface) = interfaces.get_mut(bridge_id) {
+                iface.mac = port_mac;
+            }
+        }
+        if let Some(iface) = interfaces.get_mut(port_id) {
+            iface.master = Some(bridge_id);
+        }
+        Ok(())
+    }
+
+    /// Remove an interface from a bridge
+    pub fn remove_bridge_port(&self, bridge: &str, port: &str) -> Result<(), BridgeError> {
+        let mut interfaces = self.interfaces.lock();
+        let bridge_id = interfaces.by_name(bridge).ok_or(InterfaceError::NotFound)?.id;
+        let port_id = interfaces.by_name(port).ok_or(InterfaceError::NotFound)?.id;
+        self.bridges
+            .lock()
+            .get_mut(&bridge_id)
+            .ok_or(BridgeError::NotBridge)?
+            .remove_port(port_id)?;
+        if let Some(iface) = interfaces.get_mut(port_id) {
+            iface.master = None;
+        }
+        Ok(())
+    }
+
+    /// Access a bridge by name
+    pub fn with_bridge<R>(&self, name: &str, f: impl FnOnce(&mut Bridge) -> R) -> Result<R, BridgeError> {
+        let id = self.interface_id(name)?;
+        let mut bridges = self.bridges.lock();
+        let br = bridges.get_mut(&id).ok_or(BridgeError::NotBridge)?;
+        Ok(f(br))
+    }
+
+    /// Names of bridge interfaces
+    pub fn bridge_interfaces(&self) -> Vec<alloc::string::String> {
+        let interfaces = self.interfaces.lock();
+        interfaces
+            .iter()
+            .filter(|i| i.kind == InterfaceKind::Bridge)
+            .map(|i| i.name.clone())
+            .collect()
+    }
+
+    /// Access the interface table
+    pub fn with_interfaces<R>(&self, f: impl FnOnce(&InterfaceTable) -> R) -> R {
+        f(&self.interfaces.lock())
+    }
+
+    /// Add an Ethernet interface (initially down)
+    pub fn add_interface(&self, name: &str, mac: MacAddress, mtu: usize) -> Result<InterfaceId, InterfaceError> {
+        self.interfaces
+            .lock()
+            .add(name, InterfaceKind::Ethernet, mac, mtu)
+    }
+
+    /// Remove an interface and its routes
+    pub fn remove_interface(&self, name: &str) -> Result<(), InterfaceError> {
+        let mut interfaces = self.interfaces.lock();
+        let id = interfaces.by_name(name).ok_or(InterfaceError::NotFound)?.id;
+        if id == self.primary {
+            return Err(InterfaceError::NotPermitted);
+        }
+        // VLAN インターフェースが載っている間は親を削除できない
+        if interfaces.iter().any(|i| i.vlan.is_some_and(|v| v.parent == id)) {
+            return Err(InterfaceError::NotPermitted);
+        }
+        let removed = interfaces.remove(id)?;
+        self.routes.lock().remove_interface(id);
+        self.wireguard.lock().remove(&id);
+        let mut bridges = self.bridges.lock();
+        if let Some(master) = removed.master
+            && let Some(br) = bridges.get_mut(&master)
+        {
+            let _ = br.remove_port(id);
+        }
+        if let Some(br) = bridges.remove(&id) {
+            for &port in br.ports() {
+                if let Some(iface) = interfaces.get_mut(port) {
+                    iface.master = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring an interface up or down
+    pub fn set_interface_up(&self, name: &str, up: bool) -> Result<(), InterfaceError> {
+        let mut interfaces = self.interfaces.lock();
+        let iface = interfaces.by_name_mut(name).ok_or(InterfaceError::NotFound)?;
+        if iface.is_loopback() && !up {
+            return Err(InterfaceError::NotPermitted);
+        }
+        iface.up = up;
+        Ok(())
+    }
+
+    /// Assign an address to an interface (installs the connected route)
+    pub fn add_address(&self, name: &str, addr: InterfaceAddress) -> Result<(), InterfaceError> {
+        let mut interfaces = self.interfaces.lock();
+        let iface = interfaces.by_name_mut(name).ok_or(InterfaceError::NotFound)?;
+        iface.add_address(addr)?;
+        let id = iface.id;
+        let _ = self.routes.lock().add(Route::connected(addr, id));
+        Ok(())
+    }
+
+    /// Remove an address from an interface (removes the connected route)
+    pub fn remove_address(&self, name: &str, addr: Ipv4Address) -> Result<(), InterfaceError> {
+        let mut interfaces = self.interfaces.lock();
+        let iface = interfaces.by_name_mut(name).ok_or(InterfaceError::NotFound)?;
+        if iface.is_loopback() && addr == LOOPBACK_ADDRESS.address {
+            return Err(InterfaceError::NotPermitted);
+        }
+        let removed = iface.remove_address(&addr)?;
+        let id = iface.id;
+        self.routes.lock().remove_connected(removed, id);
+        Ok(())
+    }
+
+    /// Get all routes (most specific first)
+    pub fn routes(&self) -> Vec<Route> {
+        self.routes.lock().routes()
+    }
+
+    /// Add a route
+    pub fn add_route(&self, route: Route) -> Result<(), RouteError> {
+        self.routes.lock().add(route)
+    }
+
+    /// Remove routes to a destination
+    pub fn remove_route(
+        &self,
+        destination: Ipv4Address,
+        prefix_len: u8,
+        interface: Option<InterfaceId>,
+    ) -> Result<usize, RouteError> {
+        self.routes
+            .lock()
+            .remove(destination, prefix_len, interface)
+    }
+
+    /// Get ARP cache entries (for debugging)
+    pub fn arp_cache(&self) -> Vec<(Ipv4Address, MacAddress)> {
+        self.arp
+            .lock()
+            .cache()
+            .all_entries()
             .iter()
             .filter(|e| e.state == super::arp::ArpEntryState::Resolved)
             .map(|e| (e.ip, e.mac))
@@ -676,115 +1898,33 @@ impl NetworkStack {
         // Update dependent processors
         self.ipv4.lock().set_config(config.ipv4.clone());
         self.arp.lock().set_local(config.mac, ip);
+
+        {
+            let mut interfaces = self.interfaces.lock();
+            let mut routes = self.routes.lock();
+            Self::apply_ipv4_config(&mut interfaces, &mut routes, self.primary, &config.ipv4);
+        }
+        drop(config);
+
+        // 新しいアドレスを mDNS でアナウンス
+        self.announce_mdns();
     }
     
     /// Send ICMP echo request (ping)
     pub fn send_icmp_echo_request(&self, target: Ipv4Address, sequence: u16) -> Result<u64, ()> {
-        let local_ip = self.ipv4_address();
         let identifier = 0x1234u16; // Fixed identifier for now
-        
-        // Allocate packet buffer
-        let mut buffer = self.tx_pool.alloc().ok_or(())?;
-        let buf = buffer.as_mut_slice();
-        
-        // Build packet: Ethernet + IPv4 + ICMP
-        let eth_hdr_len = 14;
-        let ip_hdr_len = 20;
-        let icmp_hdr_len = 8; // ICMP echo header
-        let total_len = eth_hdr_len + ip_hdr_len + icmp_hdr_len;
-        
-        if buf.len() < total_len {
-            return Err(());
-        }
-        
-        // Need to resolve target MAC via ARP
-        let current_time = self.current_time();
-        let target_mac = {
-            let arp = self.arp.lock();
-            arp.cache().lookup(target, current_time)
-        };
-        
-        let dst_mac = match target_mac {
-            Some(mac) => mac,
-            None => {
-                // For gateway, use broadcast initially
-                // In a real implementation, we'd send ARP request and wait
-                MacAddress::BROADCAST
-            }
-        };
-        
-        // Build Ethernet header
-        let src_mac = self.mac_address();
-        buf[0..6].copy_from_slice(dst_mac.as_bytes());
-        buf[6..12].copy_from_slice(src_mac.as_bytes());
-        buf[12] = 0x08; // EtherType IPv4
-        buf[13] = 0x00;
-        
-        // Build IPv4 header
-        let ip_start = eth_hdr_len;
-        buf[ip_start] = 0x45; // Version 4, IHL 5
-        buf[ip_start + 1] = 0x00; // DSCP/ECN
-        let total_ip_len = (ip_hdr_len + icmp_hdr_len) as u16;
-        buf[ip_start + 2] = (total_ip_len >> 8) as u8;
-        buf[ip_start + 3] = total_ip_len as u8;
-        buf[ip_start + 4..ip_start + 6].copy_from_slice(&[0x00, 0x00]); // ID
-        buf[ip_start + 6..ip_start + 8].copy_from_slice(&[0x40, 0x00]); // Flags + Fragment
-        buf[ip_start + 8] = 64; // TTL
-        buf[ip_start + 9] = 1; // Protocol: ICMP
-        buf[ip_start + 10..ip_start + 12].copy_from_slice(&[0x00, 0x00]); // Checksum placeholder
-        buf[ip_start + 12..ip_start + 16].copy_from_slice(local_ip.as_bytes());
-        buf[ip_start + 16..ip_start + 20].copy_from_slice(target.as_bytes());
-        
-        // Calculate IP checksum
-        let ip_checksum = Self::checksum(&buf[ip_start..ip_start + ip_hdr_len]);
-        buf[ip_start + 10] = (ip_checksum >> 8) as u8;
-        buf[ip_start + 11] = ip_checksum as u8;
-        
-        // Build ICMP echo request manually
-        let icmp_start = ip_start + ip_hdr_len;
-        buf[icmp_start] = 8; // Type: Echo Request
-        buf[icmp_start + 1] = 0; // Code: 0
-        buf[icmp_start + 2..icmp_start + 4].copy_from_slice(&[0, 0]); // Checksum placeholder
-        buf[icmp_start + 4..icmp_start + 6].copy_from_slice(&identifier.to_be_bytes());
-        buf[icmp_start + 6..icmp_start + 8].copy_from_slice(&sequence.to_be_bytes());
-        
-        // Calculate ICMP checksum
-        let icmp_checksum = Self::checksum(&buf[icmp_start..icmp_start + icmp_hdr_len]);
-        buf[icmp_start + 2] = (icmp_checksum >> 8) as u8;
-        buf[icmp_start + 3] = icmp_checksum as u8;
-        
+
         // Record send time
         let send_time = self.current_time();
-        
-        // Transmit
-        if self.transmit(&buf[..total_len]) {
-            // In a real implementation, we'd wait for echo reply
-            // For now, return estimated RTT
-            Ok(send_time)
-        } else {
-            Err(())
-        }
-    }
-    
-    /// Calculate IP/ICMP checksum
-    fn checksum(data: &[u8]) -> u16 {
-        let mut sum: u32 = 0;
-        let mut i = 0;
-        
-        while i < data.len() - 1 {
-            sum += ((data[i] as u32) << 8) | (data[i + 1] as u32);
-            i += 2;
-        }
-        
-        if i < data.len() {
-            sum += (data[i] as u32) << 8;
-        }
-        
-        while sum > 0xFFFF {
-            sum = (sum & 0xFFFF) + (sum >> 16);
-        }
-        
-        !sum as u16
+
+        let sent = self.route_output(None, target, IpProtocol::Icmp, IcmpEchoHeader::SIZE, |buf, _| {
+            let mut icmp = IcmpEchoBuilder::new(buf)?;
+            icmp.build_request(identifier, sequence);
+            Some(icmp.finalize())
+        });
+
+        // In a real implementation, we'd wait for echo reply
+        if sent { Ok(send_time) } else { Err(()) }
     }
 
     /// Periodic maintenance (call from timer)
@@ -793,6 +1933,13 @@ impl NetworkStack {
 
         // Expire old ARP entries
         self.arp.lock().cache().expire_old(current_time);
+
+        // Expire idle tracked connections
+        self.firewall.lock().expire(crate::time::current_tick());
+        self.nat.lock().expire(crate::time::current_tick());
+
+        // Expire cached mDNS records
+        super::mdns::expire(crate::time::current_tick());
     }
 }
 
@@ -817,11 +1964,94 @@ pub fn stack() -> &'static Mutex<Option<NetworkStack>> {
 
 /// Process a received packet
 pub fn receive(data: &[u8]) {
-    if let Some(ref stack) = *NETWORK_STACK.lock() {
-        stack.receive(data);
+    let pending = match *NETWORK_STACK.lock() {
+        Some(ref stack) => {
+            stack.receive(data);
+            stack.take_pending_tcp()
+        }
+        None => return,
+    };
+    deliver_tcp(pending);
+}
+
+/// Process a packet received on a NIC
+pub fn receive_on(id: InterfaceId, data: &[u8]) {
+    let pending = match *NETWORK_STACK.lock() {
+        Some(ref stack) => {
+            stack.receive_on(id, data);
+            stack.take_pending_tcp()
+        }
+        None => return,
+    };
+    deliver_tcp(pending);
+}
+
+/// Deliver TCP segments to the endpoint layer (stack lock must not be held)
+fn deliver_tcp(segments: Vec<PendingTcpSegment>) {
+    for seg in segments {
+        super::endpoint::process_tcp_segment(*seg.src.as_bytes(), *seg.dst.as_bytes(), &seg.segment);
     }
 }
 
+/// Maximum loopback packets delivered per poll
+const LOOPBACK_POLL_BUDGET: usize = 64;
+
+/// Loopback polling in progress (prevents nested draining)
+static LOOPBACK_POLLING: AtomicBool = AtomicBool::new(false);
+
+/// Deliver packets queued on the loopback device
+///
+/// ネットワークタスク（LoopbackRxイベント）から呼ばれる。
+/// 戻り値: 処理したパケット数
+pub fn poll_loopback() -> usize {
+    if LOOPBACK_POLLING.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+
+    let mut delivered = 0;
+    while delivered < LOOPBACK_POLL_BUDGET {
+        let pending = {
+            let guard = NETWORK_STACK.lock();
+            let Some(ref stack) = *guard else {
+                break;
+            };
+            let Some(packet) = stack.dequeue_loopback() else {
+                break;
+            };
+            stack.receive_loopback(&packet);
+            stack.take_pending_tcp()
+        };
+        delivered += 1;
+        deliver_tcp(pending);
+    }
+
+    LOOPBACK_POLLING.store(false, Ordering::Release);
+
+    // 予算を使い切った場合は残りを次回に回す
+    if delivered == LOOPBACK_POLL_BUDGET {
+        super::endpoint::send_event_ignore(super::endpoint::NetworkEvent::LoopbackRx);
+    }
+    delivered
+}
+
+/// Run WireGuard timers
+///
+/// タイマータスクから呼ばれる。戻り値: WireGuard インターフェースがあれば true
+pub fn poll_wireguard() -> bool {
+    NETWORK_STACK
+        .lock()
+        .as_ref()
+        .is_some_and(|s| s.wireguard_timers())
+}
+
+/// Select the source address for a destination
+pub fn select_source(dst_ip: Ipv4Address) -> Option<Ipv4Address> {
+    NETWORK_STACK
+        .lock()
+        .as_ref()
+        .and_then(|s| s.select_source(dst_ip))
+}
+
 /// Send a UDP datagram
 pub fn send_udp(src_port: u16, dst_ip: Ipv4Address, dst_port: u16, data: &[u8]) -> bool {
     if let Some(ref stack) = *NETWORK_STACK.lock() {
@@ -845,6 +2075,13 @@ pub fn bind_udp(port: u16) -> Option<UdpSocket> {
     NETWORK_STACK.lock().as_ref().and_then(|s| s.bind_udp(port))
 }
 
+/// Release a UDP port
+pub fn unbind_udp(port: u16) {
+    if let Some(ref stack) = *NETWORK_STACK.lock() {
+        stack.unbind_udp(port);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -860,4 +2097,356 @@ mod tests {
         );
         assert!(config.icmp_echo_enabled);
     }
+
+    fn test_config() -> NetworkConfig {
+        NetworkConfig {
+            ipv4: Ipv4Config {
+                address: Ipv4Address::new([10, 0, 2, 15]),
+                subnet_mask: Ipv4Address::new([255, 255, 255, 0]),
+                gateway: Ipv4Address::new([10, 0, 2, 2]),
+                dns: None,
+            },
+            ..NetworkConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_loopback_and_primary_interface() {
+        let stack = NetworkStack::new(test_config());
+
+        let names: Vec<_> = stack.with_interfaces(|t| t.iter().map(|i| i.name.clone()).collect());
+        assert_eq!(names, ["lo", "eth0"]);
+
+        let route = stack.route(Ipv4Address::LOOPBACK).unwrap();
+        assert_eq!(route.interface, LOOPBACK_ID);
+        let route = stack.route(Ipv4Address::new([8, 8, 8, 8])).unwrap();
+        assert_eq!(route.next_hop, Ipv4Address::new([10, 0, 2, 2]));
+        assert_eq!(route.source, Ipv4Address::new([10, 0, 2, 15]));
+
+        // DHCPによるアドレス変更で接続経路も置き換わる
+        stack.update_ip(Ipv4Address::new([10, 0, 2, 16]));
+        assert_eq!(
+            stack.select_source(Ipv4Address::new([10, 0, 2, 99])),
+            Some(Ipv4Address::new([10, 0, 2, 16]))
+        );
+        assert!(stack.route(Ipv4Address::new([10, 0, 2, 15])).unwrap().interface != LOOPBACK_ID);
+    }
+
+    #[test]
+    fn test_udp_over_loopback() {
+        let stack = NetworkStack::new(test_config());
+        let socket = stack.bind_udp(7007).unwrap();
+
+        assert!(stack.send_udp(5000, Ipv4Address::LOOPBACK, 7007, b"hello"));
+        // 自ホストのNICアドレス宛てもループバック経由
+        assert!(stack.send_udp(5000, Ipv4Address::new([10, 0, 2, 15]), 7007, b"world"));
+
+        while let Some(packet) = stack.dequeue_loopback() {
+            stack.receive_loopback(&packet);
+        }
+        assert_eq!(socket.rx_queue_len(), 2);
+
+        let lo = stack.with_interfaces(|t| t.get(LOOPBACK_ID).unwrap().stats);
+        assert_eq!(lo.tx_packets, 2);
+        assert_eq!(lo.rx_packets, 2);
+    }
+
+    #[test]
+    fn test_tcp_both_ways_over_loopback() {
+        use crate::net::endpoint::tests::loopback;
+
+        let lo_stats = || {
+            let guard = stack().lock();
+            guard.as_ref().unwrap().with_interfaces(|t| t.get(LOOPBACK_ID).unwrap().stats)
+        };
+        let _guard = loopback::setup();
+        let lo_before = lo_stats();
+        let (client, server, _listener) = loopback::connect(18083);
+
+        // 双方が同時に複数セグメント分を書く
+        let upload: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let download: Vec<u8> = (0..30_000u32).map(|i| (i / 3) as u8).collect();
+        let (mut up_sent, mut down_sent) = (0, 0);
+        let (mut up_received, mut down_received) = (Vec::new(), Vec::new());
+        let mut buffer = [0u8; 2048];
+        for _ in 0..500 {
+            if up_sent < upload.len() {
+                up_sent += client.send(&upload[up_sent..]).unwrap_or(0);
+            }
+            if down_sent < download.len() {
+                down_sent += server.send(&download[down_sent..]).unwrap_or(0);
+            }
+            loopback::step();
+            while let Ok(n) = server.recv(&mut buffer) {
+                up_received.extend_from_slice(&buffer[..n]);
+            }
+            while let Ok(n) = client.recv(&mut buffer) {
+                down_received.extend_from_slice(&buffer[..n]);
+            }
+            if up_received.len() == upload.len() && down_received.len() == download.len() {
+                break;
+            }
+        }
+        assert_eq!(up_received, upload);
+        assert_eq!(down_received, download);
+
+        // 全てループバックデバイスを経由した
+        let lo = lo_stats();
+        assert!(lo.tx_packets - lo_before.tx_packets >= 30);
+        assert_eq!(lo.tx_packets - lo_before.tx_packets, lo.rx_packets - lo_before.rx_packets);
+    }
+
+    #[test]
+    fn test_firewall_input_and_output_hooks() {
+        use crate::net::firewall::FirewallRule;
+
+        let stack = NetworkStack::new(test_config());
+        let socket = stack.bind_udp(7007).unwrap();
+        stack.with_firewall(|fw| {
+            fw.add_rule(FirewallRule::parse("output udp dport 9 drop").unwrap()).unwrap();
+            fw.add_rule(FirewallRule::parse("input udp iface lo dport 7007 src 127.0.0.2 drop").unwrap())
+                .unwrap();
+        });
+
+        // output チェーンで破棄された送信は失敗する
+        assert!(!stack.send_udp(5000, Ipv4Address::LOOPBACK, 9, b"discard"));
+        assert!(stack.send_udp(5000, Ipv4Address::LOOPBACK, 7007, b"hello"));
+        while let Some(packet) = stack.dequeue_loopback() {
+            stack.receive_loopback(&packet);
+        }
+        assert_eq!(socket.rx_queue_len(), 1);
+
+        let counters: Vec<u64> = stack.with_firewall(|fw| fw.rules().iter().map(|r| r.packets).collect());
+        assert_eq!(counters, [1, 0]);
+        // 送信（output）と受信（input）の双方が同じフローとして追跡される
+        assert_eq!(stack.with_firewall(|fw| fw.conntrack().len()), 1);
+    }
+
+    /// 送信フレームの記録先（test_mdns_and_igmp_over_multicast 専用）
+    static SENT_FRAMES: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+    fn record_frame(frame: &[u8]) -> bool {
+        SENT_FRAMES.lock().push(frame.to_vec());
+        true
+    }
+
+    /// リンク上の別ホスト (10.0.2.20) からのマルチキャストフレームを組み立てる
+    fn multicast_frame(
+        group: Ipv4Address,
+        protocol: IpProtocol,
+        build: impl FnOnce(&mut [u8]) -> Option<usize>,
+    ) -> Vec<u8> {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let mut frame = EthernetFrameMut::new(&mut buffer).unwrap();
+        frame
+            .set_destination(MacAddress::ipv4_multicast(*group.as_bytes()))
+            .set_source(MacAddress::from_octets(0x52, 0x54, 0, 0, 0, 0x20))
+            .set_ether_type(EtherType::Ipv4);
+        let src = Ipv4Address::new([10, 0, 2, 20]);
+        let len = NetworkStack::build_ipv4(frame.payload_mut(), src, group, protocol, 255, build).unwrap();
+        frame.set_payload_len(len);
+        frame.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_mdns_and_igmp_over_multicast() {
+        use crate::net::igmp::IgmpType;
+        use crate::net::ipv4::Ipv4Packet;
+        use crate::net::mdns::{Message, Question, RecordData, message::TYPE_A};
+
+        let stack = NetworkStack::new(test_config());
+        stack.set_transmit_fn(record_frame);
+        SENT_FRAMES.lock().clear();
+
+        // ranyos.local の A 問い合わせにマルチキャストで応答する
+        let mut query = Message::query();
+        query.questions.push(Question::new("ranyos.local", TYPE_A));
+        let query = query.encode();
+        let src = Ipv4Address::new([10, 0, 2, 20]);
+        stack.receive(&multicast_frame(MDNS_GROUP, IpProtocol::Udp, |buf| {
+            UdpProcessor::build_packet(buf, src, MDNS_PORT, MDNS_GROUP, MDNS_PORT, &query)
+        }));
+
+        let sent = core::mem::take(&mut *SENT_FRAMES.lock());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(&sent[0][..6], MacAddress::ipv4_multicast([224, 0, 0, 251]).as_bytes());
+        let ip = Ipv4Packet::parse(&sent[0][EthernetHeader::SIZE..]).unwrap();
+        assert_eq!(ip.destination(), MDNS_GROUP);
+        assert_eq!(ip.ttl(), MDNS_IP_TTL);
+        let udp = UdpPacket::parse(ip.payload()).unwrap();
+        let response = Message::parse(udp.payload()).unwrap();
+        assert_eq!(response.answers[0].data, RecordData::A(Ipv4Address::new([10, 0, 2, 15])));
+
+        // IGMP 一般クエリには参加中グループの Report を返す
+        stack.join_multicast("eth0", Ipv4Address::new([239, 1, 2, 3])).unwrap();
+        SENT_FRAMES.lock().clear();
+        let general_query = IgmpMessage {
+            kind: IgmpType::MembershipQuery,
+            max_resp_time: 100,
+            group: Ipv4Address::ANY,
+        };
+        stack.receive(&multicast_frame(Ipv4Address::ALL_HOSTS, IpProtocol::Igmp, |buf| {
+            general_query.write(buf)
+        }));
+        let reported: Vec<Ipv4Address> = SENT_FRAMES
+            .lock()
+            .iter()
+            .filter_map(|frame| {
+                let ip = Ipv4Packet::parse(&frame[EthernetHeader::SIZE..])?;
+                assert_eq!(ip.ttl(), IGMP_TTL);
+                IgmpMessage::parse(ip.payload()).map(|m| m.group)
+            })
+            .collect();
+        assert_eq!(reported, [MDNS_GROUP, Ipv4Address::new([239, 1, 2, 3])]);
+    }
+
+    #[test]
+    fn test_secondary_address_and_routes() {
+        let stack = NetworkStack::new(test_config());
+        let secondary = InterfaceAddress::new(Ipv4Address::new([192, 168, 50, 1]), 24);
+        stack.add_address("eth0", secondary).unwrap();
+
+        // 追加アドレス宛てのパケットを受理し、同一サブネット宛ての送信元に選ばれる
+        assert!(stack.with_interfaces(|t| t.accepts(&secondary.address)));
+        assert_eq!(
+            stack.select_source(Ipv4Address::new([192, 168, 50, 9])),
+            Some(secondary.address)
+        );
+
+        stack
+            .add_route(Route::new(
+                Ipv4Address::new([172, 16, 0, 0]),
+                12,
+                Some(Ipv4Address::new([192, 168, 50, 254])),
+                stack.primary,
+                10,
+            ))
+            .unwrap();
+        let route = stack.route(Ipv4Address::new([172, 20, 1, 1])).unwrap();
+        assert_eq!(route.next_hop, Ipv4Address::new([192, 168, 50, 254]));
+        assert_eq!(route.source, secondary.address);
+
+        stack.remove_address("eth0", secondary.address).unwrap();
+        assert!(!stack.with_interfaces(|t| t.is_local(&secondary.address)));
+        assert_eq!(
+            stack.remove_address("lo", Ipv4Address::LOOPBACK),
+            Err(InterfaceError::NotPermitted)
+        );
+    }
+
+    /// 送信フレームの記録先（test_forwarding_with_masquerade 専用）
+    static WAN_FRAMES: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+    static LAN_FRAMES: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+    fn record_wan(frame: &[u8]) -> bool {
+        WAN_FRAMES.lock().push(frame.to_vec());
+        true
+    }
+
+    fn record_lan(frame: &[u8]) -> bool {
+        LAN_FRAMES.lock().push(frame.to_vec());
+        true
+    }
+
+    /// `dst_mac` 宛ての UDP フレーム
+    #[allow(clippy::too_many_arguments)]
+    fn udp_frame(
+        src_mac: MacAddress,
+        dst_mac: MacAddress,
+        src: Ipv4Address,
+        src_port: u16,
+        dst: Ipv4Address,
+        dst_port: u16,
+        ttl: u8,
+    ) -> Vec<u8> {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        let mut frame = EthernetFrameMut::new(&mut buffer).unwrap();
+        frame
+            .set_destination(dst_mac)
+            .set_source(src_mac)
+            .set_ether_type(EtherType::Ipv4);
+        let len = NetworkStack::build_ipv4(frame.payload_mut(), src, dst, IpProtocol::Udp, ttl, |buf| {
+            UdpProcessor::build_packet(buf, src, src_port, dst, dst_port, b"query")
+        })
+        .unwrap();
+        frame.set_payload_len(len);
+        frame.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_forwarding_with_masquerade() {
+        let stack = NetworkStack::new(test_config());
+        let lan_mac = MacAddress::from_octets(0x02, 0, 0, 0, 0, 0x02);
+        let lan = stack.add_interface("eth1", lan_mac, MTU).unwrap();
+        stack.set_interface_up("eth1", true).unwrap();
+        let router_lan = Ipv4Address::new([192, 168, 50, 1]);
+        stack.add_address("eth1", InterfaceAddress::new(router_lan, 24)).unwrap();
+        stack.set_transmit_fn(record_wan);
+        stack.set_interface_transmit_fn(lan, record_lan);
+        WAN_FRAMES.lock().clear();
+        LAN_FRAMES.lock().clear();
+
+        let host = Ipv4Address::new([192, 168, 50, 10]);
+        let host_mac = MacAddress::from_octets(0x52, 0x54, 0, 0, 0, 0x10);
+        let gateway_mac = MacAddress::from_octets(0x52, 0x54, 0, 0, 0, 0x02);
+        let remote = Ipv4Address::new([93, 184, 216, 34]);
+
+        // LAN 側の ARP 要求には eth1 の MAC で応答する
+        let mut buffer = [0u8; 64];
+        let mut frame = EthernetFrameMut::new(&mut buffer).unwrap();
+        frame
+            .set_destination(MacAddress::BROADCAST)
+            .set_source(host_mac)
+            .set_ether_type(EtherType::Arp);
+        let len = ArpProcessor::new(host_mac, host)
+            .build_request(frame.payload_mut(), router_lan)
+            .unwrap();
+        frame.set_payload_len(len);
+        frame.pad_to_minimum();
+        stack.receive_on(lan, frame.as_bytes());
+        let replies = core::mem::take(&mut *LAN_FRAMES.lock());
+        assert_eq!(replies.len(), 1);
+        assert_eq!(&replies[0][..6], host_mac.as_bytes());
+        assert_eq!(&replies[0][6..12], lan_mac.as_bytes());
+        stack.arp.lock().cache().insert(Ipv4Address::new([10, 0, 2, 2]), gateway_mac, 0);
+
+        // 転送が無効なら破棄
+        let outbound = udp_frame(host_mac, lan_mac, host, 5000, remote, 53, 64);
+        stack.receive_on(lan, &outbound);
+        assert!(WAN_FRAMES.lock().is_empty());
+
+        // 送信元を eth0 のアドレスに変換し、TTL を減らしてゲートウェイへ
+        stack.set_forwarding(true);
+        stack.with_nat(|nat| nat.add_masquerade("eth0"));
+        stack.receive_on(lan, &outbound);
+        let sent = core::mem::take(&mut *WAN_FRAMES.lock());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(&sent[0][..6], gateway_mac.as_bytes());
+        let ip = Ipv4Packet::parse(&sent[0][EthernetHeader::SIZE..]).unwrap();
+        assert!(ip.verify_checksum());
+        assert_eq!((ip.source(), ip.destination(), ip.ttl()), (Ipv4Address::new([10, 0, 2, 15]), remote, 63));
+        let port = UdpPacket::parse(ip.payload()).unwrap().src_port();
+
+        // 応答は LAN のホストへ戻る
+        let eth0_mac = stack.mac_address();
+        stack.receive(&udp_frame(gateway_mac, eth0_mac, remote, 53, Ipv4Address::new([10, 0, 2, 15]), port, 60));
+        let delivered = core::mem::take(&mut *LAN_FRAMES.lock());
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(&delivered[0][6..12], lan_mac.as_bytes());
+        let ip = Ipv4Packet::parse(&delivered[0][EthernetHeader::SIZE..]).unwrap();
+        assert_eq!((ip.destination(), ip.ttl()), (host, 59));
+        let udp = UdpPacket::parse(ip.payload()).unwrap();
+        assert_eq!((udp.dst_port(), udp.payload()), (5000, &b"query"[..]));
+
+        // TTL 切れは ICMP Time Exceeded を受信インターフェースのアドレスから返す
+        stack.receive_on(lan, &udp_frame(host_mac, lan_mac, host, 5001, remote, 53, 1));
+        let errors = core::mem::take(&mut *LAN_FRAMES.lock());
+        assert_eq!(errors.len(), 1);
+        let ip = Ipv4Packet::parse(&errors[0][EthernetHeader::SIZE..]).unwrap();
+        assert_eq!((ip.source(), ip.destination()), (router_lan, host));
+        assert_eq!(ip.payload()[0], 11);
+        assert!(WAN_FRAMES.lock().is_empty());
+        assert_eq!(stack.forward_stats().forwarded.load(Ordering::Relaxed), 2);
+        assert_eq!(stack.forward_stats().ttl_exceeded.load(Ordering::Relaxed), 1);
+    }
 }
@@ -107,7 +107,30 @@ pub struct TcpStats {
     pub packets_sent: u64,
     pub packets_received: u64,
     pub retransmissions: u64,
+    /// PAWSで破棄したセグメント数
+    pub paws_rejected: u64,
+    /// 最新のRTTサンプル（マイクロ秒）
     pub rtt_us: u64,
+
+    // 輻輳制御
+    /// 輻輳制御アルゴリズム名
+    pub congestion_algorithm: &'static str,
+    /// 輻輳ウィンドウ（バイト）
+    pub cwnd: u32,
+    /// スロースタート閾値（バイト）
+    pub ssthresh: u32,
+    /// 送信中のバイト数
+    pub bytes_in_flight: u32,
+    /// ペーシングレート（バイト/秒、0 = 未算出）
+    pub pacing_rate: u64,
+    /// 平滑化RTT（マイクロ秒）
+    pub srtt_us: u64,
+    /// 最小RTT（マイクロ秒、0 = 未測定）
+    pub min_rtt_us: u64,
+    /// RTTサンプル総数
+    pub rtt_samples: u64,
+    /// 直近のRTTサンプル（マイクロ秒、古い順）
+    pub recent_rtt_us: Vec<u64>,
 }
 
 // ============================================================================
@@ -142,8 +142,9 @@ impl<'a> UdpPacket<'a> {
         let pseudo = pseudo_header_checksum(src_ip, dst_ip, IpProtocol::Udp, length);
 
         // Include the checksum in the data for verification
+        // (data_checksumは補数を返すため、正しいパケットでは0になる)
         let actual_checksum = data_checksum(&self.data[..length as usize], pseudo);
-        actual_checksum == 0xFFFF
+        actual_checksum == 0
     }
 }
 
@@ -444,6 +445,11 @@ impl UdpSocketTable {
         None
     }
 
+    /// Check whether a port is bound
+    pub fn is_bound(&self, port: u16) -> bool {
+        self.find(port).is_some()
+    }
+
     /// Deliver a datagram to the appropriate socket
     pub fn deliver(&self, datagram: UdpDatagram) -> bool {
         use core::sync::atomic::Ordering;
@@ -0,0 +1,901 @@
+//! # WireGuard - 暗号化トンネル
+//!
+//! カーネル間を結ぶ点対点の VPN インターフェース（`wg0` など）。
+//! プロトコルは WireGuard（Noise IK + Curve25519 / ChaCha20-Poly1305 / BLAKE2s）で、
+//! Linux の `wg` と相互に接続できる。
+//!
+//! - `noise`: ハンドシェイクメッセージの生成と検証
+//! - `cookie`: mac1 / mac2 とクッキー応答による DoS 対策
+//! - `allowed_ips`: 宛先からピアを選び、復号したパケットの送信元を確かめる表
+//! - `peer`: ピアごとのセッションとタイマー
+//! - `replay`: 受信カウンタのリプレイ防止窓
+//!
+//! 本体（`WireGuard`）はパケットを受け取り、送るべき UDP データグラムと
+//! 受信した内側のパケットを返すだけで、送受信は `NetworkStack` が行う。
+//! スタックはトンネルを `InterfaceKind::WireGuard` のインターフェースとして持ち、
+//! 経路がそのインターフェースを指すパケットを `encapsulate` し、待ち受けポート宛ての
+//! UDP を `decapsulate` して内側のパケットをそのインターフェースで受信する。
+//! 再送・キープアライブ・鍵の更新は `start_timers` のタスクが `tick` を呼んで進める。
+
+pub mod allowed_ips;
+pub mod cookie;
+pub mod crypto;
+pub mod noise;
+pub mod peer;
+pub mod replay;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use self::allowed_ips::AllowedIps;
+use self::cookie::{CookieChecker, LoadMonitor};
+use self::crypto::Key;
+use self::noise::{
+    COOKIE_REPLY_LEN, INITIATION_LEN, MESSAGE_COOKIE_REPLY, MESSAGE_INITIATION, MESSAGE_RESPONSE,
+    MESSAGE_TRANSPORT, RESPONSE_LEN, StaticIdentity, TRANSPORT_MIN_LEN,
+};
+use self::peer::{PendingHandshake, Peer, REKEY_ATTEMPT_TIME_MS, REKEY_TIMEOUT_MS};
+use super::interface::{InterfaceAddress, InterfaceError};
+use super::ipv4::Ipv4Packet;
+use super::udp::UdpAddr;
+
+/// 既定の待ち受けポート
+pub const DEFAULT_LISTEN_PORT: u16 = 51820;
+
+/// トンネルの MTU（1500 - IPv4 20 - UDP 8 - WireGuard 32）
+pub const WIREGUARD_MTU: usize = 1420;
+
+/// 1 つのインターフェースに登録できるピア数
+pub const MAX_PEERS: usize = 256;
+
+/// 同じピアからの Initiation を受け付ける最短間隔
+const MIN_INITIATION_INTERVAL_MS: u64 = 20;
+
+/// 内側のパケットを埋める単位
+const PADDING_MULTIPLE: usize = 16;
+
+/// タイマーを進める間隔
+const TIMER_INTERVAL_MS: u64 = 250;
+
+/// WireGuard errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WgError {
+    /// Creating or looking up the interface failed
+    Interface(InterfaceError),
+    /// Interface exists but is not a WireGuard tunnel
+    NotWireGuard,
+    /// Listen port already used by a socket or another tunnel
+    PortInUse,
+    /// Peer is not configured
+    PeerNotFound,
+    /// Too many peers on the interface
+    TooManyPeers,
+    /// Peer public key equals the interface's own
+    InvalidKey,
+    /// No peer's allowed IPs contain the destination
+    NoRoute,
+    /// Peer has no known endpoint
+    NoEndpoint,
+    /// Malformed message or inner packet
+    InvalidMessage,
+    /// mac1 does not match our public key
+    InvalidMac,
+    /// Decryption or handshake verification failed
+    Unauthenticated,
+    /// Replayed counter or handshake timestamp
+    Replay,
+    /// Handshake rate limit exceeded
+    RateLimited,
+    /// Inner source address is outside the peer's allowed IPs
+    SourceNotAllowed,
+    /// Session keys are too old to use
+    KeyExpired,
+    /// No hardware random number generator for keys and nonces
+    NoEntropy,
+}
+
+impl fmt::Display for WgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WgError::Interface(e) => write!(f, "{}", e),
+            WgError::NotWireGuard => write!(f, "Not a WireGuard interface"),
+            WgError::PortInUse => write!(f, "Listen port already in use"),
+            WgError::PeerNotFound => write!(f, "No such peer"),
+            WgError::TooManyPeers => write!(f, "Too many peers (max {})", MAX_PEERS),
+            WgError::InvalidKey => write!(f, "Peer key equals the interface key"),
+            WgError::NoRoute => write!(f, "No peer for destination"),
+            WgError::NoEndpoint => write!(f, "Peer has no endpoint"),
+            WgError::InvalidMessage => write!(f, "Malformed message"),
+            WgError::InvalidMac => write!(f, "Invalid mac1"),
+            WgError::Unauthenticated => write!(f, "Authentication failed"),
+            WgError::Replay => write!(f, "Replayed message"),
+            WgError::RateLimited => write!(f, "Handshake rate limited"),
+            WgError::SourceNotAllowed => write!(f, "Source address not allowed for peer"),
+            WgError::KeyExpired => write!(f, "Session key expired"),
+            WgError::NoEntropy => write!(f, "Hardware random number generator unavailable"),
+        }
+    }
+}
+
+impl From<InterfaceError> for WgError {
+    fn from(e: InterfaceError) -> Self {
+        WgError::Interface(e)
+    }
+}
+
+/// Peer configuration (`wg set ... peer`)
+///
+/// 既存のピアに適用すると、指定した項目だけを更新し、許可アドレスは追加する。
+#[derive(Debug, Clone)]
+pub struct PeerConfig {
+    /// Peer public key
+    pub public_key: Key,
+    /// Pre-shared key
+    pub preshared_key: Option<Key>,
+    /// Endpoint
+    pub endpoint: Option<UdpAddr>,
+    /// Allowed source / destination prefixes
+    pub allowed_ips: Vec<InterfaceAddress>,
+    /// Persistent keepalive interval in seconds (0 disables)
+    pub persistent_keepalive: Option<u16>,
+}
+
+impl PeerConfig {
+    /// Configure a peer by public key
+    pub fn new(public_key: Key) -> Self {
+        PeerConfig {
+            public_key,
+            preshared_key: None,
+            endpoint: None,
+            allowed_ips: Vec::new(),
+            persistent_keepalive: None,
+        }
+    }
+
+    /// Set the endpoint
+    pub fn endpoint(mut self, endpoint: UdpAddr) -> Self {
+        self.endpoint = Some(endpoint);
+        self
+    }
+
+    /// Add an allowed prefix
+    pub fn allowed_ip(mut self, prefix: InterfaceAddress) -> Self {
+        self.allowed_ips.push(prefix);
+        self
+    }
+
+    /// Set the pre-shared key
+    pub fn preshared_key(mut self, key: Key) -> Self {
+        self.preshared_key = Some(key);
+        self
+    }
+
+    /// Set the persistent keepalive interval (seconds)
+    pub fn persistent_keepalive(mut self, seconds: u16) -> Self {
+        self.persistent_keepalive = Some(seconds);
+        self
+    }
+}
+
+/// UDP datagram to send to a peer
+#[derive(Debug, Clone)]
+pub struct Datagram {
+    /// Destination
+    pub endpoint: UdpAddr,
+    /// WireGuard message
+    pub data: Vec<u8>,
+}
+
+/// Result of processing a received datagram
+#[derive(Debug, Default)]
+pub struct Decapsulated {
+    /// Inner IPv4 packet to receive on the tunnel interface
+    pub packet: Option<Vec<u8>>,
+    /// Messages to send in response (handshake, cookie reply, staged packets)
+    pub datagrams: Vec<Datagram>,
+}
+
+/// Tunnel counters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WgStats {
+    /// Completed handshakes
+    pub handshakes: u64,
+    /// Handshake attempts abandoned after REKEY_ATTEMPT_TIME
+    pub handshake_timeouts: u64,
+    /// Cookie replies sent while under load
+    pub cookie_replies: u64,
+    /// Messages dropped for a bad mac1
+    pub invalid_mac: u64,
+    /// Messages that failed authentication
+    pub unauthenticated: u64,
+    /// Replayed messages
+    pub replayed: u64,
+    /// Handshakes dropped by the rate limiter
+    pub rate_limited: u64,
+    /// Inner packets from outside the peer's allowed IPs
+    pub source_not_allowed: u64,
+    /// Outgoing packets with no matching peer
+    pub no_route: u64,
+}
+
+/// WireGuard tunnel (one interface)
+pub struct WireGuard {
+    identity: StaticIdentity,
+    listen_port: u16,
+    mtu: usize,
+    peers: BTreeMap<Key, Peer>,
+    allowed_ips: AllowedIps<Key>,
+    /// 自分のインデックス → ピア
+    indices: BTreeMap<u32, Key>,
+    checker: CookieChecker,
+    load: LoadMonitor,
+    stats: WgStats,
+}
+
+impl WireGuard {
+    /// Create a tunnel with a private key and UDP listen port
+    pub fn new(private_key: Key, listen_port: u16) -> Self {
+        let identity = StaticIdentity::new(private_key);
+        WireGuard {
+            checker: CookieChecker::new(&identity.public),
+            identity,
+            listen_port,
+            mtu: WIREGUARD_MTU,
+            peers: BTreeMap::new(),
+            allowed_ips: AllowedIps::new(),
+            indices: BTreeMap::new(),
+            load: LoadMonitor::new(),
+            stats: WgStats::default(),
+        }
+    }
+
+    /// Interface public key
+    pub fn public_key(&self) -> &Key {
+        &self.identity.public
+    }
+
+    /// UDP listen port
+    pub fn listen_port(&self) -> u16 {
+        self.listen_port
+    }
+
+    /// Counters
+    pub fn stats(&self) -> &WgStats {
+        &self.stats
+    }
+
+    /// Configured peers
+    pub fn peers(&self) -> impl Iterator<Item = &Peer> {
+        self.peers.values()
+    }
+
+    /// Look up a peer
+    pub fn peer(&self, public_key: &Key) -> Option<&Peer> {
+        self.peers.get(public_key)
+    }
+
+    /// Allowed IPs of a peer
+    pub fn allowed_ips_of(&self, public_key: &Key) -> Vec<InterfaceAddress> {
+        self.allowed_ips.prefixes_of(public_key).collect()
+    }
+
+    /// Add a peer or update an existing one
+    pub fn set_peer(&mut self, config: PeerConfig) -> Result<(), WgError> {
+        if config.public_key == self.identity.public {
+            return Err(WgError::InvalidKey);
+        }
+        if !self.peers.contains_key(&config.public_key) && self.peers.len() >= MAX_PEERS {
+            return Err(WgError::TooManyPeers);
+        }
+        let peer = self
+            .peers
+            .entry(config.public_key)
+            .or_insert_with(|| Peer::new(config.public_key));
+        if let Some(key) = config.preshared_key {
+            peer.preshared_key = key;
+        }
+        if let Some(endpoint) = config.endpoint {
+            peer.endpoint = Some(endpoint);
+        }
+        if let Some(seconds) = config.persistent_keepalive {
+            peer.persistent_keepalive = seconds;
+        }
+        for prefix in config.allowed_ips {
+            self.allowed_ips.insert(prefix, config.public_key);
+        }
+        Ok(())
+    }
+
+    /// Remove a peer and its allowed IPs
+    pub fn remove_peer(&mut self, public_key: &Key) -> Result<(), WgError> {
+        self.peers.remove(public_key).ok_or(WgError::PeerNotFound)?;
+        self.allowed_ips.remove_value(public_key);
+        self.indices.retain(|_, key| key != public_key);
+        Ok(())
+    }
+
+    /// Encrypt an outgoing IPv4 packet for the peer that owns its destination
+    ///
+    /// 鍵が無ければパケットを保持して Initiation を返す（応答を受けた時点で送る）。
+    pub fn encapsulate(&mut self, packet: &[u8], now_ms: u64) -> Result<Vec<Datagram>, WgError> {
+        let ip = Ipv4Packet::parse(packet).ok_or(WgError::InvalidMessage)?;
+        let Some(&key) = self.allowed_ips.lookup(&ip.destination()) else {
+            self.stats.no_route += 1;
+            return Err(WgError::NoRoute);
+        };
+        let peer = self.peers.get_mut(&key).ok_or(WgError::NoRoute)?;
+        if peer.endpoint.is_none() {
+            return Err(WgError::NoEndpoint);
+        }
+
+        if peer.has_session(now_ms) {
+            let mut datagrams = Vec::new();
+            datagrams.extend(self.send_data(&key, ip.as_bytes(), now_ms));
+            if self.rekey_on_send(&key, now_ms) {
+                datagrams.extend(self.begin_handshake(&key, now_ms)?);
+            }
+            return Ok(datagrams);
+        }
+
+        peer.stage(ip.as_bytes());
+        if peer.handshake_pending() {
+            return Ok(Vec::new());
+        }
+        Ok(self.begin_handshake(&key, now_ms)?.into_iter().collect())
+    }
+
+    /// Process a datagram received on the listen port
+    pub fn decapsulate(
+        &mut self,
+        src: UdpAddr,
+        data: &[u8],
+        now_ms: u64,
+    ) -> Result<Decapsulated, WgError> {
+        let kind = noise::message_type(data).ok_or(WgError::InvalidMessage)?;
+        let result = match (kind, data.len()) {
+            (MESSAGE_INITIATION, INITIATION_LEN) | (MESSAGE_RESPONSE, RESPONSE_LEN) => {
+                self.check_handshake(src, data, now_ms)
+                    .and_then(|cookie| match cookie {
+                        Some(reply) => Ok(reply),
+                        None if kind == MESSAGE_INITIATION => {
+                            self.handle_initiation(src, data, now_ms)
+                        }
+                        None => self.handle_response(src, data, now_ms),
+                    })
+            }
+            (MESSAGE_COOKIE_REPLY, COOKIE_REPLY_LEN) => self.handle_cookie_reply(data, now_ms),
+            (MESSAGE_TRANSPORT, len) if len >= TRANSPORT_MIN_LEN => {
+                self.handle_transport(src, data, now_ms)
+            }
+            _ => Err(WgError::InvalidMessage),
+        };
+        if let Err(e) = result {
+            let counter = match e {
+                WgError::InvalidMac => &mut self.stats.invalid_mac,
+                WgError::Unauthenticated => &mut self.stats.unauthenticated,
+                WgError::Replay => &mut self.stats.replayed,
+                WgError::RateLimited => &mut self.stats.rate_limited,
+                WgError::SourceNotAllowed => &mut self.stats.source_not_allowed,
+                _ => return result,
+            };
+            *counter += 1;
+        }
+        result
+    }
+
+    /// Advance timers: handshake retries, keepalives, rekeying and key expiry
+    pub fn tick(&mut self, now_ms: u64) -> Vec<Datagram> {
+        let mut datagrams = Vec::new();
+        let keys: Vec<Key> = self.peers.keys().copied().collect();
+        for key in keys {
+            let Some(peer) = self.peers.get_mut(&key) else {
+                continue;
+            };
+            peer.expire_sessions(now_ms);
+
+            // Initiation の再送（REKEY_ATTEMPT_TIME を過ぎたらあきらめる）
+            let mut initiate = false;
+            let pending = peer
+                .handshake
+                .as_ref()
+                .map(|h| (h.started_ms, h.sent_ms + REKEY_TIMEOUT_MS + h.jitter_ms));
+            if let Some((started_ms, retry_ms)) = pending {
+                if now_ms.saturating_sub(started_ms) >= REKEY_ATTEMPT_TIME_MS {
+                    peer.handshake = None;
+                    peer.staged.clear();
+                    self.stats.handshake_timeouts += 1;
+                } else if now_ms >= retry_ms {
+                    initiate = true;
+                }
+            } else if !peer.staged.is_empty() && !peer.has_session(now_ms) {
+                initiate = true;
+            }
+
+            // 送ったデータに応答が無い
+            if peer.handshake_due.is_some_and(|due| now_ms >= due) {
+                peer.handshake_due = None;
+                initiate |= !peer.handshake_pending();
+            }
+
+            // 受け取ったデータへの応答が無い、または永続キープアライブ
+            let keepalive = peer.keepalive_due.is_some_and(|due| now_ms >= due)
+                || (peer.persistent_keepalive > 0
+                    && peer.last_sent_ms.is_none_or(|sent| {
+                        now_ms.saturating_sub(sent) >= peer.persistent_keepalive as u64 * 1_000
+                    }));
+
+            // 乱数が得られなければ次のタイマーで再び試す
+            if initiate {
+                datagrams.extend(self.begin_handshake(&key, now_ms).ok().flatten());
+            } else if keepalive {
+                if self.peers.get(&key).is_some_and(|p| p.has_session(now_ms)) {
+                    datagrams.extend(self.send_data(&key, &[], now_ms));
+                } else if let Some(peer) = self.peers.get_mut(&key) {
+                    peer.keepalive_due = None;
+                    if peer.persistent_keepalive > 0 && !peer.handshake_pending() {
+                        datagrams.extend(self.begin_handshake(&key, now_ms).ok().flatten());
+                    }
+                }
+            }
+        }
+
+        let peers = &self.peers;
+        self.indices
+            .retain(|index, key| peers.get(key).is_some_and(|p| p.owns_index(*index)));
+        datagrams
+    }
+
+    /// mac1 と、高負荷時の mac2 / 流量を確かめる
+    ///
+    /// 戻り値: クッキー応答を返すべきなら Some
+    fn check_handshake(
+        &mut self,
+        src: UdpAddr,
+        msg: &[u8],
+        now_ms: u64,
+    ) -> Result<Option<Decapsulated>, WgError> {
+        if !self.checker.check_mac1(msg) {
+            return Err(WgError::InvalidMac);
+        }
+        if !self.load.record(now_ms) {
+            return Ok(None);
+        }
+        if !self.checker.check_mac2(msg, &src, now_ms) {
+            self.stats.cookie_replies += 1;
+            let reply = self
+                .checker
+                .create_reply(msg, &src, now_ms)
+                .ok_or(WgError::NoEntropy)?;
+            return Ok(Some(Decapsulated {
+                packet: None,
+                datagrams: alloc::vec![Datagram {
+                    endpoint: src,
+                    data: reply.to_vec(),
+                }],
+            }));
+        }
+        if !self.load.allow(&src, now_ms) {
+            return Err(WgError::RateLimited);
+        }
+        Ok(None)
+    }
+
+    /// Initiation を受けて Response を返す
+    fn handle_initiation(
+        &mut self,
+        src: UdpAddr,
+        msg: &[u8],
+        now_ms: u64,
+    ) -> Result<Decapsulated, WgError> {
+        let initiation =
+            noise::consume_initiation(&self.identity, msg).ok_or(WgError::Unauthenticated)?;
+        let key = initiation.peer_public;
+        let peer = self.peers.get_mut(&key).ok_or(WgError::Unauthenticated)?;
+        if peer.last_timestamp.is_some_and(|last| initiation.timestamp <= last) {
+            return Err(WgError::Replay);
+        }
+        if peer
+            .last_initiation_ms
+            .is_some_and(|last| now_ms.saturating_sub(last) < MIN_INITIATION_INTERVAL_MS)
+        {
+            return Err(WgError::RateLimited);
+        }
+
+        let ephemeral = crypto::generate_private_key().ok_or(WgError::NoEntropy)?;
+        let local_index = self.allocate_index(key)?;
+        let peer = self.peers.get_mut(&key).ok_or(WgError::PeerNotFound)?;
+        let (mut response, keys) = noise::create_response(
+            &initiation,
+            &peer.preshared_key,
+            local_index,
+            ephemeral,
+        )
+        .ok_or(WgError::Unauthenticated)?;
+        peer.macs.add_macs(&mut response, now_ms);
+
+        peer.last_timestamp = Some(initiation.timestamp);
+        peer.last_initiation_ms = Some(now_ms);
+        peer.endpoint = Some(src);
+        peer.install_responder_session(keys, now_ms);
+        peer.record_received(now_ms, false);
+        peer.record_sent(now_ms, false);
+        self.stats.handshakes += 1;
+
+        Ok(Decapsulated {
+            packet: None,
+            datagrams: alloc::vec![Datagram {
+                endpoint: src,
+                data: response.to_vec(),
+            }],
+        })
+    }
+
+    /// Response を受けてセッションを確立し、保持していたパケットを送る
+    fn handle_response(
+        &mut self,
+        src: UdpAddr,
+        msg: &[u8],
+        now_ms: u64,
+    ) -> Result<Decapsulated, WgError> {
+        let index = noise::read_index(msg, 8);
+        let key = *self.indices.get(&index).ok_or(WgError::Unauthenticated)?;
+        let peer = self.peers.get_mut(&key).ok_or(WgError::Unauthenticated)?;
+        let handshake = peer
+            .handshake
+            .as_ref()
+            .filter(|h| h.state.local_index == index)
+            .ok_or(WgError::Unauthenticated)?;
+        let keys = noise::consume_response(
+            &self.identity,
+            &handshake.state,
+            &peer.preshared_key,
+            msg,
+        )
+        .ok_or(WgError::Unauthenticated)?;
+
+        peer.handshake = None;
+        peer.endpoint = Some(src);
+        peer.install_initiator_session(keys, now_ms);
+        peer.record_received(now_ms, false);
+        self.stats.handshakes += 1;
+
+        // 応答側は最初の Transport でセッションを確定するため、何も無ければキープアライブ
+        let staged: Vec<Vec<u8>> = peer.staged.drain(..).collect();
+        let mut datagrams = Vec::new();
+        for packet in &staged {
+            datagrams.extend(self.send_data(&key, packet, now_ms));
+        }
+        if staged.is_empty() {
+            datagrams.extend(self.send_data(&key, &[], now_ms));
+        }
+        Ok(Decapsulated {
+            packet: None,
+            datagrams,
+        })
+    }
+
+    /// クッキー応答を取り込む（次の再送から mac2 を付ける）
+    fn handle_cookie_reply(&mut self, msg: &[u8], now_ms: u64) -> Result<Decapsulated, WgError> {
+        let index = noise::read_index(msg, 4);
+        let key = *self.indices.get(&index).ok_or(WgError::Unauthenticated)?;
+        let peer = self.peers.get_mut(&key).ok_or(WgError::Unauthenticated)?;
+        if !peer.macs.consume_reply(msg, now_ms) {
+            return Err(WgError::Unauthenticated);
+        }
+        Ok(Decapsulated::default())
+    }
+
+    /// Transport を復号し、内側のパケットを確かめる
+    fn handle_transport(
+        &mut self,
+        src: UdpAddr,
+        msg: &[u8],
+        now_ms: u64,
+    ) -> Result<Decapsulated, WgError> {
+        let index = noise::read_index(msg, 4);
+        let key = *self.indices.get(&index).ok_or(WgError::Unauthenticated)?;
+        let peer = self.peers.get_mut(&key).ok_or(WgError::Unauthenticated)?;
+        let (session, unconfirmed) = peer.session_mut(index).ok_or(WgError::Unauthenticated)?;
+        let mut plaintext = session.decrypt(msg, now_ms)?;
+        let rekey = session.needs_rekey_on_receive(now_ms);
+        if unconfirmed {
+            peer.confirm_next();
+        }
+        peer.endpoint = Some(src);
+        peer.record_received(now_ms, !plaintext.is_empty());
+
+        let mut result = Decapsulated::default();
+        if rekey && !peer.handshake_pending() {
+            result.datagrams.extend(self.begin_handshake(&key, now_ms).ok().flatten());
+        }
+        // 空のペイロードはキープアライブ
+        if plaintext.is_empty() {
+            return Ok(result);
+        }
+
+        let (len, source) = {
+            let ip = Ipv4Packet::parse(&plaintext).ok_or(WgError::InvalidMessage)?;
+            (ip.as_bytes().len(), ip.source())
+        };
+        if self.allowed_ips.lookup(&source) != Some(&key) {
+            return Err(WgError::SourceNotAllowed);
+        }
+        // パディングを除く
+        plaintext.truncate(len);
+        if let Some(peer) = self.peers.get_mut(&key) {
+            peer.rx_bytes += len as u64;
+        }
+        result.packet = Some(plaintext);
+        Ok(result)
+    }
+
+    /// Initiation を作る（再送なら開始時刻を引き継ぐ）
+    ///
+    /// 送り先が無ければ Ok(None)、乱数が得られなければ `NoEntropy`。
+    fn begin_handshake(&mut self, key: &Key, now_ms: u64) -> Result<Option<Datagram>, WgError> {
+        let Some(endpoint) = self.peers.get(key).and_then(|p| p.endpoint) else {
+            return Ok(None);
+        };
+        let ephemeral = crypto::generate_private_key().ok_or(WgError::NoEntropy)?;
+        let local_index = self.allocate_index(*key)?;
+        let Some(peer) = self.peers.get_mut(key) else {
+            return Ok(None);
+        };
+        let timestamp = peer.next_timestamp(crate::time::now_nanos());
+        let Some((mut msg, state)) = noise::create_initiation(
+            &self.identity,
+            &peer.public_key,
+            local_index,
+            ephemeral,
+            timestamp,
+        ) else {
+            return Ok(None);
+        };
+        peer.macs.add_macs(&mut msg, now_ms);
+
+        let started_ms = peer.handshake.as_ref().map_or(now_ms, |h| h.started_ms);
+        peer.handshake = Some(PendingHandshake {
+            state,
+            sent_ms: now_ms,
+            started_ms,
+            jitter_ms: Peer::random_jitter(),
+        });
+        peer.last_sent_ms = Some(now_ms);
+        Ok(Some(Datagram {
+            endpoint,
+            data: msg.to_vec(),
+        }))
+    }
+
+    /// 現在のセッションで暗号化する（空ならキープアライブ）
+    fn send_data(&mut self, key: &Key, payload: &[u8], now_ms: u64) -> Option<Datagram> {
+        let mtu = self.mtu;
+        let peer = self.peers.get_mut(key)?;
+        let endpoint = peer.endpoint?;
+        let session = peer.current.as_mut().filter(|s| s.can_send(now_ms))?;
+        let padded = payload.len().next_multiple_of(PADDING_MULTIPLE).min(mtu);
+        let data = session.encrypt(payload, padded);
+        peer.tx_bytes += payload.len() as u64;
+        peer.record_sent(now_ms, !payload.is_empty());
+        Some(Datagram { endpoint, data })
+    }
+
+    /// 送信後に鍵を作り直すべきか
+    fn rekey_on_send(&self, key: &Key, now_ms: u64) -> bool {
+        self.peers.get(key).is_some_and(|peer| {
+            !peer.handshake_pending()
+                && peer
+                    .current
+                    .as_ref()
+                    .is_some_and(|s| s.needs_rekey_on_send(now_ms))
+        })
+    }
+
+    /// 未使用の受信側インデックスを割り当てる
+    fn allocate_index(&mut self, key: Key) -> Result<u32, WgError> {
+        loop {
+            let mut bytes = [0u8; 4];
+            crypto::random_bytes(&mut bytes).ok_or(WgError::NoEntropy)?;
+            let index = u32::from_le_bytes(bytes);
+            if index != 0 && !self.indices.contains_key(&index) {
+                self.indices.insert(index, key);
+                return Ok(index);
+            }
+        }
+    }
+}
+
+/// タイマータスクが動いているか
+static TIMER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Start the timer task (no-op if already running)
+///
+/// トンネルが 1 つも無くなるとタスクは終わる。
+pub fn start_timers() {
+    if TIMER_RUNNING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    crate::task::spawn(run_timers());
+}
+
+/// タイマーループ
+async fn run_timers() {
+    loop {
+        crate::task::sleep_ms(TIMER_INTERVAL_MS).await;
+        if super::stack::poll_wireguard() {
+            continue;
+        }
+        TIMER_RUNNING.store(false, Ordering::Release);
+        // 停止と同時に作られたトンネルがあれば続ける
+        if !super::stack::poll_wireguard() || TIMER_RUNNING.swap(true, Ordering::AcqRel) {
+            break;
+        }
+    }
+}
+
+/// Generate a private key (`wg genkey`)
+pub fn generate_private_key() -> Result<Key, WgError> {
+    crypto::generate_private_key().ok_or(WgError::NoEntropy)
+}
+
+/// Derive the public key of a private key (`wg pubkey`)
+pub fn public_key(private_key: &Key) -> Key {
+    crypto::public_key(private_key)
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ipv4::{IpProtocol, Ipv4Address, Ipv4PacketMut};
+    use crate::net::wireguard::crypto::KEY_LEN;
+
+    const ALICE_PORT: u16 = 51820;
+    const BOB_PORT: u16 = 51821;
+
+    fn addr(d: u8) -> Ipv4Address {
+        Ipv4Address::from_octets(10, 9, 0, d)
+    }
+
+    fn endpoint(port: u16) -> UdpAddr {
+        UdpAddr::new(Ipv4Address::from_octets(192, 0, 2, 1), port)
+    }
+
+    fn packet(src: Ipv4Address, dst: Ipv4Address, payload: &[u8]) -> Vec<u8> {
+        let mut buffer = alloc::vec![0u8; 20 + payload.len()];
+        let mut ip = Ipv4PacketMut::new(&mut buffer).unwrap();
+        ip.init_header()
+            .set_source(src)
+            .set_destination(dst)
+            .set_protocol(IpProtocol::Udp)
+            .set_ttl(64);
+        ip.payload_mut()[..payload.len()].copy_from_slice(payload);
+        ip.finalize(payload.len());
+        buffer
+    }
+
+    /// 10.9.0.1 (alice) <-> 10.9.0.2 (bob)
+    fn pair() -> (WireGuard, WireGuard) {
+        let mut alice = WireGuard::new([1u8; KEY_LEN], ALICE_PORT);
+        let mut bob = WireGuard::new([2u8; KEY_LEN], BOB_PORT);
+        let psk = [3u8; KEY_LEN];
+        alice
+            .set_peer(
+                PeerConfig::new(*bob.public_key())
+                    .endpoint(endpoint(BOB_PORT))
+                    .allowed_ip(InterfaceAddress::new(addr(2), 32))
+                    .preshared_key(psk),
+            )
+            .unwrap();
+        bob.set_peer(
+            PeerConfig::new(*alice.public_key())
+                .allowed_ip(InterfaceAddress::new(addr(1), 32))
+                .preshared_key(psk),
+        )
+        .unwrap();
+        (alice, bob)
+    }
+
+    #[test]
+    fn test_handshake_and_transport() {
+        let (mut alice, mut bob) = pair();
+        let bob_key = *bob.public_key();
+        let ping = packet(addr(1), addr(2), b"ping");
+
+        // 鍵が無いので Initiation を送ってパケットは保持
+        let out = alice.encapsulate(&ping, 0).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].data.len(), INITIATION_LEN);
+        assert_eq!(alice.peer(&bob_key).unwrap().staged_len(), 1);
+        // 応答を待つ間は再送しない
+        assert!(alice.encapsulate(&ping, 1).unwrap().is_empty());
+
+        let response = bob.decapsulate(endpoint(ALICE_PORT), &out[0].data, 10).unwrap();
+        assert_eq!(response.datagrams.len(), 1);
+        assert_eq!(response.datagrams[0].endpoint, endpoint(ALICE_PORT));
+
+        // Response を受けると保持していた 2 つのパケットを送る
+        let staged = alice
+            .decapsulate(endpoint(BOB_PORT), &response.datagrams[0].data, 20)
+            .unwrap();
+        assert_eq!(staged.datagrams.len(), 2);
+        let first = &staged.datagrams[0].data;
+        // パディングで 16 バイト単位
+        assert_eq!((first.len() - TRANSPORT_MIN_LEN) % PADDING_MULTIPLE, 0);
+        let received = bob.decapsulate(endpoint(ALICE_PORT), first, 30).unwrap();
+        assert_eq!(received.packet.as_deref(), Some(&ping[..]));
+        assert_eq!(alice.stats().handshakes, 1);
+        assert_eq!(bob.stats().handshakes, 1);
+
+        // 同じメッセージはリプレイとして拒否
+        assert_eq!(
+            bob.decapsulate(endpoint(ALICE_PORT), first, 31).unwrap_err(),
+            WgError::Replay
+        );
+
+        // 逆方向（応答側のセッションは確定済み）
+        let pong = packet(addr(2), addr(1), b"pong");
+        let out = bob.encapsulate(&pong, 40).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].endpoint, endpoint(ALICE_PORT));
+        let received = alice.decapsulate(endpoint(BOB_PORT), &out[0].data, 50).unwrap();
+        assert_eq!(received.packet.as_deref(), Some(&pong[..]));
+    }
+
+    #[test]
+    fn test_source_check_and_keepalive_timers() {
+        let (mut alice, mut bob) = pair();
+        let alice_key = *alice.public_key();
+        let init = alice.encapsulate(&packet(addr(1), addr(2), b"x"), 0).unwrap();
+        let response = bob.decapsulate(endpoint(ALICE_PORT), &init[0].data, 0).unwrap();
+        let staged = alice
+            .decapsulate(endpoint(BOB_PORT), &response.datagrams[0].data, 0)
+            .unwrap();
+        bob.decapsulate(endpoint(ALICE_PORT), &staged.datagrams[0].data, 0)
+            .unwrap();
+
+        // Alice が許可されていない送信元で送ったパケットは Bob が捨てる
+        let spoofed = packet(addr(7), addr(2), b"spoof");
+        let forged = {
+            let peer = alice.peers.get_mut(bob.public_key()).unwrap();
+            peer.current.as_mut().unwrap().encrypt(&spoofed, spoofed.len())
+        };
+        assert_eq!(
+            bob.decapsulate(endpoint(ALICE_PORT), &forged, 1).unwrap_err(),
+            WgError::SourceNotAllowed
+        );
+        assert_eq!(bob.stats().source_not_allowed, 1);
+
+        // データを受け取った Bob は、応答が無ければキープアライブを送る
+        assert!(bob.tick(5_000).is_empty());
+        let keepalive = bob.tick(10_000);
+        assert_eq!(keepalive.len(), 1);
+        assert_eq!(keepalive[0].data.len(), TRANSPORT_MIN_LEN);
+
+        // キープアライブが届かず、送ったデータに応答が無い Alice はハンドシェイクし直す
+        let out = alice.tick(15_000);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].data.len(), INITIATION_LEN);
+        assert!(alice.peer(bob.public_key()).unwrap().handshake_pending());
+
+        // 再送は REKEY_TIMEOUT ごと、REKEY_ATTEMPT_TIME であきらめる
+        assert!(alice.tick(15_000 + 1_000).is_empty());
+        assert_eq!(alice.tick(15_000 + REKEY_TIMEOUT_MS + 400).len(), 1);
+        assert!(alice.tick(15_000 + REKEY_ATTEMPT_TIME_MS).is_empty());
+        assert!(!alice.peer(bob.public_key()).unwrap().handshake_pending());
+        assert_eq!(alice.stats().handshake_timeouts, 1);
+
+        // 古いタイムスタンプの Initiation は拒否
+        assert_eq!(
+            bob.decapsulate(endpoint(ALICE_PORT), &init[0].data, 20_000).unwrap_err(),
+            WgError::Replay
+        );
+        bob.remove_peer(&alice_key).unwrap();
+        assert!(bob.peer(&alice_key).is_none());
+    }
+}
@@ -0,0 +1,111 @@
+//! 許可アドレス（Cryptokey Routing）
+//!
+//! プレフィックス → ピアの表。送信時は宛先の最長一致でピアを選び、
+//! 受信時は復号したパケットの送信元がそのピアのプレフィックスに入っているかを確かめる。
+//! 同じプレフィックスを別のピアに割り当てると、前のピアから外れる。
+
+use alloc::vec::Vec;
+
+use crate::net::interface::InterfaceAddress;
+use crate::net::ipv4::Ipv4Address;
+
+/// 許可アドレスの表
+#[derive(Debug, Clone)]
+pub struct AllowedIps<T> {
+    /// (ネットワークアドレス, 値)。プレフィックスの長い順
+    entries: Vec<(InterfaceAddress, T)>,
+}
+
+impl<T: Clone + PartialEq> AllowedIps<T> {
+    /// 空の表
+    pub const fn new() -> Self {
+        AllowedIps {
+            entries: Vec::new(),
+        }
+    }
+
+    /// プレフィックスを割り当てる（ホスト部は落とす）
+    pub fn insert(&mut self, prefix: InterfaceAddress, value: T) {
+        let prefix = InterfaceAddress::new(prefix.network(), prefix.prefix_len.min(32));
+        self.entries.retain(|(p, _)| *p != prefix);
+        let at = self
+            .entries
+            .iter()
+            .position(|(p, _)| p.prefix_len < prefix.prefix_len)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(at, (prefix, value));
+    }
+
+    /// 最長一致で引く
+    pub fn lookup(&self, addr: &Ipv4Address) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|(prefix, _)| prefix.contains(addr))
+            .map(|(_, value)| value)
+    }
+
+    /// 値に割り当てたプレフィックスをすべて外す
+    pub fn remove_value(&mut self, value: &T) {
+        self.entries.retain(|(_, v)| v != value);
+    }
+
+    /// 値に割り当てたプレフィックス
+    pub fn prefixes_of<'a>(&'a self, value: &'a T) -> impl Iterator<Item = InterfaceAddress> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(_, v)| v == value)
+            .map(|(prefix, _)| *prefix)
+    }
+
+    /// 登録数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 空か
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T: Clone + PartialEq> Default for AllowedIps<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(a: u8, b: u8, c: u8, d: u8, len: u8) -> InterfaceAddress {
+        InterfaceAddress::new(Ipv4Address::from_octets(a, b, c, d), len)
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut table = AllowedIps::new();
+        table.insert(prefix(0, 0, 0, 0, 0), 'a');
+        table.insert(prefix(10, 9, 0, 77, 24), 'b');
+        table.insert(prefix(10, 9, 0, 2, 32), 'c');
+
+        assert_eq!(table.lookup(&Ipv4Address::from_octets(10, 9, 0, 2)), Some(&'c'));
+        assert_eq!(table.lookup(&Ipv4Address::from_octets(10, 9, 0, 3)), Some(&'b'));
+        assert_eq!(table.lookup(&Ipv4Address::from_octets(8, 8, 8, 8)), Some(&'a'));
+        assert_eq!(
+            table.prefixes_of(&'b').collect::<Vec<_>>(),
+            [prefix(10, 9, 0, 0, 24)]
+        );
+
+        // 同じプレフィックスは後から割り当てたピアへ移る
+        table.insert(prefix(10, 9, 0, 0, 24), 'c');
+        assert_eq!(table.lookup(&Ipv4Address::from_octets(10, 9, 0, 3)), Some(&'c'));
+        table.remove_value(&'c');
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.lookup(&Ipv4Address::from_octets(10, 9, 0, 3)), Some(&'a'));
+    }
+}
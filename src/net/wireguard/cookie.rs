@@ -0,0 +1,304 @@
+//! mac1 / mac2 とクッキー（DoS 対策）
+//!
+//! ハンドシェイクメッセージの末尾 32 バイトは mac1（受信側の公開鍵から作る）と
+//! mac2（受信側から渡されたクッキーで作る）。mac1 が合わないメッセージは
+//! 公開鍵を知らない相手からのものとして、DH を計算する前に捨てる。
+//!
+//! 負荷が高いとき、mac2 の無いメッセージは処理せずにクッキー応答を返し、
+//! 送信元アドレスへの到達性を証明させる。mac2 が正しいメッセージも
+//! 送信元ごとに流量を制限する（ホワイトペーパー 5.3, 5.4.7）。
+
+use alloc::collections::BTreeMap;
+
+use super::crypto::{self, KEY_LEN, Key, MAC_LEN, TAG_LEN, XNONCE_LEN, hash, mac};
+use super::noise::{COOKIE_REPLY_LEN, MESSAGE_COOKIE_REPLY};
+use crate::net::firewall::rule::RateLimit;
+use crate::net::udp::UdpAddr;
+
+/// mac1 の鍵のラベル
+pub const LABEL_MAC1: &[u8] = b"mac1----";
+/// クッキー応答の鍵のラベル
+pub const LABEL_COOKIE: &[u8] = b"cookie--";
+
+/// クッキー用の秘密を作り直す間隔
+pub const COOKIE_SECRET_LIFETIME_MS: u64 = 120_000;
+/// 受け取ったクッキーを使う期間（秘密の更新とのずれを見込む）
+pub const COOKIE_LIFETIME_MS: u64 = COOKIE_SECRET_LIFETIME_MS - 5_000;
+
+/// この数を超えるハンドシェイクを 1 秒間に受けたら高負荷
+pub const UNDER_LOAD_THRESHOLD: u32 = 64;
+/// 高負荷の判定を続ける時間
+const UNDER_LOAD_HOLD_MS: u64 = 1_000;
+
+/// 高負荷時、送信元ごとに許すハンドシェイク（毎秒）
+const HANDSHAKES_PER_SOURCE: u32 = 20;
+/// 送信元ごとのバースト
+const HANDSHAKE_BURST: u32 = 5;
+/// 流量を記録する送信元の上限
+const MAX_TRACKED_SOURCES: usize = 4096;
+
+/// mac1 と mac2 の位置
+fn mac_offsets(msg: &[u8]) -> (usize, usize) {
+    let mac2 = msg.len() - MAC_LEN;
+    (mac2 - MAC_LEN, mac2)
+}
+
+/// クッキーに結び付ける送信元（アドレス + ポート）
+fn source_bytes(src: &UdpAddr) -> [u8; 6] {
+    let mut bytes = [0u8; 6];
+    bytes[..4].copy_from_slice(src.ip.as_bytes());
+    bytes[4..].copy_from_slice(&src.port.to_be_bytes());
+    bytes
+}
+
+/// 相手に送るメッセージの mac1 / mac2 を作る（ピアごと）
+#[derive(Clone)]
+pub struct MacGenerator {
+    mac1_key: Key,
+    cookie_key: Key,
+    /// 受け取ったクッキーと受信時刻
+    cookie: Option<([u8; MAC_LEN], u64)>,
+    /// 最後に送った mac1（クッキー応答の AAD）
+    last_mac1: Option<[u8; MAC_LEN]>,
+}
+
+impl MacGenerator {
+    /// 相手の公開鍵から作る
+    pub fn new(peer_public: &Key) -> Self {
+        MacGenerator {
+            mac1_key: hash(&[LABEL_MAC1, peer_public]),
+            cookie_key: hash(&[LABEL_COOKIE, peer_public]),
+            cookie: None,
+            last_mac1: None,
+        }
+    }
+
+    /// mac1 と、有効なクッキーがあれば mac2 を書き込む
+    pub fn add_macs(&mut self, msg: &mut [u8], now_ms: u64) {
+        let (mac1_at, mac2_at) = mac_offsets(msg);
+        let mac1 = mac(&self.mac1_key, &[&msg[..mac1_at]]);
+        msg[mac1_at..mac2_at].copy_from_slice(&mac1);
+        self.last_mac1 = Some(mac1);
+
+        match self.cookie {
+            Some((cookie, received)) if now_ms.saturating_sub(received) < COOKIE_LIFETIME_MS => {
+                let mac2 = mac(&cookie, &[&msg[..mac2_at]]);
+                msg[mac2_at..].copy_from_slice(&mac2);
+            }
+            _ => msg[mac2_at..].fill(0),
+        }
+    }
+
+    /// クッキー応答を取り込む（最後に送ったメッセージへの応答でなければ false）
+    pub fn consume_reply(&mut self, msg: &[u8], now_ms: u64) -> bool {
+        let Some(last_mac1) = self.last_mac1 else {
+            return false;
+        };
+        if msg.len() != COOKIE_REPLY_LEN {
+            return false;
+        }
+        let mut nonce = [0u8; XNONCE_LEN];
+        nonce.copy_from_slice(&msg[8..32]);
+        let mut cookie = [0u8; MAC_LEN + TAG_LEN];
+        cookie.copy_from_slice(&msg[32..64]);
+        if crypto::xopen(&self.cookie_key, &nonce, &last_mac1, &mut cookie).is_none() {
+            return false;
+        }
+        let mut value = [0u8; MAC_LEN];
+        value.copy_from_slice(&cookie[..MAC_LEN]);
+        self.cookie = Some((value, now_ms));
+        true
+    }
+}
+
+/// 受け取ったメッセージの mac1 / mac2 を検証し、クッキーを発行する（自分の鍵で）
+pub struct CookieChecker {
+    mac1_key: Key,
+    cookie_key: Key,
+    /// クッキーの秘密と作成時刻
+    secret: Option<(Key, u64)>,
+}
+
+impl CookieChecker {
+    /// 自分の公開鍵から作る
+    pub fn new(local_public: &Key) -> Self {
+        CookieChecker {
+            mac1_key: hash(&[LABEL_MAC1, local_public]),
+            cookie_key: hash(&[LABEL_COOKIE, local_public]),
+            secret: None,
+        }
+    }
+
+    /// mac1 が正しいか
+    pub fn check_mac1(&self, msg: &[u8]) -> bool {
+        let (mac1_at, mac2_at) = mac_offsets(msg);
+        let expected = mac(&self.mac1_key, &[&msg[..mac1_at]]);
+        crypto::constant_time_eq(&expected, &msg[mac1_at..mac2_at])
+    }
+
+    /// mac2 が送信元に発行したクッキーで作られているか
+    pub fn check_mac2(&mut self, msg: &[u8], src: &UdpAddr, now_ms: u64) -> bool {
+        let (_, mac2_at) = mac_offsets(msg);
+        let Some(cookie) = self.cookie(src, now_ms) else {
+            return false;
+        };
+        let expected = mac(&cookie, &[&msg[..mac2_at]]);
+        crypto::constant_time_eq(&expected, &msg[mac2_at..])
+    }
+
+    /// `msg` に対するクッキー応答を作る
+    ///
+    /// ノンスや秘密に使う乱数が得られなければ None。
+    pub fn create_reply(
+        &mut self,
+        msg: &[u8],
+        src: &UdpAddr,
+        now_ms: u64,
+    ) -> Option<[u8; COOKIE_REPLY_LEN]> {
+        let (mac1_at, mac2_at) = mac_offsets(msg);
+        let mut reply = [0u8; COOKIE_REPLY_LEN];
+        reply[0] = MESSAGE_COOKIE_REPLY;
+        // 受信側インデックス = 元のメッセージの送信側インデックス
+        reply[4..8].copy_from_slice(&msg[4..8]);
+
+        let mut nonce = [0u8; XNONCE_LEN];
+        crypto::random_bytes(&mut nonce)?;
+        reply[8..32].copy_from_slice(&nonce);
+        let cookie = self.cookie(src, now_ms)?;
+        reply[32..48].copy_from_slice(&cookie);
+        crypto::xseal(&self.cookie_key, &nonce, &msg[mac1_at..mac2_at], &mut reply[32..64]);
+        Some(reply)
+    }
+
+    /// 送信元のクッキー（秘密は 2 分ごとに作り直す）
+    fn cookie(&mut self, src: &UdpAddr, now_ms: u64) -> Option<[u8; MAC_LEN]> {
+        let secret = match self.secret {
+            Some((secret, created)) if now_ms.saturating_sub(created) < COOKIE_SECRET_LIFETIME_MS => {
+                secret
+            }
+            _ => {
+                let mut secret = [0u8; KEY_LEN];
+                crypto::random_bytes(&mut secret)?;
+                self.secret = Some((secret, now_ms));
+                secret
+            }
+        };
+        Some(mac(&secret, &[&source_bytes(src)]))
+    }
+}
+
+/// ハンドシェイクの受信量から高負荷かを判定し、送信元ごとに流量を制限する
+pub struct LoadMonitor {
+    /// 1 秒間にこの数を超えたら高負荷
+    pub threshold: u32,
+    window_start_ms: u64,
+    count: u32,
+    under_load_until: u64,
+    sources: BTreeMap<[u8; 4], (RateLimit, u64)>,
+}
+
+impl LoadMonitor {
+    /// 既定のしきい値で作る
+    pub fn new() -> Self {
+        LoadMonitor {
+            threshold: UNDER_LOAD_THRESHOLD,
+            window_start_ms: 0,
+            count: 0,
+            under_load_until: 0,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// ハンドシェイクを 1 つ数え、高負荷なら true
+    pub fn record(&mut self, now_ms: u64) -> bool {
+        if now_ms.saturating_sub(self.window_start_ms) >= 1_000 {
+            self.window_start_ms = now_ms;
+            self.count = 0;
+        }
+        self.count += 1;
+        if self.count > self.threshold {
+            self.under_load_until = now_ms + UNDER_LOAD_HOLD_MS;
+        }
+        now_ms < self.under_load_until
+    }
+
+    /// 送信元からのハンドシェイクを処理してよいか（高負荷時のみ呼ぶ）
+    pub fn allow(&mut self, src: &UdpAddr, now_ms: u64) -> bool {
+        let key = *src.ip.as_bytes();
+        if !self.sources.contains_key(&key) && self.sources.len() >= MAX_TRACKED_SOURCES {
+            // しばらく来ていない送信元を忘れる
+            self.sources
+                .retain(|_, (_, last)| now_ms.saturating_sub(*last) < 1_000);
+            if self.sources.len() >= MAX_TRACKED_SOURCES {
+                return false;
+            }
+        }
+        let (limit, last) = self
+            .sources
+            .entry(key)
+            .or_insert_with(|| (RateLimit::new(HANDSHAKES_PER_SOURCE, 1_000, HANDSHAKE_BURST), now_ms));
+        *last = now_ms;
+        limit.allow(now_ms)
+    }
+}
+
+impl Default for LoadMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ipv4::Ipv4Address;
+
+    #[test]
+    fn test_macs_and_cookie_reply() {
+        let responder_public = crypto::public_key(&[8u8; KEY_LEN]);
+        let mut generator = MacGenerator::new(&responder_public);
+        let mut checker = CookieChecker::new(&responder_public);
+        let src = UdpAddr::new(Ipv4Address::from_octets(192, 0, 2, 1), 51820);
+
+        let mut msg = [0x5Au8; 148];
+        msg[..4].copy_from_slice(&[1, 0, 0, 0]);
+        generator.add_macs(&mut msg, 0);
+        assert!(checker.check_mac1(&msg));
+        assert!(!checker.check_mac2(&msg, &src, 0));
+
+        // 別の公開鍵宛ての mac1 は通らない
+        let other = CookieChecker::new(&crypto::public_key(&[9u8; KEY_LEN]));
+        assert!(!other.check_mac1(&msg));
+
+        // クッキーを受け取ると mac2 が付く
+        let reply = checker.create_reply(&msg, &src, 0).unwrap();
+        assert!(generator.consume_reply(&reply, 10));
+        generator.add_macs(&mut msg, 20);
+        assert!(checker.check_mac1(&msg));
+        assert!(checker.check_mac2(&msg, &src, 20));
+        // 送信元ポートが違えばクッキーも違う
+        assert!(!checker.check_mac2(&msg, &UdpAddr::new(src.ip, 51821), 20));
+        // 期限を過ぎたクッキーは使わない
+        generator.add_macs(&mut msg, 10 + COOKIE_LIFETIME_MS);
+        assert_eq!(&msg[132..], &[0u8; MAC_LEN]);
+    }
+
+    #[test]
+    fn test_load_monitor() {
+        let mut load = LoadMonitor::new();
+        load.threshold = 3;
+        assert!(!(0..3).any(|_| load.record(100)));
+        assert!(load.record(100));
+        // 次の秒も保持し、その後に解除
+        assert!(load.record(1_050));
+        assert!(!load.record(2_200));
+
+        let src = UdpAddr::new(Ipv4Address::from_octets(192, 0, 2, 1), 1);
+        let allowed = (0..10).filter(|_| load.allow(&src, 5_000)).count();
+        assert_eq!(allowed, HANDSHAKE_BURST as usize);
+    }
+}
@@ -0,0 +1,338 @@
+//! 暗号プリミティブ
+//!
+//! ホワイトペーパー 5.4 節の HASH / MAC / HMAC / KDF / AEAD / DH。
+//! 計算そのものは監査済みクレート（curve25519-dalek, chacha20poly1305, blake2）
+//! に任せ、ここではプロトコルが要求する組み立てだけを行う。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use blake2::digest::consts::U16;
+use blake2::{Blake2s256, Blake2sMac, Digest};
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use curve25519_dalek::montgomery::MontgomeryPoint;
+
+/// 鍵・ハッシュの長さ
+pub const KEY_LEN: usize = 32;
+/// AEAD 認証タグの長さ
+pub const TAG_LEN: usize = 16;
+/// MAC（鍵付き BLAKE2s-128）の長さ
+pub const MAC_LEN: usize = 16;
+/// TAI64N タイムスタンプの長さ
+pub const TIMESTAMP_LEN: usize = 12;
+/// XChaCha20 のノンス長
+pub const XNONCE_LEN: usize = 24;
+
+/// 32 バイトの鍵
+pub type Key = [u8; KEY_LEN];
+
+/// HMAC のブロック長（BLAKE2s）
+const BLOCK_LEN: usize = 64;
+
+/// TAI64 ラベル（2^62 + TAI と UTC の差 10 秒）
+const TAI64_BASE: u64 = 0x4000_0000_0000_000A;
+
+/// タイムスタンプのナノ秒を丸める単位（送信時刻の推測を難しくする）
+pub const TIMESTAMP_WHITENER: u32 = 0x0100_0000;
+
+/// HASH: BLAKE2s-256（`parts` を連結した入力）
+pub fn hash(parts: &[&[u8]]) -> Key {
+    let mut hasher = Blake2s256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// MAC: 鍵付き BLAKE2s-128
+pub fn mac(key: &[u8], parts: &[&[u8]]) -> [u8; MAC_LEN] {
+    use blake2::digest::Mac;
+
+    let mut mac = <Blake2sMac<U16> as KeyInit>::new_from_slice(key)
+        .expect("BLAKE2s key is at most 32 bytes");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// HMAC-BLAKE2s
+pub fn hmac(key: &Key, parts: &[&[u8]]) -> Key {
+    let mut ipad = [0x36u8; BLOCK_LEN];
+    let mut opad = [0x5Cu8; BLOCK_LEN];
+    for (i, byte) in key.iter().enumerate() {
+        ipad[i] ^= byte;
+        opad[i] ^= byte;
+    }
+    let mut inner = Blake2s256::new();
+    inner.update(ipad);
+    for part in parts {
+        inner.update(part);
+    }
+    let inner: Key = inner.finalize().into();
+    hash(&[&opad, &inner])
+}
+
+/// KDF_n: HKDF（HMAC-BLAKE2s）で `N` 個の 32 バイト値を導出
+pub fn kdf<const N: usize>(key: &Key, input: &[u8]) -> [Key; N] {
+    let prk = hmac(key, &[input]);
+    let mut out = [[0u8; KEY_LEN]; N];
+    let mut previous: Option<Key> = None;
+    for (i, slot) in out.iter_mut().enumerate() {
+        let counter = [i as u8 + 1];
+        let t = match previous {
+            Some(ref prev) => hmac(&prk, &[prev, &counter]),
+            None => hmac(&prk, &[&counter]),
+        };
+        *slot = t;
+        previous = Some(t);
+    }
+    out
+}
+
+/// AEAD のノンス（32 ビットの 0 + 64 ビットのカウンタ LE）
+fn nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+/// ChaCha20-Poly1305 で暗号化
+///
+/// `buffer` は平文の後ろに `TAG_LEN` バイトの領域を持つ。その場で暗号文とタグに置き換える。
+pub fn seal(key: &Key, counter: u64, aad: &[u8], buffer: &mut [u8]) {
+    let len = buffer.len() - TAG_LEN;
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let (data, tag_out) = buffer.split_at_mut(len);
+    match cipher.encrypt_in_place_detached(&nonce(counter).into(), aad, data) {
+        Ok(tag) => tag_out.copy_from_slice(&tag),
+        // 長さ上限（256 GiB）を超えることはないが、平文は残さない
+        Err(_) => buffer.fill(0),
+    }
+}
+
+/// ChaCha20-Poly1305 で復号
+///
+/// `buffer` は暗号文 + タグ。成功すれば先頭が平文になり、その長さを返す。
+pub fn open(key: &Key, counter: u64, aad: &[u8], buffer: &mut [u8]) -> Option<usize> {
+    let len = buffer.len().checked_sub(TAG_LEN)?;
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let (data, tag) = buffer.split_at_mut(len);
+    cipher
+        .decrypt_in_place_detached(&nonce(counter).into(), aad, data, (&*tag).into())
+        .ok()?;
+    Some(len)
+}
+
+/// XChaCha20-Poly1305 で暗号化（クッキー応答）
+pub fn xseal(key: &Key, nonce: &[u8; XNONCE_LEN], aad: &[u8], buffer: &mut [u8]) {
+    let len = buffer.len() - TAG_LEN;
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let (data, tag_out) = buffer.split_at_mut(len);
+    match cipher.encrypt_in_place_detached(nonce.into(), aad, data) {
+        Ok(tag) => tag_out.copy_from_slice(&tag),
+        Err(_) => buffer.fill(0),
+    }
+}
+
+/// XChaCha20-Poly1305 で復号（クッキー応答）
+pub fn xopen(key: &Key, nonce: &[u8; XNONCE_LEN], aad: &[u8], buffer: &mut [u8]) -> Option<usize> {
+    let len = buffer.len().checked_sub(TAG_LEN)?;
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let (data, tag) = buffer.split_at_mut(len);
+    cipher
+        .decrypt_in_place_detached(nonce.into(), aad, data, (&*tag).into())
+        .ok()?;
+    Some(len)
+}
+
+/// Curve25519 の Diffie-Hellman
+///
+/// 結果が 0（小位数の公開鍵）なら None。
+pub fn dh(private: &Key, public: &Key) -> Option<Key> {
+    let shared = MontgomeryPoint(*public).mul_clamped(*private).to_bytes();
+    (shared != [0u8; KEY_LEN]).then_some(shared)
+}
+
+/// 秘密鍵から公開鍵を求める
+pub fn public_key(private: &Key) -> Key {
+    MontgomeryPoint::mul_base_clamped(*private).to_bytes()
+}
+
+/// 新しい秘密鍵（クランプ済み、`wg genkey` と同じ形）
+///
+/// ハードウェア乱数が使えなければ None。
+pub fn generate_private_key() -> Option<Key> {
+    let mut key = [0u8; KEY_LEN];
+    random_bytes(&mut key)?;
+    key[0] &= 248;
+    key[31] = (key[31] & 127) | 64;
+    Some(key)
+}
+
+/// RDRAND が一時的に失敗したときの再試行回数（Intel の推奨値）
+const RDRAND_RETRIES: usize = 10;
+
+/// 乱数で埋める
+///
+/// RDRAND だけを使う。予測できる値で代用すると鍵やノンスが推測されるので、
+/// 使えない CPU や再試行しても失敗する場合は None を返す。
+pub fn random_bytes(buf: &mut [u8]) -> Option<()> {
+    use x86_64::instructions::random::RdRand;
+
+    let rdrand = RdRand::new()?;
+    for chunk in buf.chunks_mut(8) {
+        let value = (0..RDRAND_RETRIES).find_map(|_| rdrand.get_u64())?;
+        chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+    }
+    Some(())
+}
+
+/// TAI64N タイムスタンプ（Unix 時刻のナノ秒から）
+pub fn tai64n(unix_nanos: u64) -> [u8; TIMESTAMP_LEN] {
+    let seconds = TAI64_BASE + unix_nanos / 1_000_000_000;
+    let nanos = (unix_nanos % 1_000_000_000) as u32 / TIMESTAMP_WHITENER * TIMESTAMP_WHITENER;
+    let mut out = [0u8; TIMESTAMP_LEN];
+    out[..8].copy_from_slice(&seconds.to_be_bytes());
+    out[8..].copy_from_slice(&nanos.to_be_bytes());
+    out
+}
+
+/// 鍵を比較（内容に依存しない時間で）
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ============================================================================
+// 鍵の表記（Base64、`wg` と同じ 44 文字）
+// ============================================================================
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// 鍵を Base64 にする
+pub fn encode_key(key: &Key) -> String {
+    let mut out = String::with_capacity(44);
+    for chunk in key.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Base64 の鍵を読む（32 バイトでなければ None）
+pub fn decode_key(s: &str) -> Option<Key> {
+    let s = s.trim();
+    if s.len() != 44 || !s.ends_with('=') {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(33);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        let mut valid = 0;
+        for &c in chunk {
+            let value = match c {
+                b'=' => None,
+                _ => Some(BASE64.iter().position(|&b| b == c)? as u32),
+            };
+            n <<= 6;
+            if let Some(value) = value {
+                n |= value;
+                valid += 1;
+            }
+        }
+        bytes.extend_from_slice(&n.to_be_bytes()[1..valid]);
+    }
+    bytes.try_into().ok()
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_blake2s_and_x25519_vectors() {
+        // BLAKE2s-256("")
+        assert_eq!(
+            hash(&[]).to_vec(),
+            hex("69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9")
+        );
+        // RFC 7748 6.1
+        let alice: Key = hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a")
+            .try_into()
+            .unwrap();
+        let bob_public: Key = hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            public_key(&alice).to_vec(),
+            hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            dh(&alice, &bob_public).unwrap().to_vec(),
+            hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+        );
+        // 小位数点（0）との DH は拒否
+        assert!(dh(&alice, &[0u8; KEY_LEN]).is_none());
+    }
+
+    #[test]
+    fn test_aead_round_trip_and_tamper() {
+        let key = [7u8; KEY_LEN];
+        let mut buffer = [0u8; 5 + TAG_LEN];
+        buffer[..5].copy_from_slice(b"hello");
+        seal(&key, 3, b"ad", &mut buffer);
+        assert_ne!(&buffer[..5], b"hello");
+
+        let mut copy = buffer;
+        assert_eq!(open(&key, 3, b"ad", &mut copy), Some(5));
+        assert_eq!(&copy[..5], b"hello");
+        // カウンタ・AAD・暗号文のどれが違っても失敗
+        assert_eq!(open(&key, 4, b"ad", &mut buffer.clone()), None);
+        assert_eq!(open(&key, 3, b"xx", &mut buffer.clone()), None);
+        buffer[0] ^= 1;
+        assert_eq!(open(&key, 3, b"ad", &mut buffer), None);
+
+        let nonce = [9u8; XNONCE_LEN];
+        let mut cookie = [1u8; MAC_LEN + TAG_LEN];
+        xseal(&key, &nonce, b"mac1", &mut cookie);
+        assert_eq!(xopen(&key, &nonce, b"mac1", &mut cookie), Some(MAC_LEN));
+        assert_eq!(&cookie[..MAC_LEN], &[1u8; MAC_LEN]);
+    }
+
+    #[test]
+    fn test_key_encoding_and_tai64n() {
+        let key: Key = core::array::from_fn(|i| i as u8 * 7);
+        let text = encode_key(&key);
+        assert_eq!(text.len(), 44);
+        assert_eq!(decode_key(&text), Some(key));
+        assert_eq!(
+            encode_key(&[0u8; KEY_LEN]),
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+        );
+        assert_eq!(decode_key("AAAA"), None);
+        assert_eq!(decode_key("!AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="), None);
+
+        let stamp = tai64n(1_700_000_000_123_456_789);
+        assert_eq!(&stamp[..8], &(TAI64_BASE + 1_700_000_000).to_be_bytes());
+        assert!(tai64n(1_700_000_001_000_000_000) > stamp);
+    }
+}
@@ -0,0 +1,321 @@
+//! Noise IK ハンドシェイク
+//!
+//! `Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s` のメッセージ 1（Initiation）と
+//! メッセージ 2（Response）の生成・検証と、転送用の鍵の導出（ホワイトペーパー 5.4.2〜5.4.5）。
+//!
+//! ここは状態を持たない計算だけを扱う。一時鍵とインデックスは呼び出し側が渡し、
+//! mac1 / mac2 は `cookie` が後から埋める。
+
+use super::crypto::{self, KEY_LEN, Key, TAG_LEN, TIMESTAMP_LEN, hash, kdf};
+
+/// プロトコル名（連鎖鍵の初期値）
+pub const CONSTRUCTION: &[u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+/// 識別子（ハッシュの初期値に混ぜる）
+pub const IDENTIFIER: &[u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+
+/// メッセージ種別
+pub const MESSAGE_INITIATION: u8 = 1;
+pub const MESSAGE_RESPONSE: u8 = 2;
+pub const MESSAGE_COOKIE_REPLY: u8 = 3;
+pub const MESSAGE_TRANSPORT: u8 = 4;
+
+/// Initiation の長さ
+pub const INITIATION_LEN: usize = 148;
+/// Response の長さ
+pub const RESPONSE_LEN: usize = 92;
+/// Cookie Reply の長さ
+pub const COOKIE_REPLY_LEN: usize = 64;
+/// Transport のヘッダ長（種別・受信側インデックス・カウンタ）
+pub const TRANSPORT_HEADER_LEN: usize = 16;
+/// 最小の Transport（空のキープアライブ）
+pub const TRANSPORT_MIN_LEN: usize = TRANSPORT_HEADER_LEN + TAG_LEN;
+
+/// 静的な鍵ペア
+#[derive(Clone)]
+pub struct StaticIdentity {
+    /// 秘密鍵
+    pub private: Key,
+    /// 公開鍵
+    pub public: Key,
+}
+
+impl StaticIdentity {
+    /// 秘密鍵から作る
+    pub fn new(private: Key) -> Self {
+        StaticIdentity {
+            public: crypto::public_key(&private),
+            private,
+        }
+    }
+}
+
+/// 送信した Initiation の状態（Response を待つ）
+#[derive(Clone)]
+pub struct InitiatorHandshake {
+    /// 自分のインデックス（Response の受信側インデックス）
+    pub local_index: u32,
+    chaining_key: Key,
+    hash: Key,
+    ephemeral_private: Key,
+}
+
+/// 検証済みの Initiation
+#[derive(Clone)]
+pub struct ConsumedInitiation {
+    /// 相手の静的公開鍵
+    pub peer_public: Key,
+    /// 相手のタイムスタンプ（TAI64N、リプレイ判定用）
+    pub timestamp: [u8; TIMESTAMP_LEN],
+    /// 相手のインデックス
+    pub remote_index: u32,
+    chaining_key: Key,
+    hash: Key,
+    remote_ephemeral: Key,
+}
+
+/// ハンドシェイクで得た転送用の鍵
+#[derive(Clone)]
+pub struct SessionKeys {
+    /// 送信鍵
+    pub send: Key,
+    /// 受信鍵
+    pub recv: Key,
+    /// 自分のインデックス（相手が Transport に付ける）
+    pub local_index: u32,
+    /// 相手のインデックス（自分が Transport に付ける）
+    pub remote_index: u32,
+    /// こちらが開始側か
+    pub initiator: bool,
+}
+
+/// 固定長ヘッダ（種別 + 予約 3 バイト）の種別
+pub fn message_type(data: &[u8]) -> Option<u8> {
+    match data {
+        [kind, 0, 0, 0, ..] => Some(*kind),
+        _ => None,
+    }
+}
+
+/// リトルエンディアンの u32 を読む
+pub fn read_index(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+/// 連鎖鍵とハッシュの初期値
+fn initial_chain() -> (Key, Key) {
+    let chaining_key = hash(&[CONSTRUCTION]);
+    let hash = hash(&[&chaining_key, IDENTIFIER]);
+    (chaining_key, hash)
+}
+
+fn key_at(data: &[u8], offset: usize) -> Key {
+    let mut key = [0u8; KEY_LEN];
+    key.copy_from_slice(&data[offset..offset + KEY_LEN]);
+    key
+}
+
+/// Initiation を作る（開始側）
+///
+/// mac1 / mac2 の領域は 0 のまま返す。
+pub fn create_initiation(
+    identity: &StaticIdentity,
+    peer_public: &Key,
+    local_index: u32,
+    ephemeral_private: Key,
+    timestamp: [u8; TIMESTAMP_LEN],
+) -> Option<([u8; INITIATION_LEN], InitiatorHandshake)> {
+    let (chaining_key, h) = initial_chain();
+    let h = hash(&[&h, peer_public]);
+
+    let mut msg = [0u8; INITIATION_LEN];
+    msg[0] = MESSAGE_INITIATION;
+    msg[4..8].copy_from_slice(&local_index.to_le_bytes());
+
+    let ephemeral = crypto::public_key(&ephemeral_private);
+    msg[8..40].copy_from_slice(&ephemeral);
+    let [chaining_key] = kdf(&chaining_key, &ephemeral);
+    let h = hash(&[&h, &ephemeral]);
+
+    // 静的公開鍵を暗号化
+    let [chaining_key, key] = kdf(&chaining_key, &crypto::dh(&ephemeral_private, peer_public)?);
+    msg[40..72].copy_from_slice(&identity.public);
+    crypto::seal(&key, 0, &h, &mut msg[40..88]);
+    let h = hash(&[&h, &msg[40..88]]);
+
+    // タイムスタンプを暗号化
+    let [chaining_key, key] = kdf(&chaining_key, &crypto::dh(&identity.private, peer_public)?);
+    msg[88..100].copy_from_slice(&timestamp);
+    crypto::seal(&key, 0, &h, &mut msg[88..116]);
+    let h = hash(&[&h, &msg[88..116]]);
+
+    Some((
+        msg,
+        InitiatorHandshake {
+            local_index,
+            chaining_key,
+            hash: h,
+            ephemeral_private,
+        },
+    ))
+}
+
+/// Initiation を検証する（応答側）
+///
+/// 相手が誰か・タイムスタンプが新しいかの判断は呼び出し側が行う。
+pub fn consume_initiation(identity: &StaticIdentity, msg: &[u8]) -> Option<ConsumedInitiation> {
+    if msg.len() != INITIATION_LEN || message_type(msg)? != MESSAGE_INITIATION {
+        return None;
+    }
+    let (chaining_key, h) = initial_chain();
+    let h = hash(&[&h, &identity.public]);
+
+    let ephemeral = key_at(msg, 8);
+    let [chaining_key] = kdf(&chaining_key, &ephemeral);
+    let h = hash(&[&h, &ephemeral]);
+
+    let [chaining_key, key] = kdf(&chaining_key, &crypto::dh(&identity.private, &ephemeral)?);
+    let mut static_public = [0u8; KEY_LEN + TAG_LEN];
+    static_public.copy_from_slice(&msg[40..88]);
+    crypto::open(&key, 0, &h, &mut static_public)?;
+    let peer_public = key_at(&static_public, 0);
+    let h = hash(&[&h, &msg[40..88]]);
+
+    let [chaining_key, key] = kdf(&chaining_key, &crypto::dh(&identity.private, &peer_public)?);
+    let mut timestamp = [0u8; TIMESTAMP_LEN + TAG_LEN];
+    timestamp.copy_from_slice(&msg[88..116]);
+    crypto::open(&key, 0, &h, &mut timestamp)?;
+    let h = hash(&[&h, &msg[88..116]]);
+
+    let mut stamp = [0u8; TIMESTAMP_LEN];
+    stamp.copy_from_slice(&timestamp[..TIMESTAMP_LEN]);
+    Some(ConsumedInitiation {
+        peer_public,
+        timestamp: stamp,
+        remote_index: read_index(msg, 4),
+        chaining_key,
+        hash: h,
+        remote_ephemeral: ephemeral,
+    })
+}
+
+/// Response を作る（応答側）
+///
+/// mac1 / mac2 の領域は 0 のまま返す。
+pub fn create_response(
+    initiation: &ConsumedInitiation,
+    preshared_key: &Key,
+    local_index: u32,
+    ephemeral_private: Key,
+) -> Option<([u8; RESPONSE_LEN], SessionKeys)> {
+    let mut msg = [0u8; RESPONSE_LEN];
+    msg[0] = MESSAGE_RESPONSE;
+    msg[4..8].copy_from_slice(&local_index.to_le_bytes());
+    msg[8..12].copy_from_slice(&initiation.remote_index.to_le_bytes());
+
+    let ephemeral = crypto::public_key(&ephemeral_private);
+    msg[12..44].copy_from_slice(&ephemeral);
+    let [chaining_key] = kdf(&initiation.chaining_key, &ephemeral);
+    let h = hash(&[&initiation.hash, &ephemeral]);
+    let [chaining_key] = kdf(
+        &chaining_key,
+        &crypto::dh(&ephemeral_private, &initiation.remote_ephemeral)?,
+    );
+    let [chaining_key] = kdf(
+        &chaining_key,
+        &crypto::dh(&ephemeral_private, &initiation.peer_public)?,
+    );
+
+    let [chaining_key, tau, key] = kdf(&chaining_key, preshared_key);
+    let h = hash(&[&h, &tau]);
+    crypto::seal(&key, 0, &h, &mut msg[44..60]);
+
+    let [initiator_send, initiator_recv] = kdf(&chaining_key, &[]);
+    Some((
+        msg,
+        SessionKeys {
+            send: initiator_recv,
+            recv: initiator_send,
+            local_index,
+            remote_index: initiation.remote_index,
+            initiator: false,
+        },
+    ))
+}
+
+/// Response を検証して鍵を導出する（開始側）
+pub fn consume_response(
+    identity: &StaticIdentity,
+    handshake: &InitiatorHandshake,
+    preshared_key: &Key,
+    msg: &[u8],
+) -> Option<SessionKeys> {
+    if msg.len() != RESPONSE_LEN
+        || message_type(msg)? != MESSAGE_RESPONSE
+        || read_index(msg, 8) != handshake.local_index
+    {
+        return None;
+    }
+    let ephemeral = key_at(msg, 12);
+    let [chaining_key] = kdf(&handshake.chaining_key, &ephemeral);
+    let h = hash(&[&handshake.hash, &ephemeral]);
+    let [chaining_key] = kdf(
+        &chaining_key,
+        &crypto::dh(&handshake.ephemeral_private, &ephemeral)?,
+    );
+    let [chaining_key] = kdf(&chaining_key, &crypto::dh(&identity.private, &ephemeral)?);
+
+    let [chaining_key, tau, key] = kdf(&chaining_key, preshared_key);
+    let h = hash(&[&h, &tau]);
+    let mut empty = [0u8; TAG_LEN];
+    empty.copy_from_slice(&msg[44..60]);
+    crypto::open(&key, 0, &h, &mut empty)?;
+
+    let [send, recv] = kdf(&chaining_key, &[]);
+    Some(SessionKeys {
+        send,
+        recv,
+        local_index: handshake.local_index,
+        remote_index: read_index(msg, 4),
+        initiator: true,
+    })
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_derives_matching_keys() {
+        let alice = StaticIdentity::new([1u8; KEY_LEN]);
+        let bob = StaticIdentity::new([2u8; KEY_LEN]);
+        let psk = [3u8; KEY_LEN];
+        let stamp = crypto::tai64n(1_700_000_000_000_000_000);
+
+        let (initiation, handshake) =
+            create_initiation(&alice, &bob.public, 0x1111, [4u8; KEY_LEN], stamp).unwrap();
+        let consumed = consume_initiation(&bob, &initiation).unwrap();
+        assert_eq!(consumed.peer_public, alice.public);
+        assert_eq!(consumed.timestamp, stamp);
+        assert_eq!(consumed.remote_index, 0x1111);
+
+        let (response, bob_keys) = create_response(&consumed, &psk, 0x2222, [5u8; KEY_LEN]).unwrap();
+        let alice_keys = consume_response(&alice, &handshake, &psk, &response).unwrap();
+        assert_eq!(alice_keys.send, bob_keys.recv);
+        assert_eq!(alice_keys.recv, bob_keys.send);
+        assert_ne!(alice_keys.send, alice_keys.recv);
+        assert_eq!(alice_keys.remote_index, 0x2222);
+        assert_eq!(bob_keys.remote_index, 0x1111);
+
+        // 事前共有鍵が違えば Response の検証に失敗
+        assert!(consume_response(&alice, &handshake, &[0u8; KEY_LEN], &response).is_none());
+        // 宛先が違う Initiation は復号できない
+        let carol = StaticIdentity::new([6u8; KEY_LEN]);
+        assert!(consume_initiation(&carol, &initiation).is_none());
+    }
+}
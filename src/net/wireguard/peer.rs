@@ -0,0 +1,316 @@
+//! ピアとセッション
+//!
+//! ピアごとのハンドシェイク状態、セッション（直前・現在・確認待ちの 3 つ）、
+//! 鍵が揃うまで送れないパケットのキュー、タイマーの期限を持つ。
+//! 定数はホワイトペーパー 6.1 節の値。
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::WgError;
+use super::cookie::MacGenerator;
+use super::crypto::{self, KEY_LEN, Key, TAG_LEN, TIMESTAMP_LEN, TIMESTAMP_WHITENER};
+use super::noise::{InitiatorHandshake, MESSAGE_TRANSPORT, SessionKeys, TRANSPORT_HEADER_LEN};
+use super::replay::ReplayWindow;
+use crate::net::udp::UdpAddr;
+
+/// この数のメッセージを送ったら鍵を作り直す
+pub const REKEY_AFTER_MESSAGES: u64 = 1 << 60;
+/// この数に達した鍵では送受信しない
+pub const REJECT_AFTER_MESSAGES: u64 = u64::MAX - (1 << 13);
+/// 開始側がこの時間を過ぎた鍵で送るときは作り直す
+pub const REKEY_AFTER_TIME_MS: u64 = 120_000;
+/// この時間を過ぎた鍵では送受信しない
+pub const REJECT_AFTER_TIME_MS: u64 = 180_000;
+/// ハンドシェイクを再送し続ける時間
+pub const REKEY_ATTEMPT_TIME_MS: u64 = 90_000;
+/// Initiation の再送間隔
+pub const REKEY_TIMEOUT_MS: u64 = 5_000;
+/// データを受け取ってから、送るものが無いときにキープアライブを送るまでの時間
+pub const KEEPALIVE_TIMEOUT_MS: u64 = 10_000;
+
+/// 鍵を待つ間に保持するパケット数
+pub const MAX_STAGED_PACKETS: usize = 128;
+
+/// 再送間隔に加える揺らぎの上限
+const REKEY_JITTER_MS: u64 = 333;
+
+/// 1 つの鍵の組による送受信
+pub struct Session {
+    /// 導出した鍵とインデックス
+    pub keys: SessionKeys,
+    /// 鍵を導出した時刻
+    pub created_ms: u64,
+    send_counter: u64,
+    replay: ReplayWindow,
+}
+
+impl Session {
+    /// 導出した鍵から作る
+    pub fn new(keys: SessionKeys, now_ms: u64) -> Self {
+        Session {
+            keys,
+            created_ms: now_ms,
+            send_counter: 0,
+            replay: ReplayWindow::new(),
+        }
+    }
+
+    fn age(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_sub(self.created_ms)
+    }
+
+    /// 送信に使えるか
+    pub fn can_send(&self, now_ms: u64) -> bool {
+        self.age(now_ms) < REJECT_AFTER_TIME_MS && self.send_counter < REJECT_AFTER_MESSAGES
+    }
+
+    /// 送信時に鍵を作り直すべきか（開始側だけが判断する）
+    pub fn needs_rekey_on_send(&self, now_ms: u64) -> bool {
+        self.keys.initiator
+            && (self.age(now_ms) >= REKEY_AFTER_TIME_MS || self.send_counter >= REKEY_AFTER_MESSAGES)
+    }
+
+    /// 受信時に鍵を作り直すべきか（期限切れの直前、開始側だけ）
+    pub fn needs_rekey_on_receive(&self, now_ms: u64) -> bool {
+        self.keys.initiator
+            && self.age(now_ms) >= REJECT_AFTER_TIME_MS - KEEPALIVE_TIMEOUT_MS - REKEY_TIMEOUT_MS
+    }
+
+    /// Transport メッセージを作る
+    ///
+    /// `payload` を `padded_len` バイトまで 0 で埋めてから暗号化する。
+    pub fn encrypt(&mut self, payload: &[u8], padded_len: usize) -> Vec<u8> {
+        let counter = self.send_counter;
+        self.send_counter += 1;
+
+        let padded_len = padded_len.max(payload.len());
+        let mut msg = vec![0u8; TRANSPORT_HEADER_LEN + padded_len + TAG_LEN];
+        msg[0] = MESSAGE_TRANSPORT;
+        msg[4..8].copy_from_slice(&self.keys.remote_index.to_le_bytes());
+        msg[8..16].copy_from_slice(&counter.to_le_bytes());
+        msg[TRANSPORT_HEADER_LEN..TRANSPORT_HEADER_LEN + payload.len()].copy_from_slice(payload);
+        crypto::seal(&self.keys.send, counter, &[], &mut msg[TRANSPORT_HEADER_LEN..]);
+        msg
+    }
+
+    /// Transport メッセージを復号する
+    pub fn decrypt(&mut self, msg: &[u8], now_ms: u64) -> Result<Vec<u8>, WgError> {
+        if self.age(now_ms) >= REJECT_AFTER_TIME_MS {
+            return Err(WgError::KeyExpired);
+        }
+        let mut counter = [0u8; 8];
+        counter.copy_from_slice(&msg[8..16]);
+        let counter = u64::from_le_bytes(counter);
+        if !self.replay.is_fresh(counter, REJECT_AFTER_MESSAGES) {
+            return Err(WgError::Replay);
+        }
+
+        let mut buffer = msg[TRANSPORT_HEADER_LEN..].to_vec();
+        let len = crypto::open(&self.keys.recv, counter, &[], &mut buffer)
+            .ok_or(WgError::Unauthenticated)?;
+        if !self.replay.accept(counter, REJECT_AFTER_MESSAGES) {
+            return Err(WgError::Replay);
+        }
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+}
+
+/// 応答を待っている Initiation
+pub struct PendingHandshake {
+    /// Response の検証に使う状態
+    pub state: InitiatorHandshake,
+    /// 最後に送った時刻
+    pub sent_ms: u64,
+    /// 最初に送った時刻（再送をあきらめる判断に使う）
+    pub started_ms: u64,
+    /// 次の再送までの揺らぎ
+    pub jitter_ms: u64,
+}
+
+/// ピア
+pub struct Peer {
+    /// 静的公開鍵
+    pub public_key: Key,
+    /// 事前共有鍵（未設定なら 0）
+    pub preshared_key: Key,
+    /// 送信先（最後に認証できたパケットの送信元に追従する）
+    pub endpoint: Option<UdpAddr>,
+    /// 永続キープアライブの間隔（秒、0 で無効）
+    pub persistent_keepalive: u16,
+    /// 最後にハンドシェイクを完了した時刻
+    pub last_handshake_ms: Option<u64>,
+    /// 受信したデータ量（復号後）
+    pub rx_bytes: u64,
+    /// 送信したデータ量（暗号化前）
+    pub tx_bytes: u64,
+    pub(super) macs: MacGenerator,
+    pub(super) handshake: Option<PendingHandshake>,
+    pub(super) current: Option<Session>,
+    pub(super) previous: Option<Session>,
+    /// 応答側で導出し、最初のデータを待っているセッション
+    pub(super) next: Option<Session>,
+    pub(super) staged: VecDeque<Vec<u8>>,
+    /// 最後に受け付けた Initiation のタイムスタンプ
+    pub(super) last_timestamp: Option<[u8; TIMESTAMP_LEN]>,
+    /// 最後に Initiation を受け付けた時刻
+    pub(super) last_initiation_ms: Option<u64>,
+    /// 最後に送った Initiation の時刻（TAI64N の単調増加用、Unix ナノ秒）
+    last_sent_timestamp_nanos: u64,
+    pub(super) last_sent_ms: Option<u64>,
+    pub(super) last_received_ms: Option<u64>,
+    /// 受け取ったデータに応答が無ければキープアライブを送る時刻
+    pub(super) keepalive_due: Option<u64>,
+    /// 送ったデータに応答が無ければハンドシェイクし直す時刻
+    pub(super) handshake_due: Option<u64>,
+}
+
+impl Peer {
+    /// 公開鍵から作る
+    pub fn new(public_key: Key) -> Self {
+        Peer {
+            public_key,
+            preshared_key: [0u8; KEY_LEN],
+            endpoint: None,
+            persistent_keepalive: 0,
+            last_handshake_ms: None,
+            rx_bytes: 0,
+            tx_bytes: 0,
+            macs: MacGenerator::new(&public_key),
+            handshake: None,
+            current: None,
+            previous: None,
+            next: None,
+            staged: VecDeque::new(),
+            last_timestamp: None,
+            last_initiation_ms: None,
+            last_sent_timestamp_nanos: 0,
+            last_sent_ms: None,
+            last_received_ms: None,
+            keepalive_due: None,
+            handshake_due: None,
+        }
+    }
+
+    /// 送信に使えるセッションがあるか
+    pub fn has_session(&self, now_ms: u64) -> bool {
+        self.current.as_ref().is_some_and(|s| s.can_send(now_ms))
+    }
+
+    /// ハンドシェイクの応答を待っているか
+    pub fn handshake_pending(&self) -> bool {
+        self.handshake.is_some()
+    }
+
+    /// 鍵待ちのパケット数
+    pub fn staged_len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// 次に送る Initiation のタイムスタンプ
+    ///
+    /// 相手は古いタイムスタンプを拒否するため、時計が進んでいなくても増やす。
+    pub(super) fn next_timestamp(&mut self, unix_nanos: u64) -> [u8; TIMESTAMP_LEN] {
+        let nanos = unix_nanos.max(self.last_sent_timestamp_nanos + TIMESTAMP_WHITENER as u64);
+        self.last_sent_timestamp_nanos = nanos;
+        crypto::tai64n(nanos)
+    }
+
+    /// 再送間隔の揺らぎ（乱数が得られなければ 0）
+    pub(super) fn random_jitter() -> u64 {
+        let mut bytes = [0u8; 8];
+        if crypto::random_bytes(&mut bytes).is_none() {
+            return 0;
+        }
+        u64::from_le_bytes(bytes) % REKEY_JITTER_MS
+    }
+
+    /// インデックスがこのピアのセッションかハンドシェイクのものか
+    pub(super) fn owns_index(&self, index: u32) -> bool {
+        self.handshake
+            .as_ref()
+            .is_some_and(|h| h.state.local_index == index)
+            || self.session(index).is_some()
+    }
+
+    fn session(&self, index: u32) -> Option<&Session> {
+        [&self.current, &self.previous, &self.next]
+            .into_iter()
+            .flatten()
+            .find(|s| s.keys.local_index == index)
+    }
+
+    /// 受信側インデックスのセッション（確認待ちなら true も返す）
+    pub(super) fn session_mut(&mut self, index: u32) -> Option<(&mut Session, bool)> {
+        let matches = |s: &Option<Session>| s.as_ref().is_some_and(|s| s.keys.local_index == index);
+        if matches(&self.current) {
+            self.current.as_mut().map(|s| (s, false))
+        } else if matches(&self.next) {
+            self.next.as_mut().map(|s| (s, true))
+        } else if matches(&self.previous) {
+            self.previous.as_mut().map(|s| (s, false))
+        } else {
+            None
+        }
+    }
+
+    /// 開始側で完了したセッションを使い始める
+    pub(super) fn install_initiator_session(&mut self, keys: SessionKeys, now_ms: u64) {
+        self.previous = self.current.take();
+        self.current = Some(Session::new(keys, now_ms));
+        self.next = None;
+        self.last_handshake_ms = Some(now_ms);
+    }
+
+    /// 応答側で導出したセッションを、相手が使うまで確認待ちにする
+    pub(super) fn install_responder_session(&mut self, keys: SessionKeys, now_ms: u64) {
+        self.next = Some(Session::new(keys, now_ms));
+        self.last_handshake_ms = Some(now_ms);
+    }
+
+    /// 確認待ちのセッションを現在のセッションにする
+    pub(super) fn confirm_next(&mut self) {
+        if let Some(next) = self.next.take() {
+            self.previous = self.current.replace(next);
+        }
+    }
+
+    /// 鍵を完全に捨てる時期を過ぎたセッションを消す
+    pub(super) fn expire_sessions(&mut self, now_ms: u64) {
+        let expired =
+            |s: &Option<Session>| s.as_ref().is_some_and(|s| s.age(now_ms) >= REJECT_AFTER_TIME_MS * 3);
+        for slot in [&mut self.current, &mut self.previous, &mut self.next] {
+            if expired(slot) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// 鍵を待つパケットを積む（あふれたら古いものを捨てる）
+    pub(super) fn stage(&mut self, packet: &[u8]) {
+        if self.staged.len() >= MAX_STAGED_PACKETS {
+            self.staged.pop_front();
+        }
+        self.staged.push_back(packet.to_vec());
+    }
+
+    /// 認証済みのパケットを送った
+    pub(super) fn record_sent(&mut self, now_ms: u64, data: bool) {
+        self.last_sent_ms = Some(now_ms);
+        self.keepalive_due = None;
+        if data && self.handshake_due.is_none() {
+            self.handshake_due = Some(now_ms + KEEPALIVE_TIMEOUT_MS + REKEY_TIMEOUT_MS);
+        }
+    }
+
+    /// 認証済みのパケットを受け取った
+    pub(super) fn record_received(&mut self, now_ms: u64, data: bool) {
+        self.last_received_ms = Some(now_ms);
+        self.handshake_due = None;
+        if data && self.keepalive_due.is_none() {
+            self.keepalive_due = Some(now_ms + KEEPALIVE_TIMEOUT_MS);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! リプレイ防止のカウンタ窓
+//!
+//! RFC 6479 のビットマップ方式。受け取った最大のカウンタから
+//! `WINDOW_SIZE` 以内で、まだ受け取っていないカウンタだけを受け付ける。
+//! 窓は 64 ビットのブロックを環状に並べ、前進するときはブロック単位で消す。
+
+/// ブロック数（2 の累乗）
+const RING_BLOCKS: usize = 32;
+/// 1 ブロックのビット数
+const BLOCK_BITS: u64 = 64;
+
+/// 受け付ける古さ（最大カウンタからの差）
+pub const WINDOW_SIZE: u64 = (RING_BLOCKS as u64 - 1) * BLOCK_BITS;
+
+/// リプレイ防止窓
+#[derive(Debug, Clone)]
+pub struct ReplayWindow {
+    /// 受け取った最大のカウンタ
+    last: u64,
+    ring: [u64; RING_BLOCKS],
+}
+
+impl ReplayWindow {
+    /// 何も受け取っていない窓
+    pub const fn new() -> Self {
+        ReplayWindow {
+            last: 0,
+            ring: [0; RING_BLOCKS],
+        }
+    }
+
+    /// 受け付けられるカウンタか（窓は動かさない）
+    ///
+    /// 復号の前に呼び、明らかなリプレイで AEAD を計算しないようにする。
+    pub fn is_fresh(&self, counter: u64, limit: u64) -> bool {
+        if counter >= limit {
+            return false;
+        }
+        if counter > self.last {
+            return true;
+        }
+        if self.last - counter > WINDOW_SIZE {
+            return false;
+        }
+        let (block, bit) = Self::position(counter);
+        self.ring[block] & bit == 0
+    }
+
+    /// カウンタを記録する（復号に成功した後に呼ぶ）
+    ///
+    /// 戻り値: 初めて見るカウンタなら true
+    pub fn accept(&mut self, counter: u64, limit: u64) -> bool {
+        if counter >= limit {
+            return false;
+        }
+        if counter > self.last {
+            // 新しく窓に入るブロックを消す
+            let current = self.last / BLOCK_BITS;
+            let target = counter / BLOCK_BITS;
+            let advance = (target - current).min(RING_BLOCKS as u64);
+            for i in 1..=advance {
+                self.ring[((current + i) as usize) & (RING_BLOCKS - 1)] = 0;
+            }
+            self.last = counter;
+        } else if self.last - counter > WINDOW_SIZE {
+            return false;
+        }
+        let (block, bit) = Self::position(counter);
+        let old = self.ring[block];
+        self.ring[block] = old | bit;
+        old & bit == 0
+    }
+
+    fn position(counter: u64) -> (usize, u64) {
+        let block = ((counter / BLOCK_BITS) as usize) & (RING_BLOCKS - 1);
+        (block, 1u64 << (counter % BLOCK_BITS))
+    }
+}
+
+impl Default for ReplayWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: u64 = u64::MAX;
+
+    #[test]
+    fn test_replay_window() {
+        let mut window = ReplayWindow::new();
+        assert!(window.accept(0, LIMIT));
+        assert!(!window.is_fresh(0, LIMIT));
+        assert!(!window.accept(0, LIMIT));
+
+        // 順不同でも一度ずつ受け付ける
+        assert!(window.accept(5, LIMIT));
+        assert!(window.accept(3, LIMIT));
+        assert!(!window.accept(5, LIMIT));
+        assert!(window.is_fresh(4, LIMIT));
+
+        // 窓より古いものは拒否
+        assert!(window.accept(WINDOW_SIZE + 10, LIMIT));
+        assert!(!window.is_fresh(9, LIMIT));
+        assert!(window.is_fresh(10, LIMIT));
+        assert!(window.accept(10, LIMIT));
+
+        // 大きく前進すると窓は空になる
+        assert!(window.accept(100_000, LIMIT));
+        assert!(window.accept(100_000 - WINDOW_SIZE, LIMIT));
+        assert!(!window.accept(100_000 - WINDOW_SIZE - 1, LIMIT));
+
+        // 上限以上のカウンタは受け付けない
+        assert!(!window.is_fresh(200_000, 200_000));
+        assert!(!window.accept(200_000, 200_000));
+    }
+}
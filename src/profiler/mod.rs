@@ -324,6 +324,11 @@ impl CpuProfiler {
         self.enabled.store(false, Ordering::SeqCst);
     }
 
+    /// プロファイリング中か
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
     /// サンプルを記録
     pub fn record_sample(&self) {
         if !self.enabled.load(Ordering::Relaxed) {
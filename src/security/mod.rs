@@ -572,6 +572,9 @@ pub fn init() {
     // カーネルドメイン（ID=0）の権限を設定
     ACCESS_CONTROL.set_capabilities(0, SecurityCapabilities::KERNEL);
 
+    // ポリシーエンジン（ドメインごとのネットワークポート制限など）
+    policy::init();
+
     crate::log!("[SECURITY] Security framework initialized\n");
     crate::log!("[SECURITY] Audit logging: enabled\n");
 }
@@ -121,6 +121,30 @@ impl PolicyObject {
             _ => false,
         }
     }
+
+    /// Check if this object matches a network protocol and port
+    ///
+    /// パターン: `tcp:80`, `udp:5000-6000`, `tcp:*`, `*:53`, `tcp`（全ポート）。
+    /// Any は一致させない（ネットワーク判定は Network オブジェクトのみが対象）。
+    pub fn matches_network(&self, protocol: &str, port: u16) -> bool {
+        let PolicyObject::Network(pattern) = self else {
+            return false;
+        };
+        let (proto, ports) = pattern.split_once(':').unwrap_or((pattern.as_str(), "*"));
+        if proto != "*" && !proto.eq_ignore_ascii_case(protocol) {
+            return false;
+        }
+        if ports == "*" {
+            return true;
+        }
+        match ports.split_once('-') {
+            Some((start, end)) => match (start.parse::<u16>(), end.parse::<u16>()) {
+                (Ok(start), Ok(end)) => (start..=end).contains(&port),
+                _ => false,
+            },
+            None => ports.parse::<u16>() == Ok(port),
+        }
+    }
 }
 
 impl Default for PolicyObject {
@@ -274,6 +298,34 @@ impl PolicyRule {
 
         true
     }
+
+    /// Check if rule matches a network (protocol/port) request
+    pub fn matches_network(
+        &self,
+        domain_id: u64,
+        domain_type: &str,
+        protocol: &str,
+        port: u16,
+        operation: PolicyOperation,
+    ) -> bool {
+        if !self.enabled {
+            return false;
+        }
+
+        if !self.subject.matches_domain(domain_id, domain_type) {
+            return false;
+        }
+
+        if !self.object.matches_network(protocol, port) {
+            return false;
+        }
+
+        if self.operation != PolicyOperation::Any && self.operation != operation {
+            return false;
+        }
+
+        true
+    }
 }
 
 impl fmt::Display for PolicyRule {
@@ -449,6 +501,41 @@ impl SecurityPolicy {
         PolicyDecision::default_deny()
     }
 
+    /// Check network policy for a socket operation
+    ///
+    /// Network オブジェクトを持つルールのみを評価し、一致するルールが
+    /// なければ許可する（ポート制限はドメインごとのオプトイン）。
+    pub fn check_network(
+        &mut self,
+        domain_id: u64,
+        domain_type: &str,
+        protocol: &str,
+        port: u16,
+        operation: PolicyOperation,
+    ) -> PolicyDecision {
+        self.stats.total_checks += 1;
+
+        if self.enabled {
+            for rule in &self.rules {
+                if rule.matches_network(domain_id, domain_type, protocol, port, operation) {
+                    if rule.action.is_allow() {
+                        self.stats.allowed += 1;
+                    } else {
+                        self.stats.denied += 1;
+                    }
+                    return PolicyDecision::from_rule(rule);
+                }
+            }
+        }
+
+        self.stats.allowed += 1;
+        PolicyDecision {
+            action: PolicyAction::Allow,
+            matched_rule: None,
+            audit_message: None,
+        }
+    }
+
     /// Enable/disable policy
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
@@ -536,6 +623,30 @@ pub fn check_path_policy(
         .check_path(domain_id, domain_type, path, operation)
 }
 
+/// Check network policy for a socket operation
+pub fn check_network_policy(
+    domain_id: u64,
+    domain_type: &str,
+    protocol: &str,
+    port: u16,
+    operation: PolicyOperation,
+) -> PolicyDecision {
+    POLICY
+        .write()
+        .check_network(domain_id, domain_type, protocol, port, operation)
+}
+
+/// Get rules with a network object
+pub fn network_rules() -> Vec<PolicyRule> {
+    POLICY
+        .read()
+        .rules()
+        .iter()
+        .filter(|r| matches!(r.object, PolicyObject::Network(_)))
+        .cloned()
+        .collect()
+}
+
 /// Add a rule to global policy
 pub fn add_rule(rule: PolicyRule) {
     POLICY.write().add_rule(rule);
@@ -631,4 +742,57 @@ mod tests {
         let decision = policy.check(1, "app", 0, "file", PolicyOperation::Write);
         assert!(!decision.action.is_allow());
     }
+
+    #[test]
+    fn test_network_matching() {
+        let object = PolicyObject::Network(String::from("tcp:8000-8100"));
+        assert!(object.matches_network("tcp", 8080));
+        assert!(object.matches_network("TCP", 8000));
+        assert!(!object.matches_network("tcp", 8101));
+        assert!(!object.matches_network("udp", 8080));
+
+        assert!(PolicyObject::Network(String::from("*:53")).matches_network("udp", 53));
+        assert!(PolicyObject::Network(String::from("udp")).matches_network("udp", 1234));
+        assert!(!PolicyObject::Any.matches_network("tcp", 80));
+    }
+
+    #[test]
+    fn test_network_policy() {
+        let mut policy = SecurityPolicy::new("test");
+
+        // 全体のデフォルト拒否はネットワーク判定に影響しない
+        policy.add_rule(
+            PolicyRule::new(
+                PolicySubject::Any,
+                PolicyObject::Any,
+                PolicyOperation::Any,
+                PolicyAction::Deny,
+            )
+            .with_priority(0),
+        );
+        // ドメイン2は 8080 のみ待ち受け可能
+        policy.add_rule(
+            PolicyRule::new(
+                PolicySubject::Domain(2),
+                PolicyObject::Network(String::from("tcp:8080")),
+                PolicyOperation::Receive,
+                PolicyAction::Allow,
+            )
+            .with_priority(200),
+        );
+        policy.add_rule(PolicyRule::new(
+            PolicySubject::Domain(2),
+            PolicyObject::Network(String::from("*:*")),
+            PolicyOperation::Receive,
+            PolicyAction::Deny,
+        ));
+
+        let check = |policy: &mut SecurityPolicy, domain, port, op| {
+            policy.check_network(domain, "app", "tcp", port, op).action.is_allow()
+        };
+        assert!(check(&mut policy, 2, 8080, PolicyOperation::Receive));
+        assert!(!check(&mut policy, 2, 80, PolicyOperation::Receive));
+        assert!(check(&mut policy, 2, 80, PolicyOperation::Send));
+        assert!(check(&mut policy, 3, 80, PolicyOperation::Receive));
+    }
 }
@@ -124,4 +124,1044 @@ impl NetNamespace {
         }
         ExoValue::Array(results)
     }
-}
+
+    /// TCP輻輳制御アルゴリズム（引数なし: 取得、あり: システムデフォルトを設定）
+    pub fn congestion(algorithm: Option<&str>) -> ExoValue {
+        if let Some(name) = algorithm {
+            if let Err(e) = crate::net::set_congestion_algorithm(name) {
+                return ExoValue::Error(e);
+            }
+        }
+        let mut map = BTreeMap::new();
+        map.insert(
+            String::from("default"),
+            ExoValue::String(String::from(crate::net::get_congestion_algorithm())),
+        );
+        map.insert(
+            String::from("available"),
+            ExoValue::Array(
+                ["newreno", "cubic", "bbr"]
+                    .iter()
+                    .map(|name| ExoValue::String(String::from(*name)))
+                    .collect(),
+            ),
+        );
+        ExoValue::Map(map)
+    }
+
+    /// TCP接続一覧（輻輳制御・RTT統計付き）
+    pub fn tcp_connections() -> ExoValue {
+        let Some(connections) = crate::net::get_tcp_connections() else {
+            return ExoValue::Array(Vec::new());
+        };
+        let values: Vec<ExoValue> = connections
+            .into_iter()
+            .map(|c| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("local"), ExoValue::String(c.local_addr));
+                map.insert(String::from("remote"), ExoValue::String(c.remote_addr));
+                map.insert(String::from("state"), ExoValue::String(c.state));
+                map.insert(
+                    String::from("cc"),
+                    ExoValue::String(String::from(c.stats.congestion_algorithm)),
+                );
+                map.insert(String::from("cwnd"), ExoValue::Int(c.stats.cwnd as i64));
+                map.insert(String::from("ssthresh"), ExoValue::Int(c.stats.ssthresh as i64));
+                map.insert(
+                    String::from("in_flight"),
+                    ExoValue::Int(c.stats.bytes_in_flight as i64),
+                );
+                map.insert(
+                    String::from("pacing_rate"),
+                    ExoValue::Int(c.stats.pacing_rate as i64),
+                );
+                map.insert(String::from("rtt_us"), ExoValue::Int(c.stats.rtt_us as i64));
+                map.insert(String::from("srtt_us"), ExoValue::Int(c.stats.srtt_us as i64));
+                map.insert(
+                    String::from("min_rtt_us"),
+                    ExoValue::Int(c.stats.min_rtt_us as i64),
+                );
+                map.insert(
+                    String::from("rtt_samples"),
+                    ExoValue::Array(
+                        c.stats
+                            .recent_rtt_us
+                            .iter()
+                            .map(|rtt| ExoValue::Int(*rtt as i64))
+                            .collect(),
+                    ),
+                );
+                map.insert(
+                    String::from("retransmissions"),
+                    ExoValue::Int(c.stats.retransmissions as i64),
+                );
+                map.insert(
+                    String::from("paws_rejected"),
+                    ExoValue::Int(c.stats.paws_rejected as i64),
+                );
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// インターフェース一覧（アドレス・統計付き）
+    pub fn interfaces() -> ExoValue {
+        let Some(interfaces) = crate::net::get_interfaces() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = interfaces
+            .into_iter()
+            .map(|iface| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(iface.name));
+                map.insert(String::from("kind"), ExoValue::String(String::from(iface.kind)));
+                map.insert(
+                    String::from("mac"),
+                    ExoValue::String(format!(
+                        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                        iface.mac[0], iface.mac[1], iface.mac[2],
+                        iface.mac[3], iface.mac[4], iface.mac[5]
+                    )),
+                );
+                map.insert(String::from("mtu"), ExoValue::Int(iface.mtu as i64));
+                map.insert(String::from("up"), ExoValue::Bool(iface.up));
+                map.insert(
+                    String::from("addresses"),
+                    ExoValue::Array(iface.addresses.into_iter().map(ExoValue::String).collect()),
+                );
+                map.insert(String::from("rx_packets"), ExoValue::Int(iface.stats.rx_packets as i64));
+                map.insert(String::from("tx_packets"), ExoValue::Int(iface.stats.tx_packets as i64));
+                map.insert(String::from("rx_bytes"), ExoValue::Int(iface.stats.rx_bytes as i64));
+                map.insert(String::from("tx_bytes"), ExoValue::Int(iface.stats.tx_bytes as i64));
+                map.insert(String::from("tx_errors"), ExoValue::Int(iface.stats.tx_errors as i64));
+                map.insert(String::from("dropped"), ExoValue::Int(iface.stats.dropped as i64));
+                if let Some(master) = iface.master {
+                    map.insert(String::from("master"), ExoValue::String(master));
+                }
+                if let Some(vlan_id) = iface.vlan_id {
+                    map.insert(String::from("vlan"), ExoValue::Int(vlan_id as i64));
+                }
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// インターフェースにアドレスを追加
+    pub fn addr_add(name: &str, ip: [u8; 4], prefix_len: u8) -> ExoValue {
+        match crate::net::add_interface_address(name, ip, prefix_len) {
+            Ok(()) => Self::interfaces(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// インターフェースからアドレスを削除
+    pub fn addr_del(name: &str, ip: [u8; 4]) -> ExoValue {
+        match crate::net::remove_interface_address(name, ip) {
+            Ok(()) => Self::interfaces(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// インターフェースのup/down
+    pub fn link(name: &str, up: bool) -> ExoValue {
+        match crate::net::set_interface_up(name, up) {
+            Ok(()) => Self::interfaces(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ルーティングテーブル
+    pub fn routes() -> ExoValue {
+        let Some(routes) = crate::net::get_routes() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = routes
+            .into_iter()
+            .map(|r| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("destination"), ExoValue::String(r.destination));
+                map.insert(
+                    String::from("gateway"),
+                    match r.gateway {
+                        Some(gw) => ExoValue::String(format!("{}.{}.{}.{}", gw[0], gw[1], gw[2], gw[3])),
+                        None => ExoValue::Nil,
+                    },
+                );
+                map.insert(String::from("interface"), ExoValue::String(r.interface));
+                map.insert(String::from("metric"), ExoValue::Int(r.metric as i64));
+                map.insert(
+                    String::from("source"),
+                    match r.source {
+                        Some(src) => ExoValue::String(format!("{}.{}.{}.{}", src[0], src[1], src[2], src[3])),
+                        None => ExoValue::Nil,
+                    },
+                );
+                map.insert(String::from("origin"), ExoValue::String(String::from(r.origin)));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// 静的ルートを追加（gateway/interfaceのどちらかが必要）
+    pub fn route_add(
+        destination: [u8; 4],
+        prefix_len: u8,
+        gateway: Option<[u8; 4]>,
+        interface: Option<&str>,
+        metric: Option<u32>,
+    ) -> ExoValue {
+        match crate::net::add_route(destination, prefix_len, gateway, interface, metric) {
+            Ok(()) => Self::routes(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ルートを削除
+    pub fn route_del(destination: [u8; 4], prefix_len: u8, interface: Option<&str>) -> ExoValue {
+        match crate::net::remove_route(destination, prefix_len, interface) {
+            Ok(removed) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("removed"), ExoValue::Int(removed as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// 宛先への経路を解決（出力インターフェース・ネクストホップ・送信元アドレス）
+    pub fn route_get(destination: [u8; 4]) -> ExoValue {
+        match crate::net::route_lookup(destination) {
+            Ok(lookup) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("interface"), ExoValue::String(lookup.interface));
+                map.insert(
+                    String::from("next_hop"),
+                    ExoValue::String(format!(
+                        "{}.{}.{}.{}",
+                        lookup.next_hop[0], lookup.next_hop[1], lookup.next_hop[2], lookup.next_hop[3]
+                    )),
+                );
+                map.insert(
+                    String::from("source"),
+                    ExoValue::String(format!(
+                        "{}.{}.{}.{}",
+                        lookup.source[0], lookup.source[1], lookup.source[2], lookup.source[3]
+                    )),
+                );
+                map.insert(String::from("mtu"), ExoValue::Int(lookup.mtu as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// パケットキャプチャ開始（BPF風フィルタ: "tcp port 80 and host 10.0.2.2" 等）
+    pub fn capture_start(filter: Option<&str>, max_packets: Option<usize>) -> ExoValue {
+        match crate::net::start_capture(filter, max_packets) {
+            Ok(()) => Self::capture_stats(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// パケットキャプチャ停止
+    pub fn capture_stop() -> ExoValue {
+        crate::net::stop_capture();
+        Self::capture_stats()
+    }
+
+    /// キャプチャ状態・統計
+    pub fn capture_stats() -> ExoValue {
+        let Some(status) = crate::net::get_capture_status() else {
+            return ExoValue::Error(String::from("No capture session"));
+        };
+        let mut map = BTreeMap::new();
+        map.insert(String::from("active"), ExoValue::Bool(status.active));
+        map.insert(String::from("filter"), ExoValue::String(status.filter));
+        map.insert(String::from("seen"), ExoValue::Int(status.stats.seen as i64));
+        map.insert(String::from("captured"), ExoValue::Int(status.stats.captured as i64));
+        map.insert(String::from("filtered"), ExoValue::Int(status.stats.filtered as i64));
+        map.insert(String::from("overwritten"), ExoValue::Int(status.stats.overwritten as i64));
+        map.insert(String::from("dropped"), ExoValue::Int(status.stats.dropped as i64));
+        map.insert(String::from("packets"), ExoValue::Int(status.stats.packets as i64));
+        map.insert(String::from("bytes"), ExoValue::Int(status.stats.bytes as i64));
+        ExoValue::Map(map)
+    }
+
+    /// キャプチャ済みパケットの要約（直近 limit 件、古い順）
+    pub fn capture_show(limit: usize) -> ExoValue {
+        let values: Vec<ExoValue> = crate::net::get_captured_packets(limit)
+            .into_iter()
+            .map(|p| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("time_us"), ExoValue::Int(p.timestamp_us as i64));
+                map.insert(String::from("dir"), ExoValue::String(String::from(p.direction)));
+                map.insert(String::from("len"), ExoValue::Int(p.length as i64));
+                map.insert(String::from("summary"), ExoValue::String(p.summary));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// pcapngファイルとして保存
+    pub fn capture_save(path: &str) -> ExoValue {
+        match crate::net::save_capture(path) {
+            Ok(bytes) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("path"), ExoValue::String(String::from(path)));
+                map.insert(String::from("bytes"), ExoValue::Int(bytes as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// pcapngをシリアルポート（COM2）へ送信
+    pub fn capture_serial() -> ExoValue {
+        match crate::net::stream_capture_serial() {
+            Ok(bytes) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("port"), ExoValue::String(String::from("COM2")));
+                map.insert(String::from("bytes"), ExoValue::Int(bytes as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ファイアウォールルールを追加（例: "input tcp dport 22 state new accept"）
+    pub fn fw_add(spec: &str, position: Option<usize>) -> ExoValue {
+        match crate::net::add_firewall_rule(spec, position) {
+            Ok(id) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("id"), ExoValue::Int(id as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ファイアウォールルールを削除
+    pub fn fw_del(id: u32) -> ExoValue {
+        match crate::net::delete_firewall_rule(id) {
+            Ok(()) => Self::fw_list(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ファイアウォールルール一覧（評価順、カウンタ付き）
+    pub fn fw_list() -> ExoValue {
+        let Some(rules) = crate::net::get_firewall_rules() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = rules
+            .into_iter()
+            .map(|r| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("id"), ExoValue::Int(r.id as i64));
+                map.insert(String::from("chain"), ExoValue::String(String::from(r.chain)));
+                map.insert(String::from("rule"), ExoValue::String(r.rule));
+                map.insert(String::from("packets"), ExoValue::Int(r.packets as i64));
+                map.insert(String::from("bytes"), ExoValue::Int(r.bytes as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// チェーンのポリシーとカウンタ
+    pub fn fw_chains() -> ExoValue {
+        let Some(chains) = crate::net::get_firewall_chains() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = chains
+            .into_iter()
+            .map(|c| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("chain"), ExoValue::String(String::from(c.chain)));
+                map.insert(String::from("policy"), ExoValue::String(String::from(c.policy)));
+                map.insert(String::from("accepted"), ExoValue::Int(c.accepted as i64));
+                map.insert(String::from("dropped"), ExoValue::Int(c.dropped as i64));
+                map.insert(String::from("policy_packets"), ExoValue::Int(c.policy_packets as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// チェーンのデフォルトポリシーを設定（"accept" / "drop"）
+    pub fn fw_policy(chain: &str, action: &str) -> ExoValue {
+        match crate::net::set_firewall_policy(chain, action) {
+            Ok(()) => Self::fw_chains(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ルールを全削除（チェーン指定可）
+    pub fn fw_flush(chain: Option<&str>) -> ExoValue {
+        match crate::net::flush_firewall(chain) {
+            Ok(removed) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("removed"), ExoValue::Int(removed as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// コネクション追跡テーブル
+    pub fn conntrack() -> ExoValue {
+        let Some(entries) = crate::net::get_conntrack() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = entries
+            .into_iter()
+            .map(|c| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("proto"), ExoValue::String(String::from(c.protocol)));
+                map.insert(String::from("flow"), ExoValue::String(c.flow));
+                map.insert(
+                    String::from("tcp_state"),
+                    match c.tcp_state {
+                        Some(state) => ExoValue::String(String::from(state)),
+                        None => ExoValue::Nil,
+                    },
+                );
+                map.insert(String::from("replied"), ExoValue::Bool(c.replied));
+                map.insert(String::from("packets"), ExoValue::Int(c.packets as i64));
+                map.insert(String::from("bytes"), ExoValue::Int(c.bytes as i64));
+                map.insert(String::from("expires_ms"), ExoValue::Int(c.expires_ms as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// コネクション追跡テーブルを消去
+    pub fn conntrack_flush() -> ExoValue {
+        match crate::net::flush_conntrack() {
+            Ok(()) => ExoValue::Nil,
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ドメインのポート利用を許可/拒否（security::policy）
+    pub fn port_policy(domain: Option<u64>, ports: &str, direction: Option<&str>, allow: bool) -> ExoValue {
+        match crate::net::add_port_policy(domain, ports, direction, allow) {
+            Ok(_) => Self::port_rules(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ポートポリシー一覧
+    pub fn port_rules() -> ExoValue {
+        let values: Vec<ExoValue> = crate::net::get_port_policies()
+            .into_iter()
+            .map(|p| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("id"), ExoValue::Int(p.id as i64));
+                map.insert(
+                    String::from("domain"),
+                    match p.domain {
+                        Some(id) => ExoValue::Int(id as i64),
+                        None => ExoValue::String(String::from("*")),
+                    },
+                );
+                map.insert(String::from("ports"), ExoValue::String(p.ports));
+                map.insert(String::from("direction"), ExoValue::String(String::from(p.direction)));
+                map.insert(String::from("action"), ExoValue::String(String::from(p.action)));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// ポートポリシーを削除
+    pub fn port_del(id: u64) -> ExoValue {
+        match crate::net::remove_port_policy(id) {
+            Ok(()) => Self::port_rules(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// 名前解決（`.local` は mDNS、async版）
+    pub async fn resolve(hostname: &str) -> ExoValue {
+        match crate::net::dns_resolve_async(hostname).await {
+            Ok(addrs) => ExoValue::Array(
+                addrs
+                    .iter()
+                    .map(|a| ExoValue::String(format!("{}.{}.{}.{}", a[0], a[1], a[2], a[3])))
+                    .collect(),
+            ),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// mDNS レスポンダの状態（ホスト名・公開サービス・キャッシュ）
+    pub fn mdns() -> ExoValue {
+        let info = crate::net::get_mdns_info();
+        let services: Vec<ExoValue> = info
+            .services
+            .into_iter()
+            .map(|s| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(s.name));
+                map.insert(String::from("type"), ExoValue::String(s.service_type));
+                map.insert(String::from("port"), ExoValue::Int(s.port as i64));
+                map.insert(
+                    String::from("txt"),
+                    ExoValue::Array(s.txt.into_iter().map(ExoValue::String).collect()),
+                );
+                ExoValue::Map(map)
+            })
+            .collect();
+        let cache: Vec<ExoValue> = info
+            .cache
+            .into_iter()
+            .map(|r| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(r.name));
+                map.insert(String::from("type"), ExoValue::String(String::from(r.rtype)));
+                map.insert(String::from("data"), ExoValue::String(r.data));
+                map.insert(String::from("ttl"), ExoValue::Int(r.ttl as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+
+        let mut stats = BTreeMap::new();
+        stats.insert(String::from("queries_received"), ExoValue::Int(info.stats.queries_received as i64));
+        stats.insert(String::from("responses_sent"), ExoValue::Int(info.stats.responses_sent as i64));
+        stats.insert(String::from("queries_sent"), ExoValue::Int(info.stats.queries_sent as i64));
+        stats.insert(String::from("responses_received"), ExoValue::Int(info.stats.responses_received as i64));
+        stats.insert(String::from("errors"), ExoValue::Int(info.stats.errors as i64));
+
+        let mut map = BTreeMap::new();
+        map.insert(String::from("hostname"), ExoValue::String(info.hostname));
+        map.insert(String::from("services"), ExoValue::Array(services));
+        map.insert(String::from("cache"), ExoValue::Array(cache));
+        map.insert(String::from("stats"), ExoValue::Map(stats));
+        ExoValue::Map(map)
+    }
+
+    /// mDNS ホスト名を変更
+    pub fn mdns_host(hostname: &str) -> ExoValue {
+        match crate::net::set_mdns_hostname(hostname) {
+            Ok(()) => Self::mdns(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// DNS-SD サービスを公開
+    pub fn mdns_add(service_type: &str, port: u16, txt: &[&str]) -> ExoValue {
+        match crate::net::register_mdns_service(service_type, port, txt) {
+            Ok(()) => Self::mdns(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// DNS-SD サービスの公開を停止
+    pub fn mdns_del(service_type: &str) -> ExoValue {
+        match crate::net::unregister_mdns_service(service_type) {
+            Ok(()) => Self::mdns(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// リンク上のサービスを探索（async版）
+    pub async fn mdns_browse(service_type: &str) -> ExoValue {
+        match crate::net::mdns_browse(service_type).await {
+            Ok(instances) => ExoValue::Array(
+                instances
+                    .into_iter()
+                    .map(|i| {
+                        let mut map = BTreeMap::new();
+                        map.insert(String::from("name"), ExoValue::String(i.name));
+                        map.insert(
+                            String::from("host"),
+                            i.host.map_or(ExoValue::Nil, ExoValue::String),
+                        );
+                        map.insert(
+                            String::from("port"),
+                            i.port.map_or(ExoValue::Nil, |p| ExoValue::Int(p as i64)),
+                        );
+                        map.insert(
+                            String::from("address"),
+                            i.address
+                                .map_or(ExoValue::Nil, |a| ExoValue::String(format!("{}", a))),
+                        );
+                        map.insert(
+                            String::from("txt"),
+                            ExoValue::Array(i.txt.into_iter().map(ExoValue::String).collect()),
+                        );
+                        ExoValue::Map(map)
+                    })
+                    .collect(),
+            ),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// 参加中のマルチキャストグループ
+    pub fn mcast() -> ExoValue {
+        let Some(groups) = crate::net::get_multicast_groups() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        ExoValue::Array(
+            groups
+                .into_iter()
+                .map(|g| {
+                    let mut map = BTreeMap::new();
+                    map.insert(String::from("interface"), ExoValue::String(g.interface));
+                    map.insert(
+                        String::from("group"),
+                        ExoValue::String(format!(
+                            "{}.{}.{}.{}",
+                            g.group[0], g.group[1], g.group[2], g.group[3]
+                        )),
+                    );
+                    ExoValue::Map(map)
+                })
+                .collect(),
+        )
+    }
+
+    /// マルチキャストグループに参加/脱退（IGMP Report / Leave を送信）
+    pub fn mcast_member(interface: &str, group: [u8; 4], join: bool) -> ExoValue {
+        let result = if join {
+            crate::net::join_multicast_group(interface, group)
+        } else {
+            crate::net::leave_multicast_group(interface, group)
+        };
+        match result {
+            Ok(()) => Self::mcast(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// DHCP サーバーの状態とリース一覧
+    pub fn dhcpd() -> ExoValue {
+        use crate::net::dhcp::server::{self, LeaseState};
+
+        let now = crate::time::now();
+        let leases: Vec<ExoValue> = server::leases()
+            .into_iter()
+            .map(|lease| {
+                let state = match lease.state {
+                    LeaseState::Offered => "offered",
+                    LeaseState::Bound => "bound",
+                    LeaseState::Declined => "declined",
+                };
+                let mut map = BTreeMap::new();
+                map.insert(String::from("mac"), ExoValue::String(format!("{}", lease.mac)));
+                map.insert(String::from("address"), ExoValue::String(format!("{}", lease.address)));
+                map.insert(String::from("state"), ExoValue::String(String::from(state)));
+                map.insert(
+                    String::from("expires_s"),
+                    ExoValue::Int(lease.expires.saturating_sub(now) as i64),
+                );
+                map.insert(
+                    String::from("hostname"),
+                    lease.hostname.map_or(ExoValue::Nil, ExoValue::String),
+                );
+                ExoValue::Map(map)
+            })
+            .collect();
+
+        let mut map = BTreeMap::new();
+        map.insert(String::from("running"), ExoValue::Bool(server::is_running()));
+        map.insert(String::from("leases"), ExoValue::Array(leases));
+        ExoValue::Map(map)
+    }
+
+    /// DHCP サーバーを開始（サーバー自身をルーター・DNS として配る）
+    pub fn dhcpd_start(address: [u8; 4], prefix_len: u8, start: [u8; 4], end: [u8; 4]) -> ExoValue {
+        use crate::net::dhcp::server::{self, DhcpServerConfig};
+        use crate::net::ipv4::Ipv4Address;
+
+        let mask = Ipv4Address::from_u32(u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0));
+        let config = DhcpServerConfig::new(Ipv4Address::new(address), mask)
+            .pool(Ipv4Address::new(start), Ipv4Address::new(end));
+        match server::start(config) {
+            Ok(()) => Self::dhcpd(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// 固定割り当てを追加
+    pub fn dhcpd_static(mac: &str, address: [u8; 4]) -> ExoValue {
+        use crate::net::ethernet::MacAddress;
+
+        let Some(mac) = MacAddress::parse(mac) else {
+            return ExoValue::Error(format!("Invalid MAC address: {}", mac));
+        };
+        let address = crate::net::ipv4::Ipv4Address::new(address);
+        match crate::net::dhcp::server::add_static_lease(mac, address) {
+            Ok(()) => Self::dhcpd(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// DHCP サーバーを停止
+    pub fn dhcpd_stop() -> ExoValue {
+        crate::net::dhcp::server::stop();
+        Self::dhcpd()
+    }
+
+    /// DNS フォワーダの状態と統計
+    pub fn dnsd() -> ExoValue {
+        use crate::net::dns::forwarder;
+        use core::sync::atomic::Ordering;
+
+        let stats = forwarder::stats();
+        let mut map = BTreeMap::new();
+        map.insert(
+            String::from("port"),
+            forwarder::running_port().map_or(ExoValue::Nil, |port| ExoValue::Int(port as i64)),
+        );
+        map.insert(String::from("queries"), ExoValue::Int(stats.queries.load(Ordering::Relaxed) as i64));
+        map.insert(String::from("cache_hits"), ExoValue::Int(stats.cache_hits.load(Ordering::Relaxed) as i64));
+        map.insert(String::from("forwarded"), ExoValue::Int(stats.forwarded.load(Ordering::Relaxed) as i64));
+        map.insert(String::from("failures"), ExoValue::Int(stats.failures.load(Ordering::Relaxed) as i64));
+        let cache: Vec<ExoValue> = forwarder::cache_entries()
+            .into_iter()
+            .map(|(key, ttl, records)| {
+                let mut entry = BTreeMap::new();
+                entry.insert(String::from("key"), ExoValue::String(key));
+                entry.insert(String::from("ttl"), ExoValue::Int(ttl as i64));
+                entry.insert(String::from("records"), ExoValue::Int(records as i64));
+                ExoValue::Map(entry)
+            })
+            .collect();
+        map.insert(String::from("cache"), ExoValue::Array(cache));
+        ExoValue::Map(map)
+    }
+
+    /// DNS フォワーダを開始（上流を省略するとリゾルバの設定を使う）
+    pub fn dnsd_start(upstream: Option<[u8; 4]>) -> ExoValue {
+        use crate::net::dns::forwarder::{self, ForwarderConfig};
+
+        let config = ForwarderConfig {
+            upstreams: upstream
+                .map(|ip| alloc::vec![crate::net::ipv4::Ipv4Address::new(ip)])
+                .unwrap_or_default(),
+            ..ForwarderConfig::default()
+        };
+        match forwarder::start(config) {
+            Ok(()) => Self::dnsd(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// DNS フォワーダを停止してキャッシュを消す
+    pub fn dnsd_stop() -> ExoValue {
+        crate::net::dns::forwarder::stop();
+        crate::net::dns::forwarder::flush_cache();
+        Self::dnsd()
+    }
+
+    /// IPv4 転送の状態（`enabled` 指定時は切り替えてから表示）
+    pub fn forward(enabled: Option<bool>) -> ExoValue {
+        if let Some(enabled) = enabled
+            && let Err(e) = crate::net::set_forwarding(enabled)
+        {
+            return ExoValue::Error(e);
+        }
+        let Some(info) = crate::net::get_forwarding() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let mut map = BTreeMap::new();
+        map.insert(String::from("enabled"), ExoValue::Bool(info.enabled));
+        map.insert(
+            String::from("masquerade"),
+            ExoValue::Array(info.masquerade.into_iter().map(ExoValue::String).collect()),
+        );
+        map.insert(String::from("forwarded"), ExoValue::Int(info.forwarded as i64));
+        map.insert(String::from("ttl_exceeded"), ExoValue::Int(info.ttl_exceeded as i64));
+        map.insert(String::from("no_route"), ExoValue::Int(info.no_route as i64));
+        map.insert(String::from("too_big"), ExoValue::Int(info.too_big as i64));
+        map.insert(String::from("filtered"), ExoValue::Int(info.filtered as i64));
+        map.insert(String::from("nat_dropped"), ExoValue::Int(info.nat_dropped as i64));
+        map.insert(String::from("nat_entries"), ExoValue::Int(info.nat_entries as i64));
+        ExoValue::Map(map)
+    }
+
+    /// マスカレードを有効化/無効化
+    pub fn masquerade(iface: &str, enable: bool) -> ExoValue {
+        let result = if enable {
+            crate::net::add_masquerade(iface)
+        } else {
+            crate::net::remove_masquerade(iface)
+        };
+        match result {
+            Ok(()) => Self::forward(None),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ポート転送ルールを追加
+    pub fn portfwd(protocol: &str, port: u16, to: [u8; 4], to_port: u16, iface: Option<&str>) -> ExoValue {
+        match crate::net::add_port_forward(protocol, port, to, to_port, iface) {
+            Ok(_) => Self::portfwd_list(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ポート転送ルールを削除
+    pub fn portfwd_del(id: u32) -> ExoValue {
+        match crate::net::remove_port_forward(id) {
+            Ok(()) => Self::portfwd_list(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ポート転送ルール一覧
+    pub fn portfwd_list() -> ExoValue {
+        let Some(rules) = crate::net::get_port_forwards() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = rules
+            .into_iter()
+            .map(|r| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("id"), ExoValue::Int(r.id as i64));
+                map.insert(String::from("rule"), ExoValue::String(r.rule));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// NAT 変換テーブル
+    pub fn nat() -> ExoValue {
+        let Some(entries) = crate::net::get_nat_entries() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = entries
+            .into_iter()
+            .map(|e| {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("proto"), ExoValue::String(String::from(e.protocol)));
+                map.insert(String::from("original"), ExoValue::String(e.original));
+                map.insert(String::from("translated"), ExoValue::String(e.translated));
+                map.insert(String::from("replied"), ExoValue::Bool(e.replied));
+                map.insert(String::from("packets"), ExoValue::Int(e.packets as i64));
+                map.insert(String::from("bytes"), ExoValue::Int(e.bytes as i64));
+                map.insert(String::from("expires_ms"), ExoValue::Int(e.expires_ms as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// WireGuard インターフェース一覧
+    pub fn wg(iface: Option<&str>) -> ExoValue {
+        let Some(infos) = crate::net::get_wireguard() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = infos
+            .into_iter()
+            .filter(|info| iface.is_none_or(|name| info.name == name))
+            .map(|info| {
+                let peers: Vec<ExoValue> = info
+                    .peers
+                    .into_iter()
+                    .map(|p| {
+                        let mut map = BTreeMap::new();
+                        map.insert(String::from("public_key"), ExoValue::String(p.public_key));
+                        map.insert(
+                            String::from("endpoint"),
+                            p.endpoint.map(ExoValue::String).unwrap_or(ExoValue::Nil),
+                        );
+                        map.insert(
+                            String::from("allowed_ips"),
+                            ExoValue::Array(p.allowed_ips.into_iter().map(ExoValue::String).collect()),
+                        );
+                        map.insert(
+                            String::from("latest_handshake_ms"),
+                            match p.latest_handshake_ms {
+                                Some(ms) => ExoValue::Int(ms as i64),
+                                None => ExoValue::Nil,
+                            },
+                        );
+                        map.insert(String::from("rx_bytes"), ExoValue::Int(p.rx_bytes as i64));
+                        map.insert(String::from("tx_bytes"), ExoValue::Int(p.tx_bytes as i64));
+                        map.insert(
+                            String::from("keepalive"),
+                            ExoValue::Int(p.persistent_keepalive as i64),
+                        );
+                        map.insert(String::from("connected"), ExoValue::Bool(p.connected));
+                        ExoValue::Map(map)
+                    })
+                    .collect();
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(info.name));
+                map.insert(String::from("public_key"), ExoValue::String(info.public_key));
+                map.insert(String::from("listen_port"), ExoValue::Int(info.listen_port as i64));
+                map.insert(String::from("peers"), ExoValue::Array(peers));
+                map.insert(String::from("handshakes"), ExoValue::Int(info.stats.handshakes as i64));
+                map.insert(
+                    String::from("cookie_replies"),
+                    ExoValue::Int(info.stats.cookie_replies as i64),
+                );
+                map.insert(
+                    String::from("rejected"),
+                    ExoValue::Int(
+                        (info.stats.invalid_mac
+                            + info.stats.unauthenticated
+                            + info.stats.replayed
+                            + info.stats.rate_limited
+                            + info.stats.source_not_allowed) as i64,
+                    ),
+                );
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// WireGuard インターフェースを作成
+    pub fn wg_add(iface: &str, listen_port: u16, private_key: Option<&str>) -> ExoValue {
+        match crate::net::add_wireguard(iface, listen_port, private_key) {
+            Ok(public_key) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(String::from(iface)));
+                map.insert(String::from("public_key"), ExoValue::String(public_key));
+                map.insert(String::from("listen_port"), ExoValue::Int(listen_port as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// WireGuard インターフェースを削除
+    pub fn wg_del(iface: &str) -> ExoValue {
+        match crate::net::remove_wireguard(iface) {
+            Ok(()) => Self::wg(None),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// WireGuard ピアを追加/更新
+    pub fn wg_peer(
+        iface: &str,
+        public_key: &str,
+        allowed_ips: &str,
+        endpoint: Option<([u8; 4], u16)>,
+        keepalive: u16,
+        preshared_key: Option<&str>,
+    ) -> ExoValue {
+        match crate::net::set_wireguard_peer(iface, public_key, allowed_ips, endpoint, keepalive, preshared_key) {
+            Ok(()) => Self::wg(Some(iface)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// WireGuard ピアを削除
+    pub fn wg_peer_del(iface: &str, public_key: &str) -> ExoValue {
+        match crate::net::remove_wireguard_peer(iface, public_key) {
+            Ok(()) => Self::wg(Some(iface)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// VLAN インターフェースを作成
+    pub fn vlan_add(parent: &str, vlan_id: u16, priority: u8, name: Option<&str>) -> ExoValue {
+        match crate::net::add_vlan(parent, vlan_id, priority, name) {
+            Ok(name) => {
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(name));
+                map.insert(String::from("parent"), ExoValue::String(String::from(parent)));
+                map.insert(String::from("vlan"), ExoValue::Int(vlan_id as i64));
+                map.insert(String::from("priority"), ExoValue::Int(priority as i64));
+                ExoValue::Map(map)
+            }
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// VLAN / ブリッジインターフェースを削除
+    pub fn link_del(name: &str) -> ExoValue {
+        match crate::net::remove_link(name) {
+            Ok(()) => Self::interfaces(),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ブリッジ一覧（ポート・MAC テーブル付き）
+    pub fn br(bridge: Option<&str>) -> ExoValue {
+        let Some(infos) = crate::net::get_bridges() else {
+            return ExoValue::Error(String::from("Network stack not initialized"));
+        };
+        let values: Vec<ExoValue> = infos
+            .into_iter()
+            .filter(|info| bridge.is_none_or(|name| info.name == name))
+            .map(|info| {
+                let fdb: Vec<ExoValue> = info
+                    .fdb
+                    .into_iter()
+                    .map(|entry| {
+                        let mut map = BTreeMap::new();
+                        map.insert(String::from("mac"), ExoValue::String(entry.mac));
+                        map.insert(String::from("port"), ExoValue::String(entry.port));
+                        map.insert(String::from("age_ms"), ExoValue::Int(entry.age_ms as i64));
+                        map.insert(String::from("static"), ExoValue::Bool(entry.is_static));
+                        ExoValue::Map(map)
+                    })
+                    .collect();
+                let mut map = BTreeMap::new();
+                map.insert(String::from("name"), ExoValue::String(info.name));
+                map.insert(String::from("mac"), ExoValue::String(info.mac));
+                map.insert(String::from("up"), ExoValue::Bool(info.up));
+                map.insert(
+                    String::from("ports"),
+                    ExoValue::Array(info.ports.into_iter().map(ExoValue::String).collect()),
+                );
+                map.insert(String::from("ageing_ms"), ExoValue::Int(info.ageing_ms as i64));
+                map.insert(String::from("fdb"), ExoValue::Array(fdb));
+                map.insert(String::from("forwarded"), ExoValue::Int(info.stats.forwarded as i64));
+                map.insert(String::from("flooded"), ExoValue::Int(info.stats.flooded as i64));
+                map.insert(String::from("local"), ExoValue::Int(info.stats.local as i64));
+                map.insert(String::from("dropped"), ExoValue::Int(info.stats.dropped as i64));
+                map.insert(String::from("learned"), ExoValue::Int(info.stats.learned as i64));
+                map.insert(String::from("aged"), ExoValue::Int(info.stats.aged as i64));
+                ExoValue::Map(map)
+            })
+            .collect();
+        ExoValue::Array(values)
+    }
+
+    /// ブリッジを作成
+    pub fn br_add(bridge: &str) -> ExoValue {
+        match crate::net::add_bridge(bridge) {
+            Ok(()) => Self::br(Some(bridge)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ブリッジにポートを追加
+    pub fn br_addif(bridge: &str, port: &str) -> ExoValue {
+        match crate::net::add_bridge_port(bridge, port) {
+            Ok(()) => Self::br(Some(bridge)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// ブリッジからポートを外す
+    pub fn br_delif(bridge: &str, port: &str) -> ExoValue {
+        match crate::net::remove_bridge_port(bridge, port) {
+            Ok(()) => Self::br(Some(bridge)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+
+    /// MAC アドレスのエージング時間を設定
+    pub fn br_ageing(bridge: &str, secs: u64) -> ExoValue {
+        match crate::net::set_bridge_ageing(bridge, secs * 1000) {
+            Ok(()) => Self::br(Some(bridge)),
+            Err(e) => ExoValue::Error(e),
+        }
+    }
+}
\ No newline at end of file
@@ -46,6 +46,62 @@ impl SysNamespace {
         map.insert(String::from("seconds"), ExoValue::Int(seconds as i64));
         map.insert(String::from("hours"), ExoValue::Int((seconds / 3600) as i64));
         map.insert(String::from("minutes"), ExoValue::Int(((seconds % 3600) / 60) as i64));
+
+        let now = crate::time::now();
+        map.insert(String::from("unix"), ExoValue::Int(now as i64));
+        map.insert(
+            String::from("utc"),
+            ExoValue::String(format!("{}", crate::io::rtc::DateTime::from_unix_timestamp(now as i64))),
+        );
+        map.insert(String::from("ntp"), Self::ntp_status());
+        ExoValue::Map(map)
+    }
+
+    /// NTP同期の状態を取得・操作
+    ///
+    /// `server` を指定するとそのサーバーで同期を始め、`stop` で同期ループを止める。
+    pub fn ntp(server: Option<crate::net::ipv4::Ipv4Address>, stop: bool) -> ExoValue {
+        if stop {
+            crate::net::ntp::stop();
+        } else if let Some(server) = server {
+            crate::net::ntp::set_servers(alloc::vec![server]);
+            crate::net::ntp::start();
+        }
+        Self::ntp_status()
+    }
+
+    fn ntp_status() -> ExoValue {
+        let status = crate::net::ntp::status();
+        let mut map = BTreeMap::new();
+        map.insert(String::from("running"), ExoValue::Bool(status.running));
+        map.insert(String::from("synchronized"), ExoValue::Bool(status.synchronized));
+        let servers: Vec<ExoValue> = crate::net::ntp::servers()
+            .iter()
+            .map(|server| ExoValue::String(format!("{}", server)))
+            .collect();
+        map.insert(String::from("servers"), ExoValue::Array(servers));
+        if let Some(server) = status.server {
+            map.insert(String::from("server"), ExoValue::String(format!("{}", server)));
+            map.insert(String::from("stratum"), ExoValue::Int(status.stratum as i64));
+            map.insert(String::from("reference"), ExoValue::String(status.reference));
+        }
+        map.insert(String::from("offset_us"), ExoValue::Int(status.offset / 1000));
+        map.insert(String::from("delay_us"), ExoValue::Int(status.delay / 1000));
+        map.insert(String::from("jitter_us"), ExoValue::Int(status.jitter / 1000));
+        map.insert(
+            String::from("freq_ppm"),
+            ExoValue::Float(status.frequency_ppb as f64 / 1000.0),
+        );
+        map.insert(String::from("steps"), ExoValue::Int(status.steps as i64));
+        map.insert(String::from("samples"), ExoValue::Int(status.samples as i64));
+        map.insert(String::from("poll_s"), ExoValue::Int(1i64 << status.poll));
+        if let Some(last) = status.last_sync {
+            let age = crate::time::current_tick().saturating_sub(last) / 1000;
+            map.insert(String::from("last_sync_s_ago"), ExoValue::Int(age as i64));
+        }
+        if let Some(error) = status.last_error {
+            map.insert(String::from("last_error"), ExoValue::String(error));
+        }
         ExoValue::Map(map)
     }
 
@@ -199,4 +255,33 @@ impl SysNamespace {
         // 実際のリブートは危険なのでメッセージのみ
         ExoValue::String(String::from("Reboot command received. Use Ctrl+Alt+Del to actually reboot."))
     }
+
+    /// メトリクス/制御API（port 指定で起動、"stop" で停止）
+    ///
+    /// token を指定しない場合、制御（POST）エンドポイントは無効
+    pub fn api(port: Option<u16>, token: Option<String>, stop: bool) -> ExoValue {
+        if stop {
+            crate::monitor::api::stop();
+        } else if let Some(port) = port {
+            let config = crate::monitor::api::ApiConfig { port, token };
+            if let Err(e) = crate::monitor::api::start(config) {
+                return ExoValue::Error(e);
+            }
+        }
+
+        let mut map = BTreeMap::new();
+        match crate::monitor::api::running_port() {
+            Some(port) => {
+                map.insert(String::from("running"), ExoValue::Bool(true));
+                map.insert(String::from("port"), ExoValue::Int(port as i64));
+                map.insert(String::from("control"), ExoValue::Bool(crate::monitor::api::control_enabled()));
+                map.insert(String::from("json"), ExoValue::String(format!("http://<ip>:{}/api/v1", port)));
+                map.insert(String::from("prometheus"), ExoValue::String(format!("http://<ip>:{}/metrics", port)));
+            }
+            None => {
+                map.insert(String::from("running"), ExoValue::Bool(false));
+            }
+        }
+        ExoValue::Map(map)
+    }
 }
@@ -193,6 +193,18 @@ impl ExoShell {
             "config" => NetNamespace::config(),
             "stats" => NetNamespace::stats(),
             "arp" => NetNamespace::arp_cache(),
+            "tcp" => NetNamespace::tcp_connections(),
+            "cc" => match args.first() {
+                None => NetNamespace::congestion(None),
+                Some(ExoValue::String(s)) => NetNamespace::congestion(Some(s.as_str())),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("cc"),
+                        expected: "文字列 (newreno, cubic, bbr)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
             "ping" => {
                 let ip_str = match args.first() {
                     Some(ExoValue::String(s)) => s.clone(),
@@ -228,15 +240,745 @@ impl ExoShell {
                     ),
                 }
             }
+            "ifaces" => NetNamespace::interfaces(),
+            "addr_add" => {
+                let (iface, cidr) = match (
+                    Self::str_arg("addr_add", args, 0, "インターフェース名"),
+                    Self::str_arg("addr_add", args, 1, "アドレス (x.x.x.x/len)"),
+                ) {
+                    (Ok(iface), Ok(cidr)) => (iface, cidr),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                match Self::parse_cidr(cidr) {
+                    Some((ip, prefix_len)) => NetNamespace::addr_add(iface, ip, prefix_len),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: cidr.to_string() }.to_string()
+                    ),
+                }
+            }
+            "addr_del" => {
+                let (iface, addr) = match (
+                    Self::str_arg("addr_del", args, 0, "インターフェース名"),
+                    Self::str_arg("addr_del", args, 1, "IPアドレス"),
+                ) {
+                    (Ok(iface), Ok(addr)) => (iface, addr),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                // "x.x.x.x/len" 形式も受け付ける
+                match Self::parse_cidr(addr) {
+                    Some((ip, _)) => NetNamespace::addr_del(iface, ip),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: addr.to_string() }.to_string()
+                    ),
+                }
+            }
+            "link" => {
+                let iface = match Self::str_arg("link", args, 0, "インターフェース名") {
+                    Ok(iface) => iface,
+                    Err(e) => return e,
+                };
+                match args.get(1) {
+                    Some(ExoValue::Bool(up)) => NetNamespace::link(iface, *up),
+                    Some(other) => ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("link"),
+                            expected: "真偽値 (true: up, false: down)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                    None => ExoValue::Error(
+                        ParseError::MissingArgument {
+                            method: String::from("link"),
+                            argument: "up",
+                        }.to_string() + "\n使用法: net.link(\"eth1\", true)"
+                    ),
+                }
+            }
+            "routes" => NetNamespace::routes(),
+            "route_add" => {
+                let (dst, via) = match (
+                    Self::str_arg("route_add", args, 0, "宛先 (x.x.x.x/len または default)"),
+                    Self::str_arg("route_add", args, 1, "ゲートウェイまたはインターフェース名"),
+                ) {
+                    (Ok(dst), Ok(via)) => (dst, via),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                let Some((destination, prefix_len)) = Self::parse_cidr(dst) else {
+                    return ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: dst.to_string() }.to_string()
+                    );
+                };
+                // 第2引数: IPアドレスならゲートウェイ、それ以外はインターフェース（直結ルート）
+                let (gateway, mut interface) = match Self::parse_ipv4(via) {
+                    Some(gw) => (Some(gw), None),
+                    None => (None, Some(via)),
+                };
+                let mut metric = None;
+                for arg in args.iter().skip(2) {
+                    match arg {
+                        ExoValue::String(s) if interface.is_none() => interface = Some(s.as_str()),
+                        ExoValue::Int(n) if (0..=u32::MAX as i64).contains(n) => metric = Some(*n as u32),
+                        other => return ExoValue::Error(
+                            ParseError::InvalidArgumentType {
+                                method: String::from("route_add"),
+                                expected: "インターフェース名 または メトリック",
+                                found: format!("{:?}", other),
+                            }.to_string()
+                        ),
+                    }
+                }
+                NetNamespace::route_add(destination, prefix_len, gateway, interface, metric)
+            }
+            "route_del" => {
+                let dst = match Self::str_arg("route_del", args, 0, "宛先 (x.x.x.x/len または default)") {
+                    Ok(dst) => dst,
+                    Err(e) => return e,
+                };
+                let Some((destination, prefix_len)) = Self::parse_cidr(dst) else {
+                    return ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: dst.to_string() }.to_string()
+                    );
+                };
+                let interface = match args.get(1) {
+                    None => None,
+                    Some(ExoValue::String(s)) => Some(s.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("route_del"),
+                            expected: "文字列 (インターフェース名)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::route_del(destination, prefix_len, interface)
+            }
+            "route_get" => {
+                let dst = match Self::str_arg("route_get", args, 0, "IPアドレス") {
+                    Ok(dst) => dst,
+                    Err(e) => return e,
+                };
+                match Self::parse_ipv4(dst) {
+                    Some(ip) => NetNamespace::route_get(ip),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: dst.to_string() }.to_string()
+                    ),
+                }
+            }
+            "capture_start" => {
+                let filter = match args.first() {
+                    None => None,
+                    Some(ExoValue::String(s)) => Some(s.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("capture_start"),
+                            expected: "文字列 (フィルタ式: \"tcp port 80 and host 10.0.2.2\")",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                let max_packets = match args.get(1) {
+                    None => None,
+                    Some(ExoValue::Int(n)) if *n > 0 => Some(*n as usize),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("capture_start"),
+                            expected: "正の整数 (リングバッファのパケット数)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::capture_start(filter, max_packets)
+            }
+            "capture_stop" => NetNamespace::capture_stop(),
+            "capture_stats" => NetNamespace::capture_stats(),
+            "capture_show" => {
+                let limit = args.first()
+                    .and_then(|v| match v { ExoValue::Int(n) if *n > 0 => Some(*n as usize), _ => None })
+                    .unwrap_or(20);
+                NetNamespace::capture_show(limit)
+            }
+            "capture_save" => match Self::str_arg("capture_save", args, 0, "パス") {
+                Ok(path) => NetNamespace::capture_save(path),
+                Err(e) => e,
+            },
+            "capture_serial" => NetNamespace::capture_serial(),
+            "fw_add" => {
+                let spec = match Self::str_arg("fw_add", args, 0, "ルール (\"input tcp dport 22 accept\")") {
+                    Ok(spec) => spec,
+                    Err(e) => return e,
+                };
+                let position = match args.get(1) {
+                    None => None,
+                    Some(ExoValue::Int(n)) if *n >= 0 => Some(*n as usize),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("fw_add"),
+                            expected: "0以上の整数 (チェーン内の挿入位置)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::fw_add(spec, position)
+            }
+            "fw_del" => match args.first() {
+                Some(ExoValue::Int(id)) if (0..=u32::MAX as i64).contains(id) => NetNamespace::fw_del(*id as u32),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("fw_del"),
+                        expected: "整数 (ルールID)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+                None => ExoValue::Error(
+                    ParseError::MissingArgument {
+                        method: String::from("fw_del"),
+                        argument: "ルールID",
+                    }.to_string()
+                ),
+            },
+            "fw_list" => NetNamespace::fw_list(),
+            "fw_chains" => NetNamespace::fw_chains(),
+            "fw_policy" => match (
+                Self::str_arg("fw_policy", args, 0, "チェーン (input/output/forward)"),
+                Self::str_arg("fw_policy", args, 1, "動作 (accept/drop)"),
+            ) {
+                (Ok(chain), Ok(action)) => NetNamespace::fw_policy(chain, action),
+                (Err(e), _) | (_, Err(e)) => e,
+            },
+            "fw_flush" => match args.first() {
+                None => NetNamespace::fw_flush(None),
+                Some(ExoValue::String(chain)) => NetNamespace::fw_flush(Some(chain.as_str())),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("fw_flush"),
+                        expected: "文字列 (チェーン名)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "conntrack" => NetNamespace::conntrack(),
+            "conntrack_flush" => NetNamespace::conntrack_flush(),
+            "port_allow" | "port_deny" => {
+                let method = if name == "port_allow" { "port_allow" } else { "port_deny" };
+                // 第1引数: ドメインID、または "*"（全ドメイン）
+                let domain = match args.first() {
+                    Some(ExoValue::Int(id)) if *id >= 0 => Some(*id as u64),
+                    Some(ExoValue::String(s)) if s == "*" => None,
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from(method),
+                            expected: "ドメインID または \"*\"",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                    None => return ExoValue::Error(
+                        ParseError::MissingArgument {
+                            method: String::from(method),
+                            argument: "ドメインID",
+                        }.to_string() + "\n使用法: net.port_allow(2, \"tcp:8080\", \"in\")"
+                    ),
+                };
+                let ports = match Self::str_arg(method, args, 1, "ポート (\"tcp:8080\")") {
+                    Ok(ports) => ports,
+                    Err(e) => return e,
+                };
+                let direction = match args.get(2) {
+                    None => None,
+                    Some(ExoValue::String(s)) => Some(s.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from(method),
+                            expected: "文字列 (in/out/any)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::port_policy(domain, ports, direction, name == "port_allow")
+            }
+            "port_rules" => NetNamespace::port_rules(),
+            "resolve" => match Self::str_arg("resolve", args, 0, "ホスト名 (\"ranyos.local\")") {
+                Ok(hostname) => NetNamespace::resolve(hostname).await,
+                Err(e) => e,
+            },
+            "mdns" => NetNamespace::mdns(),
+            "mdns_host" => match Self::str_arg("mdns_host", args, 0, "ホスト名") {
+                Ok(hostname) => NetNamespace::mdns_host(hostname),
+                Err(e) => e,
+            },
+            "mdns_add" => {
+                let service_type = match Self::str_arg("mdns_add", args, 0, "サービス種別 (\"_http._tcp\")") {
+                    Ok(service_type) => service_type,
+                    Err(e) => return e,
+                };
+                let port = match args.get(1) {
+                    Some(ExoValue::Int(port)) if (1..=65535).contains(port) => *port as u16,
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("mdns_add"),
+                            expected: "整数 (ポート番号 1-65535)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                    None => return ExoValue::Error(
+                        ParseError::MissingArgument {
+                            method: String::from("mdns_add"),
+                            argument: "ポート番号",
+                        }.to_string() + "\n使用法: net.mdns_add(\"_http._tcp\", 80, \"path=/\")"
+                    ),
+                };
+                let mut txt = Vec::new();
+                for arg in &args[2..] {
+                    match arg {
+                        ExoValue::String(entry) => txt.push(entry.as_str()),
+                        other => return ExoValue::Error(
+                            ParseError::InvalidArgumentType {
+                                method: String::from("mdns_add"),
+                                expected: "文字列 (TXT \"key=value\")",
+                                found: format!("{:?}", other),
+                            }.to_string()
+                        ),
+                    }
+                }
+                NetNamespace::mdns_add(service_type, port, &txt)
+            }
+            "mdns_del" => match Self::str_arg("mdns_del", args, 0, "サービス種別") {
+                Ok(service_type) => NetNamespace::mdns_del(service_type),
+                Err(e) => e,
+            },
+            "mdns_browse" => match Self::str_arg("mdns_browse", args, 0, "サービス種別 (\"_http._tcp\")") {
+                Ok(service_type) => NetNamespace::mdns_browse(service_type).await,
+                Err(e) => e,
+            },
+            "mcast" => NetNamespace::mcast(),
+            "mcast_join" | "mcast_leave" => {
+                let (iface, group) = match (
+                    Self::str_arg(name, args, 0, "インターフェース名"),
+                    Self::str_arg(name, args, 1, "グループアドレス"),
+                ) {
+                    (Ok(iface), Ok(group)) => (iface, group),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                match Self::parse_ipv4(group) {
+                    Some(group) => NetNamespace::mcast_member(iface, group, name == "mcast_join"),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: group.to_string() }.to_string()
+                    ),
+                }
+            }
+            "port_del" => match args.first() {
+                Some(ExoValue::Int(id)) if *id >= 0 => NetNamespace::port_del(*id as u64),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("port_del"),
+                        expected: "整数 (ルールID)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+                None => ExoValue::Error(
+                    ParseError::MissingArgument {
+                        method: String::from("port_del"),
+                        argument: "ルールID",
+                    }.to_string()
+                ),
+            },
+            "dhcpd" => NetNamespace::dhcpd(),
+            "dhcpd_start" => {
+                let (cidr, start, end) = match (
+                    Self::str_arg("dhcpd_start", args, 0, "サーバーアドレス (x.x.x.x/len)"),
+                    Self::str_arg("dhcpd_start", args, 1, "プール先頭"),
+                    Self::str_arg("dhcpd_start", args, 2, "プール末尾"),
+                ) {
+                    (Ok(cidr), Ok(start), Ok(end)) => (cidr, start, end),
+                    (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+                };
+                let Some((address, prefix_len)) = Self::parse_cidr(cidr).filter(|(_, len)| *len > 0) else {
+                    return ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: cidr.to_string() }.to_string()
+                    );
+                };
+                match (Self::parse_ipv4(start), Self::parse_ipv4(end)) {
+                    (Some(start), Some(end)) => NetNamespace::dhcpd_start(address, prefix_len, start, end),
+                    (None, _) => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: start.to_string() }.to_string()
+                    ),
+                    (_, None) => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: end.to_string() }.to_string()
+                    ),
+                }
+            }
+            "dhcpd_static" => {
+                let (mac, addr) = match (
+                    Self::str_arg("dhcpd_static", args, 0, "MACアドレス"),
+                    Self::str_arg("dhcpd_static", args, 1, "IPアドレス"),
+                ) {
+                    (Ok(mac), Ok(addr)) => (mac, addr),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                match Self::parse_ipv4(addr) {
+                    Some(addr) => NetNamespace::dhcpd_static(mac, addr),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: addr.to_string() }.to_string()
+                    ),
+                }
+            }
+            "dhcpd_stop" => NetNamespace::dhcpd_stop(),
+            "dnsd" => NetNamespace::dnsd(),
+            "dnsd_start" => match args.first() {
+                None => NetNamespace::dnsd_start(None),
+                Some(ExoValue::String(s)) if let Some(upstream) = Self::parse_ipv4(s) => {
+                    NetNamespace::dnsd_start(Some(upstream))
+                }
+                Some(ExoValue::String(s)) => ExoValue::Error(
+                    ParseError::InvalidIpAddress { value: s.clone() }.to_string()
+                ),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("dnsd_start"),
+                        expected: "文字列 (上流DNSサーバー)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "dnsd_stop" => NetNamespace::dnsd_stop(),
+            "forward" => match args.first() {
+                None => NetNamespace::forward(None),
+                Some(ExoValue::Bool(enabled)) => NetNamespace::forward(Some(*enabled)),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("forward"),
+                        expected: "真偽値 (true: 転送を有効化)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "masq" | "masq_del" => {
+                let add = name == "masq";
+                match Self::str_arg(name, args, 0, "インターフェース名") {
+                    Ok(iface) => NetNamespace::masquerade(iface, add),
+                    Err(e) => e,
+                }
+            }
+            "portfwd" => {
+                let (protocol, port, to, to_port) = match (
+                    Self::str_arg("portfwd", args, 0, "プロトコル (tcp/udp)"),
+                    Self::port_arg("portfwd", args, 1, "ポート番号"),
+                    Self::str_arg("portfwd", args, 2, "転送先アドレス"),
+                    Self::port_arg("portfwd", args, 3, "転送先ポート"),
+                ) {
+                    (Ok(protocol), Ok(port), Ok(to), Ok(to_port)) => (protocol, port, to, to_port),
+                    (Err(e), ..) | (_, Err(e), ..) | (_, _, Err(e), _) | (.., Err(e)) => return e,
+                };
+                let iface = match args.get(4) {
+                    None => None,
+                    Some(ExoValue::String(iface)) => Some(iface.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("portfwd"),
+                            expected: "文字列 (受信インターフェース名)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                match Self::parse_ipv4(to) {
+                    Some(to) => NetNamespace::portfwd(protocol, port, to, to_port, iface),
+                    None => ExoValue::Error(
+                        ParseError::InvalidIpAddress { value: to.to_string() }.to_string()
+                    ),
+                }
+            }
+            "portfwd_del" => match args.first() {
+                Some(ExoValue::Int(id)) if (0..=u32::MAX as i64).contains(id) => {
+                    NetNamespace::portfwd_del(*id as u32)
+                }
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("portfwd_del"),
+                        expected: "整数 (ルールID)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+                None => ExoValue::Error(
+                    ParseError::MissingArgument {
+                        method: String::from("portfwd_del"),
+                        argument: "ルールID",
+                    }.to_string()
+                ),
+            },
+            "portfwd_list" => NetNamespace::portfwd_list(),
+            "nat" => NetNamespace::nat(),
+            "wg" => match args.first() {
+                None => NetNamespace::wg(None),
+                Some(ExoValue::String(iface)) => NetNamespace::wg(Some(iface.as_str())),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("wg"),
+                        expected: "文字列 (インターフェース名)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "wg_add" => {
+                let (iface, port) = match (
+                    Self::str_arg("wg_add", args, 0, "インターフェース名"),
+                    Self::port_arg("wg_add", args, 1, "待ち受けポート"),
+                ) {
+                    (Ok(iface), Ok(port)) => (iface, port),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                let private_key = match args.get(2) {
+                    None => None,
+                    Some(ExoValue::String(key)) => Some(key.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("wg_add"),
+                            expected: "文字列 (Base64 の秘密鍵)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::wg_add(iface, port, private_key)
+            }
+            "wg_del" => match Self::str_arg("wg_del", args, 0, "インターフェース名") {
+                Ok(iface) => NetNamespace::wg_del(iface),
+                Err(e) => e,
+            },
+            "wg_peer" => {
+                let (iface, public_key, allowed_ips) = match (
+                    Self::str_arg("wg_peer", args, 0, "インターフェース名"),
+                    Self::str_arg("wg_peer", args, 1, "ピアの公開鍵"),
+                    Self::str_arg("wg_peer", args, 2, "許可するアドレス (\"10.0.0.2/32,...\")"),
+                ) {
+                    (Ok(iface), Ok(key), Ok(ips)) => (iface, key, ips),
+                    (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+                };
+                // エンドポイントは "x.x.x.x:port"（nil で省略）
+                let endpoint = match args.get(3) {
+                    None | Some(ExoValue::Nil) => None,
+                    Some(ExoValue::String(s)) => {
+                        let parsed = s.rsplit_once(':').and_then(|(ip, port)| {
+                            Some((Self::parse_ipv4(ip)?, port.parse::<u16>().ok().filter(|p| *p > 0)?))
+                        });
+                        match parsed {
+                            Some(endpoint) => Some(endpoint),
+                            None => return ExoValue::Error(
+                                ParseError::InvalidIpAddress { value: s.clone() }.to_string()
+                            ),
+                        }
+                    }
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("wg_peer"),
+                            expected: "文字列 (エンドポイント \"x.x.x.x:port\")",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                let keepalive = match args.get(4) {
+                    None => 0,
+                    Some(ExoValue::Int(secs)) if (0..=65535).contains(secs) => *secs as u16,
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("wg_peer"),
+                            expected: "整数 (キープアライブ秒数 0-65535)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                let preshared_key = match args.get(5) {
+                    None => None,
+                    Some(ExoValue::String(key)) => Some(key.as_str()),
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("wg_peer"),
+                            expected: "文字列 (Base64 の事前共有鍵)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::wg_peer(iface, public_key, allowed_ips, endpoint, keepalive, preshared_key)
+            }
+            "wg_peer_del" => {
+                let (iface, public_key) = match (
+                    Self::str_arg("wg_peer_del", args, 0, "インターフェース名"),
+                    Self::str_arg("wg_peer_del", args, 1, "ピアの公開鍵"),
+                ) {
+                    (Ok(iface), Ok(key)) => (iface, key),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                NetNamespace::wg_peer_del(iface, public_key)
+            }
+            "vlan_add" => {
+                let parent = match Self::str_arg("vlan_add", args, 0, "親インターフェース名") {
+                    Ok(parent) => parent,
+                    Err(e) => return e,
+                };
+                let vlan_id = match args.get(1) {
+                    Some(ExoValue::Int(id)) if (1..=crate::net::VLAN_ID_MAX as i64).contains(id) => *id as u16,
+                    Some(other) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("vlan_add"),
+                            expected: "整数 (VLAN ID 1-4094)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                    None => return ExoValue::Error(
+                        ParseError::MissingArgument {
+                            method: String::from("vlan_add"),
+                            argument: "VLAN ID",
+                        }.to_string()
+                    ),
+                };
+                // 3 番目は名前（文字列）または優先度（整数）
+                let (link_name, priority) = match (args.get(2), args.get(3)) {
+                    (None, _) => (None, 0),
+                    (Some(ExoValue::String(name)), None) => (Some(name.as_str()), 0),
+                    (Some(ExoValue::String(name)), Some(ExoValue::Int(pcp))) if (0..=7).contains(pcp) => {
+                        (Some(name.as_str()), *pcp as u8)
+                    }
+                    (Some(ExoValue::Int(pcp)), None) if (0..=7).contains(pcp) => (None, *pcp as u8),
+                    (Some(other), _) => return ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("vlan_add"),
+                            expected: "文字列 (インターフェース名) と整数 (優先度 0-7)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                };
+                NetNamespace::vlan_add(parent, vlan_id, priority, link_name)
+            }
+            "link_del" => match Self::str_arg("link_del", args, 0, "インターフェース名") {
+                Ok(name) => NetNamespace::link_del(name),
+                Err(e) => e,
+            },
+            "br" => match args.first() {
+                None => NetNamespace::br(None),
+                Some(ExoValue::String(bridge)) => NetNamespace::br(Some(bridge.as_str())),
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("br"),
+                        expected: "文字列 (ブリッジ名)",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "br_add" => match Self::str_arg("br_add", args, 0, "ブリッジ名") {
+                Ok(bridge) => NetNamespace::br_add(bridge),
+                Err(e) => e,
+            },
+            "br_addif" | "br_delif" => {
+                let (bridge, port) = match (
+                    Self::str_arg(name, args, 0, "ブリッジ名"),
+                    Self::str_arg(name, args, 1, "ポートのインターフェース名"),
+                ) {
+                    (Ok(bridge), Ok(port)) => (bridge, port),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                if name == "br_addif" {
+                    NetNamespace::br_addif(bridge, port)
+                } else {
+                    NetNamespace::br_delif(bridge, port)
+                }
+            }
+            "br_ageing" => {
+                let bridge = match Self::str_arg("br_ageing", args, 0, "ブリッジ名") {
+                    Ok(bridge) => bridge,
+                    Err(e) => return e,
+                };
+                match args.get(1) {
+                    Some(ExoValue::Int(secs)) if *secs >= 0 => NetNamespace::br_ageing(bridge, *secs as u64),
+                    other => ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("br_ageing"),
+                            expected: "整数 (エージング秒数、0 で無効)",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                }
+            }
             _ => ExoValue::Error(
                 ParseError::UnknownMethod {
                     namespace: String::from("net"),
                     method: name.to_string(),
-                }.to_string() + "\n有効なメソッド: config, stats, arp, ping"
+                }.to_string() + "\n有効なメソッド: config, stats, arp, tcp, cc, ping, ifaces, addr_add, addr_del, link, routes, route_add, route_del, route_get, capture_start, capture_stop, capture_stats, capture_show, capture_save, capture_serial, fw_add, fw_del, fw_list, fw_chains, fw_policy, fw_flush, conntrack, conntrack_flush, port_allow, port_deny, port_rules, port_del, resolve, mdns, mdns_host, mdns_add, mdns_del, mdns_browse, mcast, mcast_join, mcast_leave, dhcpd, dhcpd_start, dhcpd_static, dhcpd_stop, dnsd, dnsd_start, dnsd_stop, forward, masq, masq_del, portfwd, portfwd_del, portfwd_list, nat, wg, wg_add, wg_del, wg_peer, wg_peer_del, vlan_add, link_del, br, br_add, br_addif, br_delif, br_ageing"
             ),
         }
     }
 
+    /// 文字列引数を取得（不足・型不正はエラー値）
+    fn str_arg<'a>(
+        method: &str,
+        args: &'a [ExoValue],
+        index: usize,
+        argument: &'static str,
+    ) -> Result<&'a str, ExoValue> {
+        match args.get(index) {
+            Some(ExoValue::String(s)) => Ok(s.as_str()),
+            Some(other) => Err(ExoValue::Error(
+                ParseError::InvalidArgumentType {
+                    method: method.to_string(),
+                    expected: "文字列",
+                    found: format!("{:?}", other),
+                }.to_string()
+            )),
+            None => Err(ExoValue::Error(
+                ParseError::MissingArgument {
+                    method: method.to_string(),
+                    argument,
+                }.to_string()
+            )),
+        }
+    }
+
+    /// ポート番号引数を取得（1-65535）
+    fn port_arg(
+        method: &str,
+        args: &[ExoValue],
+        index: usize,
+        argument: &'static str,
+    ) -> Result<u16, ExoValue> {
+        match args.get(index) {
+            Some(ExoValue::Int(port)) if (1..=65535).contains(port) => Ok(*port as u16),
+            Some(other) => Err(ExoValue::Error(
+                ParseError::InvalidArgumentType {
+                    method: method.to_string(),
+                    expected: "整数 (ポート番号 1-65535)",
+                    found: format!("{:?}", other),
+                }.to_string()
+            )),
+            None => Err(ExoValue::Error(
+                ParseError::MissingArgument {
+                    method: method.to_string(),
+                    argument,
+                }.to_string()
+            )),
+        }
+    }
+
+    /// "x.x.x.x" をパース
+    fn parse_ipv4(s: &str) -> Option<[u8; 4]> {
+        let mut octets = [0u8; 4];
+        let mut parts = s.split('.');
+        for octet in octets.iter_mut() {
+            *octet = parts.next()?.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(octets)
+    }
+
+    /// "x.x.x.x/len" をパース（プレフィックス省略時は /32、"default" は 0.0.0.0/0）
+    fn parse_cidr(s: &str) -> Option<([u8; 4], u8)> {
+        if s == "default" {
+            return Some(([0, 0, 0, 0], 0));
+        }
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, len.parse::<u8>().ok().filter(|len| *len <= 32)?),
+            None => (s, 32),
+        };
+        Some((Self::parse_ipv4(addr)?, prefix_len))
+    }
+
     /// proc.* メソッド（構造化版）
     fn eval_proc_method(&self, name: &str, args: &[ExoValue]) -> ExoValue {
         match name {
@@ -279,7 +1021,7 @@ impl ExoShell {
     }
 
     /// sys.* メソッド（構造化版）
-    fn eval_sys_method(&self, name: &str, _args: &[ExoValue]) -> ExoValue {
+    fn eval_sys_method(&self, name: &str, args: &[ExoValue]) -> ExoValue {
         match name {
             "info" => SysNamespace::info(),
             "memory" | "mem" => SysNamespace::memory(),
@@ -291,11 +1033,49 @@ impl ExoShell {
             "power" => SysNamespace::power(),
             "shutdown" => SysNamespace::shutdown(),
             "reboot" => SysNamespace::reboot(),
+            "api" => match (args.first(), args.get(1)) {
+                (None, _) => SysNamespace::api(None, None, false),
+                (Some(ExoValue::Int(port)), token) if (1..=65535).contains(port) => match token {
+                    None => SysNamespace::api(Some(*port as u16), None, false),
+                    Some(ExoValue::String(token)) => {
+                        SysNamespace::api(Some(*port as u16), Some(token.clone()), false)
+                    }
+                    Some(other) => ExoValue::Error(
+                        ParseError::InvalidArgumentType {
+                            method: String::from("api"),
+                            expected: "APIトークン（文字列）",
+                            found: format!("{:?}", other),
+                        }.to_string()
+                    ),
+                },
+                (Some(ExoValue::String(s)), _) if s == "stop" => SysNamespace::api(None, None, true),
+                (Some(other), _) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("api"),
+                        expected: "ポート番号 (1-65535) または \"stop\"",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
+            "ntp" => match args.first() {
+                None => SysNamespace::ntp(None, false),
+                Some(ExoValue::String(s)) if s == "stop" => SysNamespace::ntp(None, true),
+                Some(ExoValue::String(s)) if let Some(octets) = Self::parse_ipv4(s) => {
+                    SysNamespace::ntp(Some(crate::net::ipv4::Ipv4Address::new(octets)), false)
+                }
+                Some(other) => ExoValue::Error(
+                    ParseError::InvalidArgumentType {
+                        method: String::from("ntp"),
+                        expected: "サーバーのIPアドレス (\"x.x.x.x\") または \"stop\"",
+                        found: format!("{:?}", other),
+                    }.to_string()
+                ),
+            },
             _ => ExoValue::Error(
                 ParseError::UnknownMethod {
                     namespace: String::from("sys"),
                     method: name.to_string(),
-                }.to_string() + "\n有効なメソッド: info, memory, time, monitor, dashboard, thermal, watchdog, power, shutdown, reboot"
+                }.to_string() + "\n有効なメソッド: info, memory, time, monitor, dashboard, thermal, watchdog, power, shutdown, reboot, api, ntp"
             ),
         }
     }
@@ -922,7 +1702,70 @@ impl ExoShell {
     net.config()          - Show network configuration
     net.stats()           - Show TX/RX statistics
     net.arp()             - Show ARP cache
+    net.tcp()             - TCP connections (cwnd, pacing, RTT)
+    net.cc("cubic")       - Get/set default congestion control
     net.ping("ip", count) - Send ICMP echo
+    net.ifaces()          - List interfaces and addresses
+    net.addr_add("eth0", "10.0.3.15/24") - Add an address
+    net.addr_del("eth0", "10.0.3.15")    - Remove an address
+    net.link("eth1", true) - Bring an interface up/down
+    net.routes()          - Show routing table
+    net.route_add("10.1.0.0/16", "10.0.2.2", metric) - Add a route
+    net.route_del("10.1.0.0/16") - Remove a route
+    net.route_get("ip")   - Resolve route and source address
+    net.capture_start("tcp port 80") - Start packet capture (BPF-like filter)
+    net.capture_stop()    - Stop packet capture
+    net.capture_stats()   - Capture status and counters
+    net.capture_show(20)  - Show recently captured packets
+    net.capture_save("/cap.pcapng") - Save capture as pcapng
+    net.capture_serial()  - Stream capture as pcapng over COM2
+    net.fw_add("input tcp dport 22 state new accept") - Add firewall rule
+    net.fw_del(id)        - Delete firewall rule
+    net.fw_list()         - Firewall rules with packet/byte counters
+    net.fw_chains()       - Chain policies and counters
+    net.fw_policy("input", "drop") - Set chain default policy
+    net.fw_flush("input") - Remove rules (all chains if omitted)
+    net.conntrack()       - Connection tracking table
+    net.conntrack_flush() - Clear connection tracking table
+    net.port_allow(2, "tcp:8080", "in") - Allow a domain to use ports
+    net.port_deny(2, "*:*", "in") - Deny a domain ports (allow wins)
+    net.port_rules()      - List domain port policies
+    net.port_del(id)      - Remove a domain port policy
+    net.resolve("ranyos.local") - Resolve a name (.local via mDNS)
+    net.mdns()            - mDNS hostname, services and record cache
+    net.mdns_host("lab1") - Change the announced hostname
+    net.mdns_add("_http._tcp", 80, "path=/") - Publish a DNS-SD service
+    net.mdns_del("_http._tcp") - Withdraw a published service
+    net.mdns_browse("_http._tcp") - Discover services on the link
+    net.mcast()           - Joined multicast groups
+    net.mcast_join("eth0", "239.1.1.1") - Join a group (IGMP)
+    net.mcast_leave("eth0", "239.1.1.1") - Leave a group
+    net.dhcpd()           - DHCP server state and leases
+    net.dhcpd_start("10.0.0.1/24", "10.0.0.100", "10.0.0.200") - Serve addresses
+    net.dhcpd_static("52:54:00:12:34:56", "10.0.0.50") - Pin an address to a MAC
+    net.dhcpd_stop()      - Stop the DHCP server
+    net.dnsd()            - DNS forwarder statistics and cache
+    net.dnsd_start("1.1.1.1") - Start the caching forwarder on port 53
+    net.dnsd_stop()       - Stop the forwarder and flush its cache
+    net.forward(true)     - Enable IPv4 forwarding (status if omitted)
+    net.masq("eth0")      - Masquerade connections leaving an interface
+    net.masq_del("eth0")  - Stop masquerading on an interface
+    net.portfwd("tcp", 8080, "192.168.50.10", 80) - Forward a port to a host
+    net.portfwd_del(id)   - Remove a port forward
+    net.portfwd_list()    - List port forwards
+    net.nat()             - NAT translation table
+    net.wg("wg0")         - WireGuard interfaces and peers
+    net.wg_add("wg0", 51820) - Create a WireGuard interface (key generated if omitted)
+    net.wg_del("wg0")     - Remove a WireGuard interface
+    net.wg_peer("wg0", key, "10.0.0.2/32", "192.0.2.1:51820", 25) - Add or update a peer
+    net.wg_peer_del("wg0", key) - Remove a peer
+    net.vlan_add("eth0", 100) - Create an 802.1Q VLAN interface (eth0.100)
+    net.link_del("eth0.100") - Remove a VLAN or bridge interface
+    net.br("br0")         - Bridges, ports and MAC tables
+    net.br_add("br0")     - Create an Ethernet bridge
+    net.br_addif("br0", "eth1") - Add a port to a bridge
+    net.br_delif("br0", "eth1") - Remove a port from a bridge
+    net.br_ageing("br0", 300) - Set MAC ageing time in seconds (0 = never)
 
   proc.* - Process/Task
     proc.list()           - List tasks
@@ -944,6 +1787,8 @@ impl ExoShell {
     sys.power()           - Power state/CPU idle stats
     sys.shutdown()        - Request shutdown
     sys.reboot()          - Request reboot
+    sys.api(9100, "tok")  - Start JSON/Prometheus API; POST needs "Bearer tok" (sys.api("stop"))
+    sys.ntp("10.0.2.2")   - Sync clock via NTP (sys.ntp() status, sys.ntp("stop"))
 
 [Method Chaining]
   fs.entries("/").filter("|e| e.size > 1024").map("|e| e.name")
@@ -1012,10 +1857,21 @@ impl ExoShell {
 
         let methods: &[&str] = match namespace {
             "fs" => &["entries", "read", "stat", "mkdir", "remove", "cd", "pwd", "write"],
-            "net" => &["config", "stats", "arp", "ping"],
+            "net" => &[
+                "config", "stats", "arp", "tcp", "cc", "ping", "ifaces", "addr_add", "addr_del",
+                "link", "routes", "route_add", "route_del", "route_get", "capture_start",
+                "capture_stop", "capture_stats", "capture_show", "capture_save", "capture_serial",
+                "fw_add", "fw_del", "fw_list", "fw_chains", "fw_policy", "fw_flush", "conntrack",
+                "conntrack_flush", "port_allow", "port_deny", "port_rules", "port_del", "resolve",
+                "mdns", "mdns_host", "mdns_add", "mdns_del", "mdns_browse", "mcast", "mcast_join",
+                "mcast_leave", "dhcpd", "dhcpd_start", "dhcpd_static", "dhcpd_stop", "dnsd",
+                "dnsd_start", "dnsd_stop", "forward", "masq", "masq_del", "portfwd", "portfwd_del",
+                "portfwd_list", "nat", "wg", "wg_add", "wg_del", "wg_peer", "wg_peer_del",
+                "vlan_add", "link_del", "br", "br_add", "br_addif", "br_delif", "br_ageing",
+            ],
             "proc" => &["list", "info"],
             "cap" => &["list", "grant", "revoke"],
-            "sys" => &["info", "memory", "time", "monitor", "dashboard", "thermal", "watchdog", "power", "shutdown", "reboot"],
+            "sys" => &["info", "memory", "time", "monitor", "dashboard", "thermal", "watchdog", "power", "shutdown", "reboot", "api", "ntp"],
             _ => return Vec::new(),
         };
 
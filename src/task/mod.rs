@@ -30,7 +30,7 @@ pub use environ::{
     EnvError, EnvKey, EnvValue, Environment, environ, get_home, get_path, get_pwd, get_user,
     getenv, kernel_env, putenv, set_pwd, setenv, unsetenv,
 };
-pub use executor::Executor;
+pub use executor::{Executor, ExecutorStatsSnapshot, get_executor_stats};
 #[allow(unused_imports)]
 pub use interrupt_waker::{
     AtomicWaker, InterruptFuture, InterruptSource, InterruptWakerRegistry, InterruptWakerStats,
@@ -41,7 +41,7 @@ pub use interrupt_waker::{
 pub use per_core_executor::{
     ExecutorManager, ExecutorStats, PerCoreExecutor, Priority, Task as CoreTask,
     TaskId as CoreTaskId, TaskMetadata, TaskState as CoreTaskState, executor_manager,
-    init_executors, spawn, spawn_with_priority,
+    init_executors, spawn, spawn_on, spawn_with_priority,
 };
 #[allow(unused_imports)]
 pub use preemption::{
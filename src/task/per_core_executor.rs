@@ -522,6 +522,11 @@ impl ExecutorManager {
         false
     }
 
+    /// 初期化済みのコア数
+    pub fn core_count(&self) -> usize {
+        self.core_count.load(Ordering::Acquire)
+    }
+
     /// 全エグゼキュータの統計を取得
     pub fn all_stats(&self) -> alloc::vec::Vec<ExecutorStats> {
         self.executors.lock().iter().map(|e| e.stats()).collect()
@@ -642,6 +647,22 @@ where
     EXECUTOR_MANAGER.spawn(task);
 }
 
+/// 指定コアに固定してタスクをspawn
+///
+/// Wakerは同じコアへ再スケジュールするため、タスクは以後そのコアで実行される。
+/// High/Realtime 優先度ならスチール対象にもならない。
+/// エグゼキュータ未初期化、またはコアが存在しない場合は通常のspawnにフォールバック。
+pub fn spawn_on<F>(core_id: u32, future: F, priority: Priority)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let task = Task::new(future, priority, None);
+    match EXECUTOR_MANAGER.get_executor(core_id) {
+        Some(executor) => executor.spawn(task),
+        None => EXECUTOR_MANAGER.spawn(task),
+    }
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -7,6 +7,10 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+pub mod timescale;
+
+pub use timescale::{SharedTimescale, Timescale};
+
 use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use spin::Mutex;
 use x86_64::instructions::port::Port;
@@ -93,6 +97,8 @@ mod pit {
     pub const CHANNEL0_DATA: u16 = 0x40;
     pub const CHANNEL2_DATA: u16 = 0x42;
     pub const COMMAND: u16 = 0x43;
+    /// チャネル2のゲート (bit 0)、スピーカー (bit 1)、出力 (bit 5)
+    pub const CHANNEL2_GATE: u16 = 0x61;
 
     /// PITの基本周波数 (Hz)
     pub const BASE_FREQUENCY: u64 = 1193182;
@@ -101,6 +107,7 @@ mod pit {
     pub const MODE_SQUARE_WAVE: u8 = 0x36; // Channel 0, Mode 3
     pub const MODE_ONE_SHOT: u8 = 0x30; // Channel 0, Mode 0
     pub const MODE_RATE_GEN: u8 = 0x34; // Channel 0, Mode 2
+    pub const MODE_CHANNEL2_ONE_SHOT: u8 = 0xB0; // Channel 2, Mode 0
     pub const READBACK: u8 = 0xE2; // Read-back command
 }
 
@@ -307,6 +314,12 @@ pub struct SystemClock {
     tsc_info: Mutex<Option<TscInfo>>,
     /// 最後に読んだTSC値
     last_tsc: AtomicU64,
+    /// TSC周波数 (Hz, 0ならtickで稼働時間を数える)
+    tsc_frequency: AtomicU64,
+    /// 稼働時間0に相当するTSC値
+    boot_tsc: AtomicU64,
+    /// 稼働時間 → Unix時刻の写像 (時刻同期で調整され、ロックなしで読む)
+    timescale: SharedTimescale,
     /// 初期化済みフラグ
     initialized: AtomicBool,
 }
@@ -320,6 +333,9 @@ impl SystemClock {
             timer_source: Mutex::new(TimerSource::PIT),
             tsc_info: Mutex::new(None),
             last_tsc: AtomicU64::new(0),
+            tsc_frequency: AtomicU64::new(0),
+            boot_tsc: AtomicU64::new(0),
+            timescale: SharedTimescale::new(),
             initialized: AtomicBool::new(false),
         }
     }
@@ -327,6 +343,7 @@ impl SystemClock {
     /// 起動時刻を設定
     pub fn set_boot_time(&self, unix_timestamp: u64) {
         self.boot_time.store(unix_timestamp, Ordering::SeqCst);
+        self.adjust(|ts, uptime| ts.set(uptime, unix_timestamp * NANOS_PER_SEC + uptime));
     }
 
     /// 起動時刻を取得 (Unixタイムスタンプ)
@@ -335,8 +352,17 @@ impl SystemClock {
     }
 
     /// 稼働時間を取得 (ナノ秒)
+    ///
+    /// TSCがキャリブレーション済みならTSCから求める（割り込みハンドラからも安全）。
     pub fn uptime_nanos(&self) -> u64 {
-        self.uptime_nanos.load(Ordering::Relaxed)
+        let frequency = self.tsc_frequency.load(Ordering::Acquire);
+        if frequency == 0 {
+            return self.uptime_nanos.load(Ordering::Relaxed);
+        }
+        let elapsed = self
+            .read_tsc()
+            .saturating_sub(self.boot_tsc.load(Ordering::Relaxed));
+        (elapsed as u128 * NANOS_PER_SEC as u128 / frequency as u128) as u64
     }
 
     /// 稼働時間を取得 (ミリ秒)
@@ -351,7 +377,60 @@ impl SystemClock {
 
     /// 現在のUnixタイムスタンプを取得
     pub fn now(&self) -> u64 {
-        self.boot_time() + (self.uptime_nanos() / NANOS_PER_SEC)
+        self.now_nanos() / NANOS_PER_SEC
+    }
+
+    /// 現在のUnix時刻を取得 (ナノ秒)
+    ///
+    /// ロックを取らないので、受信処理や割り込みハンドラからも呼べる。
+    pub fn now_nanos(&self) -> u64 {
+        let uptime = self.uptime_nanos();
+        self.timescale.read().wall_nanos(uptime)
+    }
+
+    /// 時刻を `offset` ナノ秒だけ飛ばす
+    pub fn step_by(&self, offset: i64) {
+        self.adjust(|ts, uptime| ts.step(uptime, offset));
+    }
+
+    /// `offset` ナノ秒のずれを徐々に吸収する
+    pub fn slew_by(&self, offset: i64) {
+        self.adjust(|ts, uptime| ts.slew(uptime, offset));
+    }
+
+    /// まだ吸収していないずれ (ナノ秒)
+    pub fn slew_remaining(&self) -> i64 {
+        let uptime = self.uptime_nanos();
+        self.timescale.read().slew_remaining(uptime)
+    }
+
+    /// 周波数補正を設定 (ppb)
+    pub fn set_frequency_ppb(&self, ppb: i64) {
+        self.adjust(|ts, uptime| ts.set_frequency(uptime, ppb));
+    }
+
+    /// 周波数補正を取得 (ppb)
+    pub fn frequency_ppb(&self) -> i64 {
+        self.timescale.read().frequency_ppb()
+    }
+
+    /// 現在の稼働時間で写像を調整する
+    ///
+    /// 書き込み中に割り込みハンドラが `now_nanos` を呼ぶと読み終わらないので、
+    /// 割り込みを止めて書く。
+    fn adjust(&self, f: impl FnOnce(&mut Timescale, u64)) {
+        crate::interrupts::without_interrupts(|| {
+            let uptime = self.uptime_nanos();
+            self.timescale.update(|ts| f(ts, uptime));
+        });
+    }
+
+    /// 周波数補正を反映したTSC周波数の推定値 (Hz)
+    pub fn corrected_tsc_frequency(&self) -> u64 {
+        let frequency = self.tsc_frequency.load(Ordering::Relaxed) as i128;
+        let ppb = self.frequency_ppb() as i128;
+        // 時計を ppb だけ速めている = TSC は公称値より ppb だけ遅い
+        (frequency - frequency * ppb / NANOS_PER_SEC as i128).max(0) as u64
     }
 
     /// 稼働時間を更新 (タイマー割り込みから呼ばれる)
@@ -367,7 +446,16 @@ impl SystemClock {
     }
 
     /// TSC情報を設定
+    ///
+    /// 以後の稼働時間はTSCから求める。それまでの稼働時間は引き継ぐ。
     pub fn set_tsc_info(&self, info: TscInfo) {
+        if info.frequency != 0 {
+            let uptime = self.uptime_nanos();
+            let offset = info.nanos_to_tsc(uptime);
+            self.boot_tsc
+                .store(self.read_tsc().saturating_sub(offset), Ordering::Relaxed);
+            self.tsc_frequency.store(info.frequency, Ordering::Release);
+        }
         *self.tsc_info.lock() = Some(info);
         *self.timer_source.lock() = TimerSource::TSC;
     }
@@ -457,47 +545,82 @@ impl Pit {
 }
 
 /// TSC周波数をキャリブレーション
+///
+/// CPUID が TSC とクリスタルの比（leaf 0x15）か基準周波数（leaf 0x16）を返せばそれを使い、
+/// 無ければ PIT チャネル2で 10ms を計る。チャネル0（タイマー割り込み）には触れない。
 pub fn calibrate_tsc() -> Option<TscInfo> {
-    // CPUIDでTSC周波数を取得できるか確認
-    // 簡易実装: PITを使ってTSC周波数を測定
+    let frequency = cpuid_tsc_frequency().or_else(pit_tsc_frequency)?;
+    Some(TscInfo {
+        frequency,
+        invariant: tsc_is_invariant(),
+        nanos_to_tsc_mult: 0,
+        nanos_to_tsc_shift: 0,
+    })
+}
+
+/// CPUID から TSC 周波数を求める (Hz)
+fn cpuid_tsc_frequency() -> Option<u64> {
+    use core::arch::x86_64::__cpuid;
 
-    // 10ms間のTSCカウントを測定
+    let max_leaf = __cpuid(0).eax;
+    if max_leaf >= 0x15 {
+        let leaf = __cpuid(0x15);
+        if leaf.eax != 0 && leaf.ebx != 0 && leaf.ecx != 0 {
+            return Some(leaf.ecx as u64 * leaf.ebx as u64 / leaf.eax as u64);
+        }
+    }
+    if max_leaf >= 0x16 {
+        let mhz = __cpuid(0x16).eax & 0xFFFF;
+        if mhz != 0 {
+            return Some(mhz as u64 * 1_000_000);
+        }
+    }
+    None
+}
+
+/// 不変 TSC か（CPUID 0x8000_0007 EDX bit 8）
+fn tsc_is_invariant() -> bool {
+    use core::arch::x86_64::__cpuid;
+
+    __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+/// PIT チャネル2のワンショットで 10ms の TSC カウントを測る (Hz)
+///
+/// チャネル2はゲートをポート 0x61 で開閉でき、出力も同じポートで読めるので、
+/// 割り込みを使わずに計れる。スピーカーは切っておく。
+fn pit_tsc_frequency() -> Option<u64> {
     let pit_ticks = (pit::BASE_FREQUENCY / 100) as u16; // 10ms
 
     unsafe {
+        let mut gate_port: Port<u8> = Port::new(pit::CHANNEL2_GATE);
         let mut cmd_port: Port<u8> = Port::new(pit::COMMAND);
-        let mut data_port: Port<u8> = Port::new(pit::CHANNEL0_DATA);
+        let mut data_port: Port<u8> = Port::new(pit::CHANNEL2_DATA);
 
-        // Channel 0, Mode 0 (One-shot), 16-bit
-        cmd_port.write(pit::MODE_ONE_SHOT);
-
-        let start_tsc = core::arch::x86_64::_rdtsc();
-
-        // カウント値を設定
+        // ゲートを閉じ、スピーカーを切ってからカウント値を設定
+        let saved = gate_port.read();
+        gate_port.write(saved & !0x03);
+        cmd_port.write(pit::MODE_CHANNEL2_ONE_SHOT);
         data_port.write((pit_ticks & 0xFF) as u8);
         data_port.write((pit_ticks >> 8) as u8);
 
-        // カウント完了を待機
-        loop {
-            cmd_port.write(0xE2); // Read-back
-            let status = data_port.read();
-            if status & 0x80 != 0 {
-                break;
+        // ゲートを開いて計測開始、出力が立つまで待つ
+        gate_port.write((saved & !0x02) | 0x01);
+        let start_tsc = core::arch::x86_64::_rdtsc();
+        let mut spins = 0u64;
+        while gate_port.read() & 0x20 == 0 {
+            spins += 1;
+            if spins > 100_000_000 {
+                gate_port.write(saved);
+                return None;
             }
+            core::hint::spin_loop();
         }
-
         let end_tsc = core::arch::x86_64::_rdtsc();
+        gate_port.write(saved);
 
-        let tsc_diff = end_tsc.saturating_sub(start_tsc);
-        let frequency = tsc_diff * 100; // 10ms → 1秒に換算
-
-        // 不変TSCかどうかはCPUIDで確認 (簡易実装では常にtrue)
-        Some(TscInfo {
-            frequency,
-            invariant: true,
-            nanos_to_tsc_mult: 0,
-            nanos_to_tsc_shift: 0,
-        })
+        let frequency = end_tsc.saturating_sub(start_tsc) * 100; // 10ms → 1秒に換算
+        (frequency != 0).then_some(frequency)
     }
 }
 
@@ -526,19 +649,25 @@ pub fn pit() -> &'static Pit {
 }
 
 /// 時間管理を初期化
-pub fn init(tick_frequency: u64) {
-    // PITを初期化
-    PIT.init(tick_frequency);
+///
+/// TSCをキャリブレーションし、RTCから起動時刻を読む。タイマー割り込みは
+/// APICタイマーが受け持つので、PITチャネル0は設定しない。
+pub fn init() {
+    // TSCをキャリブレーション
+    if let Some(tsc_info) = calibrate_tsc() {
+        SYSTEM_CLOCK.set_tsc_info(tsc_info);
+    }
 
     // RTCから現在時刻を読み取り
     let datetime = RTC.read_datetime();
-    let boot_time = datetime.to_unix_timestamp() as u64;
+    let boot_time = datetime.to_unix_timestamp().max(0) as u64;
     SYSTEM_CLOCK.set_boot_time(boot_time);
+    SYSTEM_CLOCK.initialized.store(true, Ordering::Release);
+}
 
-    // TSCをキャリブレーション
-    if let Some(tsc_info) = calibrate_tsc() {
-        SYSTEM_CLOCK.set_tsc_info(tsc_info);
-    }
+/// 時間管理が初期化済みか
+pub fn is_initialized() -> bool {
+    SYSTEM_CLOCK.initialized.load(Ordering::Acquire)
 }
 
 /// タイマーティック (割り込みハンドラから呼ばれる)
@@ -556,6 +685,11 @@ pub fn now() -> u64 {
     SYSTEM_CLOCK.now()
 }
 
+/// 現在のUnix時刻を取得 (ナノ秒)
+pub fn now_nanos() -> u64 {
+    SYSTEM_CLOCK.now_nanos()
+}
+
 /// 高精度な時刻を取得 (ナノ秒)
 pub fn precise_time_nanos() -> u64 {
     SYSTEM_CLOCK.precise_time_nanos()
@@ -0,0 +1,277 @@
+//! 壁時計のタイムスケール
+//!
+//! 稼働時間（TSC から求めた単調なナノ秒）を Unix 時刻へ写像する。
+//! NTP などの時刻同期はこの写像を次の3通りで調整する。
+//!
+//! - ステップ: 時刻を一度に飛ばす（大きなずれの修正）
+//! - スルー: 最大 `MAX_SLEW_PPM` の速さで少しずつずれを吸収する（時刻は連続）
+//! - 周波数補正: TSC 周波数の誤差を ppb 単位で打ち消す
+//!
+//! 調整のたびに現在点を新しい基準点にするので、それまでの経過は変わらない。
+//!
+//! 写像は `SharedTimescale` に置き、受信処理や割り込みハンドラからロックなしで読む。
+
+use core::sync::atomic::{fence, AtomicI64, AtomicU64, Ordering};
+
+use super::NANOS_PER_SEC;
+
+/// スルーの最大速度 (ppm)
+pub const MAX_SLEW_PPM: i64 = 500;
+
+/// 周波数補正の上限 (ppm)
+pub const MAX_FREQUENCY_PPM: i64 = 500;
+
+/// 稼働時間 → Unix 時刻の写像
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timescale {
+    /// 基準点の稼働時間 (ns)
+    anchor_uptime: u64,
+    /// 基準点の Unix 時刻 (ns)
+    anchor_wall: u64,
+    /// 周波数補正 (ppb, 正なら速める)
+    frequency_ppb: i64,
+    /// 基準点から吸収するずれ (ns)
+    slew: i64,
+}
+
+impl Timescale {
+    /// 稼働時間 0 を Unix 時刻 0 とする
+    pub const fn new() -> Self {
+        Self {
+            anchor_uptime: 0,
+            anchor_wall: 0,
+            frequency_ppb: 0,
+            slew: 0,
+        }
+    }
+
+    /// 稼働時間 `uptime` における Unix 時刻 (ns)
+    pub fn wall_nanos(&self, uptime: u64) -> u64 {
+        let elapsed = uptime.saturating_sub(self.anchor_uptime) as i128;
+        let frequency = elapsed * self.frequency_ppb as i128 / NANOS_PER_SEC as i128;
+        let wall = self.anchor_wall as i128 + elapsed + frequency + self.slewed(elapsed) as i128;
+        wall.clamp(0, u64::MAX as i128) as u64
+    }
+
+    /// 基準点から `elapsed` ns の間に吸収したずれ
+    fn slewed(&self, elapsed: i128) -> i64 {
+        let limit = (elapsed * MAX_SLEW_PPM as i128 / 1_000_000) as i64;
+        self.slew.clamp(-limit, limit)
+    }
+
+    /// まだ吸収していないずれ (ns)
+    pub fn slew_remaining(&self, uptime: u64) -> i64 {
+        let elapsed = uptime.saturating_sub(self.anchor_uptime) as i128;
+        self.slew - self.slewed(elapsed)
+    }
+
+    /// 周波数補正 (ppb)
+    pub fn frequency_ppb(&self) -> i64 {
+        self.frequency_ppb
+    }
+
+    /// 現在点を基準点にする
+    fn reanchor(&mut self, uptime: u64) {
+        let remaining = self.slew_remaining(uptime);
+        self.anchor_wall = self.wall_nanos(uptime);
+        self.anchor_uptime = uptime.max(self.anchor_uptime);
+        self.slew = remaining;
+    }
+
+    /// 時刻を設定する（未吸収のずれは捨てる）
+    pub fn set(&mut self, uptime: u64, wall_nanos: u64) {
+        self.reanchor(uptime);
+        self.anchor_wall = wall_nanos;
+        self.slew = 0;
+    }
+
+    /// 時刻を `offset` ns 飛ばす
+    pub fn step(&mut self, uptime: u64, offset: i64) {
+        let wall = self.wall_nanos(uptime) as i128 + offset as i128;
+        self.set(uptime, wall.clamp(0, u64::MAX as i128) as u64);
+    }
+
+    /// `offset` ns のずれを徐々に吸収する（前回の残りは置き換える）
+    pub fn slew(&mut self, uptime: u64, offset: i64) {
+        self.reanchor(uptime);
+        self.slew = offset;
+    }
+
+    /// 周波数補正を設定する（±`MAX_FREQUENCY_PPM` に制限）
+    pub fn set_frequency(&mut self, uptime: u64, ppb: i64) {
+        self.reanchor(uptime);
+        let limit = MAX_FREQUENCY_PPM * 1000;
+        self.frequency_ppb = ppb.clamp(-limit, limit);
+    }
+}
+
+impl Default for Timescale {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// ロックなしで読める `Timescale`（シーケンスロック）
+///
+/// 書き込み中はシーケンス番号が奇数になる。読み手は読む前後で番号が同じ偶数なら
+/// その値を使い、違えば読み直す。書き手同士は番号を奇数にする CAS で排他する。
+pub struct SharedTimescale {
+    sequence: AtomicU64,
+    anchor_uptime: AtomicU64,
+    anchor_wall: AtomicU64,
+    frequency_ppb: AtomicI64,
+    slew: AtomicI64,
+}
+
+impl SharedTimescale {
+    /// 稼働時間 0 を Unix 時刻 0 とする
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            anchor_uptime: AtomicU64::new(0),
+            anchor_wall: AtomicU64::new(0),
+            frequency_ppb: AtomicI64::new(0),
+            slew: AtomicI64::new(0),
+        }
+    }
+
+    /// 現在の写像を読む
+    pub fn read(&self) -> Timescale {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before & 1 == 0 {
+                let value = Timescale {
+                    anchor_uptime: self.anchor_uptime.load(Ordering::Relaxed),
+                    anchor_wall: self.anchor_wall.load(Ordering::Relaxed),
+                    frequency_ppb: self.frequency_ppb.load(Ordering::Relaxed),
+                    slew: self.slew.load(Ordering::Relaxed),
+                };
+                fence(Ordering::Acquire);
+                if self.sequence.load(Ordering::Relaxed) == before {
+                    return value;
+                }
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// 写像を書き換える
+    ///
+    /// 書き込み中に同じ CPU で `read` すると終わらないので、割り込みハンドラから
+    /// 読まれる場合は割り込みを止めて呼ぶ。
+    pub fn update<R>(&self, f: impl FnOnce(&mut Timescale) -> R) -> R {
+        let mut sequence = self.sequence.load(Ordering::Relaxed);
+        loop {
+            if sequence & 1 == 0 {
+                match self.sequence.compare_exchange_weak(
+                    sequence,
+                    sequence + 1,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => break,
+                    Err(current) => sequence = current,
+                }
+            } else {
+                core::hint::spin_loop();
+                sequence = self.sequence.load(Ordering::Relaxed);
+            }
+        }
+        fence(Ordering::Release);
+
+        let mut value = Timescale {
+            anchor_uptime: self.anchor_uptime.load(Ordering::Relaxed),
+            anchor_wall: self.anchor_wall.load(Ordering::Relaxed),
+            frequency_ppb: self.frequency_ppb.load(Ordering::Relaxed),
+            slew: self.slew.load(Ordering::Relaxed),
+        };
+        let result = f(&mut value);
+        self.anchor_uptime.store(value.anchor_uptime, Ordering::Relaxed);
+        self.anchor_wall.store(value.anchor_wall, Ordering::Relaxed);
+        self.frequency_ppb.store(value.frequency_ppb, Ordering::Relaxed);
+        self.slew.store(value.slew, Ordering::Relaxed);
+
+        self.sequence.store(sequence + 2, Ordering::Release);
+        result
+    }
+}
+
+impl Default for SharedTimescale {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: u64 = NANOS_PER_SEC;
+
+    #[test]
+    fn test_step_and_frequency() {
+        let mut ts = Timescale::new();
+        ts.set(0, 1_000 * SEC);
+        assert_eq!(ts.wall_nanos(5 * SEC), 1_005 * SEC);
+
+        ts.step(5 * SEC, -2 * SEC as i64);
+        assert_eq!(ts.wall_nanos(5 * SEC), 1_003 * SEC);
+        assert_eq!(ts.wall_nanos(6 * SEC), 1_004 * SEC);
+
+        // +100 ppm: 10秒で 1ms 進む
+        ts.set_frequency(6 * SEC, 100_000);
+        assert_eq!(ts.wall_nanos(16 * SEC), 1_014 * SEC + 1_000_000);
+        // 基準点より前の稼働時間は基準点として扱う
+        assert_eq!(ts.wall_nanos(0), 1_004 * SEC);
+
+        ts.set_frequency(0, 10_000_000);
+        assert_eq!(ts.frequency_ppb(), MAX_FREQUENCY_PPM * 1000);
+    }
+
+    #[test]
+    fn test_slew() {
+        let mut ts = Timescale::new();
+        ts.set(0, 100 * SEC);
+
+        // 1ms のずれは 500ppm で 2秒かけて吸収される
+        ts.slew(0, 1_000_000);
+        assert_eq!(ts.wall_nanos(SEC), 101 * SEC + 500_000);
+        assert_eq!(ts.slew_remaining(SEC), 500_000);
+        assert_eq!(ts.wall_nanos(10 * SEC), 110 * SEC + 1_000_000);
+        assert_eq!(ts.slew_remaining(10 * SEC), 0);
+
+        // 吸収中に基準点を移しても時刻は連続
+        ts.slew(20 * SEC, -1_000_000);
+        let before = ts.wall_nanos(21 * SEC);
+        ts.set_frequency(21 * SEC, 0);
+        assert_eq!(ts.wall_nanos(21 * SEC), before);
+        assert_eq!(ts.slew_remaining(21 * SEC), -500_000);
+        assert_eq!(ts.wall_nanos(30 * SEC), 130 * SEC);
+
+        // ステップは残りのスルーを捨てる
+        ts.slew(30 * SEC, 5_000_000);
+        ts.step(30 * SEC, SEC as i64);
+        assert_eq!(ts.slew_remaining(40 * SEC), 0);
+        assert_eq!(ts.wall_nanos(40 * SEC), 141 * SEC);
+    }
+
+    #[test]
+    fn test_shared_timescale() {
+        let shared = SharedTimescale::new();
+        assert_eq!(shared.read(), Timescale::new());
+
+        shared.update(|ts| ts.set(0, 100 * SEC));
+        shared.update(|ts| ts.set_frequency(SEC, 100_000));
+        let mut expected = Timescale::new();
+        expected.set(0, 100 * SEC);
+        expected.set_frequency(SEC, 100_000);
+        assert_eq!(shared.read(), expected);
+        assert_eq!(shared.update(|ts| ts.wall_nanos(11 * SEC)), 111 * SEC + 1_000_000);
+        // 書き込みごとにシーケンス番号は偶数のまま 2 ずつ進む
+        assert_eq!(shared.sequence.load(Ordering::Relaxed), 6);
+    }
+}
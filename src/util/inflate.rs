@@ -0,0 +1,527 @@
+//! # Inflate - DEFLATE / gzip / zlib 展開
+//!
+//! HTTP の `Content-Encoding: gzip` / `deflate` のボディと PNG の画像データを展開する。
+//! DEFLATE (RFC 1951) のデコーダと、gzip (RFC 1952) / zlib (RFC 1950)
+//! のコンテナ処理を実装する。
+//!
+//! ハフマン符号はテーブルを作らず、符号長ごとの符号数から1ビットずつ
+//! 正規符号を辿って復号する（zlib の puff と同じ方式）。
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// 最大符号長
+const MAX_BITS: usize = 15;
+/// リテラル/長さ符号の数
+const MAX_LIT_CODES: usize = 288;
+/// 距離符号の数
+const MAX_DIST_CODES: usize = 30;
+
+/// 長さ符号 257..285 の基準値
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+/// 長さ符号の追加ビット数
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// 距離符号の基準値
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// 距離符号の追加ビット数
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// 符号長符号の並び順
+const CODE_LENGTH_ORDER: [usize; 19] =
+    [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// gzip ヘッダフラグ
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+
+// ============================================================================
+// エラー
+// ============================================================================
+
+/// 展開エラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    /// 入力が途中で終わった
+    Truncated,
+    /// 予約済みのブロック種別 (BTYPE=11)
+    InvalidBlockType,
+    /// 非圧縮ブロックの LEN/NLEN 不一致
+    StoredLengthMismatch,
+    /// 符号長の組が不正
+    InvalidCodeLengths,
+    /// 復号できない符号
+    InvalidCode,
+    /// 出力の先頭より前を参照する距離
+    InvalidDistance,
+    /// gzip / zlib ヘッダが不正
+    InvalidHeader,
+    /// CRC32 / Adler-32 / サイズの不一致
+    ChecksumMismatch,
+    /// 展開後のサイズが上限を超えた
+    OutputTooLarge,
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InflateError::Truncated => write!(f, "Compressed data is truncated"),
+            InflateError::InvalidBlockType => write!(f, "Invalid deflate block type"),
+            InflateError::StoredLengthMismatch => write!(f, "Stored block length mismatch"),
+            InflateError::InvalidCodeLengths => write!(f, "Invalid Huffman code lengths"),
+            InflateError::InvalidCode => write!(f, "Invalid Huffman code"),
+            InflateError::InvalidDistance => write!(f, "Back-reference distance too far"),
+            InflateError::InvalidHeader => write!(f, "Invalid gzip/zlib header"),
+            InflateError::ChecksumMismatch => write!(f, "Decompressed data checksum mismatch"),
+            InflateError::OutputTooLarge => write!(f, "Decompressed data exceeds size limit"),
+        }
+    }
+}
+
+// ============================================================================
+// ビット読み出し
+// ============================================================================
+
+/// LSB ファーストのビットリーダー
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit_buf: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// `count` ビット (最大16) を読む
+    fn bits(&mut self, count: u32) -> Result<u32, InflateError> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.pos).ok_or(InflateError::Truncated)?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u32 << count) - 1);
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// 残りビットを捨ててバイト境界に揃える
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+
+    /// バイト境界から `len` バイトを取り出す
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], InflateError> {
+        let end = self.pos.checked_add(len).ok_or(InflateError::Truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or(InflateError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+// ============================================================================
+// ハフマン符号
+// ============================================================================
+
+/// 正規ハフマン符号
+struct Huffman {
+    /// 符号長ごとの符号数
+    counts: [u16; MAX_BITS + 1],
+    /// 符号順に並べたシンボル
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// 符号長の列から構築する
+    ///
+    /// 過剰に割り当てられた組はエラー。不完全な組は許容する
+    /// （距離符号が1つだけのストリームを正しく扱うため）。
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidCodeLengths);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = alloc::vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    /// シンボルを1つ復号する
+    fn decode(&self, reader: &mut BitReader<'_>) -> Result<u16, InflateError> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(InflateError::InvalidCode)
+    }
+}
+
+/// 固定ハフマン符号 (BTYPE=01)
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; MAX_LIT_CODES];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let literal = Huffman::new(&lengths).expect("fixed literal code is complete");
+    let distance = Huffman::new(&[5u8; MAX_DIST_CODES]).expect("fixed distance code is valid");
+    (literal, distance)
+}
+
+/// 動的ハフマン符号 (BTYPE=10) を読む
+fn dynamic_codes(reader: &mut BitReader<'_>) -> Result<(Huffman, Huffman), InflateError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > MAX_LIT_CODES - 2 || distance_count > MAX_DIST_CODES {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let total = literal_count + distance_count;
+    let mut lengths = Vec::with_capacity(total);
+    while lengths.len() < total {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or(InflateError::InvalidCodeLengths)?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if lengths.len() + repeat > total {
+            return Err(InflateError::InvalidCodeLengths);
+        }
+        lengths.resize(lengths.len() + repeat, value);
+    }
+
+    // ブロック終端符号 (256) が無いストリームは復号できない
+    if lengths[256] == 0 {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+    let literal = Huffman::new(&lengths[..literal_count])?;
+    let distance = Huffman::new(&lengths[literal_count..])?;
+    Ok((literal, distance))
+}
+
+// ============================================================================
+// DEFLATE
+// ============================================================================
+
+/// ハフマン符号化されたブロックを1つ展開する
+fn inflate_block(
+    reader: &mut BitReader<'_>,
+    out: &mut Vec<u8>,
+    literal: &Huffman,
+    distance: &Huffman,
+    limit: usize,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = literal.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                if out.len() >= limit {
+                    return Err(InflateError::OutputTooLarge);
+                }
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(InflateError::InvalidCode);
+                }
+                let length = LENGTH_BASE[index] as usize
+                    + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = distance.decode(reader)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err(InflateError::InvalidCode);
+                }
+                let dist =
+                    DIST_BASE[index] as usize + reader.bits(DIST_EXTRA[index] as u32)? as usize;
+                if dist > out.len() {
+                    return Err(InflateError::InvalidDistance);
+                }
+                if out.len() + length > limit {
+                    return Err(InflateError::OutputTooLarge);
+                }
+                // 重なりのあるコピー（距離 < 長さ）は1バイトずつ
+                let start = out.len() - dist;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+/// 生の DEFLATE ストリームを展開する
+///
+/// 戻り値: (展開データ, 消費した入力バイト数)
+fn inflate_stream(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), InflateError> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::new();
+    let mut fixed = None;
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = reader.bytes(4)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(InflateError::StoredLengthMismatch);
+                }
+                if out.len() + len as usize > limit {
+                    return Err(InflateError::OutputTooLarge);
+                }
+                out.extend_from_slice(reader.bytes(len as usize)?);
+            }
+            1 => {
+                let (literal, distance) = fixed.get_or_insert_with(fixed_codes);
+                inflate_block(&mut reader, &mut out, literal, distance, limit)?;
+            }
+            2 => {
+                let (literal, distance) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literal, &distance, limit)?;
+            }
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+        if last {
+            break;
+        }
+    }
+
+    Ok((out, reader.pos))
+}
+
+/// 生の DEFLATE (RFC 1951) データを展開する
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    inflate_stream(data, limit).map(|(out, _)| out)
+}
+
+// ============================================================================
+// コンテナ形式
+// ============================================================================
+
+/// gzip (RFC 1952) データを展開する
+///
+/// 複数メンバーの連結には対応せず、先頭メンバーのみを展開する
+pub fn gunzip(data: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b || data[2] != 8 {
+        return Err(InflateError::InvalidHeader);
+    }
+    let flags = data[3];
+    let mut pos = 10;
+
+    if flags & GZIP_FEXTRA != 0 {
+        let extra = data.get(pos..pos + 2).ok_or(InflateError::Truncated)?;
+        pos += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            let field = data.get(pos..).ok_or(InflateError::Truncated)?;
+            let end = field.iter().position(|&b| b == 0).ok_or(InflateError::Truncated)?;
+            pos += end + 1;
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        pos += 2;
+    }
+
+    let body = data.get(pos..).ok_or(InflateError::Truncated)?;
+    let (out, consumed) = inflate_stream(body, limit)?;
+    let trailer = body
+        .get(consumed..consumed + 8)
+        .ok_or(InflateError::Truncated)?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32(&out) || size != out.len() as u32 {
+        return Err(InflateError::ChecksumMismatch);
+    }
+    Ok(out)
+}
+
+/// `Content-Encoding: deflate` を展開する
+///
+/// 仕様上は zlib 形式だが、生の DEFLATE を送るサーバーもあるため
+/// zlib ヘッダが無ければ生データとして扱う
+pub fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, InflateError> {
+    let is_zlib = data.len() >= 2
+        && data[0] & 0x0f == 8
+        && (u16::from_be_bytes([data[0], data[1]]) % 31 == 0);
+    if !is_zlib {
+        return inflate(data, limit);
+    }
+    // プリセット辞書は未対応
+    if data[1] & 0x20 != 0 {
+        return Err(InflateError::InvalidHeader);
+    }
+
+    let (out, consumed) = inflate_stream(&data[2..], limit)?;
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or(InflateError::Truncated)?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&out) {
+        return Err(InflateError::ChecksumMismatch);
+    }
+    Ok(out)
+}
+
+/// CRC-32 (IEEE 802.3, 反転多項式 0xEDB88320)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Adler-32
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552バイトごとに剰余を取ればオーバーフローしない
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+// ============================================================================
+// テスト
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: usize = 1 << 20;
+
+    #[test]
+    fn test_stored_and_fixed_blocks() {
+        let stored = [0x01, 0x03, 0x00, 0xfc, 0xff, 0x61, 0x62, 0x63];
+        assert_eq!(inflate(&stored, LIMIT).unwrap(), b"abc");
+
+        // 後方参照を含む固定ハフマンブロック
+        let fixed = [
+            0xf3, 0x48, 0xcd, 0xc9, 0xc9, 0xd7, 0x51, 0xf0, 0x40, 0xa2, 0x14, 0x01,
+        ];
+        assert_eq!(inflate(&fixed, LIMIT).unwrap(), b"Hello, Hello, Hello!");
+        assert_eq!(inflate(&fixed, 8), Err(InflateError::OutputTooLarge));
+        assert_eq!(inflate(&fixed[..6], LIMIT), Err(InflateError::Truncated));
+
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_dynamic_block() {
+        let data = [
+            0x1d, 0x4e, 0x49, 0x0a, 0x00, 0x31, 0x0c, 0xfa, 0x4a, 0xbf, 0xe6, 0x41, 0x68, 0xa0,
+            0x44, 0x68, 0xfd, 0x3f, 0x63, 0xa6, 0x87, 0x80, 0x6b, 0x35, 0x5d, 0xe4, 0x16, 0x2d,
+            0x82, 0xa4, 0x9d, 0x23, 0x0f, 0xc3, 0x2a, 0x51, 0xfa, 0x89, 0x33, 0x42, 0x90, 0xb5,
+            0x63, 0x97, 0x8a, 0x81, 0x3d, 0x0c, 0x51, 0xf2, 0x33, 0x84, 0x38, 0x2e, 0xfb, 0x4d,
+            0x1c, 0x58, 0x6e, 0xa4, 0x92, 0x30, 0x9f, 0xe7, 0x16, 0x9f, 0x74, 0xd7, 0xb6, 0xdb,
+            0x82, 0x2e, 0xb8, 0xa3, 0x76, 0xda, 0xba, 0x5d, 0xaa, 0x8d, 0x08, 0xab, 0x7e, 0xef,
+            0x3f, 0x25, 0xb1, 0xce, 0xaf, 0x07, 0x53, 0x22, 0x2e, 0x6e, 0x1f, 0xb1, 0xed, 0x5a,
+            0x33, 0x29, 0x2f, 0x59, 0xcc, 0xce, 0x40, 0x7f,
+        ];
+        let out = inflate(&data, LIMIT).unwrap();
+        assert_eq!(out.len(), 200);
+        assert!(out.starts_with(b"tetieehoetoeaeee"));
+        assert_eq!(crc32(&out), 0xffa4_e4fa);
+    }
+
+    #[test]
+    fn test_gzip_and_zlib_containers() {
+        let gzip = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x4b, 0x4c, 0x84, 0x80,
+            0x24, 0x30, 0x48, 0x06, 0x82, 0x94, 0x94, 0x94, 0xd4, 0x44, 0x12, 0x44, 0x4b, 0x32,
+            0x52, 0x15, 0x8a, 0x12, 0xf3, 0x2a, 0x15, 0xb2, 0x53, 0x8b, 0xf2, 0x52, 0x73, 0x14,
+            0x12, 0x87, 0x80, 0x89, 0x00, 0x40, 0xd0, 0xf9, 0xe3, 0xf6, 0x00, 0x00, 0x00,
+        ];
+        let out = gunzip(&gzip, LIMIT).unwrap();
+        assert_eq!(out.len(), 246);
+        assert!(out.ends_with(b"dddethe rany kernel "));
+
+        let mut corrupt = gzip;
+        corrupt[gzip.len() - 8] ^= 1;
+        assert_eq!(gunzip(&corrupt, LIMIT), Err(InflateError::ChecksumMismatch));
+
+        let zlib = [
+            0x78, 0x9c, 0x4b, 0x49, 0x4d, 0xcb, 0x49, 0x2c, 0x49, 0x55, 0x48, 0xca, 0x4f, 0xa9,
+            0x04, 0x00, 0x1e, 0x23, 0x04, 0xa4,
+        ];
+        assert_eq!(zlib_decompress(&zlib, LIMIT).unwrap(), b"deflate body");
+        // zlib ヘッダ無しの生データも受け付ける
+        assert_eq!(zlib_decompress(&zlib[2..16], LIMIT).unwrap(), b"deflate body");
+    }
+}
@@ -0,0 +1,6 @@
+// ============================================================================
+// src/util/mod.rs - 共通ユーティリティ
+// 複数のサブシステムから使う、特定の層に属さない処理
+// ============================================================================
+
+pub mod inflate;